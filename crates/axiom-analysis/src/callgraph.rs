@@ -0,0 +1,369 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Static call graph.
+//!
+//! Built from GCC `-fcallgraph-info` files (`.ci`, VCG format) or from a
+//! disassembly of the linked image.
+
+use axiom_core::walk::find_files;
+use axiom_core::{Location, Position, Range};
+use axiom_toolchain::run_limited;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Callee name GCC uses for calls through a pointer.
+const INDIRECT_CALL: &str = "__indirect_call";
+
+/// Whole-program call graph keyed by function name.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallGraph {
+    /// Callees of each function.
+    pub edges: BTreeMap<String, BTreeSet<String>>,
+    /// Functions that make calls through function pointers.
+    #[serde(default)]
+    pub indirect_callers: BTreeSet<String>,
+    /// Definition sites, where known.
+    #[serde(default)]
+    pub locations: BTreeMap<String, Location>,
+}
+
+impl CallGraph {
+    /// Create an empty call graph.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a function with no calls.
+    pub fn add_function(&mut self, name: impl Into<String>) {
+        self.edges.entry(name.into()).or_default();
+    }
+
+    /// Add a call edge.
+    pub fn add_call(&mut self, caller: impl Into<String>, callee: impl Into<String>) {
+        let callee = callee.into();
+        self.add_function(callee.clone());
+        self.edges.entry(caller.into()).or_default().insert(callee);
+    }
+
+    /// Record that a function calls through a pointer.
+    pub fn add_indirect_call(&mut self, caller: impl Into<String>) {
+        let caller = caller.into();
+        self.add_function(caller.clone());
+        self.indirect_callers.insert(caller);
+    }
+
+    /// Functions called by `name`.
+    pub fn callees(&self, name: &str) -> impl Iterator<Item = &str> {
+        self.edges
+            .get(name)
+            .into_iter()
+            .flat_map(|callees| callees.iter().map(String::as_str))
+    }
+
+    /// All functions in the graph.
+    pub fn functions(&self) -> impl Iterator<Item = &str> {
+        self.edges.keys().map(String::as_str)
+    }
+
+    /// Merge another graph into this one.
+    pub fn merge(&mut self, other: CallGraph) {
+        for (caller, callees) in other.edges {
+            self.edges.entry(caller).or_default().extend(callees);
+        }
+        self.indirect_callers.extend(other.indirect_callers);
+        for (name, location) in other.locations {
+            self.locations.entry(name).or_insert(location);
+        }
+    }
+
+    /// Functions reachable from the given roots, including the roots.
+    pub fn reachable_from(&self, roots: &[String]) -> BTreeSet<String> {
+        let mut seen = BTreeSet::new();
+        let mut stack: Vec<&str> = roots.iter().map(String::as_str).collect();
+        while let Some(name) = stack.pop() {
+            if seen.insert(name.to_string()) {
+                stack.extend(self.callees(name));
+            }
+        }
+        seen
+    }
+
+    /// Functions not reachable from the given roots.
+    ///
+    /// Functions only reached through pointers appear here too, so results
+    /// are candidates for review rather than proof of dead code.
+    pub fn unreachable_from(&self, roots: &[String]) -> Vec<String> {
+        let reachable = self.reachable_from(roots);
+        self.functions()
+            .filter(|f| !reachable.contains(*f))
+            .map(String::from)
+            .collect()
+    }
+}
+
+/// Build a call graph from every `.ci` file under a build directory.
+pub fn load_callgraph_info(build_dir: &Path) -> std::io::Result<CallGraph> {
+    let mut graph = CallGraph::new();
+    for path in find_files(build_dir, &["ci"])? {
+        graph.merge(parse_callgraph_info(&std::fs::read_to_string(path)?));
+    }
+    Ok(graph)
+}
+
+/// Parse a GCC `-fcallgraph-info` file.
+///
+/// ```text
+/// node: { title: "main" label: "main\nsrc/main.c:5:5" }
+/// edge: { sourcename: "main" targetname: "uart_init" }
+/// ```
+pub fn parse_callgraph_info(content: &str) -> CallGraph {
+    let mut graph = CallGraph::new();
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.starts_with("node:") {
+            let Some(title) = vcg_attribute(line, "title") else {
+                continue;
+            };
+            if title == INDIRECT_CALL {
+                continue;
+            }
+            graph.add_function(title.clone());
+            // Second label line is the definition site for defined functions
+            if let Some(site) = vcg_attribute(line, "label")
+                .as_deref()
+                .and_then(|l| l.split("\\n").nth(1))
+                .and_then(parse_site)
+            {
+                graph.locations.insert(title, site);
+            }
+        } else if line.starts_with("edge:") {
+            let (Some(source), Some(target)) = (
+                vcg_attribute(line, "sourcename"),
+                vcg_attribute(line, "targetname"),
+            ) else {
+                continue;
+            };
+            if target == INDIRECT_CALL {
+                graph.add_indirect_call(source);
+            } else {
+                graph.add_call(source, target);
+            }
+        }
+    }
+
+    graph
+}
+
+/// Read a quoted VCG attribute value.
+fn vcg_attribute(line: &str, name: &str) -> Option<String> {
+    let start = line.find(&format!("{}:", name))? + name.len() + 1;
+    let rest = line[start..].trim_start().strip_prefix('"')?;
+    let mut value = String::new();
+    let mut chars = rest.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => return Some(value),
+            '\\' => {
+                // Keep escapes other than \" so label line breaks stay visible
+                match chars.next() {
+                    Some('"') => value.push('"'),
+                    Some(other) => {
+                        value.push('\\');
+                        value.push(other);
+                    }
+                    None => {}
+                }
+            }
+            _ => value.push(c),
+        }
+    }
+    None
+}
+
+/// Parse `file:line:column`.
+fn parse_site(site: &str) -> Option<Location> {
+    let mut parts = site.rsplitn(3, ':');
+    let column: u32 = parts.next()?.parse().ok()?;
+    let line: u32 = parts.next()?.parse().ok()?;
+    let file = parts.next()?;
+    let position = Position::new(line.saturating_sub(1), column.saturating_sub(1));
+    Some(Location::new(
+        PathBuf::from(file),
+        Range::new(position, position),
+    ))
+}
+
+/// Disassemble an image with objdump and build its call graph.
+pub fn callgraph_from_elf(objdump: &Path, elf: &Path) -> std::io::Result<CallGraph> {
+    let output = run_limited(Command::new(objdump).arg("-d").arg(elf))?;
+    if !output.status.success() {
+        return Err(std::io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(parse_objdump_calls(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+/// Build a call graph from `objdump -d` output.
+///
+/// Direct calls (`bl`, `blx`, `call`) and tail calls (`b`, `b.w`, `jmp` to
+/// the start of another function) become edges; calls through registers
+/// mark the caller as making indirect calls.
+pub fn parse_objdump_calls(disassembly: &str) -> CallGraph {
+    let mut graph = CallGraph::new();
+    let mut current: Option<String> = None;
+
+    for line in disassembly.lines() {
+        // Function header: "08000130 <main>:"
+        if let Some(name) = line
+            .strip_suffix(">:")
+            .and_then(|l| l.split_once(" <"))
+            .map(|(_, name)| name)
+        {
+            graph.add_function(name);
+            current = Some(name.to_string());
+            continue;
+        }
+
+        let Some(caller) = current.as_deref() else {
+            continue;
+        };
+
+        // Instruction: "address:\tbytes\tmnemonic\toperands"
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() < 3 {
+            continue;
+        }
+        let instruction = fields[2..].join(" ");
+        let mut words = instruction.split_whitespace();
+        let Some(mnemonic) = words.next() else {
+            continue;
+        };
+        let operands: String = words.collect::<Vec<_>>().join(" ");
+        let target = operands
+            .split_once('<')
+            .and_then(|(_, t)| t.split_once('>'))
+            .map(|(t, _)| t);
+
+        match mnemonic {
+            "bl" | "blx" | "call" | "callq" => match target {
+                Some(t) => {
+                    let callee = t.split('+').next().unwrap_or(t);
+                    graph.add_call(caller.to_string(), callee);
+                }
+                None => graph.add_indirect_call(caller.to_string()),
+            },
+            "b" | "b.w" | "b.n" | "jmp" | "jmpq" => {
+                if let Some(t) = target {
+                    if !t.contains('+') && t != caller {
+                        graph.add_call(caller.to_string(), t);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    graph
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_calls() {
+        let mut graph = CallGraph::new();
+        graph.add_call("main", "uart_init");
+        graph.add_call("main", "hal_init");
+        graph.add_function("SysTick_Handler");
+
+        assert_eq!(
+            graph.callees("main").collect::<Vec<_>>(),
+            vec!["hal_init", "uart_init"]
+        );
+        assert_eq!(graph.callees("uart_init").count(), 0);
+        assert_eq!(graph.functions().count(), 4);
+    }
+
+    #[test]
+    fn test_parse_callgraph_info() {
+        let ci = r#"graph: { title: "src/main.c"
+node: { title: "main" label: "main\nsrc/main.c:5:5\n16 bytes (static)" }
+node: { title: "uart_init" label: "uart_init\nsrc/main.c:1:6" }
+node: { title: "printf" label: "printf\n<built-in>" shape : ellipse }
+edge: { sourcename: "main" targetname: "uart_init" label: "src/main.c:7:3" }
+edge: { sourcename: "main" targetname: "printf" label: "src/main.c:8:3" }
+edge: { sourcename: "main" targetname: "__indirect_call" label: "src/main.c:9:3" }
+}
+"#;
+        let graph = parse_callgraph_info(ci);
+        assert_eq!(
+            graph.callees("main").collect::<Vec<_>>(),
+            vec!["printf", "uart_init"]
+        );
+        assert!(graph.indirect_callers.contains("main"));
+        assert!(!graph.edges.contains_key(INDIRECT_CALL));
+
+        let main = &graph.locations["main"];
+        assert_eq!(main.path, PathBuf::from("src/main.c"));
+        assert_eq!(main.range.start, Position::new(4, 4));
+        assert!(!graph.locations.contains_key("printf"));
+    }
+
+    #[test]
+    fn test_parse_objdump_arm() {
+        let disassembly = "
+08000100 <uart_init>:
+ 8000100:\tb480      \tpush\t{r7}
+ 8000102:\tf000 b801 \tb.w\t8000108 <hal_delay>
+08000108 <hal_delay>:
+ 8000108:\t4770      \tbx\tlr
+08000110 <main>:
+ 8000110:\tf7ff fff6 \tbl\t8000100 <uart_init>
+ 8000114:\t4798      \tblx\tr3
+ 8000116:\te7fe      \tb.n\t8000116 <main+0x6>
+";
+        let graph = parse_objdump_calls(disassembly);
+        assert_eq!(graph.callees("main").collect::<Vec<_>>(), vec!["uart_init"]);
+        assert_eq!(
+            graph.callees("uart_init").collect::<Vec<_>>(),
+            vec!["hal_delay"]
+        );
+        assert!(graph.indirect_callers.contains("main"));
+    }
+
+    #[test]
+    fn test_parse_objdump_x86() {
+        let disassembly = "
+0000000000401126 <main>:
+  401126:\te8 db ff ff ff       \tcall   401106 <helper>
+  40112b:\tff d0                \tcall   *%rax
+";
+        let graph = parse_objdump_calls(disassembly);
+        assert_eq!(graph.callees("main").collect::<Vec<_>>(), vec!["helper"]);
+        assert!(graph.indirect_callers.contains("main"));
+    }
+
+    #[test]
+    fn test_reachability() {
+        let mut graph = CallGraph::new();
+        graph.add_call("main", "a");
+        graph.add_call("a", "b");
+        graph.add_call("unused", "b");
+        graph.add_function("SysTick_Handler");
+
+        let roots = vec!["main".to_string()];
+        assert_eq!(graph.reachable_from(&roots).len(), 3);
+        assert_eq!(
+            graph.unreachable_from(&roots),
+            vec!["SysTick_Handler", "unused"]
+        );
+    }
+}
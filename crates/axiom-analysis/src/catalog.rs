@@ -0,0 +1,486 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Assert and error-code catalog extraction.
+//!
+//! Macro patterns live in `.axiom/error-catalog.toml`:
+//!
+//! ```toml
+//! [[pattern]]
+//! macro = "FW_ASSERT"
+//! code_arg = 0
+//! message_arg = 1
+//! ```
+
+use axiom_core::walk::find_files;
+use axiom_core::{Location, Position, Range};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Source extensions scanned for catalog macros.
+pub const CATALOG_EXTENSIONS: &[&str] = &["c", "h", "cc", "cpp", "hh", "hpp", "hxx", "cxx"];
+
+/// Error type for catalog extraction.
+#[derive(Debug, Error)]
+pub enum CatalogError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("TOML parse error in {path}: {source}")]
+    Toml {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+}
+
+/// A macro whose invocations carry an error code.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CatalogPattern {
+    /// Macro name.
+    #[serde(rename = "macro")]
+    pub macro_name: String,
+    /// Zero-based index of the code argument.
+    pub code_arg: usize,
+    /// Zero-based index of the message argument, if any.
+    #[serde(default)]
+    pub message_arg: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct CatalogConfig {
+    #[serde(default, rename = "pattern")]
+    patterns: Vec<CatalogPattern>,
+}
+
+/// One macro invocation with a numeric code.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CatalogEntry {
+    /// Numeric code.
+    pub code: u64,
+    /// Code as written in source (e.g. "0x1001").
+    pub code_text: String,
+    /// Macro that was invoked.
+    pub macro_name: String,
+    /// Message text, with adjacent string literals joined.
+    pub message: Option<String>,
+    /// Invocation site.
+    pub location: Location,
+}
+
+/// A code used by more than one invocation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DuplicateCode {
+    /// The duplicated code.
+    pub code: u64,
+    /// Every site using it.
+    pub locations: Vec<Location>,
+}
+
+/// Extracted catalog.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorCatalog {
+    /// Entries sorted by code, then location.
+    pub entries: Vec<CatalogEntry>,
+    /// Codes used more than once.
+    pub duplicates: Vec<DuplicateCode>,
+}
+
+/// Path of a project's catalog configuration.
+pub fn catalog_config_path(project_root: &Path) -> PathBuf {
+    project_root.join(".axiom").join("error-catalog.toml")
+}
+
+/// Load a project's catalog patterns; a missing file means none.
+pub fn load_catalog_patterns(project_root: &Path) -> Result<Vec<CatalogPattern>, CatalogError> {
+    let path = catalog_config_path(project_root);
+    if !path.is_file() {
+        return Ok(Vec::new());
+    }
+    let content = std::fs::read_to_string(&path)?;
+    let config: CatalogConfig =
+        toml::from_str(&content).map_err(|source| CatalogError::Toml { path, source })?;
+    Ok(config.patterns)
+}
+
+/// Extract the catalog from every source file under a project root.
+pub fn extract_catalog(
+    project_root: &Path,
+    patterns: &[CatalogPattern],
+) -> Result<ErrorCatalog, CatalogError> {
+    let mut entries = Vec::new();
+    for path in find_files(project_root, CATALOG_EXTENSIONS)? {
+        let source = std::fs::read_to_string(&path)?;
+        entries.extend(scan_source(&path, &source, patterns));
+    }
+    Ok(ErrorCatalog::new(entries))
+}
+
+/// Find catalog macro invocations in one source file.
+pub fn scan_source(path: &Path, source: &str, patterns: &[CatalogPattern]) -> Vec<CatalogEntry> {
+    let mut entries = Vec::new();
+    if patterns.is_empty() {
+        return entries;
+    }
+
+    let bytes = source.as_bytes();
+    let mut i = 0;
+    let mut line = 0u32;
+    let mut line_start = 0usize;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\n' => {
+                line += 1;
+                line_start = i + 1;
+                i += 1;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                i = source[i..].find('\n').map(|n| i + n).unwrap_or(bytes.len());
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                let end = source[i + 2..]
+                    .find("*/")
+                    .map(|n| i + 2 + n + 2)
+                    .unwrap_or(bytes.len());
+                for (offset, _) in source[i..end].match_indices('\n') {
+                    line += 1;
+                    line_start = i + offset + 1;
+                }
+                i = end;
+            }
+            b'"' | b'\'' => i = skip_literal(bytes, i),
+            c if is_ident_start(c) => {
+                let start = i;
+                while i < bytes.len() && is_ident_char(bytes[i]) {
+                    i += 1;
+                }
+                let ident = &source[start..i];
+                let Some(pattern) = patterns.iter().find(|p| p.macro_name == ident) else {
+                    continue;
+                };
+                let open = i + source[i..].len() - source[i..].trim_start().len();
+                if bytes.get(open) != Some(&b'(') {
+                    continue;
+                }
+                let Some((args, close)) = split_arguments(source, open) else {
+                    continue;
+                };
+
+                let position = Position::new(line, (start - line_start) as u32);
+                let location = Location::new(path.to_path_buf(), Range::new(position, position));
+                if let Some(entry) = catalog_entry(pattern, &args, location) {
+                    entries.push(entry);
+                }
+
+                // Arguments may span lines
+                for (offset, _) in source[i..close].match_indices('\n') {
+                    line += 1;
+                    line_start = i + offset + 1;
+                }
+                i = close + 1;
+            }
+            _ => i += 1,
+        }
+    }
+
+    entries
+}
+
+fn catalog_entry(
+    pattern: &CatalogPattern,
+    args: &[&str],
+    location: Location,
+) -> Option<CatalogEntry> {
+    let code_text = args.get(pattern.code_arg)?.trim();
+    let code = parse_integer(code_text)?;
+    let message = pattern
+        .message_arg
+        .and_then(|i| args.get(i))
+        .and_then(|arg| parse_string_literals(arg));
+
+    Some(CatalogEntry {
+        code,
+        code_text: code_text.to_string(),
+        macro_name: pattern.macro_name.clone(),
+        message,
+        location,
+    })
+}
+
+impl ErrorCatalog {
+    /// Build a catalog from entries, sorting them and finding duplicates.
+    pub fn new(mut entries: Vec<CatalogEntry>) -> Self {
+        entries.sort_by(|a, b| {
+            (a.code, &a.location.path, a.location.range.start.line).cmp(&(
+                b.code,
+                &b.location.path,
+                b.location.range.start.line,
+            ))
+        });
+
+        let mut by_code: BTreeMap<u64, Vec<Location>> = BTreeMap::new();
+        for entry in &entries {
+            by_code
+                .entry(entry.code)
+                .or_default()
+                .push(entry.location.clone());
+        }
+        let duplicates = by_code
+            .into_iter()
+            .filter(|(_, locations)| locations.len() > 1)
+            .map(|(code, locations)| DuplicateCode { code, locations })
+            .collect();
+
+        Self {
+            entries,
+            duplicates,
+        }
+    }
+
+    /// Export as CSV (code, macro, message, file, line).
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("code,macro,message,file,line\n");
+        for e in &self.entries {
+            out.push_str(&format!(
+                "{},{},{},{},{}\n",
+                csv_field(&e.code_text),
+                csv_field(&e.macro_name),
+                csv_field(e.message.as_deref().unwrap_or_default()),
+                csv_field(&e.location.path.display().to_string()),
+                e.location.range.start.line + 1
+            ));
+        }
+        out
+    }
+
+    /// Export as a Markdown table.
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::from("| Code | Macro | Message | Location |\n|---|---|---|---|\n");
+        for e in &self.entries {
+            out.push_str(&format!(
+                "| {} | {} | {} | {}:{} |\n",
+                e.code_text,
+                e.macro_name,
+                e.message.as_deref().unwrap_or_default().replace('|', "\\|"),
+                e.location.path.display(),
+                e.location.range.start.line + 1
+            ));
+        }
+        out
+    }
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn is_ident_start(c: u8) -> bool {
+    c.is_ascii_alphabetic() || c == b'_'
+}
+
+fn is_ident_char(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || c == b'_'
+}
+
+/// Index just past a string or character literal starting at `start`.
+fn skip_literal(bytes: &[u8], start: usize) -> usize {
+    let quote = bytes[start];
+    let mut i = start + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            c if c == quote => return i + 1,
+            b'\n' => return i,
+            _ => i += 1,
+        }
+    }
+    bytes.len()
+}
+
+/// Split the arguments of a call whose `(` is at `open`.
+///
+/// Returns the argument texts and the index of the closing `)`.
+fn split_arguments(source: &str, open: usize) -> Option<(Vec<&str>, usize)> {
+    let bytes = source.as_bytes();
+    let mut args = Vec::new();
+    let mut depth = 0usize;
+    let mut arg_start = open + 1;
+    let mut i = open + 1;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' | b'\'' => {
+                i = skip_literal(bytes, i);
+                continue;
+            }
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' if depth == 0 => {
+                args.push(&source[arg_start..i]);
+                return Some((args, i));
+            }
+            b')' | b']' | b'}' => depth = depth.saturating_sub(1),
+            b',' if depth == 0 => {
+                args.push(&source[arg_start..i]);
+                arg_start = i + 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    None
+}
+
+/// Parse a C integer literal (decimal, hex, octal or binary, with suffixes).
+fn parse_integer(text: &str) -> Option<u64> {
+    let digits = text.trim_end_matches(['u', 'U', 'l', 'L']);
+    if let Some(hex) = digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        u64::from_str_radix(hex, 16).ok()
+    } else if let Some(bin) = digits
+        .strip_prefix("0b")
+        .or_else(|| digits.strip_prefix("0B"))
+    {
+        u64::from_str_radix(bin, 2).ok()
+    } else if digits.len() > 1 && digits.starts_with('0') {
+        u64::from_str_radix(&digits[1..], 8).ok()
+    } else {
+        digits.parse().ok()
+    }
+}
+
+/// Join adjacent string literals (`"a" "b"`), undoing simple escapes.
+fn parse_string_literals(text: &str) -> Option<String> {
+    let mut out = String::new();
+    let mut rest = text.trim();
+    let mut found = false;
+
+    while let Some(body) = rest.strip_prefix('"') {
+        let mut chars = body.char_indices();
+        let mut end = None;
+        while let Some((idx, c)) = chars.next() {
+            match c {
+                '\\' => match chars.next() {
+                    Some((_, 'n')) => out.push('\n'),
+                    Some((_, 't')) => out.push('\t'),
+                    Some((_, other)) => out.push(other),
+                    None => {}
+                },
+                '"' => {
+                    end = Some(idx);
+                    break;
+                }
+                _ => out.push(c),
+            }
+        }
+        rest = body[end? + 1..].trim_start();
+        found = true;
+    }
+
+    (found && rest.is_empty()).then_some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn patterns() -> Vec<CatalogPattern> {
+        vec![
+            CatalogPattern {
+                macro_name: "FW_ASSERT".to_string(),
+                code_arg: 1,
+                message_arg: Some(2),
+            },
+            CatalogPattern {
+                macro_name: "FW_ERROR".to_string(),
+                code_arg: 0,
+                message_arg: None,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_scan_source() {
+        let source = r#"#define FW_ASSERT(cond, code, msg) do {} while (0)
+/* FW_ERROR(99) in a comment */
+void f(int x) {
+    FW_ASSERT(x > 0 && (x < 10), 0x1001u, "x out of range, "
+              "expected 1..9");
+    const char *s = "FW_ERROR(98)";
+    FW_ERROR(42);
+}
+"#;
+        let entries = scan_source(Path::new("src/f.c"), source, &patterns());
+        assert_eq!(entries.len(), 2);
+
+        assert_eq!(entries[0].code, 0x1001);
+        assert_eq!(entries[0].code_text, "0x1001u");
+        assert_eq!(
+            entries[0].message.as_deref(),
+            Some("x out of range, expected 1..9")
+        );
+        assert_eq!(entries[0].location.range.start, Position::new(3, 4));
+
+        assert_eq!(entries[1].code, 42);
+        assert_eq!(entries[1].message, None);
+        assert_eq!(entries[1].location.range.start, Position::new(6, 4));
+    }
+
+    #[test]
+    fn test_parse_integer() {
+        assert_eq!(parse_integer("0x1F"), Some(31));
+        assert_eq!(parse_integer("0b101"), Some(5));
+        assert_eq!(parse_integer("017"), Some(15));
+        assert_eq!(parse_integer("42UL"), Some(42));
+        assert_eq!(parse_integer("ERR_TIMEOUT"), None);
+    }
+
+    #[test]
+    fn test_duplicates_and_export() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join(".axiom")).unwrap();
+        fs::write(
+            catalog_config_path(root),
+            "[[pattern]]\nmacro = \"FW_ERROR\"\ncode_arg = 0\nmessage_arg = 1\n",
+        )
+        .unwrap();
+        fs::write(root.join("a.c"), "FW_ERROR(16, \"bad, \\\"crc\\\"\");\n").unwrap();
+        fs::write(
+            root.join("b.c"),
+            "FW_ERROR(0x10, \"timeout\");\nFW_ERROR(7, \"ok\");\n",
+        )
+        .unwrap();
+
+        let patterns = load_catalog_patterns(root).unwrap();
+        let catalog = extract_catalog(root, &patterns).unwrap();
+        assert_eq!(catalog.entries.len(), 3);
+        assert_eq!(catalog.entries[0].code, 7);
+
+        assert_eq!(catalog.duplicates.len(), 1);
+        assert_eq!(catalog.duplicates[0].code, 16);
+        assert_eq!(catalog.duplicates[0].locations.len(), 2);
+
+        let csv = catalog.to_csv();
+        assert!(csv.starts_with("code,macro,message,file,line\n7,FW_ERROR,ok,"));
+        assert!(csv.contains("\"bad, \"\"crc\"\"\""));
+
+        let md = catalog.to_markdown();
+        assert!(md.contains("| 0x10 | FW_ERROR | timeout |"));
+    }
+
+    #[test]
+    fn test_missing_config() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(load_catalog_patterns(dir.path()).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,435 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Clang Static Analyzer integration.
+//!
+//! Runs `clang --analyze` on one translation unit at a time and reads the
+//! plist report, which carries the full bug path rather than just the
+//! final location.
+
+use axiom_core::{Diagnostic, Location, Position, Range};
+use axiom_toolchain::{is_timeout, run_limited};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Instant;
+use thiserror::Error;
+
+/// Static analyzer errors.
+#[derive(Debug, Error)]
+pub enum AnalyzerError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid plist: {0}")]
+    Xml(#[from] roxmltree::Error),
+
+    #[error("Malformed analyzer report: {0}")]
+    Malformed(String),
+}
+
+/// A request to analyze one translation unit.
+#[derive(Debug, Clone)]
+pub struct AnalyzerRequest {
+    /// Path to clang.
+    pub clang: PathBuf,
+    /// Translation unit to analyze.
+    pub source: PathBuf,
+    /// Where the plist report is written.
+    pub plist_output: PathBuf,
+    /// Compiler flags (defines, include paths, target).
+    pub flags: Vec<String>,
+    /// Additional checkers to enable (e.g. "security.insecureAPI.strcpy").
+    pub checkers: Vec<String>,
+}
+
+impl AnalyzerRequest {
+    /// Create a new request.
+    pub fn new(clang: PathBuf, source: PathBuf, plist_output: PathBuf) -> Self {
+        Self {
+            clang,
+            source,
+            plist_output,
+            flags: Vec::new(),
+            checkers: Vec::new(),
+        }
+    }
+
+    /// Add a compiler flag.
+    pub fn with_flag(mut self, flag: impl Into<String>) -> Self {
+        self.flags.push(flag.into());
+        self
+    }
+
+    /// Enable an additional checker.
+    pub fn with_checker(mut self, checker: impl Into<String>) -> Self {
+        self.checkers.push(checker.into());
+        self
+    }
+}
+
+/// One step along an analyzer bug path.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnalyzerStep {
+    /// Where the event happens.
+    pub location: Location,
+    /// Event description.
+    pub message: String,
+    /// Call depth (0 is the function containing the bug).
+    pub depth: u32,
+}
+
+/// A path-sensitive bug report.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnalyzerReport {
+    /// Checker that produced the report (e.g. "core.NullDereference").
+    pub check_name: String,
+    /// Bug category (e.g. "Logic error").
+    pub category: String,
+    /// Bug description.
+    pub description: String,
+    /// Location of the bug.
+    pub location: Location,
+    /// Events leading to the bug, in order.
+    pub steps: Vec<AnalyzerStep>,
+}
+
+impl AnalyzerReport {
+    /// Convert into a warning followed by one note per path step.
+    pub fn to_diagnostics(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = vec![Diagnostic::warning(&self.description)
+            .with_code(&self.check_name)
+            .with_location(self.location.clone())];
+        diagnostics.extend(self.steps.iter().enumerate().map(|(i, step)| {
+            Diagnostic::note(format!("{}. {}", i + 1, step.message))
+                .with_code(&self.check_name)
+                .with_location(step.location.clone())
+        }));
+        diagnostics
+    }
+}
+
+/// Result of analyzing one translation unit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnalyzerResult {
+    /// Exit code of clang.
+    pub exit_code: i32,
+    /// Bug reports.
+    pub reports: Vec<AnalyzerReport>,
+    /// Compiler errors that prevented analysis.
+    pub diagnostics: Vec<Diagnostic>,
+    /// Duration in milliseconds.
+    pub duration_ms: u64,
+    /// Whether clang was killed for exceeding the tool timeout.
+    #[serde(default)]
+    pub timed_out: bool,
+}
+
+/// Build clang arguments for an analyzer request.
+pub fn build_analyzer_command(request: &AnalyzerRequest) -> Vec<String> {
+    let mut args = vec![
+        "--analyze".to_string(),
+        "--analyzer-output".to_string(),
+        "plist".to_string(),
+        "-o".to_string(),
+        request.plist_output.display().to_string(),
+    ];
+
+    for checker in &request.checkers {
+        args.push("-Xclang".to_string());
+        args.push(format!("-analyzer-checker={}", checker));
+    }
+
+    args.extend(request.flags.iter().cloned());
+    args.push(request.source.display().to_string());
+    args
+}
+
+/// Run the analyzer on one translation unit.
+pub fn run_clang_analyzer(request: &AnalyzerRequest) -> AnalyzerResult {
+    let args = build_analyzer_command(request);
+    let start = Instant::now();
+    let output = run_limited(Command::new(&request.clang).args(&args));
+    let duration_ms = start.elapsed().as_millis() as u64;
+
+    let output = match output {
+        Ok(output) => output,
+        Err(e) => {
+            return AnalyzerResult {
+                exit_code: -1,
+                reports: Vec::new(),
+                diagnostics: vec![Diagnostic::error(format!("Failed to run clang: {}", e))],
+                duration_ms,
+                timed_out: is_timeout(&e),
+            }
+        }
+    };
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let mut diagnostics: Vec<Diagnostic> = stderr
+        .lines()
+        .filter(|l| l.contains("error:"))
+        .map(Diagnostic::error)
+        .collect();
+
+    let reports = match read_analyzer_plist(&request.plist_output) {
+        Ok(reports) => reports,
+        Err(e) => {
+            // No plist is expected when the TU fails to compile
+            if output.status.success() {
+                diagnostics.push(Diagnostic::error(e.to_string()));
+            }
+            Vec::new()
+        }
+    };
+
+    AnalyzerResult {
+        exit_code: output.status.code().unwrap_or(-1),
+        reports,
+        diagnostics,
+        duration_ms,
+        timed_out: false,
+    }
+}
+
+/// Read an analyzer plist report from disk.
+pub fn read_analyzer_plist(path: &Path) -> Result<Vec<AnalyzerReport>, AnalyzerError> {
+    parse_analyzer_plist(&std::fs::read_to_string(path)?)
+}
+
+/// Parse an analyzer plist report.
+pub fn parse_analyzer_plist(xml: &str) -> Result<Vec<AnalyzerReport>, AnalyzerError> {
+    // Analyzer plists carry a DOCTYPE declaration
+    let options = roxmltree::ParsingOptions {
+        allow_dtd: true,
+        ..Default::default()
+    };
+    let doc = roxmltree::Document::parse_with_options(xml, options)?;
+    let root = doc
+        .root_element()
+        .children()
+        .find(|n| n.has_tag_name("dict"))
+        .ok_or_else(|| AnalyzerError::Malformed("missing top-level dict".to_string()))?;
+
+    let files: Vec<PathBuf> = dict_get(root, "files")
+        .map(|array| {
+            elements(array)
+                .map(|n| PathBuf::from(n.text().unwrap_or_default()))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let Some(diagnostics) = dict_get(root, "diagnostics") else {
+        return Ok(Vec::new());
+    };
+
+    elements(diagnostics)
+        .map(|diag| {
+            let location = dict_get(diag, "location")
+                .and_then(|l| parse_location(l, &files))
+                .ok_or_else(|| AnalyzerError::Malformed("report without location".to_string()))?;
+
+            let steps = dict_get(diag, "path")
+                .map(|path| {
+                    elements(path)
+                        .filter(|piece| dict_string(*piece, "kind") == Some("event"))
+                        .filter_map(|event| {
+                            Some(AnalyzerStep {
+                                location: parse_location(dict_get(event, "location")?, &files)?,
+                                message: dict_string(event, "message")?.to_string(),
+                                depth: dict_string(event, "depth")
+                                    .and_then(|d| d.parse().ok())
+                                    .unwrap_or(0),
+                            })
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            Ok(AnalyzerReport {
+                check_name: dict_string(diag, "check_name")
+                    .unwrap_or_default()
+                    .to_string(),
+                category: dict_string(diag, "category")
+                    .unwrap_or_default()
+                    .to_string(),
+                description: dict_string(diag, "description")
+                    .unwrap_or_default()
+                    .to_string(),
+                location,
+                steps,
+            })
+        })
+        .collect()
+}
+
+/// Child elements of a plist node, skipping whitespace text.
+fn elements<'a, 'input>(
+    node: roxmltree::Node<'a, 'input>,
+) -> impl Iterator<Item = roxmltree::Node<'a, 'input>> {
+    node.children().filter(|n| n.is_element())
+}
+
+/// Look up a key in a plist `<dict>`.
+fn dict_get<'a, 'input>(
+    dict: roxmltree::Node<'a, 'input>,
+    key: &str,
+) -> Option<roxmltree::Node<'a, 'input>> {
+    let mut children = elements(dict);
+    while let Some(node) = children.next() {
+        let value = children.next()?;
+        if node.has_tag_name("key") && node.text() == Some(key) {
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// Look up a scalar value in a plist `<dict>`.
+fn dict_string<'a>(dict: roxmltree::Node<'a, '_>, key: &str) -> Option<&'a str> {
+    dict_get(dict, key).and_then(|n| n.text())
+}
+
+/// Parse a `{line, col, file}` dict; line and column are 1-based.
+fn parse_location(dict: roxmltree::Node, files: &[PathBuf]) -> Option<Location> {
+    let line: u32 = dict_string(dict, "line")?.parse().ok()?;
+    let col: u32 = dict_string(dict, "col")?.parse().ok()?;
+    let file: usize = dict_string(dict, "file")?.parse().ok()?;
+    let position = Position::new(line.saturating_sub(1), col.saturating_sub(1));
+    Some(Location::new(
+        files.get(file)?.clone(),
+        Range::new(position, position),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axiom_core::Severity;
+
+    const PLIST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple Computer//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+ <key>clang_version</key>
+<string>clang version 17.0.6</string>
+ <key>diagnostics</key>
+ <array>
+  <dict>
+   <key>path</key>
+   <array>
+    <dict>
+     <key>kind</key><string>event</string>
+     <key>location</key>
+     <dict>
+      <key>line</key><integer>3</integer>
+      <key>col</key><integer>5</integer>
+      <key>file</key><integer>0</integer>
+     </dict>
+     <key>depth</key><integer>0</integer>
+     <key>extended_message</key>
+     <string>&apos;p&apos; initialized to a null pointer value</string>
+     <key>message</key>
+     <string>&apos;p&apos; initialized to a null pointer value</string>
+    </dict>
+    <dict>
+     <key>kind</key><string>control</string>
+     <key>edges</key>
+     <array/>
+    </dict>
+    <dict>
+     <key>kind</key><string>event</string>
+     <key>location</key>
+     <dict>
+      <key>line</key><integer>4</integer>
+      <key>col</key><integer>8</integer>
+      <key>file</key><integer>0</integer>
+     </dict>
+     <key>depth</key><integer>0</integer>
+     <key>message</key>
+     <string>Dereference of null pointer (loaded from variable &apos;p&apos;)</string>
+    </dict>
+   </array>
+   <key>description</key><string>Dereference of null pointer (loaded from variable &apos;p&apos;)</string>
+   <key>category</key><string>Logic error</string>
+   <key>type</key><string>Dereference of null pointer</string>
+   <key>check_name</key><string>core.NullDereference</string>
+   <key>location</key>
+   <dict>
+    <key>line</key><integer>4</integer>
+    <key>col</key><integer>8</integer>
+    <key>file</key><integer>0</integer>
+   </dict>
+  </dict>
+ </array>
+ <key>files</key>
+ <array>
+  <string>src/main.c</string>
+ </array>
+</dict>
+</plist>
+"#;
+
+    #[test]
+    fn test_parse_plist() {
+        let reports = parse_analyzer_plist(PLIST).unwrap();
+        assert_eq!(reports.len(), 1);
+
+        let report = &reports[0];
+        assert_eq!(report.check_name, "core.NullDereference");
+        assert_eq!(report.category, "Logic error");
+        assert_eq!(report.location.path, PathBuf::from("src/main.c"));
+        assert_eq!(report.location.range.start, Position::new(3, 7));
+
+        assert_eq!(report.steps.len(), 2);
+        assert_eq!(
+            report.steps[0].message,
+            "'p' initialized to a null pointer value"
+        );
+        assert_eq!(report.steps[0].location.range.start, Position::new(2, 4));
+    }
+
+    #[test]
+    fn test_report_to_diagnostics() {
+        let diags = parse_analyzer_plist(PLIST).unwrap()[0].to_diagnostics();
+        assert_eq!(diags.len(), 3);
+        assert_eq!(diags[0].severity, Severity::Warning);
+        assert_eq!(diags[0].code.as_deref(), Some("core.NullDereference"));
+        assert_eq!(diags[1].severity, Severity::Note);
+        assert!(diags[1].message.starts_with("1. 'p' initialized"));
+    }
+
+    #[test]
+    fn test_empty_report() {
+        let plist = r#"<plist version="1.0"><dict>
+            <key>diagnostics</key><array></array>
+            <key>files</key><array></array>
+        </dict></plist>"#;
+        assert!(parse_analyzer_plist(plist).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_build_command() {
+        let request = AnalyzerRequest::new(
+            PathBuf::from("/usr/bin/clang"),
+            PathBuf::from("main.c"),
+            PathBuf::from("out/main.plist"),
+        )
+        .with_checker("security.insecureAPI.strcpy")
+        .with_flag("-Iinc");
+
+        let args = build_analyzer_command(&request);
+        assert_eq!(
+            &args[..5],
+            &[
+                "--analyze",
+                "--analyzer-output",
+                "plist",
+                "-o",
+                "out/main.plist"
+            ]
+        );
+        assert!(args.contains(&"-analyzer-checker=security.insecureAPI.strcpy".to_string()));
+        assert_eq!(args.last().unwrap(), "main.c");
+    }
+}
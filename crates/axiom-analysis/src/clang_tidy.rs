@@ -0,0 +1,771 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! clang-tidy integration.
+//!
+//! Findings are read from the fixes file clang-tidy writes with
+//! `--export-fixes`; the text it prints is only parsed when no fixes file
+//! was written.
+
+use axiom_core::{Diagnostic, Location, Position, Range, Severity};
+use axiom_toolchain::{is_timeout, run_limited};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Instant;
+
+/// Known paths for clang-tidy.
+const CLANG_TIDY_PATHS: &[&str] = &[
+    "/usr/bin/clang-tidy",
+    "/opt/homebrew/opt/llvm/bin/clang-tidy",
+    "/usr/local/opt/llvm/bin/clang-tidy",
+    "/usr/local/bin/clang-tidy",
+];
+
+/// Find a clang-tidy binary in the known locations.
+pub fn detect_clang_tidy() -> Option<PathBuf> {
+    CLANG_TIDY_PATHS
+        .iter()
+        .map(PathBuf::from)
+        .find(|p| p.exists())
+}
+
+/// A request to run clang-tidy over a set of sources.
+#[derive(Debug, Clone)]
+pub struct ClangTidyRequest {
+    /// Path to the clang-tidy binary.
+    pub clang_tidy: PathBuf,
+    /// Source files to analyze.
+    pub sources: Vec<PathBuf>,
+    /// Directory containing compile_commands.json (preferred over flags).
+    pub compile_commands_dir: Option<PathBuf>,
+    /// Compiler flags used when no compilation database is available.
+    pub compile_flags: Vec<String>,
+    /// Check filter (e.g. "-*,bugprone-*,cert-*").
+    pub checks: Option<String>,
+    /// File clang-tidy exports its findings to.
+    pub export_fixes: Option<PathBuf>,
+}
+
+impl ClangTidyRequest {
+    /// Create a new request.
+    pub fn new(clang_tidy: PathBuf, sources: Vec<PathBuf>) -> Self {
+        Self {
+            clang_tidy,
+            sources,
+            compile_commands_dir: None,
+            compile_flags: Vec::new(),
+            checks: None,
+            export_fixes: None,
+        }
+    }
+
+    /// Use the compilation database in a directory.
+    pub fn with_compile_commands(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.compile_commands_dir = Some(dir.into());
+        self
+    }
+
+    /// Add a compiler flag (used without a compilation database).
+    pub fn with_flag(mut self, flag: impl Into<String>) -> Self {
+        self.compile_flags.push(flag.into());
+        self
+    }
+
+    /// Set the check filter.
+    pub fn with_checks(mut self, checks: impl Into<String>) -> Self {
+        self.checks = Some(checks.into());
+        self
+    }
+
+    /// Export findings to a file.
+    pub fn with_export_fixes(mut self, path: impl Into<PathBuf>) -> Self {
+        self.export_fixes = Some(path.into());
+        self
+    }
+}
+
+/// Kind of inline suppression comment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SuppressionKind {
+    /// `NOLINT` on the same line.
+    NoLint,
+    /// `NOLINTNEXTLINE` for the following line.
+    NoLintNextLine,
+    /// `NOLINTBEGIN` opening a suppressed region.
+    NoLintBegin,
+    /// `NOLINTEND` closing a suppressed region.
+    NoLintEnd,
+}
+
+/// An inline suppression found in source.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Suppression {
+    /// Where the suppression comment appears.
+    pub location: Location,
+    /// Kind of suppression.
+    pub kind: SuppressionKind,
+    /// Checks named in the suppression; empty means all checks.
+    pub checks: Vec<String>,
+}
+
+/// Summary of findings clang-tidy suppressed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SuppressionSummary {
+    /// Total suppressed warnings.
+    pub total: u32,
+    /// Suppressed because they were in non-user code (headers).
+    pub non_user_code: u32,
+    /// Suppressed by NOLINT comments.
+    pub nolint: u32,
+}
+
+/// Result of a clang-tidy run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClangTidyResult {
+    /// Exit code of clang-tidy.
+    pub exit_code: i32,
+    /// Findings, with the check name as the diagnostic code.
+    pub diagnostics: Vec<Diagnostic>,
+    /// Counts reported by clang-tidy for suppressed findings.
+    pub suppressed: SuppressionSummary,
+    /// Inline suppression comments in the analyzed sources.
+    pub suppressions: Vec<Suppression>,
+    /// Duration in milliseconds.
+    pub duration_ms: u64,
+    /// Whether clang-tidy was killed for exceeding the tool timeout.
+    #[serde(default)]
+    pub timed_out: bool,
+}
+
+/// Build clang-tidy arguments for a request.
+pub fn build_clang_tidy_command(request: &ClangTidyRequest) -> Vec<String> {
+    let mut args = Vec::new();
+
+    if let Some(ref checks) = request.checks {
+        args.push(format!("--checks={}", checks));
+    }
+
+    if let Some(ref path) = request.export_fixes {
+        args.push(format!("--export-fixes={}", path.display()));
+    }
+
+    if let Some(ref dir) = request.compile_commands_dir {
+        args.push("-p".to_string());
+        args.push(dir.display().to_string());
+    }
+
+    args.extend(request.sources.iter().map(|s| s.display().to_string()));
+
+    if request.compile_commands_dir.is_none() {
+        // Everything after "--" is passed to the compiler frontend
+        args.push("--".to_string());
+        args.extend(request.compile_flags.iter().cloned());
+    }
+
+    args
+}
+
+/// Run clang-tidy.
+///
+/// Without an export path in the request, findings are exported to a
+/// temporary directory private to this run.
+pub fn run_clang_tidy(request: &ClangTidyRequest) -> ClangTidyResult {
+    let temp_dir = match request.export_fixes {
+        Some(_) => None,
+        None => tempfile::TempDir::new().ok(),
+    };
+    let request = match temp_dir {
+        Some(ref dir) => request
+            .clone()
+            .with_export_fixes(dir.path().join("fixes.yaml")),
+        None => request.clone(),
+    };
+
+    let args = build_clang_tidy_command(&request);
+    let start = Instant::now();
+    let output = run_limited(Command::new(&request.clang_tidy).args(&args));
+    let duration_ms = start.elapsed().as_millis() as u64;
+
+    let suppressions = request
+        .sources
+        .iter()
+        .filter_map(|path| {
+            let source = std::fs::read_to_string(path).ok()?;
+            Some(find_suppressions(path, &source))
+        })
+        .flatten()
+        .collect();
+
+    match output {
+        Ok(output) => {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            // clang-tidy only writes the fixes file when it has findings
+            let exported = request
+                .export_fixes
+                .as_ref()
+                .and_then(|path| std::fs::read_to_string(path).ok());
+            let diagnostics = match exported {
+                Some(yaml) => {
+                    parse_exported_fixes(&yaml, |path| std::fs::read_to_string(path).ok())
+                }
+                None => parse_clang_tidy_output(&stdout),
+            };
+            ClangTidyResult {
+                exit_code: output.status.code().unwrap_or(-1),
+                diagnostics,
+                suppressed: parse_suppression_summary(&stderr),
+                suppressions,
+                duration_ms,
+                timed_out: false,
+            }
+        }
+        Err(e) => ClangTidyResult {
+            exit_code: -1,
+            diagnostics: vec![Diagnostic::error(format!(
+                "Failed to run clang-tidy: {}",
+                e
+            ))],
+            suppressed: SuppressionSummary::default(),
+            suppressions,
+            duration_ms,
+            timed_out: is_timeout(&e),
+        },
+    }
+}
+
+/// A finding or note from an exported fixes file.
+#[derive(Debug, Default)]
+struct ExportedMessage {
+    message: String,
+    file_path: String,
+    file_offset: usize,
+}
+
+/// A finding from an exported fixes file.
+#[derive(Debug, Default)]
+struct ExportedDiagnostic {
+    check: String,
+    level: String,
+    message: ExportedMessage,
+    notes: Vec<ExportedMessage>,
+}
+
+/// Parse the fixes file clang-tidy writes with `--export-fixes`.
+///
+/// The file records byte offsets, so `read_source` supplies the contents of
+/// each referenced file to turn them into line and column. Findings in
+/// files it can't supply are placed at the start of the file. Notes
+/// inherit the check name of the finding they explain.
+pub fn parse_exported_fixes(
+    yaml: &str,
+    mut read_source: impl FnMut(&Path) -> Option<String>,
+) -> Vec<Diagnostic> {
+    let mut sources: HashMap<String, Option<String>> = HashMap::new();
+    let mut locate = |message: &ExportedMessage| {
+        if message.file_path.is_empty() {
+            return None;
+        }
+        let source = sources
+            .entry(message.file_path.clone())
+            .or_insert_with(|| read_source(Path::new(&message.file_path)));
+        let position = source
+            .as_deref()
+            .map(|source| offset_position(source, message.file_offset))
+            .unwrap_or(Position::new(0, 0));
+        Some(Location::new(
+            PathBuf::from(&message.file_path),
+            Range::new(position, position),
+        ))
+    };
+
+    let mut diagnostics = Vec::new();
+    for exported in parse_exported_diagnostics(yaml) {
+        let severity = match exported.level.as_str() {
+            "Error" => Severity::Error,
+            "Remark" => Severity::Note,
+            _ => Severity::Warning,
+        };
+        let check = Some(exported.check).filter(|c| !c.is_empty());
+
+        let mut diag = Diagnostic::new(severity, exported.message.message.clone());
+        diag.location = locate(&exported.message);
+        diag.code = check.clone();
+        diagnostics.push(diag);
+
+        for note in &exported.notes {
+            let mut diag = Diagnostic::note(note.message.clone());
+            diag.location = locate(note);
+            diag.code = check.clone();
+            diagnostics.push(diag);
+        }
+    }
+    diagnostics
+}
+
+/// Read the diagnostics out of an exported fixes file.
+///
+/// Only the subset of YAML clang-tidy emits is understood: block mappings
+/// and sequences with scalar values. Replacements and ranges are skipped.
+fn parse_exported_diagnostics(yaml: &str) -> Vec<ExportedDiagnostic> {
+    let mut diagnostics: Vec<ExportedDiagnostic> = Vec::new();
+    // Enclosing mapping keys and their indentation
+    let mut sections: Vec<(usize, &str)> = Vec::new();
+
+    for line in yaml.lines() {
+        let content = line.trim_start();
+        if content.is_empty() || content.starts_with('#') {
+            continue;
+        }
+        let mut indent = line.len() - content.len();
+        let (item, content) = match content.strip_prefix("- ") {
+            Some(rest) => {
+                indent += 2;
+                (true, rest.trim_start())
+            }
+            None => (false, content),
+        };
+        let Some((key, value)) = split_key(content) else {
+            continue;
+        };
+
+        while sections.last().is_some_and(|(i, _)| *i >= indent) {
+            sections.pop();
+        }
+        let parent = sections.last().map(|(_, key)| *key);
+        let top_level = sections.len() == 1 && parent == Some("Diagnostics");
+
+        if item && top_level {
+            diagnostics.push(ExportedDiagnostic::default());
+        }
+        if item && parent == Some("Notes") {
+            if let Some(diag) = diagnostics.last_mut() {
+                diag.notes.push(ExportedMessage::default());
+            }
+        }
+
+        if value.is_empty() {
+            sections.push((indent, key));
+            continue;
+        }
+        let Some(diag) = diagnostics.last_mut() else {
+            continue;
+        };
+
+        // Before clang-tidy 9 the message fields sat on the diagnostic
+        let message = match parent {
+            Some("DiagnosticMessage") => Some(&mut diag.message),
+            Some("Notes") => diag.notes.last_mut(),
+            _ if top_level => Some(&mut diag.message),
+            _ => None,
+        };
+        match (key, message) {
+            ("DiagnosticName", _) if top_level => diag.check = unquote(value),
+            ("Level", _) if top_level => diag.level = unquote(value),
+            ("Message", Some(message)) => message.message = unquote(value),
+            ("FilePath", Some(message)) => message.file_path = unquote(value),
+            ("FileOffset", Some(message)) => {
+                message.file_offset = value.parse().unwrap_or(0);
+            }
+            _ => {}
+        }
+    }
+
+    diagnostics
+}
+
+/// Split `Key: value` (or `Key:` opening a block) into its parts.
+fn split_key(content: &str) -> Option<(&str, &str)> {
+    match content.split_once(": ") {
+        Some((key, value)) => Some((key, value.trim())),
+        None => content.strip_suffix(':').map(|key| (key, "")),
+    }
+}
+
+/// The value of a plain, single-quoted or double-quoted YAML scalar.
+fn unquote(value: &str) -> String {
+    if let Some(inner) = value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')) {
+        return inner.replace("''", "'");
+    }
+    let Some(inner) = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) else {
+        return value.to_string();
+    };
+
+    let mut unquoted = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unquoted.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unquoted.push('\n'),
+            Some('t') => unquoted.push('\t'),
+            Some(other) => unquoted.push(other),
+            None => {}
+        }
+    }
+    unquoted
+}
+
+/// Zero-based line and byte column of a byte offset into source.
+fn offset_position(source: &str, offset: usize) -> Position {
+    let before = &source.as_bytes()[..offset.min(source.len())];
+    let line = before.iter().filter(|&&b| b == b'\n').count();
+    let column = before.iter().rev().take_while(|&&b| b != b'\n').count();
+    Position::new(line as u32, column as u32)
+}
+
+/// Parse clang-tidy findings.
+///
+/// Lines look like `src/main.c:12:5: warning: message [check-name]`.
+/// Notes inherit the check name of the finding they explain.
+pub fn parse_clang_tidy_output(output: &str) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut current_check: Option<String> = None;
+
+    for line in output.lines() {
+        let Some((location, severity, message)) = split_diagnostic_line(line) else {
+            continue;
+        };
+
+        let (message, check) = match message.rfind(" [") {
+            Some(idx) if message.ends_with(']') => (
+                &message[..idx],
+                Some(message[idx + 2..message.len() - 1].to_string()),
+            ),
+            _ => (message, None),
+        };
+
+        let code = match severity {
+            Severity::Note => current_check.clone(),
+            _ => {
+                current_check = check.clone();
+                check
+            }
+        };
+
+        let mut diag = Diagnostic::new(severity, message).with_location(location);
+        diag.code = code;
+        diagnostics.push(diag);
+    }
+
+    diagnostics
+}
+
+/// Split `path:line:col: severity: message` into its parts.
+fn split_diagnostic_line(line: &str) -> Option<(Location, Severity, &str)> {
+    let markers = [
+        (": error: ", Severity::Error),
+        (": warning: ", Severity::Warning),
+        (": note: ", Severity::Note),
+    ];
+    let (idx, marker, severity) = markers
+        .iter()
+        .filter_map(|(marker, severity)| line.find(marker).map(|idx| (idx, *marker, *severity)))
+        .min_by_key(|(idx, _, _)| *idx)?;
+
+    // Split from the right so Windows drive letters survive.
+    let mut parts = line[..idx].rsplitn(3, ':');
+    let column: u32 = parts.next()?.parse().ok()?;
+    let line_no: u32 = parts.next()?.parse().ok()?;
+    let path = parts.next()?;
+
+    let position = Position::new(line_no.saturating_sub(1), column.saturating_sub(1));
+    let location = Location::new(PathBuf::from(path), Range::new(position, position));
+    Some((location, severity, line[idx + marker.len()..].trim()))
+}
+
+/// Parse the "Suppressed N warnings (...)" summary clang-tidy prints.
+pub fn parse_suppression_summary(stderr: &str) -> SuppressionSummary {
+    let mut summary = SuppressionSummary::default();
+
+    for line in stderr.lines() {
+        let Some(rest) = line.trim().strip_prefix("Suppressed ") else {
+            continue;
+        };
+        summary.total += leading_number(rest);
+
+        if let (Some(open), Some(close)) = (rest.find('('), rest.rfind(')')) {
+            for part in rest[open + 1..close].split(',') {
+                let part = part.trim();
+                let count = leading_number(part);
+                if part.contains("non-user code") {
+                    summary.non_user_code += count;
+                } else if part.contains("NOLINT") {
+                    summary.nolint += count;
+                }
+            }
+        }
+    }
+
+    summary
+}
+
+fn leading_number(s: &str) -> u32 {
+    s.split_whitespace()
+        .next()
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(0)
+}
+
+/// Find NOLINT-style suppression comments in source.
+pub fn find_suppressions(path: &Path, source: &str) -> Vec<Suppression> {
+    let mut suppressions = Vec::new();
+
+    for (line_no, line) in source.lines().enumerate() {
+        let Some(idx) = line.find("NOLINT") else {
+            continue;
+        };
+        let rest = &line[idx + "NOLINT".len()..];
+
+        let (kind, rest) = if let Some(rest) = rest.strip_prefix("NEXTLINE") {
+            (SuppressionKind::NoLintNextLine, rest)
+        } else if let Some(rest) = rest.strip_prefix("BEGIN") {
+            (SuppressionKind::NoLintBegin, rest)
+        } else if let Some(rest) = rest.strip_prefix("END") {
+            (SuppressionKind::NoLintEnd, rest)
+        } else {
+            (SuppressionKind::NoLint, rest)
+        };
+
+        let checks = rest
+            .strip_prefix('(')
+            .and_then(|r| r.split_once(')'))
+            .map(|(list, _)| {
+                list.split(',')
+                    .map(|c| c.trim().to_string())
+                    .filter(|c| !c.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let position = Position::new(line_no as u32, idx as u32);
+        suppressions.push(Suppression {
+            location: Location::new(path.to_path_buf(), Range::new(position, position)),
+            kind,
+            checks,
+        });
+    }
+
+    suppressions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_with_compile_commands() {
+        let request = ClangTidyRequest::new(
+            PathBuf::from("/usr/bin/clang-tidy"),
+            vec![PathBuf::from("src/main.c")],
+        )
+        .with_compile_commands("build")
+        .with_checks("-*,bugprone-*");
+
+        let args = build_clang_tidy_command(&request);
+        assert_eq!(
+            args,
+            vec!["--checks=-*,bugprone-*", "-p", "build", "src/main.c"]
+        );
+    }
+
+    #[test]
+    fn test_command_with_flags() {
+        let request =
+            ClangTidyRequest::new(PathBuf::from("clang-tidy"), vec![PathBuf::from("main.c")])
+                .with_flag("-DSTM32F4")
+                .with_flag("-Iinc");
+
+        let args = build_clang_tidy_command(&request);
+        assert_eq!(args, vec!["main.c", "--", "-DSTM32F4", "-Iinc"]);
+    }
+
+    #[test]
+    fn test_parse_output() {
+        let output = "\
+src/main.c:12:5: warning: Value stored to 'x' is never read [clang-analyzer-deadcode.DeadStores]
+    x = 5;
+    ^
+src/main.c:10:3: note: previous assignment is here
+src/uart.c:3:1: error: function 'f' has no prototype [readability-non-const-parameter]
+";
+        let diags = parse_clang_tidy_output(output);
+        assert_eq!(diags.len(), 3);
+
+        assert_eq!(diags[0].severity, Severity::Warning);
+        assert_eq!(diags[0].message, "Value stored to 'x' is never read");
+        assert_eq!(
+            diags[0].code.as_deref(),
+            Some("clang-analyzer-deadcode.DeadStores")
+        );
+        let loc = diags[0].location.as_ref().unwrap();
+        assert_eq!(loc.path, PathBuf::from("src/main.c"));
+        assert_eq!(loc.range.start, Position::new(11, 4));
+
+        assert_eq!(diags[1].severity, Severity::Note);
+        assert_eq!(
+            diags[1].code.as_deref(),
+            Some("clang-analyzer-deadcode.DeadStores")
+        );
+
+        assert_eq!(diags[2].severity, Severity::Error);
+        assert_eq!(
+            diags[2].code.as_deref(),
+            Some("readability-non-const-parameter")
+        );
+    }
+
+    #[test]
+    fn test_command_with_export_fixes() {
+        let request =
+            ClangTidyRequest::new(PathBuf::from("clang-tidy"), vec![PathBuf::from("main.c")])
+                .with_compile_commands("build")
+                .with_export_fixes("out/fixes.yaml");
+
+        let args = build_clang_tidy_command(&request);
+        assert_eq!(
+            args,
+            vec!["--export-fixes=out/fixes.yaml", "-p", "build", "main.c"]
+        );
+    }
+
+    #[test]
+    fn test_parse_exported_fixes() {
+        let yaml = "\
+---
+MainSourceFile:  '/proj/src/main.c'
+Diagnostics:
+  - DiagnosticName:  clang-analyzer-deadcode.DeadStores
+    DiagnosticMessage:
+      Message:         'Value stored to ''x'' is never read'
+      FilePath:        '/proj/src/main.c'
+      FileOffset:      29
+      Replacements:    []
+      Ranges:
+        - FilePath:        '/proj/src/main.c'
+          FileOffset:      29
+          Length:          5
+    Notes:
+      - Message:         \"previous assignment\\nis here\"
+        FilePath:        '/proj/src/main.c'
+        FileOffset:      16
+        Replacements:    []
+    Level:           Warning
+    BuildDirectory:  '/proj'
+  - DiagnosticName:  clang-diagnostic-error
+    DiagnosticMessage:
+      Message:         'no such file'
+      FilePath:        ''
+      FileOffset:      0
+      Replacements:    []
+    Level:           Error
+    BuildDirectory:  '/proj'
+...
+";
+        let source = "int f(void) {\n  int x = 1;\n  x = 5;\n}\n";
+        let diags = parse_exported_fixes(yaml, |path| {
+            assert_eq!(path, Path::new("/proj/src/main.c"));
+            Some(source.to_string())
+        });
+        assert_eq!(diags.len(), 3);
+
+        assert_eq!(diags[0].severity, Severity::Warning);
+        assert_eq!(diags[0].message, "Value stored to 'x' is never read");
+        assert_eq!(
+            diags[0].code.as_deref(),
+            Some("clang-analyzer-deadcode.DeadStores")
+        );
+        let loc = diags[0].location.as_ref().unwrap();
+        assert_eq!(loc.path, PathBuf::from("/proj/src/main.c"));
+        assert_eq!(loc.range.start, Position::new(2, 2));
+
+        assert_eq!(diags[1].severity, Severity::Note);
+        assert_eq!(diags[1].message, "previous assignment\nis here");
+        assert_eq!(
+            diags[1].code.as_deref(),
+            Some("clang-analyzer-deadcode.DeadStores")
+        );
+        assert_eq!(
+            diags[1].location.as_ref().unwrap().range.start,
+            Position::new(1, 2)
+        );
+
+        assert_eq!(diags[2].severity, Severity::Error);
+        assert_eq!(diags[2].code.as_deref(), Some("clang-diagnostic-error"));
+        assert!(diags[2].location.is_none());
+    }
+
+    #[test]
+    fn test_parse_exported_fixes_legacy_layout() {
+        let yaml = "\
+Diagnostics:
+  - DiagnosticName:  cert-err33-c
+    Message:         unchecked return
+    FileOffset:      4
+    FilePath:        main.c
+    Replacements:
+      - FilePath:        main.c
+        Offset:          0
+        Length:          0
+        ReplacementText: '(void)'
+";
+        let diags = parse_exported_fixes(yaml, |_| None);
+        assert_eq!(diags.len(), 1);
+        assert_eq!(diags[0].message, "unchecked return");
+        assert_eq!(diags[0].code.as_deref(), Some("cert-err33-c"));
+        assert_eq!(
+            diags[0].location.as_ref().unwrap().range.start,
+            Position::new(0, 0)
+        );
+    }
+
+    #[test]
+    fn test_parse_windows_path() {
+        let diags = parse_clang_tidy_output(r"C:\proj\main.c:4:2: warning: oops [cert-err33-c]");
+        let loc = diags[0].location.as_ref().unwrap();
+        assert_eq!(loc.path, PathBuf::from(r"C:\proj\main.c"));
+        assert_eq!(loc.range.start, Position::new(3, 1));
+    }
+
+    #[test]
+    fn test_parse_suppression_summary() {
+        let stderr =
+            "12 warnings generated.\nSuppressed 12 warnings (10 in non-user code, 2 NOLINT).\n";
+        let summary = parse_suppression_summary(stderr);
+        assert_eq!(
+            summary,
+            SuppressionSummary {
+                total: 12,
+                non_user_code: 10,
+                nolint: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn test_find_suppressions() {
+        let source = "\
+int a = 1; // NOLINT
+// NOLINTNEXTLINE(cert-err33-c, bugprone-branch-clone)
+int b = 2;
+// NOLINTBEGIN
+// NOLINTEND
+";
+        let found = find_suppressions(Path::new("a.c"), source);
+        assert_eq!(found.len(), 4);
+        assert_eq!(found[0].kind, SuppressionKind::NoLint);
+        assert!(found[0].checks.is_empty());
+        assert_eq!(found[1].kind, SuppressionKind::NoLintNextLine);
+        assert_eq!(
+            found[1].checks,
+            vec!["cert-err33-c", "bugprone-branch-clone"]
+        );
+        assert_eq!(found[1].location.range.start, Position::new(1, 3));
+        assert_eq!(found[2].kind, SuppressionKind::NoLintBegin);
+        assert_eq!(found[3].kind, SuppressionKind::NoLintEnd);
+    }
+}
@@ -0,0 +1,723 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Data and control coupling analysis (DO-178C 6.4.4.2c).
+//!
+//! Each source file is a module. Data coupling is a function using a
+//! global variable defined in another module, or passing arguments to a
+//! function defined in another module; control coupling is a call into
+//! another module. Couplings are found by parsing the sources, so calls
+//! through function pointers and accesses hidden in macros defined outside
+//! the parsed files are not seen.
+//!
+//! [`verify_coupling_coverage`] checks each coupling against a structural
+//! coverage report: a coupling is exercised when its site was executed
+//! and, for calls, the callee ran.
+
+use axiom_core::walk::find_files;
+use axiom_parser::{AstNode, Language, ParseError, Parser};
+use axiom_toolchain::{CoverageCounter, CoverageReport, FileCoverage};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Source extensions analyzed as modules.
+pub const COUPLING_EXTENSIONS: &[&str] = &["c", "cc", "cpp", "cxx"];
+
+/// Node kinds that declare a name.
+const DECLARATOR_KINDS: &[&str] = &[
+    "identifier",
+    "init_declarator",
+    "pointer_declarator",
+    "array_declarator",
+    "function_declarator",
+];
+
+/// Coupling analysis errors.
+#[derive(Debug, Error)]
+pub enum CouplingError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+}
+
+/// Kind of coupling between two modules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CouplingKind {
+    /// Data coupling through a shared global variable.
+    Global,
+    /// Data coupling through call arguments.
+    Parameter,
+    /// Control coupling through a call.
+    Control,
+}
+
+impl CouplingKind {
+    /// Human-readable name.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Global => "global data",
+            Self::Parameter => "parameter data",
+            Self::Control => "control",
+        }
+    }
+}
+
+/// How a function uses a global variable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DataAccess {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+impl DataAccess {
+    fn combine(self, other: DataAccess) -> DataAccess {
+        if self == other {
+            self
+        } else {
+            DataAccess::ReadWrite
+        }
+    }
+}
+
+/// A global variable visible to other modules.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GlobalVariable {
+    /// Variable name.
+    pub name: String,
+    /// Module defining it.
+    pub module: PathBuf,
+    /// Line of the definition (1-based).
+    pub line: u32,
+}
+
+/// A coupling from a function in one module to a variable or function of
+/// another.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Coupling {
+    /// Kind of coupling.
+    pub kind: CouplingKind,
+    /// Module making the access or call.
+    pub module: PathBuf,
+    /// Function making the access or call.
+    pub function: String,
+    /// Line of the first access or the call (1-based).
+    pub line: u32,
+    /// Variable accessed or function called.
+    pub target: String,
+    /// Module defining the target.
+    pub target_module: PathBuf,
+    /// Use of the variable, for global data coupling.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub access: Option<DataAccess>,
+}
+
+/// Inter-module couplings of a set of sources.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CouplingAnalysis {
+    /// Globals visible to other modules, by module and line.
+    pub globals: Vec<GlobalVariable>,
+    /// Couplings, by module, line and kind.
+    pub couplings: Vec<Coupling>,
+}
+
+impl CouplingAnalysis {
+    /// Couplings of one kind.
+    pub fn of_kind(&self, kind: CouplingKind) -> impl Iterator<Item = &Coupling> {
+        self.couplings.iter().filter(move |c| c.kind == kind)
+    }
+}
+
+/// A coupling and whether testing exercised it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CouplingCoverage {
+    /// The coupling.
+    pub coupling: Coupling,
+    /// The site was executed and, for calls, the callee ran.
+    pub exercised: bool,
+}
+
+/// Couplings checked against a coverage report.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CouplingCoverageReport {
+    /// Every coupling, in analysis order.
+    pub couplings: Vec<CouplingCoverage>,
+}
+
+impl CouplingCoverageReport {
+    /// Couplings no test exercised.
+    pub fn unexercised(&self) -> impl Iterator<Item = &Coupling> {
+        self.couplings
+            .iter()
+            .filter(|c| !c.exercised)
+            .map(|c| &c.coupling)
+    }
+
+    /// Exercised and total couplings of one kind.
+    pub fn counter(&self, kind: CouplingKind) -> CoverageCounter {
+        let couplings: Vec<&CouplingCoverage> = self
+            .couplings
+            .iter()
+            .filter(|c| c.coupling.kind == kind)
+            .collect();
+        CoverageCounter {
+            covered: couplings.iter().filter(|c| c.exercised).count(),
+            total: couplings.len(),
+            justified: 0,
+        }
+    }
+}
+
+/// Analyze the couplings between every source module under a project
+/// root. Module paths are relative to the root.
+pub fn analyze_project_coupling(project_root: &Path) -> Result<CouplingAnalysis, CouplingError> {
+    let mut sources = Vec::new();
+    for path in find_files(project_root, COUPLING_EXTENSIONS)? {
+        let source = std::fs::read_to_string(&path)?;
+        let relative = path.strip_prefix(project_root).unwrap_or(&path);
+        sources.push((relative.to_path_buf(), source));
+    }
+    let mut parser = Parser::new()?;
+    Ok(analyze_coupling(&mut parser, &sources)?)
+}
+
+/// Analyze the couplings between modules given as path and source.
+pub fn analyze_coupling(
+    parser: &mut Parser,
+    sources: &[(PathBuf, String)],
+) -> Result<CouplingAnalysis, ParseError> {
+    let mut modules = Vec::new();
+    for (path, source) in sources {
+        let Some(language) = Language::from_path(path) else {
+            continue;
+        };
+        let root = parser.parse(source, language)?;
+        modules.push(Module::extract(path, source, &root));
+    }
+
+    let globals: Vec<GlobalVariable> = modules.iter().flat_map(|m| m.globals.clone()).collect();
+    let mut variables: BTreeMap<&str, &Path> = BTreeMap::new();
+    for global in &globals {
+        variables
+            .entry(global.name.as_str())
+            .or_insert(global.module.as_path());
+    }
+    let mut functions: BTreeMap<&str, &Path> = BTreeMap::new();
+    for module in &modules {
+        for function in module.functions.iter().filter(|f| !f.is_static) {
+            functions
+                .entry(function.name.as_str())
+                .or_insert(module.path.as_path());
+        }
+    }
+
+    let mut couplings = Vec::new();
+    for module in &modules {
+        for function in &module.functions {
+            for (name, &(access, line)) in &function.accesses {
+                if module.private.contains(name) {
+                    continue;
+                }
+                let Some(&target_module) = variables.get(name.as_str()) else {
+                    continue;
+                };
+                if target_module != module.path {
+                    couplings.push(Coupling {
+                        kind: CouplingKind::Global,
+                        module: module.path.clone(),
+                        function: function.name.clone(),
+                        line,
+                        target: name.clone(),
+                        target_module: target_module.to_path_buf(),
+                        access: Some(access),
+                    });
+                }
+            }
+
+            for call in &function.calls {
+                if module.private.contains(&call.callee) {
+                    continue;
+                }
+                let Some(&target_module) = functions.get(call.callee.as_str()) else {
+                    continue;
+                };
+                if target_module == module.path {
+                    continue;
+                }
+                let coupling = |kind| Coupling {
+                    kind,
+                    module: module.path.clone(),
+                    function: function.name.clone(),
+                    line: call.line,
+                    target: call.callee.clone(),
+                    target_module: target_module.to_path_buf(),
+                    access: None,
+                };
+                couplings.push(coupling(CouplingKind::Control));
+                if call.arguments > 0 {
+                    couplings.push(coupling(CouplingKind::Parameter));
+                }
+            }
+        }
+    }
+    couplings.sort_by(|a, b| {
+        (&a.module, a.line, a.kind, &a.target).cmp(&(&b.module, b.line, b.kind, &b.target))
+    });
+
+    Ok(CouplingAnalysis { globals, couplings })
+}
+
+/// Check which couplings a coverage report exercised.
+pub fn verify_coupling_coverage(
+    analysis: &CouplingAnalysis,
+    coverage: &CoverageReport,
+) -> CouplingCoverageReport {
+    let file = |module: &Path| {
+        coverage
+            .files
+            .iter()
+            .find(|f| f.path.ends_with(module) || module.ends_with(&f.path))
+    };
+    let site_executed =
+        |file: &FileCoverage, line: u32| file.lines.iter().any(|l| l.line == line && l.count > 0);
+
+    let couplings = analysis
+        .couplings
+        .iter()
+        .map(|coupling| {
+            let site = file(&coupling.module).is_some_and(|f| site_executed(f, coupling.line));
+            // A callee without function records counts as run when called
+            let callee = coupling.kind == CouplingKind::Global
+                || file(&coupling.target_module)
+                    .and_then(|f| f.functions.iter().find(|f| f.name == coupling.target))
+                    .is_none_or(|f| f.execution_count > 0);
+            CouplingCoverage {
+                coupling: coupling.clone(),
+                exercised: site && callee,
+            }
+        })
+        .collect();
+    CouplingCoverageReport { couplings }
+}
+
+/// Definitions and uses found in one module.
+struct Module {
+    path: PathBuf,
+    globals: Vec<GlobalVariable>,
+    /// `static` variables and functions, which shadow other modules' names.
+    private: BTreeSet<String>,
+    functions: Vec<FunctionModel>,
+}
+
+struct FunctionModel {
+    name: String,
+    is_static: bool,
+    /// Non-local names used, with the first line of use.
+    accesses: BTreeMap<String, (DataAccess, u32)>,
+    calls: Vec<CallSite>,
+}
+
+struct CallSite {
+    callee: String,
+    line: u32,
+    arguments: usize,
+}
+
+impl Module {
+    fn extract(path: &Path, source: &str, root: &AstNode) -> Self {
+        let mut module = Module {
+            path: path.to_path_buf(),
+            globals: Vec::new(),
+            private: BTreeSet::new(),
+            functions: Vec::new(),
+        };
+        let lines: Vec<&str> = source.lines().collect();
+        module.visit(&lines, root);
+        module
+    }
+
+    fn visit(&mut self, lines: &[&str], node: &AstNode) {
+        for child in &node.children {
+            match child.kind.as_str() {
+                "declaration" => self.declaration(lines, child),
+                "function_definition" => self.function(lines, child),
+                // Preprocessor conditionals and linkage blocks
+                _ => self.visit(lines, child),
+            }
+        }
+    }
+
+    fn declaration(&mut self, lines: &[&str], node: &AstNode) {
+        let storage = storage_class(lines, node);
+        if storage == Some("extern") {
+            return;
+        }
+        for declarator in declarators(node) {
+            if contains_kind(declarator, "function_declarator") {
+                continue;
+            }
+            let Some(name) = declarator_name(declarator) else {
+                continue;
+            };
+            if storage == Some("static") {
+                self.private.insert(name.to_string());
+            } else {
+                self.globals.push(GlobalVariable {
+                    name: name.to_string(),
+                    module: self.path.clone(),
+                    line: declarator.range.start.line + 1,
+                });
+            }
+        }
+    }
+
+    fn function(&mut self, lines: &[&str], node: &AstNode) {
+        let Some(name) = node
+            .children
+            .iter()
+            .find(|c| contains_kind(c, "function_declarator"))
+            .and_then(declarator_name)
+        else {
+            return;
+        };
+        let is_static = storage_class(lines, node) == Some("static");
+        if is_static {
+            self.private.insert(name.to_string());
+        }
+
+        let mut locals = BTreeSet::new();
+        for parameter in node.find_by_kind("parameter_declaration") {
+            locals.extend(declarators(parameter).filter_map(declarator_name));
+        }
+        let body = node
+            .children
+            .iter()
+            .find(|c| c.kind == "compound_statement");
+        for declaration in body.iter().flat_map(|b| b.find_by_kind("declaration")) {
+            locals.extend(declarators(declaration).filter_map(declarator_name));
+        }
+
+        let mut function = FunctionModel {
+            name: name.to_string(),
+            is_static,
+            accesses: BTreeMap::new(),
+            calls: Vec::new(),
+        };
+        if let Some(body) = body {
+            let mut walker = BodyWalker {
+                lines,
+                locals: &locals,
+                function: &mut function,
+            };
+            walker.walk(body);
+        }
+        self.functions.push(function);
+    }
+}
+
+/// Records the non-local names a function body reads, writes and calls.
+struct BodyWalker<'a> {
+    lines: &'a [&'a str],
+    locals: &'a BTreeSet<&'a str>,
+    function: &'a mut FunctionModel,
+}
+
+impl BodyWalker<'_> {
+    fn walk(&mut self, node: &AstNode) {
+        match node.kind.as_str() {
+            "identifier" => self.access(node, DataAccess::Read),
+            "assignment_expression" if node.children.len() == 2 => {
+                let (target, value) = (&node.children[0], &node.children[1]);
+                let operator = text_between(self.lines, target, value).trim();
+                let access = if operator == "=" {
+                    DataAccess::Write
+                } else {
+                    DataAccess::ReadWrite
+                };
+                self.target(target, access);
+                self.walk(value);
+            }
+            "update_expression" => {
+                for child in &node.children {
+                    self.target(child, DataAccess::ReadWrite);
+                }
+            }
+            "call_expression" => {
+                let mut children = node.children.iter();
+                match children.next() {
+                    Some(callee) if callee.kind == "identifier" => {
+                        if let Some(name) = callee.text.as_deref() {
+                            if !self.locals.contains(name) {
+                                self.function.calls.push(CallSite {
+                                    callee: name.to_string(),
+                                    line: callee.range.start.line + 1,
+                                    arguments: children
+                                        .clone()
+                                        .find(|c| c.kind == "argument_list")
+                                        .map_or(0, |a| a.children.len()),
+                                });
+                            }
+                        }
+                    }
+                    // Calls through pointers read the pointer
+                    Some(callee) => self.walk(callee),
+                    None => {}
+                }
+                for child in children {
+                    self.walk(child);
+                }
+            }
+            _ => {
+                for child in &node.children {
+                    self.walk(child);
+                }
+            }
+        }
+    }
+
+    /// Walk the target of an assignment, recording the access on the
+    /// variable it stores into.
+    fn target(&mut self, node: &AstNode, access: DataAccess) {
+        match node.kind.as_str() {
+            "identifier" => self.access(node, access),
+            "subscript_expression" | "field_expression" | "parenthesized_expression" => {
+                let mut children = node.children.iter();
+                if let Some(base) = children.next() {
+                    self.target(base, access);
+                }
+                for child in children.filter(|c| c.kind != "field_identifier") {
+                    self.walk(child);
+                }
+            }
+            // Stores through pointers read the pointer
+            _ => self.walk(node),
+        }
+    }
+
+    fn access(&mut self, node: &AstNode, access: DataAccess) {
+        let Some(name) = node.text.as_deref() else {
+            return;
+        };
+        if self.locals.contains(name) {
+            return;
+        }
+        let line = node.range.start.line + 1;
+        self.function
+            .accesses
+            .entry(name.to_string())
+            .and_modify(|(existing, first)| {
+                *existing = existing.combine(access);
+                *first = (*first).min(line);
+            })
+            .or_insert((access, line));
+    }
+}
+
+/// Declarator children of a declaration.
+fn declarators(node: &AstNode) -> impl Iterator<Item = &AstNode> {
+    node.children
+        .iter()
+        .filter(|c| DECLARATOR_KINDS.contains(&c.kind.as_str()))
+}
+
+/// Name a declarator declares.
+fn declarator_name(node: &AstNode) -> Option<&str> {
+    match node.kind.as_str() {
+        "identifier" | "field_identifier" => node.text.as_deref(),
+        // `ns::name`
+        "qualified_identifier" => node.children.last().and_then(declarator_name),
+        _ => node.children.first().and_then(declarator_name),
+    }
+}
+
+fn contains_kind(node: &AstNode, kind: &str) -> bool {
+    node.kind == kind || node.children.iter().any(|c| contains_kind(c, kind))
+}
+
+/// `static` or `extern`, if the declaration has a storage class.
+fn storage_class<'a>(lines: &[&'a str], node: &AstNode) -> Option<&'a str> {
+    node.children
+        .iter()
+        .filter(|c| c.kind == "storage_class_specifier")
+        .find_map(|c| {
+            let start = c.range.start;
+            let end = c.range.end;
+            if start.line != end.line {
+                return None;
+            }
+            lines
+                .get(start.line as usize)?
+                .get(start.column as usize..end.column as usize)
+        })
+}
+
+/// Source between the end of one node and the start of the next on the
+/// same line; empty otherwise.
+fn text_between<'a>(lines: &[&'a str], before: &AstNode, after: &AstNode) -> &'a str {
+    let (end, start) = (before.range.end, after.range.start);
+    if end.line != start.line {
+        return "";
+    }
+    lines
+        .get(end.line as usize)
+        .and_then(|l| l.get(end.column as usize..start.column as usize))
+        .unwrap_or("")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axiom_toolchain::{FunctionCoverage, LineCoverage};
+
+    const SENSOR: &str = "\
+int sensor_value;
+static int cache;
+
+int sensor_read(int channel)
+{
+    cache = channel;
+    sensor_value = channel * 2;
+    return sensor_value;
+}
+";
+
+    const FILTER: &str = "\
+extern int sensor_value;
+extern int sensor_read(int channel);
+static int cache;
+
+int filter_step(void)
+{
+    int v = sensor_read(1);
+    cache += v;
+    return v + sensor_value;
+}
+";
+
+    const MAIN: &str = "\
+extern int sensor_value;
+int filter_step(void);
+
+int main(void)
+{
+    sensor_value = 0;
+    sensor_value++;
+    filter_step();
+    return 0;
+}
+";
+
+    fn analysis() -> CouplingAnalysis {
+        let mut parser = Parser::new().unwrap();
+        let sources = [
+            (PathBuf::from("src/sensor.c"), SENSOR.to_string()),
+            (PathBuf::from("src/filter.c"), FILTER.to_string()),
+            (PathBuf::from("src/main.c"), MAIN.to_string()),
+        ];
+        analyze_coupling(&mut parser, &sources).unwrap()
+    }
+
+    fn summary(c: &Coupling) -> (CouplingKind, &str, &str, u32, Option<DataAccess>) {
+        (
+            c.kind,
+            c.function.as_str(),
+            c.target.as_str(),
+            c.line,
+            c.access,
+        )
+    }
+
+    #[test]
+    fn test_analyze_coupling() {
+        let analysis = analysis();
+        assert_eq!(analysis.globals.len(), 1);
+        assert_eq!(analysis.globals[0].name, "sensor_value");
+        assert_eq!(analysis.globals[0].module, PathBuf::from("src/sensor.c"));
+
+        let couplings: Vec<_> = analysis.couplings.iter().map(summary).collect();
+        assert_eq!(
+            couplings,
+            [
+                (
+                    CouplingKind::Parameter,
+                    "filter_step",
+                    "sensor_read",
+                    7,
+                    None
+                ),
+                (CouplingKind::Control, "filter_step", "sensor_read", 7, None),
+                (
+                    CouplingKind::Global,
+                    "filter_step",
+                    "sensor_value",
+                    9,
+                    Some(DataAccess::Read)
+                ),
+                (
+                    CouplingKind::Global,
+                    "main",
+                    "sensor_value",
+                    6,
+                    Some(DataAccess::ReadWrite)
+                ),
+                (CouplingKind::Control, "main", "filter_step", 8, None),
+            ]
+        );
+        assert_eq!(analysis.of_kind(CouplingKind::Global).count(), 2);
+    }
+
+    #[test]
+    fn test_locals_shadow_globals() {
+        let mut parser = Parser::new().unwrap();
+        let sources = [
+            (PathBuf::from("a.c"), "int mode;\n".to_string()),
+            (
+                PathBuf::from("b.c"),
+                "void f(int mode) { mode = 1; }\nvoid g(void) { int mode = 2; (void)mode; }\n"
+                    .to_string(),
+            ),
+        ];
+        let analysis = analyze_coupling(&mut parser, &sources).unwrap();
+        assert!(analysis.couplings.is_empty());
+    }
+
+    #[test]
+    fn test_verify_coupling_coverage() {
+        let analysis = analysis();
+        let mut filter = FileCoverage::new("/build/src/filter.c");
+        filter.lines = [(7, 3), (9, 0)]
+            .into_iter()
+            .map(|(line, count)| LineCoverage {
+                line,
+                count,
+                unexecuted_block: false,
+            })
+            .collect();
+        let mut sensor = FileCoverage::new("/build/src/sensor.c");
+        sensor.functions.push(FunctionCoverage {
+            name: "sensor_read".to_string(),
+            demangled_name: None,
+            start_line: 4,
+            end_line: Some(9),
+            execution_count: 3,
+            blocks: None,
+            blocks_executed: None,
+        });
+        let coverage = CoverageReport::from_files([filter, sensor]);
+
+        let report = verify_coupling_coverage(&analysis, &coverage);
+        let unexercised: Vec<_> = report.unexercised().map(summary).collect();
+        assert_eq!(unexercised.len(), 3);
+        assert_eq!(unexercised[0].2, "sensor_value");
+        assert_eq!(report.counter(CouplingKind::Control).covered, 1);
+        assert_eq!(report.counter(CouplingKind::Control).total, 2);
+        assert_eq!(report.counter(CouplingKind::Parameter).covered, 1);
+        assert_eq!(report.counter(CouplingKind::Global).covered, 0);
+    }
+}
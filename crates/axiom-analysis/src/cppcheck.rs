@@ -0,0 +1,292 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! cppcheck integration.
+
+use axiom_core::{Diagnostic, Location, Position, Range, Severity};
+use axiom_toolchain::{is_timeout, run_limited, ArmCompileRequest};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::Instant;
+use thiserror::Error;
+
+/// Known paths for cppcheck.
+const CPPCHECK_PATHS: &[&str] = &[
+    "/usr/bin/cppcheck",
+    "/opt/homebrew/bin/cppcheck",
+    "/usr/local/bin/cppcheck",
+];
+
+/// Checks enabled when none are configured.
+const DEFAULT_ENABLE: &[&str] = &["warning", "style", "performance", "portability"];
+
+/// cppcheck errors.
+#[derive(Debug, Error)]
+pub enum CppcheckError {
+    #[error("Invalid cppcheck XML: {0}")]
+    Xml(#[from] roxmltree::Error),
+}
+
+/// Find a cppcheck binary in the known locations.
+pub fn detect_cppcheck() -> Option<PathBuf> {
+    CPPCHECK_PATHS
+        .iter()
+        .map(PathBuf::from)
+        .find(|p| p.exists())
+}
+
+/// A request to run cppcheck.
+#[derive(Debug, Clone)]
+pub struct CppcheckRequest {
+    /// Path to the cppcheck binary.
+    pub cppcheck: PathBuf,
+    /// Files or directories to analyze.
+    pub sources: Vec<PathBuf>,
+    /// Preprocessor defines (`NAME` or `NAME=VALUE`).
+    pub defines: Vec<String>,
+    /// Include directories.
+    pub include_paths: Vec<PathBuf>,
+    /// Check categories passed to `--enable`.
+    pub enable: Vec<String>,
+}
+
+impl CppcheckRequest {
+    /// Create a new request.
+    pub fn new(cppcheck: PathBuf, sources: Vec<PathBuf>) -> Self {
+        Self {
+            cppcheck,
+            sources,
+            defines: Vec::new(),
+            include_paths: Vec::new(),
+            enable: DEFAULT_ENABLE.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    /// Create a request using the defines and include paths of an ARM compile.
+    pub fn for_arm(cppcheck: PathBuf, sources: Vec<PathBuf>, compile: &ArmCompileRequest) -> Self {
+        let mut request = Self::new(cppcheck, sources);
+        request.defines = compile.all_defines();
+        request.include_paths = compile.include_paths.clone();
+        request
+    }
+
+    /// Add a preprocessor define.
+    pub fn with_define(mut self, define: impl Into<String>) -> Self {
+        self.defines.push(define.into());
+        self
+    }
+
+    /// Add an include directory.
+    pub fn with_include_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.include_paths.push(path.into());
+        self
+    }
+
+    /// Set the enabled check categories.
+    pub fn with_enable(mut self, enable: Vec<String>) -> Self {
+        self.enable = enable;
+        self
+    }
+}
+
+/// Result of a cppcheck run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CppcheckResult {
+    /// Exit code of cppcheck.
+    pub exit_code: i32,
+    /// Findings, with the cppcheck error ID as the diagnostic code.
+    pub diagnostics: Vec<Diagnostic>,
+    /// Duration in milliseconds.
+    pub duration_ms: u64,
+    /// Whether cppcheck was killed for exceeding the tool timeout.
+    #[serde(default)]
+    pub timed_out: bool,
+}
+
+/// Build cppcheck arguments for a request.
+pub fn build_cppcheck_command(request: &CppcheckRequest) -> Vec<String> {
+    let mut args = vec![
+        "--xml".to_string(),
+        "--xml-version=2".to_string(),
+        "--quiet".to_string(),
+        "--inline-suppr".to_string(),
+    ];
+
+    if !request.enable.is_empty() {
+        args.push(format!("--enable={}", request.enable.join(",")));
+    }
+
+    args.extend(request.defines.iter().map(|d| format!("-D{}", d)));
+    args.extend(
+        request
+            .include_paths
+            .iter()
+            .map(|p| format!("-I{}", p.display())),
+    );
+    args.extend(request.sources.iter().map(|s| s.display().to_string()));
+
+    args
+}
+
+/// Run cppcheck.
+pub fn run_cppcheck(request: &CppcheckRequest) -> CppcheckResult {
+    let args = build_cppcheck_command(request);
+    let start = Instant::now();
+    let output = run_limited(Command::new(&request.cppcheck).args(&args));
+    let duration_ms = start.elapsed().as_millis() as u64;
+
+    match output {
+        Ok(output) => {
+            // The XML report is written to stderr
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let diagnostics = match parse_cppcheck_xml(&stderr) {
+                Ok(diagnostics) => diagnostics,
+                Err(e) => vec![Diagnostic::error(e.to_string())],
+            };
+            CppcheckResult {
+                exit_code: output.status.code().unwrap_or(-1),
+                diagnostics,
+                duration_ms,
+                timed_out: false,
+            }
+        }
+        Err(e) => CppcheckResult {
+            exit_code: -1,
+            diagnostics: vec![Diagnostic::error(format!("Failed to run cppcheck: {}", e))],
+            duration_ms,
+            timed_out: is_timeout(&e),
+        },
+    }
+}
+
+/// Parse a cppcheck version 2 XML report.
+///
+/// The first `<location>` of each error is the finding itself; further
+/// locations become notes carrying their `info` text.
+pub fn parse_cppcheck_xml(xml: &str) -> Result<Vec<Diagnostic>, CppcheckError> {
+    let doc = roxmltree::Document::parse(xml)?;
+    let mut diagnostics = Vec::new();
+
+    for error in doc.descendants().filter(|n| n.has_tag_name("error")) {
+        let id = error.attribute("id").unwrap_or("unknown");
+        let severity = match error.attribute("severity") {
+            Some("error") => Severity::Error,
+            Some("information") | Some("debug") => Severity::Note,
+            _ => Severity::Warning,
+        };
+        let message = error
+            .attribute("msg")
+            .or_else(|| error.attribute("verbose"))
+            .unwrap_or_default();
+
+        let mut locations = error
+            .children()
+            .filter(|n| n.has_tag_name("location"))
+            .filter_map(|n| Some((parse_location(&n)?, n.attribute("info"))));
+
+        let mut diag = Diagnostic::new(severity, message).with_code(id);
+        if let Some((location, _)) = locations.next() {
+            diag = diag.with_location(location);
+        }
+        diagnostics.push(diag);
+
+        for (location, info) in locations {
+            if let Some(info) = info {
+                diagnostics.push(Diagnostic::note(info).with_code(id).with_location(location));
+            }
+        }
+    }
+
+    Ok(diagnostics)
+}
+
+fn parse_location(node: &roxmltree::Node) -> Option<Location> {
+    let file = node.attribute("file")?;
+    let line: u32 = node.attribute("line")?.parse().ok()?;
+    let column: u32 = node
+        .attribute("column")
+        .and_then(|c| c.parse().ok())
+        .unwrap_or(1);
+    let position = Position::new(line.saturating_sub(1), column.saturating_sub(1));
+    Some(Location::new(
+        PathBuf::from(file),
+        Range::new(position, position),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axiom_toolchain::{ArmMcuConfig, FloatAbi};
+
+    const REPORT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<results version="2">
+    <cppcheck version="2.13.0"/>
+    <errors>
+        <error id="nullPointer" severity="error" msg="Null pointer dereference: p" verbose="Null pointer dereference: p" cwe="476" file0="src/main.c">
+            <location file="src/main.c" line="5" column="6" info="Null pointer dereference"/>
+            <location file="src/main.c" line="4" column="10" info="Assignment &apos;p=0&apos;, assigned value is 0"/>
+            <symbol>p</symbol>
+        </error>
+        <error id="unusedVariable" severity="style" msg="Unused variable: x" verbose="Unused variable: x" cwe="563" file0="src/uart.c">
+            <location file="src/uart.c" line="12" column="9"/>
+        </error>
+        <error id="missingIncludeSystem" severity="information" msg="Include file not found." verbose="Include file not found."/>
+    </errors>
+</results>
+"#;
+
+    #[test]
+    fn test_parse_xml() {
+        let diags = parse_cppcheck_xml(REPORT).unwrap();
+        assert_eq!(diags.len(), 4);
+
+        assert_eq!(diags[0].severity, Severity::Error);
+        assert_eq!(diags[0].code.as_deref(), Some("nullPointer"));
+        assert_eq!(diags[0].message, "Null pointer dereference: p");
+        let loc = diags[0].location.as_ref().unwrap();
+        assert_eq!(loc.path, PathBuf::from("src/main.c"));
+        assert_eq!(loc.range.start, Position::new(4, 5));
+
+        assert_eq!(diags[1].severity, Severity::Note);
+        assert_eq!(diags[1].message, "Assignment 'p=0', assigned value is 0");
+        assert_eq!(diags[1].code.as_deref(), Some("nullPointer"));
+
+        assert_eq!(diags[2].severity, Severity::Warning);
+        assert_eq!(diags[2].code.as_deref(), Some("unusedVariable"));
+
+        assert_eq!(diags[3].severity, Severity::Note);
+        assert!(diags[3].location.is_none());
+    }
+
+    #[test]
+    fn test_parse_invalid_xml() {
+        assert!(parse_cppcheck_xml("<results>").is_err());
+    }
+
+    #[test]
+    fn test_command_from_arm_request() {
+        let mcu = ArmMcuConfig::new("cortex-m4")
+            .with_fpu("fpv4-sp-d16", FloatAbi::Hard)
+            .with_define("STM32F407xx");
+        let compile =
+            ArmCompileRequest::new(PathBuf::from("src/main.c"), PathBuf::from("main.o"), mcu)
+                .with_define("USE_HAL_DRIVER")
+                .with_include_path("inc");
+
+        let request = CppcheckRequest::for_arm(
+            PathBuf::from("/usr/bin/cppcheck"),
+            vec![PathBuf::from("src")],
+            &compile,
+        );
+        let args = build_cppcheck_command(&request);
+
+        assert!(args.contains(&"--xml-version=2".to_string()));
+        assert!(args.contains(&"--enable=warning,style,performance,portability".to_string()));
+        assert!(args.contains(&"-DSTM32F407xx".to_string()));
+        assert!(args.contains(&"-DUSE_HAL_DRIVER".to_string()));
+        assert!(args.contains(&"-Iinc".to_string()));
+        assert_eq!(args.last().unwrap(), "src");
+    }
+}
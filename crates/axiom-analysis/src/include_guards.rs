@@ -0,0 +1,498 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Include guard consistency checks.
+
+use axiom_core::walk::find_files;
+use axiom_core::{FixIt, Location, Position, Range};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Header file extensions.
+pub const HEADER_EXTENSIONS: &[&str] = &["h", "hh", "hpp", "hxx"];
+
+/// Include guard errors.
+#[derive(Debug, Error)]
+pub enum GuardError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("{0} changed since the fix was planned")]
+    StaleFix(PathBuf),
+}
+
+/// How a header protects against multiple inclusion.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum HeaderGuard {
+    /// `#pragma once`.
+    PragmaOnce,
+    /// `#ifndef NAME` / `#define NAME` / `#endif`.
+    Macro { name: String },
+    /// `#ifndef` and `#define` name different macros.
+    Mismatched { ifndef: String, define: String },
+    /// No guard.
+    Missing,
+}
+
+/// A problem with a header's guard.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum GuardIssue {
+    /// Header has no guard.
+    Missing { path: PathBuf },
+    /// `#ifndef` and `#define` disagree.
+    Mismatched {
+        path: PathBuf,
+        ifndef: String,
+        define: String,
+    },
+    /// Guard macro is also used by other headers.
+    Duplicate {
+        path: PathBuf,
+        name: String,
+        others: Vec<PathBuf>,
+    },
+}
+
+impl GuardIssue {
+    /// Header the issue applies to.
+    pub fn path(&self) -> &Path {
+        match self {
+            GuardIssue::Missing { path }
+            | GuardIssue::Mismatched { path, .. }
+            | GuardIssue::Duplicate { path, .. } => path,
+        }
+    }
+}
+
+/// Result of checking a project's headers.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GuardReport {
+    /// Guard found in each header.
+    pub headers: BTreeMap<PathBuf, HeaderGuard>,
+    /// Problems found.
+    pub issues: Vec<GuardIssue>,
+}
+
+/// A planned guard fix, previewable before it is applied.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GuardFix {
+    /// Header to fix.
+    pub path: PathBuf,
+    /// Guard macro the fix introduces.
+    pub guard: String,
+    /// Edits against the current file contents.
+    pub edits: Vec<FixIt>,
+    /// File contents after the edits.
+    pub preview: String,
+}
+
+/// Check every header under a project root.
+pub fn check_include_guards(root: &Path) -> Result<GuardReport, GuardError> {
+    let mut report = GuardReport::default();
+    let mut by_name: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+
+    for path in find_files(root, HEADER_EXTENSIONS)? {
+        let source = std::fs::read_to_string(&path)?;
+        let guard = detect_guard(&source).guard;
+
+        match &guard {
+            HeaderGuard::Missing => report
+                .issues
+                .push(GuardIssue::Missing { path: path.clone() }),
+            HeaderGuard::Mismatched { ifndef, define } => {
+                report.issues.push(GuardIssue::Mismatched {
+                    path: path.clone(),
+                    ifndef: ifndef.clone(),
+                    define: define.clone(),
+                })
+            }
+            HeaderGuard::Macro { name } => {
+                by_name.entry(name.clone()).or_default().push(path.clone());
+            }
+            HeaderGuard::PragmaOnce => {}
+        }
+        report.headers.insert(path, guard);
+    }
+
+    for (name, paths) in by_name.into_iter().filter(|(_, p)| p.len() > 1) {
+        for path in &paths {
+            report.issues.push(GuardIssue::Duplicate {
+                path: path.clone(),
+                name: name.clone(),
+                others: paths.iter().filter(|p| *p != path).cloned().collect(),
+            });
+        }
+    }
+
+    Ok(report)
+}
+
+/// A guard along with where its macro names appear.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GuardDetection {
+    /// The guard.
+    pub guard: HeaderGuard,
+    /// Spans of the macro name on the `#ifndef` and `#define` lines.
+    name_ranges: Vec<Range>,
+    /// First line after any leading comment block.
+    body_start: u32,
+}
+
+/// Detect the include guard of a header.
+pub fn detect_guard(source: &str) -> GuardDetection {
+    let lines: Vec<&str> = source.lines().collect();
+    let code: Vec<(u32, &str)> = code_lines(&lines);
+    let body_start = code.first().map(|(n, _)| *n).unwrap_or(lines.len() as u32);
+    let missing = GuardDetection {
+        guard: HeaderGuard::Missing,
+        name_ranges: Vec::new(),
+        body_start,
+    };
+
+    let Some(&(first_no, first)) = code.first() else {
+        return missing;
+    };
+
+    match directive(first) {
+        Some(("pragma", "once")) => GuardDetection {
+            guard: HeaderGuard::PragmaOnce,
+            name_ranges: Vec::new(),
+            body_start,
+        },
+        Some(("ifndef", ifndef)) => {
+            let Some(&(define_no, define_line)) = code.get(1) else {
+                return missing;
+            };
+            let Some(("define", define_rest)) = directive(define_line) else {
+                return missing;
+            };
+            let define = define_rest.split_whitespace().next().unwrap_or_default();
+            let closed = code
+                .last()
+                .and_then(|(_, l)| directive(l))
+                .map(|(kw, _)| kw == "endif")
+                .unwrap_or(false);
+            if !closed || code.len() < 3 {
+                return missing;
+            }
+
+            let name_ranges = vec![
+                name_range(first_no, first, ifndef),
+                name_range(define_no, define_line, define),
+            ];
+            let guard = if ifndef == define {
+                HeaderGuard::Macro {
+                    name: ifndef.to_string(),
+                }
+            } else {
+                HeaderGuard::Mismatched {
+                    ifndef: ifndef.to_string(),
+                    define: define.to_string(),
+                }
+            };
+            GuardDetection {
+                guard,
+                name_ranges,
+                body_start,
+            }
+        }
+        _ => missing,
+    }
+}
+
+/// Guard macro name derived from a header's path relative to the project.
+///
+/// `src/drivers/uart.h` becomes `SRC_DRIVERS_UART_H`.
+pub fn guard_name_for(root: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    let mut name: String = relative
+        .to_string_lossy()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    name
+}
+
+/// Plan a fix that gives a header a guard derived from its path.
+///
+/// Existing guard macros are renamed in place; unguarded headers are
+/// wrapped after their leading comment block. Returns `None` for headers
+/// using `#pragma once`.
+pub fn plan_guard_fix(root: &Path, path: &Path, source: &str) -> Option<GuardFix> {
+    let detection = detect_guard(source);
+    let guard = guard_name_for(root, path);
+    let location = |range| Location::new(path.to_path_buf(), range);
+
+    let edits = match detection.guard {
+        HeaderGuard::PragmaOnce => return None,
+        HeaderGuard::Macro { .. } | HeaderGuard::Mismatched { .. } => detection
+            .name_ranges
+            .iter()
+            .map(|range| FixIt::new(location(*range), guard.clone()))
+            .collect(),
+        HeaderGuard::Missing => {
+            let start = Position::new(detection.body_start, 0);
+            let end = end_position(source);
+            let trailer = if source.is_empty() || source.ends_with('\n') {
+                format!("\n#endif /* {} */\n", guard)
+            } else {
+                format!("\n\n#endif /* {} */\n", guard)
+            };
+            vec![
+                FixIt::new(
+                    location(Range::new(start, start)),
+                    format!("#ifndef {0}\n#define {0}\n\n", guard),
+                ),
+                FixIt::new(location(Range::new(end, end)), trailer),
+            ]
+        }
+    };
+
+    let preview = apply_edits(source, &edits)?;
+    Some(GuardFix {
+        path: path.to_path_buf(),
+        guard,
+        edits,
+        preview,
+    })
+}
+
+/// Apply a planned fix to the file on disk.
+pub fn apply_guard_fix(fix: &GuardFix) -> Result<(), GuardError> {
+    let source = std::fs::read_to_string(&fix.path)?;
+    let updated =
+        apply_edits(&source, &fix.edits).ok_or_else(|| GuardError::StaleFix(fix.path.clone()))?;
+    if updated != fix.preview {
+        return Err(GuardError::StaleFix(fix.path.clone()));
+    }
+    std::fs::write(&fix.path, updated)?;
+    Ok(())
+}
+
+/// Apply non-overlapping edits to source text.
+fn apply_edits(source: &str, edits: &[FixIt]) -> Option<String> {
+    let line_starts: Vec<usize> = std::iter::once(0)
+        .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+        .collect();
+    let offset = |p: Position| -> Option<usize> {
+        let start = *line_starts.get(p.line as usize)?;
+        let offset = start + p.column as usize;
+        (offset <= source.len()).then_some(offset)
+    };
+
+    let mut spans = edits
+        .iter()
+        .map(|e| {
+            let range = e.location.range;
+            Some((
+                offset(range.start)?,
+                offset(range.end)?,
+                e.replacement.as_str(),
+            ))
+        })
+        .collect::<Option<Vec<_>>>()?;
+    spans.sort_by_key(|(start, _, _)| *start);
+
+    let mut out = String::with_capacity(source.len() + 64);
+    let mut cursor = 0;
+    for (start, end, replacement) in spans {
+        out.push_str(source.get(cursor..start)?);
+        out.push_str(replacement);
+        cursor = end;
+    }
+    out.push_str(source.get(cursor..)?);
+    Some(out)
+}
+
+/// Position just past the end of the source.
+fn end_position(source: &str) -> Position {
+    let line = source.matches('\n').count() as u32;
+    let column = source.rsplit('\n').next().unwrap_or_default().len() as u32;
+    Position::new(line, column)
+}
+
+/// Non-blank lines outside comments, with 0-based line numbers.
+fn code_lines<'a>(lines: &[&'a str]) -> Vec<(u32, &'a str)> {
+    let mut code = Vec::new();
+    let mut in_block = false;
+
+    for (n, line) in lines.iter().enumerate() {
+        let mut rest = line.trim();
+        loop {
+            if in_block {
+                match rest.find("*/") {
+                    Some(end) => {
+                        rest = rest[end + 2..].trim_start();
+                        in_block = false;
+                    }
+                    None => {
+                        rest = "";
+                        break;
+                    }
+                }
+            } else if let Some(after) = rest.strip_prefix("/*") {
+                rest = after;
+                in_block = true;
+            } else {
+                break;
+            }
+        }
+        if !rest.is_empty() && !rest.starts_with("//") {
+            code.push((n as u32, *line));
+        }
+    }
+
+    code
+}
+
+/// Split a preprocessor line into directive keyword and first argument.
+fn directive(line: &str) -> Option<(&str, &str)> {
+    let rest = line.trim().strip_prefix('#')?.trim_start();
+    let (keyword, rest) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    let argument = rest.split_whitespace().next().unwrap_or_default();
+    Some((keyword, argument))
+}
+
+/// Span of `name` within a line.
+fn name_range(line_no: u32, line: &str, name: &str) -> Range {
+    // The name follows the directive keyword, so search after the '#'
+    let hash = line.find('#').unwrap_or(0);
+    let start = line[hash..].find(name).map(|i| i + hash).unwrap_or(0) as u32;
+    Range::new(
+        Position::new(line_no, start),
+        Position::new(line_no, start + name.len() as u32),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_detect_guard() {
+        let guarded = "// SPDX-License-Identifier: MIT\n/* uart\n * driver */\n#ifndef UART_H\n#define UART_H\nvoid uart_init(void);\n#endif /* UART_H */\n";
+        assert_eq!(
+            detect_guard(guarded).guard,
+            HeaderGuard::Macro {
+                name: "UART_H".to_string()
+            }
+        );
+
+        assert_eq!(
+            detect_guard("#pragma once\nint x;\n").guard,
+            HeaderGuard::PragmaOnce
+        );
+        assert_eq!(detect_guard("int x;\n").guard, HeaderGuard::Missing);
+        assert_eq!(
+            detect_guard("#ifndef A_H\n#define B_H\n#endif\n").guard,
+            HeaderGuard::Mismatched {
+                ifndef: "A_H".to_string(),
+                define: "B_H".to_string()
+            }
+        );
+        // Guard that does not cover the whole file
+        assert_eq!(
+            detect_guard("#ifndef A_H\n#define A_H\n#endif\nint x;\n").guard,
+            HeaderGuard::Missing
+        );
+    }
+
+    #[test]
+    fn test_guard_name_for() {
+        let root = Path::new("/proj");
+        assert_eq!(
+            guard_name_for(root, Path::new("/proj/src/drivers/uart-dma.h")),
+            "SRC_DRIVERS_UART_DMA_H"
+        );
+        assert_eq!(guard_name_for(root, Path::new("/proj/8bit.h")), "_8BIT_H");
+    }
+
+    #[test]
+    fn test_check_project_finds_duplicates() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("inc")).unwrap();
+        fs::write(
+            root.join("inc/a.h"),
+            "#ifndef CONFIG_H\n#define CONFIG_H\n#endif\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("inc/b.h"),
+            "#ifndef CONFIG_H\n#define CONFIG_H\n#endif\n",
+        )
+        .unwrap();
+        fs::write(root.join("inc/c.h"), "#pragma once\n").unwrap();
+        fs::write(root.join("inc/d.h"), "int d;\n").unwrap();
+
+        let report = check_include_guards(root).unwrap();
+        assert_eq!(report.headers.len(), 4);
+        assert_eq!(report.issues.len(), 3);
+        assert!(report.issues.contains(&GuardIssue::Missing {
+            path: root.join("inc/d.h")
+        }));
+        assert!(report.issues.contains(&GuardIssue::Duplicate {
+            path: root.join("inc/a.h"),
+            name: "CONFIG_H".to_string(),
+            others: vec![root.join("inc/b.h")],
+        }));
+    }
+
+    #[test]
+    fn test_fix_missing_guard_after_license() {
+        let root = Path::new("/proj");
+        let path = root.join("inc/led.h");
+        let source = "// SPDX-License-Identifier: MIT\n\nvoid led_on(void);";
+
+        let fix = plan_guard_fix(root, &path, source).unwrap();
+        assert_eq!(fix.guard, "INC_LED_H");
+        assert_eq!(
+            fix.preview,
+            "// SPDX-License-Identifier: MIT\n\n#ifndef INC_LED_H\n#define INC_LED_H\n\nvoid led_on(void);\n\n#endif /* INC_LED_H */\n"
+        );
+        assert_eq!(
+            detect_guard(&fix.preview).guard,
+            HeaderGuard::Macro { name: fix.guard }
+        );
+    }
+
+    #[test]
+    fn test_fix_renames_duplicate_guard() {
+        let root = Path::new("/proj");
+        let source = "#ifndef CONFIG_H\n# define  CONFIG_H\nint x;\n#endif\n";
+        let fix = plan_guard_fix(root, &root.join("b.h"), source).unwrap();
+        assert_eq!(fix.preview, "#ifndef B_H\n# define  B_H\nint x;\n#endif\n");
+        assert!(plan_guard_fix(root, &root.join("c.h"), "#pragma once\n").is_none());
+    }
+
+    #[test]
+    fn test_apply_fix() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("gpio.h");
+        fs::write(&path, "void gpio_init(void);\n").unwrap();
+
+        let fix = plan_guard_fix(dir.path(), &path, &fs::read_to_string(&path).unwrap()).unwrap();
+        apply_guard_fix(&fix).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), fix.preview);
+
+        // Applying again against the changed file is rejected
+        assert!(matches!(
+            apply_guard_fix(&fix),
+            Err(GuardError::StaleFix(_))
+        ));
+    }
+}
@@ -0,0 +1,28 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Axiom Analysis
+//!
+//! Static analysis passes and project-defined diagnostic rules.
+
+mod callgraph;
+mod catalog;
+mod clang_analyzer;
+mod clang_tidy;
+mod coupling;
+mod cppcheck;
+mod include_guards;
+mod misra;
+mod rules;
+mod stack;
+
+pub use callgraph::*;
+pub use catalog::*;
+pub use clang_analyzer::*;
+pub use clang_tidy::*;
+pub use coupling::*;
+pub use cppcheck::*;
+pub use include_guards::*;
+pub use misra::*;
+pub use rules::*;
+pub use stack::*;
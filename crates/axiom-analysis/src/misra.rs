@@ -0,0 +1,355 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! MISRA C:2012 checker output.
+//!
+//! Findings from cppcheck's MISRA addon (`--addon=misra`, reported in its
+//! XML as `misra-c2012-<rule>` errors) and from PC-lint Plus (text output
+//! whose messages end in a `[MISRA 2012 Rule <n>, <category>]` tag), plus
+//! the inline suppression comments both checkers honour.
+
+use crate::{parse_cppcheck_xml, CppcheckError};
+use axiom_core::{Location, Position, Range, Severity};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// cppcheck error ID prefix of MISRA C:2012 findings.
+const CPPCHECK_MISRA_PREFIX: &str = "misra-c2012-";
+
+/// Checker a MISRA finding or suppression comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MisraChecker {
+    /// cppcheck with the MISRA addon.
+    Cppcheck,
+    /// PC-lint Plus.
+    PcLint,
+}
+
+/// A reported violation of a MISRA C:2012 rule or directive.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MisraFinding {
+    /// Rule number (e.g. "10.4"), or "Dir " and the directive number.
+    pub rule: String,
+    /// Checker that reported it.
+    pub checker: MisraChecker,
+    /// Checker's own message ID (e.g. "misra-c2012-10.4", "9034").
+    pub check: String,
+    /// Where the violation is.
+    pub location: Option<Location>,
+    /// Checker message.
+    pub message: String,
+}
+
+impl MisraFinding {
+    /// 1-based line of the violation, if located.
+    pub fn line(&self) -> Option<u32> {
+        self.location.as_ref().map(|l| l.range.start.line + 1)
+    }
+}
+
+/// The MISRA rule of a cppcheck error ID (`misra-c2012-10.4`,
+/// `misra-c2012-dir-4.6`).
+fn cppcheck_rule(id: &str) -> Option<String> {
+    let rule = id.strip_prefix(CPPCHECK_MISRA_PREFIX)?;
+    Some(match rule.strip_prefix("dir-") {
+        Some(directive) => format!("Dir {}", directive),
+        None => rule.to_string(),
+    })
+}
+
+/// Extract MISRA findings from a cppcheck version 2 XML report.
+pub fn parse_cppcheck_misra(xml: &str) -> Result<Vec<MisraFinding>, CppcheckError> {
+    let mut findings = Vec::new();
+    for diag in parse_cppcheck_xml(xml)? {
+        // Further locations of an error come back as notes
+        let Some(code) = diag.code.filter(|_| diag.severity != Severity::Note) else {
+            continue;
+        };
+        let Some(rule) = cppcheck_rule(&code) else {
+            continue;
+        };
+        findings.push(MisraFinding {
+            rule,
+            checker: MisraChecker::Cppcheck,
+            check: code.clone(),
+            location: diag.location,
+            message: diag.message,
+        });
+    }
+    Ok(findings)
+}
+
+/// The MISRA rule in a PC-lint message's trailing tag, e.g.
+/// `[MISRA 2012 Rule 10.3, required]` or `[MISRA 2012 Directive 4.6, advisory]`.
+fn pclint_rule(message: &str) -> Option<String> {
+    let tag = &message[message.rfind("[MISRA")?..];
+    let rest = tag.strip_prefix("[MISRA")?.trim_start();
+    let rest = rest.strip_prefix("C:").unwrap_or(rest).trim_start();
+    let rest = rest.strip_prefix("2012")?.trim_start();
+    let (directive, rest) = if let Some(rest) = rest.strip_prefix("Rule") {
+        (false, rest)
+    } else {
+        (true, rest.strip_prefix("Directive")?)
+    };
+    let number: String = rest
+        .trim_start()
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '.')
+        .collect();
+    if number.is_empty() {
+        return None;
+    }
+    Some(if directive {
+        format!("Dir {}", number)
+    } else {
+        number
+    })
+}
+
+/// Split a PC-lint location prefix: `file(line)` or `file:line[:column]`.
+fn pclint_location(prefix: &str) -> Option<Location> {
+    let (path, line, column) = if let Some(open) = prefix.strip_suffix(')') {
+        let (path, line) = open.rsplit_once('(')?;
+        (path, line.trim().parse::<u32>().ok()?, 1)
+    } else {
+        let mut parts = prefix.rsplitn(3, ':');
+        let last: u32 = parts.next()?.trim().parse().ok()?;
+        match parts.next().map(|p| p.trim().parse::<u32>()) {
+            Some(Ok(line)) => (parts.next()?, line, last),
+            Some(Err(_)) => (prefix.rsplit_once(':')?.0, last, 1),
+            None => return None,
+        }
+    };
+    let position = Position::new(line.saturating_sub(1), column.saturating_sub(1));
+    Some(Location::new(
+        PathBuf::from(path.trim()),
+        Range::new(position, position),
+    ))
+}
+
+/// Extract MISRA findings from PC-lint Plus text output, in either the
+/// `file(line): Note 9034: ...` or the `file:line:col: note 9034: ...`
+/// message format. Messages without a MISRA tag are skipped.
+pub fn parse_pclint_misra(output: &str) -> Vec<MisraFinding> {
+    const KINDS: &[&str] = &["error", "warning", "info", "note", "supplemental"];
+    let mut findings = Vec::new();
+
+    for line in output.lines() {
+        let Some(rule) = pclint_rule(line) else {
+            continue;
+        };
+        // Find ": <kind> <number>:" and split the location off before it
+        let lower = line.to_ascii_lowercase();
+        let Some((idx, kind)) = KINDS
+            .iter()
+            .filter_map(|kind| lower.find(&format!(": {} ", kind)).map(|idx| (idx, *kind)))
+            .min_by_key(|(idx, _)| *idx)
+        else {
+            continue;
+        };
+        let rest = &line[idx + kind.len() + 3..];
+        let Some((number, message)) = rest.split_once(':') else {
+            continue;
+        };
+        let number = number.trim();
+        if number.is_empty() || !number.chars().all(|c| c.is_ascii_digit()) {
+            continue;
+        }
+        findings.push(MisraFinding {
+            rule,
+            checker: MisraChecker::PcLint,
+            check: number.to_string(),
+            location: pclint_location(&line[..idx]),
+            message: message.trim().to_string(),
+        });
+    }
+
+    findings
+}
+
+/// An inline MISRA checker suppression found in source.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MisraSuppression {
+    /// Where the suppression comment appears.
+    pub location: Location,
+    /// Checker that honours it.
+    pub checker: MisraChecker,
+    /// Check IDs suppressed (cppcheck error IDs or PC-lint message numbers).
+    pub checks: Vec<String>,
+    /// 1-based line the suppression covers; `None` for the rest of the file.
+    pub line: Option<u32>,
+}
+
+impl MisraSuppression {
+    /// MISRA rules named by the suppression, for checkers whose IDs carry
+    /// the rule number.
+    pub fn rules(&self) -> Vec<String> {
+        self.checks
+            .iter()
+            .filter_map(|c| cppcheck_rule(c))
+            .collect()
+    }
+}
+
+fn split_ids(list: &str) -> Vec<String> {
+    list.split(|c: char| c == ',' || c.is_whitespace())
+        .map(|c| c.trim().to_string())
+        .filter(|c| !c.is_empty())
+        .collect()
+}
+
+/// Find cppcheck (`// cppcheck-suppress <id>`, `cppcheck-suppress[a,b]`)
+/// and PC-lint (`//lint !e9034`, `/*lint -e9034 */`, `-e{9034}`)
+/// suppression comments in source.
+///
+/// A cppcheck suppression on a line of its own covers the next line; one
+/// after code covers its own line. PC-lint `!e` covers its own line and
+/// `-e` the rest of the file.
+pub fn find_misra_suppressions(path: &Path, source: &str) -> Vec<MisraSuppression> {
+    let mut suppressions = Vec::new();
+
+    for (line_no, line) in source.lines().enumerate() {
+        let number = line_no as u32 + 1;
+        let position = |idx: usize| {
+            let position = Position::new(line_no as u32, idx as u32);
+            Location::new(path.to_path_buf(), Range::new(position, position))
+        };
+
+        if let Some(idx) = line.find("cppcheck-suppress") {
+            let rest = &line[idx + "cppcheck-suppress".len()..];
+            let checks = match rest.strip_prefix('[') {
+                Some(list) => split_ids(list.split_once(']').map_or(list, |(l, _)| l)),
+                None => split_ids(rest.trim_start_matches(['-', ' ']))
+                    .into_iter()
+                    .take(1)
+                    .collect(),
+            };
+            let own_line = line[..idx]
+                .trim_end()
+                .trim_end_matches("//")
+                .trim_end_matches("/*")
+                .trim()
+                .is_empty();
+            suppressions.push(MisraSuppression {
+                location: position(idx),
+                checker: MisraChecker::Cppcheck,
+                checks,
+                line: Some(if own_line { number + 1 } else { number }),
+            });
+        }
+
+        let Some(idx) = line.find("lint ").or_else(|| line.find("lint\t")) else {
+            continue;
+        };
+        if !line[..idx].ends_with("//") && !line[..idx].ends_with("/*") {
+            continue;
+        }
+        for option in line[idx + 4..].split_whitespace() {
+            let option = option.trim_end_matches("*/");
+            let (same_line, ids) = if let Some(ids) = option.strip_prefix("!e") {
+                (true, ids)
+            } else if let Some(ids) = option.strip_prefix("-e") {
+                (false, ids)
+            } else {
+                continue;
+            };
+            let ids = ids
+                .trim_start_matches(['(', '{'])
+                .trim_end_matches([')', '}']);
+            suppressions.push(MisraSuppression {
+                location: position(idx),
+                checker: MisraChecker::PcLint,
+                checks: split_ids(ids),
+                line: same_line.then_some(number),
+            });
+        }
+    }
+
+    suppressions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CPPCHECK: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<results version="2">
+    <cppcheck version="2.13.0"/>
+    <errors>
+        <error id="misra-c2012-10.4" severity="style" msg="misra violation (use --rule-texts=&lt;file&gt; to get proper output)" verbose="misra violation">
+            <location file="src/nav.c" line="12" column="14"/>
+        </error>
+        <error id="unusedVariable" severity="style" msg="Unused variable: x">
+            <location file="src/nav.c" line="3" column="9"/>
+        </error>
+        <error id="misra-c2012-dir-4.6" severity="style" msg="misra violation">
+            <location file="src/nav.h" line="2" column="1"/>
+        </error>
+    </errors>
+</results>
+"#;
+
+    #[test]
+    fn test_parse_cppcheck_misra() {
+        let findings = parse_cppcheck_misra(CPPCHECK).unwrap();
+        assert_eq!(findings.len(), 2);
+        assert_eq!(findings[0].rule, "10.4");
+        assert_eq!(findings[0].check, "misra-c2012-10.4");
+        assert_eq!(findings[0].line(), Some(12));
+        assert_eq!(findings[1].rule, "Dir 4.6");
+    }
+
+    #[test]
+    fn test_parse_pclint_misra() {
+        let output = "\
+src/nav.c(12): Note 9034: expression assigned to a narrower essential type [MISRA 2012 Rule 10.3, required]
+src/nav.c:20:5: warning 9050: dependence placed on operator precedence [MISRA 2012 Rule 12.1, advisory]
+src/nav.h(2): Info 9026: function-like macro defined [MISRA 2012 Directive 4.9, advisory]
+src/nav.c(30): Warning 534: ignoring return value of function
+";
+        let findings = parse_pclint_misra(output);
+        assert_eq!(findings.len(), 3);
+        assert_eq!(findings[0].rule, "10.3");
+        assert_eq!(findings[0].check, "9034");
+        assert_eq!(
+            findings[0].location.as_ref().unwrap().path,
+            PathBuf::from("src/nav.c")
+        );
+        assert_eq!(findings[0].line(), Some(12));
+        assert_eq!(
+            findings[0].message,
+            "expression assigned to a narrower essential type [MISRA 2012 Rule 10.3, required]"
+        );
+        assert_eq!(findings[1].rule, "12.1");
+        assert_eq!(findings[1].line(), Some(20));
+        assert_eq!(
+            findings[1].location.as_ref().unwrap().range.start,
+            Position::new(19, 4)
+        );
+        assert_eq!(findings[2].rule, "Dir 4.9");
+    }
+
+    #[test]
+    fn test_find_misra_suppressions() {
+        let source = "\
+// cppcheck-suppress misra-c2012-10.4
+x = y + 1u;
+z = (int)w; // cppcheck-suppress[misra-c2012-10.8,misra-c2012-11.3]
+a = b; //lint !e9034
+/*lint -e{9050} */
+";
+        let found = find_misra_suppressions(Path::new("a.c"), source);
+        assert_eq!(found.len(), 4);
+        assert_eq!(found[0].checks, ["misra-c2012-10.4"]);
+        assert_eq!(found[0].rules(), ["10.4"]);
+        assert_eq!(found[0].line, Some(2));
+        assert_eq!(found[1].checks, ["misra-c2012-10.8", "misra-c2012-11.3"]);
+        assert_eq!(found[1].line, Some(3));
+        assert_eq!(found[2].checker, MisraChecker::PcLint);
+        assert_eq!(found[2].checks, ["9034"]);
+        assert_eq!(found[2].line, Some(4));
+        assert_eq!(found[3].checks, ["9050"]);
+        assert_eq!(found[3].line, None);
+    }
+}
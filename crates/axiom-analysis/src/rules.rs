@@ -0,0 +1,390 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Project-defined diagnostic rules.
+//!
+//! Rules live in `.axiom/rules/*.toml` or `*.json` and pair a tree-sitter
+//! query with a message and severity:
+//!
+//! ```toml
+//! [[rule]]
+//! id = "HL-001"
+//! message = "Dynamic allocation via {fn} is forbidden"
+//! severity = "error"
+//! capture = "fn"
+//! query = '''
+//! ((call_expression function: (identifier) @fn)
+//!  (#match? @fn "^(malloc|calloc|realloc|free)$"))
+//! '''
+//! ```
+
+use axiom_core::{Diagnostic, Location, Severity};
+use axiom_parser::{Language, ParseError, Parser, QueryMatch};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Error type for rule loading and evaluation.
+#[derive(Debug, thiserror::Error)]
+pub enum RuleError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("TOML parse error in {path}: {source}")]
+    Toml {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+
+    #[error("JSON parse error in {path}: {source}")]
+    Json {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+
+    #[error("Unsupported rule file format: {0}")]
+    UnsupportedFormat(PathBuf),
+
+    #[error("Duplicate rule ID: {0}")]
+    DuplicateId(String),
+
+    #[error("Unknown language '{language}' in rule {rule}")]
+    UnknownLanguage { rule: String, language: String },
+
+    #[error("Invalid query in rule {rule}: {source}")]
+    InvalidQuery { rule: String, source: ParseError },
+}
+
+/// Severity as written in rule files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleSeverity {
+    /// Reported as an error.
+    Error,
+    /// Reported as a warning.
+    #[default]
+    Warning,
+    /// Reported as a note.
+    Note,
+}
+
+impl From<RuleSeverity> for Severity {
+    fn from(severity: RuleSeverity) -> Self {
+        match severity {
+            RuleSeverity::Error => Severity::Error,
+            RuleSeverity::Warning => Severity::Warning,
+            RuleSeverity::Note => Severity::Note,
+        }
+    }
+}
+
+/// A single declarative rule.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rule {
+    /// Unique rule identifier, reported as the diagnostic code.
+    pub id: String,
+    /// Message template; `{capture}` is replaced with the captured text.
+    pub message: String,
+    /// Severity of reported findings.
+    #[serde(default)]
+    pub severity: RuleSeverity,
+    /// Tree-sitter query.
+    pub query: String,
+    /// Capture that locates the finding (defaults to the first capture).
+    #[serde(default)]
+    pub capture: Option<String>,
+    /// Languages the rule applies to ("c", "cpp"); empty means all.
+    #[serde(default)]
+    pub languages: Vec<String>,
+}
+
+impl Rule {
+    /// Languages this rule applies to.
+    fn target_languages(&self) -> Result<Vec<Language>, RuleError> {
+        if self.languages.is_empty() {
+            return Ok(vec![Language::C, Language::Cpp]);
+        }
+
+        self.languages
+            .iter()
+            .map(|name| match name.to_lowercase().as_str() {
+                "c" => Ok(Language::C),
+                "cpp" | "c++" => Ok(Language::Cpp),
+                _ => Err(RuleError::UnknownLanguage {
+                    rule: self.id.clone(),
+                    language: name.clone(),
+                }),
+            })
+            .collect()
+    }
+
+    /// Build a diagnostic for a query match.
+    fn diagnostic(&self, path: &Path, m: &QueryMatch) -> Option<Diagnostic> {
+        let anchor = match &self.capture {
+            Some(name) => m.capture(name)?,
+            None => m.captures.first()?,
+        };
+
+        let mut message = self.message.clone();
+        for capture in &m.captures {
+            message = message.replace(&format!("{{{}}}", capture.name), &capture.text);
+        }
+
+        Some(
+            Diagnostic::new(self.severity.into(), message)
+                .with_code(self.id.clone())
+                .with_location(Location::new(path.to_path_buf(), anchor.range)),
+        )
+    }
+}
+
+/// On-disk layout of a rule file.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct RuleFile {
+    #[serde(default, rename = "rule", alias = "rules")]
+    rules: Vec<Rule>,
+}
+
+/// A set of rules loaded for a project.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RuleSet {
+    rules: Vec<Rule>,
+}
+
+impl RuleSet {
+    /// Directory holding a project's rule files.
+    pub fn project_dir(project_root: &Path) -> PathBuf {
+        project_root.join(".axiom").join("rules")
+    }
+
+    /// Create a rule set from rules, rejecting duplicate IDs.
+    pub fn new(rules: Vec<Rule>) -> Result<Self, RuleError> {
+        let mut set = Self::default();
+        set.extend(rules)?;
+        Ok(set)
+    }
+
+    /// Load all rule files for a project, in file name order.
+    ///
+    /// A project without a rules directory has no rules.
+    pub fn load_project(project_root: &Path) -> Result<Self, RuleError> {
+        let dir = Self::project_dir(project_root);
+        let mut set = Self::default();
+        if !dir.is_dir() {
+            return Ok(set);
+        }
+
+        let mut files: Vec<PathBuf> = fs::read_dir(&dir)?
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| matches!(extension(p).as_deref(), Some("toml" | "json")))
+            .collect();
+        files.sort();
+
+        for file in files {
+            set.extend(load_rule_file(&file)?)?;
+        }
+
+        Ok(set)
+    }
+
+    /// Load a single rule file (TOML or JSON, by extension).
+    pub fn load(path: &Path) -> Result<Self, RuleError> {
+        Self::new(load_rule_file(path)?)
+    }
+
+    /// Rules in load order.
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    /// Check that every rule's query compiles for its languages.
+    pub fn validate(&self, parser: &Parser) -> Result<(), RuleError> {
+        for rule in &self.rules {
+            for language in rule.target_languages()? {
+                parser
+                    .validate_query(&rule.query, language)
+                    .map_err(|source| RuleError::InvalidQuery {
+                        rule: rule.id.clone(),
+                        source,
+                    })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Evaluate all applicable rules against a source file.
+    pub fn evaluate(
+        &self,
+        parser: &mut Parser,
+        path: &Path,
+        source: &str,
+        language: Language,
+    ) -> Result<Vec<Diagnostic>, RuleError> {
+        let mut diagnostics = Vec::new();
+
+        for rule in &self.rules {
+            if !rule.target_languages()?.contains(&language) {
+                continue;
+            }
+
+            let matches = parser
+                .query(source, language, &rule.query)
+                .map_err(|source| RuleError::InvalidQuery {
+                    rule: rule.id.clone(),
+                    source,
+                })?;
+
+            diagnostics.extend(matches.iter().filter_map(|m| rule.diagnostic(path, m)));
+        }
+
+        Ok(diagnostics)
+    }
+
+    fn extend(&mut self, rules: Vec<Rule>) -> Result<(), RuleError> {
+        let mut ids: HashSet<String> = self.rules.iter().map(|r| r.id.clone()).collect();
+        for rule in rules {
+            if !ids.insert(rule.id.clone()) {
+                return Err(RuleError::DuplicateId(rule.id));
+            }
+            self.rules.push(rule);
+        }
+        Ok(())
+    }
+}
+
+fn extension(path: &Path) -> Option<String> {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+}
+
+fn load_rule_file(path: &Path) -> Result<Vec<Rule>, RuleError> {
+    let content = fs::read_to_string(path)?;
+
+    let file: RuleFile = match extension(path).as_deref() {
+        Some("toml") => toml::from_str(&content).map_err(|source| RuleError::Toml {
+            path: path.to_path_buf(),
+            source,
+        })?,
+        Some("json") => serde_json::from_str(&content).map_err(|source| RuleError::Json {
+            path: path.to_path_buf(),
+            source,
+        })?,
+        _ => return Err(RuleError::UnsupportedFormat(path.to_path_buf())),
+    };
+
+    Ok(file.rules)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axiom_core::Position;
+    use tempfile::TempDir;
+
+    const MALLOC_RULE: &str = r#"
+[[rule]]
+id = "HL-001"
+message = "Dynamic allocation via {fn} is forbidden"
+severity = "error"
+capture = "fn"
+query = '''
+((call_expression function: (identifier) @fn)
+ (#match? @fn "^(malloc|calloc|realloc|free)$"))
+'''
+"#;
+
+    fn write_rules(dir: &Path, name: &str, content: &str) {
+        let rules_dir = RuleSet::project_dir(dir);
+        fs::create_dir_all(&rules_dir).unwrap();
+        fs::write(rules_dir.join(name), content).unwrap();
+    }
+
+    #[test]
+    fn test_load_missing_dir() {
+        let dir = TempDir::new().unwrap();
+        let set = RuleSet::load_project(dir.path()).unwrap();
+        assert!(set.rules().is_empty());
+    }
+
+    #[test]
+    fn test_evaluate_toml_rule() {
+        let dir = TempDir::new().unwrap();
+        write_rules(dir.path(), "alloc.toml", MALLOC_RULE);
+        let set = RuleSet::load_project(dir.path()).unwrap();
+
+        let mut parser = Parser::new().unwrap();
+        set.validate(&parser).unwrap();
+
+        let source = "void f(void) {\n    char *p = malloc(4);\n    free(p);\n}\n";
+        let diags = set
+            .evaluate(&mut parser, Path::new("f.c"), source, Language::C)
+            .unwrap();
+
+        assert_eq!(diags.len(), 2);
+        assert_eq!(diags[0].severity, Severity::Error);
+        assert_eq!(diags[0].code.as_deref(), Some("HL-001"));
+        assert_eq!(
+            diags[0].message,
+            "Dynamic allocation via malloc is forbidden"
+        );
+        let location = diags[0].location.as_ref().unwrap();
+        assert_eq!(location.range.start, Position::new(1, 14));
+    }
+
+    #[test]
+    fn test_json_rules_and_language_filter() {
+        let dir = TempDir::new().unwrap();
+        write_rules(
+            dir.path(),
+            "goto.json",
+            r#"{"rules": [{"id": "HL-002", "message": "goto used", "languages": ["cpp"],
+                "query": "(goto_statement) @goto"}]}"#,
+        );
+        let set = RuleSet::load_project(dir.path()).unwrap();
+        let mut parser = Parser::new().unwrap();
+        let source = "void f(void) { goto out; out: return; }";
+
+        let c_diags = set
+            .evaluate(&mut parser, Path::new("f.c"), source, Language::C)
+            .unwrap();
+        assert!(c_diags.is_empty());
+
+        let cpp_diags = set
+            .evaluate(&mut parser, Path::new("f.cpp"), source, Language::Cpp)
+            .unwrap();
+        assert_eq!(cpp_diags.len(), 1);
+        assert_eq!(cpp_diags[0].severity, Severity::Warning);
+    }
+
+    #[test]
+    fn test_duplicate_ids_rejected() {
+        let dir = TempDir::new().unwrap();
+        write_rules(dir.path(), "a.toml", MALLOC_RULE);
+        write_rules(dir.path(), "b.toml", MALLOC_RULE);
+
+        let result = RuleSet::load_project(dir.path());
+        assert!(matches!(result, Err(RuleError::DuplicateId(id)) if id == "HL-001"));
+    }
+
+    #[test]
+    fn test_invalid_query_rejected() {
+        let set = RuleSet::new(vec![Rule {
+            id: "BAD".to_string(),
+            message: "bad".to_string(),
+            severity: RuleSeverity::Note,
+            query: "(no_such_node)".to_string(),
+            capture: None,
+            languages: vec!["c".to_string()],
+        }])
+        .unwrap();
+
+        let parser = Parser::new().unwrap();
+        assert!(matches!(
+            set.validate(&parser),
+            Err(RuleError::InvalidQuery { .. })
+        ));
+    }
+}
@@ -0,0 +1,366 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Worst-case stack depth from GCC `-fstack-usage` output.
+
+use crate::CallGraph;
+use axiom_core::walk::find_files;
+use axiom_core::{Location, Position, Range};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// How GCC bounded a function's frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StackQualifier {
+    /// Fixed frame size.
+    Static,
+    /// Frame grows at run time (alloca, VLAs) without a known bound.
+    Dynamic,
+    /// Frame grows at run time but is bounded.
+    DynamicBounded,
+}
+
+/// One function's frame from a `.su` file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StackFrame {
+    /// Function name.
+    pub function: String,
+    /// Where the function is defined.
+    pub location: Location,
+    /// Frame size in bytes.
+    pub bytes: u64,
+    /// Frame qualifier.
+    pub qualifier: StackQualifier,
+}
+
+/// Worst-case stack depth of one entry point.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StackDepth {
+    /// Entry point (e.g. "main" or an interrupt handler).
+    pub entry: String,
+    /// Deepest stack usage found, in bytes.
+    pub worst_case_bytes: u64,
+    /// Call chain producing the worst case, starting at the entry.
+    pub path: Vec<String>,
+    /// Reachable functions with an unbounded dynamic frame.
+    pub dynamic: Vec<String>,
+    /// Reachable functions without stack usage data.
+    pub unknown: Vec<String>,
+    /// Reachable functions that call through function pointers.
+    #[serde(default)]
+    pub indirect: Vec<String>,
+    /// Whether recursion is reachable from the entry.
+    pub recursive: bool,
+}
+
+impl StackDepth {
+    /// Whether the worst case is a true upper bound.
+    pub fn is_bounded(&self) -> bool {
+        self.dynamic.is_empty()
+            && self.unknown.is_empty()
+            && self.indirect.is_empty()
+            && !self.recursive
+    }
+}
+
+/// Path of the `.su` file GCC writes next to an object file.
+pub fn stack_usage_path(object: &Path) -> PathBuf {
+    object.with_extension("su")
+}
+
+/// Parse a `.su` file.
+///
+/// Lines look like `src/main.c:12:5:main\t16\tstatic`.
+pub fn parse_stack_usage(content: &str) -> Vec<StackFrame> {
+    content.lines().filter_map(parse_stack_usage_line).collect()
+}
+
+fn parse_stack_usage_line(line: &str) -> Option<StackFrame> {
+    let mut fields = line.rsplitn(3, '\t');
+    let qualifier = match fields.next()?.trim() {
+        "static" => StackQualifier::Static,
+        "dynamic" => StackQualifier::Dynamic,
+        "dynamic,bounded" => StackQualifier::DynamicBounded,
+        _ => return None,
+    };
+    let bytes: u64 = fields.next()?.trim().parse().ok()?;
+    let site = fields.next()?;
+
+    // The function name follows the first ":<line>:<column>:", so paths with
+    // drive letters and C++ names containing "::" both survive
+    site.match_indices(':').find_map(|(idx, _)| {
+        let (line_no, column, name_start) = line_column_at(site, idx)?;
+        let position = Position::new(line_no.saturating_sub(1), column.saturating_sub(1));
+        Some(StackFrame {
+            function: site[name_start..].to_string(),
+            location: Location::new(PathBuf::from(&site[..idx]), Range::new(position, position)),
+            bytes,
+            qualifier,
+        })
+    })
+}
+
+/// Parse `:<line>:<column>:` at `idx`, returning the offset after it.
+fn line_column_at(s: &str, idx: usize) -> Option<(u32, u32, usize)> {
+    let rest = &s[idx + 1..];
+    let (line, rest) = rest.split_once(':')?;
+    let (column, _) = rest.split_once(':')?;
+    let line_no = line.parse().ok()?;
+    let column_no = column.parse().ok()?;
+    Some((line_no, column_no, idx + line.len() + column.len() + 3))
+}
+
+/// Load every `.su` file under a build directory.
+pub fn load_stack_usage(build_dir: &Path) -> std::io::Result<Vec<StackFrame>> {
+    let mut frames = Vec::new();
+    for path in find_files(build_dir, &["su"])? {
+        frames.extend(parse_stack_usage(&std::fs::read_to_string(path)?));
+    }
+    Ok(frames)
+}
+
+/// Compute worst-case stack depth for each entry point.
+///
+/// Functions defined in several files (file-local statics) use the largest
+/// frame. Recursive cycles contribute their frames once and mark the result
+/// as unbounded.
+pub fn worst_case_stack(
+    frames: &[StackFrame],
+    graph: &CallGraph,
+    entries: &[String],
+) -> Vec<StackDepth> {
+    let mut by_name: HashMap<&str, &StackFrame> = HashMap::new();
+    for frame in frames {
+        let entry = by_name.entry(frame.function.as_str()).or_insert(frame);
+        if frame.bytes > entry.bytes {
+            *entry = frame;
+        }
+    }
+
+    entries
+        .iter()
+        .map(|entry| {
+            let mut walk = StackWalk {
+                frames: &by_name,
+                graph,
+                on_path: Vec::new(),
+                reached_cycle: false,
+                memo: HashMap::new(),
+                depth: StackDepth {
+                    entry: entry.clone(),
+                    worst_case_bytes: 0,
+                    path: Vec::new(),
+                    dynamic: Vec::new(),
+                    unknown: Vec::new(),
+                    indirect: Vec::new(),
+                    recursive: false,
+                },
+            };
+            let (bytes, path) = walk.visit(entry);
+            let mut depth = walk.depth;
+            depth.worst_case_bytes = bytes;
+            depth.path = path;
+            depth.dynamic.sort();
+            depth.dynamic.dedup();
+            depth.unknown.sort();
+            depth.unknown.dedup();
+            depth.indirect.sort();
+            depth.indirect.dedup();
+            depth
+        })
+        .collect()
+}
+
+struct StackWalk<'a> {
+    frames: &'a HashMap<&'a str, &'a StackFrame>,
+    graph: &'a CallGraph,
+    on_path: Vec<String>,
+    /// Whether the current subtree reached a function already on the path.
+    reached_cycle: bool,
+    /// Results for functions whose subtree reached no cycle, which don't
+    /// depend on the path taken to them.
+    memo: HashMap<String, (u64, Vec<String>)>,
+    depth: StackDepth,
+}
+
+impl StackWalk<'_> {
+    /// Deepest usage below and including `function`, with its call chain.
+    fn visit(&mut self, function: &str) -> (u64, Vec<String>) {
+        if let Some(result) = self.memo.get(function) {
+            return result.clone();
+        }
+        if self.on_path.iter().any(|f| f == function) {
+            self.depth.recursive = true;
+            self.reached_cycle = true;
+            return (0, Vec::new());
+        }
+
+        let own = match self.frames.get(function) {
+            Some(frame) => {
+                if frame.qualifier == StackQualifier::Dynamic {
+                    self.depth.dynamic.push(function.to_string());
+                }
+                frame.bytes
+            }
+            None => {
+                self.depth.unknown.push(function.to_string());
+                0
+            }
+        };
+
+        if self.graph.indirect_callers.contains(function) {
+            self.depth.indirect.push(function.to_string());
+        }
+
+        let outer_cycle = std::mem::take(&mut self.reached_cycle);
+        self.on_path.push(function.to_string());
+        let callees: Vec<String> = self.graph.callees(function).map(String::from).collect();
+        let (deepest, mut chain) = callees.iter().map(|callee| self.visit(callee)).fold(
+            (0, Vec::new()),
+            |best, candidate| {
+                if candidate.0 > best.0 {
+                    candidate
+                } else {
+                    best
+                }
+            },
+        );
+        self.on_path.pop();
+
+        chain.insert(0, function.to_string());
+        let result = (own + deepest, chain);
+        if !self.reached_cycle {
+            self.memo.insert(function.to_string(), result.clone());
+        }
+        self.reached_cycle |= outer_cycle;
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SU: &str = "\
+src/main.c:20:5:main\t24\tstatic
+src/uart.c:10:6:uart_write\t40\tstatic
+src/uart.c:30:13:format\t128\tdynamic,bounded
+C:\\proj\\src\\log.cpp:7:6:void Log::emit(const char*)\t64\tdynamic
+";
+
+    fn frames() -> Vec<StackFrame> {
+        parse_stack_usage(SU)
+    }
+
+    #[test]
+    fn test_parse_stack_usage() {
+        let frames = frames();
+        assert_eq!(frames.len(), 4);
+        assert_eq!(frames[0].function, "main");
+        assert_eq!(frames[0].bytes, 24);
+        assert_eq!(frames[0].location.range.start, Position::new(19, 4));
+        assert_eq!(frames[2].qualifier, StackQualifier::DynamicBounded);
+
+        assert_eq!(frames[3].function, "void Log::emit(const char*)");
+        assert_eq!(
+            frames[3].location.path,
+            PathBuf::from("C:\\proj\\src\\log.cpp")
+        );
+        assert_eq!(frames[3].qualifier, StackQualifier::Dynamic);
+    }
+
+    #[test]
+    fn test_worst_case_path() {
+        let mut graph = CallGraph::new();
+        graph.add_call("main", "uart_write");
+        graph.add_call("main", "format");
+        graph.add_call("uart_write", "format");
+
+        let depths = worst_case_stack(&frames(), &graph, &["main".to_string()]);
+        let main = &depths[0];
+        assert_eq!(main.worst_case_bytes, 24 + 40 + 128);
+        assert_eq!(main.path, vec!["main", "uart_write", "format"]);
+        assert!(main.is_bounded());
+    }
+
+    #[test]
+    fn test_unbounded_results() {
+        let mut graph = CallGraph::new();
+        graph.add_call("main", "parse");
+        graph.add_call("parse", "parse");
+        graph.add_call("main", "void Log::emit(const char*)");
+        graph.add_call("main", "memcpy");
+
+        let mut frames = frames();
+        frames.extend(parse_stack_usage("src/p.c:1:5:parse\t32\tstatic\n"));
+
+        let depth = &worst_case_stack(&frames, &graph, &["main".to_string()])[0];
+        assert!(depth.recursive);
+        assert!(depth.indirect.is_empty());
+        assert_eq!(depth.dynamic, vec!["void Log::emit(const char*)"]);
+        assert_eq!(depth.unknown, vec!["memcpy"]);
+        assert!(!depth.is_bounded());
+        assert_eq!(depth.worst_case_bytes, 24 + 64);
+    }
+
+    #[test]
+    fn test_shared_callees() {
+        // Each level calls both functions of the next, so an unmemoized
+        // walk visits 2^40 chains
+        let mut graph = CallGraph::new();
+        let mut su = String::new();
+        for level in 0..40 {
+            for side in ["l", "r"] {
+                let caller = format!("{}{}", side, level);
+                su.push_str(&format!("src/a.c:1:1:{}\t8\tstatic\n", caller));
+                graph.add_call(caller.clone(), format!("l{}", level + 1));
+                graph.add_call(caller, format!("r{}", level + 1));
+            }
+        }
+        su.push_str("src/a.c:1:1:l40\t16\tstatic\nsrc/a.c:1:1:r40\t4\tstatic\n");
+
+        let depth = &worst_case_stack(&parse_stack_usage(&su), &graph, &["l0".to_string()])[0];
+        assert_eq!(depth.worst_case_bytes, 40 * 8 + 16);
+        assert_eq!(depth.path.len(), 41);
+        assert_eq!(depth.path.last().unwrap(), "l40");
+        assert!(depth.is_bounded());
+    }
+
+    #[test]
+    fn test_cycle_results_depend_on_path() {
+        let mut graph = CallGraph::new();
+        graph.add_call("main", "a");
+        graph.add_call("main", "b");
+        graph.add_call("a", "b");
+        graph.add_call("b", "a");
+        let su = "src/a.c:1:1:main\t8\tstatic\n\
+                  src/a.c:2:1:a\t16\tstatic\n\
+                  src/a.c:3:1:b\t32\tstatic\n";
+
+        // Neither a nor b is counted twice, whichever is entered first
+        let depth = &worst_case_stack(&parse_stack_usage(su), &graph, &["main".to_string()])[0];
+        assert!(depth.recursive);
+        assert_eq!(depth.worst_case_bytes, 8 + 16 + 32);
+    }
+
+    #[test]
+    fn test_indirect_calls_are_unbounded() {
+        let mut graph = CallGraph::new();
+        graph.add_call("main", "uart_write");
+        graph.add_indirect_call("uart_write");
+
+        let depth = &worst_case_stack(&frames(), &graph, &["main".to_string()])[0];
+        assert_eq!(depth.indirect, vec!["uart_write"]);
+        assert!(!depth.is_bounded());
+    }
+
+    #[test]
+    fn test_stack_usage_path() {
+        assert_eq!(
+            stack_usage_path(Path::new("build/main.o")),
+            PathBuf::from("build/main.su")
+        );
+    }
+}
@@ -0,0 +1,348 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Requirement annotation syntax.
+//!
+//! Requirement IDs in comments are recognized by prefix (`REQ-NAV-012`,
+//! `SRS-4.1`) or by anchored regular expressions matched against whole
+//! words (`^[A-Z]{3}-\d{4}$`). Comment markers followed by a colon
+//! (`TEST:`, `DERIVED:`) set the kind of link for the IDs after them on
+//! the same line, as do the Doxygen tags `@req`, `@satisfies`,
+//! `@verifies` and `@derived` (or `\req` and so on). Teams with an existing
+//! numbering scheme configure IDs and markers in the compliance settings;
+//! the default is `REQ-` with `TEST` and `DERIVED` markers.
+
+use crate::LinkType;
+use regex::Regex;
+use std::sync::OnceLock;
+use thiserror::Error;
+
+/// Doxygen tags and the link type they select.
+const DOXYGEN_TAGS: &[(&str, LinkType)] = &[
+    ("req", LinkType::Implements),
+    ("satisfies", LinkType::Implements),
+    ("verifies", LinkType::Tests),
+    ("derived", LinkType::Derived),
+];
+
+/// Annotation syntax errors.
+#[derive(Debug, Error)]
+pub enum AnnotationSyntaxError {
+    #[error("No requirement ID prefix or pattern is configured")]
+    NoIdSyntax,
+
+    #[error("Invalid requirement ID prefix '{0}'")]
+    InvalidPrefix(String),
+
+    #[error("Requirement ID prefixes '{0}' and '{1}' overlap")]
+    OverlappingPrefixes(String, String),
+
+    #[error("Requirement ID pattern '{0}' must be anchored with ^ and $")]
+    Unanchored(String),
+
+    #[error("Invalid requirement ID pattern '{pattern}': {source}")]
+    InvalidPattern {
+        pattern: String,
+        source: regex::Error,
+    },
+
+    #[error("Requirement ID pattern '{0}' matches an empty ID")]
+    EmptyMatch(String),
+
+    #[error("Invalid comment marker '{0}'")]
+    InvalidMarker(String),
+
+    #[error("Comment marker '{0}' is configured for more than one link type")]
+    DuplicateMarker(String),
+
+    #[error("Comment marker '{0}' is also a requirement ID")]
+    MarkerIsId(String),
+}
+
+/// How requirement annotations are written in comments.
+#[derive(Debug, Clone)]
+pub struct AnnotationSyntax {
+    prefixes: Vec<String>,
+    patterns: Vec<Regex>,
+    test_markers: Vec<String>,
+    derived_markers: Vec<String>,
+}
+
+impl Default for AnnotationSyntax {
+    fn default() -> Self {
+        Self {
+            prefixes: vec!["REQ-".to_string()],
+            patterns: Vec::new(),
+            test_markers: vec!["TEST".to_string()],
+            derived_markers: vec!["DERIVED".to_string()],
+        }
+    }
+}
+
+impl AnnotationSyntax {
+    /// Build and validate a syntax. Prefixes must be letters, digits, `_`
+    /// and `-` starting with a letter, and none may start another; patterns
+    /// must be anchored and never match an empty ID; markers must be
+    /// identifiers that are not themselves requirement IDs.
+    pub fn new(
+        prefixes: Vec<String>,
+        id_patterns: Vec<String>,
+        test_markers: Vec<String>,
+        derived_markers: Vec<String>,
+    ) -> Result<Self, AnnotationSyntaxError> {
+        if prefixes.is_empty() && id_patterns.is_empty() {
+            return Err(AnnotationSyntaxError::NoIdSyntax);
+        }
+
+        for prefix in &prefixes {
+            let mut chars = prefix.chars();
+            let valid = chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+                && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+            if !valid {
+                return Err(AnnotationSyntaxError::InvalidPrefix(prefix.clone()));
+            }
+        }
+        for (i, a) in prefixes.iter().enumerate() {
+            for b in &prefixes[i + 1..] {
+                if a.starts_with(b.as_str()) || b.starts_with(a.as_str()) {
+                    return Err(AnnotationSyntaxError::OverlappingPrefixes(
+                        a.clone(),
+                        b.clone(),
+                    ));
+                }
+            }
+        }
+
+        let mut patterns = Vec::new();
+        for pattern in id_patterns {
+            if !pattern.starts_with('^') || !pattern.ends_with('$') || pattern.ends_with("\\$") {
+                return Err(AnnotationSyntaxError::Unanchored(pattern));
+            }
+            let regex =
+                Regex::new(&pattern).map_err(|source| AnnotationSyntaxError::InvalidPattern {
+                    pattern: pattern.clone(),
+                    source,
+                })?;
+            if regex.is_match("") {
+                return Err(AnnotationSyntaxError::EmptyMatch(pattern));
+            }
+            patterns.push(regex);
+        }
+
+        let syntax = Self {
+            prefixes,
+            patterns,
+            test_markers,
+            derived_markers,
+        };
+        let mut seen = Vec::new();
+        for marker in syntax.test_markers.iter().chain(&syntax.derived_markers) {
+            let mut chars = marker.chars();
+            let valid = chars
+                .next()
+                .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if !valid {
+                return Err(AnnotationSyntaxError::InvalidMarker(marker.clone()));
+            }
+            if seen.contains(&marker) {
+                return Err(AnnotationSyntaxError::DuplicateMarker(marker.clone()));
+            }
+            if syntax.is_requirement_id(marker) {
+                return Err(AnnotationSyntaxError::MarkerIsId(marker.clone()));
+            }
+            seen.push(marker);
+        }
+        Ok(syntax)
+    }
+
+    /// Whether a word is a requirement ID.
+    pub fn is_requirement_id(&self, word: &str) -> bool {
+        let by_prefix = self.prefixes.iter().any(|prefix| {
+            word.strip_prefix(prefix.as_str()).is_some_and(|rest| {
+                !rest.is_empty()
+                    && rest
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+            })
+        });
+        by_prefix || self.patterns.iter().any(|p| p.is_match(word))
+    }
+
+    /// A string identifying the syntax, for caches of scan results.
+    pub fn fingerprint(&self) -> String {
+        let patterns: Vec<&str> = self.patterns.iter().map(Regex::as_str).collect();
+        format!(
+            "{:?}",
+            (
+                &self.prefixes,
+                patterns,
+                &self.test_markers,
+                &self.derived_markers
+            )
+        )
+    }
+
+    /// Link type a marker word selects.
+    fn marker(&self, word: &str) -> Option<LinkType> {
+        if self.test_markers.iter().any(|m| m == word) {
+            Some(LinkType::Tests)
+        } else if self.derived_markers.iter().any(|m| m == word) {
+            Some(LinkType::Derived)
+        } else {
+            None
+        }
+    }
+
+    /// Requirement IDs in one line of comment text, with their link types.
+    pub fn find_annotations<'a>(&self, text: &'a str) -> Vec<(&'a str, LinkType)> {
+        let mut found = Vec::new();
+        let mut link_type = LinkType::Implements;
+        for word in word_pattern().find_iter(text) {
+            let followed_by_colon = text[word.end()..].trim_start().starts_with(':');
+            let tag = DOXYGEN_TAGS
+                .iter()
+                .find(|(tag, _)| *tag == word.as_str())
+                .filter(|_| text[..word.start()].ends_with(['@', '\\']));
+            match (self.marker(word.as_str()), tag) {
+                // Markers and tags apply to the IDs after them on the line
+                (Some(marker), _) if followed_by_colon => link_type = marker,
+                (_, Some(&(_, tagged))) => link_type = tagged,
+                _ if self.is_requirement_id(word.as_str()) => {
+                    found.push((word.as_str(), link_type));
+                }
+                _ => {}
+            }
+        }
+        found
+    }
+}
+
+/// Words that may be IDs: letters, digits, `_`, `-` and `.`, starting and
+/// ending with a letter, digit or `_`.
+fn word_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"[A-Za-z0-9_](?:[A-Za-z0-9_.-]*[A-Za-z0-9_])?").expect("valid pattern")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn test_default_syntax() {
+        let syntax = AnnotationSyntax::default();
+        assert_eq!(
+            syntax.find_annotations("DERIVED: REQ-NAV-090, see REQ-NAV-002."),
+            [
+                ("REQ-NAV-090", LinkType::Derived),
+                ("REQ-NAV-002", LinkType::Derived)
+            ]
+        );
+        assert_eq!(
+            syntax.find_annotations("Implements REQ-1 (TEST REQ-2) XREQ-3 REQ-"),
+            [
+                ("REQ-1", LinkType::Implements),
+                ("REQ-2", LinkType::Implements)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_doxygen_tags() {
+        let syntax = AnnotationSyntax::default();
+        assert_eq!(
+            syntax.find_annotations(" * @req REQ-1, REQ-2"),
+            [
+                ("REQ-1", LinkType::Implements),
+                ("REQ-2", LinkType::Implements)
+            ]
+        );
+        assert_eq!(
+            syntax.find_annotations(" * \\verifies REQ-3 \\derived REQ-4 req REQ-5"),
+            [
+                ("REQ-3", LinkType::Tests),
+                ("REQ-4", LinkType::Derived),
+                ("REQ-5", LinkType::Derived)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_custom_syntax() {
+        let syntax = AnnotationSyntax::new(
+            strings(&["SRS-", "HLR-", "LLR-"]),
+            strings(&[r"^[A-Z]{3}\d{4}$"]),
+            strings(&["VERIFIES"]),
+            strings(&["DERIVED"]),
+        )
+        .unwrap();
+        assert_eq!(
+            syntax.find_annotations("LLR-4.1.2 refines HLR-12; VERIFIES: SRS-7 NAV0042 NAV00421"),
+            [
+                ("LLR-4.1.2", LinkType::Implements),
+                ("HLR-12", LinkType::Implements),
+                ("SRS-7", LinkType::Tests),
+                ("NAV0042", LinkType::Tests),
+            ]
+        );
+        assert_eq!(
+            syntax.find_annotations("REQ-1 TEST: SRS-1"),
+            [("SRS-1", LinkType::Implements)]
+        );
+    }
+
+    #[test]
+    fn test_invalid_syntax() {
+        let new = |prefixes: &[&str], patterns: &[&str], tests: &[&str]| {
+            AnnotationSyntax::new(
+                strings(prefixes),
+                strings(patterns),
+                strings(tests),
+                strings(&["DERIVED"]),
+            )
+        };
+        assert!(matches!(
+            new(&[], &[], &["TEST"]),
+            Err(AnnotationSyntaxError::NoIdSyntax)
+        ));
+        assert!(matches!(
+            new(&["REQ "], &[], &["TEST"]),
+            Err(AnnotationSyntaxError::InvalidPrefix(_))
+        ));
+        assert!(matches!(
+            new(&["REQ-", "REQ-NAV-"], &[], &["TEST"]),
+            Err(AnnotationSyntaxError::OverlappingPrefixes(..))
+        ));
+        assert!(matches!(
+            new(&[], &[r"[A-Z]+-\d+"], &["TEST"]),
+            Err(AnnotationSyntaxError::Unanchored(_))
+        ));
+        assert!(matches!(
+            new(&[], &["^(A$"], &["TEST"]),
+            Err(AnnotationSyntaxError::InvalidPattern { .. })
+        ));
+        assert!(matches!(
+            new(&[], &[r"^\d*$"], &["TEST"]),
+            Err(AnnotationSyntaxError::EmptyMatch(_))
+        ));
+        assert!(matches!(
+            new(&["REQ-"], &[], &["DERIVED"]),
+            Err(AnnotationSyntaxError::DuplicateMarker(_))
+        ));
+        assert!(matches!(
+            new(&[], &["^[A-Z]+$"], &["TEST"]),
+            Err(AnnotationSyntaxError::MarkerIsId(_))
+        ));
+        assert!(matches!(
+            new(&["REQ-"], &[], &["TEST:"]),
+            Err(AnnotationSyntaxError::InvalidMarker(_))
+        ));
+    }
+}
@@ -0,0 +1,562 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Compliance baselines.
+//!
+//! A baseline freezes the compliance state of a project under a label
+//! (`SOI-2`, `v1.3-rc1`): the traceability matrix, a coverage summary, the
+//! SHA-256 of every traced source and the pinned tool versions. Baselines
+//! are stored as JSON in `.axiom/baselines/<label>.json`, and
+//! [`diff_baselines`] reports what moved between two of them.
+
+use crate::{
+    generate_traceability_matrix, AnnotationSyntax, LinkType, TraceError, TraceLink,
+    TraceabilityMatrix, VerificationStatus, TRACE_EXTENSIONS,
+};
+use axiom_core::walk::find_files;
+use axiom_toolchain::{
+    sha256_file, CoverageDelta, CoverageRecord, CoverageReport, LockedToolchain, LockfileError,
+    ToolchainLock,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Baseline errors.
+#[derive(Debug, Error)]
+pub enum BaselineError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error(transparent)]
+    Trace(#[from] TraceError),
+
+    #[error(transparent)]
+    Lockfile(#[from] LockfileError),
+
+    #[error("Invalid baseline label: {0:?}")]
+    InvalidLabel(String),
+
+    #[error("Baseline not found: {0}")]
+    NotFound(String),
+
+    #[error("Baseline already exists: {0}")]
+    AlreadyExists(String),
+}
+
+/// The compliance state of a project at one point.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Baseline {
+    /// Label, unique within the project.
+    pub label: String,
+    /// When the baseline was taken (seconds since the Unix epoch).
+    pub timestamp: u64,
+    /// Traceability matrix, with any recorded test results.
+    pub matrix: TraceabilityMatrix,
+    /// Coverage summary, if coverage data was supplied.
+    #[serde(default)]
+    pub coverage: Option<CoverageRecord>,
+    /// SHA-256 of each traced source, by path relative to the project root.
+    #[serde(default)]
+    pub checksums: BTreeMap<PathBuf, String>,
+    /// Pinned toolchains.
+    #[serde(default)]
+    pub tools: Vec<LockedToolchain>,
+}
+
+impl Baseline {
+    /// Baseline of a matrix alone.
+    pub fn new(label: impl Into<String>, timestamp: u64, matrix: TraceabilityMatrix) -> Self {
+        Self {
+            label: label.into(),
+            timestamp,
+            matrix,
+            coverage: None,
+            checksums: BTreeMap::new(),
+            tools: Vec::new(),
+        }
+    }
+
+    /// Record a coverage report.
+    pub fn with_coverage(mut self, report: &CoverageReport) -> Self {
+        self.coverage = Some(CoverageRecord::from_report(
+            report,
+            self.timestamp,
+            None,
+            self.label.clone(),
+        ));
+        self
+    }
+
+    /// Record source checksums.
+    pub fn with_checksums(mut self, checksums: BTreeMap<PathBuf, String>) -> Self {
+        self.checksums = checksums;
+        self
+    }
+
+    /// Record pinned toolchains.
+    pub fn with_tools(mut self, tools: Vec<LockedToolchain>) -> Self {
+        self.tools = tools;
+        self
+    }
+
+    /// Capture a project's current state: its traceability matrix, source
+    /// checksums and toolchain lock, plus coverage if given.
+    pub fn capture(
+        project_root: &Path,
+        syntax: &AnnotationSyntax,
+        label: impl Into<String>,
+        timestamp: u64,
+        coverage: Option<&CoverageReport>,
+    ) -> Result<Self, BaselineError> {
+        let matrix = generate_traceability_matrix(project_root, syntax)?;
+        let mut checksums = BTreeMap::new();
+        for path in find_files(project_root, TRACE_EXTENSIONS)? {
+            let relative = path.strip_prefix(project_root).unwrap_or(&path);
+            checksums.insert(relative.to_path_buf(), sha256_file(&path)?);
+        }
+        let tools = ToolchainLock::load(project_root)?
+            .map(|lock| lock.toolchains)
+            .unwrap_or_default();
+
+        let mut baseline = Self::new(label, timestamp, matrix)
+            .with_checksums(checksums)
+            .with_tools(tools);
+        if let Some(report) = coverage {
+            baseline = baseline.with_coverage(report);
+        }
+        Ok(baseline)
+    }
+}
+
+/// Store of a project's baselines, one JSON file per label.
+#[derive(Debug, Clone)]
+pub struct BaselineStore {
+    dir: PathBuf,
+}
+
+impl BaselineStore {
+    /// Create a store backed by the given directory.
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Create the store for a project root.
+    pub fn for_project(project_root: &Path) -> Self {
+        Self::new(project_root.join(".axiom").join("baselines"))
+    }
+
+    fn path(&self, label: &str) -> Result<PathBuf, BaselineError> {
+        let valid = !label.is_empty()
+            && !label.starts_with('.')
+            && label
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+        if !valid {
+            return Err(BaselineError::InvalidLabel(label.to_string()));
+        }
+        Ok(self.dir.join(format!("{}.json", label)))
+    }
+
+    /// Save a baseline. Baselines are immutable, so an existing label is
+    /// an error.
+    pub fn save(&self, baseline: &Baseline) -> Result<(), BaselineError> {
+        let path = self.path(&baseline.label)?;
+        if path.exists() {
+            return Err(BaselineError::AlreadyExists(baseline.label.clone()));
+        }
+        std::fs::create_dir_all(&self.dir)?;
+        std::fs::write(&path, serde_json::to_string_pretty(baseline)?)?;
+        Ok(())
+    }
+
+    /// Load a baseline by label.
+    pub fn load(&self, label: &str) -> Result<Baseline, BaselineError> {
+        let path = self.path(label)?;
+        if !path.exists() {
+            return Err(BaselineError::NotFound(label.to_string()));
+        }
+        Ok(serde_json::from_str(&std::fs::read_to_string(&path)?)?)
+    }
+
+    /// Labels of the stored baselines, sorted.
+    pub fn labels(&self) -> Result<Vec<String>, BaselineError> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut labels = Vec::new();
+        for entry in std::fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|e| e == "json") {
+                if let Some(stem) = path.file_stem() {
+                    labels.push(stem.to_string_lossy().into_owned());
+                }
+            }
+        }
+        labels.sort();
+        Ok(labels)
+    }
+}
+
+/// How a requirement differs between two baselines.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RequirementChange {
+    /// Requirement ID.
+    pub requirement: String,
+    /// The database entry (text, status, attributes) changed.
+    pub definition_changed: bool,
+    /// Implementing or derived functions added or removed.
+    pub implementation_changed: bool,
+    /// Verifying tests added or removed.
+    pub tests_changed: bool,
+    /// Verification status, if it changed: (before, after).
+    pub verification: Option<(VerificationStatus, VerificationStatus)>,
+}
+
+/// A source whose checksum differs, or that exists in only one baseline.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "change", content = "path", rename_all = "kebab-case")]
+pub enum FileChange {
+    Added(PathBuf),
+    Removed(PathBuf),
+    Modified(PathBuf),
+}
+
+/// A tool whose pinned version differs between baselines.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolChange {
+    /// Driver path.
+    pub path: PathBuf,
+    /// Version in the older baseline, if pinned there.
+    pub from: Option<String>,
+    /// Version in the newer baseline, if pinned there.
+    pub to: Option<String>,
+}
+
+/// Differences between two baselines.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BaselineDiff {
+    /// Older baseline label.
+    pub from: String,
+    /// Newer baseline label.
+    pub to: String,
+    /// Requirements only in the newer baseline.
+    pub added_requirements: Vec<String>,
+    /// Requirements only in the older baseline.
+    pub removed_requirements: Vec<String>,
+    /// Requirements in both that changed.
+    pub changed_requirements: Vec<RequirementChange>,
+    /// Change in overall coverage, when both baselines have coverage.
+    pub coverage: Option<CoverageDelta>,
+    /// Change in coverage per source present in both.
+    pub file_coverage: BTreeMap<PathBuf, CoverageDelta>,
+    /// Sources added, removed or modified.
+    pub files: Vec<FileChange>,
+    /// Tools whose version changed.
+    pub tools: Vec<ToolChange>,
+}
+
+impl BaselineDiff {
+    /// Whether nothing changed.
+    pub fn is_empty(&self) -> bool {
+        self.added_requirements.is_empty()
+            && self.removed_requirements.is_empty()
+            && self.changed_requirements.is_empty()
+            && self.files.is_empty()
+            && self.tools.is_empty()
+            && self.coverage.is_none_or(|d| d == CoverageDelta::default())
+    }
+}
+
+/// Functions linked to a requirement, by file, function and link type;
+/// line numbers are left out so code moving within a file is not a change.
+fn link_keys(links: &[TraceLink]) -> BTreeSet<(&Path, Option<&str>, LinkType)> {
+    links
+        .iter()
+        .map(|l| (l.file.as_path(), l.function.as_deref(), l.link_type))
+        .collect()
+}
+
+/// Compare two baselines.
+pub fn diff_baselines(from: &Baseline, to: &Baseline) -> BaselineDiff {
+    let old_rows: BTreeMap<String, _> = from
+        .matrix
+        .rows()
+        .into_iter()
+        .map(|r| (r.requirement.clone(), r))
+        .collect();
+    let new_rows: BTreeMap<String, _> = to
+        .matrix
+        .rows()
+        .into_iter()
+        .map(|r| (r.requirement.clone(), r))
+        .collect();
+
+    let mut changed_requirements = Vec::new();
+    for (id, new) in &new_rows {
+        let Some(old) = old_rows.get(id) else {
+            continue;
+        };
+        let change = RequirementChange {
+            requirement: id.clone(),
+            definition_changed: old.definition != new.definition,
+            implementation_changed: link_keys(&old.implemented_by)
+                != link_keys(&new.implemented_by),
+            tests_changed: link_keys(&old.tested_by) != link_keys(&new.tested_by),
+            verification: (old.verification != new.verification)
+                .then_some((old.verification, new.verification)),
+        };
+        if change.definition_changed
+            || change.implementation_changed
+            || change.tests_changed
+            || change.verification.is_some()
+        {
+            changed_requirements.push(change);
+        }
+    }
+
+    let (coverage, file_coverage) = match (&from.coverage, &to.coverage) {
+        (Some(old), Some(new)) => {
+            let files = new
+                .files
+                .iter()
+                .filter_map(|file| {
+                    let previous = old.files.iter().find(|f| f.path == file.path)?;
+                    Some((
+                        file.path.clone(),
+                        CoverageDelta::between(&previous.summary, &file.summary),
+                    ))
+                })
+                .collect();
+            (
+                Some(CoverageDelta::between(&old.summary, &new.summary)),
+                files,
+            )
+        }
+        _ => (None, BTreeMap::new()),
+    };
+
+    let mut files = Vec::new();
+    for (path, hash) in &to.checksums {
+        match from.checksums.get(path) {
+            None => files.push(FileChange::Added(path.clone())),
+            Some(old) if old != hash => files.push(FileChange::Modified(path.clone())),
+            Some(_) => {}
+        }
+    }
+    files.extend(
+        from.checksums
+            .keys()
+            .filter(|path| !to.checksums.contains_key(*path))
+            .map(|path| FileChange::Removed(path.clone())),
+    );
+
+    let versions = |tools: &[LockedToolchain]| -> BTreeMap<PathBuf, String> {
+        tools
+            .iter()
+            .map(|t| (t.path.clone(), t.version.clone()))
+            .collect()
+    };
+    let (old_tools, new_tools) = (versions(&from.tools), versions(&to.tools));
+    let tools = old_tools
+        .keys()
+        .chain(new_tools.keys())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .filter(|path| old_tools.get(*path) != new_tools.get(*path))
+        .map(|path| ToolChange {
+            path: path.clone(),
+            from: old_tools.get(path).cloned(),
+            to: new_tools.get(path).cloned(),
+        })
+        .collect();
+
+    BaselineDiff {
+        from: from.label.clone(),
+        to: to.label.clone(),
+        added_requirements: new_rows
+            .keys()
+            .filter(|id| !old_rows.contains_key(*id))
+            .cloned()
+            .collect(),
+        removed_requirements: old_rows
+            .keys()
+            .filter(|id| !new_rows.contains_key(*id))
+            .cloned()
+            .collect(),
+        changed_requirements,
+        coverage,
+        file_coverage,
+        files,
+        tools,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Requirement, RequirementStatus};
+    use axiom_toolchain::ToolchainKind;
+    use tempfile::TempDir;
+
+    fn link(requirement: &str, function: &str, line: u32, link_type: LinkType) -> TraceLink {
+        TraceLink {
+            requirement: requirement.to_string(),
+            file: PathBuf::from("src/nav.c"),
+            line,
+            function: Some(function.to_string()),
+            link_type,
+        }
+    }
+
+    fn matrix(requirements: Vec<Requirement>, links: Vec<TraceLink>) -> TraceabilityMatrix {
+        TraceabilityMatrix {
+            requirements,
+            links,
+            ..Default::default()
+        }
+    }
+
+    fn tool(version: &str) -> LockedToolchain {
+        LockedToolchain {
+            kind: ToolchainKind::ArmGcc,
+            path: PathBuf::from("/opt/arm/bin/arm-none-eabi-gcc"),
+            version: version.to_string(),
+            binaries: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_diff_baselines() {
+        let old = Baseline::new(
+            "SOI-1",
+            100,
+            matrix(
+                vec![
+                    Requirement::new("REQ-1", "Update position"),
+                    Requirement::new("REQ-2", "Clamp heading"),
+                    Requirement::new("REQ-3", "Log faults"),
+                ],
+                vec![
+                    link("REQ-1", "nav_update", 10, LinkType::Implements),
+                    link("REQ-2", "nav_clamp", 20, LinkType::Implements),
+                ],
+            ),
+        )
+        .with_checksums(BTreeMap::from([
+            (PathBuf::from("src/nav.c"), "aa".to_string()),
+            (PathBuf::from("src/log.c"), "bb".to_string()),
+        ]))
+        .with_tools(vec![tool("12.3")]);
+
+        let mut clamp = Requirement::new("REQ-2", "Clamp heading to 0..360");
+        clamp.status = RequirementStatus::Approved;
+        let new = Baseline::new(
+            "SOI-2",
+            200,
+            matrix(
+                vec![
+                    Requirement::new("REQ-1", "Update position"),
+                    clamp,
+                    Requirement::new("REQ-4", "Report health"),
+                ],
+                vec![
+                    // Moved within the file: not a change
+                    link("REQ-1", "nav_update", 14, LinkType::Implements),
+                    link("REQ-2", "nav_clamp", 24, LinkType::Implements),
+                    link("REQ-2", "test_nav_clamp", 5, LinkType::Tests),
+                ],
+            ),
+        )
+        .with_checksums(BTreeMap::from([
+            (PathBuf::from("src/nav.c"), "ac".to_string()),
+            (PathBuf::from("src/health.c"), "cc".to_string()),
+        ]))
+        .with_tools(vec![tool("13.2")]);
+
+        let diff = diff_baselines(&old, &new);
+        assert_eq!(diff.added_requirements, ["REQ-4"]);
+        assert_eq!(diff.removed_requirements, ["REQ-3"]);
+        assert_eq!(diff.changed_requirements.len(), 1);
+        let change = &diff.changed_requirements[0];
+        assert_eq!(change.requirement, "REQ-2");
+        assert!(change.definition_changed);
+        assert!(!change.implementation_changed);
+        assert!(change.tests_changed);
+        assert_eq!(
+            change.verification,
+            Some((VerificationStatus::Untested, VerificationStatus::NotRun))
+        );
+        assert_eq!(
+            diff.files,
+            [
+                FileChange::Added(PathBuf::from("src/health.c")),
+                FileChange::Modified(PathBuf::from("src/nav.c")),
+                FileChange::Removed(PathBuf::from("src/log.c")),
+            ]
+        );
+        assert_eq!(diff.tools.len(), 1);
+        assert_eq!(diff.tools[0].from.as_deref(), Some("12.3"));
+        assert_eq!(diff.tools[0].to.as_deref(), Some("13.2"));
+        assert!(diff.coverage.is_none());
+        assert!(!diff.is_empty());
+        assert!(diff_baselines(&new, &new).is_empty());
+    }
+
+    #[test]
+    fn test_coverage_delta() {
+        let report = |covered: usize| {
+            CoverageReport::from_files(axiom_toolchain::parse_lcov(&format!(
+                "SF:src/nav.c\n{}end_of_record\n",
+                (1..=4)
+                    .map(|l| format!("DA:{},{}\n", l, (l <= covered) as u32))
+                    .collect::<String>()
+            )))
+        };
+        let old = Baseline::new("a", 1, TraceabilityMatrix::default()).with_coverage(&report(2));
+        let new = Baseline::new("b", 2, TraceabilityMatrix::default()).with_coverage(&report(3));
+        let diff = diff_baselines(&old, &new);
+        assert_eq!(diff.coverage.unwrap().lines, 25.0);
+        assert_eq!(diff.file_coverage[Path::new("src/nav.c")].lines, 25.0);
+    }
+
+    #[test]
+    fn test_capture_and_store() {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("src")).unwrap();
+        std::fs::write(
+            dir.path().join("src/nav.c"),
+            "/* Implements REQ-1 */\nvoid nav_update(void) {}\n",
+        )
+        .unwrap();
+
+        let baseline =
+            Baseline::capture(dir.path(), &AnnotationSyntax::default(), "SOI-1", 100, None)
+                .unwrap();
+        assert_eq!(baseline.matrix.links.len(), 1);
+        assert_eq!(baseline.checksums.len(), 1);
+        assert_eq!(baseline.checksums[Path::new("src/nav.c")].len(), 64);
+        assert!(baseline.tools.is_empty());
+
+        let store = BaselineStore::for_project(dir.path());
+        store.save(&baseline).unwrap();
+        assert!(matches!(
+            store.save(&baseline),
+            Err(BaselineError::AlreadyExists(_))
+        ));
+        assert_eq!(store.load("SOI-1").unwrap(), baseline);
+        assert_eq!(store.labels().unwrap(), ["SOI-1"]);
+        assert!(matches!(
+            store.load("SOI-9"),
+            Err(BaselineError::NotFound(_))
+        ));
+        assert!(matches!(
+            store.load("../SOI-1"),
+            Err(BaselineError::InvalidLabel(_))
+        ));
+    }
+}
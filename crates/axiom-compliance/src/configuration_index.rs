@@ -0,0 +1,368 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Software Configuration Index (SCI).
+//!
+//! Identifies exactly what a build was made from: every source file with
+//! its SHA-256 and the last commit that changed it, the repository
+//! revision, the pinned toolchains and tool usage, and the compile and
+//! link options of the build profile. Everything is read from the working
+//! tree, git, `toolchain.lock`, the tool usage log and the project's build
+//! profiles, so the index can be regenerated for every release.
+
+use crate::qualification_docs::{render_document, Section};
+use crate::DocumentFormat;
+use axiom_core::time::format_timestamp;
+use axiom_core::walk::find_files;
+use axiom_git::{get_status, GitError, Repository};
+use axiom_toolchain::{
+    find_build_profile, sha256_file, tool_usage_statistics, BuildProfile, LockedToolchain,
+    LockfileError, ProfileError, ToolLogError, ToolQualificationLogger, ToolUsageStats,
+    ToolchainLock,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Extensions of the files under configuration control: C and C++
+/// sources and headers, assembly, linker scripts and make fragments.
+pub const CONFIGURATION_EXTENSIONS: &[&str] = &[
+    "c", "h", "cc", "cpp", "cxx", "hh", "hpp", "hxx", "s", "ld", "mk",
+];
+
+/// Configuration index errors.
+#[derive(Debug, Error)]
+pub enum ConfigurationIndexError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Git(#[from] GitError),
+
+    #[error(transparent)]
+    Lockfile(#[from] LockfileError),
+
+    #[error(transparent)]
+    ToolLog(#[from] ToolLogError),
+
+    #[error(transparent)]
+    Profile(#[from] ProfileError),
+}
+
+/// One file under configuration control.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConfigurationItem {
+    /// Path relative to the project root.
+    pub path: PathBuf,
+    /// SHA-256 of the contents.
+    pub sha256: String,
+    /// Last commit that changed the file; `None` if never committed.
+    pub commit: Option<String>,
+    /// The working copy differs from the committed file.
+    pub modified: bool,
+}
+
+/// Revision of the repository the index was generated from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RepositoryState {
+    /// HEAD commit; `None` before the first commit.
+    pub commit: Option<String>,
+    /// Checked-out branch.
+    pub branch: Option<String>,
+    /// The working tree has uncommitted changes.
+    pub dirty: bool,
+}
+
+/// A Software Configuration Index.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConfigurationIndex {
+    /// Project name.
+    pub project: String,
+    /// Generation time (seconds since the Unix epoch).
+    pub timestamp: u64,
+    /// Repository revision; `None` outside a git repository.
+    pub repository: Option<RepositoryState>,
+    /// Build profile the options come from.
+    pub profile: BuildProfile,
+    /// Compiler options of the profile.
+    pub compile_flags: Vec<String>,
+    /// Linker options of the profile.
+    pub link_flags: Vec<String>,
+    /// Pinned toolchains.
+    pub toolchains: Vec<LockedToolchain>,
+    /// Tools the project has run, from the tool usage log.
+    pub tool_usage: Vec<ToolUsageStats>,
+    /// Files under configuration control, by path.
+    pub items: Vec<ConfigurationItem>,
+}
+
+impl ConfigurationIndex {
+    /// Whether every item is committed and unmodified, as a release build
+    /// requires.
+    pub fn is_clean(&self) -> bool {
+        self.repository.as_ref().is_some_and(|r| !r.dirty)
+            && self
+                .items
+                .iter()
+                .all(|item| item.commit.is_some() && !item.modified)
+    }
+}
+
+/// Generate the configuration index of a project built with the named
+/// build profile (normally `release`).
+pub fn generate_configuration_index(
+    project_root: &Path,
+    profile: &str,
+    timestamp: u64,
+) -> Result<ConfigurationIndex, ConfigurationIndexError> {
+    let project = project_root
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let profile = find_build_profile(Some(project_root), profile)?;
+
+    let mut items = Vec::new();
+    for path in find_files(project_root, CONFIGURATION_EXTENSIONS)? {
+        let relative = path.strip_prefix(project_root).unwrap_or(&path);
+        items.push(ConfigurationItem {
+            path: relative.to_path_buf(),
+            sha256: sha256_file(&path)?,
+            commit: None,
+            modified: false,
+        });
+    }
+
+    let repository = match Repository::discover(project_root) {
+        Ok(repo) => {
+            // Git paths are relative to the repository; items to the project
+            let prefix = project_root
+                .canonicalize()
+                .ok()
+                .zip(repo.path().canonicalize().ok())
+                .and_then(|(root, workdir)| root.strip_prefix(workdir).ok().map(Path::to_path_buf))
+                .unwrap_or_default();
+            let paths: Vec<PathBuf> = items.iter().map(|i| prefix.join(&i.path)).collect();
+            let commits = repo.last_commits_for(&paths)?;
+            let status = get_status(&repo)?;
+            let changed: HashSet<&PathBuf> = status
+                .staged
+                .iter()
+                .chain(&status.modified)
+                .chain(&status.untracked)
+                .map(|entry| &entry.path)
+                .collect();
+            for (item, path) in items.iter_mut().zip(&paths) {
+                item.commit = commits.get(path).cloned();
+                item.modified = changed.contains(path);
+            }
+            Some(RepositoryState {
+                commit: repo.last_commit()?.map(|c| c.id),
+                branch: repo.current_branch()?,
+                dirty: status.has_changes(),
+            })
+        }
+        Err(_) => None,
+    };
+
+    let records = ToolQualificationLogger::for_project(project_root).load()?;
+    Ok(ConfigurationIndex {
+        project,
+        timestamp,
+        repository,
+        compile_flags: profile.compile_flags(),
+        link_flags: profile.ldflags.clone(),
+        profile,
+        toolchains: ToolchainLock::load(project_root)?
+            .map(|lock| lock.toolchains)
+            .unwrap_or_default(),
+        tool_usage: tool_usage_statistics(&records),
+        items,
+    })
+}
+
+/// Render a configuration index as a document.
+pub fn render_configuration_index(index: &ConfigurationIndex, format: DocumentFormat) -> String {
+    let none = || "-".to_string();
+
+    let mut repository = Section::new("Software identification");
+    repository = match index.repository {
+        Some(ref repo) => repository.table(
+            vec!["Item", "Value"],
+            vec![
+                vec![
+                    "Commit".to_string(),
+                    repo.commit.clone().unwrap_or_else(none),
+                ],
+                vec![
+                    "Branch".to_string(),
+                    repo.branch.clone().unwrap_or_else(none),
+                ],
+                vec![
+                    "Uncommitted changes".to_string(),
+                    if repo.dirty { "Yes" } else { "No" }.to_string(),
+                ],
+            ],
+        ),
+        None => repository.paragraph("The project is not under git version control."),
+    };
+    if !index.is_clean() {
+        repository = repository.paragraph(
+            "Warning: some files are uncommitted or modified; this index does not identify a reproducible build.",
+        );
+    }
+
+    let build = Section::new("Build options")
+        .paragraph(format!("Build profile: {}", index.profile.name))
+        .table(
+            vec!["Stage", "Options"],
+            vec![
+                vec!["Compile".to_string(), index.compile_flags.join(" ")],
+                vec!["Link".to_string(), index.link_flags.join(" ")],
+            ],
+        );
+
+    let toolchains = Section::new("Toolchain identification").table_or(
+        vec!["Toolchain", "Version", "Binary", "SHA-256"],
+        index
+            .toolchains
+            .iter()
+            .flat_map(|tc| {
+                tc.binaries.iter().map(move |(binary, sha256)| {
+                    vec![
+                        tc.path.display().to_string(),
+                        tc.version.clone(),
+                        binary.clone(),
+                        sha256.clone(),
+                    ]
+                })
+            })
+            .collect(),
+        "No toolchain is pinned in toolchain.lock.",
+    );
+
+    let usage = Section::new("Tool usage").table_or(
+        vec!["Tool", "Versions", "Invocations", "Last used"],
+        index
+            .tool_usage
+            .iter()
+            .map(|stats| {
+                vec![
+                    stats.tool.clone(),
+                    stats
+                        .versions
+                        .iter()
+                        .cloned()
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    stats.invocations.to_string(),
+                    format_timestamp(stats.last_used),
+                ]
+            })
+            .collect(),
+        "No tool usage has been logged.",
+    );
+
+    let items = Section::new("Source files").table_or(
+        vec!["File", "SHA-256", "Commit", "Modified"],
+        index
+            .items
+            .iter()
+            .map(|item| {
+                vec![
+                    item.path.display().to_string(),
+                    item.sha256.clone(),
+                    item.commit.clone().unwrap_or_else(none),
+                    if item.modified { "Yes" } else { "No" }.to_string(),
+                ]
+            })
+            .collect(),
+        "No source files.",
+    );
+
+    let title = format!("Software Configuration Index: {}", index.project);
+    let generated = format!(
+        "Generated {} by Axiom {}",
+        format_timestamp(index.timestamp),
+        env!("CARGO_PKG_VERSION")
+    );
+    render_document(
+        format,
+        &title,
+        &generated,
+        &[repository, build, toolchains, usage, items],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn project() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("src")).unwrap();
+        std::fs::write(
+            dir.path().join("src/nav.c"),
+            "int nav(void) { return 0; }\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("src/startup.s"), "b reset\n").unwrap();
+        std::fs::write(dir.path().join("README"), "nav\n").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_index_without_git() {
+        let dir = project();
+        let index = generate_configuration_index(dir.path(), "release", 0).unwrap();
+        let paths: Vec<&Path> = index.items.iter().map(|i| i.path.as_path()).collect();
+        assert_eq!(paths, [Path::new("src/nav.c"), Path::new("src/startup.s")]);
+        assert_eq!(index.items[0].sha256.len(), 64);
+        assert!(index.repository.is_none());
+        assert!(!index.is_clean());
+        assert!(index.compile_flags.contains(&"-DNDEBUG".to_string()));
+
+        let markdown = render_configuration_index(&index, DocumentFormat::Markdown);
+        assert!(markdown.starts_with("# Software Configuration Index:"));
+        assert!(markdown.contains("not under git version control"));
+        assert!(markdown.contains("| src/nav.c |"));
+    }
+
+    #[test]
+    fn test_index_with_git() {
+        let dir = project();
+        let root = dir.path();
+        let git = git2::Repository::init(root).unwrap();
+        let mut config = git.config().unwrap();
+        config.set_str("user.name", "Test").unwrap();
+        config.set_str("user.email", "test@test.com").unwrap();
+        let mut index = git.index().unwrap();
+        index
+            .add_all(["*"], git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        index.write().unwrap();
+        let tree = git.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = git.signature().unwrap();
+        let commit = git
+            .commit(Some("HEAD"), &sig, &sig, "v1", &tree, &[])
+            .unwrap()
+            .to_string();
+
+        let index = generate_configuration_index(root, "release", 0).unwrap();
+        assert_eq!(
+            index.repository.as_ref().unwrap().commit,
+            Some(commit.clone())
+        );
+        assert!(index.items.iter().all(|i| i.commit == Some(commit.clone())));
+        assert!(index.is_clean());
+
+        std::fs::write(root.join("src/nav.c"), "int nav(void) { return 1; }\n").unwrap();
+        let index = generate_configuration_index(root, "release", 0).unwrap();
+        assert!(index.items[0].modified);
+        assert!(!index.items[1].modified);
+        assert!(!index.is_clean());
+        let html = render_configuration_index(&index, DocumentFormat::Html);
+        assert!(html.contains("does not identify a reproducible build"));
+    }
+}
@@ -0,0 +1,525 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Coding-standard deviation records.
+//!
+//! A deviation records that a MISRA C:2012 rule is knowingly violated at a
+//! place in the code, why that is acceptable, and who approved it. Records
+//! live in `.axiom/deviations.json`. Findings from an external MISRA
+//! checker and the suppression comments in the sources are correlated with
+//! the approved records: a suppression with no approved record behind it
+//! hides a violation nobody signed off on.
+
+use axiom_analysis::{
+    find_misra_suppressions, parse_cppcheck_misra, parse_pclint_misra, CppcheckError, MisraFinding,
+    MisraSuppression,
+};
+use axiom_core::walk::find_files;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Deviations file format version.
+pub const DEVIATIONS_VERSION: u32 = 1;
+
+/// Deviation record errors.
+#[derive(Debug, Error)]
+pub enum DeviationError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("JSON error in {path}: {source}")]
+    Json {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+
+    #[error("Deviations file version {0} is newer than supported")]
+    UnsupportedVersion(u32),
+
+    #[error("Deviation already exists: {0}")]
+    Duplicate(String),
+
+    #[error("Deviation not found: {0}")]
+    NotFound(String),
+
+    #[error("Deviation {0} has no justification")]
+    MissingJustification(String),
+
+    #[error(transparent)]
+    Cppcheck(#[from] CppcheckError),
+}
+
+/// Review state of a deviation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DeviationStatus {
+    /// Awaiting review.
+    #[default]
+    Proposed,
+    /// Approved; the violation is accepted.
+    Approved,
+    /// Rejected; the violation must be fixed.
+    Rejected,
+}
+
+/// Sign-off of a deviation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Approval {
+    /// Who approved it.
+    pub approver: String,
+    /// When (seconds since the Unix epoch).
+    pub timestamp: u64,
+}
+
+/// A deviation from a coding-standard rule.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviationRecord {
+    /// Record ID (e.g. "DEV-0001").
+    pub id: String,
+    /// Rule deviated from (e.g. "10.4", "Dir 4.9").
+    pub rule: String,
+    /// File the deviation applies to, relative to the project root; `None`
+    /// for the whole project.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+    /// Line (1-based) the deviation applies to; `None` for the whole file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<u32>,
+    /// Why the violation is acceptable.
+    pub justification: String,
+    /// Checker message IDs the deviation also covers, for checkers whose
+    /// IDs do not carry the rule number (e.g. PC-lint "9034").
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub checks: Vec<String>,
+    /// Review state.
+    #[serde(default)]
+    pub status: DeviationStatus,
+    /// Sign-off, once approved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub approval: Option<Approval>,
+    /// When the record was raised (seconds since the Unix epoch).
+    pub created: u64,
+}
+
+impl DeviationRecord {
+    /// Create a proposed project-wide deviation.
+    pub fn new(
+        id: impl Into<String>,
+        rule: impl Into<String>,
+        justification: impl Into<String>,
+        timestamp: u64,
+    ) -> Self {
+        Self {
+            id: id.into(),
+            rule: rule.into(),
+            file: None,
+            line: None,
+            justification: justification.into(),
+            checks: Vec::new(),
+            status: DeviationStatus::Proposed,
+            approval: None,
+            created: timestamp,
+        }
+    }
+
+    /// Limit the deviation to a file, or a line of it.
+    pub fn with_location(mut self, file: impl Into<PathBuf>, line: Option<u32>) -> Self {
+        self.file = Some(file.into());
+        self.line = line;
+        self
+    }
+
+    /// Add a checker message ID the deviation covers.
+    pub fn with_check(mut self, check: impl Into<String>) -> Self {
+        self.checks.push(check.into());
+        self
+    }
+
+    /// Approve the deviation.
+    pub fn approve(mut self, approver: impl Into<String>, timestamp: u64) -> Self {
+        self.status = DeviationStatus::Approved;
+        self.approval = Some(Approval {
+            approver: approver.into(),
+            timestamp,
+        });
+        self
+    }
+
+    /// Whether the deviation is approved.
+    pub fn is_approved(&self) -> bool {
+        self.status == DeviationStatus::Approved && self.approval.is_some()
+    }
+
+    /// Whether the deviation applies at a location. A `None` line is a
+    /// whole-file suppression, which only a whole-file deviation covers.
+    pub(crate) fn applies_at(&self, file: &Path, line: Option<u32>) -> bool {
+        self.file.as_deref().is_none_or(|f| f == file) && self.line.is_none_or(|l| line == Some(l))
+    }
+
+    /// Whether the deviation covers a checker finding.
+    pub fn covers_finding(&self, finding: &MisraFinding) -> bool {
+        let named = self.rule == finding.rule || self.checks.contains(&finding.check);
+        named
+            && match finding.location {
+                Some(ref location) => self.applies_at(&location.path, finding.line()),
+                None => self.file.is_none(),
+            }
+    }
+
+    /// Whether the deviation covers a suppression comment.
+    pub fn covers_suppression(&self, suppression: &MisraSuppression) -> bool {
+        let named = suppression.rules().contains(&self.rule)
+            || suppression.checks.iter().any(|c| self.checks.contains(c));
+        named && self.applies_at(&suppression.location.path, suppression.line)
+    }
+
+    fn check(&self) -> Result<(), DeviationError> {
+        if self.justification.trim().is_empty() {
+            return Err(DeviationError::MissingJustification(self.id.clone()));
+        }
+        Ok(())
+    }
+}
+
+/// On-disk form of the deviations file.
+#[derive(Serialize, Deserialize)]
+struct DeviationsFile {
+    version: u32,
+    #[serde(default)]
+    deviations: Vec<DeviationRecord>,
+}
+
+/// File-backed deviation record database.
+#[derive(Debug, Clone)]
+pub struct DeviationStore {
+    path: PathBuf,
+}
+
+impl DeviationStore {
+    /// Create a store backed by the given file.
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Create the store for a project root.
+    pub fn for_project(project_root: &Path) -> Self {
+        Self::new(project_root.join(".axiom").join("deviations.json"))
+    }
+
+    /// Path of the backing file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Load all records, by ID; a missing file yields none.
+    pub fn load(&self) -> Result<Vec<DeviationRecord>, DeviationError> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = fs::read_to_string(&self.path)?;
+        let file: DeviationsFile =
+            serde_json::from_str(&content).map_err(|source| DeviationError::Json {
+                path: self.path.clone(),
+                source,
+            })?;
+        if file.version > DEVIATIONS_VERSION {
+            return Err(DeviationError::UnsupportedVersion(file.version));
+        }
+        Ok(file.deviations)
+    }
+
+    fn save(&self, mut deviations: Vec<DeviationRecord>) -> Result<(), DeviationError> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        deviations.sort_by(|a, b| a.id.cmp(&b.id));
+        let file = DeviationsFile {
+            version: DEVIATIONS_VERSION,
+            deviations,
+        };
+        let json = serde_json::to_string_pretty(&file).map_err(|source| DeviationError::Json {
+            path: self.path.clone(),
+            source,
+        })?;
+        fs::write(&self.path, json + "\n")?;
+        Ok(())
+    }
+
+    /// Look up a record.
+    pub fn get(&self, id: &str) -> Result<DeviationRecord, DeviationError> {
+        self.load()?
+            .into_iter()
+            .find(|d| d.id == id)
+            .ok_or_else(|| DeviationError::NotFound(id.to_string()))
+    }
+
+    /// The next free sequential ID (`DEV-0001`, `DEV-0002`, ...).
+    pub fn next_id(&self) -> Result<String, DeviationError> {
+        let last = self
+            .load()?
+            .iter()
+            .filter_map(|d| d.id.strip_prefix("DEV-")?.parse::<u32>().ok())
+            .max()
+            .unwrap_or(0);
+        Ok(format!("DEV-{:04}", last + 1))
+    }
+
+    /// Add a record.
+    pub fn create(&self, deviation: DeviationRecord) -> Result<(), DeviationError> {
+        deviation.check()?;
+        let mut deviations = self.load()?;
+        if deviations.iter().any(|d| d.id == deviation.id) {
+            return Err(DeviationError::Duplicate(deviation.id));
+        }
+        deviations.push(deviation);
+        self.save(deviations)
+    }
+
+    /// Replace a record, returning the previous version.
+    pub fn update(&self, deviation: DeviationRecord) -> Result<DeviationRecord, DeviationError> {
+        deviation.check()?;
+        let mut deviations = self.load()?;
+        let existing = deviations
+            .iter_mut()
+            .find(|d| d.id == deviation.id)
+            .ok_or_else(|| DeviationError::NotFound(deviation.id.clone()))?;
+        let previous = std::mem::replace(existing, deviation);
+        self.save(deviations)?;
+        Ok(previous)
+    }
+}
+
+/// Read MISRA findings from a checker report: cppcheck XML if the file is
+/// XML, PC-lint text output otherwise.
+pub fn read_misra_findings(path: &Path) -> Result<Vec<MisraFinding>, DeviationError> {
+    let content = fs::read_to_string(path)?;
+    if content.trim_start().starts_with('<') {
+        Ok(parse_cppcheck_misra(&content)?)
+    } else {
+        Ok(parse_pclint_misra(&content))
+    }
+}
+
+/// A checker finding and the approved deviation covering it, if any.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CorrelatedFinding {
+    pub finding: MisraFinding,
+    pub deviation: Option<String>,
+}
+
+/// A suppression comment and the approved deviation behind it, if any.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CorrelatedSuppression {
+    pub suppression: MisraSuppression,
+    pub deviation: Option<String>,
+}
+
+/// Checker findings and suppressions matched against deviation records.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviationCorrelation {
+    /// Every finding, with its deviation.
+    pub findings: Vec<CorrelatedFinding>,
+    /// Every suppression comment, with its deviation.
+    pub suppressions: Vec<CorrelatedSuppression>,
+    /// Approved deviations no finding or suppression matched.
+    pub unused: Vec<String>,
+    /// Deviations still awaiting review.
+    pub pending: Vec<String>,
+}
+
+impl DeviationCorrelation {
+    /// Findings no approved deviation covers.
+    pub fn undeviated_findings(&self) -> Vec<&MisraFinding> {
+        self.findings
+            .iter()
+            .filter(|f| f.deviation.is_none())
+            .map(|f| &f.finding)
+            .collect()
+    }
+
+    /// Suppressions no approved deviation justifies.
+    pub fn unapproved_suppressions(&self) -> Vec<&MisraSuppression> {
+        self.suppressions
+            .iter()
+            .filter(|s| s.deviation.is_none())
+            .map(|s| &s.suppression)
+            .collect()
+    }
+}
+
+/// Match findings and suppressions with the approved deviations covering
+/// them.
+pub fn correlate_deviations(
+    deviations: &[DeviationRecord],
+    findings: &[MisraFinding],
+    suppressions: &[MisraSuppression],
+) -> DeviationCorrelation {
+    let approved: Vec<&DeviationRecord> = deviations.iter().filter(|d| d.is_approved()).collect();
+    let mut used = BTreeSet::new();
+
+    let findings = findings
+        .iter()
+        .map(|finding| {
+            let deviation = approved
+                .iter()
+                .find(|d| d.covers_finding(finding))
+                .map(|d| d.id.clone());
+            used.extend(deviation.clone());
+            CorrelatedFinding {
+                finding: finding.clone(),
+                deviation,
+            }
+        })
+        .collect();
+    let suppressions = suppressions
+        .iter()
+        .map(|suppression| {
+            let deviation = approved
+                .iter()
+                .find(|d| d.covers_suppression(suppression))
+                .map(|d| d.id.clone());
+            used.extend(deviation.clone());
+            CorrelatedSuppression {
+                suppression: suppression.clone(),
+                deviation,
+            }
+        })
+        .collect();
+
+    DeviationCorrelation {
+        findings,
+        suppressions,
+        unused: approved
+            .iter()
+            .filter(|d| !used.contains(&d.id))
+            .map(|d| d.id.clone())
+            .collect(),
+        pending: deviations
+            .iter()
+            .filter(|d| d.status == DeviationStatus::Proposed)
+            .map(|d| d.id.clone())
+            .collect(),
+    }
+}
+
+/// MISRA suppression comments in a project's sources, with paths relative
+/// to the project root.
+pub fn find_project_misra_suppressions(
+    project_root: &Path,
+) -> Result<Vec<MisraSuppression>, DeviationError> {
+    let mut suppressions = Vec::new();
+    for path in find_files(project_root, crate::TRACE_EXTENSIONS)? {
+        let source = fs::read_to_string(&path)?;
+        let relative = path.strip_prefix(project_root).unwrap_or(&path);
+        suppressions.extend(find_misra_suppressions(relative, &source));
+    }
+    Ok(suppressions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const NAV: &str = "\
+int nav(int x)
+{
+    // cppcheck-suppress misra-c2012-10.4
+    return x + 1u;
+}
+
+int clamp(int x)
+{
+    return (x > 360) ? 360 : x; //lint !e9034
+}
+
+int wrap(int x)
+{
+    // cppcheck-suppress misra-c2012-12.1
+    return x % 360 + 1;
+}
+";
+
+    #[test]
+    fn test_store() {
+        let dir = TempDir::new().unwrap();
+        let store = DeviationStore::for_project(dir.path());
+        assert_eq!(store.next_id().unwrap(), "DEV-0001");
+        assert!(matches!(
+            store.create(DeviationRecord::new("DEV-0001", "10.4", " ", 0)),
+            Err(DeviationError::MissingJustification(_))
+        ));
+        store
+            .create(DeviationRecord::new(
+                "DEV-0001",
+                "10.4",
+                "Unsigned literal",
+                0,
+            ))
+            .unwrap();
+        assert!(matches!(
+            store.create(DeviationRecord::new("DEV-0001", "10.4", "Again", 0)),
+            Err(DeviationError::Duplicate(_))
+        ));
+        let approved = store.get("DEV-0001").unwrap().approve("J. Reviewer", 5);
+        store.update(approved).unwrap();
+        assert!(store.get("DEV-0001").unwrap().is_approved());
+        assert_eq!(store.next_id().unwrap(), "DEV-0002");
+    }
+
+    #[test]
+    fn test_correlate() {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/nav.c"), NAV).unwrap();
+        let suppressions = find_project_misra_suppressions(dir.path()).unwrap();
+        assert_eq!(suppressions.len(), 3);
+
+        let report = dir.path().join("pclint.txt");
+        std::fs::write(
+            &report,
+            "src/nav.c(9): Note 9034: narrower type [MISRA 2012 Rule 10.3, required]\n\
+             src/nav.c(20): Note 9050: precedence [MISRA 2012 Rule 12.1, advisory]\n",
+        )
+        .unwrap();
+        let findings = read_misra_findings(&report).unwrap();
+        assert_eq!(findings.len(), 2);
+
+        let deviations = vec![
+            DeviationRecord::new("DEV-0001", "10.4", "Unsigned literal", 0)
+                .with_location("src/nav.c", Some(4))
+                .approve("J. Reviewer", 1),
+            DeviationRecord::new("DEV-0002", "10.3", "Clamped range", 0)
+                .with_location("src/nav.c", None)
+                .with_check("9034")
+                .approve("J. Reviewer", 1),
+            DeviationRecord::new("DEV-0003", "12.1", "Precedence is clear", 0),
+            DeviationRecord::new("DEV-0004", "17.7", "Return ignored", 0).approve("J. Reviewer", 1),
+        ];
+        let correlation = correlate_deviations(&deviations, &findings, &suppressions);
+
+        assert_eq!(
+            correlation.findings[0].deviation.as_deref(),
+            Some("DEV-0002")
+        );
+        assert_eq!(correlation.undeviated_findings().len(), 1);
+        assert_eq!(correlation.undeviated_findings()[0].rule, "12.1");
+        assert_eq!(
+            correlation.suppressions[0].deviation.as_deref(),
+            Some("DEV-0001")
+        );
+        assert_eq!(
+            correlation.suppressions[1].deviation.as_deref(),
+            Some("DEV-0002")
+        );
+        // Proposed deviations do not justify a suppression
+        let unapproved = correlation.unapproved_suppressions();
+        assert_eq!(unapproved.len(), 1);
+        assert_eq!(unapproved[0].checks, ["misra-c2012-12.1"]);
+        assert_eq!(correlation.unused, ["DEV-0004"]);
+        assert_eq!(correlation.pending, ["DEV-0003"]);
+    }
+}
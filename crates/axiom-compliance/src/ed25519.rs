@@ -0,0 +1,594 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Ed25519 signatures (RFC 8032).
+//!
+//! A small self-contained implementation: field elements are five 51-bit
+//! limbs, points use extended twisted Edwards coordinates and scalars are
+//! reduced modulo the group order by shift-and-subtract. Scalar
+//! multiplication always performs the addition and selects the result, so
+//! the secret scalar does not choose which operations run.
+
+use sha2::{Digest, Sha512};
+
+const MASK: u64 = (1 << 51) - 1;
+
+/// Element of GF(2^255 - 19).
+#[derive(Clone, Copy)]
+struct Fe([u64; 5]);
+
+impl Fe {
+    const ZERO: Fe = Fe([0; 5]);
+    const ONE: Fe = Fe([1, 0, 0, 0, 0]);
+
+    fn from_u64(n: u64) -> Fe {
+        Fe([n & MASK, n >> 51, 0, 0, 0])
+    }
+
+    /// Little-endian decoding; the top bit is ignored.
+    fn from_bytes(bytes: &[u8; 32]) -> Fe {
+        let word = |i: usize| u64::from_le_bytes(bytes[i * 8..i * 8 + 8].try_into().unwrap());
+        let (w0, w1, w2, w3) = (word(0), word(1), word(2), word(3));
+        Fe([
+            w0 & MASK,
+            ((w0 >> 51) | (w1 << 13)) & MASK,
+            ((w1 >> 38) | (w2 << 26)) & MASK,
+            ((w2 >> 25) | (w3 << 39)) & MASK,
+            (w3 >> 12) & MASK,
+        ])
+    }
+
+    /// Canonical little-endian encoding.
+    fn to_bytes(self) -> [u8; 32] {
+        let mut h = Fe::carry(Fe::carry(self.0).0).0;
+        // h < 2^255 + small; subtract p once if h >= p
+        let mut q = (h[0] + 19) >> 51;
+        for limb in &h[1..] {
+            q = (limb + q) >> 51;
+        }
+        h[0] += 19 * q;
+        for i in 0..4 {
+            h[i + 1] += h[i] >> 51;
+            h[i] &= MASK;
+        }
+        h[4] &= MASK;
+
+        let mut bytes = [0u8; 32];
+        let mut acc: u128 = 0;
+        let mut bits = 0;
+        let mut index = 0;
+        for limb in h {
+            acc |= (limb as u128) << bits;
+            bits += 51;
+            while bits >= 8 {
+                bytes[index] = acc as u8;
+                acc >>= 8;
+                bits -= 8;
+                index += 1;
+            }
+        }
+        bytes[index] = acc as u8;
+        bytes
+    }
+
+    fn carry(mut l: [u64; 5]) -> Fe {
+        for i in 0..4 {
+            l[i + 1] += l[i] >> 51;
+            l[i] &= MASK;
+        }
+        l[0] += 19 * (l[4] >> 51);
+        l[4] &= MASK;
+        Fe(l)
+    }
+
+    fn add(self, rhs: Fe) -> Fe {
+        let mut l = self.0;
+        for (a, b) in l.iter_mut().zip(rhs.0) {
+            *a += b;
+        }
+        Fe::carry(l)
+    }
+
+    fn sub(self, rhs: Fe) -> Fe {
+        // Add 2p so limbs stay positive
+        let two_p = [(MASK - 18) * 2, MASK * 2, MASK * 2, MASK * 2, MASK * 2];
+        let mut l = self.0;
+        for i in 0..5 {
+            l[i] = l[i] + two_p[i] - rhs.0[i];
+        }
+        Fe::carry(l)
+    }
+
+    fn neg(self) -> Fe {
+        Fe::ZERO.sub(self)
+    }
+
+    fn mul(self, rhs: Fe) -> Fe {
+        let a = self.0;
+        let b = rhs.0;
+        let m = |x: u64, y: u64| x as u128 * y as u128;
+        let b19 = [b[0], b[1] * 19, b[2] * 19, b[3] * 19, b[4] * 19];
+        let r = [
+            m(a[0], b[0]) + m(a[1], b19[4]) + m(a[2], b19[3]) + m(a[3], b19[2]) + m(a[4], b19[1]),
+            m(a[0], b[1]) + m(a[1], b[0]) + m(a[2], b19[4]) + m(a[3], b19[3]) + m(a[4], b19[2]),
+            m(a[0], b[2]) + m(a[1], b[1]) + m(a[2], b[0]) + m(a[3], b19[4]) + m(a[4], b19[3]),
+            m(a[0], b[3]) + m(a[1], b[2]) + m(a[2], b[1]) + m(a[3], b[0]) + m(a[4], b19[4]),
+            m(a[0], b[4]) + m(a[1], b[3]) + m(a[2], b[2]) + m(a[3], b[1]) + m(a[4], b[0]),
+        ];
+        let mut out = [0u64; 5];
+        let mut carry: u128 = 0;
+        for i in 0..5 {
+            let v = r[i] + carry;
+            out[i] = v as u64 & MASK;
+            carry = v >> 51;
+        }
+        out[0] += carry as u64 * 19;
+        out[1] += out[0] >> 51;
+        out[0] &= MASK;
+        Fe(out)
+    }
+
+    fn square(self) -> Fe {
+        self.mul(self)
+    }
+
+    /// `self` raised to a little-endian exponent.
+    fn pow(self, exponent: &[u8; 32]) -> Fe {
+        let mut result = Fe::ONE;
+        for i in (0..256).rev() {
+            result = result.square();
+            if (exponent[i / 8] >> (i % 8)) & 1 == 1 {
+                result = result.mul(self);
+            }
+        }
+        result
+    }
+
+    fn invert(self) -> Fe {
+        // p - 2
+        let mut exponent = [0xff; 32];
+        exponent[0] = 0xeb;
+        exponent[31] = 0x7f;
+        self.pow(&exponent)
+    }
+
+    fn is_zero(self) -> bool {
+        self.to_bytes() == [0; 32]
+    }
+
+    fn is_negative(self) -> bool {
+        self.to_bytes()[0] & 1 == 1
+    }
+
+    fn equals(self, rhs: Fe) -> bool {
+        self.to_bytes() == rhs.to_bytes()
+    }
+
+    /// `b` if `choice` is 1, else `a`, without branching on `choice`.
+    fn select(a: Fe, b: Fe, choice: u64) -> Fe {
+        let mask = choice.wrapping_neg();
+        let mut l = a.0;
+        for (x, y) in l.iter_mut().zip(b.0) {
+            *x ^= (*x ^ y) & mask;
+        }
+        Fe(l)
+    }
+}
+
+/// Curve constant d = -121665 / 121666.
+fn curve_d() -> Fe {
+    Fe::from_u64(121665)
+        .neg()
+        .mul(Fe::from_u64(121666).invert())
+}
+
+/// Square root of -1: 2^((p - 1) / 4).
+fn sqrt_m1() -> Fe {
+    let mut exponent = [0xff; 32];
+    exponent[0] = 0xfb;
+    exponent[31] = 0x1f;
+    Fe::from_u64(2).pow(&exponent)
+}
+
+/// Curve point in extended coordinates (X:Y:Z:T), x = X/Z, y = Y/Z,
+/// xy = T/Z.
+#[derive(Clone, Copy)]
+struct Point {
+    x: Fe,
+    y: Fe,
+    z: Fe,
+    t: Fe,
+}
+
+impl Point {
+    const IDENTITY: Point = Point {
+        x: Fe::ZERO,
+        y: Fe::ONE,
+        z: Fe::ONE,
+        t: Fe::ZERO,
+    };
+
+    fn base() -> Point {
+        let mut encoded = [0x66; 32];
+        encoded[0] = 0x58;
+        Point::decompress(&encoded).expect("base point")
+    }
+
+    fn add(self, rhs: Point, d2: Fe) -> Point {
+        let a = self.y.sub(self.x).mul(rhs.y.sub(rhs.x));
+        let b = self.y.add(self.x).mul(rhs.y.add(rhs.x));
+        let c = self.t.mul(d2).mul(rhs.t);
+        let d = self.z.add(self.z).mul(rhs.z);
+        let (e, f, g, h) = (b.sub(a), d.sub(c), d.add(c), b.add(a));
+        Point {
+            x: e.mul(f),
+            y: g.mul(h),
+            z: f.mul(g),
+            t: e.mul(h),
+        }
+    }
+
+    fn neg(self) -> Point {
+        Point {
+            x: self.x.neg(),
+            t: self.t.neg(),
+            ..self
+        }
+    }
+
+    fn select(a: Point, b: Point, choice: u64) -> Point {
+        Point {
+            x: Fe::select(a.x, b.x, choice),
+            y: Fe::select(a.y, b.y, choice),
+            z: Fe::select(a.z, b.z, choice),
+            t: Fe::select(a.t, b.t, choice),
+        }
+    }
+
+    /// Multiply by a little-endian scalar.
+    fn mul(self, scalar: &[u8; 32]) -> Point {
+        let d2 = curve_d().add(curve_d());
+        let mut result = Point::IDENTITY;
+        for i in (0..256).rev() {
+            result = result.add(result, d2);
+            let sum = result.add(self, d2);
+            result = Point::select(result, sum, ((scalar[i / 8] >> (i % 8)) & 1) as u64);
+        }
+        result
+    }
+
+    fn compress(self) -> [u8; 32] {
+        let z_inv = self.z.invert();
+        let x = self.x.mul(z_inv);
+        let mut bytes = self.y.mul(z_inv).to_bytes();
+        bytes[31] |= (x.is_negative() as u8) << 7;
+        bytes
+    }
+
+    fn decompress(bytes: &[u8; 32]) -> Option<Point> {
+        let y = Fe::from_bytes(bytes);
+        if y.to_bytes()[..31] != bytes[..31] || y.to_bytes()[31] != bytes[31] & 0x7f {
+            return None;
+        }
+        let sign = bytes[31] >> 7 == 1;
+
+        // x^2 = (y^2 - 1) / (d y^2 + 1)
+        let y2 = y.square();
+        let u = y2.sub(Fe::ONE);
+        let v = curve_d().mul(y2).add(Fe::ONE);
+        let v3 = v.square().mul(v);
+        // (p - 5) / 8
+        let mut exponent = [0xff; 32];
+        exponent[0] = 0xfd;
+        exponent[31] = 0x0f;
+        let mut x = u.mul(v3).mul(u.mul(v3.square().mul(v)).pow(&exponent));
+        let vx2 = v.mul(x.square());
+        if !vx2.equals(u) {
+            if !vx2.equals(u.neg()) {
+                return None;
+            }
+            x = x.mul(sqrt_m1());
+        }
+        if x.is_zero() && sign {
+            return None;
+        }
+        if x.is_negative() != sign {
+            x = x.neg();
+        }
+        Some(Point {
+            x,
+            y,
+            z: Fe::ONE,
+            t: x.mul(y),
+        })
+    }
+}
+
+/// Group order L = 2^252 + 27742317777372353535851937790883648493, as
+/// little-endian 64-bit words.
+const ORDER: [u64; 4] = [
+    0x5812631a5cf5d3ed,
+    0x14def9dea2f79cd6,
+    0x0000000000000000,
+    0x1000000000000000,
+];
+
+fn geq_order(n: &[u64; 4]) -> bool {
+    for i in (0..4).rev() {
+        if n[i] != ORDER[i] {
+            return n[i] > ORDER[i];
+        }
+    }
+    true
+}
+
+fn sub_order(n: &mut [u64; 4]) {
+    let mut borrow = 0u64;
+    for i in 0..4 {
+        let (v, b1) = n[i].overflowing_sub(ORDER[i]);
+        let (v, b2) = v.overflowing_sub(borrow);
+        n[i] = v;
+        borrow = (b1 || b2) as u64;
+    }
+}
+
+/// Reduce a little-endian number of up to 512 bits modulo L.
+fn reduce(words: &[u64; 8]) -> [u8; 32] {
+    let mut rem = [0u64; 4];
+    for i in (0..512).rev() {
+        // rem < L < 2^253, so doubling cannot overflow
+        for j in (1..4).rev() {
+            rem[j] = (rem[j] << 1) | (rem[j - 1] >> 63);
+        }
+        rem[0] = (rem[0] << 1) | ((words[i / 64] >> (i % 64)) & 1);
+        if geq_order(&rem) {
+            sub_order(&mut rem);
+        }
+    }
+    let mut bytes = [0u8; 32];
+    for (i, word) in rem.iter().enumerate() {
+        bytes[i * 8..i * 8 + 8].copy_from_slice(&word.to_le_bytes());
+    }
+    bytes
+}
+
+fn words<const N: usize>(bytes: &[u8]) -> [u64; N] {
+    let mut words = [0u64; N];
+    for (i, chunk) in bytes.chunks(8).enumerate() {
+        words[i] = u64::from_le_bytes(chunk.try_into().unwrap());
+    }
+    words
+}
+
+fn reduce_hash(hash: &[u8]) -> [u8; 32] {
+    reduce(&words::<8>(hash))
+}
+
+/// (a * b + c) mod L.
+fn mul_add(a: &[u8; 32], b: &[u8; 32], c: &[u8; 32]) -> [u8; 32] {
+    let a = words::<4>(a);
+    let b = words::<4>(b);
+    let mut product = [0u64; 8];
+    for i in 0..4 {
+        let mut carry: u128 = 0;
+        for j in 0..4 {
+            let v = a[i] as u128 * b[j] as u128 + product[i + j] as u128 + carry;
+            product[i + j] = v as u64;
+            carry = v >> 64;
+        }
+        product[i + 4] = carry as u64;
+    }
+    let mut carry: u128 = 0;
+    for (i, word) in words::<4>(c).iter().enumerate() {
+        let v = product[i] as u128 + *word as u128 + carry;
+        product[i] = v as u64;
+        carry = v >> 64;
+    }
+    for word in &mut product[4..] {
+        let v = *word as u128 + carry;
+        *word = v as u64;
+        carry = v >> 64;
+    }
+    reduce(&product)
+}
+
+/// Expanded secret key: the clamped scalar and the nonce prefix.
+fn expand(seed: &[u8; 32]) -> ([u8; 32], [u8; 32]) {
+    let hash = Sha512::digest(seed);
+    let mut scalar: [u8; 32] = hash[..32].try_into().unwrap();
+    scalar[0] &= 248;
+    scalar[31] &= 127;
+    scalar[31] |= 64;
+    (scalar, hash[32..].try_into().unwrap())
+}
+
+/// Public key for a 32-byte secret seed.
+pub(crate) fn public_key(seed: &[u8; 32]) -> [u8; 32] {
+    let (scalar, _) = expand(seed);
+    Point::base().mul(&scalar).compress()
+}
+
+/// Sign a message with a 32-byte secret seed.
+pub(crate) fn sign(seed: &[u8; 32], message: &[u8]) -> [u8; 64] {
+    let (scalar, prefix) = expand(seed);
+    let public = Point::base().mul(&scalar).compress();
+
+    let r = reduce_hash(
+        &Sha512::new()
+            .chain_update(prefix)
+            .chain_update(message)
+            .finalize(),
+    );
+    let big_r = Point::base().mul(&r).compress();
+    let k = reduce_hash(
+        &Sha512::new()
+            .chain_update(big_r)
+            .chain_update(public)
+            .chain_update(message)
+            .finalize(),
+    );
+    let s = mul_add(&k, &reduce(&words::<8>(&[scalar, [0; 32]].concat())), &r);
+
+    let mut signature = [0u8; 64];
+    signature[..32].copy_from_slice(&big_r);
+    signature[32..].copy_from_slice(&s);
+    signature
+}
+
+/// Check a signature against a public key.
+pub(crate) fn verify(public: &[u8; 32], message: &[u8], signature: &[u8; 64]) -> bool {
+    let Some(a) = Point::decompress(public) else {
+        return false;
+    };
+    let s: [u8; 32] = signature[32..].try_into().unwrap();
+    if geq_order(&words::<4>(&s)) {
+        return false;
+    }
+    let k = reduce_hash(
+        &Sha512::new()
+            .chain_update(&signature[..32])
+            .chain_update(public)
+            .chain_update(message)
+            .finalize(),
+    );
+    // [s]B - [k]A must equal R
+    let d2 = curve_d().add(curve_d());
+    let check = Point::base().mul(&s).add(a.neg().mul(&k), d2);
+    check.compress()[..] == signature[..32]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_rfc8032_vectors() {
+        let vectors = [
+            (
+                "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+                "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a",
+                "",
+                "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b",
+            ),
+            (
+                "4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb",
+                "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c",
+                "72",
+                "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00",
+            ),
+            (
+                "c5aa8df43f9f837bedb7442f31dcb7b166d38535076f094b85ce3a2e0b4458f7",
+                "fc51cd8e6218a1a38da47ed00230f0580816ed13ba3303ac5deb911548908025",
+                "af82",
+                "6291d657deec24024827e69c3abe01a30ce548a284743a445e3680d7db5ac3ac18ff9b538d16f290ae67f760984dc6594a7c15e9716ed28dc027beceea1ec40a",
+            ),
+        ];
+        for (seed, public, message, signature) in vectors {
+            let seed: [u8; 32] = hex(seed).try_into().unwrap();
+            let message = hex(message);
+            assert_eq!(public_key(&seed).to_vec(), hex(public));
+            let signed = sign(&seed, &message);
+            assert_eq!(signed.to_vec(), hex(signature));
+            assert!(verify(&public_key(&seed), &message, &signed));
+        }
+    }
+
+    #[test]
+    fn test_verify_rejects_changes() {
+        let seed = [7u8; 32];
+        let public = public_key(&seed);
+        let signature = sign(&seed, b"matrix");
+        assert!(verify(&public, b"matrix", &signature));
+        assert!(!verify(&public, b"matriX", &signature));
+
+        let mut bad = signature;
+        bad[5] ^= 1;
+        assert!(!verify(&public, b"matrix", &bad));
+        assert!(!verify(&public_key(&[8u8; 32]), b"matrix", &signature));
+    }
+
+    fn order_bytes() -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        for (i, word) in ORDER.iter().enumerate() {
+            bytes[i * 8..i * 8 + 8].copy_from_slice(&word.to_le_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_verify_rejects_unreduced_s() {
+        let seed = [7u8; 32];
+        let public = public_key(&seed);
+        let signature = sign(&seed, b"matrix");
+
+        // S + L satisfies the group equation but is not reduced
+        let mut s = words::<4>(&signature[32..]);
+        let mut carry = 0u128;
+        for (word, order) in s.iter_mut().zip(ORDER) {
+            let v = *word as u128 + order as u128 + carry;
+            *word = v as u64;
+            carry = v >> 64;
+        }
+        let mut unreduced = signature;
+        for (i, word) in s.iter().enumerate() {
+            unreduced[32 + i * 8..40 + i * 8].copy_from_slice(&word.to_le_bytes());
+        }
+        assert!(!verify(&public, b"matrix", &unreduced));
+
+        let mut at_order = signature;
+        at_order[32..].copy_from_slice(&order_bytes());
+        assert!(!verify(&public, b"matrix", &at_order));
+    }
+
+    #[test]
+    fn test_verify_rejects_non_canonical_encodings() {
+        // The identity as y = 1 and as the non-canonical y = p + 1
+        let mut identity = [0u8; 32];
+        identity[0] = 1;
+        let mut non_canonical = [0xff; 32];
+        non_canonical[0] = 0xee;
+        non_canonical[31] = 0x7f;
+        assert!(Point::decompress(&non_canonical).is_none());
+
+        // x = 0 with the sign bit set
+        let mut negative_zero = identity;
+        negative_zero[31] |= 0x80;
+        assert!(Point::decompress(&negative_zero).is_none());
+
+        // With A and R the identity and S = 0, [S]B - [k]A = R holds
+        let signature = |r: &[u8; 32]| {
+            let mut signature = [0u8; 64];
+            signature[..32].copy_from_slice(r);
+            signature
+        };
+        assert!(verify(&identity, b"matrix", &signature(&identity)));
+        assert!(!verify(&identity, b"matrix", &signature(&non_canonical)));
+        assert!(!verify(&non_canonical, b"matrix", &signature(&identity)));
+        assert!(!verify(&negative_zero, b"matrix", &signature(&identity)));
+    }
+
+    #[test]
+    fn test_verify_rejects_wrong_key() {
+        let message = hex("72");
+        let signature: [u8; 64] = hex("92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00")
+            .try_into()
+            .unwrap();
+        let signer: [u8; 32] =
+            hex("3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c")
+                .try_into()
+                .unwrap();
+        let other: [u8; 32] =
+            hex("d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a")
+                .try_into()
+                .unwrap();
+        assert!(verify(&signer, &message, &signature));
+        assert!(!verify(&other, &message, &signature));
+    }
+}
@@ -0,0 +1,689 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Consolidated gap analysis.
+//!
+//! One report of everything standing between the project and its
+//! objectives: untested, unimplemented and unjustified derived
+//! requirements, system requirements not allocated to software or not
+//! validated, software requirements no system requirement is allocated to,
+//! untraced functions, uncovered code, tool invocations outside the pinned
+//! toolchains, inline suppressions of coding-standard
+//! checks no approved deviation covers and deviations awaiting approval.
+//! Each gap is prioritized by the DAL policy, so a gap against an
+//! objective the level requires ranks above one it does not.
+
+use crate::{
+    allocation_coverage, correlate_deviations, find_project_misra_suppressions,
+    generate_traceability_matrix, AnnotationSyntax, DalPolicy, DeviationError, DeviationRecord,
+    DeviationStatus, DeviationStore, SystemRequirement, SystemRequirementError,
+    SystemRequirementStore, TraceError, TraceabilityMatrix, ValidationStatus, TRACE_EXTENSIONS,
+};
+use axiom_analysis::{find_suppressions, MisraSuppression, Suppression, SuppressionKind};
+use axiom_core::walk::find_files;
+use axiom_toolchain::{
+    CoverageReport, LockedToolchain, LockfileError, ToolLogError, ToolQualificationLogger,
+    ToolUsageRecord, ToolchainLock,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Gap analysis errors.
+#[derive(Debug, Error)]
+pub enum GapAnalysisError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Trace(#[from] TraceError),
+
+    #[error(transparent)]
+    Lockfile(#[from] LockfileError),
+
+    #[error(transparent)]
+    ToolLog(#[from] ToolLogError),
+
+    #[error(transparent)]
+    Deviation(#[from] DeviationError),
+
+    #[error(transparent)]
+    SystemRequirement(#[from] SystemRequirementError),
+}
+
+/// Priority of a gap, highest first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GapPriority {
+    /// Blocks an objective the DAL policy requires.
+    High,
+    /// Weakens the evidence but no required objective hinges on it alone.
+    Medium,
+    /// Not required at this level.
+    Low,
+}
+
+/// Kind of gap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GapCategory {
+    UntestedRequirement,
+    UnimplementedRequirement,
+    UnjustifiedDerivedRequirement,
+    UnallocatedSystemRequirement,
+    UnvalidatedSystemRequirement,
+    UnallocatedSoftwareRequirement,
+    UntracedFunction,
+    UncoveredCode,
+    UnqualifiedToolUsage,
+    OpenDeviation,
+}
+
+impl GapCategory {
+    /// Serialized name.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::UntestedRequirement => "untested-requirement",
+            Self::UnimplementedRequirement => "unimplemented-requirement",
+            Self::UnjustifiedDerivedRequirement => "unjustified-derived-requirement",
+            Self::UnallocatedSystemRequirement => "unallocated-system-requirement",
+            Self::UnvalidatedSystemRequirement => "unvalidated-system-requirement",
+            Self::UnallocatedSoftwareRequirement => "unallocated-software-requirement",
+            Self::UntracedFunction => "untraced-function",
+            Self::UncoveredCode => "uncovered-code",
+            Self::UnqualifiedToolUsage => "unqualified-tool-usage",
+            Self::OpenDeviation => "open-deviation",
+        }
+    }
+
+    /// Label for reports.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::UntestedRequirement => "Untested requirement",
+            Self::UnimplementedRequirement => "Unimplemented requirement",
+            Self::UnjustifiedDerivedRequirement => "Unjustified derived requirement",
+            Self::UnallocatedSystemRequirement => "Unallocated system requirement",
+            Self::UnvalidatedSystemRequirement => "Unvalidated system requirement",
+            Self::UnallocatedSoftwareRequirement => "Unallocated software requirement",
+            Self::UntracedFunction => "Untraced function",
+            Self::UncoveredCode => "Uncovered code",
+            Self::UnqualifiedToolUsage => "Unqualified tool usage",
+            Self::OpenDeviation => "Open deviation",
+        }
+    }
+}
+
+/// One gap.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Gap {
+    /// Priority under the policy.
+    pub priority: GapPriority,
+    /// Kind of gap.
+    pub category: GapCategory,
+    /// What the gap is about: a requirement ID, function, file or tool.
+    pub subject: String,
+    /// Source file, if the gap has one.
+    pub file: Option<PathBuf>,
+    /// Line in the file (1-based), if the gap has one.
+    pub line: Option<u32>,
+    /// Explanation.
+    pub detail: String,
+}
+
+/// Everything a gap analysis draws on.
+#[derive(Debug, Clone, Default)]
+pub struct GapSources {
+    /// Traceability matrix.
+    pub matrix: TraceabilityMatrix,
+    /// Structural coverage, if measured.
+    pub coverage: Option<CoverageReport>,
+    /// Tool usage log.
+    pub tool_usage: Vec<ToolUsageRecord>,
+    /// Pinned toolchains.
+    pub toolchains: Vec<LockedToolchain>,
+    /// Inline suppressions in the sources.
+    pub suppressions: Vec<Suppression>,
+    /// MISRA checker suppressions in the sources.
+    pub misra_suppressions: Vec<MisraSuppression>,
+    /// Coding-standard deviation records.
+    pub deviations: Vec<DeviationRecord>,
+    /// ARP4754A system requirements.
+    pub system_requirements: Vec<SystemRequirement>,
+}
+
+impl GapSources {
+    /// Sources with only a matrix.
+    pub fn new(matrix: TraceabilityMatrix) -> Self {
+        Self {
+            matrix,
+            ..Default::default()
+        }
+    }
+
+    /// Set the coverage report.
+    pub fn with_coverage(mut self, coverage: CoverageReport) -> Self {
+        self.coverage = Some(coverage);
+        self
+    }
+
+    /// Set the tool usage records and pinned toolchains they are checked
+    /// against.
+    pub fn with_tool_usage(
+        mut self,
+        records: Vec<ToolUsageRecord>,
+        toolchains: Vec<LockedToolchain>,
+    ) -> Self {
+        self.tool_usage = records;
+        self.toolchains = toolchains;
+        self
+    }
+
+    /// Set the inline suppressions.
+    pub fn with_suppressions(mut self, suppressions: Vec<Suppression>) -> Self {
+        self.suppressions = suppressions;
+        self
+    }
+
+    /// Set the deviation records and the MISRA checker suppressions they
+    /// are checked against.
+    pub fn with_deviations(
+        mut self,
+        deviations: Vec<DeviationRecord>,
+        misra_suppressions: Vec<MisraSuppression>,
+    ) -> Self {
+        self.deviations = deviations;
+        self.misra_suppressions = misra_suppressions;
+        self
+    }
+
+    /// Set the system requirements.
+    pub fn with_system_requirements(mut self, requirements: Vec<SystemRequirement>) -> Self {
+        self.system_requirements = requirements;
+        self
+    }
+
+    /// Gather a project's matrix, system requirements, tool usage log,
+    /// toolchain lock, deviation records and source suppressions, plus
+    /// coverage if given.
+    pub fn load(
+        project_root: &Path,
+        syntax: &AnnotationSyntax,
+        coverage: Option<CoverageReport>,
+    ) -> Result<Self, GapAnalysisError> {
+        let mut suppressions = Vec::new();
+        for path in find_files(project_root, TRACE_EXTENSIONS)? {
+            let source = std::fs::read_to_string(&path)?;
+            let relative = path.strip_prefix(project_root).unwrap_or(&path);
+            suppressions.extend(find_suppressions(relative, &source));
+        }
+        let toolchains = ToolchainLock::load(project_root)?
+            .map(|lock| lock.toolchains)
+            .unwrap_or_default();
+        let records = ToolQualificationLogger::for_project(project_root).load()?;
+
+        let mut sources = Self::new(generate_traceability_matrix(project_root, syntax)?)
+            .with_tool_usage(records, toolchains)
+            .with_suppressions(suppressions)
+            .with_deviations(
+                DeviationStore::for_project(project_root).load()?,
+                find_project_misra_suppressions(project_root)?,
+            )
+            .with_system_requirements(SystemRequirementStore::for_project(project_root).load()?);
+        sources.coverage = coverage;
+        Ok(sources)
+    }
+}
+
+/// Gaps, highest priority first.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GapReport {
+    /// Policy the gaps were prioritized under.
+    pub policy: DalPolicy,
+    /// Gaps by priority, category and subject.
+    pub gaps: Vec<Gap>,
+}
+
+impl GapReport {
+    /// Gaps of one priority.
+    pub fn with_priority(&self, priority: GapPriority) -> Vec<&Gap> {
+        self.gaps
+            .iter()
+            .filter(|g| g.priority == priority)
+            .collect()
+    }
+
+    /// Number of gaps per category.
+    pub fn counts(&self) -> BTreeMap<GapCategory, usize> {
+        let mut counts = BTreeMap::new();
+        for gap in &self.gaps {
+            *counts.entry(gap.category).or_insert(0) += 1;
+        }
+        counts
+    }
+}
+
+/// Whether a tool invocation used a pinned binary: the locked driver at
+/// its locked version, or a locked companion binary installed beside it.
+fn is_pinned(record: &ToolUsageRecord, toolchains: &[LockedToolchain]) -> bool {
+    toolchains.iter().any(|locked| {
+        if record.path == locked.path {
+            return record.version == locked.version;
+        }
+        record.path.parent() == locked.path.parent() && locked.binaries.contains_key(&record.tool)
+    })
+}
+
+fn required(yes: bool, otherwise: GapPriority) -> GapPriority {
+    if yes {
+        GapPriority::High
+    } else {
+        otherwise
+    }
+}
+
+/// Merge every kind of gap into one report prioritized by the policy.
+pub fn generate_gap_analysis(sources: &GapSources, policy: &DalPolicy) -> GapReport {
+    let matrix = &sources.matrix;
+    let mut gaps = Vec::new();
+    let mut push =
+        |priority, category, subject: String, location: Option<(PathBuf, u32)>, detail| {
+            let (file, line) = location.map_or((None, None), |(f, l)| (Some(f), Some(l)));
+            gaps.push(Gap {
+                priority,
+                category,
+                subject,
+                file,
+                line,
+                detail,
+            });
+        };
+
+    for id in matrix.find_untested_requirements() {
+        push(
+            required(policy.trace_high_level, GapPriority::Medium),
+            GapCategory::UntestedRequirement,
+            id,
+            None,
+            "No passing test verifies the requirement.".to_string(),
+        );
+    }
+    for id in matrix.find_unimplemented_requirements() {
+        push(
+            required(policy.trace_low_level, GapPriority::Medium),
+            GapCategory::UnimplementedRequirement,
+            id,
+            None,
+            "No code implements the requirement.".to_string(),
+        );
+    }
+    for finding in matrix.find_unjustified_derived_requirements() {
+        push(
+            required(policy.trace_low_level, GapPriority::Medium),
+            GapCategory::UnjustifiedDerivedRequirement,
+            finding.requirement.clone(),
+            None,
+            format!("Derived requirement: {}.", finding.issues().join(", ")),
+        );
+    }
+
+    // Allocation gaps only mean something once system requirements are kept
+    if policy.system_allocation || !sources.system_requirements.is_empty() {
+        let allocation = allocation_coverage(&sources.system_requirements, &matrix.requirements);
+        let priority = required(policy.system_allocation, GapPriority::Low);
+        for row in &allocation.rows {
+            let mut problems = Vec::new();
+            if row.software.is_empty() {
+                problems.push("Not allocated to any software requirement.".to_string());
+            }
+            if !row.unknown.is_empty() {
+                problems.push(format!(
+                    "Allocated to unknown or retired requirement(s): {}.",
+                    row.unknown.join(", ")
+                ));
+            }
+            if !problems.is_empty() {
+                push(
+                    priority,
+                    GapCategory::UnallocatedSystemRequirement,
+                    row.system.clone(),
+                    None,
+                    problems.join(" "),
+                );
+            }
+            if row.validation != ValidationStatus::Validated {
+                let detail = match row.validation {
+                    ValidationStatus::Invalid => "Found invalid; needs rework.",
+                    _ => "Not validated.",
+                };
+                push(
+                    priority,
+                    GapCategory::UnvalidatedSystemRequirement,
+                    row.system.clone(),
+                    None,
+                    detail.to_string(),
+                );
+            }
+        }
+        for id in &allocation.unallocated_software {
+            push(
+                priority,
+                GapCategory::UnallocatedSoftwareRequirement,
+                id.clone(),
+                None,
+                "No system requirement is allocated to it.".to_string(),
+            );
+        }
+    }
+    for function in matrix.find_untraceable_functions() {
+        push(
+            required(policy.trace_low_level, GapPriority::Low),
+            GapCategory::UntracedFunction,
+            function.name.clone(),
+            Some((function.file.clone(), function.line)),
+            "No requirement traces to the function.".to_string(),
+        );
+    }
+
+    if let Some(ref coverage) = sources.coverage {
+        for file in &coverage.files {
+            let uncovered = file.unresolved_lines();
+            let Some(&first) = uncovered.first() else {
+                continue;
+            };
+            push(
+                required(policy.coverage_criterion.is_some(), GapPriority::Low),
+                GapCategory::UncoveredCode,
+                file.path.display().to_string(),
+                Some((file.path.clone(), first)),
+                format!(
+                    "{} of {} executable lines never executed and not justified.",
+                    uncovered.len(),
+                    file.lines.len()
+                ),
+            );
+        }
+    }
+
+    let qualification_required =
+        policy.development_tool_level.is_some() || policy.verification_tool_level.is_some();
+    let mut unpinned: BTreeMap<(&Path, &str), usize> = BTreeMap::new();
+    for record in &sources.tool_usage {
+        if !is_pinned(record, &sources.toolchains) {
+            *unpinned
+                .entry((record.path.as_path(), record.version.as_str()))
+                .or_insert(0) += 1;
+        }
+    }
+    for ((path, version), count) in unpinned {
+        push(
+            required(qualification_required, GapPriority::Low),
+            GapCategory::UnqualifiedToolUsage,
+            path.display().to_string(),
+            None,
+            format!(
+                "{} invocation(s) of version {} outside the pinned toolchains.",
+                count, version
+            ),
+        );
+    }
+
+    let approved: Vec<&DeviationRecord> = sources
+        .deviations
+        .iter()
+        .filter(|d| d.is_approved())
+        .collect();
+    for suppression in &sources.suppressions {
+        let line = suppression.location.range.start.line + 1;
+        let covered = match suppression.kind {
+            SuppressionKind::NoLint => Some(line),
+            SuppressionKind::NoLintNextLine => Some(line + 1),
+            SuppressionKind::NoLintBegin => None,
+            SuppressionKind::NoLintEnd => continue,
+        };
+        let justified = !suppression.checks.is_empty()
+            && suppression.checks.iter().all(|check| {
+                approved.iter().any(|d| {
+                    d.checks.contains(check) && d.applies_at(&suppression.location.path, covered)
+                })
+            });
+        if justified {
+            continue;
+        }
+        let checks = if suppression.checks.is_empty() {
+            "all checks".to_string()
+        } else {
+            suppression.checks.join(", ")
+        };
+        push(
+            GapPriority::Medium,
+            GapCategory::OpenDeviation,
+            checks.clone(),
+            Some((
+                suppression.location.path.clone(),
+                suppression.location.range.start.line + 1,
+            )),
+            format!(
+                "Inline suppression of {} without a deviation record.",
+                checks
+            ),
+        );
+    }
+
+    let correlation = correlate_deviations(&sources.deviations, &[], &sources.misra_suppressions);
+    for suppression in correlation.unapproved_suppressions() {
+        let checks = suppression.checks.join(", ");
+        push(
+            GapPriority::Medium,
+            GapCategory::OpenDeviation,
+            checks.clone(),
+            Some((
+                suppression.location.path.clone(),
+                suppression.location.range.start.line + 1,
+            )),
+            format!(
+                "MISRA suppression of {} without an approved deviation.",
+                checks
+            ),
+        );
+    }
+    for deviation in &sources.deviations {
+        if deviation.status == DeviationStatus::Proposed {
+            push(
+                GapPriority::Medium,
+                GapCategory::OpenDeviation,
+                deviation.id.clone(),
+                deviation.file.clone().zip(deviation.line),
+                format!("Deviation from rule {} awaiting approval.", deviation.rule),
+            );
+        }
+    }
+
+    gaps.sort_by(|a, b| {
+        (a.priority, a.category, &a.subject, &a.file, a.line)
+            .cmp(&(b.priority, b.category, &b.subject, &b.file, b.line))
+    });
+    GapReport {
+        policy: policy.clone(),
+        gaps,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ComplianceConfig, ComplianceMode, DesignAssuranceLevel, Requirement};
+    use axiom_toolchain::{parse_lcov, ToolchainKind};
+    use std::collections::BTreeMap;
+    use tempfile::TempDir;
+
+    const NAV: &str = "\
+/* Implements REQ-NAV-001 */
+int nav_update(int x)
+{
+    return x + 1; // NOLINT(readability-magic-numbers)
+}
+
+int nav_helper(void)
+{
+    return 0;
+}
+";
+
+    fn policy(dal: DesignAssuranceLevel) -> DalPolicy {
+        ComplianceConfig::new(dal)
+            .with_mode(ComplianceMode::Do178c)
+            .with_mode(ComplianceMode::Do330)
+            .policy()
+    }
+
+    fn sources(root: &Path) -> GapSources {
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("src/nav.c"), NAV).unwrap();
+        let coverage = CoverageReport::from_files(parse_lcov(
+            "SF:src/nav.c\nDA:3,1\nDA:4,1\nDA:9,0\nend_of_record\n",
+        ));
+        let gcc = PathBuf::from("/opt/arm/bin/arm-none-eabi-gcc");
+        let locked = LockedToolchain {
+            kind: ToolchainKind::ArmGcc,
+            path: gcc.clone(),
+            version: "13.2.1".to_string(),
+            binaries: BTreeMap::from([("arm-none-eabi-objcopy".to_string(), "ab".to_string())]),
+        };
+        let records = vec![
+            ToolUsageRecord::new(1, &gcc, "13.2.1"),
+            ToolUsageRecord::new(2, Path::new("/opt/arm/bin/arm-none-eabi-objcopy"), "2.41"),
+            ToolUsageRecord::new(3, &gcc, "12.3.1"),
+            ToolUsageRecord::new(4, &gcc, "12.3.1"),
+        ];
+        let mut sources =
+            GapSources::load(root, &AnnotationSyntax::default(), Some(coverage)).unwrap();
+        sources.matrix.requirements = vec![Requirement::new("REQ-NAV-001", "Update")];
+        sources.with_tool_usage(records, vec![locked])
+    }
+
+    #[test]
+    fn test_gap_analysis() {
+        let dir = TempDir::new().unwrap();
+        let report = generate_gap_analysis(&sources(dir.path()), &policy(DesignAssuranceLevel::A));
+
+        let counts = report.counts();
+        assert_eq!(counts[&GapCategory::UntestedRequirement], 1);
+        assert_eq!(counts[&GapCategory::UntracedFunction], 1);
+        assert_eq!(counts[&GapCategory::UncoveredCode], 1);
+        assert_eq!(counts[&GapCategory::UnqualifiedToolUsage], 1);
+        assert_eq!(counts[&GapCategory::OpenDeviation], 1);
+        assert!(!counts.contains_key(&GapCategory::UnimplementedRequirement));
+
+        // Required objectives first, deviations after
+        assert_eq!(report.gaps[0].category, GapCategory::UntestedRequirement);
+        assert_eq!(report.with_priority(GapPriority::High).len(), 4);
+        let tool = &report.gaps[3];
+        assert_eq!(tool.category, GapCategory::UnqualifiedToolUsage);
+        assert!(tool.detail.starts_with("2 invocation(s) of version 12.3.1"));
+        let deviation = report.gaps.last().unwrap();
+        assert_eq!(deviation.priority, GapPriority::Medium);
+        assert_eq!(deviation.subject, "readability-magic-numbers");
+        assert_eq!(deviation.file.as_deref(), Some(Path::new("src/nav.c")));
+        assert_eq!(deviation.line, Some(4));
+        let uncovered = &report.gaps[2];
+        assert_eq!(uncovered.line, Some(9));
+    }
+
+    #[test]
+    fn test_approved_deviations_close_gaps() {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("src")).unwrap();
+        std::fs::write(
+            dir.path().join("src/nav.c"),
+            "int a = 1; // NOLINT(readability-magic-numbers)\n\
+             // cppcheck-suppress misra-c2012-10.4\n\
+             int b = a + 1u;\n\
+             // cppcheck-suppress misra-c2012-12.1\n\
+             int c = a + b * 2;\n",
+        )
+        .unwrap();
+        let store = DeviationStore::for_project(dir.path());
+        for deviation in [
+            DeviationRecord::new("DEV-0001", "Dir 4.1", "Named by context", 0)
+                .with_location("src/nav.c", Some(1))
+                .with_check("readability-magic-numbers")
+                .approve("J. Reviewer", 1),
+            DeviationRecord::new("DEV-0002", "10.4", "Unsigned literal", 0)
+                .with_location("src/nav.c", Some(3))
+                .approve("J. Reviewer", 1),
+            DeviationRecord::new("DEV-0003", "12.1", "Precedence is clear", 0),
+        ] {
+            store.create(deviation).unwrap();
+        }
+
+        let sources = GapSources::load(dir.path(), &AnnotationSyntax::default(), None).unwrap();
+        let report = generate_gap_analysis(&sources, &policy(DesignAssuranceLevel::C));
+        let deviations: Vec<&str> = report
+            .gaps
+            .iter()
+            .filter(|g| g.category == GapCategory::OpenDeviation)
+            .map(|g| g.subject.as_str())
+            .collect();
+        assert_eq!(deviations, ["DEV-0003", "misra-c2012-12.1"]);
+    }
+
+    #[test]
+    fn test_priority_follows_policy() {
+        let dir = TempDir::new().unwrap();
+        let report = generate_gap_analysis(&sources(dir.path()), &policy(DesignAssuranceLevel::E));
+        assert!(report.with_priority(GapPriority::High).is_empty());
+        assert_eq!(report.gaps[0].priority, GapPriority::Medium);
+        assert_eq!(report.gaps.last().unwrap().priority, GapPriority::Low);
+    }
+
+    #[test]
+    fn test_system_allocation_gaps() {
+        let dir = TempDir::new().unwrap();
+        let sources = sources(dir.path()).with_system_requirements(vec![
+            SystemRequirement::new("SYS-1", "Navigate").with_allocation("REQ-NAV-009"),
+            SystemRequirement::new("SYS-2", "Navigate well")
+                .with_validation(crate::ValidationMethod::Review, "Minutes 3")
+                .with_allocation("REQ-NAV-001"),
+        ]);
+        let arp = ComplianceConfig::new(DesignAssuranceLevel::B)
+            .with_mode(ComplianceMode::Arp4754a)
+            .policy();
+
+        let report = generate_gap_analysis(&sources, &arp);
+        let system: Vec<(GapPriority, GapCategory, &str)> = report
+            .gaps
+            .iter()
+            .filter(|g| g.subject.starts_with("SYS-"))
+            .map(|g| (g.priority, g.category, g.subject.as_str()))
+            .collect();
+        assert_eq!(
+            system,
+            [
+                (
+                    GapPriority::High,
+                    GapCategory::UnallocatedSystemRequirement,
+                    "SYS-1"
+                ),
+                (
+                    GapPriority::High,
+                    GapCategory::UnvalidatedSystemRequirement,
+                    "SYS-1"
+                ),
+            ]
+        );
+        let counts = report.counts();
+        assert!(!counts.contains_key(&GapCategory::UnallocatedSoftwareRequirement));
+
+        let unallocated = GapSources::new(sources.matrix.clone());
+        let report = generate_gap_analysis(&unallocated, &arp);
+        assert_eq!(
+            report.counts()[&GapCategory::UnallocatedSoftwareRequirement],
+            1
+        );
+        let report = generate_gap_analysis(&unallocated, &policy(DesignAssuranceLevel::A));
+        assert!(!report
+            .counts()
+            .contains_key(&GapCategory::UnallocatedSoftwareRequirement));
+    }
+}
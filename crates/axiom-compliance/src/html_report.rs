@@ -0,0 +1,348 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! HTML compliance report.
+//!
+//! Renders the traceability matrix, its gaps and the structural coverage
+//! summary as a single HTML file with inline styles and no scripts, so it
+//! can be attached to a certification data package and opened offline.
+
+use crate::{DalPolicy, TraceLink, TraceabilityMatrix, VerificationStatus};
+use axiom_core::time::format_timestamp;
+use axiom_toolchain::{CoverageCounter, CoverageReport};
+use std::fmt::Write as _;
+
+pub(crate) const STYLE: &str = "body{font-family:sans-serif;margin:2em;color:#222}\
+table{border-collapse:collapse;margin-bottom:2em;width:100%}\
+th,td{border:1px solid #bbb;padding:4px 8px;text-align:left;vertical-align:top}\
+th{background:#eee}.gap{background:#fde2e2}.ok{background:#e2f5e2}\
+code{font-size:90%}";
+
+/// Options for an HTML compliance report.
+#[derive(Debug, Clone, Default)]
+pub struct HtmlReport {
+    /// Report title.
+    pub title: String,
+    /// Generation time (seconds since the Unix epoch).
+    pub timestamp: u64,
+    /// Policy to check coverage against.
+    pub policy: Option<DalPolicy>,
+    /// Structural coverage to summarize.
+    pub coverage: Option<CoverageReport>,
+}
+
+impl HtmlReport {
+    /// Create a report with a title and generation time.
+    pub fn new(title: impl Into<String>, timestamp: u64) -> Self {
+        Self {
+            title: title.into(),
+            timestamp,
+            ..Default::default()
+        }
+    }
+
+    /// Check coverage against a policy.
+    pub fn with_policy(mut self, policy: DalPolicy) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    /// Include a coverage summary.
+    pub fn with_coverage(mut self, coverage: CoverageReport) -> Self {
+        self.coverage = Some(coverage);
+        self
+    }
+
+    /// Render the report for a traceability matrix.
+    pub fn render(&self, matrix: &TraceabilityMatrix) -> String {
+        let rows = matrix.rows();
+        let untested = matrix.find_untested_requirements();
+        let unimplemented = matrix.find_unimplemented_requirements();
+        let untraceable = matrix.find_untraceable_functions();
+        let unjustified = matrix.find_unjustified_derived_requirements();
+
+        let mut html = String::new();
+        let _ = writeln!(html, "<!DOCTYPE html>");
+        let _ = writeln!(html, "<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">");
+        let _ = writeln!(html, "<title>{}</title>", escape(&self.title));
+        let _ = writeln!(html, "<style>{}</style>\n</head>\n<body>", STYLE);
+        let _ = writeln!(html, "<h1>{}</h1>", escape(&self.title));
+        let _ = writeln!(
+            html,
+            "<p>Generated {} by Axiom {}</p>",
+            format_timestamp(self.timestamp),
+            env!("CARGO_PKG_VERSION")
+        );
+        if let Some(ref policy) = self.policy {
+            let modes: Vec<&str> = policy.modes.iter().map(|m| m.name()).collect();
+            let _ = writeln!(
+                html,
+                "<p>Design Assurance Level {} ({})</p>",
+                policy.dal,
+                if modes.is_empty() {
+                    "no compliance modes".to_string()
+                } else {
+                    modes.join(", ")
+                }
+            );
+        }
+
+        let _ = writeln!(html, "<h2>Summary</h2>\n<table>");
+        for (label, count) in [
+            ("Requirements", rows.len()),
+            ("Trace links", matrix.links.len()),
+            ("Untested requirements", untested.len()),
+            ("Unimplemented requirements", unimplemented.len()),
+            ("Untraceable functions", untraceable.len()),
+            ("Unjustified derived requirements", unjustified.len()),
+        ] {
+            let _ = writeln!(html, "<tr><th>{}</th><td>{}</td></tr>", label, count);
+        }
+        let _ = writeln!(html, "</table>");
+
+        let _ = writeln!(html, "<h2>Traceability matrix</h2>\n<table>");
+        let _ = writeln!(
+            html,
+            "<tr><th>Requirement</th><th>Text</th><th>Status</th><th>Implemented by</th><th>Tested by</th><th>Verification</th></tr>"
+        );
+        for row in &rows {
+            let gap = row.implemented_by.is_empty()
+                || row.tested_by.is_empty()
+                || row.verification == VerificationStatus::Failed;
+            let (text, status) = match row.definition {
+                Some(ref r) => (escape(&r.text), r.status.as_str()),
+                None => (String::new(), "undefined"),
+            };
+            let _ = writeln!(
+                html,
+                "<tr class=\"{}\"><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                if gap { "gap" } else { "ok" },
+                escape(&row.requirement),
+                text,
+                status,
+                links(&row.implemented_by),
+                links(&row.tested_by),
+                row.verification.as_str()
+            );
+        }
+        let _ = writeln!(html, "</table>");
+
+        let _ = writeln!(html, "<h2>Untested requirements</h2>");
+        list(&mut html, untested.iter().map(|id| escape(id)));
+        let _ = writeln!(html, "<h2>Unimplemented requirements</h2>");
+        list(&mut html, unimplemented.iter().map(|id| escape(id)));
+        let _ = writeln!(html, "<h2>Untraceable functions</h2>");
+        list(
+            &mut html,
+            untraceable.iter().map(|f| {
+                format!(
+                    "<code>{}</code> ({}:{})",
+                    escape(&f.name),
+                    escape(&f.file.display().to_string()),
+                    f.line
+                )
+            }),
+        );
+
+        let _ = writeln!(html, "<h2>Unjustified derived requirements</h2>");
+        list(
+            &mut html,
+            unjustified.iter().map(|finding| {
+                format!(
+                    "{}: {}",
+                    escape(&finding.requirement),
+                    finding.issues().join(", ")
+                )
+            }),
+        );
+
+        if let Some(ref coverage) = self.coverage {
+            self.render_coverage(&mut html, coverage);
+        }
+
+        let _ = writeln!(html, "</body>\n</html>");
+        html
+    }
+
+    fn render_coverage(&self, html: &mut String, coverage: &CoverageReport) {
+        let _ = writeln!(html, "<h2>Structural coverage</h2>\n<table>");
+        let _ = writeln!(
+            html,
+            "<tr><th>File</th><th>Lines</th><th>Branches</th><th>Functions</th></tr>"
+        );
+        let files = coverage
+            .files
+            .iter()
+            .map(|f| (f.path.display().to_string(), f.summary()));
+        for (name, summary) in files.chain([("Total".to_string(), coverage.summary)]) {
+            let _ = writeln!(
+                html,
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                escape(&name),
+                counter(summary.lines),
+                counter(summary.branches),
+                counter(summary.functions)
+            );
+        }
+        let _ = writeln!(html, "</table>");
+
+        let Some(ref policy) = self.policy else {
+            return;
+        };
+        let Some(criterion) = policy.coverage_criterion else {
+            return;
+        };
+        let _ = writeln!(
+            html,
+            "<p>Required: {} coverage at {:.1}%</p>",
+            criterion, policy.coverage_threshold
+        );
+        let shortfalls = policy.coverage_shortfalls(&coverage.summary);
+        list(
+            html,
+            shortfalls.iter().map(|s| {
+                format!(
+                    "{} coverage {:.1}% is below {:.1}%",
+                    s.criterion, s.achieved, s.required
+                )
+            }),
+        );
+    }
+}
+
+/// `file:line (function)` for each link, one per line.
+fn links(links: &[TraceLink]) -> String {
+    links
+        .iter()
+        .map(|l| {
+            let mut text = format!("{}:{}", escape(&l.file.display().to_string()), l.line);
+            if let Some(ref function) = l.function {
+                let _ = write!(text, " <code>{}</code>", escape(function));
+            }
+            text
+        })
+        .collect::<Vec<_>>()
+        .join("<br>")
+}
+
+/// `covered/total (percent)` plus any justified items, or a dash when there
+/// is nothing to cover.
+fn counter(counter: CoverageCounter) -> String {
+    if counter.total == 0 {
+        "&ndash;".to_string()
+    } else if counter.justified == 0 {
+        format!(
+            "{}/{} ({:.1}%)",
+            counter.covered,
+            counter.total,
+            counter.percent()
+        )
+    } else {
+        format!(
+            "{}/{} ({:.1}%), {} justified",
+            counter.covered,
+            counter.total,
+            counter.percent(),
+            counter.justified
+        )
+    }
+}
+
+/// Write items as a bulleted list, or "None." when empty.
+fn list(html: &mut String, items: impl Iterator<Item = String>) {
+    let items: Vec<String> = items.collect();
+    if items.is_empty() {
+        let _ = writeln!(html, "<p>None.</p>");
+        return;
+    }
+    let _ = writeln!(html, "<ul>");
+    for item in items {
+        let _ = writeln!(html, "<li>{}</li>", item);
+    }
+    let _ = writeln!(html, "</ul>");
+}
+
+/// Escape text for HTML content and attribute values.
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ComplianceConfig, ComplianceMode, DesignAssuranceLevel, FileTrace, LinkType, Requirement,
+        SourceFunction,
+    };
+    use axiom_toolchain::{FileCoverage, LineCoverage};
+    use std::path::PathBuf;
+
+    fn matrix() -> TraceabilityMatrix {
+        let trace = FileTrace {
+            links: vec![TraceLink {
+                requirement: "REQ-1".to_string(),
+                file: PathBuf::from("src/a.c"),
+                line: 3,
+                function: Some("limit".to_string()),
+                link_type: LinkType::Implements,
+            }],
+            functions: vec![
+                SourceFunction {
+                    name: "limit".to_string(),
+                    file: PathBuf::from("src/a.c"),
+                    line: 4,
+                    end_line: 9,
+                },
+                SourceFunction {
+                    name: "helper".to_string(),
+                    file: PathBuf::from("src/a.c"),
+                    line: 11,
+                    end_line: 12,
+                },
+            ],
+        };
+        TraceabilityMatrix::new(vec![Requirement::new("REQ-1", "Limit <rate>")], [trace])
+    }
+
+    #[test]
+    fn test_render_matrix() {
+        let html = HtmlReport::new("Nav & Guidance", 0).render(&matrix());
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>Nav &amp; Guidance</title>"));
+        assert!(html.contains("Generated 1970-01-01T00:00:00Z"));
+        assert!(html.contains("<td>Limit &lt;rate&gt;</td>"));
+        assert!(html.contains("src/a.c:3 <code>limit</code>"));
+        assert!(html.contains("<li><code>helper</code> (src/a.c:11)</li>"));
+        assert!(!html.contains("<script"));
+    }
+
+    #[test]
+    fn test_render_coverage_against_policy() {
+        let mut file = FileCoverage::new("src/a.c");
+        file.lines = vec![
+            LineCoverage {
+                line: 5,
+                count: 1,
+                unexecuted_block: false,
+            },
+            LineCoverage {
+                line: 6,
+                count: 0,
+                unexecuted_block: false,
+            },
+        ];
+        let policy = ComplianceConfig::new(DesignAssuranceLevel::C)
+            .with_mode(ComplianceMode::Do178c)
+            .policy();
+        let html = HtmlReport::new("Nav", 0)
+            .with_policy(policy)
+            .with_coverage(CoverageReport::from_files([file]))
+            .render(&matrix());
+        assert!(html.contains("Design Assurance Level C (DO-178C)"));
+        assert!(html.contains("<td>1/2 (50.0%)</td>"));
+        assert!(html.contains("statement coverage 50.0% is below 100.0%"));
+    }
+}
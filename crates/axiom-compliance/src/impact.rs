@@ -0,0 +1,379 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Change impact analysis.
+//!
+//! Joins the diff of a commit range with the traceability matrix to scope
+//! re-verification: which functions the changed lines fall in, which
+//! requirements those functions implement or test, which tests must be
+//! re-run and which sources need their structural coverage re-measured.
+//! Changed functions no requirement traces to are reported too, since
+//! they have no test to justify them, as are the open problem reports
+//! whose requirements or files the range touches.
+
+use crate::{
+    generate_traceability_matrix, AnnotationSyntax, LinkType, ProblemReportError,
+    ProblemReportStore, SourceFunction, TraceError, TraceLink, TraceabilityMatrix,
+    TRACE_EXTENSIONS,
+};
+use axiom_git::{get_range_diff, FileDiff, GitError, Repository};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Impact analysis errors.
+#[derive(Debug, Error)]
+pub enum ImpactError {
+    #[error(transparent)]
+    Git(#[from] GitError),
+
+    #[error(transparent)]
+    Trace(#[from] TraceError),
+
+    #[error(transparent)]
+    ProblemReport(#[from] ProblemReportError),
+}
+
+/// Why a requirement is in the re-verification scope.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImpactedRequirement {
+    /// Requirement ID.
+    pub requirement: String,
+    /// A function implementing the requirement changed.
+    pub implementation_changed: bool,
+    /// A test of the requirement changed.
+    pub test_changed: bool,
+    /// An annotation referencing the requirement was added or removed.
+    pub annotation_changed: bool,
+    /// Tests to re-run for the requirement.
+    pub tests: Vec<TraceLink>,
+}
+
+/// Re-verification scope of a commit range.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChangeImpact {
+    /// Start of the range.
+    pub from: String,
+    /// End of the range.
+    pub to: String,
+    /// Files changed in the range, relative to the project root.
+    pub changed_files: Vec<PathBuf>,
+    /// Functions containing changed lines.
+    pub changed_functions: Vec<SourceFunction>,
+    /// Requirements affected, by ID.
+    pub requirements: Vec<ImpactedRequirement>,
+    /// Test functions to re-run, by name.
+    pub tests: Vec<String>,
+    /// Traced sources whose coverage results are stale.
+    pub coverage_files: Vec<PathBuf>,
+    /// Changed functions with no requirement link.
+    pub untraced_functions: Vec<SourceFunction>,
+    /// Open problem reports affecting the changed requirements or files.
+    #[serde(default)]
+    pub problem_reports: Vec<String>,
+}
+
+/// Lines a file diff touches on the new side. A pure deletion touches the
+/// line it was removed before, so the function it came from still counts.
+fn touched_lines(diff: &FileDiff) -> BTreeSet<u32> {
+    let mut lines = BTreeSet::new();
+    for hunk in &diff.hunks {
+        for line in &hunk.lines {
+            match line.origin {
+                '+' => lines.extend(line.new_lineno),
+                '-' => {
+                    lines.insert(hunk.new_start.max(1));
+                    lines.insert(hunk.new_start + 1);
+                }
+                _ => {}
+            }
+        }
+    }
+    lines
+}
+
+/// Requirement IDs on removed lines.
+fn removed_refs(diff: &FileDiff, syntax: &AnnotationSyntax) -> BTreeSet<String> {
+    diff.hunks
+        .iter()
+        .flat_map(|h| &h.lines)
+        .filter(|l| l.origin == '-')
+        .flat_map(|l| syntax.find_annotations(&l.content))
+        .map(|(id, _)| id.to_string())
+        .collect()
+}
+
+fn entry<'a>(
+    requirements: &'a mut BTreeMap<String, ImpactedRequirement>,
+    id: &str,
+) -> &'a mut ImpactedRequirement {
+    requirements
+        .entry(id.to_string())
+        .or_insert_with(|| ImpactedRequirement {
+            requirement: id.to_string(),
+            ..Default::default()
+        })
+}
+
+/// Scope re-verification for diffs whose paths are relative to the
+/// matrix's project root. `matrix` must reflect the end of the range.
+pub fn change_impact(
+    matrix: &TraceabilityMatrix,
+    syntax: &AnnotationSyntax,
+    diffs: &[FileDiff],
+    from: &str,
+    to: &str,
+) -> ChangeImpact {
+    let mut changed_files = BTreeSet::new();
+    let mut changed_functions = Vec::new();
+    let mut requirements: BTreeMap<String, ImpactedRequirement> = BTreeMap::new();
+
+    for diff in diffs {
+        let Some(path) = diff.new_path.as_ref().or(diff.old_path.as_ref()) else {
+            continue;
+        };
+        changed_files.insert(path.clone());
+        if diff.new_path.is_none() {
+            for id in removed_refs(diff, syntax) {
+                entry(&mut requirements, &id).annotation_changed = true;
+            }
+            continue;
+        }
+
+        let touched = touched_lines(diff);
+        let touches = |start: u32, end: u32| touched.range(start..=end.max(start)).next().is_some();
+        for function in matrix.functions.iter().filter(|f| &f.file == path) {
+            if touches(function.line, function.end_line) {
+                changed_functions.push(function.clone());
+            }
+        }
+        for link in matrix.links.iter().filter(|l| &l.file == path) {
+            if touches(link.line, link.line) {
+                entry(&mut requirements, &link.requirement).annotation_changed = true;
+            }
+        }
+        for id in removed_refs(diff, syntax) {
+            entry(&mut requirements, &id).annotation_changed = true;
+        }
+    }
+
+    let mut untraced_functions = Vec::new();
+    for function in &changed_functions {
+        let links: Vec<&TraceLink> = matrix
+            .links
+            .iter()
+            .filter(|l| l.file == function.file && l.function.as_deref() == Some(&function.name))
+            .collect();
+        if links.is_empty() {
+            untraced_functions.push(function.clone());
+        }
+        for link in links {
+            let impacted = entry(&mut requirements, &link.requirement);
+            match link.link_type {
+                LinkType::Tests => impacted.test_changed = true,
+                LinkType::Implements | LinkType::Derived => impacted.implementation_changed = true,
+            }
+        }
+    }
+
+    let mut tests = BTreeSet::new();
+    for impacted in requirements.values_mut() {
+        impacted.tests = matrix
+            .links_for(&impacted.requirement)
+            .into_iter()
+            .filter(|l| l.link_type == LinkType::Tests)
+            .cloned()
+            .collect();
+        tests.extend(impacted.tests.iter().filter_map(|l| l.function.clone()));
+    }
+
+    let coverage_files = changed_files
+        .iter()
+        .filter(|path| {
+            path.extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| TRACE_EXTENSIONS.contains(&e))
+        })
+        .filter(|path| matrix.functions.iter().any(|f| &f.file == *path))
+        .cloned()
+        .collect();
+
+    ChangeImpact {
+        from: from.to_string(),
+        to: to.to_string(),
+        changed_files: changed_files.into_iter().collect(),
+        changed_functions,
+        requirements: requirements.into_values().collect(),
+        tests: tests.into_iter().collect(),
+        coverage_files,
+        untraced_functions,
+        problem_reports: Vec::new(),
+    }
+}
+
+/// Scope re-verification for the commit range `from..to` of the repository
+/// containing a project, with the project's open problem reports that the
+/// range affects. The matrix is built from the working tree, which should
+/// be at `to`.
+pub fn analyze_change_impact(
+    project_root: &Path,
+    syntax: &AnnotationSyntax,
+    from: &str,
+    to: &str,
+) -> Result<ChangeImpact, ImpactError> {
+    let repo = Repository::discover(project_root)?;
+    let matrix = generate_traceability_matrix(project_root, syntax)?;
+
+    // Diff paths are relative to the repository; the matrix's to the project
+    let prefix = project_root
+        .canonicalize()
+        .ok()
+        .zip(repo.path().canonicalize().ok())
+        .and_then(|(root, workdir)| root.strip_prefix(workdir).ok().map(Path::to_path_buf))
+        .unwrap_or_default();
+    let relative = |path: Option<PathBuf>| {
+        path.and_then(|p| p.strip_prefix(&prefix).ok().map(Path::to_path_buf))
+    };
+    let diffs: Vec<FileDiff> = get_range_diff(&repo, from, to)?
+        .into_iter()
+        .map(|diff| FileDiff {
+            old_path: relative(diff.old_path),
+            new_path: relative(diff.new_path),
+            ..diff
+        })
+        .filter(|diff| diff.old_path.is_some() || diff.new_path.is_some())
+        .collect();
+
+    let mut impact = change_impact(&matrix, syntax, &diffs, from, to);
+    impact.problem_reports = ProblemReportStore::for_project(project_root)
+        .affected_by(&impact)?
+        .into_iter()
+        .map(|r| r.id)
+        .collect();
+    Ok(impact)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ProblemReport, Requirement};
+    use tempfile::TempDir;
+
+    const NAV_V1: &str = "\
+/* Implements REQ-NAV-001 */
+int nav_update(int x)
+{
+    return x + 1;
+}
+
+/* Implements REQ-NAV-002 */
+int nav_clamp(int x)
+{
+    return x > 360 ? 360 : x;
+}
+
+int nav_helper(void)
+{
+    return 0;
+}
+";
+
+    const TEST_NAV: &str = "\
+// TEST: REQ-NAV-001
+void test_nav_update(void) {}
+
+// TEST: REQ-NAV-002
+void test_nav_clamp(void) {}
+";
+
+    fn commit_all(repo: &Repository, message: &str) {
+        for path in ["README", "src/nav.c", "tests/test_nav.c"] {
+            repo.stage(Path::new(path)).unwrap();
+        }
+        repo.commit(message).unwrap();
+    }
+
+    #[test]
+    fn test_change_impact() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::create_dir_all(root.join("tests")).unwrap();
+        std::fs::write(root.join("src/nav.c"), NAV_V1).unwrap();
+        std::fs::write(root.join("tests/test_nav.c"), TEST_NAV).unwrap();
+        std::fs::write(root.join("README"), "nav\n").unwrap();
+        let git = git2::Repository::init(root).unwrap();
+        let mut config = git.config().unwrap();
+        config.set_str("user.name", "Test").unwrap();
+        config.set_str("user.email", "test@test.com").unwrap();
+        let mut index = git.index().unwrap();
+        index
+            .add_all(["*"], git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        index.write().unwrap();
+        let tree = git.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = git.signature().unwrap();
+        git.commit(Some("HEAD"), &sig, &sig, "v1", &tree, &[])
+            .unwrap();
+        let repo = Repository::open(root).unwrap();
+
+        let nav_v2 = NAV_V1
+            .replace("return x + 1;", "return x + 2;")
+            .replace("return 0;", "return 1;");
+        std::fs::write(root.join("src/nav.c"), nav_v2).unwrap();
+        std::fs::write(root.join("README"), "nav v2\n").unwrap();
+        commit_all(&repo, "v2");
+
+        ProblemReportStore::for_project(root)
+            .create(ProblemReport::new("PR-0001", "Update overshoots", 0).with_file("src/nav.c"))
+            .unwrap();
+
+        let impact =
+            analyze_change_impact(root, &AnnotationSyntax::default(), "HEAD~1", "HEAD").unwrap();
+        assert_eq!(
+            impact.changed_files,
+            [PathBuf::from("README"), PathBuf::from("src/nav.c")]
+        );
+        let names: Vec<&str> = impact
+            .changed_functions
+            .iter()
+            .map(|f| f.name.as_str())
+            .collect();
+        assert_eq!(names, ["nav_update", "nav_helper"]);
+        assert_eq!(impact.requirements.len(), 1);
+        assert_eq!(impact.requirements[0].requirement, "REQ-NAV-001");
+        assert!(impact.requirements[0].implementation_changed);
+        assert!(!impact.requirements[0].annotation_changed);
+        assert_eq!(impact.tests, ["test_nav_update"]);
+        assert_eq!(impact.coverage_files, [PathBuf::from("src/nav.c")]);
+        assert_eq!(impact.untraced_functions[0].name, "nav_helper");
+        assert_eq!(impact.problem_reports, ["PR-0001"]);
+    }
+
+    #[test]
+    fn test_removed_annotation() {
+        let matrix = TraceabilityMatrix::new(vec![Requirement::new("REQ-NAV-002", "Clamp")], []);
+        let diff = FileDiff {
+            old_path: Some(PathBuf::from("src/nav.c")),
+            new_path: Some(PathBuf::from("src/nav.c")),
+            hunks: vec![axiom_git::DiffHunk {
+                old_start: 7,
+                old_lines: 1,
+                new_start: 6,
+                new_lines: 0,
+                lines: vec![axiom_git::DiffLine {
+                    origin: '-',
+                    content: "/* Implements REQ-NAV-002 */\n".to_string(),
+                    old_lineno: Some(7),
+                    new_lineno: None,
+                }],
+            }],
+            is_binary: false,
+        };
+        let impact = change_impact(&matrix, &AnnotationSyntax::default(), &[diff], "a", "b");
+        assert_eq!(impact.requirements.len(), 1);
+        assert!(impact.requirements[0].annotation_changed);
+        assert!(impact.tests.is_empty());
+    }
+}
@@ -0,0 +1,63 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Axiom Compliance
+//!
+//! Certification policy, requirements and traceability.
+
+mod annotation_syntax;
+mod baseline;
+mod configuration_index;
+mod deviations;
+mod ed25519;
+mod gap_analysis;
+mod html_report;
+mod impact;
+mod link_verification;
+mod matrix_store;
+mod modes;
+mod object_trace;
+mod pdf;
+mod problem_reports;
+mod qualification_docs;
+mod reqif;
+mod requirement_coverage;
+mod requirements;
+mod requirements_csv;
+mod sarif;
+mod signing;
+mod snapshot;
+mod status;
+mod system_requirements;
+mod test_results;
+mod test_skeleton;
+mod trace_cache;
+mod traceability;
+
+pub use annotation_syntax::*;
+pub use baseline::*;
+pub use configuration_index::*;
+pub use deviations::*;
+pub use gap_analysis::*;
+pub use html_report::*;
+pub use impact::*;
+pub use link_verification::*;
+pub use matrix_store::*;
+pub use modes::*;
+pub use object_trace::*;
+pub use pdf::*;
+pub use problem_reports::*;
+pub use qualification_docs::*;
+pub use reqif::*;
+pub use requirement_coverage::*;
+pub use requirements::*;
+pub use requirements_csv::*;
+pub use sarif::*;
+pub use signing::*;
+pub use snapshot::*;
+pub use status::*;
+pub use system_requirements::*;
+pub use test_results::*;
+pub use test_skeleton::*;
+pub use trace_cache::*;
+pub use traceability::*;
@@ -0,0 +1,313 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Traceability link re-verification.
+//!
+//! A saved matrix records each link by file and line, which go stale as soon
+//! as the source is edited. Verification rescans the linked files and finds
+//! each link again: still in place, moved to another line, changed (the
+//! requirement is still annotated in the file, but as another kind of link
+//! or in another function) or deleted.
+
+use crate::{scan_file, AnnotationSyntax, TraceError, TraceLink, TraceabilityMatrix};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// State of a recorded link in the current sources.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LinkStatus {
+    /// The annotation is where the link records it.
+    Valid,
+    /// The same annotation is on another line.
+    Moved,
+    /// The requirement is annotated with another link type or function.
+    Changed,
+    /// The annotation or its file is gone.
+    Deleted,
+}
+
+impl LinkStatus {
+    /// Serialized name.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LinkStatus::Valid => "valid",
+            LinkStatus::Moved => "moved",
+            LinkStatus::Changed => "changed",
+            LinkStatus::Deleted => "deleted",
+        }
+    }
+
+    /// Whether the link no longer supports the evidence it was recorded
+    /// for.
+    pub fn is_broken(&self) -> bool {
+        matches!(self, LinkStatus::Changed | LinkStatus::Deleted)
+    }
+}
+
+/// A recorded link checked against current sources.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LinkVerification {
+    /// The link as recorded.
+    pub link: TraceLink,
+    /// Its state now.
+    pub status: LinkStatus,
+    /// The matching link in current sources, unless deleted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current: Option<TraceLink>,
+}
+
+/// Check every link of a matrix against the files under `project_root`.
+/// Results are in the matrix's link order.
+pub fn verify_links(
+    matrix: &TraceabilityMatrix,
+    project_root: &Path,
+    syntax: &AnnotationSyntax,
+) -> Result<Vec<LinkVerification>, TraceError> {
+    let mut by_file: BTreeMap<&Path, Vec<usize>> = BTreeMap::new();
+    for (index, link) in matrix.links.iter().enumerate() {
+        by_file.entry(&link.file).or_default().push(index);
+    }
+
+    let mut parser = None;
+    let mut results: Vec<Option<LinkVerification>> = vec![None; matrix.links.len()];
+    for (file, indices) in by_file {
+        let path = project_root.join(file);
+        let current = match std::fs::read(&path) {
+            Ok(content) => {
+                let source = String::from_utf8_lossy(&content);
+                scan_file(&mut parser, syntax, &path, file, &source)?
+                    .map(|trace| trace.links)
+                    .unwrap_or_default()
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let recorded: Vec<&TraceLink> = indices.iter().map(|&i| &matrix.links[i]).collect();
+        for (index, verification) in indices.into_iter().zip(match_links(&recorded, current)) {
+            results[index] = Some(verification);
+        }
+    }
+    Ok(results.into_iter().flatten().collect())
+}
+
+/// Pair recorded links with current ones from the same file: exact matches
+/// first, then the nearest line with the same link type and function, then
+/// any remaining annotation of the requirement.
+fn match_links(recorded: &[&TraceLink], current: Vec<TraceLink>) -> Vec<LinkVerification> {
+    let mut available: Vec<Option<TraceLink>> = current.into_iter().map(Some).collect();
+    let mut matched: Vec<Option<(LinkStatus, TraceLink)>> = vec![None; recorded.len()];
+
+    let accepts = |status, candidate: &TraceLink, link: &TraceLink| match status {
+        LinkStatus::Valid => candidate == link,
+        LinkStatus::Moved => {
+            candidate.link_type == link.link_type && candidate.function == link.function
+        }
+        _ => true,
+    };
+    for status in [LinkStatus::Valid, LinkStatus::Moved, LinkStatus::Changed] {
+        for (link, slot) in recorded.iter().zip(matched.iter_mut()) {
+            if slot.is_some() {
+                continue;
+            }
+            let nearest = available
+                .iter()
+                .enumerate()
+                .filter_map(|(i, candidate)| Some((i, candidate.as_ref()?)))
+                .filter(|(_, candidate)| {
+                    candidate.requirement == link.requirement && accepts(status, candidate, link)
+                })
+                .min_by_key(|(_, candidate)| candidate.line.abs_diff(link.line))
+                .map(|(i, _)| i);
+            if let Some(current) = nearest.and_then(|i| available[i].take()) {
+                *slot = Some((status, current));
+            }
+        }
+    }
+
+    recorded
+        .iter()
+        .zip(matched)
+        .map(|(link, matched)| {
+            let (status, current) = match matched {
+                Some((status, current)) => (status, Some(current)),
+                None => (LinkStatus::Deleted, None),
+            };
+            LinkVerification {
+                link: (*link).clone(),
+                status,
+                current,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generate_traceability_matrix, LinkType};
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    const NAV: &str = "\
+// REQ-NAV-001
+void nav_update(void) {}
+
+// REQ-NAV-002
+void nav_reset(void) {}
+
+// TEST: REQ-NAV-003
+void test_nav(void) {}
+";
+
+    #[test]
+    fn test_verify_links() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("nav.c"), NAV).unwrap();
+        std::fs::write(
+            dir.path().join("boot.c"),
+            "// REQ-BOOT-001\nvoid boot(void) {}\n",
+        )
+        .unwrap();
+        let syntax = AnnotationSyntax::default();
+        let matrix = generate_traceability_matrix(dir.path(), &syntax).unwrap();
+
+        let statuses = |verifications: &[LinkVerification]| -> Vec<(String, LinkStatus)> {
+            verifications
+                .iter()
+                .map(|v| (v.link.requirement.clone(), v.status))
+                .collect()
+        };
+        let verifications = verify_links(&matrix, dir.path(), &syntax).unwrap();
+        assert!(verifications.iter().all(|v| v.status == LinkStatus::Valid));
+
+        let edited = format!(
+            "#include \"nav.h\"\n{}",
+            NAV.replace("TEST: REQ-NAV-003", "REQ-NAV-003")
+                .replace("// REQ-NAV-002\n", "")
+        );
+        std::fs::write(dir.path().join("nav.c"), edited).unwrap();
+        std::fs::remove_file(dir.path().join("boot.c")).unwrap();
+
+        let verifications = verify_links(&matrix, dir.path(), &syntax).unwrap();
+        assert_eq!(
+            statuses(&verifications),
+            [
+                ("REQ-BOOT-001".to_string(), LinkStatus::Deleted),
+                ("REQ-NAV-001".to_string(), LinkStatus::Moved),
+                ("REQ-NAV-002".to_string(), LinkStatus::Deleted),
+                ("REQ-NAV-003".to_string(), LinkStatus::Changed),
+            ]
+        );
+        assert_eq!(verifications[1].current.as_ref().unwrap().line, 2);
+        assert!(verifications[3].status.is_broken());
+        assert!(!verifications[1].status.is_broken());
+
+        // A file that is not UTF-8 is still checked
+        std::fs::write(
+            dir.path().join("nav.c"),
+            [&b"/* \xa9 */\n"[..], NAV.as_bytes()].concat(),
+        )
+        .unwrap();
+        let verifications = verify_links(&matrix, dir.path(), &syntax).unwrap();
+        assert_eq!(verifications[1].status, LinkStatus::Moved);
+        assert_eq!(verifications[1].current.as_ref().unwrap().line, 2);
+    }
+
+    fn link(requirement: &str, file: &str, line: u32) -> TraceLink {
+        TraceLink {
+            requirement: requirement.to_string(),
+            file: PathBuf::from(file),
+            line,
+            function: Some("nav_update".to_string()),
+            link_type: LinkType::Implements,
+        }
+    }
+
+    fn matrix(links: Vec<TraceLink>) -> TraceabilityMatrix {
+        TraceabilityMatrix {
+            links,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_dangling_links() {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        let syntax = AnnotationSyntax::default();
+
+        // A file that was never there leaves the link deleted
+        let verifications = verify_links(
+            &matrix(vec![link("REQ-NAV-001", "src/gone.c", 1)]),
+            dir.path(),
+            &syntax,
+        )
+        .unwrap();
+        assert_eq!(verifications[0].status, LinkStatus::Deleted);
+        assert!(verifications[0].current.is_none());
+
+        // A path that cannot be read as a file is an error
+        let result = verify_links(
+            &matrix(vec![link("REQ-NAV-001", "src", 1)]),
+            dir.path(),
+            &syntax,
+        );
+        assert!(matches!(result, Err(TraceError::Io(_))));
+    }
+
+    #[test]
+    fn test_unknown_target() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("nav.c"), NAV).unwrap();
+        let syntax = AnnotationSyntax::default();
+
+        // Another requirement's annotation on the recorded line is no match
+        let verifications = verify_links(
+            &matrix(vec![link("REQ-NAV-999", "nav.c", 1)]),
+            dir.path(),
+            &syntax,
+        )
+        .unwrap();
+        assert_eq!(verifications[0].status, LinkStatus::Deleted);
+        assert!(verifications[0].status.is_broken());
+    }
+
+    #[test]
+    fn test_duplicate_links() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("nav.c"), NAV).unwrap();
+        let syntax = AnnotationSyntax::default();
+        let mut matrix = generate_traceability_matrix(dir.path(), &syntax).unwrap();
+        let first = matrix.links[0].clone();
+        matrix.links.insert(1, first.clone());
+
+        // One annotation satisfies only one of the recorded copies
+        let verifications = verify_links(&matrix, dir.path(), &syntax).unwrap();
+        assert_eq!(verifications.len(), matrix.links.len());
+        assert_eq!(verifications[0].status, LinkStatus::Valid);
+        assert_eq!(verifications[1].status, LinkStatus::Deleted);
+        assert_eq!(verifications[1].link, first);
+
+        // A second annotation of the requirement takes up the copy
+        std::fs::write(
+            dir.path().join("nav.c"),
+            format!("{}\n// REQ-NAV-001\nvoid nav_poll(void) {{}}\n", NAV),
+        )
+        .unwrap();
+        let verifications = verify_links(&matrix, dir.path(), &syntax).unwrap();
+        assert_eq!(verifications[0].status, LinkStatus::Valid);
+        assert_eq!(verifications[1].status, LinkStatus::Changed);
+        assert_eq!(
+            verifications[1]
+                .current
+                .as_ref()
+                .unwrap()
+                .function
+                .as_deref(),
+            Some("nav_poll")
+        );
+    }
+}
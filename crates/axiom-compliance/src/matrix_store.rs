@@ -0,0 +1,201 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Saved traceability matrices.
+//!
+//! A generated matrix is written to `.axiom/traceability-matrix.json` so it
+//! can be committed with the project, diffed across releases and shown at
+//! launch without rescanning. The file carries a schema version; files from
+//! a newer Axiom are rejected rather than misread.
+
+use crate::TraceabilityMatrix;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Current saved matrix schema version.
+pub const MATRIX_SCHEMA_VERSION: u32 = 1;
+
+/// Saved matrix errors.
+#[derive(Debug, Error)]
+pub enum MatrixStoreError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("JSON error in {path}: {source}")]
+    Json {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+
+    #[error("Traceability matrix schema version {0} is newer than supported")]
+    UnsupportedVersion(u32),
+}
+
+/// A matrix as saved, with when it was generated.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedMatrix {
+    /// Schema version of the file.
+    pub version: u32,
+    /// When the matrix was generated (Unix seconds).
+    pub timestamp: u64,
+    /// The matrix.
+    pub matrix: TraceabilityMatrix,
+}
+
+/// Project saved matrix file.
+pub struct MatrixStore {
+    path: PathBuf,
+}
+
+impl MatrixStore {
+    /// Create a store backed by the given file.
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Create the store for a project root.
+    pub fn for_project(project_root: &Path) -> Self {
+        Self::new(project_root.join(".axiom").join("traceability-matrix.json"))
+    }
+
+    /// Path of the backing file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Load the saved matrix, if any.
+    pub fn load(&self) -> Result<Option<SavedMatrix>, MatrixStoreError> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&self.path)?;
+        let json_error = |source| MatrixStoreError::Json {
+            path: self.path.clone(),
+            source,
+        };
+
+        // Check the version before the shape, which newer schemas may change
+        #[derive(Deserialize)]
+        struct Version {
+            version: u32,
+        }
+        let Version { version } = serde_json::from_str(&content).map_err(json_error)?;
+        if version > MATRIX_SCHEMA_VERSION {
+            return Err(MatrixStoreError::UnsupportedVersion(version));
+        }
+        serde_json::from_str(&content).map(Some).map_err(json_error)
+    }
+
+    /// Save a matrix generated at `timestamp`, replacing any saved one.
+    pub fn save(
+        &self,
+        matrix: &TraceabilityMatrix,
+        timestamp: u64,
+    ) -> Result<(), MatrixStoreError> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let saved = SavedMatrix {
+            version: MATRIX_SCHEMA_VERSION,
+            timestamp,
+            matrix: matrix.clone(),
+        };
+        let json =
+            serde_json::to_string_pretty(&saved).map_err(|source| MatrixStoreError::Json {
+                path: self.path.clone(),
+                source,
+            })?;
+        fs::write(&self.path, json + "\n")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generate_traceability_matrix, AnnotationSyntax, Requirement, RequirementStore};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(
+            dir.path().join("nav.c"),
+            "// REQ-NAV-001\nvoid nav(void) {}\n",
+        )
+        .unwrap();
+        RequirementStore::for_project(dir.path())
+            .create(Requirement::new("REQ-NAV-001", "Update navigation"))
+            .unwrap();
+        let matrix =
+            generate_traceability_matrix(dir.path(), &AnnotationSyntax::default()).unwrap();
+
+        let store = MatrixStore::for_project(dir.path());
+        assert!(store.load().unwrap().is_none());
+        store.save(&matrix, 100).unwrap();
+        let saved = store.load().unwrap().unwrap();
+        assert_eq!(saved.version, MATRIX_SCHEMA_VERSION);
+        assert_eq!(saved.timestamp, 100);
+        assert_eq!(saved.matrix, matrix);
+
+        std::fs::write(store.path(), r#"{"version": 99, "rows": []}"#).unwrap();
+        assert!(matches!(
+            store.load(),
+            Err(MatrixStoreError::UnsupportedVersion(99))
+        ));
+        std::fs::write(store.path(), r#"{"version": 1}"#).unwrap();
+        assert!(matches!(store.load(), Err(MatrixStoreError::Json { .. })));
+    }
+
+    fn matrix(ids: &[&str]) -> TraceabilityMatrix {
+        let requirements = ids.iter().map(|id| Requirement::new(*id, "x")).collect();
+        TraceabilityMatrix::new(requirements, [])
+    }
+
+    #[test]
+    fn test_missing_store() {
+        let dir = TempDir::new().unwrap();
+        let store = MatrixStore::new(dir.path().join("a").join("b").join("matrix.json"));
+        assert!(store.load().unwrap().is_none());
+
+        // Saving creates the missing directories
+        store.save(&matrix(&["REQ-1"]), 1).unwrap();
+        assert!(store.load().unwrap().is_some());
+    }
+
+    #[test]
+    fn test_corrupt_store() {
+        let dir = TempDir::new().unwrap();
+        let store = MatrixStore::for_project(dir.path());
+        store.save(&matrix(&["REQ-1", "REQ-2"]), 1).unwrap();
+
+        let content = std::fs::read_to_string(store.path()).unwrap();
+        std::fs::write(store.path(), &content[..content.len() / 2]).unwrap();
+        match store.load() {
+            Err(MatrixStoreError::Json { path, .. }) => assert_eq!(path, store.path()),
+            other => panic!("expected a JSON error, got {:?}", other),
+        }
+
+        std::fs::write(store.path(), "").unwrap();
+        assert!(matches!(store.load(), Err(MatrixStoreError::Json { .. })));
+        std::fs::write(store.path(), "\u{0}garbage").unwrap();
+        assert!(matches!(store.load(), Err(MatrixStoreError::Json { .. })));
+    }
+
+    #[test]
+    fn test_save_overwrites() {
+        let dir = TempDir::new().unwrap();
+        let store = MatrixStore::for_project(dir.path());
+        store
+            .save(&matrix(&["REQ-1", "REQ-2", "REQ-3"]), 1)
+            .unwrap();
+        let smaller = matrix(&["REQ-4"]);
+        store.save(&smaller, 2).unwrap();
+
+        let saved = store.load().unwrap().unwrap();
+        assert_eq!(saved.timestamp, 2);
+        assert_eq!(saved.matrix, smaller);
+    }
+}
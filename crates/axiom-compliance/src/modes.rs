@@ -0,0 +1,588 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Compliance modes and DAL policy.
+//!
+//! A project declares its Design Assurance Level and the standards it
+//! follows in `.axiom/compliance.toml`:
+//!
+//! ```toml
+//! dal = "B"
+//! modes = ["do-178c", "do-330"]
+//! test_dir = "tests"
+//! test_framework = "unity"
+//! ```
+//!
+//! [`DalPolicy`] turns that declaration into the concrete objectives the
+//! rest of the system enforces: which structural coverage criterion
+//! applies and at what threshold, how deep traceability must go, and the
+//! qualification level required of development tools.
+
+use crate::TestFramework;
+use axiom_toolchain::{CoverageCounter, CoverageSummary};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Compliance configuration errors.
+#[derive(Debug, Error)]
+pub enum ComplianceError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("TOML parse error in {path}: {source}")]
+    Toml {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+
+    #[error("Unknown design assurance level: {0}")]
+    UnknownLevel(String),
+}
+
+/// Certification standard a project follows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum ComplianceMode {
+    /// DO-178C software considerations.
+    #[serde(rename = "do-178c")]
+    Do178c,
+    /// DO-330 tool qualification.
+    #[serde(rename = "do-330")]
+    Do330,
+    /// ARP4754A system development.
+    #[serde(rename = "arp4754a")]
+    Arp4754a,
+}
+
+impl ComplianceMode {
+    /// Document name.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ComplianceMode::Do178c => "DO-178C",
+            ComplianceMode::Do330 => "DO-330",
+            ComplianceMode::Arp4754a => "ARP4754A",
+        }
+    }
+}
+
+impl fmt::Display for ComplianceMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Design Assurance Level, from A (catastrophic failure condition) to E
+/// (no safety effect).
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(try_from = "String", into = "String")]
+pub enum DesignAssuranceLevel {
+    A,
+    B,
+    C,
+    D,
+    #[default]
+    E,
+}
+
+impl DesignAssuranceLevel {
+    /// All levels, most stringent first.
+    pub const ALL: [DesignAssuranceLevel; 5] = [
+        DesignAssuranceLevel::A,
+        DesignAssuranceLevel::B,
+        DesignAssuranceLevel::C,
+        DesignAssuranceLevel::D,
+        DesignAssuranceLevel::E,
+    ];
+
+    /// Failure condition the level is assigned for.
+    pub fn failure_condition(&self) -> &'static str {
+        match self {
+            DesignAssuranceLevel::A => "catastrophic",
+            DesignAssuranceLevel::B => "hazardous",
+            DesignAssuranceLevel::C => "major",
+            DesignAssuranceLevel::D => "minor",
+            DesignAssuranceLevel::E => "no safety effect",
+        }
+    }
+
+    /// Whether this level is at least as stringent as `other`.
+    pub fn at_least(&self, other: DesignAssuranceLevel) -> bool {
+        *self <= other
+    }
+}
+
+impl fmt::Display for DesignAssuranceLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let level = match self {
+            DesignAssuranceLevel::A => "A",
+            DesignAssuranceLevel::B => "B",
+            DesignAssuranceLevel::C => "C",
+            DesignAssuranceLevel::D => "D",
+            DesignAssuranceLevel::E => "E",
+        };
+        f.write_str(level)
+    }
+}
+
+impl FromStr for DesignAssuranceLevel {
+    type Err = ComplianceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let level = s.trim();
+        let level = level
+            .strip_prefix("DAL")
+            .or_else(|| level.strip_prefix("dal"))
+            .unwrap_or(level)
+            .trim_start_matches(['-', ' ']);
+        match level.to_ascii_uppercase().as_str() {
+            "A" => Ok(DesignAssuranceLevel::A),
+            "B" => Ok(DesignAssuranceLevel::B),
+            "C" => Ok(DesignAssuranceLevel::C),
+            "D" => Ok(DesignAssuranceLevel::D),
+            "E" => Ok(DesignAssuranceLevel::E),
+            _ => Err(ComplianceError::UnknownLevel(s.to_string())),
+        }
+    }
+}
+
+impl TryFrom<String> for DesignAssuranceLevel {
+    type Error = ComplianceError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<DesignAssuranceLevel> for String {
+    fn from(level: DesignAssuranceLevel) -> Self {
+        level.to_string()
+    }
+}
+
+/// Structural coverage criterion, weakest first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CoverageCriterion {
+    /// Every statement executed.
+    Statement,
+    /// Every decision taken both ways.
+    Decision,
+    /// Modified condition/decision coverage.
+    Mcdc,
+}
+
+impl CoverageCriterion {
+    /// This criterion and the weaker ones it subsumes.
+    pub fn implied(&self) -> Vec<CoverageCriterion> {
+        [
+            CoverageCriterion::Statement,
+            CoverageCriterion::Decision,
+            CoverageCriterion::Mcdc,
+        ]
+        .into_iter()
+        .filter(|c| c <= self)
+        .collect()
+    }
+
+    /// The gcov measure for this criterion: lines for statement coverage,
+    /// branch outcomes for decision coverage. gcov doesn't measure MC/DC,
+    /// which needs separate evidence.
+    pub fn measure(&self, summary: &CoverageSummary) -> Option<CoverageCounter> {
+        match self {
+            CoverageCriterion::Statement => Some(summary.lines),
+            CoverageCriterion::Decision => Some(summary.branches),
+            CoverageCriterion::Mcdc => None,
+        }
+    }
+}
+
+impl fmt::Display for CoverageCriterion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            CoverageCriterion::Statement => "statement",
+            CoverageCriterion::Decision => "decision",
+            CoverageCriterion::Mcdc => "MC/DC",
+        };
+        f.write_str(name)
+    }
+}
+
+/// DO-330 tool qualification level, most rigorous first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum ToolQualificationLevel {
+    #[serde(rename = "TQL-1")]
+    Tql1,
+    #[serde(rename = "TQL-2")]
+    Tql2,
+    #[serde(rename = "TQL-3")]
+    Tql3,
+    #[serde(rename = "TQL-4")]
+    Tql4,
+    #[serde(rename = "TQL-5")]
+    Tql5,
+}
+
+impl ToolQualificationLevel {
+    /// Level for a tool whose output is part of the airborne software
+    /// (criteria 1).
+    pub fn development(dal: DesignAssuranceLevel) -> Option<Self> {
+        match dal {
+            DesignAssuranceLevel::A => Some(ToolQualificationLevel::Tql1),
+            DesignAssuranceLevel::B => Some(ToolQualificationLevel::Tql2),
+            DesignAssuranceLevel::C => Some(ToolQualificationLevel::Tql3),
+            DesignAssuranceLevel::D => Some(ToolQualificationLevel::Tql4),
+            DesignAssuranceLevel::E => None,
+        }
+    }
+
+    /// Level for a tool that automates verification (criteria 3).
+    pub fn verification(dal: DesignAssuranceLevel) -> Option<Self> {
+        (dal != DesignAssuranceLevel::E).then_some(ToolQualificationLevel::Tql5)
+    }
+}
+
+impl fmt::Display for ToolQualificationLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let level = match self {
+            ToolQualificationLevel::Tql1 => "TQL-1",
+            ToolQualificationLevel::Tql2 => "TQL-2",
+            ToolQualificationLevel::Tql3 => "TQL-3",
+            ToolQualificationLevel::Tql4 => "TQL-4",
+            ToolQualificationLevel::Tql5 => "TQL-5",
+        };
+        f.write_str(level)
+    }
+}
+
+/// A project's declared assurance level and standards.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ComplianceConfig {
+    /// Design Assurance Level of the software.
+    #[serde(default)]
+    pub dal: DesignAssuranceLevel,
+    /// Standards the project follows.
+    #[serde(default)]
+    pub modes: BTreeSet<ComplianceMode>,
+    /// Directory for generated test skeletons, relative to the project root.
+    #[serde(default = "default_test_dir")]
+    pub test_dir: PathBuf,
+    /// Unit test framework generated tests are written for.
+    #[serde(default)]
+    pub test_framework: TestFramework,
+}
+
+fn default_test_dir() -> PathBuf {
+    PathBuf::from("tests")
+}
+
+impl Default for ComplianceConfig {
+    fn default() -> Self {
+        Self {
+            dal: DesignAssuranceLevel::default(),
+            modes: BTreeSet::new(),
+            test_dir: default_test_dir(),
+            test_framework: TestFramework::default(),
+        }
+    }
+}
+
+impl ComplianceConfig {
+    /// Create a configuration for a level with no modes enabled.
+    pub fn new(dal: DesignAssuranceLevel) -> Self {
+        Self {
+            dal,
+            ..Default::default()
+        }
+    }
+
+    /// Enable a mode.
+    pub fn with_mode(mut self, mode: ComplianceMode) -> Self {
+        self.modes.insert(mode);
+        self
+    }
+
+    /// Whether a mode is enabled.
+    pub fn is_enabled(&self, mode: ComplianceMode) -> bool {
+        self.modes.contains(&mode)
+    }
+
+    /// Objectives that follow from the level and modes.
+    pub fn policy(&self) -> DalPolicy {
+        DalPolicy::new(self.dal, &self.modes)
+    }
+}
+
+/// Structural coverage achieved below the policy threshold.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CoverageShortfall {
+    /// Criterion not met.
+    pub criterion: CoverageCriterion,
+    /// Required percentage.
+    pub required: f64,
+    /// Achieved percentage.
+    pub achieved: f64,
+}
+
+/// Concrete objectives for a Design Assurance Level and set of modes.
+///
+/// DO-178C objectives apply only with [`ComplianceMode::Do178c`], tool
+/// qualification only with [`ComplianceMode::Do330`] and system allocation
+/// only with [`ComplianceMode::Arp4754a`]. DAL E has no objectives.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DalPolicy {
+    /// Design Assurance Level.
+    pub dal: DesignAssuranceLevel,
+    /// Enabled modes.
+    pub modes: BTreeSet<ComplianceMode>,
+    /// Strongest structural coverage criterion required.
+    pub coverage_criterion: Option<CoverageCriterion>,
+    /// Percentage of the criterion that must be achieved.
+    pub coverage_threshold: f64,
+    /// Source code must trace to low-level requirements.
+    pub trace_low_level: bool,
+    /// Tests must trace to high-level requirements.
+    pub trace_high_level: bool,
+    /// Object code must trace to source code.
+    pub trace_object_code: bool,
+    /// Data and control coupling must be verified.
+    pub coupling_analysis: bool,
+    /// Verification must be performed independently of development.
+    pub independent_verification: bool,
+    /// Qualification level for development tools, such as the compiler.
+    pub development_tool_level: Option<ToolQualificationLevel>,
+    /// Qualification level for verification tools, such as coverage
+    /// analysis.
+    pub verification_tool_level: Option<ToolQualificationLevel>,
+    /// Software requirements must be allocated from system requirements.
+    pub system_allocation: bool,
+}
+
+impl DalPolicy {
+    /// Derive the objectives for a level and enabled modes.
+    pub fn new(dal: DesignAssuranceLevel, modes: &BTreeSet<ComplianceMode>) -> Self {
+        use DesignAssuranceLevel::{A, B, C, D};
+
+        let do178c = modes.contains(&ComplianceMode::Do178c);
+        let do330 = modes.contains(&ComplianceMode::Do330);
+        let arp4754a = modes.contains(&ComplianceMode::Arp4754a);
+
+        let coverage_criterion = match dal {
+            A if do178c => Some(CoverageCriterion::Mcdc),
+            B if do178c => Some(CoverageCriterion::Decision),
+            C if do178c => Some(CoverageCriterion::Statement),
+            _ => None,
+        };
+
+        Self {
+            dal,
+            modes: modes.clone(),
+            coverage_criterion,
+            coverage_threshold: if coverage_criterion.is_some() {
+                100.0
+            } else {
+                0.0
+            },
+            trace_low_level: do178c && dal.at_least(C),
+            trace_high_level: do178c && dal.at_least(D),
+            trace_object_code: do178c && dal == A,
+            coupling_analysis: do178c && dal.at_least(C),
+            independent_verification: do178c && dal.at_least(B),
+            development_tool_level: if do330 {
+                ToolQualificationLevel::development(dal)
+            } else {
+                None
+            },
+            verification_tool_level: if do330 {
+                ToolQualificationLevel::verification(dal)
+            } else {
+                None
+            },
+            system_allocation: arp4754a && dal.at_least(D),
+        }
+    }
+
+    /// Override the coverage threshold, e.g. while coverage is being
+    /// brought up.
+    pub fn with_coverage_threshold(mut self, percent: f64) -> Self {
+        self.coverage_threshold = percent;
+        self
+    }
+
+    /// Criteria required, including the weaker ones the strongest implies.
+    pub fn required_criteria(&self) -> Vec<CoverageCriterion> {
+        self.coverage_criterion
+            .map(|c| c.implied())
+            .unwrap_or_default()
+    }
+
+    /// Measurable criteria whose coverage falls below the threshold.
+    ///
+    /// MC/DC isn't measured by gcov and is never reported here.
+    pub fn coverage_shortfalls(&self, summary: &CoverageSummary) -> Vec<CoverageShortfall> {
+        self.required_criteria()
+            .into_iter()
+            .filter_map(|criterion| {
+                let achieved = criterion.measure(summary)?.percent();
+                (achieved < self.coverage_threshold).then_some(CoverageShortfall {
+                    criterion,
+                    required: self.coverage_threshold,
+                    achieved,
+                })
+            })
+            .collect()
+    }
+}
+
+impl Default for DalPolicy {
+    fn default() -> Self {
+        ComplianceConfig::default().policy()
+    }
+}
+
+/// Path of a project's compliance configuration.
+pub fn compliance_config_path(project_root: &Path) -> PathBuf {
+    project_root.join(".axiom").join("compliance.toml")
+}
+
+/// Load a project's compliance configuration; a missing file gives DAL E
+/// with no modes.
+pub fn load_compliance_config(project_root: &Path) -> Result<ComplianceConfig, ComplianceError> {
+    let path = compliance_config_path(project_root);
+    if !path.is_file() {
+        return Ok(ComplianceConfig::default());
+    }
+    let content = std::fs::read_to_string(&path)?;
+    toml::from_str(&content).map_err(|source| ComplianceError::Toml { path, source })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn counter(covered: usize, total: usize) -> CoverageCounter {
+        CoverageCounter {
+            covered,
+            total,
+            justified: 0,
+        }
+    }
+
+    #[test]
+    fn test_parse_level() {
+        assert_eq!(
+            "DAL-A".parse::<DesignAssuranceLevel>().unwrap(),
+            DesignAssuranceLevel::A
+        );
+        assert_eq!(
+            "c".parse::<DesignAssuranceLevel>().unwrap(),
+            DesignAssuranceLevel::C
+        );
+        assert!("F".parse::<DesignAssuranceLevel>().is_err());
+        assert!(DesignAssuranceLevel::A.at_least(DesignAssuranceLevel::C));
+        assert!(!DesignAssuranceLevel::D.at_least(DesignAssuranceLevel::C));
+    }
+
+    #[test]
+    fn test_policy_by_level() {
+        let config = ComplianceConfig::new(DesignAssuranceLevel::A)
+            .with_mode(ComplianceMode::Do178c)
+            .with_mode(ComplianceMode::Do330);
+        let a = config.policy();
+        assert_eq!(a.coverage_criterion, Some(CoverageCriterion::Mcdc));
+        assert!(a.trace_object_code);
+        assert!(a.independent_verification);
+        assert_eq!(a.development_tool_level, Some(ToolQualificationLevel::Tql1));
+        assert_eq!(
+            a.verification_tool_level,
+            Some(ToolQualificationLevel::Tql5)
+        );
+        assert!(!a.system_allocation);
+
+        let b = ComplianceConfig {
+            dal: DesignAssuranceLevel::B,
+            ..config.clone()
+        }
+        .policy();
+        assert_eq!(b.coverage_criterion, Some(CoverageCriterion::Decision));
+        assert!(!b.trace_object_code);
+        assert_eq!(b.development_tool_level, Some(ToolQualificationLevel::Tql2));
+
+        let d = ComplianceConfig {
+            dal: DesignAssuranceLevel::D,
+            ..config
+        }
+        .policy();
+        assert_eq!(d.coverage_criterion, None);
+        assert!(d.trace_high_level);
+        assert!(!d.trace_low_level);
+
+        // Without DO-178C, the level alone imposes nothing
+        let bare = ComplianceConfig::new(DesignAssuranceLevel::A).policy();
+        assert_eq!(bare.coverage_criterion, None);
+        assert!(!bare.trace_high_level);
+        assert_eq!(bare.development_tool_level, None);
+    }
+
+    #[test]
+    fn test_coverage_shortfalls() {
+        let summary = CoverageSummary {
+            lines: counter(10, 10),
+            branches: counter(3, 4),
+            ..Default::default()
+        };
+
+        let policy = ComplianceConfig::new(DesignAssuranceLevel::A)
+            .with_mode(ComplianceMode::Do178c)
+            .policy();
+        assert_eq!(
+            policy.required_criteria(),
+            vec![
+                CoverageCriterion::Statement,
+                CoverageCriterion::Decision,
+                CoverageCriterion::Mcdc
+            ]
+        );
+        let shortfalls = policy.coverage_shortfalls(&summary);
+        assert_eq!(shortfalls.len(), 1);
+        assert_eq!(shortfalls[0].criterion, CoverageCriterion::Decision);
+        assert_eq!(shortfalls[0].achieved, 75.0);
+
+        let relaxed = policy.with_coverage_threshold(70.0);
+        assert!(relaxed.coverage_shortfalls(&summary).is_empty());
+
+        let c = ComplianceConfig::new(DesignAssuranceLevel::C)
+            .with_mode(ComplianceMode::Do178c)
+            .policy();
+        assert!(c.coverage_shortfalls(&summary).is_empty());
+    }
+
+    #[test]
+    fn test_load_config() {
+        let dir = TempDir::new().unwrap();
+        assert_eq!(
+            load_compliance_config(dir.path()).unwrap(),
+            ComplianceConfig::default()
+        );
+
+        let path = compliance_config_path(dir.path());
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, "dal = \"B\"\nmodes = [\"do-178c\", \"arp4754a\"]\n").unwrap();
+        let config = load_compliance_config(dir.path()).unwrap();
+        assert_eq!(config.dal, DesignAssuranceLevel::B);
+        assert!(config.is_enabled(ComplianceMode::Arp4754a));
+        assert!(!config.is_enabled(ComplianceMode::Do330));
+        assert!(config.policy().system_allocation);
+
+        std::fs::write(&path, "dal = \"Z\"\n").unwrap();
+        assert!(matches!(
+            load_compliance_config(dir.path()),
+            Err(ComplianceError::Toml { .. })
+        ));
+    }
+}
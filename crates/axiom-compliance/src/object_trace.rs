@@ -0,0 +1,295 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Source-to-object-code traceability.
+//!
+//! DAL A requires showing that the object code corresponds to the source
+//! (DO-178C 6.4.4.2b). Requirement-linked functions are matched against
+//! the function symbols of the linked ELF or linker map: functions with no
+//! symbol were inlined or removed by the compiler or linker, and symbols
+//! with no source function were introduced by the compiler or pulled in
+//! from libraries. Both need a review record.
+
+use crate::{SourceFunction, TraceabilityMatrix};
+use axiom_toolchain::{read_map_file, ElfError, ElfFile, MemoryMap, SymbolKind};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use thiserror::Error;
+
+/// Object trace errors.
+#[derive(Debug, Error)]
+pub enum ObjectTraceError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("ELF error: {0}")]
+    Elf(#[from] ElfError),
+}
+
+/// A function symbol in the linked image.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ObjectSymbol {
+    /// Symbol name.
+    pub name: String,
+    /// Start address.
+    pub address: u64,
+    /// Size in bytes, 0 if unknown.
+    pub size: u64,
+}
+
+/// Whether a source function made it into the object code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ObjectPresence {
+    /// Present under its own name.
+    Present,
+    /// Present only as compiler-specialized copies (`.constprop`, `.isra`,
+    /// `.part`, ...).
+    Specialized,
+    /// No symbol: inlined everywhere or removed as unused.
+    OptimizedAway,
+}
+
+/// Object code correlation for one requirement-linked function.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FunctionObjectTrace {
+    /// The source function.
+    pub function: SourceFunction,
+    /// Requirements the function is linked to.
+    pub requirements: Vec<String>,
+    /// Whether the function appears in the object code.
+    pub presence: ObjectPresence,
+    /// Matching symbols.
+    pub symbols: Vec<ObjectSymbol>,
+}
+
+/// Source-to-object correlation for a project.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ObjectTrace {
+    /// Requirement-linked functions, in matrix order.
+    pub functions: Vec<FunctionObjectTrace>,
+    /// Function symbols with no source function in the project.
+    pub unmatched_symbols: Vec<ObjectSymbol>,
+}
+
+impl ObjectTrace {
+    /// Linked functions that have no symbol in the object code.
+    pub fn optimized_away(&self) -> Vec<&FunctionObjectTrace> {
+        self.functions
+            .iter()
+            .filter(|f| f.presence == ObjectPresence::OptimizedAway)
+            .collect()
+    }
+}
+
+/// Defined function symbols of an ELF file, with the Thumb bit cleared.
+pub fn elf_function_symbols(elf: &ElfFile) -> Vec<ObjectSymbol> {
+    let mut symbols: Vec<ObjectSymbol> = elf
+        .symbols
+        .iter()
+        .filter(|s| s.kind == SymbolKind::Func && !s.is_undefined() && !s.name.is_empty())
+        .map(|s| ObjectSymbol {
+            name: s.name.clone(),
+            address: s.value & !1,
+            size: s.size,
+        })
+        .collect();
+    symbols.sort_by(|a, b| a.address.cmp(&b.address).then(a.name.cmp(&b.name)));
+    symbols.dedup();
+    symbols
+}
+
+/// Function symbols of a linker map: symbols in `.text` input sections,
+/// or the section's own name under `-ffunction-sections` when the map
+/// lists no symbol for it (static functions).
+pub fn map_function_symbols(map: &MemoryMap) -> Vec<ObjectSymbol> {
+    let mut symbols = Vec::new();
+    for input in map
+        .sections
+        .iter()
+        .filter(|s| s.is_allocated())
+        .flat_map(|s| &s.inputs)
+    {
+        let function_section = input.name.strip_prefix(".text.");
+        if input.name != ".text" && function_section.is_none() {
+            continue;
+        }
+        if input.symbols.is_empty() {
+            if let Some(name) = function_section.filter(|_| input.size > 0) {
+                symbols.push(ObjectSymbol {
+                    name: name.to_string(),
+                    address: input.address,
+                    size: input.size,
+                });
+            }
+            continue;
+        }
+        symbols.extend(input.symbols.iter().map(|s| ObjectSymbol {
+            name: s.name.clone(),
+            address: s.address,
+            size: s.size,
+        }));
+    }
+    symbols.sort_by(|a, b| a.address.cmp(&b.address).then(a.name.cmp(&b.name)));
+    symbols
+}
+
+/// Read function symbols from a linker map (`.map`) or an ELF image.
+pub fn read_object_symbols(path: &Path) -> Result<Vec<ObjectSymbol>, ObjectTraceError> {
+    if path.extension().is_some_and(|e| e == "map") {
+        Ok(map_function_symbols(&read_map_file(path)?))
+    } else {
+        Ok(elf_function_symbols(&ElfFile::read(path)?))
+    }
+}
+
+/// Name of the source function a symbol was compiled from, stripping
+/// GCC clone suffixes such as `foo.constprop.0`.
+fn base_name(symbol: &str) -> &str {
+    symbol.split('.').next().unwrap_or(symbol)
+}
+
+/// Correlate the requirement-linked functions of a matrix with object
+/// code symbols.
+pub fn correlate_object_code(matrix: &TraceabilityMatrix, symbols: &[ObjectSymbol]) -> ObjectTrace {
+    let mut by_name: BTreeMap<&str, Vec<&ObjectSymbol>> = BTreeMap::new();
+    for symbol in symbols {
+        by_name
+            .entry(base_name(&symbol.name))
+            .or_default()
+            .push(symbol);
+    }
+
+    let mut functions = Vec::new();
+    for function in &matrix.functions {
+        let requirements: BTreeSet<&str> = matrix
+            .links
+            .iter()
+            .filter(|l| l.file == function.file && l.function.as_ref() == Some(&function.name))
+            .map(|l| l.requirement.as_str())
+            .collect();
+        if requirements.is_empty() {
+            continue;
+        }
+        let matches: Vec<ObjectSymbol> = by_name
+            .get(function.name.as_str())
+            .map(|s| s.iter().map(|&s| s.clone()).collect())
+            .unwrap_or_default();
+        let presence = if matches.iter().any(|s| s.name == function.name) {
+            ObjectPresence::Present
+        } else if matches.is_empty() {
+            ObjectPresence::OptimizedAway
+        } else {
+            ObjectPresence::Specialized
+        };
+        functions.push(FunctionObjectTrace {
+            function: function.clone(),
+            requirements: requirements.into_iter().map(str::to_string).collect(),
+            presence,
+            symbols: matches,
+        });
+    }
+
+    let source: BTreeSet<&str> = matrix.functions.iter().map(|f| f.name.as_str()).collect();
+    let unmatched_symbols = symbols
+        .iter()
+        .filter(|s| !source.contains(base_name(&s.name)))
+        .cloned()
+        .collect();
+
+    ObjectTrace {
+        functions,
+        unmatched_symbols,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FileTrace, LinkType, TraceLink};
+    use axiom_toolchain::parse_map_file;
+    use std::path::PathBuf;
+
+    const MAP: &str = "\
+Memory Configuration
+
+Name             Origin             Length             Attributes
+FLASH            0x0000000008000000 0x0000000000100000 xr
+
+Linker script and memory map
+
+.text           0x0000000008000000       0x90
+ .text.limit    0x0000000008000000       0x20 build/a.o
+                0x0000000008000000                limit
+ .text.scale.constprop.0
+                0x0000000008000020       0x30 build/a.o
+ .text          0x0000000008000050       0x40 libgcc.a(_udivsi3.o)
+                0x0000000008000050                __aeabi_uidiv
+";
+
+    fn function(name: &str, line: u32) -> SourceFunction {
+        SourceFunction {
+            name: name.to_string(),
+            file: PathBuf::from("src/a.c"),
+            line,
+            end_line: line + 3,
+        }
+    }
+
+    fn link(requirement: &str, name: &str, line: u32) -> TraceLink {
+        TraceLink {
+            requirement: requirement.to_string(),
+            file: PathBuf::from("src/a.c"),
+            line,
+            function: Some(name.to_string()),
+            link_type: LinkType::Implements,
+        }
+    }
+
+    #[test]
+    fn test_map_symbols() {
+        let symbols = map_function_symbols(&parse_map_file(MAP));
+        let names: Vec<&str> = symbols.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["limit", "scale.constprop.0", "__aeabi_uidiv"]);
+        assert_eq!(symbols[1].address, 0x0800_0020);
+        assert_eq!(symbols[1].size, 0x30);
+    }
+
+    #[test]
+    fn test_correlate() {
+        let trace = FileTrace {
+            links: vec![
+                link("REQ-1", "limit", 1),
+                link("REQ-2", "scale", 10),
+                link("REQ-3", "clamp", 20),
+            ],
+            functions: vec![
+                function("limit", 2),
+                function("scale", 11),
+                function("clamp", 21),
+                function("helper", 30),
+            ],
+        };
+        let matrix = TraceabilityMatrix::new(Vec::new(), [trace]);
+        let result = correlate_object_code(&matrix, &map_function_symbols(&parse_map_file(MAP)));
+
+        let presence: Vec<(&str, ObjectPresence)> = result
+            .functions
+            .iter()
+            .map(|f| (f.function.name.as_str(), f.presence))
+            .collect();
+        assert_eq!(
+            presence,
+            [
+                ("limit", ObjectPresence::Present),
+                ("scale", ObjectPresence::Specialized),
+                ("clamp", ObjectPresence::OptimizedAway),
+            ]
+        );
+        assert_eq!(result.functions[0].requirements, ["REQ-1"]);
+        assert_eq!(result.optimized_away().len(), 1);
+        assert_eq!(result.unmatched_symbols.len(), 1);
+        assert_eq!(result.unmatched_symbols[0].name, "__aeabi_uidiv");
+    }
+}
@@ -0,0 +1,610 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! PDF export of compliance artifacts.
+//!
+//! A minimal PDF 1.4 writer: text pages set in the standard Helvetica and
+//! Courier fonts, which every reader provides, so nothing is embedded or
+//! fetched. Each page carries a header with the document title and a
+//! footer stamping the project, baseline, generation time, tool version
+//! and page number, so a page separated from its document still says
+//! where it came from.
+
+use crate::{DalPolicy, DeviationRecord, DeviationStatus, LinkType, TraceabilityMatrix};
+use axiom_core::time::format_timestamp;
+use axiom_toolchain::{CoverageCounter, CoverageReport};
+use std::fmt::Write as _;
+
+/// A4 page width in points.
+const PAGE_WIDTH: f32 = 595.0;
+/// A4 page height in points.
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 50.0;
+const BODY_SIZE: f32 = 9.0;
+const LINE_HEIGHT: f32 = 11.0;
+const HEADING_SIZE: f32 = 12.0;
+/// Body line capacity of one page.
+const LINES_PER_PAGE: usize = ((PAGE_HEIGHT - 2.0 * MARGIN - 30.0) / LINE_HEIGHT) as usize;
+/// Courier glyphs are 0.6 em wide.
+const CHARS_PER_LINE: usize = ((PAGE_WIDTH - 2.0 * MARGIN) / (BODY_SIZE * 0.6)) as usize;
+
+/// Provenance stamped on every page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PdfMetadata {
+    /// Project name.
+    pub project: String,
+    /// Baseline or release label, if any.
+    pub baseline: Option<String>,
+    /// Generation time (seconds since the Unix epoch).
+    pub timestamp: u64,
+    /// Version of the generating tool.
+    pub tool_version: String,
+}
+
+impl PdfMetadata {
+    /// Metadata for a project, stamped with this Axiom version.
+    pub fn new(project: impl Into<String>, timestamp: u64) -> Self {
+        Self {
+            project: project.into(),
+            baseline: None,
+            timestamp,
+            tool_version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+
+    /// Set the baseline label.
+    pub fn with_baseline(mut self, baseline: impl Into<String>) -> Self {
+        self.baseline = Some(baseline.into());
+        self
+    }
+
+    /// Footer text for a page.
+    fn footer(&self, page: usize, pages: usize) -> String {
+        format!(
+            "{} | Baseline {} | Generated {} | Axiom {} | Page {} of {}",
+            self.project,
+            self.baseline.as_deref().unwrap_or("none"),
+            format_timestamp(self.timestamp),
+            self.tool_version,
+            page,
+            pages
+        )
+    }
+}
+
+/// A line of document content.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PdfLine {
+    Heading(String),
+    Text(String),
+}
+
+/// A text document rendered to PDF.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PdfDocument {
+    title: String,
+    metadata: PdfMetadata,
+    lines: Vec<PdfLine>,
+}
+
+impl PdfDocument {
+    /// Create an empty document.
+    pub fn new(title: impl Into<String>, metadata: PdfMetadata) -> Self {
+        Self {
+            title: title.into(),
+            metadata,
+            lines: Vec::new(),
+        }
+    }
+
+    /// Add a section heading.
+    pub fn heading(&mut self, text: impl Into<String>) -> &mut Self {
+        if !self.lines.is_empty() {
+            self.lines.push(PdfLine::Text(String::new()));
+        }
+        self.lines.push(PdfLine::Heading(text.into()));
+        self
+    }
+
+    /// Add monospaced text, wrapping lines wider than the page.
+    pub fn text(&mut self, text: &str) -> &mut Self {
+        for line in text.lines() {
+            let chars: Vec<char> = line.chars().collect();
+            if chars.is_empty() {
+                self.lines.push(PdfLine::Text(String::new()));
+            }
+            for chunk in chars.chunks(CHARS_PER_LINE) {
+                self.lines.push(PdfLine::Text(chunk.iter().collect()));
+            }
+        }
+        self
+    }
+
+    /// Number of pages the document renders to.
+    pub fn page_count(&self) -> usize {
+        self.lines.len().div_ceil(LINES_PER_PAGE).max(1)
+    }
+
+    /// Render the document as PDF bytes.
+    pub fn render(&self) -> Vec<u8> {
+        let pages: Vec<&[PdfLine]> = if self.lines.is_empty() {
+            vec![&[]]
+        } else {
+            self.lines.chunks(LINES_PER_PAGE).collect()
+        };
+
+        // Objects 1-5 are fixed; each page then takes a page object and a
+        // content stream.
+        let mut objects: Vec<String> = Vec::new();
+        objects.push("<< /Type /Catalog /Pages 2 0 R >>".to_string());
+        let kids: Vec<String> = (0..pages.len())
+            .map(|i| format!("{} 0 R", 6 + 2 * i))
+            .collect();
+        objects.push(format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids.join(" "),
+            pages.len()
+        ));
+        objects.push(
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>"
+                .to_string(),
+        );
+        objects.push(
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Courier /Encoding /WinAnsiEncoding >>"
+                .to_string(),
+        );
+        let date: String = format_timestamp(self.metadata.timestamp)
+            .chars()
+            .filter(char::is_ascii_digit)
+            .collect();
+        objects.push(format!(
+            "<< /Title ({}) /Subject ({}) /Producer (Axiom {}) /CreationDate (D:{}Z) >>",
+            pdf_string(&self.title),
+            pdf_string(&self.metadata.project),
+            pdf_string(&self.metadata.tool_version),
+            date
+        ));
+
+        for (index, lines) in pages.iter().enumerate() {
+            let content = self.page_content(lines, index + 1, pages.len());
+            objects.push(format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+                 /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+                PAGE_WIDTH,
+                PAGE_HEIGHT,
+                7 + 2 * index
+            ));
+            objects.push(format!(
+                "<< /Length {} >>\nstream\n{}endstream",
+                content.len(),
+                content
+            ));
+        }
+
+        let mut pdf = String::from("%PDF-1.4\n");
+        let mut offsets = Vec::with_capacity(objects.len());
+        for (i, object) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            let _ = write!(pdf, "{} 0 obj\n{}\nendobj\n", i + 1, object);
+        }
+        let xref = pdf.len();
+        let _ = write!(pdf, "xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+        for offset in offsets {
+            let _ = writeln!(pdf, "{:010} 00000 n ", offset);
+        }
+        let _ = write!(
+            pdf,
+            "trailer\n<< /Size {} /Root 1 0 R /Info 5 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref
+        );
+        pdf.into_bytes()
+    }
+
+    /// Content stream for one page.
+    fn page_content(&self, lines: &[PdfLine], page: usize, pages: usize) -> String {
+        let mut content = String::new();
+        let top = PAGE_HEIGHT - MARGIN;
+        let _ = writeln!(
+            content,
+            "BT /F1 {} Tf {} {} Td ({}) Tj ET",
+            HEADING_SIZE,
+            MARGIN,
+            top,
+            pdf_string(&self.title)
+        );
+        let _ = writeln!(
+            content,
+            "BT /F1 7 Tf {} {} Td ({}) Tj ET",
+            MARGIN,
+            MARGIN - 20.0,
+            pdf_string(&self.metadata.footer(page, pages))
+        );
+
+        let mut y = top - 30.0;
+        for line in lines {
+            let (font, size, text) = match line {
+                PdfLine::Heading(text) => ("F1", BODY_SIZE + 1.0, text),
+                PdfLine::Text(text) => ("F2", BODY_SIZE, text),
+            };
+            if !text.is_empty() {
+                let _ = writeln!(
+                    content,
+                    "BT /{} {} Tf {} {} Td ({}) Tj ET",
+                    font,
+                    size,
+                    MARGIN,
+                    y,
+                    pdf_string(text)
+                );
+            }
+            y -= LINE_HEIGHT;
+        }
+        content
+    }
+}
+
+/// Escape text for a PDF literal string, replacing characters outside
+/// printable ASCII.
+fn pdf_string(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | '(' | ')' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            ' '..='~' => escaped.push(c),
+            _ => escaped.push('?'),
+        }
+    }
+    escaped
+}
+
+/// Pad or truncate to a column width.
+fn column(text: &str, width: usize) -> String {
+    let mut text: String = text.chars().take(width).collect();
+    while text.chars().count() < width {
+        text.push(' ');
+    }
+    text
+}
+
+/// Traceability matrix as a PDF document.
+pub fn traceability_pdf(matrix: &TraceabilityMatrix, metadata: PdfMetadata) -> PdfDocument {
+    let mut doc = PdfDocument::new("Traceability Matrix", metadata);
+    let rows = matrix.rows();
+
+    doc.heading("Summary");
+    doc.text(&format!(
+        "Requirements: {}\nTrace links: {}\nUntested requirements: {}\nUntraceable functions: {}\n\
+         Unjustified derived requirements: {}",
+        rows.len(),
+        matrix.links.len(),
+        matrix.find_untested_requirements().len(),
+        matrix.find_untraceable_functions().len(),
+        matrix.find_unjustified_derived_requirements().len()
+    ));
+
+    doc.heading("Matrix");
+    doc.text(&format!(
+        "{} {} {}",
+        column("Requirement", 20),
+        column("Link", 10),
+        "Location"
+    ));
+    for row in &rows {
+        if row.implemented_by.is_empty() && row.tested_by.is_empty() {
+            doc.text(&format!(
+                "{} {}",
+                column(&row.requirement, 20),
+                "(no links)"
+            ));
+        }
+        for link in row.implemented_by.iter().chain(&row.tested_by) {
+            let kind = match link.link_type {
+                LinkType::Implements => "implements",
+                LinkType::Tests => "tested by",
+                LinkType::Derived => "derived",
+            };
+            let mut location = format!("{}:{}", link.file.display(), link.line);
+            if let Some(ref function) = link.function {
+                let _ = write!(location, " {}", function);
+            }
+            doc.text(&format!(
+                "{} {} {}",
+                column(&row.requirement, 20),
+                column(kind, 10),
+                location
+            ));
+        }
+    }
+
+    doc.heading("Untested requirements");
+    let untested = matrix.find_untested_requirements();
+    doc.text(&if untested.is_empty() {
+        "None.".to_string()
+    } else {
+        untested.join("\n")
+    });
+
+    doc.heading("Untraceable functions");
+    let untraceable: Vec<String> = matrix
+        .find_untraceable_functions()
+        .iter()
+        .map(|f| format!("{} ({}:{})", f.name, f.file.display(), f.line))
+        .collect();
+    doc.text(&if untraceable.is_empty() {
+        "None.".to_string()
+    } else {
+        untraceable.join("\n")
+    });
+
+    doc.heading("Unjustified derived requirements");
+    let unjustified: Vec<String> = matrix
+        .find_unjustified_derived_requirements()
+        .iter()
+        .map(|f| format!("{}: {}", f.requirement, f.issues().join(", ")))
+        .collect();
+    doc.text(&if unjustified.is_empty() {
+        "None.".to_string()
+    } else {
+        unjustified.join("\n")
+    });
+    doc
+}
+
+/// Coverage report as a PDF document, checked against a policy if given.
+pub fn coverage_pdf(
+    report: &CoverageReport,
+    policy: Option<&DalPolicy>,
+    metadata: PdfMetadata,
+) -> PdfDocument {
+    let mut doc = PdfDocument::new("Structural Coverage Report", metadata);
+    let counter = |c: CoverageCounter| {
+        if c.total == 0 {
+            "-".to_string()
+        } else if c.justified == 0 {
+            format!("{}/{} ({:.1}%)", c.covered, c.total, c.percent())
+        } else {
+            format!(
+                "{}/{} ({:.1}%) +{}",
+                c.covered,
+                c.total,
+                c.percent(),
+                c.justified
+            )
+        }
+    };
+
+    doc.heading("Coverage by file");
+    doc.text(&format!(
+        "{} {} {} {}",
+        column("File", 40),
+        column("Lines", 16),
+        column("Branches", 16),
+        "Functions"
+    ));
+    let files = report
+        .files
+        .iter()
+        .map(|f| (f.path.display().to_string(), f.summary()));
+    for (name, summary) in files.chain([("Total".to_string(), report.summary)]) {
+        doc.text(&format!(
+            "{} {} {} {}",
+            column(&name, 40),
+            column(&counter(summary.lines), 16),
+            column(&counter(summary.branches), 16),
+            counter(summary.functions)
+        ));
+    }
+
+    if let Some(policy) = policy {
+        doc.heading("Policy");
+        match policy.coverage_criterion {
+            Some(criterion) => {
+                doc.text(&format!(
+                    "DAL {}: {} coverage required at {:.1}%",
+                    policy.dal, criterion, policy.coverage_threshold
+                ));
+                for shortfall in policy.coverage_shortfalls(&report.summary) {
+                    doc.text(&format!(
+                        "Shortfall: {} coverage {:.1}% is below {:.1}%",
+                        shortfall.criterion, shortfall.achieved, shortfall.required
+                    ));
+                }
+            }
+            None => {
+                doc.text(&format!(
+                    "DAL {}: no structural coverage required",
+                    policy.dal
+                ));
+            }
+        }
+    }
+
+    doc.heading("Justified gaps");
+    let mut any = false;
+    for file in &report.files {
+        for justification in &file.justifications {
+            any = true;
+            let lines = match justification.end_line {
+                Some(end) if end > justification.line => format!("{}-{}", justification.line, end),
+                _ => justification.line.to_string(),
+            };
+            doc.text(&format!(
+                "{}:{} ({}): {}",
+                file.path.display(),
+                lines,
+                justification.kind.label(),
+                justification.rationale
+            ));
+        }
+    }
+    if !any {
+        doc.text("None.");
+    }
+
+    doc.heading("Unresolved lines");
+    let mut any = false;
+    for file in &report.files {
+        let lines = file.unresolved_lines();
+        if lines.is_empty() {
+            continue;
+        }
+        any = true;
+        let lines: Vec<String> = lines.iter().map(u32::to_string).collect();
+        doc.text(&format!("{}: {}", file.path.display(), lines.join(", ")));
+    }
+    if !any {
+        doc.text("None.");
+    }
+    doc
+}
+
+/// Deviation records as a PDF document.
+pub fn deviations_pdf(deviations: &[DeviationRecord], metadata: PdfMetadata) -> PdfDocument {
+    let mut doc = PdfDocument::new("Deviation Report", metadata);
+    let status = |d: &DeviationRecord| match d.status {
+        DeviationStatus::Proposed => "proposed",
+        DeviationStatus::Approved => "approved",
+        DeviationStatus::Rejected => "rejected",
+    };
+    let count = |s: DeviationStatus| deviations.iter().filter(|d| d.status == s).count();
+
+    doc.heading("Summary");
+    doc.text(&format!(
+        "Deviations: {}\nApproved: {}\nProposed: {}\nRejected: {}",
+        deviations.len(),
+        count(DeviationStatus::Approved),
+        count(DeviationStatus::Proposed),
+        count(DeviationStatus::Rejected)
+    ));
+
+    doc.heading("Deviations");
+    doc.text(&format!(
+        "{} {} {} {}",
+        column("ID", 12),
+        column("Rule", 14),
+        column("Status", 10),
+        "Location"
+    ));
+    for deviation in deviations {
+        let location = match (&deviation.file, deviation.line) {
+            (Some(file), Some(line)) => format!("{}:{}", file.display(), line),
+            (Some(file), None) => file.display().to_string(),
+            (None, _) => "(project)".to_string(),
+        };
+        doc.text(&format!(
+            "{} {} {} {}",
+            column(&deviation.id, 12),
+            column(&deviation.rule, 14),
+            column(status(deviation), 10),
+            location
+        ));
+    }
+
+    for deviation in deviations {
+        doc.heading(format!("{}: {}", deviation.id, deviation.rule));
+        doc.text(&format!("Raised: {}", format_timestamp(deviation.created)));
+        if !deviation.checks.is_empty() {
+            doc.text(&format!("Checks: {}", deviation.checks.join(", ")));
+        }
+        match deviation.approval {
+            Some(ref approval) => doc.text(&format!(
+                "Approved by {} on {}",
+                approval.approver,
+                format_timestamp(approval.timestamp)
+            )),
+            None => doc.text(&format!("Status: {}", status(deviation))),
+        };
+        doc.text(&format!("Justification: {}", deviation.justification));
+    }
+    doc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FileTrace, Requirement, TraceLink};
+    use axiom_toolchain::{FileCoverage, LineCoverage};
+    use std::path::PathBuf;
+
+    fn metadata() -> PdfMetadata {
+        PdfMetadata::new("nav (fw)", 0).with_baseline("SOI-2")
+    }
+
+    #[test]
+    fn test_render_structure() {
+        let mut doc = PdfDocument::new("Report", metadata());
+        doc.heading("Body");
+        for i in 0..LINES_PER_PAGE + 5 {
+            doc.text(&format!("line {}", i));
+        }
+        assert_eq!(doc.page_count(), 2);
+
+        let pdf = String::from_utf8(doc.render()).unwrap();
+        assert!(pdf.starts_with("%PDF-1.4\n"));
+        assert!(pdf.ends_with("%%EOF\n"));
+        assert!(pdf.contains("/Count 2"));
+        assert!(pdf.contains("(nav \\(fw\\) | Baseline SOI-2 | Generated 1970-01-01T00:00:00Z"));
+        assert!(pdf.contains("Page 2 of 2"));
+        assert!(pdf.contains("/CreationDate (D:19700101000000Z)"));
+
+        // Every xref entry points at its object.
+        let xref = pdf.rfind("xref\n").unwrap();
+        for (i, entry) in pdf[xref..].lines().skip(3).take(9).enumerate() {
+            let offset: usize = entry[..10].parse().unwrap();
+            assert!(pdf[offset..].starts_with(&format!("{} 0 obj", i + 1)));
+        }
+    }
+
+    #[test]
+    fn test_wrap_and_escape() {
+        let mut doc = PdfDocument::new("R", metadata());
+        doc.text(&"x".repeat(CHARS_PER_LINE + 1));
+        assert_eq!(doc.lines.len(), 2);
+        assert_eq!(pdf_string("a(b)\\ é"), "a\\(b\\)\\\\ ?");
+    }
+
+    #[test]
+    fn test_artifact_documents() {
+        let trace = FileTrace {
+            links: vec![TraceLink {
+                requirement: "REQ-1".to_string(),
+                file: PathBuf::from("src/a.c"),
+                line: 3,
+                function: Some("limit".to_string()),
+                link_type: LinkType::Implements,
+            }],
+            functions: Vec::new(),
+        };
+        let matrix = TraceabilityMatrix::new(vec![Requirement::new("REQ-2", "x")], [trace]);
+        let pdf = String::from_utf8(traceability_pdf(&matrix, metadata()).render()).unwrap();
+        assert!(pdf.contains("(REQ-1                implements src/a.c:3 limit)"));
+        assert!(pdf.contains("(REQ-2                \\(no links\\))"));
+
+        let mut file = FileCoverage::new("src/a.c");
+        file.lines = vec![LineCoverage {
+            line: 7,
+            count: 0,
+            unexecuted_block: false,
+        }];
+        let report = CoverageReport::from_files([file]);
+        let pdf = String::from_utf8(coverage_pdf(&report, None, metadata()).render()).unwrap();
+        assert!(pdf.contains("(src/a.c: 7)"));
+
+        let deviations = [
+            DeviationRecord::new("DEV-0001", "11.3", "Register access", 0)
+                .with_location("src/hw.c", Some(12))
+                .approve("qa", 0),
+            DeviationRecord::new("DEV-0002", "Dir 4.9", "Macro (legacy)", 0),
+        ];
+        let pdf = String::from_utf8(deviations_pdf(&deviations, metadata()).render()).unwrap();
+        assert!(pdf.contains("(Deviations: 2)"));
+        assert!(pdf.contains("(DEV-0001     11.3           approved   src/hw.c:12)"));
+        assert!(pdf.contains("(DEV-0002     Dir 4.9        proposed   \\(project\\))"));
+        assert!(pdf.contains("(Approved by qa on 1970-01-01T00:00:00Z)"));
+        assert!(pdf.contains("(Justification: Macro \\(legacy\\))"));
+    }
+}
@@ -0,0 +1,409 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Problem reports.
+//!
+//! DO-178C requires a record of every problem found in the software and
+//! its life cycle data, and of what was done about it. Reports live in
+//! `.axiom/problem-reports.json` next to the requirements, reference the
+//! requirements and files they affect and, when a problem is accepted
+//! rather than fixed, the deviation records that justify it. Change
+//! impact analysis lists the open reports a commit range touches.
+
+use crate::ChangeImpact;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Problem reports file format version.
+pub const PROBLEM_REPORTS_VERSION: u32 = 1;
+
+/// Problem report errors.
+#[derive(Debug, Error)]
+pub enum ProblemReportError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("JSON error in {path}: {source}")]
+    Json {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+
+    #[error("Problem reports file version {0} is newer than supported")]
+    UnsupportedVersion(u32),
+
+    #[error("Problem report already exists: {0}")]
+    Duplicate(String),
+
+    #[error("Problem report not found: {0}")]
+    NotFound(String),
+
+    #[error("Problem report {0} cannot be closed without a disposition")]
+    MissingDisposition(String),
+
+    #[error("Problem report {0} is accepted as a deviation without a deviation record")]
+    MissingDeviation(String),
+}
+
+/// Where a problem report is in its life cycle.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ProblemStatus {
+    /// Reported, not yet analyzed.
+    #[default]
+    Open,
+    /// Cause and impact understood; disposition pending.
+    Analyzed,
+    /// Disposition implemented; awaiting verification.
+    Resolved,
+    /// Resolution verified.
+    Closed,
+}
+
+impl ProblemStatus {
+    /// Whether the report still needs work.
+    pub fn is_open(&self) -> bool {
+        *self != Self::Closed
+    }
+}
+
+/// What was decided about a problem.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Disposition {
+    /// Corrected in the software or its data.
+    Fixed,
+    /// Left for a later release.
+    Deferred,
+    /// Accepted under an approved deviation.
+    Deviation,
+    /// Analysis showed no problem.
+    NotAProblem,
+}
+
+/// A problem report.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProblemReport {
+    /// Report ID (e.g. "PR-0001").
+    pub id: String,
+    /// One-line summary.
+    pub title: String,
+    /// Full description of the problem.
+    #[serde(default)]
+    pub description: String,
+    /// Life cycle status.
+    #[serde(default)]
+    pub status: ProblemStatus,
+    /// Decision taken, once there is one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disposition: Option<Disposition>,
+    /// Justification of the disposition.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rationale: Option<String>,
+    /// Requirements affected.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub requirements: Vec<String>,
+    /// Files affected, relative to the project root.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<PathBuf>,
+    /// Deviation records the problem is accepted under.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deviations: Vec<String>,
+    /// When the report was raised (seconds since the Unix epoch).
+    pub created: u64,
+    /// When the report last changed (seconds since the Unix epoch).
+    pub updated: u64,
+}
+
+impl ProblemReport {
+    /// Create an open report.
+    pub fn new(id: impl Into<String>, title: impl Into<String>, timestamp: u64) -> Self {
+        Self {
+            id: id.into(),
+            title: title.into(),
+            description: String::new(),
+            status: ProblemStatus::Open,
+            disposition: None,
+            rationale: None,
+            requirements: Vec::new(),
+            files: Vec::new(),
+            deviations: Vec::new(),
+            created: timestamp,
+            updated: timestamp,
+        }
+    }
+
+    /// Set the description.
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = description.into();
+        self
+    }
+
+    /// Add an affected requirement.
+    pub fn with_requirement(mut self, id: impl Into<String>) -> Self {
+        self.requirements.push(id.into());
+        self
+    }
+
+    /// Add an affected file.
+    pub fn with_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.files.push(path.into());
+        self
+    }
+
+    /// Add a deviation record the problem is accepted under.
+    pub fn with_deviation(mut self, id: impl Into<String>) -> Self {
+        self.deviations.push(id.into());
+        self
+    }
+
+    /// Set the disposition and its justification.
+    pub fn with_disposition(
+        mut self,
+        disposition: Disposition,
+        rationale: impl Into<String>,
+    ) -> Self {
+        self.disposition = Some(disposition);
+        self.rationale = Some(rationale.into());
+        self
+    }
+
+    /// Set the status.
+    pub fn with_status(mut self, status: ProblemStatus) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Whether the report affects any requirement or file in the scope of
+    /// a change.
+    pub fn is_affected_by(&self, impact: &ChangeImpact) -> bool {
+        self.requirements
+            .iter()
+            .any(|id| impact.requirements.iter().any(|r| &r.requirement == id))
+            || self.files.iter().any(|f| impact.changed_files.contains(f))
+    }
+
+    fn check(&self) -> Result<(), ProblemReportError> {
+        if self.status == ProblemStatus::Closed && self.disposition.is_none() {
+            return Err(ProblemReportError::MissingDisposition(self.id.clone()));
+        }
+        if self.disposition == Some(Disposition::Deviation) && self.deviations.is_empty() {
+            return Err(ProblemReportError::MissingDeviation(self.id.clone()));
+        }
+        Ok(())
+    }
+}
+
+/// On-disk form of the problem reports file.
+#[derive(Serialize, Deserialize)]
+struct ProblemReportsFile {
+    version: u32,
+    #[serde(default)]
+    reports: Vec<ProblemReport>,
+}
+
+/// File-backed problem report database.
+#[derive(Debug, Clone)]
+pub struct ProblemReportStore {
+    path: PathBuf,
+}
+
+impl ProblemReportStore {
+    /// Create a store backed by the given file.
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Create the store for a project root.
+    pub fn for_project(project_root: &Path) -> Self {
+        Self::new(project_root.join(".axiom").join("problem-reports.json"))
+    }
+
+    /// Path of the backing file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Load all reports, by ID; a missing file yields none.
+    pub fn load(&self) -> Result<Vec<ProblemReport>, ProblemReportError> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = fs::read_to_string(&self.path)?;
+        let file: ProblemReportsFile =
+            serde_json::from_str(&content).map_err(|source| ProblemReportError::Json {
+                path: self.path.clone(),
+                source,
+            })?;
+        if file.version > PROBLEM_REPORTS_VERSION {
+            return Err(ProblemReportError::UnsupportedVersion(file.version));
+        }
+        Ok(file.reports)
+    }
+
+    fn save(&self, mut reports: Vec<ProblemReport>) -> Result<(), ProblemReportError> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        reports.sort_by(|a, b| a.id.cmp(&b.id));
+        let file = ProblemReportsFile {
+            version: PROBLEM_REPORTS_VERSION,
+            reports,
+        };
+        let json =
+            serde_json::to_string_pretty(&file).map_err(|source| ProblemReportError::Json {
+                path: self.path.clone(),
+                source,
+            })?;
+        fs::write(&self.path, json + "\n")?;
+        Ok(())
+    }
+
+    /// Look up a report.
+    pub fn get(&self, id: &str) -> Result<ProblemReport, ProblemReportError> {
+        self.load()?
+            .into_iter()
+            .find(|r| r.id == id)
+            .ok_or_else(|| ProblemReportError::NotFound(id.to_string()))
+    }
+
+    /// The next free sequential ID (`PR-0001`, `PR-0002`, ...).
+    pub fn next_id(&self) -> Result<String, ProblemReportError> {
+        let last = self
+            .load()?
+            .iter()
+            .filter_map(|r| r.id.strip_prefix("PR-")?.parse::<u32>().ok())
+            .max()
+            .unwrap_or(0);
+        Ok(format!("PR-{:04}", last + 1))
+    }
+
+    /// Add a report.
+    pub fn create(&self, report: ProblemReport) -> Result<(), ProblemReportError> {
+        report.check()?;
+        let mut reports = self.load()?;
+        if reports.iter().any(|r| r.id == report.id) {
+            return Err(ProblemReportError::Duplicate(report.id));
+        }
+        reports.push(report);
+        self.save(reports)
+    }
+
+    /// Replace a report, returning the previous version.
+    pub fn update(&self, report: ProblemReport) -> Result<ProblemReport, ProblemReportError> {
+        report.check()?;
+        let mut reports = self.load()?;
+        let existing = reports
+            .iter_mut()
+            .find(|r| r.id == report.id)
+            .ok_or_else(|| ProblemReportError::NotFound(report.id.clone()))?;
+        let previous = std::mem::replace(existing, report);
+        self.save(reports)?;
+        Ok(previous)
+    }
+
+    /// Open reports affecting the requirements or files in the scope of a
+    /// change.
+    pub fn affected_by(
+        &self,
+        impact: &ChangeImpact,
+    ) -> Result<Vec<ProblemReport>, ProblemReportError> {
+        Ok(self
+            .load()?
+            .into_iter()
+            .filter(|r| r.status.is_open() && r.is_affected_by(impact))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{change_impact, AnnotationSyntax, TraceabilityMatrix};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_store_lifecycle() {
+        let dir = TempDir::new().unwrap();
+        let store = ProblemReportStore::for_project(dir.path());
+        assert!(store.load().unwrap().is_empty());
+        assert_eq!(store.next_id().unwrap(), "PR-0001");
+
+        store
+            .create(
+                ProblemReport::new("PR-0001", "Heading wraps at 359", 10)
+                    .with_requirement("REQ-NAV-002")
+                    .with_file("src/nav.c"),
+            )
+            .unwrap();
+        assert_eq!(store.next_id().unwrap(), "PR-0002");
+        assert!(matches!(
+            store.create(ProblemReport::new("PR-0001", "Again", 11)),
+            Err(ProblemReportError::Duplicate(_))
+        ));
+
+        let closed = store
+            .get("PR-0001")
+            .unwrap()
+            .with_status(ProblemStatus::Closed);
+        assert!(matches!(
+            store.update(closed.clone()),
+            Err(ProblemReportError::MissingDisposition(_))
+        ));
+        assert!(matches!(
+            store.update(
+                closed
+                    .clone()
+                    .with_disposition(Disposition::Deviation, "Benign")
+            ),
+            Err(ProblemReportError::MissingDeviation(_))
+        ));
+        let previous = store
+            .update(closed.with_disposition(Disposition::Fixed, "Clamped in nav_clamp"))
+            .unwrap();
+        assert_eq!(previous.status, ProblemStatus::Open);
+        assert_eq!(
+            store.get("PR-0001").unwrap().disposition,
+            Some(Disposition::Fixed)
+        );
+    }
+
+    #[test]
+    fn test_affected_by_change() {
+        let dir = TempDir::new().unwrap();
+        let store = ProblemReportStore::for_project(dir.path());
+        store
+            .create(ProblemReport::new("PR-0001", "Drift", 0).with_file("src/nav.c"))
+            .unwrap();
+        store
+            .create(ProblemReport::new("PR-0002", "Timing", 0).with_file("src/timer.c"))
+            .unwrap();
+        store
+            .create(
+                ProblemReport::new("PR-0003", "Old drift", 0)
+                    .with_file("src/nav.c")
+                    .with_disposition(Disposition::NotAProblem, "Expected")
+                    .with_status(ProblemStatus::Closed),
+            )
+            .unwrap();
+
+        let diff = axiom_git::FileDiff {
+            old_path: Some(PathBuf::from("src/nav.c")),
+            new_path: Some(PathBuf::from("src/nav.c")),
+            hunks: Vec::new(),
+            is_binary: false,
+        };
+        let impact = change_impact(
+            &TraceabilityMatrix::new(Vec::new(), []),
+            &AnnotationSyntax::default(),
+            &[diff],
+            "a",
+            "b",
+        );
+        let affected = store.affected_by(&impact).unwrap();
+        assert_eq!(affected.len(), 1);
+        assert_eq!(affected[0].id, "PR-0001");
+    }
+}
@@ -0,0 +1,532 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! DO-330 tool qualification documents.
+//!
+//! Skeleton Tool Qualification Plan (TQP), Tool Operational Requirements
+//! (TOR) and Tool Accomplishment Summary (TAS) documents, filled in from
+//! what the project records: pinned toolchain versions and checksums from
+//! `toolchain.lock`, usage from the tool usage log, the operational
+//! verification suite and its baseline, and the TQL the DAL policy
+//! requires. Anything that needs engineering judgement is left as a
+//! marked TBD.
+
+use crate::html_report::escape;
+use crate::{load_compliance_config, ComplianceError, ComplianceMode, DalPolicy};
+use axiom_core::time::format_timestamp;
+use axiom_toolchain::{
+    standard_cases, tool_usage_statistics, LockedToolchain, LockfileError, QualificationBaseline,
+    QualificationCase, QualificationError, ToolLogError, ToolQualificationLogger, ToolUsageStats,
+    ToolchainLock,
+};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::path::Path;
+use thiserror::Error;
+
+/// Qualification document errors.
+#[derive(Debug, Error)]
+pub enum QualificationDocError {
+    #[error(transparent)]
+    Compliance(#[from] ComplianceError),
+
+    #[error(transparent)]
+    Lockfile(#[from] LockfileError),
+
+    #[error(transparent)]
+    ToolLog(#[from] ToolLogError),
+
+    #[error(transparent)]
+    Qualification(#[from] QualificationError),
+}
+
+/// DO-330 qualification document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum QualificationDocKind {
+    /// Tool Qualification Plan.
+    Tqp,
+    /// Tool Operational Requirements.
+    Tor,
+    /// Tool Accomplishment Summary.
+    Tas,
+}
+
+impl QualificationDocKind {
+    /// Document title.
+    pub fn title(&self) -> &'static str {
+        match self {
+            Self::Tqp => "Tool Qualification Plan",
+            Self::Tor => "Tool Operational Requirements",
+            Self::Tas => "Tool Accomplishment Summary",
+        }
+    }
+}
+
+/// Output format of a document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DocumentFormat {
+    Markdown,
+    Html,
+}
+
+/// Recorded data the documents are populated from.
+#[derive(Debug, Clone)]
+pub struct QualificationData {
+    /// Project name.
+    pub project: String,
+    /// Generation time (seconds since the Unix epoch).
+    pub timestamp: u64,
+    /// Objectives for the project's level and modes.
+    pub policy: DalPolicy,
+    /// Pinned toolchains.
+    pub toolchains: Vec<LockedToolchain>,
+    /// Per-tool usage from the tool usage log.
+    pub usage: Vec<ToolUsageStats>,
+    /// Operational verification cases.
+    pub cases: Vec<QualificationCase>,
+    /// Recorded operational verification baseline.
+    pub baseline: Option<QualificationBaseline>,
+}
+
+impl QualificationData {
+    /// Data with no tools, usage or verification recorded.
+    pub fn new(project: impl Into<String>, timestamp: u64, policy: DalPolicy) -> Self {
+        Self {
+            project: project.into(),
+            timestamp,
+            policy,
+            toolchains: Vec::new(),
+            usage: Vec::new(),
+            cases: Vec::new(),
+            baseline: None,
+        }
+    }
+
+    /// Gather a project's compliance configuration, toolchain lock, tool
+    /// usage log and qualification baseline.
+    pub fn load(project_root: &Path, timestamp: u64) -> Result<Self, QualificationDocError> {
+        let project = project_root
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let policy = load_compliance_config(project_root)?.policy();
+        let records = ToolQualificationLogger::for_project(project_root).load()?;
+        Ok(Self {
+            toolchains: ToolchainLock::load(project_root)?
+                .map(|lock| lock.toolchains)
+                .unwrap_or_default(),
+            usage: tool_usage_statistics(&records),
+            cases: standard_cases(),
+            baseline: QualificationBaseline::load(project_root)?,
+            ..Self::new(project, timestamp, policy)
+        })
+    }
+}
+
+/// A numbered document section.
+pub(crate) struct Section {
+    heading: &'static str,
+    paragraphs: Vec<String>,
+    table: Option<(Vec<&'static str>, Vec<Vec<String>>)>,
+}
+
+impl Section {
+    pub(crate) fn new(heading: &'static str) -> Self {
+        Self {
+            heading,
+            paragraphs: Vec::new(),
+            table: None,
+        }
+    }
+
+    pub(crate) fn paragraph(mut self, text: impl Into<String>) -> Self {
+        self.paragraphs.push(text.into());
+        self
+    }
+
+    pub(crate) fn table(mut self, headers: Vec<&'static str>, rows: Vec<Vec<String>>) -> Self {
+        self.table = Some((headers, rows));
+        self
+    }
+
+    /// A table, or a paragraph saying why there is none.
+    pub(crate) fn table_or(
+        self,
+        headers: Vec<&'static str>,
+        rows: Vec<Vec<String>>,
+        empty: &str,
+    ) -> Self {
+        if rows.is_empty() {
+            self.paragraph(empty)
+        } else {
+            self.table(headers, rows)
+        }
+    }
+}
+
+fn tbd(text: &str) -> String {
+    format!("TBD: {}", text)
+}
+
+fn level_section(policy: &DalPolicy) -> Section {
+    let level = |tql: Option<_>| tql.map_or("Not required".to_string(), |t| format!("{}", t));
+    let mut section = Section::new("Qualification level").table(
+        vec!["Tool role", "Qualification level"],
+        vec![
+            vec![
+                "Development (output is part of the software)".to_string(),
+                level(policy.development_tool_level),
+            ],
+            vec![
+                "Verification (eliminates or automates verification)".to_string(),
+                level(policy.verification_tool_level),
+            ],
+        ],
+    );
+    section = section.paragraph(format!("Software level: DAL {}.", policy.dal));
+    if !policy.modes.contains(&ComplianceMode::Do330) {
+        section = section
+            .paragraph("DO-330 mode is not enabled in .axiom/compliance.toml; no TQL applies.");
+    }
+    section
+}
+
+fn tools_section(data: &QualificationData, heading: &'static str) -> Section {
+    let rows = data
+        .toolchains
+        .iter()
+        .map(|t| {
+            vec![
+                t.kind.to_string(),
+                t.version.clone(),
+                t.path.display().to_string(),
+            ]
+        })
+        .collect();
+    Section::new(heading).table_or(
+        vec!["Toolchain", "Version", "Driver"],
+        rows,
+        "No toolchains are pinned; run the toolchain lock to record them.",
+    )
+}
+
+fn checksums_section(data: &QualificationData) -> Section {
+    let rows = data
+        .toolchains
+        .iter()
+        .flat_map(|t| {
+            t.binaries
+                .iter()
+                .map(|(name, sha)| vec![name.clone(), t.version.clone(), sha.clone()])
+        })
+        .collect();
+    Section::new("Tool configuration").table_or(
+        vec!["Binary", "Version", "SHA-256"],
+        rows,
+        "No binary checksums are recorded in toolchain.lock.",
+    )
+}
+
+fn usage_section(data: &QualificationData, heading: &'static str) -> Section {
+    let rows = data
+        .usage
+        .iter()
+        .map(|u| {
+            vec![
+                u.tool.clone(),
+                u.versions.iter().cloned().collect::<Vec<_>>().join(", "),
+                u.invocations.to_string(),
+                u.failures.to_string(),
+                format_timestamp(u.first_used),
+                format_timestamp(u.last_used),
+            ]
+        })
+        .collect();
+    Section::new(heading).table_or(
+        vec![
+            "Tool",
+            "Versions",
+            "Invocations",
+            "Failures",
+            "First used",
+            "Last used",
+        ],
+        rows,
+        "The tool usage log is empty.",
+    )
+}
+
+fn sections(kind: QualificationDocKind, data: &QualificationData) -> Vec<Section> {
+    match kind {
+        QualificationDocKind::Tqp => vec![
+            Section::new("Purpose").paragraph(format!(
+                "This plan describes how the tools used to develop and verify the {} software \
+                 are qualified.",
+                data.project
+            )),
+            level_section(&data.policy),
+            tools_section(data, "Tools"),
+            checksums_section(data),
+            Section::new("Qualification activities")
+                .paragraph(format!(
+                    "Tool operation is verified by {} known-input cases whose output checksums \
+                     must match the recorded baseline (see the TOR).",
+                    data.cases.len()
+                ))
+                .paragraph(tbd(
+                    "qualification liaison, TOR verification, configuration management and \
+                     quality assurance of the tools.",
+                )),
+            usage_section(data, "Tool usage to date"),
+        ],
+        QualificationDocKind::Tor => {
+            let requirements = data
+                .cases
+                .iter()
+                .enumerate()
+                .map(|(i, case)| {
+                    vec![
+                        format!("TOR-{:03}", i + 1),
+                        case.description.clone(),
+                        case.id.clone(),
+                    ]
+                })
+                .collect();
+            vec![
+                tools_section(data, "Operational environment"),
+                Section::new("Operational requirements").table_or(
+                    vec!["ID", "Requirement", "Verified by"],
+                    requirements,
+                    "No operational verification cases are defined.",
+                ),
+                usage_section(data, "Functions in use"),
+                Section::new("Additional requirements").paragraph(tbd(
+                    "project-specific operational requirements, such as options and language \
+                     features the software relies on.",
+                )),
+            ]
+        }
+        QualificationDocKind::Tas => {
+            let verification = match data.baseline {
+                Some(ref baseline) => {
+                    let rows = baseline
+                        .cases
+                        .iter()
+                        .map(|expected| {
+                            let description = data
+                                .cases
+                                .iter()
+                                .find(|c| c.id == expected.id)
+                                .map(|c| c.description.clone())
+                                .unwrap_or_default();
+                            vec![expected.id.clone(), description, expected.checksum.clone()]
+                        })
+                        .collect();
+                    Section::new("Operational verification")
+                        .paragraph(format!(
+                            "Baseline recorded with toolchain version {}.",
+                            baseline.version
+                        ))
+                        .table(vec!["Case", "Description", "Expected SHA-256"], rows)
+                }
+                None => Section::new("Operational verification")
+                    .paragraph(tbd("no qualification baseline has been recorded.")),
+            };
+            vec![
+                checksums_section(data),
+                level_section(&data.policy),
+                verification,
+                usage_section(data, "Tool usage"),
+                Section::new("Deviations from the plan")
+                    .paragraph(tbd("deviations from the TQP and their justification.")),
+                Section::new("Compliance statement")
+                    .paragraph(tbd("statement of compliance with the TQP.")),
+            ]
+        }
+    }
+}
+
+/// Render a qualification document.
+pub fn render_qualification_document(
+    kind: QualificationDocKind,
+    format: DocumentFormat,
+    data: &QualificationData,
+) -> String {
+    let sections = sections(kind, data);
+    let generated = format!(
+        "Generated {} by Axiom {}",
+        format_timestamp(data.timestamp),
+        env!("CARGO_PKG_VERSION")
+    );
+    let title = format!("{}: {}", kind.title(), data.project);
+    render_document(format, &title, &generated, &sections)
+}
+
+/// Render numbered sections as a document.
+pub(crate) fn render_document(
+    format: DocumentFormat,
+    title: &str,
+    generated: &str,
+    sections: &[Section],
+) -> String {
+    match format {
+        DocumentFormat::Markdown => render_markdown(title, generated, sections),
+        DocumentFormat::Html => render_html(title, generated, sections),
+    }
+}
+
+fn render_markdown(title: &str, generated: &str, sections: &[Section]) -> String {
+    let cell = |text: &str| text.replace('|', "\\|");
+    let mut out = String::new();
+    let _ = writeln!(out, "# {}\n", title);
+    let _ = writeln!(out, "{}\n", generated);
+    for (i, section) in sections.iter().enumerate() {
+        let _ = writeln!(out, "## {}. {}\n", i + 1, section.heading);
+        for paragraph in &section.paragraphs {
+            let _ = writeln!(out, "{}\n", paragraph);
+        }
+        if let Some((ref headers, ref rows)) = section.table {
+            let _ = writeln!(out, "| {} |", headers.join(" | "));
+            let _ = writeln!(out, "|{}", "---|".repeat(headers.len()));
+            for row in rows {
+                let cells: Vec<String> = row.iter().map(|c| cell(c)).collect();
+                let _ = writeln!(out, "| {} |", cells.join(" | "));
+            }
+            out.push('\n');
+        }
+    }
+    out
+}
+
+fn render_html(title: &str, generated: &str, sections: &[Section]) -> String {
+    let title = escape(title);
+    let mut out = String::new();
+    let _ = writeln!(out, "<!DOCTYPE html>");
+    let _ = writeln!(out, "<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">");
+    let _ = writeln!(out, "<title>{}</title>", title);
+    let _ = writeln!(
+        out,
+        "<style>{}</style>\n</head>\n<body>",
+        crate::html_report::STYLE
+    );
+    let _ = writeln!(out, "<h1>{}</h1>\n<p>{}</p>", title, escape(generated));
+    for (i, section) in sections.iter().enumerate() {
+        let _ = writeln!(out, "<h2>{}. {}</h2>", i + 1, escape(section.heading));
+        for paragraph in &section.paragraphs {
+            let _ = writeln!(out, "<p>{}</p>", escape(paragraph));
+        }
+        if let Some((ref headers, ref rows)) = section.table {
+            let _ = write!(out, "<table>\n<tr>");
+            for header in headers {
+                let _ = write!(out, "<th>{}</th>", escape(header));
+            }
+            let _ = writeln!(out, "</tr>");
+            for row in rows {
+                let _ = write!(out, "<tr>");
+                for cell in row {
+                    let _ = write!(out, "<td>{}</td>", escape(cell));
+                }
+                let _ = writeln!(out, "</tr>");
+            }
+            let _ = writeln!(out, "</table>");
+        }
+    }
+    let _ = writeln!(out, "</body>\n</html>");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ComplianceConfig, DesignAssuranceLevel};
+    use axiom_toolchain::{ExpectedChecksum, ToolUsageRecord, ToolchainKind};
+    use std::collections::BTreeMap;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    fn data() -> QualificationData {
+        let policy = ComplianceConfig::new(DesignAssuranceLevel::B)
+            .with_mode(ComplianceMode::Do178c)
+            .with_mode(ComplianceMode::Do330)
+            .policy();
+        let mut data = QualificationData::new("nav", 0, policy);
+        data.toolchains = vec![LockedToolchain {
+            kind: ToolchainKind::ArmGcc,
+            path: PathBuf::from("/opt/arm/bin/arm-none-eabi-gcc"),
+            version: "13.2.1".to_string(),
+            binaries: BTreeMap::from([("arm-none-eabi-gcc".to_string(), "ab12".to_string())]),
+        }];
+        data.usage = tool_usage_statistics(&[ToolUsageRecord::new(
+            60,
+            Path::new("/opt/arm/bin/arm-none-eabi-gcc"),
+            "13.2.1",
+        )]);
+        data.cases = standard_cases();
+        data.baseline = Some(QualificationBaseline {
+            version: "13.2.1".to_string(),
+            toolchain_sha256: None,
+            cases: vec![ExpectedChecksum {
+                id: data.cases[0].id.clone(),
+                checksum: "cd34".to_string(),
+            }],
+        });
+        data
+    }
+
+    #[test]
+    fn test_markdown_documents() {
+        let data = data();
+        let tqp = render_qualification_document(
+            QualificationDocKind::Tqp,
+            DocumentFormat::Markdown,
+            &data,
+        );
+        assert!(tqp.starts_with("# Tool Qualification Plan: nav\n"));
+        assert!(tqp.contains("| Development (output is part of the software) | TQL-2 |"));
+        assert!(tqp.contains("| arm-none-eabi-gcc | 13.2.1 | ab12 |"));
+        assert!(tqp.contains("TBD: qualification liaison"));
+
+        let tor = render_qualification_document(
+            QualificationDocKind::Tor,
+            DocumentFormat::Markdown,
+            &data,
+        );
+        assert!(tor.contains(&format!(
+            "| TOR-001 | {} | {} |",
+            data.cases[0].description, data.cases[0].id
+        )));
+
+        let tas = render_qualification_document(
+            QualificationDocKind::Tas,
+            DocumentFormat::Markdown,
+            &data,
+        );
+        assert!(tas.contains("Baseline recorded with toolchain version 13.2.1."));
+        assert!(tas.contains("| cd34 |"));
+        assert!(tas.contains(
+            "| arm-none-eabi-gcc | 13.2.1 | 1 | 0 | 1970-01-01T00:01:00Z | 1970-01-01T00:01:00Z |"
+        ));
+    }
+
+    #[test]
+    fn test_html_and_load() {
+        let html =
+            render_qualification_document(QualificationDocKind::Tas, DocumentFormat::Html, &data());
+        assert!(html.contains("<title>Tool Accomplishment Summary: nav</title>"));
+        assert!(html.contains("<td>ab12</td>"));
+
+        let dir = TempDir::new().unwrap();
+        let loaded = QualificationData::load(dir.path(), 0).unwrap();
+        assert!(loaded.toolchains.is_empty());
+        assert!(loaded.baseline.is_none());
+        let tas = render_qualification_document(
+            QualificationDocKind::Tas,
+            DocumentFormat::Markdown,
+            &loaded,
+        );
+        assert!(tas.contains("TBD: no qualification baseline has been recorded."));
+        assert!(tas.contains("DO-330 mode is not enabled"));
+    }
+}
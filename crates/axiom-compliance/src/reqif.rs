@@ -0,0 +1,695 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! ReqIF exchange.
+//!
+//! Reads and writes OMG ReqIF 1.2 documents as produced by DOORS and
+//! Polarion. On import, each SPEC-OBJECT becomes a requirement: well-known
+//! attributes (`ReqIF.ForeignID`, `ReqIF.Text`, ...) map onto requirement
+//! fields, the SPEC-OBJECT identifier is kept as the external ID, and all
+//! other attributes are kept by name. SPEC-RELATIONs of a refinement type
+//! set the parent of their source. Export writes the same mapping back,
+//! with each parent link as a `Refines` relation.
+
+use crate::{
+    is_valid_requirement_id, DesignAssuranceLevel, MergeSummary, Requirement, RequirementError,
+    RequirementSet, RequirementStore,
+};
+use axiom_core::time::format_timestamp;
+use roxmltree::{Document, Node};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write as _;
+use std::path::Path;
+use thiserror::Error;
+
+/// ReqIF namespace.
+pub const REQIF_NAMESPACE: &str = "http://www.omg.org/spec/ReqIF/20110401/reqif.xsd";
+
+/// Attribute holding the requirement ID.
+const FOREIGN_ID: &str = "ReqIF.ForeignID";
+/// Attribute holding the requirement statement.
+const TEXT: &str = "ReqIF.Text";
+const KIND: &str = "Axiom.Kind";
+const STATUS: &str = "Axiom.Status";
+const DAL: &str = "Axiom.DAL";
+const RATIONALE: &str = "Axiom.Rationale";
+
+/// Attribute names read as the requirement ID, in order of preference.
+const ID_ATTRIBUTES: &[&str] = &[FOREIGN_ID, "ID", "Identifier", "Object Identifier"];
+/// Attribute names read as the requirement statement.
+const TEXT_ATTRIBUTES: &[&str] = &[TEXT, "Object Text", "Text", "Description", "ReqIF.Name"];
+const RATIONALE_ATTRIBUTES: &[&str] = &[RATIONALE, "Rationale"];
+
+/// Relation types whose source refines their target.
+const REFINEMENT_RELATIONS: &[&str] = &["Refines", "Satisfies", "Decomposes", "Parent"];
+
+/// ReqIF errors.
+#[derive(Debug, Error)]
+pub enum ReqIfError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("XML parse error: {0}")]
+    Xml(#[from] roxmltree::Error),
+
+    #[error("Not a ReqIF document")]
+    NotReqIf,
+
+    #[error("SPEC-OBJECT {0} has no usable requirement ID")]
+    MissingId(String),
+
+    #[error(transparent)]
+    Requirement(#[from] RequirementError),
+}
+
+/// A relation between two requirements.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReqIfRelation {
+    /// Source requirement ID.
+    pub source: String,
+    /// Target requirement ID.
+    pub target: String,
+    /// Relation type name.
+    pub kind: String,
+}
+
+/// Requirements and relations read from a ReqIF document.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReqIfDocument {
+    /// Document title, if present.
+    pub title: Option<String>,
+    /// Requirements in document order.
+    pub requirements: Vec<Requirement>,
+    /// Relations between requirements in the document.
+    pub relations: Vec<ReqIfRelation>,
+}
+
+/// Parse a ReqIF document.
+pub fn parse_reqif(content: &str) -> Result<ReqIfDocument, ReqIfError> {
+    let doc = Document::parse(content)?;
+    let root = doc.root_element();
+    if root.tag_name().name() != "REQ-IF" {
+        return Err(ReqIfError::NotReqIf);
+    }
+
+    // Identifier to LONG-NAME for attribute definitions, enum values and
+    // relation types.
+    let names: HashMap<&str, &str> = root
+        .descendants()
+        .filter(|n| {
+            let tag = n.tag_name().name();
+            tag.starts_with("ATTRIBUTE-DEFINITION-")
+                || tag == "ENUM-VALUE"
+                || tag == "SPEC-RELATION-TYPE"
+        })
+        .filter_map(|n| Some((n.attribute("IDENTIFIER")?, n.attribute("LONG-NAME")?)))
+        .collect();
+
+    let title = root
+        .descendants()
+        .find(|n| n.has_tag_name("TITLE"))
+        .and_then(|n| n.text())
+        .map(|t| t.trim().to_string());
+
+    let mut requirements = Vec::new();
+    let mut ids = HashMap::new();
+    for object in root.descendants().filter(|n| n.has_tag_name("SPEC-OBJECT")) {
+        let identifier = object.attribute("IDENTIFIER").unwrap_or_default();
+        let values = attribute_values(object, &names);
+        let requirement = spec_object_requirement(identifier, values)?;
+        ids.insert(identifier, requirement.id.clone());
+        requirements.push(requirement);
+    }
+
+    let mut relations = Vec::new();
+    for relation in root
+        .descendants()
+        .filter(|n| n.has_tag_name("SPEC-RELATION"))
+    {
+        let end = |tag: &str| {
+            relation
+                .children()
+                .find(|n| n.has_tag_name(tag))
+                .and_then(|n| reference(n))
+                .and_then(|r| ids.get(r))
+        };
+        let (Some(source), Some(target)) = (end("SOURCE"), end("TARGET")) else {
+            continue;
+        };
+        let kind = relation
+            .children()
+            .find(|n| n.has_tag_name("TYPE"))
+            .and_then(|n| reference(n))
+            .map(|r| names.get(r).copied().unwrap_or(r))
+            .unwrap_or_default();
+        relations.push(ReqIfRelation {
+            source: source.clone(),
+            target: target.clone(),
+            kind: kind.to_string(),
+        });
+    }
+
+    // The first refinement of each requirement becomes its parent.
+    for relation in &relations {
+        let refines = REFINEMENT_RELATIONS
+            .iter()
+            .any(|r| r.eq_ignore_ascii_case(&relation.kind));
+        if !refines || relation.source == relation.target {
+            continue;
+        }
+        if let Some(requirement) = requirements
+            .iter_mut()
+            .find(|r| r.id == relation.source && r.parent.is_none())
+        {
+            requirement.parent = Some(relation.target.clone());
+        }
+    }
+
+    Ok(ReqIfDocument {
+        title,
+        requirements,
+        relations,
+    })
+}
+
+/// Text of the first element child, e.g. the identifier in a `*-REF`.
+fn reference<'a>(node: Node<'a, '_>) -> Option<&'a str> {
+    node.children()
+        .find(|n| n.is_element())
+        .and_then(|n| n.text())
+        .map(str::trim)
+}
+
+/// Attribute values of a SPEC-OBJECT, by attribute LONG-NAME.
+fn attribute_values(object: Node, names: &HashMap<&str, &str>) -> BTreeMap<String, String> {
+    let mut values = BTreeMap::new();
+    let Some(list) = object.children().find(|n| n.has_tag_name("VALUES")) else {
+        return values;
+    };
+
+    for value in list.children().filter(|n| n.is_element()) {
+        let Some(definition) = value
+            .children()
+            .find(|n| n.has_tag_name("DEFINITION"))
+            .and_then(|n| reference(n))
+        else {
+            continue;
+        };
+        let name = names.get(definition).copied().unwrap_or(definition);
+
+        let text = match value.tag_name().name() {
+            "ATTRIBUTE-VALUE-XHTML" => value
+                .children()
+                .find(|n| n.has_tag_name("THE-VALUE"))
+                .map(xhtml_text),
+            "ATTRIBUTE-VALUE-ENUMERATION" => value
+                .children()
+                .find(|n| n.has_tag_name("VALUES"))
+                .map(|list| {
+                    list.children()
+                        .filter(|n| n.has_tag_name("ENUM-VALUE-REF"))
+                        .filter_map(|n| n.text())
+                        .map(|r| names.get(r.trim()).copied().unwrap_or(r.trim()))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                }),
+            _ => value.attribute("THE-VALUE").map(str::to_string),
+        };
+        if let Some(text) = text {
+            values.insert(name.to_string(), text);
+        }
+    }
+    values
+}
+
+/// Plain text of an XHTML value, one line per block element.
+fn xhtml_text(node: Node) -> String {
+    let mut text = String::new();
+    for n in node.descendants() {
+        if let Some(t) = n.text().filter(|_| n.is_text()) {
+            text.push_str(t);
+        } else if matches!(n.tag_name().name(), "p" | "div" | "br" | "li") {
+            text.push('\n');
+        }
+    }
+    text.lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Build a requirement from a SPEC-OBJECT's identifier and values.
+fn spec_object_requirement(
+    identifier: &str,
+    mut values: BTreeMap<String, String>,
+) -> Result<Requirement, ReqIfError> {
+    let mut take = |candidates: &[&str]| {
+        let key = candidates
+            .iter()
+            .find_map(|c| values.keys().find(|k| k.eq_ignore_ascii_case(c)).cloned())?;
+        values.remove(&key)
+    };
+
+    let id = take(ID_ATTRIBUTES)
+        .map(|id| id.trim().to_string())
+        .filter(|id| is_valid_requirement_id(id))
+        .or_else(|| Some(identifier.to_string()).filter(|id| is_valid_requirement_id(id)))
+        .ok_or_else(|| ReqIfError::MissingId(identifier.to_string()))?;
+    let text = take(TEXT_ATTRIBUTES).unwrap_or_default();
+    let kind = take(&[KIND]).map(|k| k.parse()).transpose()?;
+    let status = take(&[STATUS]).map(|s| s.parse()).transpose()?;
+    let dal = take(&[DAL])
+        .map(|d| {
+            d.parse::<DesignAssuranceLevel>()
+                .map_err(|_| RequirementError::InvalidValue {
+                    field: "dal",
+                    value: d,
+                })
+        })
+        .transpose()?;
+    let rationale = take(RATIONALE_ATTRIBUTES).filter(|r| !r.is_empty());
+
+    let mut requirement = Requirement::new(id, text);
+    requirement.kind = kind.unwrap_or_default();
+    requirement.status = status.unwrap_or_default();
+    requirement.dal = dal;
+    requirement.rationale = rationale;
+    requirement.external_id = Some(identifier.to_string());
+    requirement.attributes = values;
+    Ok(requirement)
+}
+
+/// Import a ReqIF file into a requirements store, adding new requirements
+/// and updating existing ones by ID.
+pub fn import_reqif(store: &RequirementStore, path: &Path) -> Result<MergeSummary, ReqIfError> {
+    let content = std::fs::read_to_string(path)?;
+    let document = parse_reqif(&content)?;
+    let mut set = store.load()?;
+    let summary = set.merge(document.requirements)?;
+    store.save(&set)?;
+    Ok(summary)
+}
+
+/// Write requirements and their parent links as a ReqIF document.
+///
+/// Requirements keep their external ID as SPEC-OBJECT identifier when it
+/// is a valid XML ID, so re-importing into the originating tool updates
+/// the same objects.
+pub fn export_reqif(set: &RequirementSet, title: &str, timestamp: u64) -> String {
+    let now = format_timestamp(timestamp);
+    let builtin = [FOREIGN_ID, TEXT, KIND, STATUS, DAL, RATIONALE];
+    let custom: BTreeSet<&str> = set
+        .iter()
+        .flat_map(|r| r.attributes.keys().map(String::as_str))
+        .filter(|name| !builtin.contains(name))
+        .collect();
+    let definitions: Vec<(String, &str)> = builtin
+        .into_iter()
+        .chain(custom)
+        .enumerate()
+        .map(|(i, name)| (format!("axiom-attribute-{}", i), name))
+        .collect();
+
+    let mut used = BTreeSet::new();
+    let identifiers: HashMap<&str, String> = set
+        .iter()
+        .map(|r| {
+            let identifier = r
+                .external_id
+                .as_deref()
+                .filter(|id| is_xml_id(id) && !used.contains(*id))
+                .unwrap_or(&r.id)
+                .to_string();
+            used.insert(identifier.clone());
+            (r.id.as_str(), identifier)
+        })
+        .collect();
+
+    let mut xml = String::new();
+    let _ = writeln!(xml, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    let _ = writeln!(xml, r#"<REQ-IF xmlns="{}">"#, REQIF_NAMESPACE);
+    let _ = writeln!(xml, "  <THE-HEADER>");
+    let _ = writeln!(xml, r#"    <REQ-IF-HEADER IDENTIFIER="axiom-header">"#);
+    let _ = writeln!(xml, "      <CREATION-TIME>{}</CREATION-TIME>", now);
+    let _ = writeln!(xml, "      <REQ-IF-TOOL-ID>Axiom</REQ-IF-TOOL-ID>");
+    let _ = writeln!(xml, "      <REQ-IF-VERSION>1.0</REQ-IF-VERSION>");
+    let _ = writeln!(xml, "      <SOURCE-TOOL-ID>Axiom</SOURCE-TOOL-ID>");
+    let _ = writeln!(xml, "      <TITLE>{}</TITLE>", escape(title));
+    let _ = writeln!(xml, "    </REQ-IF-HEADER>");
+    let _ = writeln!(xml, "  </THE-HEADER>");
+    let _ = writeln!(xml, "  <CORE-CONTENT>");
+    let _ = writeln!(xml, "    <REQ-IF-CONTENT>");
+
+    let _ = writeln!(xml, "      <DATATYPES>");
+    let _ = writeln!(
+        xml,
+        r#"        <DATATYPE-DEFINITION-STRING IDENTIFIER="axiom-string" LONG-NAME="String" LAST-CHANGE="{}" MAX-LENGTH="65535"/>"#,
+        now
+    );
+    let _ = writeln!(xml, "      </DATATYPES>");
+
+    let _ = writeln!(xml, "      <SPEC-TYPES>");
+    let _ = writeln!(
+        xml,
+        r#"        <SPEC-OBJECT-TYPE IDENTIFIER="axiom-requirement" LONG-NAME="Requirement" LAST-CHANGE="{}">"#,
+        now
+    );
+    let _ = writeln!(xml, "          <SPEC-ATTRIBUTES>");
+    for (identifier, name) in &definitions {
+        let _ = writeln!(
+            xml,
+            r#"            <ATTRIBUTE-DEFINITION-STRING IDENTIFIER="{}" LONG-NAME="{}" LAST-CHANGE="{}">"#,
+            identifier,
+            escape(name),
+            now
+        );
+        let _ = writeln!(
+            xml,
+            "              <TYPE><DATATYPE-DEFINITION-STRING-REF>axiom-string</DATATYPE-DEFINITION-STRING-REF></TYPE>"
+        );
+        let _ = writeln!(xml, "            </ATTRIBUTE-DEFINITION-STRING>");
+    }
+    let _ = writeln!(xml, "          </SPEC-ATTRIBUTES>");
+    let _ = writeln!(xml, "        </SPEC-OBJECT-TYPE>");
+    let _ = writeln!(
+        xml,
+        r#"        <SPEC-RELATION-TYPE IDENTIFIER="axiom-refines" LONG-NAME="Refines" LAST-CHANGE="{}"/>"#,
+        now
+    );
+    let _ = writeln!(
+        xml,
+        r#"        <SPECIFICATION-TYPE IDENTIFIER="axiom-specification" LONG-NAME="Specification" LAST-CHANGE="{}"/>"#,
+        now
+    );
+    let _ = writeln!(xml, "      </SPEC-TYPES>");
+
+    let definition = |name: &str| {
+        definitions
+            .iter()
+            .find(|(_, n)| *n == name)
+            .map(|(identifier, _)| identifier.as_str())
+            .unwrap_or_default()
+    };
+
+    let _ = writeln!(xml, "      <SPEC-OBJECTS>");
+    for requirement in set.iter() {
+        let _ = writeln!(
+            xml,
+            r#"        <SPEC-OBJECT IDENTIFIER="{}" LAST-CHANGE="{}">"#,
+            escape(&identifiers[requirement.id.as_str()]),
+            now
+        );
+        let _ = writeln!(
+            xml,
+            "          <TYPE><SPEC-OBJECT-TYPE-REF>axiom-requirement</SPEC-OBJECT-TYPE-REF></TYPE>"
+        );
+        let _ = writeln!(xml, "          <VALUES>");
+        let mut values = vec![
+            (FOREIGN_ID, requirement.id.clone()),
+            (TEXT, requirement.text.clone()),
+            (KIND, requirement.kind.as_str().to_string()),
+            (STATUS, requirement.status.as_str().to_string()),
+        ];
+        if let Some(dal) = requirement.dal {
+            values.push((DAL, dal.to_string()));
+        }
+        if let Some(ref rationale) = requirement.rationale {
+            values.push((RATIONALE, rationale.clone()));
+        }
+        values.extend(
+            requirement
+                .attributes
+                .iter()
+                .filter(|(name, _)| !builtin.contains(&name.as_str()))
+                .map(|(name, value)| (name.as_str(), value.clone())),
+        );
+        for (name, value) in values {
+            let _ = writeln!(
+                xml,
+                r#"            <ATTRIBUTE-VALUE-STRING THE-VALUE="{}"><DEFINITION><ATTRIBUTE-DEFINITION-STRING-REF>{}</ATTRIBUTE-DEFINITION-STRING-REF></DEFINITION></ATTRIBUTE-VALUE-STRING>"#,
+                escape(&value),
+                definition(name)
+            );
+        }
+        let _ = writeln!(xml, "          </VALUES>");
+        let _ = writeln!(xml, "        </SPEC-OBJECT>");
+    }
+    let _ = writeln!(xml, "      </SPEC-OBJECTS>");
+
+    let _ = writeln!(xml, "      <SPEC-RELATIONS>");
+    for requirement in set.iter() {
+        let Some(ref parent) = requirement.parent else {
+            continue;
+        };
+        let source = &identifiers[requirement.id.as_str()];
+        let _ = writeln!(
+            xml,
+            r#"        <SPEC-RELATION IDENTIFIER="axiom-refines-{}" LAST-CHANGE="{}">"#,
+            escape(source),
+            now
+        );
+        let _ = writeln!(
+            xml,
+            "          <TYPE><SPEC-RELATION-TYPE-REF>axiom-refines</SPEC-RELATION-TYPE-REF></TYPE>"
+        );
+        let _ = writeln!(
+            xml,
+            "          <SOURCE><SPEC-OBJECT-REF>{}</SPEC-OBJECT-REF></SOURCE>",
+            escape(source)
+        );
+        let _ = writeln!(
+            xml,
+            "          <TARGET><SPEC-OBJECT-REF>{}</SPEC-OBJECT-REF></TARGET>",
+            escape(&identifiers[parent.as_str()])
+        );
+        let _ = writeln!(xml, "        </SPEC-RELATION>");
+    }
+    let _ = writeln!(xml, "      </SPEC-RELATIONS>");
+
+    let _ = writeln!(xml, "      <SPECIFICATIONS>");
+    let _ = writeln!(
+        xml,
+        r#"        <SPECIFICATION IDENTIFIER="axiom-specification-1" LONG-NAME="{}" LAST-CHANGE="{}">"#,
+        escape(title),
+        now
+    );
+    let _ = writeln!(
+        xml,
+        "          <TYPE><SPECIFICATION-TYPE-REF>axiom-specification</SPECIFICATION-TYPE-REF></TYPE>"
+    );
+    let roots: Vec<&Requirement> = set.iter().filter(|r| r.parent.is_none()).collect();
+    if !roots.is_empty() {
+        let _ = writeln!(xml, "          <CHILDREN>");
+        for requirement in roots {
+            write_hierarchy(&mut xml, set, &identifiers, requirement, &now, 12);
+        }
+        let _ = writeln!(xml, "          </CHILDREN>");
+    }
+    let _ = writeln!(xml, "        </SPECIFICATION>");
+    let _ = writeln!(xml, "      </SPECIFICATIONS>");
+
+    let _ = writeln!(xml, "    </REQ-IF-CONTENT>");
+    let _ = writeln!(xml, "  </CORE-CONTENT>");
+    let _ = writeln!(xml, "</REQ-IF>");
+    xml
+}
+
+/// Write a SPEC-HIERARCHY entry for a requirement and its children.
+fn write_hierarchy(
+    xml: &mut String,
+    set: &RequirementSet,
+    identifiers: &HashMap<&str, String>,
+    requirement: &Requirement,
+    now: &str,
+    indent: usize,
+) {
+    let pad = " ".repeat(indent);
+    let identifier = escape(&identifiers[requirement.id.as_str()]);
+    let _ = writeln!(
+        xml,
+        r#"{}<SPEC-HIERARCHY IDENTIFIER="axiom-hierarchy-{}" LAST-CHANGE="{}">"#,
+        pad, identifier, now
+    );
+    let _ = writeln!(
+        xml,
+        "{}  <OBJECT><SPEC-OBJECT-REF>{}</SPEC-OBJECT-REF></OBJECT>",
+        pad, identifier
+    );
+    let children = set.children(&requirement.id);
+    if !children.is_empty() {
+        let _ = writeln!(xml, "{}  <CHILDREN>", pad);
+        for child in children {
+            write_hierarchy(xml, set, identifiers, child, now, indent + 4);
+        }
+        let _ = writeln!(xml, "{}  </CHILDREN>", pad);
+    }
+    let _ = writeln!(xml, "{}</SPEC-HIERARCHY>", pad);
+}
+
+/// Export a requirements store to a ReqIF file.
+pub fn export_reqif_file(
+    store: &RequirementStore,
+    path: &Path,
+    title: &str,
+    timestamp: u64,
+) -> Result<(), ReqIfError> {
+    let set = store.load()?;
+    std::fs::write(path, export_reqif(&set, title, timestamp))?;
+    Ok(())
+}
+
+/// Whether a string is usable as an XML ID (an NCName, restricted to ASCII).
+fn is_xml_id(id: &str) -> bool {
+    id.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// Escape text for use in XML content and attribute values.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\n' => escaped.push_str("&#10;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{RequirementKind, RequirementStatus};
+    use tempfile::TempDir;
+
+    const DOORS_EXPORT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<REQ-IF xmlns="http://www.omg.org/spec/ReqIF/20110401/reqif.xsd" xmlns:xhtml="http://www.w3.org/1999/xhtml">
+  <THE-HEADER><REQ-IF-HEADER IDENTIFIER="h"><TITLE>Navigation SRS</TITLE></REQ-IF-HEADER></THE-HEADER>
+  <CORE-CONTENT><REQ-IF-CONTENT>
+    <DATATYPES>
+      <DATATYPE-DEFINITION-ENUMERATION IDENTIFIER="dt-prio">
+        <SPECIFIED-VALUES>
+          <ENUM-VALUE IDENTIFIER="ev-high" LONG-NAME="High"/>
+        </SPECIFIED-VALUES>
+      </DATATYPE-DEFINITION-ENUMERATION>
+    </DATATYPES>
+    <SPEC-TYPES>
+      <SPEC-OBJECT-TYPE IDENTIFIER="t">
+        <SPEC-ATTRIBUTES>
+          <ATTRIBUTE-DEFINITION-STRING IDENTIFIER="a-id" LONG-NAME="ReqIF.ForeignID"/>
+          <ATTRIBUTE-DEFINITION-XHTML IDENTIFIER="a-text" LONG-NAME="ReqIF.Text"/>
+          <ATTRIBUTE-DEFINITION-ENUMERATION IDENTIFIER="a-prio" LONG-NAME="Priority"/>
+          <ATTRIBUTE-DEFINITION-STRING IDENTIFIER="a-status" LONG-NAME="Axiom.Status"/>
+        </SPEC-ATTRIBUTES>
+      </SPEC-OBJECT-TYPE>
+      <SPEC-RELATION-TYPE IDENTIFIER="rt-sat" LONG-NAME="Satisfies"/>
+    </SPEC-TYPES>
+    <SPEC-OBJECTS>
+      <SPEC-OBJECT IDENTIFIER="_obj1">
+        <VALUES>
+          <ATTRIBUTE-VALUE-STRING THE-VALUE="SRS-1"><DEFINITION><ATTRIBUTE-DEFINITION-STRING-REF>a-id</ATTRIBUTE-DEFINITION-STRING-REF></DEFINITION></ATTRIBUTE-VALUE-STRING>
+          <ATTRIBUTE-VALUE-XHTML><DEFINITION><ATTRIBUTE-DEFINITION-XHTML-REF>a-text</ATTRIBUTE-DEFINITION-XHTML-REF></DEFINITION>
+            <THE-VALUE><xhtml:div><xhtml:p>Compute   position</xhtml:p><xhtml:p>every cycle.</xhtml:p></xhtml:div></THE-VALUE>
+          </ATTRIBUTE-VALUE-XHTML>
+          <ATTRIBUTE-VALUE-ENUMERATION><DEFINITION><ATTRIBUTE-DEFINITION-ENUMERATION-REF>a-prio</ATTRIBUTE-DEFINITION-ENUMERATION-REF></DEFINITION>
+            <VALUES><ENUM-VALUE-REF>ev-high</ENUM-VALUE-REF></VALUES>
+          </ATTRIBUTE-VALUE-ENUMERATION>
+          <ATTRIBUTE-VALUE-STRING THE-VALUE="Approved"><DEFINITION><ATTRIBUTE-DEFINITION-STRING-REF>a-status</ATTRIBUTE-DEFINITION-STRING-REF></DEFINITION></ATTRIBUTE-VALUE-STRING>
+        </VALUES>
+      </SPEC-OBJECT>
+      <SPEC-OBJECT IDENTIFIER="_obj2">
+        <VALUES>
+          <ATTRIBUTE-VALUE-STRING THE-VALUE="SRS-2"><DEFINITION><ATTRIBUTE-DEFINITION-STRING-REF>a-id</ATTRIBUTE-DEFINITION-STRING-REF></DEFINITION></ATTRIBUTE-VALUE-STRING>
+        </VALUES>
+      </SPEC-OBJECT>
+    </SPEC-OBJECTS>
+    <SPEC-RELATIONS>
+      <SPEC-RELATION IDENTIFIER="r1">
+        <TYPE><SPEC-RELATION-TYPE-REF>rt-sat</SPEC-RELATION-TYPE-REF></TYPE>
+        <SOURCE><SPEC-OBJECT-REF>_obj2</SPEC-OBJECT-REF></SOURCE>
+        <TARGET><SPEC-OBJECT-REF>_obj1</SPEC-OBJECT-REF></TARGET>
+      </SPEC-RELATION>
+    </SPEC-RELATIONS>
+  </REQ-IF-CONTENT></CORE-CONTENT>
+</REQ-IF>"#;
+
+    #[test]
+    fn test_parse_doors_export() {
+        let doc = parse_reqif(DOORS_EXPORT).unwrap();
+        assert_eq!(doc.title.as_deref(), Some("Navigation SRS"));
+        assert_eq!(doc.requirements.len(), 2);
+
+        let first = &doc.requirements[0];
+        assert_eq!(first.id, "SRS-1");
+        assert_eq!(first.text, "Compute position\nevery cycle.");
+        assert_eq!(first.status, RequirementStatus::Approved);
+        assert_eq!(first.external_id.as_deref(), Some("_obj1"));
+        assert_eq!(first.attributes["Priority"], "High");
+
+        assert_eq!(doc.requirements[1].parent.as_deref(), Some("SRS-1"));
+        assert_eq!(doc.relations[0].kind, "Satisfies");
+
+        assert!(matches!(parse_reqif("<SPEC/>"), Err(ReqIfError::NotReqIf)));
+    }
+
+    #[test]
+    fn test_export_round_trip() {
+        let mut set = RequirementSet::new();
+        set.insert(
+            Requirement::new("REQ-1", "Limit <speed> & \"rate\"")
+                .with_dal(DesignAssuranceLevel::B)
+                .with_rationale("Structural\nlimit")
+                .with_attribute("Priority", "High"),
+        )
+        .unwrap();
+        let mut child = Requirement::new("REQ-2", "Clamp output")
+            .with_kind(RequirementKind::LowLevel)
+            .with_parent("REQ-1");
+        child.external_id = Some("_doors42".to_string());
+        set.insert(child).unwrap();
+
+        let xml = export_reqif(&set, "Nav", 0);
+        assert!(xml.contains("<CREATION-TIME>1970-01-01T00:00:00Z</CREATION-TIME>"));
+        assert!(xml.contains(r#"<SPEC-OBJECT IDENTIFIER="_doors42""#));
+
+        let doc = parse_reqif(&xml).unwrap();
+        let imported: Vec<_> = doc.requirements.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(imported, ["REQ-1", "REQ-2"]);
+        assert_eq!(doc.requirements[0].text, "Limit <speed> & \"rate\"");
+        assert_eq!(
+            doc.requirements[0].rationale.as_deref(),
+            Some("Structural\nlimit")
+        );
+        assert_eq!(doc.requirements[0].dal, Some(DesignAssuranceLevel::B));
+        assert_eq!(doc.requirements[0].attributes["Priority"], "High");
+        assert_eq!(doc.requirements[1].kind, RequirementKind::LowLevel);
+        assert_eq!(doc.requirements[1].parent.as_deref(), Some("REQ-1"));
+        assert_eq!(doc.requirements[1].external_id.as_deref(), Some("_doors42"));
+    }
+
+    #[test]
+    fn test_import_into_store() {
+        let dir = TempDir::new().unwrap();
+        let store = RequirementStore::for_project(dir.path());
+        let path = dir.path().join("srs.reqif");
+        std::fs::write(&path, DOORS_EXPORT).unwrap();
+
+        let summary = import_reqif(&store, &path).unwrap();
+        assert_eq!(summary.created, ["SRS-1", "SRS-2"]);
+        let summary = import_reqif(&store, &path).unwrap();
+        assert!(summary.created.is_empty() && summary.updated.is_empty());
+
+        let out = dir.path().join("out.reqif");
+        export_reqif_file(&store, &out, "Nav", 0).unwrap();
+        let doc = parse_reqif(&std::fs::read_to_string(out).unwrap()).unwrap();
+        assert_eq!(doc.requirements.len(), 2);
+    }
+}
@@ -0,0 +1,302 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Structural coverage per requirement.
+//!
+//! Joins the traceability matrix with coverage data: each requirement's
+//! coverage is the sum over the functions that implement it (including
+//! derived links), measured over their line ranges. Requirements whose code
+//! falls below the DAL threshold, or whose code has no coverage data at all,
+//! are reported as below threshold.
+
+use crate::{CoverageShortfall, DalPolicy, LinkType, SourceFunction, TraceabilityMatrix};
+use axiom_toolchain::{CoverageReport, CoverageSummary};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// An implementing function and its coverage.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImplementingFunction {
+    /// Function name.
+    pub name: String,
+    /// Source file, relative to the project root.
+    pub file: PathBuf,
+    /// Coverage of its lines; `None` if the coverage data has no record of
+    /// the file.
+    pub summary: Option<CoverageSummary>,
+}
+
+/// Coverage of one requirement's implementation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RequirementCoverage {
+    /// Requirement ID.
+    pub requirement: String,
+    /// Functions implementing it, by file and name.
+    pub functions: Vec<ImplementingFunction>,
+    /// Totals across the measured functions.
+    pub summary: CoverageSummary,
+    /// Measurable criteria below the policy threshold.
+    pub shortfalls: Vec<CoverageShortfall>,
+}
+
+impl RequirementCoverage {
+    /// Implementing functions without coverage data.
+    pub fn unmeasured(&self) -> Vec<&ImplementingFunction> {
+        self.functions
+            .iter()
+            .filter(|f| f.summary.is_none())
+            .collect()
+    }
+}
+
+/// Coverage of every implemented requirement.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RequirementCoverageReport {
+    /// Requirements with implementing functions, by ID.
+    pub requirements: Vec<RequirementCoverage>,
+    /// Whether the policy requires structural coverage.
+    pub coverage_required: bool,
+}
+
+impl RequirementCoverageReport {
+    /// Requirements whose implementation misses the coverage threshold or
+    /// is unmeasured, when coverage is required.
+    pub fn below_threshold(&self) -> Vec<&RequirementCoverage> {
+        if !self.coverage_required {
+            return Vec::new();
+        }
+        self.requirements
+            .iter()
+            .filter(|r| !r.shortfalls.is_empty() || !r.unmeasured().is_empty())
+            .collect()
+    }
+}
+
+/// Aggregate coverage per requirement over its implementing functions.
+pub fn requirement_coverage(
+    matrix: &TraceabilityMatrix,
+    coverage: &CoverageReport,
+    policy: &DalPolicy,
+) -> RequirementCoverageReport {
+    let mut implementing: BTreeMap<&str, BTreeMap<(&PathBuf, &str), &SourceFunction>> =
+        BTreeMap::new();
+    for link in matrix
+        .links
+        .iter()
+        .filter(|l| l.link_type != LinkType::Tests)
+    {
+        let Some(name) = &link.function else {
+            continue;
+        };
+        // Doxygen links in headers name functions defined elsewhere
+        let Some(function) = matrix
+            .functions
+            .iter()
+            .filter(|f| &f.name == name)
+            .min_by_key(|f| f.file != link.file)
+        else {
+            continue;
+        };
+        implementing
+            .entry(&link.requirement)
+            .or_default()
+            .insert((&function.file, &function.name), function);
+    }
+
+    let requirements = implementing
+        .into_iter()
+        .map(|(requirement, functions)| {
+            let functions: Vec<ImplementingFunction> = functions
+                .into_values()
+                .map(|function| ImplementingFunction {
+                    name: function.name.clone(),
+                    file: function.file.clone(),
+                    summary: coverage
+                        .files
+                        .iter()
+                        .find(|f| f.path.ends_with(&function.file))
+                        .map(|f| f.range_summary(function.line, function.end_line)),
+                })
+                .collect();
+            let summary = functions
+                .iter()
+                .filter_map(|f| f.summary)
+                .fold(CoverageSummary::default(), |a, b| a + b);
+            RequirementCoverage {
+                requirement: requirement.to_string(),
+                shortfalls: policy.coverage_shortfalls(&summary),
+                functions,
+                summary,
+            }
+        })
+        .collect();
+
+    RequirementCoverageReport {
+        requirements,
+        coverage_required: policy.coverage_criterion.is_some(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ComplianceMode, CoverageCriterion, DesignAssuranceLevel, FileTrace, TraceLink};
+    use axiom_toolchain::{BranchCoverage, FileCoverage, LineCoverage};
+    use std::path::Path;
+
+    fn function(name: &str, line: u32, end_line: u32) -> SourceFunction {
+        SourceFunction {
+            name: name.to_string(),
+            file: PathBuf::from("src/nav.c"),
+            line,
+            end_line,
+        }
+    }
+
+    fn link(requirement: &str, function: &str, link_type: LinkType) -> TraceLink {
+        TraceLink {
+            requirement: requirement.to_string(),
+            file: PathBuf::from("src/nav.c"),
+            line: 1,
+            function: Some(function.to_string()),
+            link_type,
+        }
+    }
+
+    #[test]
+    fn test_requirement_coverage() {
+        let matrix = TraceabilityMatrix::new(
+            Vec::new(),
+            vec![FileTrace {
+                links: vec![
+                    link("REQ-1", "nav_update", LinkType::Implements),
+                    link("REQ-1", "nav_clamp", LinkType::Implements),
+                    link("REQ-2", "nav_clamp", LinkType::Derived),
+                    link("REQ-3", "test_nav", LinkType::Tests),
+                    link("REQ-4", "boot", LinkType::Implements),
+                ],
+                functions: vec![
+                    function("nav_update", 1, 4),
+                    function("nav_clamp", 6, 9),
+                    function("test_nav", 11, 12),
+                ],
+            }],
+        );
+        let mut file = FileCoverage::new("/build/src/nav.c");
+        for (line, count) in [(2, 1), (3, 1), (7, 1), (8, 0)] {
+            file.lines.push(LineCoverage {
+                line,
+                count,
+                unexecuted_block: false,
+            });
+        }
+        let coverage = CoverageReport::from_files([file]);
+
+        let policy = DalPolicy::new(DesignAssuranceLevel::C, &[ComplianceMode::Do178c].into())
+            .with_coverage_threshold(70.0);
+        let report = requirement_coverage(&matrix, &coverage, &policy);
+        assert_eq!(report.requirements.len(), 2);
+        let req1 = &report.requirements[0];
+        assert_eq!(req1.requirement, "REQ-1");
+        assert_eq!(req1.functions.len(), 2);
+        assert_eq!(
+            (req1.summary.lines.covered, req1.summary.lines.total),
+            (3, 4)
+        );
+        assert!(req1.unmeasured().is_empty());
+
+        let below: Vec<_> = report
+            .below_threshold()
+            .iter()
+            .map(|r| r.requirement.as_str())
+            .collect();
+        assert_eq!(below, ["REQ-2"]);
+
+        let policy = DalPolicy::new(DesignAssuranceLevel::E, &Default::default());
+        let report = requirement_coverage(&matrix, &CoverageReport::default(), &policy);
+        assert!(report.below_threshold().is_empty());
+        assert_eq!(
+            report.requirements[0].unmeasured()[0].file,
+            Path::new("src/nav.c")
+        );
+    }
+
+    #[test]
+    fn test_threshold_boundary() {
+        let matrix = TraceabilityMatrix::new(
+            Vec::new(),
+            vec![FileTrace {
+                links: vec![
+                    link("REQ-1", "nav_update", LinkType::Implements),
+                    link("REQ-2", "nav_clamp", LinkType::Implements),
+                ],
+                functions: vec![function("nav_update", 1, 4), function("nav_clamp", 6, 9)],
+            }],
+        );
+        // nav_update fully covered; nav_clamp at half its lines and branches
+        let mut file = FileCoverage::new("src/nav.c");
+        for (line, count) in [(2, 1), (3, 1), (7, 1), (8, 0)] {
+            file.lines.push(LineCoverage {
+                line,
+                count,
+                unexecuted_block: false,
+            });
+        }
+        for (line, index, count) in [(3, 0, 1), (3, 1, 1), (7, 0, 1), (7, 1, 0)] {
+            file.branches.push(BranchCoverage {
+                line,
+                index,
+                count,
+                executed: true,
+                fallthrough: false,
+                throw: false,
+            });
+        }
+        let coverage = CoverageReport::from_files([file]);
+
+        let below = |dal, threshold: Option<f64>| -> Vec<(String, Vec<CoverageCriterion>)> {
+            let mut policy = DalPolicy::new(dal, &[ComplianceMode::Do178c].into());
+            if let Some(threshold) = threshold {
+                policy = policy.with_coverage_threshold(threshold);
+            }
+            requirement_coverage(&matrix, &coverage, &policy)
+                .below_threshold()
+                .iter()
+                .map(|r| {
+                    let criteria = r.shortfalls.iter().map(|s| s.criterion).collect();
+                    (r.requirement.clone(), criteria)
+                })
+                .collect()
+        };
+        let statement = CoverageCriterion::Statement;
+        let decision = CoverageCriterion::Decision;
+
+        // DAL C: statement coverage, 100% unless relaxed
+        assert_eq!(
+            below(DesignAssuranceLevel::C, None),
+            [("REQ-2".to_string(), vec![statement])]
+        );
+        assert!(below(DesignAssuranceLevel::C, Some(50.0)).is_empty());
+        assert_eq!(
+            below(DesignAssuranceLevel::C, Some(50.1)),
+            [("REQ-2".to_string(), vec![statement])]
+        );
+
+        // DAL B adds decision coverage, judged against the same threshold
+        assert!(below(DesignAssuranceLevel::B, Some(50.0)).is_empty());
+        assert_eq!(
+            below(DesignAssuranceLevel::B, Some(50.1)),
+            [("REQ-2".to_string(), vec![statement, decision])]
+        );
+
+        // DAL A requires MC/DC, which is unmeasured and never a shortfall
+        assert_eq!(
+            below(DesignAssuranceLevel::A, None),
+            [("REQ-2".to_string(), vec![statement, decision])]
+        );
+
+        // DAL D requires no structural coverage
+        assert!(below(DesignAssuranceLevel::D, Some(100.0)).is_empty());
+    }
+}
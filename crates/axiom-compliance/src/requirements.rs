@@ -0,0 +1,799 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Requirements database.
+//!
+//! Requirements are stored in `.axiom/requirements.json` in the project
+//! root, so they can be committed and reviewed alongside the code that
+//! implements them. Each requirement may refine a parent; the store keeps
+//! identifiers unique and the parent hierarchy acyclic.
+
+use crate::DesignAssuranceLevel;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Current version of the requirements file format.
+pub const REQUIREMENTS_VERSION: u32 = 1;
+
+/// Requirements database errors.
+#[derive(Debug, Error)]
+pub enum RequirementError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("JSON error in {path}: {source}")]
+    Json {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+
+    #[error("Requirements file version {0} is newer than supported")]
+    UnsupportedVersion(u32),
+
+    #[error("Invalid requirement ID: {0:?}")]
+    InvalidId(String),
+
+    #[error("Invalid requirement {field}: {value:?}")]
+    InvalidValue { field: &'static str, value: String },
+
+    #[error("Requirement already exists: {0}")]
+    Duplicate(String),
+
+    #[error("Requirement not found: {0}")]
+    NotFound(String),
+
+    #[error("Parent {parent} of {id} does not exist")]
+    UnknownParent { id: String, parent: String },
+
+    #[error("Making {parent} the parent of {id} would create a cycle")]
+    Cycle { id: String, parent: String },
+
+    #[error("Requirement {0} has children")]
+    HasChildren(String),
+}
+
+/// Level of a requirement in the hierarchy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RequirementKind {
+    /// High-level software requirement.
+    #[default]
+    HighLevel,
+    /// Low-level software requirement.
+    LowLevel,
+}
+
+impl RequirementKind {
+    /// Serialized name.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RequirementKind::HighLevel => "high-level",
+            RequirementKind::LowLevel => "low-level",
+        }
+    }
+}
+
+impl FromStr for RequirementKind {
+    type Err = RequirementError;
+
+    /// Parse a kind, ignoring case and accepting spaces or underscores for
+    /// dashes, plus the `HLR`/`LLR` abbreviations.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match normalize(s).as_str() {
+            "high-level" | "hlr" => Ok(RequirementKind::HighLevel),
+            "low-level" | "llr" => Ok(RequirementKind::LowLevel),
+            _ => Err(RequirementError::InvalidValue {
+                field: "kind",
+                value: s.to_string(),
+            }),
+        }
+    }
+}
+
+/// Lifecycle state of a requirement.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RequirementStatus {
+    /// Being written.
+    #[default]
+    Draft,
+    /// Submitted for review.
+    InReview,
+    /// Reviewed and baselined.
+    Approved,
+    /// No longer applicable; kept for history.
+    Retired,
+}
+
+impl RequirementStatus {
+    /// Serialized name.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RequirementStatus::Draft => "draft",
+            RequirementStatus::InReview => "in-review",
+            RequirementStatus::Approved => "approved",
+            RequirementStatus::Retired => "retired",
+        }
+    }
+}
+
+impl FromStr for RequirementStatus {
+    type Err = RequirementError;
+
+    /// Parse a status, ignoring case and accepting spaces or underscores
+    /// for dashes.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match normalize(s).as_str() {
+            "draft" => Ok(RequirementStatus::Draft),
+            "in-review" => Ok(RequirementStatus::InReview),
+            "approved" => Ok(RequirementStatus::Approved),
+            "retired" => Ok(RequirementStatus::Retired),
+            _ => Err(RequirementError::InvalidValue {
+                field: "status",
+                value: s.to_string(),
+            }),
+        }
+    }
+}
+
+/// Lowercase a name and turn spaces and underscores into dashes.
+fn normalize(s: &str) -> String {
+    s.trim().to_lowercase().replace([' ', '_'], "-")
+}
+
+/// A requirement.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Requirement {
+    /// Unique identifier (e.g. `REQ-NAV-012`).
+    pub id: String,
+    /// Requirement statement.
+    pub text: String,
+    /// Level in the hierarchy.
+    #[serde(default)]
+    pub kind: RequirementKind,
+    /// Lifecycle state.
+    #[serde(default)]
+    pub status: RequirementStatus,
+    /// Requirement this one refines.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    /// Assurance level, when it differs from the project's.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dal: Option<DesignAssuranceLevel>,
+    /// Why the requirement exists. Required for derived requirements.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rationale: Option<String>,
+    /// Derived requirement: not traceable to a higher-level requirement.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub derived: bool,
+    /// A derived requirement has been provided to the system safety
+    /// assessment process.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub safety_feedback: bool,
+    /// Identifier in the tool the requirement was imported from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external_id: Option<String>,
+    /// Further attributes, by name, kept for round-tripping with other tools.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub attributes: BTreeMap<String, String>,
+}
+
+impl Requirement {
+    /// Create a draft high-level requirement.
+    pub fn new(id: impl Into<String>, text: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            text: text.into(),
+            kind: RequirementKind::default(),
+            status: RequirementStatus::default(),
+            parent: None,
+            dal: None,
+            rationale: None,
+            derived: false,
+            safety_feedback: false,
+            external_id: None,
+            attributes: BTreeMap::new(),
+        }
+    }
+
+    /// Set the kind.
+    pub fn with_kind(mut self, kind: RequirementKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Set the status.
+    pub fn with_status(mut self, status: RequirementStatus) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Set the parent.
+    pub fn with_parent(mut self, parent: impl Into<String>) -> Self {
+        self.parent = Some(parent.into());
+        self
+    }
+
+    /// Set the assurance level.
+    pub fn with_dal(mut self, dal: DesignAssuranceLevel) -> Self {
+        self.dal = Some(dal);
+        self
+    }
+
+    /// Set the rationale.
+    pub fn with_rationale(mut self, rationale: impl Into<String>) -> Self {
+        self.rationale = Some(rationale.into());
+        self
+    }
+
+    /// Mark the requirement derived, with the rationale that justifies it.
+    pub fn with_derived(mut self, rationale: impl Into<String>) -> Self {
+        self.derived = true;
+        self.rationale = Some(rationale.into());
+        self
+    }
+
+    /// Record that the requirement was provided to the safety assessment.
+    pub fn with_safety_feedback(mut self) -> Self {
+        self.safety_feedback = true;
+        self
+    }
+
+    /// Set an attribute.
+    pub fn with_attribute(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.attributes.insert(name.into(), value.into());
+        self
+    }
+
+    /// Assurance level, falling back to the project's.
+    pub fn effective_dal(&self, project: DesignAssuranceLevel) -> DesignAssuranceLevel {
+        self.dal.unwrap_or(project)
+    }
+}
+
+/// Whether a string is a usable requirement ID: ASCII letters, digits,
+/// `-`, `_` and `.`, starting with a letter.
+pub fn is_valid_requirement_id(id: &str) -> bool {
+    id.starts_with(|c: char| c.is_ascii_alphabetic())
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// Filter for listing requirements.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RequirementQuery {
+    /// Only this kind.
+    #[serde(default)]
+    pub kind: Option<RequirementKind>,
+    /// Only this status.
+    #[serde(default)]
+    pub status: Option<RequirementStatus>,
+    /// Only children of this requirement.
+    #[serde(default)]
+    pub parent: Option<String>,
+    /// Only derived (or only non-derived) requirements.
+    #[serde(default)]
+    pub derived: Option<bool>,
+    /// Only requirements whose ID or text contains this, ignoring case.
+    #[serde(default)]
+    pub text: Option<String>,
+}
+
+impl RequirementQuery {
+    /// Whether a requirement matches the query.
+    fn matches(&self, requirement: &Requirement) -> bool {
+        if self.kind.is_some_and(|kind| kind != requirement.kind) {
+            return false;
+        }
+        if self
+            .status
+            .is_some_and(|status| status != requirement.status)
+        {
+            return false;
+        }
+        if let Some(ref parent) = self.parent {
+            if requirement.parent.as_ref() != Some(parent) {
+                return false;
+            }
+        }
+        if self.derived.is_some_and(|d| d != requirement.derived) {
+            return false;
+        }
+        if let Some(ref text) = self.text {
+            let needle = text.to_lowercase();
+            if !requirement.id.to_lowercase().contains(&needle)
+                && !requirement.text.to_lowercase().contains(&needle)
+            {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// A validated set of requirements, ordered by ID.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RequirementSet {
+    requirements: BTreeMap<String, Requirement>,
+}
+
+impl RequirementSet {
+    /// Create an empty set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of requirements.
+    pub fn len(&self) -> usize {
+        self.requirements.len()
+    }
+
+    /// Whether the set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.requirements.is_empty()
+    }
+
+    /// Look up a requirement.
+    pub fn get(&self, id: &str) -> Option<&Requirement> {
+        self.requirements.get(id)
+    }
+
+    /// Whether a requirement exists.
+    pub fn contains(&self, id: &str) -> bool {
+        self.requirements.contains_key(id)
+    }
+
+    /// All requirements, ordered by ID.
+    pub fn iter(&self) -> impl Iterator<Item = &Requirement> {
+        self.requirements.values()
+    }
+
+    /// Requirements matching a query, ordered by ID.
+    pub fn query(&self, query: &RequirementQuery) -> Vec<&Requirement> {
+        self.iter().filter(|r| query.matches(r)).collect()
+    }
+
+    /// Direct children of a requirement.
+    pub fn children(&self, id: &str) -> Vec<&Requirement> {
+        self.iter()
+            .filter(|r| r.parent.as_deref() == Some(id))
+            .collect()
+    }
+
+    /// Add a new requirement.
+    pub fn insert(&mut self, requirement: Requirement) -> Result<(), RequirementError> {
+        if self.contains(&requirement.id) {
+            return Err(RequirementError::Duplicate(requirement.id));
+        }
+        self.check(&requirement)?;
+        self.requirements
+            .insert(requirement.id.clone(), requirement);
+        Ok(())
+    }
+
+    /// Replace an existing requirement, returning the previous version.
+    pub fn update(&mut self, requirement: Requirement) -> Result<Requirement, RequirementError> {
+        if !self.contains(&requirement.id) {
+            return Err(RequirementError::NotFound(requirement.id));
+        }
+        self.check(&requirement)?;
+        Ok(self
+            .requirements
+            .insert(requirement.id.clone(), requirement)
+            .expect("requirement exists"))
+    }
+
+    /// Remove a requirement that no other requirement refines.
+    pub fn remove(&mut self, id: &str) -> Result<Requirement, RequirementError> {
+        if !self.contains(id) {
+            return Err(RequirementError::NotFound(id.to_string()));
+        }
+        if !self.children(id).is_empty() {
+            return Err(RequirementError::HasChildren(id.to_string()));
+        }
+        Ok(self.requirements.remove(id).expect("requirement exists"))
+    }
+
+    /// Add new requirements and replace existing ones, as an import does.
+    ///
+    /// Parents are applied before their children, so the input may be in
+    /// any order. On error the set is left unchanged.
+    pub fn merge(
+        &mut self,
+        requirements: Vec<Requirement>,
+    ) -> Result<MergeSummary, RequirementError> {
+        let mut merged = self.clone();
+        let summary = merged.insert_ordered(requirements, true)?;
+        *self = merged;
+        Ok(summary)
+    }
+
+    /// Insert requirements parents first, replacing existing ones only when
+    /// `replace` is set.
+    fn insert_ordered(
+        &mut self,
+        mut pending: Vec<Requirement>,
+        replace: bool,
+    ) -> Result<MergeSummary, RequirementError> {
+        let mut summary = MergeSummary::default();
+        while !pending.is_empty() {
+            let before = pending.len();
+            let mut deferred = Vec::new();
+            for requirement in pending {
+                let ready = requirement
+                    .parent
+                    .as_ref()
+                    .is_none_or(|parent| self.contains(parent));
+                if !ready {
+                    deferred.push(requirement);
+                } else if replace && self.contains(&requirement.id) {
+                    if self.update(requirement.clone())? != requirement {
+                        summary.updated.push(requirement.id);
+                    }
+                } else {
+                    summary.created.push(requirement.id.clone());
+                    self.insert(requirement)?;
+                }
+            }
+            if deferred.len() == before {
+                // Remaining parents are missing or cyclic; report the first.
+                self.insert(deferred.remove(0))?;
+            }
+            pending = deferred;
+        }
+        Ok(summary)
+    }
+
+    /// Validate a requirement's ID and parent against the set.
+    fn check(&self, requirement: &Requirement) -> Result<(), RequirementError> {
+        if !is_valid_requirement_id(&requirement.id) {
+            return Err(RequirementError::InvalidId(requirement.id.clone()));
+        }
+
+        let Some(ref parent) = requirement.parent else {
+            return Ok(());
+        };
+        if !self.contains(parent) {
+            return Err(RequirementError::UnknownParent {
+                id: requirement.id.clone(),
+                parent: parent.clone(),
+            });
+        }
+
+        // Walk up from the new parent; reaching the requirement itself
+        // means it would become its own ancestor.
+        let mut ancestor = Some(parent);
+        while let Some(current) = ancestor {
+            if *current == requirement.id {
+                return Err(RequirementError::Cycle {
+                    id: requirement.id.clone(),
+                    parent: parent.clone(),
+                });
+            }
+            ancestor = self.get(current).and_then(|r| r.parent.as_ref());
+        }
+        Ok(())
+    }
+}
+
+/// Requirements added and changed by a merge.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MergeSummary {
+    /// IDs of new requirements.
+    pub created: Vec<String>,
+    /// IDs of existing requirements whose content changed.
+    pub updated: Vec<String>,
+}
+
+/// On-disk form of the requirements file.
+#[derive(Serialize, Deserialize)]
+struct RequirementsFile {
+    version: u32,
+    #[serde(default)]
+    requirements: Vec<Requirement>,
+}
+
+/// File-backed requirements database.
+#[derive(Debug, Clone)]
+pub struct RequirementStore {
+    path: PathBuf,
+}
+
+impl RequirementStore {
+    /// Create a store backed by the given file.
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Create the store for a project root.
+    pub fn for_project(project_root: &Path) -> Self {
+        Self::new(project_root.join(".axiom").join("requirements.json"))
+    }
+
+    /// Path of the backing file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Load all requirements; a missing file yields an empty set.
+    ///
+    /// Requirements are inserted parents first, so the file may list them
+    /// in any order.
+    pub fn load(&self) -> Result<RequirementSet, RequirementError> {
+        if !self.path.exists() {
+            return Ok(RequirementSet::new());
+        }
+
+        let content = fs::read_to_string(&self.path)?;
+        let file: RequirementsFile =
+            serde_json::from_str(&content).map_err(|source| RequirementError::Json {
+                path: self.path.clone(),
+                source,
+            })?;
+        if file.version > REQUIREMENTS_VERSION {
+            return Err(RequirementError::UnsupportedVersion(file.version));
+        }
+
+        let mut set = RequirementSet::new();
+        set.insert_ordered(file.requirements, false)?;
+        Ok(set)
+    }
+
+    /// Write all requirements, replacing the file.
+    pub fn save(&self, set: &RequirementSet) -> Result<(), RequirementError> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = RequirementsFile {
+            version: REQUIREMENTS_VERSION,
+            requirements: set.iter().cloned().collect(),
+        };
+        let json =
+            serde_json::to_string_pretty(&file).map_err(|source| RequirementError::Json {
+                path: self.path.clone(),
+                source,
+            })?;
+        fs::write(&self.path, json + "\n")?;
+        Ok(())
+    }
+
+    /// Add a requirement.
+    pub fn create(&self, requirement: Requirement) -> Result<(), RequirementError> {
+        let mut set = self.load()?;
+        set.insert(requirement)?;
+        self.save(&set)
+    }
+
+    /// Replace a requirement, returning the previous version.
+    pub fn update(&self, requirement: Requirement) -> Result<Requirement, RequirementError> {
+        let mut set = self.load()?;
+        let previous = set.update(requirement)?;
+        self.save(&set)?;
+        Ok(previous)
+    }
+
+    /// Delete a requirement, returning it.
+    pub fn delete(&self, id: &str) -> Result<Requirement, RequirementError> {
+        let mut set = self.load()?;
+        let removed = set.remove(id)?;
+        self.save(&set)?;
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn hierarchy() -> RequirementSet {
+        let mut set = RequirementSet::new();
+        set.insert(Requirement::new("REQ-NAV-001", "Compute position"))
+            .unwrap();
+        set.insert(
+            Requirement::new("REQ-NAV-002", "Filter GPS fixes")
+                .with_kind(RequirementKind::LowLevel)
+                .with_parent("REQ-NAV-001"),
+        )
+        .unwrap();
+        set
+    }
+
+    #[test]
+    fn test_insert_validation() {
+        let mut set = hierarchy();
+        assert!(matches!(
+            set.insert(Requirement::new("REQ-NAV-001", "Again")),
+            Err(RequirementError::Duplicate(_))
+        ));
+        assert!(matches!(
+            set.insert(Requirement::new("1 bad", "")),
+            Err(RequirementError::InvalidId(_))
+        ));
+        assert!(matches!(
+            set.insert(Requirement::new("REQ-NAV-003", "").with_parent("REQ-NAV-999")),
+            Err(RequirementError::UnknownParent { .. })
+        ));
+        assert_eq!(set.children("REQ-NAV-001").len(), 1);
+    }
+
+    #[test]
+    fn test_update_and_remove() {
+        let mut set = hierarchy();
+        let cyclic = set
+            .get("REQ-NAV-001")
+            .unwrap()
+            .clone()
+            .with_parent("REQ-NAV-002");
+        assert!(matches!(
+            set.update(cyclic),
+            Err(RequirementError::Cycle { .. })
+        ));
+
+        let approved = set
+            .get("REQ-NAV-002")
+            .unwrap()
+            .clone()
+            .with_status(RequirementStatus::Approved);
+        let previous = set.update(approved).unwrap();
+        assert_eq!(previous.status, RequirementStatus::Draft);
+        assert!(matches!(
+            set.update(Requirement::new("REQ-NAV-404", "")),
+            Err(RequirementError::NotFound(_))
+        ));
+
+        assert!(matches!(
+            set.remove("REQ-NAV-001"),
+            Err(RequirementError::HasChildren(_))
+        ));
+        set.remove("REQ-NAV-002").unwrap();
+        set.remove("REQ-NAV-001").unwrap();
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn test_parse_kind_and_status() {
+        assert_eq!(
+            "LLR".parse::<RequirementKind>().unwrap(),
+            RequirementKind::LowLevel
+        );
+        assert_eq!(
+            "High Level".parse::<RequirementKind>().unwrap(),
+            RequirementKind::HighLevel
+        );
+        assert_eq!(
+            "In_Review".parse::<RequirementStatus>().unwrap(),
+            RequirementStatus::InReview
+        );
+        assert!("done".parse::<RequirementStatus>().is_err());
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut set = hierarchy();
+        let summary = set
+            .merge(vec![
+                Requirement::new("REQ-NAV-004", "Smooth heading").with_parent("REQ-NAV-003"),
+                Requirement::new("REQ-NAV-003", "Compute heading"),
+                Requirement::new("REQ-NAV-001", "Compute position and velocity"),
+                set.get("REQ-NAV-002").unwrap().clone(),
+            ])
+            .unwrap();
+        assert_eq!(summary.created, ["REQ-NAV-003", "REQ-NAV-004"]);
+        assert_eq!(summary.updated, ["REQ-NAV-001"]);
+        assert_eq!(set.len(), 4);
+
+        let before = set.clone();
+        assert!(set
+            .merge(vec![
+                Requirement::new("REQ-NAV-005", ""),
+                Requirement::new("REQ-NAV-006", "").with_parent("REQ-NAV-999"),
+            ])
+            .is_err());
+        assert_eq!(set, before);
+    }
+
+    #[test]
+    fn test_query() {
+        let set = hierarchy();
+        let query = RequirementQuery {
+            kind: Some(RequirementKind::LowLevel),
+            ..Default::default()
+        };
+        assert_eq!(set.query(&query)[0].id, "REQ-NAV-002");
+
+        let query = RequirementQuery {
+            text: Some("position".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(set.query(&query)[0].id, "REQ-NAV-001");
+
+        let query = RequirementQuery {
+            parent: Some("REQ-NAV-001".to_string()),
+            status: Some(RequirementStatus::Approved),
+            ..Default::default()
+        };
+        assert!(set.query(&query).is_empty());
+
+        let query = RequirementQuery {
+            derived: Some(true),
+            ..Default::default()
+        };
+        assert!(set.query(&query).is_empty());
+    }
+
+    #[test]
+    fn test_store_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let store = RequirementStore::for_project(dir.path());
+        assert!(store.load().unwrap().is_empty());
+
+        store
+            .create(
+                Requirement::new("REQ-NAV-001", "Compute position")
+                    .with_dal(DesignAssuranceLevel::B)
+                    .with_rationale("Needed for guidance"),
+            )
+            .unwrap();
+        store
+            .create(Requirement::new("REQ-NAV-002", "Filter GPS fixes").with_parent("REQ-NAV-001"))
+            .unwrap();
+        let set = store.load().unwrap();
+        assert_eq!(set.len(), 2);
+        assert_eq!(
+            set.get("REQ-NAV-001").unwrap().dal,
+            Some(DesignAssuranceLevel::B)
+        );
+
+        store
+            .update(
+                Requirement::new("REQ-NAV-002", "Reject stale GPS fixes")
+                    .with_parent("REQ-NAV-001"),
+            )
+            .unwrap();
+        assert_eq!(
+            store.load().unwrap().get("REQ-NAV-002").unwrap().text,
+            "Reject stale GPS fixes"
+        );
+        store.delete("REQ-NAV-002").unwrap();
+        assert_eq!(store.load().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_load_out_of_order_and_version() {
+        let dir = TempDir::new().unwrap();
+        let store = RequirementStore::for_project(dir.path());
+        fs::create_dir_all(store.path().parent().unwrap()).unwrap();
+
+        fs::write(
+            store.path(),
+            r#"{"version": 1, "requirements": [
+                {"id": "LLR-2", "text": "b", "kind": "low-level", "parent": "HLR-1"},
+                {"id": "HLR-1", "text": "a"}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(store.load().unwrap().len(), 2);
+
+        fs::write(
+            store.path(),
+            r#"{"version": 1, "requirements": [{"id": "LLR-2", "text": "b", "parent": "HLR-9"}]}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            store.load(),
+            Err(RequirementError::UnknownParent { .. })
+        ));
+
+        fs::write(store.path(), r#"{"version": 2}"#).unwrap();
+        assert!(matches!(
+            store.load(),
+            Err(RequirementError::UnsupportedVersion(2))
+        ));
+    }
+}
@@ -0,0 +1,478 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! CSV exchange for requirements.
+//!
+//! Spreadsheets and DOORS module exports name their columns freely, so the
+//! column for each requirement field is configured in
+//! `.axiom/requirements-csv.toml`:
+//!
+//! ```toml
+//! id = "Absolute Number"
+//! id_prefix = "SRS-"
+//! text = "Object Text"
+//! parent = "Parent ID"
+//! delimiter = ";"
+//! ```
+//!
+//! Column names match case-insensitively. Columns not mapped to a field are
+//! kept as requirement attributes unless `keep_unmapped` is false, and are
+//! written back out on export.
+
+use crate::{
+    DesignAssuranceLevel, MergeSummary, Requirement, RequirementError, RequirementSet,
+    RequirementStore,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// CSV exchange errors.
+#[derive(Debug, Error)]
+pub enum CsvError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("TOML parse error in {path}: {source}")]
+    Toml {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+
+    #[error("CSV has no {0:?} column")]
+    MissingColumn(String),
+
+    #[error("Unterminated quoted field starting on line {0}")]
+    UnterminatedQuote(usize),
+
+    #[error("Row {row}: {source}")]
+    Row {
+        row: usize,
+        source: RequirementError,
+    },
+
+    #[error(transparent)]
+    Requirement(#[from] RequirementError),
+}
+
+/// Column names for each requirement field.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CsvMapping {
+    /// Field separator.
+    pub delimiter: char,
+    /// Requirement ID column.
+    pub id: String,
+    /// Prefix added to IDs on import and removed on export, for sources
+    /// that number requirements without one.
+    pub id_prefix: Option<String>,
+    /// Requirement statement column.
+    pub text: String,
+    /// Kind column.
+    pub kind: Option<String>,
+    /// Status column.
+    pub status: Option<String>,
+    /// Parent ID column.
+    pub parent: Option<String>,
+    /// Assurance level column.
+    pub dal: Option<String>,
+    /// Rationale column.
+    pub rationale: Option<String>,
+    /// Column holding the identifier in the source tool.
+    pub external_id: Option<String>,
+    /// Keep unmapped columns as attributes.
+    pub keep_unmapped: bool,
+}
+
+impl Default for CsvMapping {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            id: "ID".to_string(),
+            id_prefix: None,
+            text: "Text".to_string(),
+            kind: Some("Kind".to_string()),
+            status: Some("Status".to_string()),
+            parent: Some("Parent".to_string()),
+            dal: Some("DAL".to_string()),
+            rationale: Some("Rationale".to_string()),
+            external_id: Some("External ID".to_string()),
+            keep_unmapped: true,
+        }
+    }
+}
+
+impl CsvMapping {
+    /// Mapped column names, in export order.
+    fn columns(&self) -> Vec<&str> {
+        [
+            Some(&self.id),
+            Some(&self.text),
+            self.kind.as_ref(),
+            self.status.as_ref(),
+            self.parent.as_ref(),
+            self.dal.as_ref(),
+            self.rationale.as_ref(),
+            self.external_id.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+        .map(String::as_str)
+        .collect()
+    }
+
+    /// Prefix a source ID.
+    fn import_id(&self, id: &str) -> String {
+        match self.id_prefix {
+            Some(ref prefix) if !id.starts_with(prefix.as_str()) => format!("{}{}", prefix, id),
+            _ => id.to_string(),
+        }
+    }
+
+    /// Strip the prefix from an ID.
+    fn export_id<'a>(&self, id: &'a str) -> &'a str {
+        self.id_prefix
+            .as_deref()
+            .and_then(|prefix| id.strip_prefix(prefix))
+            .unwrap_or(id)
+    }
+}
+
+/// Path of a project's CSV column mapping.
+pub fn csv_mapping_path(project_root: &Path) -> PathBuf {
+    project_root.join(".axiom").join("requirements-csv.toml")
+}
+
+/// Load a project's CSV column mapping; a missing file gives the default
+/// columns, which are also what export writes.
+pub fn load_csv_mapping(project_root: &Path) -> Result<CsvMapping, CsvError> {
+    let path = csv_mapping_path(project_root);
+    if !path.is_file() {
+        return Ok(CsvMapping::default());
+    }
+    let content = std::fs::read_to_string(&path)?;
+    toml::from_str(&content).map_err(|source| CsvError::Toml { path, source })
+}
+
+/// Split CSV text into records of fields (RFC 4180: quoted fields may hold
+/// delimiters, doubled quotes and line breaks).
+fn parse_records(content: &str, delimiter: char) -> Result<Vec<Vec<String>>, CsvError> {
+    let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut quote_line = 0;
+    let mut line = 1;
+    let mut chars = content.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => quoted = false,
+                _ => {
+                    if c == '\n' {
+                        line += 1;
+                    }
+                    field.push(c);
+                }
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() => {
+                quoted = true;
+                quote_line = line;
+            }
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                line += 1;
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            _ if c == delimiter => record.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    if quoted {
+        return Err(CsvError::UnterminatedQuote(quote_line));
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+
+    records.retain(|r| r.iter().any(|f| !f.trim().is_empty()));
+    Ok(records)
+}
+
+/// Read requirements from CSV text.
+pub fn parse_requirements_csv(
+    content: &str,
+    mapping: &CsvMapping,
+) -> Result<Vec<Requirement>, CsvError> {
+    let mut records = parse_records(content, mapping.delimiter)?.into_iter();
+    let Some(header) = records.next() else {
+        return Ok(Vec::new());
+    };
+    let header: Vec<String> = header.iter().map(|h| h.trim().to_string()).collect();
+    let column = |name: &str| header.iter().position(|h| h.eq_ignore_ascii_case(name));
+    let required = |name: &str| column(name).ok_or_else(|| CsvError::MissingColumn(name.into()));
+    let optional = |name: &Option<String>| name.as_deref().and_then(column);
+
+    let id = required(&mapping.id)?;
+    let text = required(&mapping.text)?;
+    let kind = optional(&mapping.kind);
+    let status = optional(&mapping.status);
+    let parent = optional(&mapping.parent);
+    let dal = optional(&mapping.dal);
+    let rationale = optional(&mapping.rationale);
+    let external_id = optional(&mapping.external_id);
+    let mapped: Vec<usize> = [Some(id), Some(text), kind, status, parent, dal, rationale]
+        .into_iter()
+        .chain([external_id])
+        .flatten()
+        .collect();
+
+    let mut requirements = Vec::new();
+    for (index, record) in records.enumerate() {
+        let row = index + 2;
+        let cell = |column: Option<usize>| {
+            column
+                .and_then(|c| record.get(c))
+                .map(|v| v.trim())
+                .filter(|v| !v.is_empty())
+        };
+        let at_row = |source| CsvError::Row { row, source };
+
+        let mut requirement = Requirement::new(
+            mapping.import_id(cell(Some(id)).unwrap_or_default()),
+            cell(Some(text)).unwrap_or_default(),
+        );
+        if let Some(kind) = cell(kind) {
+            requirement.kind = kind.parse().map_err(at_row)?;
+        }
+        if let Some(status) = cell(status) {
+            requirement.status = status.parse().map_err(at_row)?;
+        }
+        requirement.parent = cell(parent).map(|p| mapping.import_id(p));
+        if let Some(dal) = cell(dal) {
+            let level = dal.parse::<DesignAssuranceLevel>().map_err(|_| {
+                at_row(RequirementError::InvalidValue {
+                    field: "dal",
+                    value: dal.to_string(),
+                })
+            })?;
+            requirement.dal = Some(level);
+        }
+        requirement.rationale = cell(rationale).map(str::to_string);
+        requirement.external_id = cell(external_id).map(str::to_string);
+        if mapping.keep_unmapped {
+            for (column, name) in header.iter().enumerate() {
+                if mapped.contains(&column) || name.is_empty() {
+                    continue;
+                }
+                if let Some(value) = cell(Some(column)) {
+                    requirement
+                        .attributes
+                        .insert(name.clone(), value.to_string());
+                }
+            }
+        }
+        requirements.push(requirement);
+    }
+    Ok(requirements)
+}
+
+/// Write requirements as CSV: the mapped columns, then one column per
+/// attribute name.
+pub fn export_requirements_csv(set: &RequirementSet, mapping: &CsvMapping) -> String {
+    let mapped = mapping.columns();
+    let attributes: BTreeSet<&str> = set
+        .iter()
+        .flat_map(|r| r.attributes.keys().map(String::as_str))
+        .filter(|name| !mapped.iter().any(|m| m.eq_ignore_ascii_case(name)))
+        .collect();
+
+    let separator = mapping.delimiter.to_string();
+    let field = |s: &str| {
+        if s.contains([mapping.delimiter, '"', '\n', '\r']) {
+            format!("\"{}\"", s.replace('"', "\"\""))
+        } else {
+            s.to_string()
+        }
+    };
+
+    let header: Vec<String> = mapped
+        .iter()
+        .copied()
+        .chain(attributes.iter().copied())
+        .map(field)
+        .collect();
+    let mut out = header.join(&separator) + "\n";
+
+    for requirement in set.iter() {
+        let mut row = vec![
+            mapping.export_id(&requirement.id).to_string(),
+            requirement.text.clone(),
+        ];
+        if mapping.kind.is_some() {
+            row.push(requirement.kind.as_str().to_string());
+        }
+        if mapping.status.is_some() {
+            row.push(requirement.status.as_str().to_string());
+        }
+        if mapping.parent.is_some() {
+            let parent = requirement.parent.as_deref().unwrap_or_default();
+            row.push(mapping.export_id(parent).to_string());
+        }
+        if mapping.dal.is_some() {
+            row.push(requirement.dal.map(|d| d.to_string()).unwrap_or_default());
+        }
+        if mapping.rationale.is_some() {
+            row.push(requirement.rationale.clone().unwrap_or_default());
+        }
+        if mapping.external_id.is_some() {
+            row.push(requirement.external_id.clone().unwrap_or_default());
+        }
+        for name in &attributes {
+            row.push(
+                requirement
+                    .attributes
+                    .get(*name)
+                    .cloned()
+                    .unwrap_or_default(),
+            );
+        }
+        let row: Vec<String> = row.iter().map(|s| field(s)).collect();
+        out.push_str(&row.join(&separator));
+        out.push('\n');
+    }
+    out
+}
+
+/// Import a CSV file into a requirements store, adding new requirements
+/// and updating existing ones by ID.
+pub fn import_requirements_csv(
+    store: &RequirementStore,
+    path: &Path,
+    mapping: &CsvMapping,
+) -> Result<MergeSummary, CsvError> {
+    let content = std::fs::read_to_string(path)?;
+    let requirements = parse_requirements_csv(&content, mapping)?;
+    let mut set = store.load()?;
+    let summary = set.merge(requirements)?;
+    store.save(&set)?;
+    Ok(summary)
+}
+
+/// Export a requirements store to a CSV file.
+pub fn export_requirements_csv_file(
+    store: &RequirementStore,
+    path: &Path,
+    mapping: &CsvMapping,
+) -> Result<(), CsvError> {
+    let set = store.load()?;
+    std::fs::write(path, export_requirements_csv(&set, mapping))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{RequirementKind, RequirementStatus};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_records() {
+        let records = parse_records("a,\"b,\"\"c\"\"\nd\"\r\n\n1,2\n", ',').unwrap();
+        assert_eq!(records, vec![vec!["a", "b,\"c\"\nd"], vec!["1", "2"]]);
+        assert!(matches!(
+            parse_records("a\n\"open", ','),
+            Err(CsvError::UnterminatedQuote(2))
+        ));
+    }
+
+    #[test]
+    fn test_import_doors_columns() {
+        let mapping = CsvMapping {
+            delimiter: ';',
+            id: "Absolute Number".to_string(),
+            id_prefix: Some("SRS-".to_string()),
+            text: "Object Text".to_string(),
+            parent: Some("Parent".to_string()),
+            ..Default::default()
+        };
+        let content = "\u{feff}Absolute Number;Object Text;Parent;Status;Priority\n\
+                       1;Compute position;;approved;High\n\
+                       2;\"Filter; smooth\";1;;\n";
+        let requirements = parse_requirements_csv(content, &mapping).unwrap();
+        assert_eq!(requirements.len(), 2);
+        assert_eq!(requirements[0].id, "SRS-1");
+        assert_eq!(requirements[0].status, RequirementStatus::Approved);
+        assert_eq!(requirements[0].attributes["Priority"], "High");
+        assert_eq!(requirements[1].text, "Filter; smooth");
+        assert_eq!(requirements[1].parent.as_deref(), Some("SRS-1"));
+        assert!(requirements[1].attributes.is_empty());
+
+        let missing = CsvMapping {
+            text: "Body".to_string(),
+            ..mapping.clone()
+        };
+        assert!(matches!(
+            parse_requirements_csv(content, &missing),
+            Err(CsvError::MissingColumn(_))
+        ));
+
+        let bad = "ID,Text,Kind\nREQ-1,x,epic\n";
+        assert!(matches!(
+            parse_requirements_csv(bad, &CsvMapping::default()),
+            Err(CsvError::Row { row: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let store = RequirementStore::for_project(dir.path());
+        let mut set = RequirementSet::new();
+        set.insert(
+            Requirement::new("REQ-1", "Limit \"rate\", always")
+                .with_dal(DesignAssuranceLevel::A)
+                .with_rationale("Line one\nline two")
+                .with_attribute("Priority", "High"),
+        )
+        .unwrap();
+        set.insert(
+            Requirement::new("REQ-2", "Clamp")
+                .with_kind(RequirementKind::LowLevel)
+                .with_parent("REQ-1"),
+        )
+        .unwrap();
+
+        let path = dir.path().join("reqs.csv");
+        std::fs::write(&path, export_requirements_csv(&set, &CsvMapping::default())).unwrap();
+        let summary = import_requirements_csv(&store, &path, &CsvMapping::default()).unwrap();
+        assert_eq!(summary.created, ["REQ-1", "REQ-2"]);
+        assert_eq!(store.load().unwrap(), set);
+    }
+
+    #[test]
+    fn test_load_mapping() {
+        let dir = TempDir::new().unwrap();
+        assert_eq!(load_csv_mapping(dir.path()).unwrap(), CsvMapping::default());
+
+        let path = csv_mapping_path(dir.path());
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, "id = \"Key\"\ndelimiter = \"\\t\"\n").unwrap();
+        let mapping = load_csv_mapping(dir.path()).unwrap();
+        assert_eq!(mapping.id, "Key");
+        assert_eq!(mapping.delimiter, '\t');
+        assert_eq!(mapping.text, "Text");
+    }
+}
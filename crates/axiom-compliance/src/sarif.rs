@@ -0,0 +1,545 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! SARIF export.
+//!
+//! Compliance gaps (untraced functions, uncovered code, open deviations and
+//! the rest), static-analysis diagnostics and MISRA findings are written as
+//! a SARIF 2.1.0 log, one run per tool, for review tools and dashboards
+//! that already read SARIF. Paths under the project root are written
+//! relative to `%SRCROOT%`.
+
+use crate::{GapPriority, GapReport};
+use axiom_analysis::{MisraChecker, MisraFinding};
+use axiom_core::{Diagnostic, Location, Severity};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+/// SARIF version written.
+pub const SARIF_VERSION: &str = "2.1.0";
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// Base ID paths under the project root are relative to.
+const SOURCE_ROOT: &str = "%SRCROOT%";
+
+/// SARIF log under construction.
+#[derive(Debug, Clone)]
+pub struct SarifExport {
+    project_root: PathBuf,
+    runs: Vec<Run>,
+}
+
+impl SarifExport {
+    /// An empty log for a project.
+    pub fn new(project_root: impl Into<PathBuf>) -> Self {
+        Self {
+            project_root: project_root.into(),
+            runs: Vec::new(),
+        }
+    }
+
+    /// Add a gap report as a run of Axiom, with gap categories as rules.
+    pub fn with_gaps(mut self, report: &GapReport) -> Self {
+        let mut run = Run::new("Axiom");
+        for gap in &report.gaps {
+            let rule_id = gap.category.as_str().to_string();
+            run.add_rule(&rule_id, gap.category.label());
+            let level = match gap.priority {
+                GapPriority::High => "error",
+                GapPriority::Medium => "warning",
+                GapPriority::Low => "note",
+            };
+            let location = gap
+                .file
+                .as_ref()
+                .map(|file| self.location(file, gap.line.map(Region::line)));
+            let mut properties = BTreeMap::new();
+            properties.insert("subject".to_string(), gap.subject.clone());
+            run.results.push(SarifResult {
+                rule_id: Some(rule_id),
+                level,
+                message: Message::new(format!("{}: {}", gap.subject, gap.detail)),
+                locations: location.into_iter().collect(),
+                properties,
+            });
+        }
+        self.runs.push(run);
+        self
+    }
+
+    /// Add a static-analysis tool's diagnostics as a run, with their codes
+    /// as rules.
+    pub fn with_diagnostics(mut self, tool: &str, diagnostics: &[Diagnostic]) -> Self {
+        let mut run = Run::new(tool);
+        for diagnostic in diagnostics {
+            if let Some(code) = &diagnostic.code {
+                run.add_rule(code, code);
+            }
+            let level = match diagnostic.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+                Severity::Note => "note",
+            };
+            run.results.push(SarifResult {
+                rule_id: diagnostic.code.clone(),
+                level,
+                message: Message::new(diagnostic.message.clone()),
+                locations: diagnostic
+                    .location
+                    .iter()
+                    .map(|l| self.location(&l.path, Some(Region::of(l))))
+                    .collect(),
+                properties: BTreeMap::new(),
+            });
+        }
+        self.runs.push(run);
+        self
+    }
+
+    /// Add MISRA findings as one run per checker, with the checkers' IDs as
+    /// rules.
+    pub fn with_misra_findings(mut self, findings: &[MisraFinding]) -> Self {
+        for checker in [MisraChecker::Cppcheck, MisraChecker::PcLint] {
+            let name = match checker {
+                MisraChecker::Cppcheck => "cppcheck",
+                MisraChecker::PcLint => "PC-lint Plus",
+            };
+            let mut run = Run::new(name);
+            for finding in findings.iter().filter(|f| f.checker == checker) {
+                let description = match finding.rule.strip_prefix("Dir ") {
+                    Some(directive) => format!("MISRA C:2012 Directive {}", directive),
+                    None => format!("MISRA C:2012 Rule {}", finding.rule),
+                };
+                run.add_rule(&finding.check, &description);
+                run.results.push(SarifResult {
+                    rule_id: Some(finding.check.clone()),
+                    level: "warning",
+                    message: Message::new(format!("{}: {}", description, finding.message)),
+                    locations: finding
+                        .location
+                        .iter()
+                        .map(|l| self.location(&l.path, Some(Region::of(l))))
+                        .collect(),
+                    properties: BTreeMap::new(),
+                });
+            }
+            if !run.results.is_empty() {
+                self.runs.push(run);
+            }
+        }
+        self
+    }
+
+    /// The log as JSON.
+    pub fn render(&self) -> String {
+        let log = SarifLog {
+            schema: SARIF_SCHEMA,
+            version: SARIF_VERSION,
+            runs: &self.runs,
+        };
+        serde_json::to_string_pretty(&log).expect("SARIF serializes") + "\n"
+    }
+
+    fn location(&self, path: &Path, region: Option<Region>) -> ResultLocation {
+        let relative = path
+            .strip_prefix(&self.project_root)
+            .ok()
+            .or(path.is_relative().then_some(path));
+        let artifact_location = match relative {
+            Some(relative) => ArtifactLocation {
+                uri: uri_path(relative),
+                uri_base_id: Some(SOURCE_ROOT),
+            },
+            None => ArtifactLocation {
+                uri: format!("file://{}", uri_path(path)),
+                uri_base_id: None,
+            },
+        };
+        ResultLocation {
+            physical_location: PhysicalLocation {
+                artifact_location,
+                region,
+            },
+        }
+    }
+}
+
+/// A path with `/` separators, percent-encoded for use in a URI.
+fn uri_path(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut uri = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => {
+                uri.push(byte as char)
+            }
+            _ => {
+                let _ = write!(uri, "%{:02X}", byte);
+            }
+        }
+    }
+    uri
+}
+
+#[derive(Serialize)]
+struct SarifLog<'a> {
+    #[serde(rename = "$schema")]
+    schema: &'static str,
+    version: &'static str,
+    runs: &'a [Run],
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct Run {
+    tool: Tool,
+    results: Vec<SarifResult>,
+}
+
+impl Run {
+    fn new(name: &str) -> Self {
+        Self {
+            tool: Tool {
+                driver: Driver {
+                    name: name.to_string(),
+                    rules: Vec::new(),
+                },
+            },
+            results: Vec::new(),
+        }
+    }
+
+    fn add_rule(&mut self, id: &str, description: &str) {
+        let rules = &mut self.tool.driver.rules;
+        if !rules.iter().any(|r| r.id == id) {
+            rules.push(Rule {
+                id: id.to_string(),
+                short_description: Message::new(description.to_string()),
+            });
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct Tool {
+    driver: Driver,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct Driver {
+    name: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Rule {
+    id: String,
+    short_description: Message,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct Message {
+    text: String,
+}
+
+impl Message {
+    fn new(text: String) -> Self {
+        Self { text }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifResult {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rule_id: Option<String>,
+    level: &'static str,
+    message: Message,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    locations: Vec<ResultLocation>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    properties: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ResultLocation {
+    physical_location: PhysicalLocation,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PhysicalLocation {
+    artifact_location: ArtifactLocation,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    region: Option<Region>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ArtifactLocation {
+    uri: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    uri_base_id: Option<&'static str>,
+}
+
+/// 1-based lines and columns.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Region {
+    start_line: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    start_column: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    end_line: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    end_column: Option<u32>,
+}
+
+impl Region {
+    fn line(line: u32) -> Self {
+        Self {
+            start_line: line,
+            start_column: None,
+            end_line: None,
+            end_column: None,
+        }
+    }
+
+    fn of(location: &Location) -> Self {
+        let (start, end) = (location.range.start, location.range.end);
+        let spans = (end.line, end.column) > (start.line, start.column);
+        Self {
+            start_line: start.line + 1,
+            start_column: Some(start.column + 1),
+            end_line: spans.then_some(end.line + 1),
+            end_column: spans.then_some(end.column + 1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Gap, GapCategory};
+    use axiom_core::{Position, Range};
+
+    #[test]
+    fn test_sarif_export() {
+        let report = GapReport {
+            policy: Default::default(),
+            gaps: vec![
+                Gap {
+                    priority: GapPriority::High,
+                    category: GapCategory::UntracedFunction,
+                    subject: "nav_clamp".to_string(),
+                    file: Some(PathBuf::from("src/nav.c")),
+                    line: Some(12),
+                    detail: "No requirement traces to the function.".to_string(),
+                },
+                Gap {
+                    priority: GapPriority::Low,
+                    category: GapCategory::UntestedRequirement,
+                    subject: "REQ-1".to_string(),
+                    file: None,
+                    line: None,
+                    detail: "No passing test verifies the requirement.".to_string(),
+                },
+            ],
+        };
+        let range = Range::new(Position::new(4, 2), Position::new(4, 9));
+        let diagnostics = [
+            Diagnostic::warning("unused variable 'x'")
+                .with_code("misc-unused")
+                .with_location(Location::new(PathBuf::from("/work/nav/src/nav.c"), range)),
+            Diagnostic::error("outside").with_location(Location::new(
+                PathBuf::from("/usr/include/stdio.h"),
+                Range::new(Position::new(0, 0), Position::new(0, 0)),
+            )),
+        ];
+        let misra = [MisraFinding {
+            rule: "Dir 4.6".to_string(),
+            checker: MisraChecker::Cppcheck,
+            check: "misra-c2012-dir-4.6".to_string(),
+            location: Some(Location::new(PathBuf::from("src/nav.c"), range)),
+            message: "Use fixed-width types".to_string(),
+        }];
+
+        let json = SarifExport::new("/work/nav")
+            .with_gaps(&report)
+            .with_diagnostics("clang-tidy", &diagnostics)
+            .with_misra_findings(&misra)
+            .render();
+        let log: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(log["version"], "2.1.0");
+        let runs = log["runs"].as_array().unwrap();
+        assert_eq!(runs.len(), 3);
+
+        let gap = &runs[0]["results"][0];
+        assert_eq!(runs[0]["tool"]["driver"]["name"], "Axiom");
+        assert_eq!(
+            runs[0]["tool"]["driver"]["rules"][0]["id"],
+            "untraced-function"
+        );
+        assert_eq!(gap["level"], "error");
+        let location = &gap["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "src/nav.c");
+        assert_eq!(location["artifactLocation"]["uriBaseId"], "%SRCROOT%");
+        assert_eq!(location["region"]["startLine"], 12);
+        assert!(runs[0]["results"][1].get("locations").is_none());
+
+        let tidy = &runs[1]["results"];
+        assert_eq!(tidy[0]["ruleId"], "misc-unused");
+        let location = &tidy[0]["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "src/nav.c");
+        assert_eq!(location["region"]["startColumn"], 3);
+        assert_eq!(location["region"]["endColumn"], 10);
+        let outside = &tidy[1]["locations"][0]["physicalLocation"];
+        assert_eq!(
+            outside["artifactLocation"]["uri"],
+            "file:///usr/include/stdio.h"
+        );
+        assert!(outside["region"].get("endLine").is_none());
+
+        assert_eq!(runs[2]["tool"]["driver"]["name"], "cppcheck");
+        assert_eq!(
+            runs[2]["tool"]["driver"]["rules"][0]["shortDescription"]["text"],
+            "MISRA C:2012 Directive 4.6"
+        );
+    }
+
+    fn gap(priority: GapPriority, file: Option<&str>) -> Gap {
+        Gap {
+            priority,
+            category: GapCategory::UntestedRequirement,
+            subject: "REQ-1".to_string(),
+            file: file.map(PathBuf::from),
+            line: None,
+            detail: "No passing test verifies the requirement.".to_string(),
+        }
+    }
+
+    fn render(export: SarifExport) -> serde_json::Value {
+        serde_json::from_str(&export.render()).unwrap()
+    }
+
+    #[test]
+    fn test_empty_export() {
+        let log = render(SarifExport::new("/work/nav"));
+        assert_eq!(log["runs"], serde_json::json!([]));
+
+        let report = GapReport {
+            policy: Default::default(),
+            gaps: Vec::new(),
+        };
+        let log = render(
+            SarifExport::new("/work/nav")
+                .with_gaps(&report)
+                .with_diagnostics("clang-tidy", &[])
+                .with_misra_findings(&[]),
+        );
+        let runs = log["runs"].as_array().unwrap();
+        assert_eq!(runs.len(), 2);
+        for run in runs {
+            assert_eq!(run["results"], serde_json::json!([]));
+            assert!(run["tool"]["driver"].get("rules").is_none());
+        }
+    }
+
+    #[test]
+    fn test_uri_escaping() {
+        let range = Range::new(Position::new(0, 0), Position::new(0, 0));
+        let diagnostics = [
+            Diagnostic::warning("inside")
+                .with_location(Location::new("/work/nav/src/my file#1%.c".into(), range)),
+            Diagnostic::warning("outside")
+                .with_location(Location::new("/opt/gcc/inc/\u{fc}ber.h".into(), range)),
+        ];
+        let log = render(SarifExport::new("/work/nav").with_diagnostics("gcc", &diagnostics));
+        let uri = |i: usize| {
+            log["runs"][0]["results"][i]["locations"][0]["physicalLocation"]["artifactLocation"]
+                ["uri"]
+                .clone()
+        };
+        assert_eq!(uri(0), "src/my%20file%231%25.c");
+        assert_eq!(uri(1), "file:///opt/gcc/inc/%C3%BCber.h");
+        assert_eq!(uri_path(Path::new("src\\nav.c")), "src/nav.c");
+    }
+
+    #[test]
+    fn test_severity_levels() {
+        let report = GapReport {
+            policy: Default::default(),
+            gaps: vec![
+                gap(GapPriority::High, None),
+                gap(GapPriority::Medium, None),
+                gap(GapPriority::Low, None),
+            ],
+        };
+        let diagnostics = [
+            Diagnostic::error("e"),
+            Diagnostic::warning("w"),
+            Diagnostic::note("n"),
+        ];
+        let misra = [MisraFinding {
+            rule: "10.4".to_string(),
+            checker: MisraChecker::PcLint,
+            check: "9034".to_string(),
+            location: None,
+            message: "Mixed essential types".to_string(),
+        }];
+        let log = render(
+            SarifExport::new("/work/nav")
+                .with_gaps(&report)
+                .with_diagnostics("gcc", &diagnostics)
+                .with_misra_findings(&misra),
+        );
+        let levels = |run: usize| -> Vec<String> {
+            log["runs"][run]["results"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|r| r["level"].as_str().unwrap().to_string())
+                .collect()
+        };
+        assert_eq!(levels(0), ["error", "warning", "note"]);
+        assert_eq!(levels(1), ["error", "warning", "note"]);
+        assert_eq!(levels(2), ["warning"]);
+        assert_eq!(log["runs"][2]["tool"]["driver"]["name"], "PC-lint Plus");
+    }
+
+    #[test]
+    fn test_results_without_location() {
+        let report = GapReport {
+            policy: Default::default(),
+            gaps: vec![gap(GapPriority::Low, None)],
+        };
+        let misra = [MisraFinding {
+            rule: "Dir 1.1".to_string(),
+            checker: MisraChecker::Cppcheck,
+            check: "misra-c2012-dir-1.1".to_string(),
+            location: None,
+            message: "Implementation-defined behaviour".to_string(),
+        }];
+        let log = render(
+            SarifExport::new("/work/nav")
+                .with_gaps(&report)
+                .with_diagnostics("gcc", &[Diagnostic::error("no input files")])
+                .with_misra_findings(&misra),
+        );
+        for run in log["runs"].as_array().unwrap() {
+            let result = &run["results"][0];
+            assert!(result.get("locations").is_none());
+            assert!(result["message"]["text"].is_string());
+        }
+        assert!(log["runs"][1]["results"][0].get("ruleId").is_none());
+        assert_eq!(
+            log["runs"][2]["results"][0]["ruleId"],
+            "misra-c2012-dir-1.1"
+        );
+    }
+}
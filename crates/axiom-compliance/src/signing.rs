@@ -0,0 +1,349 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Artifact signing.
+//!
+//! Exported certification data (matrices, coverage reports, tool usage
+//! logs) is signed with a per-project Ed25519 key. The key lives in the
+//! user's key directory rather than the project tree, so it is never
+//! committed or shared along with the sources. The signature goes in a
+//! detached `<artifact>.sig` file beside the artifact, so the artifact
+//! itself is unchanged. A recipient holding the project's public key can
+//! then show the file was not altered after generation.
+
+use crate::ed25519;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Signing errors.
+#[derive(Debug, Error)]
+pub enum SigningError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("Invalid signing key in {0}")]
+    InvalidKey(PathBuf),
+
+    #[error("Invalid public key: {0}")]
+    InvalidPublicKey(String),
+
+    #[error("Unsupported signature algorithm: {0}")]
+    UnsupportedAlgorithm(String),
+
+    #[error("No random source available: {0}")]
+    Random(String),
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex<const N: usize>(s: &str) -> Option<[u8; N]> {
+    let s = s.trim();
+    if s.len() != N * 2 || !s.is_ascii() {
+        return None;
+    }
+    let mut bytes = [0u8; N];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&s[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(bytes)
+}
+
+/// An Ed25519 public key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PublicKey([u8; 32]);
+
+impl PublicKey {
+    /// Parse a hex-encoded key.
+    pub fn from_hex(s: &str) -> Result<Self, SigningError> {
+        from_hex(s)
+            .map(Self)
+            .ok_or_else(|| SigningError::InvalidPublicKey(s.to_string()))
+    }
+
+    /// Hex encoding.
+    pub fn to_hex(&self) -> String {
+        to_hex(&self.0)
+    }
+
+    /// Whether `signature` is this key's signature of `message`.
+    pub fn verify(&self, message: &[u8], signature: &[u8; 64]) -> bool {
+        ed25519::verify(&self.0, message, signature)
+    }
+}
+
+/// An Ed25519 signing key.
+#[derive(Clone)]
+pub struct SigningKey {
+    seed: [u8; 32],
+    public: PublicKey,
+}
+
+impl std::fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SigningKey")
+            .field("public", &self.public.to_hex())
+            .finish_non_exhaustive()
+    }
+}
+
+impl SigningKey {
+    /// Key from a 32-byte secret seed.
+    pub fn from_seed(seed: [u8; 32]) -> Self {
+        Self {
+            public: PublicKey(ed25519::public_key(&seed)),
+            seed,
+        }
+    }
+
+    /// Generate a key from the operating system's random source.
+    pub fn generate() -> Result<Self, SigningError> {
+        let mut seed = [0u8; 32];
+        getrandom::fill(&mut seed).map_err(|e| SigningError::Random(e.to_string()))?;
+        Ok(Self::from_seed(seed))
+    }
+
+    /// The matching public key.
+    pub fn public_key(&self) -> PublicKey {
+        self.public
+    }
+
+    /// Sign a message.
+    pub fn sign(&self, message: &[u8]) -> [u8; 64] {
+        ed25519::sign(&self.seed, message)
+    }
+}
+
+/// Path of a project's signing key in a key directory, named by a digest
+/// of the project's canonical path.
+pub fn signing_key_path(keys_dir: &Path, project_root: &Path) -> PathBuf {
+    let root = project_root
+        .canonicalize()
+        .unwrap_or_else(|_| project_root.to_path_buf());
+    let digest = to_hex(&Sha256::digest(root.as_os_str().as_encoded_bytes()));
+    keys_dir.join(format!("{}.key", &digest[..32]))
+}
+
+/// Load the signing key at `path`, generating and saving one if there is
+/// none. The key file holds the hex-encoded seed and is created readable
+/// only by its owner.
+pub fn load_or_create_signing_key(path: &Path) -> Result<SigningKey, SigningError> {
+    if path.exists() {
+        let content = std::fs::read_to_string(path)?;
+        return from_hex(&content)
+            .map(SigningKey::from_seed)
+            .ok_or_else(|| SigningError::InvalidKey(path.to_path_buf()));
+    }
+
+    let key = SigningKey::generate()?;
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = options.open(path)?;
+    writeln!(file, "{}", to_hex(&key.seed))?;
+    Ok(key)
+}
+
+/// Detached signature of an artifact, stored as JSON in `<artifact>.sig`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArtifactSignature {
+    /// Signature algorithm; always `ed25519`.
+    pub algorithm: String,
+    /// Artifact file name.
+    pub file: String,
+    /// SHA-256 of the artifact contents.
+    pub sha256: String,
+    /// When the artifact was signed (seconds since the Unix epoch).
+    pub timestamp: u64,
+    /// Hex-encoded public key of the signer.
+    pub public_key: String,
+    /// Hex-encoded signature of the signed fields.
+    pub signature: String,
+}
+
+impl ArtifactSignature {
+    /// The bytes the signature covers: file name, digest and timestamp.
+    fn message(&self) -> Vec<u8> {
+        format!(
+            "axiom-artifact\n{}\n{}\n{}\n",
+            self.file, self.sha256, self.timestamp
+        )
+        .into_bytes()
+    }
+}
+
+/// Outcome of checking an artifact's signature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SignatureStatus {
+    /// Signed by the expected key and unchanged since.
+    Valid,
+    /// The contents no longer match the signed digest.
+    Modified,
+    /// The signature file was altered or does not verify.
+    BadSignature,
+    /// The signature verifies, but with a key other than the trusted one.
+    UntrustedKey,
+    /// No signature file.
+    Unsigned,
+}
+
+/// Path of an artifact's detached signature.
+pub fn signature_path(artifact: &Path) -> PathBuf {
+    let mut name = artifact.as_os_str().to_os_string();
+    name.push(".sig");
+    PathBuf::from(name)
+}
+
+/// Sign an artifact and write its `.sig` file.
+pub fn sign_artifact(
+    key: &SigningKey,
+    artifact: &Path,
+    timestamp: u64,
+) -> Result<ArtifactSignature, SigningError> {
+    let content = std::fs::read(artifact)?;
+    let mut signature = ArtifactSignature {
+        algorithm: "ed25519".to_string(),
+        file: artifact
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        sha256: to_hex(&Sha256::digest(&content)),
+        timestamp,
+        public_key: key.public_key().to_hex(),
+        signature: String::new(),
+    };
+    signature.signature = to_hex(&key.sign(&signature.message()));
+    std::fs::write(
+        signature_path(artifact),
+        serde_json::to_string_pretty(&signature)?,
+    )?;
+    Ok(signature)
+}
+
+/// Check an artifact against its `.sig` file. With `trusted`, the
+/// signature must also be by that key; without it, any valid signature
+/// is accepted, which proves integrity but not origin.
+pub fn verify_artifact(
+    artifact: &Path,
+    trusted: Option<&PublicKey>,
+) -> Result<SignatureStatus, SigningError> {
+    let sig_path = signature_path(artifact);
+    if !sig_path.exists() {
+        return Ok(SignatureStatus::Unsigned);
+    }
+    let signature: ArtifactSignature = serde_json::from_str(&std::fs::read_to_string(&sig_path)?)?;
+    if signature.algorithm != "ed25519" {
+        return Err(SigningError::UnsupportedAlgorithm(signature.algorithm));
+    }
+
+    let (Some(public), Some(bytes)) = (
+        from_hex::<32>(&signature.public_key),
+        from_hex::<64>(&signature.signature),
+    ) else {
+        return Ok(SignatureStatus::BadSignature);
+    };
+    let public = PublicKey(public);
+    if !public.verify(&signature.message(), &bytes) {
+        return Ok(SignatureStatus::BadSignature);
+    }
+    if trusted.is_some_and(|t| *t != public) {
+        return Ok(SignatureStatus::UntrustedKey);
+    }
+    let content = std::fs::read(artifact)?;
+    if to_hex(&Sha256::digest(&content)) != signature.sha256 {
+        return Ok(SignatureStatus::Modified);
+    }
+    Ok(SignatureStatus::Valid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_key_is_created_once() {
+        let keys = TempDir::new().unwrap();
+        let project = TempDir::new().unwrap();
+        let path = signing_key_path(keys.path(), project.path());
+        assert!(path.starts_with(keys.path()));
+        assert_ne!(path, signing_key_path(keys.path(), keys.path()));
+
+        let key = load_or_create_signing_key(&path).unwrap();
+        let again = load_or_create_signing_key(&path).unwrap();
+        assert_eq!(key.public_key(), again.public_key());
+        assert_eq!(
+            PublicKey::from_hex(&key.public_key().to_hex()).unwrap(),
+            key.public_key()
+        );
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        std::fs::write(&path, "not a key").unwrap();
+        assert!(matches!(
+            load_or_create_signing_key(&path),
+            Err(SigningError::InvalidKey(_))
+        ));
+    }
+
+    #[test]
+    fn test_sign_and_verify_artifact() {
+        let dir = TempDir::new().unwrap();
+        let artifact = dir.path().join("matrix.html");
+        std::fs::write(&artifact, "<html>matrix</html>").unwrap();
+        assert_eq!(
+            verify_artifact(&artifact, None).unwrap(),
+            SignatureStatus::Unsigned
+        );
+
+        let key = SigningKey::from_seed([1; 32]);
+        let signature = sign_artifact(&key, &artifact, 1_700_000_000).unwrap();
+        assert_eq!(signature.file, "matrix.html");
+        assert!(dir.path().join("matrix.html.sig").exists());
+        assert_eq!(
+            verify_artifact(&artifact, Some(&key.public_key())).unwrap(),
+            SignatureStatus::Valid
+        );
+
+        let other = SigningKey::from_seed([2; 32]);
+        assert_eq!(
+            verify_artifact(&artifact, Some(&other.public_key())).unwrap(),
+            SignatureStatus::UntrustedKey
+        );
+
+        std::fs::write(&artifact, "<html>edited</html>").unwrap();
+        assert_eq!(
+            verify_artifact(&artifact, None).unwrap(),
+            SignatureStatus::Modified
+        );
+
+        // Re-pointing the digest at the new contents breaks the signature
+        let sig_path = signature_path(&artifact);
+        let mut forged = signature.clone();
+        forged.sha256 = to_hex(&Sha256::digest(b"<html>edited</html>"));
+        std::fs::write(&sig_path, serde_json::to_string(&forged).unwrap()).unwrap();
+        assert_eq!(
+            verify_artifact(&artifact, None).unwrap(),
+            SignatureStatus::BadSignature
+        );
+    }
+}
@@ -0,0 +1,323 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Compliance snapshots.
+//!
+//! A snapshot records the checksum of every traceable source in a project
+//! and, for each traced requirement, the files whose annotations trace it.
+//! Comparing a snapshot taken when a compliance mode was switched off with
+//! one taken now tells a deviation report which files were added, removed
+//! or modified in between and which requirements those changes touched,
+//! instead of leaving the earlier state unknown.
+//!
+//! Unlike a [`Baseline`](crate::Baseline), a snapshot has no label, matrix,
+//! coverage or tools; it is cheap enough to take on every mode change.
+
+use crate::{
+    generate_traceability_matrix, AnnotationSyntax, FileChange, TraceError, TRACE_EXTENSIONS,
+};
+use axiom_core::time::unix_now;
+use axiom_core::walk::find_files;
+use axiom_toolchain::sha256_file;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Snapshot errors.
+#[derive(Debug, Error)]
+pub enum SnapshotError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Trace(#[from] TraceError),
+}
+
+/// Sources and tracing of a project at one moment.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ComplianceSnapshot {
+    /// When the snapshot was taken (seconds since the Unix epoch).
+    pub timestamp: u64,
+    /// SHA-256 of each traceable source, by path relative to the project
+    /// root.
+    pub files: BTreeMap<PathBuf, String>,
+    /// Files tracing each requirement, by requirement ID.
+    pub requirements: BTreeMap<String, BTreeSet<PathBuf>>,
+}
+
+impl ComplianceSnapshot {
+    /// Walk a project and record its sources and traced requirements.
+    pub fn capture(project_root: &Path, syntax: &AnnotationSyntax) -> Result<Self, SnapshotError> {
+        let mut files = BTreeMap::new();
+        for path in find_files(project_root, TRACE_EXTENSIONS)? {
+            let relative = path.strip_prefix(project_root).unwrap_or(&path);
+            files.insert(relative.to_path_buf(), sha256_file(&path)?);
+        }
+
+        let matrix = generate_traceability_matrix(project_root, syntax)?;
+        let mut requirements: BTreeMap<String, BTreeSet<PathBuf>> = BTreeMap::new();
+        for link in matrix.links {
+            requirements
+                .entry(link.requirement)
+                .or_default()
+                .insert(link.file);
+        }
+
+        Ok(Self {
+            timestamp: unix_now(),
+            files,
+            requirements,
+        })
+    }
+
+    /// What changed since an earlier snapshot.
+    pub fn changes_since(&self, previous: &ComplianceSnapshot) -> SnapshotChanges {
+        let mut files = Vec::new();
+        for (path, sha256) in &self.files {
+            match previous.files.get(path) {
+                None => files.push(FileChange::Added(path.clone())),
+                Some(old) if old != sha256 => files.push(FileChange::Modified(path.clone())),
+                Some(_) => {}
+            }
+        }
+        for path in previous.files.keys() {
+            if !self.files.contains_key(path) {
+                files.push(FileChange::Removed(path.clone()));
+            }
+        }
+
+        let empty = BTreeSet::new();
+        let ids: BTreeSet<&String> = self
+            .requirements
+            .keys()
+            .chain(previous.requirements.keys())
+            .collect();
+        let mut requirements = Vec::new();
+        for id in ids {
+            let old = previous.requirements.get(id).unwrap_or(&empty);
+            let new = self.requirements.get(id).unwrap_or(&empty);
+            let change = TracingChange {
+                requirement: id.clone(),
+                added: new.difference(old).cloned().collect(),
+                removed: old.difference(new).cloned().collect(),
+                modified: new
+                    .intersection(old)
+                    .filter(|path| self.files.get(*path) != previous.files.get(*path))
+                    .cloned()
+                    .collect(),
+            };
+            if !change.is_empty() {
+                requirements.push(change);
+            }
+        }
+
+        SnapshotChanges {
+            files,
+            requirements,
+        }
+    }
+}
+
+/// How the files tracing a requirement changed between snapshots.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TracingChange {
+    /// Requirement ID.
+    pub requirement: String,
+    /// Files that began tracing it.
+    pub added: Vec<PathBuf>,
+    /// Files that stopped tracing it.
+    pub removed: Vec<PathBuf>,
+    /// Files that trace it in both and were modified.
+    pub modified: Vec<PathBuf>,
+}
+
+impl TracingChange {
+    /// Whether nothing changed.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+/// Differences between two snapshots.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotChanges {
+    /// Sources added, removed or modified.
+    pub files: Vec<FileChange>,
+    /// Requirements whose tracing files changed.
+    pub requirements: Vec<TracingChange>,
+}
+
+impl SnapshotChanges {
+    /// Whether nothing changed.
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty() && self.requirements.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write(dir: &TempDir, path: &str, content: &str) {
+        let path = dir.path().join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_capture_and_changes() {
+        let dir = TempDir::new().unwrap();
+        let syntax = AnnotationSyntax::default();
+        write(
+            &dir,
+            "src/nav.c",
+            "/* Implements REQ-1 */\nvoid nav(void) {}\n",
+        );
+        write(
+            &dir,
+            "src/gps.c",
+            "/* Implements REQ-2 */\nvoid gps(void) {}\n",
+        );
+        write(&dir, "src/util.c", "void util(void) {}\n");
+
+        let before = ComplianceSnapshot::capture(dir.path(), &syntax).unwrap();
+        assert_eq!(before.files.len(), 3);
+        assert_eq!(before.files[Path::new("src/util.c")].len(), 64);
+        assert_eq!(
+            before.requirements["REQ-1"],
+            BTreeSet::from([PathBuf::from("src/nav.c")])
+        );
+        assert!(before.changes_since(&before).is_empty());
+
+        write(
+            &dir,
+            "src/nav.c",
+            "/* Implements REQ-1 */\nvoid nav(int) {}\n",
+        );
+        write(
+            &dir,
+            "src/imu.c",
+            "/* Implements REQ-1 */\nvoid imu(void) {}\n",
+        );
+        std::fs::remove_file(dir.path().join("src/gps.c")).unwrap();
+
+        let after = ComplianceSnapshot::capture(dir.path(), &syntax).unwrap();
+        let changes = after.changes_since(&before);
+        assert_eq!(
+            changes.files,
+            [
+                FileChange::Added("src/imu.c".into()),
+                FileChange::Modified("src/nav.c".into()),
+                FileChange::Removed("src/gps.c".into()),
+            ]
+        );
+        assert_eq!(
+            changes.requirements,
+            [
+                TracingChange {
+                    requirement: "REQ-1".into(),
+                    added: vec!["src/imu.c".into()],
+                    removed: vec![],
+                    modified: vec!["src/nav.c".into()],
+                },
+                TracingChange {
+                    requirement: "REQ-2".into(),
+                    added: vec![],
+                    removed: vec!["src/gps.c".into()],
+                    modified: vec![],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_capture_missing_project() {
+        let dir = TempDir::new().unwrap();
+        let result =
+            ComplianceSnapshot::capture(&dir.path().join("missing"), &AnnotationSyntax::default());
+        assert!(matches!(result, Err(SnapshotError::Io(_))));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_capture_unreadable_source() {
+        let dir = TempDir::new().unwrap();
+        write(&dir, "src/nav.c", "void nav(void) {}\n");
+        std::os::unix::fs::symlink(dir.path().join("gone.c"), dir.path().join("src/gps.c"))
+            .unwrap();
+
+        let result = ComplianceSnapshot::capture(dir.path(), &AnnotationSyntax::default());
+        assert!(matches!(result, Err(SnapshotError::Io(_))));
+    }
+
+    fn snapshot(files: &[(&str, &str)], requirements: &[(&str, &[&str])]) -> ComplianceSnapshot {
+        ComplianceSnapshot {
+            timestamp: 0,
+            files: files
+                .iter()
+                .map(|(path, sha256)| (PathBuf::from(path), sha256.to_string()))
+                .collect(),
+            requirements: requirements
+                .iter()
+                .map(|(id, paths)| (id.to_string(), paths.iter().map(PathBuf::from).collect()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_changes_between_snapshots() {
+        let before = snapshot(
+            &[("a.c", "1"), ("b.c", "2"), ("c.c", "3")],
+            &[("REQ-1", &["a.c", "b.c"]), ("REQ-2", &["c.c"])],
+        );
+        let after = snapshot(
+            &[("a.c", "1"), ("b.c", "9"), ("c.c", "3"), ("d.c", "4")],
+            &[
+                ("REQ-1", &["b.c"]),
+                ("REQ-2", &["c.c"]),
+                ("REQ-3", &["d.c"]),
+            ],
+        );
+
+        let changes = after.changes_since(&before);
+        assert_eq!(
+            changes.files,
+            [
+                FileChange::Modified("b.c".into()),
+                FileChange::Added("d.c".into()),
+            ]
+        );
+        assert_eq!(
+            changes.requirements,
+            [
+                TracingChange {
+                    requirement: "REQ-1".into(),
+                    added: vec![],
+                    removed: vec!["a.c".into()],
+                    modified: vec!["b.c".into()],
+                },
+                TracingChange {
+                    requirement: "REQ-3".into(),
+                    added: vec!["d.c".into()],
+                    removed: vec![],
+                    modified: vec![],
+                },
+            ]
+        );
+
+        // In reverse, additions become removals
+        let reverse = before.changes_since(&after);
+        assert_eq!(
+            reverse.files,
+            [
+                FileChange::Modified("b.c".into()),
+                FileChange::Removed("d.c".into()),
+            ]
+        );
+        assert_eq!(reverse.requirements[0].added, [PathBuf::from("a.c")]);
+        assert_eq!(reverse.requirements[1].removed, [PathBuf::from("d.c")]);
+        assert!(!reverse.is_empty());
+    }
+}
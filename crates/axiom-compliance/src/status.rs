@@ -0,0 +1,264 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Compliance dashboard status.
+//!
+//! [`ComplianceStatus`] gathers the figures the dashboard shows at a
+//! glance: requirement counts, trace and structural coverage, open
+//! deviations and tool qualification. It is computed from the same sources
+//! as the gap analysis in one pass, so the frontend makes a single call.
+
+use crate::{
+    generate_gap_analysis, load_compliance_config, AnnotationSyntax, ComplianceError,
+    CoverageShortfall, DalPolicy, GapAnalysisError, GapCategory, GapPriority, GapSources,
+    RequirementStatus,
+};
+use axiom_toolchain::{
+    CoverageReport, CoverageSummary, IntegrityReport, ToolLogError, ToolQualificationLogger,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::Path;
+use thiserror::Error;
+
+/// Compliance status errors.
+#[derive(Debug, Error)]
+pub enum ComplianceStatusError {
+    #[error(transparent)]
+    Config(#[from] ComplianceError),
+
+    #[error(transparent)]
+    GapAnalysis(#[from] GapAnalysisError),
+
+    #[error(transparent)]
+    ToolLog(#[from] ToolLogError),
+}
+
+/// Requirement counts. All but `retired` count active requirements only.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RequirementCounts {
+    /// Active requirements, defined in the database or referenced in code.
+    pub active: usize,
+    /// Requirements retired in the database.
+    pub retired: usize,
+    /// Implemented by code.
+    pub implemented: usize,
+    /// Verified by a passing test, or by any test before results are
+    /// recorded.
+    pub tested: usize,
+    /// Both implemented and tested.
+    pub traced: usize,
+    /// Derived requirements.
+    pub derived: usize,
+}
+
+impl RequirementCounts {
+    /// Percentage of active requirements both implemented and tested; 100
+    /// when there are none.
+    pub fn trace_percent(&self) -> f64 {
+        if self.active == 0 {
+            100.0
+        } else {
+            self.traced as f64 * 100.0 / self.active as f64
+        }
+    }
+}
+
+/// Structural coverage against the policy.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StructuralCoverage {
+    /// Totals over every measured file.
+    pub summary: CoverageSummary,
+    /// Required criteria below the threshold.
+    pub shortfalls: Vec<CoverageShortfall>,
+}
+
+/// State of tool qualification evidence.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolQualificationStatus {
+    /// The policy requires qualified tools.
+    pub required: bool,
+    /// Logged tool invocations.
+    pub invocations: usize,
+    /// Tool binaries and versions run outside the pinned toolchains.
+    pub unqualified_tools: usize,
+    /// The usage log's hash chain verified without issues.
+    pub log_intact: bool,
+}
+
+/// Headline compliance figures for a project.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ComplianceStatus {
+    /// Policy the figures were judged against.
+    pub policy: DalPolicy,
+    /// Requirement counts.
+    pub requirements: RequirementCounts,
+    /// Percentage of active requirements both implemented and tested.
+    pub trace_coverage: f64,
+    /// Structural coverage, if measured.
+    pub structural_coverage: Option<StructuralCoverage>,
+    /// Suppressions without an approved deviation and deviations awaiting
+    /// approval.
+    pub open_deviations: usize,
+    /// Tool qualification.
+    pub tool_qualification: ToolQualificationStatus,
+    /// Gaps blocking an objective the policy requires.
+    pub high_priority_gaps: usize,
+}
+
+impl ComplianceStatus {
+    /// Load a project's compliance configuration, gap sources and tool
+    /// usage log, plus coverage if given, and compute its status.
+    pub fn compute(
+        project_root: &Path,
+        syntax: &AnnotationSyntax,
+        coverage: Option<CoverageReport>,
+    ) -> Result<Self, ComplianceStatusError> {
+        let policy = load_compliance_config(project_root)?.policy();
+        let sources = GapSources::load(project_root, syntax, coverage)?;
+        let integrity = ToolQualificationLogger::for_project(project_root).verify_integrity()?;
+        Ok(Self::from_sources(&sources, &policy, &integrity))
+    }
+
+    /// Status of already-gathered sources and tool log integrity.
+    pub fn from_sources(
+        sources: &GapSources,
+        policy: &DalPolicy,
+        integrity: &IntegrityReport,
+    ) -> Self {
+        let matrix = &sources.matrix;
+        let retired: BTreeSet<&str> = matrix
+            .requirements
+            .iter()
+            .filter(|r| r.status == RequirementStatus::Retired)
+            .map(|r| r.id.as_str())
+            .collect();
+        let untested: BTreeSet<String> = matrix.find_untested_requirements().into_iter().collect();
+        let unimplemented: BTreeSet<String> = matrix
+            .find_unimplemented_requirements()
+            .into_iter()
+            .collect();
+
+        let mut requirements = RequirementCounts {
+            retired: retired.len(),
+            derived: matrix.derived_requirements().len(),
+            ..Default::default()
+        };
+        for id in matrix.requirement_ids() {
+            if retired.contains(id) {
+                continue;
+            }
+            let implemented = !unimplemented.contains(id);
+            let tested = !untested.contains(id);
+            requirements.active += 1;
+            requirements.implemented += implemented as usize;
+            requirements.tested += tested as usize;
+            requirements.traced += (implemented && tested) as usize;
+        }
+
+        let gaps = generate_gap_analysis(sources, policy);
+        let counts = gaps.counts();
+        let count = |category| counts.get(&category).copied().unwrap_or(0);
+
+        Self {
+            policy: policy.clone(),
+            trace_coverage: requirements.trace_percent(),
+            requirements,
+            structural_coverage: sources
+                .coverage
+                .as_ref()
+                .map(|coverage| StructuralCoverage {
+                    summary: coverage.summary,
+                    shortfalls: policy.coverage_shortfalls(&coverage.summary),
+                }),
+            open_deviations: count(GapCategory::OpenDeviation),
+            tool_qualification: ToolQualificationStatus {
+                required: policy.development_tool_level.is_some()
+                    || policy.verification_tool_level.is_some(),
+                invocations: sources.tool_usage.len(),
+                unqualified_tools: count(GapCategory::UnqualifiedToolUsage),
+                log_intact: integrity.is_intact(),
+            },
+            high_priority_gaps: gaps.with_priority(GapPriority::High).len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ComplianceConfig, ComplianceMode, DesignAssuranceLevel, DeviationRecord, Requirement,
+    };
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_compute() {
+        let dir = TempDir::new().unwrap();
+        let src = dir.path().join("src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(
+            src.join("nav.c"),
+            "/* Implements REQ-1 */\nvoid nav(void) {}\n\
+             /* Implements REQ-2 */\nvoid gps(void) {}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            src.join("test_nav.c"),
+            "/* TEST: REQ-1 */\nvoid t(void) {}\n",
+        )
+        .unwrap();
+
+        let status =
+            ComplianceStatus::compute(dir.path(), &AnnotationSyntax::default(), None).unwrap();
+        assert_eq!(
+            status.requirements,
+            RequirementCounts {
+                active: 2,
+                implemented: 2,
+                tested: 1,
+                traced: 1,
+                ..Default::default()
+            }
+        );
+        assert_eq!(status.trace_coverage, 50.0);
+        assert!(status.structural_coverage.is_none());
+        assert_eq!(status.open_deviations, 0);
+        assert!(status.tool_qualification.log_intact);
+        assert_eq!(status.tool_qualification.invocations, 0);
+    }
+
+    #[test]
+    fn test_from_sources() {
+        let mut retired = Requirement::new("REQ-9", "Old");
+        retired.status = RequirementStatus::Retired;
+        let matrix = crate::TraceabilityMatrix {
+            requirements: vec![Requirement::new("REQ-1", "Navigate"), retired],
+            ..Default::default()
+        };
+        let sources = GapSources::new(matrix).with_deviations(
+            vec![DeviationRecord::new(
+                "DEV-1",
+                "Rule 11.3",
+                "Register access",
+                1,
+            )],
+            Vec::new(),
+        );
+        let policy = ComplianceConfig::new(DesignAssuranceLevel::A)
+            .with_mode(ComplianceMode::Do178c)
+            .with_mode(ComplianceMode::Do330)
+            .policy();
+        let integrity = ToolQualificationLogger::for_project(TempDir::new().unwrap().path())
+            .verify_integrity()
+            .unwrap();
+
+        let status = ComplianceStatus::from_sources(&sources, &policy, &integrity);
+        assert_eq!(status.requirements.active, 1);
+        assert_eq!(status.requirements.retired, 1);
+        assert_eq!(status.trace_coverage, 0.0);
+        assert_eq!(status.open_deviations, 1);
+        assert!(status.tool_qualification.required);
+        assert_eq!(status.high_priority_gaps, 2);
+    }
+}
@@ -0,0 +1,448 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! ARP4754A system requirements and their allocation to software.
+//!
+//! System requirements are stored in `.axiom/system-requirements.json`.
+//! Each records its validation (ARP4754A section 5.4: is the requirement
+//! correct and complete?) and the software requirements it is allocated
+//! to. The allocation report shows which system requirements reach
+//! software, which software requirements trace up to no system
+//! requirement, and which allocations name requirements that don't exist.
+
+use crate::{
+    is_valid_requirement_id, DesignAssuranceLevel, Requirement, RequirementKind, RequirementStatus,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Current version of the system requirements file format.
+pub const SYSTEM_REQUIREMENTS_VERSION: u32 = 1;
+
+/// System requirements errors.
+#[derive(Debug, Error)]
+pub enum SystemRequirementError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("JSON error in {path}: {source}")]
+    Json {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+
+    #[error("System requirements file version {0} is newer than supported")]
+    UnsupportedVersion(u32),
+
+    #[error("Invalid system requirement ID: {0:?}")]
+    InvalidId(String),
+
+    #[error("System requirement already exists: {0}")]
+    Duplicate(String),
+
+    #[error("System requirement not found: {0}")]
+    NotFound(String),
+}
+
+/// Whether a system requirement has been shown correct and complete.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ValidationStatus {
+    /// Not yet validated.
+    #[default]
+    NotValidated,
+    /// Validated with recorded evidence.
+    Validated,
+    /// Found incorrect or incomplete; needs rework.
+    Invalid,
+}
+
+/// How a system requirement was validated (ARP4754A table 6).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ValidationMethod {
+    /// Traced to a higher-level requirement or assumption.
+    Traceability,
+    /// Analysis.
+    Analysis,
+    /// Modeling or simulation.
+    Modeling,
+    /// Test.
+    Test,
+    /// Similarity to a validated system in service.
+    Similarity,
+    /// Engineering review.
+    Review,
+}
+
+/// A system-level requirement.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SystemRequirement {
+    /// Unique identifier (e.g. `SYS-NAV-001`).
+    pub id: String,
+    /// Requirement statement.
+    pub text: String,
+    /// Lifecycle state.
+    #[serde(default)]
+    pub status: RequirementStatus,
+    /// Development assurance level assigned by the safety assessment.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dal: Option<DesignAssuranceLevel>,
+    /// Validation state.
+    #[serde(default)]
+    pub validation: ValidationStatus,
+    /// How it was validated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validation_method: Option<ValidationMethod>,
+    /// Reference to the validation evidence.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validation_evidence: Option<String>,
+    /// Software requirements it is allocated to.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allocated_to: Vec<String>,
+}
+
+impl SystemRequirement {
+    /// Create a draft, unvalidated system requirement.
+    pub fn new(id: impl Into<String>, text: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            text: text.into(),
+            status: RequirementStatus::default(),
+            dal: None,
+            validation: ValidationStatus::default(),
+            validation_method: None,
+            validation_evidence: None,
+            allocated_to: Vec::new(),
+        }
+    }
+
+    /// Set the lifecycle state.
+    pub fn with_status(mut self, status: RequirementStatus) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Set the assurance level.
+    pub fn with_dal(mut self, dal: DesignAssuranceLevel) -> Self {
+        self.dal = Some(dal);
+        self
+    }
+
+    /// Record a validation and its evidence.
+    pub fn with_validation(
+        mut self,
+        method: ValidationMethod,
+        evidence: impl Into<String>,
+    ) -> Self {
+        self.validation = ValidationStatus::Validated;
+        self.validation_method = Some(method);
+        self.validation_evidence = Some(evidence.into());
+        self
+    }
+
+    /// Allocate to a software requirement.
+    pub fn with_allocation(mut self, requirement: impl Into<String>) -> Self {
+        let requirement = requirement.into();
+        if !self.allocated_to.contains(&requirement) {
+            self.allocated_to.push(requirement);
+        }
+        self
+    }
+
+    /// Whether the requirement is still in force.
+    pub fn is_active(&self) -> bool {
+        self.status != RequirementStatus::Retired
+    }
+}
+
+/// A system requirement's allocation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AllocationRow {
+    /// System requirement ID.
+    pub system: String,
+    /// Validation state.
+    pub validation: ValidationStatus,
+    /// Active software requirements it is allocated to.
+    pub software: Vec<String>,
+    /// Allocations to software requirements that don't exist or are
+    /// retired.
+    pub unknown: Vec<String>,
+}
+
+/// Allocation of system requirements to software requirements.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AllocationReport {
+    /// Active system requirements, by ID.
+    pub rows: Vec<AllocationRow>,
+    /// Active high-level software requirements, not derived and without a
+    /// parent, that no system requirement is allocated to.
+    pub unallocated_software: Vec<String>,
+}
+
+impl AllocationReport {
+    /// System requirements allocated to no software requirement.
+    pub fn unallocated(&self) -> Vec<&str> {
+        self.rows
+            .iter()
+            .filter(|r| r.software.is_empty())
+            .map(|r| r.system.as_str())
+            .collect()
+    }
+
+    /// System requirements not validated.
+    pub fn unvalidated(&self) -> Vec<&str> {
+        self.rows
+            .iter()
+            .filter(|r| r.validation != ValidationStatus::Validated)
+            .map(|r| r.system.as_str())
+            .collect()
+    }
+
+    /// Percentage of system requirements allocated to software; 100 when
+    /// there are none.
+    pub fn allocated_percent(&self) -> f64 {
+        if self.rows.is_empty() {
+            return 100.0;
+        }
+        let allocated = self.rows.len() - self.unallocated().len();
+        allocated as f64 * 100.0 / self.rows.len() as f64
+    }
+}
+
+/// Check system requirement allocations against the software requirements.
+pub fn allocation_coverage(
+    system: &[SystemRequirement],
+    software: &[Requirement],
+) -> AllocationReport {
+    let active: BTreeSet<&str> = software
+        .iter()
+        .filter(|r| r.status != RequirementStatus::Retired)
+        .map(|r| r.id.as_str())
+        .collect();
+
+    let mut rows: Vec<AllocationRow> = system
+        .iter()
+        .filter(|r| r.is_active())
+        .map(|requirement| {
+            let (software, unknown) = requirement
+                .allocated_to
+                .iter()
+                .cloned()
+                .partition(|id| active.contains(id.as_str()));
+            AllocationRow {
+                system: requirement.id.clone(),
+                validation: requirement.validation,
+                software,
+                unknown,
+            }
+        })
+        .collect();
+    rows.sort_by(|a, b| a.system.cmp(&b.system));
+
+    let allocated: BTreeSet<&str> = rows
+        .iter()
+        .flat_map(|r| r.software.iter().map(String::as_str))
+        .collect();
+    let unallocated_software = software
+        .iter()
+        .filter(|r| {
+            r.status != RequirementStatus::Retired
+                && r.kind == RequirementKind::HighLevel
+                && r.parent.is_none()
+                && !r.derived
+                && !allocated.contains(r.id.as_str())
+        })
+        .map(|r| r.id.clone())
+        .collect();
+
+    AllocationReport {
+        rows,
+        unallocated_software,
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SystemRequirementsFile {
+    version: u32,
+    requirements: Vec<SystemRequirement>,
+}
+
+/// File-backed system requirements database.
+#[derive(Debug, Clone)]
+pub struct SystemRequirementStore {
+    path: PathBuf,
+}
+
+impl SystemRequirementStore {
+    /// Create a store backed by the given file.
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Create the store for a project root.
+    pub fn for_project(project_root: &Path) -> Self {
+        Self::new(project_root.join(".axiom").join("system-requirements.json"))
+    }
+
+    /// Path of the backing file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Load all system requirements, by ID; a missing file yields none.
+    pub fn load(&self) -> Result<Vec<SystemRequirement>, SystemRequirementError> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = fs::read_to_string(&self.path)?;
+        let file: SystemRequirementsFile =
+            serde_json::from_str(&content).map_err(|source| SystemRequirementError::Json {
+                path: self.path.clone(),
+                source,
+            })?;
+        if file.version > SYSTEM_REQUIREMENTS_VERSION {
+            return Err(SystemRequirementError::UnsupportedVersion(file.version));
+        }
+        Ok(file.requirements)
+    }
+
+    fn save(&self, mut requirements: Vec<SystemRequirement>) -> Result<(), SystemRequirementError> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        requirements.sort_by(|a, b| a.id.cmp(&b.id));
+        let file = SystemRequirementsFile {
+            version: SYSTEM_REQUIREMENTS_VERSION,
+            requirements,
+        };
+        let json =
+            serde_json::to_string_pretty(&file).map_err(|source| SystemRequirementError::Json {
+                path: self.path.clone(),
+                source,
+            })?;
+        fs::write(&self.path, json + "\n")?;
+        Ok(())
+    }
+
+    /// Add a system requirement.
+    pub fn create(&self, requirement: SystemRequirement) -> Result<(), SystemRequirementError> {
+        if !is_valid_requirement_id(&requirement.id) {
+            return Err(SystemRequirementError::InvalidId(requirement.id));
+        }
+        let mut requirements = self.load()?;
+        if requirements.iter().any(|r| r.id == requirement.id) {
+            return Err(SystemRequirementError::Duplicate(requirement.id));
+        }
+        requirements.push(requirement);
+        self.save(requirements)
+    }
+
+    /// Replace a system requirement, returning the previous version.
+    pub fn update(
+        &self,
+        requirement: SystemRequirement,
+    ) -> Result<SystemRequirement, SystemRequirementError> {
+        let mut requirements = self.load()?;
+        let existing = requirements
+            .iter_mut()
+            .find(|r| r.id == requirement.id)
+            .ok_or_else(|| SystemRequirementError::NotFound(requirement.id.clone()))?;
+        let previous = std::mem::replace(existing, requirement);
+        self.save(requirements)?;
+        Ok(previous)
+    }
+
+    /// Delete a system requirement, returning it.
+    pub fn delete(&self, id: &str) -> Result<SystemRequirement, SystemRequirementError> {
+        let mut requirements = self.load()?;
+        let index = requirements
+            .iter()
+            .position(|r| r.id == id)
+            .ok_or_else(|| SystemRequirementError::NotFound(id.to_string()))?;
+        let removed = requirements.remove(index);
+        self.save(requirements)?;
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_store() {
+        let dir = TempDir::new().unwrap();
+        let store = SystemRequirementStore::for_project(dir.path());
+        assert!(store.load().unwrap().is_empty());
+
+        store
+            .create(SystemRequirement::new("SYS-2", "Report position").with_allocation("REQ-2"))
+            .unwrap();
+        store
+            .create(SystemRequirement::new("SYS-1", "Compute position"))
+            .unwrap();
+        assert!(matches!(
+            store.create(SystemRequirement::new("SYS-1", "Again")),
+            Err(SystemRequirementError::Duplicate(_))
+        ));
+        assert!(matches!(
+            store.create(SystemRequirement::new("1 SYS", "Bad")),
+            Err(SystemRequirementError::InvalidId(_))
+        ));
+
+        let validated = SystemRequirement::new("SYS-1", "Compute position")
+            .with_validation(ValidationMethod::Analysis, "SAR-12")
+            .with_allocation("REQ-1");
+        let previous = store.update(validated).unwrap();
+        assert_eq!(previous.validation, ValidationStatus::NotValidated);
+
+        let loaded = store.load().unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded[0].id, "SYS-1");
+        assert_eq!(
+            loaded[0].validation_method,
+            Some(ValidationMethod::Analysis)
+        );
+        assert_eq!(store.delete("SYS-2").unwrap().allocated_to, ["REQ-2"]);
+        assert!(matches!(
+            store.delete("SYS-2"),
+            Err(SystemRequirementError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_allocation_coverage() {
+        let system = [
+            SystemRequirement::new("SYS-1", "Compute position")
+                .with_validation(ValidationMethod::Review, "Review minutes 4")
+                .with_allocation("REQ-1")
+                .with_allocation("REQ-9"),
+            SystemRequirement::new("SYS-2", "Report position").with_allocation("REQ-OLD"),
+            SystemRequirement::new("SYS-3", "Legacy")
+                .with_status(RequirementStatus::Retired)
+                .with_allocation("REQ-2"),
+        ];
+        let software = [
+            Requirement::new("REQ-1", "Filter fixes"),
+            Requirement::new("REQ-2", "Format output"),
+            Requirement::new("REQ-3", "Clamp").with_derived("Sensor limits"),
+            Requirement::new("REQ-4", "Smooth").with_parent("REQ-1"),
+            Requirement::new("REQ-OLD", "Old").with_status(RequirementStatus::Retired),
+        ];
+
+        let report = allocation_coverage(&system, &software);
+        assert_eq!(report.rows.len(), 2);
+        assert_eq!(report.rows[0].software, ["REQ-1"]);
+        assert_eq!(report.rows[0].unknown, ["REQ-9"]);
+        assert_eq!(report.rows[1].unknown, ["REQ-OLD"]);
+        assert_eq!(report.unallocated(), ["SYS-2"]);
+        assert_eq!(report.unvalidated(), ["SYS-2"]);
+        assert_eq!(report.unallocated_software, ["REQ-2"]);
+        assert_eq!(report.allocated_percent(), 50.0);
+    }
+}
@@ -0,0 +1,280 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! JUnit/xUnit test result ingestion.
+//!
+//! Test runners (Unity, CppUTest, GoogleTest, CTest) all emit some dialect
+//! of JUnit XML. Cases are matched to `TEST:` annotations by the name of
+//! the annotated test function, so a requirement counts as verified only
+//! when a test for it actually ran and passed.
+
+use axiom_toolchain::{HostTestOutcome, HostTestRun};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Test result errors.
+#[derive(Debug, Error)]
+pub enum TestResultError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid XML in {path}: {source}")]
+    Xml {
+        path: PathBuf,
+        source: roxmltree::Error,
+    },
+
+    #[error("{0} is not a JUnit result file")]
+    NotJunit(PathBuf),
+}
+
+/// Outcome of one test case.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TestOutcome {
+    Passed,
+    Failed,
+    /// The test could not run to completion (crash, exception).
+    Error,
+    Skipped,
+}
+
+/// One `<testcase>` result.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TestCaseResult {
+    /// Enclosing test suite name.
+    pub suite: String,
+    /// Class or group name.
+    pub classname: Option<String>,
+    /// Test case name.
+    pub name: String,
+    /// Outcome.
+    pub outcome: TestOutcome,
+    /// Duration in seconds.
+    pub time: Option<f64>,
+    /// Failure, error or skip message.
+    pub message: Option<String>,
+}
+
+impl TestCaseResult {
+    /// Whether this result is for the given test function. Runners report
+    /// either the bare function name or a qualified one (`group.name`,
+    /// `suite::name`, `name()`).
+    pub fn matches_function(&self, function: &str) -> bool {
+        let name = self.name.trim_end_matches("()");
+        let last = name.rsplit(['.', ':', '/']).next().unwrap_or(name);
+        name == function || last == function
+    }
+}
+
+/// Verification state of a requirement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum VerificationStatus {
+    /// A matched test passed and none failed.
+    Passed,
+    /// At least one matched test failed or errored.
+    Failed,
+    /// Tests are linked but none has a result (or all were skipped).
+    NotRun,
+    /// No test is linked.
+    Untested,
+}
+
+impl VerificationStatus {
+    /// Lowercase label for reports.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Passed => "passed",
+            Self::Failed => "failed",
+            Self::NotRun => "not-run",
+            Self::Untested => "untested",
+        }
+    }
+
+    /// Status from the results matched to a requirement's tests.
+    pub fn from_results<'a>(
+        has_tests: bool,
+        results: impl IntoIterator<Item = &'a TestCaseResult>,
+    ) -> Self {
+        if !has_tests {
+            return Self::Untested;
+        }
+        let mut status = Self::NotRun;
+        for result in results {
+            match result.outcome {
+                TestOutcome::Failed | TestOutcome::Error => return Self::Failed,
+                TestOutcome::Passed => status = Self::Passed,
+                TestOutcome::Skipped => {}
+            }
+        }
+        status
+    }
+}
+
+/// Parse a JUnit XML document.
+///
+/// Accepts a `<testsuites>` root or a single `<testsuite>`, including
+/// nested suites.
+pub fn parse_junit(xml: &str, path: &Path) -> Result<Vec<TestCaseResult>, TestResultError> {
+    let doc = roxmltree::Document::parse(xml).map_err(|source| TestResultError::Xml {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let root = doc.root_element();
+    if !matches!(root.tag_name().name(), "testsuites" | "testsuite") {
+        return Err(TestResultError::NotJunit(path.to_path_buf()));
+    }
+
+    let mut results = Vec::new();
+    for case in root.descendants().filter(|n| n.has_tag_name("testcase")) {
+        let suite = case
+            .ancestors()
+            .find(|n| n.has_tag_name("testsuite"))
+            .and_then(|s| s.attribute("name"))
+            .unwrap_or_default();
+        let mut outcome = TestOutcome::Passed;
+        let mut message = None;
+        for child in case.children().filter(|n| n.is_element()) {
+            let child_outcome = match child.tag_name().name() {
+                "failure" => TestOutcome::Failed,
+                "error" => TestOutcome::Error,
+                "skipped" => TestOutcome::Skipped,
+                _ => continue,
+            };
+            outcome = child_outcome;
+            message = child
+                .attribute("message")
+                .or_else(|| child.text())
+                .map(|m| m.trim().to_string())
+                .filter(|m| !m.is_empty());
+            break;
+        }
+        // CppUTest and older runners mark ignored tests with an attribute.
+        if outcome == TestOutcome::Passed
+            && matches!(
+                case.attribute("status"),
+                Some("notrun" | "skipped" | "disabled")
+            )
+        {
+            outcome = TestOutcome::Skipped;
+        }
+        results.push(TestCaseResult {
+            suite: suite.to_string(),
+            classname: case.attribute("classname").map(str::to_string),
+            name: case.attribute("name").unwrap_or_default().to_string(),
+            outcome,
+            time: case.attribute("time").and_then(|t| t.parse().ok()),
+            message,
+        });
+    }
+    Ok(results)
+}
+
+/// Read and parse JUnit XML files.
+pub fn read_junit(paths: &[PathBuf]) -> Result<Vec<TestCaseResult>, TestResultError> {
+    let mut results = Vec::new();
+    for path in paths {
+        let xml = std::fs::read_to_string(path)?;
+        results.extend(parse_junit(&xml, path)?);
+    }
+    Ok(results)
+}
+
+/// Results of a host test run, named after its executable.
+pub fn host_test_results(run: &HostTestRun) -> Vec<TestCaseResult> {
+    let suite = run
+        .build
+        .executable
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    run.cases
+        .iter()
+        .map(|case| TestCaseResult {
+            suite: suite.clone(),
+            classname: None,
+            name: case.name.clone(),
+            outcome: match case.outcome {
+                HostTestOutcome::Passed => TestOutcome::Passed,
+                HostTestOutcome::Failed => TestOutcome::Failed,
+                HostTestOutcome::Skipped => TestOutcome::Skipped,
+            },
+            time: None,
+            message: case.message.clone(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JUNIT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites>
+  <testsuite name="test_nav" tests="4">
+    <testcase classname="test_nav" name="test_nav_update" time="0.002"/>
+    <testcase classname="test_nav" name="test_nav_clamp">
+      <failure message="Expected 10 Was 11">test_nav.c:42</failure>
+    </testcase>
+    <testcase classname="test_nav" name="test_nav_fault"><skipped/></testcase>
+    <testcase classname="NavGroup" name="NavGroup.test_nav_init()" status="notrun"/>
+  </testsuite>
+</testsuites>
+"#;
+
+    #[test]
+    fn test_parse_junit() {
+        let results = parse_junit(JUNIT, Path::new("results.xml")).unwrap();
+        assert_eq!(results.len(), 4);
+        assert_eq!(results[0].suite, "test_nav");
+        assert_eq!(results[0].outcome, TestOutcome::Passed);
+        assert_eq!(results[0].time, Some(0.002));
+        assert_eq!(results[1].outcome, TestOutcome::Failed);
+        assert_eq!(results[1].message.as_deref(), Some("Expected 10 Was 11"));
+        assert_eq!(results[2].outcome, TestOutcome::Skipped);
+        assert_eq!(results[3].outcome, TestOutcome::Skipped);
+        assert!(results[3].matches_function("test_nav_init"));
+        assert!(!results[0].matches_function("test_nav"));
+
+        assert!(matches!(
+            parse_junit("<html/>", Path::new("index.html")),
+            Err(TestResultError::NotJunit(_))
+        ));
+    }
+
+    #[test]
+    fn test_junit_from_host_run() {
+        let cases = axiom_toolchain::parse_host_test_output(
+            "t.c:3:test_a:PASS\nt.c:9:test_b:FAIL: Expected 1\n",
+        );
+        let xml = axiom_toolchain::host_tests_to_junit("nav", &cases, 10);
+        let results = parse_junit(&xml, Path::new("nav.junit.xml")).unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].outcome, TestOutcome::Passed);
+        assert_eq!(results[1].outcome, TestOutcome::Failed);
+        assert_eq!(results[1].message.as_deref(), Some("Expected 1"));
+    }
+
+    #[test]
+    fn test_status_from_results() {
+        let results = parse_junit(JUNIT, Path::new("results.xml")).unwrap();
+        assert_eq!(
+            VerificationStatus::from_results(false, &results),
+            VerificationStatus::Untested
+        );
+        assert_eq!(
+            VerificationStatus::from_results(true, &results[..1]),
+            VerificationStatus::Passed
+        );
+        assert_eq!(
+            VerificationStatus::from_results(true, &results),
+            VerificationStatus::Failed
+        );
+        assert_eq!(
+            VerificationStatus::from_results(true, &results[2..]),
+            VerificationStatus::NotRun
+        );
+    }
+}
@@ -0,0 +1,215 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Test skeletons for untested requirements.
+//!
+//! Each untested requirement gets a test file in the project's test
+//! directory holding one annotated test whose body is a TODO. The test is
+//! marked ignored or skipped in the configured framework, so it reports
+//! as not run rather than passing until someone writes the real check.
+
+use crate::{ComplianceConfig, TraceabilityMatrix};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Unit test framework generated tests target.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TestFramework {
+    #[default]
+    Unity,
+    Cmocka,
+    Cpputest,
+    /// Plain C with no framework; the test is an empty function.
+    Plain,
+}
+
+impl TestFramework {
+    /// Source file extension.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Cpputest => "cpp",
+            _ => "c",
+        }
+    }
+}
+
+/// A generated test file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TestSkeleton {
+    /// Requirement the test verifies.
+    pub requirement: String,
+    /// File path, relative to the project root.
+    pub path: PathBuf,
+    /// File contents.
+    pub content: String,
+}
+
+/// `REQ-NAV-001` as an identifier: `req_nav_001`.
+fn identifier(id: &str) -> String {
+    id.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Render a test file for one requirement.
+///
+/// The requirement ID appears only in the annotation; requirement text is
+/// left out because any IDs it mentions would be scanned as test links.
+pub fn render_test_skeleton(framework: TestFramework, requirement: &str) -> String {
+    let name = identifier(requirement);
+    let mut out =
+        String::from("/* Generated test skeleton: replace the TODO with test logic. */\n\n");
+    // Comments are scanned for IDs, so only the string literal names it
+    let todo = "TODO: exercise the requirement and check the result";
+    match framework {
+        TestFramework::Unity => {
+            out.push_str("#include \"unity.h\"\n\n");
+            out.push_str(&format!(
+                "// TEST: {req}\nvoid test_{name}(void)\n{{\n    TEST_IGNORE_MESSAGE(\"TODO: verify {req}\");\n}}\n",
+                req = requirement,
+            ));
+        }
+        TestFramework::Cmocka => {
+            out.push_str(
+                "#include <stdarg.h>\n#include <stddef.h>\n#include <stdint.h>\n\
+                 #include <setjmp.h>\n#include <cmocka.h>\n\n",
+            );
+            out.push_str(&format!(
+                "// TEST: {req}\nstatic void test_{name}(void **state)\n{{\n    (void)state;\n    \
+                 /* {todo} */\n    skip();\n}}\n",
+                req = requirement,
+            ));
+        }
+        TestFramework::Cpputest => {
+            let group = name.to_uppercase();
+            out.push_str("#include \"CppUTest/TestHarness.h\"\n\n");
+            out.push_str(&format!(
+                "TEST_GROUP({group})\n{{\n}};\n\n// TEST: {req}\nIGNORE_TEST({group}, Verify)\n{{\n    \
+                 // {todo}\n}}\n",
+                req = requirement,
+            ));
+        }
+        TestFramework::Plain => {
+            out.push_str(&format!(
+                "// TEST: {req}\nvoid test_{name}(void)\n{{\n    /* {todo} */\n}}\n",
+                req = requirement,
+            ));
+        }
+    }
+    out
+}
+
+/// Skeletons for the matrix's untested requirements, placed in the
+/// configured test directory as `test_<requirement>.<ext>`.
+pub fn generate_test_skeletons(
+    matrix: &TraceabilityMatrix,
+    config: &ComplianceConfig,
+) -> Vec<TestSkeleton> {
+    matrix
+        .find_untested_requirements()
+        .into_iter()
+        .map(|id| {
+            let path = config.test_dir.join(format!(
+                "test_{}.{}",
+                identifier(&id),
+                config.test_framework.extension()
+            ));
+            TestSkeleton {
+                content: render_test_skeleton(config.test_framework, &id),
+                requirement: id,
+                path,
+            }
+        })
+        .collect()
+}
+
+/// Write skeletons under a project root, leaving existing files untouched.
+/// Returns the paths written.
+pub fn write_test_skeletons(
+    project_root: &Path,
+    skeletons: &[TestSkeleton],
+) -> std::io::Result<Vec<PathBuf>> {
+    let mut written = Vec::new();
+    for skeleton in skeletons {
+        let path = project_root.join(&skeleton.path);
+        if path.exists() {
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, &skeleton.content)?;
+        written.push(skeleton.path.clone());
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_requirement_annotations, AnnotationSyntax, LinkType, Requirement};
+    use axiom_parser::{Language, Parser};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_skeletons_parse_as_test_links() {
+        let mut parser = Parser::new().unwrap();
+        for framework in [
+            TestFramework::Unity,
+            TestFramework::Cmocka,
+            TestFramework::Plain,
+        ] {
+            let source = render_test_skeleton(framework, "REQ-NAV-001");
+            let trace = parse_requirement_annotations(
+                &mut parser,
+                &AnnotationSyntax::default(),
+                Path::new("tests/test_req_nav_001.c"),
+                &source,
+                Language::C,
+            )
+            .unwrap();
+            assert_eq!(trace.links.len(), 1, "{:?}", framework);
+            assert_eq!(trace.links[0].link_type, LinkType::Tests);
+            assert_eq!(trace.links[0].function.as_deref(), Some("test_req_nav_001"));
+        }
+        let unity = render_test_skeleton(TestFramework::Unity, "REQ-NAV-001");
+        assert!(unity.contains("TEST_IGNORE_MESSAGE(\"TODO: verify REQ-NAV-001\");"));
+        let cpputest = render_test_skeleton(TestFramework::Cpputest, "REQ-NAV-001");
+        assert!(cpputest.contains("IGNORE_TEST(REQ_NAV_001, Verify)"));
+    }
+
+    #[test]
+    fn test_generate_and_write() {
+        let matrix = TraceabilityMatrix::new(
+            vec![
+                Requirement::new("REQ-1", "First"),
+                Requirement::new("REQ-2", "Second"),
+            ],
+            [],
+        );
+        let config = ComplianceConfig {
+            test_dir: PathBuf::from("test/unit"),
+            ..Default::default()
+        };
+        let skeletons = generate_test_skeletons(&matrix, &config);
+        assert_eq!(skeletons.len(), 2);
+        assert_eq!(skeletons[0].path, Path::new("test/unit/test_req_1.c"));
+
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("test/unit")).unwrap();
+        std::fs::write(dir.path().join("test/unit/test_req_2.c"), "existing").unwrap();
+        let written = write_test_skeletons(dir.path(), &skeletons).unwrap();
+        assert_eq!(written, [PathBuf::from("test/unit/test_req_1.c")]);
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("test/unit/test_req_2.c")).unwrap(),
+            "existing"
+        );
+    }
+}
@@ -0,0 +1,262 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Incremental traceability scanning.
+//!
+//! Parsing every source on each matrix refresh is slow on large trees, so
+//! per-file scan results are cached in `.axiom/trace-cache.json`, keyed by
+//! content hash. A file whose size and modification time are unchanged is
+//! not read at all; one whose content hash is unchanged is not re-parsed.
+//! Changing the annotation syntax or the Axiom version discards the cache.
+//! Editors with a file watcher can [`invalidate`](TraceCache::invalidate)
+//! saved files to force a re-check when timestamps are too coarse.
+
+use crate::{AnnotationSyntax, FileTrace};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use thiserror::Error;
+
+/// Trace cache errors.
+#[derive(Debug, Error)]
+pub enum TraceCacheError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("JSON error in {path}: {source}")]
+    Json {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+}
+
+/// Scan result for one file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct CachedTrace {
+    sha256: String,
+    len: u64,
+    modified: Option<u64>,
+    trace: FileTrace,
+}
+
+/// Size and modification time (nanoseconds since the epoch) of a file.
+pub(crate) type FileStamp = (u64, Option<u64>);
+
+/// Cached per-file scan results, by path relative to the project root.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TraceCache {
+    version: String,
+    syntax: String,
+    files: BTreeMap<PathBuf, CachedTrace>,
+}
+
+impl TraceCache {
+    /// Number of cached files.
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Whether nothing is cached.
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Force a file to be re-checked on the next scan.
+    pub fn invalidate(&mut self, file: &Path) {
+        if let Some(cached) = self.files.get_mut(file) {
+            cached.modified = None;
+        }
+    }
+
+    /// Discard the cache if it was built by another Axiom version or with
+    /// another annotation syntax.
+    pub(crate) fn prepare(&mut self, syntax: &AnnotationSyntax) {
+        let version = env!("CARGO_PKG_VERSION");
+        let fingerprint = syntax.fingerprint();
+        if self.version != version || self.syntax != fingerprint {
+            *self = Self {
+                version: version.to_string(),
+                syntax: fingerprint,
+                files: BTreeMap::new(),
+            };
+        }
+    }
+
+    /// The cached trace of a file whose size and modification time are
+    /// unchanged.
+    pub(crate) fn by_stamp(&self, file: &Path, stamp: FileStamp) -> Option<&FileTrace> {
+        let cached = self.files.get(file)?;
+        (stamp.1.is_some() && (cached.len, cached.modified) == stamp).then_some(&cached.trace)
+    }
+
+    /// The cached trace of a file whose content is unchanged, recording its
+    /// new stamp.
+    pub(crate) fn by_content(
+        &mut self,
+        file: &Path,
+        stamp: FileStamp,
+        sha256: &str,
+    ) -> Option<&FileTrace> {
+        let cached = self.files.get_mut(file).filter(|c| c.sha256 == sha256)?;
+        (cached.len, cached.modified) = stamp;
+        Some(&cached.trace)
+    }
+
+    /// Record a file's scan result.
+    pub(crate) fn insert(
+        &mut self,
+        file: &Path,
+        stamp: FileStamp,
+        sha256: String,
+        trace: FileTrace,
+    ) {
+        let (len, modified) = stamp;
+        self.files.insert(
+            file.to_path_buf(),
+            CachedTrace {
+                sha256,
+                len,
+                modified,
+                trace,
+            },
+        );
+    }
+
+    /// Drop files not in `files`.
+    pub(crate) fn retain(&mut self, files: &[PathBuf]) {
+        self.files.retain(|path, _| files.contains(path));
+    }
+}
+
+/// Size and modification time of a file.
+pub(crate) fn file_stamp(path: &Path) -> std::io::Result<FileStamp> {
+    let metadata = fs::metadata(path)?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_nanos() as u64);
+    Ok((metadata.len(), modified))
+}
+
+/// Hex SHA-256 of file content.
+pub(crate) fn content_sha256(content: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(content))
+}
+
+/// Project trace cache file.
+pub struct TraceCacheStore {
+    path: PathBuf,
+}
+
+impl TraceCacheStore {
+    /// Create a store backed by the given file.
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Create the store for a project root.
+    pub fn for_project(project_root: &Path) -> Self {
+        Self::new(project_root.join(".axiom").join("trace-cache.json"))
+    }
+
+    /// Path of the backing file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Load the cache; a missing file yields an empty one.
+    pub fn load(&self) -> Result<TraceCache, TraceCacheError> {
+        if !self.path.exists() {
+            return Ok(TraceCache::default());
+        }
+        let content = fs::read_to_string(&self.path)?;
+        serde_json::from_str(&content).map_err(|source| TraceCacheError::Json {
+            path: self.path.clone(),
+            source,
+        })
+    }
+
+    /// Save the cache.
+    pub fn save(&self, cache: &TraceCache) -> Result<(), TraceCacheError> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string(cache).map_err(|source| TraceCacheError::Json {
+            path: self.path.clone(),
+            source,
+        })?;
+        fs::write(&self.path, json)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate_traceability_matrix_cached;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_incremental_scan() {
+        let dir = TempDir::new().unwrap();
+        let nav = dir.path().join("nav.c");
+        std::fs::write(&nav, "// REQ-1\nvoid nav(void) {}\n").unwrap();
+        std::fs::write(dir.path().join("boot.c"), "// REQ-2\nvoid boot(void) {}\n").unwrap();
+        let syntax = AnnotationSyntax::default();
+
+        let mut cache = TraceCache::default();
+        let matrix = generate_traceability_matrix_cached(dir.path(), &syntax, &mut cache).unwrap();
+        assert_eq!(matrix.links.len(), 2);
+        assert_eq!(cache.len(), 2);
+
+        // A cached trace is reused while the stamp matches, even if stale
+        let stamp = file_stamp(&nav).unwrap();
+        let mut stale = cache.files[Path::new("nav.c")].trace.clone();
+        stale.links.clear();
+        cache.insert(Path::new("nav.c"), stamp, "stale".into(), stale);
+        let matrix = generate_traceability_matrix_cached(dir.path(), &syntax, &mut cache).unwrap();
+        assert_eq!(matrix.links.len(), 1);
+
+        // Invalidating re-hashes the file and re-parses changed content
+        cache.invalidate(Path::new("nav.c"));
+        let matrix = generate_traceability_matrix_cached(dir.path(), &syntax, &mut cache).unwrap();
+        assert_eq!(matrix.links.len(), 2);
+        assert_ne!(cache.files[Path::new("nav.c")].sha256, "stale");
+
+        std::fs::remove_file(dir.path().join("boot.c")).unwrap();
+        let matrix = generate_traceability_matrix_cached(dir.path(), &syntax, &mut cache).unwrap();
+        assert_eq!(matrix.links.len(), 1);
+        assert_eq!(cache.len(), 1);
+
+        let other = AnnotationSyntax::new(
+            vec!["SRS-".into()],
+            Vec::new(),
+            vec!["TEST".into()],
+            Vec::new(),
+        )
+        .unwrap();
+        let matrix = generate_traceability_matrix_cached(dir.path(), &other, &mut cache).unwrap();
+        assert!(matrix.links.is_empty());
+    }
+
+    #[test]
+    fn test_store_round_trip() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("nav.c"), "// REQ-1\nvoid nav(void) {}\n").unwrap();
+        let store = TraceCacheStore::for_project(dir.path());
+        assert!(store.load().unwrap().is_empty());
+
+        let mut cache = TraceCache::default();
+        generate_traceability_matrix_cached(dir.path(), &AnnotationSyntax::default(), &mut cache)
+            .unwrap();
+        store.save(&cache).unwrap();
+        assert_eq!(store.load().unwrap(), cache);
+
+        std::fs::write(store.path(), "{").unwrap();
+        assert!(matches!(store.load(), Err(TraceCacheError::Json { .. })));
+    }
+}
@@ -0,0 +1,1253 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Requirement traceability.
+//!
+//! Source comments link code to requirements:
+//!
+//! ```c
+//! /* Implements REQ-NAV-012 */
+//! void nav_update(void) { ... }
+//!
+//! // TEST: REQ-NAV-012
+//! void test_nav_update(void) { ... }
+//!
+//! // DERIVED: REQ-NAV-090
+//! static void nav_clamp(void) { ... }
+//! ```
+//!
+//! A `TEST:` marker makes a verification link and `DERIVED:` a derived
+//! link; any other mention implements the requirement. ID prefixes and
+//! markers are configurable (see [`AnnotationSyntax`]). A link belongs to
+//! the function whose body holds the comment, or to the function directly
+//! below it. The matrix joins these links with the requirements database.
+//!
+//! Doxygen blocks may use tags instead:
+//!
+//! ```c
+//! /**
+//!  * @brief Update the navigation solution.
+//!  * @req REQ-NAV-012
+//!  */
+//! void nav_update(void);
+//! ```
+//!
+//! `@req` and `\satisfies` implement, `@verifies` verifies and `@derived`
+//! derives. A Doxygen block belongs to the function its `\fn` command
+//! names, or else to the definition or prototype it documents, so tags in
+//! headers trace to the function they describe.
+//!
+//! Assembly sources (`.s`, `.S`) and linker scripts (`.ld`, `.lds`) are
+//! scanned too, since startup code and memory layout are requirement-driven.
+//! Assembly comments may use `@`, `;`, `//`, `/* */` or a leading `#`, and
+//! a link belongs to the function label holding or following it. Linker
+//! script links belong to no function.
+
+use crate::{
+    content_sha256, file_stamp, AnnotationSyntax, Requirement, RequirementError, RequirementStatus,
+    RequirementStore, TestCaseResult, TraceCache, TraceCacheStore, VerificationStatus,
+};
+use axiom_core::walk::{find_files, has_extension};
+use axiom_parser::{Language, ParseError, Parser};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Source file extensions scanned for annotations.
+pub const TRACE_EXTENSIONS: &[&str] = &[
+    "c", "h", "cc", "cpp", "cxx", "hh", "hpp", "hxx", "s", "ld", "lds",
+];
+
+/// Assembly source extensions (`.S` matches too).
+pub const ASSEMBLY_EXTENSIONS: &[&str] = &["s"];
+
+/// Linker script extensions.
+pub const LINKER_SCRIPT_EXTENSIONS: &[&str] = &["ld", "lds"];
+
+const FUNCTION_QUERY: &str = r#"
+(function_definition declarator: (function_declarator declarator: (identifier) @name)) @function
+(function_definition declarator: (pointer_declarator declarator: (function_declarator declarator: (identifier) @name))) @function
+"#;
+
+const PROTOTYPE_QUERY: &str = r#"
+(declaration declarator: (function_declarator declarator: (identifier) @name)) @prototype
+(declaration declarator: (pointer_declarator declarator: (function_declarator declarator: (identifier) @name))) @prototype
+"#;
+
+/// Traceability errors.
+#[derive(Debug, Error)]
+pub enum TraceError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Parse error in {path}: {source}")]
+    Parse { path: PathBuf, source: ParseError },
+
+    #[error(transparent)]
+    Requirement(#[from] RequirementError),
+}
+
+/// How code relates to a requirement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LinkType {
+    /// Code implements the requirement.
+    Implements,
+    /// A test verifies the requirement.
+    Tests,
+    /// Code implements a derived requirement.
+    Derived,
+}
+
+impl LinkType {
+    /// Serialized name.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LinkType::Implements => "implements",
+            LinkType::Tests => "tests",
+            LinkType::Derived => "derived",
+        }
+    }
+}
+
+/// A requirement annotation in source.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TraceLink {
+    /// Requirement ID.
+    pub requirement: String,
+    /// Source file, relative to the project root.
+    pub file: PathBuf,
+    /// Line of the annotation (1-based).
+    pub line: u32,
+    /// Function the annotation belongs to.
+    pub function: Option<String>,
+    /// Kind of link.
+    pub link_type: LinkType,
+}
+
+/// A function definition in source.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceFunction {
+    /// Function name.
+    pub name: String,
+    /// Source file, relative to the project root.
+    pub file: PathBuf,
+    /// First line of the definition (1-based).
+    pub line: u32,
+    /// Last line of the definition (1-based).
+    pub end_line: u32,
+}
+
+/// Annotations and functions found in one file.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileTrace {
+    /// Requirement annotations.
+    pub links: Vec<TraceLink>,
+    /// Function definitions.
+    pub functions: Vec<SourceFunction>,
+}
+
+/// Find requirement annotations and function definitions in a C or C++
+/// source. `file` is recorded as given.
+pub fn parse_requirement_annotations(
+    parser: &mut Parser,
+    syntax: &AnnotationSyntax,
+    file: &Path,
+    source: &str,
+    language: Language,
+) -> Result<FileTrace, ParseError> {
+    let functions: Vec<SourceFunction> = parser
+        .query(source, language, FUNCTION_QUERY)?
+        .iter()
+        .filter_map(|m| {
+            let name = m.capture("name")?;
+            let function = m.capture("function")?;
+            Some(SourceFunction {
+                name: name.text.clone(),
+                file: file.to_path_buf(),
+                line: function.range.start.line + 1,
+                end_line: function.range.end.line + 1,
+            })
+        })
+        .collect();
+
+    let prototypes: Vec<(String, u32)> = parser
+        .query(source, language, PROTOTYPE_QUERY)?
+        .iter()
+        .filter_map(|m| {
+            let name = m.capture("name")?;
+            let prototype = m.capture("prototype")?;
+            Some((name.text.clone(), prototype.range.start.line + 1))
+        })
+        .collect();
+
+    let lines: Vec<&str> = source.lines().collect();
+    let mut links = Vec::new();
+    for comment in parser.query(source, language, "(comment) @comment")? {
+        let capture = &comment.captures[0];
+        let start = capture.range.start.line + 1;
+        let end = capture.range.end.line + 1;
+        let function = if is_doxygen(&capture.text) {
+            documented_function(&capture.text, &functions, &prototypes, &lines, start, end)
+        } else {
+            owning_function(&functions, &lines, start, end).map(|f| f.name.clone())
+        };
+
+        for (offset, text) in capture.text.lines().enumerate() {
+            for (requirement, link_type) in syntax.find_annotations(text) {
+                links.push(TraceLink {
+                    requirement: requirement.to_string(),
+                    file: file.to_path_buf(),
+                    line: start + offset as u32,
+                    function: function.clone(),
+                    link_type,
+                });
+            }
+        }
+    }
+
+    Ok(FileTrace { links, functions })
+}
+
+/// Find requirement annotations and function labels in an assembly
+/// source. A function is a label declared with `.type name, %function`,
+/// `.func name` or a preceding `.thumb_func`, or an armasm `name PROC`
+/// block; it ends at its `.size`, `.endfunc` or `ENDP`, or else before the
+/// next function. `file` is recorded as given.
+pub fn parse_assembly_annotations(
+    syntax: &AnnotationSyntax,
+    file: &Path,
+    source: &str,
+) -> FileTrace {
+    let lines = split_comments(source, &["//", "@", ";"], true);
+    let code: Vec<&str> = lines.iter().map(|(code, _)| code.as_str()).collect();
+    let functions = assembly_functions(file, &code);
+    let links = comment_links(syntax, file, &lines, |line| {
+        owning_function(&functions, &code, line, line).map(|f| f.name.clone())
+    });
+    FileTrace { links, functions }
+}
+
+/// Find requirement annotations in a linker script's `/* */` comments.
+/// `file` is recorded as given.
+pub fn parse_linker_script_annotations(
+    syntax: &AnnotationSyntax,
+    file: &Path,
+    source: &str,
+) -> FileTrace {
+    let lines = split_comments(source, &[], false);
+    FileTrace {
+        links: comment_links(syntax, file, &lines, |_| None),
+        functions: Vec::new(),
+    }
+}
+
+/// Links in each line's comment text, owned by `function(line)`.
+fn comment_links(
+    syntax: &AnnotationSyntax,
+    file: &Path,
+    lines: &[(String, String)],
+    function: impl Fn(u32) -> Option<String>,
+) -> Vec<TraceLink> {
+    let mut links = Vec::new();
+    for (index, (_, comment)) in lines.iter().enumerate() {
+        let line = index as u32 + 1;
+        for (requirement, link_type) in syntax.find_annotations(comment) {
+            links.push(TraceLink {
+                requirement: requirement.to_string(),
+                file: file.to_path_buf(),
+                line,
+                function: function(line),
+                link_type,
+            });
+        }
+    }
+    links
+}
+
+/// Split each line into its code and its comment text. Block comments
+/// (`/* */`) may span lines; `line_markers` start comments running to the
+/// end of the line, and with `hash_lines` a line starting `# ` is a comment
+/// (unlike `#include` and other preprocessor directives).
+fn split_comments(source: &str, line_markers: &[&str], hash_lines: bool) -> Vec<(String, String)> {
+    let mut lines = Vec::new();
+    let mut in_block = false;
+    for line in source.lines() {
+        let trimmed = line.trim_start();
+        if !in_block
+            && hash_lines
+            && (trimmed == "#" || trimmed.starts_with("# ") || trimmed.starts_with("#\t"))
+        {
+            lines.push((String::new(), line.to_string()));
+            continue;
+        }
+
+        let mut code = String::new();
+        let mut comment = String::new();
+        let mut rest = line;
+        loop {
+            if in_block {
+                let Some(close) = rest.find("*/") else {
+                    comment.push_str(rest);
+                    break;
+                };
+                comment.push_str(&rest[..close]);
+                comment.push(' ');
+                rest = &rest[close + 2..];
+                in_block = false;
+                continue;
+            }
+
+            let block = rest.find("/*");
+            let marker = line_markers
+                .iter()
+                .filter_map(|marker| find_marker(rest, marker))
+                .min();
+            match (block, marker) {
+                (Some(open), marker) if marker.is_none_or(|marker| open < marker) => {
+                    code.push_str(&rest[..open]);
+                    code.push(' ');
+                    rest = &rest[open + 2..];
+                    in_block = true;
+                }
+                (_, Some(start)) => {
+                    code.push_str(&rest[..start]);
+                    comment.push_str(&rest[start..]);
+                    break;
+                }
+                _ => {
+                    code.push_str(rest);
+                    break;
+                }
+            }
+        }
+        lines.push((code, comment));
+    }
+    lines
+}
+
+/// Position of a line comment marker. An `@` after a comma is a symbol
+/// type operand (`.type name, @function`), not a comment.
+fn find_marker(text: &str, marker: &str) -> Option<usize> {
+    text.match_indices(marker)
+        .map(|(position, _)| position)
+        .find(|&position| marker != "@" || !text[..position].trim_end().ends_with(','))
+}
+
+/// Function labels in assembly, given its code lines without comments.
+fn assembly_functions(file: &Path, code: &[&str]) -> Vec<SourceFunction> {
+    let declared: BTreeSet<&str> = code
+        .iter()
+        .filter_map(|line| {
+            let line = line.trim();
+            if let Some(operands) = directive(line, ".type") {
+                let (name, kind) = operands.split_once(',')?;
+                let kind = kind.trim().trim_start_matches(['%', '@', '#']);
+                matches!(kind.trim_matches('"'), "function" | "STT_FUNC").then(|| name.trim())
+            } else {
+                directive(line, ".func")?.split(',').next().map(str::trim)
+            }
+        })
+        .collect();
+
+    let mut functions: Vec<SourceFunction> = Vec::new();
+    let mut open = false;
+    let mut thumb_func = false;
+    for (index, line) in code.iter().enumerate() {
+        let number = index as u32 + 1;
+        let trimmed = line.trim();
+        let mut words = trimmed.split_whitespace();
+        let start = match label(trimmed) {
+            Some(name) => {
+                (std::mem::take(&mut thumb_func) || declared.contains(name)).then_some(name)
+            }
+            None if !line.starts_with(char::is_whitespace) => {
+                let name = words.next();
+                name.filter(|_| words.next().is_some_and(|w| w.eq_ignore_ascii_case("PROC")))
+            }
+            None => None,
+        };
+
+        if let Some(name) = start {
+            if let Some(last) = functions.last_mut().filter(|_| open) {
+                last.end_line = number - 1;
+            }
+            functions.push(SourceFunction {
+                name: name.to_string(),
+                file: file.to_path_buf(),
+                line: number,
+                end_line: number,
+            });
+            open = true;
+        } else if directive(trimmed, ".thumb_func").is_some() {
+            thumb_func = true;
+        } else if let Some(last) = functions.last_mut().filter(|_| open) {
+            let sized = directive(trimmed, ".size")
+                .and_then(|operands| operands.split(',').next())
+                .is_some_and(|name| name.trim() == last.name);
+            if sized
+                || directive(trimmed, ".endfunc").is_some()
+                || trimmed.eq_ignore_ascii_case("ENDP")
+            {
+                last.end_line = number;
+                open = false;
+            }
+        }
+    }
+    if let Some(last) = functions.last_mut().filter(|_| open) {
+        last.end_line = code.len() as u32;
+    }
+    functions
+}
+
+/// Operands of a directive line, if `line` is that directive.
+fn directive<'a>(line: &'a str, name: &str) -> Option<&'a str> {
+    let operands = line.strip_prefix(name)?;
+    (operands.is_empty() || operands.starts_with(char::is_whitespace)).then(|| operands.trim())
+}
+
+/// Name of a label defined at the start of a line (`name:`).
+fn label(line: &str) -> Option<&str> {
+    let (name, _) = line.split_once(':')?;
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || matches!(c, '_' | '.' | '$'))
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '$'));
+    valid.then_some(name)
+}
+
+/// The function whose definition contains lines `start..=end`, or else the
+/// one that follows them with only blank or comment lines in between.
+fn owning_function<'a>(
+    functions: &'a [SourceFunction],
+    lines: &[&str],
+    start: u32,
+    end: u32,
+) -> Option<&'a SourceFunction> {
+    if let Some(enclosing) = functions
+        .iter()
+        .find(|f| f.line <= start && end <= f.end_line)
+    {
+        return Some(enclosing);
+    }
+
+    let next = functions
+        .iter()
+        .filter(|f| f.line > end)
+        .min_by_key(|f| f.line)?;
+    follows(lines, end, next.line).then_some(next)
+}
+
+/// Whether only blank or comment lines lie between line `end` and `line`.
+fn follows(lines: &[&str], end: u32, line: u32) -> bool {
+    let between = lines.iter().take(line as usize - 1).skip(end as usize);
+    between.map(|line| line.trim()).all(|line| {
+        line.is_empty() || line.starts_with("//") || line.starts_with("/*") || line.starts_with('*')
+    })
+}
+
+/// Whether a comment is a Doxygen block (`/**`, `/*!`, `///` or `//!`).
+fn is_doxygen(comment: &str) -> bool {
+    (comment.starts_with("/**") && !comment.starts_with("/***") && comment != "/**/")
+        || comment.starts_with("/*!")
+        || (comment.starts_with("///") && !comment.starts_with("////"))
+        || comment.starts_with("//!")
+}
+
+/// The function a Doxygen block documents: the one its `\fn` command
+/// names, the definition holding it, or else the definition or prototype
+/// directly below it.
+fn documented_function(
+    comment: &str,
+    functions: &[SourceFunction],
+    prototypes: &[(String, u32)],
+    lines: &[&str],
+    start: u32,
+    end: u32,
+) -> Option<String> {
+    if let Some(name) = fn_command(comment) {
+        return Some(name.to_string());
+    }
+    if let Some(enclosing) = functions
+        .iter()
+        .find(|f| f.line <= start && end <= f.end_line)
+    {
+        return Some(enclosing.name.clone());
+    }
+
+    let next = functions
+        .iter()
+        .map(|f| (f.name.as_str(), f.line))
+        .chain(prototypes.iter().map(|(name, line)| (name.as_str(), *line)))
+        .filter(|&(_, line)| line > end)
+        .min_by_key(|&(_, line)| line)?;
+    follows(lines, end, next.1).then(|| next.0.to_string())
+}
+
+/// Function named by a `\fn` or `@fn` command.
+fn fn_command(comment: &str) -> Option<&str> {
+    let position = comment.find("\\fn ").or_else(|| comment.find("@fn "))?;
+    let signature = comment[position + 4..].lines().next()?;
+    let signature = signature.split('(').next()?;
+    signature
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .rfind(|word| !word.is_empty())
+}
+
+/// One requirement's row in the traceability matrix.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TraceRow {
+    /// Requirement ID.
+    pub requirement: String,
+    /// Requirement from the database, if it is defined there.
+    pub definition: Option<Requirement>,
+    /// Implementing and derived links.
+    pub implemented_by: Vec<TraceLink>,
+    /// Verifying links.
+    pub tested_by: Vec<TraceLink>,
+    /// Verification state from the recorded test results.
+    pub verification: VerificationStatus,
+}
+
+/// A derived requirement that is not fully justified.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DerivedRequirementFinding {
+    /// Requirement ID.
+    pub requirement: String,
+    /// Code marks it `DERIVED:` but the database does not flag it derived
+    /// (or does not define it).
+    pub undeclared: bool,
+    /// No rationale is recorded.
+    pub missing_rationale: bool,
+    /// It has not been provided to the safety assessment.
+    pub missing_safety_feedback: bool,
+}
+
+impl DerivedRequirementFinding {
+    /// The problems, as short phrases for reports.
+    pub fn issues(&self) -> Vec<&'static str> {
+        [
+            (self.undeclared, "not declared derived"),
+            (self.missing_rationale, "no rationale"),
+            (
+                self.missing_safety_feedback,
+                "not provided to safety assessment",
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(found, issue)| found.then_some(issue))
+        .collect()
+    }
+}
+
+/// Requirements joined with the code and tests that reference them.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TraceabilityMatrix {
+    /// Requirements from the project database.
+    pub requirements: Vec<Requirement>,
+    /// Annotations, by file and line.
+    pub links: Vec<TraceLink>,
+    /// Function definitions, by file and line.
+    pub functions: Vec<SourceFunction>,
+    /// Test results ingested from JUnit files.
+    #[serde(default)]
+    pub test_results: Vec<TestCaseResult>,
+}
+
+impl TraceabilityMatrix {
+    /// Build a matrix from database requirements and per-file traces.
+    pub fn new(
+        requirements: Vec<Requirement>,
+        traces: impl IntoIterator<Item = FileTrace>,
+    ) -> Self {
+        let mut matrix = Self {
+            requirements,
+            ..Default::default()
+        };
+        for trace in traces {
+            matrix.links.extend(trace.links);
+            matrix.functions.extend(trace.functions);
+        }
+        matrix
+            .links
+            .sort_by(|a, b| (&a.file, a.line).cmp(&(&b.file, b.line)));
+        matrix
+            .functions
+            .sort_by(|a, b| (&a.file, a.line).cmp(&(&b.file, b.line)));
+        matrix
+    }
+
+    /// Record test results against the matrix's `TEST:` links.
+    pub fn with_test_results(mut self, results: Vec<TestCaseResult>) -> Self {
+        self.test_results = results;
+        self
+    }
+
+    /// Test results for the functions that test a requirement.
+    pub fn results_for(&self, id: &str) -> Vec<&TestCaseResult> {
+        let functions: BTreeSet<&str> = self
+            .links
+            .iter()
+            .filter(|l| l.requirement == id && l.link_type == LinkType::Tests)
+            .filter_map(|l| l.function.as_deref())
+            .collect();
+        self.test_results
+            .iter()
+            .filter(|r| functions.iter().any(|f| r.matches_function(f)))
+            .collect()
+    }
+
+    /// IDs of database requirements and of every referenced requirement.
+    pub fn requirement_ids(&self) -> BTreeSet<&str> {
+        self.requirements
+            .iter()
+            .map(|r| r.id.as_str())
+            .chain(self.links.iter().map(|l| l.requirement.as_str()))
+            .collect()
+    }
+
+    /// Database requirement by ID.
+    pub fn requirement(&self, id: &str) -> Option<&Requirement> {
+        self.requirements.iter().find(|r| r.id == id)
+    }
+
+    /// Links to a requirement.
+    pub fn links_for(&self, id: &str) -> Vec<&TraceLink> {
+        self.links.iter().filter(|l| l.requirement == id).collect()
+    }
+
+    /// One row per requirement, ordered by ID.
+    pub fn rows(&self) -> Vec<TraceRow> {
+        let mut rows: BTreeMap<&str, TraceRow> = self
+            .requirement_ids()
+            .into_iter()
+            .map(|id| {
+                let row = TraceRow {
+                    requirement: id.to_string(),
+                    definition: self.requirement(id).cloned(),
+                    implemented_by: Vec::new(),
+                    tested_by: Vec::new(),
+                    verification: VerificationStatus::Untested,
+                };
+                (id, row)
+            })
+            .collect();
+        for link in &self.links {
+            if let Some(row) = rows.get_mut(link.requirement.as_str()) {
+                match link.link_type {
+                    LinkType::Tests => row.tested_by.push(link.clone()),
+                    LinkType::Implements | LinkType::Derived => {
+                        row.implemented_by.push(link.clone())
+                    }
+                }
+            }
+        }
+        for row in rows.values_mut() {
+            row.verification = VerificationStatus::from_results(
+                !row.tested_by.is_empty(),
+                self.results_for(&row.requirement),
+            );
+        }
+        rows.into_values().collect()
+    }
+
+    /// Whether a requirement is retired in the database.
+    fn is_retired(&self, id: &str) -> bool {
+        self.requirement(id)
+            .is_some_and(|r| r.status == RequirementStatus::Retired)
+    }
+
+    /// Active requirements with no passing test. Until test results are
+    /// recorded, any test that references a requirement counts.
+    pub fn find_untested_requirements(&self) -> Vec<String> {
+        let recorded = !self.test_results.is_empty();
+        self.rows()
+            .into_iter()
+            .filter(|row| {
+                let tested = if recorded {
+                    row.verification == VerificationStatus::Passed
+                } else {
+                    !row.tested_by.is_empty()
+                };
+                !tested && !self.is_retired(&row.requirement)
+            })
+            .map(|row| row.requirement)
+            .collect()
+    }
+
+    /// Active requirements no code implements.
+    pub fn find_unimplemented_requirements(&self) -> Vec<String> {
+        self.rows()
+            .into_iter()
+            .filter(|row| row.implemented_by.is_empty() && !self.is_retired(&row.requirement))
+            .map(|row| row.requirement)
+            .collect()
+    }
+
+    /// Active requirements flagged derived in the database or referenced
+    /// by a `DERIVED:` annotation, ordered by ID.
+    pub fn derived_requirements(&self) -> Vec<String> {
+        let derived: BTreeSet<&str> = self
+            .requirements
+            .iter()
+            .filter(|r| r.derived)
+            .map(|r| r.id.as_str())
+            .chain(
+                self.links
+                    .iter()
+                    .filter(|l| l.link_type == LinkType::Derived)
+                    .map(|l| l.requirement.as_str()),
+            )
+            .filter(|id| !self.is_retired(id))
+            .collect();
+        derived.into_iter().map(str::to_string).collect()
+    }
+
+    /// Derived requirements lacking a declaration, rationale or safety
+    /// assessment feedback.
+    pub fn find_unjustified_derived_requirements(&self) -> Vec<DerivedRequirementFinding> {
+        self.derived_requirements()
+            .into_iter()
+            .filter_map(|id| {
+                let definition = self.requirement(&id);
+                let finding = DerivedRequirementFinding {
+                    undeclared: !definition.is_some_and(|r| r.derived),
+                    missing_rationale: definition
+                        .and_then(|r| r.rationale.as_deref())
+                        .is_none_or(|r| r.trim().is_empty()),
+                    missing_safety_feedback: !definition.is_some_and(|r| r.safety_feedback),
+                    requirement: id,
+                };
+                (finding.undeclared || finding.missing_rationale || finding.missing_safety_feedback)
+                    .then_some(finding)
+            })
+            .collect()
+    }
+
+    /// Referenced requirements missing from the database. Empty when the
+    /// project keeps no database.
+    pub fn find_unknown_requirements(&self) -> Vec<String> {
+        if self.requirements.is_empty() {
+            return Vec::new();
+        }
+        let known: BTreeSet<&str> = self.requirements.iter().map(|r| r.id.as_str()).collect();
+        let unknown: BTreeSet<&str> = self
+            .links
+            .iter()
+            .map(|l| l.requirement.as_str())
+            .filter(|id| !known.contains(id))
+            .collect();
+        unknown.into_iter().map(str::to_string).collect()
+    }
+
+    /// Functions outside test files that no annotation links to.
+    ///
+    /// A test file is one holding at least one `TEST:` annotation.
+    pub fn find_untraceable_functions(&self) -> Vec<&SourceFunction> {
+        let test_files: BTreeSet<&Path> = self
+            .links
+            .iter()
+            .filter(|l| l.link_type == LinkType::Tests)
+            .map(|l| l.file.as_path())
+            .collect();
+        let traced: BTreeSet<(&Path, &str)> = self
+            .links
+            .iter()
+            .filter_map(|l| Some((l.file.as_path(), l.function.as_deref()?)))
+            .collect();
+        self.functions
+            .iter()
+            .filter(|f| !test_files.contains(f.file.as_path()))
+            .filter(|f| !traced.contains(&(f.file.as_path(), f.name.as_str())))
+            .collect()
+    }
+
+    /// Export the links as CSV (requirement, type, file, line, function).
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("requirement,type,file,line,function\n");
+        for link in &self.links {
+            out.push_str(&format!(
+                "{},{},{},{},{}\n",
+                csv_field(&link.requirement),
+                link.link_type.as_str(),
+                csv_field(&link.file.display().to_string()),
+                link.line,
+                csv_field(link.function.as_deref().unwrap_or_default())
+            ));
+        }
+        out
+    }
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// Scan a project's sources, assembly and linker scripts and join their
+/// annotations with the requirements database. Scan results are cached in
+/// the project (see [`TraceCache`]), so only changed files are parsed.
+pub fn generate_traceability_matrix(
+    project_root: &Path,
+    syntax: &AnnotationSyntax,
+) -> Result<TraceabilityMatrix, TraceError> {
+    // The cache only saves work: an unreadable one is rebuilt, and a
+    // read-only project is scanned in full each time
+    let store = TraceCacheStore::for_project(project_root);
+    let mut cache = store.load().unwrap_or_default();
+    let matrix = generate_traceability_matrix_cached(project_root, syntax, &mut cache)?;
+    let _ = store.save(&cache);
+    Ok(matrix)
+}
+
+/// [`generate_traceability_matrix`] with a cache held by the caller,
+/// updated to match the project.
+pub fn generate_traceability_matrix_cached(
+    project_root: &Path,
+    syntax: &AnnotationSyntax,
+    cache: &mut TraceCache,
+) -> Result<TraceabilityMatrix, TraceError> {
+    let requirements: Vec<Requirement> = RequirementStore::for_project(project_root)
+        .load()?
+        .iter()
+        .cloned()
+        .collect();
+
+    cache.prepare(syntax);
+    let mut parser = None;
+    let mut traces = Vec::new();
+    let mut scanned = Vec::new();
+    for path in find_files(project_root, TRACE_EXTENSIONS)? {
+        let relative = path.strip_prefix(project_root).unwrap_or(&path);
+        let stamp = file_stamp(&path)?;
+        if let Some(trace) = cache.by_stamp(relative, stamp) {
+            traces.push(trace.clone());
+            scanned.push(relative.to_path_buf());
+            continue;
+        }
+
+        let content = std::fs::read(&path)?;
+        let sha256 = content_sha256(&content);
+        if let Some(trace) = cache.by_content(relative, stamp, &sha256) {
+            traces.push(trace.clone());
+            scanned.push(relative.to_path_buf());
+            continue;
+        }
+
+        // Legacy sources in Latin-1 and the like still trace; only the
+        // annotations need to be ASCII
+        let source = String::from_utf8_lossy(&content);
+        let Some(trace) = scan_file(&mut parser, syntax, &path, relative, &source)? else {
+            continue;
+        };
+        cache.insert(relative, stamp, sha256, trace.clone());
+        traces.push(trace);
+        scanned.push(relative.to_path_buf());
+    }
+    cache.retain(&scanned);
+
+    Ok(TraceabilityMatrix::new(requirements, traces))
+}
+
+/// Parse one file by its kind; `None` for files of no known kind. The
+/// parser is created on first use.
+pub(crate) fn scan_file(
+    parser: &mut Option<Parser>,
+    syntax: &AnnotationSyntax,
+    path: &Path,
+    relative: &Path,
+    source: &str,
+) -> Result<Option<FileTrace>, TraceError> {
+    if has_extension(path, ASSEMBLY_EXTENSIONS) {
+        return Ok(Some(parse_assembly_annotations(syntax, relative, source)));
+    }
+    if has_extension(path, LINKER_SCRIPT_EXTENSIONS) {
+        return Ok(Some(parse_linker_script_annotations(
+            syntax, relative, source,
+        )));
+    }
+    let Some(language) = Language::from_path(path) else {
+        return Ok(None);
+    };
+
+    let parse_error = |source| TraceError::Parse {
+        path: path.to_path_buf(),
+        source,
+    };
+    let parser = match parser {
+        Some(parser) => parser,
+        None => parser.insert(Parser::new().map_err(parse_error)?),
+    };
+    parse_requirement_annotations(parser, syntax, relative, source, language)
+        .map(Some)
+        .map_err(parse_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TestOutcome;
+    use tempfile::TempDir;
+
+    const NAV: &str = r#"#include "nav.h"
+
+/* Implements REQ-NAV-001 */
+void nav_update(void)
+{
+    // DERIVED: REQ-NAV-090, see REQ-NAV-002
+    nav_clamp();
+}
+
+static int *nav_state(void) { return 0; }
+
+const char *s = "REQ-NAV-404";
+"#;
+
+    const NAV_TEST: &str = r#"
+// TEST: REQ-NAV-001
+void test_nav_update(void) {}
+"#;
+
+    fn trace(file: &str, source: &str) -> FileTrace {
+        let mut parser = Parser::new().unwrap();
+        parse_requirement_annotations(
+            &mut parser,
+            &AnnotationSyntax::default(),
+            Path::new(file),
+            source,
+            Language::C,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse_annotations() {
+        let trace = trace("src/nav.c", NAV);
+        let names: Vec<_> = trace.functions.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["nav_update", "nav_state"]);
+
+        let links: Vec<_> = trace
+            .links
+            .iter()
+            .map(|l| {
+                (
+                    l.requirement.as_str(),
+                    l.line,
+                    l.function.as_deref(),
+                    l.link_type,
+                )
+            })
+            .collect();
+        assert_eq!(
+            links,
+            [
+                ("REQ-NAV-001", 3, Some("nav_update"), LinkType::Implements),
+                ("REQ-NAV-090", 6, Some("nav_update"), LinkType::Derived),
+                ("REQ-NAV-002", 6, Some("nav_update"), LinkType::Derived),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_doxygen_annotations() {
+        let source = r#"/**
+ * @brief Update the navigation solution.
+ * @req REQ-NAV-001
+ */
+void nav_update(void);
+
+/*!
+ * \fn int nav_clamp(int x)
+ * \satisfies REQ-NAV-002
+ */
+
+/// @verifies REQ-NAV-001
+/// Bare mention of REQ-NAV-003.
+void test_nav_update(void) {}
+
+int nav_clamp(int x) { return x; }
+"#;
+        let trace = trace("src/nav.h", source);
+        let links: Vec<_> = trace
+            .links
+            .iter()
+            .map(|l| {
+                (
+                    l.requirement.as_str(),
+                    l.line,
+                    l.function.as_deref(),
+                    l.link_type,
+                )
+            })
+            .collect();
+        assert_eq!(
+            links,
+            [
+                ("REQ-NAV-001", 3, Some("nav_update"), LinkType::Implements),
+                ("REQ-NAV-002", 9, Some("nav_clamp"), LinkType::Implements),
+                ("REQ-NAV-001", 12, Some("test_nav_update"), LinkType::Tests),
+                (
+                    "REQ-NAV-003",
+                    13,
+                    Some("test_nav_update"),
+                    LinkType::Implements
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_assembly_annotations() {
+        let source = "\
+    .syntax unified
+/* Vector table: REQ-BOOT-001 */
+    .section .isr_vector,\"a\",%progbits
+    .word _estack
+
+@ Reset entry point
+@ REQ-BOOT-002
+    .global Reset_Handler
+    .type Reset_Handler, %function
+Reset_Handler:
+    ldr sp, =_estack      @ REQ-BOOT-003
+.Lcopy:
+    bl SystemInit
+    .size Reset_Handler, .-Reset_Handler
+
+# TEST: REQ-BOOT-004
+    .thumb_func
+Default_Handler:
+    b Default_Handler
+";
+        let trace = parse_assembly_annotations(
+            &AnnotationSyntax::default(),
+            Path::new("startup.S"),
+            source,
+        );
+        let names: Vec<_> = trace
+            .functions
+            .iter()
+            .map(|f| (f.name.as_str(), f.line, f.end_line))
+            .collect();
+        assert_eq!(
+            names,
+            [("Reset_Handler", 10, 14), ("Default_Handler", 18, 19)]
+        );
+        let links: Vec<_> = trace
+            .links
+            .iter()
+            .map(|l| {
+                (
+                    l.requirement.as_str(),
+                    l.line,
+                    l.function.as_deref(),
+                    l.link_type,
+                )
+            })
+            .collect();
+        assert_eq!(
+            links,
+            [
+                ("REQ-BOOT-001", 2, None, LinkType::Implements),
+                ("REQ-BOOT-002", 7, None, LinkType::Implements),
+                (
+                    "REQ-BOOT-003",
+                    11,
+                    Some("Reset_Handler"),
+                    LinkType::Implements
+                ),
+                ("REQ-BOOT-004", 16, None, LinkType::Tests),
+            ]
+        );
+
+        let armasm = "\
+; REQ-BOOT-010
+Reset_Handler PROC
+        EXPORT Reset_Handler [WEAK]
+        LDR R0, =SystemInit ; REQ-BOOT-011
+        ENDP
+";
+        let trace = parse_assembly_annotations(
+            &AnnotationSyntax::default(),
+            Path::new("startup.s"),
+            armasm,
+        );
+        assert_eq!(trace.functions.len(), 1);
+        assert_eq!(trace.functions[0].end_line, 5);
+        assert!(trace
+            .links
+            .iter()
+            .all(|l| l.function.as_deref() == Some("Reset_Handler")));
+        assert_eq!(trace.links.len(), 2);
+    }
+
+    #[test]
+    fn test_linker_script_annotations() {
+        let source = "\
+/* Memory map per REQ-MEM-001.
+ * DERIVED: REQ-MEM-090 */
+MEMORY
+{
+    FLASH (rx) : ORIGIN = 0x08000000, LENGTH = 512K /* REQ-MEM-002 */
+}
+";
+        let trace = parse_linker_script_annotations(
+            &AnnotationSyntax::default(),
+            Path::new("flash.ld"),
+            source,
+        );
+        assert!(trace.functions.is_empty());
+        let links: Vec<_> = trace
+            .links
+            .iter()
+            .map(|l| (l.requirement.as_str(), l.line, l.link_type))
+            .collect();
+        assert_eq!(
+            links,
+            [
+                ("REQ-MEM-001", 1, LinkType::Implements),
+                ("REQ-MEM-090", 2, LinkType::Derived),
+                ("REQ-MEM-002", 5, LinkType::Implements),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_matrix_queries() {
+        let requirements = vec![
+            Requirement::new("REQ-NAV-001", "Update navigation"),
+            Requirement::new("REQ-NAV-003", "Report faults"),
+            Requirement::new("REQ-NAV-004", "Legacy").with_status(RequirementStatus::Retired),
+        ];
+        let matrix = TraceabilityMatrix::new(
+            requirements,
+            [trace("src/nav.c", NAV), trace("test/test_nav.c", NAV_TEST)],
+        );
+
+        let rows = matrix.rows();
+        assert_eq!(rows.len(), 5);
+        assert_eq!(rows[0].requirement, "REQ-NAV-001");
+        assert_eq!(rows[0].tested_by[0].file, Path::new("test/test_nav.c"));
+        assert!(rows[1].definition.is_none());
+
+        assert_eq!(
+            matrix.find_untested_requirements(),
+            ["REQ-NAV-002", "REQ-NAV-003", "REQ-NAV-090"]
+        );
+        assert_eq!(matrix.find_unimplemented_requirements(), ["REQ-NAV-003"]);
+        assert_eq!(
+            matrix.find_unknown_requirements(),
+            ["REQ-NAV-002", "REQ-NAV-090"]
+        );
+        let untraced: Vec<_> = matrix
+            .find_untraceable_functions()
+            .iter()
+            .map(|f| f.name.as_str())
+            .collect();
+        assert_eq!(untraced, ["nav_state"]);
+        assert!(matrix
+            .to_csv()
+            .contains("REQ-NAV-001,tests,test/test_nav.c,2,test_nav_update\n"));
+    }
+
+    #[test]
+    fn test_derived_requirements() {
+        let requirements = vec![
+            Requirement::new("REQ-NAV-001", "Update navigation"),
+            Requirement::new("REQ-NAV-002", "Filter sensor noise")
+                .with_derived("Sensor limitation")
+                .with_safety_feedback(),
+            Requirement::new("REQ-NAV-005", "Watchdog kick").with_derived(""),
+        ];
+        let matrix = TraceabilityMatrix::new(requirements, [trace("src/nav.c", NAV)]);
+
+        assert_eq!(
+            matrix.derived_requirements(),
+            ["REQ-NAV-002", "REQ-NAV-005", "REQ-NAV-090"]
+        );
+        let findings = matrix.find_unjustified_derived_requirements();
+        assert_eq!(findings.len(), 2);
+        assert_eq!(findings[0].requirement, "REQ-NAV-005");
+        assert_eq!(
+            findings[0].issues(),
+            ["no rationale", "not provided to safety assessment"]
+        );
+        assert_eq!(findings[1].requirement, "REQ-NAV-090");
+        assert!(findings[1].undeclared);
+    }
+
+    #[test]
+    fn test_verification_from_results() {
+        let matrix = TraceabilityMatrix::new(
+            vec![Requirement::new("REQ-NAV-001", "Update navigation")],
+            [trace("src/nav.c", NAV), trace("test/test_nav.c", NAV_TEST)],
+        );
+        assert_eq!(matrix.rows()[0].verification, VerificationStatus::NotRun);
+        assert!(!matrix
+            .find_untested_requirements()
+            .contains(&"REQ-NAV-001".to_string()));
+
+        let result = |outcome| TestCaseResult {
+            suite: "test_nav".to_string(),
+            classname: None,
+            name: "test_nav_update".to_string(),
+            outcome,
+            time: None,
+            message: None,
+        };
+        let failed = matrix
+            .clone()
+            .with_test_results(vec![result(TestOutcome::Failed)]);
+        assert_eq!(failed.rows()[0].verification, VerificationStatus::Failed);
+        assert!(failed
+            .find_untested_requirements()
+            .contains(&"REQ-NAV-001".to_string()));
+
+        let passed = matrix.with_test_results(vec![result(TestOutcome::Passed)]);
+        assert_eq!(passed.results_for("REQ-NAV-001").len(), 1);
+        assert_eq!(passed.rows()[0].verification, VerificationStatus::Passed);
+        assert_eq!(passed.rows()[1].verification, VerificationStatus::Untested);
+    }
+
+    #[test]
+    fn test_generate_matrix() {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/nav.c"), NAV).unwrap();
+        std::fs::write(dir.path().join("src/notes.txt"), "REQ-NAV-777").unwrap();
+        std::fs::write(dir.path().join("src/startup.S"), "@ REQ-NAV-001\n").unwrap();
+        std::fs::write(dir.path().join("flash.ld"), "/* REQ-NAV-001 */\n").unwrap();
+        RequirementStore::for_project(dir.path())
+            .create(Requirement::new("REQ-NAV-001", "Update navigation"))
+            .unwrap();
+
+        let matrix =
+            generate_traceability_matrix(dir.path(), &AnnotationSyntax::default()).unwrap();
+        assert_eq!(matrix.requirements.len(), 1);
+        assert_eq!(matrix.links.len(), 5);
+        assert_eq!(matrix.links[0].file, Path::new("flash.ld"));
+        assert_eq!(matrix.links[1].file, Path::new("src/nav.c"));
+        assert_eq!(matrix.links[4].file, Path::new("src/startup.S"));
+    }
+
+    #[test]
+    fn test_generate_matrix_non_utf8() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(
+            dir.path().join("legacy.c"),
+            b"/* Copyright \xa9 1998 */\n// REQ-NAV-001\nvoid nav_update(void) {}\n",
+        )
+        .unwrap();
+
+        let matrix =
+            generate_traceability_matrix(dir.path(), &AnnotationSyntax::default()).unwrap();
+        assert_eq!(matrix.links.len(), 1);
+        assert_eq!(matrix.links[0].line, 2);
+        assert_eq!(matrix.links[0].function.as_deref(), Some("nav_update"));
+    }
+}
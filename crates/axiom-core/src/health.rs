@@ -0,0 +1,150 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Project health findings.
+//!
+//! Each subsystem contributes findings about the local setup (toolchains,
+//! linker scripts, repository, settings); the report orders them so the
+//! problem most likely to break a build comes first.
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// How urgently a finding needs attention.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthPriority {
+    /// Builds will fail until this is fixed.
+    Critical,
+    /// Likely to cause wrong or surprising results.
+    Warning,
+    /// Worth knowing, no action required.
+    Info,
+}
+
+/// A single problem found by a health check.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HealthFinding {
+    /// Priority.
+    pub priority: HealthPriority,
+    /// Identifier of the check (e.g. "toolchain.arm-gcc").
+    pub check: String,
+    /// What is wrong.
+    pub message: String,
+    /// What to do about it.
+    pub action: Option<String>,
+    /// File or directory concerned.
+    pub path: Option<PathBuf>,
+}
+
+impl HealthFinding {
+    /// Create a finding.
+    pub fn new(
+        priority: HealthPriority,
+        check: impl Into<String>,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            priority,
+            check: check.into(),
+            message: message.into(),
+            action: None,
+            path: None,
+        }
+    }
+
+    /// Create a critical finding.
+    pub fn critical(check: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::new(HealthPriority::Critical, check, message)
+    }
+
+    /// Create a warning finding.
+    pub fn warning(check: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::new(HealthPriority::Warning, check, message)
+    }
+
+    /// Create an informational finding.
+    pub fn info(check: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::new(HealthPriority::Info, check, message)
+    }
+
+    /// Add a suggested action.
+    pub fn with_action(mut self, action: impl Into<String>) -> Self {
+        self.action = Some(action.into());
+        self
+    }
+
+    /// Add the path concerned.
+    pub fn with_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+}
+
+/// Findings from a set of health checks, highest priority first.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HealthReport {
+    /// Categories of checks that ran (e.g. "toolchain", "git").
+    pub categories: Vec<String>,
+    /// Findings, ordered by priority then by the order checks ran.
+    pub findings: Vec<HealthFinding>,
+}
+
+impl HealthReport {
+    /// Create an empty report.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the findings of one category of checks.
+    pub fn add(&mut self, category: impl Into<String>, findings: Vec<HealthFinding>) {
+        self.categories.push(category.into());
+        self.findings.extend(findings);
+        self.findings.sort_by_key(|f| f.priority);
+    }
+
+    /// Number of findings with a priority.
+    pub fn count(&self, priority: HealthPriority) -> usize {
+        self.findings
+            .iter()
+            .filter(|f| f.priority == priority)
+            .count()
+    }
+
+    /// Whether nothing critical was found.
+    pub fn is_healthy(&self) -> bool {
+        self.count(HealthPriority::Critical) == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_orders_by_priority() {
+        let mut report = HealthReport::new();
+        report.add(
+            "git",
+            vec![
+                HealthFinding::info("git.repo", "Not a repository"),
+                HealthFinding::warning("git.head", "Detached HEAD"),
+            ],
+        );
+        assert!(report.is_healthy());
+
+        report.add(
+            "toolchain",
+            vec![
+                HealthFinding::critical("toolchain.arm-gcc", "ARM GCC not found")
+                    .with_action("Install arm-none-eabi-gcc"),
+            ],
+        );
+
+        let checks: Vec<_> = report.findings.iter().map(|f| f.check.as_str()).collect();
+        assert_eq!(checks, vec!["toolchain.arm-gcc", "git.head", "git.repo"]);
+        assert_eq!(report.categories, vec!["git", "toolchain"]);
+        assert_eq!(report.count(HealthPriority::Critical), 1);
+        assert!(!report.is_healthy());
+    }
+}
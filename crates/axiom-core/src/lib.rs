@@ -6,7 +6,11 @@
 //! Shared types and utilities for the Axiom IDE.
 
 pub mod error;
+pub mod health;
+pub mod time;
 pub mod types;
+pub mod walk;
 
 pub use error::{AxiomError, Result};
+pub use health::{HealthFinding, HealthPriority, HealthReport};
 pub use types::*;
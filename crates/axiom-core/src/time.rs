@@ -0,0 +1,75 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Timestamp helpers.
+//!
+//! Timestamps are stored as seconds since the Unix epoch (UTC) and only
+//! formatted for display, so records stay comparable across machines.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Seconds in one day.
+pub const SECONDS_PER_DAY: u64 = 86_400;
+
+/// Current time in seconds since the Unix epoch.
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Format a timestamp as a UTC calendar date (`YYYY-MM-DD`).
+pub fn format_date(timestamp: u64) -> String {
+    let (year, month, day) = civil_from_days(timestamp / SECONDS_PER_DAY);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Format a timestamp as an RFC 3339 UTC date-time (`YYYY-MM-DDTHH:MM:SSZ`).
+pub fn format_timestamp(timestamp: u64) -> String {
+    let secs = timestamp % SECONDS_PER_DAY;
+    format!(
+        "{}T{:02}:{:02}:{:02}Z",
+        format_date(timestamp),
+        secs / 3600,
+        (secs % 3600) / 60,
+        secs % 60
+    )
+}
+
+/// Convert days since the epoch to a (year, month, day) civil date.
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    // Howard Hinnant's days-to-civil algorithm, restricted to dates >= 1970.
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_epoch() {
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(format_timestamp(0), "1970-01-01T00:00:00Z");
+    }
+
+    #[test]
+    fn test_format_leap_day() {
+        // 2024-02-29T12:34:56Z
+        assert_eq!(format_timestamp(1_709_210_096), "2024-02-29T12:34:56Z");
+    }
+
+    #[test]
+    fn test_unix_now_is_after_2024() {
+        assert!(unix_now() > 1_704_067_200);
+    }
+}
@@ -55,7 +55,7 @@ impl Location {
 }
 
 /// Severity level for diagnostics.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Severity {
     /// Error - compilation cannot proceed.
     Error,
@@ -65,6 +65,25 @@ pub enum Severity {
     Note,
 }
 
+/// A suggested edit that resolves a diagnostic.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FixIt {
+    /// Source span to replace (an empty range is an insertion).
+    pub location: Location,
+    /// Replacement text (empty for a deletion).
+    pub replacement: String,
+}
+
+impl FixIt {
+    /// Create a new fix-it hint.
+    pub fn new(location: Location, replacement: impl Into<String>) -> Self {
+        Self {
+            location,
+            replacement: replacement.into(),
+        }
+    }
+}
+
 /// A diagnostic message from a tool.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Diagnostic {
@@ -74,25 +93,39 @@ pub struct Diagnostic {
     pub message: String,
     /// Location in source (if available).
     pub location: Option<Location>,
+    /// Rule or check identifier (e.g. "-Wunused-variable", "misc-unused").
+    #[serde(default)]
+    pub code: Option<String>,
+    /// Suggested edits reported by the tool.
+    #[serde(default)]
+    pub fixits: Vec<FixIt>,
 }
 
 impl Diagnostic {
-    /// Create an error diagnostic.
-    pub fn error(message: impl Into<String>) -> Self {
+    /// Create a diagnostic with the given severity.
+    pub fn new(severity: Severity, message: impl Into<String>) -> Self {
         Self {
-            severity: Severity::Error,
+            severity,
             message: message.into(),
             location: None,
+            code: None,
+            fixits: Vec::new(),
         }
     }
 
+    /// Create an error diagnostic.
+    pub fn error(message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, message)
+    }
+
     /// Create a warning diagnostic.
     pub fn warning(message: impl Into<String>) -> Self {
-        Self {
-            severity: Severity::Warning,
-            message: message.into(),
-            location: None,
-        }
+        Self::new(Severity::Warning, message)
+    }
+
+    /// Create a note diagnostic.
+    pub fn note(message: impl Into<String>) -> Self {
+        Self::new(Severity::Note, message)
     }
 
     /// Attach a location to this diagnostic.
@@ -100,6 +133,18 @@ impl Diagnostic {
         self.location = Some(location);
         self
     }
+
+    /// Attach a rule or check identifier to this diagnostic.
+    pub fn with_code(mut self, code: impl Into<String>) -> Self {
+        self.code = Some(code.into());
+        self
+    }
+
+    /// Attach a fix-it hint to this diagnostic.
+    pub fn with_fixit(mut self, fixit: FixIt) -> Self {
+        self.fixits.push(fixit);
+        self
+    }
 }
 
 #[cfg(test)]
@@ -128,5 +173,14 @@ mod tests {
         assert_eq!(diag.severity, Severity::Error);
         assert_eq!(diag.message, "test error");
         assert!(diag.location.is_none());
+        assert!(diag.fixits.is_empty());
+    }
+
+    #[test]
+    fn test_diagnostic_with_fixit() {
+        let range = Range::new(Position::new(4, 10), Position::new(4, 10));
+        let fixit = FixIt::new(Location::new(PathBuf::from("main.c"), range), ";");
+        let diag = Diagnostic::error("expected ';'").with_fixit(fixit.clone());
+        assert_eq!(diag.fixits, vec![fixit]);
     }
 }
@@ -0,0 +1,416 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Project file discovery.
+//!
+//! Walks a project tree on several threads and returns files in sorted order
+//! so every scan sees them in the same sequence. Hidden directories, common
+//! build output directories and paths excluded by `.gitignore` files are
+//! skipped, so vendored build output stays out of analysis.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex};
+
+/// Directory names never descended into.
+pub const SKIPPED_DIRS: &[&str] = &["build", "target", "node_modules"];
+
+/// Find files under `root` whose extension is in `extensions`.
+///
+/// Extensions are compared case-insensitively and given without the dot.
+/// Results are sorted.
+pub fn find_files(root: &Path, extensions: &[&str]) -> io::Result<Vec<PathBuf>> {
+    FileWalker::new(extensions).find(root)
+}
+
+/// Configurable project file search.
+#[derive(Debug, Clone)]
+pub struct FileWalker {
+    extensions: Vec<String>,
+    gitignore: bool,
+    threads: usize,
+}
+
+impl FileWalker {
+    /// Search for files with the given extensions, honouring `.gitignore`
+    /// on as many threads as the machine has cores.
+    pub fn new(extensions: &[&str]) -> Self {
+        Self {
+            extensions: extensions.iter().map(|e| e.to_string()).collect(),
+            gitignore: true,
+            threads: std::thread::available_parallelism().map_or(1, |n| n.get()),
+        }
+    }
+
+    /// Whether `.gitignore` files exclude paths.
+    pub fn with_gitignore(mut self, gitignore: bool) -> Self {
+        self.gitignore = gitignore;
+        self
+    }
+
+    /// Number of threads walking the tree (at least one).
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Find matching files under `root`, sorted.
+    pub fn find(&self, root: &Path) -> io::Result<Vec<PathBuf>> {
+        let queue = Queue {
+            state: Mutex::new(QueueState {
+                dirs: vec![(root.to_path_buf(), Arc::new(Vec::new()))],
+                active: 0,
+                error: None,
+            }),
+            ready: Condvar::new(),
+        };
+
+        let mut files: Vec<PathBuf> = std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..self.threads)
+                .map(|_| scope.spawn(|| self.work(&queue)))
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().expect("walker thread panicked"))
+                .collect()
+        });
+
+        if let Some(error) = queue.state.into_inner().expect("walker lock").error {
+            return Err(error);
+        }
+        files.sort();
+        Ok(files)
+    }
+
+    /// Take directories from the queue until the walk is done.
+    fn work(&self, queue: &Queue) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        loop {
+            let (dir, rules) = {
+                let mut state = queue.state.lock().expect("walker lock");
+                loop {
+                    if let Some(next) = state.dirs.pop() {
+                        state.active += 1;
+                        break next;
+                    }
+                    if state.active == 0 {
+                        return files;
+                    }
+                    state = queue.ready.wait(state).expect("walker lock");
+                }
+            };
+
+            let result = self.visit(&dir, rules, &mut files);
+            let mut state = queue.state.lock().expect("walker lock");
+            state.active -= 1;
+            match result {
+                Ok(subdirs) if state.error.is_none() => state.dirs.extend(subdirs),
+                Ok(_) => {}
+                Err(error) => {
+                    state.dirs.clear();
+                    state.error.get_or_insert(error);
+                }
+            }
+            queue.ready.notify_all();
+        }
+    }
+
+    /// Collect one directory's matching files and return its subdirectories
+    /// to walk.
+    fn visit(
+        &self,
+        dir: &Path,
+        rules: Arc<Vec<IgnoreRule>>,
+        files: &mut Vec<PathBuf>,
+    ) -> io::Result<Vec<(PathBuf, Arc<Vec<IgnoreRule>>)>> {
+        let gitignore = if self.gitignore {
+            std::fs::read_to_string(dir.join(".gitignore"))
+        } else {
+            Err(io::ErrorKind::NotFound.into())
+        };
+        let rules = match gitignore {
+            Ok(content) => {
+                let mut rules = rules.as_ref().clone();
+                rules.extend(parse_gitignore(dir, &content));
+                Arc::new(rules)
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => rules,
+            Err(e) => return Err(e),
+        };
+
+        let mut subdirs = Vec::new();
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            let is_dir = entry.file_type()?.is_dir();
+
+            if is_ignored(&rules, &path, is_dir) {
+                continue;
+            }
+            if is_dir {
+                if !name.starts_with('.') && !SKIPPED_DIRS.contains(&name.as_ref()) {
+                    subdirs.push((path, Arc::clone(&rules)));
+                }
+            } else if self
+                .extensions
+                .iter()
+                .any(|x| has_extension(&path, &[x.as_str()]))
+            {
+                files.push(path);
+            }
+        }
+        Ok(subdirs)
+    }
+}
+
+/// Directories waiting to be walked, shared by the walker threads.
+struct Queue {
+    state: Mutex<QueueState>,
+    ready: Condvar,
+}
+
+struct QueueState {
+    dirs: Vec<(PathBuf, Arc<Vec<IgnoreRule>>)>,
+    /// Directories being visited, whose subdirectories are not queued yet.
+    active: usize,
+    error: Option<io::Error>,
+}
+
+/// One `.gitignore` pattern.
+#[derive(Debug, Clone)]
+struct IgnoreRule {
+    /// Directory holding the `.gitignore`.
+    base: PathBuf,
+    pattern: String,
+    negated: bool,
+    dir_only: bool,
+    /// Matched against the path from `base` rather than the file name.
+    anchored: bool,
+}
+
+fn parse_gitignore(base: &Path, content: &str) -> Vec<IgnoreRule> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with('#') {
+                return None;
+            }
+            let (negated, line) = match line.strip_prefix('!') {
+                Some(rest) => (true, rest),
+                None => (false, line.strip_prefix('\\').unwrap_or(line)),
+            };
+            let (dir_only, line) = match line.strip_suffix('/') {
+                Some(rest) => (true, rest),
+                None => (false, line),
+            };
+            let anchored = line.contains('/');
+            let pattern = line.strip_prefix('/').unwrap_or(line);
+            (!pattern.is_empty()).then(|| IgnoreRule {
+                base: base.to_path_buf(),
+                pattern: pattern.to_string(),
+                negated,
+                dir_only,
+                anchored,
+            })
+        })
+        .collect()
+}
+
+/// Whether the last rule matching a path excludes it.
+fn is_ignored(rules: &[IgnoreRule], path: &Path, is_dir: bool) -> bool {
+    rules
+        .iter()
+        .rev()
+        .find(|rule| {
+            if rule.dir_only && !is_dir {
+                return false;
+            }
+            let Ok(relative) = path.strip_prefix(&rule.base) else {
+                return false;
+            };
+            let text = if rule.anchored {
+                relative.to_string_lossy().replace('\\', "/")
+            } else {
+                relative
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default()
+            };
+            glob_match(rule.pattern.as_bytes(), text.as_bytes())
+        })
+        .is_some_and(|rule| !rule.negated)
+}
+
+/// Match a gitignore glob: `*` and `?` stop at `/`, `**` crosses it, and
+/// `[...]` is a character class.
+fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        [b'*', b'*', rest @ ..] => {
+            // `**/` also matches no directories at all
+            if rest
+                .strip_prefix(b"/")
+                .is_some_and(|after| glob_match(after, text))
+            {
+                return true;
+            }
+            (0..=text.len()).any(|i| glob_match(rest, &text[i..]))
+        }
+        [b'*', rest @ ..] => (0..=text.len())
+            .take_while(|&i| !text[..i].contains(&b'/'))
+            .any(|i| glob_match(rest, &text[i..])),
+        [b'?', rest @ ..] => match text {
+            [c, tail @ ..] => *c != b'/' && glob_match(rest, tail),
+            [] => false,
+        },
+        [b'[', rest @ ..] => match (class_match(rest, text.first().copied()), text) {
+            (Some((true, after)), [_, tail @ ..]) => glob_match(after, tail),
+            (Some(_), _) => false,
+            // An unclosed bracket is literal
+            (None, [b'[', tail @ ..]) => glob_match(rest, tail),
+            (None, _) => false,
+        },
+        [b'\\', c, rest @ ..] | [c, rest @ ..] => match text {
+            [t, tail @ ..] => t == c && glob_match(rest, tail),
+            [] => false,
+        },
+    }
+}
+
+/// Match a byte against a class whose `[` is already consumed; the result
+/// and the pattern after `]`, or `None` if the class is not closed.
+fn class_match(class: &[u8], c: Option<u8>) -> Option<(bool, &[u8])> {
+    let (negated, class) = match class {
+        [b'!' | b'^', rest @ ..] => (true, rest),
+        _ => (false, class),
+    };
+    // A `]` right after the opening bracket is a member
+    let close = class.iter().skip(1).position(|&b| b == b']')? + 1;
+    let (members, after) = (&class[..close], &class[close + 1..]);
+    let Some(c) = c.filter(|&c| c != b'/') else {
+        return Some((false, after));
+    };
+
+    let mut matched = false;
+    let mut i = 0;
+    while i < members.len() {
+        if i + 2 < members.len() && members[i + 1] == b'-' {
+            matched |= (members[i]..=members[i + 2]).contains(&c);
+            i += 3;
+        } else {
+            matched |= members[i] == c;
+            i += 1;
+        }
+    }
+    Some((matched != negated, after))
+}
+
+/// Whether a path has one of the given extensions (case-insensitive).
+pub fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| extensions.iter().any(|x| x.eq_ignore_ascii_case(e)))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_find_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("src/drivers")).unwrap();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::create_dir_all(root.join("build")).unwrap();
+        fs::write(root.join("src/main.c"), "").unwrap();
+        fs::write(root.join("src/drivers/uart.H"), "").unwrap();
+        fs::write(root.join("src/notes.txt"), "").unwrap();
+        fs::write(root.join(".git/x.c"), "").unwrap();
+        fs::write(root.join("build/gen.c"), "").unwrap();
+
+        let files = find_files(root, &["c", "h"]).unwrap();
+        assert_eq!(
+            files,
+            vec![root.join("src/drivers/uart.H"), root.join("src/main.c")]
+        );
+    }
+
+    #[test]
+    fn test_gitignore() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for path in [
+            "src/main.c",
+            "src/gen/tables.c",
+            "src/gen/keep.c",
+            "vendor/hal/hal.c",
+            "out/app.c",
+            "lib/out/util.c",
+            "lib/debug.c",
+            "lib/core.c",
+        ] {
+            fs::create_dir_all(root.join(path).parent().unwrap()).unwrap();
+            fs::write(root.join(path), "").unwrap();
+        }
+        fs::write(root.join(".gitignore"), "# build output\n/out/\nvendor\n").unwrap();
+        fs::write(root.join("src/.gitignore"), "gen/*\n!gen/keep.c\n").unwrap();
+        fs::write(root.join("lib/.gitignore"), "d[a-f]bug.?\n").unwrap();
+
+        let files = find_files(root, &["c"]).unwrap();
+        let relative: Vec<_> = files
+            .iter()
+            .map(|f| f.strip_prefix(root).unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(
+            relative,
+            [
+                "lib/core.c",
+                "lib/out/util.c",
+                "src/gen/keep.c",
+                "src/main.c"
+            ]
+        );
+
+        let all = FileWalker::new(&["c"]).with_gitignore(false).find(root);
+        assert_eq!(all.unwrap().len(), 8);
+    }
+
+    #[test]
+    fn test_parallel_walk_matches_sequential() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for i in 0..20 {
+            let sub = root.join(format!("m{i}/n{}", i % 3));
+            fs::create_dir_all(&sub).unwrap();
+            fs::write(sub.join(format!("f{i}.c")), "").unwrap();
+        }
+
+        let sequential = FileWalker::new(&["c"]).with_threads(1).find(root).unwrap();
+        let parallel = FileWalker::new(&["c"]).with_threads(8).find(root).unwrap();
+        assert_eq!(sequential.len(), 20);
+        assert_eq!(sequential, parallel);
+        assert!(FileWalker::new(&["c"]).find(&root.join("missing")).is_err());
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match(b"*.o", b"main.o"));
+        assert!(!glob_match(b"*.o", b"obj/main.o"));
+        assert!(glob_match(b"**/gen", b"gen"));
+        assert!(glob_match(b"**/gen", b"a/b/gen"));
+        assert!(glob_match(b"docs/**", b"docs/a/b.c"));
+        assert!(glob_match(b"a/**/b", b"a/b"));
+        assert!(glob_match(b"a/**/b", b"a/x/y/b"));
+        assert!(glob_match(b"file?.[ch]", b"file1.h"));
+        assert!(!glob_match(b"file?.[!ch]", b"file1.h"));
+        assert!(glob_match(b"[]]x", b"]x"));
+        assert!(glob_match(b"\\*.c", b"*.c"));
+        assert!(!glob_match(b"\\*.c", b"a.c"));
+    }
+}
@@ -109,6 +109,23 @@ pub fn get_commit_file_diff(repo: &Repository, commit_id: &str, path: &std::path
     Ok(diffs.pop())
 }
 
+/// Get the diff between two revisions (`from..to`), without context lines.
+///
+/// Revisions are anything git accepts: commit IDs, branches, tags, `HEAD~3`.
+pub fn get_range_diff(repo: &Repository, from: &str, to: &str) -> Result<Vec<FileDiff>, GitError> {
+    let from_tree = repo.inner().revparse_single(from)?.peel_to_tree()?;
+    let to_tree = repo.inner().revparse_single(to)?.peel_to_tree()?;
+
+    let mut opts = git2::DiffOptions::new();
+    opts.context_lines(0);
+
+    let diff = repo
+        .inner()
+        .diff_tree_to_tree(Some(&from_tree), Some(&to_tree), Some(&mut opts))?;
+
+    parse_diff(&diff)
+}
+
 /// Parse a git2 diff into our types.
 fn parse_diff(diff: &git2::Diff) -> Result<Vec<FileDiff>, GitError> {
     let mut file_diffs = Vec::new();
@@ -226,4 +243,20 @@ mod tests {
         assert_eq!(diff[0].new_path, Some(PathBuf::from("file.txt")));
         assert!(!diff[0].hunks.is_empty());
     }
+
+    #[test]
+    fn test_range_diff() {
+        let (dir, repo) = init_test_repo();
+        fs::write(dir.path().join("file.txt"), "line1\nmodified\nline3\n").unwrap();
+        repo.stage(std::path::Path::new("file.txt")).unwrap();
+        repo.commit("Modify").unwrap();
+
+        let diff = get_range_diff(&repo, "HEAD~1", "HEAD").unwrap();
+        assert_eq!(diff.len(), 1);
+        let lines = &diff[0].hunks[0].lines;
+        assert_eq!(lines.len(), 2);
+        assert_eq!((lines[0].origin, lines[0].old_lineno), ('-', Some(2)));
+        assert_eq!((lines[1].origin, lines[1].new_lineno), ('+', Some(2)));
+        assert!(get_range_diff(&repo, "HEAD", "HEAD").unwrap().is_empty());
+    }
 }
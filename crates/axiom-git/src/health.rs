@@ -0,0 +1,115 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Repository health checks.
+
+use crate::{get_status, Repository};
+use axiom_core::HealthFinding;
+use std::path::Path;
+
+/// Check the repository containing `path` for problems that block commits
+/// or make build provenance unreliable.
+pub fn diagnose_repository(path: &Path) -> Vec<HealthFinding> {
+    let repo = match Repository::discover(path) {
+        Ok(repo) => repo,
+        Err(_) => {
+            return vec![HealthFinding::info(
+                "git.repository",
+                "Project is not in a Git repository",
+            )
+            .with_action("Initialize a repository so builds can be traced to a commit")
+            .with_path(path)];
+        }
+    };
+
+    let mut findings = Vec::new();
+
+    let lock = repo.inner().path().join("index.lock");
+    if lock.exists() {
+        findings.push(
+            HealthFinding::critical("git.index-lock", "Git index is locked by another process")
+                .with_action("If no Git command is running, delete the lock file")
+                .with_path(lock),
+        );
+    }
+
+    match repo.last_commit() {
+        Ok(None) | Err(_) => findings.push(
+            HealthFinding::info("git.commits", "Repository has no commits")
+                .with_action("Commit the project so build reports record a revision"),
+        ),
+        Ok(Some(_)) => {
+            if repo.inner().head_detached().unwrap_or(false) {
+                findings.push(
+                    HealthFinding::warning("git.head", "HEAD is detached")
+                        .with_action("Check out a branch before committing"),
+                );
+            }
+        }
+    }
+
+    match get_status(&repo) {
+        Ok(status) => {
+            for entry in &status.conflicted {
+                findings.push(
+                    HealthFinding::critical("git.conflict", "File has unresolved merge conflicts")
+                        .with_action("Resolve the conflict markers and stage the file")
+                        .with_path(repo.path().join(&entry.path)),
+                );
+            }
+        }
+        Err(e) => findings.push(
+            HealthFinding::critical(
+                "git.status",
+                format!("Cannot read repository status: {}", e),
+            )
+            .with_action("Run `git status` to inspect the repository"),
+        ),
+    }
+
+    if let Ok(Some(branch)) = repo.current_branch() {
+        if let Ok(remote) = repo.remote_status(&branch) {
+            if remote.behind > 0 {
+                findings.push(
+                    HealthFinding::info(
+                        "git.remote",
+                        format!(
+                            "Branch {} is {} commit(s) behind its remote",
+                            branch, remote.behind
+                        ),
+                    )
+                    .with_action("Pull to build the latest revision"),
+                );
+            }
+        }
+    }
+
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::Repository as Git2Repo;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_not_a_repository() {
+        let dir = TempDir::new().unwrap();
+        let findings = diagnose_repository(dir.path());
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].check, "git.repository");
+    }
+
+    #[test]
+    fn test_empty_repository_with_lock() {
+        let dir = TempDir::new().unwrap();
+        Git2Repo::init(dir.path()).unwrap();
+        fs::write(dir.path().join(".git").join("index.lock"), "").unwrap();
+
+        let findings = diagnose_repository(dir.path());
+        let checks: Vec<_> = findings.iter().map(|f| f.check.as_str()).collect();
+        assert_eq!(checks, vec!["git.index-lock", "git.commits"]);
+    }
+}
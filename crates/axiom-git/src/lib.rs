@@ -6,9 +6,13 @@
 //! Git integration via libgit2.
 
 mod diff;
+mod health;
 mod repo;
+mod requirements;
 mod status;
 
 pub use diff::*;
+pub use health::*;
 pub use repo::*;
+pub use requirements::*;
 pub use status::*;
@@ -286,6 +286,74 @@ impl Repository {
 
         Ok(files)
     }
+
+    /// Get the first parent of a commit.
+    pub fn parent_id(&self, commit_id: &str) -> Result<Option<String>, GitError> {
+        let oid = git2::Oid::from_str(commit_id)?;
+        let commit = self.inner.find_commit(oid)?;
+        Ok(commit.parent_ids().next().map(|id| id.to_string()))
+    }
+
+    /// Get a file's text content at a commit.
+    ///
+    /// Returns `None` if the file does not exist there or is not UTF-8.
+    pub fn file_at(&self, commit_id: &str, path: &Path) -> Result<Option<String>, GitError> {
+        let oid = git2::Oid::from_str(commit_id)?;
+        let tree = self.inner.find_commit(oid)?.tree()?;
+        let entry = match tree.get_path(path) {
+            Ok(entry) => entry,
+            Err(_) => return Ok(None),
+        };
+        let blob = match entry.to_object(&self.inner)?.into_blob() {
+            Ok(blob) => blob,
+            Err(_) => return Ok(None),
+        };
+        Ok(std::str::from_utf8(blob.content()).ok().map(str::to_string))
+    }
+
+    /// Get the most recent commit reachable from HEAD that changed each of
+    /// the given paths (relative to the repository root).
+    ///
+    /// Paths never committed are left out of the result.
+    pub fn last_commits_for(
+        &self,
+        paths: &[PathBuf],
+    ) -> Result<std::collections::HashMap<PathBuf, String>, GitError> {
+        let mut found = std::collections::HashMap::new();
+        if self.inner.head().is_err() {
+            return Ok(found);
+        }
+        let mut remaining: Vec<&PathBuf> = paths.iter().collect();
+
+        let mut revwalk = self.inner.revwalk()?;
+        revwalk.push_head()?;
+        revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)?;
+
+        let blob_id = |tree: &git2::Tree, path: &Path| tree.get_path(path).ok().map(|e| e.id());
+        for oid in revwalk {
+            if remaining.is_empty() {
+                break;
+            }
+            let commit = self.inner.find_commit(oid?)?;
+            let tree = commit.tree()?;
+            let parent_tree = match commit.parent(0) {
+                Ok(parent) => Some(parent.tree()?),
+                Err(_) => None,
+            };
+            remaining.retain(|path| {
+                let current = blob_id(&tree, path);
+                let previous = parent_tree.as_ref().and_then(|t| blob_id(t, path));
+                if current.is_some() && current != previous {
+                    found.insert((*path).clone(), commit.id().to_string());
+                    false
+                } else {
+                    true
+                }
+            });
+        }
+
+        Ok(found)
+    }
 }
 
 #[cfg(test)]
@@ -342,4 +410,41 @@ mod tests {
         let commit_id = repo.commit("Add test file").unwrap();
         assert!(!commit_id.is_empty());
     }
+
+    #[test]
+    fn test_file_at_commit() {
+        let (dir, repo) = init_test_repo();
+        let first = repo.last_commit().unwrap().unwrap().id;
+
+        fs::write(dir.path().join("test.txt"), "hello").unwrap();
+        repo.stage(Path::new("test.txt")).unwrap();
+        let second = repo.commit("Add test file").unwrap();
+
+        assert_eq!(repo.parent_id(&second).unwrap(), Some(first.clone()));
+        assert_eq!(repo.parent_id(&first).unwrap(), None);
+        assert_eq!(
+            repo.file_at(&second, Path::new("test.txt")).unwrap().as_deref(),
+            Some("hello")
+        );
+        assert_eq!(repo.file_at(&first, Path::new("test.txt")).unwrap(), None);
+    }
+
+    #[test]
+    fn test_last_commits_for() {
+        let (dir, repo) = init_test_repo();
+        fs::write(dir.path().join("a.txt"), "a").unwrap();
+        fs::write(dir.path().join("b.txt"), "b").unwrap();
+        repo.stage(Path::new("a.txt")).unwrap();
+        repo.stage(Path::new("b.txt")).unwrap();
+        let first = repo.commit("Add files").unwrap();
+        fs::write(dir.path().join("b.txt"), "b2").unwrap();
+        repo.stage(Path::new("b.txt")).unwrap();
+        let second = repo.commit("Change b").unwrap();
+
+        let paths = [PathBuf::from("a.txt"), PathBuf::from("b.txt"), PathBuf::from("c.txt")];
+        let commits = repo.last_commits_for(&paths).unwrap();
+        assert_eq!(commits.len(), 2);
+        assert_eq!(commits[&paths[0]], first);
+        assert_eq!(commits[&paths[1]], second);
+    }
 }
@@ -0,0 +1,485 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Requirement annotation history.
+//!
+//! Source files reference requirements with `REQ-` tags in comments
+//! (e.g. `/* Implements REQ-NAV-012 */`). Walking the commit history and
+//! comparing the tags in each changed file against its parent shows when
+//! a requirement's implementation was added, removed, or relocated. The
+//! volatility report ranks requirements by how often that happens.
+
+use crate::{CommitInfo, GitError, Repository};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+/// Prefix that introduces a requirement tag.
+const REQ_PREFIX: &str = "REQ-";
+
+/// Where a requirement tag appears.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct RequirementLocation {
+    /// Path relative to repository root.
+    pub file: String,
+    /// Line number (1-based).
+    pub line: u32,
+}
+
+/// A single requirement tag found in a file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RequirementRef {
+    /// Requirement identifier, including the `REQ-` prefix.
+    pub id: String,
+    /// Location of the tag.
+    pub location: RequirementLocation,
+}
+
+/// Kind of change to a requirement tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RequirementChangeKind {
+    /// Tag appears in a file that did not reference it before.
+    Added,
+    /// Tag no longer appears where it used to.
+    Removed,
+    /// Tag was removed from one file and added to another.
+    Moved,
+}
+
+/// A change to a requirement tag in one commit.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RequirementChange {
+    /// Requirement identifier.
+    pub id: String,
+    /// Kind of change.
+    pub kind: RequirementChangeKind,
+    /// Location before the commit (None if added).
+    pub old: Option<RequirementLocation>,
+    /// Location after the commit (None if removed).
+    pub new: Option<RequirementLocation>,
+}
+
+/// Requirement tag changes introduced by a commit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitRequirementChanges {
+    /// The commit.
+    pub commit: CommitInfo,
+    /// Tag changes relative to the commit's first parent.
+    pub changes: Vec<RequirementChange>,
+}
+
+/// Churn statistics for one requirement.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequirementVolatility {
+    /// Requirement identifier.
+    pub id: String,
+    /// Number of commits that changed the requirement's tags.
+    pub commits: usize,
+    /// Number of distinct authors of those commits.
+    pub authors: usize,
+    /// Tags added.
+    pub added: usize,
+    /// Tags removed.
+    pub removed: usize,
+    /// Tags moved between files.
+    pub moved: usize,
+    /// Timestamp of the most recent change.
+    pub last_changed: i64,
+}
+
+impl RequirementVolatility {
+    /// Total number of tag changes.
+    pub fn churn(&self) -> usize {
+        self.added + self.removed + self.moved
+    }
+}
+
+/// Requirement volatility across a range of history.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VolatilityReport {
+    /// Number of commits examined.
+    pub commits_scanned: usize,
+    /// Number of commits that changed at least one tag.
+    pub commits_with_changes: usize,
+    /// Requirements, most volatile first.
+    pub requirements: Vec<RequirementVolatility>,
+}
+
+/// Extract requirement tags from file contents.
+pub fn extract_requirement_refs(file: &str, text: &str) -> Vec<RequirementRef> {
+    let mut refs = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let mut search = 0;
+        while let Some(offset) = line[search..].find(REQ_PREFIX) {
+            let start = search + offset;
+            search = start + REQ_PREFIX.len();
+
+            // Ignore tags embedded in a longer identifier (e.g. `MY_REQ-1`).
+            let embedded = line[..start]
+                .chars()
+                .next_back()
+                .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_');
+            if embedded {
+                continue;
+            }
+
+            let rest = &line[search..];
+            let len = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+                .unwrap_or(rest.len());
+            let suffix = rest[..len].trim_end_matches('-');
+            if suffix.is_empty() {
+                continue;
+            }
+
+            refs.push(RequirementRef {
+                id: format!("{}{}", REQ_PREFIX, suffix),
+                location: RequirementLocation {
+                    file: file.to_string(),
+                    line: index as u32 + 1,
+                },
+            });
+            search += suffix.len();
+        }
+    }
+
+    refs
+}
+
+/// Compare requirement tags before and after a change.
+///
+/// Tags are compared per requirement and file, so edits that only shift
+/// line numbers are not reported. When a requirement loses a tag in one
+/// file and gains one in another, the pair is reported as a move.
+pub fn diff_requirement_refs(
+    old: &[RequirementRef],
+    new: &[RequirementRef],
+) -> Vec<RequirementChange> {
+    let old_by_id = group_by_id_and_file(old);
+    let new_by_id = group_by_id_and_file(new);
+
+    let ids: BTreeSet<&str> = old_by_id.keys().chain(new_by_id.keys()).copied().collect();
+    let mut changes = Vec::new();
+
+    for id in ids {
+        let empty = BTreeMap::new();
+        let old_files = old_by_id.get(id).unwrap_or(&empty);
+        let new_files = new_by_id.get(id).unwrap_or(&empty);
+
+        let files: BTreeSet<&str> = old_files.keys().chain(new_files.keys()).copied().collect();
+        let mut removed = Vec::new();
+        let mut added = Vec::new();
+
+        for file in files {
+            let before = old_files.get(file).map(Vec::as_slice).unwrap_or(&[]);
+            let after = new_files.get(file).map(Vec::as_slice).unwrap_or(&[]);
+
+            if before.len() > after.len() {
+                removed.extend(unmatched_lines(before, after, before.len() - after.len()));
+            } else if after.len() > before.len() {
+                added.extend(unmatched_lines(after, before, after.len() - before.len()));
+            }
+        }
+
+        let mut added = added.into_iter().peekable();
+        let mut leftover_removed = Vec::new();
+        for old_loc in removed {
+            match added.next_if(|new_loc| new_loc.file != old_loc.file) {
+                Some(new_loc) => changes.push(RequirementChange {
+                    id: id.to_string(),
+                    kind: RequirementChangeKind::Moved,
+                    old: Some(old_loc),
+                    new: Some(new_loc),
+                }),
+                None => leftover_removed.push(old_loc),
+            }
+        }
+
+        changes.extend(
+            leftover_removed
+                .into_iter()
+                .map(|old_loc| RequirementChange {
+                    id: id.to_string(),
+                    kind: RequirementChangeKind::Removed,
+                    old: Some(old_loc),
+                    new: None,
+                }),
+        );
+        changes.extend(added.map(|new_loc| RequirementChange {
+            id: id.to_string(),
+            kind: RequirementChangeKind::Added,
+            old: None,
+            new: Some(new_loc),
+        }));
+    }
+
+    changes
+}
+
+/// Group tag locations by requirement, then by file.
+fn group_by_id_and_file(
+    refs: &[RequirementRef],
+) -> BTreeMap<&str, BTreeMap<&str, Vec<RequirementLocation>>> {
+    let mut grouped: BTreeMap<&str, BTreeMap<&str, Vec<RequirementLocation>>> = BTreeMap::new();
+    for r in refs {
+        grouped
+            .entry(r.id.as_str())
+            .or_default()
+            .entry(r.location.file.as_str())
+            .or_default()
+            .push(r.location.clone());
+    }
+    grouped
+}
+
+/// Pick `count` locations from `from`, preferring lines not present in `other`.
+fn unmatched_lines(
+    from: &[RequirementLocation],
+    other: &[RequirementLocation],
+    count: usize,
+) -> Vec<RequirementLocation> {
+    let other_lines: BTreeSet<u32> = other.iter().map(|l| l.line).collect();
+    let (fresh, shared): (Vec<_>, Vec<_>) =
+        from.iter().partition(|l| !other_lines.contains(&l.line));
+    fresh
+        .into_iter()
+        .chain(shared)
+        .take(count)
+        .cloned()
+        .collect()
+}
+
+/// Requirement tag changes for a single commit, relative to its first parent.
+pub fn commit_requirement_changes(
+    repo: &Repository,
+    commit_id: &str,
+) -> Result<Vec<RequirementChange>, GitError> {
+    let parent = repo.parent_id(commit_id)?;
+
+    let mut old = Vec::new();
+    let mut new = Vec::new();
+    for file in repo.commit_files(commit_id)? {
+        let path = Path::new(&file);
+        if let Some(parent) = &parent {
+            if let Some(text) = repo.file_at(parent, path)? {
+                old.extend(extract_requirement_refs(&file, &text));
+            }
+        }
+        if let Some(text) = repo.file_at(commit_id, path)? {
+            new.extend(extract_requirement_refs(&file, &text));
+        }
+    }
+
+    Ok(diff_requirement_refs(&old, &new))
+}
+
+/// Requirement tag changes for the most recent `limit` commits.
+///
+/// Commits are returned newest first; commits that did not touch any
+/// requirement tag are omitted.
+pub fn requirement_history(
+    repo: &Repository,
+    limit: usize,
+) -> Result<Vec<CommitRequirementChanges>, GitError> {
+    let mut history = Vec::new();
+    for commit in repo.log(limit)? {
+        let changes = commit_requirement_changes(repo, &commit.id)?;
+        if !changes.is_empty() {
+            history.push(CommitRequirementChanges { commit, changes });
+        }
+    }
+    Ok(history)
+}
+
+/// Rank requirements by how often their tags changed.
+///
+/// Requirements touched by more commits come first; ties are broken by
+/// total churn and then by identifier.
+pub fn volatility_report(
+    history: &[CommitRequirementChanges],
+    commits_scanned: usize,
+) -> VolatilityReport {
+    struct Accumulator<'a> {
+        commits: BTreeSet<&'a str>,
+        authors: BTreeSet<&'a str>,
+        added: usize,
+        removed: usize,
+        moved: usize,
+        last_changed: i64,
+    }
+
+    let mut by_id: BTreeMap<&str, Accumulator> = BTreeMap::new();
+    for entry in history {
+        for change in &entry.changes {
+            let acc = by_id
+                .entry(change.id.as_str())
+                .or_insert_with(|| Accumulator {
+                    commits: BTreeSet::new(),
+                    authors: BTreeSet::new(),
+                    added: 0,
+                    removed: 0,
+                    moved: 0,
+                    last_changed: i64::MIN,
+                });
+            acc.commits.insert(entry.commit.id.as_str());
+            acc.authors.insert(entry.commit.email.as_str());
+            match change.kind {
+                RequirementChangeKind::Added => acc.added += 1,
+                RequirementChangeKind::Removed => acc.removed += 1,
+                RequirementChangeKind::Moved => acc.moved += 1,
+            }
+            acc.last_changed = acc.last_changed.max(entry.commit.timestamp);
+        }
+    }
+
+    let mut requirements: Vec<RequirementVolatility> = by_id
+        .into_iter()
+        .map(|(id, acc)| RequirementVolatility {
+            id: id.to_string(),
+            commits: acc.commits.len(),
+            authors: acc.authors.len(),
+            added: acc.added,
+            removed: acc.removed,
+            moved: acc.moved,
+            last_changed: acc.last_changed,
+        })
+        .collect();
+
+    requirements.sort_by(|a, b| {
+        b.commits
+            .cmp(&a.commits)
+            .then_with(|| b.churn().cmp(&a.churn()))
+            .then_with(|| a.id.cmp(&b.id))
+    });
+
+    VolatilityReport {
+        commits_scanned,
+        commits_with_changes: history.len(),
+        requirements,
+    }
+}
+
+/// Build a volatility report over the most recent `limit` commits.
+pub fn requirement_volatility(
+    repo: &Repository,
+    limit: usize,
+) -> Result<VolatilityReport, GitError> {
+    let commits_scanned = repo.log(limit)?.len();
+    let history = requirement_history(repo, limit)?;
+    Ok(volatility_report(&history, commits_scanned))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::Repository as Git2Repo;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn init_test_repo() -> (TempDir, Repository) {
+        let dir = TempDir::new().unwrap();
+        let repo = Git2Repo::init(dir.path()).unwrap();
+
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Test").unwrap();
+        config.set_str("user.email", "test@test.com").unwrap();
+
+        let sig = repo.signature().unwrap();
+        let tree_id = repo.index().unwrap().write_tree().unwrap();
+        let tree = repo.find_tree(tree_id).unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "Initial", &tree, &[])
+            .unwrap();
+
+        let repo = Repository::open(dir.path()).unwrap();
+        (dir, repo)
+    }
+
+    fn commit_file(dir: &TempDir, repo: &Repository, name: &str, contents: &str) -> String {
+        fs::write(dir.path().join(name), contents).unwrap();
+        repo.stage(Path::new(name)).unwrap();
+        repo.commit(&format!("Update {}", name)).unwrap()
+    }
+
+    fn refs(file: &str, text: &str) -> Vec<RequirementRef> {
+        extract_requirement_refs(file, text)
+    }
+
+    #[test]
+    fn test_extract_refs() {
+        let text = "/* Implements REQ-NAV-012, REQ-7. */\nint x; // MY_REQ-1 REQ- REQ-PWR-3-\n";
+        let found = refs("nav.c", text);
+
+        let ids: Vec<_> = found
+            .iter()
+            .map(|r| (r.id.as_str(), r.location.line))
+            .collect();
+        assert_eq!(
+            ids,
+            vec![("REQ-NAV-012", 1), ("REQ-7", 1), ("REQ-PWR-3", 2)]
+        );
+    }
+
+    #[test]
+    fn test_diff_ignores_line_shifts() {
+        let old = refs("a.c", "// REQ-1\n");
+        let new = refs("a.c", "\n\n// REQ-1\n");
+        assert!(diff_requirement_refs(&old, &new).is_empty());
+    }
+
+    #[test]
+    fn test_diff_added_removed_moved() {
+        let mut old = refs("a.c", "// REQ-1\n// REQ-2\n");
+        old.extend(refs("b.c", "// REQ-3\n"));
+        let mut new = refs("a.c", "\n// REQ-2\n// REQ-2\n");
+        new.extend(refs("c.c", "// REQ-3\n"));
+
+        let changes = diff_requirement_refs(&old, &new);
+        let summary: Vec<_> = changes.iter().map(|c| (c.id.as_str(), c.kind)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("REQ-1", RequirementChangeKind::Removed),
+                ("REQ-2", RequirementChangeKind::Added),
+                ("REQ-3", RequirementChangeKind::Moved),
+            ]
+        );
+
+        let moved = &changes[2];
+        assert_eq!(moved.old.as_ref().unwrap().file, "b.c");
+        assert_eq!(moved.new.as_ref().unwrap().file, "c.c");
+
+        // REQ-2 stayed on line 2, so the tag on line 3 is the new one.
+        assert_eq!(changes[1].new.as_ref().unwrap().line, 3);
+    }
+
+    #[test]
+    fn test_volatility_from_history() {
+        let (dir, repo) = init_test_repo();
+
+        commit_file(&dir, &repo, "nav.c", "// REQ-NAV-1\n// REQ-PWR-1\n");
+        commit_file(&dir, &repo, "nav.c", "// REQ-NAV-1\n");
+        commit_file(&dir, &repo, "nav.c", "");
+        commit_file(&dir, &repo, "guidance.c", "// REQ-NAV-1\n");
+        commit_file(&dir, &repo, "notes.txt", "unrelated\n");
+
+        let history = requirement_history(&repo, 100).unwrap();
+        assert_eq!(history.len(), 4);
+
+        let report = requirement_volatility(&repo, 100).unwrap();
+        assert_eq!(report.commits_scanned, 6);
+        assert_eq!(report.commits_with_changes, 4);
+        assert_eq!(report.requirements.len(), 2);
+
+        let nav = &report.requirements[0];
+        assert_eq!(nav.id, "REQ-NAV-1");
+        assert_eq!(nav.commits, 3);
+        assert_eq!((nav.added, nav.removed, nav.moved), (2, 1, 0));
+        assert_eq!(nav.authors, 1);
+
+        let pwr = &report.requirements[1];
+        assert_eq!(pwr.id, "REQ-PWR-1");
+        assert_eq!(pwr.commits, 2);
+        assert_eq!(pwr.churn(), 2);
+    }
+}
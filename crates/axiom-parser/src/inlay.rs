@@ -0,0 +1,355 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Inlay hints for numeric literals.
+//!
+//! Hex literals are annotated with their decimal and binary values. Hex
+//! literals that match a known register address are annotated with the
+//! register name instead. Literals inside `#define` bodies are included,
+//! since that is where most register addresses live.
+
+use crate::{Language, ParseError, Parser};
+use axiom_core::Position;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Widest value for which a binary equivalent is shown.
+const MAX_BINARY_BITS: u32 = 32;
+
+/// Kind of inlay hint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InlayHintKind {
+    /// Decimal/binary equivalent of a literal.
+    Value,
+    /// Register name for an address literal.
+    Register,
+}
+
+/// A hint rendered inline after a literal.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InlayHint {
+    /// Position the hint is anchored to (end of the literal).
+    pub position: Position,
+    /// Hint text.
+    pub label: String,
+    /// Hint kind.
+    pub kind: InlayHintKind,
+}
+
+/// Which hints to produce.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct InlayHintOptions {
+    /// Show decimal equivalents of hex literals.
+    pub decimal: bool,
+    /// Show binary equivalents of hex literals up to 32 bits.
+    pub binary: bool,
+    /// Show register names for address literals.
+    pub registers: bool,
+}
+
+impl Default for InlayHintOptions {
+    fn default() -> Self {
+        Self {
+            decimal: true,
+            binary: true,
+            registers: true,
+        }
+    }
+}
+
+/// A named register.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegisterName {
+    /// Peripheral name (e.g. "RCC").
+    pub peripheral: String,
+    /// Register name (e.g. "AHB1ENR").
+    pub register: String,
+}
+
+/// Register addresses, typically populated from an SVD file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RegisterMap {
+    registers: BTreeMap<u64, RegisterName>,
+}
+
+impl RegisterMap {
+    /// Create an empty register map.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a register at an absolute address.
+    pub fn insert(&mut self, address: u64, peripheral: &str, register: &str) {
+        self.registers.insert(
+            address,
+            RegisterName {
+                peripheral: peripheral.to_string(),
+                register: register.to_string(),
+            },
+        );
+    }
+
+    /// Look up the register at an address.
+    pub fn lookup(&self, address: u64) -> Option<&RegisterName> {
+        self.registers.get(&address)
+    }
+
+    /// Number of registers.
+    pub fn len(&self) -> usize {
+        self.registers.len()
+    }
+
+    /// Check if the map is empty.
+    pub fn is_empty(&self) -> bool {
+        self.registers.is_empty()
+    }
+}
+
+impl Parser {
+    /// Compute inlay hints for hex literals in source code.
+    ///
+    /// Hints are returned in source order.
+    pub fn inlay_hints(
+        &mut self,
+        source: &str,
+        language: Language,
+        registers: &RegisterMap,
+        options: &InlayHintOptions,
+    ) -> Result<Vec<InlayHint>, ParseError> {
+        let tree = self.parse_tree(source, language)?;
+        let source_bytes = source.as_bytes();
+
+        let mut literals = Vec::new();
+        let mut cursor = tree.walk();
+        let mut stack = vec![tree.root_node()];
+        while let Some(node) = stack.pop() {
+            match node.kind() {
+                "number_literal" => {
+                    let end = node.end_position();
+                    let text = node.utf8_text(source_bytes).unwrap_or("");
+                    literals.push((Position::new(end.row as u32, end.column as u32), text));
+                }
+                "preproc_arg" => {
+                    let start = node.start_position();
+                    let text = node.utf8_text(source_bytes).unwrap_or("");
+                    literals.extend(scan_number_tokens(
+                        text,
+                        Position::new(start.row as u32, start.column as u32),
+                    ));
+                }
+                _ => stack.extend(node.children(&mut cursor)),
+            }
+        }
+
+        let mut hints: Vec<InlayHint> = literals
+            .into_iter()
+            .filter_map(|(position, text)| literal_hint(text, position, registers, options))
+            .collect();
+        hints.sort_by_key(|h| (h.position.line, h.position.column));
+
+        Ok(hints)
+    }
+}
+
+/// Build the hint for a single literal, if any.
+fn literal_hint(
+    text: &str,
+    position: Position,
+    registers: &RegisterMap,
+    options: &InlayHintOptions,
+) -> Option<InlayHint> {
+    let value = parse_hex_literal(text)?;
+
+    if options.registers {
+        if let Some(name) = registers.lookup(value) {
+            return Some(InlayHint {
+                position,
+                label: format!("{}.{}", name.peripheral, name.register),
+                kind: InlayHintKind::Register,
+            });
+        }
+    }
+
+    let mut parts = Vec::new();
+    if options.decimal {
+        parts.push(value.to_string());
+    }
+    if options.binary && value <= u32::MAX as u64 {
+        parts.push(format_binary(value));
+    }
+    if parts.is_empty() {
+        return None;
+    }
+
+    Some(InlayHint {
+        position,
+        label: format!("= {}", parts.join(" | ")),
+        kind: InlayHintKind::Value,
+    })
+}
+
+/// Parse a C/C++ hex integer literal, ignoring suffixes and digit separators.
+pub fn parse_hex_literal(text: &str) -> Option<u64> {
+    let digits = text
+        .strip_prefix("0x")
+        .or_else(|| text.strip_prefix("0X"))?
+        .trim_end_matches(['u', 'U', 'l', 'L', 'z', 'Z']);
+    let digits: String = digits.chars().filter(|&c| c != '\'').collect();
+    if digits.is_empty() {
+        return None;
+    }
+    u64::from_str_radix(&digits, 16).ok()
+}
+
+/// Format a value as binary, grouped into nibbles.
+fn format_binary(value: u64) -> String {
+    let bits = (u64::BITS - value.leading_zeros()).clamp(1, MAX_BINARY_BITS);
+    let nibbles = bits.div_ceil(4);
+    let groups: Vec<String> = (0..nibbles)
+        .rev()
+        .map(|i| format!("{:04b}", (value >> (i * 4)) & 0xF))
+        .collect();
+    format!("0b{}", groups.join("_"))
+}
+
+/// Find number tokens in raw preprocessor text.
+///
+/// Comments, string/char literals, and identifiers are skipped. Returned
+/// positions are the end of each token, offset from `origin`.
+fn scan_number_tokens(text: &str, origin: Position) -> Vec<(Position, &str)> {
+    let bytes = text.as_bytes();
+    let mut tokens = Vec::new();
+    let mut line = origin.line;
+    let mut line_start: isize = -(origin.column as isize);
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let c = bytes[i];
+        if c == b'\n' {
+            line += 1;
+            line_start = i as isize + 1;
+            i += 1;
+        } else if c == b'/' && bytes.get(i + 1) == Some(&b'/') {
+            while i < bytes.len() && bytes[i] != b'\n' {
+                i += 1;
+            }
+        } else if c == b'/' && bytes.get(i + 1) == Some(&b'*') {
+            i += 2;
+            while i < bytes.len() && !(bytes[i] == b'*' && bytes.get(i + 1) == Some(&b'/')) {
+                if bytes[i] == b'\n' {
+                    line += 1;
+                    line_start = i as isize + 1;
+                }
+                i += 1;
+            }
+            i += 2;
+        } else if c == b'"' || c == b'\'' {
+            i += 1;
+            while i < bytes.len() && bytes[i] != c && bytes[i] != b'\n' {
+                i += if bytes[i] == b'\\' { 2 } else { 1 };
+            }
+            i += 1;
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < bytes.len()
+                && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'\'' || bytes[i] == b'.')
+            {
+                i += 1;
+            }
+            let column = (i as isize - line_start) as u32;
+            tokens.push((Position::new(line, column), &text[start..i]));
+        } else if c.is_ascii_alphabetic() || c == b'_' {
+            while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                i += 1;
+            }
+        } else {
+            i += 1;
+        }
+    }
+
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hints(source: &str, registers: &RegisterMap) -> Vec<InlayHint> {
+        let mut parser = Parser::new().unwrap();
+        parser
+            .inlay_hints(source, Language::C, registers, &InlayHintOptions::default())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parse_hex_literal() {
+        assert_eq!(parse_hex_literal("0xFF"), Some(255));
+        assert_eq!(parse_hex_literal("0X10UL"), Some(16));
+        assert_eq!(parse_hex_literal("0xdead'beef"), Some(0xdead_beef));
+        assert_eq!(parse_hex_literal("255"), None);
+        assert_eq!(parse_hex_literal("0x"), None);
+        assert_eq!(parse_hex_literal("0x1.8p1"), None);
+    }
+
+    #[test]
+    fn test_format_binary() {
+        assert_eq!(format_binary(0), "0b0000");
+        assert_eq!(format_binary(0xA0), "0b1010_0000");
+        assert_eq!(format_binary(0x100), "0b0001_0000_0000");
+    }
+
+    #[test]
+    fn test_value_hints() {
+        let source = "int x = 0xA0;\nint y = 42;\n";
+        let result = hints(source, &RegisterMap::new());
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].label, "= 160 | 0b1010_0000");
+        assert_eq!(result[0].kind, InlayHintKind::Value);
+        assert_eq!(result[0].position, Position::new(0, 12));
+    }
+
+    #[test]
+    fn test_wide_values_skip_binary() {
+        let result = hints(
+            "unsigned long long x = 0x100000000ULL;",
+            &RegisterMap::new(),
+        );
+        assert_eq!(result[0].label, "= 4294967296");
+    }
+
+    #[test]
+    fn test_register_hints_in_defines() {
+        let mut registers = RegisterMap::new();
+        registers.insert(0x4002_1030, "RCC", "AHB1ENR");
+
+        let source = "#define RCC_AHB1ENR (*(volatile unsigned *)0x40021030UL) /* 0x10 */\n\
+                      void f(void) { *(volatile unsigned *)0x40021030 = 0x1; }\n";
+        let result = hints(source, &registers);
+
+        let labels: Vec<_> = result
+            .iter()
+            .map(|h| (h.position.line, h.label.as_str()))
+            .collect();
+        assert_eq!(
+            labels,
+            vec![(0, "RCC.AHB1ENR"), (1, "RCC.AHB1ENR"), (1, "= 1 | 0b0001"),]
+        );
+        assert_eq!(result[0].position.column, 55);
+        assert_eq!(result[0].kind, InlayHintKind::Register);
+    }
+
+    #[test]
+    fn test_options_disable_hints() {
+        let mut parser = Parser::new().unwrap();
+        let options = InlayHintOptions {
+            decimal: false,
+            binary: false,
+            registers: true,
+        };
+        let result = parser
+            .inlay_hints("int x = 0xA0;", Language::C, &RegisterMap::new(), &options)
+            .unwrap();
+        assert!(result.is_empty());
+    }
+}
@@ -6,7 +6,11 @@
 //! C/C++ parsing using tree-sitter.
 
 mod ast;
+mod inlay;
 mod parser;
+mod query;
 
 pub use ast::*;
+pub use inlay::*;
 pub use parser::*;
+pub use query::*;
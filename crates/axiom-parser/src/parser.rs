@@ -23,6 +23,14 @@ impl Language {
             _ => None,
         }
     }
+
+    /// Get the tree-sitter grammar for this language.
+    pub(crate) fn ts_language(&self) -> tree_sitter::Language {
+        match self {
+            Language::C => tree_sitter_c::language(),
+            Language::Cpp => tree_sitter_cpp::language(),
+        }
+    }
 }
 
 /// Parser error.
@@ -34,6 +42,9 @@ pub enum ParseError {
     #[error("Parse failed")]
     ParseFailed,
 
+    #[error("Invalid query: {0}")]
+    InvalidQuery(String),
+
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 }
@@ -49,12 +60,12 @@ impl Parser {
     pub fn new() -> Result<Self, ParseError> {
         let mut c_parser = tree_sitter::Parser::new();
         c_parser
-            .set_language(tree_sitter_c::language())
+            .set_language(Language::C.ts_language())
             .map_err(|_| ParseError::UnsupportedLanguage)?;
 
         let mut cpp_parser = tree_sitter::Parser::new();
         cpp_parser
-            .set_language(tree_sitter_cpp::language())
+            .set_language(Language::Cpp.ts_language())
             .map_err(|_| ParseError::UnsupportedLanguage)?;
 
         Ok(Self {
@@ -65,16 +76,25 @@ impl Parser {
 
     /// Parse source code.
     pub fn parse(&mut self, source: &str, language: Language) -> Result<AstNode, ParseError> {
+        let tree = self.parse_tree(source, language)?;
+        let root = tree.root_node();
+        let source_bytes = source.as_bytes();
+
+        Ok(AstNode::from_ts_node(root, source_bytes))
+    }
+
+    /// Parse source code into a raw tree-sitter tree.
+    pub(crate) fn parse_tree(
+        &mut self,
+        source: &str,
+        language: Language,
+    ) -> Result<tree_sitter::Tree, ParseError> {
         let parser = match language {
             Language::C => &mut self.c_parser,
             Language::Cpp => &mut self.cpp_parser,
         };
 
-        let tree = parser.parse(source, None).ok_or(ParseError::ParseFailed)?;
-        let root = tree.root_node();
-        let source_bytes = source.as_bytes();
-
-        Ok(AstNode::from_ts_node(root, source_bytes))
+        parser.parse(source, None).ok_or(ParseError::ParseFailed)
     }
 
     /// Parse a file.
@@ -0,0 +1,148 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Tree-sitter query support.
+
+use crate::{Language, ParseError, Parser};
+use axiom_core::{Position, Range};
+use serde::{Deserialize, Serialize};
+
+/// A node captured by a query pattern.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueryCapture {
+    /// Capture name (without the leading `@`).
+    pub name: String,
+    /// Node kind.
+    pub kind: String,
+    /// Range in source.
+    pub range: Range,
+    /// Source text of the captured node.
+    pub text: String,
+}
+
+/// One match of a query pattern.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueryMatch {
+    /// Index of the pattern that matched.
+    pub pattern_index: usize,
+    /// Captured nodes in pattern order.
+    pub captures: Vec<QueryCapture>,
+}
+
+impl QueryMatch {
+    /// Find a capture by name.
+    pub fn capture(&self, name: &str) -> Option<&QueryCapture> {
+        self.captures.iter().find(|c| c.name == name)
+    }
+}
+
+impl Parser {
+    /// Check that a query compiles for a language.
+    pub fn validate_query(&self, query: &str, language: Language) -> Result<(), ParseError> {
+        compile_query(query, language).map(|_| ())
+    }
+
+    /// Run a tree-sitter query against source code.
+    ///
+    /// Text predicates (`#eq?`, `#match?`) are applied.
+    pub fn query(
+        &mut self,
+        source: &str,
+        language: Language,
+        query: &str,
+    ) -> Result<Vec<QueryMatch>, ParseError> {
+        let query = compile_query(query, language)?;
+        let tree = self.parse_tree(source, language)?;
+        let source_bytes = source.as_bytes();
+        let names = query.capture_names();
+
+        let mut cursor = tree_sitter::QueryCursor::new();
+        let matches = cursor
+            .matches(&query, tree.root_node(), source_bytes)
+            .map(|m| QueryMatch {
+                pattern_index: m.pattern_index,
+                captures: m
+                    .captures
+                    .iter()
+                    .map(|c| QueryCapture {
+                        name: names[c.index as usize].clone(),
+                        kind: c.node.kind().to_string(),
+                        range: node_range(&c.node),
+                        text: c.node.utf8_text(source_bytes).unwrap_or("").to_string(),
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        Ok(matches)
+    }
+}
+
+fn compile_query(query: &str, language: Language) -> Result<tree_sitter::Query, ParseError> {
+    tree_sitter::Query::new(language.ts_language(), query).map_err(|e| {
+        ParseError::InvalidQuery(format!(
+            "{} at {}:{}",
+            e.message.trim(),
+            e.row + 1,
+            e.column + 1
+        ))
+    })
+}
+
+fn node_range(node: &tree_sitter::Node) -> Range {
+    let start = node.start_position();
+    let end = node.end_position();
+    Range::new(
+        Position::new(start.row as u32, start.column as u32),
+        Position::new(end.row as u32, end.column as u32),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_captures() {
+        let mut parser = Parser::new().unwrap();
+        let source = "int add(int a, int b) { return a + b; }\nvoid noop(void) {}\n";
+
+        let matches = parser
+            .query(
+                source,
+                Language::C,
+                "(function_definition declarator: (function_declarator declarator: (identifier) @name))",
+            )
+            .unwrap();
+
+        let names: Vec<&str> = matches
+            .iter()
+            .map(|m| m.capture("name").unwrap().text.as_str())
+            .collect();
+        assert_eq!(names, vec!["add", "noop"]);
+        assert_eq!(matches[1].captures[0].range.start, Position::new(1, 5));
+    }
+
+    #[test]
+    fn test_query_predicates() {
+        let mut parser = Parser::new().unwrap();
+        let source = "void f(void) { char *p = malloc(4); free(p); }";
+
+        let matches = parser
+            .query(
+                source,
+                Language::C,
+                "((call_expression function: (identifier) @fn) (#eq? @fn \"malloc\"))",
+            )
+            .unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].captures[0].text, "malloc");
+    }
+
+    #[test]
+    fn test_invalid_query() {
+        let parser = Parser::new().unwrap();
+        let result = parser.validate_query("(not_a_node_kind)", Language::C);
+        assert!(matches!(result, Err(ParseError::InvalidQuery(_))));
+    }
+}
@@ -0,0 +1,188 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Settings file health checks.
+
+use crate::{Settings, SCHEMA_VERSION};
+use axiom_core::HealthFinding;
+use std::fs;
+use std::path::Path;
+
+/// Top-level tables understood by the current schema.
+const KNOWN_SECTIONS: &[&str] = &[
+    "version",
+    "toolchains",
+    "build",
+    "editor",
+    "assembly",
+    "debug",
+    "ui",
+    "compliance",
+];
+
+/// Check a settings file for problems without modifying it.
+///
+/// Unlike [`crate::load`], this never migrates or rewrites the file.
+pub fn diagnose_settings(path: &Path) -> Vec<HealthFinding> {
+    if !path.exists() {
+        return vec![
+            HealthFinding::info("settings.file", "No settings file, using defaults")
+                .with_path(path),
+        ];
+    }
+
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => {
+            return vec![HealthFinding::critical(
+                "settings.file",
+                format!("Cannot read settings file: {}", e),
+            )
+            .with_action("Check the file permissions")
+            .with_path(path)];
+        }
+    };
+
+    let table: toml::Table = match toml::from_str(&content) {
+        Ok(table) => table,
+        Err(e) => {
+            return vec![HealthFinding::critical(
+                "settings.syntax",
+                format!("Settings file is not valid TOML: {}", e),
+            )
+            .with_action("Fix the syntax error or delete the file to restore defaults")
+            .with_path(path)];
+        }
+    };
+
+    let mut findings = Vec::new();
+    for key in table.keys() {
+        if !KNOWN_SECTIONS.contains(&key.as_str()) {
+            findings.push(
+                HealthFinding::warning(
+                    "settings.unknown-key",
+                    format!("Unknown settings section `{}` is ignored", key),
+                )
+                .with_action("Remove it or check the spelling")
+                .with_path(path),
+            );
+        }
+    }
+
+    let settings: Settings = match toml::from_str(&content) {
+        Ok(settings) => settings,
+        Err(e) => {
+            findings.push(
+                HealthFinding::critical(
+                    "settings.schema",
+                    format!("Settings do not match the schema: {}", e),
+                )
+                .with_action("Correct the value or reset settings to defaults")
+                .with_path(path),
+            );
+            return findings;
+        }
+    };
+
+    if settings.version > SCHEMA_VERSION {
+        findings.push(
+            HealthFinding::critical(
+                "settings.version",
+                format!(
+                    "Settings schema version {} is newer than supported version {}",
+                    settings.version, SCHEMA_VERSION
+                ),
+            )
+            .with_action("Update Axiom or reset settings to defaults")
+            .with_path(path),
+        );
+    } else if settings.version < SCHEMA_VERSION {
+        findings.push(
+            HealthFinding::info(
+                "settings.version",
+                format!(
+                    "Settings schema version {} will be migrated to {}",
+                    settings.version, SCHEMA_VERSION
+                ),
+            )
+            .with_path(path),
+        );
+    }
+
+    let toolchains = &settings.toolchains;
+    for (name, configured) in [
+        ("clang_path", &toolchains.clang_path),
+        ("gcc_path", &toolchains.gcc_path),
+        ("arm_gcc_path", &toolchains.arm_gcc_path),
+    ] {
+        if let Some(tool) = configured {
+            if !tool.exists() {
+                findings.push(
+                    HealthFinding::warning(
+                        "settings.toolchain-path",
+                        format!("Configured toolchains.{} does not exist", name),
+                    )
+                    .with_action("Update the path or clear it to use auto-detection")
+                    .with_path(tool),
+                );
+            }
+        }
+    }
+
+    if settings.build.optimization_level > 3 {
+        findings.push(
+            HealthFinding::warning(
+                "settings.optimization",
+                format!(
+                    "Optimization level {} is out of range (0-3)",
+                    settings.build.optimization_level
+                ),
+            )
+            .with_path(path),
+        );
+    }
+
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_missing_and_invalid_files() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("settings.toml");
+        assert_eq!(diagnose_settings(&path)[0].check, "settings.file");
+
+        fs::write(&path, "version = [").unwrap();
+        let findings = diagnose_settings(&path);
+        assert_eq!(findings[0].check, "settings.syntax");
+        assert!(findings[0].action.is_some());
+    }
+
+    #[test]
+    fn test_schema_issues() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("settings.toml");
+        fs::write(
+            &path,
+            "version = 99\n[biuld]\njobs = 4\n[toolchains]\narm_gcc_path = \"/nonexistent/arm-none-eabi-gcc\"\n",
+        )
+        .unwrap();
+
+        let checks: Vec<_> = diagnose_settings(&path)
+            .into_iter()
+            .map(|f| f.check)
+            .collect();
+        assert_eq!(
+            checks,
+            vec![
+                "settings.unknown-key",
+                "settings.version",
+                "settings.toolchain-path"
+            ]
+        );
+    }
+}
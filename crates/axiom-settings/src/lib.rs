@@ -8,10 +8,14 @@
 mod schema;
 mod migration;
 mod persistence;
+mod health;
+mod startup;
 
 pub use schema::*;
 pub use migration::*;
 pub use persistence::*;
+pub use health::*;
+pub use startup::*;
 
 /// Current settings schema version.
 pub const SCHEMA_VERSION: u32 = 1;
@@ -36,6 +36,10 @@ pub struct Settings {
     /// UI settings.
     #[serde(default)]
     pub ui: UiSettings,
+
+    /// Compliance and audit settings.
+    #[serde(default)]
+    pub compliance: ComplianceSettings,
 }
 
 fn default_version() -> u32 {
@@ -52,6 +56,7 @@ impl Default for Settings {
             assembly: AssemblySettings::default(),
             debug: DebugSettings::default(),
             ui: UiSettings::default(),
+            compliance: ComplianceSettings::default(),
         }
     }
 }
@@ -98,6 +103,24 @@ pub struct BuildSettings {
     /// Enable debug symbols.
     #[serde(default = "default_true")]
     pub debug_symbols: bool,
+
+    /// Warning profile name (e.g. "avionics-strict").
+    #[serde(default)]
+    pub warning_profile: Option<String>,
+
+    /// Build profile name (e.g. "release"); `None` builds with the request
+    /// defaults.
+    #[serde(default)]
+    pub profile: Option<String>,
+
+    /// Seconds before a hung compiler, linker or objcopy is killed (0 waits
+    /// indefinitely).
+    #[serde(default = "default_tool_timeout")]
+    pub tool_timeout_secs: u64,
+
+    /// Captured output per tool stream, in MiB (0 for no limit).
+    #[serde(default = "default_tool_output_mib")]
+    pub max_tool_output_mib: u64,
 }
 
 impl Default for BuildSettings {
@@ -106,6 +129,10 @@ impl Default for BuildSettings {
             output_dir: default_build_dir(),
             optimization_level: default_opt_level(),
             debug_symbols: true,
+            warning_profile: None,
+            profile: None,
+            tool_timeout_secs: default_tool_timeout(),
+            max_tool_output_mib: default_tool_output_mib(),
         }
     }
 }
@@ -118,6 +145,14 @@ fn default_opt_level() -> u8 {
     0
 }
 
+fn default_tool_timeout() -> u64 {
+    600
+}
+
+fn default_tool_output_mib() -> u64 {
+    16
+}
+
 /// Editor configuration.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct EditorSettings {
@@ -250,6 +285,100 @@ pub enum Theme {
     Light,
 }
 
+/// Compliance and audit configuration.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ComplianceSettings {
+    /// Record command line, working directory, environment and toolchain
+    /// hash with every compile and link result.
+    #[serde(default)]
+    pub capture_environment: bool,
+
+    /// Environment variables to record; empty means the toolchain defaults.
+    #[serde(default)]
+    pub captured_variables: Vec<String>,
+
+    /// Hash the toolchain binary when capturing.
+    #[serde(default = "default_true")]
+    pub hash_toolchain: bool,
+
+    /// Refuse to build when the resolved toolchain differs from the
+    /// project's `toolchain.lock`, instead of only warning.
+    #[serde(default)]
+    pub enforce_toolchain_lock: bool,
+
+    /// Rotate the tool usage log into a compressed segment once it reaches
+    /// this many megabytes.
+    #[serde(default)]
+    pub tool_log_max_mb: Option<u64>,
+
+    /// Rotate the tool usage log once its oldest record is this many days
+    /// old.
+    #[serde(default)]
+    pub tool_log_max_age_days: Option<u64>,
+
+    /// How requirement annotations are written in source comments.
+    #[serde(default)]
+    pub annotations: AnnotationSettings,
+}
+
+impl Default for ComplianceSettings {
+    fn default() -> Self {
+        Self {
+            capture_environment: false,
+            captured_variables: Vec::new(),
+            hash_toolchain: true,
+            enforce_toolchain_lock: false,
+            tool_log_max_mb: None,
+            tool_log_max_age_days: None,
+            annotations: AnnotationSettings::default(),
+        }
+    }
+}
+
+/// Requirement annotation syntax for traceability.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AnnotationSettings {
+    /// Requirement ID prefixes (e.g. `SRS-`, `HLR-`, `LLR-`).
+    #[serde(default = "default_requirement_prefixes")]
+    pub prefixes: Vec<String>,
+
+    /// Anchored regular expressions for IDs without a common prefix
+    /// (e.g. `^[A-Z]{3}\d{4}$`).
+    #[serde(default)]
+    pub id_patterns: Vec<String>,
+
+    /// Comment markers for verification links (`TEST:`).
+    #[serde(default = "default_test_markers")]
+    pub test_markers: Vec<String>,
+
+    /// Comment markers for derived requirement links (`DERIVED:`).
+    #[serde(default = "default_derived_markers")]
+    pub derived_markers: Vec<String>,
+}
+
+impl Default for AnnotationSettings {
+    fn default() -> Self {
+        Self {
+            prefixes: default_requirement_prefixes(),
+            id_patterns: Vec::new(),
+            test_markers: default_test_markers(),
+            derived_markers: default_derived_markers(),
+        }
+    }
+}
+
+fn default_requirement_prefixes() -> Vec<String> {
+    vec!["REQ-".to_string()]
+}
+
+fn default_test_markers() -> Vec<String> {
+    vec!["TEST".to_string()]
+}
+
+fn default_derived_markers() -> Vec<String> {
+    vec!["DERIVED".to_string()]
+}
+
 fn default_theme() -> Theme {
     Theme::Dark
 }
@@ -269,6 +398,9 @@ mod tests {
         assert_eq!(settings.ui.theme, Theme::Dark);
         assert_eq!(settings.editor.font_size, 14);
         assert!(settings.toolchains.auto_detect);
+        assert!(!settings.compliance.capture_environment);
+        assert_eq!(settings.compliance.annotations.prefixes, ["REQ-"]);
+        assert_eq!(settings.build.tool_timeout_secs, 600);
     }
 
     #[test]
@@ -0,0 +1,322 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Startup failure tracking and safe mode.
+//!
+//! A small journal next to the settings file records which subsystem is
+//! being initialized. Release builds abort on panic, so a crash is only
+//! visible on the next launch: a boot that never reached "ready" counts as
+//! a failure, and the subsystem it was initializing is disabled. When the
+//! crash cannot be attributed, optional subsystems are disabled one per
+//! failed boot until the app starts again.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Failed boots before subsystems are disabled without a known culprit.
+pub const SAFE_MODE_THRESHOLD: u32 = 2;
+
+/// A startup subsystem that safe mode can disable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Subsystem {
+    /// Loading the settings file; disabled means running on defaults.
+    Settings,
+    /// Symbol indexing of parsed files.
+    SymbolIndex,
+    /// Compliance environment capture on compile and link.
+    Compliance,
+    /// Opening a terminal session when the window loads.
+    TerminalAutostart,
+}
+
+impl Subsystem {
+    /// Order in which unattributed failures disable subsystems.
+    pub const ISOLATION_ORDER: [Subsystem; 4] = [
+        Subsystem::SymbolIndex,
+        Subsystem::Compliance,
+        Subsystem::TerminalAutostart,
+        Subsystem::Settings,
+    ];
+
+    /// Human-readable name.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Subsystem::Settings => "settings",
+            Subsystem::SymbolIndex => "symbol index",
+            Subsystem::Compliance => "compliance capture",
+            Subsystem::TerminalAutostart => "terminal autostart",
+        }
+    }
+}
+
+/// Why a subsystem was disabled.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubsystemFailure {
+    /// The subsystem.
+    pub subsystem: Subsystem,
+    /// Failure description.
+    pub reason: String,
+    /// Whether the failure was traced to this subsystem, rather than it
+    /// being disabled to isolate an unattributed crash.
+    pub attributed: bool,
+}
+
+/// Persisted startup state.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct StartupJournal {
+    /// A boot started and has not reached ready.
+    #[serde(default)]
+    booting: bool,
+    /// Consecutive boots that did not reach ready.
+    #[serde(default)]
+    failures: u32,
+    /// Subsystem being initialized when the journal was last written.
+    #[serde(default)]
+    current: Option<Subsystem>,
+    /// Disabled subsystems.
+    #[serde(default)]
+    disabled: Vec<SubsystemFailure>,
+}
+
+/// Safe mode summary for the UI.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SafeModeStatus {
+    /// At least one subsystem is disabled.
+    pub active: bool,
+    /// Consecutive failed boots before this one.
+    pub failed_boots: u32,
+    /// Disabled subsystems with reasons.
+    pub disabled: Vec<SubsystemFailure>,
+}
+
+/// Tracks subsystem initialization for the current boot.
+#[derive(Debug)]
+pub struct StartupGuard {
+    path: PathBuf,
+    journal: StartupJournal,
+}
+
+/// Get the default startup journal path.
+pub fn default_startup_journal_path() -> PathBuf {
+    crate::default_settings_path().with_file_name("startup.toml")
+}
+
+impl StartupGuard {
+    /// Start a boot, accounting for how the previous one ended.
+    ///
+    /// A missing or unreadable journal is treated as a clean start.
+    pub fn begin(path: &Path) -> Self {
+        let mut journal: StartupJournal = fs::read_to_string(path)
+            .ok()
+            .and_then(|content| toml::from_str(&content).ok())
+            .unwrap_or_default();
+
+        if journal.booting {
+            journal.failures += 1;
+            match journal.current.take() {
+                Some(subsystem) => disable(
+                    &mut journal,
+                    subsystem,
+                    format!(
+                        "Startup stopped while initializing the {}",
+                        subsystem.name()
+                    ),
+                    true,
+                ),
+                None if journal.failures >= SAFE_MODE_THRESHOLD => {
+                    let next = Subsystem::ISOLATION_ORDER
+                        .into_iter()
+                        .find(|s| !journal.disabled.iter().any(|d| d.subsystem == *s));
+                    if let Some(subsystem) = next {
+                        let reason = format!(
+                            "Disabled after {} failed startups to isolate the fault",
+                            journal.failures
+                        );
+                        disable(&mut journal, subsystem, reason, false);
+                    }
+                }
+                None => {}
+            }
+        }
+
+        journal.booting = true;
+        let guard = Self {
+            path: path.to_path_buf(),
+            journal,
+        };
+        guard.write();
+        guard
+    }
+
+    /// Start a boot using the default journal path.
+    pub fn begin_default() -> Self {
+        Self::begin(&default_startup_journal_path())
+    }
+
+    /// Whether a subsystem may run.
+    pub fn is_enabled(&self, subsystem: Subsystem) -> bool {
+        !self
+            .journal
+            .disabled
+            .iter()
+            .any(|d| d.subsystem == subsystem)
+    }
+
+    /// Run a subsystem's initialization, recording it in the journal.
+    ///
+    /// After the boot is ready this just calls `init`; crashes past that
+    /// point are not startup failures.
+    pub fn run<T>(&mut self, subsystem: Subsystem, init: impl FnOnce() -> T) -> T {
+        if !self.journal.booting {
+            return init();
+        }
+        self.journal.current = Some(subsystem);
+        self.write();
+        let result = init();
+        self.journal.current = None;
+        self.write();
+        result
+    }
+
+    /// Disable a subsystem that failed without crashing (e.g. a corrupt file).
+    pub fn record_failure(&mut self, subsystem: Subsystem, reason: impl Into<String>) {
+        disable(&mut self.journal, subsystem, reason.into(), true);
+        self.write();
+    }
+
+    /// Mark the boot as successful.
+    ///
+    /// Disabled subsystems stay disabled until [`StartupGuard::reset`].
+    pub fn mark_ready(&mut self) {
+        self.journal.booting = false;
+        self.journal.failures = 0;
+        self.journal.current = None;
+        self.write();
+    }
+
+    /// Re-enable all subsystems.
+    pub fn reset(&mut self) {
+        self.journal.disabled.clear();
+        self.journal.failures = 0;
+        self.write();
+    }
+
+    /// Current safe mode status.
+    pub fn status(&self) -> SafeModeStatus {
+        SafeModeStatus {
+            active: !self.journal.disabled.is_empty(),
+            failed_boots: self.journal.failures,
+            disabled: self.journal.disabled.clone(),
+        }
+    }
+
+    /// Write the journal, ignoring errors: tracking must never stop startup.
+    fn write(&self) {
+        if let Some(parent) = self.path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        if let Ok(content) = toml::to_string_pretty(&self.journal) {
+            let _ = fs::write(&self.path, content);
+        }
+    }
+}
+
+fn disable(journal: &mut StartupJournal, subsystem: Subsystem, reason: String, attributed: bool) {
+    journal.disabled.retain(|d| d.subsystem != subsystem);
+    journal.disabled.push(SubsystemFailure {
+        subsystem,
+        reason,
+        attributed,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_clean_boots() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("startup.toml");
+
+        for _ in 0..3 {
+            let mut guard = StartupGuard::begin(&path);
+            assert!(!guard.status().active);
+            guard.run(Subsystem::Settings, || ());
+            guard.mark_ready();
+        }
+    }
+
+    #[test]
+    fn test_crash_in_subsystem_disables_it() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("startup.toml");
+
+        let mut guard = StartupGuard::begin(&path);
+        guard.run(Subsystem::Settings, || ());
+        // Simulate a crash: the journal is left pointing at the index.
+        guard.journal.current = Some(Subsystem::SymbolIndex);
+        guard.write();
+        drop(guard);
+
+        let guard = StartupGuard::begin(&path);
+        let status = guard.status();
+        assert!(status.active);
+        assert_eq!(status.failed_boots, 1);
+        assert_eq!(status.disabled[0].subsystem, Subsystem::SymbolIndex);
+        assert!(status.disabled[0].attributed);
+        assert!(!guard.is_enabled(Subsystem::SymbolIndex));
+        assert!(guard.is_enabled(Subsystem::Settings));
+    }
+
+    #[test]
+    fn test_unattributed_crashes_disable_one_by_one() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("startup.toml");
+
+        // Boots that never reach ready, outside any tracked subsystem.
+        drop(StartupGuard::begin(&path));
+        let guard = StartupGuard::begin(&path);
+        assert!(!guard.status().active);
+        drop(guard);
+
+        let guard = StartupGuard::begin(&path);
+        assert!(!guard.is_enabled(Subsystem::SymbolIndex));
+        assert!(guard.is_enabled(Subsystem::Compliance));
+        drop(guard);
+
+        let mut guard = StartupGuard::begin(&path);
+        assert!(!guard.is_enabled(Subsystem::Compliance));
+        assert!(!guard.status().disabled[1].attributed);
+
+        // A successful boot keeps the isolation but clears the failure count.
+        guard.mark_ready();
+        let mut guard = StartupGuard::begin(&path);
+        let status = guard.status();
+        assert_eq!(status.failed_boots, 0);
+        assert_eq!(status.disabled.len(), 2);
+
+        guard.reset();
+        guard.mark_ready();
+        assert!(!StartupGuard::begin(&path).status().active);
+    }
+
+    #[test]
+    fn test_record_failure_and_corrupt_journal() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("startup.toml");
+        fs::write(&path, "not = [valid").unwrap();
+
+        let mut guard = StartupGuard::begin(&path);
+        assert!(!guard.status().active);
+        guard.record_failure(Subsystem::Settings, "TOML parse error");
+        guard.mark_ready();
+
+        let guard = StartupGuard::begin(&path);
+        assert_eq!(guard.status().disabled[0].reason, "TOML parse error");
+        assert!(!guard.is_enabled(Subsystem::Settings));
+    }
+}
@@ -0,0 +1,191 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Static library (archive) creation.
+
+use crate::limits::{is_timeout, run_limited};
+use crate::DetectedToolchain;
+use axiom_core::Diagnostic;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Instant;
+
+/// A request to build a static library.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchiveRequest {
+    /// Output archive path (e.g. "build/libdrivers.a").
+    pub output: PathBuf,
+    /// Member objects, in archive order.
+    pub objects: Vec<PathBuf>,
+    /// Zero timestamps, UIDs and modes so identical inputs give identical archives.
+    pub deterministic: bool,
+    /// Write a symbol index.
+    pub index: bool,
+}
+
+impl ArchiveRequest {
+    /// Create a deterministic, indexed archive request.
+    pub fn new(output: impl Into<PathBuf>, objects: Vec<PathBuf>) -> Self {
+        Self {
+            output: output.into(),
+            objects,
+            deterministic: true,
+            index: true,
+        }
+    }
+
+    /// Set deterministic mode.
+    pub fn with_deterministic(mut self, deterministic: bool) -> Self {
+        self.deterministic = deterministic;
+        self
+    }
+
+    /// Set whether a symbol index is written.
+    pub fn with_index(mut self, index: bool) -> Self {
+        self.index = index;
+        self
+    }
+
+    /// Keep only objects whose file name matches one of `names`.
+    pub fn select(mut self, names: &[&str]) -> Self {
+        self.objects.retain(|o| {
+            o.file_name()
+                .map(|n| names.iter().any(|name| n.to_string_lossy() == *name))
+                .unwrap_or(false)
+        });
+        self
+    }
+}
+
+/// Result of building an archive.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveResult {
+    /// Exit code of ar.
+    pub exit_code: i32,
+    /// Standard output.
+    pub stdout: String,
+    /// Standard error.
+    pub stderr: String,
+    /// Duration in milliseconds.
+    pub duration_ms: u64,
+    /// Whether the ar was killed for exceeding the tool timeout.
+    #[serde(default)]
+    pub timed_out: bool,
+    /// Parsed diagnostics.
+    pub diagnostics: Vec<Diagnostic>,
+    /// Output archive path.
+    pub output: PathBuf,
+}
+
+impl ArchiveResult {
+    /// Check if the archive was built.
+    pub fn success(&self) -> bool {
+        self.exit_code == 0
+    }
+}
+
+/// Build ar arguments for an archive request.
+///
+/// Uses `r` (replace members) and `c` (create silently); `s` writes the
+/// index and `D` enables deterministic mode.
+pub fn build_archive_command(request: &ArchiveRequest) -> Vec<String> {
+    let mut ops = String::from("rc");
+    if request.index {
+        ops.push('s');
+    }
+    ops.push(if request.deterministic { 'D' } else { 'U' });
+
+    let mut args = vec![ops, request.output.display().to_string()];
+    args.extend(request.objects.iter().map(|o| o.display().to_string()));
+    args
+}
+
+/// Build a static library with the toolchain's ar.
+///
+/// Any existing archive is removed first so stale members do not survive.
+pub fn create_archive(toolchain: &DetectedToolchain, request: &ArchiveRequest) -> ArchiveResult {
+    let ar = toolchain.sibling_tool("ar");
+    let args = build_archive_command(request);
+    let start = Instant::now();
+
+    if request.output.exists() {
+        let _ = std::fs::remove_file(&request.output);
+    }
+    let output = run_limited(&mut toolchain.tool_command(&ar, &args));
+
+    let duration_ms = start.elapsed().as_millis() as u64;
+
+    match output {
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+            ArchiveResult {
+                exit_code: output.status.code().unwrap_or(-1),
+                stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+                diagnostics: stderr
+                    .lines()
+                    .filter(|l| !l.trim().is_empty())
+                    .map(|l| Diagnostic::error(l.trim()))
+                    .collect(),
+                stderr,
+                duration_ms,
+                timed_out: false,
+                output: request.output.clone(),
+            }
+        }
+        Err(e) => ArchiveResult {
+            exit_code: -1,
+            stdout: String::new(),
+            stderr: format!("{}: {}", ar.display(), e),
+            duration_ms,
+            timed_out: is_timeout(&e),
+            diagnostics: vec![Diagnostic::error(format!("{}: {}", ar.display(), e))],
+            output: request.output.clone(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn objects() -> Vec<PathBuf> {
+        vec![
+            PathBuf::from("build/uart.o"),
+            PathBuf::from("build/spi.o"),
+            PathBuf::from("build/main.o"),
+        ]
+    }
+
+    #[test]
+    fn test_build_archive_command() {
+        let req = ArchiveRequest::new("build/libdrivers.a", objects());
+        let args = build_archive_command(&req);
+        assert_eq!(args[0], "rcsD");
+        assert_eq!(args[1], "build/libdrivers.a");
+        assert_eq!(args.len(), 5);
+
+        let req = req.with_deterministic(false).with_index(false);
+        assert_eq!(build_archive_command(&req)[0], "rcU");
+    }
+
+    #[test]
+    fn test_select_objects() {
+        let req = ArchiveRequest::new("build/libdrivers.a", objects()).select(&["uart.o", "spi.o"]);
+        assert_eq!(
+            req.objects,
+            vec![PathBuf::from("build/uart.o"), PathBuf::from("build/spi.o")]
+        );
+    }
+
+    #[test]
+    fn test_create_archive_missing_ar() {
+        let tc = DetectedToolchain::new(
+            crate::ToolchainKind::ArmGcc,
+            PathBuf::from("/nonexistent/arm-none-eabi-gcc"),
+            "13.2.1".to_string(),
+        );
+        let result = create_archive(&tc, &ArchiveRequest::new("/nonexistent/lib.a", objects()));
+        assert!(!result.success());
+        assert!(result.stderr.contains("arm-none-eabi-ar"));
+    }
+}
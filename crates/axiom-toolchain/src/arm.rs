@@ -0,0 +1,559 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! ARM Cortex-M compilation.
+
+use crate::invocation::{command_argv, parse_diagnostics};
+use crate::limits::is_timeout;
+use crate::response_file::run_tool;
+use crate::tool_log::{log_invocation, unlogged_usage};
+use crate::{
+    normalize_diagnostics, BuildProfile, CompileResult, DetectedToolchain, EnvironmentCapture,
+    SourceKind, ToolQualificationLogger, ToolchainKind, WarningProfile,
+};
+use axiom_core::Diagnostic;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Instant;
+
+/// Floating-point calling convention.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FloatAbi {
+    /// Software floating point.
+    #[default]
+    Soft,
+    /// FPU instructions with the soft-float calling convention.
+    SoftFp,
+    /// FPU instructions and registers for arguments.
+    Hard,
+}
+
+impl FloatAbi {
+    /// Value for `-mfloat-abi=`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            FloatAbi::Soft => "soft",
+            FloatAbi::SoftFp => "softfp",
+            FloatAbi::Hard => "hard",
+        }
+    }
+}
+
+/// Optimization level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum OptimizationLevel {
+    /// No optimization (`-O0`).
+    #[default]
+    O0,
+    /// `-O1`.
+    O1,
+    /// `-O2`.
+    O2,
+    /// `-O3`.
+    O3,
+    /// Optimize for size (`-Os`).
+    Os,
+    /// Optimize for debugging (`-Og`).
+    Og,
+}
+
+impl OptimizationLevel {
+    /// Parse a `-O` flag (`-O` alone means `-O1`).
+    pub fn from_flag(flag: &str) -> Option<Self> {
+        match flag {
+            "-O0" => Some(OptimizationLevel::O0),
+            "-O" | "-O1" => Some(OptimizationLevel::O1),
+            "-O2" => Some(OptimizationLevel::O2),
+            "-O3" => Some(OptimizationLevel::O3),
+            "-Os" => Some(OptimizationLevel::Os),
+            "-Og" => Some(OptimizationLevel::Og),
+            _ => None,
+        }
+    }
+
+    /// Compiler flag for this level.
+    pub fn flag(&self) -> &'static str {
+        match self {
+            OptimizationLevel::O0 => "-O0",
+            OptimizationLevel::O1 => "-O1",
+            OptimizationLevel::O2 => "-O2",
+            OptimizationLevel::O3 => "-O3",
+            OptimizationLevel::Os => "-Os",
+            OptimizationLevel::Og => "-Og",
+        }
+    }
+}
+
+/// Target MCU configuration.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArmMcuConfig {
+    /// CPU name (e.g. "cortex-m4").
+    pub cpu: String,
+    /// Generate Thumb code.
+    pub thumb: bool,
+    /// FPU name (e.g. "fpv4-sp-d16"), if any.
+    pub fpu: Option<String>,
+    /// Floating-point ABI.
+    pub float_abi: FloatAbi,
+    /// Device defines (e.g. "STM32F407xx").
+    pub defines: Vec<String>,
+}
+
+impl ArmMcuConfig {
+    /// Create a configuration for a CPU without an FPU.
+    pub fn new(cpu: impl Into<String>) -> Self {
+        Self {
+            cpu: cpu.into(),
+            thumb: true,
+            fpu: None,
+            float_abi: FloatAbi::Soft,
+            defines: Vec::new(),
+        }
+    }
+
+    /// Set the FPU and floating-point ABI.
+    pub fn with_fpu(mut self, fpu: impl Into<String>, float_abi: FloatAbi) -> Self {
+        self.fpu = Some(fpu.into());
+        self.float_abi = float_abi;
+        self
+    }
+
+    /// Add a device define.
+    pub fn with_define(mut self, define: impl Into<String>) -> Self {
+        self.defines.push(define.into());
+        self
+    }
+
+    /// Machine flags (`-mcpu`, `-mthumb`, `-mfpu`, `-mfloat-abi`).
+    pub fn machine_flags(&self) -> Vec<String> {
+        let mut flags = vec![format!("-mcpu={}", self.cpu)];
+        if self.thumb {
+            flags.push("-mthumb".to_string());
+        }
+        if let Some(ref fpu) = self.fpu {
+            flags.push(format!("-mfpu={}", fpu));
+        }
+        flags.push(format!("-mfloat-abi={}", self.float_abi.name()));
+        flags
+    }
+}
+
+/// A request to compile one source for an ARM target.
+#[derive(Debug, Clone)]
+pub struct ArmCompileRequest {
+    /// Source file path.
+    pub source: PathBuf,
+    /// Output object path.
+    pub output: PathBuf,
+    /// Target MCU.
+    pub mcu: ArmMcuConfig,
+    /// Include directories.
+    pub include_paths: Vec<PathBuf>,
+    /// Preprocessor defines (`NAME` or `NAME=VALUE`).
+    pub defines: Vec<String>,
+    /// Optimization level.
+    pub optimization: OptimizationLevel,
+    /// Include debug symbols.
+    pub debug: bool,
+    /// Warning profile (optional).
+    pub warning_profile: Option<WarningProfile>,
+    /// Emit a `.su` stack usage file next to the object.
+    pub stack_usage: bool,
+    /// Emit a `.ci` call graph file next to the object.
+    pub callgraph_info: bool,
+    /// Additional compiler flags.
+    pub flags: Vec<String>,
+    /// Record the invocation environment in the result.
+    pub capture: Option<EnvironmentCapture>,
+    /// Log the invocation for tool qualification.
+    pub usage_log: Option<ToolQualificationLogger>,
+}
+
+impl ArmCompileRequest {
+    /// Create a new ARM compile request.
+    pub fn new(source: PathBuf, output: PathBuf, mcu: ArmMcuConfig) -> Self {
+        Self {
+            source,
+            output,
+            mcu,
+            include_paths: Vec::new(),
+            defines: Vec::new(),
+            optimization: OptimizationLevel::O0,
+            debug: true,
+            warning_profile: None,
+            stack_usage: false,
+            callgraph_info: false,
+            flags: Vec::new(),
+            capture: None,
+            usage_log: None,
+        }
+    }
+
+    /// Add an include directory.
+    pub fn with_include_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.include_paths.push(path.into());
+        self
+    }
+
+    /// Add a preprocessor define.
+    pub fn with_define(mut self, define: impl Into<String>) -> Self {
+        self.defines.push(define.into());
+        self
+    }
+
+    /// Set the optimization level.
+    pub fn with_optimization(mut self, level: OptimizationLevel) -> Self {
+        self.optimization = level;
+        self
+    }
+
+    /// Enable or disable debug symbols.
+    pub fn with_debug(mut self, debug: bool) -> Self {
+        self.debug = debug;
+        self
+    }
+
+    /// Set the warning profile.
+    pub fn with_warning_profile(mut self, profile: WarningProfile) -> Self {
+        self.warning_profile = Some(profile);
+        self
+    }
+
+    /// Enable or disable `-fstack-usage` output.
+    pub fn with_stack_usage(mut self, enabled: bool) -> Self {
+        self.stack_usage = enabled;
+        self
+    }
+
+    /// Enable or disable `-fcallgraph-info` output.
+    pub fn with_callgraph_info(mut self, enabled: bool) -> Self {
+        self.callgraph_info = enabled;
+        self
+    }
+
+    /// Add a compiler flag.
+    pub fn with_flag(mut self, flag: impl Into<String>) -> Self {
+        self.flags.push(flag.into());
+        self
+    }
+
+    /// Apply a build profile: its optimization level and debug setting
+    /// replace the request's, and its defines, include paths and flags are
+    /// added.
+    pub fn with_profile(mut self, profile: &BuildProfile) -> Self {
+        self.optimization = profile.optimization;
+        self.debug = profile.debug;
+        self.defines.extend(profile.defines.iter().cloned());
+        self.include_paths
+            .extend(profile.include_paths.iter().cloned());
+        self.flags.extend(profile.cflags.iter().cloned());
+        self
+    }
+
+    /// Record the command line, environment and toolchain hash in the result.
+    pub fn with_environment_capture(mut self, capture: EnvironmentCapture) -> Self {
+        self.capture = Some(capture);
+        self
+    }
+
+    /// Log the invocation, with source and output checksums, to a tool
+    /// usage log.
+    pub fn with_usage_log(mut self, log: ToolQualificationLogger) -> Self {
+        self.usage_log = Some(log);
+        self
+    }
+
+    /// Files the compile writes: the object and any stack usage or call
+    /// graph file.
+    pub fn outputs(&self) -> Vec<PathBuf> {
+        let mut outputs = vec![self.output.clone()];
+        if self.stack_usage {
+            outputs.push(self.output.with_extension("su"));
+        }
+        if self.callgraph_info {
+            outputs.push(self.output.with_extension("ci"));
+        }
+        outputs
+    }
+
+    /// All preprocessor defines: device defines first, then request defines.
+    pub fn all_defines(&self) -> Vec<String> {
+        self.mcu
+            .defines
+            .iter()
+            .chain(&self.defines)
+            .cloned()
+            .collect()
+    }
+}
+
+/// Build arm-none-eabi-gcc arguments for a compile request.
+///
+/// Assembly sources get the machine flags, debug info, and include paths
+/// (for `.include`); defines only apply when they're preprocessed, and the
+/// C code generation and warning flags are left out.
+pub fn build_arm_compile_command(request: &ArmCompileRequest) -> Vec<String> {
+    let kind = SourceKind::from_path(&request.source);
+    let mut args = Vec::new();
+    if let Some(language) = kind.language() {
+        args.push("-x".to_string());
+        args.push(language.to_string());
+    }
+    args.extend([
+        "-c".to_string(),
+        request.source.display().to_string(),
+        "-o".to_string(),
+        request.output.display().to_string(),
+    ]);
+
+    args.extend(request.mcu.machine_flags());
+    if !kind.is_assembly() {
+        args.push(request.optimization.flag().to_string());
+    }
+    if request.debug {
+        args.push("-g".to_string());
+    }
+
+    if !kind.is_assembly() {
+        // One section per function/object so the linker can discard unused code
+        args.push("-ffunction-sections".to_string());
+        args.push("-fdata-sections".to_string());
+        args.push("-fdiagnostics-parseable-fixits".to_string());
+        if request.stack_usage {
+            args.push("-fstack-usage".to_string());
+        }
+        if request.callgraph_info {
+            args.push("-fcallgraph-info".to_string());
+        }
+    }
+
+    if kind.is_preprocessed() {
+        args.extend(request.all_defines().iter().map(|d| format!("-D{}", d)));
+    }
+    args.extend(
+        request
+            .include_paths
+            .iter()
+            .map(|p| format!("-I{}", p.display())),
+    );
+
+    if let Some(profile) = request.warning_profile.filter(|_| !kind.is_assembly()) {
+        args.extend(profile.flags(ToolchainKind::ArmGcc));
+    }
+
+    args.extend(request.flags.iter().cloned());
+    args
+}
+
+/// Exact argv [`compile_arm`] would run, program first, for review or
+/// certification records. Container toolchains include the engine
+/// invocation.
+pub fn dry_run_arm(toolchain: &DetectedToolchain, request: &ArmCompileRequest) -> Vec<String> {
+    command_argv(&toolchain.command(&build_arm_compile_command(request)))
+}
+
+/// Compile a source file with an ARM GCC toolchain.
+pub fn compile_arm(toolchain: &DetectedToolchain, request: &ArmCompileRequest) -> CompileResult {
+    let args = build_arm_compile_command(request);
+    let mut invocation = request
+        .capture
+        .as_ref()
+        .map(|capture| capture.capture(&toolchain.path, &args));
+    let start = Instant::now();
+
+    let (output, response_file) = run_tool(toolchain, &toolchain.path, &args, &request.output);
+    if let Some(invocation) = invocation.as_mut() {
+        invocation.response_file = response_file;
+    }
+
+    let duration_ms = start.elapsed().as_millis() as u64;
+
+    let mut result = match output {
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+            let mut diagnostics = parse_diagnostics(&stderr, ToolchainKind::ArmGcc);
+            normalize_diagnostics(&mut diagnostics, ToolchainKind::ArmGcc, &toolchain.version);
+            CompileResult {
+                exit_code: output.status.code().unwrap_or(-1),
+                stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+                diagnostics,
+                stderr,
+                duration_ms,
+                timed_out: false,
+                warning_profile: request.warning_profile,
+                invocation,
+            }
+        }
+        Err(e) => CompileResult {
+            exit_code: -1,
+            stdout: String::new(),
+            stderr: e.to_string(),
+            duration_ms,
+            timed_out: is_timeout(&e),
+            diagnostics: vec![Diagnostic::error(e.to_string())],
+            warning_profile: request.warning_profile,
+            invocation,
+        },
+    };
+
+    let logged = log_invocation(
+        request.usage_log.as_ref(),
+        &toolchain.path,
+        &toolchain.version,
+        |record| {
+            record
+                .with_arguments(args)
+                .with_result(result.exit_code, duration_ms)
+                .with_inputs([&request.source])
+                .with_outputs(request.outputs())
+        },
+    );
+    if let Err(e) = logged {
+        result.diagnostics.push(unlogged_usage(e));
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stm32f4() -> ArmMcuConfig {
+        ArmMcuConfig::new("cortex-m4")
+            .with_fpu("fpv4-sp-d16", FloatAbi::Hard)
+            .with_define("STM32F407xx")
+    }
+
+    #[test]
+    fn test_machine_flags() {
+        assert_eq!(
+            stm32f4().machine_flags(),
+            vec![
+                "-mcpu=cortex-m4",
+                "-mthumb",
+                "-mfpu=fpv4-sp-d16",
+                "-mfloat-abi=hard"
+            ]
+        );
+        assert_eq!(
+            ArmMcuConfig::new("cortex-m0plus").machine_flags(),
+            vec!["-mcpu=cortex-m0plus", "-mthumb", "-mfloat-abi=soft"]
+        );
+    }
+
+    #[test]
+    fn test_build_arm_compile_command() {
+        let request = ArmCompileRequest::new(
+            PathBuf::from("src/main.c"),
+            PathBuf::from("build/main.o"),
+            stm32f4(),
+        )
+        .with_include_path("inc")
+        .with_define("USE_HAL_DRIVER")
+        .with_optimization(OptimizationLevel::Os)
+        .with_flag("-std=c11");
+
+        let args = build_arm_compile_command(&request);
+        assert_eq!(&args[..4], &["-c", "src/main.c", "-o", "build/main.o"]);
+        assert!(args.contains(&"-mfloat-abi=hard".to_string()));
+        assert!(args.contains(&"-Os".to_string()));
+        assert!(args.contains(&"-g".to_string()));
+        assert!(args.contains(&"-ffunction-sections".to_string()));
+        assert!(args.contains(&"-Iinc".to_string()));
+        assert!(!args.contains(&"-fstack-usage".to_string()));
+
+        let device = args.iter().position(|a| a == "-DSTM32F407xx").unwrap();
+        let hal = args.iter().position(|a| a == "-DUSE_HAL_DRIVER").unwrap();
+        assert!(device < hal);
+        assert_eq!(args.last().unwrap(), "-std=c11");
+    }
+
+    #[test]
+    fn test_dry_run_arm() {
+        let tc = DetectedToolchain::new(
+            ToolchainKind::ArmGcc,
+            PathBuf::from("/opt/arm/bin/arm-none-eabi-gcc"),
+            "13.2.1".to_string(),
+        );
+        let request = ArmCompileRequest::new(
+            PathBuf::from("src/main.c"),
+            PathBuf::from("build/main.o"),
+            stm32f4(),
+        );
+
+        let argv = dry_run_arm(&tc, &request);
+        assert_eq!(argv[0], "/opt/arm/bin/arm-none-eabi-gcc");
+        assert_eq!(argv[1..], build_arm_compile_command(&request)[..]);
+    }
+
+    #[test]
+    fn test_build_arm_assembly_command() {
+        let request = ArmCompileRequest::new(
+            PathBuf::from("startup/startup_stm32f407xx.s"),
+            PathBuf::from("build/startup.o"),
+            stm32f4(),
+        )
+        .with_include_path("inc")
+        .with_warning_profile(WarningProfile::AvionicsStrict)
+        .with_stack_usage(true);
+
+        let args = build_arm_compile_command(&request);
+        assert_eq!(&args[..3], &["-x", "assembler", "-c"]);
+        assert!(args.contains(&"-mcpu=cortex-m4".to_string()));
+        assert!(args.contains(&"-g".to_string()));
+        assert!(args.contains(&"-Iinc".to_string()));
+        assert!(!args.contains(&"-DSTM32F407xx".to_string()));
+        assert!(!args.contains(&"-O0".to_string()));
+        assert!(!args.contains(&"-fstack-usage".to_string()));
+        assert!(!args.iter().any(|a| a.starts_with("-W")));
+
+        let request = ArmCompileRequest::new(
+            PathBuf::from("startup.S"),
+            PathBuf::from("startup.o"),
+            stm32f4(),
+        );
+        let args = build_arm_compile_command(&request);
+        assert_eq!(&args[..2], &["-x", "assembler-with-cpp"]);
+        assert!(args.contains(&"-DSTM32F407xx".to_string()));
+    }
+
+    #[test]
+    fn test_analysis_output_flags() {
+        let request = ArmCompileRequest::new(PathBuf::from("a.c"), PathBuf::from("a.o"), stm32f4())
+            .with_stack_usage(true);
+        assert!(build_arm_compile_command(&request).contains(&"-fstack-usage".to_string()));
+    }
+
+    #[test]
+    fn test_all_defines() {
+        let request = ArmCompileRequest::new(PathBuf::from("a.c"), PathBuf::from("a.o"), stm32f4())
+            .with_define("DEBUG=1");
+        assert_eq!(request.all_defines(), vec!["STM32F407xx", "DEBUG=1"]);
+    }
+
+    #[test]
+    fn test_with_profile() {
+        let request = ArmCompileRequest::new(PathBuf::from("a.c"), PathBuf::from("a.o"), stm32f4())
+            .with_define("BOARD=2")
+            .with_profile(&BuildProfile::release());
+        let args = build_arm_compile_command(&request);
+        assert!(args.contains(&"-Os".to_string()));
+        assert!(!args.contains(&"-O0".to_string()));
+        assert_eq!(
+            request.all_defines(),
+            vec!["STM32F407xx", "BOARD=2", "NDEBUG"]
+        );
+
+        let coverage =
+            ArmCompileRequest::new(PathBuf::from("a.c"), PathBuf::from("a.o"), stm32f4())
+                .with_profile(&BuildProfile::coverage());
+        assert!(build_arm_compile_command(&coverage).contains(&"--coverage".to_string()));
+    }
+
+    #[test]
+    fn test_float_abi_serde() {
+        let json = serde_json::to_string(&FloatAbi::SoftFp).unwrap();
+        assert_eq!(json, "\"softfp\"");
+    }
+}
@@ -0,0 +1,652 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Compiler assembly output for review.
+//!
+//! Assembly is generated with the flags a source was last compiled with, so
+//! it matches the object that was actually built. With source interleaving
+//! the compiler also gets `-g -fverbose-asm`: its `.file` and `.loc`
+//! directives are resolved to the source lines shown above the code they
+//! produced, and the debug sections are dropped. C++ symbols are demangled
+//! with the toolchain's `c++filt`.
+
+use crate::limits::run_limited;
+use crate::{CachedFlags, DetectedToolchain, ToolchainKind};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use thiserror::Error;
+
+/// Names passed to one `c++filt` run.
+const DEMANGLE_BATCH: usize = 256;
+
+/// Local labels that only anchor debug information.
+const DEBUG_LABELS: &[&str] = &[
+    ".LVL",
+    ".LVU",
+    ".LFB",
+    ".LFE",
+    ".LBB",
+    ".LBE",
+    ".LBI",
+    ".Ltext",
+    ".Letext",
+    ".Ldebug",
+    ".Ltmp",
+    ".Lfunc_begin",
+    ".Lfunc_end",
+    ".Lcu_begin",
+    ".Linfo_string",
+    ".Lsection",
+    ".Lline_table",
+];
+
+/// Errors from generating assembly.
+#[derive(Debug, Error)]
+pub enum AssemblyError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Compiler failed: {0}")]
+    Failed(String),
+}
+
+/// How assembly is generated and presented.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AssemblyOptions {
+    /// Pass `-g -fverbose-asm` and show source lines above their code.
+    pub interleave_source: bool,
+    /// Demangle C++ symbol names.
+    pub demangle: bool,
+}
+
+impl Default for AssemblyOptions {
+    fn default() -> Self {
+        Self {
+            interleave_source: true,
+            demangle: true,
+        }
+    }
+}
+
+impl AssemblyOptions {
+    /// Set source interleaving.
+    pub fn with_interleave_source(mut self, interleave: bool) -> Self {
+        self.interleave_source = interleave;
+        self
+    }
+
+    /// Set demangling.
+    pub fn with_demangle(mut self, demangle: bool) -> Self {
+        self.demangle = demangle;
+        self
+    }
+}
+
+/// One line of assembly output.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum AssemblyLine {
+    /// Source line the following code was generated from.
+    Source {
+        file: PathBuf,
+        line: u32,
+        text: String,
+    },
+    /// Label, with its demangled name.
+    Label {
+        name: String,
+        demangled: Option<String>,
+    },
+    /// Instruction, with the demangled names of the symbols it references.
+    Instruction { text: String, symbols: Vec<String> },
+    /// Assembler directive.
+    Directive { text: String },
+}
+
+/// Assembly generated for one source file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssemblyOutput {
+    /// Source file.
+    pub source: PathBuf,
+    /// Line comment marker of the target's assembler.
+    pub comment: String,
+    /// Parsed lines.
+    pub lines: Vec<AssemblyLine>,
+}
+
+impl AssemblyOutput {
+    /// Render as assembly text, with source lines and demangled names as
+    /// comments.
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        for line in &self.lines {
+            match line {
+                AssemblyLine::Source {
+                    file,
+                    line,
+                    text: source,
+                } => {
+                    text.push_str(&format!(
+                        "{} {}:{}: {}\n",
+                        self.comment,
+                        file.display(),
+                        line,
+                        source.trim()
+                    ));
+                }
+                AssemblyLine::Label { name, demangled } => {
+                    text.push_str(name);
+                    text.push(':');
+                    if let Some(demangled) = demangled {
+                        text.push_str(&format!("\t{} {}", self.comment, demangled));
+                    }
+                    text.push('\n');
+                }
+                AssemblyLine::Instruction {
+                    text: insn,
+                    symbols,
+                } => {
+                    text.push('\t');
+                    text.push_str(insn);
+                    if !symbols.is_empty() {
+                        text.push_str(&format!("\t{} {}", self.comment, symbols.join(", ")));
+                    }
+                    text.push('\n');
+                }
+                AssemblyLine::Directive { text: directive } => {
+                    text.push('\t');
+                    text.push_str(directive);
+                    text.push('\n');
+                }
+            }
+        }
+        text
+    }
+}
+
+/// Line comment marker for a toolchain's assembler.
+fn comment_marker(kind: ToolchainKind) -> &'static str {
+    match kind {
+        ToolchainKind::ArmGcc => "@",
+        _ => "#",
+    }
+}
+
+/// Arguments producing assembly on stdout: the cached flags without
+/// dependency-file output or LTO, then `-S`.
+pub fn assembly_command(
+    source: &Path,
+    flags: &CachedFlags,
+    options: AssemblyOptions,
+) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut iter = flags.flags.iter();
+    while let Some(flag) = iter.next() {
+        match flag.as_str() {
+            "-MD" | "-MMD" | "-MP" => {}
+            "-MF" | "-MT" | "-MQ" => {
+                iter.next();
+            }
+            _ if flag.starts_with("-MF") || flag.starts_with("-MT") || flag.starts_with("-MQ") => {}
+            _ if flag.starts_with("-flto") => {}
+            _ => args.push(flag.clone()),
+        }
+    }
+    if options.interleave_source {
+        args.extend(["-g".to_string(), "-fverbose-asm".to_string()]);
+    }
+    args.extend([
+        "-S".to_string(),
+        source.display().to_string(),
+        "-o".to_string(),
+        "-".to_string(),
+    ]);
+    args
+}
+
+/// Generate assembly for a source file with its cached flags.
+pub fn generate_assembly(
+    source: &Path,
+    flags: &CachedFlags,
+    options: AssemblyOptions,
+) -> Result<AssemblyOutput, AssemblyError> {
+    let output =
+        run_limited(Command::new(&flags.compiler).args(assembly_command(source, flags, options)))?;
+    if !output.status.success() {
+        return Err(AssemblyError::Failed(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    let asm = String::from_utf8_lossy(&output.stdout);
+
+    let mut lines = parse_assembly(&asm, source_reader());
+    if !options.interleave_source {
+        lines.retain(|line| !matches!(line, AssemblyLine::Source { .. }));
+    }
+
+    if options.demangle {
+        let toolchain =
+            DetectedToolchain::new(flags.kind, flags.compiler.clone(), flags.version.clone());
+        let names = referenced_symbols(&lines);
+        if !names.is_empty() {
+            let demangled = demangle_symbols(&toolchain.sibling_tool("c++filt"), &names);
+            apply_demangled(&mut lines, &demangled);
+        }
+    }
+
+    Ok(AssemblyOutput {
+        source: source.to_path_buf(),
+        comment: comment_marker(flags.kind).to_string(),
+        lines,
+    })
+}
+
+/// Reader of source lines by file and 1-based line number, loading each
+/// file once.
+pub(crate) fn source_reader() -> impl FnMut(&Path, u32) -> Option<String> {
+    let mut files: HashMap<PathBuf, Option<Vec<String>>> = HashMap::new();
+    move |file, line| {
+        let contents = files.entry(file.to_path_buf()).or_insert_with(|| {
+            std::fs::read_to_string(file)
+                .ok()
+                .map(|text| text.lines().map(str::to_string).collect())
+        });
+        contents
+            .as_ref()?
+            .get(line.checked_sub(1)? as usize)
+            .cloned()
+    }
+}
+
+/// Parse compiler assembly, resolving `.loc` directives to source lines
+/// with `read_line(file, line)`.
+///
+/// Debug sections, CFI directives and debug-only labels are dropped.
+/// Symbols are left mangled.
+pub fn parse_assembly(
+    asm: &str,
+    mut read_line: impl FnMut(&Path, u32) -> Option<String>,
+) -> Vec<AssemblyLine> {
+    let mut lines = Vec::new();
+    let mut files: HashMap<u32, PathBuf> = HashMap::new();
+    let mut in_debug = false;
+    let mut location: Option<(u32, u32)> = None;
+    let mut shown: Option<(u32, u32)> = None;
+
+    for raw in asm.lines() {
+        let line = raw.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (word, rest) = line
+            .split_once(char::is_whitespace)
+            .map_or((line, ""), |(word, rest)| (word, rest.trim()));
+
+        match word {
+            ".section" | ".pushsection" => {
+                in_debug = rest.starts_with(".debug");
+                if in_debug {
+                    continue;
+                }
+            }
+            ".text" | ".data" | ".bss" => in_debug = false,
+            _ => {}
+        }
+        if in_debug {
+            continue;
+        }
+
+        if word == ".file" {
+            if let Some((number, path)) = parse_file_directive(rest) {
+                files.insert(number, path);
+            }
+            continue;
+        }
+        if word == ".loc" {
+            let mut fields = rest.split_whitespace().map(str::parse::<u32>);
+            if let (Some(Ok(file)), Some(Ok(line))) = (fields.next(), fields.next()) {
+                location = Some((file, line)).filter(|&(_, line)| line > 0);
+            }
+            continue;
+        }
+        if word.starts_with(".cfi_") {
+            continue;
+        }
+
+        if let Some(name) = label_name(line) {
+            if !DEBUG_LABELS.iter().any(|prefix| name.starts_with(prefix)) {
+                lines.push(AssemblyLine::Label {
+                    name: name.to_string(),
+                    demangled: None,
+                });
+            }
+            continue;
+        }
+        if line.starts_with('.') {
+            lines.push(AssemblyLine::Directive {
+                text: line.to_string(),
+            });
+            continue;
+        }
+        // Assembler comments, e.g. -fverbose-asm's option summary
+        if line.starts_with(['@', '#', ';']) || line.starts_with("//") {
+            continue;
+        }
+
+        if location != shown {
+            if let Some((file, number)) = location {
+                if let Some(path) = files.get(&file) {
+                    lines.push(AssemblyLine::Source {
+                        file: path.clone(),
+                        line: number,
+                        text: read_line(path, number).unwrap_or_default(),
+                    });
+                }
+            }
+            shown = location;
+        }
+        lines.push(AssemblyLine::Instruction {
+            text: line.to_string(),
+            symbols: Vec::new(),
+        });
+    }
+    lines
+}
+
+/// Number and path of a `.file` directive: `1 "main.c"` or, as Clang
+/// writes it, `1 "/work" "main.c"`.
+fn parse_file_directive(rest: &str) -> Option<(u32, PathBuf)> {
+    let (number, rest) = rest.split_once(char::is_whitespace)?;
+    let number = number.parse().ok()?;
+    let strings: Vec<&str> = rest.split('"').skip(1).step_by(2).take(2).collect();
+    let path = match strings[..] {
+        [name] => PathBuf::from(name),
+        [dir, name] => Path::new(dir).join(name),
+        _ => return None,
+    };
+    Some((number, path))
+}
+
+/// Name of a label definition line.
+fn label_name(line: &str) -> Option<&str> {
+    let end = line.find(':')?;
+    let name = &line[..end];
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '$'));
+    valid.then_some(name)
+}
+
+/// Itanium-mangled names (`_Z...`) in a piece of assembly.
+pub fn mangled_symbols(text: &str) -> Vec<&str> {
+    let is_symbol = |c: char| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '$');
+    let mut names = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("_Z") {
+        let before = rest[..start].chars().next_back();
+        let tail = &rest[start..];
+        let len = tail.find(|c| !is_symbol(c)).unwrap_or(tail.len());
+        if !before.is_some_and(is_symbol) && len > 2 {
+            names.push(tail[..len].trim_end_matches('.'));
+        }
+        rest = &tail[len.max(2)..];
+    }
+    names
+}
+
+/// Mangled names referenced by labels and instructions.
+fn referenced_symbols(lines: &[AssemblyLine]) -> Vec<String> {
+    let mut names = BTreeSet::new();
+    for line in lines {
+        match line {
+            AssemblyLine::Label { name, .. } => names.extend(mangled_symbols(name)),
+            AssemblyLine::Instruction { text, .. } => names.extend(mangled_symbols(text)),
+            _ => {}
+        }
+    }
+    names.into_iter().map(str::to_string).collect()
+}
+
+/// Fill in demangled names from a mangled-to-demangled map.
+fn apply_demangled(lines: &mut [AssemblyLine], demangled: &HashMap<String, String>) {
+    for line in lines {
+        match line {
+            AssemblyLine::Label {
+                name,
+                demangled: out,
+            } => {
+                *out = demangled.get(name.as_str()).cloned();
+            }
+            AssemblyLine::Instruction { text, symbols } => {
+                *symbols = mangled_symbols(text)
+                    .into_iter()
+                    .filter_map(|name| demangled.get(name).cloned())
+                    .collect();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Demangle names with `c++filt`, one argument per name.
+///
+/// Names it leaves unchanged, and every name when it cannot be run, are
+/// absent from the result.
+pub fn demangle_symbols(cxxfilt: &Path, names: &[String]) -> HashMap<String, String> {
+    let mut demangled = HashMap::new();
+    for batch in names.chunks(DEMANGLE_BATCH) {
+        let Ok(output) = run_limited(Command::new(cxxfilt).args(batch)) else {
+            break;
+        };
+        if !output.status.success() {
+            break;
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        for (name, line) in batch.iter().zip(stdout.lines()) {
+            if line != name {
+                demangled.insert(name.clone(), line.to_string());
+            }
+        }
+    }
+    demangled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GCC_ARM: &str = "\
+\t.cpu cortex-m4
+\t.file\t\"main.cpp\"
+\t.text
+.Ltext0:
+\t.file 1 \"src/main.cpp\"
+\t.align\t1
+\t.global\t_Z3addii
+\t.type\t_Z3addii, %function
+_Z3addii:
+.LFB0:
+\t.loc 1 3 23
+\t.cfi_startproc
+\t@ args = 0, pretend = 0, frame = 0
+\tadd\tr0, r0, r1\t@ tmp116, a, b
+\t.loc 1 3 34
+\tbx\tlr\t@
+\t.cfi_endproc
+.LFE0:
+\t.loc 1 5 1
+\tbl\t_Z3addii\t@
+\t.section\t.debug_info,\"\",%progbits
+.Ldebug_info0:
+\t.4byte\t0x5c
+\t.text
+\tnop
+";
+
+    #[test]
+    fn test_parse_assembly() {
+        let lines = parse_assembly(GCC_ARM, |file, line| {
+            assert_eq!(file, Path::new("src/main.cpp"));
+            Some(format!("line {}", line))
+        });
+        let source = |line: u32| AssemblyLine::Source {
+            file: PathBuf::from("src/main.cpp"),
+            line,
+            text: format!("line {}", line),
+        };
+        let insn = |text: &str| AssemblyLine::Instruction {
+            text: text.to_string(),
+            symbols: Vec::new(),
+        };
+        let directive = |text: &str| AssemblyLine::Directive {
+            text: text.to_string(),
+        };
+        assert_eq!(
+            lines,
+            vec![
+                directive(".cpu cortex-m4"),
+                directive(".text"),
+                directive(".align\t1"),
+                directive(".global\t_Z3addii"),
+                directive(".type\t_Z3addii, %function"),
+                AssemblyLine::Label {
+                    name: "_Z3addii".to_string(),
+                    demangled: None,
+                },
+                source(3),
+                insn("add\tr0, r0, r1\t@ tmp116, a, b"),
+                insn("bx\tlr\t@"),
+                source(5),
+                insn("bl\t_Z3addii\t@"),
+                directive(".text"),
+                insn("nop"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_file_directive() {
+        assert_eq!(
+            parse_file_directive("1 \"src/main.c\""),
+            Some((1, PathBuf::from("src/main.c")))
+        );
+        assert_eq!(
+            parse_file_directive("0 \"/work\" \"main.c\" md5 0x1234"),
+            Some((0, PathBuf::from("/work/main.c")))
+        );
+        assert_eq!(parse_file_directive("\"main.c\""), None);
+    }
+
+    #[test]
+    fn test_mangled_symbols() {
+        assert_eq!(mangled_symbols("bl\t_Z3addii"), vec!["_Z3addii"]);
+        assert_eq!(
+            mangled_symbols("ldr r0, .L3+4 @ _ZN3app4tickEv.constprop.0, x_Z1"),
+            vec!["_ZN3app4tickEv.constprop.0"]
+        );
+        assert!(mangled_symbols("bl main").is_empty());
+    }
+
+    #[test]
+    fn test_assembly_command() {
+        let flags = CachedFlags {
+            compiler: PathBuf::from("arm-none-eabi-gcc"),
+            kind: ToolchainKind::ArmGcc,
+            version: "13".to_string(),
+            flags: ["-Os", "-MMD", "-MF", "main.d", "-flto", "-mcpu=cortex-m4"]
+                .map(String::from)
+                .to_vec(),
+        };
+        assert_eq!(
+            assembly_command(Path::new("main.c"), &flags, AssemblyOptions::default()),
+            [
+                "-Os",
+                "-mcpu=cortex-m4",
+                "-g",
+                "-fverbose-asm",
+                "-S",
+                "main.c",
+                "-o",
+                "-"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_text() {
+        let output = AssemblyOutput {
+            source: PathBuf::from("main.cpp"),
+            comment: "@".to_string(),
+            lines: vec![
+                AssemblyLine::Label {
+                    name: "_Z3addii".to_string(),
+                    demangled: Some("add(int, int)".to_string()),
+                },
+                AssemblyLine::Source {
+                    file: PathBuf::from("main.cpp"),
+                    line: 3,
+                    text: "    return a + b;".to_string(),
+                },
+                AssemblyLine::Instruction {
+                    text: "bl\t_Z3addii".to_string(),
+                    symbols: vec!["add(int, int)".to_string()],
+                },
+            ],
+        };
+        assert_eq!(
+            output.text(),
+            "_Z3addii:\t@ add(int, int)\n\
+             @ main.cpp:3: return a + b;\n\
+             \tbl\t_Z3addii\t@ add(int, int)\n"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_generate_assembly() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = tempfile::TempDir::new().unwrap();
+        let source = temp.path().join("main.cpp");
+        std::fs::write(&source, "int add(int a, int b)\n{\n    return a + b;\n}\n").unwrap();
+        // Stand-in compiler and c++filt
+        let compiler = temp.path().join("arm-none-eabi-gcc");
+        std::fs::write(
+            &compiler,
+            format!(
+                "#!/bin/sh\nprintf '\\t.file 1 \"{}\"\\n_Z3addii:\\n\\t.loc 1 3 5\\n\\tadd r0, r0, r1\\n'\n",
+                source.display()
+            ),
+        )
+        .unwrap();
+        let cxxfilt = temp.path().join("arm-none-eabi-c++filt");
+        std::fs::write(&cxxfilt, "#!/bin/sh\necho 'add(int, int)'\n").unwrap();
+        for tool in [&compiler, &cxxfilt] {
+            std::fs::set_permissions(tool, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        let flags = CachedFlags {
+            compiler,
+            kind: ToolchainKind::ArmGcc,
+            version: "13".to_string(),
+            flags: vec!["-Os".to_string()],
+        };
+
+        let output = generate_assembly(&source, &flags, AssemblyOptions::default()).unwrap();
+        assert_eq!(
+            output.text(),
+            format!(
+                "_Z3addii:\t@ add(int, int)\n@ {}:3: return a + b;\n\tadd r0, r0, r1\n",
+                source.display()
+            )
+        );
+
+        let options = AssemblyOptions::default()
+            .with_interleave_source(false)
+            .with_demangle(false);
+        let output = generate_assembly(&source, &flags, options).unwrap();
+        assert_eq!(output.text(), "_Z3addii:\n\tadd r0, r0, r1\n");
+    }
+}
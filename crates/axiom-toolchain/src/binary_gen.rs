@@ -0,0 +1,694 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Firmware image generation (raw binary, Intel HEX, Motorola S-record).
+//!
+//! Images are produced natively from the ELF; objcopy is kept as a
+//! fallback for ELFs the native reader rejects.
+
+use crate::limits::{is_timeout, run_limited, tool_limits};
+use crate::tool_log::log_invocation;
+use crate::{
+    DetectedToolchain, ElfError, ElfFile, ToolLogError, ToolQualificationLogger, SHF_ALLOC,
+    SHT_NOBITS,
+};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::path::Path;
+use std::time::Instant;
+use thiserror::Error;
+
+/// Data bytes per HEX/SREC record, matching objcopy.
+const RECORD_BYTES: usize = 16;
+
+/// Image generation and parsing errors.
+#[derive(Debug, Error)]
+pub enum BinaryGenError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("ELF error: {0}")]
+    Elf(#[from] ElfError),
+
+    #[error("Line {line}: {message}")]
+    Malformed { line: usize, message: String },
+
+    #[error("Line {line}: checksum mismatch (expected {expected:02X}, found {found:02X})")]
+    Checksum {
+        line: usize,
+        expected: u8,
+        found: u8,
+    },
+
+    #[error("Address 0x{0:X} does not fit the output format")]
+    AddressRange(u64),
+
+    #[error("objcopy failed: {0}")]
+    Objcopy(String),
+
+    #[error("objcopy timed out after {0}s")]
+    TimedOut(u64),
+
+    #[error("Tool usage not logged: {0}")]
+    UsageLog(#[from] ToolLogError),
+}
+
+/// Output image format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BinaryFormat {
+    /// Raw binary from the lowest address, gaps filled.
+    Bin,
+    /// Intel HEX.
+    Ihex,
+    /// Motorola S-record.
+    Srec,
+}
+
+impl BinaryFormat {
+    /// objcopy `-O` target name.
+    pub fn objcopy_target(&self) -> &'static str {
+        match self {
+            BinaryFormat::Bin => "binary",
+            BinaryFormat::Ihex => "ihex",
+            BinaryFormat::Srec => "srec",
+        }
+    }
+}
+
+/// How an image was produced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GenerationMethod {
+    /// Built-in encoder.
+    Native,
+    /// Toolchain objcopy.
+    Objcopy,
+}
+
+/// A contiguous block of image data.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImageBlock {
+    /// Load address of the first byte.
+    pub address: u32,
+    /// Contents.
+    pub data: Vec<u8>,
+}
+
+impl ImageBlock {
+    /// One past the last address.
+    pub fn end(&self) -> u64 {
+        u64::from(self.address) + self.data.len() as u64
+    }
+}
+
+/// A firmware image: load-address-ordered data blocks and an entry point.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FirmwareImage {
+    /// Non-overlapping blocks, sorted by address and merged when adjacent.
+    pub blocks: Vec<ImageBlock>,
+    /// Entry point, if recorded.
+    pub entry: Option<u32>,
+}
+
+impl FirmwareImage {
+    /// Build an image from the loadable sections of an ELF, at their load
+    /// addresses.
+    pub fn from_elf(elf: &ElfFile) -> Result<Self, BinaryGenError> {
+        let mut image = FirmwareImage {
+            entry: u32::try_from(elf.entry).ok(),
+            ..Default::default()
+        };
+        for section in &elf.sections {
+            if section.flags & SHF_ALLOC == 0 || section.section_type == SHT_NOBITS {
+                continue;
+            }
+            let Some(data) = elf.section_data(section).filter(|d| !d.is_empty()) else {
+                continue;
+            };
+            let lma = elf.load_address(section);
+            let address = u32::try_from(lma).map_err(|_| BinaryGenError::AddressRange(lma))?;
+            image.insert(address, data);
+        }
+        Ok(image)
+    }
+
+    /// Read an ELF file and build its image.
+    pub fn read_elf(path: &Path) -> Result<Self, BinaryGenError> {
+        Self::from_elf(&ElfFile::read(path)?)
+    }
+
+    /// Add data at an address, merging with adjacent blocks.
+    ///
+    /// Later data overwrites earlier data where they overlap.
+    pub fn insert(&mut self, address: u32, data: &[u8]) {
+        if data.is_empty() {
+            return;
+        }
+        let start = u64::from(address);
+        let end = start + data.len() as u64;
+
+        // Blocks overlapping or touching the new range
+        let first = self.blocks.partition_point(|b| b.end() < start);
+        let last = first
+            + self.blocks[first..]
+                .iter()
+                .take_while(|b| u64::from(b.address) <= end)
+                .count();
+        if first == last {
+            self.blocks.insert(
+                first,
+                ImageBlock {
+                    address,
+                    data: data.to_vec(),
+                },
+            );
+            return;
+        }
+
+        let mut touched = self.blocks.drain(first..last);
+        let mut merged = touched.next().unwrap_or(ImageBlock {
+            address,
+            data: Vec::new(),
+        });
+        let merged_start = start.min(u64::from(merged.address));
+        if u64::from(merged.address) > merged_start {
+            let mut prefixed = vec![0; (u64::from(merged.address) - merged_start) as usize];
+            prefixed.extend_from_slice(&merged.data);
+            merged = ImageBlock {
+                address,
+                data: prefixed,
+            };
+        }
+        let rest: Vec<ImageBlock> = touched.collect();
+        let merged_end = rest
+            .iter()
+            .map(ImageBlock::end)
+            .fold(end.max(merged.end()), u64::max);
+        merged.data.resize((merged_end - merged_start) as usize, 0);
+
+        for block in rest {
+            let offset = (u64::from(block.address) - merged_start) as usize;
+            merged.data[offset..offset + block.data.len()].copy_from_slice(&block.data);
+        }
+        let offset = (start - merged_start) as usize;
+        merged.data[offset..offset + data.len()].copy_from_slice(data);
+        self.blocks.insert(first, merged);
+    }
+
+    /// Address ranges covered by the image, as `(start, end)` pairs.
+    pub fn ranges(&self) -> Vec<(u32, u64)> {
+        self.blocks.iter().map(|b| (b.address, b.end())).collect()
+    }
+
+    /// Total number of data bytes.
+    pub fn size(&self) -> usize {
+        self.blocks.iter().map(|b| b.data.len()).sum()
+    }
+
+    /// Whether every byte in `address..address + len` is present.
+    pub fn contains(&self, address: u32, len: usize) -> bool {
+        self.read(address, len).is_some()
+    }
+
+    /// Read bytes at an address; `None` if any byte is missing.
+    pub fn read(&self, address: u32, len: usize) -> Option<&[u8]> {
+        let block = self
+            .blocks
+            .iter()
+            .find(|b| b.address <= address && u64::from(address) < b.end())?;
+        let offset = (address - block.address) as usize;
+        block.data.get(offset..offset.checked_add(len)?)
+    }
+
+    /// Flatten to a raw binary from the lowest address, filling gaps.
+    pub fn to_bin(&self, fill: u8) -> Vec<u8> {
+        let Some(first) = self.blocks.first() else {
+            return Vec::new();
+        };
+        let base = first.address;
+        let end = self.blocks.iter().map(ImageBlock::end).max().unwrap_or(0);
+        let mut out = vec![fill; (end - u64::from(base)) as usize];
+        for block in &self.blocks {
+            let offset = (block.address - base) as usize;
+            out[offset..offset + block.data.len()].copy_from_slice(&block.data);
+        }
+        out
+    }
+
+    /// Encode as Intel HEX.
+    pub fn to_intel_hex(&self) -> String {
+        let mut out = String::new();
+        let mut upper: Option<u16> = None;
+
+        for block in &self.blocks {
+            let mut address = block.address;
+            let mut remaining = block.data.as_slice();
+            while !remaining.is_empty() {
+                let high = (address >> 16) as u16;
+                if upper != Some(high) {
+                    hex_record(&mut out, 0, 0x04, &high.to_be_bytes());
+                    upper = Some(high);
+                }
+                // Records do not cross a 64 KiB boundary
+                let to_boundary = 0x1_0000 - (address & 0xffff) as usize;
+                let len = remaining.len().min(RECORD_BYTES).min(to_boundary);
+                hex_record(&mut out, address as u16, 0x00, &remaining[..len]);
+                remaining = &remaining[len..];
+                address = address.wrapping_add(len as u32);
+            }
+        }
+
+        if let Some(entry) = self.entry {
+            hex_record(&mut out, 0, 0x05, &entry.to_be_bytes());
+        }
+        hex_record(&mut out, 0, 0x01, &[]);
+        out
+    }
+
+    /// Encode as Motorola S-records, using the narrowest address width that
+    /// fits the image.
+    pub fn to_srec(&self, header: &str) -> String {
+        let max = self.blocks.iter().map(ImageBlock::end).max().unwrap_or(0);
+        let max = max
+            .saturating_sub(1)
+            .max(u64::from(self.entry.unwrap_or(0)));
+        let (data_type, end_type, width) = if max <= 0xffff {
+            (1, 9, 2)
+        } else if max <= 0xff_ffff {
+            (2, 8, 3)
+        } else {
+            (3, 7, 4)
+        };
+
+        let mut out = String::new();
+        srec_record(&mut out, 0, 0, 2, header.as_bytes());
+        let mut count = 0u32;
+        for block in &self.blocks {
+            for (i, chunk) in block.data.chunks(RECORD_BYTES).enumerate() {
+                let address = block.address + (i * RECORD_BYTES) as u32;
+                srec_record(&mut out, data_type, address, width, chunk);
+                count += 1;
+            }
+        }
+        if count <= 0xffff {
+            srec_record(&mut out, 5, count, 2, &[]);
+        } else if count <= 0xff_ffff {
+            srec_record(&mut out, 6, count, 3, &[]);
+        }
+        srec_record(&mut out, end_type, self.entry.unwrap_or(0), width, &[]);
+        out
+    }
+
+    /// Encode in the given format.
+    pub fn encode(&self, format: BinaryFormat) -> Vec<u8> {
+        match format {
+            BinaryFormat::Bin => self.to_bin(0xff),
+            BinaryFormat::Ihex => self.to_intel_hex().into_bytes(),
+            BinaryFormat::Srec => self.to_srec("").into_bytes(),
+        }
+    }
+}
+
+fn hex_record(out: &mut String, address: u16, record_type: u8, data: &[u8]) {
+    let [hi, lo] = address.to_be_bytes();
+    let mut sum = (data.len() as u8)
+        .wrapping_add(hi)
+        .wrapping_add(lo)
+        .wrapping_add(record_type);
+    let _ = write!(out, ":{:02X}{:04X}{:02X}", data.len(), address, record_type);
+    for b in data {
+        sum = sum.wrapping_add(*b);
+        let _ = write!(out, "{:02X}", b);
+    }
+    let _ = writeln!(out, "{:02X}", sum.wrapping_neg());
+}
+
+fn srec_record(out: &mut String, record_type: u8, address: u32, width: usize, data: &[u8]) {
+    let address_bytes = &address.to_be_bytes()[4 - width..];
+    let count = (width + data.len() + 1) as u8;
+    let mut sum = count;
+    let _ = write!(out, "S{}{:02X}", record_type, count);
+    for b in address_bytes.iter().chain(data) {
+        sum = sum.wrapping_add(*b);
+        let _ = write!(out, "{:02X}", b);
+    }
+    let _ = writeln!(out, "{:02X}", !sum);
+}
+
+/// Decode the hex digits of a record body.
+fn decode_hex(line: usize, text: &str) -> Result<Vec<u8>, BinaryGenError> {
+    let malformed = |message: &str| BinaryGenError::Malformed {
+        line,
+        message: message.to_string(),
+    };
+    if text.len() & 1 == 1 {
+        return Err(malformed("odd number of hex digits"));
+    }
+    // Bytewise, so non-ASCII text can't split a character
+    let digit = |b: u8| char::from(b).to_digit(16);
+    text.as_bytes()
+        .chunks(2)
+        .map(|pair| match (digit(pair[0]), digit(pair[1])) {
+            (Some(high), Some(low)) => Ok((high << 4 | low) as u8),
+            _ => Err(malformed("invalid hex digit")),
+        })
+        .collect()
+}
+
+/// Parse Intel HEX text, validating every checksum.
+pub fn parse_intel_hex(text: &str) -> Result<FirmwareImage, BinaryGenError> {
+    let mut image = FirmwareImage::default();
+    let mut base: u32 = 0;
+
+    for (idx, raw) in text.lines().enumerate() {
+        let line = idx + 1;
+        let raw = raw.trim();
+        if raw.is_empty() {
+            continue;
+        }
+        let malformed = |message: &str| BinaryGenError::Malformed {
+            line,
+            message: message.to_string(),
+        };
+        let body = raw
+            .strip_prefix(':')
+            .ok_or_else(|| malformed("missing ':'"))?;
+        let bytes = decode_hex(line, body)?;
+        if bytes.len() < 5 || bytes.len() != bytes[0] as usize + 5 {
+            return Err(malformed("length does not match byte count"));
+        }
+
+        let (payload, checksum) = bytes.split_at(bytes.len() - 1);
+        let expected = payload
+            .iter()
+            .fold(0u8, |acc, b| acc.wrapping_add(*b))
+            .wrapping_neg();
+        if expected != checksum[0] {
+            return Err(BinaryGenError::Checksum {
+                line,
+                expected,
+                found: checksum[0],
+            });
+        }
+
+        let offset = u16::from_be_bytes([payload[1], payload[2]]);
+        let data = &payload[4..];
+        match payload[3] {
+            0x00 => image.insert(base.wrapping_add(u32::from(offset)), data),
+            0x01 => break,
+            0x02 if data.len() == 2 => {
+                base = u32::from(u16::from_be_bytes([data[0], data[1]])) << 4
+            }
+            0x04 if data.len() == 2 => {
+                base = u32::from(u16::from_be_bytes([data[0], data[1]])) << 16
+            }
+            0x03 if data.len() == 4 => {
+                let cs = u32::from(u16::from_be_bytes([data[0], data[1]]));
+                let ip = u32::from(u16::from_be_bytes([data[2], data[3]]));
+                image.entry = Some((cs << 4) + ip);
+            }
+            0x05 if data.len() == 4 => {
+                image.entry = Some(u32::from_be_bytes([data[0], data[1], data[2], data[3]]))
+            }
+            other => return Err(malformed(&format!("unsupported record type {:02X}", other))),
+        }
+    }
+
+    Ok(image)
+}
+
+/// Parse Motorola S-record text, validating every checksum.
+pub fn parse_srec(text: &str) -> Result<FirmwareImage, BinaryGenError> {
+    let mut image = FirmwareImage::default();
+
+    for (idx, raw) in text.lines().enumerate() {
+        let line = idx + 1;
+        let raw = raw.trim();
+        if raw.is_empty() {
+            continue;
+        }
+        let malformed = |message: &str| BinaryGenError::Malformed {
+            line,
+            message: message.to_string(),
+        };
+        let mut chars = raw.chars();
+        if chars.next() != Some('S') {
+            return Err(malformed("missing 'S'"));
+        }
+        let record_type = chars
+            .next()
+            .and_then(|c| c.to_digit(10))
+            .ok_or_else(|| malformed("invalid record type"))?;
+        let bytes = decode_hex(line, &raw[2..])?;
+        if bytes.is_empty() || bytes.len() != bytes[0] as usize + 1 {
+            return Err(malformed("length does not match byte count"));
+        }
+
+        let (payload, checksum) = bytes.split_at(bytes.len() - 1);
+        let expected = !payload.iter().fold(0u8, |acc, b| acc.wrapping_add(*b));
+        if expected != checksum[0] {
+            return Err(BinaryGenError::Checksum {
+                line,
+                expected,
+                found: checksum[0],
+            });
+        }
+
+        let width = match record_type {
+            0 | 1 | 5 | 9 => 2,
+            2 | 6 | 8 => 3,
+            3 | 7 => 4,
+            _ => {
+                return Err(malformed(&format!(
+                    "unsupported record type S{}",
+                    record_type
+                )))
+            }
+        };
+        let fields = &payload[1..];
+        if fields.len() < width {
+            return Err(malformed("record shorter than its address"));
+        }
+        let address = fields[..width]
+            .iter()
+            .fold(0u32, |acc, b| (acc << 8) | u32::from(*b));
+        let data = &fields[width..];
+
+        match record_type {
+            1..=3 => image.insert(address, data),
+            7..=9 => image.entry = Some(address),
+            _ => {}
+        }
+    }
+
+    Ok(image)
+}
+
+/// Build objcopy arguments for converting an ELF.
+pub fn build_objcopy_command(elf: &Path, output: &Path, format: BinaryFormat) -> Vec<String> {
+    vec![
+        "-O".to_string(),
+        format.objcopy_target().to_string(),
+        elf.display().to_string(),
+        output.display().to_string(),
+    ]
+}
+
+/// Convert an ELF with the toolchain's objcopy, logging the run if given a
+/// tool usage log. objcopy is logged with the toolchain's version.
+pub fn objcopy_image(
+    toolchain: &DetectedToolchain,
+    elf: &Path,
+    output: &Path,
+    format: BinaryFormat,
+    usage_log: Option<&ToolQualificationLogger>,
+) -> Result<(), BinaryGenError> {
+    let objcopy = toolchain.sibling_tool("objcopy");
+    let args = build_objcopy_command(elf, output, format);
+    let start = Instant::now();
+    let result = run_limited(&mut toolchain.tool_command(&objcopy, &args));
+    let logged = log_invocation(usage_log, &objcopy, &toolchain.version, |record| {
+        record
+            .with_arguments(args)
+            .with_result(
+                result
+                    .as_ref()
+                    .map_or(-1, |r| r.status.code().unwrap_or(-1)),
+                start.elapsed().as_millis() as u64,
+            )
+            .with_inputs([elf])
+            .with_outputs([output])
+    });
+    let result = result.map_err(|e| {
+        if is_timeout(&e) {
+            BinaryGenError::TimedOut(tool_limits().timeout_secs.unwrap_or_default())
+        } else {
+            BinaryGenError::Objcopy(format!("{}: {}", objcopy.display(), e))
+        }
+    })?;
+    if !result.status.success() {
+        return Err(BinaryGenError::Objcopy(
+            String::from_utf8_lossy(&result.stderr).trim().to_string(),
+        ));
+    }
+    logged?;
+    Ok(())
+}
+
+/// Write a firmware image, natively if possible and via objcopy otherwise.
+///
+/// Only objcopy runs are logged to `usage_log`: native conversion runs no
+/// external tool.
+pub fn generate_image(
+    toolchain: Option<&DetectedToolchain>,
+    elf: &Path,
+    output: &Path,
+    format: BinaryFormat,
+    usage_log: Option<&ToolQualificationLogger>,
+) -> Result<GenerationMethod, BinaryGenError> {
+    match FirmwareImage::read_elf(elf) {
+        Ok(image) => {
+            std::fs::write(output, image.encode(format))?;
+            Ok(GenerationMethod::Native)
+        }
+        Err(err) => match toolchain {
+            Some(toolchain) => {
+                objcopy_image(toolchain, elf, output, format, usage_log)?;
+                Ok(GenerationMethod::Objcopy)
+            }
+            None => Err(err),
+        },
+    }
+}
+
+/// Read a HEX or SREC file, choosing the parser from its first record.
+pub fn read_image(path: &Path) -> Result<FirmwareImage, BinaryGenError> {
+    let text = std::fs::read_to_string(path)?;
+    if text.trim_start().starts_with('S') {
+        parse_srec(&text)
+    } else {
+        parse_intel_hex(&text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image() -> FirmwareImage {
+        let mut image = FirmwareImage {
+            entry: Some(0x0800_0101),
+            ..Default::default()
+        };
+        image.insert(0x0800_0000, &(0u8..40).collect::<Vec<_>>());
+        image.insert(0x0800_1000, &[0xde, 0xad, 0xbe, 0xef]);
+        image
+    }
+
+    #[test]
+    fn test_insert_merges_adjacent() {
+        let mut image = image();
+        image.insert(0x0800_0028, &[0xaa; 8]);
+        assert_eq!(image.blocks.len(), 2);
+        assert_eq!(image.blocks[0].data.len(), 48);
+
+        image.insert(0x0800_0004, &[0xff; 2]);
+        assert_eq!(image.read(0x0800_0003, 4).unwrap(), &[3, 0xff, 0xff, 6]);
+    }
+
+    #[test]
+    fn test_address_queries() {
+        let image = image();
+        assert_eq!(
+            image.ranges(),
+            vec![(0x0800_0000, 0x0800_0028), (0x0800_1000, 0x0800_1004)]
+        );
+        assert_eq!(image.size(), 44);
+        assert!(image.contains(0x0800_1000, 4));
+        assert!(!image.contains(0x0800_0020, 16));
+        assert_eq!(image.read(0x0800_1002, 2).unwrap(), &[0xbe, 0xef]);
+    }
+
+    #[test]
+    fn test_intel_hex_round_trip() {
+        let hex = image().to_intel_hex();
+        let lines: Vec<_> = hex.lines().collect();
+        assert_eq!(lines[0], ":020000040800F2");
+        assert_eq!(lines[1], ":10000000000102030405060708090A0B0C0D0E0F78");
+        assert_eq!(*lines.last().unwrap(), ":00000001FF");
+
+        assert_eq!(parse_intel_hex(&hex).unwrap(), image());
+    }
+
+    #[test]
+    fn test_intel_hex_checksum() {
+        let err = parse_intel_hex(":020000040800F3\n").unwrap_err();
+        assert!(matches!(
+            err,
+            BinaryGenError::Checksum {
+                line: 1,
+                expected: 0xf2,
+                found: 0xf3
+            }
+        ));
+        assert!(matches!(
+            parse_intel_hex("020000040800F2"),
+            Err(BinaryGenError::Malformed { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_non_ascii_hex_digits() {
+        // Two-byte characters straddling a digit pair
+        assert!(matches!(
+            parse_intel_hex(":0\u{e9}0\n"),
+            Err(BinaryGenError::Malformed { line: 1, .. })
+        ));
+        assert!(matches!(
+            parse_srec("S10\u{e9}0\n"),
+            Err(BinaryGenError::Malformed { line: 1, .. })
+        ));
+        assert!(matches!(
+            parse_intel_hex(":+1\n"),
+            Err(BinaryGenError::Malformed { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_srec_round_trip() {
+        let srec = image().to_srec("fw");
+        let lines: Vec<_> = srec.lines().collect();
+        assert_eq!(lines[0], "S005000066771D");
+        assert!(lines[1].starts_with("S3150800000000"));
+        assert_eq!(*lines.last().unwrap(), "S70508000101F0");
+
+        assert_eq!(parse_srec(&srec).unwrap(), image());
+    }
+
+    #[test]
+    fn test_srec_checksum() {
+        let mut lines: Vec<String> = image().to_srec("").lines().map(String::from).collect();
+        // Flip the first data byte of the first S3 record
+        lines[1].replace_range(12..13, "1");
+        assert!(matches!(
+            parse_srec(&lines.join("\n")),
+            Err(BinaryGenError::Checksum { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_to_bin_fills_gaps() {
+        let mut image = FirmwareImage::default();
+        image.insert(0x100, &[1, 2]);
+        image.insert(0x104, &[3]);
+        assert_eq!(image.to_bin(0xff), vec![1, 2, 0xff, 0xff, 3]);
+    }
+
+    #[test]
+    fn test_objcopy_command() {
+        let args =
+            build_objcopy_command(Path::new("fw.elf"), Path::new("fw.hex"), BinaryFormat::Ihex);
+        assert_eq!(args, vec!["-O", "ihex", "fw.elf", "fw.hex"]);
+    }
+}
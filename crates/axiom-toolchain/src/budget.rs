@@ -0,0 +1,434 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Flash and RAM budgets.
+//!
+//! A project declares its budgets in `.axiom/memory-budget.toml`:
+//!
+//! ```toml
+//! flash = "90%"
+//! ram = "24K"
+//! ```
+//!
+//! Limits are byte counts (optionally with a `K` or `M` suffix) or a
+//! percentage of the device's memory. Device capacity is the sum of the
+//! linker script's MEMORY regions: writable regions count as RAM, the rest
+//! as flash. Usage is measured on the linked image, so a budget can fail a
+//! build long before the linker reports a region overflow.
+
+use crate::{ElfError, ElfFile, MemoryRegion, SectionSizes};
+use axiom_core::Diagnostic;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Diagnostic code attached to budget violations.
+pub const BUDGET_DIAGNOSTIC_CODE: &str = "memory-budget";
+
+/// Budget errors.
+#[derive(Debug, Error)]
+pub enum BudgetError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("TOML parse error in {path}: {source}")]
+    Toml {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+
+    #[error("Invalid budget limit: {0}")]
+    InvalidLimit(String),
+
+    #[error("{0} budget is a percentage but the {0} size is unknown")]
+    UnknownCapacity(BudgetMemory),
+
+    #[error("ELF error: {0}")]
+    Elf(#[from] ElfError),
+}
+
+/// Memory a budget applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BudgetMemory {
+    /// Text plus initial values of data.
+    Flash,
+    /// Data plus bss.
+    Ram,
+}
+
+impl fmt::Display for BudgetMemory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            BudgetMemory::Flash => "flash",
+            BudgetMemory::Ram => "RAM",
+        })
+    }
+}
+
+/// A budget limit.
+///
+/// Written as a byte count (`32768`, `"32K"`, `"1M"`) or a percentage
+/// (`"90%"`).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "LimitRepr", into = "String")]
+pub enum BudgetLimit {
+    /// Absolute limit in bytes.
+    Bytes(u64),
+    /// Percentage of the memory's capacity.
+    Percent(f64),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LimitRepr {
+    Bytes(u64),
+    Text(String),
+}
+
+impl TryFrom<LimitRepr> for BudgetLimit {
+    type Error = BudgetError;
+
+    fn try_from(repr: LimitRepr) -> Result<Self, BudgetError> {
+        match repr {
+            LimitRepr::Bytes(bytes) => Ok(BudgetLimit::Bytes(bytes)),
+            LimitRepr::Text(text) => text.parse(),
+        }
+    }
+}
+
+impl FromStr for BudgetLimit {
+    type Err = BudgetError;
+
+    fn from_str(s: &str) -> Result<Self, BudgetError> {
+        let invalid = || BudgetError::InvalidLimit(s.to_string());
+        let text = s.trim();
+        if let Some(percent) = text.strip_suffix('%') {
+            let percent: f64 = percent.trim().parse().map_err(|_| invalid())?;
+            return if percent > 0.0 && percent <= 100.0 {
+                Ok(BudgetLimit::Percent(percent))
+            } else {
+                Err(invalid())
+            };
+        }
+        let (digits, scale) = match text.char_indices().last() {
+            Some((i, 'K' | 'k')) => (&text[..i], 1024),
+            Some((i, 'M' | 'm')) => (&text[..i], 1024 * 1024),
+            _ => (text, 1),
+        };
+        let bytes: u64 = digits.trim().parse().map_err(|_| invalid())?;
+        bytes
+            .checked_mul(scale)
+            .map(BudgetLimit::Bytes)
+            .ok_or_else(invalid)
+    }
+}
+
+impl fmt::Display for BudgetLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BudgetLimit::Bytes(bytes) => write!(f, "{}", bytes),
+            BudgetLimit::Percent(percent) => write!(f, "{}%", percent),
+        }
+    }
+}
+
+impl From<BudgetLimit> for String {
+    fn from(limit: BudgetLimit) -> Self {
+        limit.to_string()
+    }
+}
+
+impl BudgetLimit {
+    /// Limit in bytes for a memory of the given capacity.
+    pub fn bytes(&self, capacity: Option<u64>) -> Option<u64> {
+        match *self {
+            BudgetLimit::Bytes(bytes) => Some(bytes),
+            BudgetLimit::Percent(percent) => {
+                capacity.map(|capacity| (capacity as f64 * percent / 100.0) as u64)
+            }
+        }
+    }
+}
+
+/// Flash and RAM budgets for a project.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MemoryBudget {
+    /// Flash budget.
+    #[serde(default)]
+    pub flash: Option<BudgetLimit>,
+    /// RAM budget.
+    #[serde(default)]
+    pub ram: Option<BudgetLimit>,
+}
+
+impl MemoryBudget {
+    /// Set the flash budget.
+    pub fn with_flash(mut self, limit: BudgetLimit) -> Self {
+        self.flash = Some(limit);
+        self
+    }
+
+    /// Set the RAM budget.
+    pub fn with_ram(mut self, limit: BudgetLimit) -> Self {
+        self.ram = Some(limit);
+        self
+    }
+
+    /// Whether no budget is set.
+    pub fn is_empty(&self) -> bool {
+        self.flash.is_none() && self.ram.is_none()
+    }
+
+    /// Compare image sizes against the budgets, given the device's MEMORY
+    /// regions. Returns the violations, flash first.
+    pub fn evaluate(
+        &self,
+        sizes: &SectionSizes,
+        regions: &[MemoryRegion],
+    ) -> Result<Vec<BudgetViolation>, BudgetError> {
+        let capacity = MemoryCapacity::from_regions(regions);
+        let budgets = [
+            (
+                BudgetMemory::Flash,
+                self.flash,
+                sizes.flash(),
+                capacity.flash,
+            ),
+            (BudgetMemory::Ram, self.ram, sizes.ram(), capacity.ram),
+        ];
+
+        let mut violations = Vec::new();
+        for (memory, budget, used, capacity) in budgets {
+            let Some(budget) = budget else {
+                continue;
+            };
+            let limit = budget
+                .bytes(capacity)
+                .ok_or(BudgetError::UnknownCapacity(memory))?;
+            if used > limit {
+                violations.push(BudgetViolation {
+                    memory,
+                    used,
+                    limit,
+                    budget,
+                    capacity,
+                });
+            }
+        }
+        Ok(violations)
+    }
+
+    /// Evaluate a linked image against the budgets.
+    pub fn evaluate_elf(
+        &self,
+        elf: &Path,
+        regions: &[MemoryRegion],
+    ) -> Result<Vec<BudgetViolation>, BudgetError> {
+        let sizes = SectionSizes::from_elf(&ElfFile::read(elf)?);
+        self.evaluate(&sizes, regions)
+    }
+}
+
+/// Flash and RAM sizes of a device.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct MemoryCapacity {
+    flash: Option<u64>,
+    ram: Option<u64>,
+}
+
+impl MemoryCapacity {
+    /// Sum writable and read-only regions, skipping the map file's
+    /// `*default*` catch-all.
+    fn from_regions(regions: &[MemoryRegion]) -> Self {
+        let mut capacity = Self::default();
+        for region in regions.iter().filter(|r| !r.name.starts_with('*')) {
+            let total = if region.attributes.contains(['w', 'W']) {
+                &mut capacity.ram
+            } else {
+                &mut capacity.flash
+            };
+            *total = Some(total.unwrap_or(0) + region.length);
+        }
+        capacity
+    }
+}
+
+/// A memory whose usage exceeds its budget.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BudgetViolation {
+    /// Memory over budget.
+    pub memory: BudgetMemory,
+    /// Bytes used by the image.
+    pub used: u64,
+    /// Budget in bytes.
+    pub limit: u64,
+    /// Budget as declared.
+    pub budget: BudgetLimit,
+    /// Device capacity, when known.
+    pub capacity: Option<u64>,
+}
+
+impl BudgetViolation {
+    /// Bytes over budget.
+    pub fn excess(&self) -> u64 {
+        self.used - self.limit
+    }
+
+    /// Error diagnostic for the build output.
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let budget = match (self.budget, self.capacity) {
+            (BudgetLimit::Percent(percent), Some(capacity)) => {
+                format!("{} bytes ({}% of {})", self.limit, percent, capacity)
+            }
+            _ => format!("{} bytes", self.limit),
+        };
+        Diagnostic::error(format!(
+            "{} budget exceeded by {} bytes: {} bytes used, budget {}",
+            self.memory,
+            self.excess(),
+            self.used,
+            budget
+        ))
+        .with_code(BUDGET_DIAGNOSTIC_CODE)
+    }
+}
+
+/// Path of a project's memory budget file.
+pub fn memory_budget_path(project_root: &Path) -> PathBuf {
+    project_root.join(".axiom").join("memory-budget.toml")
+}
+
+/// Load a project's memory budget; a missing file gives no budgets.
+pub fn load_memory_budget(project_root: &Path) -> Result<MemoryBudget, BudgetError> {
+    let path = memory_budget_path(project_root);
+    if !path.is_file() {
+        return Ok(MemoryBudget::default());
+    }
+    let content = std::fs::read_to_string(&path)?;
+    toml::from_str(&content).map_err(|source| BudgetError::Toml { path, source })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn region(name: &str, length: u64, attributes: &str) -> MemoryRegion {
+        MemoryRegion {
+            name: name.to_string(),
+            origin: 0,
+            length,
+            attributes: attributes.to_string(),
+            used: 0,
+        }
+    }
+
+    fn regions() -> Vec<MemoryRegion> {
+        vec![
+            region("FLASH", 0x10000, "rx"),
+            region("RAM", 0x4000, "xrw"),
+            region("CCMRAM", 0x1000, "rw"),
+            region("*default*", 0xffff_ffff, ""),
+        ]
+    }
+
+    #[test]
+    fn test_parse_limits() {
+        assert_eq!(
+            "4096".parse::<BudgetLimit>().unwrap(),
+            BudgetLimit::Bytes(4096)
+        );
+        assert_eq!(
+            "32K".parse::<BudgetLimit>().unwrap(),
+            BudgetLimit::Bytes(32768)
+        );
+        assert_eq!(
+            "1M".parse::<BudgetLimit>().unwrap(),
+            BudgetLimit::Bytes(1 << 20)
+        );
+        assert_eq!(
+            "87.5%".parse::<BudgetLimit>().unwrap(),
+            BudgetLimit::Percent(87.5)
+        );
+        assert!("150%".parse::<BudgetLimit>().is_err());
+        assert!("lots".parse::<BudgetLimit>().is_err());
+    }
+
+    #[test]
+    fn test_load_budget() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(load_memory_budget(dir.path()).unwrap().is_empty());
+
+        std::fs::create_dir(dir.path().join(".axiom")).unwrap();
+        std::fs::write(
+            memory_budget_path(dir.path()),
+            "flash = \"90%\"\nram = 16384\n",
+        )
+        .unwrap();
+        let budget = load_memory_budget(dir.path()).unwrap();
+        assert_eq!(budget.flash, Some(BudgetLimit::Percent(90.0)));
+        assert_eq!(budget.ram, Some(BudgetLimit::Bytes(16384)));
+
+        let json = serde_json::to_string(&budget).unwrap();
+        assert_eq!(json, r#"{"flash":"90%","ram":"16384"}"#);
+        assert_eq!(serde_json::from_str::<MemoryBudget>(&json).unwrap(), budget);
+
+        std::fs::write(memory_budget_path(dir.path()), "flash = \"most\"\n").unwrap();
+        assert!(matches!(
+            load_memory_budget(dir.path()),
+            Err(BudgetError::Toml { .. })
+        ));
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let sizes = SectionSizes {
+            text: 0xe800,
+            data: 0x400,
+            bss: 0x3000,
+        };
+        let budget = MemoryBudget::default()
+            .with_flash(BudgetLimit::Percent(90.0))
+            .with_ram(BudgetLimit::Bytes(0x4000));
+
+        let violations = budget.evaluate(&sizes, &regions()).unwrap();
+        assert_eq!(violations.len(), 1);
+        let flash = &violations[0];
+        assert_eq!(flash.memory, BudgetMemory::Flash);
+        assert_eq!(flash.used, 0xec00);
+        assert_eq!(flash.limit, 58982);
+        assert_eq!(flash.capacity, Some(0x10000));
+        assert_eq!(flash.excess(), 1434);
+
+        let diagnostic = flash.to_diagnostic();
+        assert_eq!(diagnostic.severity, axiom_core::Severity::Error);
+        assert_eq!(diagnostic.code.as_deref(), Some(BUDGET_DIAGNOSTIC_CODE));
+        assert_eq!(
+            diagnostic.message,
+            "flash budget exceeded by 1434 bytes: 60416 bytes used, budget 58982 bytes (90% of 65536)"
+        );
+
+        // RAM capacity includes CCMRAM, so 60% is 12288 bytes
+        let ram = MemoryBudget::default().with_ram(BudgetLimit::Percent(60.0));
+        let violations = ram.evaluate(&sizes, &regions()).unwrap();
+        assert_eq!(violations[0].memory, BudgetMemory::Ram);
+        assert_eq!(violations[0].limit, 12288);
+    }
+
+    #[test]
+    fn test_percentage_needs_capacity() {
+        let budget = MemoryBudget::default().with_ram(BudgetLimit::Percent(50.0));
+        assert!(matches!(
+            budget.evaluate(&SectionSizes::default(), &[region("FLASH", 0x1000, "rx")]),
+            Err(BudgetError::UnknownCapacity(BudgetMemory::Ram))
+        ));
+
+        let absolute = MemoryBudget::default().with_ram(BudgetLimit::Bytes(16));
+        assert!(absolute
+            .evaluate(&SectionSizes::default(), &[])
+            .unwrap()
+            .is_empty());
+    }
+}
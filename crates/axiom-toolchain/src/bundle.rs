@@ -0,0 +1,525 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Offline toolchain bundles.
+//!
+//! Installs an ARM GCC toolchain from a local `.tar.xz`/`.tar.bz2`/`.tar.gz`
+//! or `.zip` archive into an Axiom-managed directory, for machines with no
+//! network access. The archive is checked against a `sha256sum`-style
+//! manifest before anything is extracted, and installed bundles are picked
+//! up by toolchain detection ahead of system installs.
+
+use crate::limits::run_limited;
+use crate::zip::{zip_entries, zip_read};
+use crate::{detect_at_path, sha256_file, DetectedToolchain, ToolchainKind};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+
+/// Marker file written into each installed bundle.
+const MARKER: &str = ".axiom-bundle.json";
+
+/// Error type for bundle installation.
+#[derive(Debug, thiserror::Error)]
+pub enum BundleError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid manifest line {line}: {message}")]
+    Manifest { line: usize, message: String },
+
+    #[error("{0} is not listed in the manifest")]
+    NotInManifest(String),
+
+    #[error("Checksum mismatch: expected {expected}, got {actual}")]
+    ChecksumMismatch { expected: String, actual: String },
+
+    #[error("Unsupported archive format: {0}")]
+    UnsupportedArchive(String),
+
+    #[error("Extraction failed: {0}")]
+    Extract(String),
+
+    #[error("No arm-none-eabi-gcc found in {0}")]
+    NoCompiler(String),
+
+    #[error("Toolchain {0} is already installed")]
+    AlreadyInstalled(String),
+}
+
+/// Archive container format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ArchiveFormat {
+    Tar,
+    Zip,
+}
+
+impl ArchiveFormat {
+    /// Format and bundle name from an archive file name.
+    ///
+    /// `arm-gnu-toolchain-13.2.rel1-x86_64-arm-none-eabi.tar.xz` is a tar
+    /// archive named `arm-gnu-toolchain-13.2.rel1-x86_64-arm-none-eabi`.
+    pub fn from_file_name(name: &str) -> Option<(Self, &str)> {
+        let lower = name.to_ascii_lowercase();
+        for ext in [
+            ".tar.xz", ".tar.bz2", ".tar.gz", ".txz", ".tbz2", ".tgz", ".tar",
+        ] {
+            if lower.ends_with(ext) {
+                return Some((Self::Tar, &name[..name.len() - ext.len()]));
+            }
+        }
+        if lower.ends_with(".zip") {
+            return Some((Self::Zip, &name[..name.len() - 4]));
+        }
+        None
+    }
+}
+
+/// A toolchain installed from an offline bundle.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstalledToolchain {
+    /// Bundle name, also the directory name under the install root.
+    pub name: String,
+    /// Archive file the bundle was installed from.
+    pub archive: String,
+    /// Verified SHA-256 of the archive.
+    pub sha256: String,
+    /// Directory the bundle was extracted into.
+    pub root: PathBuf,
+    /// Path to `arm-none-eabi-gcc`.
+    pub compiler: PathBuf,
+}
+
+impl InstalledToolchain {
+    /// Detect the compiler, marked as bundled.
+    pub fn detect(&self) -> Option<DetectedToolchain> {
+        detect_at_path(&self.compiler, ToolchainKind::ArmGcc).map(DetectedToolchain::as_bundled)
+    }
+}
+
+/// Contents of the marker file; the compiler path is relative to the bundle.
+#[derive(Debug, Serialize, Deserialize)]
+struct BundleMarker {
+    name: String,
+    archive: String,
+    sha256: String,
+    compiler: PathBuf,
+}
+
+/// Default directory for installed toolchain bundles.
+pub fn default_toolchain_dir() -> Option<PathBuf> {
+    if cfg!(windows) {
+        std::env::var_os("LOCALAPPDATA")
+            .map(|local| PathBuf::from(local).join("axiom").join("toolchains"))
+    } else if cfg!(target_os = "macos") {
+        std::env::var_os("HOME").map(|home| {
+            PathBuf::from(home)
+                .join("Library")
+                .join("Application Support")
+                .join("com.hawklogic.axiom")
+                .join("toolchains")
+        })
+    } else {
+        std::env::var_os("HOME").map(|home| {
+            PathBuf::from(home)
+                .join(".local")
+                .join("share")
+                .join("axiom")
+                .join("toolchains")
+        })
+    }
+}
+
+/// Parse a `sha256sum`-style manifest into file name → lowercase hash.
+///
+/// Lines are `<hash>  <name>` or `<hash> *<name>`; blank lines and `#`
+/// comments are skipped. Names are reduced to their file name so manifests
+/// generated with directory prefixes still match.
+pub fn parse_checksum_manifest(content: &str) -> Result<HashMap<String, String>, BundleError> {
+    let mut entries = HashMap::new();
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let invalid = |message: &str| BundleError::Manifest {
+            line: i + 1,
+            message: message.to_string(),
+        };
+        let (hash, name) = line
+            .split_once(char::is_whitespace)
+            .ok_or_else(|| invalid("expected '<sha256>  <file>'"))?;
+        if hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(invalid("not a SHA-256 digest"));
+        }
+        let name = name.trim_start().trim_start_matches('*');
+        let name = name.rsplit(['/', '\\']).next().unwrap_or(name);
+        if name.is_empty() {
+            return Err(invalid("missing file name"));
+        }
+        entries.insert(name.to_string(), hash.to_ascii_lowercase());
+    }
+    Ok(entries)
+}
+
+/// Check an archive's SHA-256 against the manifest, returning the hash.
+pub fn verify_archive(archive: &Path, manifest: &Path) -> Result<String, BundleError> {
+    let entries = parse_checksum_manifest(&std::fs::read_to_string(manifest)?)?;
+    let name = file_name(archive);
+    let expected = entries
+        .get(&name)
+        .ok_or_else(|| BundleError::NotInManifest(name.clone()))?;
+    let actual = sha256_file(archive)?;
+    if actual != *expected {
+        return Err(BundleError::ChecksumMismatch {
+            expected: expected.clone(),
+            actual,
+        });
+    }
+    Ok(actual)
+}
+
+/// Verify and install a toolchain archive under `install_root`.
+///
+/// The archive is extracted into a staging directory and only moved into
+/// `install_root/<name>` once it's known to contain `arm-none-eabi-gcc`, so
+/// a failed install leaves nothing behind.
+pub fn install_toolchain_bundle(
+    archive: &Path,
+    manifest: &Path,
+    install_root: &Path,
+) -> Result<InstalledToolchain, BundleError> {
+    let archive_name = file_name(archive);
+    let (format, name) = ArchiveFormat::from_file_name(&archive_name)
+        .ok_or_else(|| BundleError::UnsupportedArchive(archive_name.clone()))?;
+    let sha256 = verify_archive(archive, manifest)?;
+
+    let root = install_root.join(name);
+    if root.exists() {
+        return Err(BundleError::AlreadyInstalled(name.to_string()));
+    }
+    std::fs::create_dir_all(install_root)?;
+    let staging = install_root.join(format!(".staging-{}", name));
+    if staging.exists() {
+        std::fs::remove_dir_all(&staging)?;
+    }
+    std::fs::create_dir_all(&staging)?;
+
+    let result = extract(archive, format, &staging).and_then(|()| {
+        find_compiler(&staging, 3).ok_or_else(|| BundleError::NoCompiler(archive_name.clone()))
+    });
+    let compiler = match result {
+        Ok(compiler) => compiler,
+        Err(e) => {
+            let _ = std::fs::remove_dir_all(&staging);
+            return Err(e);
+        }
+    };
+    let relative = compiler
+        .strip_prefix(&staging)
+        .unwrap_or(&compiler)
+        .to_path_buf();
+
+    let marker = BundleMarker {
+        name: name.to_string(),
+        archive: archive_name,
+        sha256,
+        compiler: relative,
+    };
+    let json =
+        serde_json::to_string_pretty(&marker).map_err(|e| BundleError::Extract(e.to_string()))?;
+    std::fs::write(staging.join(MARKER), json)?;
+    std::fs::rename(&staging, &root)?;
+
+    Ok(installed_from_marker(&root, marker))
+}
+
+/// Bundles installed under `install_root`, sorted by name.
+pub fn installed_toolchains(install_root: &Path) -> Vec<InstalledToolchain> {
+    let Ok(dir) = std::fs::read_dir(install_root) else {
+        return Vec::new();
+    };
+    let mut installed: Vec<InstalledToolchain> = dir
+        .flatten()
+        .filter_map(|entry| {
+            let root = entry.path();
+            let content = std::fs::read_to_string(root.join(MARKER)).ok()?;
+            let marker: BundleMarker = serde_json::from_str(&content).ok()?;
+            Some(installed_from_marker(&root, marker))
+        })
+        .collect();
+    installed.sort_by(|a, b| a.name.cmp(&b.name));
+    installed
+}
+
+/// First usable ARM GCC from the default bundle directory.
+pub fn detect_bundled_arm_gcc() -> Option<DetectedToolchain> {
+    installed_toolchains(&default_toolchain_dir()?)
+        .iter()
+        .find_map(InstalledToolchain::detect)
+}
+
+fn installed_from_marker(root: &Path, marker: BundleMarker) -> InstalledToolchain {
+    InstalledToolchain {
+        name: marker.name,
+        archive: marker.archive,
+        sha256: marker.sha256,
+        compiler: root.join(marker.compiler),
+        root: root.to_path_buf(),
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn extract(archive: &Path, format: ArchiveFormat, dest: &Path) -> Result<(), BundleError> {
+    match format {
+        // tar detects the compression itself and refuses `..` members
+        ArchiveFormat::Tar => {
+            let output = run_limited(
+                Command::new("tar")
+                    .arg("-xf")
+                    .arg(archive)
+                    .arg("-C")
+                    .arg(dest),
+            )
+            .map_err(|e| BundleError::Extract(format!("cannot run tar: {}", e)))?;
+            if !output.status.success() {
+                return Err(BundleError::Extract(
+                    String::from_utf8_lossy(&output.stderr).trim().to_string(),
+                ));
+            }
+            Ok(())
+        }
+        ArchiveFormat::Zip => extract_zip(&std::fs::read(archive)?, dest),
+    }
+}
+
+fn extract_zip(data: &[u8], dest: &Path) -> Result<(), BundleError> {
+    for entry in zip_entries(data).map_err(BundleError::Extract)? {
+        let relative = Path::new(&entry.name);
+        if relative
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+        {
+            return Err(BundleError::Extract(format!(
+                "unsafe path in archive: {}",
+                entry.name
+            )));
+        }
+        let path = dest.join(relative);
+        if entry.is_dir() {
+            std::fs::create_dir_all(&path)?;
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let content = zip_read(data, &entry).map_err(BundleError::Extract)?;
+        std::fs::write(&path, content)?;
+        #[cfg(unix)]
+        if let Some(mode) = entry.unix_mode {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode & 0o777))?;
+        }
+    }
+    Ok(())
+}
+
+/// Look for `bin/arm-none-eabi-gcc` at most `depth` directories down.
+fn find_compiler(dir: &Path, depth: usize) -> Option<PathBuf> {
+    let exe = if cfg!(windows) {
+        "arm-none-eabi-gcc.exe"
+    } else {
+        "arm-none-eabi-gcc"
+    };
+    let candidate = dir.join("bin").join(exe);
+    if candidate.is_file() {
+        return Some(candidate);
+    }
+    if depth == 0 {
+        return None;
+    }
+    let mut subdirs: Vec<PathBuf> = std::fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.is_dir())
+        .collect();
+    subdirs.sort();
+    subdirs.iter().find_map(|sub| find_compiler(sub, depth - 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sha256_file;
+    use tempfile::TempDir;
+
+    /// Build a stored zip whose entries carry Unix modes.
+    fn zip(entries: &[(&str, &[u8], u32)]) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut central = Vec::new();
+        for (name, content, mode) in entries {
+            let offset = out.len() as u32;
+            out.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+            out.extend_from_slice(&[20, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+            out.extend_from_slice(&(content.len() as u32).to_le_bytes());
+            out.extend_from_slice(&(content.len() as u32).to_le_bytes());
+            out.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            out.extend_from_slice(&0u16.to_le_bytes());
+            out.extend_from_slice(name.as_bytes());
+            out.extend_from_slice(content);
+
+            central.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+            central.extend_from_slice(&0x0314u16.to_le_bytes());
+            central.extend_from_slice(&[20, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+            central.extend_from_slice(&(content.len() as u32).to_le_bytes());
+            central.extend_from_slice(&(content.len() as u32).to_le_bytes());
+            central.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            central.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0]);
+            central.extend_from_slice(&(mode << 16).to_le_bytes());
+            central.extend_from_slice(&offset.to_le_bytes());
+            central.extend_from_slice(name.as_bytes());
+        }
+        let start = out.len() as u32;
+        out.extend_from_slice(&central);
+        out.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+        out.extend_from_slice(&[0, 0, 0, 0]);
+        out.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        out.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        out.extend_from_slice(&(central.len() as u32).to_le_bytes());
+        out.extend_from_slice(&start.to_le_bytes());
+        out.extend_from_slice(&0u16.to_le_bytes());
+        out
+    }
+
+    fn write_bundle(dir: &Path, name: &str, data: &[u8]) -> (PathBuf, PathBuf) {
+        let archive = dir.join(name);
+        std::fs::write(&archive, data).unwrap();
+        let manifest = dir.join("SHA256SUMS");
+        std::fs::write(
+            &manifest,
+            format!("{}  {}\n", sha256_file(&archive).unwrap(), name),
+        )
+        .unwrap();
+        (archive, manifest)
+    }
+
+    #[test]
+    fn test_archive_format() {
+        assert_eq!(
+            ArchiveFormat::from_file_name("arm-gnu-toolchain-13.2.rel1.tar.xz"),
+            Some((ArchiveFormat::Tar, "arm-gnu-toolchain-13.2.rel1"))
+        );
+        assert_eq!(
+            ArchiveFormat::from_file_name("gcc-arm-10.3-win32.ZIP"),
+            Some((ArchiveFormat::Zip, "gcc-arm-10.3-win32"))
+        );
+        assert_eq!(ArchiveFormat::from_file_name("toolchain.7z"), None);
+    }
+
+    #[test]
+    fn test_parse_checksum_manifest() {
+        let hash = "a".repeat(64);
+        let content = format!(
+            "# release checksums\n{}  gcc.tar.xz\n{} *dist/gcc.zip\n\n",
+            hash,
+            hash.to_uppercase()
+        );
+        let entries = parse_checksum_manifest(&content).unwrap();
+        assert_eq!(entries.get("gcc.tar.xz"), Some(&hash));
+        assert_eq!(entries.get("gcc.zip"), Some(&hash));
+
+        let err = parse_checksum_manifest("deadbeef  gcc.tar.xz").unwrap_err();
+        assert!(matches!(err, BundleError::Manifest { line: 1, .. }));
+    }
+
+    #[test]
+    fn test_checksum_mismatch_installs_nothing() {
+        let temp = TempDir::new().unwrap();
+        let archive = temp.path().join("gcc.zip");
+        std::fs::write(&archive, zip(&[("bin/arm-none-eabi-gcc", b"", 0o755)])).unwrap();
+        let manifest = temp.path().join("SHA256SUMS");
+        std::fs::write(&manifest, format!("{}  gcc.zip\n", "0".repeat(64))).unwrap();
+
+        let install = temp.path().join("toolchains");
+        let err = install_toolchain_bundle(&archive, &manifest, &install).unwrap_err();
+        assert!(matches!(err, BundleError::ChecksumMismatch { .. }));
+        assert!(!install.exists());
+    }
+
+    #[test]
+    fn test_archive_missing_from_manifest() {
+        let temp = TempDir::new().unwrap();
+        let (archive, _) = write_bundle(temp.path(), "gcc.zip", &zip(&[]));
+        let manifest = temp.path().join("other.sha256");
+        std::fs::write(&manifest, format!("{}  other.zip\n", "0".repeat(64))).unwrap();
+
+        let err = verify_archive(&archive, &manifest).unwrap_err();
+        assert!(matches!(err, BundleError::NotInManifest(name) if name == "gcc.zip"));
+    }
+
+    #[test]
+    fn test_rejects_unsafe_zip_paths() {
+        let temp = TempDir::new().unwrap();
+        let (archive, manifest) =
+            write_bundle(temp.path(), "evil.zip", &zip(&[("../escape", b"x", 0o644)]));
+
+        let install = temp.path().join("toolchains");
+        let err = install_toolchain_bundle(&archive, &manifest, &install).unwrap_err();
+        assert!(matches!(err, BundleError::Extract(_)));
+        assert!(!temp.path().join("escape").exists());
+        assert!(installed_toolchains(&install).is_empty());
+    }
+
+    #[test]
+    fn test_missing_compiler() {
+        let temp = TempDir::new().unwrap();
+        let (archive, manifest) =
+            write_bundle(temp.path(), "docs.zip", &zip(&[("README", b"hi", 0o644)]));
+
+        let install = temp.path().join("toolchains");
+        let err = install_toolchain_bundle(&archive, &manifest, &install).unwrap_err();
+        assert!(matches!(err, BundleError::NoCompiler(_)));
+        assert!(std::fs::read_dir(&install).unwrap().next().is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_install_zip_bundle() {
+        let temp = TempDir::new().unwrap();
+        let gcc = b"#!/bin/sh\necho 'arm-none-eabi-gcc (Arm GNU Toolchain 13.2.rel1) 13.2.1'\n";
+        let data = zip(&[
+            ("arm-gnu-13.2/", b"", 0o755),
+            ("arm-gnu-13.2/bin/arm-none-eabi-gcc", gcc, 0o755),
+            ("arm-gnu-13.2/share/doc.txt", b"docs", 0o644),
+        ]);
+        let (archive, manifest) = write_bundle(temp.path(), "arm-gnu-13.2.zip", &data);
+
+        let install = temp.path().join("toolchains");
+        let installed = install_toolchain_bundle(&archive, &manifest, &install).unwrap();
+        assert_eq!(installed.name, "arm-gnu-13.2");
+        assert_eq!(installed.root, install.join("arm-gnu-13.2"));
+        assert_eq!(
+            installed.compiler,
+            installed.root.join("arm-gnu-13.2/bin/arm-none-eabi-gcc")
+        );
+        assert_eq!(installed.sha256, sha256_file(&archive).unwrap());
+
+        let detected = installed.detect().unwrap();
+        assert_eq!(detected.kind, ToolchainKind::ArmGcc);
+        assert_eq!(detected.version, "13.2.1");
+        assert!(detected.bundled);
+
+        assert_eq!(installed_toolchains(&install), vec![installed]);
+
+        let err = install_toolchain_bundle(&archive, &manifest, &install).unwrap_err();
+        assert!(matches!(err, BundleError::AlreadyInstalled(_)));
+    }
+}
@@ -0,0 +1,280 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Check-on-save compilation.
+//!
+//! Recompiles only the saved translation unit to a throwaway object using the
+//! flags captured from its last real compile. Saves within the debounce
+//! window supersede each other so only the latest one is compiled.
+
+use crate::invocation::parse_diagnostics;
+use crate::limits::{is_timeout, run_limited};
+use crate::{
+    build_arm_compile_command, build_command, normalize_diagnostics, ArmCompileRequest,
+    CompileRequest, CompileResult, DetectedToolchain, ToolchainKind,
+};
+use axiom_core::Diagnostic;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+/// Default debounce window in milliseconds.
+pub const DEFAULT_SAVE_DEBOUNCE_MS: u64 = 300;
+
+/// Effective compile flags for one translation unit.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CachedFlags {
+    /// Compiler binary.
+    pub compiler: PathBuf,
+    /// Toolchain kind.
+    pub kind: ToolchainKind,
+    /// Compiler version, for diagnostic normalization.
+    #[serde(default)]
+    pub version: String,
+    /// Flags, without the source, `-c`, and `-o <output>`.
+    pub flags: Vec<String>,
+}
+
+impl CachedFlags {
+    /// Capture the flags of a generic compile request.
+    pub fn from_compile(toolchain: &DetectedToolchain, request: &CompileRequest) -> Self {
+        let args = build_command(toolchain, request);
+        Self {
+            compiler: toolchain.path.clone(),
+            kind: toolchain.kind,
+            version: toolchain.version.clone(),
+            flags: strip_io_args(&args, &request.source, &request.output),
+        }
+    }
+
+    /// Capture the flags of an ARM compile request.
+    pub fn from_arm_compile(toolchain: &DetectedToolchain, request: &ArmCompileRequest) -> Self {
+        let args = build_arm_compile_command(request);
+        Self {
+            compiler: toolchain.path.clone(),
+            kind: toolchain.kind,
+            version: toolchain.version.clone(),
+            flags: strip_io_args(&args, &request.source, &request.output),
+        }
+    }
+}
+
+/// Remove `-c <source>` and `-o <output>` from an argument list.
+fn strip_io_args(args: &[String], source: &Path, output: &Path) -> Vec<String> {
+    let source = source.display().to_string();
+    let output = output.display().to_string();
+    let mut flags = Vec::with_capacity(args.len());
+    let mut iter = args.iter().peekable();
+
+    while let Some(arg) = iter.next() {
+        if arg == "-o" && iter.peek().map(|a| **a == output).unwrap_or(false) {
+            iter.next();
+        } else if arg != "-c" && *arg != source {
+            flags.push(arg.clone());
+        }
+    }
+
+    flags
+}
+
+/// Debounces saves and holds the flag cache.
+#[derive(Debug)]
+pub struct SaveChecker {
+    window: Duration,
+    flags: HashMap<PathBuf, CachedFlags>,
+    generations: HashMap<PathBuf, u64>,
+}
+
+impl SaveChecker {
+    /// Create a checker with a debounce window.
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            flags: HashMap::new(),
+            generations: HashMap::new(),
+        }
+    }
+
+    /// Debounce window.
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Remember the effective flags for a source file.
+    pub fn cache_flags(&mut self, source: PathBuf, flags: CachedFlags) {
+        self.flags.insert(source, flags);
+    }
+
+    /// Cached flags for a source file.
+    pub fn cached_flags(&self, source: &Path) -> Option<&CachedFlags> {
+        self.flags.get(source)
+    }
+
+    /// Record a save, returning a ticket for it.
+    pub fn notify_saved(&mut self, source: &Path) -> u64 {
+        let generation = self.generations.entry(source.to_path_buf()).or_insert(0);
+        *generation += 1;
+        *generation
+    }
+
+    /// Whether a ticket is still the latest save of its file.
+    pub fn is_current(&self, source: &Path, ticket: u64) -> bool {
+        self.generations.get(source) == Some(&ticket)
+    }
+}
+
+impl Default for SaveChecker {
+    fn default() -> Self {
+        Self::new(Duration::from_millis(DEFAULT_SAVE_DEBOUNCE_MS))
+    }
+}
+
+/// Compile one source to a throwaway object with cached flags.
+pub fn check_source(source: &Path, flags: &CachedFlags) -> CompileResult {
+    let object = throwaway_object_path(source);
+    // Flags go first so a cached `-x` applies to the source
+    let mut args = flags.flags.clone();
+    args.extend([
+        "-c".to_string(),
+        source.display().to_string(),
+        "-o".to_string(),
+        object.display().to_string(),
+    ]);
+
+    let start = Instant::now();
+    let output = run_limited(Command::new(&flags.compiler).args(&args));
+    let duration_ms = start.elapsed().as_millis() as u64;
+    let _ = std::fs::remove_file(&object);
+
+    match output {
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+            let mut diagnostics = parse_diagnostics(&stderr, flags.kind);
+            normalize_diagnostics(&mut diagnostics, flags.kind, &flags.version);
+            CompileResult {
+                exit_code: output.status.code().unwrap_or(-1),
+                stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+                diagnostics,
+                stderr,
+                duration_ms,
+                timed_out: false,
+                warning_profile: None,
+                invocation: None,
+            }
+        }
+        Err(e) => CompileResult {
+            exit_code: -1,
+            stdout: String::new(),
+            stderr: e.to_string(),
+            duration_ms,
+            timed_out: is_timeout(&e),
+            diagnostics: vec![Diagnostic::error(e.to_string())],
+            warning_profile: None,
+            invocation: None,
+        },
+    }
+}
+
+/// Temporary object path unique to a source file.
+fn throwaway_object_path(source: &Path) -> PathBuf {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    source.hash(&mut hasher);
+    std::env::temp_dir().join(format!(
+        "axiom-check-{}-{:016x}.o",
+        std::process::id(),
+        hasher.finish()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ArmMcuConfig, OptimizationLevel};
+
+    #[test]
+    fn test_flags_from_compile() {
+        let tc = DetectedToolchain::new(
+            ToolchainKind::Clang,
+            PathBuf::from("/usr/bin/clang"),
+            "15.0.0".to_string(),
+        );
+        let request = CompileRequest::new(PathBuf::from("main.c"), PathBuf::from("main.o"))
+            .with_optimization(2)
+            .with_flag("-DNDEBUG");
+
+        let cached = CachedFlags::from_compile(&tc, &request);
+        assert_eq!(cached.compiler, PathBuf::from("/usr/bin/clang"));
+        assert!(cached.flags.contains(&"-O2".to_string()));
+        assert!(cached.flags.contains(&"-DNDEBUG".to_string()));
+        assert!(!cached.flags.iter().any(|f| f == "-c" || f == "-o"));
+        assert!(!cached.flags.iter().any(|f| f == "main.c" || f == "main.o"));
+    }
+
+    #[test]
+    fn test_flags_from_arm_compile() {
+        let tc = DetectedToolchain::new(
+            ToolchainKind::ArmGcc,
+            PathBuf::from("arm-none-eabi-gcc"),
+            "13.2.1".to_string(),
+        );
+        let request = ArmCompileRequest::new(
+            PathBuf::from("src/main.c"),
+            PathBuf::from("build/main.o"),
+            ArmMcuConfig::new("cortex-m0plus"),
+        )
+        .with_optimization(OptimizationLevel::Os);
+
+        let cached = CachedFlags::from_arm_compile(&tc, &request);
+        assert_eq!(cached.kind, ToolchainKind::ArmGcc);
+        assert_eq!(cached.flags[0], "-mcpu=cortex-m0plus");
+        assert!(cached.flags.contains(&"-Os".to_string()));
+        assert!(!cached.flags.contains(&"build/main.o".to_string()));
+    }
+
+    #[test]
+    fn test_debounce_tickets() {
+        let mut checker = SaveChecker::default();
+        let path = Path::new("main.c");
+
+        let first = checker.notify_saved(path);
+        let second = checker.notify_saved(path);
+        assert!(!checker.is_current(path, first));
+        assert!(checker.is_current(path, second));
+
+        let other = checker.notify_saved(Path::new("uart.c"));
+        assert!(checker.is_current(Path::new("uart.c"), other));
+        assert!(checker.is_current(path, second));
+    }
+
+    #[test]
+    fn test_flag_cache() {
+        let mut checker = SaveChecker::new(Duration::from_millis(50));
+        assert_eq!(checker.window(), Duration::from_millis(50));
+        assert!(checker.cached_flags(Path::new("main.c")).is_none());
+
+        let flags = CachedFlags {
+            compiler: PathBuf::from("gcc"),
+            kind: ToolchainKind::Gcc,
+            version: "12.2.0".to_string(),
+            flags: vec!["-O0".to_string()],
+        };
+        checker.cache_flags(PathBuf::from("main.c"), flags.clone());
+        assert_eq!(checker.cached_flags(Path::new("main.c")), Some(&flags));
+    }
+
+    #[test]
+    fn test_check_source_missing_compiler() {
+        let flags = CachedFlags {
+            compiler: PathBuf::from("/nonexistent/cc"),
+            kind: ToolchainKind::Gcc,
+            version: "12.2.0".to_string(),
+            flags: Vec::new(),
+        };
+        let result = check_source(Path::new("main.c"), &flags);
+        assert!(!result.success());
+        assert_eq!(result.diagnostics.len(), 1);
+    }
+}
@@ -0,0 +1,247 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Toolchains run inside a Docker or Podman image.
+//!
+//! The workspace is bind-mounted at the same path inside the container, so
+//! request paths and diagnostics need no rewriting, and networking is off
+//! unless asked for. Builds then depend only on the image, not on whatever
+//! is installed on the host.
+
+use crate::detection::parse_version;
+use crate::limits::run_limited;
+use crate::{DetectedToolchain, ToolchainKind, ToolchainSource};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Container engine used to run the image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ContainerEngine {
+    Docker,
+    Podman,
+}
+
+impl ContainerEngine {
+    /// Engine executable.
+    pub fn program(&self) -> &'static str {
+        match self {
+            ContainerEngine::Docker => "docker",
+            ContainerEngine::Podman => "podman",
+        }
+    }
+
+    /// Whether the engine's CLI responds.
+    pub fn is_available(&self) -> bool {
+        run_limited(Command::new(self.program()).arg("--version"))
+            .map(|output| output.status.success())
+            .unwrap_or(false)
+    }
+}
+
+impl std::fmt::Display for ContainerEngine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ContainerEngine::Docker => write!(f, "Docker"),
+            ContainerEngine::Podman => write!(f, "Podman"),
+        }
+    }
+}
+
+/// First installed container engine, preferring Podman.
+pub fn find_container_engine() -> Option<ContainerEngine> {
+    [ContainerEngine::Podman, ContainerEngine::Docker]
+        .into_iter()
+        .find(ContainerEngine::is_available)
+}
+
+/// How to run tools inside a container image.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContainerConfig {
+    /// Container engine.
+    pub engine: ContainerEngine,
+    /// Image reference; pin a digest (`image@sha256:...`) for reproducible
+    /// builds.
+    pub image: String,
+    /// Host directory mounted read-write at the same path.
+    pub workspace: PathBuf,
+    /// Allow network access from the container.
+    #[serde(default)]
+    pub network: bool,
+}
+
+impl ContainerConfig {
+    /// Run tools from `image` with `workspace` mounted and no network.
+    pub fn new(engine: ContainerEngine, image: impl Into<String>, workspace: PathBuf) -> Self {
+        Self {
+            engine,
+            image: image.into(),
+            workspace,
+            network: false,
+        }
+    }
+
+    /// Set whether the container has network access.
+    pub fn with_network(mut self, network: bool) -> Self {
+        self.network = network;
+        self
+    }
+
+    /// Engine arguments running `program` with `args` in the image.
+    ///
+    /// The working directory carries over when it lies inside the
+    /// workspace; otherwise the workspace root is used.
+    pub fn run_args(&self, program: &Path, args: &[String]) -> Vec<String> {
+        let workspace = self.workspace.display().to_string();
+        let workdir = std::env::current_dir()
+            .ok()
+            .filter(|dir| dir.starts_with(&self.workspace))
+            .unwrap_or_else(|| self.workspace.clone());
+
+        let mut run = vec![
+            "run".to_string(),
+            "--rm".to_string(),
+            "-i".to_string(),
+            "-v".to_string(),
+            format!("{}:{}", workspace, workspace),
+            "-w".to_string(),
+            workdir.display().to_string(),
+        ];
+        if !self.network {
+            run.push("--network=none".to_string());
+        }
+        // Docker runs as root by default; keep outputs owned by whoever
+        // owns the workspace. Rootless Podman already maps to the caller.
+        if let (ContainerEngine::Docker, Some(user)) =
+            (self.engine, workspace_owner(&self.workspace))
+        {
+            run.push("--user".to_string());
+            run.push(user);
+        }
+        run.push(self.image.clone());
+        run.push(program.display().to_string());
+        run.extend(args.iter().cloned());
+        run
+    }
+
+    /// Command running `program` with `args` in the image.
+    pub fn command(&self, program: &Path, args: &[String]) -> Command {
+        let mut command = Command::new(self.engine.program());
+        command.args(self.run_args(program, args));
+        command
+    }
+}
+
+#[cfg(unix)]
+fn workspace_owner(workspace: &Path) -> Option<String> {
+    use std::os::unix::fs::MetadataExt;
+    let metadata = std::fs::metadata(workspace).ok()?;
+    Some(format!("{}:{}", metadata.uid(), metadata.gid()))
+}
+
+#[cfg(not(unix))]
+fn workspace_owner(_workspace: &Path) -> Option<String> {
+    None
+}
+
+/// Detect a toolchain at `path` inside a container image.
+pub fn detect_in_container(
+    config: &ContainerConfig,
+    path: &Path,
+    kind: ToolchainKind,
+) -> Option<DetectedToolchain> {
+    let output = run_limited(&mut config.command(path, &["--version".to_string()])).ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let version = parse_version(&String::from_utf8_lossy(&output.stdout), kind)?;
+    Some(
+        DetectedToolchain::new(kind, path.to_path_buf(), version)
+            .with_source(ToolchainSource::Container(config.clone())),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_run_args() {
+        let temp = TempDir::new().unwrap();
+        let workspace = temp.path().to_path_buf();
+        let config = ContainerConfig::new(
+            ContainerEngine::Podman,
+            "ghcr.io/acme/arm-gcc@sha256:abc",
+            workspace.clone(),
+        );
+        let ws = workspace.display().to_string();
+
+        let args = config.run_args(
+            Path::new("/opt/gcc/bin/arm-none-eabi-gcc"),
+            &["-c".to_string(), format!("{}/main.c", ws)],
+        );
+        assert_eq!(
+            args,
+            vec![
+                "run".to_string(),
+                "--rm".to_string(),
+                "-i".to_string(),
+                "-v".to_string(),
+                format!("{}:{}", ws, ws),
+                "-w".to_string(),
+                ws.clone(),
+                "--network=none".to_string(),
+                "ghcr.io/acme/arm-gcc@sha256:abc".to_string(),
+                "/opt/gcc/bin/arm-none-eabi-gcc".to_string(),
+                "-c".to_string(),
+                format!("{}/main.c", ws),
+            ]
+        );
+
+        let networked = config.with_network(true).run_args(Path::new("gcc"), &[]);
+        assert!(!networked.contains(&"--network=none".to_string()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_docker_runs_as_workspace_owner() {
+        let temp = TempDir::new().unwrap();
+        let config = ContainerConfig::new(
+            ContainerEngine::Docker,
+            "arm-gcc:13",
+            temp.path().to_path_buf(),
+        );
+        let args = config.run_args(Path::new("gcc"), &[]);
+        let user = args.iter().position(|a| a == "--user").unwrap();
+        assert_eq!(args[user + 1], workspace_owner(temp.path()).unwrap());
+        assert_eq!(args[user + 2], "arm-gcc:13");
+    }
+
+    #[test]
+    fn test_container_source_round_trips() {
+        let config = ContainerConfig::new(
+            ContainerEngine::Docker,
+            "arm-gcc:13",
+            PathBuf::from("/work"),
+        );
+        let toolchain = DetectedToolchain::new(
+            ToolchainKind::ArmGcc,
+            PathBuf::from("/opt/gcc/bin/arm-none-eabi-gcc"),
+            "13.2.1".to_string(),
+        )
+        .with_source(ToolchainSource::Container(config));
+
+        let json = serde_json::to_string(&toolchain).unwrap();
+        assert!(json.contains("\"type\":\"container\""));
+        let parsed: DetectedToolchain = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, toolchain);
+
+        let local: DetectedToolchain = serde_json::from_str(
+            r#"{"kind":"Gcc","path":"/usr/bin/gcc","version":"12","bundled":false}"#,
+        )
+        .unwrap();
+        assert_eq!(local.source, ToolchainSource::Local);
+    }
+}
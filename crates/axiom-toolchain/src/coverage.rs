@@ -0,0 +1,1224 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Structural coverage from gcov.
+//!
+//! Three inputs are understood:
+//!
+//! - gcov's text format (`foo.c.gcov`, or `gcov -t` output), best produced
+//!   with `-b -c` so branch and call lines carry counts rather than
+//!   percentages;
+//! - gcov's JSON intermediate format (`gcov --json-format`, gzipped as
+//!   `foo.gcda.gcov.json.gz`, or plain with `-t`);
+//! - gcovr's JSON report (`gcovr --json`);
+//! - lcov tracefiles (`.info`), as written by `lcov --capture` and many
+//!   existing test harnesses.
+//!
+//! All of them produce [`FileCoverage`] records; [`generate_coverage_report`]
+//! merges records for the same source across inputs and attaches the
+//! project's coverage justifications, so totals tell justified gaps from
+//! unresolved ones.
+
+use crate::CoverageJustification;
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Coverage errors.
+#[derive(Debug, Error)]
+pub enum CoverageError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("JSON parse error in {path}: {source}")]
+    Json {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+
+    #[error("Unrecognized coverage file: {0}")]
+    UnknownFormat(PathBuf),
+}
+
+/// Coverage input format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CoverageFormat {
+    /// gcov's `.gcov` text format.
+    GcovText,
+    /// `gcov --json-format`.
+    GcovJson,
+    /// `gcovr --json`.
+    GcovrJson,
+    /// lcov tracefile.
+    Lcov,
+}
+
+impl CoverageFormat {
+    /// Guess the format of a coverage file from its contents.
+    pub fn detect(content: &str) -> Option<Self> {
+        let start = content.trim_start();
+        if start.starts_with('{') {
+            if start.contains("\"gcovr/format_version\"") {
+                Some(CoverageFormat::GcovrJson)
+            } else if start.contains("\"gcc_version\"") {
+                Some(CoverageFormat::GcovJson)
+            } else {
+                None
+            }
+        } else if start
+            .lines()
+            .next()
+            .is_some_and(|line| line.contains(":    0:Source:") || line.contains(":0:Source:"))
+        {
+            Some(CoverageFormat::GcovText)
+        } else if start.starts_with("TN:") || start.starts_with("SF:") {
+            Some(CoverageFormat::Lcov)
+        } else {
+            None
+        }
+    }
+}
+
+/// Execution count of one source line.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LineCoverage {
+    /// 1-based line number.
+    pub line: u32,
+    /// Times the line was executed.
+    pub count: u64,
+    /// Some basic block on the line was never executed, although the line
+    /// was (gcov's `*` marker).
+    #[serde(default)]
+    pub unexecuted_block: bool,
+}
+
+/// One outcome of a conditional branch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BranchCoverage {
+    /// Line of the branch.
+    pub line: u32,
+    /// Branch number on the line, in gcov order.
+    pub index: u32,
+    /// Times this outcome was taken.
+    pub count: u64,
+    /// Whether the branch was reached at all.
+    pub executed: bool,
+    /// Fall-through edge rather than a jump.
+    #[serde(default)]
+    pub fallthrough: bool,
+    /// Exception edge.
+    #[serde(default)]
+    pub throw: bool,
+}
+
+impl BranchCoverage {
+    /// Whether the outcome was taken at least once.
+    pub fn is_covered(&self) -> bool {
+        self.count > 0
+    }
+}
+
+/// A call site.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallCoverage {
+    /// Line of the call.
+    pub line: u32,
+    /// Call number on the line, in gcov order.
+    pub index: u32,
+    /// Whether the call was made at all.
+    pub executed: bool,
+    /// Times the call returned.
+    pub returned: u64,
+}
+
+/// Execution summary of one function.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FunctionCoverage {
+    /// Symbol name (mangled for C++).
+    pub name: String,
+    /// Demangled name, when it differs.
+    #[serde(default)]
+    pub demangled_name: Option<String>,
+    /// First line.
+    pub start_line: u32,
+    /// Last line, when known.
+    #[serde(default)]
+    pub end_line: Option<u32>,
+    /// Times the function was called.
+    pub execution_count: u64,
+    /// Basic blocks, when known.
+    #[serde(default)]
+    pub blocks: Option<u32>,
+    /// Basic blocks executed, when known.
+    #[serde(default)]
+    pub blocks_executed: Option<u32>,
+}
+
+/// Covered and total counts for one coverage measure.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoverageCounter {
+    /// Items executed at least once.
+    pub covered: usize,
+    /// All items.
+    pub total: usize,
+    /// Items never executed but resolved by a justification.
+    #[serde(default)]
+    pub justified: usize,
+}
+
+impl CoverageCounter {
+    fn count<T>(items: &[T], covered: impl Fn(&T) -> bool, justified: impl Fn(&T) -> bool) -> Self {
+        Self {
+            covered: items.iter().filter(|item| covered(item)).count(),
+            total: items.len(),
+            justified: items
+                .iter()
+                .filter(|item| !covered(item) && justified(item))
+                .count(),
+        }
+    }
+
+    /// Items neither executed nor justified.
+    pub fn unresolved(&self) -> usize {
+        self.total - self.covered - self.justified
+    }
+
+    /// Covered items as a percentage; 100 when there are none.
+    pub fn percent(&self) -> f64 {
+        if self.total == 0 {
+            100.0
+        } else {
+            self.covered as f64 * 100.0 / self.total as f64
+        }
+    }
+}
+
+impl std::ops::Add for CoverageCounter {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            covered: self.covered + other.covered,
+            total: self.total + other.total,
+            justified: self.justified + other.justified,
+        }
+    }
+}
+
+/// Line, branch, call and function totals.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoverageSummary {
+    /// Executable lines.
+    pub lines: CoverageCounter,
+    /// Branch outcomes.
+    pub branches: CoverageCounter,
+    /// Call sites that returned.
+    pub calls: CoverageCounter,
+    /// Functions called.
+    pub functions: CoverageCounter,
+}
+
+impl std::ops::Add for CoverageSummary {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            lines: self.lines + other.lines,
+            branches: self.branches + other.branches,
+            calls: self.calls + other.calls,
+            functions: self.functions + other.functions,
+        }
+    }
+}
+
+/// Coverage of one source file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileCoverage {
+    /// Source path as recorded by the compiler.
+    pub path: PathBuf,
+    /// Executable lines, by line number.
+    pub lines: Vec<LineCoverage>,
+    /// Branch outcomes, by line and index.
+    #[serde(default)]
+    pub branches: Vec<BranchCoverage>,
+    /// Call sites, by line and index.
+    #[serde(default)]
+    pub calls: Vec<CallCoverage>,
+    /// Functions, by start line.
+    #[serde(default)]
+    pub functions: Vec<FunctionCoverage>,
+    /// Justifications of this file's coverage gaps.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub justifications: Vec<CoverageJustification>,
+}
+
+impl FileCoverage {
+    /// Empty coverage for a source.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            lines: Vec::new(),
+            branches: Vec::new(),
+            calls: Vec::new(),
+            functions: Vec::new(),
+            justifications: Vec::new(),
+        }
+    }
+
+    /// The justification covering a line, if any.
+    pub fn line_justification(&self, line: u32) -> Option<&CoverageJustification> {
+        self.justifications.iter().find(|j| j.covers_line(line))
+    }
+
+    fn justifies_branch(&self, line: u32, index: u32) -> bool {
+        self.justifications
+            .iter()
+            .any(|j| j.covers_branch(line, index))
+    }
+
+    /// Totals for this file.
+    pub fn summary(&self) -> CoverageSummary {
+        self.summary_where(|_| true)
+    }
+
+    /// Totals for lines `start..=end`, such as one function's body.
+    pub fn range_summary(&self, start: u32, end: u32) -> CoverageSummary {
+        self.summary_where(|line| (start..=end).contains(&line))
+    }
+
+    fn summary_where(&self, in_scope: impl Fn(u32) -> bool) -> CoverageSummary {
+        let line_justified = |line| self.line_justification(line).is_some();
+        let lines: Vec<_> = self.lines.iter().filter(|l| in_scope(l.line)).collect();
+        let branches: Vec<_> = self.branches.iter().filter(|b| in_scope(b.line)).collect();
+        let calls: Vec<_> = self.calls.iter().filter(|c| in_scope(c.line)).collect();
+        let functions: Vec<_> = self
+            .functions
+            .iter()
+            .filter(|f| in_scope(f.start_line))
+            .collect();
+        CoverageSummary {
+            lines: CoverageCounter::count(&lines, |l| l.count > 0, |l| line_justified(l.line)),
+            branches: CoverageCounter::count(
+                &branches,
+                |b| b.is_covered(),
+                |b| self.justifies_branch(b.line, b.index),
+            ),
+            calls: CoverageCounter::count(&calls, |c| c.returned > 0, |c| line_justified(c.line)),
+            functions: CoverageCounter::count(
+                &functions,
+                |f| f.execution_count > 0,
+                |f| line_justified(f.start_line),
+            ),
+        }
+    }
+
+    /// Lines never executed.
+    pub fn uncovered_lines(&self) -> Vec<u32> {
+        self.lines
+            .iter()
+            .filter(|l| l.count == 0)
+            .map(|l| l.line)
+            .collect()
+    }
+
+    /// Lines never executed and not justified.
+    pub fn unresolved_lines(&self) -> Vec<u32> {
+        self.uncovered_lines()
+            .into_iter()
+            .filter(|&line| self.line_justification(line).is_none())
+            .collect()
+    }
+
+    /// Add another record for the same source, summing counts.
+    pub fn merge(&mut self, other: FileCoverage) {
+        let mut lines: BTreeMap<u32, LineCoverage> =
+            self.lines.drain(..).map(|l| (l.line, l)).collect();
+        for line in other.lines {
+            lines
+                .entry(line.line)
+                .and_modify(|existing| {
+                    existing.count += line.count;
+                    existing.unexecuted_block &= line.unexecuted_block;
+                })
+                .or_insert(line);
+        }
+        self.lines = lines.into_values().collect();
+
+        let mut branches: BTreeMap<(u32, u32), BranchCoverage> = self
+            .branches
+            .drain(..)
+            .map(|b| ((b.line, b.index), b))
+            .collect();
+        for branch in other.branches {
+            branches
+                .entry((branch.line, branch.index))
+                .and_modify(|existing| {
+                    existing.count += branch.count;
+                    existing.executed |= branch.executed;
+                })
+                .or_insert(branch);
+        }
+        self.branches = branches.into_values().collect();
+
+        let mut calls: BTreeMap<(u32, u32), CallCoverage> = self
+            .calls
+            .drain(..)
+            .map(|c| ((c.line, c.index), c))
+            .collect();
+        for call in other.calls {
+            calls
+                .entry((call.line, call.index))
+                .and_modify(|existing| {
+                    existing.returned += call.returned;
+                    existing.executed |= call.executed;
+                })
+                .or_insert(call);
+        }
+        self.calls = calls.into_values().collect();
+
+        for function in other.functions {
+            match self.functions.iter_mut().find(|f| f.name == function.name) {
+                Some(existing) => {
+                    existing.execution_count += function.execution_count;
+                    existing.blocks_executed =
+                        existing.blocks_executed.max(function.blocks_executed);
+                }
+                None => self.functions.push(function),
+            }
+        }
+        self.functions.sort_by_key(|f| f.start_line);
+
+        for justification in other.justifications {
+            if !self.justifications.contains(&justification) {
+                self.justifications.push(justification);
+            }
+        }
+    }
+
+    fn sort(&mut self) {
+        self.lines.sort_by_key(|l| l.line);
+        self.branches.sort_by_key(|b| (b.line, b.index));
+        self.calls.sort_by_key(|c| (c.line, c.index));
+        self.functions.sort_by_key(|f| f.start_line);
+    }
+}
+
+/// Coverage across sources.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoverageReport {
+    /// Files, by path.
+    pub files: Vec<FileCoverage>,
+    /// Totals across files.
+    pub summary: CoverageSummary,
+}
+
+impl CoverageReport {
+    /// Build a report, merging records for the same path.
+    pub fn from_files(files: impl IntoIterator<Item = FileCoverage>) -> Self {
+        let mut by_path: BTreeMap<PathBuf, FileCoverage> = BTreeMap::new();
+        for mut file in files {
+            file.sort();
+            match by_path.get_mut(&file.path) {
+                Some(existing) => existing.merge(file),
+                None => {
+                    by_path.insert(file.path.clone(), file);
+                }
+            }
+        }
+        let files: Vec<FileCoverage> = by_path.into_values().collect();
+        let summary = files
+            .iter()
+            .map(FileCoverage::summary)
+            .fold(CoverageSummary::default(), |a, b| a + b);
+        Self { files, summary }
+    }
+
+    /// Coverage of one source.
+    pub fn file(&self, path: &Path) -> Option<&FileCoverage> {
+        self.files.iter().find(|f| f.path == path)
+    }
+
+    /// Attach justifications to the files they apply to and recompute the
+    /// totals.
+    pub fn with_justifications(mut self, justifications: &[CoverageJustification]) -> Self {
+        for file in &mut self.files {
+            for justification in justifications {
+                if justification.applies_to(&file.path)
+                    && !file.justifications.contains(justification)
+                {
+                    file.justifications.push(justification.clone());
+                }
+            }
+        }
+        self.summary = self
+            .files
+            .iter()
+            .map(FileCoverage::summary)
+            .fold(CoverageSummary::default(), |a, b| a + b);
+        self
+    }
+}
+
+/// Parse gcov's text format. `gcov -t` output holding several sources
+/// gives one record per source.
+///
+/// Without `-c`, gcov prints branch and call results as percentages; a
+/// nonzero percentage is taken as one execution.
+pub fn parse_gcov_output(content: &str) -> Vec<FileCoverage> {
+    let mut files = Vec::new();
+    let mut current: Option<FileCoverage> = None;
+    let mut pending_function: Option<FunctionCoverage> = None;
+    let mut last_line = 0;
+    let mut branch_index = 0;
+    let mut call_index = 0;
+
+    for raw in content.lines() {
+        let trimmed = raw.trim_start();
+
+        if let Some(rest) = trimmed.strip_prefix("function ") {
+            pending_function = parse_function_line(rest);
+            continue;
+        }
+        if let Some(rest) = trimmed.strip_prefix("branch ") {
+            if let Some(file) = current.as_mut() {
+                let (executed, count) = parse_outcome(rest, "taken");
+                file.branches.push(BranchCoverage {
+                    line: last_line,
+                    index: branch_index,
+                    count,
+                    executed,
+                    fallthrough: rest.contains("(fallthrough)"),
+                    throw: rest.contains("(throw)"),
+                });
+                branch_index += 1;
+            }
+            continue;
+        }
+        if let Some(rest) = trimmed.strip_prefix("call ") {
+            if let Some(file) = current.as_mut() {
+                let (executed, returned) = parse_outcome(rest, "returned");
+                file.calls.push(CallCoverage {
+                    line: last_line,
+                    index: call_index,
+                    executed,
+                    returned,
+                });
+                call_index += 1;
+            }
+            continue;
+        }
+
+        // count:line:source
+        let mut parts = raw.splitn(3, ':');
+        let (Some(count), Some(line), Some(text)) = (parts.next(), parts.next(), parts.next())
+        else {
+            continue;
+        };
+        let Ok(line) = line.trim().parse::<u32>() else {
+            continue;
+        };
+        let count = count.trim();
+
+        if line == 0 {
+            if let Some(source) = text.strip_prefix("Source:") {
+                files.extend(current.take());
+                current = Some(FileCoverage::new(source.trim()));
+            }
+            continue;
+        }
+        let Some(file) = current.as_mut() else {
+            continue;
+        };
+
+        last_line = line;
+        branch_index = 0;
+        call_index = 0;
+        if let Some(mut function) = pending_function.take() {
+            function.start_line = line;
+            file.functions.push(function);
+        }
+        if count == "-" {
+            continue;
+        }
+        let unexecuted_block = count.ends_with('*');
+        let count = match count.trim_end_matches('*') {
+            "#####" | "=====" => 0,
+            n => match n.parse() {
+                Ok(n) => n,
+                Err(_) => continue,
+            },
+        };
+        file.lines.push(LineCoverage {
+            line,
+            count,
+            unexecuted_block,
+        });
+    }
+    files.extend(current);
+    files
+}
+
+/// `NAME called N returned X% blocks executed Y%`
+fn parse_function_line(rest: &str) -> Option<FunctionCoverage> {
+    let mut words = rest.split_whitespace();
+    let name = words.next()?.to_string();
+    let mut execution_count = 0;
+    while let Some(word) = words.next() {
+        if word == "called" {
+            execution_count = words.next()?.parse().ok()?;
+        }
+    }
+    Some(FunctionCoverage {
+        name,
+        demangled_name: None,
+        start_line: 0,
+        end_line: None,
+        execution_count,
+        blocks: None,
+        blocks_executed: None,
+    })
+}
+
+/// `0 taken 2 (fallthrough)`, `1 never executed`, `0 returned 100%`.
+fn parse_outcome(rest: &str, verb: &str) -> (bool, u64) {
+    if rest.contains("never executed") {
+        return (false, 0);
+    }
+    let value = rest
+        .split_whitespace()
+        .skip_while(|w| *w != verb)
+        .nth(1)
+        .unwrap_or("0");
+    let count = match value.strip_suffix('%') {
+        Some(percent) => u64::from(percent.parse::<f64>().is_ok_and(|p| p > 0.0)),
+        None => value.parse().unwrap_or(0),
+    };
+    (true, count)
+}
+
+#[derive(Deserialize)]
+struct GcovJson {
+    #[serde(default)]
+    files: Vec<GcovJsonFile>,
+}
+
+#[derive(Deserialize)]
+struct GcovJsonFile {
+    file: PathBuf,
+    #[serde(default)]
+    functions: Vec<GcovJsonFunction>,
+    #[serde(default)]
+    lines: Vec<GcovJsonLine>,
+}
+
+#[derive(Deserialize)]
+struct GcovJsonFunction {
+    name: String,
+    #[serde(default)]
+    demangled_name: Option<String>,
+    #[serde(alias = "lineno")]
+    start_line: u32,
+    #[serde(default)]
+    end_line: Option<u32>,
+    #[serde(default)]
+    execution_count: u64,
+    #[serde(default)]
+    blocks: Option<u32>,
+    #[serde(default)]
+    blocks_executed: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct GcovJsonLine {
+    line_number: u32,
+    #[serde(default)]
+    count: u64,
+    #[serde(default)]
+    unexecuted_block: bool,
+    #[serde(default)]
+    branches: Vec<GcovJsonBranch>,
+    #[serde(default)]
+    calls: Vec<GcovJsonCall>,
+    #[serde(default, rename = "gcovr/noncode")]
+    noncode: bool,
+    #[serde(default, rename = "gcovr/excluded")]
+    excluded: bool,
+}
+
+#[derive(Deserialize)]
+struct GcovJsonBranch {
+    #[serde(default)]
+    count: u64,
+    #[serde(default)]
+    fallthrough: bool,
+    #[serde(default)]
+    throw: bool,
+}
+
+#[derive(Deserialize)]
+struct GcovJsonCall {
+    #[serde(default)]
+    returned: u64,
+}
+
+impl From<GcovJsonFile> for FileCoverage {
+    fn from(json: GcovJsonFile) -> Self {
+        let mut file = FileCoverage::new(json.file);
+        // gcov lists a line once per function instantiated on it
+        let mut lines: BTreeMap<u32, GcovJsonLine> = BTreeMap::new();
+        for line in json.lines.into_iter().filter(|l| !l.noncode && !l.excluded) {
+            match lines.get_mut(&line.line_number) {
+                Some(existing) => {
+                    existing.count += line.count;
+                    existing.unexecuted_block |= line.unexecuted_block;
+                    existing.branches.extend(line.branches);
+                    existing.calls.extend(line.calls);
+                }
+                None => {
+                    lines.insert(line.line_number, line);
+                }
+            }
+        }
+
+        for (number, line) in lines {
+            let executed = line.count > 0;
+            file.branches.extend(
+                line.branches
+                    .iter()
+                    .zip(0..)
+                    .map(|(b, index)| BranchCoverage {
+                        line: number,
+                        index,
+                        count: b.count,
+                        executed,
+                        fallthrough: b.fallthrough,
+                        throw: b.throw,
+                    }),
+            );
+            file.calls
+                .extend(line.calls.iter().zip(0..).map(|(c, index)| CallCoverage {
+                    line: number,
+                    index,
+                    executed,
+                    returned: c.returned,
+                }));
+            file.lines.push(LineCoverage {
+                line: number,
+                count: line.count,
+                unexecuted_block: line.unexecuted_block,
+            });
+        }
+
+        file.functions = json
+            .functions
+            .into_iter()
+            .map(|f| FunctionCoverage {
+                demangled_name: f.demangled_name.filter(|d| *d != f.name),
+                name: f.name,
+                start_line: f.start_line,
+                end_line: f.end_line,
+                execution_count: f.execution_count,
+                blocks: f.blocks,
+                blocks_executed: f.blocks_executed,
+            })
+            .collect();
+        file.sort();
+        file
+    }
+}
+
+/// Parse `gcov --json-format` output (uncompressed).
+pub fn parse_gcov_json(content: &str) -> Result<Vec<FileCoverage>, serde_json::Error> {
+    // `gcov -t --json-format` writes one document per line
+    let mut files = Vec::new();
+    for document in serde_json::Deserializer::from_str(content).into_iter::<GcovJson>() {
+        files.extend(document?.files.into_iter().map(FileCoverage::from));
+    }
+    Ok(files)
+}
+
+/// Parse a `gcovr --json` report.
+pub fn parse_gcovr_json(content: &str) -> Result<Vec<FileCoverage>, serde_json::Error> {
+    let report: GcovJson = serde_json::from_str(content)?;
+    Ok(report.files.into_iter().map(FileCoverage::from).collect())
+}
+
+/// Parse an lcov tracefile. Each `SF:` record gives one [`FileCoverage`];
+/// lcov has no call data, so `calls` stays empty.
+pub fn parse_lcov(content: &str) -> Vec<FileCoverage> {
+    let mut files = Vec::new();
+    let mut current: Option<FileCoverage> = None;
+
+    for line in content.lines().map(str::trim) {
+        if line == "end_of_record" {
+            files.extend(current.take().map(|mut file| {
+                file.sort();
+                file
+            }));
+            continue;
+        }
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        if key == "SF" {
+            files.extend(current.take());
+            current = Some(FileCoverage::new(value));
+            continue;
+        }
+        let Some(file) = current.as_mut() else {
+            continue;
+        };
+        let fields: Vec<&str> = value.split(',').collect();
+
+        match (key, fields.as_slice()) {
+            // DA:<line>,<count>[,<checksum>]
+            ("DA", [line, count, ..]) => {
+                if let (Ok(line), Ok(count)) = (line.parse(), count.parse()) {
+                    file.lines.push(LineCoverage {
+                        line,
+                        count,
+                        unexecuted_block: false,
+                    });
+                }
+            }
+            // BRDA:<line>,[<exception>]<block>,<branch>,<taken or ->
+            ("BRDA", [line, block, _, taken]) => {
+                let Ok(line) = line.parse() else {
+                    continue;
+                };
+                let index = file.branches.iter().filter(|b| b.line == line).count() as u32;
+                file.branches.push(BranchCoverage {
+                    line,
+                    index,
+                    count: taken.parse().unwrap_or(0),
+                    executed: *taken != "-",
+                    fallthrough: false,
+                    throw: block.starts_with('e'),
+                });
+            }
+            // FN:<line>,<name> or FN:<line>,<end line>,<name>
+            ("FN", [line, name]) | ("FN", [line, _, name]) => {
+                if let Ok(start_line) = line.parse() {
+                    let end_line = match fields.as_slice() {
+                        [_, end, _] => end.parse().ok(),
+                        _ => None,
+                    };
+                    file.functions.push(FunctionCoverage {
+                        name: name.to_string(),
+                        demangled_name: None,
+                        start_line,
+                        end_line,
+                        execution_count: 0,
+                        blocks: None,
+                        blocks_executed: None,
+                    });
+                }
+            }
+            // FNDA:<count>,<name>
+            ("FNDA", [count, name]) => {
+                let count: u64 = count.parse().unwrap_or(0);
+                if let Some(function) = file.functions.iter_mut().find(|f| f.name == *name) {
+                    function.execution_count += count;
+                }
+            }
+            _ => {}
+        }
+    }
+    files.extend(current);
+    files
+}
+
+/// Read one coverage file, decompressing `.gz` files.
+pub fn read_coverage_file(
+    path: &Path,
+    format: Option<CoverageFormat>,
+) -> Result<Vec<FileCoverage>, CoverageError> {
+    let data = std::fs::read(path)?;
+    let content = if path.extension().is_some_and(|e| e == "gz") {
+        let mut content = String::new();
+        GzDecoder::new(&data[..]).read_to_string(&mut content)?;
+        content
+    } else {
+        String::from_utf8_lossy(&data).into_owned()
+    };
+
+    let format = format
+        .or_else(|| CoverageFormat::detect(&content))
+        .ok_or_else(|| CoverageError::UnknownFormat(path.to_path_buf()))?;
+    let json_error = |source| CoverageError::Json {
+        path: path.to_path_buf(),
+        source,
+    };
+    match format {
+        CoverageFormat::GcovText => Ok(parse_gcov_output(&content)),
+        CoverageFormat::GcovJson => parse_gcov_json(&content).map_err(json_error),
+        CoverageFormat::GcovrJson => parse_gcovr_json(&content).map_err(json_error),
+        CoverageFormat::Lcov => Ok(parse_lcov(&content)),
+    }
+}
+
+/// Build a coverage report from gcov text, gcov JSON, gcovr JSON or lcov
+/// files, detecting each file's format unless one is given. Uncovered
+/// items the justifications cover are counted as justified.
+pub fn generate_coverage_report(
+    paths: &[PathBuf],
+    format: Option<CoverageFormat>,
+    justifications: &[CoverageJustification],
+) -> Result<CoverageReport, CoverageError> {
+    let mut files = Vec::new();
+    for path in paths {
+        files.extend(read_coverage_file(path, format)?);
+    }
+    Ok(CoverageReport::from_files(files).with_justifications(justifications))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::JustificationKind;
+
+    const GCOV_TEXT: &str = "\
+        -:    0:Source:src/filter.c
+        -:    0:Graph:build/filter.gcno
+        -:    1:#include \"filter.h\"
+        -:    2:
+function filter_step called 4 returned 100% blocks executed 83%
+        4:    3:int filter_step(int x)
+        -:    4:{
+       4*:    5:    if (x > LIMIT)
+branch  0 taken 1 (fallthrough)
+branch  1 taken 3
+        1:    6:        return clamp(x);
+call    0 returned 1
+        3:    7:    return x;
+    #####:    8:    abort();
+call    0 never executed
+branch  1 never executed
+        -:    9:}
+";
+
+    #[test]
+    fn test_parse_gcov_text() {
+        let files = parse_gcov_output(GCOV_TEXT);
+        assert_eq!(files.len(), 1);
+        let file = &files[0];
+        assert_eq!(file.path, PathBuf::from("src/filter.c"));
+        assert_eq!(
+            file.lines
+                .iter()
+                .map(|l| (l.line, l.count))
+                .collect::<Vec<_>>(),
+            [(3, 4), (5, 4), (6, 1), (7, 3), (8, 0)]
+        );
+        assert!(file.lines[1].unexecuted_block);
+        assert_eq!(file.uncovered_lines(), [8]);
+
+        assert_eq!(file.functions.len(), 1);
+        assert_eq!(file.functions[0].name, "filter_step");
+        assert_eq!(file.functions[0].start_line, 3);
+        assert_eq!(file.functions[0].execution_count, 4);
+
+        assert_eq!(file.branches.len(), 3);
+        assert_eq!((file.branches[0].line, file.branches[0].count), (5, 1));
+        assert!(file.branches[0].fallthrough);
+        assert_eq!((file.branches[1].index, file.branches[1].count), (1, 3));
+        assert!(!file.branches[2].executed);
+
+        assert_eq!(file.calls.len(), 2);
+        assert_eq!((file.calls[0].line, file.calls[0].returned), (6, 1));
+        assert!(!file.calls[1].executed);
+
+        let summary = file.summary();
+        assert_eq!(
+            summary.lines,
+            CoverageCounter {
+                covered: 4,
+                total: 5,
+                justified: 0
+            }
+        );
+        assert_eq!(
+            summary.branches,
+            CoverageCounter {
+                covered: 2,
+                total: 3,
+                justified: 0
+            }
+        );
+        assert_eq!(
+            summary.calls,
+            CoverageCounter {
+                covered: 1,
+                total: 2,
+                justified: 0
+            }
+        );
+        assert_eq!(summary.functions.percent(), 100.0);
+    }
+
+    #[test]
+    fn test_parse_gcov_text_percentages() {
+        let files = parse_gcov_output(
+            "        -:    0:Source:a.c\n        2:    1:if (x) y();\nbranch  0 taken 0%\nbranch  1 taken 100%\ncall    0 returned 50%\n",
+        );
+        let branches: Vec<u64> = files[0].branches.iter().map(|b| b.count).collect();
+        assert_eq!(branches, [0, 1]);
+        assert_eq!(files[0].calls[0].returned, 1);
+    }
+
+    const GCOV_JSON: &str = r#"{"format_version": "2", "gcc_version": "13.2.0",
+        "current_working_directory": "/work", "data_file": "build/filter.gcda",
+        "files": [{"file": "src/filter.c",
+            "functions": [{"name": "filter_step", "demangled_name": "filter_step",
+                "start_line": 3, "start_column": 5, "end_line": 9, "end_column": 1,
+                "blocks": 6, "blocks_executed": 5, "execution_count": 4}],
+            "lines": [
+                {"line_number": 3, "count": 4, "unexecuted_block": false,
+                 "function_name": "filter_step", "branches": [], "calls": []},
+                {"line_number": 5, "count": 4, "unexecuted_block": true,
+                 "function_name": "filter_step",
+                 "branches": [{"count": 1, "throw": false, "fallthrough": true,
+                               "source_block_id": 2, "destination_block_id": 3},
+                              {"count": 3, "throw": false, "fallthrough": false,
+                               "source_block_id": 2, "destination_block_id": 4}],
+                 "calls": []},
+                {"line_number": 6, "count": 1, "unexecuted_block": false,
+                 "function_name": "filter_step", "branches": [],
+                 "calls": [{"source_block_id": 3, "destination_block_id": 5,
+                            "returned": 1}]},
+                {"line_number": 7, "count": 3, "unexecuted_block": false,
+                 "function_name": "filter_step", "branches": [], "calls": []},
+                {"line_number": 8, "count": 0, "unexecuted_block": false,
+                 "function_name": "filter_step", "branches": [],
+                 "calls": [{"source_block_id": 6, "destination_block_id": 7,
+                            "returned": 0}]}
+            ]}]}"#;
+
+    #[test]
+    fn test_parse_gcov_json() {
+        assert_eq!(
+            CoverageFormat::detect(GCOV_JSON),
+            Some(CoverageFormat::GcovJson)
+        );
+        // `gcov -t` writes one document per data file
+        let content = format!(
+            "{}\n{}\n",
+            GCOV_JSON.replace('\n', " "),
+            GCOV_JSON.replace('\n', " ")
+        );
+        let files = parse_gcov_json(&content).unwrap();
+        assert_eq!(files.len(), 2);
+
+        let file = &files[0];
+        assert_eq!(file.lines.len(), 5);
+        assert_eq!(file.functions[0].demangled_name, None);
+        assert_eq!(file.functions[0].end_line, Some(9));
+        assert_eq!(file.functions[0].blocks_executed, Some(5));
+        assert_eq!(file.branches.len(), 2);
+        assert!(file.branches[0].fallthrough && file.branches[0].executed);
+        assert_eq!(file.calls.len(), 2);
+        assert!(!file.calls[1].executed);
+
+        let text = &parse_gcov_output(GCOV_TEXT)[0];
+        assert_eq!(file.lines, text.lines);
+        assert_eq!(file.summary().lines, text.summary().lines);
+        assert_eq!(file.summary().calls, text.summary().calls);
+    }
+
+    #[test]
+    fn test_parse_gcovr_json() {
+        let content = r#"{"gcovr/format_version": "0.6", "files": [{
+            "file": "src/filter.c",
+            "functions": [{"name": "filter_step", "lineno": 3, "execution_count": 4}],
+            "lines": [
+                {"line_number": 3, "count": 4, "branches": []},
+                {"line_number": 4, "count": 0, "branches": [], "gcovr/noncode": true},
+                {"line_number": 5, "count": 4, "branches": [
+                    {"count": 1, "fallthrough": true, "throw": false},
+                    {"count": 0, "fallthrough": false, "throw": false}]},
+                {"line_number": 8, "count": 0, "branches": [], "gcovr/excluded": true}
+            ]}]}"#;
+        assert_eq!(
+            CoverageFormat::detect(content),
+            Some(CoverageFormat::GcovrJson)
+        );
+        let files = parse_gcovr_json(content).unwrap();
+        let file = &files[0];
+        assert_eq!(
+            file.lines.iter().map(|l| l.line).collect::<Vec<_>>(),
+            [3, 5]
+        );
+        assert_eq!(file.functions[0].start_line, 3);
+        assert_eq!(
+            file.summary().branches,
+            CoverageCounter {
+                covered: 1,
+                total: 2,
+                justified: 0
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_lcov() {
+        let content = "\
+TN:unit
+SF:/work/src/filter.c
+FN:3,9,filter_step
+FN:12,filter_reset
+FNDA:4,filter_step
+FNDA:0,filter_reset
+FNF:2
+FNH:1
+DA:3,4
+DA:5,4
+DA:6,1
+DA:7,3
+DA:8,0,Ae2m
+DA:12,0
+LF:6
+LH:4
+BRDA:5,0,0,1
+BRDA:5,0,1,3
+BRDA:8,e0,0,-
+BRF:3
+BRH:2
+end_of_record
+SF:/work/src/ring.c
+DA:1,2
+end_of_record
+";
+        assert_eq!(CoverageFormat::detect(content), Some(CoverageFormat::Lcov));
+        let files = parse_lcov(content);
+        assert_eq!(files.len(), 2);
+
+        let file = &files[0];
+        assert_eq!(file.path, PathBuf::from("/work/src/filter.c"));
+        assert_eq!(file.uncovered_lines(), [8, 12]);
+        assert_eq!(file.functions[0].end_line, Some(9));
+        assert_eq!(file.functions[0].execution_count, 4);
+        assert_eq!(file.functions[1].end_line, None);
+        assert_eq!(
+            file.branches
+                .iter()
+                .map(|b| (b.line, b.index, b.count, b.executed))
+                .collect::<Vec<_>>(),
+            [(5, 0, 1, true), (5, 1, 3, true), (8, 0, 0, false)]
+        );
+        assert!(file.branches[2].throw);
+
+        let summary = file.summary();
+        assert_eq!(
+            summary.lines,
+            CoverageCounter {
+                covered: 4,
+                total: 6,
+                justified: 0
+            }
+        );
+        assert_eq!(
+            summary.branches,
+            CoverageCounter {
+                covered: 2,
+                total: 3,
+                justified: 0
+            }
+        );
+        assert_eq!(
+            summary.functions,
+            CoverageCounter {
+                covered: 1,
+                total: 2,
+                justified: 0
+            }
+        );
+        assert_eq!(summary.calls.total, 0);
+        assert_eq!(files[1].lines[0].count, 2);
+    }
+
+    #[test]
+    fn test_justified_gaps() {
+        let report = CoverageReport::from_files(parse_gcov_output(GCOV_TEXT));
+        assert_eq!(report.summary.lines.unresolved(), 1);
+
+        let report = report.with_justifications(&[
+            CoverageJustification::new(
+                "filter.c",
+                8,
+                JustificationKind::Defensive,
+                "LIMIT bounds every input",
+            ),
+            CoverageJustification::new("other.c", 8, JustificationKind::Robustness, "n/a"),
+        ]);
+        let file = &report.files[0];
+        assert_eq!(file.justifications.len(), 1);
+        assert_eq!(
+            file.line_justification(8).unwrap().kind,
+            JustificationKind::Defensive
+        );
+        assert_eq!(file.uncovered_lines(), [8]);
+        assert!(file.unresolved_lines().is_empty());
+        assert_eq!(
+            report.summary.lines,
+            CoverageCounter {
+                covered: 4,
+                total: 5,
+                justified: 1
+            }
+        );
+        assert_eq!(report.summary.lines.unresolved(), 0);
+        assert_eq!(report.summary.calls.justified, 1);
+        assert_eq!(report.summary.branches.unresolved(), 0);
+
+        assert_eq!(file.range_summary(5, 6).lines.percent(), 100.0);
+        assert_eq!(
+            file.range_summary(7, 9).lines,
+            CoverageCounter {
+                covered: 1,
+                total: 2,
+                justified: 1
+            }
+        );
+        assert_eq!(file.range_summary(1, 2), CoverageSummary::default());
+    }
+
+    #[test]
+    fn test_generate_report_merges_formats() {
+        let dir = tempfile::tempdir().unwrap();
+        let text = dir.path().join("filter.c.gcov");
+        std::fs::write(&text, GCOV_TEXT).unwrap();
+
+        let gz = dir.path().join("filter.gcda.gcov.json.gz");
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        std::io::Write::write_all(&mut encoder, GCOV_JSON.as_bytes()).unwrap();
+        std::fs::write(&gz, encoder.finish().unwrap()).unwrap();
+
+        let report = generate_coverage_report(&[text.clone(), gz], None, &[]).unwrap();
+        assert_eq!(report.files.len(), 1);
+        let file = report.file(Path::new("src/filter.c")).unwrap();
+        assert_eq!(file.lines[0].count, 8);
+        assert_eq!(file.functions.len(), 1);
+        assert_eq!(file.functions[0].execution_count, 8);
+        assert_eq!(file.branches[1].count, 6);
+        assert_eq!(
+            report.summary.lines,
+            CoverageCounter {
+                covered: 4,
+                total: 5,
+                justified: 0
+            }
+        );
+
+        let unknown = dir.path().join("notes.txt");
+        std::fs::write(&unknown, "hello").unwrap();
+        assert!(matches!(
+            generate_coverage_report(&[unknown], None, &[]),
+            Err(CoverageError::UnknownFormat(_))
+        ));
+        assert!(generate_coverage_report(&[text], Some(CoverageFormat::GcovJson), &[]).is_err());
+    }
+}
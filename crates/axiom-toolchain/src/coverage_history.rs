@@ -0,0 +1,497 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Coverage history.
+//!
+//! Each coverage run appends its totals and per-file uncovered lines, keyed
+//! by git commit and build configuration, to `.axiom/coverage-history.jsonl`
+//! in the project root. Queries report per-configuration trends, builds
+//! whose coverage dropped, and lines uncovered since a baseline commit.
+
+use crate::{CoverageCounter, CoverageReport, CoverageSummary};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Error type for coverage history persistence.
+#[derive(Debug, thiserror::Error)]
+pub enum CoverageHistoryError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Malformed coverage record on line {line}: {source}")]
+    Parse {
+        line: usize,
+        source: serde_json::Error,
+    },
+
+    #[error("JSON serialize error: {0}")]
+    Serialize(#[from] serde_json::Error),
+}
+
+/// Coverage totals and uncovered lines of one source.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileCoverageRecord {
+    /// Source path.
+    pub path: PathBuf,
+    /// Totals for the file.
+    pub summary: CoverageSummary,
+    /// Executable lines never executed.
+    pub uncovered_lines: Vec<u32>,
+}
+
+/// Coverage of one build.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoverageRecord {
+    /// Run time (seconds since the Unix epoch).
+    pub timestamp: u64,
+    /// Commit the build was made from, if known.
+    pub commit: Option<String>,
+    /// Build configuration (e.g. profile name).
+    pub config: String,
+    /// Totals across files.
+    pub summary: CoverageSummary,
+    /// Per-file totals, by path.
+    pub files: Vec<FileCoverageRecord>,
+}
+
+impl CoverageRecord {
+    /// Summarize a coverage report.
+    pub fn from_report(
+        report: &CoverageReport,
+        timestamp: u64,
+        commit: Option<String>,
+        config: impl Into<String>,
+    ) -> Self {
+        Self {
+            timestamp,
+            commit,
+            config: config.into(),
+            summary: report.summary,
+            files: report
+                .files
+                .iter()
+                .map(|file| FileCoverageRecord {
+                    path: file.path.clone(),
+                    summary: file.summary(),
+                    uncovered_lines: file.uncovered_lines(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Uncovered lines of a source.
+    fn uncovered(&self, path: &Path) -> BTreeSet<u32> {
+        self.files
+            .iter()
+            .find(|f| f.path == path)
+            .map(|f| f.uncovered_lines.iter().copied().collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Change in coverage, in percentage points.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct CoverageDelta {
+    /// Change in line coverage.
+    pub lines: f64,
+    /// Change in branch coverage.
+    pub branches: f64,
+    /// Change in function coverage.
+    pub functions: f64,
+}
+
+impl CoverageDelta {
+    /// Change from an earlier summary.
+    pub fn between(previous: &CoverageSummary, current: &CoverageSummary) -> Self {
+        let change = |a: CoverageCounter, b: CoverageCounter| b.percent() - a.percent();
+        Self {
+            lines: change(previous.lines, current.lines),
+            branches: change(previous.branches, current.branches),
+            functions: change(previous.functions, current.functions),
+        }
+    }
+}
+
+/// Query parameters for coverage history.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoverageQuery {
+    /// Only include this configuration.
+    #[serde(default)]
+    pub config: Option<String>,
+    /// Only include runs at or after this timestamp.
+    #[serde(default)]
+    pub since: Option<u64>,
+    /// Only include runs before this timestamp.
+    #[serde(default)]
+    pub until: Option<u64>,
+}
+
+impl CoverageQuery {
+    /// Whether a record matches the query.
+    fn matches(&self, record: &CoverageRecord) -> bool {
+        if let Some(ref config) = self.config {
+            if *config != record.config {
+                return false;
+            }
+        }
+        if let Some(since) = self.since {
+            if record.timestamp < since {
+                return false;
+            }
+        }
+        if let Some(until) = self.until {
+            if record.timestamp >= until {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// One run in a coverage trend.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoveragePoint {
+    /// Run time.
+    pub timestamp: u64,
+    /// Commit, if known.
+    pub commit: Option<String>,
+    /// Totals.
+    pub summary: CoverageSummary,
+}
+
+/// Coverage history of one configuration.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CoverageTrend {
+    /// Build configuration.
+    pub config: String,
+    /// Runs in order; reruns of the same commit keep the latest.
+    pub points: Vec<CoveragePoint>,
+    /// Change from the first to the last run.
+    pub net: CoverageDelta,
+}
+
+/// A run whose line or branch coverage dropped past the threshold.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CoverageRegression {
+    /// Build configuration.
+    pub config: String,
+    /// The run that dropped.
+    pub point: CoveragePoint,
+    /// Commit of the preceding run.
+    pub previous_commit: Option<String>,
+    /// Change from the preceding run.
+    pub delta: CoverageDelta,
+}
+
+/// Lines of one source uncovered in a run but not in its baseline.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NewlyUncovered {
+    /// Source path.
+    pub path: PathBuf,
+    /// Line numbers, ascending.
+    pub lines: Vec<u32>,
+}
+
+/// Append-only store of coverage records.
+#[derive(Debug, Clone)]
+pub struct CoverageHistoryStore {
+    path: PathBuf,
+}
+
+impl CoverageHistoryStore {
+    /// Create a store backed by the given file.
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Create the store for a project root.
+    pub fn for_project(project_root: &Path) -> Self {
+        Self::new(project_root.join(".axiom").join("coverage-history.jsonl"))
+    }
+
+    /// Path of the backing file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append a record.
+    pub fn append(&self, record: &CoverageRecord) -> Result<(), CoverageHistoryError> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let line = serde_json::to_string(record)?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", line)?;
+        Ok(())
+    }
+
+    /// Summarize a coverage report and append its record.
+    pub fn record_report(
+        &self,
+        report: &CoverageReport,
+        timestamp: u64,
+        commit: Option<String>,
+        config: &str,
+    ) -> Result<CoverageRecord, CoverageHistoryError> {
+        let record = CoverageRecord::from_report(report, timestamp, commit, config);
+        self.append(&record)?;
+        Ok(record)
+    }
+
+    /// Load all records in the order they were recorded.
+    ///
+    /// A missing file yields an empty list.
+    pub fn load(&self) -> Result<Vec<CoverageRecord>, CoverageHistoryError> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let content = fs::read_to_string(&self.path)?;
+        content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                serde_json::from_str(line).map_err(|source| CoverageHistoryError::Parse {
+                    line: i + 1,
+                    source,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Matching records per configuration, keeping the latest rerun of a
+/// commit.
+fn history_by_config<'a>(
+    records: &'a [CoverageRecord],
+    query: &CoverageQuery,
+) -> BTreeMap<&'a str, Vec<&'a CoverageRecord>> {
+    let mut by_config: BTreeMap<&str, Vec<&CoverageRecord>> = BTreeMap::new();
+    for record in records.iter().filter(|r| query.matches(r)) {
+        let history = by_config.entry(&record.config).or_default();
+        match history.last_mut() {
+            Some(last) if last.commit.is_some() && last.commit == record.commit => {
+                *last = record;
+            }
+            _ => history.push(record),
+        }
+    }
+    by_config
+}
+
+fn point(record: &CoverageRecord) -> CoveragePoint {
+    CoveragePoint {
+        timestamp: record.timestamp,
+        commit: record.commit.clone(),
+        summary: record.summary,
+    }
+}
+
+/// Per-configuration coverage trends, ordered by configuration name.
+pub fn coverage_trends(records: &[CoverageRecord], query: &CoverageQuery) -> Vec<CoverageTrend> {
+    history_by_config(records, query)
+        .into_iter()
+        .map(|(config, history)| {
+            let net = match (history.first(), history.last()) {
+                (Some(first), Some(last)) => CoverageDelta::between(&first.summary, &last.summary),
+                _ => CoverageDelta::default(),
+            };
+            CoverageTrend {
+                config: config.to_string(),
+                points: history.into_iter().map(point).collect(),
+                net,
+            }
+        })
+        .collect()
+}
+
+/// Runs whose line or branch coverage fell by more than `threshold`
+/// percentage points from the preceding run of the same configuration.
+pub fn coverage_regressions(
+    records: &[CoverageRecord],
+    query: &CoverageQuery,
+    threshold: f64,
+) -> Vec<CoverageRegression> {
+    let mut regressions = Vec::new();
+    for (config, history) in history_by_config(records, query) {
+        for pair in history.windows(2) {
+            let delta = CoverageDelta::between(&pair[0].summary, &pair[1].summary);
+            if delta.lines < -threshold || delta.branches < -threshold {
+                regressions.push(CoverageRegression {
+                    config: config.to_string(),
+                    point: point(pair[1]),
+                    previous_commit: pair[0].commit.clone(),
+                    delta,
+                });
+            }
+        }
+    }
+    regressions.sort_by_key(|r| r.point.timestamp);
+    regressions
+}
+
+/// Lines uncovered in `current` that were not uncovered in `baseline`,
+/// including lines of sources the baseline didn't measure.
+///
+/// Lines are compared by number, so edits that move code between the two
+/// runs show up as changes.
+pub fn newly_uncovered(baseline: &CoverageRecord, current: &CoverageRecord) -> Vec<NewlyUncovered> {
+    current
+        .files
+        .iter()
+        .filter_map(|file| {
+            let before = baseline.uncovered(&file.path);
+            let lines: Vec<u32> = file
+                .uncovered_lines
+                .iter()
+                .copied()
+                .filter(|line| !before.contains(line))
+                .collect();
+            (!lines.is_empty()).then(|| NewlyUncovered {
+                path: file.path.clone(),
+                lines,
+            })
+        })
+        .collect()
+}
+
+/// Lines newly uncovered in the latest run of `config` since the latest
+/// run of the baseline commit. `None` if either run is missing.
+pub fn uncovered_since(
+    records: &[CoverageRecord],
+    baseline_commit: &str,
+    config: &str,
+) -> Option<Vec<NewlyUncovered>> {
+    let runs: Vec<&CoverageRecord> = records.iter().filter(|r| r.config == config).collect();
+    let baseline = runs
+        .iter()
+        .rev()
+        .find(|r| r.commit.as_deref() == Some(baseline_commit))?;
+    let current = runs.last()?;
+    Some(newly_uncovered(baseline, current))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FileCoverage, LineCoverage};
+    use tempfile::TempDir;
+
+    fn report(files: &[(&str, &[(u32, u64)])]) -> CoverageReport {
+        CoverageReport::from_files(files.iter().map(|(path, lines)| {
+            let mut file = FileCoverage::new(*path);
+            file.lines = lines
+                .iter()
+                .map(|&(line, count)| LineCoverage {
+                    line,
+                    count,
+                    unexecuted_block: false,
+                })
+                .collect();
+            file
+        }))
+    }
+
+    fn record(timestamp: u64, commit: &str, covered: &[(u32, u64)]) -> CoverageRecord {
+        CoverageRecord::from_report(
+            &report(&[("src/filter.c", covered)]),
+            timestamp,
+            Some(commit.to_string()),
+            "coverage",
+        )
+    }
+
+    #[test]
+    fn test_append_and_load() {
+        let dir = TempDir::new().unwrap();
+        let store = CoverageHistoryStore::for_project(dir.path());
+        assert!(store.load().unwrap().is_empty());
+
+        let report = report(&[("src/filter.c", &[(3, 4), (8, 0)])]);
+        let first = store
+            .record_report(&report, 100, Some("a1".to_string()), "coverage")
+            .unwrap();
+        assert_eq!(
+            first.summary.lines,
+            CoverageCounter {
+                covered: 1,
+                total: 2,
+                justified: 0
+            }
+        );
+        assert_eq!(first.files[0].uncovered_lines, [8]);
+        assert_eq!(store.load().unwrap(), vec![first]);
+
+        fs::write(store.path(), "{\n").unwrap();
+        assert!(matches!(
+            store.load(),
+            Err(CoverageHistoryError::Parse { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_trends_and_regressions() {
+        let records = vec![
+            record(100, "a1", &[(1, 1), (2, 0), (3, 0), (4, 0)]),
+            record(200, "b2", &[(1, 1), (2, 1), (3, 0), (4, 0)]),
+            // Rerun of the same commit replaces the earlier record
+            record(210, "b2", &[(1, 1), (2, 1), (3, 1), (4, 0)]),
+            record(300, "c3", &[(1, 1), (2, 0), (3, 0), (4, 0)]),
+        ];
+        let trends = coverage_trends(&records, &CoverageQuery::default());
+        assert_eq!(trends.len(), 1);
+        assert_eq!(trends[0].points.len(), 3);
+        assert_eq!(trends[0].points[1].summary.lines.covered, 3);
+        assert_eq!(trends[0].net.lines, 0.0);
+
+        let regressions = coverage_regressions(&records, &CoverageQuery::default(), 10.0);
+        assert_eq!(regressions.len(), 1);
+        assert_eq!(regressions[0].point.commit.as_deref(), Some("c3"));
+        assert_eq!(regressions[0].previous_commit.as_deref(), Some("b2"));
+        assert_eq!(regressions[0].delta.lines, -50.0);
+
+        let query = CoverageQuery {
+            config: Some("release".to_string()),
+            ..Default::default()
+        };
+        assert!(coverage_trends(&records, &query).is_empty());
+    }
+
+    #[test]
+    fn test_newly_uncovered() {
+        let mut records = vec![
+            record(100, "a1", &[(1, 1), (2, 1), (3, 0)]),
+            record(200, "b2", &[(1, 1), (2, 0), (3, 0)]),
+        ];
+        records[1].files.push(FileCoverageRecord {
+            path: PathBuf::from("src/ring.c"),
+            summary: CoverageSummary::default(),
+            uncovered_lines: vec![7],
+        });
+
+        let changes = uncovered_since(&records, "a1", "coverage").unwrap();
+        assert_eq!(
+            changes,
+            vec![
+                NewlyUncovered {
+                    path: PathBuf::from("src/filter.c"),
+                    lines: vec![2],
+                },
+                NewlyUncovered {
+                    path: PathBuf::from("src/ring.c"),
+                    lines: vec![7],
+                },
+            ]
+        );
+        assert!(uncovered_since(&records, "zz", "coverage").is_none());
+        assert!(newly_uncovered(&records[1], &records[1]).is_empty());
+    }
+}
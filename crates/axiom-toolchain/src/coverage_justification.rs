@@ -0,0 +1,318 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Justified coverage gaps.
+//!
+//! Code that no requirements-based test can reach (defensive checks,
+//! deactivated code, robustness handling) is resolved by analysis rather
+//! than by more tests. Projects record those analyses in
+//! `.axiom/coverage-justifications.toml`:
+//!
+//! ```toml
+//! [[justification]]
+//! path = "src/nav.c"
+//! line = 42
+//! end_line = 44
+//! kind = "defensive"
+//! rationale = "Heading is range-checked by the caller; the clamp cannot trigger."
+//! ```
+//!
+//! A justification without `end_line` covers one line; one with `branch`
+//! covers only that branch outcome of the line. Justifications are attached
+//! to the matching files of a [`CoverageReport`](crate::CoverageReport),
+//! whose totals then count justified items apart from unresolved ones.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Coverage justification errors.
+#[derive(Debug, Error)]
+pub enum JustificationError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("TOML parse error in {path}: {source}")]
+    Toml {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+}
+
+/// Why uncovered code is acceptable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum JustificationKind {
+    /// Defensive code guarding a condition that cannot occur.
+    Defensive,
+    /// Deactivated code not executed in this configuration.
+    Deactivated,
+    /// Robustness handling of abnormal inputs or hardware faults.
+    Robustness,
+}
+
+impl JustificationKind {
+    /// Human-readable name.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Defensive => "defensive code",
+            Self::Deactivated => "deactivated code",
+            Self::Robustness => "robustness",
+        }
+    }
+}
+
+/// Analysis resolving a coverage gap.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoverageJustification {
+    /// Source path, relative to the project root.
+    pub path: PathBuf,
+    /// First line covered (1-based).
+    pub line: u32,
+    /// Last line covered; the first line alone if absent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_line: Option<u32>,
+    /// Branch outcome on `line` covered; every line and branch in the range
+    /// if absent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch: Option<u32>,
+    /// Category of justification.
+    pub kind: JustificationKind,
+    /// The analysis.
+    pub rationale: String,
+}
+
+impl CoverageJustification {
+    /// Justify one line.
+    pub fn new(
+        path: impl Into<PathBuf>,
+        line: u32,
+        kind: JustificationKind,
+        rationale: impl Into<String>,
+    ) -> Self {
+        Self {
+            path: path.into(),
+            line,
+            end_line: None,
+            branch: None,
+            kind,
+            rationale: rationale.into(),
+        }
+    }
+
+    /// Extend to a range of lines.
+    pub fn with_end_line(mut self, end_line: u32) -> Self {
+        self.end_line = Some(end_line);
+        self
+    }
+
+    /// Narrow to one branch outcome of the line.
+    pub fn with_branch(mut self, branch: u32) -> Self {
+        self.branch = Some(branch);
+        self
+    }
+
+    /// Whether the justification is for a coverage record's source.
+    /// Recorded paths may be absolute, so a suffix match is enough.
+    pub fn applies_to(&self, path: &Path) -> bool {
+        path.ends_with(&self.path)
+    }
+
+    fn in_range(&self, line: u32) -> bool {
+        (self.line..=self.end_line.unwrap_or(self.line).max(self.line)).contains(&line)
+    }
+
+    /// Whether the justification covers a whole line.
+    pub fn covers_line(&self, line: u32) -> bool {
+        self.branch.is_none() && self.in_range(line)
+    }
+
+    /// Whether the justification covers a branch outcome.
+    pub fn covers_branch(&self, line: u32, index: u32) -> bool {
+        match self.branch {
+            Some(branch) => line == self.line && index == branch,
+            None => self.in_range(line),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct JustificationsFile {
+    #[serde(default)]
+    justification: Vec<CoverageJustification>,
+}
+
+/// Path of a project's coverage justifications.
+pub fn coverage_justifications_path(project_root: &Path) -> PathBuf {
+    project_root
+        .join(".axiom")
+        .join("coverage-justifications.toml")
+}
+
+/// Load a project's coverage justifications; none if the file is missing.
+pub fn load_coverage_justifications(
+    project_root: &Path,
+) -> Result<Vec<CoverageJustification>, JustificationError> {
+    let path = coverage_justifications_path(project_root);
+    if !path.is_file() {
+        return Ok(Vec::new());
+    }
+    let content = std::fs::read_to_string(&path)?;
+    let file: JustificationsFile =
+        toml::from_str(&content).map_err(|source| JustificationError::Toml { path, source })?;
+    Ok(file.justification)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BranchCoverage, CoverageCounter, CoverageReport, FileCoverage, LineCoverage};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_load_justifications() {
+        let dir = TempDir::new().unwrap();
+        assert!(load_coverage_justifications(dir.path()).unwrap().is_empty());
+
+        let path = coverage_justifications_path(dir.path());
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(
+            &path,
+            r#"
+[[justification]]
+path = "src/nav.c"
+line = 42
+end_line = 44
+kind = "defensive"
+rationale = "Range-checked by the caller"
+
+[[justification]]
+path = "src/nav.c"
+line = 50
+branch = 1
+kind = "robustness"
+rationale = "Sensor fault path"
+"#,
+        )
+        .unwrap();
+        let justifications = load_coverage_justifications(dir.path()).unwrap();
+        assert_eq!(justifications.len(), 2);
+        assert!(justifications[0].applies_to(Path::new("/build/src/nav.c")));
+        assert!(justifications[0].covers_line(43));
+        assert!(!justifications[0].covers_line(45));
+        assert!(justifications[0].covers_branch(44, 0));
+        assert!(!justifications[1].covers_line(50));
+        assert!(justifications[1].covers_branch(50, 1));
+        assert!(!justifications[1].covers_branch(50, 0));
+
+        std::fs::write(&path, "[[justification]]\npath = 1\n").unwrap();
+        assert!(matches!(
+            load_coverage_justifications(dir.path()),
+            Err(JustificationError::Toml { .. })
+        ));
+    }
+
+    /// `src/nav.c` with lines 10-14 and two branch outcomes on line 12;
+    /// only line 10 and the first outcome executed.
+    fn report() -> CoverageReport {
+        let mut file = FileCoverage::new("/build/src/nav.c");
+        for line in 10..=14 {
+            file.lines.push(LineCoverage {
+                line,
+                count: (line == 10) as u64,
+                unexecuted_block: false,
+            });
+        }
+        for index in 0..2 {
+            file.branches.push(BranchCoverage {
+                line: 12,
+                index,
+                count: (index == 0) as u64,
+                executed: true,
+                fallthrough: false,
+                throw: false,
+            });
+        }
+        CoverageReport::from_files([file])
+    }
+
+    fn justify(line: u32) -> CoverageJustification {
+        CoverageJustification::new(
+            "src/nav.c",
+            line,
+            JustificationKind::Defensive,
+            "Unreachable",
+        )
+    }
+
+    #[test]
+    fn test_stale_justifications() {
+        // The justified line and file are gone from the coverage data
+        let report = report().with_justifications(&[
+            justify(40),
+            CoverageJustification::new("src/gone.c", 11, JustificationKind::Deactivated, "Removed"),
+        ]);
+        let file = &report.files[0];
+        assert_eq!(file.justifications, [justify(40)]);
+        assert_eq!(report.summary.lines.justified, 0);
+        assert_eq!(report.summary.lines.unresolved(), 4);
+        assert_eq!(file.unresolved_lines(), [11, 12, 13, 14]);
+
+        // A justified line that is now executed counts as covered only
+        let report = report.with_justifications(&[justify(10)]);
+        assert_eq!(
+            report.summary.lines,
+            CoverageCounter {
+                covered: 1,
+                total: 5,
+                justified: 0
+            }
+        );
+    }
+
+    #[test]
+    fn test_duplicate_justifications() {
+        let report = report().with_justifications(&[justify(11), justify(11)]);
+        assert_eq!(report.files[0].justifications.len(), 1);
+
+        // Reapplying, or a second analysis of the same line, counts it once
+        let report = report
+            .with_justifications(&[justify(11)])
+            .with_justifications(&[justify(11).with_end_line(12)]);
+        assert_eq!(report.files[0].justifications.len(), 2);
+        assert_eq!(
+            report.summary.lines,
+            CoverageCounter {
+                covered: 1,
+                total: 5,
+                justified: 2
+            }
+        );
+    }
+
+    #[test]
+    fn test_partially_justified() {
+        let report = report()
+            .with_justifications(&[justify(10).with_end_line(12), justify(12).with_branch(1)]);
+        let summary = report.summary;
+        assert_eq!(
+            summary.lines,
+            CoverageCounter {
+                covered: 1,
+                total: 5,
+                justified: 2
+            }
+        );
+        assert_eq!(summary.lines.unresolved(), 2);
+        assert_eq!(
+            summary.branches,
+            CoverageCounter {
+                covered: 1,
+                total: 2,
+                justified: 1
+            }
+        );
+        assert_eq!(summary.branches.unresolved(), 0);
+        assert_eq!(report.files[0].unresolved_lines(), [13, 14]);
+    }
+}
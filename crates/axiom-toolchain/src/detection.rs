@@ -1,9 +1,11 @@
 // SPDX-License-Identifier: Apache-2.0
 // Copyright 2024 HawkLogic Systems
 
-//! Toolchain detection from known paths.
+//! Toolchain detection from PATH, known install locations, and the Windows
+//! registry.
 
-use crate::{DetectedToolchain, ToolchainKind};
+use crate::limits::run_limited;
+use crate::{detect_bundled_arm_gcc, DetectedToolchain, ToolchainKind};
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
@@ -40,61 +42,251 @@ const PYTHON_PATHS: &[&str] = &[
     "/usr/local/bin/python3",
 ];
 
+/// Executable names searched for on PATH, without the platform suffix.
+const CLANG_NAMES: &[&str] = &["clang"];
+const GCC_NAMES: &[&str] = &["gcc", "gcc-13", "gcc-12", "gcc-11"];
+const ARM_GCC_NAMES: &[&str] = &["arm-none-eabi-gcc"];
+const PYTHON_NAMES: &[&str] = &["python3", "python"];
+
+/// Uninstall registry keys listing installed programs on Windows.
+const UNINSTALL_KEYS: &[&str] = &[
+    r"HKLM\SOFTWARE\Microsoft\Windows\CurrentVersion\Uninstall",
+    r"HKLM\SOFTWARE\WOW6432Node\Microsoft\Windows\CurrentVersion\Uninstall",
+    r"HKCU\SOFTWARE\Microsoft\Windows\CurrentVersion\Uninstall",
+];
+
 /// Detect all available toolchains.
 pub fn detect_all() -> Vec<DetectedToolchain> {
-    let mut toolchains = Vec::new();
+    [
+        ToolchainKind::Clang,
+        ToolchainKind::Gcc,
+        ToolchainKind::ArmGcc,
+        ToolchainKind::Python,
+    ]
+    .into_iter()
+    .filter_map(detect)
+    .collect()
+}
 
-    // Detect Clang
-    for path in CLANG_PATHS {
-        if let Some(tc) = detect_at_path(Path::new(path), ToolchainKind::Clang) {
-            toolchains.push(tc);
-            break; // Take first found
+/// Detect a specific toolchain kind.
+///
+/// Installed offline bundles win for ARM GCC; after that the first working
+/// candidate from [`candidate_paths`] is taken.
+pub fn detect(kind: ToolchainKind) -> Option<DetectedToolchain> {
+    if kind == ToolchainKind::ArmGcc {
+        if let Some(tc) = detect_bundled_arm_gcc() {
+            return Some(tc);
         }
     }
 
-    // Detect GCC
-    for path in GCC_PATHS {
-        if let Some(tc) = detect_at_path(Path::new(path), ToolchainKind::Gcc) {
-            toolchains.push(tc);
-            break;
-        }
+    candidate_paths(kind)
+        .iter()
+        .find_map(|path| detect_at_path(path, kind))
+}
+
+/// Every place a toolchain kind may be installed, most preferred first:
+/// the user's PATH, the well-known install paths, installer locations (Arm
+/// installer via the Windows registry, xPack, scoop, the macOS pkg), and
+/// finally whatever `which`/`where` reports.
+pub fn candidate_paths(kind: ToolchainKind) -> Vec<PathBuf> {
+    let (known, names) = match kind {
+        ToolchainKind::Clang => (CLANG_PATHS, CLANG_NAMES),
+        ToolchainKind::Gcc => (GCC_PATHS, GCC_NAMES),
+        ToolchainKind::ArmGcc => (ARM_GCC_PATHS, ARM_GCC_NAMES),
+        ToolchainKind::Python => (PYTHON_PATHS, PYTHON_NAMES),
+    };
+
+    let mut candidates = Vec::new();
+    if let Some(path) = std::env::var_os("PATH") {
+        candidates.extend(search_path(&path, names));
     }
+    candidates.extend(known.iter().map(PathBuf::from));
+    for dir in install_dirs(kind) {
+        candidates.extend(names.iter().map(|name| dir.join(executable(name))));
+    }
+    candidates.extend(names.iter().filter_map(|name| which(name)));
 
-    // Detect ARM GCC
-    for path in ARM_GCC_PATHS {
-        if let Some(tc) = detect_at_path(Path::new(path), ToolchainKind::ArmGcc) {
-            toolchains.push(tc);
-            break;
-        }
+    let mut seen = std::collections::HashSet::new();
+    candidates.retain(|path| path.is_file() && seen.insert(path.clone()));
+    candidates
+}
+
+/// Executables with the given names in each directory of a PATH-style list.
+pub fn search_path(path: &std::ffi::OsStr, names: &[&str]) -> Vec<PathBuf> {
+    std::env::split_paths(path)
+        .flat_map(|dir| names.iter().map(move |name| dir.join(executable(name))))
+        .filter(|candidate| candidate.is_file())
+        .collect()
+}
+
+fn executable(name: &str) -> String {
+    format!("{}{}", name, std::env::consts::EXE_SUFFIX)
+}
+
+/// Ask the shell's `which` (or `where` on Windows) for a program.
+fn which(name: &str) -> Option<PathBuf> {
+    let finder = if cfg!(windows) { "where" } else { "which" };
+    let output = run_limited(Command::new(finder).arg(name)).ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .map(PathBuf::from)
+}
+
+/// `bin` directories of toolchains installed outside PATH by the Arm
+/// installer, xPack, scoop, or the Arm macOS package.
+fn install_dirs(kind: ToolchainKind) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    for root in registry_install_locations(registry_patterns(kind)) {
+        dirs.push(root.join("bin"));
+        dirs.push(root);
+    }
+    if kind != ToolchainKind::ArmGcc {
+        return dirs;
     }
 
-    // Detect Python
-    for path in PYTHON_PATHS {
-        if let Some(tc) = detect_at_path(Path::new(path), ToolchainKind::Python) {
-            toolchains.push(tc);
-            break;
+    let home = std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(PathBuf::from);
+    let mut xpack_roots = Vec::new();
+    if let Some(appdata) = std::env::var_os("APPDATA") {
+        xpack_roots.push(PathBuf::from(appdata).join("xPacks"));
+    }
+    if let Some(home) = &home {
+        xpack_roots.push(home.join(".local").join("xPacks"));
+        xpack_roots.push(home.join("Library").join("xPacks"));
+    }
+    for root in xpack_roots {
+        let package = root.join("@xpack-dev-tools").join("arm-none-eabi-gcc");
+        for version in sorted_subdirs(&package).into_iter().rev() {
+            dirs.push(version.join(".content").join("bin"));
         }
     }
 
-    toolchains
+    let scoop = std::env::var_os("SCOOP")
+        .map(PathBuf::from)
+        .or_else(|| home.as_ref().map(|h| h.join("scoop")));
+    if let Some(scoop) = scoop {
+        dirs.push(
+            scoop
+                .join("apps")
+                .join("gcc-arm-none-eabi")
+                .join("current")
+                .join("bin"),
+        );
+    }
+
+    for version in sorted_subdirs(Path::new("/Applications/ArmGNUToolchain"))
+        .into_iter()
+        .rev()
+    {
+        dirs.push(version.join("arm-none-eabi").join("bin"));
+    }
+    dirs
 }
 
-/// Detect a specific toolchain kind.
-pub fn detect(kind: ToolchainKind) -> Option<DetectedToolchain> {
-    let paths = match kind {
-        ToolchainKind::Clang => CLANG_PATHS,
-        ToolchainKind::Gcc => GCC_PATHS,
-        ToolchainKind::ArmGcc => ARM_GCC_PATHS,
-        ToolchainKind::Python => PYTHON_PATHS,
+fn sorted_subdirs(dir: &Path) -> Vec<PathBuf> {
+    let mut subdirs: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|e| e.path())
+                .filter(|p| p.is_dir())
+                .collect()
+        })
+        .unwrap_or_default();
+    subdirs.sort();
+    subdirs
+}
+
+/// Display-name fragments identifying a toolchain in the uninstall keys.
+fn registry_patterns(kind: ToolchainKind) -> &'static [&'static str] {
+    match kind {
+        ToolchainKind::Clang => &["LLVM"],
+        ToolchainKind::Gcc => &[],
+        ToolchainKind::ArmGcc => &[
+            "Arm GNU Toolchain",
+            "GNU Arm Embedded Toolchain",
+            "GNU Tools for Arm Embedded Processors",
+        ],
+        ToolchainKind::Python => &["Python 3"],
+    }
+}
+
+/// Install directories of Windows programs whose display name contains
+/// one of the patterns.
+fn registry_install_locations(patterns: &[&str]) -> Vec<PathBuf> {
+    if !cfg!(windows) || patterns.is_empty() {
+        return Vec::new();
+    }
+    let mut locations = Vec::new();
+    for key in UNINSTALL_KEYS {
+        let Ok(output) = run_limited(Command::new("reg").args(["query", key, "/s"])) else {
+            continue;
+        };
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        locations.extend(
+            parse_uninstall_entries(&stdout)
+                .into_iter()
+                .filter(|entry| patterns.iter().any(|p| entry.display_name.contains(p)))
+                .map(|entry| entry.location),
+        );
+    }
+    locations
+}
+
+/// A program listed under an uninstall registry key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct UninstallEntry {
+    display_name: String,
+    location: PathBuf,
+}
+
+/// Parse `reg query <key> /s` output into programs with an install
+/// directory, taken from `InstallLocation` or else the directory of the
+/// uninstaller.
+fn parse_uninstall_entries(output: &str) -> Vec<UninstallEntry> {
+    let mut entries = Vec::new();
+    let mut flush = |name: &mut Option<String>, location: &mut Option<PathBuf>| {
+        if let (Some(display_name), Some(location)) = (name.take(), location.take()) {
+            entries.push(UninstallEntry {
+                display_name,
+                location,
+            });
+        }
     };
 
-    for path in paths {
-        if let Some(tc) = detect_at_path(Path::new(path), kind) {
-            return Some(tc);
+    let mut name = None;
+    let mut location: Option<PathBuf> = None;
+    for line in output.lines() {
+        if line.starts_with("HKEY_") {
+            flush(&mut name, &mut location);
+            continue;
+        }
+        let mut fields = line.trim().splitn(3, "    ");
+        let (Some(value), Some(_), Some(data)) = (fields.next(), fields.next(), fields.next())
+        else {
+            continue;
+        };
+        let data = data.trim();
+        match value {
+            "DisplayName" => name = Some(data.to_string()),
+            "InstallLocation" if !data.is_empty() => {
+                location = Some(PathBuf::from(data.trim_end_matches('\\')))
+            }
+            "UninstallString" if location.is_none() => {
+                let exe = data.trim_matches('"');
+                location = exe.rsplit_once('\\').map(|(dir, _)| PathBuf::from(dir));
+            }
+            _ => {}
         }
     }
-
-    None
+    flush(&mut name, &mut location);
+    entries
 }
 
 /// Detect a toolchain at a specific path.
@@ -109,7 +301,7 @@ pub fn detect_at_path(path: &Path, kind: ToolchainKind) -> Option<DetectedToolch
 
 /// Get version string for a toolchain binary.
 fn get_version(path: &Path, kind: ToolchainKind) -> Option<String> {
-    let output = Command::new(path).arg("--version").output().ok()?;
+    let output = run_limited(Command::new(path).arg("--version")).ok()?;
 
     if !output.status.success() {
         return None;
@@ -120,7 +312,7 @@ fn get_version(path: &Path, kind: ToolchainKind) -> Option<String> {
 }
 
 /// Parse version from --version output.
-fn parse_version(output: &str, kind: ToolchainKind) -> Option<String> {
+pub(crate) fn parse_version(output: &str, kind: ToolchainKind) -> Option<String> {
     let first_line = output.lines().next()?;
 
     match kind {
@@ -197,6 +389,55 @@ mod tests {
         assert_eq!(version, Some("13.2.0".to_string()));
     }
 
+    #[test]
+    fn test_search_path() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let first = temp.path().join("a");
+        let second = temp.path().join("b");
+        std::fs::create_dir_all(&first).unwrap();
+        std::fs::create_dir_all(&second).unwrap();
+        let gcc = second.join(executable("arm-none-eabi-gcc"));
+        std::fs::write(&gcc, b"").unwrap();
+
+        let path = std::env::join_paths([&first, &second]).unwrap();
+        assert_eq!(search_path(&path, ARM_GCC_NAMES), vec![gcc]);
+        assert!(search_path(&path, CLANG_NAMES).is_empty());
+    }
+
+    #[test]
+    fn test_parse_uninstall_entries() {
+        let output = "\r
+HKEY_LOCAL_MACHINE\\SOFTWARE\\WOW6432Node\\Microsoft\\Windows\\CurrentVersion\\Uninstall\\Arm GNU Toolchain arm-none-eabi 13.2.rel1\r
+    DisplayName    REG_SZ    Arm GNU Toolchain arm-none-eabi 13.2.rel1\r
+    InstallLocation    REG_SZ    C:\\Program Files (x86)\\Arm GNU Toolchain arm-none-eabi\\13.2 Rel1\\\r
+\r
+HKEY_LOCAL_MACHINE\\SOFTWARE\\WOW6432Node\\Microsoft\\Windows\\CurrentVersion\\Uninstall\\GNU Arm Embedded Toolchain 10 2021.10\r
+    DisplayName    REG_SZ    GNU Arm Embedded Toolchain 10 2021.10\r
+    UninstallString    REG_SZ    \"C:\\Program Files (x86)\\GNU Arm Embedded Toolchain\\10 2021.10\\uninstall.exe\"\r
+\r
+HKEY_LOCAL_MACHINE\\SOFTWARE\\WOW6432Node\\Microsoft\\Windows\\CurrentVersion\\Uninstall\\NoLocation\r
+    DisplayName    REG_SZ    Something Else\r
+";
+        let entries = parse_uninstall_entries(output);
+        assert_eq!(
+            entries,
+            vec![
+                UninstallEntry {
+                    display_name: "Arm GNU Toolchain arm-none-eabi 13.2.rel1".to_string(),
+                    location: PathBuf::from(
+                        "C:\\Program Files (x86)\\Arm GNU Toolchain arm-none-eabi\\13.2 Rel1"
+                    ),
+                },
+                UninstallEntry {
+                    display_name: "GNU Arm Embedded Toolchain 10 2021.10".to_string(),
+                    location: PathBuf::from(
+                        "C:\\Program Files (x86)\\GNU Arm Embedded Toolchain\\10 2021.10"
+                    ),
+                },
+            ]
+        );
+    }
+
     #[test]
     fn test_parse_python_version() {
         let output = "Python 3.11.6";
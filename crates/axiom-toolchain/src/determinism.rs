@@ -0,0 +1,392 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Reproducible-build verification.
+//!
+//! A target is compiled and linked twice with the checkout path mapped away
+//! (`-ffile-prefix-map`) and the date and time macros pinned, hashing every
+//! artifact after each build. Artifacts whose hashes differ are
+//! nondeterministic; a clean report is direct evidence for a tool
+//! qualification argument that the same inputs give the same firmware.
+
+use crate::{sha256_file, ArmCompileRequest, ArmLinkRequest, DetectedToolchain};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Error type for determinism verification.
+#[derive(Debug, thiserror::Error)]
+pub enum DeterminismError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Build {build} failed on {}: {message}", .output.display())]
+    BuildFailed {
+        build: u8,
+        output: PathBuf,
+        message: String,
+    },
+}
+
+/// A target to build twice.
+#[derive(Debug, Clone)]
+pub struct DeterminismRequest {
+    /// Project root, mapped to `.` in debug info and `__FILE__`.
+    pub project_root: PathBuf,
+    /// Sources to compile.
+    pub compiles: Vec<ArmCompileRequest>,
+    /// Link of the compiled objects.
+    pub link: ArmLinkRequest,
+    /// Seconds since the Unix epoch used for `__DATE__`, `__TIME__` and
+    /// `__TIMESTAMP__`.
+    pub source_date_epoch: u64,
+}
+
+impl DeterminismRequest {
+    /// Build `compiles` and `link` with timestamps pinned to the epoch.
+    pub fn new(
+        project_root: PathBuf,
+        compiles: Vec<ArmCompileRequest>,
+        link: ArmLinkRequest,
+    ) -> Self {
+        Self {
+            project_root,
+            compiles,
+            link,
+            source_date_epoch: 0,
+        }
+    }
+
+    /// Pin timestamps to a time, e.g. the last commit's.
+    pub fn with_source_date_epoch(mut self, epoch: u64) -> Self {
+        self.source_date_epoch = epoch;
+        self
+    }
+
+    /// Flags added to every compile.
+    pub fn reproducible_flags(&self) -> Vec<String> {
+        let (date, time, timestamp) = build_timestamps(self.source_date_epoch);
+        vec![
+            format!("-ffile-prefix-map={}=.", self.project_root.display()),
+            "-Wno-builtin-macro-redefined".to_string(),
+            format!("-D__DATE__=\"{}\"", date),
+            format!("-D__TIME__=\"{}\"", time),
+            format!("-D__TIMESTAMP__=\"{}\"", timestamp),
+        ]
+    }
+
+    /// Every artifact the build writes: objects, then the ELF and map file.
+    pub fn artifacts(&self) -> Vec<PathBuf> {
+        self.compiles
+            .iter()
+            .map(|compile| compile.output.clone())
+            .chain(std::iter::once(self.link.output.clone()))
+            .chain(self.link.linker.map_file.clone())
+            .collect()
+    }
+}
+
+/// One artifact's hashes from both builds.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArtifactComparison {
+    /// Artifact path.
+    pub path: PathBuf,
+    /// SHA-256 after the first build, if the artifact was written.
+    pub first: Option<String>,
+    /// SHA-256 after the second build, if the artifact was written.
+    pub second: Option<String>,
+}
+
+impl ArtifactComparison {
+    /// Whether both builds wrote identical contents.
+    pub fn is_deterministic(&self) -> bool {
+        self.first.is_some() && self.first == self.second
+    }
+}
+
+/// Result of building a target twice.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeterminismReport {
+    /// Flags added to every compile.
+    pub flags: Vec<String>,
+    /// Timestamp the date and time macros were pinned to.
+    pub source_date_epoch: u64,
+    /// Every artifact, in build order.
+    pub artifacts: Vec<ArtifactComparison>,
+}
+
+impl DeterminismReport {
+    /// Whether every artifact was identical across both builds.
+    pub fn is_deterministic(&self) -> bool {
+        self.artifacts
+            .iter()
+            .all(ArtifactComparison::is_deterministic)
+    }
+
+    /// Artifacts that differed between the builds.
+    pub fn nondeterministic(&self) -> Vec<&ArtifactComparison> {
+        self.artifacts
+            .iter()
+            .filter(|artifact| !artifact.is_deterministic())
+            .collect()
+    }
+}
+
+/// Build a target twice with reproducible flags and compare every artifact.
+///
+/// Outputs are removed before each build so a stale file can't stand in for
+/// one that wasn't written. The second build's outputs are left in place.
+pub fn verify_determinism(
+    toolchain: &DetectedToolchain,
+    request: &DeterminismRequest,
+) -> Result<DeterminismReport, DeterminismError> {
+    let flags = request.reproducible_flags();
+    let compiles: Vec<ArmCompileRequest> = request
+        .compiles
+        .iter()
+        .map(|compile| {
+            let mut compile = compile.clone();
+            compile.flags.extend(flags.iter().cloned());
+            compile
+        })
+        .collect();
+    let artifacts = request.artifacts();
+
+    let mut builds = Vec::with_capacity(2);
+    for build in 1..=2 {
+        for path in &artifacts {
+            if path.exists() {
+                std::fs::remove_file(path)?;
+            }
+        }
+        run_build(toolchain, &compiles, &request.link, build)?;
+        builds.push(
+            artifacts
+                .iter()
+                .map(|path| sha256_file(path).ok())
+                .collect::<Vec<_>>(),
+        );
+    }
+
+    let second = builds.pop().unwrap_or_default();
+    let first = builds.pop().unwrap_or_default();
+    Ok(DeterminismReport {
+        flags,
+        source_date_epoch: request.source_date_epoch,
+        artifacts: artifacts
+            .into_iter()
+            .zip(first.into_iter().zip(second))
+            .map(|(path, (first, second))| ArtifactComparison {
+                path,
+                first,
+                second,
+            })
+            .collect(),
+    })
+}
+
+fn run_build(
+    toolchain: &DetectedToolchain,
+    compiles: &[ArmCompileRequest],
+    link: &ArmLinkRequest,
+    build: u8,
+) -> Result<(), DeterminismError> {
+    for compile in compiles {
+        if let Some(parent) = compile.output.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let result = crate::compile_arm(toolchain, compile);
+        if !result.success() {
+            return Err(DeterminismError::BuildFailed {
+                build,
+                output: compile.output.clone(),
+                message: result.stderr,
+            });
+        }
+    }
+    let result = crate::link_arm(toolchain, link);
+    if !result.success() {
+        return Err(DeterminismError::BuildFailed {
+            build,
+            output: link.output.clone(),
+            message: result.stderr,
+        });
+    }
+    Ok(())
+}
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+
+/// `__DATE__`, `__TIME__` and `__TIMESTAMP__` values for a UTC time, in
+/// the formats GCC uses (`"Jan  1 1970"`, `"00:00:00"`,
+/// `"Thu Jan  1 00:00:00 1970"`).
+pub fn build_timestamps(epoch: u64) -> (String, String, String) {
+    let days = epoch / 86_400;
+    let secs = epoch % 86_400;
+    let (year, month, day) = civil_from_days(days);
+    let month = MONTHS[month as usize - 1];
+    let weekday = WEEKDAYS[(days % 7) as usize];
+    let time = format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60);
+    (
+        format!("{} {:>2} {}", month, day, year),
+        time.clone(),
+        format!("{} {} {:>2} {} {}", weekday, month, day, time, year),
+    )
+}
+
+/// Gregorian (year, month, day) for days since 1970-01-01.
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    // Howard Hinnant's algorithm, shifted so eras start on March 1st
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ArmMcuConfig, LinkerConfig, ToolchainKind};
+    use std::path::Path;
+    use tempfile::TempDir;
+
+    fn request(root: &Path) -> DeterminismRequest {
+        let mcu = ArmMcuConfig::new("cortex-m4");
+        let object = root.join("build/main.o");
+        DeterminismRequest::new(
+            root.to_path_buf(),
+            vec![ArmCompileRequest::new(
+                root.join("src/main.c"),
+                object.clone(),
+                mcu.clone(),
+            )],
+            ArmLinkRequest::new(
+                vec![object],
+                root.join("build/firmware.elf"),
+                mcu,
+                LinkerConfig::new(root.join("app.ld"))
+                    .with_map_file(root.join("build/firmware.map")),
+            ),
+        )
+    }
+
+    #[test]
+    fn test_build_timestamps() {
+        assert_eq!(
+            build_timestamps(0),
+            (
+                "Jan  1 1970".to_string(),
+                "00:00:00".to_string(),
+                "Thu Jan  1 00:00:00 1970".to_string()
+            )
+        );
+        // 2024-02-29T13:45:07Z
+        assert_eq!(
+            build_timestamps(1_709_214_307),
+            (
+                "Feb 29 2024".to_string(),
+                "13:45:07".to_string(),
+                "Thu Feb 29 13:45:07 2024".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_reproducible_flags_and_artifacts() {
+        let root = Path::new("/work/app");
+        let request = request(root).with_source_date_epoch(86_400);
+        let flags = request.reproducible_flags();
+        assert_eq!(flags[0], "-ffile-prefix-map=/work/app=.");
+        assert!(flags.contains(&"-D__DATE__=\"Jan  2 1970\"".to_string()));
+        assert_eq!(
+            request.artifacts(),
+            vec![
+                root.join("build/main.o"),
+                root.join("build/firmware.elf"),
+                root.join("build/firmware.map"),
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    fn fake_gcc(dir: &Path, script: &str) -> DetectedToolchain {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = dir.join("arm-none-eabi-gcc");
+        std::fs::write(&path, format!("#!/bin/sh\n{}", script)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        DetectedToolchain::new(ToolchainKind::ArmGcc, path, "13.2.1".to_string())
+    }
+
+    // Stand-in driver that writes its argv to the -o file and to any -Map file
+    #[cfg(unix)]
+    const WRITE_OUTPUTS: &str = r#"
+out=""; map=""; prev=""
+for arg in "$@"; do
+  [ "$prev" = "-o" ] && out="$arg"
+  case "$arg" in -Wl,-Map=*) map="${arg#-Wl,-Map=}" ;; esac
+  prev="$arg"
+done
+echo "$@" $STAMP > "$out"
+[ -n "$map" ] && echo map > "$map"
+exit 0
+"#;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_identical_builds_are_deterministic() {
+        let temp = TempDir::new().unwrap();
+        let tc = fake_gcc(temp.path(), WRITE_OUTPUTS);
+        let report = verify_determinism(&tc, &request(temp.path())).unwrap();
+
+        assert!(report.is_deterministic(), "{:?}", report);
+        assert_eq!(report.artifacts.len(), 3);
+        let object = std::fs::read_to_string(temp.path().join("build/main.o")).unwrap();
+        assert!(object.contains("-D__TIME__=\"00:00:00\""));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_reports_nondeterministic_artifacts() {
+        let temp = TempDir::new().unwrap();
+        // The ELF gets a fresh value each run; objects stay stable
+        let counter = temp.path().join("counter");
+        let script = format!(
+            "echo x >> {}\ncase \"$*\" in *-c*) STAMP= ;; *) STAMP=$(wc -l < {}) ;; esac\n{}",
+            counter.display(),
+            counter.display(),
+            WRITE_OUTPUTS
+        );
+        let tc = fake_gcc(temp.path(), &script);
+        let report = verify_determinism(&tc, &request(temp.path())).unwrap();
+
+        assert!(!report.is_deterministic());
+        let differing: Vec<&Path> = report
+            .nondeterministic()
+            .iter()
+            .map(|artifact| artifact.path.as_path())
+            .collect();
+        assert_eq!(differing, vec![temp.path().join("build/firmware.elf")]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_build_failure() {
+        let temp = TempDir::new().unwrap();
+        let tc = fake_gcc(temp.path(), "echo 'main.c:1: error: boom' >&2\nexit 1\n");
+        let err = verify_determinism(&tc, &request(temp.path())).unwrap_err();
+        assert!(matches!(
+            err,
+            DeterminismError::BuildFailed { build: 1, .. }
+        ));
+        assert!(err.to_string().contains("boom"));
+    }
+}
@@ -0,0 +1,1444 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Built-in Thumb disassembler.
+//!
+//! Decodes the Thumb and Thumb-2 (ARMv7-M) code of a Cortex-M image straight
+//! from the parsed ELF, so code can be inspected without
+//! `arm-none-eabi-objdump`. Branch and literal targets are annotated with
+//! symbol names, and instructions are grouped under the source lines they
+//! were generated from when the image has DWARF line tables.
+//!
+//! ARM mapping symbols (`$t`, `$d`) separate code from literal pools; data
+//! is shown as `.word`. Encodings the decoder does not cover, such as
+//! floating-point and DSP instructions, are shown as `.inst` so the listing
+//! stays aligned.
+
+use crate::assembly::source_reader;
+use crate::{
+    DebugInfo, DwarfError, ElfError, ElfFile, ElfSection, SymbolKind, EM_ARM, SHF_EXECINSTR,
+    SHT_NOBITS,
+};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use thiserror::Error;
+
+/// Core register names.
+const REGISTERS: [&str; 16] = [
+    "r0", "r1", "r2", "r3", "r4", "r5", "r6", "r7", "r8", "r9", "r10", "r11", "r12", "sp", "lr",
+    "pc",
+];
+
+/// Condition code suffixes; AL is left implicit.
+const CONDITIONS: [&str; 16] = [
+    "eq", "ne", "cs", "cc", "mi", "pl", "vs", "vc", "hi", "ls", "ge", "lt", "gt", "le", "", "",
+];
+
+/// Errors from disassembling an image.
+#[derive(Debug, Error)]
+pub enum DisasmError {
+    #[error("ELF error: {0}")]
+    Elf(#[from] ElfError),
+
+    #[error("DWARF error: {0}")]
+    Dwarf(#[from] DwarfError),
+
+    #[error("Unsupported machine {0}: only ARM Thumb code can be disassembled")]
+    UnsupportedMachine(u16),
+
+    #[error("Function not found: {0}")]
+    FunctionNotFound(String),
+}
+
+/// A decoded instruction.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DisassembledInstruction {
+    /// Address.
+    pub address: u64,
+    /// Encoding: one halfword, or the first halfword in the high 16 bits.
+    pub raw: u32,
+    /// Size in bytes (2 or 4).
+    pub size: u8,
+    /// Mnemonic, with condition and width suffixes.
+    pub mnemonic: String,
+    /// Operands.
+    pub operands: String,
+    /// Branch target or PC-relative address.
+    #[serde(default)]
+    pub target: Option<u64>,
+    /// Symbol at the target, as `name` or `name+0x10`.
+    #[serde(default)]
+    pub target_symbol: Option<String>,
+    /// Extra detail, such as the value a literal load reads.
+    #[serde(default)]
+    pub comment: Option<String>,
+}
+
+/// One line of a disassembly listing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum DisassemblyLine {
+    /// Symbol defined at an address.
+    Symbol { name: String, address: u64 },
+    /// Source line the following instructions were generated from.
+    Source {
+        file: PathBuf,
+        line: u32,
+        text: String,
+    },
+    /// Decoded instruction.
+    Instruction(DisassembledInstruction),
+    /// Data in a code section, such as a literal pool entry.
+    Data { address: u64, size: u8, value: u32 },
+}
+
+/// Render a listing in the style of `objdump -d -S`.
+pub fn disassembly_text(lines: &[DisassemblyLine]) -> String {
+    let mut text = String::new();
+    for line in lines {
+        match line {
+            DisassemblyLine::Symbol { name, address } => {
+                text.push_str(&format!("\n{:08x} <{}>:\n", address, name));
+            }
+            DisassemblyLine::Source {
+                file,
+                line,
+                text: source,
+            } => {
+                text.push_str(&format!("{}:{}\n{}\n", file.display(), line, source));
+            }
+            DisassemblyLine::Instruction(insn) => {
+                let raw = if insn.size == 4 {
+                    format!("{:04x} {:04x}", insn.raw >> 16, insn.raw & 0xFFFF)
+                } else {
+                    format!("{:04x}     ", insn.raw)
+                };
+                text.push_str(&format!(
+                    "{:8x}:\t{} \t{}\t{}",
+                    insn.address, raw, insn.mnemonic, insn.operands
+                ));
+                if let Some(symbol) = &insn.target_symbol {
+                    text.push_str(&format!(" <{}>", symbol));
+                }
+                if let Some(comment) = &insn.comment {
+                    text.push_str(&format!("\t@ {}", comment));
+                }
+                text.push('\n');
+            }
+            DisassemblyLine::Data {
+                address,
+                size,
+                value,
+            } => {
+                let directive = match size {
+                    4 => ".word",
+                    2 => ".short",
+                    _ => ".byte",
+                };
+                let width = *size as usize * 2;
+                text.push_str(&format!(
+                    "{:8x}:\t{:0width$x}\t{}\t0x{:0width$x}\n",
+                    address,
+                    value,
+                    directive,
+                    value,
+                    width = width
+                ));
+            }
+        }
+    }
+    text
+}
+
+/// Disassemble the executable sections of an ARM image, or one function.
+///
+/// With debug information, each run of instructions from the same source
+/// line is preceded by that line.
+pub fn disassemble(
+    elf: &ElfFile,
+    debug: Option<&DebugInfo>,
+    function: Option<&str>,
+) -> Result<Vec<DisassemblyLine>, DisasmError> {
+    if elf.machine != EM_ARM {
+        return Err(DisasmError::UnsupportedMachine(elf.machine));
+    }
+    let symbols = SymbolMap::new(elf);
+
+    let ranges: Vec<(&ElfSection, u64, u64)> = match function {
+        Some(name) => {
+            let symbol = elf
+                .symbol(name)
+                .filter(|s| s.kind == SymbolKind::Func)
+                .ok_or_else(|| DisasmError::FunctionNotFound(name.to_string()))?;
+            let section = elf
+                .symbol_section(symbol)
+                .ok_or_else(|| DisasmError::FunctionNotFound(name.to_string()))?;
+            let start = symbol.value & !1;
+            vec![(section, start, start + symbol.size.max(2))]
+        }
+        None => elf
+            .sections
+            .iter()
+            .filter(|s| s.flags & SHF_EXECINSTR != 0 && s.section_type != SHT_NOBITS)
+            .map(|s| (s, s.addr, s.addr + s.size))
+            .collect(),
+    };
+
+    let mut read_line = source_reader();
+    let mut lines = Vec::new();
+    for (section, start, end) in ranges {
+        let Some(data) = elf.section_data(section) else {
+            continue;
+        };
+        let index = elf
+            .sections
+            .iter()
+            .position(|s| std::ptr::eq(s, section))
+            .unwrap_or_default();
+        let mapping = mapping_symbols(elf, index);
+        let read = |address: u64, size: u64| -> Option<u32> {
+            let offset = usize::try_from(address.checked_sub(section.addr)?).ok()?;
+            let bytes = data.get(offset..offset + size as usize)?;
+            let value = bytes
+                .iter()
+                .rev()
+                .fold(0, |value, &b| value << 8 | u32::from(b));
+            Some(if elf.big_endian {
+                swap(value, size)
+            } else {
+                value
+            })
+        };
+
+        let mut decoder = ThumbDecoder::default();
+        let mut shown: Option<(PathBuf, u32)> = None;
+        let mut address = start;
+        while address < end {
+            for name in symbols.at(address) {
+                lines.push(DisassemblyLine::Symbol {
+                    name: name.to_string(),
+                    address,
+                });
+                decoder = ThumbDecoder::default();
+            }
+
+            // Data runs to the next mapping symbol
+            let next_mapping = mapping
+                .iter()
+                .find(|&&(at, _)| at > address)
+                .map_or(end, |&(at, _)| at.min(end));
+            let is_data = mapping
+                .iter()
+                .rev()
+                .find(|&&(at, _)| at <= address)
+                .is_some_and(|&(_, data)| data);
+            if is_data {
+                let size = [4, 2, 1]
+                    .into_iter()
+                    .find(|&size| address % size == 0 && address + size <= next_mapping)
+                    .unwrap_or(1);
+                let Some(value) = read(address, size) else {
+                    break;
+                };
+                lines.push(DisassemblyLine::Data {
+                    address,
+                    size: size as u8,
+                    value,
+                });
+                address += size;
+                continue;
+            }
+
+            let Some(hw1) = read(address, 2).map(|v| v as u16) else {
+                break;
+            };
+            let hw2 = read(address + 2, 2).map(|v| v as u16);
+            let decoded = decoder.decode(address, hw1, hw2);
+
+            if let Some(source) = debug.and_then(|debug| debug.source_for(address)) {
+                let key = (source.path, source.line);
+                if shown.as_ref() != Some(&key) {
+                    lines.push(DisassemblyLine::Source {
+                        text: read_line(&key.0, key.1).unwrap_or_default(),
+                        file: key.0.clone(),
+                        line: key.1,
+                    });
+                    shown = Some(key);
+                }
+            }
+
+            let raw = match (decoded.size, hw2) {
+                (4, Some(hw2)) => u32::from(hw1) << 16 | u32::from(hw2),
+                _ => u32::from(hw1),
+            };
+            let mut comment = decoded.comment;
+            let mut target_symbol = decoded.target.and_then(|t| symbols.describe(t));
+            if decoded.literal {
+                // Show the word a literal load reads, and what it points to
+                if let Some(value) = decoded.target.and_then(|t| read(t, 4)) {
+                    let pointee = symbols.containing(u64::from(value));
+                    comment = Some(match pointee {
+                        Some(name) => format!("0x{:x} <{}>", value, name),
+                        None => format!("0x{:x}", value),
+                    });
+                }
+                target_symbol = None;
+            }
+            lines.push(DisassemblyLine::Instruction(DisassembledInstruction {
+                address,
+                raw,
+                size: decoded.size,
+                mnemonic: decoded.mnemonic,
+                operands: decoded.operands,
+                target: decoded.target,
+                target_symbol,
+                comment,
+            }));
+            address += u64::from(decoded.size);
+        }
+    }
+    Ok(lines)
+}
+
+fn swap(value: u32, size: u64) -> u32 {
+    match size {
+        4 => value.swap_bytes(),
+        2 => u32::from((value as u16).swap_bytes()),
+        _ => value,
+    }
+}
+
+/// Code/data transitions of a section from its `$t` and `$d` mapping
+/// symbols, sorted by address; `true` marks data.
+fn mapping_symbols(elf: &ElfFile, section_index: usize) -> Vec<(u64, bool)> {
+    let mut mapping: Vec<(u64, bool)> = elf
+        .symbols
+        .iter()
+        .filter(|s| s.section_index as usize == section_index)
+        .filter_map(|s| {
+            let kind = s.name.strip_prefix('$')?;
+            match kind.split('.').next()? {
+                "d" => Some((s.value, true)),
+                "t" | "a" => Some((s.value & !1, false)),
+                _ => None,
+            }
+        })
+        .collect();
+    mapping.sort();
+    mapping
+}
+
+/// Named symbols by address, for labels and target annotation.
+struct SymbolMap {
+    /// Address, size and name, sorted by address.
+    symbols: Vec<(u64, u64, String)>,
+}
+
+impl SymbolMap {
+    fn new(elf: &ElfFile) -> Self {
+        let mut symbols: Vec<(u64, u64, String)> = elf
+            .symbols
+            .iter()
+            .filter(|s| !s.is_undefined() && !s.name.is_empty() && !s.name.starts_with('$'))
+            .filter(|s| {
+                matches!(
+                    s.kind,
+                    SymbolKind::Func | SymbolKind::Object | SymbolKind::NoType
+                )
+            })
+            .map(|s| {
+                let address = if s.kind == SymbolKind::Func {
+                    s.value & !1
+                } else {
+                    s.value
+                };
+                (address, s.size, s.name.clone())
+            })
+            .collect();
+        symbols.sort();
+        symbols.dedup();
+        Self { symbols }
+    }
+
+    /// Names of the symbols starting at an address.
+    fn at(&self, address: u64) -> impl Iterator<Item = &str> {
+        let start = self.symbols.partition_point(|s| s.0 < address);
+        self.symbols[start..]
+            .iter()
+            .take_while(move |s| s.0 == address)
+            .map(|s| s.2.as_str())
+    }
+
+    /// Nearest preceding symbol, as `name` or `name+0x10`.
+    fn describe(&self, address: u64) -> Option<String> {
+        let idx = self.symbols.partition_point(|s| s.0 <= address);
+        let (start, _, name) = self.symbols[..idx].last()?;
+        Some(match address - start {
+            0 => name.clone(),
+            offset => format!("{}+0x{:x}", name, offset),
+        })
+    }
+
+    /// Symbol whose extent covers an address (Thumb bit ignored).
+    fn containing(&self, address: u64) -> Option<String> {
+        let address = address & !1;
+        let idx = self.symbols.partition_point(|s| s.0 <= address);
+        self.symbols[..idx]
+            .iter()
+            .rev()
+            .find(|(start, size, _)| address < start + (*size).max(1))
+            .and(self.describe(address))
+    }
+}
+
+/// One decoded instruction, before annotation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Decoded {
+    size: u8,
+    mnemonic: String,
+    operands: String,
+    target: Option<u64>,
+    /// Whether `target` is a literal the instruction loads.
+    literal: bool,
+    comment: Option<String>,
+}
+
+impl Decoded {
+    fn new(size: u8, mnemonic: impl Into<String>, operands: impl Into<String>) -> Self {
+        Self {
+            size,
+            mnemonic: mnemonic.into(),
+            operands: operands.into(),
+            target: None,
+            literal: false,
+            comment: None,
+        }
+    }
+
+    fn with_target(mut self, target: u64) -> Self {
+        self.target = Some(target & 0xFFFF_FFFF);
+        self
+    }
+
+    fn with_literal(mut self, target: u64) -> Self {
+        self.literal = true;
+        self.with_target(target)
+    }
+
+    /// Note large immediates in hex.
+    fn with_immediate(mut self, value: u32) -> Self {
+        if value > 255 {
+            self.comment = Some(format!("0x{:x}", value));
+        }
+        self
+    }
+
+    fn unknown16(hw: u16) -> Self {
+        Self::new(2, ".inst.n", format!("0x{:04x}", hw))
+    }
+
+    fn unknown32(hw1: u16, hw2: u16) -> Self {
+        Self::new(4, ".inst.w", format!("0x{:04x}{:04x}", hw1, hw2))
+    }
+}
+
+/// Thumb decoder, tracking IT blocks across instructions.
+#[derive(Debug, Default)]
+struct ThumbDecoder {
+    /// ITSTATE: first condition in the high nibble, remaining mask below.
+    it: u8,
+}
+
+/// Whether a first halfword starts a 32-bit instruction.
+fn is_wide(hw1: u16) -> bool {
+    hw1 >> 11 >= 0b11101
+}
+
+fn reg(n: impl Into<u32>) -> &'static str {
+    REGISTERS[(n.into() & 15) as usize]
+}
+
+/// Register list, e.g. `{r4, r5, lr}`.
+fn reglist(bits: u16) -> String {
+    let names: Vec<&str> = (0..16u32)
+        .filter(|i| bits & (1 << i) != 0)
+        .map(reg)
+        .collect();
+    format!("{{{}}}", names.join(", "))
+}
+
+/// Memory operand with an optional offset and indexing mode.
+fn memory(rn: u32, offset: i64, index: bool, writeback: bool) -> String {
+    match (index, writeback) {
+        (true, false) if offset == 0 => format!("[{}]", reg(rn)),
+        (true, false) => format!("[{}, #{}]", reg(rn), offset),
+        (true, true) => format!("[{}, #{}]!", reg(rn), offset),
+        (false, _) => format!("[{}], #{}", reg(rn), offset),
+    }
+}
+
+/// `, <shift> #n` suffix of a shifted register operand.
+fn shift_suffix(shift_type: u16, imm5: u32) -> String {
+    match (shift_type, imm5) {
+        (0, 0) => String::new(),
+        (0, n) => format!(", lsl #{}", n),
+        (1, n) => format!(", lsr #{}", if n == 0 { 32 } else { n }),
+        (2, n) => format!(", asr #{}", if n == 0 { 32 } else { n }),
+        (_, 0) => ", rrx".to_string(),
+        (_, n) => format!(", ror #{}", n),
+    }
+}
+
+/// Expand a Thumb-2 modified immediate.
+fn thumb_expand_imm(imm12: u32) -> u32 {
+    let imm8 = imm12 & 0xFF;
+    if imm12 >> 10 == 0 {
+        match (imm12 >> 8) & 3 {
+            0 => imm8,
+            1 => imm8 << 16 | imm8,
+            2 => imm8 << 24 | imm8 << 8,
+            _ => imm8 * 0x0101_0101,
+        }
+    } else {
+        (0x80 | (imm12 & 0x7F)).rotate_right(imm12 >> 7)
+    }
+}
+
+fn sign_extend(value: u32, bits: u32) -> i64 {
+    let shift = 64 - bits;
+    ((value as i64) << shift) >> shift
+}
+
+/// Special register name for MRS/MSR.
+fn special_register(sysm: u16) -> String {
+    let name = match sysm {
+        0 => "apsr",
+        1 => "iapsr",
+        2 => "eapsr",
+        3 => "xpsr",
+        5 => "ipsr",
+        6 => "epsr",
+        7 => "iepsr",
+        8 => "msp",
+        9 => "psp",
+        16 => "primask",
+        17 => "basepri",
+        18 => "basepri_max",
+        19 => "faultmask",
+        20 => "control",
+        other => return other.to_string(),
+    };
+    name.to_string()
+}
+
+/// Name of a data-processing opcode shared by the 32-bit register and
+/// modified-immediate forms; `None` for unallocated opcodes.
+fn data_processing(op: u16) -> Option<&'static str> {
+    Some(match op {
+        0 => "and",
+        1 => "bic",
+        2 => "orr",
+        3 => "orn",
+        4 => "eor",
+        8 => "add",
+        10 => "adc",
+        11 => "sbc",
+        13 => "sub",
+        14 => "rsb",
+        _ => return None,
+    })
+}
+
+impl ThumbDecoder {
+    /// Decode the instruction at `address`. `hw2` is the following
+    /// halfword, if any.
+    fn decode(&mut self, address: u64, hw1: u16, hw2: Option<u16>) -> Decoded {
+        let in_it = self.it & 0xF != 0;
+        let cond = if in_it {
+            CONDITIONS[(self.it >> 4) as usize]
+        } else {
+            ""
+        };
+
+        let decoded = match (is_wide(hw1), hw2) {
+            (true, Some(hw2)) => self.decode32(address, hw1, hw2, cond),
+            (true, None) => Decoded::unknown16(hw1),
+            (false, _) => self.decode16(address, hw1, in_it, cond),
+        };
+
+        if in_it {
+            self.it = if self.it & 7 == 0 {
+                0
+            } else {
+                (self.it & 0xE0) | ((self.it << 1) & 0x1F)
+            };
+        }
+        if hw1 & 0xFF00 == 0xBF00 && hw1 & 0xF != 0 {
+            self.it = hw1 as u8;
+        }
+        decoded
+    }
+
+    fn decode16(&self, address: u64, hw: u16, in_it: bool, cond: &str) -> Decoded {
+        let low = |shift: u16| u32::from((hw >> shift) & 7);
+        // Flag-setting forms drop the S inside an IT block
+        let s = |base: &str| {
+            if in_it {
+                format!("{}{}", base, cond)
+            } else {
+                format!("{}s", base)
+            }
+        };
+        let c = |base: &str| format!("{}{}", base, cond);
+        let pc_base = (address + 4) & !3;
+
+        match hw >> 11 {
+            0b00000..=0b00010 => {
+                let imm5 = u32::from((hw >> 6) & 31);
+                let (rd, rm) = (reg(low(0)), reg(low(3)));
+                match hw >> 11 {
+                    0 if imm5 == 0 => Decoded::new(2, s("mov"), format!("{}, {}", rd, rm)),
+                    0 => Decoded::new(2, s("lsl"), format!("{}, {}, #{}", rd, rm, imm5)),
+                    op => {
+                        let name = if op == 1 { "lsr" } else { "asr" };
+                        let amount = if imm5 == 0 { 32 } else { imm5 };
+                        Decoded::new(2, s(name), format!("{}, {}, #{}", rd, rm, amount))
+                    }
+                }
+            }
+            0b00011 => {
+                let name = if hw & 0x200 != 0 { "sub" } else { "add" };
+                let third = if hw & 0x400 != 0 {
+                    format!("#{}", low(6))
+                } else {
+                    reg(low(6)).to_string()
+                };
+                Decoded::new(
+                    2,
+                    s(name),
+                    format!("{}, {}, {}", reg(low(0)), reg(low(3)), third),
+                )
+            }
+            0b00100..=0b00111 => {
+                let mnemonic = match (hw >> 11) & 3 {
+                    0 => s("mov"),
+                    1 => c("cmp"),
+                    2 => s("add"),
+                    _ => s("sub"),
+                };
+                Decoded::new(2, mnemonic, format!("{}, #{}", reg(low(8)), hw & 0xFF))
+            }
+            0b01000 if hw & 0x400 == 0 => {
+                let (rdn, rm) = (reg(low(0)), reg(low(3)));
+                let two = format!("{}, {}", rdn, rm);
+                match (hw >> 6) & 15 {
+                    0 => Decoded::new(2, s("and"), two),
+                    1 => Decoded::new(2, s("eor"), two),
+                    2 => Decoded::new(2, s("lsl"), two),
+                    3 => Decoded::new(2, s("lsr"), two),
+                    4 => Decoded::new(2, s("asr"), two),
+                    5 => Decoded::new(2, s("adc"), two),
+                    6 => Decoded::new(2, s("sbc"), two),
+                    7 => Decoded::new(2, s("ror"), two),
+                    8 => Decoded::new(2, c("tst"), two),
+                    9 => Decoded::new(2, s("rsb"), format!("{}, #0", two)),
+                    10 => Decoded::new(2, c("cmp"), two),
+                    11 => Decoded::new(2, c("cmn"), two),
+                    12 => Decoded::new(2, s("orr"), two),
+                    13 => Decoded::new(2, s("mul"), format!("{}, {}", two, rdn)),
+                    14 => Decoded::new(2, s("bic"), two),
+                    _ => Decoded::new(2, s("mvn"), two),
+                }
+            }
+            0b01000 => {
+                let rm = reg((hw >> 3) & 15);
+                let rdn = reg(((hw >> 4) & 8) | (hw & 7));
+                match (hw >> 8) & 3 {
+                    0 => Decoded::new(2, c("add"), format!("{}, {}", rdn, rm)),
+                    1 => Decoded::new(2, c("cmp"), format!("{}, {}", rdn, rm)),
+                    2 => Decoded::new(2, c("mov"), format!("{}, {}", rdn, rm)),
+                    _ if hw & 0x80 != 0 => Decoded::new(2, c("blx"), rm),
+                    _ => Decoded::new(2, c("bx"), rm),
+                }
+            }
+            0b01001 => {
+                let imm = u64::from(hw & 0xFF) * 4;
+                Decoded::new(2, c("ldr"), format!("{}, [pc, #{}]", reg(low(8)), imm))
+                    .with_literal(pc_base + imm)
+            }
+            0b01010 | 0b01011 => {
+                let name = [
+                    "str", "strh", "strb", "ldrsb", "ldr", "ldrh", "ldrb", "ldrsh",
+                ][usize::from((hw >> 9) & 7)];
+                Decoded::new(
+                    2,
+                    c(name),
+                    format!("{}, [{}, {}]", reg(low(0)), reg(low(3)), reg(low(6))),
+                )
+            }
+            0b01100..=0b10001 => {
+                let (name, scale) = match hw >> 11 {
+                    0b01100 => ("str", 4),
+                    0b01101 => ("ldr", 4),
+                    0b01110 => ("strb", 1),
+                    0b01111 => ("ldrb", 1),
+                    0b10000 => ("strh", 2),
+                    _ => ("ldrh", 2),
+                };
+                let offset = i64::from((hw >> 6) & 31) * scale;
+                Decoded::new(
+                    2,
+                    c(name),
+                    format!("{}, {}", reg(low(0)), memory(low(3), offset, true, false)),
+                )
+            }
+            0b10010 | 0b10011 => {
+                let name = if hw & 0x800 != 0 { "ldr" } else { "str" };
+                let offset = i64::from(hw & 0xFF) * 4;
+                Decoded::new(
+                    2,
+                    c(name),
+                    format!("{}, {}", reg(low(8)), memory(13, offset, true, false)),
+                )
+            }
+            0b10100 => {
+                let imm = u64::from(hw & 0xFF) * 4;
+                Decoded::new(
+                    2,
+                    c("adr"),
+                    format!("{}, 0x{:x}", reg(low(8)), pc_base + imm),
+                )
+                .with_target(pc_base + imm)
+            }
+            0b10101 => Decoded::new(
+                2,
+                c("add"),
+                format!("{}, sp, #{}", reg(low(8)), u32::from(hw & 0xFF) * 4),
+            ),
+            0b10110 | 0b10111 => self.decode_misc16(address, hw, cond),
+            0b11000 => Decoded::new(
+                2,
+                c("stmia"),
+                format!("{}!, {}", reg(low(8)), reglist(hw & 0xFF)),
+            ),
+            0b11001 => {
+                let rn = low(8);
+                let writeback = if hw & (1 << rn) == 0 { "!" } else { "" };
+                Decoded::new(
+                    2,
+                    c("ldmia"),
+                    format!("{}{}, {}", reg(rn), writeback, reglist(hw & 0xFF)),
+                )
+            }
+            0b11010 | 0b11011 => {
+                let cc = usize::from((hw >> 8) & 15);
+                match cc {
+                    14 => Decoded::new(2, c("udf"), format!("#{}", hw & 0xFF)),
+                    15 => Decoded::new(2, c("svc"), format!("{}", hw & 0xFF)),
+                    _ => {
+                        let offset = sign_extend(u32::from(hw & 0xFF), 8) * 2;
+                        let target = (address as i64 + 4 + offset) as u64;
+                        Decoded::new(
+                            2,
+                            format!("b{}.n", CONDITIONS[cc]),
+                            format!("0x{:x}", target),
+                        )
+                        .with_target(target)
+                    }
+                }
+            }
+            0b11100 => {
+                let offset = sign_extend(u32::from(hw & 0x7FF), 11) * 2;
+                let target = (address as i64 + 4 + offset) as u64;
+                Decoded::new(2, format!("{}.n", c("b")), format!("0x{:x}", target))
+                    .with_target(target)
+            }
+            _ => Decoded::unknown16(hw),
+        }
+    }
+
+    fn decode_misc16(&self, address: u64, hw: u16, cond: &str) -> Decoded {
+        let c = |base: &str| format!("{}{}", base, cond);
+        let two = || format!("{}, {}", reg(hw & 7), reg((hw >> 3) & 7));
+
+        match (hw >> 8) & 15 {
+            0b0000 => {
+                let name = if hw & 0x80 != 0 { "sub" } else { "add" };
+                Decoded::new(2, c(name), format!("sp, #{}", u32::from(hw & 0x7F) * 4))
+            }
+            0b0001 | 0b0011 | 0b1001 | 0b1011 => {
+                let imm = u64::from((hw >> 9) & 1) << 6 | u64::from((hw >> 3) & 31) << 1;
+                let target = address + 4 + imm;
+                let name = if hw & 0x800 != 0 { "cbnz" } else { "cbz" };
+                Decoded::new(2, name, format!("{}, 0x{:x}", reg(hw & 7), target))
+                    .with_target(target)
+            }
+            0b0010 => {
+                let name = ["sxth", "sxtb", "uxth", "uxtb"][usize::from((hw >> 6) & 3)];
+                Decoded::new(2, c(name), two())
+            }
+            0b0100 | 0b0101 => {
+                let list = (hw & 0xFF) | if hw & 0x100 != 0 { 1 << 14 } else { 0 };
+                Decoded::new(2, c("push"), reglist(list))
+            }
+            0b0110 if hw & 0xFFE8 == 0xB660 => {
+                let name = if hw & 0x10 != 0 { "cpsid" } else { "cpsie" };
+                let mut flags = String::new();
+                if hw & 2 != 0 {
+                    flags.push('i');
+                }
+                if hw & 1 != 0 {
+                    flags.push('f');
+                }
+                Decoded::new(2, name, flags)
+            }
+            0b1010 => match (hw >> 6) & 3 {
+                0 => Decoded::new(2, c("rev"), two()),
+                1 => Decoded::new(2, c("rev16"), two()),
+                3 => Decoded::new(2, c("revsh"), two()),
+                _ => Decoded::unknown16(hw),
+            },
+            0b1100 | 0b1101 => {
+                let list = (hw & 0xFF) | if hw & 0x100 != 0 { 1 << 15 } else { 0 };
+                Decoded::new(2, c("pop"), reglist(list))
+            }
+            0b1110 => Decoded::new(2, "bkpt", format!("0x{:04x}", hw & 0xFF)),
+            0b1111 if hw & 0xF != 0 => {
+                let first = (hw >> 4) & 15;
+                let mask = hw & 15;
+                let mut name = "it".to_string();
+                for bit in (mask.trailing_zeros() + 1..4).rev() {
+                    name.push(if (mask >> bit) & 1 == first & 1 {
+                        't'
+                    } else {
+                        'e'
+                    });
+                }
+                Decoded::new(2, name, CONDITIONS[usize::from(first)])
+            }
+            0b1111 => match (hw >> 4) & 15 {
+                0 => Decoded::new(2, c("nop"), ""),
+                1 => Decoded::new(2, c("yield"), ""),
+                2 => Decoded::new(2, c("wfe"), ""),
+                3 => Decoded::new(2, c("wfi"), ""),
+                4 => Decoded::new(2, c("sev"), ""),
+                _ => Decoded::unknown16(hw),
+            },
+            _ => Decoded::unknown16(hw),
+        }
+    }
+
+    fn decode32(&self, address: u64, hw1: u16, hw2: u16, cond: &str) -> Decoded {
+        let rn = u32::from(hw1 & 15);
+        let rd = u32::from((hw2 >> 8) & 15);
+        let rm = u32::from(hw2 & 15);
+        let rt = u32::from((hw2 >> 12) & 15);
+        let set_flags = hw1 & 0x10 != 0;
+        let c = |base: &str| format!("{}{}", base, cond);
+        let cs = |base: &str| format!("{}{}{}", base, if set_flags { "s" } else { "" }, cond);
+        let unknown = || Decoded::unknown32(hw1, hw2);
+        let pc_base = (address + 4) & !3;
+
+        match (hw1 >> 11) & 3 {
+            0b01 if hw1 & 0xFE40 == 0xE800 => {
+                let load = hw1 & 0x10 != 0;
+                let writeback = hw1 & 0x20 != 0;
+                let list = reglist(hw2);
+                match ((hw1 >> 7) & 3, load) {
+                    (1, true) if writeback && rn == 13 => Decoded::new(4, c("pop.w"), list),
+                    (2, false) if writeback && rn == 13 => Decoded::new(4, c("push.w"), list),
+                    (op @ (1 | 2), _) => {
+                        let name = match (op, load) {
+                            (1, true) => "ldmia.w",
+                            (1, false) => "stmia.w",
+                            (_, true) => "ldmdb",
+                            (_, false) => "stmdb",
+                        };
+                        let wb = if writeback { "!" } else { "" };
+                        Decoded::new(4, c(name), format!("{}{}, {}", reg(rn), wb, list))
+                    }
+                    _ => unknown(),
+                }
+            }
+            0b01 if hw1 & 0xFE40 == 0xE840 => {
+                if hw1 & 0xFFF0 == 0xE8D0 && hw2 & 0xFFE0 == 0xF000 {
+                    return if hw2 & 0x10 != 0 {
+                        Decoded::new(4, c("tbh"), format!("[{}, {}, lsl #1]", reg(rn), reg(rm)))
+                    } else {
+                        Decoded::new(4, c("tbb"), format!("[{}, {}]", reg(rn), reg(rm)))
+                    };
+                }
+                let index = hw1 & 0x100 != 0;
+                let writeback = hw1 & 0x20 != 0;
+                let load = hw1 & 0x10 != 0;
+                let imm = i64::from(hw2 & 0xFF) * 4;
+                if index || writeback {
+                    let offset = if hw1 & 0x80 != 0 { imm } else { -imm };
+                    let name = if load { "ldrd" } else { "strd" };
+                    return Decoded::new(
+                        4,
+                        c(name),
+                        format!(
+                            "{}, {}, {}",
+                            reg(rt),
+                            reg(rd),
+                            memory(rn, offset, index, writeback)
+                        ),
+                    );
+                }
+                match hw1 & 0xFFF0 {
+                    0xE850 => Decoded::new(
+                        4,
+                        c("ldrex"),
+                        format!("{}, {}", reg(rt), memory(rn, imm, true, false)),
+                    ),
+                    0xE840 => Decoded::new(
+                        4,
+                        c("strex"),
+                        format!("{}, {}, {}", reg(rd), reg(rt), memory(rn, imm, true, false)),
+                    ),
+                    _ => unknown(),
+                }
+            }
+            0b01 if hw1 & 0xFE00 == 0xEA00 => {
+                let op = (hw1 >> 5) & 15;
+                let imm5 = u32::from((hw2 >> 12) & 7) << 2 | u32::from((hw2 >> 6) & 3);
+                let shift_type = (hw2 >> 4) & 3;
+                let shifted = format!("{}{}", reg(rm), shift_suffix(shift_type, imm5));
+                let compare = rd == 15 && set_flags;
+                match op {
+                    0 if compare => {
+                        Decoded::new(4, c("tst.w"), format!("{}, {}", reg(rn), shifted))
+                    }
+                    4 if compare => Decoded::new(4, c("teq"), format!("{}, {}", reg(rn), shifted)),
+                    8 if compare => {
+                        Decoded::new(4, c("cmn.w"), format!("{}, {}", reg(rn), shifted))
+                    }
+                    13 if compare => {
+                        Decoded::new(4, c("cmp.w"), format!("{}, {}", reg(rn), shifted))
+                    }
+                    2 if rn == 15 => {
+                        let name = match (shift_type, imm5) {
+                            (0, 0) => {
+                                return Decoded::new(
+                                    4,
+                                    cs("mov") + ".w",
+                                    format!("{}, {}", reg(rd), reg(rm)),
+                                )
+                            }
+                            (0, _) => "lsl",
+                            (1, _) => "lsr",
+                            (2, _) => "asr",
+                            (_, 0) => {
+                                return Decoded::new(
+                                    4,
+                                    cs("rrx"),
+                                    format!("{}, {}", reg(rd), reg(rm)),
+                                )
+                            }
+                            _ => "ror",
+                        };
+                        let amount = if imm5 == 0 { 32 } else { imm5 };
+                        Decoded::new(
+                            4,
+                            cs(name) + ".w",
+                            format!("{}, {}, #{}", reg(rd), reg(rm), amount),
+                        )
+                    }
+                    3 if rn == 15 => {
+                        Decoded::new(4, cs("mvn") + ".w", format!("{}, {}", reg(rd), shifted))
+                    }
+                    _ => match data_processing(op) {
+                        Some(name) => Decoded::new(
+                            4,
+                            cs(name) + ".w",
+                            format!("{}, {}, {}", reg(rd), reg(rn), shifted),
+                        ),
+                        None => unknown(),
+                    },
+                }
+            }
+            0b10 if hw2 & 0x8000 == 0 => {
+                let imm12 = u32::from((hw1 >> 10) & 1) << 11
+                    | u32::from((hw2 >> 12) & 7) << 8
+                    | u32::from(hw2 & 0xFF);
+                if hw1 & 0x0200 == 0 {
+                    let op = (hw1 >> 5) & 15;
+                    let imm = thumb_expand_imm(imm12);
+                    let compare = rd == 15 && set_flags;
+                    let decoded = match op {
+                        0 if compare => Decoded::new(4, c("tst"), format!("{}, #{}", reg(rn), imm)),
+                        4 if compare => Decoded::new(4, c("teq"), format!("{}, #{}", reg(rn), imm)),
+                        8 if compare => {
+                            Decoded::new(4, c("cmn.w"), format!("{}, #{}", reg(rn), imm))
+                        }
+                        13 if compare => {
+                            Decoded::new(4, c("cmp.w"), format!("{}, #{}", reg(rn), imm))
+                        }
+                        2 if rn == 15 => {
+                            Decoded::new(4, cs("mov") + ".w", format!("{}, #{}", reg(rd), imm))
+                        }
+                        3 if rn == 15 => {
+                            Decoded::new(4, cs("mvn"), format!("{}, #{}", reg(rd), imm))
+                        }
+                        _ => match data_processing(op) {
+                            Some(name) => Decoded::new(
+                                4,
+                                cs(name) + ".w",
+                                format!("{}, {}, #{}", reg(rd), reg(rn), imm),
+                            ),
+                            None => return unknown(),
+                        },
+                    };
+                    return decoded.with_immediate(imm);
+                }
+
+                let lsb = u32::from((hw2 >> 12) & 7) << 2 | u32::from((hw2 >> 6) & 3);
+                match (hw1 >> 4) & 31 {
+                    0 if rn == 15 => {
+                        Decoded::new(4, c("addw"), format!("{}, pc, #{}", reg(rd), imm12))
+                            .with_target(pc_base + u64::from(imm12))
+                    }
+                    10 if rn == 15 => {
+                        Decoded::new(4, c("subw"), format!("{}, pc, #{}", reg(rd), imm12))
+                            .with_target(pc_base - u64::from(imm12))
+                    }
+                    0 => Decoded::new(
+                        4,
+                        c("addw"),
+                        format!("{}, {}, #{}", reg(rd), reg(rn), imm12),
+                    )
+                    .with_immediate(imm12),
+                    10 => Decoded::new(
+                        4,
+                        c("subw"),
+                        format!("{}, {}, #{}", reg(rd), reg(rn), imm12),
+                    )
+                    .with_immediate(imm12),
+                    op @ (4 | 12) => {
+                        let imm16 = u32::from(hw1 & 15) << 12 | imm12;
+                        let name = if op == 4 { "movw" } else { "movt" };
+                        Decoded::new(4, c(name), format!("{}, #{}", reg(rd), imm16))
+                            .with_immediate(imm16)
+                    }
+                    op @ (20 | 28) => {
+                        let name = if op == 20 { "sbfx" } else { "ubfx" };
+                        let width = u32::from(hw2 & 31) + 1;
+                        Decoded::new(
+                            4,
+                            c(name),
+                            format!("{}, {}, #{}, #{}", reg(rd), reg(rn), lsb, width),
+                        )
+                    }
+                    22 => {
+                        let msb = u32::from(hw2 & 31);
+                        let Some(width) = (msb + 1).checked_sub(lsb) else {
+                            return unknown();
+                        };
+                        if rn == 15 {
+                            Decoded::new(4, c("bfc"), format!("{}, #{}, #{}", reg(rd), lsb, width))
+                        } else {
+                            Decoded::new(
+                                4,
+                                c("bfi"),
+                                format!("{}, {}, #{}, #{}", reg(rd), reg(rn), lsb, width),
+                            )
+                        }
+                    }
+                    op @ (16 | 24) if lsb == 0 && hw1 & 0x20 == 0 => {
+                        let (name, saturate) = if op == 16 {
+                            ("ssat", u32::from(hw2 & 31) + 1)
+                        } else {
+                            ("usat", u32::from(hw2 & 31))
+                        };
+                        Decoded::new(
+                            4,
+                            c(name),
+                            format!("{}, #{}, {}", reg(rd), saturate, reg(rn)),
+                        )
+                    }
+                    _ => unknown(),
+                }
+            }
+            0b10 => match hw2 & 0xD000 {
+                0x8000 if (hw1 >> 6) & 15 < 14 => {
+                    let imm = u32::from((hw1 >> 10) & 1) << 20
+                        | u32::from((hw2 >> 11) & 1) << 19
+                        | u32::from((hw2 >> 13) & 1) << 18
+                        | u32::from(hw1 & 0x3F) << 12
+                        | u32::from(hw2 & 0x7FF) << 1;
+                    let target = (address as i64 + 4 + sign_extend(imm, 21)) as u64;
+                    let cc = CONDITIONS[usize::from((hw1 >> 6) & 15)];
+                    Decoded::new(4, format!("b{}.w", cc), format!("0x{:x}", target))
+                        .with_target(target)
+                }
+                0x8000 => self.decode_control(hw1, hw2, cond),
+                0x9000 | 0xD000 => {
+                    let s = u32::from((hw1 >> 10) & 1);
+                    let j1 = u32::from((hw2 >> 13) & 1);
+                    let j2 = u32::from((hw2 >> 11) & 1);
+                    let i1 = !(j1 ^ s) & 1;
+                    let i2 = !(j2 ^ s) & 1;
+                    let imm = s << 24
+                        | i1 << 23
+                        | i2 << 22
+                        | u32::from(hw1 & 0x3FF) << 12
+                        | u32::from(hw2 & 0x7FF) << 1;
+                    let target = (address as i64 + 4 + sign_extend(imm, 25)) as u64;
+                    let name = if hw2 & 0x4000 != 0 {
+                        c("bl")
+                    } else {
+                        format!("{}.w", c("b"))
+                    };
+                    Decoded::new(4, name, format!("0x{:x}", target)).with_target(target)
+                }
+                _ => unknown(),
+            },
+            0b11 if hw1 & 0xFE00 == 0xF800 => {
+                let sign = hw1 & 0x100 != 0;
+                let upper = hw1 & 0x80 != 0;
+                let size = usize::from((hw1 >> 5) & 3);
+                let load = hw1 & 0x10 != 0;
+                if size == 3 || (sign && !load) {
+                    return unknown();
+                }
+                let name = format!(
+                    "{}{}{}",
+                    if load { "ldr" } else { "str" },
+                    if sign { "s" } else { "" },
+                    ["b", "h", ""][size]
+                );
+                let imm12 = u64::from(hw2 & 0xFFF);
+                if load && rn == 15 {
+                    let (target, offset) = if upper {
+                        (pc_base + imm12, imm12 as i64)
+                    } else {
+                        (pc_base - imm12, -(imm12 as i64))
+                    };
+                    return Decoded::new(
+                        4,
+                        c(&name) + ".w",
+                        format!("{}, [pc, #{}]", reg(rt), offset),
+                    )
+                    .with_literal(target);
+                }
+                if upper {
+                    return Decoded::new(
+                        4,
+                        c(&name) + ".w",
+                        format!("{}, {}", reg(rt), memory(rn, imm12 as i64, true, false)),
+                    );
+                }
+                if hw2 & 0x800 != 0 {
+                    let index = hw2 & 0x400 != 0;
+                    let add = hw2 & 0x200 != 0;
+                    let writeback = hw2 & 0x100 != 0;
+                    let imm8 = i64::from(hw2 & 0xFF);
+                    if index && add && !writeback {
+                        return Decoded::new(
+                            4,
+                            c(&(name + "t")),
+                            format!("{}, {}", reg(rt), memory(rn, imm8, true, false)),
+                        );
+                    }
+                    let offset = if add { imm8 } else { -imm8 };
+                    return Decoded::new(
+                        4,
+                        c(&name),
+                        format!("{}, {}", reg(rt), memory(rn, offset, index, writeback)),
+                    );
+                }
+                if hw2 & 0xFC0 == 0 {
+                    let shift = (hw2 >> 4) & 3;
+                    let shift = if shift == 0 {
+                        String::new()
+                    } else {
+                        format!(", lsl #{}", shift)
+                    };
+                    return Decoded::new(
+                        4,
+                        c(&name) + ".w",
+                        format!("{}, [{}, {}{}]", reg(rt), reg(rn), reg(rm), shift),
+                    );
+                }
+                unknown()
+            }
+            0b11 if hw1 & 0xFF80 == 0xFB00 => match ((hw1 >> 4) & 7, (hw2 >> 4) & 3) {
+                (0, 0) if rt == 15 => Decoded::new(
+                    4,
+                    c("mul.w"),
+                    format!("{}, {}, {}", reg(rd), reg(rn), reg(rm)),
+                ),
+                (0, 0) => Decoded::new(
+                    4,
+                    c("mla"),
+                    format!("{}, {}, {}, {}", reg(rd), reg(rn), reg(rm), reg(rt)),
+                ),
+                (0, 1) => Decoded::new(
+                    4,
+                    c("mls"),
+                    format!("{}, {}, {}, {}", reg(rd), reg(rn), reg(rm), reg(rt)),
+                ),
+                _ => unknown(),
+            },
+            0b11 if hw1 & 0xFF80 == 0xFB80 => {
+                let long = |name: &str| {
+                    Decoded::new(
+                        4,
+                        c(name),
+                        format!("{}, {}, {}, {}", reg(rt), reg(rd), reg(rn), reg(rm)),
+                    )
+                };
+                let divide = |name: &str| {
+                    Decoded::new(4, c(name), format!("{}, {}, {}", reg(rd), reg(rn), reg(rm)))
+                };
+                match ((hw1 >> 4) & 7, (hw2 >> 4) & 15) {
+                    (0, 0) => long("smull"),
+                    (1, 15) => divide("sdiv"),
+                    (2, 0) => long("umull"),
+                    (3, 15) => divide("udiv"),
+                    (4, 0) => long("smlal"),
+                    (6, 0) => long("umlal"),
+                    _ => unknown(),
+                }
+            }
+            0b11 if hw1 & 0xFF00 == 0xFA00 => {
+                if hw1 & 0xFF80 == 0xFA00 && hw2 & 0xF0F0 == 0xF000 {
+                    let name = ["lsl", "lsr", "asr", "ror"][usize::from((hw1 >> 5) & 3)];
+                    return Decoded::new(
+                        4,
+                        cs(name) + ".w",
+                        format!("{}, {}, {}", reg(rd), reg(rn), reg(rm)),
+                    );
+                }
+                if hw1 & 0xFF80 == 0xFA00 && hw2 & 0xF0C0 == 0xF080 && rn == 15 {
+                    let name = match (hw1 >> 4) & 7 {
+                        0 => "sxth.w",
+                        1 => "uxth.w",
+                        4 => "sxtb.w",
+                        5 => "uxtb.w",
+                        _ => return unknown(),
+                    };
+                    let rotation = (hw2 >> 4) & 3;
+                    let rotation = if rotation == 0 {
+                        String::new()
+                    } else {
+                        format!(", ror #{}", rotation * 8)
+                    };
+                    return Decoded::new(
+                        4,
+                        c(name),
+                        format!("{}, {}{}", reg(rd), reg(rm), rotation),
+                    );
+                }
+                if hw1 & 0xFFF0 == 0xFA90 && hw2 & 0xF0C0 == 0xF080 {
+                    let name = ["rev.w", "rev16.w", "rbit", "revsh.w"][usize::from((hw2 >> 4) & 3)];
+                    return Decoded::new(4, c(name), format!("{}, {}", reg(rd), reg(rm)));
+                }
+                if hw1 & 0xFFF0 == 0xFAB0 && hw2 & 0xF0F0 == 0xF080 {
+                    return Decoded::new(4, c("clz"), format!("{}, {}", reg(rd), reg(rm)));
+                }
+                unknown()
+            }
+            _ => unknown(),
+        }
+    }
+
+    /// MSR, MRS, barriers and wide hints.
+    fn decode_control(&self, hw1: u16, hw2: u16, cond: &str) -> Decoded {
+        let c = |base: &str| format!("{}{}", base, cond);
+        if hw1 & 0xFFE0 == 0xF380 {
+            let sysm = special_register(hw2 & 0xFF);
+            return Decoded::new(4, c("msr"), format!("{}, {}", sysm, reg(hw1 & 15)));
+        }
+        if hw1 & 0xFFE0 == 0xF3E0 {
+            let sysm = special_register(hw2 & 0xFF);
+            return Decoded::new(4, c("mrs"), format!("{}, {}", reg((hw2 >> 8) & 15), sysm));
+        }
+        if hw1 == 0xF3BF {
+            let option = match hw2 & 15 {
+                15 => "sy".to_string(),
+                other => format!("#{}", other),
+            };
+            return match (hw2 >> 4) & 15 {
+                4 => Decoded::new(4, c("dsb"), option),
+                5 => Decoded::new(4, c("dmb"), option),
+                6 => Decoded::new(4, c("isb"), option),
+                _ => Decoded::unknown32(hw1, hw2),
+            };
+        }
+        if hw1 == 0xF3AF && hw2 & 0xFF00 == 0x8000 {
+            let name = match hw2 & 0xFF {
+                0 => "nop.w",
+                1 => "yield.w",
+                2 => "wfe.w",
+                3 => "wfi.w",
+                4 => "sev.w",
+                _ => return Decoded::unknown32(hw1, hw2),
+            };
+            return Decoded::new(4, c(name), "");
+        }
+        Decoded::unknown32(hw1, hw2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{build_test_elf, build_test_symtab, SHT_SYMTAB};
+
+    /// Decode a sequence of halfwords at 0x0800_0100, as `mnemonic operands`.
+    fn decode(halfwords: &[u16]) -> Vec<String> {
+        let mut decoder = ThumbDecoder::default();
+        let mut address = 0x0800_0100;
+        let mut out = Vec::new();
+        let mut i = 0;
+        while i < halfwords.len() {
+            let decoded = decoder.decode(address, halfwords[i], halfwords.get(i + 1).copied());
+            out.push(
+                format!("{} {}", decoded.mnemonic, decoded.operands)
+                    .trim_end()
+                    .to_string(),
+            );
+            i += usize::from(decoded.size / 2);
+            address += u64::from(decoded.size);
+        }
+        out
+    }
+
+    #[test]
+    fn test_decode16() {
+        assert_eq!(
+            decode(&[0xb580, 0x2001, 0x1840, 0x4770, 0xbd80, 0x4b02, 0xd0fe, 0xe7fe]),
+            [
+                "push {r7, lr}",
+                "movs r0, #1",
+                "adds r0, r0, r1",
+                "bx lr",
+                "pop {r7, pc}",
+                "ldr r3, [pc, #8]",
+                "beq.n 0x800010c",
+                "b.n 0x800010e",
+            ]
+        );
+        assert_eq!(
+            decode(&[0x6858, 0x9101, 0xb082, 0xb108, 0xb672, 0xbf30, 0xde00]),
+            [
+                "ldr r0, [r3, #4]",
+                "str r1, [sp, #4]",
+                "sub sp, #8",
+                "cbz r0, 0x800010c",
+                "cpsid i",
+                "wfi",
+                "udf #0",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_it_block() {
+        assert_eq!(
+            decode(&[0xbf0c, 0x2001, 0x2000, 0x2002]),
+            ["ite eq", "moveq r0, #1", "movne r0, #0", "movs r0, #2"]
+        );
+    }
+
+    #[test]
+    fn test_decode32() {
+        assert_eq!(
+            decode(&[
+                0xf000, 0xf802, // bl
+                0xf240, 0x0300, // movw
+                0xf2c2, 0x0300, // movt
+                0xe92d, 0x4ff0, // push.w
+                0xe8bd, 0x8ff0, // pop.w
+                0xfb03, 0xf302, // mul
+                0xfbb0, 0xf0f1, // udiv
+                0xf8d3, 0x2004, // ldr.w
+                0xea4f, 0x0343, // lsl.w
+                0xf04f, 0x30ff, // mov.w
+            ]),
+            [
+                "bl 0x8000108",
+                "movw r3, #0",
+                "movt r3, #8192",
+                "push.w {r4, r5, r6, r7, r8, r9, r10, r11, lr}",
+                "pop.w {r4, r5, r6, r7, r8, r9, r10, r11, pc}",
+                "mul.w r3, r3, r2",
+                "udiv r0, r0, r1",
+                "ldr.w r2, [r3, #4]",
+                "lsl.w r3, r3, #1",
+                "mov.w r0, #4294967295",
+            ]
+        );
+        assert_eq!(
+            decode(&[
+                0xf3bf, 0x8f4f, // dsb
+                0xf380, 0x8808, // msr
+                0xf3ef, 0x8009, // mrs
+                0xe8df, 0xf003, // tbb
+                0xf853, 0x0c04, // ldr with negative offset
+                0xee07, 0x0a90, // vmov, not decoded
+            ]),
+            [
+                "dsb sy",
+                "msr msp, r0",
+                "mrs r0, psp",
+                "tbb [pc, r3]",
+                "ldr r0, [r3, #-4]",
+                ".inst.w 0xee070a90",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_thumb_expand_imm() {
+        assert_eq!(thumb_expand_imm(0x0FF), 0xFF);
+        assert_eq!(thumb_expand_imm(0x1AB), 0x00AB_00AB);
+        assert_eq!(thumb_expand_imm(0x3FF), 0xFFFF_FFFF);
+        assert_eq!(thumb_expand_imm(0x400), 0x8000_0000);
+    }
+
+    #[test]
+    fn test_disassemble() {
+        // main: push; ldr r0, [pc, #8]; bl helper; pop; pad; .word counter
+        // helper: bx lr
+        let code: Vec<u8> = [
+            0xb580u16, 0x4802, 0xf000, 0xf804, 0xbd80, 0x0000, 0x0000, 0x2000, 0x4770,
+        ]
+        .iter()
+        .flat_map(|hw| hw.to_le_bytes())
+        .collect();
+        let (symtab, strtab) = build_test_symtab(&[
+            ("main", 0x1, 0x10, 0x12, 1),
+            ("$t", 0x0, 0, 0x00, 1),
+            ("$d", 0xc, 0, 0x00, 1),
+            ("$t", 0x10, 0, 0x00, 1),
+            ("helper", 0x11, 0x2, 0x12, 1),
+            ("counter", 0x2000_0000, 4, 0x11, 2),
+        ]);
+        let data = build_test_elf(&[
+            (".text", 1, &code),
+            (".bss", SHT_NOBITS, &[]),
+            (".symtab", SHT_SYMTAB, &symtab),
+            (".strtab", 3, &strtab),
+        ]);
+        let mut elf = ElfFile::parse(data).unwrap();
+        elf.sections[1].flags = SHF_EXECINSTR;
+
+        let lines = disassemble(&elf, None, None).unwrap();
+        let text = disassembly_text(&lines);
+        assert!(text.contains("00000000 <main>:\n"), "{}", text);
+        assert!(text.contains("ldr\tr0, [pc, #8]\t@ 0x20000000 <counter>"));
+        assert!(text.contains("bl\t0x10 <helper>"));
+        assert!(text.contains("       c:\t20000000\t.word\t0x20000000"));
+        assert!(text.contains("00000010 <helper>:\n      10:\t4770      \tbx\tlr"));
+
+        let lines = disassemble(&elf, None, Some("helper")).unwrap();
+        assert_eq!(lines.len(), 2);
+        assert!(matches!(
+            disassemble(&elf, None, Some("missing")),
+            Err(DisasmError::FunctionNotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,373 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Project health checks for toolchains, linker scripts, include paths, and
+//! build caches.
+
+use crate::{
+    detect_makefile, load_host_test_config, profile_from_makefile, read_linker_script,
+    BuildReportStore, DetectedToolchain, SizeHistoryStore, ToolchainKind,
+};
+use axiom_core::walk::find_files;
+use axiom_core::{HealthFinding, Severity};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Tools a cross build needs alongside the compiler.
+const ARM_SIBLING_TOOLS: &[&str] = &["as", "ld", "objcopy", "objdump", "size", "ar"];
+
+/// Whether the project looks like it targets bare-metal ARM.
+fn is_arm_project(project_root: &Path) -> bool {
+    let has_linker_script = find_files(project_root, &["ld"]).is_ok_and(|f| !f.is_empty());
+    let makefile_mentions_arm = detect_makefile(project_root)
+        .and_then(|info| fs::read_to_string(info.path).ok())
+        .is_some_and(|content| {
+            content.contains("arm-none-eabi") || content.contains("-mcpu=cortex")
+        });
+    has_linker_script || makefile_mentions_arm
+}
+
+fn tool_exists(path: &Path) -> bool {
+    path.is_file() || path.with_extension("exe").is_file()
+}
+
+/// Check that the compilers the project needs are installed and complete.
+pub fn diagnose_toolchains(
+    toolchains: &[DetectedToolchain],
+    project_root: &Path,
+) -> Vec<HealthFinding> {
+    let mut findings = Vec::new();
+    let compilers: Vec<_> = toolchains
+        .iter()
+        .filter(|t| t.kind != ToolchainKind::Python)
+        .collect();
+
+    if compilers.is_empty() {
+        findings.push(
+            HealthFinding::critical("toolchain.compiler", "No C compiler was found").with_action(
+                "Install Clang, GCC, or arm-none-eabi-gcc, or set its path in settings",
+            ),
+        );
+    }
+
+    for toolchain in &compilers {
+        if !tool_exists(&toolchain.path) {
+            findings.push(
+                HealthFinding::critical(
+                    "toolchain.missing",
+                    format!("{} is no longer at its detected path", toolchain.kind),
+                )
+                .with_action("Re-run toolchain detection")
+                .with_path(&toolchain.path),
+            );
+        }
+    }
+
+    let arm = compilers.iter().find(|t| t.kind == ToolchainKind::ArmGcc);
+    match arm {
+        Some(arm) if tool_exists(&arm.path) => {
+            for tool in ARM_SIBLING_TOOLS {
+                let path = arm.sibling_tool(tool);
+                if !tool_exists(&path) {
+                    findings.push(
+                        HealthFinding::warning(
+                            "toolchain.incomplete",
+                            format!("ARM GCC installation has no {}", tool),
+                        )
+                        .with_action("Reinstall the full GNU Arm Embedded toolchain")
+                        .with_path(path),
+                    );
+                }
+            }
+        }
+        Some(_) => {}
+        None if is_arm_project(project_root) => findings.push(
+            HealthFinding::critical(
+                "toolchain.arm-gcc",
+                "Project targets ARM but arm-none-eabi-gcc was not found",
+            )
+            .with_action("Install the GNU Arm Embedded toolchain or set its path in settings"),
+        ),
+        None => {}
+    }
+
+    findings
+}
+
+/// Check that an ARM project has a linker script and that each one parses
+/// and validates.
+pub fn diagnose_linker_scripts(project_root: &Path) -> Vec<HealthFinding> {
+    let mut findings = Vec::new();
+    let scripts = find_files(project_root, &["ld"]).unwrap_or_default();
+
+    if scripts.is_empty() {
+        if is_arm_project(project_root) {
+            findings.push(
+                HealthFinding::warning("linker.missing", "ARM project has no linker script")
+                    .with_action("Generate a baseline script from the MCU database"),
+            );
+        }
+        return findings;
+    }
+
+    for path in scripts {
+        let script = match read_linker_script(&path) {
+            Ok(script) => script,
+            Err(e) => {
+                findings.push(
+                    HealthFinding::critical("linker.parse", format!("Cannot parse: {}", e))
+                        .with_action("Fix the linker script syntax")
+                        .with_path(&path),
+                );
+                continue;
+            }
+        };
+
+        if script.memory.is_empty() {
+            findings.push(
+                HealthFinding::warning("linker.memory", "Linker script has no MEMORY block")
+                    .with_path(&path),
+            );
+        }
+        for diagnostic in script.validate(&path) {
+            let finding = match diagnostic.severity {
+                Severity::Error => HealthFinding::critical("linker.validate", diagnostic.message),
+                Severity::Warning => HealthFinding::warning("linker.validate", diagnostic.message),
+                Severity::Note => HealthFinding::info("linker.validate", diagnostic.message),
+            };
+            findings.push(finding.with_path(&path));
+        }
+    }
+
+    findings
+}
+
+/// Check that include directories from the Makefile and host test
+/// configuration exist.
+pub fn diagnose_include_paths(project_root: &Path) -> Vec<HealthFinding> {
+    let mut findings = Vec::new();
+    let mut missing = |source: &str, dir: &Path| {
+        let resolved = project_root.join(dir);
+        if !resolved.is_dir() {
+            findings.push(
+                HealthFinding::warning(
+                    "includes.missing",
+                    format!("Include directory from {} does not exist", source),
+                )
+                .with_action("Create the directory or remove it from the include paths")
+                .with_path(resolved),
+            );
+        }
+    };
+
+    if let Some(info) = detect_makefile(project_root) {
+        if let Ok(content) = fs::read_to_string(&info.path) {
+            let profile = profile_from_makefile(&content, "makefile");
+            for dir in &profile.include_paths {
+                // Unexpanded references point at the environment or make internals
+                if !dir.to_string_lossy().contains('$') {
+                    missing("the Makefile", dir);
+                }
+            }
+        }
+    }
+
+    match load_host_test_config(project_root) {
+        Ok(config) => {
+            for dir in config.include_paths.iter().chain(&config.mock_dirs) {
+                missing("the host test configuration", dir);
+            }
+        }
+        Err(e) => findings.push(
+            HealthFinding::critical("includes.host-test", e.to_string())
+                .with_action("Fix .axiom/host-test.toml"),
+        ),
+    }
+
+    findings
+}
+
+/// Check project history files and build outputs for corruption or
+/// staleness.
+///
+/// `output_dir` is the build output directory relative to the project root.
+pub fn diagnose_caches(project_root: &Path, output_dir: &Path) -> Vec<HealthFinding> {
+    let mut findings = Vec::new();
+
+    let reports = BuildReportStore::for_project(project_root);
+    if let Err(e) = reports.load() {
+        findings.push(
+            HealthFinding::warning(
+                "caches.build-reports",
+                format!("Build report log is unreadable: {}", e),
+            )
+            .with_action("Delete the file to start a new log")
+            .with_path(reports.path()),
+        );
+    }
+    let sizes = SizeHistoryStore::for_project(project_root);
+    if let Err(e) = sizes.load() {
+        findings.push(
+            HealthFinding::warning(
+                "caches.size-history",
+                format!("Size history is unreadable: {}", e),
+            )
+            .with_action("Delete the file to start a new history")
+            .with_path(sizes.path()),
+        );
+    }
+
+    // Objects built before the last build configuration change
+    let config_files: Vec<PathBuf> = detect_makefile(project_root)
+        .map(|info| info.path)
+        .into_iter()
+        .chain(Some(crate::host_test_config_path(project_root)))
+        .collect();
+    let Some(changed) = config_files.iter().filter_map(|p| modified(p)).max() else {
+        return findings;
+    };
+    let output = project_root.join(output_dir);
+    let objects = if output.is_dir() {
+        find_files(&output, &["o", "obj"]).unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+    let stale = objects
+        .iter()
+        .filter(|o| modified(o).is_some_and(|t| t < changed))
+        .count();
+    if stale > 0 {
+        findings.push(
+            HealthFinding::warning(
+                "caches.stale-objects",
+                format!(
+                    "{} object file(s) predate the last build configuration change",
+                    stale
+                ),
+            )
+            .with_action("Clean and rebuild")
+            .with_path(output),
+        );
+    }
+
+    findings
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    fn checks(findings: &[HealthFinding]) -> Vec<&str> {
+        findings.iter().map(|f| f.check.as_str()).collect()
+    }
+
+    #[test]
+    fn test_arm_project_without_arm_toolchain() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("Makefile"), "CC = arm-none-eabi-gcc\n").unwrap();
+
+        let findings = diagnose_toolchains(&[], dir.path());
+        assert_eq!(
+            checks(&findings),
+            vec!["toolchain.compiler", "toolchain.arm-gcc"]
+        );
+
+        let findings = diagnose_linker_scripts(dir.path());
+        assert_eq!(checks(&findings), vec!["linker.missing"]);
+    }
+
+    #[test]
+    fn test_incomplete_arm_install() {
+        let dir = TempDir::new().unwrap();
+        let gcc = dir.path().join("arm-none-eabi-gcc");
+        fs::write(&gcc, "").unwrap();
+        for tool in ["as", "ld", "objcopy", "objdump", "ar"] {
+            fs::write(dir.path().join(format!("arm-none-eabi-{}", tool)), "").unwrap();
+        }
+        let toolchain = DetectedToolchain::new(ToolchainKind::ArmGcc, gcc, "13.2".into());
+
+        let findings = diagnose_toolchains(&[toolchain], dir.path());
+        assert_eq!(checks(&findings), vec!["toolchain.incomplete"]);
+        assert!(findings[0].message.contains("size"));
+    }
+
+    #[test]
+    fn test_linker_script_validation() {
+        let dir = TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("app.ld"),
+            "MEMORY\n{\n  FLASH (rx) : ORIGIN = 0x08000000, LENGTH = 64K\n}\n\
+             SECTIONS\n{\n  .data : { *(.data) } > RAM\n}\n",
+        )
+        .unwrap();
+
+        let findings = diagnose_linker_scripts(dir.path());
+        assert_eq!(checks(&findings), vec!["linker.validate"]);
+        assert_eq!(findings[0].priority, axiom_core::HealthPriority::Critical);
+    }
+
+    #[test]
+    fn test_missing_include_paths() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir(dir.path().join("inc")).unwrap();
+        fs::write(
+            dir.path().join("Makefile"),
+            "CFLAGS = -Iinc -Idrivers/inc -I$(SDK)/include\n",
+        )
+        .unwrap();
+        fs::create_dir(dir.path().join(".axiom")).unwrap();
+        fs::write(
+            dir.path().join(".axiom").join("host-test.toml"),
+            "mock_dirs = [\"test/mocks\"]\n",
+        )
+        .unwrap();
+
+        let findings = diagnose_include_paths(dir.path());
+        let paths: Vec<_> = findings.iter().filter_map(|f| f.path.clone()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                dir.path().join("drivers/inc"),
+                dir.path().join("test/mocks")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_corrupt_history_and_stale_objects() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir(dir.path().join(".axiom")).unwrap();
+        fs::write(
+            dir.path().join(".axiom").join("build-reports.jsonl"),
+            "{not json\n",
+        )
+        .unwrap();
+
+        let build = dir.path().join("build");
+        fs::create_dir(&build).unwrap();
+        let object = build.join("main.o");
+        fs::write(&object, "").unwrap();
+        let makefile = dir.path().join("Makefile");
+        fs::write(&makefile, "all:\n").unwrap();
+        let past = SystemTime::now() - Duration::from_secs(3600);
+        fs::File::options()
+            .write(true)
+            .open(&object)
+            .unwrap()
+            .set_modified(past)
+            .unwrap();
+
+        let findings = diagnose_caches(dir.path(), Path::new("build"));
+        assert_eq!(
+            checks(&findings),
+            vec!["caches.build-reports", "caches.stale-objects"]
+        );
+    }
+}
@@ -0,0 +1,783 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! DWARF line tables for address/source mapping.
+
+use crate::{ElfError, ElfFile, SymbolKind, EM_ARM};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+const DW_LNS_COPY: u8 = 1;
+const DW_LNS_ADVANCE_PC: u8 = 2;
+const DW_LNS_ADVANCE_LINE: u8 = 3;
+const DW_LNS_SET_FILE: u8 = 4;
+const DW_LNS_SET_COLUMN: u8 = 5;
+const DW_LNS_NEGATE_STMT: u8 = 6;
+const DW_LNS_CONST_ADD_PC: u8 = 8;
+const DW_LNS_FIXED_ADVANCE_PC: u8 = 9;
+
+const DW_LNE_END_SEQUENCE: u8 = 1;
+const DW_LNE_SET_ADDRESS: u8 = 2;
+const DW_LNE_DEFINE_FILE: u8 = 3;
+
+const DW_LNCT_PATH: u64 = 1;
+const DW_LNCT_DIRECTORY_INDEX: u64 = 2;
+
+/// DWARF parsing errors.
+#[derive(Debug, Error)]
+pub enum DwarfError {
+    #[error("ELF error: {0}")]
+    Elf(#[from] ElfError),
+
+    #[error("No {0} section (build with -g)")]
+    MissingSection(&'static str),
+
+    #[error("Truncated DWARF data at offset {0}")]
+    Truncated(usize),
+
+    #[error("Unsupported DWARF line table version {0}")]
+    UnsupportedVersion(u16),
+
+    #[error("Unsupported DWARF form 0x{0:x}")]
+    UnsupportedForm(u64),
+}
+
+/// One row of the line-number matrix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LineRow {
+    /// Instruction address.
+    pub address: u64,
+    /// Index into [`LineTable::files`].
+    pub file: usize,
+    /// Line number (1-based; 0 means no source line).
+    pub line: u32,
+    /// Column number (1-based; 0 means unknown).
+    pub column: u32,
+    /// Recommended breakpoint location.
+    pub is_stmt: bool,
+}
+
+/// A contiguous run of addresses from one line program.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LineSequence {
+    /// First address.
+    pub start: u64,
+    /// One past the last address.
+    pub end: u64,
+    /// Rows in address order.
+    pub rows: Vec<LineRow>,
+}
+
+/// A source position resolved from an address.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceLine {
+    /// Source file, as recorded by the compiler.
+    pub path: PathBuf,
+    /// Line number (1-based).
+    pub line: u32,
+    /// Column number (1-based; 0 means unknown).
+    pub column: u32,
+}
+
+/// Decoded `.debug_line` contents for a whole image.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LineTable {
+    /// Source files referenced by all units.
+    pub files: Vec<PathBuf>,
+    /// Address sequences, sorted by start address.
+    pub sequences: Vec<LineSequence>,
+}
+
+impl LineTable {
+    /// Decode the line tables of an ELF file.
+    pub fn from_elf(elf: &ElfFile) -> Result<Self, DwarfError> {
+        let section = |name: &'static str| {
+            elf.section(name)
+                .and_then(|s| elf.section_data(s))
+                .unwrap_or(&[])
+        };
+        let debug_line = elf
+            .section(".debug_line")
+            .and_then(|s| elf.section_data(s))
+            .ok_or(DwarfError::MissingSection(".debug_line"))?;
+        Self::parse(
+            debug_line,
+            section(".debug_line_str"),
+            section(".debug_str"),
+            elf.big_endian,
+        )
+    }
+
+    /// Decode raw `.debug_line` data.
+    ///
+    /// `line_str` and `strings` are the `.debug_line_str` and `.debug_str`
+    /// sections, needed for DWARF 5 file tables.
+    pub fn parse(
+        debug_line: &[u8],
+        line_str: &[u8],
+        strings: &[u8],
+        big_endian: bool,
+    ) -> Result<Self, DwarfError> {
+        let mut table = LineTable::default();
+        let mut cursor = Cursor {
+            data: debug_line,
+            pos: 0,
+            big_endian,
+        };
+        while cursor.pos < debug_line.len() {
+            parse_unit(&mut cursor, line_str, strings, &mut table)?;
+        }
+        table
+            .sequences
+            .retain(|s| !s.rows.is_empty() && s.start != 0);
+        table.sequences.sort_by_key(|s| s.start);
+        Ok(table)
+    }
+
+    /// Source line for an address.
+    pub fn lookup(&self, address: u64) -> Option<SourceLine> {
+        let idx = self.sequences.partition_point(|s| s.start <= address);
+        let sequence = self.sequences[..idx]
+            .iter()
+            .rev()
+            .find(|s| address < s.end)?;
+        let row_idx = sequence.rows.partition_point(|r| r.address <= address);
+        let row = sequence.rows.get(row_idx.checked_sub(1)?)?;
+        if row.line == 0 {
+            return None;
+        }
+        Some(SourceLine {
+            path: self.files.get(row.file)?.clone(),
+            line: row.line,
+            column: row.column,
+        })
+    }
+
+    /// Breakpoint addresses for a source line.
+    ///
+    /// `path` matches any recorded file with the same trailing components,
+    /// so both "main.c" and "src/main.c" find "/work/fw/src/main.c".
+    /// Returns the first statement address of each sequence with that line.
+    pub fn addresses_for(&self, path: &Path, line: u32) -> Vec<u64> {
+        let files: Vec<usize> = self
+            .files
+            .iter()
+            .enumerate()
+            .filter(|(_, f)| f.ends_with(path))
+            .map(|(i, _)| i)
+            .collect();
+
+        let mut addresses: Vec<u64> = self
+            .sequences
+            .iter()
+            .filter_map(|s| {
+                s.rows
+                    .iter()
+                    .find(|r| r.is_stmt && r.line == line && files.contains(&r.file))
+                    .map(|r| r.address)
+            })
+            .collect();
+        addresses.sort_unstable();
+        addresses.dedup();
+        addresses
+    }
+}
+
+/// A function symbol with its address range.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FunctionSymbol {
+    /// Symbol name.
+    pub name: String,
+    /// Start address (Thumb bit cleared).
+    pub address: u64,
+    /// Size in bytes.
+    pub size: u64,
+}
+
+/// Address/source mapping for a built image.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DebugInfo {
+    /// Decoded line tables.
+    pub lines: LineTable,
+    /// Function symbols sorted by address.
+    pub functions: Vec<FunctionSymbol>,
+}
+
+impl DebugInfo {
+    /// Read debug information from an ELF file on disk.
+    pub fn read(path: &Path) -> Result<Self, DwarfError> {
+        Self::from_elf(&ElfFile::read(path)?)
+    }
+
+    /// Build from a parsed ELF file.
+    pub fn from_elf(elf: &ElfFile) -> Result<Self, DwarfError> {
+        let thumb_mask = if elf.machine == EM_ARM { !1 } else { !0 };
+        let mut functions: Vec<FunctionSymbol> = elf
+            .symbols
+            .iter()
+            .filter(|s| s.kind == SymbolKind::Func && !s.is_undefined())
+            .map(|s| FunctionSymbol {
+                name: s.name.clone(),
+                address: s.value & thumb_mask,
+                size: s.size,
+            })
+            .collect();
+        functions.sort_by(|a, b| a.address.cmp(&b.address).then(a.name.cmp(&b.name)));
+        functions.dedup_by(|a, b| a.address == b.address && a.name == b.name);
+
+        Ok(Self {
+            lines: LineTable::from_elf(elf)?,
+            functions,
+        })
+    }
+
+    /// Source line for an address.
+    pub fn source_for(&self, address: u64) -> Option<SourceLine> {
+        self.lines.lookup(address)
+    }
+
+    /// Breakpoint addresses for a source line.
+    pub fn addresses_for(&self, path: &Path, line: u32) -> Vec<u64> {
+        self.lines.addresses_for(path, line)
+    }
+
+    /// Function containing an address.
+    pub fn function_at(&self, address: u64) -> Option<&FunctionSymbol> {
+        let idx = self.functions.partition_point(|f| f.address <= address);
+        self.functions[..idx]
+            .iter()
+            .rev()
+            .find(|f| address < f.address + f.size.max(1))
+    }
+
+    /// Address of a function by name.
+    pub fn function(&self, name: &str) -> Option<&FunctionSymbol> {
+        self.functions.iter().find(|f| f.name == name)
+    }
+}
+
+/// Bounds-checked little/big-endian reader over DWARF data.
+struct Cursor<'a> {
+    data: &'a [u8],
+    pos: usize,
+    big_endian: bool,
+}
+
+impl<'a> Cursor<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], DwarfError> {
+        let bytes = self
+            .pos
+            .checked_add(len)
+            .and_then(|end| self.data.get(self.pos..end))
+            .ok_or(DwarfError::Truncated(self.pos))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, DwarfError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn uint(&mut self, len: usize) -> Result<u64, DwarfError> {
+        let bytes = self.bytes(len)?;
+        let fold = |acc: u64, &b: &u8| (acc << 8) | u64::from(b);
+        Ok(if self.big_endian {
+            bytes.iter().fold(0, fold)
+        } else {
+            bytes.iter().rev().fold(0, fold)
+        })
+    }
+
+    fn uleb(&mut self) -> Result<u64, DwarfError> {
+        let mut result = 0u64;
+        let mut shift = 0;
+        loop {
+            let byte = self.u8()?;
+            if shift < 64 {
+                result |= u64::from(byte & 0x7f) << shift;
+            }
+            shift += 7;
+            if byte & 0x80 == 0 {
+                return Ok(result);
+            }
+        }
+    }
+
+    fn sleb(&mut self) -> Result<i64, DwarfError> {
+        let mut result = 0i64;
+        let mut shift = 0;
+        loop {
+            let byte = self.u8()?;
+            if shift < 64 {
+                result |= i64::from(byte & 0x7f) << shift;
+            }
+            shift += 7;
+            if byte & 0x80 == 0 {
+                if shift < 64 && byte & 0x40 != 0 {
+                    result |= -1 << shift;
+                }
+                return Ok(result);
+            }
+        }
+    }
+
+    fn cstr(&mut self) -> Result<&'a str, DwarfError> {
+        let rest = self.data.get(self.pos..).unwrap_or(&[]);
+        let len = rest
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or(DwarfError::Truncated(self.pos))?;
+        let s = std::str::from_utf8(&rest[..len]).unwrap_or("");
+        self.pos += len + 1;
+        Ok(s)
+    }
+}
+
+/// Attribute value read from a DWARF 5 entry format.
+enum FormValue<'a> {
+    Str(&'a str),
+    Uint(u64),
+    Skipped,
+}
+
+fn read_form<'a>(
+    c: &mut Cursor<'a>,
+    form: u64,
+    offset_size: usize,
+    line_str: &'a [u8],
+    strings: &'a [u8],
+) -> Result<FormValue<'a>, DwarfError> {
+    Ok(match form {
+        0x08 => FormValue::Str(c.cstr()?),
+        0x1f => FormValue::Str(crate::elf::read_cstr(
+            line_str,
+            c.uint(offset_size)? as usize,
+        )),
+        0x0e => FormValue::Str(crate::elf::read_cstr(
+            strings,
+            c.uint(offset_size)? as usize,
+        )),
+        0x0b => FormValue::Uint(c.uint(1)?),
+        0x05 => FormValue::Uint(c.uint(2)?),
+        0x06 => FormValue::Uint(c.uint(4)?),
+        0x07 => FormValue::Uint(c.uint(8)?),
+        0x0f => FormValue::Uint(c.uleb()?),
+        0x0d => FormValue::Uint(c.sleb()? as u64),
+        0x1e => {
+            c.bytes(16)?;
+            FormValue::Skipped
+        }
+        0x09 => {
+            let len = c.uleb()? as usize;
+            c.bytes(len)?;
+            FormValue::Skipped
+        }
+        0x0a => {
+            let len = c.u8()? as usize;
+            c.bytes(len)?;
+            FormValue::Skipped
+        }
+        other => return Err(DwarfError::UnsupportedForm(other)),
+    })
+}
+
+/// Read a DWARF 5 directory or file name table.
+fn read_entry_table<'a>(
+    c: &mut Cursor<'a>,
+    offset_size: usize,
+    line_str: &'a [u8],
+    strings: &'a [u8],
+) -> Result<Vec<(&'a str, u64)>, DwarfError> {
+    let format_count = c.u8()?;
+    let mut format = Vec::with_capacity(format_count as usize);
+    for _ in 0..format_count {
+        format.push((c.uleb()?, c.uleb()?));
+    }
+
+    let count = c.uleb()?;
+    let mut entries = Vec::new();
+    for _ in 0..count {
+        let mut path = "";
+        let mut dir = 0;
+        for &(content, form) in &format {
+            match (content, read_form(c, form, offset_size, line_str, strings)?) {
+                (DW_LNCT_PATH, FormValue::Str(s)) => path = s,
+                (DW_LNCT_DIRECTORY_INDEX, FormValue::Uint(d)) => dir = d,
+                _ => {}
+            }
+        }
+        entries.push((path, dir));
+    }
+    Ok(entries)
+}
+
+fn join_path(dir: Option<&PathBuf>, name: &str) -> PathBuf {
+    match dir {
+        Some(dir) if !Path::new(name).is_absolute() => dir.join(name),
+        _ => PathBuf::from(name),
+    }
+}
+
+/// Index of a file in the table, adding it if new.
+fn intern_file(table: &mut LineTable, path: PathBuf) -> usize {
+    match table.files.iter().position(|f| *f == path) {
+        Some(idx) => idx,
+        None => {
+            table.files.push(path);
+            table.files.len() - 1
+        }
+    }
+}
+
+/// Decode one line-number program unit, appending to `table`.
+fn parse_unit<'a>(
+    c: &mut Cursor<'a>,
+    line_str: &'a [u8],
+    strings: &'a [u8],
+    table: &mut LineTable,
+) -> Result<(), DwarfError> {
+    let (unit_length, offset_size) = match c.uint(4)? {
+        0xffff_ffff => (c.uint(8)? as usize, 8),
+        len => (len as usize, 4),
+    };
+    let unit_end = c
+        .pos
+        .checked_add(unit_length)
+        .filter(|&end| end <= c.data.len())
+        .ok_or(DwarfError::Truncated(c.pos))?;
+
+    let version = c.uint(2)? as u16;
+    if !(2..=5).contains(&version) {
+        return Err(DwarfError::UnsupportedVersion(version));
+    }
+    let mut address_size = None;
+    if version >= 5 {
+        address_size = Some(c.u8()? as usize);
+        c.u8()?;
+    }
+    let header_length = c.uint(offset_size)? as usize;
+    let program_start = c.pos + header_length;
+
+    let min_inst_length = u64::from(c.u8()?);
+    if version >= 4 {
+        c.u8()?;
+    }
+    let default_is_stmt = c.u8()? != 0;
+    let line_base = i64::from(c.u8()? as i8);
+    let line_range = c.u8()?.max(1);
+    let opcode_base = c.u8()?;
+    let standard_lengths = c.bytes(opcode_base.saturating_sub(1) as usize)?.to_vec();
+
+    // Unit-local file numbers, mapped to indices in the table-wide list
+    let mut files: Vec<usize> = Vec::new();
+    let mut dirs: Vec<PathBuf> = Vec::new();
+    if version >= 5 {
+        for (dir, _) in read_entry_table(c, offset_size, line_str, strings)? {
+            dirs.push(PathBuf::from(dir));
+        }
+        for (name, dir) in read_entry_table(c, offset_size, line_str, strings)? {
+            files.push(intern_file(table, join_path(dirs.get(dir as usize), name)));
+        }
+    } else {
+        // Directory 0 is the compilation directory; file indices start at 1
+        dirs.push(PathBuf::new());
+        loop {
+            let dir = c.cstr()?;
+            if dir.is_empty() {
+                break;
+            }
+            dirs.push(PathBuf::from(dir));
+        }
+        files.push(intern_file(table, PathBuf::new()));
+        loop {
+            let name = c.cstr()?;
+            if name.is_empty() {
+                break;
+            }
+            let dir = c.uleb()?;
+            c.uleb()?;
+            c.uleb()?;
+            files.push(intern_file(table, join_path(dirs.get(dir as usize), name)));
+        }
+    }
+
+    c.pos = program_start;
+
+    let mut address = 0u64;
+    let mut file = 1u64;
+    let mut line = 1i64;
+    let mut column = 0u64;
+    let mut is_stmt = default_is_stmt;
+    let mut sequence = LineSequence {
+        start: 0,
+        end: 0,
+        rows: Vec::new(),
+    };
+
+    while c.pos < unit_end {
+        let mut emit = false;
+        let opcode = c.u8()?;
+
+        if opcode >= opcode_base {
+            let adjusted = u64::from(opcode - opcode_base);
+            address += (adjusted / u64::from(line_range)) * min_inst_length;
+            line += line_base + (adjusted % u64::from(line_range)) as i64;
+            emit = true;
+        } else if opcode == 0 {
+            let len = c.uleb()? as usize;
+            let end = c.pos + len;
+            match c.u8()? {
+                DW_LNE_END_SEQUENCE => {
+                    sequence.end = address;
+                    if let Some(first) = sequence.rows.first() {
+                        sequence.start = first.address;
+                    }
+                    table.sequences.push(std::mem::replace(
+                        &mut sequence,
+                        LineSequence {
+                            start: 0,
+                            end: 0,
+                            rows: Vec::new(),
+                        },
+                    ));
+                    address = 0;
+                    file = 1;
+                    line = 1;
+                    column = 0;
+                    is_stmt = default_is_stmt;
+                }
+                DW_LNE_SET_ADDRESS => {
+                    let size = address_size.unwrap_or(len - 1);
+                    address = c.uint(size)?;
+                }
+                DW_LNE_DEFINE_FILE => {
+                    let name = c.cstr()?;
+                    let dir = c.uleb()?;
+                    files.push(intern_file(table, join_path(dirs.get(dir as usize), name)));
+                }
+                _ => {}
+            }
+            c.pos = end;
+        } else {
+            match opcode {
+                DW_LNS_COPY => emit = true,
+                DW_LNS_ADVANCE_PC => address += c.uleb()? * min_inst_length,
+                DW_LNS_ADVANCE_LINE => line += c.sleb()?,
+                DW_LNS_SET_FILE => file = c.uleb()?,
+                DW_LNS_SET_COLUMN => column = c.uleb()?,
+                DW_LNS_NEGATE_STMT => is_stmt = !is_stmt,
+                DW_LNS_CONST_ADD_PC => {
+                    let adjusted = u64::from(255 - opcode_base);
+                    address += (adjusted / u64::from(line_range)) * min_inst_length;
+                }
+                DW_LNS_FIXED_ADVANCE_PC => address += c.uint(2)?,
+                _ => {
+                    for _ in 0..standard_lengths[opcode as usize - 1] {
+                        c.uleb()?;
+                    }
+                }
+            }
+        }
+
+        if emit {
+            sequence.rows.push(LineRow {
+                address,
+                file: files.get(file as usize).copied().unwrap_or(0),
+                line: line.clamp(0, i64::from(u32::MAX)) as u32,
+                column: column.min(u64::from(u32::MAX)) as u32,
+                is_stmt,
+            });
+        }
+    }
+
+    c.pos = unit_end;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Assemble a DWARF 4 line program for "src/main.c".
+    fn dwarf4_unit() -> Vec<u8> {
+        let mut header = vec![
+            1,    // minimum_instruction_length
+            1,    // maximum_operations_per_instruction
+            1,    // default_is_stmt
+            0xfb, // line_base = -5
+            14,   // line_range
+            13,   // opcode_base
+            0, 1, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1,
+        ];
+        header.extend_from_slice(b"src\0\0");
+        header.extend_from_slice(b"main.c\0\x01\0\0");
+        header.extend_from_slice(b"uart.h\0\x01\0\0\0");
+
+        let program = [
+            // set_address 0x08000100
+            0x00,
+            0x05,
+            0x02,
+            0x00,
+            0x01,
+            0x00,
+            0x08,
+            // advance_line +9 -> 10, copy
+            0x03,
+            0x09,
+            0x01,
+            // special: address += 4, line += 1 -> 11
+            13 + 4 * 14 + 6,
+            // set_column 5, special: address += 2, line += 2 -> 13
+            0x05,
+            0x05,
+            13 + 2 * 14 + 7,
+            // set_file 2, negate_stmt, advance_line -12 -> 1, copy
+            0x04,
+            0x02,
+            0x06,
+            0x03,
+            0x74,
+            0x01,
+            // advance_pc 8, end_sequence
+            0x02,
+            0x08,
+            0x00,
+            0x01,
+            0x01,
+        ];
+
+        let mut unit = Vec::new();
+        unit.extend_from_slice(&4u16.to_le_bytes());
+        unit.extend_from_slice(&(header.len() as u32).to_le_bytes());
+        unit.extend(header);
+        unit.extend_from_slice(&program);
+
+        let mut data = (unit.len() as u32).to_le_bytes().to_vec();
+        data.extend(unit);
+        data
+    }
+
+    #[test]
+    fn test_parse_dwarf4() {
+        let table = LineTable::parse(&dwarf4_unit(), &[], &[], false).unwrap();
+        assert_eq!(table.sequences.len(), 1);
+
+        let seq = &table.sequences[0];
+        assert_eq!(seq.start, 0x0800_0100);
+        assert_eq!(seq.end, 0x0800_010e);
+        let lines: Vec<_> = seq.rows.iter().map(|r| (r.address, r.line)).collect();
+        assert_eq!(
+            lines,
+            vec![
+                (0x0800_0100, 10),
+                (0x0800_0104, 11),
+                (0x0800_0106, 13),
+                (0x0800_0106, 1)
+            ]
+        );
+        assert!(!seq.rows[3].is_stmt);
+        assert_eq!(table.files[seq.rows[0].file], PathBuf::from("src/main.c"));
+        assert_eq!(table.files[seq.rows[3].file], PathBuf::from("src/uart.h"));
+    }
+
+    #[test]
+    fn test_lookup() {
+        let table = LineTable::parse(&dwarf4_unit(), &[], &[], false).unwrap();
+
+        let line = table.lookup(0x0800_0105).unwrap();
+        assert_eq!(line.path, PathBuf::from("src/main.c"));
+        assert_eq!(line.line, 11);
+
+        let line = table.lookup(0x0800_0106).unwrap();
+        assert_eq!(line.line, 1);
+        assert_eq!(line.path, PathBuf::from("src/uart.h"));
+
+        assert!(table.lookup(0x0800_010e).is_none());
+        assert!(table.lookup(0x0800_00ff).is_none());
+    }
+
+    #[test]
+    fn test_addresses_for_line() {
+        let table = LineTable::parse(&dwarf4_unit(), &[], &[], false).unwrap();
+        assert_eq!(
+            table.addresses_for(Path::new("main.c"), 13),
+            vec![0x0800_0106]
+        );
+        assert_eq!(
+            table.addresses_for(Path::new("src/main.c"), 10),
+            vec![0x0800_0100]
+        );
+        assert!(table.addresses_for(Path::new("other.c"), 10).is_empty());
+        // Non-statement rows are not breakpoint candidates
+        assert!(table.addresses_for(Path::new("uart.h"), 1).is_empty());
+    }
+
+    #[test]
+    fn test_parse_dwarf5_file_table() {
+        let line_str = b"/work/fw\0src\0main.c\0";
+        let mut header = vec![1, 1, 1, 0xfb, 14, 13, 0, 1, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1];
+        // Directories: path as line_strp
+        header.extend_from_slice(&[1, 0x01, 0x1f, 2]);
+        header.extend_from_slice(&0u32.to_le_bytes());
+        header.extend_from_slice(&9u32.to_le_bytes());
+        // Files: path as line_strp, directory index as udata
+        header.extend_from_slice(&[2, 0x01, 0x1f, 0x02, 0x0b, 1]);
+        header.extend_from_slice(&13u32.to_le_bytes());
+        header.push(1);
+
+        let program = [
+            0x00, 0x05, 0x02, 0x00, 0x02, 0x00, 0x08, // set_address 0x08000200
+            0x03, 0x04, 0x01, // line 5, copy
+            0x02, 0x04, 0x00, 0x01, 0x01, // advance 4, end_sequence
+        ];
+
+        let mut unit = Vec::new();
+        unit.extend_from_slice(&5u16.to_le_bytes());
+        unit.extend_from_slice(&[4, 0]);
+        unit.extend_from_slice(&(header.len() as u32).to_le_bytes());
+        unit.extend(header);
+        unit.extend_from_slice(&program);
+        let mut data = (unit.len() as u32).to_le_bytes().to_vec();
+        data.extend(unit);
+
+        let table = LineTable::parse(&data, line_str, &[], false).unwrap();
+        let line = table.lookup(0x0800_0202).unwrap();
+        assert_eq!(line.path, PathBuf::from("src/main.c"));
+        assert_eq!(line.line, 5);
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        let mut data = 2u32.to_le_bytes().to_vec();
+        data.extend_from_slice(&9u16.to_le_bytes());
+        assert!(matches!(
+            LineTable::parse(&data, &[], &[], false),
+            Err(DwarfError::UnsupportedVersion(9))
+        ));
+    }
+
+    #[test]
+    fn test_function_at() {
+        let info = DebugInfo {
+            lines: LineTable::default(),
+            functions: vec![
+                FunctionSymbol {
+                    name: "main".to_string(),
+                    address: 0x100,
+                    size: 0x40,
+                },
+                FunctionSymbol {
+                    name: "uart_init".to_string(),
+                    address: 0x140,
+                    size: 0x20,
+                },
+            ],
+        };
+        assert_eq!(info.function_at(0x13f).unwrap().name, "main");
+        assert_eq!(info.function_at(0x140).unwrap().name, "uart_init");
+        assert!(info.function_at(0x160).is_none());
+        assert_eq!(info.function("uart_init").unwrap().address, 0x140);
+    }
+}
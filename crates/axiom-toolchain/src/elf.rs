@@ -0,0 +1,717 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Minimal ELF reader.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use thiserror::Error;
+
+/// Section type for sections that occupy no file space (.bss).
+pub const SHT_NOBITS: u32 = 8;
+
+/// Section type for the static symbol table.
+pub const SHT_SYMTAB: u32 = 2;
+
+/// Section type for the dynamic symbol table.
+pub const SHT_DYNSYM: u32 = 11;
+
+/// Section flag for writable sections.
+pub const SHF_WRITE: u64 = 0x1;
+
+/// Section flag for sections occupying memory at run time.
+pub const SHF_ALLOC: u64 = 0x2;
+
+/// Section flag for executable sections.
+pub const SHF_EXECINSTR: u64 = 0x4;
+
+/// Program header type for loadable segments.
+pub const PT_LOAD: u32 = 1;
+
+/// Section type for ARM build attributes.
+pub const SHT_ARM_ATTRIBUTES: u32 = 0x7000_0003;
+
+/// ELF machine number for 32-bit ARM.
+pub const EM_ARM: u16 = 40;
+
+/// ELF parsing errors.
+#[derive(Debug, Error)]
+pub enum ElfError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Not an ELF file")]
+    BadMagic,
+
+    #[error("Unsupported ELF class: {0}")]
+    UnsupportedClass(u8),
+
+    #[error("Truncated ELF data at offset {0}")]
+    Truncated(usize),
+}
+
+/// ELF file class.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ElfClass {
+    /// 32-bit objects.
+    Elf32,
+    /// 64-bit objects.
+    Elf64,
+}
+
+/// A section header.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ElfSection {
+    /// Section name.
+    pub name: String,
+    /// Section type (SHT_*).
+    pub section_type: u32,
+    /// Section flags (SHF_*).
+    pub flags: u64,
+    /// Virtual address.
+    pub addr: u64,
+    /// File offset.
+    pub offset: u64,
+    /// Size in bytes.
+    pub size: u64,
+    /// Linked section index (e.g. the string table of a symbol table).
+    pub link: u32,
+    /// Extra section information.
+    pub info: u32,
+    /// Required alignment.
+    pub addralign: u64,
+    /// Entry size for table sections.
+    pub entsize: u64,
+}
+
+/// A program header (segment).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ElfSegment {
+    /// Segment type (PT_*).
+    pub segment_type: u32,
+    /// Segment flags (PF_X = 1, PF_W = 2, PF_R = 4).
+    pub flags: u32,
+    /// File offset.
+    pub offset: u64,
+    /// Virtual (run-time) address.
+    pub vaddr: u64,
+    /// Physical (load) address.
+    pub paddr: u64,
+    /// Bytes in the file.
+    pub filesz: u64,
+    /// Bytes in memory; the excess over `filesz` is zero-filled.
+    pub memsz: u64,
+    /// Alignment.
+    pub align: u64,
+}
+
+impl ElfSegment {
+    /// Whether the segment is loaded into target memory.
+    pub fn is_load(&self) -> bool {
+        self.segment_type == PT_LOAD
+    }
+}
+
+/// Symbol binding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SymbolBinding {
+    Local,
+    Global,
+    Weak,
+    Other(u8),
+}
+
+impl From<u8> for SymbolBinding {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => Self::Local,
+            1 => Self::Global,
+            2 => Self::Weak,
+            other => Self::Other(other),
+        }
+    }
+}
+
+/// Symbol type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SymbolKind {
+    NoType,
+    Object,
+    Func,
+    Section,
+    File,
+    Other(u8),
+}
+
+impl From<u8> for SymbolKind {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => Self::NoType,
+            1 => Self::Object,
+            2 => Self::Func,
+            3 => Self::Section,
+            4 => Self::File,
+            other => Self::Other(other),
+        }
+    }
+}
+
+/// A symbol table entry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ElfSymbol {
+    /// Symbol name.
+    pub name: String,
+    /// Value (usually an address; bit 0 set for Thumb functions).
+    pub value: u64,
+    /// Size in bytes.
+    pub size: u64,
+    /// Binding.
+    pub binding: SymbolBinding,
+    /// Type.
+    pub kind: SymbolKind,
+    /// Index of the defining section; 0 if undefined.
+    pub section_index: u16,
+}
+
+impl ElfSymbol {
+    /// Whether the symbol is referenced but not defined here.
+    pub fn is_undefined(&self) -> bool {
+        self.section_index == 0
+    }
+}
+
+/// A parsed ELF file.
+#[derive(Debug, Clone, Serialize)]
+pub struct ElfFile {
+    /// File class.
+    pub class: ElfClass,
+    /// Whether the file is big-endian.
+    pub big_endian: bool,
+    /// Object file type (ET_*).
+    pub file_type: u16,
+    /// Target machine (EM_*).
+    pub machine: u16,
+    /// Processor-specific flags.
+    pub flags: u32,
+    /// Entry point address.
+    pub entry: u64,
+    /// Section headers.
+    pub sections: Vec<ElfSection>,
+    /// Program headers.
+    pub segments: Vec<ElfSegment>,
+    /// Symbols from .symtab, or .dynsym if there is no static table.
+    pub symbols: Vec<ElfSymbol>,
+    #[serde(skip)]
+    data: Vec<u8>,
+}
+
+/// Endian- and bounds-aware byte reader.
+struct Reader<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+impl Reader<'_> {
+    fn bytes(&self, offset: usize, len: usize) -> Result<&[u8], ElfError> {
+        offset
+            .checked_add(len)
+            .and_then(|end| self.data.get(offset..end))
+            .ok_or(ElfError::Truncated(offset))
+    }
+
+    fn u16(&self, offset: usize) -> Result<u16, ElfError> {
+        let b: [u8; 2] = self.bytes(offset, 2)?.try_into().unwrap();
+        Ok(if self.big_endian {
+            u16::from_be_bytes(b)
+        } else {
+            u16::from_le_bytes(b)
+        })
+    }
+
+    fn u32(&self, offset: usize) -> Result<u32, ElfError> {
+        let b: [u8; 4] = self.bytes(offset, 4)?.try_into().unwrap();
+        Ok(if self.big_endian {
+            u32::from_be_bytes(b)
+        } else {
+            u32::from_le_bytes(b)
+        })
+    }
+
+    fn u64(&self, offset: usize) -> Result<u64, ElfError> {
+        let b: [u8; 8] = self.bytes(offset, 8)?.try_into().unwrap();
+        Ok(if self.big_endian {
+            u64::from_be_bytes(b)
+        } else {
+            u64::from_le_bytes(b)
+        })
+    }
+
+    /// Offset of entry `index` in a table of `entsize`-byte entries at
+    /// `table`, checked so that `len` bytes can be read there.
+    fn entry(
+        &self,
+        table: u64,
+        index: usize,
+        entsize: usize,
+        len: usize,
+    ) -> Result<usize, ElfError> {
+        let base = index
+            .checked_mul(entsize)
+            .and_then(|offset| offset.checked_add(table as usize))
+            .ok_or(ElfError::Truncated(table as usize))?;
+        self.bytes(base, len)?;
+        Ok(base)
+    }
+
+    /// Read an address-sized word.
+    fn word(&self, offset: usize, class: ElfClass) -> Result<u64, ElfError> {
+        match class {
+            ElfClass::Elf32 => self.u32(offset).map(u64::from),
+            ElfClass::Elf64 => self.u64(offset),
+        }
+    }
+}
+
+impl ElfFile {
+    /// Read and parse an ELF file from disk.
+    pub fn read(path: &Path) -> Result<Self, ElfError> {
+        Self::parse(std::fs::read(path)?)
+    }
+
+    /// Parse ELF data.
+    pub fn parse(data: Vec<u8>) -> Result<Self, ElfError> {
+        if data.len() < 16 || &data[..4] != b"\x7fELF" {
+            return Err(ElfError::BadMagic);
+        }
+        let class = match data[4] {
+            1 => ElfClass::Elf32,
+            2 => ElfClass::Elf64,
+            other => return Err(ElfError::UnsupportedClass(other)),
+        };
+        let r = Reader {
+            data: &data,
+            big_endian: data[5] == 2,
+        };
+
+        let file_type = r.u16(16)?;
+        let machine = r.u16(18)?;
+        let entry = r.word(24, class)?;
+        let (phoff, shoff, flags, phentsize, phnum, shentsize, shnum, shstrndx) = match class {
+            ElfClass::Elf32 => (
+                r.word(28, class)?,
+                r.word(32, class)?,
+                r.u32(36)?,
+                r.u16(42)?,
+                r.u16(44)?,
+                r.u16(46)?,
+                r.u16(48)?,
+                r.u16(50)?,
+            ),
+            ElfClass::Elf64 => (
+                r.word(32, class)?,
+                r.word(40, class)?,
+                r.u32(48)?,
+                r.u16(54)?,
+                r.u16(56)?,
+                r.u16(58)?,
+                r.u16(60)?,
+                r.u16(62)?,
+            ),
+        };
+
+        let mut sections = Vec::with_capacity(shnum as usize);
+        let mut name_offsets = Vec::with_capacity(shnum as usize);
+        for i in 0..shnum as usize {
+            let len = match class {
+                ElfClass::Elf32 => 40,
+                ElfClass::Elf64 => 64,
+            };
+            let base = r.entry(shoff, i, shentsize as usize, len)?;
+            let w = |field32: usize, field64: usize| match class {
+                ElfClass::Elf32 => r.word(base + field32, class),
+                ElfClass::Elf64 => r.word(base + field64, class),
+            };
+            let (link, info) = match class {
+                ElfClass::Elf32 => (r.u32(base + 24)?, r.u32(base + 28)?),
+                ElfClass::Elf64 => (r.u32(base + 40)?, r.u32(base + 44)?),
+            };
+            name_offsets.push(r.u32(base)? as usize);
+            sections.push(ElfSection {
+                name: String::new(),
+                section_type: r.u32(base + 4)?,
+                flags: w(8, 8)?,
+                addr: w(12, 16)?,
+                offset: w(16, 24)?,
+                size: w(20, 32)?,
+                link,
+                info,
+                addralign: w(32, 48)?,
+                entsize: w(36, 56)?,
+            });
+        }
+
+        let names = sections
+            .get(shstrndx as usize)
+            .map(|s| r.bytes(s.offset as usize, s.size as usize))
+            .transpose()?
+            .unwrap_or(&[]);
+        for (section, offset) in sections.iter_mut().zip(name_offsets) {
+            section.name = read_cstr(names, offset).to_string();
+        }
+
+        let mut segments = Vec::with_capacity(phnum as usize);
+        for i in 0..phnum as usize {
+            let len = match class {
+                ElfClass::Elf32 => 32,
+                ElfClass::Elf64 => 56,
+            };
+            let base = r.entry(phoff, i, phentsize as usize, len)?;
+            segments.push(match class {
+                ElfClass::Elf32 => ElfSegment {
+                    segment_type: r.u32(base)?,
+                    offset: r.word(base + 4, class)?,
+                    vaddr: r.word(base + 8, class)?,
+                    paddr: r.word(base + 12, class)?,
+                    filesz: r.word(base + 16, class)?,
+                    memsz: r.word(base + 20, class)?,
+                    flags: r.u32(base + 24)?,
+                    align: r.word(base + 28, class)?,
+                },
+                ElfClass::Elf64 => ElfSegment {
+                    segment_type: r.u32(base)?,
+                    flags: r.u32(base + 4)?,
+                    offset: r.word(base + 8, class)?,
+                    vaddr: r.word(base + 16, class)?,
+                    paddr: r.word(base + 24, class)?,
+                    filesz: r.word(base + 32, class)?,
+                    memsz: r.word(base + 40, class)?,
+                    align: r.word(base + 48, class)?,
+                },
+            });
+        }
+
+        let symtab = sections
+            .iter()
+            .find(|s| s.section_type == SHT_SYMTAB)
+            .or_else(|| sections.iter().find(|s| s.section_type == SHT_DYNSYM));
+        let symbols = match symtab {
+            Some(symtab) => read_symbols(&r, class, symtab, &sections)?,
+            None => Vec::new(),
+        };
+
+        let big_endian = r.big_endian;
+        Ok(Self {
+            class,
+            big_endian,
+            file_type,
+            machine,
+            flags,
+            entry,
+            sections,
+            segments,
+            symbols,
+            data,
+        })
+    }
+
+    /// Find a section by name.
+    pub fn section(&self, name: &str) -> Option<&ElfSection> {
+        self.sections.iter().find(|s| s.name == name)
+    }
+
+    /// Get the contents of a section.
+    pub fn section_data(&self, section: &ElfSection) -> Option<&[u8]> {
+        if section.section_type == SHT_NOBITS {
+            return Some(&[]);
+        }
+        let start = usize::try_from(section.offset).ok()?;
+        let end = start.checked_add(usize::try_from(section.size).ok()?)?;
+        self.data.get(start..end)
+    }
+
+    /// Load (physical) address of a section.
+    ///
+    /// Differs from the run-time address for initialized data copied from
+    /// flash at startup; derived from the containing PT_LOAD segment.
+    pub fn load_address(&self, section: &ElfSection) -> u64 {
+        self.segments
+            .iter()
+            .filter(|seg| seg.is_load() && section.section_type != SHT_NOBITS)
+            .find(|seg| {
+                section.offset >= seg.offset
+                    && section.offset + section.size <= seg.offset + seg.filesz
+                    && section.addr >= seg.vaddr
+            })
+            .map_or(section.addr, |seg| section.addr - seg.vaddr + seg.paddr)
+    }
+
+    /// Find a defined symbol by name.
+    pub fn symbol(&self, name: &str) -> Option<&ElfSymbol> {
+        self.symbols
+            .iter()
+            .find(|s| s.name == name && !s.is_undefined())
+    }
+
+    /// Section a symbol is defined in.
+    pub fn symbol_section(&self, symbol: &ElfSymbol) -> Option<&ElfSection> {
+        if symbol.is_undefined() {
+            return None;
+        }
+        self.sections.get(symbol.section_index as usize)
+    }
+}
+
+/// Read the entries of a symbol table section, skipping the null symbol.
+fn read_symbols(
+    r: &Reader<'_>,
+    class: ElfClass,
+    symtab: &ElfSection,
+    sections: &[ElfSection],
+) -> Result<Vec<ElfSymbol>, ElfError> {
+    let strtab = sections
+        .get(symtab.link as usize)
+        .map(|s| r.bytes(s.offset as usize, s.size as usize))
+        .transpose()?
+        .unwrap_or(&[]);
+    let len = match class {
+        ElfClass::Elf32 => 16,
+        ElfClass::Elf64 => 24,
+    };
+    let entsize = match symtab.entsize {
+        0 => len,
+        size => size as usize,
+    };
+
+    // Bound the reservation by what the file actually holds
+    r.bytes(symtab.offset as usize, symtab.size as usize)?;
+    let count = symtab.size as usize / entsize;
+    let mut symbols = Vec::with_capacity(count.saturating_sub(1));
+    for i in 1..count {
+        let base = r.entry(symtab.offset, i, entsize, len)?;
+        let (name, value, size, info, shndx) = match class {
+            ElfClass::Elf32 => (
+                r.u32(base)?,
+                r.word(base + 4, class)?,
+                r.word(base + 8, class)?,
+                r.bytes(base + 12, 1)?[0],
+                r.u16(base + 14)?,
+            ),
+            ElfClass::Elf64 => (
+                r.u32(base)?,
+                r.word(base + 8, class)?,
+                r.word(base + 16, class)?,
+                r.bytes(base + 4, 1)?[0],
+                r.u16(base + 6)?,
+            ),
+        };
+        symbols.push(ElfSymbol {
+            name: read_cstr(strtab, name as usize).to_string(),
+            value,
+            size,
+            binding: SymbolBinding::from(info >> 4),
+            kind: SymbolKind::from(info & 0xf),
+            section_index: shndx,
+        });
+    }
+    Ok(symbols)
+}
+
+/// Read a NUL-terminated string from a table.
+pub(crate) fn read_cstr(table: &[u8], offset: usize) -> &str {
+    let bytes = table.get(offset..).unwrap_or(&[]);
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    std::str::from_utf8(&bytes[..end]).unwrap_or("")
+}
+
+/// Build a little-endian ELF32 relocatable object with the given sections.
+#[cfg(test)]
+pub(crate) fn build_test_elf(sections: &[(&str, u32, &[u8])]) -> Vec<u8> {
+    let mut shstrtab = vec![0u8];
+    let mut name_offsets = Vec::new();
+    for (name, _, _) in sections.iter().chain(&[(".shstrtab", 3, &[][..])]) {
+        name_offsets.push(shstrtab.len() as u32);
+        shstrtab.extend_from_slice(name.as_bytes());
+        shstrtab.push(0);
+    }
+
+    let mut data = vec![0u8; 52];
+    let mut offsets = Vec::new();
+    for (_, _, contents) in sections {
+        offsets.push(data.len() as u32);
+        data.extend_from_slice(contents);
+    }
+    let shstrtab_offset = data.len() as u32;
+    data.extend_from_slice(&shstrtab);
+    data.resize(data.len().next_multiple_of(4), 0);
+    let shoff = data.len() as u32;
+    let shnum = sections.len() as u16 + 2;
+
+    data[..8].copy_from_slice(b"\x7fELF\x01\x01\x01\x00");
+    data[16..18].copy_from_slice(&1u16.to_le_bytes());
+    data[18..20].copy_from_slice(&EM_ARM.to_le_bytes());
+    data[20..24].copy_from_slice(&1u32.to_le_bytes());
+    data[32..36].copy_from_slice(&shoff.to_le_bytes());
+    data[40..42].copy_from_slice(&52u16.to_le_bytes());
+    data[46..48].copy_from_slice(&40u16.to_le_bytes());
+    data[48..50].copy_from_slice(&shnum.to_le_bytes());
+    data[50..52].copy_from_slice(&(shnum - 1).to_le_bytes());
+
+    // Null section header
+    data.extend_from_slice(&[0u8; 40]);
+    let all = sections
+        .iter()
+        .map(|(_, t, c)| (*t, c.len() as u32))
+        .zip(offsets)
+        .chain(std::iter::once((
+            (3, shstrtab.len() as u32),
+            shstrtab_offset,
+        )));
+    // Symbol tables link to .strtab
+    let strtab = sections
+        .iter()
+        .position(|(name, _, _)| *name == ".strtab")
+        .map_or(0, |i| i as u32 + 1);
+    for (((section_type, size), offset), name) in all.zip(name_offsets) {
+        let mut header = [0u8; 40];
+        header[0..4].copy_from_slice(&name.to_le_bytes());
+        header[4..8].copy_from_slice(&section_type.to_le_bytes());
+        if section_type == SHT_SYMTAB {
+            header[24..28].copy_from_slice(&strtab.to_le_bytes());
+            header[36..40].copy_from_slice(&16u32.to_le_bytes());
+        }
+        header[16..20].copy_from_slice(&offset.to_le_bytes());
+        header[20..24].copy_from_slice(&size.to_le_bytes());
+        data.extend_from_slice(&header);
+    }
+
+    data
+}
+
+/// Build ELF32 .symtab and .strtab contents from
+/// `(name, value, size, st_info, section index)` entries.
+#[cfg(test)]
+pub(crate) fn build_test_symtab(symbols: &[(&str, u32, u32, u8, u16)]) -> (Vec<u8>, Vec<u8>) {
+    let mut symtab = vec![0u8; 16];
+    let mut strtab = vec![0u8];
+    for &(name, value, size, info, shndx) in symbols {
+        symtab.extend_from_slice(&(strtab.len() as u32).to_le_bytes());
+        symtab.extend_from_slice(&value.to_le_bytes());
+        symtab.extend_from_slice(&size.to_le_bytes());
+        symtab.extend_from_slice(&[info, 0]);
+        symtab.extend_from_slice(&shndx.to_le_bytes());
+        strtab.extend_from_slice(name.as_bytes());
+        strtab.push(0);
+    }
+    (symtab, strtab)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bad_magic() {
+        assert!(matches!(
+            ElfFile::parse(b"not an elf file".to_vec()),
+            Err(ElfError::BadMagic)
+        ));
+    }
+
+    #[test]
+    fn test_parse_sections() {
+        let data = build_test_elf(&[(".text", 1, b"\x00\xbf\x70\x47"), (".comment", 1, b"GCC\0")]);
+        let elf = ElfFile::parse(data).unwrap();
+
+        assert_eq!(elf.class, ElfClass::Elf32);
+        assert!(!elf.big_endian);
+        assert_eq!(elf.machine, EM_ARM);
+        assert_eq!(elf.sections.len(), 4);
+
+        let text = elf.section(".text").unwrap();
+        assert_eq!(elf.section_data(text).unwrap(), b"\x00\xbf\x70\x47");
+        assert!(elf.section(".data").is_none());
+    }
+
+    #[test]
+    fn test_parse_symbols() {
+        let (symtab, strtab) = build_test_symtab(&[
+            ("main", 0x0800_0101, 0x40, 0x12, 1),
+            ("Default_Handler", 0x0800_0141, 0x2, 0x22, 1),
+            ("uart_init", 0, 0, 0x10, 0),
+        ]);
+        let data = build_test_elf(&[
+            (".text", 1, &[0u8; 0x48]),
+            (".symtab", SHT_SYMTAB, &symtab),
+            (".strtab", 3, &strtab),
+        ]);
+        let elf = ElfFile::parse(data).unwrap();
+        assert_eq!(elf.symbols.len(), 3);
+
+        let main = elf.symbol("main").unwrap();
+        assert_eq!(main.value, 0x0800_0101);
+        assert_eq!(main.size, 0x40);
+        assert_eq!(main.binding, SymbolBinding::Global);
+        assert_eq!(main.kind, SymbolKind::Func);
+        assert_eq!(elf.symbol_section(main).unwrap().name, ".text");
+
+        assert_eq!(elf.symbols[1].binding, SymbolBinding::Weak);
+        assert!(elf.symbols[2].is_undefined());
+        assert!(elf.symbol("uart_init").is_none());
+    }
+
+    #[test]
+    fn test_parse_segments() {
+        let mut data = build_test_elf(&[(".text", 1, b"\x00\xbf")]);
+        let phoff = data.len() as u32;
+        let mut phdr = Vec::new();
+        for word in [PT_LOAD, 0x34, 0x2000_0000, 0x0800_1000, 0x10, 0x400, 6, 4] {
+            phdr.extend_from_slice(&word.to_le_bytes());
+        }
+        data.extend(phdr);
+        data[28..32].copy_from_slice(&phoff.to_le_bytes());
+        data[42..44].copy_from_slice(&32u16.to_le_bytes());
+        data[44..46].copy_from_slice(&1u16.to_le_bytes());
+
+        let elf = ElfFile::parse(data).unwrap();
+        assert_eq!(elf.segments.len(), 1);
+        let seg = &elf.segments[0];
+        assert!(seg.is_load());
+        assert_eq!(seg.vaddr, 0x2000_0000);
+        assert_eq!(seg.paddr, 0x0800_1000);
+        assert_eq!(seg.filesz, 0x10);
+        assert_eq!(seg.memsz, 0x400);
+        assert_eq!(seg.flags, 6);
+    }
+
+    #[test]
+    fn test_truncated() {
+        let mut data = build_test_elf(&[(".text", 1, b"\x00")]);
+        data.truncate(60);
+        assert!(matches!(ElfFile::parse(data), Err(ElfError::Truncated(_))));
+    }
+
+    #[test]
+    fn test_out_of_range_tables() {
+        let data = build_test_elf(&[(".text", 1, b"\x00")]);
+        let mut bad_shoff = data.clone();
+        bad_shoff[32..36].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(
+            ElfFile::parse(bad_shoff),
+            Err(ElfError::Truncated(_))
+        ));
+
+        // A symbol table claiming 4 GiB must fail before reserving for it
+        let (symtab, strtab) = build_test_symtab(&[("main", 0, 0, 0x12, 1)]);
+        let mut data = build_test_elf(&[
+            (".text", 1, b"\x00"),
+            (".symtab", SHT_SYMTAB, &symtab),
+            (".strtab", 3, &strtab),
+        ]);
+        let shoff = u32::from_le_bytes(data[32..36].try_into().unwrap()) as usize;
+        let size = shoff + 2 * 40 + 20;
+        data[size..size + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(ElfFile::parse(data), Err(ElfError::Truncated(_))));
+    }
+}
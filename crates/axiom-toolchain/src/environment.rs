@@ -0,0 +1,261 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Invocation environment capture for reproducibility audits.
+//!
+//! When enabled, compile and link results record the exact command line,
+//! working directory, selected environment variables and a SHA-256 of the
+//! compiler driver, so a stored result can be re-run and checked later.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+
+/// Variables that influence GCC and Clang behaviour.
+pub const DEFAULT_CAPTURED_VARIABLES: &[&str] = &[
+    "PATH",
+    "CPATH",
+    "C_INCLUDE_PATH",
+    "CPLUS_INCLUDE_PATH",
+    "LIBRARY_PATH",
+    "COMPILER_PATH",
+    "GCC_EXEC_PREFIX",
+    "SOURCE_DATE_EPOCH",
+    "LANG",
+    "LC_ALL",
+];
+
+/// What to capture with each invocation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EnvironmentCapture {
+    /// Environment variables to record; unset ones are omitted.
+    pub variables: Vec<String>,
+    /// Record a SHA-256 of the toolchain binary.
+    pub hash_toolchain: bool,
+}
+
+impl Default for EnvironmentCapture {
+    fn default() -> Self {
+        Self {
+            variables: DEFAULT_CAPTURED_VARIABLES
+                .iter()
+                .map(|v| v.to_string())
+                .collect(),
+            hash_toolchain: true,
+        }
+    }
+}
+
+impl EnvironmentCapture {
+    /// Capture the given variables instead of the defaults.
+    pub fn with_variables<S: Into<String>>(
+        mut self,
+        variables: impl IntoIterator<Item = S>,
+    ) -> Self {
+        self.variables = variables.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Set whether the toolchain binary is hashed.
+    pub fn with_hash_toolchain(mut self, hash: bool) -> Self {
+        self.hash_toolchain = hash;
+        self
+    }
+
+    /// Record an invocation about to run in the current process environment.
+    pub fn capture(&self, program: &Path, args: &[String]) -> InvocationRecord {
+        let environment = self
+            .variables
+            .iter()
+            .filter_map(|name| std::env::var(name).ok().map(|value| (name.clone(), value)))
+            .collect();
+        InvocationRecord {
+            program: program.to_path_buf(),
+            args: args.to_vec(),
+            working_dir: std::env::current_dir().unwrap_or_default(),
+            environment,
+            toolchain_sha256: if self.hash_toolchain {
+                cached_sha256(program)
+            } else {
+                None
+            },
+            response_file: None,
+        }
+    }
+}
+
+/// Everything needed to re-run a toolchain invocation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InvocationRecord {
+    /// Program that was run.
+    pub program: PathBuf,
+    /// Arguments, in order.
+    pub args: Vec<String>,
+    /// Working directory.
+    pub working_dir: PathBuf,
+    /// Captured environment variables.
+    pub environment: BTreeMap<String, String>,
+    /// SHA-256 of the program binary, in lowercase hex.
+    ///
+    /// Covers the driver only, not the compiler proper or assembler it
+    /// spawns.
+    pub toolchain_sha256: Option<String>,
+    /// Response file contents, when the arguments were too long for the
+    /// command line and passed as `@file`.
+    #[serde(default)]
+    pub response_file: Option<String>,
+}
+
+impl InvocationRecord {
+    /// Command line with arguments quoted for a POSIX shell.
+    pub fn command_line(&self) -> String {
+        std::iter::once(self.program.display().to_string())
+            .chain(self.args.iter().cloned())
+            .map(|arg| shell_quote(&arg))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// A command reproducing the invocation with the captured directory
+    /// and variables.
+    pub fn to_command(&self) -> Command {
+        let mut command = Command::new(&self.program);
+        command
+            .args(&self.args)
+            .current_dir(&self.working_dir)
+            .envs(&self.environment);
+        command
+    }
+
+    /// Whether the program binary still matches the recorded hash.
+    ///
+    /// `None` if no hash was recorded or the binary cannot be read.
+    pub fn toolchain_matches(&self) -> Option<bool> {
+        let recorded = self.toolchain_sha256.as_ref()?;
+        let current = sha256_file(&self.program).ok()?;
+        Some(*recorded == current)
+    }
+}
+
+/// SHA-256 of a file, in lowercase hex.
+pub fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+/// Hash a toolchain binary, reusing the result while its size and
+/// modification time are unchanged.
+fn cached_sha256(path: &Path) -> Option<String> {
+    type Cache = Mutex<HashMap<PathBuf, (u64, SystemTime, String)>>;
+    static CACHE: OnceLock<Cache> = OnceLock::new();
+
+    let metadata = std::fs::metadata(path).ok()?;
+    let key = (metadata.len(), metadata.modified().ok()?);
+    let cache = CACHE.get_or_init(Default::default);
+    if let Some((len, modified, hash)) = cache.lock().ok()?.get(path) {
+        if (*len, *modified) == key {
+            return Some(hash.clone());
+        }
+    }
+
+    let hash = sha256_file(path).ok()?;
+    if let Ok(mut cache) = cache.lock() {
+        cache.insert(path.to_path_buf(), (key.0, key.1, hash.clone()));
+    }
+    Some(hash)
+}
+
+/// Quote an argument for a POSIX shell if needed.
+pub(crate) fn shell_quote(arg: &str) -> String {
+    let safe = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c));
+    if safe {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_sha256_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("abc");
+        std::fs::write(&path, "abc").unwrap();
+        assert_eq!(
+            sha256_file(&path).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_capture() {
+        let dir = TempDir::new().unwrap();
+        let program = dir.path().join("cc");
+        std::fs::write(&program, "abc").unwrap();
+
+        let record = EnvironmentCapture::default()
+            .with_variables(["PATH", "AXIOM_SURELY_UNSET_VARIABLE"])
+            .capture(&program, &["-c".to_string(), "main.c".to_string()]);
+        assert_eq!(record.args, vec!["-c", "main.c"]);
+        assert!(record.environment.contains_key("PATH"));
+        assert_eq!(record.environment.len(), 1);
+        assert_eq!(record.working_dir, std::env::current_dir().unwrap());
+        assert_eq!(record.toolchain_matches(), Some(true));
+
+        std::fs::write(&program, "abcd").unwrap();
+        assert_eq!(record.toolchain_matches(), Some(false));
+
+        let unhashed = EnvironmentCapture::default()
+            .with_hash_toolchain(false)
+            .capture(&program, &[]);
+        assert_eq!(unhashed.toolchain_sha256, None);
+        assert_eq!(unhashed.toolchain_matches(), None);
+    }
+
+    #[test]
+    fn test_command_line_quoting() {
+        let record = InvocationRecord {
+            program: PathBuf::from("/opt/arm/bin/arm-none-eabi-gcc"),
+            args: vec![
+                "-DVERSION=\"1.0\"".to_string(),
+                "-o".to_string(),
+                "my file.o".to_string(),
+                "-Wl,--gc-sections".to_string(),
+            ],
+            working_dir: PathBuf::from("/proj"),
+            environment: BTreeMap::new(),
+            toolchain_sha256: None,
+            response_file: None,
+        };
+        assert_eq!(
+            record.command_line(),
+            r#"/opt/arm/bin/arm-none-eabi-gcc '-DVERSION="1.0"' -o 'my file.o' -Wl,--gc-sections"#
+        );
+    }
+}
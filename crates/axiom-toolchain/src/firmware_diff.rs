@@ -0,0 +1,661 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Structural diff between two firmware builds.
+//!
+//! Compares sections, sized symbols and image bytes, so an edit meant to
+//! have no functional effect can be checked to have changed only what was
+//! expected. ELF builds give all three; HEX/SREC builds give bytes only.
+
+use crate::{
+    read_image, BinaryGenError, ElfError, ElfFile, FirmwareImage, SymbolKind, EM_ARM, SHF_ALLOC,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+use thiserror::Error;
+
+/// Firmware diff errors.
+#[derive(Debug, Error)]
+pub enum FirmwareDiffError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("ELF error: {0}")]
+    Elf(#[from] ElfError),
+
+    #[error("Image error: {0}")]
+    Image(#[from] BinaryGenError),
+}
+
+/// An allocated section of a build.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildSection {
+    /// Section name.
+    pub name: String,
+    /// Run-time address.
+    pub address: u64,
+    /// Load address in the image.
+    pub load_address: u64,
+    /// Size in bytes.
+    pub size: u64,
+}
+
+/// A sized function or data object of a build.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildSymbol {
+    /// Symbol name.
+    pub name: String,
+    /// Run-time address (Thumb bit cleared).
+    pub address: u64,
+    /// Size in bytes.
+    pub size: u64,
+    /// Defining section.
+    pub section: Option<String>,
+}
+
+/// One side of a firmware diff.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FirmwareBuild {
+    /// Allocated sections, in header order.
+    pub sections: Vec<BuildSection>,
+    /// Sized functions and objects.
+    pub symbols: Vec<BuildSymbol>,
+    /// Loadable contents.
+    pub image: FirmwareImage,
+}
+
+impl FirmwareBuild {
+    /// Collect sections, symbols and image from a parsed ELF.
+    pub fn from_elf(elf: &ElfFile) -> Result<Self, FirmwareDiffError> {
+        let sections = elf
+            .sections
+            .iter()
+            .filter(|s| s.flags & SHF_ALLOC != 0)
+            .map(|s| BuildSection {
+                name: s.name.clone(),
+                address: s.addr,
+                load_address: elf.load_address(s),
+                size: s.size,
+            })
+            .collect();
+
+        let thumb_mask = if elf.machine == EM_ARM { !1 } else { !0 };
+        let symbols = elf
+            .symbols
+            .iter()
+            .filter(|s| matches!(s.kind, SymbolKind::Func | SymbolKind::Object))
+            .filter(|s| s.size > 0 && !s.is_undefined())
+            .map(|s| BuildSymbol {
+                name: s.name.clone(),
+                address: if s.kind == SymbolKind::Func {
+                    s.value & thumb_mask
+                } else {
+                    s.value
+                },
+                size: s.size,
+                section: elf.symbol_section(s).map(|sec| sec.name.clone()),
+            })
+            .collect();
+
+        Ok(Self {
+            sections,
+            symbols,
+            image: FirmwareImage::from_elf(elf)?,
+        })
+    }
+
+    /// A build known only by its image.
+    pub fn from_image(image: FirmwareImage) -> Self {
+        Self {
+            image,
+            ..Default::default()
+        }
+    }
+
+    /// Read an ELF, Intel HEX or S-record file.
+    pub fn read(path: &Path) -> Result<Self, FirmwareDiffError> {
+        let data = std::fs::read(path)?;
+        if data.starts_with(b"\x7fELF") {
+            Self::from_elf(&ElfFile::parse(data)?)
+        } else {
+            Ok(Self::from_image(read_image(path)?))
+        }
+    }
+
+    /// Section whose load range contains an image address.
+    fn section_at(&self, address: u64) -> Option<&BuildSection> {
+        self.sections
+            .iter()
+            .filter(|s| s.size > 0)
+            .find(|s| address >= s.load_address && address < s.load_address + s.size)
+    }
+
+    /// Contents of a section in the image, if it is loaded.
+    fn section_bytes(&self, section: &BuildSection) -> Option<&[u8]> {
+        let address = u32::try_from(section.load_address).ok()?;
+        self.image.read(address, section.size as usize)
+    }
+
+    /// Contents of a symbol in the image, if its section is loaded.
+    fn symbol_bytes(&self, symbol: &BuildSymbol) -> Option<&[u8]> {
+        let section = self
+            .sections
+            .iter()
+            .find(|s| Some(&s.name) == symbol.section.as_ref())?;
+        let load = symbol.address.checked_sub(section.address)? + section.load_address;
+        self.image
+            .read(u32::try_from(load).ok()?, symbol.size as usize)
+    }
+}
+
+/// How an item differs between builds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DiffStatus {
+    /// Identical.
+    Unchanged,
+    /// Only in the new build.
+    Added,
+    /// Only in the old build.
+    Removed,
+    /// Size changed.
+    Resized,
+    /// Same size, different contents.
+    Modified,
+    /// Same size and contents at a different address.
+    Moved,
+}
+
+/// Comparison of one section.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SectionDiff {
+    /// Section name.
+    pub name: String,
+    /// What changed.
+    pub status: DiffStatus,
+    /// Address in the old build.
+    pub old_address: Option<u64>,
+    /// Address in the new build.
+    pub new_address: Option<u64>,
+    /// Size in the old build.
+    pub old_size: Option<u64>,
+    /// Size in the new build.
+    pub new_size: Option<u64>,
+}
+
+impl SectionDiff {
+    /// Size change in bytes.
+    pub fn delta(&self) -> i64 {
+        self.new_size.unwrap_or(0) as i64 - self.old_size.unwrap_or(0) as i64
+    }
+}
+
+/// Comparison of one symbol.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SymbolDiff {
+    /// Symbol name.
+    pub name: String,
+    /// What changed.
+    pub status: DiffStatus,
+    /// Defining section in the new build, or the old one if removed.
+    pub section: Option<String>,
+    /// Address in the old build.
+    pub old_address: Option<u64>,
+    /// Address in the new build.
+    pub new_address: Option<u64>,
+    /// Size in the old build.
+    pub old_size: Option<u64>,
+    /// Size in the new build.
+    pub new_size: Option<u64>,
+}
+
+impl SymbolDiff {
+    /// Size change in bytes.
+    pub fn delta(&self) -> i64 {
+        self.new_size.unwrap_or(0) as i64 - self.old_size.unwrap_or(0) as i64
+    }
+}
+
+/// How a byte range differs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RangeChange {
+    /// Bytes present only in the new image.
+    Added,
+    /// Bytes present only in the old image.
+    Removed,
+    /// Bytes present in both with different values.
+    Modified,
+}
+
+/// A contiguous range of differing image bytes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChangedRange {
+    /// First differing load address.
+    pub start: u32,
+    /// One past the last differing address.
+    pub end: u64,
+    /// How the bytes differ.
+    pub change: RangeChange,
+    /// Section containing the range.
+    pub section: Option<String>,
+    /// Symbols overlapping the range.
+    pub symbols: Vec<String>,
+}
+
+impl ChangedRange {
+    /// Number of bytes in the range.
+    pub fn len(&self) -> u64 {
+        self.end - u64::from(self.start)
+    }
+
+    /// Whether the range is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Structural diff between two builds.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FirmwareDiff {
+    /// Every section of either build; old order first, then additions.
+    pub sections: Vec<SectionDiff>,
+    /// Symbols that changed in any way.
+    pub symbols: Vec<SymbolDiff>,
+    /// Differing image bytes, in address order.
+    pub ranges: Vec<ChangedRange>,
+    /// Image size change in bytes.
+    pub image_delta: i64,
+}
+
+impl FirmwareDiff {
+    /// Whether the builds are equivalent.
+    pub fn is_identical(&self) -> bool {
+        self.ranges.is_empty()
+            && self.symbols.is_empty()
+            && self
+                .sections
+                .iter()
+                .all(|s| s.status == DiffStatus::Unchanged)
+    }
+
+    /// Total number of differing image bytes.
+    pub fn changed_bytes(&self) -> u64 {
+        self.ranges.iter().map(ChangedRange::len).sum()
+    }
+
+    /// Sections that changed.
+    pub fn changed_sections(&self) -> impl Iterator<Item = &SectionDiff> {
+        self.sections
+            .iter()
+            .filter(|s| s.status != DiffStatus::Unchanged)
+    }
+}
+
+/// Diff two builds.
+pub fn diff_firmware(old: &FirmwareBuild, new: &FirmwareBuild) -> FirmwareDiff {
+    let ranges = changed_ranges(&old.image, &new.image)
+        .into_iter()
+        .map(|(start, end, change)| {
+            let build = if change == RangeChange::Removed {
+                old
+            } else {
+                new
+            };
+            let (section, symbols) = attribute(build, u64::from(start), end);
+            ChangedRange {
+                start,
+                end,
+                change,
+                section,
+                symbols,
+            }
+        })
+        .collect();
+
+    FirmwareDiff {
+        sections: diff_sections(old, new),
+        symbols: diff_symbols(old, new),
+        ranges,
+        image_delta: new.image.size() as i64 - old.image.size() as i64,
+    }
+}
+
+/// Read two builds from disk and diff them.
+pub fn diff_firmware_files(old: &Path, new: &Path) -> Result<FirmwareDiff, FirmwareDiffError> {
+    Ok(diff_firmware(
+        &FirmwareBuild::read(old)?,
+        &FirmwareBuild::read(new)?,
+    ))
+}
+
+/// Status of an item present in both builds.
+fn status(
+    (old_address, old_size, old_bytes): (u64, u64, Option<&[u8]>),
+    (new_address, new_size, new_bytes): (u64, u64, Option<&[u8]>),
+) -> DiffStatus {
+    if old_size != new_size {
+        DiffStatus::Resized
+    } else if old_bytes.is_some() && new_bytes.is_some() && old_bytes != new_bytes {
+        DiffStatus::Modified
+    } else if old_address != new_address {
+        DiffStatus::Moved
+    } else {
+        DiffStatus::Unchanged
+    }
+}
+
+fn diff_sections(old: &FirmwareBuild, new: &FirmwareBuild) -> Vec<SectionDiff> {
+    let mut diffs = Vec::new();
+    for section in &old.sections {
+        let matched = new.sections.iter().find(|s| s.name == section.name);
+        let status = match matched {
+            Some(other) => status(
+                (section.address, section.size, old.section_bytes(section)),
+                (other.address, other.size, new.section_bytes(other)),
+            ),
+            None => DiffStatus::Removed,
+        };
+        diffs.push(SectionDiff {
+            name: section.name.clone(),
+            status,
+            old_address: Some(section.address),
+            new_address: matched.map(|s| s.address),
+            old_size: Some(section.size),
+            new_size: matched.map(|s| s.size),
+        });
+    }
+    for section in &new.sections {
+        if old.sections.iter().all(|s| s.name != section.name) {
+            diffs.push(SectionDiff {
+                name: section.name.clone(),
+                status: DiffStatus::Added,
+                old_address: None,
+                new_address: Some(section.address),
+                old_size: None,
+                new_size: Some(section.size),
+            });
+        }
+    }
+    diffs
+}
+
+/// Symbols are matched by name; same-named locals pair up in address order.
+fn diff_symbols(old: &FirmwareBuild, new: &FirmwareBuild) -> Vec<SymbolDiff> {
+    fn by_name(build: &FirmwareBuild) -> BTreeMap<&str, Vec<&BuildSymbol>> {
+        let mut map: BTreeMap<&str, Vec<&BuildSymbol>> = BTreeMap::new();
+        for symbol in &build.symbols {
+            map.entry(&symbol.name).or_default().push(symbol);
+        }
+        for symbols in map.values_mut() {
+            symbols.sort_by_key(|s| s.address);
+            symbols.dedup_by_key(|s| s.address);
+        }
+        map
+    }
+
+    let old_symbols = by_name(old);
+    let new_symbols = by_name(new);
+    let mut names: Vec<&str> = old_symbols
+        .keys()
+        .chain(new_symbols.keys())
+        .copied()
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+
+    let mut diffs = Vec::new();
+    for name in names {
+        let before = old_symbols.get(name).map_or(&[][..], Vec::as_slice);
+        let after = new_symbols.get(name).map_or(&[][..], Vec::as_slice);
+        for i in 0..before.len().max(after.len()) {
+            let (a, b) = (before.get(i).copied(), after.get(i).copied());
+            let status = match (a, b) {
+                (Some(a), Some(b)) => status(
+                    (a.address, a.size, old.symbol_bytes(a)),
+                    (b.address, b.size, new.symbol_bytes(b)),
+                ),
+                (None, _) => DiffStatus::Added,
+                (_, None) => DiffStatus::Removed,
+            };
+            if status == DiffStatus::Unchanged {
+                continue;
+            }
+            diffs.push(SymbolDiff {
+                name: name.to_string(),
+                status,
+                section: b.or(a).and_then(|s| s.section.clone()),
+                old_address: a.map(|s| s.address),
+                new_address: b.map(|s| s.address),
+                old_size: a.map(|s| s.size),
+                new_size: b.map(|s| s.size),
+            });
+        }
+    }
+    diffs
+}
+
+/// Differing byte ranges between two images.
+fn changed_ranges(old: &FirmwareImage, new: &FirmwareImage) -> Vec<(u32, u64, RangeChange)> {
+    let mut bounds: Vec<u64> = old
+        .ranges()
+        .into_iter()
+        .chain(new.ranges())
+        .flat_map(|(start, end)| [u64::from(start), end])
+        .collect();
+    bounds.sort_unstable();
+    bounds.dedup();
+
+    let mut ranges: Vec<(u32, u64, RangeChange)> = Vec::new();
+    let mut push = |start: u64, end: u64, change: RangeChange| match ranges.last_mut() {
+        Some(last) if last.1 == start && last.2 == change => last.1 = end,
+        _ => ranges.push((start as u32, end, change)),
+    };
+
+    // Presence is uniform between consecutive block boundaries
+    for pair in bounds.windows(2) {
+        let (start, end) = (pair[0], pair[1]);
+        let len = (end - start) as usize;
+        let address = start as u32;
+        match (old.read(address, len), new.read(address, len)) {
+            (Some(a), Some(b)) => {
+                let mut run: Option<usize> = None;
+                for i in 0..=len {
+                    let differs = i < len && a[i] != b[i];
+                    match (differs, run) {
+                        (true, None) => run = Some(i),
+                        (false, Some(from)) => {
+                            push(start + from as u64, start + i as u64, RangeChange::Modified);
+                            run = None;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            (None, Some(_)) => push(start, end, RangeChange::Added),
+            (Some(_), None) => push(start, end, RangeChange::Removed),
+            (None, None) => {}
+        }
+    }
+    ranges
+}
+
+/// Section and symbols covering an image address range.
+fn attribute(build: &FirmwareBuild, start: u64, end: u64) -> (Option<String>, Vec<String>) {
+    let Some(section) = build.section_at(start) else {
+        return (None, Vec::new());
+    };
+    // Symbols use run-time addresses
+    let offset = section.address.wrapping_sub(section.load_address);
+    let (start, end) = (start.wrapping_add(offset), end.wrapping_add(offset));
+    let mut seen = HashSet::new();
+    let symbols = build
+        .symbols
+        .iter()
+        .filter(|s| s.section.as_ref() == Some(&section.name))
+        .filter(|s| s.address < end && s.address + s.size > start)
+        .filter(|s| seen.insert(s.name.as_str()))
+        .map(|s| s.name.clone())
+        .collect();
+    (Some(section.name.clone()), symbols)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn section(name: &str, address: u64, load_address: u64, size: u64) -> BuildSection {
+        BuildSection {
+            name: name.to_string(),
+            address,
+            load_address,
+            size,
+        }
+    }
+
+    fn symbol(name: &str, address: u64, size: u64, section: &str) -> BuildSymbol {
+        BuildSymbol {
+            name: name.to_string(),
+            address,
+            size,
+            section: Some(section.to_string()),
+        }
+    }
+
+    fn build(text: &[u8], data: &[u8], symbols: Vec<BuildSymbol>) -> FirmwareBuild {
+        let mut image = FirmwareImage::default();
+        image.insert(0x0800_0000, text);
+        image.insert(0x0800_0000 + text.len() as u32, data);
+        FirmwareBuild {
+            sections: vec![
+                section(".text", 0x0800_0000, 0x0800_0000, text.len() as u64),
+                section(
+                    ".data",
+                    0x2000_0000,
+                    0x0800_0000 + text.len() as u64,
+                    data.len() as u64,
+                ),
+                section(".bss", 0x2000_0000 + data.len() as u64, 0x2000_0100, 64),
+            ],
+            symbols,
+            image,
+        }
+    }
+
+    fn symbols() -> Vec<BuildSymbol> {
+        vec![
+            symbol("main", 0x0800_0000, 8, ".text"),
+            symbol("uart_init", 0x0800_0008, 8, ".text"),
+            symbol("counter", 0x2000_0000, 4, ".data"),
+        ]
+    }
+
+    #[test]
+    fn test_identical_builds() {
+        let a = build(&[1; 16], &[2; 4], symbols());
+        let diff = diff_firmware(&a, &a.clone());
+        assert!(diff.is_identical());
+        assert_eq!(diff.changed_bytes(), 0);
+        assert_eq!(diff.sections.len(), 3);
+    }
+
+    #[test]
+    fn test_modified_bytes_attributed() {
+        let old = build(&[1; 16], &[2; 4], symbols());
+        let mut text = [1u8; 16];
+        text[10] = 0xFF;
+        text[11] = 0xFE;
+        let mut data = [2u8; 4];
+        data[0] = 9;
+        let new = build(&text, &data, symbols());
+
+        let diff = diff_firmware(&old, &new);
+        assert_eq!(diff.ranges.len(), 2);
+        assert_eq!(diff.ranges[0].start, 0x0800_000A);
+        assert_eq!(diff.ranges[0].len(), 2);
+        assert_eq!(diff.ranges[0].change, RangeChange::Modified);
+        assert_eq!(diff.ranges[0].section.as_deref(), Some(".text"));
+        assert_eq!(diff.ranges[0].symbols, vec!["uart_init"]);
+        // .data is loaded from flash but symbols use its RAM address
+        assert_eq!(diff.ranges[1].section.as_deref(), Some(".data"));
+        assert_eq!(diff.ranges[1].symbols, vec!["counter"]);
+
+        let changed: Vec<_> = diff.symbols.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(changed, vec!["counter", "uart_init"]);
+        assert!(diff
+            .symbols
+            .iter()
+            .all(|s| s.status == DiffStatus::Modified));
+        assert_eq!(diff.changed_sections().count(), 2);
+    }
+
+    #[test]
+    fn test_resized_and_added() {
+        let old = build(&[1; 16], &[2; 4], symbols());
+        let mut new_symbols = symbols();
+        new_symbols[1].size = 12;
+        new_symbols.push(symbol("spi_init", 0x0800_0014, 4, ".text"));
+        let mut new = build(&[1; 24], &[2; 4], new_symbols);
+        new.sections
+            .push(section(".ramfunc", 0x2000_0200, 0x0800_0040, 4));
+
+        let diff = diff_firmware(&old, &new);
+        let text = &diff.sections[0];
+        assert_eq!(text.status, DiffStatus::Resized);
+        assert_eq!(text.delta(), 8);
+        assert_eq!(diff.sections[3].name, ".ramfunc");
+        assert_eq!(diff.sections[3].status, DiffStatus::Added);
+        assert_eq!(diff.image_delta, 8);
+
+        let uart = diff.symbols.iter().find(|s| s.name == "uart_init").unwrap();
+        assert_eq!(uart.status, DiffStatus::Resized);
+        assert_eq!(uart.delta(), 4);
+        let spi = diff.symbols.iter().find(|s| s.name == "spi_init").unwrap();
+        assert_eq!(spi.status, DiffStatus::Added);
+        assert_eq!(spi.old_size, None);
+        // "counter" moved with .data's load address but its bytes are the same
+        assert!(diff.symbols.iter().all(|s| s.name != "counter"));
+    }
+
+    #[test]
+    fn test_removed_range() {
+        let mut old = FirmwareImage::default();
+        old.insert(0x100, &[0; 8]);
+        old.insert(0x200, &[0; 4]);
+        let mut new = FirmwareImage::default();
+        new.insert(0x100, &[0; 8]);
+        new.insert(0x300, &[0; 2]);
+
+        let diff = diff_firmware(
+            &FirmwareBuild::from_image(old),
+            &FirmwareBuild::from_image(new),
+        );
+        let ranges: Vec<_> = diff
+            .ranges
+            .iter()
+            .map(|r| (r.start, r.end, r.change))
+            .collect();
+        assert_eq!(
+            ranges,
+            vec![
+                (0x200, 0x204, RangeChange::Removed),
+                (0x300, 0x302, RangeChange::Added),
+            ]
+        );
+        assert_eq!(diff.image_delta, -2);
+        assert!(!diff.is_identical());
+    }
+
+    #[test]
+    fn test_moved_symbol() {
+        let old = build(&[1; 16], &[2; 4], symbols());
+        let mut moved = symbols();
+        moved[0].address = 0x0800_0008;
+        moved[1].address = 0x0800_0000;
+        let new = build(&[1; 16], &[2; 4], moved);
+
+        let diff = diff_firmware(&old, &new);
+        assert!(diff.ranges.is_empty());
+        assert_eq!(diff.symbols.len(), 2);
+        assert!(diff.symbols.iter().all(|s| s.status == DiffStatus::Moved));
+    }
+}
@@ -0,0 +1,484 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! One-shot firmware flashing.
+//!
+//! Programs an ELF, HEX, or raw binary image through OpenOCD, probe-rs, or
+//! STM32CubeProgrammer, verifies it by reading it back, and resets the
+//! target. The backend is picked from what's installed unless the caller
+//! names one.
+
+use crate::limits::{is_timeout, run_limited, tool_limits};
+use crate::{
+    openocd_flash, read_image, target_script_for, BinaryGenError, DebugProbe, FirmwareImage,
+    OpenOcdError, OpenOcdEvent, ProbeKind,
+};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Instant;
+
+/// Known paths for STM32_Programmer_CLI.
+const STM32_PROGRAMMER_PATHS: &[&str] = &[
+    "/usr/local/STMicroelectronics/STM32Cube/STM32CubeProgrammer/bin/STM32_Programmer_CLI",
+    "/opt/st/STM32CubeProgrammer/bin/STM32_Programmer_CLI",
+    "/Applications/STMicroelectronics/STM32Cube/STM32CubeProgrammer/STM32CubeProgrammer.app/Contents/MacOs/bin/STM32_Programmer_CLI",
+    "C:\\Program Files\\STMicroelectronics\\STM32Cube\\STM32CubeProgrammer\\bin\\STM32_Programmer_CLI.exe",
+];
+
+/// Error type for flashing.
+#[derive(Debug, thiserror::Error)]
+pub enum FlashError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Cannot read image: {0}")]
+    Image(#[from] BinaryGenError),
+
+    #[error("Raw binary images need a load address")]
+    NoAddress,
+
+    #[error("No flashing tool can program {0}")]
+    NoBackend(String),
+
+    #[error("{0} is not installed")]
+    NotInstalled(FlashBackend),
+
+    #[error(transparent)]
+    OpenOcd(#[from] OpenOcdError),
+
+    #[error("{backend} failed: {message}")]
+    Failed {
+        backend: FlashBackend,
+        message: String,
+    },
+
+    #[error("Flashing timed out after {0}s")]
+    TimedOut(u64),
+}
+
+/// Tool used to program the target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FlashBackend {
+    OpenOcd,
+    ProbeRs,
+    Stm32Programmer,
+}
+
+impl std::fmt::Display for FlashBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FlashBackend::OpenOcd => write!(f, "OpenOCD"),
+            FlashBackend::ProbeRs => write!(f, "probe-rs"),
+            FlashBackend::Stm32Programmer => write!(f, "STM32CubeProgrammer"),
+        }
+    }
+}
+
+/// Options for [`flash_target`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FlashOptions {
+    /// Tool to use; picked automatically when absent.
+    #[serde(default)]
+    pub backend: Option<FlashBackend>,
+    /// Load address for raw binaries.
+    #[serde(default)]
+    pub address: Option<u64>,
+}
+
+/// A programmed address range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FlashRange {
+    /// First address.
+    pub start: u64,
+    /// One past the last address.
+    pub end: u64,
+}
+
+/// Result of a successful flash.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FlashResult {
+    /// Tool that programmed the target.
+    pub backend: FlashBackend,
+    /// Programmed ranges.
+    pub ranges: Vec<FlashRange>,
+    /// Bytes programmed.
+    pub bytes: u64,
+    /// Whether readback matched the image.
+    pub verified: bool,
+    /// Wall-clock time, including erase and verify.
+    pub duration_ms: u64,
+    /// Tool output.
+    pub log: Vec<String>,
+}
+
+/// Whether an image is a raw binary, which carries no addresses.
+fn is_raw_binary(image: &Path) -> bool {
+    image
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("bin"))
+}
+
+/// Load the image to find the ranges it will program.
+pub fn image_ranges(image: &Path, address: Option<u64>) -> Result<Vec<FlashRange>, FlashError> {
+    let extension = image
+        .extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    let loaded = match extension.as_str() {
+        "bin" => {
+            let start = address.ok_or(FlashError::NoAddress)?;
+            let len = std::fs::metadata(image)?.len();
+            return Ok(vec![FlashRange {
+                start,
+                end: start + len,
+            }]);
+        }
+        "hex" | "ihex" | "srec" | "s19" | "s28" | "s37" | "mot" => read_image(image)?,
+        _ => FirmwareImage::read_elf(image)?,
+    };
+    Ok(loaded
+        .ranges()
+        .into_iter()
+        .map(|(start, end)| FlashRange {
+            start: u64::from(start),
+            end,
+        })
+        .collect())
+}
+
+/// Find STM32_Programmer_CLI.
+pub fn find_stm32_programmer() -> Option<PathBuf> {
+    STM32_PROGRAMMER_PATHS
+        .iter()
+        .map(PathBuf::from)
+        .find(|p| p.is_file())
+}
+
+fn backend_path(backend: FlashBackend) -> Option<PathBuf> {
+    match backend {
+        FlashBackend::OpenOcd => crate::detect_openocd().map(|o| o.path),
+        FlashBackend::ProbeRs => crate::find_probe_rs(),
+        FlashBackend::Stm32Programmer => find_stm32_programmer(),
+    }
+}
+
+/// Backends that can program a part through a probe, in order of
+/// preference.
+pub fn flash_backends_for(probe: &DebugProbe, part: &str) -> Vec<FlashBackend> {
+    let mut backends = Vec::new();
+    if target_script_for(part).is_some() {
+        backends.push(FlashBackend::OpenOcd);
+    }
+    backends.push(FlashBackend::ProbeRs);
+    if probe.kind == ProbeKind::StLink && part.to_ascii_uppercase().starts_with("STM32") {
+        backends.push(FlashBackend::Stm32Programmer);
+    }
+    backends
+}
+
+/// `probe-rs` probe selector: `VID:PID[:SERIAL]`.
+fn probe_rs_selector(probe: &DebugProbe) -> String {
+    let mut selector = format!("{:04x}:{:04x}", probe.vendor_id, probe.product_id);
+    if let Some(serial) = &probe.serial {
+        selector.push(':');
+        selector.push_str(serial);
+    }
+    selector
+}
+
+/// Arguments for `probe-rs download`.
+pub fn probe_rs_download_args(
+    image: &Path,
+    probe: &DebugProbe,
+    part: &str,
+    address: Option<u64>,
+) -> Vec<String> {
+    let mut args = vec![
+        "download".to_string(),
+        "--chip".to_string(),
+        part.to_string(),
+        "--probe".to_string(),
+        probe_rs_selector(probe),
+        "--verify".to_string(),
+    ];
+    if is_raw_binary(image) {
+        args.push("--binary-format".to_string());
+        args.push("bin".to_string());
+        if let Some(address) = address {
+            args.push("--base-address".to_string());
+            args.push(format!("0x{:x}", address));
+        }
+    } else if image
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("hex"))
+    {
+        args.push("--binary-format".to_string());
+        args.push("hex".to_string());
+    }
+    args.push(image.display().to_string());
+    args
+}
+
+/// Arguments for `STM32_Programmer_CLI`: connect over SWD, write, verify,
+/// and reset.
+pub fn stm32_programmer_args(
+    image: &Path,
+    probe: &DebugProbe,
+    address: Option<u64>,
+) -> Vec<String> {
+    let mut args = vec!["-c".to_string(), "port=SWD".to_string()];
+    if let Some(serial) = &probe.serial {
+        args.push(format!("sn={}", serial));
+    }
+    args.push("-w".to_string());
+    args.push(image.display().to_string());
+    if is_raw_binary(image) {
+        if let Some(address) = address {
+            args.push(format!("0x{:08x}", address));
+        }
+    }
+    args.push("-v".to_string());
+    args.push("-rst".to_string());
+    args
+}
+
+/// Run a flashing tool and collect its output lines.
+fn run_tool(path: &Path, args: &[String]) -> Result<(bool, Vec<String>), FlashError> {
+    let output = run_limited(Command::new(path).args(args)).map_err(|e| {
+        if is_timeout(&e) {
+            FlashError::TimedOut(tool_limits().timeout_secs.unwrap_or_default())
+        } else {
+            FlashError::Io(e)
+        }
+    })?;
+    let log = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .chain(String::from_utf8_lossy(&output.stderr).lines())
+        .map(str::to_string)
+        .collect();
+    Ok((output.status.success(), log))
+}
+
+/// Last error-looking line of a tool's output.
+fn failure_message(log: &[String]) -> String {
+    log.iter()
+        .rev()
+        .find(|l| l.contains("Error") || l.contains("error"))
+        .or_else(|| log.last())
+        .map(|l| l.trim().to_string())
+        .unwrap_or_else(|| "no output".to_string())
+}
+
+/// Program an image into a target through a probe, verify it by readback,
+/// and reset the target.
+pub fn flash_target(
+    image: &Path,
+    probe: &DebugProbe,
+    part: &str,
+    options: &FlashOptions,
+) -> Result<FlashResult, FlashError> {
+    let ranges = image_ranges(image, options.address)?;
+    let bytes = ranges.iter().map(|r| r.end - r.start).sum();
+
+    let (backend, tool) = match options.backend {
+        Some(backend) => (
+            backend,
+            backend_path(backend).ok_or(FlashError::NotInstalled(backend))?,
+        ),
+        None => flash_backends_for(probe, part)
+            .into_iter()
+            .find_map(|b| backend_path(b).map(|p| (b, p)))
+            .ok_or_else(|| FlashError::NoBackend(part.to_string()))?,
+    };
+
+    let started = Instant::now();
+    let (verified, log) = match backend {
+        FlashBackend::OpenOcd => {
+            let openocd =
+                crate::detect_openocd_at(&tool).ok_or(FlashError::NotInstalled(backend))?;
+            let script = target_script_for(part)
+                .ok_or_else(|| OpenOcdError::UnknownTarget(part.to_string()))?;
+            let config = probe.openocd_config(script);
+            let output = openocd_flash(&openocd, &config, image, options.address)?;
+            let log: Vec<String> = output.messages.iter().map(|m| m.text.clone()).collect();
+            if !output.success {
+                let message = output
+                    .errors()
+                    .last()
+                    .unwrap_or("unknown error")
+                    .to_string();
+                return Err(FlashError::Failed { backend, message });
+            }
+            let verified = output.events.contains(&OpenOcdEvent::Verified);
+            (verified, log)
+        }
+        FlashBackend::ProbeRs => {
+            let args = probe_rs_download_args(image, probe, part, options.address);
+            let (success, mut log) = run_tool(&tool, &args)?;
+            if !success {
+                let message = failure_message(&log);
+                return Err(FlashError::Failed { backend, message });
+            }
+            // `download` leaves the core halted
+            let reset = [
+                "reset".to_string(),
+                "--chip".to_string(),
+                part.to_string(),
+                "--probe".to_string(),
+                probe_rs_selector(probe),
+            ];
+            let (_, reset_log) = run_tool(&tool, &reset)?;
+            log.extend(reset_log);
+            // --verify makes a readback mismatch fail the download
+            (true, log)
+        }
+        FlashBackend::Stm32Programmer => {
+            let args = stm32_programmer_args(image, probe, options.address);
+            let (success, log) = run_tool(&tool, &args)?;
+            if !success || log.iter().any(|l| l.trim_start().starts_with("Error:")) {
+                let message = failure_message(&log);
+                return Err(FlashError::Failed { backend, message });
+            }
+            let verified = log
+                .iter()
+                .any(|l| l.contains("Download verified successfully"));
+            (verified, log)
+        }
+    };
+
+    Ok(FlashResult {
+        backend,
+        ranges,
+        bytes,
+        verified,
+        duration_ms: started.elapsed().as_millis() as u64,
+        log,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ProbeSource;
+    use tempfile::TempDir;
+
+    fn stlink() -> DebugProbe {
+        DebugProbe {
+            kind: ProbeKind::StLink,
+            name: "ST-Link V2-1".to_string(),
+            vendor_id: 0x0483,
+            product_id: 0x374b,
+            serial: Some("066DFF485550755187121723".to_string()),
+            target_voltage: None,
+            source: ProbeSource::Usb,
+        }
+    }
+
+    #[test]
+    fn test_image_ranges() {
+        let dir = TempDir::new().unwrap();
+        let bin = dir.path().join("app.bin");
+        std::fs::write(&bin, [0u8; 256]).unwrap();
+        assert!(matches!(
+            image_ranges(&bin, None),
+            Err(FlashError::NoAddress)
+        ));
+        assert_eq!(
+            image_ranges(&bin, Some(0x0800_0000)).unwrap(),
+            vec![FlashRange {
+                start: 0x0800_0000,
+                end: 0x0800_0100
+            }]
+        );
+
+        let mut image = FirmwareImage::default();
+        image.insert(0x0800_0000, &[1, 2, 3, 4]);
+        image.insert(0x0800_4000, &[5, 6]);
+        let hex = dir.path().join("app.hex");
+        std::fs::write(&hex, image.to_intel_hex()).unwrap();
+        assert_eq!(
+            image_ranges(&hex, None).unwrap(),
+            vec![
+                FlashRange {
+                    start: 0x0800_0000,
+                    end: 0x0800_0004
+                },
+                FlashRange {
+                    start: 0x0800_4000,
+                    end: 0x0800_4002
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_backends_for_part() {
+        assert_eq!(
+            flash_backends_for(&stlink(), "STM32F401RE"),
+            vec![
+                FlashBackend::OpenOcd,
+                FlashBackend::ProbeRs,
+                FlashBackend::Stm32Programmer
+            ]
+        );
+        let mut jlink = stlink();
+        jlink.kind = ProbeKind::JLink;
+        assert_eq!(
+            flash_backends_for(&jlink, "EFM32GG990F1024"),
+            vec![FlashBackend::ProbeRs]
+        );
+    }
+
+    #[test]
+    fn test_tool_arguments() {
+        let probe = stlink();
+        let args = probe_rs_download_args(
+            Path::new("app.bin"),
+            &probe,
+            "STM32F401RETx",
+            Some(0x0800_0000),
+        );
+        assert_eq!(
+            args,
+            vec![
+                "download",
+                "--chip",
+                "STM32F401RETx",
+                "--probe",
+                "0483:374b:066DFF485550755187121723",
+                "--verify",
+                "--binary-format",
+                "bin",
+                "--base-address",
+                "0x8000000",
+                "app.bin"
+            ]
+        );
+
+        let args = stm32_programmer_args(Path::new("app.elf"), &probe, None);
+        assert_eq!(
+            args,
+            vec![
+                "-c",
+                "port=SWD",
+                "sn=066DFF485550755187121723",
+                "-w",
+                "app.elf",
+                "-v",
+                "-rst"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_failure_message() {
+        let log = vec![
+            "Memory Programming ...".to_string(),
+            "Error: failed to erase memory".to_string(),
+            "Disconnected".to_string(),
+        ];
+        assert_eq!(failure_message(&log), "Error: failed to erase memory");
+    }
+}
@@ -0,0 +1,1079 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! GDB/MI client.
+//!
+//! Runs GDB with the MI2 interpreter and talks to it over stdin/stdout.
+//! Commands carry a numeric token; a reader thread parses every output
+//! record, hands result records back to the waiting command by token, and
+//! queues everything else (stops, console output, notifications) as typed
+//! events for the UI to drain.
+
+use crate::{DetectedToolchain, ToolchainKind};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// Fallback GDB binaries that can debug ARM targets.
+const GDB_PATHS: &[&str] = &[
+    "/usr/bin/gdb-multiarch",
+    "/usr/local/bin/gdb-multiarch",
+    "/usr/bin/arm-none-eabi-gdb",
+    "/usr/local/bin/arm-none-eabi-gdb",
+    "/opt/homebrew/bin/arm-none-eabi-gdb",
+];
+
+/// Time allowed for a command's result record.
+///
+/// `-target-download` on a large image over a slow probe is the long pole.
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Error type for GDB operations.
+#[derive(Debug, thiserror::Error)]
+pub enum GdbError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("GDB not found")]
+    NotFound,
+
+    #[error("GDB exited")]
+    Exited,
+
+    #[error("Timed out waiting for GDB")]
+    Timeout,
+
+    #[error("GDB command failed: {0}")]
+    Command(String),
+
+    #[error("Unexpected GDB response: {0}")]
+    Protocol(String),
+}
+
+/// A value in an MI record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MiValue {
+    /// A C string constant.
+    Const(String),
+    /// `{name=value,...}`.
+    Tuple(Vec<(String, MiValue)>),
+    /// `[value,...]`; names in `[name=value,...]` lists are dropped.
+    List(Vec<MiValue>),
+}
+
+impl MiValue {
+    /// The string, if this is a constant.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            MiValue::Const(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// The items, if this is a list.
+    pub fn as_list(&self) -> &[MiValue] {
+        match self {
+            MiValue::List(items) => items,
+            _ => &[],
+        }
+    }
+
+    /// A tuple member.
+    pub fn get(&self, name: &str) -> Option<&MiValue> {
+        match self {
+            MiValue::Tuple(results) => lookup(results, name),
+            _ => None,
+        }
+    }
+
+    /// A tuple member's string value.
+    pub fn get_str(&self, name: &str) -> Option<&str> {
+        self.get(name).and_then(MiValue::as_str)
+    }
+}
+
+fn lookup<'a>(results: &'a [(String, MiValue)], name: &str) -> Option<&'a MiValue> {
+    results.iter().find(|(n, _)| n == name).map(|(_, v)| v)
+}
+
+/// Class of a result record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MiResultClass {
+    Done,
+    Running,
+    Connected,
+    Error,
+    Exit,
+}
+
+/// A `^class,results` record answering a command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MiResultRecord {
+    /// Token of the command this answers.
+    pub token: Option<u32>,
+    /// Result class.
+    pub class: MiResultClass,
+    /// Results.
+    pub results: Vec<(String, MiValue)>,
+}
+
+impl MiResultRecord {
+    /// A result.
+    pub fn get(&self, name: &str) -> Option<&MiValue> {
+        lookup(&self.results, name)
+    }
+
+    /// A result's string value.
+    pub fn get_str(&self, name: &str) -> Option<&str> {
+        self.get(name).and_then(MiValue::as_str)
+    }
+}
+
+/// Kind of an async record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MiAsyncKind {
+    /// `*` — target state changes.
+    Exec,
+    /// `+` — progress of slow operations.
+    Status,
+    /// `=` — breakpoint, thread, and library notifications.
+    Notify,
+}
+
+/// Kind of a stream record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MiStreamKind {
+    /// `~` — CLI output.
+    Console,
+    /// `@` — output from the target program.
+    Target,
+    /// `&` — GDB's internal log.
+    Log,
+}
+
+/// One line of MI output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MiRecord {
+    Result(MiResultRecord),
+    Async {
+        token: Option<u32>,
+        kind: MiAsyncKind,
+        class: String,
+        results: Vec<(String, MiValue)>,
+    },
+    Stream {
+        kind: MiStreamKind,
+        text: String,
+    },
+    /// `(gdb)`, ending a batch of output.
+    Prompt,
+}
+
+/// Parse one line of MI output.
+pub fn parse_mi_record(line: &str) -> Option<MiRecord> {
+    let line = line.trim_end();
+    if line.trim() == "(gdb)" {
+        return Some(MiRecord::Prompt);
+    }
+
+    let digits = line.bytes().take_while(u8::is_ascii_digit).count();
+    let token = line[..digits].parse().ok();
+    let rest = &line[digits..];
+    let marker = rest.chars().next()?;
+    let body = &rest[1..];
+
+    match marker {
+        '~' | '@' | '&' => {
+            let kind = match marker {
+                '~' => MiStreamKind::Console,
+                '@' => MiStreamKind::Target,
+                _ => MiStreamKind::Log,
+            };
+            let mut parser = MiParser::new(body);
+            let text = parser.c_string()?;
+            Some(MiRecord::Stream { kind, text })
+        }
+        '^' | '*' | '+' | '=' => {
+            let (class, results) = match body.split_once(',') {
+                Some((class, results)) => (class, MiParser::new(results).results()?),
+                None => (body, Vec::new()),
+            };
+            if marker == '^' {
+                let class = match class {
+                    "done" => MiResultClass::Done,
+                    "running" => MiResultClass::Running,
+                    "connected" => MiResultClass::Connected,
+                    "error" => MiResultClass::Error,
+                    "exit" => MiResultClass::Exit,
+                    _ => return None,
+                };
+                return Some(MiRecord::Result(MiResultRecord {
+                    token,
+                    class,
+                    results,
+                }));
+            }
+            let kind = match marker {
+                '*' => MiAsyncKind::Exec,
+                '+' => MiAsyncKind::Status,
+                _ => MiAsyncKind::Notify,
+            };
+            Some(MiRecord::Async {
+                token,
+                kind,
+                class: class.to_string(),
+                results,
+            })
+        }
+        _ => None,
+    }
+}
+
+struct MiParser<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> MiParser<'a> {
+    fn new(input: &'a str) -> Self {
+        Self {
+            input: input.as_bytes(),
+            pos: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        if self.peek() == Some(byte) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// `name=value` pairs separated by commas, to the end of input.
+    fn results(&mut self) -> Option<Vec<(String, MiValue)>> {
+        let mut results = vec![self.result()?];
+        while self.eat(b',') {
+            results.push(self.result()?);
+        }
+        (self.pos == self.input.len()).then_some(results)
+    }
+
+    fn result(&mut self) -> Option<(String, MiValue)> {
+        let start = self.pos;
+        while self.peek().is_some_and(|b| b != b'=') {
+            self.pos += 1;
+        }
+        let name = String::from_utf8_lossy(&self.input[start..self.pos]).into_owned();
+        if !self.eat(b'=') {
+            return None;
+        }
+        Some((name, self.value()?))
+    }
+
+    fn value(&mut self) -> Option<MiValue> {
+        match self.peek()? {
+            b'"' => self.c_string().map(MiValue::Const),
+            b'{' => {
+                self.pos += 1;
+                let mut results = Vec::new();
+                if !self.eat(b'}') {
+                    loop {
+                        results.push(self.result()?);
+                        if self.eat(b'}') {
+                            break;
+                        }
+                        if !self.eat(b',') {
+                            return None;
+                        }
+                    }
+                }
+                Some(MiValue::Tuple(results))
+            }
+            b'[' => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                if !self.eat(b']') {
+                    loop {
+                        let item = match self.peek()? {
+                            b'"' | b'{' | b'[' => self.value()?,
+                            _ => self.result()?.1,
+                        };
+                        items.push(item);
+                        if self.eat(b']') {
+                            break;
+                        }
+                        if !self.eat(b',') {
+                            return None;
+                        }
+                    }
+                }
+                Some(MiValue::List(items))
+            }
+            _ => None,
+        }
+    }
+
+    /// A quoted C string; GDB escapes non-printable bytes as octal.
+    fn c_string(&mut self) -> Option<String> {
+        if !self.eat(b'"') {
+            return None;
+        }
+        let mut bytes = Vec::new();
+        loop {
+            let byte = self.peek()?;
+            self.pos += 1;
+            match byte {
+                b'"' => break,
+                b'\\' => {
+                    let escaped = self.peek()?;
+                    self.pos += 1;
+                    match escaped {
+                        b'n' => bytes.push(b'\n'),
+                        b't' => bytes.push(b'\t'),
+                        b'r' => bytes.push(b'\r'),
+                        b'e' => bytes.push(0x1b),
+                        b'0'..=b'7' => {
+                            let mut value = u32::from(escaped - b'0');
+                            for _ in 0..2 {
+                                match self.peek() {
+                                    Some(d @ b'0'..=b'7') => {
+                                        value = value * 8 + u32::from(d - b'0');
+                                        self.pos += 1;
+                                    }
+                                    _ => break,
+                                }
+                            }
+                            bytes.push(value as u8);
+                        }
+                        other => bytes.push(other),
+                    }
+                }
+                other => bytes.push(other),
+            }
+        }
+        Some(String::from_utf8_lossy(&bytes).into_owned())
+    }
+}
+
+fn parse_number(value: &str) -> Option<u64> {
+    match value.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => value.parse().ok(),
+    }
+}
+
+/// A stack frame.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GdbFrame {
+    /// Depth, 0 for the innermost frame.
+    pub level: u32,
+    /// Program counter.
+    pub address: u64,
+    /// Function name, if known.
+    pub function: Option<String>,
+    /// Source file as recorded in the debug info.
+    pub file: Option<String>,
+    /// Absolute source path.
+    pub fullname: Option<PathBuf>,
+    /// Source line.
+    pub line: Option<u32>,
+}
+
+impl GdbFrame {
+    /// Read from a `frame={...}` tuple.
+    pub fn from_mi(value: &MiValue) -> Option<Self> {
+        Some(Self {
+            level: value
+                .get_str("level")
+                .and_then(|l| l.parse().ok())
+                .unwrap_or(0),
+            address: parse_number(value.get_str("addr")?)?,
+            function: value.get_str("func").map(str::to_string),
+            file: value.get_str("file").map(str::to_string),
+            fullname: value.get_str("fullname").map(PathBuf::from),
+            line: value.get_str("line").and_then(|l| l.parse().ok()),
+        })
+    }
+}
+
+/// A breakpoint.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GdbBreakpoint {
+    /// Breakpoint number.
+    pub number: u32,
+    /// Resolved address; absent while pending.
+    pub address: Option<u64>,
+    /// Function name, if known.
+    pub function: Option<String>,
+    /// Absolute source path.
+    pub fullname: Option<PathBuf>,
+    /// Source line.
+    pub line: Option<u32>,
+    /// Whether the breakpoint is enabled.
+    pub enabled: bool,
+    /// Times hit.
+    pub hit_count: u32,
+}
+
+impl GdbBreakpoint {
+    /// Read from a `bkpt={...}` tuple.
+    pub fn from_mi(value: &MiValue) -> Option<Self> {
+        Some(Self {
+            number: value.get_str("number")?.parse().ok()?,
+            address: value.get_str("addr").and_then(parse_number),
+            function: value.get_str("func").map(str::to_string),
+            fullname: value.get_str("fullname").map(PathBuf::from),
+            line: value.get_str("line").and_then(|l| l.parse().ok()),
+            enabled: value.get_str("enabled") != Some("n"),
+            hit_count: value
+                .get_str("times")
+                .and_then(|t| t.parse().ok())
+                .unwrap_or(0),
+        })
+    }
+}
+
+/// A local variable or argument.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GdbVariable {
+    /// Variable name.
+    pub name: String,
+    /// Type, if reported.
+    pub type_name: Option<String>,
+    /// Value; absent for aggregates, which must be expanded separately.
+    pub value: Option<String>,
+    /// Whether this is a function argument.
+    pub is_argument: bool,
+}
+
+/// A register value.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GdbRegister {
+    /// GDB register number.
+    pub number: u32,
+    /// Register name (e.g. "pc").
+    pub name: String,
+    /// Value as formatted by GDB.
+    pub value: String,
+}
+
+/// Something that happened in the debug session.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum GdbEvent {
+    /// The target stopped.
+    Stopped {
+        /// GDB's reason (e.g. "breakpoint-hit", "end-stepping-range").
+        reason: Option<String>,
+        /// Where it stopped.
+        frame: Option<GdbFrame>,
+        /// Breakpoint that was hit.
+        breakpoint: Option<u32>,
+        /// Signal name, for signal stops.
+        signal: Option<String>,
+        /// Exit code, when the program exited.
+        exit_code: Option<i32>,
+    },
+    /// The target resumed.
+    Running,
+    /// A breakpoint was created or changed (e.g. its hit count).
+    BreakpointChanged { breakpoint: GdbBreakpoint },
+    /// A breakpoint was deleted.
+    BreakpointDeleted { number: u32 },
+    /// CLI output.
+    Console { text: String },
+    /// Output from the target program.
+    Target { text: String },
+    /// GDB's internal log.
+    Log { text: String },
+    /// GDB exited.
+    Exited,
+}
+
+impl GdbEvent {
+    /// Convert an out-of-band record. Result records and prompts aren't
+    /// events.
+    pub fn from_record(record: &MiRecord) -> Option<Self> {
+        match record {
+            MiRecord::Stream { kind, text } => Some(match kind {
+                MiStreamKind::Console => GdbEvent::Console { text: text.clone() },
+                MiStreamKind::Target => GdbEvent::Target { text: text.clone() },
+                MiStreamKind::Log => GdbEvent::Log { text: text.clone() },
+            }),
+            MiRecord::Async { class, results, .. } => match class.as_str() {
+                "stopped" => Some(GdbEvent::Stopped {
+                    reason: lookup(results, "reason")
+                        .and_then(MiValue::as_str)
+                        .map(str::to_string),
+                    frame: lookup(results, "frame").and_then(GdbFrame::from_mi),
+                    breakpoint: lookup(results, "bkptno")
+                        .and_then(MiValue::as_str)
+                        .and_then(|n| n.parse().ok()),
+                    signal: lookup(results, "signal-name")
+                        .and_then(MiValue::as_str)
+                        .map(str::to_string),
+                    exit_code: lookup(results, "exit-code")
+                        .and_then(MiValue::as_str)
+                        .and_then(|c| i32::from_str_radix(c, 8).ok()),
+                }),
+                "running" => Some(GdbEvent::Running),
+                "breakpoint-created" | "breakpoint-modified" => lookup(results, "bkpt")
+                    .and_then(GdbBreakpoint::from_mi)
+                    .map(|breakpoint| GdbEvent::BreakpointChanged { breakpoint }),
+                "breakpoint-deleted" => lookup(results, "id")
+                    .and_then(MiValue::as_str)
+                    .and_then(|n| n.parse().ok())
+                    .map(|number| GdbEvent::BreakpointDeleted { number }),
+                _ => None,
+            },
+            MiRecord::Result(_) | MiRecord::Prompt => None,
+        }
+    }
+}
+
+/// Find a GDB that can debug the detected ARM toolchain's output.
+pub fn find_gdb(toolchains: &[DetectedToolchain]) -> Option<PathBuf> {
+    toolchains
+        .iter()
+        .filter(|t| t.kind == ToolchainKind::ArmGcc)
+        .map(|t| t.sibling_tool("gdb"))
+        .chain(GDB_PATHS.iter().map(PathBuf::from))
+        .find(|p| p.is_file() || p.with_extension("exe").is_file())
+}
+
+#[derive(Default)]
+struct Shared {
+    results: HashMap<u32, MiResultRecord>,
+    events: Vec<GdbEvent>,
+    exited: bool,
+}
+
+/// A running GDB session.
+pub struct GdbSession {
+    child: Child,
+    stdin: ChildStdin,
+    shared: Arc<(Mutex<Shared>, Condvar)>,
+    next_token: u32,
+}
+
+impl GdbSession {
+    /// Start GDB, optionally loading an ELF file's symbols.
+    pub fn start(gdb: &Path, elf: Option<&Path>) -> Result<Self, GdbError> {
+        let mut command = Command::new(gdb);
+        command.args(["--interpreter=mi2", "--quiet", "--nx"]);
+        if let Some(elf) = elf {
+            command.arg(elf);
+        }
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::NotFound => GdbError::NotFound,
+                _ => GdbError::Io(e),
+            })?;
+
+        let stdin = child.stdin.take().ok_or(GdbError::Exited)?;
+        let stdout = child.stdout.take().ok_or(GdbError::Exited)?;
+        let shared = Arc::new((Mutex::new(Shared::default()), Condvar::new()));
+        let reader = Arc::clone(&shared);
+        std::thread::spawn(move || {
+            let (lock, ready) = &*reader;
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                let Some(record) = parse_mi_record(&line) else {
+                    continue;
+                };
+                let Ok(mut shared) = lock.lock() else {
+                    return;
+                };
+                match record {
+                    MiRecord::Result(result) => {
+                        if let Some(token) = result.token {
+                            shared.results.insert(token, result);
+                            ready.notify_all();
+                        }
+                    }
+                    other => shared.events.extend(GdbEvent::from_record(&other)),
+                }
+            }
+            if let Ok(mut shared) = lock.lock() {
+                shared.exited = true;
+                shared.events.push(GdbEvent::Exited);
+            }
+            ready.notify_all();
+        });
+
+        let mut session = Self {
+            child,
+            stdin,
+            shared,
+            next_token: 1,
+        };
+        session.command("-gdb-set mi-async on")?;
+        session.command("-gdb-set pagination off")?;
+        session.command("-gdb-set confirm off")?;
+        Ok(session)
+    }
+
+    /// Send an MI command and wait for its result record.
+    ///
+    /// `^error` results become [`GdbError::Command`].
+    pub fn command(&mut self, command: &str) -> Result<MiResultRecord, GdbError> {
+        let token = self.next_token;
+        self.next_token += 1;
+        writeln!(self.stdin, "{}{}", token, command)?;
+        self.stdin.flush()?;
+
+        let (lock, ready) = &*self.shared;
+        let deadline = Instant::now() + COMMAND_TIMEOUT;
+        let mut shared = lock.lock().map_err(|_| GdbError::Exited)?;
+        let result = loop {
+            if let Some(result) = shared.results.remove(&token) {
+                break result;
+            }
+            if shared.exited {
+                return Err(GdbError::Exited);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(GdbError::Timeout);
+            }
+            shared = ready
+                .wait_timeout(shared, deadline - now)
+                .map_err(|_| GdbError::Exited)?
+                .0;
+        };
+
+        if result.class == MiResultClass::Error {
+            let message = result.get_str("msg").unwrap_or("unknown error");
+            return Err(GdbError::Command(message.to_string()));
+        }
+        Ok(result)
+    }
+
+    /// Drain queued events.
+    pub fn events(&self) -> Vec<GdbEvent> {
+        let (lock, _) = &*self.shared;
+        lock.lock()
+            .map(|mut shared| std::mem::take(&mut shared.events))
+            .unwrap_or_default()
+    }
+
+    /// Whether GDB is still running.
+    pub fn is_running(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+
+    /// Connect to a remote GDB server (e.g. `localhost:3333`).
+    pub fn connect(&mut self, target: &str) -> Result<(), GdbError> {
+        self.command(&format!("-target-select extended-remote {}", target))?;
+        Ok(())
+    }
+
+    /// Download the loaded ELF to the target.
+    pub fn load(&mut self) -> Result<(), GdbError> {
+        self.command("-target-download")?;
+        Ok(())
+    }
+
+    /// Insert a breakpoint at a location (`main`, `file.c:42`, `*0x08000100`).
+    pub fn insert_breakpoint(&mut self, location: &str) -> Result<GdbBreakpoint, GdbError> {
+        let result = self.command(&format!("-break-insert {}", mi_quote(location)))?;
+        result
+            .get("bkpt")
+            .and_then(GdbBreakpoint::from_mi)
+            .ok_or_else(|| GdbError::Protocol("-break-insert returned no breakpoint".to_string()))
+    }
+
+    /// Delete a breakpoint.
+    pub fn delete_breakpoint(&mut self, number: u32) -> Result<(), GdbError> {
+        self.command(&format!("-break-delete {}", number))?;
+        Ok(())
+    }
+
+    /// List breakpoints.
+    pub fn breakpoints(&mut self) -> Result<Vec<GdbBreakpoint>, GdbError> {
+        let result = self.command("-break-list")?;
+        Ok(result
+            .get("BreakpointTable")
+            .and_then(|t| t.get("body"))
+            .map(|body| {
+                body.as_list()
+                    .iter()
+                    .filter_map(GdbBreakpoint::from_mi)
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    /// Resume the target.
+    pub fn continue_execution(&mut self) -> Result<(), GdbError> {
+        self.command("-exec-continue")?;
+        Ok(())
+    }
+
+    /// Interrupt the running target.
+    pub fn interrupt(&mut self) -> Result<(), GdbError> {
+        self.command("-exec-interrupt")?;
+        Ok(())
+    }
+
+    /// Step one source line, into calls.
+    pub fn step_into(&mut self) -> Result<(), GdbError> {
+        self.command("-exec-step")?;
+        Ok(())
+    }
+
+    /// Step one source line, over calls.
+    pub fn step_over(&mut self) -> Result<(), GdbError> {
+        self.command("-exec-next")?;
+        Ok(())
+    }
+
+    /// Step one instruction.
+    pub fn step_instruction(&mut self) -> Result<(), GdbError> {
+        self.command("-exec-step-instruction")?;
+        Ok(())
+    }
+
+    /// Run until the current function returns.
+    pub fn finish(&mut self) -> Result<(), GdbError> {
+        self.command("-exec-finish")?;
+        Ok(())
+    }
+
+    /// Wait until the target stops, returning the stop event.
+    ///
+    /// Other events seen meanwhile stay queued.
+    pub fn wait_for_stop(&mut self, timeout: Duration) -> Result<GdbEvent, GdbError> {
+        let (lock, ready) = &*self.shared;
+        let deadline = Instant::now() + timeout;
+        let mut shared = lock.lock().map_err(|_| GdbError::Exited)?;
+        loop {
+            let stop = shared
+                .events
+                .iter()
+                .position(|e| matches!(e, GdbEvent::Stopped { .. }));
+            if let Some(index) = stop {
+                // Leave a copy queued so the UI also sees it
+                return Ok(shared.events[index].clone());
+            }
+            if shared.exited {
+                return Err(GdbError::Exited);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(GdbError::Timeout);
+            }
+            shared = ready
+                .wait_timeout(shared, (deadline - now).min(Duration::from_millis(50)))
+                .map_err(|_| GdbError::Exited)?
+                .0;
+        }
+    }
+
+    /// Stack frames of the current thread, innermost first.
+    pub fn stack_frames(&mut self) -> Result<Vec<GdbFrame>, GdbError> {
+        let result = self.command("-stack-list-frames")?;
+        Ok(result
+            .get("stack")
+            .map(|stack| {
+                stack
+                    .as_list()
+                    .iter()
+                    .filter_map(GdbFrame::from_mi)
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    /// Arguments and locals of the selected frame.
+    pub fn variables(&mut self) -> Result<Vec<GdbVariable>, GdbError> {
+        let result = self.command("-stack-list-variables --simple-values")?;
+        Ok(result
+            .get("variables")
+            .map(|vars| {
+                vars.as_list()
+                    .iter()
+                    .filter_map(|v| {
+                        Some(GdbVariable {
+                            name: v.get_str("name")?.to_string(),
+                            type_name: v.get_str("type").map(str::to_string),
+                            value: v.get_str("value").map(str::to_string),
+                            is_argument: v.get_str("arg") == Some("1"),
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    /// Evaluate an expression in the selected frame.
+    pub fn evaluate(&mut self, expression: &str) -> Result<String, GdbError> {
+        let result = self.command(&format!(
+            "-data-evaluate-expression {}",
+            mi_quote(expression)
+        ))?;
+        result
+            .get_str("value")
+            .map(str::to_string)
+            .ok_or_else(|| GdbError::Protocol("expression has no value".to_string()))
+    }
+
+    /// Read all registers, in hex.
+    pub fn registers(&mut self) -> Result<Vec<GdbRegister>, GdbError> {
+        let names = self.command("-data-list-register-names")?;
+        let names: Vec<&str> = names
+            .get("register-names")
+            .map(|n| {
+                n.as_list()
+                    .iter()
+                    .map(|v| v.as_str().unwrap_or(""))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let values = self.command("-data-list-register-values x")?;
+        Ok(values
+            .get("register-values")
+            .map(|list| {
+                list.as_list()
+                    .iter()
+                    .filter_map(|v| {
+                        let number: u32 = v.get_str("number")?.parse().ok()?;
+                        // Unnamed registers are gaps in GDB's numbering
+                        let name = names.get(number as usize).filter(|n| !n.is_empty())?;
+                        Some(GdbRegister {
+                            number,
+                            name: name.to_string(),
+                            value: v.get_str("value")?.to_string(),
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    /// Read target memory.
+    pub fn read_memory(&mut self, address: u64, length: usize) -> Result<Vec<u8>, GdbError> {
+        let result = self.command(&format!(
+            "-data-read-memory-bytes 0x{:x} {}",
+            address, length
+        ))?;
+        let mut bytes = vec![0u8; length];
+        for block in result
+            .get("memory")
+            .map(MiValue::as_list)
+            .unwrap_or_default()
+        {
+            let (Some(begin), Some(contents)) = (
+                block.get_str("begin").and_then(parse_number),
+                block.get_str("contents"),
+            ) else {
+                continue;
+            };
+            let offset = begin.saturating_sub(address) as usize;
+            for (i, pair) in contents.as_bytes().chunks(2).enumerate() {
+                let byte = std::str::from_utf8(pair)
+                    .ok()
+                    .and_then(|s| u8::from_str_radix(s, 16).ok())
+                    .ok_or_else(|| {
+                        GdbError::Protocol(format!("bad memory contents: {}", contents))
+                    })?;
+                if let Some(slot) = bytes.get_mut(offset + i) {
+                    *slot = byte;
+                }
+            }
+        }
+        Ok(bytes)
+    }
+
+    /// Exit GDB.
+    pub fn stop(mut self) -> Result<(), GdbError> {
+        // GDB exits before it can answer, so don't wait for a result
+        let _ = writeln!(self.stdin, "-gdb-exit");
+        let _ = self.stdin.flush();
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while Instant::now() < deadline {
+            if self.child.try_wait()?.is_some() {
+                return Ok(());
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        self.child.kill()?;
+        self.child.wait()?;
+        Ok(())
+    }
+}
+
+impl Drop for GdbSession {
+    fn drop(&mut self) {
+        if matches!(self.child.try_wait(), Ok(None)) {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
+
+/// Quote an argument for an MI command.
+fn mi_quote(arg: &str) -> String {
+    format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_result_record() {
+        let record = parse_mi_record(
+            "12^done,bkpt={number=\"1\",type=\"breakpoint\",disp=\"keep\",enabled=\"y\",\
+             addr=\"0x080001a4\",func=\"main\",file=\"src/main.c\",\
+             fullname=\"/work/src/main.c\",line=\"42\",times=\"0\"}",
+        )
+        .unwrap();
+        let MiRecord::Result(result) = record else {
+            panic!("expected result record");
+        };
+        assert_eq!(result.token, Some(12));
+        assert_eq!(result.class, MiResultClass::Done);
+
+        let bkpt = GdbBreakpoint::from_mi(result.get("bkpt").unwrap()).unwrap();
+        assert_eq!(bkpt.number, 1);
+        assert_eq!(bkpt.address, Some(0x080001a4));
+        assert_eq!(bkpt.function.as_deref(), Some("main"));
+        assert_eq!(bkpt.fullname, Some(PathBuf::from("/work/src/main.c")));
+        assert_eq!(bkpt.line, Some(42));
+        assert!(bkpt.enabled);
+
+        let record = parse_mi_record("3^error,msg=\"No symbol \\\"foo\\\" in current context.\"");
+        let Some(MiRecord::Result(result)) = record else {
+            panic!("expected result record");
+        };
+        assert_eq!(result.class, MiResultClass::Error);
+        assert_eq!(
+            result.get_str("msg"),
+            Some("No symbol \"foo\" in current context.")
+        );
+    }
+
+    #[test]
+    fn test_parse_stream_and_prompt() {
+        assert_eq!(
+            parse_mi_record("~\"Reading symbols from app.elf...\\n\""),
+            Some(MiRecord::Stream {
+                kind: MiStreamKind::Console,
+                text: "Reading symbols from app.elf...\n".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_mi_record("@\"caf\\303\\251\""),
+            Some(MiRecord::Stream {
+                kind: MiStreamKind::Target,
+                text: "café".to_string(),
+            })
+        );
+        assert_eq!(parse_mi_record("(gdb) "), Some(MiRecord::Prompt));
+        assert_eq!(parse_mi_record("not mi output"), None);
+    }
+
+    #[test]
+    fn test_parse_lists() {
+        let record = parse_mi_record(
+            "5^done,stack=[frame={level=\"0\",addr=\"0x08000210\",func=\"delay\",line=\"10\"},\
+             frame={level=\"1\",addr=\"0x080001b0\",func=\"main\",line=\"44\"}]",
+        );
+        let Some(MiRecord::Result(result)) = record else {
+            panic!("expected result record");
+        };
+        let frames: Vec<_> = result
+            .get("stack")
+            .unwrap()
+            .as_list()
+            .iter()
+            .filter_map(GdbFrame::from_mi)
+            .collect();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[1].level, 1);
+        assert_eq!(frames[1].address, 0x080001b0);
+        assert_eq!(frames[1].function.as_deref(), Some("main"));
+
+        let record = parse_mi_record("6^done,register-names=[\"r0\",\"r1\",\"\",\"pc\"]");
+        let Some(MiRecord::Result(result)) = record else {
+            panic!("expected result record");
+        };
+        let names: Vec<_> = result
+            .get("register-names")
+            .unwrap()
+            .as_list()
+            .iter()
+            .filter_map(MiValue::as_str)
+            .collect();
+        assert_eq!(names, vec!["r0", "r1", "", "pc"]);
+
+        let record = parse_mi_record("7^done,stack=[]").unwrap();
+        let MiRecord::Result(result) = record else {
+            panic!("expected result record");
+        };
+        assert!(result.get("stack").unwrap().as_list().is_empty());
+    }
+
+    #[test]
+    fn test_stopped_event() {
+        let record = parse_mi_record(
+            "*stopped,reason=\"breakpoint-hit\",disp=\"keep\",bkptno=\"1\",\
+             frame={addr=\"0x080001a4\",func=\"main\",args=[],file=\"src/main.c\",\
+             fullname=\"/work/src/main.c\",line=\"42\"},thread-id=\"1\",stopped-threads=\"all\"",
+        )
+        .unwrap();
+        let event = GdbEvent::from_record(&record).unwrap();
+        let GdbEvent::Stopped {
+            reason,
+            frame,
+            breakpoint,
+            ..
+        } = event
+        else {
+            panic!("expected stop event");
+        };
+        assert_eq!(reason.as_deref(), Some("breakpoint-hit"));
+        assert_eq!(breakpoint, Some(1));
+        let frame = frame.unwrap();
+        assert_eq!(frame.address, 0x080001a4);
+        assert_eq!(frame.line, Some(42));
+
+        let record = parse_mi_record("*stopped,reason=\"exited\",exit-code=\"01\"").unwrap();
+        assert!(matches!(
+            GdbEvent::from_record(&record),
+            Some(GdbEvent::Stopped {
+                exit_code: Some(1),
+                ..
+            })
+        ));
+
+        let record = parse_mi_record("*running,thread-id=\"all\"").unwrap();
+        assert_eq!(GdbEvent::from_record(&record), Some(GdbEvent::Running));
+
+        let record = parse_mi_record("=breakpoint-deleted,id=\"3\"").unwrap();
+        assert_eq!(
+            GdbEvent::from_record(&record),
+            Some(GdbEvent::BreakpointDeleted { number: 3 })
+        );
+    }
+
+    #[test]
+    fn test_mi_quote() {
+        assert_eq!(mi_quote("main.c:42"), "\"main.c:42\"");
+        assert_eq!(mi_quote("s == \"x\""), "\"s == \\\"x\\\"\"");
+    }
+}
@@ -0,0 +1,535 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Host test execution.
+//!
+//! Builds the host test executable (optionally instrumented with
+//! `--coverage`), runs it, parses Unity, CMocka or CppUTest output into
+//! per-case results, writes them as JUnit XML next to the executable, and
+//! collects gcov data for the sources under test, so one run feeds both
+//! the coverage report and requirement verification.
+
+use crate::limits::{is_timeout, run_limited};
+use crate::{
+    build_host_tests, parse_gcov_output, CoverageReport, DetectedToolchain, HostTestBuild,
+    HostTestConfig, HostTestError, ToolchainKind,
+};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Instant;
+
+/// Outcome of a host test case.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HostTestOutcome {
+    Passed,
+    Failed,
+    Skipped,
+}
+
+/// One test case reported by the test executable.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HostTestCase {
+    /// Test name (`Group.Name` for CppUTest).
+    pub name: String,
+    /// Test source file, if reported.
+    pub file: Option<PathBuf>,
+    /// Line of the test or failing assertion, if reported.
+    pub line: Option<u32>,
+    /// Outcome.
+    pub outcome: HostTestOutcome,
+    /// Failure or ignore message.
+    pub message: Option<String>,
+}
+
+impl HostTestCase {
+    fn new(name: impl Into<String>, outcome: HostTestOutcome) -> Self {
+        Self {
+            name: name.into(),
+            file: None,
+            line: None,
+            outcome,
+            message: None,
+        }
+    }
+}
+
+/// Result of building and running host tests.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HostTestRun {
+    /// Build result.
+    pub build: HostTestBuild,
+    /// Exit code of the test executable; absent if it did not run.
+    pub exit_code: Option<i32>,
+    /// Whether the run was killed for exceeding the time limit.
+    pub timed_out: bool,
+    /// Captured standard output.
+    pub stdout: String,
+    /// Captured standard error.
+    pub stderr: String,
+    /// Run time in milliseconds.
+    pub duration_ms: u64,
+    /// Parsed test cases, in output order.
+    pub cases: Vec<HostTestCase>,
+    /// JUnit XML file written for the run.
+    pub junit_path: Option<PathBuf>,
+    /// Coverage of the sources under test, when instrumented.
+    pub coverage: Option<CoverageReport>,
+}
+
+impl HostTestRun {
+    /// Whether the build succeeded, the executable exited cleanly and no
+    /// case failed.
+    pub fn success(&self) -> bool {
+        self.build.success()
+            && self.exit_code == Some(0)
+            && self
+                .cases
+                .iter()
+                .all(|c| c.outcome != HostTestOutcome::Failed)
+    }
+}
+
+/// Parse test output from Unity, CMocka or CppUTest (run with `-v`).
+///
+/// Lines that match none of the formats are ignored, so output from the
+/// code under test may be interleaved.
+pub fn parse_host_test_output(output: &str) -> Vec<HostTestCase> {
+    let mut cases: Vec<HostTestCase> = Vec::new();
+    let mut cmocka_current: Option<String> = None;
+    let mut cmocka_message = String::new();
+    let mut lines = output.lines().peekable();
+
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim();
+
+        // CMocka: "[ RUN      ] name" ... "[       OK ] name"
+        if let Some((tag, name)) = cmocka_line(trimmed) {
+            if tag == "RUN" {
+                cmocka_current = Some(name.to_string());
+                cmocka_message.clear();
+                continue;
+            }
+            if cmocka_current.as_deref() != Some(name) {
+                // Assertion details inside a test; summary lines after it
+                // repeat failed names
+                if cmocka_current.is_some() {
+                    cmocka_message.push_str(trimmed);
+                    cmocka_message.push('\n');
+                }
+                continue;
+            }
+            let outcome = match tag {
+                "OK" => HostTestOutcome::Passed,
+                "SKIPPED" => HostTestOutcome::Skipped,
+                _ => HostTestOutcome::Failed,
+            };
+            let mut case = HostTestCase::new(name, outcome);
+            if outcome == HostTestOutcome::Failed && !cmocka_message.is_empty() {
+                case.message = Some(cmocka_message.trim().to_string());
+            }
+            cases.push(case);
+            cmocka_current = None;
+            continue;
+        }
+        if cmocka_current.is_some() {
+            cmocka_message.push_str(trimmed);
+            cmocka_message.push('\n');
+            continue;
+        }
+
+        // CppUTest: "file:line: error: Failure in TEST(Group, Name)"
+        if let Some((location, test)) = trimmed.split_once(": error: Failure in ") {
+            if let Some(name) = cpputest_name(test) {
+                let mut case = HostTestCase::new(name, HostTestOutcome::Failed);
+                if let Some((file, line)) = location.rsplit_once(':') {
+                    case.file = Some(PathBuf::from(file));
+                    case.line = line.parse().ok();
+                }
+                let mut message = Vec::new();
+                while let Some(next) = lines.next_if(|l| l.starts_with('\t')) {
+                    message.push(next.trim());
+                }
+                case.message = (!message.is_empty()).then(|| message.join("\n"));
+                cases.push(case);
+                continue;
+            }
+        }
+        // CppUTest: "TEST(Group, Name) - 0 ms", "IGNORE_TEST(Group, Name) - 0 ms"
+        if let Some(name) = cpputest_name(trimmed) {
+            let outcome = if trimmed.starts_with("IGNORE_TEST(") {
+                HostTestOutcome::Skipped
+            } else {
+                HostTestOutcome::Passed
+            };
+            if trimmed.ends_with(" ms") && !cases.iter().any(|c| c.name == name) {
+                cases.push(HostTestCase::new(name, outcome));
+            }
+            continue;
+        }
+
+        // Unity: "file:line:name:PASS", "file:line:name:FAIL: message"
+        if let Some(case) = unity_line(trimmed) {
+            cases.push(case);
+        }
+    }
+    cases
+}
+
+fn cmocka_line(line: &str) -> Option<(&str, &str)> {
+    let rest = line.strip_prefix('[')?;
+    let (tag, name) = rest.split_once(']')?;
+    let tag = tag.trim();
+    let name = name.trim();
+    let known = matches!(tag, "RUN" | "OK" | "FAILED" | "ERROR" | "SKIPPED");
+    (known && !name.is_empty()).then_some((tag, name))
+}
+
+fn cpputest_name(text: &str) -> Option<String> {
+    let rest = text
+        .strip_prefix("TEST(")
+        .or_else(|| text.strip_prefix("IGNORE_TEST("))?;
+    let (args, _) = rest.split_once(')')?;
+    let (group, name) = args.split_once(',')?;
+    Some(format!("{}.{}", group.trim(), name.trim()))
+}
+
+fn unity_line(line: &str) -> Option<HostTestCase> {
+    let mut fields = line.splitn(5, ':');
+    let file = fields.next()?;
+    let line_no: u32 = fields.next()?.parse().ok()?;
+    let name = fields.next()?;
+    let outcome = match fields.next()? {
+        "PASS" => HostTestOutcome::Passed,
+        "FAIL" => HostTestOutcome::Failed,
+        "IGNORE" => HostTestOutcome::Skipped,
+        _ => return None,
+    };
+    let mut case = HostTestCase::new(name, outcome);
+    case.file = Some(PathBuf::from(file));
+    case.line = Some(line_no);
+    case.message = fields
+        .next()
+        .map(|m| m.trim().to_string())
+        .filter(|m| !m.is_empty());
+    Some(case)
+}
+
+/// Render cases as a JUnit XML document with a single suite.
+pub fn host_tests_to_junit(suite: &str, cases: &[HostTestCase], duration_ms: u64) -> String {
+    let count = |outcome| cases.iter().filter(|c| c.outcome == outcome).count();
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites>\n");
+    let _ = writeln!(
+        xml,
+        "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{:.3}\">",
+        escape(suite),
+        cases.len(),
+        count(HostTestOutcome::Failed),
+        count(HostTestOutcome::Skipped),
+        duration_ms as f64 / 1000.0
+    );
+    for case in cases {
+        let _ = write!(
+            xml,
+            "    <testcase classname=\"{}\" name=\"{}\"",
+            escape(suite),
+            escape(&case.name)
+        );
+        if let Some(ref file) = case.file {
+            let _ = write!(xml, " file=\"{}\"", escape(&file.display().to_string()));
+        }
+        if let Some(line) = case.line {
+            let _ = write!(xml, " line=\"{}\"", line);
+        }
+        let message = escape(case.message.as_deref().unwrap_or_default());
+        match case.outcome {
+            HostTestOutcome::Passed => xml.push_str("/>\n"),
+            HostTestOutcome::Failed => {
+                let _ = writeln!(xml, "><failure message=\"{}\"/></testcase>", message);
+            }
+            HostTestOutcome::Skipped => {
+                let _ = writeln!(xml, "><skipped message=\"{}\"/></testcase>", message);
+            }
+        }
+    }
+    xml.push_str("  </testsuite>\n</testsuites>\n");
+    xml
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\n', "&#10;")
+}
+
+/// gcov program and arguments for one source: the `gcov` matching GCC,
+/// or `llvm-cov gcov` beside Clang.
+pub fn gcov_command(
+    toolchain: &DetectedToolchain,
+    object: &Path,
+    source: &Path,
+) -> (PathBuf, Vec<String>) {
+    let mut args = Vec::new();
+    let program = match toolchain.kind {
+        ToolchainKind::Clang => {
+            args.push("gcov".to_string());
+            toolchain.sibling_tool("cov")
+        }
+        _ => {
+            // gcov must match the compiler version: gcc-12 pairs with gcov-12
+            let name = toolchain
+                .path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            match name.rfind("gcc") {
+                Some(idx) => toolchain.path.with_file_name(format!(
+                    "{}gcov{}",
+                    &name[..idx],
+                    &name[idx + 3..]
+                )),
+                None => toolchain.sibling_tool("gcov"),
+            }
+        }
+    };
+    args.extend(
+        ["-b", "-c", "-p", "-o"]
+            .iter()
+            .map(|a| a.to_string())
+            .chain([object.display().to_string(), source.display().to_string()]),
+    );
+    (program, args)
+}
+
+/// Build the host tests, run them, and collect results and coverage.
+///
+/// With `coverage`, sources are compiled with `--coverage`, stale `.gcda`
+/// counters are removed before the run, and gcov output is gathered
+/// afterwards. Results are written to `<output_dir>/<name>.junit.xml`.
+pub fn run_host_tests(
+    toolchain: &DetectedToolchain,
+    config: &HostTestConfig,
+    project_root: &Path,
+    sources: &[PathBuf],
+    name: &str,
+    coverage: bool,
+) -> Result<HostTestRun, HostTestError> {
+    let mut config = config.clone();
+    if coverage {
+        config.flags.push("--coverage".to_string());
+        config.ldflags.push("--coverage".to_string());
+    }
+    let build = build_host_tests(toolchain, &config, project_root, sources, name)?;
+    let mut run = HostTestRun {
+        build,
+        exit_code: None,
+        timed_out: false,
+        stdout: String::new(),
+        stderr: String::new(),
+        duration_ms: 0,
+        cases: Vec::new(),
+        junit_path: None,
+        coverage: None,
+    };
+    if !run.build.success() {
+        return Ok(run);
+    }
+
+    if coverage {
+        for source in sources {
+            let gcda = config
+                .object_path(project_root, source)
+                .with_extension("gcda");
+            if gcda.is_file() {
+                std::fs::remove_file(gcda)?;
+            }
+        }
+    }
+
+    let start = Instant::now();
+    let output = run_limited(
+        Command::new(&run.build.executable)
+            .args(&config.run_args)
+            .current_dir(project_root),
+    );
+    run.duration_ms = start.elapsed().as_millis() as u64;
+    match output {
+        Ok(output) => {
+            run.exit_code = output.status.code();
+            run.stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+            run.stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+        }
+        Err(e) => {
+            run.timed_out = is_timeout(&e);
+            run.stderr = e.to_string();
+        }
+    }
+    run.cases = parse_host_test_output(&run.stdout);
+
+    let junit = config
+        .output_path(project_root)
+        .join(format!("{}.junit.xml", name));
+    std::fs::write(
+        &junit,
+        host_tests_to_junit(name, &run.cases, run.duration_ms),
+    )?;
+    run.junit_path = Some(junit);
+
+    if coverage {
+        run.coverage = Some(collect_gcov(toolchain, &config, project_root, sources)?);
+    }
+    Ok(run)
+}
+
+/// Run gcov for each source in a scratch directory and parse the results.
+fn collect_gcov(
+    toolchain: &DetectedToolchain,
+    config: &HostTestConfig,
+    project_root: &Path,
+    sources: &[PathBuf],
+) -> Result<CoverageReport, HostTestError> {
+    let dir = config.output_path(project_root).join("gcov");
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir)?;
+    }
+    std::fs::create_dir_all(&dir)?;
+
+    for source in sources {
+        let object = config.object_path(project_root, source);
+        let (program, args) = gcov_command(toolchain, &object, &project_root.join(source));
+        run_limited(Command::new(program).args(args).current_dir(&dir))?;
+    }
+
+    let mut files = Vec::new();
+    let mut outputs: Vec<PathBuf> = std::fs::read_dir(&dir)?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|e| e == "gcov"))
+        .collect();
+    outputs.sort();
+    for path in outputs {
+        files.extend(parse_gcov_output(&std::fs::read_to_string(path)?));
+    }
+    Ok(CoverageReport::from_files(files))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_unity() {
+        let output = "\
+test/test_nav.c:12:test_nav_update:PASS
+test/test_nav.c:30:test_nav_clamp:FAIL: Expected 10 Was 11
+test/test_nav.c:41:test_nav_fault:IGNORE
+debug: state=3
+
+-----------------------
+3 Tests 1 Failures 1 Ignored
+FAIL
+";
+        let cases = parse_host_test_output(output);
+        assert_eq!(cases.len(), 3);
+        assert_eq!(cases[0].name, "test_nav_update");
+        assert_eq!(cases[0].line, Some(12));
+        assert_eq!(cases[1].outcome, HostTestOutcome::Failed);
+        assert_eq!(cases[1].message.as_deref(), Some("Expected 10 Was 11"));
+        assert_eq!(cases[2].outcome, HostTestOutcome::Skipped);
+    }
+
+    #[test]
+    fn test_parse_cmocka() {
+        let output = "\
+[==========] Running 2 test(s).
+[ RUN      ] test_limit
+[       OK ] test_limit
+[ RUN      ] test_scale
+[  ERROR   ] --- 0xa != 0xb
+[   LINE   ] --- test_scale.c:20: error: Failure!
+[  FAILED  ] test_scale
+[==========] 2 test(s) run.
+[  PASSED  ] 1 test(s).
+[  FAILED  ] 1 test(s), listed below:
+[  FAILED  ] test_scale
+";
+        let cases = parse_host_test_output(output);
+        assert_eq!(cases.len(), 2);
+        assert_eq!(cases[0].outcome, HostTestOutcome::Passed);
+        assert_eq!(cases[1].name, "test_scale");
+        assert_eq!(cases[1].outcome, HostTestOutcome::Failed);
+        assert!(cases[1].message.as_deref().unwrap().contains("0xa != 0xb"));
+    }
+
+    #[test]
+    fn test_parse_cpputest() {
+        let output = "\
+TEST(Nav, Update) - 0 ms
+TEST(Nav, Clamp)
+tests/NavTest.cpp:25: error: Failure in TEST(Nav, Clamp)
+\texpected <10>
+\tbut was  <11>
+
+ - 1 ms
+IGNORE_TEST(Nav, Fault) - 0 ms
+
+Errors (1 failures, 3 tests, 2 ran, 3 checks, 1 ignored, 0 filtered out, 1 ms)
+";
+        let cases = parse_host_test_output(output);
+        let summary: Vec<(&str, HostTestOutcome)> =
+            cases.iter().map(|c| (c.name.as_str(), c.outcome)).collect();
+        assert_eq!(
+            summary,
+            [
+                ("Nav.Update", HostTestOutcome::Passed),
+                ("Nav.Clamp", HostTestOutcome::Failed),
+                ("Nav.Fault", HostTestOutcome::Skipped),
+            ]
+        );
+        assert_eq!(cases[1].file, Some(PathBuf::from("tests/NavTest.cpp")));
+        assert_eq!(cases[1].line, Some(25));
+        assert_eq!(
+            cases[1].message.as_deref(),
+            Some("expected <10>\nbut was  <11>")
+        );
+    }
+
+    #[test]
+    fn test_junit_output() {
+        let cases = parse_host_test_output(
+            "t.c:3:test_a:PASS\nt.c:9:test_b:FAIL: Expected <1> Was \"2\"\n",
+        );
+        let xml = host_tests_to_junit("nav", &cases, 1500);
+        assert!(xml.contains(
+            "<testsuite name=\"nav\" tests=\"2\" failures=\"1\" skipped=\"0\" time=\"1.500\">"
+        ));
+        assert!(
+            xml.contains("<testcase classname=\"nav\" name=\"test_a\" file=\"t.c\" line=\"3\"/>")
+        );
+        assert!(xml.contains("<failure message=\"Expected &lt;1&gt; Was &quot;2&quot;\"/>"));
+    }
+
+    #[test]
+    fn test_gcov_command() {
+        let gcc = DetectedToolchain::new(
+            ToolchainKind::Gcc,
+            PathBuf::from("/usr/bin/gcc-12"),
+            "12".to_string(),
+        );
+        let (program, args) = gcov_command(&gcc, Path::new("obj/a.o"), Path::new("src/a.c"));
+        assert_eq!(program, PathBuf::from("/usr/bin/gcov-12"));
+        assert_eq!(args, ["-b", "-c", "-p", "-o", "obj/a.o", "src/a.c"]);
+
+        let clang = DetectedToolchain::new(
+            ToolchainKind::Clang,
+            PathBuf::from("/usr/bin/clang"),
+            "17".to_string(),
+        );
+        let (program, args) = gcov_command(&clang, Path::new("obj/a.o"), Path::new("src/a.c"));
+        assert_eq!(program, PathBuf::from("/usr/bin/llvm-cov"));
+        assert_eq!(args[0], "gcov");
+    }
+}
@@ -0,0 +1,486 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Host builds of device code for unit testing.
+//!
+//! Device sources are compiled with the host Clang or GCC into a separate
+//! output directory, with hardware headers shadowed by mocks. Configuration
+//! lives in `.axiom/host-test.toml`:
+//!
+//! ```toml
+//! compiler = "Clang"
+//! output_dir = "build/host-test"
+//! include_paths = ["src", "tests"]
+//! mock_dirs = ["tests/mocks"]
+//!
+//! [[stub]]
+//! header = "stm32f4xx.h"
+//!
+//! [[stub]]
+//! header = "core_cm4.h"
+//! replacement = "tests/mocks/fake_core.h"
+//! ```
+
+use crate::limits::is_timeout;
+use crate::link::parse_link_diagnostics;
+use crate::response_file::run_tool;
+use crate::{compile, CompileRequest, CompileResult, DetectedToolchain, LinkResult, ToolchainKind};
+use axiom_core::Diagnostic;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use thiserror::Error;
+
+/// Host test build errors.
+#[derive(Debug, Error)]
+pub enum HostTestError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("TOML parse error in {path}: {source}")]
+    Toml {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+
+    #[error("{0} cannot build host tests; use Clang or GCC")]
+    UnsupportedToolchain(ToolchainKind),
+}
+
+/// A hardware header replaced in host builds.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeaderStub {
+    /// Header as written in `#include` (e.g. "stm32f4xx.h").
+    pub header: String,
+    /// File to use instead, relative to the project root; an empty header
+    /// is generated when absent.
+    #[serde(default)]
+    pub replacement: Option<PathBuf>,
+}
+
+/// Host test build configuration.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HostTestConfig {
+    /// Host compiler.
+    #[serde(default = "default_compiler")]
+    pub compiler: ToolchainKind,
+    /// Output directory, relative to the project root.
+    #[serde(default = "default_output_dir")]
+    pub output_dir: PathBuf,
+    /// Optimization level (0-3).
+    #[serde(default)]
+    pub optimization: u8,
+    /// Include debug symbols.
+    #[serde(default = "default_true")]
+    pub debug: bool,
+    /// Preprocessor defines (`NAME` or `NAME=VALUE`).
+    #[serde(default = "default_defines")]
+    pub defines: Vec<String>,
+    /// Include directories, searched after mocks.
+    #[serde(default)]
+    pub include_paths: Vec<PathBuf>,
+    /// Directories of mock headers, searched before the include paths.
+    #[serde(default)]
+    pub mock_dirs: Vec<PathBuf>,
+    /// Headers to stub out.
+    #[serde(default, rename = "stub")]
+    pub stubs: Vec<HeaderStub>,
+    /// Additional compiler flags.
+    #[serde(default)]
+    pub flags: Vec<String>,
+    /// Additional linker flags.
+    #[serde(default)]
+    pub ldflags: Vec<String>,
+    /// Arguments passed to the test executable (e.g. `-v` for CppUTest).
+    #[serde(default)]
+    pub run_args: Vec<String>,
+}
+
+fn default_compiler() -> ToolchainKind {
+    ToolchainKind::Clang
+}
+
+fn default_output_dir() -> PathBuf {
+    PathBuf::from("build").join("host-test")
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_defines() -> Vec<String> {
+    vec!["HOST_TEST".to_string()]
+}
+
+impl Default for HostTestConfig {
+    fn default() -> Self {
+        Self {
+            compiler: default_compiler(),
+            output_dir: default_output_dir(),
+            optimization: 0,
+            debug: true,
+            defines: default_defines(),
+            include_paths: Vec::new(),
+            mock_dirs: Vec::new(),
+            stubs: Vec::new(),
+            flags: Vec::new(),
+            ldflags: Vec::new(),
+            run_args: Vec::new(),
+        }
+    }
+}
+
+impl HostTestConfig {
+    /// Use a different host compiler.
+    pub fn with_compiler(mut self, compiler: ToolchainKind) -> Self {
+        self.compiler = compiler;
+        self
+    }
+
+    /// Add an include directory.
+    pub fn with_include_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.include_paths.push(path.into());
+        self
+    }
+
+    /// Add a mock header directory.
+    pub fn with_mock_dir(mut self, path: impl Into<PathBuf>) -> Self {
+        self.mock_dirs.push(path.into());
+        self
+    }
+
+    /// Stub out a header, optionally with a replacement file.
+    pub fn with_stub(mut self, header: impl Into<String>, replacement: Option<PathBuf>) -> Self {
+        self.stubs.push(HeaderStub {
+            header: header.into(),
+            replacement,
+        });
+        self
+    }
+
+    /// Add a preprocessor define.
+    pub fn with_define(mut self, define: impl Into<String>) -> Self {
+        self.defines.push(define.into());
+        self
+    }
+
+    /// Add a compiler flag.
+    pub fn with_flag(mut self, flag: impl Into<String>) -> Self {
+        self.flags.push(flag.into());
+        self
+    }
+
+    /// Output directory under a project root.
+    pub fn output_path(&self, project_root: &Path) -> PathBuf {
+        project_root.join(&self.output_dir)
+    }
+
+    /// Directory holding generated and copied stub headers.
+    pub fn stub_dir(&self, project_root: &Path) -> PathBuf {
+        self.output_path(project_root).join("stubs")
+    }
+
+    /// Object path for a source, mirroring its place in the project.
+    pub fn object_path(&self, project_root: &Path, source: &Path) -> PathBuf {
+        let relative = source
+            .strip_prefix(project_root)
+            .ok()
+            .map(Path::to_path_buf)
+            .or_else(|| source.file_name().map(PathBuf::from))
+            .unwrap_or_else(|| source.to_path_buf());
+        self.output_path(project_root)
+            .join("obj")
+            .join(relative)
+            .with_extension("o")
+    }
+
+    /// Compile request for one source.
+    ///
+    /// Stubs are searched first, then mock directories, then include paths.
+    pub fn compile_request(&self, project_root: &Path, source: &Path) -> CompileRequest {
+        let mut request =
+            CompileRequest::new(source.to_path_buf(), self.object_path(project_root, source))
+                .with_optimization(self.optimization)
+                .with_debug(self.debug)
+                .with_flag(format!("-I{}", self.stub_dir(project_root).display()));
+
+        for dir in self.mock_dirs.iter().chain(&self.include_paths) {
+            request = request.with_flag(format!("-I{}", project_root.join(dir).display()));
+        }
+        for define in &self.defines {
+            request = request.with_flag(format!("-D{}", define));
+        }
+        for flag in &self.flags {
+            request = request.with_flag(flag.clone());
+        }
+        request
+    }
+}
+
+/// Result of a host test build.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HostTestBuild {
+    /// Compile result per source, in input order.
+    pub compiles: Vec<(PathBuf, CompileResult)>,
+    /// Link result; absent if any source failed to compile.
+    pub link: Option<LinkResult>,
+    /// Test executable path.
+    pub executable: PathBuf,
+}
+
+impl HostTestBuild {
+    /// Whether every source compiled and the executable linked.
+    pub fn success(&self) -> bool {
+        self.compiles.iter().all(|(_, r)| r.success())
+            && self.link.as_ref().is_some_and(LinkResult::success)
+    }
+}
+
+/// Path of a project's host test configuration.
+pub fn host_test_config_path(project_root: &Path) -> PathBuf {
+    project_root.join(".axiom").join("host-test.toml")
+}
+
+/// Load a project's host test configuration; a missing file gives defaults.
+pub fn load_host_test_config(project_root: &Path) -> Result<HostTestConfig, HostTestError> {
+    let path = host_test_config_path(project_root);
+    if !path.is_file() {
+        return Ok(HostTestConfig::default());
+    }
+    let content = std::fs::read_to_string(&path)?;
+    toml::from_str(&content).map_err(|source| HostTestError::Toml { path, source })
+}
+
+/// Write the configured stub headers and return their directory.
+pub fn write_stub_headers(
+    config: &HostTestConfig,
+    project_root: &Path,
+) -> Result<PathBuf, HostTestError> {
+    let dir = config.stub_dir(project_root);
+    std::fs::create_dir_all(&dir)?;
+    for stub in &config.stubs {
+        let path = dir.join(&stub.header);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        match stub.replacement {
+            Some(ref replacement) => {
+                std::fs::copy(project_root.join(replacement), &path)?;
+            }
+            None => std::fs::write(&path, stub_header(&stub.header))?,
+        }
+    }
+    Ok(dir)
+}
+
+/// Contents of a generated empty stub header.
+fn stub_header(header: &str) -> String {
+    let guard: String = header
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!(
+        "/* Host test stub for <{header}>. */\n\
+         #ifndef AXIOM_STUB_{guard}\n\
+         #define AXIOM_STUB_{guard}\n\
+         #endif\n"
+    )
+}
+
+/// Build host linker arguments.
+pub fn build_host_link_command(
+    config: &HostTestConfig,
+    objects: &[PathBuf],
+    output: &Path,
+) -> Vec<String> {
+    let mut args: Vec<String> = objects.iter().map(|o| o.display().to_string()).collect();
+    args.extend(config.ldflags.iter().cloned());
+    args.push("-o".to_string());
+    args.push(output.display().to_string());
+    args
+}
+
+/// Compile sources for the host and link them into a test executable.
+///
+/// The executable is written to the output directory as `name`.
+pub fn build_host_tests(
+    toolchain: &DetectedToolchain,
+    config: &HostTestConfig,
+    project_root: &Path,
+    sources: &[PathBuf],
+    name: &str,
+) -> Result<HostTestBuild, HostTestError> {
+    if !matches!(toolchain.kind, ToolchainKind::Clang | ToolchainKind::Gcc) {
+        return Err(HostTestError::UnsupportedToolchain(toolchain.kind));
+    }
+    write_stub_headers(config, project_root)?;
+
+    let mut compiles = Vec::new();
+    let mut objects = Vec::new();
+    for source in sources {
+        let request = config.compile_request(project_root, source);
+        if let Some(parent) = request.output.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        objects.push(request.output.clone());
+        compiles.push((source.clone(), compile(toolchain, &request)));
+    }
+
+    let executable = config.output_path(project_root).join(name);
+    let link = compiles
+        .iter()
+        .all(|(_, r)| r.success())
+        .then(|| link_host(toolchain, config, &objects, &executable));
+
+    Ok(HostTestBuild {
+        compiles,
+        link,
+        executable,
+    })
+}
+
+/// Link objects with the host compiler driver.
+fn link_host(
+    toolchain: &DetectedToolchain,
+    config: &HostTestConfig,
+    objects: &[PathBuf],
+    output: &Path,
+) -> LinkResult {
+    let args = build_host_link_command(config, objects, output);
+    let start = Instant::now();
+    let (result, _) = run_tool(toolchain, &toolchain.path, &args, output);
+    let duration_ms = start.elapsed().as_millis() as u64;
+
+    match result {
+        Ok(result) => {
+            let stderr = String::from_utf8_lossy(&result.stderr).to_string();
+            LinkResult {
+                exit_code: result.status.code().unwrap_or(-1),
+                stdout: String::from_utf8_lossy(&result.stdout).to_string(),
+                diagnostics: parse_link_diagnostics(&stderr),
+                stderr,
+                duration_ms,
+                timed_out: false,
+                output: output.to_path_buf(),
+                memory_map: None,
+                invocation: None,
+                budget_violations: Vec::new(),
+            }
+        }
+        Err(e) => LinkResult {
+            exit_code: -1,
+            stdout: String::new(),
+            stderr: e.to_string(),
+            duration_ms,
+            timed_out: is_timeout(&e),
+            diagnostics: vec![Diagnostic::error(e.to_string())],
+            output: output.to_path_buf(),
+            memory_map: None,
+            invocation: None,
+            budget_violations: Vec::new(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_load_config() {
+        let dir = TempDir::new().unwrap();
+        assert_eq!(
+            load_host_test_config(dir.path()).unwrap(),
+            HostTestConfig::default()
+        );
+
+        std::fs::create_dir_all(dir.path().join(".axiom")).unwrap();
+        std::fs::write(
+            host_test_config_path(dir.path()),
+            r#"
+compiler = "Gcc"
+mock_dirs = ["tests/mocks"]
+
+[[stub]]
+header = "stm32f4xx.h"
+
+[[stub]]
+header = "core_cm4.h"
+replacement = "tests/fake_core.h"
+"#,
+        )
+        .unwrap();
+        let config = load_host_test_config(dir.path()).unwrap();
+        assert_eq!(config.compiler, ToolchainKind::Gcc);
+        assert_eq!(config.defines, vec!["HOST_TEST"]);
+        assert_eq!(config.stubs.len(), 2);
+        assert_eq!(
+            config.stubs[1].replacement,
+            Some(PathBuf::from("tests/fake_core.h"))
+        );
+    }
+
+    #[test]
+    fn test_compile_request_search_order() {
+        let root = Path::new("/proj");
+        let config = HostTestConfig::default()
+            .with_include_path("src")
+            .with_mock_dir("tests/mocks")
+            .with_flag("-fsanitize=address");
+        let request = config.compile_request(root, Path::new("/proj/src/drivers/uart.c"));
+
+        assert_eq!(
+            request.output,
+            PathBuf::from("/proj/build/host-test/obj/src/drivers/uart.o")
+        );
+        let pos = |needle: &str| request.flags.iter().position(|f| f == needle).unwrap();
+        assert!(pos("-I/proj/build/host-test/stubs") < pos("-I/proj/tests/mocks"));
+        assert!(pos("-I/proj/tests/mocks") < pos("-I/proj/src"));
+        assert!(request.flags.contains(&"-DHOST_TEST".to_string()));
+        assert_eq!(request.flags.last().unwrap(), "-fsanitize=address");
+    }
+
+    #[test]
+    fn test_write_stub_headers() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("fake_core.h"), "#define FAKE 1\n").unwrap();
+        let config = HostTestConfig::default()
+            .with_stub("stm32f4xx.h", None)
+            .with_stub("cmsis/core_cm4.h", Some(PathBuf::from("fake_core.h")));
+
+        let stubs = write_stub_headers(&config, dir.path()).unwrap();
+        let generated = std::fs::read_to_string(stubs.join("stm32f4xx.h")).unwrap();
+        assert!(generated.contains("#ifndef AXIOM_STUB_STM32F4XX_H"));
+        assert_eq!(
+            std::fs::read_to_string(stubs.join("cmsis/core_cm4.h")).unwrap(),
+            "#define FAKE 1\n"
+        );
+    }
+
+    #[test]
+    fn test_rejects_cross_compiler() {
+        let tc = DetectedToolchain::new(
+            ToolchainKind::ArmGcc,
+            PathBuf::from("/usr/bin/arm-none-eabi-gcc"),
+            "13.2.1".to_string(),
+        );
+        let result = build_host_tests(
+            &tc,
+            &HostTestConfig::default(),
+            Path::new("/proj"),
+            &[],
+            "tests",
+        );
+        assert!(matches!(
+            result,
+            Err(HostTestError::UnsupportedToolchain(ToolchainKind::ArmGcc))
+        ));
+    }
+}
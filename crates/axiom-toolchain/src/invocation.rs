@@ -3,15 +3,29 @@
 
 //! Compiler invocation.
 
-use crate::{CompileRequest, CompileResult, DetectedToolchain, ToolchainKind};
-use axiom_core::Diagnostic;
+use crate::limits::is_timeout;
+use crate::response_file::run_tool;
+use crate::tool_log::{log_invocation, unlogged_usage};
+use crate::{
+    normalize_diagnostics, CompileRequest, CompileResult, DetectedToolchain, SourceKind,
+    ToolchainKind,
+};
+use axiom_core::{Diagnostic, FixIt, Location, Position, Range};
+use std::path::PathBuf;
 use std::process::Command;
 use std::time::Instant;
 
 /// Build command arguments for a compile request.
 pub fn build_command(toolchain: &DetectedToolchain, request: &CompileRequest) -> Vec<String> {
+    let kind = SourceKind::from_path(&request.source);
     let mut args = Vec::new();
 
+    // Explicit language for assembly sources
+    if let Some(language) = kind.language() {
+        args.push("-x".to_string());
+        args.push(language.to_string());
+    }
+
     // Source file
     args.push("-c".to_string());
     args.push(request.source.display().to_string());
@@ -28,6 +42,15 @@ pub fn build_command(toolchain: &DetectedToolchain, request: &CompileRequest) ->
         args.push("-g".to_string());
     }
 
+    // Machine-readable fix-it hints
+    match toolchain.kind {
+        _ if kind.is_assembly() => {}
+        ToolchainKind::Clang | ToolchainKind::Gcc | ToolchainKind::ArmGcc => {
+            args.push("-fdiagnostics-parseable-fixits".to_string());
+        }
+        ToolchainKind::Python => {}
+    }
+
     // Target (for cross-compilation)
     if let Some(ref target) = request.target {
         match toolchain.kind {
@@ -44,6 +67,11 @@ pub fn build_command(toolchain: &DetectedToolchain, request: &CompileRequest) ->
         }
     }
 
+    // Warning profile; the C warnings don't apply to assembly
+    if let Some(profile) = request.warning_profile.filter(|_| !kind.is_assembly()) {
+        args.extend(profile.flags(toolchain.kind));
+    }
+
     // Additional flags
     args.extend(request.flags.iter().cloned());
 
@@ -56,29 +84,46 @@ pub fn dry_run(toolchain: &DetectedToolchain, request: &CompileRequest) -> Strin
     format!("{} {}", toolchain.path.display(), args.join(" "))
 }
 
+/// Full argv of a command, program first.
+pub(crate) fn command_argv(command: &Command) -> Vec<String> {
+    std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect()
+}
+
 /// Execute compilation.
 pub fn compile(toolchain: &DetectedToolchain, request: &CompileRequest) -> CompileResult {
     let args = build_command(toolchain, request);
+    let mut invocation = request
+        .capture
+        .as_ref()
+        .map(|capture| capture.capture(&toolchain.path, &args));
     let start = Instant::now();
 
-    let output = Command::new(&toolchain.path)
-        .args(&args)
-        .output();
+    let (output, response_file) = run_tool(toolchain, &toolchain.path, &args, &request.output);
+    if let Some(invocation) = invocation.as_mut() {
+        invocation.response_file = response_file;
+    }
 
     let duration_ms = start.elapsed().as_millis() as u64;
 
-    match output {
+    let mut result = match output {
         Ok(output) => {
             let stdout = String::from_utf8_lossy(&output.stdout).to_string();
             let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-            let diagnostics = parse_diagnostics(&stderr, toolchain.kind);
+            let mut diagnostics = parse_diagnostics(&stderr, toolchain.kind);
+            normalize_diagnostics(&mut diagnostics, toolchain.kind, &toolchain.version);
 
             CompileResult {
                 exit_code: output.status.code().unwrap_or(-1),
                 stdout,
                 stderr,
                 duration_ms,
+                timed_out: false,
                 diagnostics,
+                warning_profile: request.warning_profile,
+                invocation,
             }
         }
         Err(e) => CompileResult {
@@ -86,14 +131,34 @@ pub fn compile(toolchain: &DetectedToolchain, request: &CompileRequest) -> Compi
             stdout: String::new(),
             stderr: e.to_string(),
             duration_ms,
+            timed_out: is_timeout(&e),
             diagnostics: vec![Diagnostic::error(e.to_string())],
+            warning_profile: request.warning_profile,
+            invocation,
         },
+    };
+
+    let logged = log_invocation(
+        request.usage_log.as_ref(),
+        &toolchain.path,
+        &toolchain.version,
+        |record| {
+            record
+                .with_arguments(args)
+                .with_result(result.exit_code, duration_ms)
+                .with_inputs([&request.source])
+                .with_outputs([&request.output])
+        },
+    );
+    if let Err(e) = logged {
+        result.diagnostics.push(unlogged_usage(e));
     }
+    result
 }
 
 /// Parse diagnostics from compiler stderr.
-fn parse_diagnostics(stderr: &str, _kind: ToolchainKind) -> Vec<Diagnostic> {
-    let mut diagnostics = Vec::new();
+pub(crate) fn parse_diagnostics(stderr: &str, _kind: ToolchainKind) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
 
     for line in stderr.lines() {
         let line = line.trim();
@@ -101,10 +166,19 @@ fn parse_diagnostics(stderr: &str, _kind: ToolchainKind) -> Vec<Diagnostic> {
             continue;
         }
 
-        // Simple heuristic: lines containing "error:" or "warning:"
-        if line.contains("error:") {
+        // Fix-it hints belong to the most recent diagnostic
+        if let Some(fixit) = parse_fixit(line) {
+            if let Some(last) = diagnostics.last_mut() {
+                last.fixits.push(fixit);
+            }
+            continue;
+        }
+
+        // Simple heuristic: lines containing "error:" or "warning:", or the
+        // assembler's capitalized "Error:" and "Warning:"
+        if line.contains("error:") || line.contains(": Error:") {
             diagnostics.push(Diagnostic::error(line.to_string()));
-        } else if line.contains("warning:") {
+        } else if line.contains("warning:") || line.contains(": Warning:") {
             diagnostics.push(Diagnostic::warning(line.to_string()));
         }
     }
@@ -112,10 +186,77 @@ fn parse_diagnostics(stderr: &str, _kind: ToolchainKind) -> Vec<Diagnostic> {
     diagnostics
 }
 
+/// Parse a machine-readable fix-it line.
+///
+/// Format (clang and GCC with `-fdiagnostics-parseable-fixits`):
+/// `fix-it:"main.c":{10:5-10:6}:"replacement"`, with 1-based lines and
+/// columns and an exclusive end column.
+fn parse_fixit(line: &str) -> Option<FixIt> {
+    let rest = line.strip_prefix("fix-it:\"")?;
+    let (file, rest) = rest.split_once("\":{")?;
+    let (span, rest) = rest.split_once("}:\"")?;
+    let replacement = rest.strip_suffix('"')?;
+
+    let (start, end) = span.split_once('-')?;
+    let range = Range::new(parse_fixit_position(start)?, parse_fixit_position(end)?);
+
+    Some(FixIt::new(
+        Location::new(PathBuf::from(file), range),
+        unescape_fixit(replacement),
+    ))
+}
+
+/// Parse a `line:column` pair into a 0-indexed position.
+fn parse_fixit_position(s: &str) -> Option<Position> {
+    let (line, column) = s.split_once(':')?;
+    let line: u32 = line.parse().ok()?;
+    let column: u32 = column.parse().ok()?;
+    Some(Position::new(line.checked_sub(1)?, column.checked_sub(1)?))
+}
+
+/// Undo the C-style escaping applied to fix-it replacement text.
+///
+/// Both compilers write non-printable bytes, including each byte of
+/// non-ASCII UTF-8, as three-digit octal escapes (`\012`, `\303\251`);
+/// clang also uses `\n` and `\t`.
+fn unescape_fixit(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] != b'\\' {
+            out.push(bytes[i]);
+            i += 1;
+            continue;
+        }
+        let octal = bytes
+            .get(i + 1..i + 4)
+            .filter(|digits| digits.iter().all(|b| (b'0'..=b'7').contains(b)));
+        if let Some(digits) = octal {
+            let value = digits
+                .iter()
+                .fold(0u32, |n, &digit| n * 8 + u32::from(digit - b'0'));
+            out.push(value as u8);
+            i += 4;
+            continue;
+        }
+        match bytes.get(i + 1) {
+            Some(b'n') => out.push(b'\n'),
+            Some(b't') => out.push(b'\t'),
+            Some(&other) => out.push(other),
+            None => out.push(b'\\'),
+        }
+        i += 2;
+    }
+
+    String::from_utf8_lossy(&out).into_owned()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::path::PathBuf;
+    use crate::WarningProfile;
 
     fn test_toolchain() -> DetectedToolchain {
         DetectedToolchain::new(
@@ -157,6 +298,22 @@ mod tests {
         assert!(!args.contains(&"-g".to_string()));
     }
 
+    #[test]
+    fn test_build_command_with_warning_profile() {
+        let tc = test_toolchain();
+        let request = CompileRequest::new(
+            PathBuf::from("main.c"),
+            PathBuf::from("main.o"),
+        )
+        .with_warning_profile(WarningProfile::AvionicsStrict)
+        .with_flag("-Wno-error=unused");
+
+        let args = build_command(&tc, &request);
+        let werror = args.iter().position(|a| a == "-Werror").unwrap();
+        let override_flag = args.iter().position(|a| a == "-Wno-error=unused").unwrap();
+        assert!(werror < override_flag);
+    }
+
     #[test]
     fn test_dry_run() {
         let tc = test_toolchain();
@@ -180,4 +337,88 @@ main.c:15:10: warning: unused variable 'y' [-Wunused-variable]
         let diags = parse_diagnostics(stderr, ToolchainKind::Clang);
         assert_eq!(diags.len(), 2);
     }
+
+    #[test]
+    fn test_parse_diagnostics_with_fixit() {
+        let stderr = r#"
+main.c:4:13: error: expected ';' after expression
+fix-it:"main.c":{4:13-4:13}:";"
+main.c:9:5: warning: unused variable 'y' [-Wunused-variable]
+        "#;
+
+        let diags = parse_diagnostics(stderr, ToolchainKind::Clang);
+        assert_eq!(diags.len(), 2);
+        assert_eq!(diags[0].fixits.len(), 1);
+        assert!(diags[1].fixits.is_empty());
+
+        let fixit = &diags[0].fixits[0];
+        assert_eq!(fixit.location.path, PathBuf::from("main.c"));
+        assert_eq!(fixit.location.range.start, Position::new(3, 12));
+        assert_eq!(fixit.location.range.end, Position::new(3, 12));
+        assert_eq!(fixit.replacement, ";");
+    }
+
+    #[test]
+    fn test_parse_fixit_escaped_replacement() {
+        let fixit = parse_fixit(r#"fix-it:"src/a.c":{2:1-2:8}:"printf(\"%d\\n\", x)""#).unwrap();
+        assert_eq!(fixit.location.range.start, Position::new(1, 0));
+        assert_eq!(fixit.location.range.end, Position::new(1, 7));
+        assert_eq!(fixit.replacement, "printf(\"%d\\n\", x)");
+    }
+
+    #[test]
+    fn test_parse_gcc_octal_fixits() {
+        let stderr = "\
+t.c: In function 'main':
+t.c:3:3: warning: implicit declaration of function 'printf' [-Wimplicit-function-declaration]
+t.c:1:1: note: include '<stdio.h>' or provide a declaration of 'printf'
+fix-it:\"t.c\":{1:1-1:1}:\"#include <stdio.h>\\012\"
+t.c:4:8: warning: unknown escape sequence: '\\e'
+fix-it:\"t.c\":{4:8-4:10}:\"caf\\303\\251\\011\\\\\"
+";
+        let diags = parse_diagnostics(stderr, ToolchainKind::Gcc);
+        assert_eq!(diags.len(), 2);
+        assert_eq!(diags[0].fixits[0].replacement, "#include <stdio.h>\n");
+        assert_eq!(diags[1].fixits[0].replacement, "caf\u{e9}\t\\");
+        assert_eq!(unescape_fixit(r"\0123\8"), "\n38");
+    }
+
+    #[test]
+    fn test_parse_fixit_rejects_other_lines() {
+        assert!(parse_fixit("main.c:1:1: error: oops").is_none());
+        assert!(parse_fixit(r#"fix-it:"main.c":{0:1-0:1}:"x""#).is_none());
+    }
+
+    #[test]
+    fn test_build_command_requests_parseable_fixits() {
+        let tc = test_toolchain();
+        let request = CompileRequest::new(PathBuf::from("main.c"), PathBuf::from("main.o"));
+        let args = build_command(&tc, &request);
+        assert!(args.contains(&"-fdiagnostics-parseable-fixits".to_string()));
+    }
+
+    #[test]
+    fn test_build_command_assembly() {
+        let tc = test_toolchain();
+        let request = CompileRequest::new(PathBuf::from("vectors.S"), PathBuf::from("vectors.o"))
+            .with_warning_profile(WarningProfile::AvionicsStrict);
+        let args = build_command(&tc, &request);
+        assert_eq!(&args[..4], &["-x", "assembler-with-cpp", "-c", "vectors.S"]);
+        assert!(!args.contains(&"-fdiagnostics-parseable-fixits".to_string()));
+        assert!(!args.contains(&"-Werror".to_string()));
+    }
+
+    #[test]
+    fn test_parse_assembler_diagnostics() {
+        let stderr = r#"
+startup.s: Assembler messages:
+startup.s:42: Error: bad instruction `bx lrr'
+startup.s:57: Warning: end of file not at end of a line; newline inserted
+        "#;
+
+        let diags = parse_diagnostics(stderr, ToolchainKind::ArmGcc);
+        assert_eq!(diags.len(), 2);
+        assert_eq!(diags[0].severity, axiom_core::Severity::Error);
+        assert_eq!(diags[1].severity, axiom_core::Severity::Warning);
+    }
 }
@@ -5,10 +5,111 @@
 //!
 //! Toolchain detection and compiler invocation.
 
+mod archive;
+mod arm;
+mod assembly;
+mod binary_gen;
+mod budget;
+mod bundle;
+mod check;
+mod container;
+mod coverage;
+mod coverage_history;
+mod coverage_justification;
 mod detection;
+mod determinism;
+mod disasm;
+mod doctor;
+mod dwarf;
+mod elf;
+mod environment;
+mod firmware_diff;
+mod flash;
+mod gdb;
+mod host_runner;
+mod host_test;
 mod invocation;
+mod limits;
+mod link;
+mod linker_gen;
+mod linker_script;
+mod lockfile;
+mod make_dry_run;
+mod makefile;
+mod map;
+mod mcu_db;
+mod normalize;
+mod openocd;
+mod pack;
+mod prelink;
+mod preprocessor;
+mod probe;
+mod profile;
+mod qualification;
+mod remote;
+mod report;
+mod response_file;
+mod size_history;
+mod size_report;
+mod stats;
+mod svd;
+mod tool_log;
+mod trace;
 mod types;
+mod warnings;
+mod weak;
+mod zip;
 
+pub use archive::*;
+pub use arm::*;
+pub use assembly::*;
+pub use binary_gen::*;
+pub use budget::*;
+pub use bundle::*;
+pub use check::*;
+pub use container::*;
+pub use coverage::*;
+pub use coverage_history::*;
+pub use coverage_justification::*;
 pub use detection::*;
+pub use determinism::*;
+pub use disasm::*;
+pub use doctor::*;
+pub use dwarf::*;
+pub use elf::*;
+pub use environment::*;
+pub use firmware_diff::*;
+pub use flash::*;
+pub use gdb::*;
+pub use host_runner::*;
+pub use host_test::*;
 pub use invocation::*;
+pub use limits::*;
+pub use link::*;
+pub use linker_gen::*;
+pub use linker_script::*;
+pub use lockfile::*;
+pub use make_dry_run::*;
+pub use makefile::*;
+pub use map::*;
+pub use mcu_db::*;
+pub use normalize::*;
+pub use openocd::*;
+pub use pack::*;
+pub use prelink::*;
+pub use preprocessor::*;
+pub use probe::*;
+pub use profile::*;
+pub use qualification::*;
+pub use remote::*;
+pub use report::*;
+pub use response_file::*;
+pub use size_history::*;
+pub use size_report::*;
+pub use stats::*;
+pub use svd::*;
+pub use tool_log::*;
+pub use trace::*;
 pub use types::*;
+pub use warnings::*;
+pub use weak::*;
@@ -0,0 +1,277 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Timeouts and output limits for tool invocations.
+//!
+//! A wedged compiler, or one waiting on an unreachable license server,
+//! would otherwise block its caller forever, and a runaway tool can print
+//! without end. Toolchain binaries are run under process-wide limits: past
+//! the timeout the process is killed and the run fails with
+//! [`io::ErrorKind::TimedOut`]; output past the size limit is discarded
+//! and a truncation notice appended.
+
+use serde::{Deserialize, Serialize};
+use std::io::{self, Read};
+use std::process::{Child, Command, Output, Stdio};
+use std::sync::RwLock;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Default tool timeout, in seconds.
+pub const DEFAULT_TOOL_TIMEOUT_SECS: u64 = 600;
+
+/// Default limit on captured output, in bytes per stream.
+pub const DEFAULT_MAX_OUTPUT_BYTES: usize = 16 * 1024 * 1024;
+
+/// How often a running tool is polled for exit.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Limits applied to a tool invocation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolLimits {
+    /// Wall-clock timeout in seconds, or `None` to wait indefinitely.
+    pub timeout_secs: Option<u64>,
+    /// Captured bytes per output stream, or `None` for no limit.
+    pub max_output_bytes: Option<usize>,
+}
+
+impl Default for ToolLimits {
+    fn default() -> Self {
+        Self {
+            timeout_secs: Some(DEFAULT_TOOL_TIMEOUT_SECS),
+            max_output_bytes: Some(DEFAULT_MAX_OUTPUT_BYTES),
+        }
+    }
+}
+
+impl ToolLimits {
+    /// No timeout and no output limit.
+    pub const fn unlimited() -> Self {
+        Self {
+            timeout_secs: None,
+            max_output_bytes: None,
+        }
+    }
+
+    /// Set the timeout.
+    pub fn with_timeout_secs(mut self, secs: Option<u64>) -> Self {
+        self.timeout_secs = secs;
+        self
+    }
+
+    /// Set the output limit.
+    pub fn with_max_output_bytes(mut self, bytes: Option<usize>) -> Self {
+        self.max_output_bytes = bytes;
+        self
+    }
+
+    /// Timeout as a duration.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout_secs.map(Duration::from_secs)
+    }
+}
+
+static LIMITS: RwLock<ToolLimits> = RwLock::new(ToolLimits {
+    timeout_secs: Some(DEFAULT_TOOL_TIMEOUT_SECS),
+    max_output_bytes: Some(DEFAULT_MAX_OUTPUT_BYTES),
+});
+
+/// Limits currently applied to tool invocations.
+pub fn tool_limits() -> ToolLimits {
+    LIMITS.read().map(|limits| *limits).unwrap_or_default()
+}
+
+/// Replace the limits applied to tool invocations.
+pub fn set_tool_limits(limits: ToolLimits) {
+    if let Ok(mut current) = LIMITS.write() {
+        *current = limits;
+    }
+}
+
+/// Whether a tool invocation failed by timing out.
+pub fn is_timeout(err: &io::Error) -> bool {
+    err.kind() == io::ErrorKind::TimedOut
+}
+
+/// Run a command to completion under the current limits.
+///
+/// Stdin is closed. On timeout the process is killed and an error of kind
+/// [`io::ErrorKind::TimedOut`] returned.
+pub fn run_limited(command: &mut Command) -> io::Result<Output> {
+    run_with_limits(command, &tool_limits())
+}
+
+/// Run a command to completion under the current limits, reading stdin
+/// from `input`, such as another process's output.
+pub(crate) fn run_limited_with_input(
+    command: &mut Command,
+    input: impl Into<Stdio>,
+) -> io::Result<Output> {
+    run_with_stdin(command, input.into(), &tool_limits())
+}
+
+/// Run a command to completion under the given limits.
+///
+/// Stdin is closed. On timeout the process is killed and an error of kind
+/// [`io::ErrorKind::TimedOut`] returned.
+pub(crate) fn run_with_limits(command: &mut Command, limits: &ToolLimits) -> io::Result<Output> {
+    run_with_stdin(command, Stdio::null(), limits)
+}
+
+fn run_with_stdin(command: &mut Command, stdin: Stdio, limits: &ToolLimits) -> io::Result<Output> {
+    let mut child = command
+        .stdin(stdin)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let stdout = capture(child.stdout.take(), limits.max_output_bytes);
+    let stderr = capture(child.stderr.take(), limits.max_output_bytes);
+
+    let status = match limits.timeout() {
+        Some(timeout) => match wait_timeout(&mut child, timeout)? {
+            Some(status) => status,
+            None => {
+                let _ = child.kill();
+                let _ = child.wait();
+                // Readers are left behind: a grandchild may still hold
+                // the pipes open
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!(
+                        "{} timed out after {}s",
+                        command.get_program().to_string_lossy(),
+                        timeout.as_secs()
+                    ),
+                ));
+            }
+        },
+        None => child.wait()?,
+    };
+
+    Ok(Output {
+        status,
+        stdout: join(stdout)?,
+        stderr: join(stderr)?,
+    })
+}
+
+/// Wait for a child up to a timeout; `None` if it is still running.
+fn wait_timeout(
+    child: &mut Child,
+    timeout: Duration,
+) -> io::Result<Option<std::process::ExitStatus>> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if Instant::now() >= deadline {
+            return Ok(None);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Read a stream on its own thread, keeping at most `limit` bytes.
+///
+/// The rest is drained so the tool is not blocked on a full pipe.
+fn capture<R: Read + Send + 'static>(
+    stream: Option<R>,
+    limit: Option<usize>,
+) -> JoinHandle<io::Result<Vec<u8>>> {
+    thread::spawn(move || {
+        let mut buffer = Vec::new();
+        let Some(mut stream) = stream else {
+            return Ok(buffer);
+        };
+        let Some(limit) = limit else {
+            stream.read_to_end(&mut buffer)?;
+            return Ok(buffer);
+        };
+        (&mut stream).take(limit as u64).read_to_end(&mut buffer)?;
+        let discarded = io::copy(&mut stream, &mut io::sink())?;
+        if discarded > 0 {
+            buffer.extend_from_slice(
+                format!(
+                    "\n[output truncated: {} bytes over the {} byte limit]\n",
+                    discarded, limit
+                )
+                .as_bytes(),
+            );
+        }
+        Ok(buffer)
+    })
+}
+
+fn join(handle: JoinHandle<io::Result<Vec<u8>>>) -> io::Result<Vec<u8>> {
+    handle
+        .join()
+        .unwrap_or_else(|_| Err(io::Error::other("output reader panicked")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_with_limits() {
+        let output = run_with_limits(
+            Command::new("sh").args(["-c", "echo out; echo err >&2; exit 3"]),
+            &ToolLimits::default(),
+        )
+        .unwrap();
+        assert_eq!(output.status.code(), Some(3));
+        assert_eq!(output.stdout, b"out\n");
+        assert_eq!(output.stderr, b"err\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_timeout() {
+        let limits = ToolLimits::unlimited().with_timeout_secs(Some(1));
+        let start = Instant::now();
+        let err = run_with_limits(Command::new("sleep").arg("30"), &limits).unwrap_err();
+        assert!(is_timeout(&err));
+        assert!(err.to_string().contains("sleep timed out after 1s"));
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_output_limit() {
+        let limits = ToolLimits::unlimited().with_max_output_bytes(Some(4));
+        let output = run_with_limits(Command::new("echo").arg("0123456789"), &limits).unwrap();
+        assert!(output.status.success());
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.starts_with("0123\n[output truncated: 7 bytes"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_with_input() {
+        let mut source = Command::new("echo")
+            .arg("piped")
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let input = source.stdout.take().unwrap();
+        let output = run_with_stdin(
+            &mut Command::new("cat"),
+            input.into(),
+            &ToolLimits::default(),
+        )
+        .unwrap();
+        source.wait().unwrap();
+        assert_eq!(output.stdout, b"piped\n");
+    }
+
+    #[test]
+    fn test_default_limits() {
+        assert_eq!(tool_limits(), ToolLimits::default());
+        assert_eq!(
+            ToolLimits::default().timeout(),
+            Some(Duration::from_secs(DEFAULT_TOOL_TIMEOUT_SECS))
+        );
+    }
+}
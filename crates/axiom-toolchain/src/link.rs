@@ -0,0 +1,804 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! ARM linking.
+
+use crate::invocation::command_argv;
+use crate::limits::is_timeout;
+use crate::response_file::run_tool;
+use crate::tool_log::{log_invocation, unlogged_usage};
+use crate::{
+    check_object_consistency, read_linker_script, read_map_file, ArmMcuConfig, BudgetViolation,
+    BuildProfile, DetectedToolchain, EnvironmentCapture, InvocationRecord, MemoryBudget, MemoryMap,
+    ToolQualificationLogger,
+};
+use axiom_core::Diagnostic;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// A project-local static library to link.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LinkArchive {
+    /// Archive path.
+    pub path: PathBuf,
+    /// Link every member, not only those resolving undefined symbols.
+    ///
+    /// Needed for archives holding interrupt handlers or other objects
+    /// referenced only from the vector table or linker script.
+    pub whole_archive: bool,
+}
+
+/// C library to link against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CLibrary {
+    /// newlib-nano, the size-optimized newlib build.
+    #[default]
+    NewlibNano,
+    /// Full newlib, with floating-point printf and reentrancy support.
+    Newlib,
+    /// picolibc.
+    Picolibc,
+}
+
+impl CLibrary {
+    /// Specs file selecting this library, if the default isn't it.
+    pub fn specs(&self) -> Option<&'static str> {
+        match self {
+            CLibrary::NewlibNano => Some("nano.specs"),
+            CLibrary::Newlib => None,
+            CLibrary::Picolibc => Some("picolibc.specs"),
+        }
+    }
+}
+
+/// System call implementation for the C library.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Syscalls {
+    /// The project provides its own (`_write`, `_sbrk`, ...).
+    #[default]
+    Project,
+    /// libnosys stubs that fail with `ENOSYS`.
+    Nosys,
+    /// librdimon, which forwards I/O to the debugger over semihosting.
+    Semihosting,
+}
+
+impl Syscalls {
+    /// Specs file providing the stubs, if any.
+    pub fn specs(&self) -> Option<&'static str> {
+        match self {
+            Syscalls::Project => None,
+            Syscalls::Nosys => Some("nosys.specs"),
+            Syscalls::Semihosting => Some("rdimon.specs"),
+        }
+    }
+}
+
+/// Linker configuration.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LinkerConfig {
+    /// Linker script.
+    pub script: PathBuf,
+    /// Where to write the map file, if wanted.
+    pub map_file: Option<PathBuf>,
+    /// Discard unused sections (`--gc-sections`).
+    pub gc_sections: bool,
+    /// Library search paths.
+    pub library_paths: Vec<PathBuf>,
+    /// Project-local archives, linked after the objects in this order.
+    #[serde(default)]
+    pub archives: Vec<LinkArchive>,
+    /// Libraries to link (`-l<name>`).
+    pub libraries: Vec<String>,
+    /// Wrap archives and libraries in a group so mutual references resolve
+    /// regardless of order.
+    #[serde(default)]
+    pub group_libraries: bool,
+    /// C library.
+    #[serde(default)]
+    pub c_library: CLibrary,
+    /// System call stubs.
+    #[serde(default)]
+    pub syscalls: Syscalls,
+    /// Link the toolchain's C runtime startup files (`crt0` and friends).
+    ///
+    /// Off by default: firmware usually brings its own startup code.
+    #[serde(default)]
+    pub startup_files: bool,
+    /// Extra specs files, passed after the library and syscall specs.
+    #[serde(default)]
+    pub specs: Vec<String>,
+    /// Additional linker flags.
+    pub flags: Vec<String>,
+}
+
+impl LinkerConfig {
+    /// Create a configuration for a linker script.
+    pub fn new(script: impl Into<PathBuf>) -> Self {
+        Self {
+            script: script.into(),
+            map_file: None,
+            gc_sections: true,
+            library_paths: Vec::new(),
+            archives: Vec::new(),
+            libraries: Vec::new(),
+            group_libraries: false,
+            c_library: CLibrary::default(),
+            syscalls: Syscalls::default(),
+            startup_files: false,
+            specs: Vec::new(),
+            flags: Vec::new(),
+        }
+    }
+
+    /// Write a map file.
+    pub fn with_map_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.map_file = Some(path.into());
+        self
+    }
+
+    /// Add a library search path.
+    pub fn with_library_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.library_paths.push(path.into());
+        self
+    }
+
+    /// Link a project-local archive.
+    pub fn with_archive(mut self, path: impl Into<PathBuf>, whole_archive: bool) -> Self {
+        self.archives.push(LinkArchive {
+            path: path.into(),
+            whole_archive,
+        });
+        self
+    }
+
+    /// Group archives and libraries with `--start-group`/`--end-group`.
+    pub fn with_group_libraries(mut self, group: bool) -> Self {
+        self.group_libraries = group;
+        self
+    }
+
+    /// Link a library.
+    pub fn with_library(mut self, name: impl Into<String>) -> Self {
+        self.libraries.push(name.into());
+        self
+    }
+
+    /// Set the C library.
+    pub fn with_c_library(mut self, library: CLibrary) -> Self {
+        self.c_library = library;
+        self
+    }
+
+    /// Set the system call stubs.
+    pub fn with_syscalls(mut self, syscalls: Syscalls) -> Self {
+        self.syscalls = syscalls;
+        self
+    }
+
+    /// Link or omit the toolchain's startup files.
+    pub fn with_startup_files(mut self, enabled: bool) -> Self {
+        self.startup_files = enabled;
+        self
+    }
+
+    /// Add a specs file.
+    pub fn with_specs(mut self, specs: impl Into<String>) -> Self {
+        self.specs.push(specs.into());
+        self
+    }
+
+    /// Specs files to pass, in order.
+    pub fn all_specs(&self) -> Vec<String> {
+        self.c_library
+            .specs()
+            .into_iter()
+            .chain(self.syscalls.specs())
+            .map(str::to_string)
+            .chain(self.specs.iter().cloned())
+            .collect()
+    }
+
+    /// Add a linker flag.
+    pub fn with_flag(mut self, flag: impl Into<String>) -> Self {
+        self.flags.push(flag.into());
+        self
+    }
+}
+
+/// A request to link objects into an ARM executable.
+#[derive(Debug, Clone)]
+pub struct ArmLinkRequest {
+    /// Objects to link, in order.
+    pub objects: Vec<PathBuf>,
+    /// Output ELF path.
+    pub output: PathBuf,
+    /// Target MCU (must match the compile flags).
+    pub mcu: ArmMcuConfig,
+    /// Linker configuration.
+    pub linker: LinkerConfig,
+    /// Record the invocation environment in the result.
+    pub capture: Option<EnvironmentCapture>,
+    /// Flash and RAM budgets the linked image must fit.
+    pub budget: Option<MemoryBudget>,
+    /// Log the invocation for tool qualification.
+    pub usage_log: Option<ToolQualificationLogger>,
+}
+
+impl ArmLinkRequest {
+    /// Create a new link request.
+    pub fn new(
+        objects: Vec<PathBuf>,
+        output: PathBuf,
+        mcu: ArmMcuConfig,
+        linker: LinkerConfig,
+    ) -> Self {
+        Self {
+            objects,
+            output,
+            mcu,
+            linker,
+            capture: None,
+            budget: None,
+            usage_log: None,
+        }
+    }
+
+    /// Add a build profile's linker flags.
+    pub fn with_profile(mut self, profile: &BuildProfile) -> Self {
+        self.linker.flags.extend(profile.ldflags.iter().cloned());
+        self
+    }
+
+    /// Record the command line, environment and toolchain hash in the result.
+    pub fn with_environment_capture(mut self, capture: EnvironmentCapture) -> Self {
+        self.capture = Some(capture);
+        self
+    }
+
+    /// Log the invocation, with checksums of the objects, archives and
+    /// linker script read and the ELF and map written, to a tool usage log.
+    pub fn with_usage_log(mut self, log: ToolQualificationLogger) -> Self {
+        self.usage_log = Some(log);
+        self
+    }
+
+    /// Files the link reads from the project: objects, archives and the
+    /// linker script.
+    pub fn inputs(&self) -> Vec<PathBuf> {
+        let mut inputs = self.objects.clone();
+        inputs.extend(self.linker.archives.iter().map(|a| a.path.clone()));
+        inputs.push(self.linker.script.clone());
+        inputs
+    }
+
+    /// Files the link writes: the ELF and any map file.
+    pub fn outputs(&self) -> Vec<PathBuf> {
+        std::iter::once(self.output.clone())
+            .chain(self.linker.map_file.clone())
+            .collect()
+    }
+
+    /// Check the linked image against flash and RAM budgets.
+    pub fn with_budget(mut self, budget: MemoryBudget) -> Self {
+        self.budget = Some(budget).filter(|b| !b.is_empty());
+        self
+    }
+}
+
+/// Result of a link.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkResult {
+    /// Exit code of the linker driver.
+    pub exit_code: i32,
+    /// Standard output.
+    pub stdout: String,
+    /// Standard error.
+    pub stderr: String,
+    /// Duration in milliseconds.
+    pub duration_ms: u64,
+    /// Whether the linker driver was killed for exceeding the tool timeout.
+    #[serde(default)]
+    pub timed_out: bool,
+    /// Parsed diagnostics.
+    pub diagnostics: Vec<Diagnostic>,
+    /// Output ELF path.
+    pub output: PathBuf,
+    /// Parsed map file, when one was requested and written.
+    pub memory_map: Option<MemoryMap>,
+    /// Exact invocation, when environment capture was enabled.
+    #[serde(default)]
+    pub invocation: Option<InvocationRecord>,
+    /// Memories over the request's budget.
+    #[serde(default)]
+    pub budget_violations: Vec<BudgetViolation>,
+}
+
+impl LinkResult {
+    /// Check if linking succeeded and the image is within budget.
+    pub fn success(&self) -> bool {
+        self.exit_code == 0 && self.budget_violations.is_empty()
+    }
+}
+
+/// Build arm-none-eabi-gcc arguments for a link request.
+pub fn build_arm_link_command(request: &ArmLinkRequest) -> Vec<String> {
+    let linker = &request.linker;
+    let mut args = request.mcu.machine_flags();
+
+    args.push("-T".to_string());
+    args.push(linker.script.display().to_string());
+    args.extend(linker.all_specs().iter().map(|s| format!("--specs={}", s)));
+    if !linker.startup_files {
+        args.push("-nostartfiles".to_string());
+    }
+
+    if linker.gc_sections {
+        args.push("-Wl,--gc-sections".to_string());
+    }
+    if let Some(ref map) = linker.map_file {
+        args.push(format!("-Wl,-Map={}", map.display()));
+    }
+
+    // Archives must follow the objects that reference them
+    args.extend(request.objects.iter().map(|o| o.display().to_string()));
+    args.extend(
+        linker
+            .library_paths
+            .iter()
+            .map(|p| format!("-L{}", p.display())),
+    );
+
+    if linker.group_libraries {
+        args.push("-Wl,--start-group".to_string());
+    }
+    for archive in &linker.archives {
+        if archive.whole_archive {
+            args.push("-Wl,--whole-archive".to_string());
+            args.push(archive.path.display().to_string());
+            args.push("-Wl,--no-whole-archive".to_string());
+        } else {
+            args.push(archive.path.display().to_string());
+        }
+    }
+    args.extend(linker.libraries.iter().map(|l| format!("-l{}", l)));
+    if linker.group_libraries {
+        args.push("-Wl,--end-group".to_string());
+    }
+    args.extend(linker.flags.iter().cloned());
+
+    args.push("-o".to_string());
+    args.push(request.output.display().to_string());
+    args
+}
+
+/// Exact argv [`link_arm`] would run, program first, for review or
+/// certification records.
+pub fn dry_run_arm_link(toolchain: &DetectedToolchain, request: &ArmLinkRequest) -> Vec<String> {
+    command_argv(&toolchain.command(&build_arm_link_command(request)))
+}
+
+/// Link with an ARM GCC toolchain.
+///
+/// Objects built with incompatible settings fail the link before the
+/// linker runs.
+pub fn link_arm(toolchain: &DetectedToolchain, request: &ArmLinkRequest) -> LinkResult {
+    if let Some(result) = check_prelink(request, Path::new("")) {
+        return result;
+    }
+
+    let args = build_arm_link_command(request);
+    let mut invocation = request
+        .capture
+        .as_ref()
+        .map(|capture| capture.capture(&toolchain.path, &args));
+    let start = Instant::now();
+
+    let (output, response_file) = run_tool(toolchain, &toolchain.path, &args, &request.output);
+    if let Some(invocation) = invocation.as_mut() {
+        invocation.response_file = response_file;
+    }
+
+    let duration_ms = start.elapsed().as_millis() as u64;
+
+    let mut result = match output {
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+            let memory_map = request
+                .linker
+                .map_file
+                .as_ref()
+                .and_then(|path| read_map_file(path).ok());
+            let mut result = LinkResult {
+                exit_code: output.status.code().unwrap_or(-1),
+                stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+                diagnostics: parse_link_diagnostics(&stderr),
+                stderr,
+                duration_ms,
+                timed_out: false,
+                output: request.output.clone(),
+                memory_map,
+                invocation,
+                budget_violations: Vec::new(),
+            };
+            check_budget(request, &mut result, Path::new(""));
+            result
+        }
+        Err(e) => LinkResult {
+            exit_code: -1,
+            stdout: String::new(),
+            stderr: e.to_string(),
+            duration_ms,
+            timed_out: is_timeout(&e),
+            diagnostics: vec![Diagnostic::error(e.to_string())],
+            output: request.output.clone(),
+            memory_map: None,
+            invocation,
+            budget_violations: Vec::new(),
+        },
+    };
+
+    let logged = log_invocation(
+        request.usage_log.as_ref(),
+        &toolchain.path,
+        &toolchain.version,
+        |record| {
+            record
+                .with_arguments(args)
+                .with_result(result.exit_code, duration_ms)
+                .with_inputs(request.inputs())
+                .with_outputs(request.outputs())
+        },
+    );
+    if let Err(e) = logged {
+        result.diagnostics.push(unlogged_usage(e));
+    }
+    result
+}
+
+/// A failed result if the request's objects were built with incompatible
+/// settings. Paths in the request are relative to `root`.
+///
+/// Objects that can't be inspected are left for the linker to report.
+pub(crate) fn check_prelink(request: &ArmLinkRequest, root: &Path) -> Option<LinkResult> {
+    let objects: Vec<PathBuf> = request.objects.iter().map(|o| root.join(o)).collect();
+    let report = check_object_consistency(&objects).ok()?;
+    if report.is_consistent() {
+        return None;
+    }
+
+    Some(LinkResult {
+        exit_code: -1,
+        stdout: String::new(),
+        stderr: report.to_string(),
+        duration_ms: 0,
+        timed_out: false,
+        diagnostics: report.to_diagnostics(),
+        output: request.output.clone(),
+        memory_map: None,
+        invocation: None,
+        budget_violations: Vec::new(),
+    })
+}
+
+/// Evaluate a successful link against the request's budget, recording each
+/// violation with an error diagnostic. Paths in the request are relative to
+/// `root`.
+///
+/// Device capacity comes from the map file's memory configuration, or from
+/// the linker script when no map was written.
+pub(crate) fn check_budget(request: &ArmLinkRequest, result: &mut LinkResult, root: &Path) {
+    let Some(budget) = request.budget.as_ref() else {
+        return;
+    };
+    if result.exit_code != 0 {
+        return;
+    }
+
+    let regions = match &result.memory_map {
+        Some(map) => map.regions.clone(),
+        None => read_linker_script(&root.join(&request.linker.script))
+            .map(|script| script.memory)
+            .unwrap_or_default(),
+    };
+    match budget.evaluate_elf(&root.join(&request.output), &regions) {
+        Ok(violations) => {
+            result
+                .diagnostics
+                .extend(violations.iter().map(BudgetViolation::to_diagnostic));
+            result.budget_violations = violations;
+        }
+        Err(e) => result.diagnostics.push(
+            Diagnostic::warning(format!("Memory budget not checked: {}", e))
+                .with_code(crate::BUDGET_DIAGNOSTIC_CODE),
+        ),
+    }
+}
+
+/// Parse linker diagnostics from stderr.
+pub(crate) fn parse_link_diagnostics(stderr: &str) -> Vec<Diagnostic> {
+    stderr
+        .lines()
+        .map(str::trim)
+        .filter_map(|line| {
+            if line.contains("undefined reference to")
+                || line.contains("overflowed by")
+                || line.contains("multiple definition of")
+                || line.contains("error:")
+            {
+                Some(Diagnostic::error(line))
+            } else if line.contains("warning:") {
+                Some(Diagnostic::warning(line))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf::build_test_elf;
+    use crate::FloatAbi;
+
+    fn request() -> ArmLinkRequest {
+        ArmLinkRequest::new(
+            vec![
+                PathBuf::from("build/startup.o"),
+                PathBuf::from("build/main.o"),
+            ],
+            PathBuf::from("build/firmware.elf"),
+            ArmMcuConfig::new("cortex-m4").with_fpu("fpv4-sp-d16", FloatAbi::Hard),
+            LinkerConfig::new("stm32f407.ld")
+                .with_map_file("build/firmware.map")
+                .with_library_path("lib")
+                .with_library("m"),
+        )
+    }
+
+    #[test]
+    fn test_build_arm_link_command() {
+        let args = build_arm_link_command(&request());
+        assert_eq!(args[0], "-mcpu=cortex-m4");
+        assert!(args.contains(&"-mfloat-abi=hard".to_string()));
+
+        let script = args.iter().position(|a| a == "-T").unwrap();
+        assert_eq!(args[script + 1], "stm32f407.ld");
+        assert!(args.contains(&"--specs=nano.specs".to_string()));
+        assert!(args.contains(&"-Wl,--gc-sections".to_string()));
+        assert!(args.contains(&"-Wl,-Map=build/firmware.map".to_string()));
+
+        let startup = args.iter().position(|a| a == "build/startup.o").unwrap();
+        let lib = args.iter().position(|a| a == "-lm").unwrap();
+        assert!(startup < lib);
+        assert_eq!(&args[args.len() - 2..], &["-o", "build/firmware.elf"]);
+    }
+
+    #[test]
+    fn test_c_library_selection() {
+        let args = build_arm_link_command(&request());
+        assert!(args.contains(&"-nostartfiles".to_string()));
+
+        let mut req = request();
+        req.linker = req
+            .linker
+            .with_c_library(CLibrary::Newlib)
+            .with_syscalls(Syscalls::Semihosting)
+            .with_startup_files(true)
+            .with_specs("board.specs");
+        let args = build_arm_link_command(&req);
+        let specs: Vec<_> = args.iter().filter(|a| a.starts_with("--specs=")).collect();
+        assert_eq!(specs, vec!["--specs=rdimon.specs", "--specs=board.specs"]);
+        assert!(!args.contains(&"-nostartfiles".to_string()));
+
+        req.linker = req.linker.with_c_library(CLibrary::Picolibc);
+        assert!(build_arm_link_command(&req).contains(&"--specs=picolibc.specs".to_string()));
+    }
+
+    #[test]
+    fn test_link_with_profile() {
+        let req = request().with_profile(&BuildProfile::coverage());
+        let args = build_arm_link_command(&req);
+        let coverage = args.iter().position(|a| a == "--coverage").unwrap();
+        assert!(coverage > args.iter().position(|a| a == "-lm").unwrap());
+    }
+
+    #[test]
+    fn test_linker_config_defaults_from_json() {
+        let config: LinkerConfig = serde_json::from_str(
+            r#"{"script": "app.ld", "map_file": null, "gc_sections": true,
+                "library_paths": [], "libraries": [], "flags": []}"#,
+        )
+        .unwrap();
+        assert_eq!(config.c_library, CLibrary::NewlibNano);
+        assert_eq!(config.syscalls, Syscalls::Project);
+        assert_eq!(config.all_specs(), vec!["nano.specs"]);
+    }
+
+    #[test]
+    fn test_link_archives() {
+        let mut req = request();
+        req.linker = req
+            .linker
+            .with_archive("build/libhal.a", false)
+            .with_archive("build/libisr.a", true)
+            .with_group_libraries(true);
+        let args = build_arm_link_command(&req);
+
+        let pos = |needle: &str| args.iter().position(|a| a == needle).unwrap();
+        assert!(pos("build/main.o") < pos("-Wl,--start-group"));
+        assert!(pos("-Wl,--start-group") < pos("build/libhal.a"));
+        assert!(pos("build/libhal.a") < pos("build/libisr.a"));
+        assert_eq!(args[pos("build/libisr.a") - 1], "-Wl,--whole-archive");
+        assert_eq!(args[pos("build/libisr.a") + 1], "-Wl,--no-whole-archive");
+        assert!(pos("-lm") < pos("-Wl,--end-group"));
+    }
+
+    #[test]
+    fn test_parse_link_diagnostics() {
+        let stderr = "\
+/opt/arm/bin/ld: build/main.o: in function `main':
+main.c:(.text.main+0x8): undefined reference to `uart_init'
+/opt/arm/bin/ld: firmware.elf section `.bss' will not fit in region `RAM'
+/opt/arm/bin/ld: region `RAM' overflowed by 1024 bytes
+/opt/arm/bin/ld: warning: firmware.elf has a LOAD segment with RWX permissions
+collect2: error: ld returned 1 exit status
+";
+        let diags = parse_link_diagnostics(stderr);
+        assert_eq!(diags.len(), 4);
+        assert!(diags[0].message.contains("uart_init"));
+        assert_eq!(diags[2].severity, axiom_core::Severity::Warning);
+    }
+
+    #[test]
+    fn test_link_missing_linker() {
+        let tc = DetectedToolchain::new(
+            crate::ToolchainKind::ArmGcc,
+            PathBuf::from("/nonexistent/arm-none-eabi-gcc"),
+            "13.2.1".to_string(),
+        );
+        let result = link_arm(&tc, &request());
+        assert!(!result.success());
+        assert!(result.memory_map.is_none());
+        assert!(result.invocation.is_none());
+
+        let captured = request().with_environment_capture(EnvironmentCapture::default());
+        let invocation = link_arm(&tc, &captured).invocation.unwrap();
+        assert_eq!(invocation.program, tc.path);
+        assert_eq!(invocation.args, build_arm_link_command(&captured));
+        assert_eq!(invocation.toolchain_sha256, None);
+    }
+
+    #[test]
+    fn test_link_usage_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let tc = DetectedToolchain::new(
+            crate::ToolchainKind::ArmGcc,
+            PathBuf::from("/nonexistent/arm-none-eabi-gcc"),
+            "13.2.1".to_string(),
+        );
+        let log = ToolQualificationLogger::for_project(dir.path());
+        let result = link_arm(&tc, &request().with_usage_log(log.clone()));
+        assert_eq!(result.diagnostics.len(), 1);
+
+        let records = log.load().unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].tool, "arm-none-eabi-gcc");
+        assert_eq!(records[0].version, "13.2.1");
+        assert_eq!(records[0].exit_code, -1);
+        assert_eq!(records[0].arguments, build_arm_link_command(&request()));
+        assert_eq!(
+            records[0]
+                .outputs
+                .iter()
+                .map(|o| &o.path)
+                .collect::<Vec<_>>(),
+            request().outputs().iter().collect::<Vec<_>>()
+        );
+        assert!(records[0].outputs.iter().all(|o| o.sha256.is_none()));
+
+        // A log that can't be written warns without failing the link
+        std::fs::write(dir.path().join("blocked"), "").unwrap();
+        let blocked = ToolQualificationLogger::new(dir.path().join("blocked/tool-usage.jsonl"));
+        let result = link_arm(&tc, &request().with_usage_log(blocked));
+        assert!(result
+            .diagnostics
+            .last()
+            .unwrap()
+            .message
+            .starts_with("Tool usage not logged"));
+    }
+
+    #[test]
+    fn test_link_inconsistent_objects() {
+        let dir = tempfile::tempdir().unwrap();
+        let tc = DetectedToolchain::new(
+            crate::ToolchainKind::ArmGcc,
+            PathBuf::from("/nonexistent/arm-none-eabi-gcc"),
+            "13.2.1".to_string(),
+        );
+        let mut objects = Vec::new();
+        for (name, compiler) in [("a.o", b"GCC: 13.2.1\0"), ("b.o", b"GCC: 12.3.1\0")] {
+            let path = dir.path().join(name);
+            std::fs::write(&path, build_test_elf(&[(".comment", 1, compiler)])).unwrap();
+            objects.push(path);
+        }
+        let log = ToolQualificationLogger::for_project(dir.path());
+        let mut request = request().with_usage_log(log.clone());
+        request.objects = objects;
+
+        let result = link_arm(&tc, &request);
+        assert!(!result.success());
+        assert_eq!(result.diagnostics.len(), 1);
+        assert_eq!(
+            result.diagnostics[0].code.as_deref(),
+            Some("prelink-mismatch")
+        );
+        assert!(result.stderr.contains("Compiler:"));
+        // The linker never ran
+        assert!(log.load().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_check_budget() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut data = crate::elf::build_test_elf(&[(".text", 1, &[0; 0x400])]);
+        let shoff = u32::from_le_bytes(data[32..36].try_into().unwrap()) as usize;
+        data[shoff + 48..shoff + 52].copy_from_slice(&0x6u32.to_le_bytes());
+        std::fs::write(dir.path().join("firmware.elf"), data).unwrap();
+        std::fs::write(
+            dir.path().join("app.ld"),
+            "MEMORY { FLASH (rx) : ORIGIN = 0x08000000, LENGTH = 1K }",
+        )
+        .unwrap();
+
+        let request = ArmLinkRequest::new(
+            Vec::new(),
+            PathBuf::from("firmware.elf"),
+            ArmMcuConfig::new("cortex-m4"),
+            LinkerConfig::new("app.ld"),
+        );
+        let mut result = LinkResult {
+            exit_code: 0,
+            stdout: String::new(),
+            stderr: String::new(),
+            duration_ms: 0,
+            timed_out: false,
+            diagnostics: Vec::new(),
+            output: request.output.clone(),
+            memory_map: None,
+            invocation: None,
+            budget_violations: Vec::new(),
+        };
+        check_budget(&request, &mut result, dir.path());
+        assert!(result.success());
+
+        let request = request
+            .with_budget(MemoryBudget::default().with_flash(crate::BudgetLimit::Percent(75.0)));
+        check_budget(&request, &mut result, dir.path());
+        assert!(!result.success());
+        assert_eq!(result.budget_violations[0].limit, 768);
+        assert_eq!(result.diagnostics.len(), 1);
+        assert_eq!(
+            result.diagnostics[0].code.as_deref(),
+            Some(crate::BUDGET_DIAGNOSTIC_CODE)
+        );
+    }
+
+    #[test]
+    fn test_dry_run_arm_link() {
+        let tc = DetectedToolchain::new(
+            crate::ToolchainKind::ArmGcc,
+            PathBuf::from("/opt/arm/bin/arm-none-eabi-gcc"),
+            "13.2.1".to_string(),
+        );
+        let argv = dry_run_arm_link(&tc, &request());
+        assert_eq!(argv[0], "/opt/arm/bin/arm-none-eabi-gcc");
+        assert_eq!(argv[1..], build_arm_link_command(&request())[..]);
+        assert_eq!(argv.last().unwrap(), "build/firmware.elf");
+    }
+}
@@ -0,0 +1,408 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! GNU ld linker script generation.
+//!
+//! Produces a baseline Cortex-M script from an MCU's flash/RAM layout: the
+//! vector table first in flash, code and read-only data after it, `.data`
+//! copied from flash to RAM, `.bss`, a reserved heap/stack block and
+//! `_estack` at the top of RAM. Symbol names follow the vendor startup
+//! files (`_sidata`, `_sdata`, `_sbss`, ...) so existing startup code links
+//! unchanged.
+
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use thiserror::Error;
+
+/// Linker script generation errors.
+#[derive(Debug, Error)]
+pub enum LinkerGenError {
+    #[error("Memory region {0} has zero size")]
+    EmptyRegion(String),
+
+    #[error("Memory region {0} is defined twice")]
+    DuplicateRegion(String),
+
+    #[error("Memory regions {0} and {1} overlap")]
+    Overlap(String, String),
+
+    #[error("Heap and stack need {needed} bytes but {region} has {available}")]
+    RamTooSmall {
+        region: String,
+        needed: u64,
+        available: u64,
+    },
+}
+
+/// A block of target memory.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemoryBlock {
+    /// Region name used in the script (e.g. "FLASH").
+    pub name: String,
+    /// Start address.
+    pub origin: u64,
+    /// Size in bytes.
+    pub size: u64,
+    /// Attribute string (e.g. "rx").
+    pub attributes: String,
+}
+
+impl MemoryBlock {
+    /// Create a memory block.
+    pub fn new(
+        name: impl Into<String>,
+        origin: u64,
+        size: u64,
+        attributes: impl Into<String>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            origin,
+            size,
+            attributes: attributes.into(),
+        }
+    }
+
+    fn end(&self) -> u64 {
+        self.origin.saturating_add(self.size)
+    }
+}
+
+/// Flash/RAM layout of an MCU.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct McuMemory {
+    /// Code flash holding the vector table, code and initialized data.
+    pub flash: MemoryBlock,
+    /// Main RAM holding data, bss, heap and stack.
+    pub ram: MemoryBlock,
+    /// Additional regions (e.g. CCMRAM), declared but not used by default.
+    pub extra: Vec<MemoryBlock>,
+}
+
+impl McuMemory {
+    /// Create a layout with a single `FLASH` and `RAM` region.
+    pub fn new(flash_origin: u64, flash_size: u64, ram_origin: u64, ram_size: u64) -> Self {
+        Self {
+            flash: MemoryBlock::new("FLASH", flash_origin, flash_size, "rx"),
+            ram: MemoryBlock::new("RAM", ram_origin, ram_size, "xrw"),
+            extra: Vec::new(),
+        }
+    }
+
+    /// Add an extra memory region.
+    pub fn with_region(mut self, region: MemoryBlock) -> Self {
+        self.extra.push(region);
+        self
+    }
+
+    /// All regions, flash and RAM first.
+    pub fn regions(&self) -> impl Iterator<Item = &MemoryBlock> {
+        [&self.flash, &self.ram].into_iter().chain(&self.extra)
+    }
+}
+
+/// Options for the generated script.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LinkerScriptOptions {
+    /// Entry symbol.
+    pub entry: String,
+    /// Minimum stack size in bytes.
+    pub stack_size: u64,
+    /// Minimum heap size in bytes.
+    pub heap_size: u64,
+    /// Comment placed at the top of the script (e.g. the part number).
+    pub header: Option<String>,
+}
+
+impl Default for LinkerScriptOptions {
+    fn default() -> Self {
+        Self {
+            entry: "Reset_Handler".to_string(),
+            stack_size: 0x400,
+            heap_size: 0x200,
+            header: None,
+        }
+    }
+}
+
+/// Generate a GNU ld script for an MCU memory layout.
+pub fn generate_linker_script(
+    memory: &McuMemory,
+    options: &LinkerScriptOptions,
+) -> Result<String, LinkerGenError> {
+    check_layout(memory, options)?;
+
+    let flash = &memory.flash.name;
+    let ram = &memory.ram.name;
+    let mut out = String::new();
+
+    if let Some(header) = &options.header {
+        let _ = writeln!(out, "/* {} */\n", header.replace("*/", "* /"));
+    }
+
+    let _ = writeln!(out, "ENTRY({})\n", options.entry);
+    let _ = writeln!(out, "_Min_Heap_Size = {:#x};", options.heap_size);
+    let _ = writeln!(out, "_Min_Stack_Size = {:#x};\n", options.stack_size);
+
+    let width = memory
+        .regions()
+        .map(|r| r.name.len() + r.attributes.len() + 3)
+        .max()
+        .unwrap_or(0);
+    out.push_str("MEMORY\n{\n");
+    for region in memory.regions() {
+        let label = format!("{} ({})", region.name, region.attributes);
+        let _ = writeln!(
+            out,
+            "  {:<width$} : ORIGIN = {:#010x}, LENGTH = {}",
+            label,
+            region.origin,
+            format_size(region.size),
+        );
+    }
+    out.push_str("}\n\n");
+    let _ = writeln!(out, "_estack = ORIGIN({ram}) + LENGTH({ram});\n");
+
+    let _ = write!(
+        out,
+        "\
+SECTIONS
+{{
+  .isr_vector :
+  {{
+    . = ALIGN(4);
+    KEEP(*(.isr_vector))
+    . = ALIGN(4);
+  }} >{flash}
+
+  .text :
+  {{
+    . = ALIGN(4);
+    *(.text)
+    *(.text*)
+    *(.glue_7)
+    *(.glue_7t)
+    *(.eh_frame)
+    KEEP(*(.init))
+    KEEP(*(.fini))
+    . = ALIGN(4);
+    _etext = .;
+  }} >{flash}
+
+  .rodata :
+  {{
+    . = ALIGN(4);
+    *(.rodata)
+    *(.rodata*)
+    . = ALIGN(4);
+  }} >{flash}
+
+  .ARM.extab :
+  {{
+    *(.ARM.extab* .gnu.linkonce.armextab.*)
+  }} >{flash}
+
+  .ARM :
+  {{
+    __exidx_start = .;
+    *(.ARM.exidx*)
+    __exidx_end = .;
+  }} >{flash}
+
+  .preinit_array :
+  {{
+    PROVIDE_HIDDEN(__preinit_array_start = .);
+    KEEP(*(.preinit_array*))
+    PROVIDE_HIDDEN(__preinit_array_end = .);
+  }} >{flash}
+
+  .init_array :
+  {{
+    PROVIDE_HIDDEN(__init_array_start = .);
+    KEEP(*(SORT(.init_array.*)))
+    KEEP(*(.init_array*))
+    PROVIDE_HIDDEN(__init_array_end = .);
+  }} >{flash}
+
+  .fini_array :
+  {{
+    PROVIDE_HIDDEN(__fini_array_start = .);
+    KEEP(*(SORT(.fini_array.*)))
+    KEEP(*(.fini_array*))
+    PROVIDE_HIDDEN(__fini_array_end = .);
+  }} >{flash}
+
+  _sidata = LOADADDR(.data);
+
+  .data :
+  {{
+    . = ALIGN(4);
+    _sdata = .;
+    *(.data)
+    *(.data*)
+    . = ALIGN(4);
+    _edata = .;
+  }} >{ram} AT> {flash}
+
+  .bss (NOLOAD) :
+  {{
+    . = ALIGN(4);
+    _sbss = .;
+    __bss_start__ = _sbss;
+    *(.bss)
+    *(.bss*)
+    *(COMMON)
+    . = ALIGN(4);
+    _ebss = .;
+    __bss_end__ = _ebss;
+  }} >{ram}
+
+  ._user_heap_stack (NOLOAD) :
+  {{
+    . = ALIGN(8);
+    PROVIDE(end = .);
+    PROVIDE(_end = .);
+    . = . + _Min_Heap_Size;
+    . = . + _Min_Stack_Size;
+    . = ALIGN(8);
+  }} >{ram}
+
+  .ARM.attributes 0 :
+  {{
+    *(.ARM.attributes)
+  }}
+}}
+"
+    );
+
+    Ok(out)
+}
+
+/// Reject layouts that cannot produce a working script.
+fn check_layout(memory: &McuMemory, options: &LinkerScriptOptions) -> Result<(), LinkerGenError> {
+    let regions: Vec<&MemoryBlock> = memory.regions().collect();
+
+    for (i, region) in regions.iter().enumerate() {
+        if region.size == 0 {
+            return Err(LinkerGenError::EmptyRegion(region.name.clone()));
+        }
+        for other in &regions[..i] {
+            if other.name == region.name {
+                return Err(LinkerGenError::DuplicateRegion(region.name.clone()));
+            }
+            if region.origin < other.end() && other.origin < region.end() {
+                return Err(LinkerGenError::Overlap(
+                    other.name.clone(),
+                    region.name.clone(),
+                ));
+            }
+        }
+    }
+
+    let needed = options.stack_size + options.heap_size;
+    if needed > memory.ram.size {
+        return Err(LinkerGenError::RamTooSmall {
+            region: memory.ram.name.clone(),
+            needed,
+            available: memory.ram.size,
+        });
+    }
+
+    Ok(())
+}
+
+/// Format a size as `K`/`M` when it divides evenly, otherwise hex.
+fn format_size(size: u64) -> String {
+    const K: u64 = 1024;
+    if size.is_multiple_of(K * K) {
+        format!("{}M", size / (K * K))
+    } else if size.is_multiple_of(K) {
+        format!("{}K", size / K)
+    } else {
+        format!("{:#x}", size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_linker_script;
+    use std::path::Path;
+
+    fn stm32f407() -> McuMemory {
+        McuMemory::new(0x0800_0000, 1024 * 1024, 0x2000_0000, 128 * 1024)
+            .with_region(MemoryBlock::new("CCMRAM", 0x1000_0000, 64 * 1024, "xrw"))
+    }
+
+    #[test]
+    fn test_generated_script_parses() {
+        let options = LinkerScriptOptions {
+            header: Some("STM32F407VG".to_string()),
+            ..Default::default()
+        };
+        let text = generate_linker_script(&stm32f407(), &options).unwrap();
+        assert!(text.starts_with("/* STM32F407VG */"));
+
+        let script = parse_linker_script(&text).unwrap();
+        assert!(script.validate(Path::new("gen.ld")).is_empty());
+        assert_eq!(script.entry.as_deref(), Some("Reset_Handler"));
+
+        let flash = script.region("FLASH").unwrap();
+        assert_eq!((flash.origin, flash.length), (0x0800_0000, 1024 * 1024));
+        assert_eq!(flash.attributes, "rx");
+        assert_eq!(script.region("CCMRAM").unwrap().length, 64 * 1024);
+
+        assert_eq!(script.sections[0].name, ".isr_vector");
+        assert_eq!(script.sections[0].region.as_deref(), Some("FLASH"));
+
+        let data = script.section(".data").unwrap();
+        assert_eq!(data.region.as_deref(), Some("RAM"));
+        assert_eq!(data.load_region.as_deref(), Some("FLASH"));
+        assert!(script.section(".bss").unwrap().noload);
+
+        assert_eq!(
+            script.assignment("_estack").unwrap().value,
+            Some(0x2002_0000)
+        );
+        assert_eq!(
+            script.assignment("_Min_Stack_Size").unwrap().value,
+            Some(0x400)
+        );
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(2 * 1024 * 1024), "2M");
+        assert_eq!(format_size(96 * 1024), "96K");
+        assert_eq!(format_size(0x180), "0x180");
+    }
+
+    #[test]
+    fn test_rejects_bad_layouts() {
+        let options = LinkerScriptOptions::default();
+
+        let empty = McuMemory::new(0x0800_0000, 0, 0x2000_0000, 0x5000);
+        assert!(matches!(
+            generate_linker_script(&empty, &options),
+            Err(LinkerGenError::EmptyRegion(name)) if name == "FLASH"
+        ));
+
+        let overlap = McuMemory::new(0x0800_0000, 0x10000, 0x0800_8000, 0x5000);
+        assert!(matches!(
+            generate_linker_script(&overlap, &options),
+            Err(LinkerGenError::Overlap(_, _))
+        ));
+
+        let tiny = McuMemory::new(0x0800_0000, 0x10000, 0x2000_0000, 0x400);
+        assert!(matches!(
+            generate_linker_script(&tiny, &options),
+            Err(LinkerGenError::RamTooSmall { needed: 0x600, .. })
+        ));
+
+        let duplicate = stm32f407().with_region(MemoryBlock::new("RAM", 0x3000_0000, 0x100, "rw"));
+        assert!(matches!(
+            generate_linker_script(&duplicate, &options),
+            Err(LinkerGenError::DuplicateRegion(name)) if name == "RAM"
+        ));
+    }
+}
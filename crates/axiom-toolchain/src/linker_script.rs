@@ -0,0 +1,1070 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! GNU ld linker script parsing.
+//!
+//! Extracts the MEMORY regions, output section placements and symbol
+//! assignments of a script. Constant expressions (numbers with `K`/`M`/`G`
+//! suffixes, arithmetic, `ORIGIN()`/`LENGTH()` and earlier symbols) are
+//! evaluated; anything depending on the location counter is kept as text.
+
+use crate::{ElfFile, MemoryMap, MemoryRegion, SHF_ALLOC, SHT_NOBITS};
+use axiom_core::{Diagnostic, Location, Position, Range};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use thiserror::Error;
+
+/// Linker script errors.
+#[derive(Debug, Error)]
+pub enum LinkerScriptError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Line {line}: {message}")]
+    Syntax { line: u32, message: String },
+}
+
+/// An output section statement.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SectionPlacement {
+    /// Output section name (e.g. ".text", "/DISCARD/").
+    pub name: String,
+    /// Explicit address, when given as a constant.
+    pub address: Option<u64>,
+    /// Run-time region (`> REGION`).
+    pub region: Option<String>,
+    /// Load region (`AT> REGION`).
+    pub load_region: Option<String>,
+    /// Declared `(NOLOAD)`.
+    pub noload: bool,
+    /// Input section descriptions, e.g. `KEEP(*(.isr_vector))`.
+    pub inputs: Vec<String>,
+    /// 1-based line of the statement.
+    pub line: u32,
+}
+
+impl SectionPlacement {
+    /// Whether this is the `/DISCARD/` pseudo-section.
+    pub fn is_discard(&self) -> bool {
+        self.name == "/DISCARD/"
+    }
+}
+
+/// A symbol assignment.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScriptAssignment {
+    /// Symbol name.
+    pub name: String,
+    /// Expression text.
+    pub expression: String,
+    /// Value, when the expression is constant.
+    pub value: Option<u64>,
+    /// Output section the assignment appears in.
+    pub section: Option<String>,
+    /// Wrapped in `PROVIDE`/`PROVIDE_HIDDEN`.
+    pub provide: bool,
+    /// 1-based line of the statement.
+    pub line: u32,
+}
+
+/// A parsed linker script.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LinkerScript {
+    /// Entry symbol (`ENTRY(...)`).
+    pub entry: Option<String>,
+    /// MEMORY regions in declaration order, with `used` zero.
+    pub memory: Vec<MemoryRegion>,
+    /// 1-based line of the MEMORY command.
+    pub memory_line: u32,
+    /// Output sections in declaration order.
+    pub sections: Vec<SectionPlacement>,
+    /// Symbol assignments, top-level and inside sections.
+    pub assignments: Vec<ScriptAssignment>,
+    /// Files named by `INCLUDE`.
+    pub includes: Vec<String>,
+}
+
+impl LinkerScript {
+    /// Find a region by name.
+    pub fn region(&self, name: &str) -> Option<&MemoryRegion> {
+        self.memory.iter().find(|r| r.name == name)
+    }
+
+    /// Find an output section by name.
+    pub fn section(&self, name: &str) -> Option<&SectionPlacement> {
+        self.sections.iter().find(|s| s.name == name)
+    }
+
+    /// Find an assignment by symbol name.
+    pub fn assignment(&self, name: &str) -> Option<&ScriptAssignment> {
+        self.assignments.iter().find(|a| a.name == name)
+    }
+
+    /// Check region references and region layout.
+    pub fn validate(&self, path: &Path) -> Vec<Diagnostic> {
+        let at = |line: u32| {
+            let pos = Position::new(line.saturating_sub(1), 0);
+            Location::new(path.to_path_buf(), Range::new(pos, pos))
+        };
+        let mut diagnostics = Vec::new();
+
+        for (i, region) in self.memory.iter().enumerate() {
+            if self.memory[..i].iter().any(|r| r.name == region.name) {
+                diagnostics.push(
+                    Diagnostic::error(format!("Memory region {} is defined twice", region.name))
+                        .with_location(at(self.memory_line)),
+                );
+            }
+            if region.length == 0 {
+                diagnostics.push(
+                    Diagnostic::warning(format!("Memory region {} has zero length", region.name))
+                        .with_location(at(self.memory_line)),
+                );
+            }
+            for other in &self.memory[..i] {
+                let overlaps = region.origin < other.origin.saturating_add(other.length)
+                    && other.origin < region.origin.saturating_add(region.length);
+                if overlaps && region.length > 0 && other.length > 0 {
+                    diagnostics.push(
+                        Diagnostic::warning(format!(
+                            "Memory regions {} and {} overlap",
+                            other.name, region.name
+                        ))
+                        .with_location(at(self.memory_line)),
+                    );
+                }
+            }
+        }
+
+        for section in &self.sections {
+            let references = [
+                ("region", &section.region),
+                ("load region", &section.load_region),
+            ];
+            for (what, name) in references {
+                if let Some(name) = name {
+                    if self.region(name).is_none() {
+                        diagnostics.push(
+                            Diagnostic::error(format!(
+                                "Section {} is placed in undefined {} {}",
+                                section.name, what, name
+                            ))
+                            .with_location(at(section.line)),
+                        );
+                    }
+                }
+            }
+        }
+        diagnostics
+    }
+
+    /// Regions with usage taken from a linker map.
+    ///
+    /// Each allocated output section counts against the region holding its
+    /// run-time address and, if loaded elsewhere, its load address.
+    pub fn usage_from_map(&self, map: &MemoryMap) -> Vec<MemoryRegion> {
+        self.usage(map.sections.iter().filter(|s| s.is_allocated()).map(|s| {
+            let load = s.load_address.filter(|&lma| lma != s.address);
+            (s.address, load, s.size)
+        }))
+    }
+
+    /// Regions with usage taken from the allocated sections of an ELF.
+    pub fn usage_from_elf(&self, elf: &ElfFile) -> Vec<MemoryRegion> {
+        self.usage(
+            elf.sections
+                .iter()
+                .filter(|s| s.flags & SHF_ALLOC != 0)
+                .map(|s| {
+                    let lma = elf.load_address(s);
+                    let load = (s.section_type != SHT_NOBITS && lma != s.addr).then_some(lma);
+                    (s.addr, load, s.size)
+                }),
+        )
+    }
+
+    fn usage(&self, sections: impl Iterator<Item = (u64, Option<u64>, u64)>) -> Vec<MemoryRegion> {
+        let mut regions = self.memory.clone();
+        for (address, load, size) in sections {
+            for addr in std::iter::once(address).chain(load) {
+                if let Some(region) = regions.iter_mut().find(|r| r.contains(addr)) {
+                    region.used += size;
+                }
+            }
+        }
+        regions
+    }
+}
+
+/// Read and parse a linker script.
+pub fn read_linker_script(path: &Path) -> Result<LinkerScript, LinkerScriptError> {
+    parse_linker_script(&std::fs::read_to_string(path)?)
+}
+
+/// Parse linker script text.
+pub fn parse_linker_script(content: &str) -> Result<LinkerScript, LinkerScriptError> {
+    let text = strip_comments(content);
+    let mut parser = Parser {
+        chars: text.chars().collect(),
+        pos: 0,
+        line: 1,
+        script: LinkerScript::default(),
+        symbols: HashMap::new(),
+    };
+    parser.parse_top()?;
+    Ok(parser.script)
+}
+
+/// Replace comments with spaces, keeping newlines so line numbers hold.
+fn strip_comments(content: &str) -> String {
+    let mut out = String::with_capacity(content.len());
+    let mut chars = content.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '/' && chars.peek() == Some(&'*') {
+            chars.next();
+            let mut prev = ' ';
+            for c in chars.by_ref() {
+                if c == '\n' {
+                    out.push('\n');
+                }
+                if prev == '*' && c == '/' {
+                    break;
+                }
+                prev = c;
+            }
+            out.push(' ');
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: u32,
+    script: LinkerScript,
+    symbols: HashMap<String, u64>,
+}
+
+impl Parser {
+    fn error(&self, message: impl Into<String>) -> LinkerScriptError {
+        LinkerScriptError::Syntax {
+            line: self.line,
+            message: message.into(),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn skip_ws(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.bump();
+        }
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_ws();
+        if self.peek() == Some(c) {
+            self.bump();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), LinkerScriptError> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(self.error(format!("expected '{}'", c)))
+        }
+    }
+
+    /// A name: anything up to whitespace or punctuation that ends a name.
+    fn name(&mut self) -> String {
+        self.skip_ws();
+        let mut name = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() || "{}()[]:;,=<>!&|+".contains(c) {
+                break;
+            }
+            name.push(c);
+            self.bump();
+        }
+        name
+    }
+
+    /// Text up to `stop` at nesting depth zero; `stop` is consumed.
+    fn until(&mut self, stop: &[char]) -> Result<(String, char), LinkerScriptError> {
+        let mut text = String::new();
+        let mut depth = 0usize;
+        while let Some(c) = self.bump() {
+            if depth == 0 && stop.contains(&c) {
+                return Ok((text, c));
+            }
+            match c {
+                '(' | '{' => depth += 1,
+                ')' | '}' => {
+                    depth = depth
+                        .checked_sub(1)
+                        .ok_or_else(|| self.error(format!("unbalanced '{}'", c)))?;
+                }
+                _ => {}
+            }
+            text.push(c);
+        }
+        Err(self.error("unexpected end of script"))
+    }
+
+    fn parse_top(&mut self) -> Result<(), LinkerScriptError> {
+        loop {
+            self.skip_ws();
+            if self.peek().is_none() {
+                return Ok(());
+            }
+            if self.eat(';') {
+                continue;
+            }
+            let line = self.line;
+            let start = self.pos;
+            let word = self.name();
+            match word.as_str() {
+                "MEMORY" => {
+                    self.script.memory_line = line;
+                    self.expect('{')?;
+                    self.parse_memory()?;
+                }
+                "SECTIONS" => {
+                    self.expect('{')?;
+                    self.parse_sections()?;
+                }
+                "INCLUDE" => {
+                    let file = self.name();
+                    self.script.includes.push(file);
+                }
+                "" => return Err(self.error("unexpected character")),
+                _ if self.eat('(') => {
+                    let (arg, _) = self.until(&[')'])?;
+                    match word.as_str() {
+                        "ENTRY" => self.script.entry = Some(arg.trim().to_string()),
+                        "PROVIDE" | "PROVIDE_HIDDEN" => self.assignment(&arg, None, true, line),
+                        _ => {}
+                    }
+                }
+                _ => {
+                    self.pos = start;
+                    let (statement, _) = self.until(&[';'])?;
+                    self.assignment(&statement, None, false, line);
+                }
+            }
+        }
+    }
+
+    fn parse_memory(&mut self) -> Result<(), LinkerScriptError> {
+        loop {
+            if self.eat('}') {
+                return Ok(());
+            }
+            let name = self.name();
+            if name.is_empty() {
+                return Err(self.error("expected memory region name"));
+            }
+            let mut attributes = String::new();
+            if self.eat('(') {
+                attributes = self.until(&[')'])?.0.trim().to_string();
+            }
+            self.expect(':')?;
+            let origin = self.memory_value(&["ORIGIN", "org", "o"], &name)?;
+            self.expect(',')?;
+            let length = self.memory_value(&["LENGTH", "len", "l"], &name)?;
+            self.script.memory.push(MemoryRegion {
+                name,
+                origin,
+                length,
+                attributes,
+                used: 0,
+            });
+        }
+    }
+
+    /// `KEY = expr`, where the expression ends at a comma, line end or `}`.
+    fn memory_value(&mut self, keys: &[&str], region: &str) -> Result<u64, LinkerScriptError> {
+        let key = self.name();
+        if !keys.contains(&key.as_str()) {
+            return Err(self.error(format!("expected {} for region {}", keys[0], region)));
+        }
+        self.expect('=')?;
+        self.skip_ws();
+        let mut text = String::new();
+        let mut depth = 0usize;
+        while let Some(c) = self.peek() {
+            if depth == 0 && (c == ',' || c == '\n' || c == '}') {
+                break;
+            }
+            match c {
+                '(' => depth += 1,
+                ')' => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            text.push(c);
+            self.bump();
+        }
+        let memory = &self.script.memory;
+        let symbols = &self.symbols;
+        eval(text.trim(), memory, symbols).ok_or_else(|| {
+            self.error(format!(
+                "cannot evaluate {} of region {}: {}",
+                keys[0],
+                region,
+                text.trim()
+            ))
+        })
+    }
+
+    fn parse_sections(&mut self) -> Result<(), LinkerScriptError> {
+        loop {
+            if self.eat('}') {
+                return Ok(());
+            }
+            if self.eat(';') {
+                continue;
+            }
+            if self.peek().is_none() {
+                return Err(self.error("unterminated SECTIONS"));
+            }
+            let line = self.line;
+            let (head, stop) = self.until(&[';', '{'])?;
+            if stop == ';' {
+                self.statement(head.trim(), None, line);
+                continue;
+            }
+            let placement = self.output_section(head.trim(), line)?;
+            self.script.sections.push(placement);
+        }
+    }
+
+    fn output_section(
+        &mut self,
+        head: &str,
+        line: u32,
+    ) -> Result<SectionPlacement, LinkerScriptError> {
+        let (before, _after) =
+            split_top_level(head, ':').ok_or_else(|| LinkerScriptError::Syntax {
+                line,
+                message: format!("expected ':' in output section '{}'", head),
+            })?;
+        let mut words = before.trim().splitn(2, char::is_whitespace);
+        let name = words.next().unwrap_or_default().to_string();
+        let mut rest = words.next().unwrap_or_default().trim().to_string();
+        let noload = rest.contains("(NOLOAD)");
+        rest = rest.replace("(NOLOAD)", "");
+        let address = match rest.trim() {
+            "" => None,
+            expr => eval(expr, &self.script.memory, &self.symbols),
+        };
+
+        let (body, _) = self.until(&['}'])?;
+        let mut placement = SectionPlacement {
+            name,
+            address,
+            region: None,
+            load_region: None,
+            noload,
+            inputs: Vec::new(),
+            line,
+        };
+        for piece in split_statements(&body) {
+            self.statement(piece.trim(), Some(&mut placement), line);
+        }
+
+        // Trailer: > REGION, AT> REGION, :PHDR, =FILL
+        loop {
+            self.skip_ws();
+            let rest: String = self.chars[self.pos..].iter().take(3).collect();
+            if rest.starts_with('>') {
+                self.bump();
+                placement.region = Some(self.name());
+            } else if rest.starts_with("AT") && rest[2..].trim_start().starts_with('>')
+                || rest == "AT " && self.after_ws_is('>')
+            {
+                self.bump();
+                self.bump();
+                self.expect('>')?;
+                placement.load_region = Some(self.name());
+            } else if rest.starts_with(':') || rest.starts_with('=') {
+                self.bump();
+                self.name();
+            } else {
+                break;
+            }
+        }
+        Ok(placement)
+    }
+
+    /// Whether the next non-space character after "AT" is `c`.
+    fn after_ws_is(&self, c: char) -> bool {
+        self.chars[self.pos + 2..]
+            .iter()
+            .find(|ch| !ch.is_whitespace())
+            .is_some_and(|&ch| ch == c)
+    }
+
+    /// A statement inside SECTIONS or an output section body.
+    fn statement(&mut self, text: &str, mut section: Option<&mut SectionPlacement>, line: u32) {
+        let section_name = section.as_ref().map(|s| s.name.clone());
+        for item in split_items(text) {
+            let head = item.split('(').next().unwrap_or_default().trim();
+            if matches!(head, "PROVIDE" | "PROVIDE_HIDDEN") {
+                let inner = item[head.len()..]
+                    .trim()
+                    .trim_start_matches('(')
+                    .trim_end_matches(')');
+                self.assignment(inner, section_name.clone(), true, line);
+            } else if split_top_level(&item, '=').is_some() {
+                self.assignment(&item, section_name.clone(), false, line);
+            } else if item.contains('(') && !is_command(head) {
+                if let Some(ref mut section) = section {
+                    section
+                        .inputs
+                        .push(item.split_whitespace().collect::<Vec<_>>().join(" "));
+                }
+            }
+        }
+    }
+
+    fn assignment(&mut self, text: &str, section: Option<String>, provide: bool, line: u32) {
+        let Some((lhs, rhs)) = split_top_level(text, '=') else {
+            return;
+        };
+        let name = lhs
+            .trim()
+            .trim_end_matches(['+', '-', '*', '/', '|', '&'])
+            .trim();
+        if name.is_empty() || name == "." {
+            return;
+        }
+        let expression = rhs.trim().to_string();
+        let value = eval(&expression, &self.script.memory, &self.symbols);
+        if let Some(value) = value {
+            self.symbols.insert(name.to_string(), value);
+        }
+        self.script.assignments.push(ScriptAssignment {
+            name: name.to_string(),
+            expression,
+            value,
+            section,
+            provide,
+            line,
+        });
+    }
+}
+
+/// Commands that look like input descriptions but are not.
+fn is_command(head: &str) -> bool {
+    matches!(
+        head,
+        "ASSERT" | "FILL" | "BYTE" | "SHORT" | "LONG" | "QUAD" | "SQUAD" | "CREATE_OBJECT_SYMBOLS"
+    )
+}
+
+/// Split at the first `sep` outside parentheses, ignoring `==`, `<=`, `>=`, `!=`.
+fn split_top_level(text: &str, sep: char) -> Option<(&str, &str)> {
+    let mut depth = 0usize;
+    let bytes: Vec<(usize, char)> = text.char_indices().collect();
+    for (i, &(offset, c)) in bytes.iter().enumerate() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            _ if c == sep && depth == 0 => {
+                if sep == '=' {
+                    let prev = i.checked_sub(1).map(|j| bytes[j].1);
+                    let next = bytes.get(i + 1).map(|b| b.1);
+                    if next == Some('=') || matches!(prev, Some('=' | '<' | '>' | '!')) {
+                        continue;
+                    }
+                }
+                return Some((&text[..offset], &text[offset + c.len_utf8()..]));
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Split a section body into `;`-terminated statements.
+fn split_statements(body: &str) -> Vec<String> {
+    let mut pieces = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0usize;
+    for c in body.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ';' if depth == 0 => {
+                pieces.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    pieces.push(current);
+    pieces.retain(|p| !p.trim().is_empty());
+    pieces
+}
+
+/// Split a statement into input descriptions and a trailing assignment.
+///
+/// `*(.text*) _etext = .` gives `["*(.text*)", "_etext = ."]`.
+fn split_items(text: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0usize;
+    let chars: Vec<char> = text.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+        if depth == 0 && c.is_whitespace() {
+            // An assignment swallows the rest of the statement
+            let rest: String = chars[i..].iter().collect();
+            let rest = rest.trim_start();
+            let operator = rest.starts_with('=') && !rest.starts_with("==")
+                || ["+=", "-=", "*=", "/=", "|=", "&="]
+                    .iter()
+                    .any(|op| rest.starts_with(op));
+            if operator {
+                current.push(' ');
+                current.push_str(rest);
+                break;
+            }
+            if rest.starts_with('(') {
+                // `KEEP (*(.init))` is one description
+                if !current.ends_with(' ') {
+                    current.push(' ');
+                }
+            } else if !current.trim().is_empty() {
+                items.push(std::mem::take(&mut current));
+            }
+        } else {
+            current.push(c);
+        }
+        i += 1;
+    }
+    if !current.trim().is_empty() {
+        items.push(current);
+    }
+    items.into_iter().map(|s| s.trim().to_string()).collect()
+}
+
+/// Evaluate a constant expression.
+fn eval(text: &str, memory: &[MemoryRegion], symbols: &HashMap<String, u64>) -> Option<u64> {
+    let mut eval = Eval {
+        tokens: tokenize(text)?,
+        pos: 0,
+        memory,
+        symbols,
+    };
+    let value = eval.binary(0)?;
+    (eval.pos == eval.tokens.len()).then_some(value)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(u64),
+    Name(String),
+    Op(&'static str),
+}
+
+const OPERATORS: &[&str] = &[
+    "<<", ">>", "(", ")", ",", "+", "-", "*", "/", "%", "&", "|", "~", "!",
+];
+
+fn tokenize(text: &str) -> Option<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut rest = text.trim();
+    while !rest.is_empty() {
+        if let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(**op)) {
+            tokens.push(Token::Op(op));
+            rest = rest[op.len()..].trim_start();
+            continue;
+        }
+        let end = rest
+            .find(|c: char| c.is_whitespace() || OPERATORS.iter().any(|op| op.starts_with(c)))
+            .unwrap_or(rest.len());
+        if end == 0 {
+            return None;
+        }
+        let word = &rest[..end];
+        tokens.push(if word.starts_with(|c: char| c.is_ascii_digit()) {
+            Token::Number(parse_number(word)?)
+        } else {
+            Token::Name(word.to_string())
+        });
+        rest = rest[end..].trim_start();
+    }
+    Some(tokens)
+}
+
+/// Parse `0x8000000`, `512K`, `1M`, `077` (octal) or decimal.
+fn parse_number(word: &str) -> Option<u64> {
+    let (digits, scale) = match word.as_bytes().last()? {
+        b'K' | b'k' => (&word[..word.len() - 1], 1024),
+        b'M' | b'm' => (&word[..word.len() - 1], 1024 * 1024),
+        b'G' | b'g' => (&word[..word.len() - 1], 1024 * 1024 * 1024),
+        _ => (word, 1),
+    };
+    let value = if let Some(hex) = digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        u64::from_str_radix(hex, 16).ok()?
+    } else if digits.len() > 1 && digits.starts_with('0') {
+        u64::from_str_radix(&digits[1..], 8).ok()?
+    } else {
+        digits.parse().ok()?
+    };
+    value.checked_mul(scale)
+}
+
+struct Eval<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    memory: &'a [MemoryRegion],
+    symbols: &'a HashMap<String, u64>,
+}
+
+impl Eval<'_> {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn peek_op(&self) -> Option<&'static str> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Op(op)) => Some(op),
+            _ => None,
+        }
+    }
+
+    /// Precedence climbing over binary operators.
+    fn binary(&mut self, min: u8) -> Option<u64> {
+        let mut lhs = self.unary()?;
+        while let Some(op) = self.peek_op() {
+            let prec = match op {
+                "|" => 1,
+                "&" => 2,
+                "<<" | ">>" => 3,
+                "+" | "-" => 4,
+                "*" | "/" | "%" => 5,
+                _ => break,
+            };
+            if prec < min {
+                break;
+            }
+            self.pos += 1;
+            let rhs = self.binary(prec + 1)?;
+            lhs = match op {
+                "|" => lhs | rhs,
+                "&" => lhs & rhs,
+                "<<" => lhs.checked_shl(u32::try_from(rhs).ok()?)?,
+                ">>" => lhs.checked_shr(u32::try_from(rhs).ok()?)?,
+                "+" => lhs.wrapping_add(rhs),
+                "-" => lhs.wrapping_sub(rhs),
+                "*" => lhs.wrapping_mul(rhs),
+                "/" => lhs.checked_div(rhs)?,
+                _ => lhs.checked_rem(rhs)?,
+            };
+        }
+        Some(lhs)
+    }
+
+    fn unary(&mut self) -> Option<u64> {
+        match self.next()? {
+            Token::Number(n) => Some(n),
+            Token::Op("-") => Some(self.unary()?.wrapping_neg()),
+            Token::Op("~") => Some(!self.unary()?),
+            Token::Op("!") => Some(u64::from(self.unary()? == 0)),
+            Token::Op("(") => {
+                let value = self.binary(0)?;
+                (self.next()? == Token::Op(")")).then_some(value)
+            }
+            Token::Name(name) if self.peek_op() == Some("(") => {
+                self.pos += 1;
+                let value = self.call(&name)?;
+                (self.next()? == Token::Op(")")).then_some(value)
+            }
+            Token::Name(name) => self.symbols.get(&name).copied(),
+            Token::Op(_) => None,
+        }
+    }
+
+    fn call(&mut self, function: &str) -> Option<u64> {
+        match function {
+            "ORIGIN" | "LENGTH" => {
+                let Token::Name(region) = self.next()? else {
+                    return None;
+                };
+                let region = self.memory.iter().find(|r| r.name == region)?;
+                Some(if function == "ORIGIN" {
+                    region.origin
+                } else {
+                    region.length
+                })
+            }
+            "MAX" | "MIN" => {
+                let a = self.binary(0)?;
+                (self.next()? == Token::Op(",")).then_some(())?;
+                let b = self.binary(0)?;
+                Some(if function == "MAX" {
+                    a.max(b)
+                } else {
+                    a.min(b)
+                })
+            }
+            "ABSOLUTE" => self.binary(0),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCRIPT: &str = r#"
+/* STM32F407VG */
+ENTRY(Reset_Handler)
+
+_Min_Heap_Size = 0x200;
+_Min_Stack_Size = 0x400; /* required stack */
+
+MEMORY
+{
+  CCMRAM (xrw) : ORIGIN = 0x10000000, LENGTH = 64K
+  RAM    (xrw) : ORIGIN = 0x20000000, LENGTH = 128K
+  FLASH  (rx)  : ORIGIN = 0x8000000,  LENGTH = 1024K
+}
+
+_estack = ORIGIN(RAM) + LENGTH(RAM);
+
+SECTIONS
+{
+  .isr_vector :
+  {
+    . = ALIGN(4);
+    KEEP(*(.isr_vector))
+    . = ALIGN(4);
+  } >FLASH
+
+  .text :
+  {
+    *(.text)
+    *(.text*)
+    KEEP (*(.init))
+    _etext = .;
+  } >FLASH
+
+  _sidata = LOADADDR(.data);
+
+  .data :
+  {
+    _sdata = .;
+    *(.data) *(.data*)
+    _edata = .;
+  } >RAM AT> FLASH
+
+  .bss (NOLOAD) :
+  {
+    _sbss = .;
+    *(.bss*)
+    *(COMMON)
+  } >RAM
+
+  ._user_heap_stack :
+  {
+    PROVIDE ( end = . );
+    . = . + _Min_Heap_Size;
+  } >RAM
+
+  /DISCARD/ :
+  {
+    libc.a ( * )
+  }
+}
+"#;
+
+    #[test]
+    fn test_parse_memory() {
+        let script = parse_linker_script(SCRIPT).unwrap();
+        assert_eq!(script.entry.as_deref(), Some("Reset_Handler"));
+        assert_eq!(script.memory.len(), 3);
+        let flash = script.region("FLASH").unwrap();
+        assert_eq!(flash.origin, 0x0800_0000);
+        assert_eq!(flash.length, 1024 * 1024);
+        assert_eq!(flash.attributes, "rx");
+        assert_eq!(script.memory_line, 8);
+    }
+
+    #[test]
+    fn test_parse_sections() {
+        let script = parse_linker_script(SCRIPT).unwrap();
+        let names: Vec<_> = script.sections.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                ".isr_vector",
+                ".text",
+                ".data",
+                ".bss",
+                "._user_heap_stack",
+                "/DISCARD/"
+            ]
+        );
+
+        let text = script.section(".text").unwrap();
+        assert_eq!(text.region.as_deref(), Some("FLASH"));
+        assert_eq!(
+            text.inputs,
+            vec!["*(.text)", "*(.text*)", "KEEP (*(.init))"]
+        );
+        assert_eq!(
+            script.section(".isr_vector").unwrap().inputs,
+            vec!["KEEP(*(.isr_vector))"]
+        );
+
+        let data = script.section(".data").unwrap();
+        assert_eq!(data.region.as_deref(), Some("RAM"));
+        assert_eq!(data.load_region.as_deref(), Some("FLASH"));
+        assert_eq!(data.inputs, vec!["*(.data)", "*(.data*)"]);
+        assert_eq!(data.line, 36);
+
+        assert!(script.section(".bss").unwrap().noload);
+        assert!(script.sections.last().unwrap().is_discard());
+    }
+
+    #[test]
+    fn test_parse_assignments() {
+        let script = parse_linker_script(SCRIPT).unwrap();
+        assert_eq!(
+            script.assignment("_Min_Stack_Size").unwrap().value,
+            Some(0x400)
+        );
+        assert_eq!(
+            script.assignment("_estack").unwrap().value,
+            Some(0x2002_0000)
+        );
+
+        let etext = script.assignment("_etext").unwrap();
+        assert_eq!(etext.section.as_deref(), Some(".text"));
+        assert_eq!(etext.value, None);
+        assert_eq!(
+            script.assignment("_sidata").unwrap().expression,
+            "LOADADDR(.data)"
+        );
+
+        let end = script.assignment("end").unwrap();
+        assert!(end.provide);
+        assert_eq!(end.section.as_deref(), Some("._user_heap_stack"));
+    }
+
+    #[test]
+    fn test_eval() {
+        let memory = vec![MemoryRegion {
+            name: "RAM".to_string(),
+            origin: 0x2000_0000,
+            length: 0x2_0000,
+            attributes: String::new(),
+            used: 0,
+        }];
+        let symbols = HashMap::from([("_stack".to_string(), 0x400)]);
+        let e = |s: &str| eval(s, &memory, &symbols);
+        assert_eq!(e("ORIGIN(RAM) + LENGTH(RAM) - _stack"), Some(0x2001_FC00));
+        assert_eq!(e("(1 << 4) * 2 + 512K"), Some(32 + 512 * 1024));
+        assert_eq!(e("MAX(4, 0x10) & ~3"), Some(16));
+        assert_eq!(e("."), None);
+        assert_eq!(e("ORIGIN(FLASH)"), None);
+    }
+
+    #[test]
+    fn test_validate() {
+        let script = parse_linker_script(
+            "MEMORY {\n FLASH (rx) : ORIGIN = 0, LENGTH = 64K\n RAM (rw) : o = 0x8000, l = 64K\n}\n\
+             SECTIONS {\n .text : { *(.text) } > FLASH\n .data : { *(.data) } > SRAM AT> FLASH\n}\n",
+        )
+        .unwrap();
+        let diags = script.validate(Path::new("app.ld"));
+        assert_eq!(diags.len(), 2);
+        assert!(diags[0].message.contains("FLASH and RAM overlap"));
+        assert!(diags[1].message.contains("undefined region SRAM"));
+        assert_eq!(diags[1].location.as_ref().unwrap().range.start.line, 6);
+    }
+
+    #[test]
+    fn test_usage_from_map() {
+        let script = parse_linker_script(SCRIPT).unwrap();
+        let map = MemoryMap {
+            regions: Vec::new(),
+            sections: vec![
+                crate::OutputSection {
+                    name: ".text".to_string(),
+                    address: 0x0800_0000,
+                    size: 0x1000,
+                    load_address: None,
+                    inputs: Vec::new(),
+                },
+                crate::OutputSection {
+                    name: ".data".to_string(),
+                    address: 0x2000_0000,
+                    size: 0x100,
+                    load_address: Some(0x0800_1000),
+                    inputs: Vec::new(),
+                },
+                crate::OutputSection {
+                    name: ".debug_info".to_string(),
+                    address: 0,
+                    size: 0x5000,
+                    load_address: None,
+                    inputs: Vec::new(),
+                },
+            ],
+        };
+        let usage = script.usage_from_map(&map);
+        let used = |name: &str| usage.iter().find(|r| r.name == name).unwrap().used;
+        assert_eq!(used("FLASH"), 0x1100);
+        assert_eq!(used("RAM"), 0x100);
+        assert_eq!(used("CCMRAM"), 0);
+        assert_eq!(usage[1].free(), 128 * 1024 - 0x100);
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        assert!(matches!(
+            parse_linker_script("MEMORY {\n FLASH : ORIGIN = 0\n}"),
+            Err(LinkerScriptError::Syntax { line: 3, .. })
+        ));
+        assert!(matches!(
+            parse_linker_script("SECTIONS {\n .text : { *(.text) \n"),
+            Err(LinkerScriptError::Syntax { .. })
+        ));
+    }
+}
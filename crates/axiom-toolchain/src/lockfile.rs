@@ -0,0 +1,355 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Per-project toolchain lockfile.
+//!
+//! `toolchain.lock` at the project root pins each toolchain's path, version
+//! and the SHA-256 of its driver and companion binaries. Checking the
+//! resolved toolchains against it shows whether everyone on a program is
+//! building with the identical compiler.
+
+use crate::{sha256_file, DetectedToolchain, ToolchainKind, ToolchainSource};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Lockfile name, at the project root.
+pub const LOCKFILE_NAME: &str = "toolchain.lock";
+
+/// Current lockfile format version.
+pub const LOCKFILE_VERSION: u32 = 1;
+
+/// Companion binaries hashed alongside the compiler driver.
+const LOCKED_TOOLS: &[&str] = &["as", "ld", "objcopy", "objdump", "size", "ar"];
+
+/// Error type for lockfile operations.
+#[derive(Debug, thiserror::Error)]
+pub enum LockfileError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid lockfile {path}: {source}")]
+    Parse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+
+    #[error("Cannot serialize lockfile: {0}")]
+    Serialize(#[from] toml::ser::Error),
+
+    #[error("Unsupported lockfile version {0}")]
+    UnsupportedVersion(u32),
+
+    #[error("Toolchain differs from {LOCKFILE_NAME}: {}", describe(.0))]
+    Mismatch(Vec<LockMismatch>),
+}
+
+/// A toolchain pinned in the lockfile.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockedToolchain {
+    /// Kind of toolchain.
+    pub kind: ToolchainKind,
+    /// Path to the driver.
+    pub path: PathBuf,
+    /// Version string reported by the driver.
+    pub version: String,
+    /// SHA-256 of each binary, keyed by file name.
+    pub binaries: BTreeMap<String, String>,
+}
+
+impl LockedToolchain {
+    /// Pin a detected toolchain, hashing its driver and whichever companion
+    /// binaries are installed next to it. Container toolchains are pinned
+    /// by path and version only; their image reference pins the binaries.
+    pub fn capture(toolchain: &DetectedToolchain) -> Result<Self, LockfileError> {
+        let mut binaries = BTreeMap::new();
+        if toolchain.source == ToolchainSource::Local {
+            let tools = std::iter::once(toolchain.path.clone()).chain(
+                LOCKED_TOOLS
+                    .iter()
+                    .map(|tool| toolchain.sibling_tool(tool))
+                    .filter(|path| path.is_file()),
+            );
+            for path in tools {
+                binaries.insert(binary_name(&path), sha256_file(&path)?);
+            }
+        }
+        Ok(Self {
+            kind: toolchain.kind,
+            path: toolchain.path.clone(),
+            version: toolchain.version.clone(),
+            binaries,
+        })
+    }
+}
+
+/// Contents of `toolchain.lock`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolchainLock {
+    /// Format version.
+    pub version: u32,
+    /// Pinned toolchains, one per kind.
+    #[serde(default, rename = "toolchain")]
+    pub toolchains: Vec<LockedToolchain>,
+}
+
+impl ToolchainLock {
+    /// Pin the given toolchains.
+    pub fn capture(toolchains: &[DetectedToolchain]) -> Result<Self, LockfileError> {
+        Ok(Self {
+            version: LOCKFILE_VERSION,
+            toolchains: toolchains
+                .iter()
+                .map(LockedToolchain::capture)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// Lockfile path for a project.
+    pub fn path_for(project_root: &Path) -> PathBuf {
+        project_root.join(LOCKFILE_NAME)
+    }
+
+    /// Load a project's lockfile, or `None` if it has none.
+    pub fn load(project_root: &Path) -> Result<Option<Self>, LockfileError> {
+        let path = Self::path_for(project_root);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(&path)?;
+        let lock: Self =
+            toml::from_str(&content).map_err(|source| LockfileError::Parse { path, source })?;
+        if lock.version > LOCKFILE_VERSION {
+            return Err(LockfileError::UnsupportedVersion(lock.version));
+        }
+        Ok(Some(lock))
+    }
+
+    /// Write the lockfile into a project.
+    pub fn save(&self, project_root: &Path) -> Result<(), LockfileError> {
+        let content = format!(
+            "# Generated by Axiom. Commit this file; do not edit by hand.\n\n{}",
+            toml::to_string_pretty(self)?
+        );
+        std::fs::write(Self::path_for(project_root), content)?;
+        Ok(())
+    }
+
+    /// The pinned toolchain of a kind.
+    pub fn toolchain(&self, kind: ToolchainKind) -> Option<&LockedToolchain> {
+        self.toolchains.iter().find(|t| t.kind == kind)
+    }
+
+    /// Differences between the pinned and the resolved toolchains.
+    ///
+    /// Only kinds that are pinned are compared.
+    pub fn verify(&self, resolved: &[DetectedToolchain]) -> Vec<LockMismatch> {
+        let mut mismatches = Vec::new();
+        for locked in &self.toolchains {
+            let Some(toolchain) = resolved.iter().find(|t| t.kind == locked.kind) else {
+                mismatches.push(LockMismatch::Missing { kind: locked.kind });
+                continue;
+            };
+            if toolchain.path != locked.path {
+                mismatches.push(LockMismatch::Path {
+                    kind: locked.kind,
+                    locked: locked.path.clone(),
+                    resolved: toolchain.path.clone(),
+                });
+            }
+            if toolchain.version != locked.version {
+                mismatches.push(LockMismatch::Version {
+                    kind: locked.kind,
+                    locked: locked.version.clone(),
+                    resolved: toolchain.version.clone(),
+                });
+            }
+            for (binary, hash) in &locked.binaries {
+                let path = toolchain.path.with_file_name(binary);
+                let actual = sha256_file(&path).ok();
+                if actual.as_ref() != Some(hash) {
+                    mismatches.push(LockMismatch::Checksum {
+                        kind: locked.kind,
+                        binary: binary.clone(),
+                        locked: hash.clone(),
+                        resolved: actual,
+                    });
+                }
+            }
+        }
+        mismatches
+    }
+
+    /// Fail if the resolved toolchains differ from the pinned ones.
+    pub fn enforce(&self, resolved: &[DetectedToolchain]) -> Result<(), LockfileError> {
+        let mismatches = self.verify(resolved);
+        if mismatches.is_empty() {
+            Ok(())
+        } else {
+            Err(LockfileError::Mismatch(mismatches))
+        }
+    }
+}
+
+/// A difference between the lockfile and the resolved toolchain.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum LockMismatch {
+    /// A pinned toolchain was not found.
+    Missing { kind: ToolchainKind },
+    /// The toolchain resolved to a different path.
+    Path {
+        kind: ToolchainKind,
+        locked: PathBuf,
+        resolved: PathBuf,
+    },
+    /// The toolchain reports a different version.
+    Version {
+        kind: ToolchainKind,
+        locked: String,
+        resolved: String,
+    },
+    /// A binary's contents changed, or it is missing (`resolved` is `None`).
+    Checksum {
+        kind: ToolchainKind,
+        binary: String,
+        locked: String,
+        resolved: Option<String>,
+    },
+}
+
+impl LockMismatch {
+    /// Human-readable description.
+    pub fn message(&self) -> String {
+        match self {
+            LockMismatch::Missing { kind } => format!("{} toolchain not found", kind),
+            LockMismatch::Path {
+                kind,
+                locked,
+                resolved,
+            } => format!(
+                "{} resolved to {} instead of {}",
+                kind,
+                resolved.display(),
+                locked.display()
+            ),
+            LockMismatch::Version {
+                kind,
+                locked,
+                resolved,
+            } => format!("{} is version {} instead of {}", kind, resolved, locked),
+            LockMismatch::Checksum {
+                binary,
+                resolved: None,
+                ..
+            } => format!("{} is missing", binary),
+            LockMismatch::Checksum { binary, .. } => format!("{} checksum changed", binary),
+        }
+    }
+}
+
+fn describe(mismatches: &[LockMismatch]) -> String {
+    mismatches
+        .iter()
+        .map(LockMismatch::message)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+fn binary_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn toolchain(dir: &Path) -> DetectedToolchain {
+        let gcc = dir.join("arm-none-eabi-gcc");
+        std::fs::write(&gcc, b"gcc").unwrap();
+        std::fs::write(dir.join("arm-none-eabi-objcopy"), b"objcopy").unwrap();
+        DetectedToolchain::new(ToolchainKind::ArmGcc, gcc, "13.2.1".to_string())
+    }
+
+    #[test]
+    fn test_capture_hashes_driver_and_companions() {
+        let temp = TempDir::new().unwrap();
+        let tc = toolchain(temp.path());
+        let lock = ToolchainLock::capture(std::slice::from_ref(&tc)).unwrap();
+
+        let locked = lock.toolchain(ToolchainKind::ArmGcc).unwrap();
+        assert_eq!(locked.version, "13.2.1");
+        assert_eq!(
+            locked.binaries.keys().collect::<Vec<_>>(),
+            vec!["arm-none-eabi-gcc", "arm-none-eabi-objcopy"]
+        );
+        assert_eq!(
+            locked.binaries["arm-none-eabi-gcc"],
+            sha256_file(&tc.path).unwrap()
+        );
+        assert!(lock.verify(&[tc]).is_empty());
+    }
+
+    #[test]
+    fn test_save_and_load_round_trip() {
+        let temp = TempDir::new().unwrap();
+        let tc = toolchain(temp.path());
+        let lock = ToolchainLock::capture(&[tc]).unwrap();
+
+        assert!(ToolchainLock::load(temp.path()).unwrap().is_none());
+        lock.save(temp.path()).unwrap();
+        assert_eq!(ToolchainLock::load(temp.path()).unwrap(), Some(lock));
+    }
+
+    #[test]
+    fn test_rejects_newer_version() {
+        let temp = TempDir::new().unwrap();
+        std::fs::write(temp.path().join(LOCKFILE_NAME), "version = 99\n").unwrap();
+        assert!(matches!(
+            ToolchainLock::load(temp.path()),
+            Err(LockfileError::UnsupportedVersion(99))
+        ));
+    }
+
+    #[test]
+    fn test_detects_differences() {
+        let temp = TempDir::new().unwrap();
+        let tc = toolchain(temp.path());
+        let lock = ToolchainLock::capture(std::slice::from_ref(&tc)).unwrap();
+
+        std::fs::write(temp.path().join("arm-none-eabi-objcopy"), b"patched").unwrap();
+        let mut upgraded = tc.clone();
+        upgraded.version = "14.1.0".to_string();
+
+        let mismatches = lock.verify(&[upgraded.clone()]);
+        assert_eq!(mismatches.len(), 2);
+        assert!(matches!(
+            &mismatches[0],
+            LockMismatch::Version { resolved, .. } if resolved == "14.1.0"
+        ));
+        assert!(matches!(
+            &mismatches[1],
+            LockMismatch::Checksum { binary, resolved: Some(_), .. } if binary == "arm-none-eabi-objcopy"
+        ));
+
+        let err = lock.enforce(&[upgraded]).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("arm-none-eabi-objcopy checksum changed"));
+    }
+
+    #[test]
+    fn test_missing_toolchain() {
+        let temp = TempDir::new().unwrap();
+        let lock = ToolchainLock::capture(&[toolchain(temp.path())]).unwrap();
+        assert_eq!(
+            lock.verify(&[]),
+            vec![LockMismatch::Missing {
+                kind: ToolchainKind::ArmGcc
+            }]
+        );
+    }
+}
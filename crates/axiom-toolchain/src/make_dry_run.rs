@@ -0,0 +1,514 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Compile commands from `make -n`.
+//!
+//! A dry run prints every recipe without running it. Compiler invocations in
+//! the output are split into per-file defines, include paths and flags, so
+//! Makefile projects get the right parser and indexer settings without hand
+//! configuration. `make -w` directory messages and `cd dir &&` prefixes are
+//! followed so relative paths resolve.
+
+use crate::limits::run_limited;
+use crate::tool_log::log_invocation;
+use crate::{CachedFlags, ToolLogError, ToolQualificationLogger, ToolchainKind};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Instant;
+
+/// Error type for make dry runs.
+#[derive(Debug, thiserror::Error)]
+pub enum MakeDryRunError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("make -n failed: {0}")]
+    Failed(String),
+
+    #[error("Tool usage not logged: {0}")]
+    UsageLog(#[from] ToolLogError),
+}
+
+/// Options that take their value as the next argument.
+const SEPARATE_VALUE_OPTIONS: &[&str] = &[
+    "-o",
+    "-D",
+    "-U",
+    "-I",
+    "-iquote",
+    "-isystem",
+    "-idirafter",
+    "-include",
+    "-imacros",
+    "-MF",
+    "-MT",
+    "-MQ",
+    "-x",
+    "-Xassembler",
+    "-Xlinker",
+    "-Xpreprocessor",
+];
+
+/// Include path options, in the order `#include "..."` searches them.
+const INCLUDE_OPTIONS: &[&str] = &["-iquote", "-I", "-isystem", "-idirafter"];
+
+/// Launchers that run the real compiler.
+const COMPILER_WRAPPERS: &[&str] = &["ccache", "sccache", "distcc"];
+
+/// One compiler invocation from a dry run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MakeCompileCommand {
+    /// Directory the command runs in.
+    pub directory: PathBuf,
+    /// Compiler as invoked.
+    pub compiler: PathBuf,
+    /// Source file, resolved against `directory`.
+    pub source: PathBuf,
+    /// Output object, if given.
+    pub output: Option<PathBuf>,
+    /// Preprocessor defines (`NAME` or `NAME=VALUE`), in order.
+    pub defines: Vec<String>,
+    /// Include directories, resolved, in the order `#include "..."`
+    /// searches them.
+    pub include_paths: Vec<PathBuf>,
+    /// Flags without the source, `-c` and `-o <output>`, with include
+    /// directories resolved.
+    pub flags: Vec<String>,
+    /// Full argv, as printed.
+    pub arguments: Vec<String>,
+}
+
+impl MakeCompileCommand {
+    /// Toolchain kind, from the compiler name.
+    pub fn kind(&self) -> ToolchainKind {
+        let name = self
+            .compiler
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        if name.contains("clang") {
+            ToolchainKind::Clang
+        } else if name.starts_with("arm-none-eabi-") {
+            ToolchainKind::ArmGcc
+        } else {
+            ToolchainKind::Gcc
+        }
+    }
+
+    /// Flags for check-on-save and the indexer.
+    pub fn cached_flags(&self, version: impl Into<String>) -> CachedFlags {
+        CachedFlags {
+            compiler: self.compiler.clone(),
+            kind: self.kind(),
+            version: version.into(),
+            flags: self.flags.clone(),
+        }
+    }
+}
+
+/// Run `make -n` in a project and extract its compile commands.
+///
+/// `-B` makes up-to-date targets print their recipes too, and `-w` prints
+/// the directory of each recursive make. The run is logged to `usage_log`
+/// if given, with the Makefile as input.
+pub fn extract_make_compile_commands(
+    project_root: &Path,
+    target: Option<&str>,
+    usage_log: Option<&ToolQualificationLogger>,
+) -> Result<Vec<MakeCompileCommand>, MakeDryRunError> {
+    let mut args = vec!["-n".to_string(), "-B".to_string(), "-w".to_string()];
+    args.extend(target.map(str::to_string));
+    let start = Instant::now();
+    let output = run_limited(Command::new("make").args(&args).current_dir(project_root))?;
+    let duration_ms = start.elapsed().as_millis() as u64;
+    if usage_log.is_some() {
+        log_invocation(usage_log, Path::new("make"), &make_version(), |record| {
+            record
+                .with_arguments(args)
+                .with_result(output.status.code().unwrap_or(-1), duration_ms)
+                .with_inputs(
+                    ["GNUmakefile", "makefile", "Makefile"]
+                        .iter()
+                        .map(|name| project_root.join(name))
+                        .filter(|path| path.is_file())
+                        .take(1),
+                )
+        })?;
+    }
+    if !output.status.success() {
+        return Err(MakeDryRunError::Failed(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(parse_make_dry_run(
+        &String::from_utf8_lossy(&output.stdout),
+        project_root,
+    ))
+}
+
+/// First line of `make --version`, or `unknown`.
+fn make_version() -> String {
+    run_limited(Command::new("make").arg("--version"))
+        .ok()
+        .and_then(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .next()
+                .map(str::to_string)
+        })
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Extract compile commands from `make -n` output run in `directory`.
+pub fn parse_make_dry_run(output: &str, directory: &Path) -> Vec<MakeCompileCommand> {
+    let mut directories = vec![directory.to_path_buf()];
+    let mut commands = Vec::new();
+
+    for line in joined_lines(output) {
+        if let Some(dir) = directory_message(&line, "Entering directory") {
+            directories.push(PathBuf::from(dir));
+            continue;
+        }
+        if directory_message(&line, "Leaving directory").is_some() {
+            if directories.len() > 1 {
+                directories.pop();
+            }
+            continue;
+        }
+
+        // Each recipe line runs in its own shell, so `cd` lasts for the line
+        let mut cwd = directories.last().cloned().unwrap_or_default();
+        for argv in shell_commands(&line) {
+            match argv.first().map(String::as_str) {
+                Some("cd") => {
+                    if let Some(dir) = argv.get(1) {
+                        cwd = cwd.join(dir);
+                    }
+                }
+                _ => commands.extend(compile_command(&argv, &cwd)),
+            }
+        }
+    }
+    commands
+}
+
+/// Lines with backslash continuations joined.
+fn joined_lines(output: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    for line in output.lines() {
+        match line.strip_suffix('\\') {
+            Some(head) => {
+                current.push_str(head);
+                current.push(' ');
+            }
+            None => {
+                current.push_str(line);
+                lines.push(std::mem::take(&mut current));
+            }
+        }
+    }
+    if !current.is_empty() {
+        lines.push(current);
+    }
+    lines
+}
+
+/// Directory from `make[1]: Entering directory '/path'`.
+fn directory_message<'a>(line: &'a str, message: &str) -> Option<&'a str> {
+    if !line.starts_with("make") {
+        return None;
+    }
+    let rest = &line[line.find(message)? + message.len()..];
+    let rest = rest.trim().trim_start_matches(['\'', '`']);
+    Some(rest.trim_end_matches('\''))
+}
+
+/// Split a shell line into the argv of each command, on `&&`, `||`, `;`
+/// and `|`, honouring quotes and backslash escapes.
+fn shell_commands(line: &str) -> Vec<Vec<String>> {
+    let mut commands = Vec::new();
+    let mut argv: Vec<String> = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                for c in chars.by_ref() {
+                    if c == '\'' {
+                        break;
+                    }
+                    word.push(c);
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' if matches!(chars.peek(), Some('"' | '\\' | '$' | '`')) => {
+                            word.extend(chars.next());
+                        }
+                        c => word.push(c),
+                    }
+                }
+            }
+            '\\' => word.get_or_insert_with(String::new).extend(chars.next()),
+            ';' | '&' | '|' => {
+                argv.extend(word.take());
+                if c != ';' && chars.peek() == Some(&c) {
+                    chars.next();
+                }
+                if !argv.is_empty() {
+                    commands.push(std::mem::take(&mut argv));
+                }
+            }
+            c if c.is_whitespace() => argv.extend(word.take()),
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    argv.extend(word);
+    if !argv.is_empty() {
+        commands.push(argv);
+    }
+    commands
+}
+
+/// Whether a program name is a C/C++ compiler driver: `gcc`, `g++`, `cc`,
+/// `c++`, `clang` or `clang++`, with any target prefix or version suffix.
+fn is_compiler(program: &str) -> bool {
+    let name = Path::new(program)
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let name = name.strip_suffix(".exe").unwrap_or(&name);
+    // gcc-12, clang-17
+    let name = name.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+    let name = name.strip_suffix('-').unwrap_or(name);
+    ["gcc", "g++", "cc", "c++", "clang", "clang++"]
+        .iter()
+        .any(|driver| name == *driver || name.ends_with(&format!("-{}", driver)))
+}
+
+fn is_source(arg: &str) -> bool {
+    matches!(
+        Path::new(arg).extension().and_then(|e| e.to_str()),
+        Some("c" | "cc" | "cpp" | "cxx" | "c++" | "C" | "s" | "S" | "sx" | "asm")
+    )
+}
+
+/// Push an option and its value, joined for `-D`, `-U` and `-I`.
+fn push_option(flags: &mut Vec<String>, option: &str, value: String) {
+    if matches!(option, "-D" | "-U" | "-I") {
+        flags.push(format!("{}{}", option, value));
+    } else {
+        flags.push(option.to_string());
+        flags.push(value);
+    }
+}
+
+fn compile_command(argv: &[String], cwd: &Path) -> Option<MakeCompileCommand> {
+    // Skip `VAR=value` prefixes and compiler launchers
+    let start = argv.iter().position(|arg| {
+        !arg.contains('=')
+            && !COMPILER_WRAPPERS
+                .iter()
+                .any(|wrapper| Path::new(arg).file_name() == Some(wrapper.as_ref()))
+    })?;
+    let (compiler, args) = argv[start..].split_first()?;
+    if !is_compiler(compiler) || !args.iter().any(|arg| arg == "-c") {
+        return None;
+    }
+
+    let mut source = None;
+    let mut output = None;
+    let mut defines = Vec::new();
+    let mut includes: Vec<(usize, PathBuf)> = Vec::new();
+    let mut flags = Vec::new();
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        let option = SEPARATE_VALUE_OPTIONS
+            .iter()
+            .find(|option| arg == *option || (option.len() == 2 && arg.starts_with(*option)));
+        let Some(&option) = option else {
+            if arg == "-c" {
+                continue;
+            }
+            if !arg.starts_with('-') && is_source(arg) && source.is_none() {
+                source = Some(cwd.join(arg));
+            } else {
+                flags.push(arg.clone());
+            }
+            continue;
+        };
+
+        let value = if arg == option {
+            match iter.next() {
+                Some(value) => value.clone(),
+                None => break,
+            }
+        } else {
+            arg[option.len()..].to_string()
+        };
+        if option == "-o" {
+            output = Some(cwd.join(&value));
+        } else if let Some(rank) = INCLUDE_OPTIONS.iter().position(|o| *o == option) {
+            let dir = cwd.join(&value);
+            push_option(&mut flags, option, dir.display().to_string());
+            includes.push((rank, dir));
+        } else {
+            if option == "-D" {
+                defines.push(value.clone());
+            }
+            push_option(&mut flags, option, value);
+        }
+    }
+
+    // Stable, so command-line order holds within each option
+    includes.sort_by_key(|(rank, _)| *rank);
+    Some(MakeCompileCommand {
+        directory: cwd.to_path_buf(),
+        compiler: PathBuf::from(compiler),
+        source: source?,
+        output,
+        defines,
+        include_paths: includes.into_iter().map(|(_, dir)| dir).collect(),
+        flags,
+        arguments: argv[start..].to_vec(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DRY_RUN: &str = "\
+make: Entering directory '/work/fw'
+mkdir -p build
+echo \"CC src/main.c\"
+arm-none-eabi-gcc -c src/main.c -o build/main.o -mcpu=cortex-m4 -mthumb -DSTM32F407xx \\
+    -D 'VERSION=\"1.2 beta\"' -Iinc -I drivers/inc -isystem /opt/cmsis -Os -g
+make[1]: Entering directory '/work/fw/lib'
+cd hal && ccache arm-none-eabi-gcc -Os -c -iquote . gpio.c -o gpio.o
+make[1]: Leaving directory '/work/fw/lib'
+arm-none-eabi-gcc -x assembler-with-cpp -c startup.S -o build/startup.o
+arm-none-eabi-gcc build/main.o build/startup.o -T app.ld -o build/fw.elf
+make: Leaving directory '/work/fw'
+";
+
+    #[test]
+    fn test_parse_make_dry_run() {
+        let commands = parse_make_dry_run(DRY_RUN, Path::new("/elsewhere"));
+        assert_eq!(commands.len(), 3);
+
+        let main = &commands[0];
+        assert_eq!(main.directory, PathBuf::from("/work/fw"));
+        assert_eq!(main.compiler, PathBuf::from("arm-none-eabi-gcc"));
+        assert_eq!(main.kind(), ToolchainKind::ArmGcc);
+        assert_eq!(main.source, PathBuf::from("/work/fw/src/main.c"));
+        assert_eq!(main.output, Some(PathBuf::from("/work/fw/build/main.o")));
+        assert_eq!(main.defines, vec!["STM32F407xx", "VERSION=\"1.2 beta\""]);
+        assert_eq!(
+            main.include_paths,
+            vec![
+                PathBuf::from("/work/fw/inc"),
+                PathBuf::from("/work/fw/drivers/inc"),
+                PathBuf::from("/opt/cmsis"),
+            ]
+        );
+        assert_eq!(
+            main.flags,
+            vec![
+                "-mcpu=cortex-m4",
+                "-mthumb",
+                "-DSTM32F407xx",
+                "-DVERSION=\"1.2 beta\"",
+                "-I/work/fw/inc",
+                "-I/work/fw/drivers/inc",
+                "-isystem",
+                "/opt/cmsis",
+                "-Os",
+                "-g",
+            ]
+        );
+
+        let gpio = &commands[1];
+        assert_eq!(gpio.directory, PathBuf::from("/work/fw/lib/hal"));
+        assert_eq!(gpio.source, PathBuf::from("/work/fw/lib/hal/gpio.c"));
+        assert_eq!(
+            gpio.include_paths,
+            vec![PathBuf::from("/work/fw/lib/hal/.")]
+        );
+        assert_eq!(gpio.arguments[0], "arm-none-eabi-gcc");
+
+        let startup = &commands[2];
+        assert_eq!(startup.directory, PathBuf::from("/work/fw"));
+        assert_eq!(startup.source, PathBuf::from("/work/fw/startup.S"));
+        assert_eq!(startup.flags, vec!["-x", "assembler-with-cpp"]);
+    }
+
+    #[test]
+    fn test_include_search_order() {
+        let commands = parse_make_dry_run(
+            "gcc -c a.c -isystem sys -Ione -iquote q -idirafter late -I two\n",
+            Path::new("/p"),
+        );
+        assert_eq!(
+            commands[0].include_paths,
+            ["q", "one", "two", "sys", "late"]
+                .iter()
+                .map(|d| Path::new("/p").join(d))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_is_compiler() {
+        for name in [
+            "gcc",
+            "/usr/bin/gcc-12",
+            "arm-none-eabi-gcc",
+            "arm-none-eabi-g++",
+            "clang++-17",
+            "cc",
+            r"C:\arm\bin\arm-none-eabi-gcc.exe",
+        ] {
+            assert!(is_compiler(name), "{}", name);
+        }
+        for name in [
+            "echo",
+            "arm-none-eabi-objcopy",
+            "gcc-ar",
+            "mkdir",
+            "cppcheck",
+        ] {
+            assert!(!is_compiler(name), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_cached_flags() {
+        let commands = parse_make_dry_run("clang -c main.c -o main.o -DX=1\n", Path::new("/p"));
+        let flags = commands[0].cached_flags("17.0.6");
+        assert_eq!(flags.kind, ToolchainKind::Clang);
+        assert_eq!(flags.flags, vec!["-DX=1"]);
+    }
+
+    #[test]
+    fn test_shell_commands() {
+        assert_eq!(
+            shell_commands(r#"cd "my dir" && gcc -c a\ b.c || true; echo 'x|y' | cat"#),
+            vec![
+                vec!["cd", "my dir"],
+                vec!["gcc", "-c", "a b.c"],
+                vec!["true"],
+                vec!["echo", "x|y"],
+                vec!["cat"],
+            ]
+        );
+    }
+}
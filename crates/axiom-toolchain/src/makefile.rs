@@ -0,0 +1,1117 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Makefile detection, parsing and flag sync.
+//!
+//! Only unconditional top-level assignments and includes are read or
+//! rewritten; anything inside `ifeq`/`ifdef` blocks is left alone because it
+//! cannot be evaluated without running make.
+
+use crate::BuildProfile;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Makefile update errors.
+#[derive(Debug, Error)]
+pub enum MakefileError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("{0} changed since the update was planned")]
+    StaleUpdate(PathBuf),
+}
+
+/// Makefile names, in the order make looks for them.
+pub const MAKEFILE_NAMES: &[&str] = &["GNUmakefile", "makefile", "Makefile"];
+
+/// A Makefile found in a project.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MakefileInfo {
+    /// Path to the Makefile.
+    pub path: PathBuf,
+    /// Explicit targets, in file order.
+    pub targets: Vec<String>,
+}
+
+/// Find the Makefile in a directory and list its targets.
+pub fn detect_makefile(dir: &Path) -> Option<MakefileInfo> {
+    let path = MAKEFILE_NAMES
+        .iter()
+        .map(|name| dir.join(name))
+        .find(|p| p.is_file())?;
+    let content = std::fs::read_to_string(&path).ok()?;
+
+    let mut targets = Vec::new();
+    for line in logical_lines(&content) {
+        if line.text.starts_with('\t') || assignment(&line.text).is_some() {
+            continue;
+        }
+        let Some((names, _)) = line.text.split_once(':') else {
+            continue;
+        };
+        for name in names.split_whitespace() {
+            // Skip special targets, pattern rules, and variable references
+            if !name.starts_with('.')
+                && !name.contains(['%', '$'])
+                && !targets.iter().any(|t| t == name)
+            {
+                targets.push(name.to_string());
+            }
+        }
+    }
+
+    Some(MakefileInfo { path, targets })
+}
+
+/// A line with continuations joined and comments removed.
+struct LogicalLine {
+    /// First physical line (0-based).
+    start: usize,
+    /// Number of physical lines.
+    count: usize,
+    /// Joined text.
+    text: String,
+    /// Nesting depth of conditionals around this line.
+    conditional_depth: usize,
+}
+
+fn logical_lines(content: &str) -> Vec<LogicalLine> {
+    let physical: Vec<&str> = content.lines().collect();
+    let mut lines = Vec::new();
+    let mut depth = 0usize;
+    let mut i = 0;
+
+    while i < physical.len() {
+        let start = i;
+        let mut text = String::new();
+        loop {
+            let raw = physical[i];
+            i += 1;
+            match raw.strip_suffix('\\') {
+                Some(head) if i < physical.len() => {
+                    text.push_str(head.trim_end());
+                    text.push(' ');
+                }
+                _ => {
+                    text.push_str(raw);
+                    break;
+                }
+            }
+        }
+        let text = strip_comment(&text).trim_end().to_string();
+
+        let keyword = text.split_whitespace().next().unwrap_or_default();
+        let line_depth = depth;
+        match keyword {
+            "ifeq" | "ifneq" | "ifdef" | "ifndef" => depth += 1,
+            "endif" => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+
+        lines.push(LogicalLine {
+            start,
+            count: i - start,
+            text,
+            conditional_depth: line_depth,
+        });
+    }
+
+    lines
+}
+
+fn strip_comment(line: &str) -> &str {
+    let mut prev = '\0';
+    for (idx, c) in line.char_indices() {
+        if c == '#' && prev != '\\' {
+            return &line[..idx];
+        }
+        prev = c;
+    }
+    line
+}
+
+/// Assignment operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AssignOp {
+    Set,
+    Append,
+    SetIfUnset,
+}
+
+/// Parse `NAME op value`, returning (name, op, value).
+fn assignment(line: &str) -> Option<(&str, AssignOp, &str)> {
+    if line.starts_with('\t') {
+        return None;
+    }
+    let mut line = line.trim();
+    for prefix in ["override ", "export "] {
+        if let Some(rest) = line.strip_prefix(prefix) {
+            line = rest.trim_start();
+        }
+    }
+
+    let eq = line.find('=')?;
+    let (lhs, value) = (&line[..eq], line[eq + 1..].trim());
+    let (name, op) = if let Some(name) = lhs.strip_suffix("::") {
+        (name, AssignOp::Set)
+    } else if let Some(name) = lhs.strip_suffix(':') {
+        (name, AssignOp::Set)
+    } else if let Some(name) = lhs.strip_suffix('+') {
+        (name, AssignOp::Append)
+    } else if let Some(name) = lhs.strip_suffix('?') {
+        (name, AssignOp::SetIfUnset)
+    } else {
+        (lhs, AssignOp::Set)
+    };
+
+    let name = name.trim();
+    if name.is_empty() || name.contains(|c: char| c.is_whitespace() || c == ':') {
+        return None;
+    }
+    Some((name, op, value))
+}
+
+/// Evaluate unconditional top-level assignments.
+fn variables(content: &str) -> HashMap<String, String> {
+    let mut vars = HashMap::new();
+    for line in logical_lines(content) {
+        if line.conditional_depth == 0 {
+            apply_assignment(&line.text, &mut vars);
+        }
+    }
+    vars
+}
+
+/// Apply a line to `vars`, if it is an assignment.
+fn apply_assignment(line: &str, vars: &mut HashMap<String, String>) {
+    let Some((name, op, value)) = assignment(line) else {
+        return;
+    };
+    match op {
+        AssignOp::Set => {
+            vars.insert(name.to_string(), value.to_string());
+        }
+        AssignOp::Append => {
+            let entry = vars.entry(name.to_string()).or_default();
+            if !entry.is_empty() {
+                entry.push(' ');
+            }
+            entry.push_str(value);
+        }
+        AssignOp::SetIfUnset => {
+            vars.entry(name.to_string())
+                .or_insert_with(|| value.to_string());
+        }
+    }
+}
+
+/// Expand `$(NAME)` and `${NAME}` references to variables defined in the
+/// file, including substitution references (`$(SRCS:.c=.o)`).
+///
+/// References that cannot be resolved (functions, environment) are kept.
+fn expand(value: &str, vars: &HashMap<String, String>, depth: usize) -> String {
+    if depth > 16 {
+        return value.to_string();
+    }
+    let mut out = String::new();
+    let mut rest = value;
+    while let Some(idx) = rest.find('$') {
+        out.push_str(&rest[..idx]);
+        let after = &rest[idx + 1..];
+        let close = match after.chars().next() {
+            Some('(') => ')',
+            Some('{') => '}',
+            _ => {
+                out.push('$');
+                rest = after;
+                continue;
+            }
+        };
+        let Some(end) = after.find(close) else {
+            out.push_str(&rest[idx..]);
+            return out;
+        };
+        let name = &after[1..end];
+        match vars.get(name) {
+            Some(v) => out.push_str(&expand(v, vars, depth + 1)),
+            None => match substitution_reference(name, vars, depth) {
+                Some(v) => out.push_str(&v),
+                None => out.push_str(&rest[idx..idx + end + 2]),
+            },
+        }
+        rest = &after[end + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Evaluate `NAME:from=to`, replacing a suffix (or a `%` pattern) in each
+/// word of the variable's value.
+fn substitution_reference(
+    reference: &str,
+    vars: &HashMap<String, String>,
+    depth: usize,
+) -> Option<String> {
+    let (name, pattern) = reference.split_once(':')?;
+    let (from, to) = pattern.split_once('=')?;
+    let value = expand(vars.get(name)?, vars, depth + 1);
+    let (from, to) = if from.contains('%') {
+        (from.to_string(), to.to_string())
+    } else {
+        (format!("%{}", from), format!("%{}", to))
+    };
+    Some(
+        value
+            .split_whitespace()
+            .map(|word| {
+                pattern_stem(&from, word).map_or(word.to_string(), |stem| to.replacen('%', stem, 1))
+            })
+            .collect::<Vec<_>>()
+            .join(" "),
+    )
+}
+
+/// Text matched by `%` when `word` matches `pattern`.
+fn pattern_stem<'a>(pattern: &str, word: &'a str) -> Option<&'a str> {
+    let (prefix, suffix) = pattern.split_once('%')?;
+    word.strip_prefix(prefix)?.strip_suffix(suffix)
+}
+
+/// Split a flag string on whitespace, keeping `$(...)` references whole.
+fn split_flags(value: &str) -> Vec<String> {
+    let mut flags = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0usize;
+
+    for c in value.chars() {
+        match c {
+            '(' | '{' if current.ends_with('$') || depth > 0 => depth += 1,
+            ')' | '}' if depth > 0 => depth -= 1,
+            c if c.is_whitespace() && depth == 0 => {
+                if !current.is_empty() {
+                    flags.push(std::mem::take(&mut current));
+                }
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    if !current.is_empty() {
+        flags.push(current);
+    }
+    flags
+}
+
+/// Build a profile from a Makefile's CFLAGS (plus CPPFLAGS) and LDFLAGS.
+pub fn profile_from_makefile(content: &str, name: impl Into<String>) -> BuildProfile {
+    profile_from_variables(&variables(content), name)
+}
+
+fn profile_from_variables(vars: &HashMap<String, String>, name: impl Into<String>) -> BuildProfile {
+    let flags = |var: &str| -> Vec<String> {
+        vars.get(var)
+            .map(|v| split_flags(&expand(v, vars, 0)))
+            .unwrap_or_default()
+    };
+
+    let mut profile = BuildProfile::new(name);
+    profile.add_compile_flags(&flags("CPPFLAGS"));
+    profile.add_compile_flags(&flags("CFLAGS"));
+    profile.ldflags = flags("LDFLAGS");
+    profile
+}
+
+/// Render a profile as Makefile variable assignments.
+pub fn profile_to_makefile(profile: &BuildProfile) -> String {
+    format!(
+        "# Build profile \"{}\"\n{}{}",
+        profile.name,
+        render_assignment("CFLAGS", &profile.compile_flags()),
+        render_assignment("LDFLAGS", &profile.ldflags)
+    )
+}
+
+fn render_assignment(name: &str, flags: &[String]) -> String {
+    if flags.is_empty() {
+        return format!("{} =\n", name);
+    }
+    let mut out = format!("{} = {}", name, flags[0]);
+    for flag in &flags[1..] {
+        out.push_str(" \\\n\t");
+        out.push_str(flag);
+    }
+    out.push('\n');
+    out
+}
+
+/// Rewrite a Makefile's CFLAGS, CPPFLAGS and LDFLAGS from a profile.
+///
+/// Unconditional top-level assignments to each are replaced by a single
+/// assignment at the first site; CFLAGS and LDFLAGS are added at the top if
+/// not yet assigned. Defines and include paths go to CPPFLAGS when the
+/// Makefile assigns it, and to CFLAGS otherwise.
+///
+/// References to other variables in the replaced assignments (such as
+/// `$(MCU)`) are written back unexpanded wherever the profile still has
+/// all of their flags in the same order.
+pub fn update_makefile(content: &str, profile: &BuildProfile) -> String {
+    let lines = logical_lines(content);
+    let vars = variables(content);
+    let assigned = |names: &[&str]| -> Vec<&LogicalLine> {
+        lines
+            .iter()
+            .filter(|line| line.conditional_depth == 0)
+            .filter(|line| assignment(&line.text).is_some_and(|(name, _, _)| names.contains(&name)))
+            .collect()
+    };
+
+    let compile = assigned(&["CFLAGS", "CPPFLAGS"]);
+    let separate_cppflags = compile
+        .iter()
+        .any(|line| assignment(&line.text).is_some_and(|(name, _, _)| name == "CPPFLAGS"));
+    let mut compile_refs = references(&compile, &vars);
+    let mut link_refs = references(&assigned(&["LDFLAGS"]), &vars);
+
+    let (cppflags, cflags) = if separate_cppflags {
+        split_compile_flags(profile)
+    } else {
+        (Vec::new(), profile.compile_flags())
+    };
+    let rendered: HashMap<&str, String> = [
+        ("CPPFLAGS", with_references(&cppflags, &mut compile_refs)),
+        ("CFLAGS", with_references(&cflags, &mut compile_refs)),
+        ("LDFLAGS", with_references(&profile.ldflags, &mut link_refs)),
+    ]
+    .into_iter()
+    .map(|(name, flags)| (name, render_assignment(name, &flags)))
+    .collect();
+
+    let physical: Vec<&str> = content.lines().collect();
+    let mut replacement: HashMap<usize, &str> = HashMap::new();
+    let mut removed = vec![false; physical.len()];
+    let mut placed: Vec<&str> = Vec::new();
+
+    for line in assigned(&["CFLAGS", "CPPFLAGS", "LDFLAGS"]) {
+        let Some((name, _, _)) = assignment(&line.text) else {
+            continue;
+        };
+        for flag in removed.iter_mut().skip(line.start).take(line.count) {
+            *flag = true;
+        }
+        if !placed.contains(&name) {
+            placed.push(name);
+            replacement.insert(line.start, name);
+        }
+    }
+
+    let mut out = String::new();
+    for name in ["CFLAGS", "LDFLAGS"] {
+        if !placed.contains(&name) {
+            out.push_str(&rendered[name]);
+        }
+    }
+    for (i, line) in physical.iter().enumerate() {
+        if let Some(name) = replacement.get(&i) {
+            out.push_str(&rendered[name]);
+        } else if !removed[i] {
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// A profile's preprocessor flags (defines and include paths) and its other
+/// compiler flags.
+fn split_compile_flags(profile: &BuildProfile) -> (Vec<String>, Vec<String>) {
+    let mut cppflags: Vec<String> = profile.defines.iter().map(|d| format!("-D{}", d)).collect();
+    cppflags.extend(
+        profile
+            .include_paths
+            .iter()
+            .map(|p| format!("-I{}", p.display())),
+    );
+
+    let mut cflags = vec![profile.optimization.flag().to_string()];
+    if profile.debug {
+        cflags.push("-g".to_string());
+    }
+    cflags.extend(profile.cflags.iter().cloned());
+    (cppflags, cflags)
+}
+
+/// A variable reference in a flags assignment, with the flags it expands to.
+struct Reference {
+    text: String,
+    flags: Vec<String>,
+}
+
+/// References to other variables in the values of some assignments.
+fn references(lines: &[&LogicalLine], vars: &HashMap<String, String>) -> Vec<Reference> {
+    let mut references: Vec<Reference> = Vec::new();
+    for line in lines {
+        let Some((_, _, value)) = assignment(&line.text) else {
+            continue;
+        };
+        for word in split_flags(value) {
+            let name = word
+                .strip_prefix("$(")
+                .and_then(|w| w.strip_suffix(')'))
+                .or_else(|| word.strip_prefix("${").and_then(|w| w.strip_suffix('}')));
+            let rewritten = ["CFLAGS", "CPPFLAGS", "LDFLAGS"];
+            if name.is_none_or(|name| rewritten.contains(&name))
+                || references.iter().any(|r| r.text == word)
+            {
+                continue;
+            }
+            let expanded = expand(&word, vars, 0);
+            // Functions and unknown variables stay as literal flags
+            if expanded != word {
+                references.push(Reference {
+                    flags: split_flags(&expanded),
+                    text: word,
+                });
+            }
+        }
+    }
+    references
+}
+
+/// Flags with each run matching an unused reference's expansion replaced
+/// by the reference. References that expand to nothing are kept at the end.
+fn with_references(flags: &[String], references: &mut Vec<Reference>) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < flags.len() {
+        let found = references
+            .iter()
+            .position(|r| !r.flags.is_empty() && flags[i..].starts_with(&r.flags));
+        match found {
+            Some(index) => {
+                let reference = references.remove(index);
+                i += reference.flags.len();
+                out.push(reference.text);
+            }
+            None => {
+                out.push(flags[i].clone());
+                i += 1;
+            }
+        }
+    }
+    references.retain(|r| {
+        if r.flags.is_empty() {
+            out.push(r.text.clone());
+        }
+        !r.flags.is_empty()
+    });
+    out
+}
+
+/// A planned Makefile update, previewable before it is written.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MakefileUpdate {
+    /// Makefile to update.
+    pub path: PathBuf,
+    /// Current contents.
+    pub original: String,
+    /// Contents after the update.
+    pub preview: String,
+}
+
+/// Plan rewriting a Makefile's flags from a profile.
+pub fn plan_makefile_update(
+    path: &Path,
+    profile: &BuildProfile,
+) -> Result<MakefileUpdate, MakefileError> {
+    let original = std::fs::read_to_string(path)?;
+    Ok(MakefileUpdate {
+        path: path.to_path_buf(),
+        preview: update_makefile(&original, profile),
+        original,
+    })
+}
+
+/// Path of the backup written before a Makefile is updated.
+pub fn makefile_backup_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".bak");
+    path.with_file_name(name)
+}
+
+/// Write a planned update, keeping the previous contents at
+/// [`makefile_backup_path`]. Returns the backup's path.
+pub fn apply_makefile_update(update: &MakefileUpdate) -> Result<PathBuf, MakefileError> {
+    if std::fs::read_to_string(&update.path)? != update.original {
+        return Err(MakefileError::StaleUpdate(update.path.clone()));
+    }
+    let backup = makefile_backup_path(&update.path);
+    std::fs::write(&backup, &update.original)?;
+    std::fs::write(&update.path, &update.preview)?;
+    Ok(backup)
+}
+
+/// An `include` directive.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MakeInclude {
+    /// File name as written, with variables expanded.
+    pub name: String,
+    /// Resolved path, if the file exists.
+    pub path: Option<PathBuf>,
+    /// `-include` or `sinclude`: a missing file is not an error.
+    pub optional: bool,
+}
+
+/// An explicit or pattern rule.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MakeRule {
+    /// Targets, with variables expanded.
+    pub targets: Vec<String>,
+    /// Normal prerequisites, with variables expanded.
+    pub prerequisites: Vec<String>,
+    /// Order-only prerequisites (after `|`).
+    pub order_only: Vec<String>,
+    /// Recipe lines, unexpanded and without the leading tab.
+    pub recipe: Vec<String>,
+    /// Makefile the rule is defined in.
+    pub file: PathBuf,
+}
+
+impl MakeRule {
+    /// Whether this is a pattern rule (`%.o: %.c`).
+    pub fn is_pattern(&self) -> bool {
+        self.targets.iter().any(|t| t.contains('%'))
+    }
+}
+
+/// A Makefile and everything it includes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MakefileModel {
+    /// Top-level Makefile.
+    pub path: PathBuf,
+    /// Variables after all unconditional assignments, unexpanded.
+    pub variables: BTreeMap<String, String>,
+    /// Include directives, in the order they were read.
+    pub includes: Vec<MakeInclude>,
+    /// Rules, in the order they were read.
+    pub rules: Vec<MakeRule>,
+}
+
+impl MakefileModel {
+    /// A variable's value with references expanded.
+    pub fn variable(&self, name: &str) -> Option<String> {
+        let vars = self.variable_map();
+        vars.get(name).map(|value| expand(value, &vars, 0))
+    }
+
+    /// A variable's value split into flags.
+    pub fn flags(&self, name: &str) -> Vec<String> {
+        self.variable(name)
+            .map(|value| split_flags(&value))
+            .unwrap_or_default()
+    }
+
+    /// Every variable with references expanded.
+    pub fn effective_variables(&self) -> BTreeMap<String, String> {
+        let vars = self.variable_map();
+        self.variables
+            .iter()
+            .map(|(name, value)| (name.clone(), expand(value, &vars, 0)))
+            .collect()
+    }
+
+    /// Build profile from the effective CFLAGS (plus CPPFLAGS) and LDFLAGS.
+    pub fn profile(&self, name: impl Into<String>) -> BuildProfile {
+        profile_from_variables(&self.variable_map(), name)
+    }
+
+    /// Explicit targets, in file order, as listed by [`detect_makefile`].
+    pub fn targets(&self) -> Vec<&str> {
+        let mut targets: Vec<&str> = Vec::new();
+        for rule in &self.rules {
+            for target in &rule.targets {
+                if !target.starts_with('.')
+                    && !target.contains(['%', '$'])
+                    && !targets.contains(&target.as_str())
+                {
+                    targets.push(target);
+                }
+            }
+        }
+        targets
+    }
+
+    /// Direct prerequisites of a target, from every explicit rule naming it.
+    pub fn prerequisites(&self, target: &str) -> Vec<&str> {
+        let mut prerequisites: Vec<&str> = Vec::new();
+        for rule in self.explicit_rules(target) {
+            for prerequisite in &rule.prerequisites {
+                if !prerequisites.contains(&prerequisite.as_str()) {
+                    prerequisites.push(prerequisite);
+                }
+            }
+        }
+        prerequisites
+    }
+
+    /// Files a target is built from: the leaves of its dependency graph.
+    ///
+    /// Prerequisites without an explicit rule go through the first
+    /// matching pattern rule (`main.o` via `%.o: %.c` to `main.c`);
+    /// anything with no rule at all is a file.
+    pub fn files(&self, target: &str) -> Vec<String> {
+        let mut files = Vec::new();
+        let mut visited = HashSet::new();
+        self.collect_files(target, &mut files, &mut visited);
+        files
+    }
+
+    fn collect_files(&self, target: &str, files: &mut Vec<String>, visited: &mut HashSet<String>) {
+        if !visited.insert(target.to_string()) {
+            return;
+        }
+        let mut prerequisites: Vec<String> = self
+            .prerequisites(target)
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        let has_rule = !self.explicit_rules(target).is_empty();
+        if prerequisites.is_empty() {
+            prerequisites = self.pattern_prerequisites(target);
+        }
+        if prerequisites.is_empty() {
+            if !has_rule && !files.iter().any(|f| f == target) {
+                files.push(target.to_string());
+            }
+            return;
+        }
+        for prerequisite in prerequisites {
+            self.collect_files(&prerequisite, files, visited);
+        }
+    }
+
+    fn explicit_rules(&self, target: &str) -> Vec<&MakeRule> {
+        self.rules
+            .iter()
+            .filter(|rule| !rule.is_pattern() && rule.targets.iter().any(|t| t == target))
+            .collect()
+    }
+
+    /// Prerequisites from the first pattern rule matching a target.
+    fn pattern_prerequisites(&self, target: &str) -> Vec<String> {
+        for rule in self
+            .rules
+            .iter()
+            .filter(|rule| !rule.prerequisites.is_empty())
+        {
+            let stem = rule
+                .targets
+                .iter()
+                .find_map(|pattern| pattern_stem(pattern, target));
+            if let Some(stem) = stem {
+                return rule
+                    .prerequisites
+                    .iter()
+                    .map(|p| p.replacen('%', stem, 1))
+                    .collect();
+            }
+        }
+        Vec::new()
+    }
+
+    fn variable_map(&self) -> HashMap<String, String> {
+        self.variables
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect()
+    }
+}
+
+/// Parse a Makefile and the files it includes.
+///
+/// Include names are resolved against the top-level Makefile's directory,
+/// where make would run. Targets and prerequisites are expanded with the
+/// variables defined before them, as make does.
+pub fn parse_makefile(path: &Path) -> std::io::Result<MakefileModel> {
+    let mut parser = MakefileParser {
+        root: path.parent().unwrap_or(Path::new("")).to_path_buf(),
+        vars: HashMap::new(),
+        includes: Vec::new(),
+        rules: Vec::new(),
+        visited: HashSet::new(),
+    };
+    parser.read(path)?;
+    Ok(MakefileModel {
+        path: path.to_path_buf(),
+        variables: parser.vars.into_iter().collect(),
+        includes: parser.includes,
+        rules: parser.rules,
+    })
+}
+
+struct MakefileParser {
+    root: PathBuf,
+    vars: HashMap<String, String>,
+    includes: Vec<MakeInclude>,
+    rules: Vec<MakeRule>,
+    visited: HashSet<PathBuf>,
+}
+
+impl MakefileParser {
+    fn read(&mut self, path: &Path) -> std::io::Result<()> {
+        let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        if !self.visited.insert(canonical) {
+            return Ok(());
+        }
+        let content = std::fs::read_to_string(path)?;
+        let mut current: Option<usize> = None;
+
+        for line in logical_lines(&content) {
+            if let Some(recipe) = line.text.strip_prefix('\t') {
+                if let Some(rule) = current.and_then(|i| self.rules.get_mut(i)) {
+                    rule.recipe.push(recipe.to_string());
+                }
+                continue;
+            }
+            if line.text.trim().is_empty() {
+                continue;
+            }
+            current = None;
+
+            if assignment(&line.text).is_some() {
+                if line.conditional_depth == 0 {
+                    apply_assignment(&line.text, &mut self.vars);
+                }
+                continue;
+            }
+            if let Some((optional, names)) = include_directive(&line.text) {
+                if line.conditional_depth == 0 {
+                    self.include(optional, names)?;
+                }
+                continue;
+            }
+            if let Some(rule) = self.rule(&line.text, path) {
+                self.rules.push(rule);
+                current = Some(self.rules.len() - 1);
+            }
+        }
+        Ok(())
+    }
+
+    fn include(&mut self, optional: bool, names: &str) -> std::io::Result<()> {
+        for name in expand(names, &self.vars, 0).split_whitespace() {
+            let path = self.root.join(name);
+            let exists = path.is_file();
+            self.includes.push(MakeInclude {
+                name: name.to_string(),
+                path: exists.then(|| path.clone()),
+                optional,
+            });
+            if exists {
+                self.read(&path)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn rule(&self, line: &str, file: &Path) -> Option<MakeRule> {
+        let (targets, rest) = line.split_once(':')?;
+        let rest = rest.strip_prefix(':').unwrap_or(rest);
+        // Target-specific variables and static pattern rules aren't modelled
+        if assignment(rest).is_some() || rest.contains(':') {
+            return None;
+        }
+        let (rest, recipe) = match rest.split_once(';') {
+            Some((rest, recipe)) => (rest, vec![recipe.trim().to_string()]),
+            None => (rest, Vec::new()),
+        };
+        let (normal, order_only) = rest.split_once('|').unwrap_or((rest, ""));
+        let words = |text: &str| -> Vec<String> {
+            expand(text, &self.vars, 0)
+                .split_whitespace()
+                .map(str::to_string)
+                .collect()
+        };
+        Some(MakeRule {
+            targets: words(targets),
+            prerequisites: words(normal),
+            order_only: words(order_only),
+            recipe,
+            file: file.to_path_buf(),
+        })
+    }
+}
+
+/// Parse `include`, `-include` or `sinclude`, returning (optional, names).
+fn include_directive(line: &str) -> Option<(bool, &str)> {
+    let (keyword, names) = line.trim_start().split_once(char::is_whitespace)?;
+    match keyword {
+        "include" => Some((false, names.trim())),
+        "-include" | "sinclude" => Some((true, names.trim())),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OptimizationLevel;
+
+    const MAKEFILE: &str = "\
+# Firmware build
+MCU = -mcpu=cortex-m4 -mthumb
+INCLUDES = -Iinc \\
+           -Idrivers/inc   # vendor headers
+CFLAGS = $(MCU) -Os -g $(INCLUDES)
+CFLAGS += -DSTM32F407xx -Wall
+ifeq ($(DEBUG),1)
+CFLAGS += -DDEBUG
+endif
+LDFLAGS := -Tstm32f407.ld -Wl,--gc-sections $(shell echo -lm)
+
+.PHONY: all clean
+all: firmware.elf
+
+firmware.elf: main.o uart.o
+\t$(CC) $(LDFLAGS) -o $@ $^
+
+%.o: %.c
+\t$(CC) $(CFLAGS) -c $<
+
+clean:
+\trm -f *.o
+";
+
+    #[test]
+    fn test_detect_makefile() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("Makefile"), MAKEFILE).unwrap();
+
+        let info = detect_makefile(dir.path()).unwrap();
+        assert_eq!(info.path, dir.path().join("Makefile"));
+        assert_eq!(info.targets, vec!["all", "firmware.elf", "clean"]);
+        assert!(detect_makefile(&dir.path().join("missing")).is_none());
+    }
+
+    #[test]
+    fn test_profile_from_makefile() {
+        let profile = profile_from_makefile(MAKEFILE, "make");
+        assert_eq!(profile.optimization, OptimizationLevel::Os);
+        assert!(profile.debug);
+        assert_eq!(profile.defines, vec!["STM32F407xx"]);
+        assert_eq!(
+            profile.include_paths,
+            vec![PathBuf::from("inc"), PathBuf::from("drivers/inc")]
+        );
+        assert_eq!(profile.cflags, vec!["-mcpu=cortex-m4", "-mthumb", "-Wall"]);
+        assert_eq!(
+            profile.ldflags,
+            vec!["-Tstm32f407.ld", "-Wl,--gc-sections", "$(shell echo -lm)"]
+        );
+    }
+
+    #[test]
+    fn test_render_and_reparse() {
+        let mut profile = BuildProfile::new("release");
+        profile.add_compile_flags(&["-O2", "-DNDEBUG", "-Iinc", "-mthumb"]);
+        profile.ldflags = vec!["-Tlink.ld".to_string()];
+
+        let rendered = profile_to_makefile(&profile);
+        assert!(rendered.contains("CFLAGS = -O2 \\\n\t-DNDEBUG"));
+        assert_eq!(profile_from_makefile(&rendered, "release"), profile);
+    }
+
+    #[test]
+    fn test_update_makefile() {
+        let mut profile = BuildProfile::new("release");
+        profile.add_compile_flags(&["-O2", "-DNDEBUG"]);
+        profile.ldflags = vec!["-Tlink.ld".to_string()];
+
+        let updated = update_makefile(MAKEFILE, &profile);
+        assert_eq!(updated.matches("CFLAGS = ").count(), 1);
+        assert!(!updated.contains("CFLAGS += -DSTM32F407xx"));
+        // Conditional appends are left in place
+        assert!(updated.contains("CFLAGS += -DDEBUG"));
+        assert!(updated.contains("LDFLAGS = -Tlink.ld\n"));
+        // Other variables and rules survive
+        assert!(updated.contains("INCLUDES = -Iinc \\\n"));
+        assert!(updated.contains("\t$(CC) $(CFLAGS) -c $<"));
+
+        let reparsed = profile_from_makefile(&updated, "release");
+        assert_eq!(reparsed, profile);
+    }
+
+    #[test]
+    fn test_update_makefile_keeps_references() {
+        let mut profile = profile_from_makefile(MAKEFILE, "make");
+        profile.optimization = OptimizationLevel::O2;
+
+        let updated = update_makefile(MAKEFILE, &profile);
+        assert!(updated.contains(
+            "CFLAGS = -O2 \\\n\t-g \\\n\t-DSTM32F407xx \\\n\t$(INCLUDES) \\\n\t$(MCU) \\\n\t-Wall\n"
+        ));
+        assert!(updated.contains("\t$(shell echo -lm)\n"));
+        assert_eq!(profile_from_makefile(&updated, "make"), profile);
+
+        // A reference whose flags were edited is expanded
+        profile.cflags.retain(|flag| flag != "-mthumb");
+        let updated = update_makefile(MAKEFILE, &profile);
+        assert!(!updated.contains("\t$(MCU)"));
+        assert!(updated.contains("\t-mcpu=cortex-m4 \\\n"));
+        assert_eq!(profile_from_makefile(&updated, "make"), profile);
+    }
+
+    #[test]
+    fn test_update_makefile_keeps_cppflags_separate() {
+        let makefile = "\
+DEFS = -DSTM32F407xx -DUSE_HAL
+CPPFLAGS = $(DEFS) -Iinc
+CFLAGS = -Os -Wall
+";
+        let mut profile = profile_from_makefile(makefile, "make");
+        profile.include_paths.push(PathBuf::from("drivers/inc"));
+
+        let updated = update_makefile(makefile, &profile);
+        assert_eq!(
+            updated,
+            "\
+LDFLAGS =
+DEFS = -DSTM32F407xx -DUSE_HAL
+CPPFLAGS = $(DEFS) \\\n\t-Iinc \\\n\t-Idrivers/inc
+CFLAGS = -Os \\\n\t-Wall
+"
+        );
+        assert_eq!(profile_from_makefile(&updated, "make"), profile);
+    }
+
+    #[test]
+    fn test_apply_makefile_update() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Makefile");
+        std::fs::write(&path, MAKEFILE).unwrap();
+        let mut profile = BuildProfile::new("release");
+        profile.add_compile_flags(&["-O2"]);
+
+        let update = plan_makefile_update(&path, &profile).unwrap();
+        assert_eq!(update.original, MAKEFILE);
+        // Planning leaves the file alone
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), MAKEFILE);
+
+        let backup = apply_makefile_update(&update).unwrap();
+        assert_eq!(backup, dir.path().join("Makefile.bak"));
+        assert_eq!(std::fs::read_to_string(&backup).unwrap(), MAKEFILE);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), update.preview);
+
+        // The file no longer matches what was planned against
+        assert!(matches!(
+            apply_makefile_update(&update),
+            Err(MakefileError::StaleUpdate(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_makefile_model() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("Makefile"), MAKEFILE).unwrap();
+
+        let model = parse_makefile(&dir.path().join("Makefile")).unwrap();
+        assert_eq!(model.targets(), vec!["all", "firmware.elf", "clean"]);
+        assert_eq!(
+            model.variables["CFLAGS"],
+            "$(MCU) -Os -g $(INCLUDES) -DSTM32F407xx -Wall"
+        );
+        assert_eq!(
+            model.flags("CFLAGS"),
+            vec![
+                "-mcpu=cortex-m4",
+                "-mthumb",
+                "-Os",
+                "-g",
+                "-Iinc",
+                "-Idrivers/inc",
+                "-DSTM32F407xx",
+                "-Wall"
+            ]
+        );
+        assert_eq!(
+            model.profile("make"),
+            profile_from_makefile(MAKEFILE, "make")
+        );
+
+        let link = model
+            .rules
+            .iter()
+            .find(|r| r.targets == ["firmware.elf"])
+            .unwrap();
+        assert_eq!(link.prerequisites, vec!["main.o", "uart.o"]);
+        assert_eq!(link.recipe, vec!["$(CC) $(LDFLAGS) -o $@ $^"]);
+        assert!(model.rules.iter().any(MakeRule::is_pattern));
+
+        assert_eq!(model.prerequisites("all"), vec!["firmware.elf"]);
+        assert_eq!(model.files("all"), vec!["main.c", "uart.c"]);
+        assert!(model.files("clean").is_empty());
+    }
+
+    #[test]
+    fn test_parse_makefile_includes() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("mk")).unwrap();
+        std::fs::write(
+            dir.path().join("mk/sources.mk"),
+            "SOURCES = src/main.c src/uart.c\nSOURCES += drivers/gpio.c\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("Makefile"),
+            "\
+MK = mk
+include $(MK)/sources.mk
+-include local.mk
+OBJS = $(SOURCES:.c=.o)
+DEPS := $(SOURCES:src/%.c=build/%.d)
+
+app.elf: $(OBJS) | build
+\t$(CC) -o $@ $^
+app.elf: LDFLAGS += -Wl,--gc-sections
+build: ; mkdir -p build
+",
+        )
+        .unwrap();
+
+        let model = parse_makefile(&dir.path().join("Makefile")).unwrap();
+        assert_eq!(
+            model.includes,
+            vec![
+                MakeInclude {
+                    name: "mk/sources.mk".to_string(),
+                    path: Some(dir.path().join("mk/sources.mk")),
+                    optional: false,
+                },
+                MakeInclude {
+                    name: "local.mk".to_string(),
+                    path: None,
+                    optional: true,
+                },
+            ]
+        );
+        assert_eq!(
+            model.variable("DEPS").unwrap(),
+            "build/main.d build/uart.d drivers/gpio.c"
+        );
+
+        let app = &model.rules[0];
+        assert_eq!(
+            app.prerequisites,
+            vec!["src/main.o", "src/uart.o", "drivers/gpio.o"]
+        );
+        assert_eq!(app.order_only, vec!["build"]);
+        assert_eq!(model.rules.len(), 2);
+        assert_eq!(model.rules[1].recipe, vec!["mkdir -p build"]);
+        assert_eq!(
+            model.files("app.elf"),
+            vec!["src/main.o", "src/uart.o", "drivers/gpio.o"]
+        );
+    }
+}
@@ -0,0 +1,496 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! GNU ld map file parsing.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Output sections that occupy no target memory.
+const NON_ALLOC_PREFIXES: &[&str] = &[
+    ".debug",
+    ".comment",
+    ".ARM.attributes",
+    ".stab",
+    ".note.gnu",
+];
+
+/// A MEMORY region from the linker script.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemoryRegion {
+    /// Region name (e.g. "FLASH").
+    pub name: String,
+    /// Start address.
+    pub origin: u64,
+    /// Size in bytes.
+    pub length: u64,
+    /// Attribute string (e.g. "xr").
+    pub attributes: String,
+    /// Bytes used by output sections.
+    pub used: u64,
+}
+
+impl MemoryRegion {
+    /// Whether an address falls inside this region.
+    pub fn contains(&self, address: u64) -> bool {
+        address >= self.origin && address - self.origin < self.length
+    }
+
+    /// Used space as a percentage of the region.
+    pub fn usage_percent(&self) -> f64 {
+        if self.length == 0 {
+            0.0
+        } else {
+            self.used as f64 * 100.0 / self.length as f64
+        }
+    }
+
+    /// Unused space in the region.
+    pub fn free(&self) -> u64 {
+        self.length.saturating_sub(self.used)
+    }
+}
+
+/// A symbol listed in the map.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MapSymbol {
+    /// Symbol name.
+    pub name: String,
+    /// Address.
+    pub address: u64,
+    /// Size, estimated from the next symbol or the end of the input section.
+    pub size: u64,
+}
+
+/// An input section placed into an output section.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InputSection {
+    /// Section name (e.g. ".text.uart_init").
+    pub name: String,
+    /// Address.
+    pub address: u64,
+    /// Size in bytes.
+    pub size: u64,
+    /// Contributing object, e.g. "build/main.o" or "libc.a(memcpy.o)".
+    pub object: String,
+    /// Symbols defined in this input section.
+    pub symbols: Vec<MapSymbol>,
+}
+
+/// An output section of the image.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutputSection {
+    /// Section name (e.g. ".text").
+    pub name: String,
+    /// Run-time address.
+    pub address: u64,
+    /// Size in bytes.
+    pub size: u64,
+    /// Load address, when different from the run-time address (.data).
+    pub load_address: Option<u64>,
+    /// Input sections, in address order.
+    pub inputs: Vec<InputSection>,
+}
+
+impl OutputSection {
+    /// Whether the section occupies target memory.
+    pub fn is_allocated(&self) -> bool {
+        !NON_ALLOC_PREFIXES.iter().any(|p| self.name.starts_with(p))
+    }
+}
+
+/// Size contributed by one object file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ObjectSize {
+    /// Object file.
+    pub object: String,
+    /// Bytes per memory region.
+    pub regions: BTreeMap<String, u64>,
+    /// Total bytes across regions.
+    pub total: u64,
+}
+
+/// Size of one symbol, with the region it lives in.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SymbolSize {
+    /// Symbol name.
+    pub name: String,
+    /// Defining object.
+    pub object: String,
+    /// Address.
+    pub address: u64,
+    /// Size in bytes.
+    pub size: u64,
+    /// Memory region, if the address is inside one.
+    pub region: Option<String>,
+}
+
+/// Structured contents of a linker map file.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemoryMap {
+    /// Memory regions with usage.
+    pub regions: Vec<MemoryRegion>,
+    /// Output sections in file order.
+    pub sections: Vec<OutputSection>,
+}
+
+impl MemoryMap {
+    /// Region containing an address.
+    pub fn region_for(&self, address: u64) -> Option<&MemoryRegion> {
+        self.regions.iter().find(|r| r.contains(address))
+    }
+
+    /// Per-object sizes, largest first.
+    ///
+    /// Initialized data counts against both its run-time and load regions.
+    pub fn object_sizes(&self) -> Vec<ObjectSize> {
+        let mut by_object: BTreeMap<&str, BTreeMap<String, u64>> = BTreeMap::new();
+        for section in self.sections.iter().filter(|s| s.is_allocated()) {
+            for input in &section.inputs {
+                let regions = by_object.entry(&input.object).or_default();
+                let mut charge = |address: u64| {
+                    if let Some(region) = self.region_for(address) {
+                        *regions.entry(region.name.clone()).or_default() += input.size;
+                    }
+                };
+                charge(input.address);
+                if let Some(load) = section.load_address {
+                    charge(load + (input.address - section.address));
+                }
+            }
+        }
+
+        let mut sizes: Vec<ObjectSize> = by_object
+            .into_iter()
+            .map(|(object, regions)| ObjectSize {
+                object: object.to_string(),
+                total: regions.values().sum(),
+                regions,
+            })
+            .collect();
+        sizes.sort_by(|a, b| b.total.cmp(&a.total).then(a.object.cmp(&b.object)));
+        sizes
+    }
+
+    /// Per-symbol sizes, largest first.
+    pub fn symbol_sizes(&self) -> Vec<SymbolSize> {
+        let mut sizes: Vec<SymbolSize> = self
+            .sections
+            .iter()
+            .filter(|s| s.is_allocated())
+            .flat_map(|s| &s.inputs)
+            .flat_map(|input| {
+                input.symbols.iter().map(move |sym| SymbolSize {
+                    name: sym.name.clone(),
+                    object: input.object.clone(),
+                    address: sym.address,
+                    size: sym.size,
+                    region: self.region_for(sym.address).map(|r| r.name.clone()),
+                })
+            })
+            .collect();
+        sizes.sort_by(|a, b| b.size.cmp(&a.size).then(a.name.cmp(&b.name)));
+        sizes
+    }
+}
+
+/// Read and parse a map file.
+pub fn read_map_file(path: &Path) -> std::io::Result<MemoryMap> {
+    Ok(parse_map_file(&std::fs::read_to_string(path)?))
+}
+
+/// Parse a GNU ld map file.
+pub fn parse_map_file(content: &str) -> MemoryMap {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut map = MemoryMap::default();
+
+    let memory_start = lines
+        .iter()
+        .position(|l| l.trim() == "Memory Configuration");
+    let layout_start = lines
+        .iter()
+        .position(|l| l.trim() == "Linker script and memory map");
+
+    if let Some(start) = memory_start {
+        let end = layout_start.unwrap_or(lines.len());
+        for line in &lines[start + 1..end] {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 3 || fields[0] == "Name" || fields[0] == "*default*" {
+                continue;
+            }
+            let (Some(origin), Some(length)) = (parse_hex(fields[1]), parse_hex(fields[2])) else {
+                continue;
+            };
+            map.regions.push(MemoryRegion {
+                name: fields[0].to_string(),
+                origin,
+                length,
+                attributes: fields.get(3).unwrap_or(&"").to_string(),
+                used: 0,
+            });
+        }
+    }
+
+    if let Some(start) = layout_start {
+        parse_layout(&lines[start + 1..], &mut map.sections);
+    }
+
+    for section in map.sections.iter_mut() {
+        finish_symbol_sizes(section);
+    }
+
+    for region in map.regions.iter_mut() {
+        region.used = map
+            .sections
+            .iter()
+            .filter(|s| s.is_allocated() && s.size > 0)
+            .map(|s| {
+                let run = region.contains(s.address);
+                let load = s.load_address.map(|a| region.contains(a)).unwrap_or(false);
+                if run || load {
+                    s.size
+                } else {
+                    0
+                }
+            })
+            .sum();
+    }
+
+    map
+}
+
+fn parse_layout(lines: &[&str], sections: &mut Vec<OutputSection>) {
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        i += 1;
+
+        let indented = line.starts_with(' ');
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        // Long names put the address and size on the next line
+        let mut fields: Vec<&str> = trimmed.split_whitespace().collect();
+        if fields.len() == 1 && i < lines.len() && lines[i].trim_start().starts_with("0x") {
+            fields.extend(lines[i].split_whitespace());
+            i += 1;
+        }
+
+        if !indented {
+            // Output section: ".text 0xADDR 0xSIZE [load address 0xLMA]"
+            let (Some(address), Some(size)) = (
+                fields.get(1).and_then(|f| parse_hex(f)),
+                fields.get(2).and_then(|f| parse_hex(f)),
+            ) else {
+                continue;
+            };
+            if fields[0] == "/DISCARD/" {
+                continue;
+            }
+            let load_address = fields
+                .windows(3)
+                .find(|w| w[0] == "load" && w[1] == "address")
+                .and_then(|w| parse_hex(w[2]));
+            sections.push(OutputSection {
+                name: fields[0].to_string(),
+                address,
+                size,
+                load_address,
+                inputs: Vec::new(),
+            });
+            continue;
+        }
+
+        let Some(section) = sections.last_mut() else {
+            continue;
+        };
+
+        if let Some(address) = parse_hex(fields[0]) {
+            // Symbol: "0xADDR name"; skip assignments and PROVIDE lines
+            if fields.len() == 2 && !fields[1].contains(['=', '(']) {
+                if let Some(input) = section.inputs.last_mut() {
+                    if address >= input.address && address <= input.address + input.size {
+                        input.symbols.push(MapSymbol {
+                            name: fields[1].to_string(),
+                            address,
+                            size: 0,
+                        });
+                    }
+                }
+            }
+            continue;
+        }
+
+        // Input section: ".text 0xADDR 0xSIZE object"
+        if fields[0].starts_with('*') || fields.len() < 4 {
+            continue;
+        }
+        let (Some(address), Some(size)) = (parse_hex(fields[1]), parse_hex(fields[2])) else {
+            continue;
+        };
+        if size == 0 {
+            continue;
+        }
+        section.inputs.push(InputSection {
+            name: fields[0].to_string(),
+            address,
+            size,
+            object: fields[3..].join(" "),
+            symbols: Vec::new(),
+        });
+    }
+}
+
+/// Estimate symbol sizes from the distance to the next symbol.
+fn finish_symbol_sizes(section: &mut OutputSection) {
+    for input in section.inputs.iter_mut() {
+        input.symbols.sort_by_key(|s| s.address);
+        let end = input.address + input.size;
+        let next: Vec<u64> = input
+            .symbols
+            .iter()
+            .skip(1)
+            .map(|s| s.address)
+            .chain(std::iter::once(end))
+            .collect();
+        for (symbol, next) in input.symbols.iter_mut().zip(next) {
+            symbol.size = next.saturating_sub(symbol.address);
+        }
+    }
+}
+
+fn parse_hex(s: &str) -> Option<u64> {
+    u64::from_str_radix(s.strip_prefix("0x")?, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAP: &str = "\
+Archive member included to satisfy reference by file (symbol)
+
+/opt/arm/lib/libc_nano.a(lib_a-memcpy.o)
+                              build/main.o (memcpy)
+
+Discarded input sections
+
+ .text          0x0000000000000000        0x0 build/main.o
+
+Memory Configuration
+
+Name             Origin             Length             Attributes
+FLASH            0x0000000008000000 0x0000000000100000 xr
+RAM              0x0000000020000000 0x0000000000020000 xrw
+*default*        0x0000000000000000 0xffffffffffffffff
+
+Linker script and memory map
+
+LOAD build/startup.o
+LOAD build/main.o
+                0x0000000020020000                _estack = 0x20020000
+
+.isr_vector     0x0000000008000000      0x188
+ *(.isr_vector)
+ .isr_vector    0x0000000008000000      0x188 build/startup.o
+                0x0000000008000000                g_pfnVectors
+                0x0000000008000188                . = ALIGN (0x4)
+
+.text           0x0000000008000188       0xa8
+ *(.text)
+ .text.main     0x0000000008000188       0x40 build/main.o
+                0x0000000008000188                main
+ .text.uart_init_with_long_name
+                0x00000000080001c8       0x48 build/uart.o
+                0x00000000080001c8                uart_init
+                0x00000000080001f0                uart_write
+ *fill*         0x0000000008000210        0x2 
+ .text          0x0000000008000212       0x1e /opt/arm/lib/libc_nano.a(lib_a-memcpy.o)
+                0x0000000008000212                memcpy
+
+.data           0x0000000020000000       0x10 load address 0x0000000008000230
+ .data.counter  0x0000000020000000       0x10 build/main.o
+                0x0000000020000000                counter
+
+.bss            0x0000000020000010      0x400
+ .bss.rx_buf    0x0000000020000010      0x400 build/uart.o
+                0x0000000020000010                rx_buf
+
+.debug_info     0x0000000000000000     0x2000
+ .debug_info    0x0000000000000000     0x2000 build/main.o
+
+/DISCARD/
+ libc.a(*)
+OUTPUT(build/firmware.elf elf32-littlearm)
+";
+
+    #[test]
+    fn test_parse_regions() {
+        let map = parse_map_file(MAP);
+        assert_eq!(map.regions.len(), 2);
+
+        let flash = &map.regions[0];
+        assert_eq!(flash.name, "FLASH");
+        assert_eq!(flash.origin, 0x0800_0000);
+        assert_eq!(flash.length, 0x10_0000);
+        assert_eq!(flash.attributes, "xr");
+        // .isr_vector + .text + .data load image
+        assert_eq!(flash.used, 0x188 + 0xa8 + 0x10);
+
+        let ram = &map.regions[1];
+        assert_eq!(ram.used, 0x10 + 0x400);
+        assert!(ram.usage_percent() > 0.7 && ram.usage_percent() < 0.8);
+    }
+
+    #[test]
+    fn test_parse_sections() {
+        let map = parse_map_file(MAP);
+        let names: Vec<_> = map.sections.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![".isr_vector", ".text", ".data", ".bss", ".debug_info"]
+        );
+
+        let text = &map.sections[1];
+        assert_eq!(text.inputs.len(), 3);
+        assert_eq!(text.inputs[1].name, ".text.uart_init_with_long_name");
+        assert_eq!(text.inputs[1].address, 0x0800_01c8);
+        assert_eq!(text.inputs[1].object, "build/uart.o");
+        assert_eq!(
+            text.inputs[2].object,
+            "/opt/arm/lib/libc_nano.a(lib_a-memcpy.o)"
+        );
+
+        let uart = &text.inputs[1].symbols;
+        assert_eq!(uart.len(), 2);
+        assert_eq!(uart[0].size, 0x28);
+        assert_eq!(uart[1].size, 0x20);
+
+        assert_eq!(map.sections[2].load_address, Some(0x0800_0230));
+        assert!(!map.sections[4].is_allocated());
+        // Linker-script assignments are not symbols
+        assert_eq!(map.sections[0].inputs[0].symbols.len(), 1);
+    }
+
+    #[test]
+    fn test_object_and_symbol_sizes() {
+        let map = parse_map_file(MAP);
+
+        let objects = map.object_sizes();
+        assert_eq!(objects[0].object, "build/uart.o");
+        assert_eq!(objects[0].regions["RAM"], 0x400);
+        assert_eq!(objects[0].regions["FLASH"], 0x48);
+
+        let main = objects.iter().find(|o| o.object == "build/main.o").unwrap();
+        assert_eq!(main.regions["FLASH"], 0x40 + 0x10);
+        assert_eq!(main.regions["RAM"], 0x10);
+
+        let symbols = map.symbol_sizes();
+        assert_eq!(symbols[0].name, "rx_buf");
+        assert_eq!(symbols[0].size, 0x400);
+        assert_eq!(symbols[0].region.as_deref(), Some("RAM"));
+    }
+}
@@ -0,0 +1,538 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Built-in database of common Cortex-M parts.
+//!
+//! Maps part numbers to machine flags, device defines and memory layout so
+//! a project can be configured by choosing "STM32F407VG" rather than
+//! entering `-mcpu`/`-mfpu` strings by hand. Lookups accept full ordering
+//! codes: "STM32F407VGT6" resolves to the "STM32F407VG" entry.
+
+use crate::{ArmMcuConfig, FloatAbi, McuMemory, MemoryBlock};
+use serde::{Deserialize, Serialize};
+
+/// Cortex-M core variant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Core {
+    M0,
+    M0Plus,
+    M3,
+    /// Cortex-M4 with single-precision FPU.
+    M4F,
+    /// Cortex-M7 with single-precision FPU.
+    M7F,
+    /// Cortex-M7 with double-precision FPU.
+    M7D,
+    /// Cortex-M33 with single-precision FPU.
+    M33F,
+}
+
+impl Core {
+    fn name(self) -> &'static str {
+        match self {
+            Core::M0 => "Cortex-M0",
+            Core::M0Plus => "Cortex-M0+",
+            Core::M3 => "Cortex-M3",
+            Core::M4F => "Cortex-M4F",
+            Core::M7F | Core::M7D => "Cortex-M7F",
+            Core::M33F => "Cortex-M33F",
+        }
+    }
+
+    fn config(self) -> ArmMcuConfig {
+        match self {
+            Core::M0 => ArmMcuConfig::new("cortex-m0"),
+            Core::M0Plus => ArmMcuConfig::new("cortex-m0plus"),
+            Core::M3 => ArmMcuConfig::new("cortex-m3"),
+            Core::M4F => ArmMcuConfig::new("cortex-m4").with_fpu("fpv4-sp-d16", FloatAbi::Hard),
+            Core::M7F => ArmMcuConfig::new("cortex-m7").with_fpu("fpv5-sp-d16", FloatAbi::Hard),
+            Core::M7D => ArmMcuConfig::new("cortex-m7").with_fpu("fpv5-d16", FloatAbi::Hard),
+            Core::M33F => ArmMcuConfig::new("cortex-m33").with_fpu("fpv5-sp-d16", FloatAbi::Hard),
+        }
+    }
+}
+
+/// A memory region beyond the main flash and RAM.
+struct ExtraRegion {
+    name: &'static str,
+    origin: u64,
+    size_kb: u64,
+}
+
+/// A database entry.
+struct Part {
+    part: &'static str,
+    vendor: &'static str,
+    family: &'static str,
+    core: Core,
+    defines: &'static [&'static str],
+    flash_origin: u64,
+    flash_kb: u64,
+    ram_origin: u64,
+    ram_kb: u64,
+    extra: &'static [ExtraRegion],
+}
+
+const STM32_FLASH: u64 = 0x0800_0000;
+const SRAM: u64 = 0x2000_0000;
+
+const PARTS: &[Part] = &[
+    // STMicroelectronics
+    Part {
+        part: "STM32F030F4",
+        vendor: "STMicroelectronics",
+        family: "STM32F0",
+        core: Core::M0,
+        defines: &["STM32F030x6"],
+        flash_origin: STM32_FLASH,
+        flash_kb: 16,
+        ram_origin: SRAM,
+        ram_kb: 4,
+        extra: &[],
+    },
+    Part {
+        part: "STM32F103C8",
+        vendor: "STMicroelectronics",
+        family: "STM32F1",
+        core: Core::M3,
+        defines: &["STM32F103xB"],
+        flash_origin: STM32_FLASH,
+        flash_kb: 64,
+        ram_origin: SRAM,
+        ram_kb: 20,
+        extra: &[],
+    },
+    Part {
+        part: "STM32F103RB",
+        vendor: "STMicroelectronics",
+        family: "STM32F1",
+        core: Core::M3,
+        defines: &["STM32F103xB"],
+        flash_origin: STM32_FLASH,
+        flash_kb: 128,
+        ram_origin: SRAM,
+        ram_kb: 20,
+        extra: &[],
+    },
+    Part {
+        part: "STM32F303RE",
+        vendor: "STMicroelectronics",
+        family: "STM32F3",
+        core: Core::M4F,
+        defines: &["STM32F303xE"],
+        flash_origin: STM32_FLASH,
+        flash_kb: 512,
+        ram_origin: SRAM,
+        ram_kb: 64,
+        extra: &[ExtraRegion {
+            name: "CCMRAM",
+            origin: 0x1000_0000,
+            size_kb: 16,
+        }],
+    },
+    Part {
+        part: "STM32F401RE",
+        vendor: "STMicroelectronics",
+        family: "STM32F4",
+        core: Core::M4F,
+        defines: &["STM32F401xE"],
+        flash_origin: STM32_FLASH,
+        flash_kb: 512,
+        ram_origin: SRAM,
+        ram_kb: 96,
+        extra: &[],
+    },
+    Part {
+        part: "STM32F407VG",
+        vendor: "STMicroelectronics",
+        family: "STM32F4",
+        core: Core::M4F,
+        defines: &["STM32F407xx"],
+        flash_origin: STM32_FLASH,
+        flash_kb: 1024,
+        ram_origin: SRAM,
+        ram_kb: 128,
+        extra: &[ExtraRegion {
+            name: "CCMRAM",
+            origin: 0x1000_0000,
+            size_kb: 64,
+        }],
+    },
+    Part {
+        part: "STM32F411RE",
+        vendor: "STMicroelectronics",
+        family: "STM32F4",
+        core: Core::M4F,
+        defines: &["STM32F411xE"],
+        flash_origin: STM32_FLASH,
+        flash_kb: 512,
+        ram_origin: SRAM,
+        ram_kb: 128,
+        extra: &[],
+    },
+    Part {
+        part: "STM32F446RE",
+        vendor: "STMicroelectronics",
+        family: "STM32F4",
+        core: Core::M4F,
+        defines: &["STM32F446xx"],
+        flash_origin: STM32_FLASH,
+        flash_kb: 512,
+        ram_origin: SRAM,
+        ram_kb: 128,
+        extra: &[],
+    },
+    Part {
+        part: "STM32F746ZG",
+        vendor: "STMicroelectronics",
+        family: "STM32F7",
+        core: Core::M7F,
+        defines: &["STM32F746xx"],
+        flash_origin: STM32_FLASH,
+        flash_kb: 1024,
+        ram_origin: SRAM,
+        ram_kb: 320,
+        extra: &[],
+    },
+    Part {
+        part: "STM32H743ZI",
+        vendor: "STMicroelectronics",
+        family: "STM32H7",
+        core: Core::M7D,
+        defines: &["STM32H743xx"],
+        flash_origin: STM32_FLASH,
+        flash_kb: 2048,
+        ram_origin: 0x2400_0000,
+        ram_kb: 512,
+        extra: &[ExtraRegion {
+            name: "DTCMRAM",
+            origin: SRAM,
+            size_kb: 128,
+        }],
+    },
+    Part {
+        part: "STM32G071RB",
+        vendor: "STMicroelectronics",
+        family: "STM32G0",
+        core: Core::M0Plus,
+        defines: &["STM32G071xx"],
+        flash_origin: STM32_FLASH,
+        flash_kb: 128,
+        ram_origin: SRAM,
+        ram_kb: 36,
+        extra: &[],
+    },
+    Part {
+        part: "STM32L053R8",
+        vendor: "STMicroelectronics",
+        family: "STM32L0",
+        core: Core::M0Plus,
+        defines: &["STM32L053xx"],
+        flash_origin: STM32_FLASH,
+        flash_kb: 64,
+        ram_origin: SRAM,
+        ram_kb: 8,
+        extra: &[],
+    },
+    Part {
+        part: "STM32L432KC",
+        vendor: "STMicroelectronics",
+        family: "STM32L4",
+        core: Core::M4F,
+        defines: &["STM32L432xx"],
+        flash_origin: STM32_FLASH,
+        flash_kb: 256,
+        ram_origin: SRAM,
+        ram_kb: 48,
+        extra: &[ExtraRegion {
+            name: "RAM2",
+            origin: 0x1000_0000,
+            size_kb: 16,
+        }],
+    },
+    Part {
+        part: "STM32L476RG",
+        vendor: "STMicroelectronics",
+        family: "STM32L4",
+        core: Core::M4F,
+        defines: &["STM32L476xx"],
+        flash_origin: STM32_FLASH,
+        flash_kb: 1024,
+        ram_origin: SRAM,
+        ram_kb: 96,
+        extra: &[ExtraRegion {
+            name: "RAM2",
+            origin: 0x1000_0000,
+            size_kb: 32,
+        }],
+    },
+    // NXP
+    Part {
+        part: "LPC1768",
+        vendor: "NXP",
+        family: "LPC17xx",
+        core: Core::M3,
+        defines: &["CORE_M3", "__LPC17XX__"],
+        flash_origin: 0,
+        flash_kb: 512,
+        ram_origin: 0x1000_0000,
+        ram_kb: 32,
+        extra: &[ExtraRegion {
+            name: "AHBRAM",
+            origin: 0x2007_C000,
+            size_kb: 32,
+        }],
+    },
+    Part {
+        part: "MK64FN1M0VLL12",
+        vendor: "NXP",
+        family: "Kinetis K64",
+        core: Core::M4F,
+        defines: &["CPU_MK64FN1M0VLL12"],
+        flash_origin: 0,
+        flash_kb: 1024,
+        ram_origin: SRAM,
+        ram_kb: 192,
+        extra: &[ExtraRegion {
+            name: "SRAM_L",
+            origin: 0x1FFF_0000,
+            size_kb: 64,
+        }],
+    },
+    Part {
+        part: "LPC55S69JBD100",
+        vendor: "NXP",
+        family: "LPC55xx",
+        core: Core::M33F,
+        defines: &["CPU_LPC55S69JBD100_cm33_core0"],
+        flash_origin: 0,
+        flash_kb: 608,
+        ram_origin: SRAM,
+        ram_kb: 256,
+        extra: &[],
+    },
+    // Nordic Semiconductor
+    Part {
+        part: "nRF51822_XXAA",
+        vendor: "Nordic Semiconductor",
+        family: "nRF51",
+        core: Core::M0,
+        defines: &["NRF51", "NRF51822_XXAA"],
+        flash_origin: 0,
+        flash_kb: 256,
+        ram_origin: SRAM,
+        ram_kb: 16,
+        extra: &[],
+    },
+    Part {
+        part: "nRF52832_XXAA",
+        vendor: "Nordic Semiconductor",
+        family: "nRF52",
+        core: Core::M4F,
+        defines: &["NRF52", "NRF52832_XXAA"],
+        flash_origin: 0,
+        flash_kb: 512,
+        ram_origin: SRAM,
+        ram_kb: 64,
+        extra: &[],
+    },
+    Part {
+        part: "nRF52840_XXAA",
+        vendor: "Nordic Semiconductor",
+        family: "nRF52",
+        core: Core::M4F,
+        defines: &["NRF52840_XXAA"],
+        flash_origin: 0,
+        flash_kb: 1024,
+        ram_origin: SRAM,
+        ram_kb: 256,
+        extra: &[],
+    },
+    Part {
+        part: "nRF5340_XXAA_APPLICATION",
+        vendor: "Nordic Semiconductor",
+        family: "nRF53",
+        core: Core::M33F,
+        defines: &["NRF5340_XXAA_APPLICATION"],
+        flash_origin: 0,
+        flash_kb: 1024,
+        ram_origin: SRAM,
+        ram_kb: 512,
+        extra: &[],
+    },
+    // Microchip
+    Part {
+        part: "ATSAMD21G18A",
+        vendor: "Microchip",
+        family: "SAM D21",
+        core: Core::M0Plus,
+        defines: &["__SAMD21G18A__"],
+        flash_origin: 0,
+        flash_kb: 256,
+        ram_origin: SRAM,
+        ram_kb: 32,
+        extra: &[],
+    },
+    Part {
+        part: "ATSAMD51J19A",
+        vendor: "Microchip",
+        family: "SAM D51",
+        core: Core::M4F,
+        defines: &["__SAMD51J19A__"],
+        flash_origin: 0,
+        flash_kb: 512,
+        ram_origin: SRAM,
+        ram_kb: 192,
+        extra: &[],
+    },
+    // Texas Instruments
+    Part {
+        part: "TM4C123GH6PM",
+        vendor: "Texas Instruments",
+        family: "Tiva C",
+        core: Core::M4F,
+        defines: &["PART_TM4C123GH6PM"],
+        flash_origin: 0,
+        flash_kb: 256,
+        ram_origin: SRAM,
+        ram_kb: 32,
+        extra: &[],
+    },
+];
+
+/// A part from the built-in database.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct McuInfo {
+    /// Part number (e.g. "STM32F407VG").
+    pub part: String,
+    /// Vendor name.
+    pub vendor: String,
+    /// Product family (e.g. "STM32F4").
+    pub family: String,
+    /// Core name (e.g. "Cortex-M4F").
+    pub core: String,
+    /// Machine flags and device defines.
+    pub config: ArmMcuConfig,
+    /// Flash/RAM layout.
+    pub memory: McuMemory,
+}
+
+impl Part {
+    fn info(&self) -> McuInfo {
+        let mut config = self.core.config();
+        for define in self.defines {
+            config = config.with_define(*define);
+        }
+
+        let mut memory = McuMemory::new(
+            self.flash_origin,
+            self.flash_kb * 1024,
+            self.ram_origin,
+            self.ram_kb * 1024,
+        );
+        for region in self.extra {
+            memory = memory.with_region(MemoryBlock::new(
+                region.name,
+                region.origin,
+                region.size_kb * 1024,
+                "xrw",
+            ));
+        }
+
+        McuInfo {
+            part: self.part.to_string(),
+            vendor: self.vendor.to_string(),
+            family: self.family.to_string(),
+            core: self.core.name().to_string(),
+            config,
+            memory,
+        }
+    }
+}
+
+/// All parts in the database.
+pub fn list_mcus() -> Vec<McuInfo> {
+    PARTS.iter().map(Part::info).collect()
+}
+
+/// Look up a part number, case-insensitively.
+///
+/// Ordering codes with package or temperature suffixes match the longest
+/// database entry they start with.
+pub fn lookup_mcu(part: &str) -> Option<McuInfo> {
+    let query = part.trim().to_ascii_uppercase();
+    PARTS
+        .iter()
+        .filter(|p| query.starts_with(&p.part.to_ascii_uppercase()))
+        .max_by_key(|p| p.part.len())
+        .map(Part::info)
+}
+
+/// Parts whose part number, family or vendor contains `query`.
+pub fn search_mcus(query: &str) -> Vec<McuInfo> {
+    let query = query.trim().to_ascii_lowercase();
+    PARTS
+        .iter()
+        .filter(|p| {
+            [p.part, p.family, p.vendor]
+                .iter()
+                .any(|field| field.to_ascii_lowercase().contains(&query))
+        })
+        .map(Part::info)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generate_linker_script, LinkerScriptOptions};
+
+    #[test]
+    fn test_lookup_ordering_code() {
+        let mcu = lookup_mcu("stm32f407vgt6").unwrap();
+        assert_eq!(mcu.part, "STM32F407VG");
+        assert_eq!(mcu.core, "Cortex-M4F");
+        assert_eq!(
+            mcu.config.machine_flags(),
+            vec![
+                "-mcpu=cortex-m4",
+                "-mthumb",
+                "-mfpu=fpv4-sp-d16",
+                "-mfloat-abi=hard"
+            ]
+        );
+        assert_eq!(mcu.config.defines, vec!["STM32F407xx"]);
+        assert_eq!(mcu.memory.flash.size, 1024 * 1024);
+        assert_eq!(mcu.memory.ram.size, 128 * 1024);
+        assert_eq!(mcu.memory.extra[0].name, "CCMRAM");
+
+        assert!(lookup_mcu("STM32F9").is_none());
+    }
+
+    #[test]
+    fn test_search() {
+        let nordic = search_mcus("nrf52");
+        let parts: Vec<_> = nordic.iter().map(|m| m.part.as_str()).collect();
+        assert_eq!(parts, vec!["nRF52832_XXAA", "nRF52840_XXAA"]);
+
+        assert!(search_mcus("nxp").iter().all(|m| m.vendor == "NXP"));
+    }
+
+    #[test]
+    fn test_database_is_consistent() {
+        let mcus = list_mcus();
+        for (i, mcu) in mcus.iter().enumerate() {
+            assert!(
+                mcus[..i].iter().all(|m| m.part != mcu.part),
+                "duplicate part {}",
+                mcu.part
+            );
+            assert_eq!(lookup_mcu(&mcu.part).unwrap().part, mcu.part);
+            assert!(
+                generate_linker_script(&mcu.memory, &LinkerScriptOptions::default()).is_ok(),
+                "bad memory layout for {}",
+                mcu.part
+            );
+        }
+    }
+}
@@ -0,0 +1,376 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Compiler-version-aware diagnostic normalization.
+//!
+//! Maps compiler diagnostics to stable codes so baselines, suppressions and
+//! statistics survive toolchain upgrades. Warnings are keyed by their `-W`
+//! option, with aliases for options that were split or renamed between
+//! releases; known errors without an option are matched by message template.
+//! Unknown diagnostics are left without a code.
+
+use crate::ToolchainKind;
+use axiom_core::{Diagnostic, Severity};
+
+/// Compiler family for normalization purposes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Family {
+    Gcc,
+    Clang,
+}
+
+impl From<ToolchainKind> for Family {
+    fn from(kind: ToolchainKind) -> Self {
+        match kind {
+            ToolchainKind::Clang => Family::Clang,
+            _ => Family::Gcc,
+        }
+    }
+}
+
+/// Inclusive-exclusive range of compiler major versions.
+#[derive(Debug, Clone, Copy)]
+struct Versions {
+    since: u32,
+    until: u32,
+}
+
+const ALL: Versions = Versions {
+    since: 0,
+    until: u32::MAX,
+};
+
+impl Versions {
+    const fn since(since: u32) -> Self {
+        Self {
+            since,
+            until: u32::MAX,
+        }
+    }
+
+    /// Unknown versions match every range.
+    fn contains(&self, major: Option<u32>) -> bool {
+        match major {
+            Some(v) => v >= self.since && v < self.until,
+            None => true,
+        }
+    }
+}
+
+/// A warning option renamed or split out in a compiler release.
+struct OptionAlias {
+    family: Family,
+    versions: Versions,
+    option: &'static str,
+    code: &'static str,
+}
+
+const OPTION_ALIASES: &[OptionAlias] = &[
+    // Clang 16 split function pointer mismatches out of -Wincompatible-pointer-types
+    OptionAlias {
+        family: Family::Clang,
+        versions: Versions::since(16),
+        option: "incompatible-function-pointer-types",
+        code: "incompatible-pointer-types",
+    },
+    // GCC 11 split reads out of -Wstringop-overflow
+    OptionAlias {
+        family: Family::Gcc,
+        versions: Versions::since(11),
+        option: "stringop-overread",
+        code: "stringop-overflow",
+    },
+    // GCC 13 reports enum/int prototype mismatches separately from conflicting types
+    OptionAlias {
+        family: Family::Gcc,
+        versions: Versions::since(13),
+        option: "enum-int-mismatch",
+        code: "conflicting-types",
+    },
+    // Clang's flow-sensitive equivalent of GCC's -Wmaybe-uninitialized
+    OptionAlias {
+        family: Family::Clang,
+        versions: ALL,
+        option: "sometimes-uninitialized",
+        code: "maybe-uninitialized",
+    },
+];
+
+/// Known diagnostics without a warning option: family, template prefix, code.
+const KNOWN_MESSAGES: &[(Family, &str, &str)] = &[
+    (Family::Gcc, "'_' undeclared", "undeclared-identifier"),
+    (
+        Family::Clang,
+        "use of undeclared identifier '_'",
+        "undeclared-identifier",
+    ),
+    (
+        Family::Gcc,
+        "_: No such file or directory",
+        "missing-include",
+    ),
+    (Family::Clang, "'_' file not found", "missing-include"),
+    (
+        Family::Gcc,
+        "too few arguments to function '_'",
+        "too-few-arguments",
+    ),
+    (
+        Family::Clang,
+        "too few arguments to function call",
+        "too-few-arguments",
+    ),
+    (
+        Family::Gcc,
+        "too many arguments to function '_'",
+        "too-many-arguments",
+    ),
+    (
+        Family::Clang,
+        "too many arguments to function call",
+        "too-many-arguments",
+    ),
+    (
+        Family::Gcc,
+        "conflicting types for '_'",
+        "conflicting-types",
+    ),
+    (
+        Family::Clang,
+        "conflicting types for '_'",
+        "conflicting-types",
+    ),
+    (Family::Gcc, "redefinition of '_'", "redefinition"),
+    (Family::Clang, "redefinition of '_'", "redefinition"),
+    (Family::Gcc, "expected '_'", "expected-token"),
+    (Family::Clang, "expected '_'", "expected-token"),
+    (Family::Gcc, "expected identifier", "expected-token"),
+    (Family::Clang, "expected identifier", "expected-token"),
+    (Family::Gcc, "unknown type name '_'", "unknown-type-name"),
+    (Family::Clang, "unknown type name '_'", "unknown-type-name"),
+];
+
+/// Leading major version of a compiler version string ("13.2.1" -> 13).
+pub fn compiler_major_version(version: &str) -> Option<u32> {
+    let digits: String = version
+        .trim()
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .collect();
+    digits.parse().ok()
+}
+
+/// Message text with location, severity, option and variable parts removed.
+///
+/// Quoted names become `'_'` and numbers become `N`, so
+/// "main.c:3:9: warning: unused variable 'x' [-Wunused-variable]" gives
+/// "unused variable '_'".
+pub fn diagnostic_template(message: &str) -> String {
+    let mut text = strip_severity(message);
+    if let Some((rest, _)) = split_option(text) {
+        text = rest;
+    }
+
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let close = match c {
+            '\'' => Some('\''),
+            '"' => Some('"'),
+            '\u{2018}' => Some('\u{2019}'),
+            '\u{201c}' => Some('\u{201d}'),
+            _ => None,
+        };
+        if let Some(close) = close {
+            for c in chars.by_ref() {
+                if c == close {
+                    break;
+                }
+            }
+            out.push_str("'_'");
+        } else if c.is_ascii_digit() {
+            while chars.peek().is_some_and(char::is_ascii_digit) {
+                chars.next();
+            }
+            out.push('N');
+        } else {
+            out.push(c);
+        }
+    }
+
+    // GCC prints missing headers unquoted: "foo.h: No such file or directory"
+    if let Some(idx) = out.find(": No such file or directory") {
+        out.replace_range(..idx, "_");
+    }
+
+    out.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Stable code for a diagnostic message, if it is known.
+pub fn normalized_code(kind: ToolchainKind, major: Option<u32>, message: &str) -> Option<String> {
+    let family = Family::from(kind);
+
+    if let Some((_, option)) = split_option(strip_severity(message)) {
+        let code = OPTION_ALIASES
+            .iter()
+            .find(|a| a.family == family && a.option == option && a.versions.contains(major))
+            .map_or(option, |a| a.code);
+        return Some(code.to_string());
+    }
+
+    let template = diagnostic_template(message);
+    KNOWN_MESSAGES
+        .iter()
+        .find(|(f, prefix, _)| *f == family && template.starts_with(prefix))
+        .map(|(_, _, code)| code.to_string())
+}
+
+/// Assign stable codes to compiler errors and warnings that have none.
+pub fn normalize_diagnostics(diagnostics: &mut [Diagnostic], kind: ToolchainKind, version: &str) {
+    let major = compiler_major_version(version);
+    for diag in diagnostics.iter_mut() {
+        if diag.code.is_some() || !matches!(diag.severity, Severity::Error | Severity::Warning) {
+            continue;
+        }
+        diag.code = normalized_code(kind, major, &diag.message);
+    }
+}
+
+/// Message text after the `file:line:col: severity:` prefix.
+fn strip_severity(message: &str) -> &str {
+    for marker in ["fatal error:", "error:", "warning:"] {
+        if let Some(idx) = message.find(marker) {
+            return message[idx + marker.len()..].trim();
+        }
+    }
+    message.trim()
+}
+
+/// Split a trailing `[-Woption]` into text and option name.
+///
+/// Handles `-Werror=option` (GCC), `-Werror,-Woption` (Clang) and
+/// GCC's `=` suffix on options taking a level (`-Wformat=`).
+fn split_option(text: &str) -> Option<(&str, &str)> {
+    let text = text.trim_end();
+    let open = text.strip_suffix(']')?.rfind('[')?;
+    let inner = &text[open + 1..text.len() - 1];
+    let last = inner.rsplit(',').next()?;
+    let option = last
+        .strip_prefix("-Werror=")
+        .or_else(|| last.strip_prefix("-W"))?
+        .trim_end_matches('=');
+    if option.is_empty() {
+        return None;
+    }
+    Some((text[..open].trim_end(), option))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_major_version() {
+        assert_eq!(compiler_major_version("13.2.1"), Some(13));
+        assert_eq!(compiler_major_version("10"), Some(10));
+        assert_eq!(compiler_major_version("unknown"), None);
+    }
+
+    #[test]
+    fn test_template() {
+        assert_eq!(
+            diagnostic_template("main.c:3:9: warning: unused variable 'x' [-Wunused-variable]"),
+            "unused variable '_'"
+        );
+        assert_eq!(
+            diagnostic_template(
+                "uart.c:12:5: error: too few arguments to function \u{2018}f\u{2019}; expected 2, have 1"
+            ),
+            "too few arguments to function '_'; expected N, have N"
+        );
+        assert_eq!(
+            diagnostic_template("main.c:1:10: fatal error: board.h: No such file or directory"),
+            "_: No such file or directory"
+        );
+    }
+
+    #[test]
+    fn test_gcc_wording_changes_keep_code() {
+        let gcc10 = "main.c:7:5: warning: 'x' may be used uninitialized in this function [-Wmaybe-uninitialized]";
+        let gcc13 = "main.c:7:5: warning: 'x' may be used uninitialized [-Wmaybe-uninitialized]";
+        let clang = "main.c:7:5: warning: variable 'x' is used uninitialized whenever 'if' condition is false [-Wsometimes-uninitialized]";
+        for (kind, major, msg) in [
+            (ToolchainKind::Gcc, Some(10), gcc10),
+            (ToolchainKind::ArmGcc, Some(13), gcc13),
+            (ToolchainKind::Clang, Some(17), clang),
+        ] {
+            assert_eq!(
+                normalized_code(kind, major, msg).as_deref(),
+                Some("maybe-uninitialized")
+            );
+        }
+    }
+
+    #[test]
+    fn test_werror_forms() {
+        let gcc = "main.c:3:9: error: unused variable 'x' [-Werror=unused-variable]";
+        let clang = "main.c:3:9: error: unused variable 'x' [-Werror,-Wunused-variable]";
+        let format = "main.c:4:1: warning: format '%d' expects argument of type 'int' [-Wformat=]";
+        assert_eq!(
+            normalized_code(ToolchainKind::Gcc, None, gcc).as_deref(),
+            Some("unused-variable")
+        );
+        assert_eq!(
+            normalized_code(ToolchainKind::Clang, None, clang).as_deref(),
+            Some("unused-variable")
+        );
+        assert_eq!(
+            normalized_code(ToolchainKind::Gcc, None, format).as_deref(),
+            Some("format")
+        );
+    }
+
+    #[test]
+    fn test_version_gated_alias() {
+        let msg = "a.c:5:9: error: incompatible function pointer types assigning to 'f' from 'g' [-Wincompatible-function-pointer-types]";
+        assert_eq!(
+            normalized_code(ToolchainKind::Clang, Some(16), msg).as_deref(),
+            Some("incompatible-pointer-types")
+        );
+        assert_eq!(
+            normalized_code(ToolchainKind::Clang, Some(15), msg).as_deref(),
+            Some("incompatible-function-pointer-types")
+        );
+    }
+
+    #[test]
+    fn test_known_errors() {
+        let gcc = "main.c:4:5: error: 'count' undeclared (first use in this function)";
+        let clang = "main.c:4:5: error: use of undeclared identifier 'count'";
+        assert_eq!(
+            normalized_code(ToolchainKind::Gcc, Some(13), gcc).as_deref(),
+            Some("undeclared-identifier")
+        );
+        assert_eq!(
+            normalized_code(ToolchainKind::Clang, Some(17), clang).as_deref(),
+            Some("undeclared-identifier")
+        );
+        assert_eq!(
+            normalized_code(ToolchainKind::Gcc, None, "main.c:1:1: error: something new"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_normalize_diagnostics() {
+        let mut diags = vec![
+            Diagnostic::warning("a.c:1:1: warning: unused variable 'x' [-Wunused-variable]"),
+            Diagnostic::warning("a.c:2:1: warning: bad [-Wfoo]").with_code("custom"),
+            Diagnostic::note("a.c:3:1: note: declared here [-Wbar]"),
+        ];
+        normalize_diagnostics(&mut diags, ToolchainKind::Gcc, "12.2.0");
+        assert_eq!(diags[0].code.as_deref(), Some("unused-variable"));
+        assert_eq!(diags[1].code.as_deref(), Some("custom"));
+        assert_eq!(diags[2].code, None);
+    }
+}
@@ -0,0 +1,910 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! OpenOCD integration.
+//!
+//! Detects the `openocd` binary and its script directory, builds
+//! configurations for common probes and targets, and runs OpenOCD either
+//! one-shot (flash, reset, halt, then exit) or as a long-running debug
+//! server controlled over its Tcl RPC port.
+
+use crate::limits::{is_timeout, run_limited};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Known paths for OpenOCD.
+const OPENOCD_PATHS: &[&str] = &[
+    "/usr/bin/openocd",
+    "/usr/local/bin/openocd",
+    "/opt/homebrew/bin/openocd",
+    "/opt/openocd/bin/openocd",
+];
+
+/// Script directories relative to the binary's directory.
+const RELATIVE_SCRIPT_DIRS: &[&str] = &["../share/openocd/scripts", "../scripts", "scripts"];
+
+/// System script directories.
+const SYSTEM_SCRIPT_DIRS: &[&str] = &[
+    "/usr/share/openocd/scripts",
+    "/usr/local/share/openocd/scripts",
+    "/opt/homebrew/share/openocd/scripts",
+];
+
+/// Target scripts by part number prefix.
+const TARGET_SCRIPTS: &[(&str, &str)] = &[
+    ("STM32F0", "target/stm32f0x.cfg"),
+    ("STM32F1", "target/stm32f1x.cfg"),
+    ("STM32F2", "target/stm32f2x.cfg"),
+    ("STM32F3", "target/stm32f3x.cfg"),
+    ("STM32F4", "target/stm32f4x.cfg"),
+    ("STM32F7", "target/stm32f7x.cfg"),
+    ("STM32G0", "target/stm32g0x.cfg"),
+    ("STM32G4", "target/stm32g4x.cfg"),
+    ("STM32H7", "target/stm32h7x.cfg"),
+    ("STM32L0", "target/stm32l0.cfg"),
+    ("STM32L1", "target/stm32l1.cfg"),
+    ("STM32L4", "target/stm32l4x.cfg"),
+    ("STM32L5", "target/stm32l5x.cfg"),
+    ("STM32U5", "target/stm32u5x.cfg"),
+    ("STM32WB", "target/stm32wbx.cfg"),
+    ("STM32WL", "target/stm32wlx.cfg"),
+    ("NRF51", "target/nrf51.cfg"),
+    ("NRF52", "target/nrf52.cfg"),
+    ("NRF53", "target/nrf53.cfg"),
+    ("ATSAMD21", "target/at91samdXX.cfg"),
+    ("ATSAMD51", "target/atsame5x.cfg"),
+    ("ATSAME5", "target/atsame5x.cfg"),
+    ("LPC17", "target/lpc17xx.cfg"),
+    ("LPC55", "target/lpc55xx.cfg"),
+    ("RP2040", "target/rp2040.cfg"),
+];
+
+/// Error type for OpenOCD operations.
+#[derive(Debug, thiserror::Error)]
+pub enum OpenOcdError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("OpenOCD not found")]
+    NotFound,
+
+    #[error("No OpenOCD target script for {0}")]
+    UnknownTarget(String),
+
+    #[error("OpenOCD exited: {0}")]
+    Exited(String),
+
+    #[error("Timed out waiting for OpenOCD")]
+    Timeout,
+
+    #[error("OpenOCD command failed: {0}")]
+    Command(String),
+}
+
+/// Debug probe family.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ProbeKind {
+    /// ST-Link (on-board or standalone).
+    StLink,
+    /// SEGGER J-Link.
+    JLink,
+    /// CMSIS-DAP / DAPLink.
+    CmsisDap,
+}
+
+impl ProbeKind {
+    /// OpenOCD interface script.
+    pub fn interface_script(&self) -> &'static str {
+        match self {
+            ProbeKind::StLink => "interface/stlink.cfg",
+            ProbeKind::JLink => "interface/jlink.cfg",
+            ProbeKind::CmsisDap => "interface/cmsis-dap.cfg",
+        }
+    }
+
+    /// Transport to select after loading the interface, if the interface
+    /// script doesn't pick one.
+    fn transport(&self) -> Option<&'static str> {
+        match self {
+            ProbeKind::StLink => None,
+            ProbeKind::JLink | ProbeKind::CmsisDap => Some("swd"),
+        }
+    }
+}
+
+impl std::fmt::Display for ProbeKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProbeKind::StLink => write!(f, "ST-Link"),
+            ProbeKind::JLink => write!(f, "J-Link"),
+            ProbeKind::CmsisDap => write!(f, "CMSIS-DAP"),
+        }
+    }
+}
+
+/// A detected OpenOCD installation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OpenOcd {
+    /// Path to the binary.
+    pub path: PathBuf,
+    /// Version (e.g. "0.12.0").
+    pub version: String,
+    /// Script directory, if found.
+    pub scripts: Option<PathBuf>,
+}
+
+/// Detect OpenOCD at the known install paths.
+pub fn detect_openocd() -> Option<OpenOcd> {
+    OPENOCD_PATHS
+        .iter()
+        .find_map(|path| detect_openocd_at(Path::new(path)))
+}
+
+/// Detect OpenOCD at a specific path.
+pub fn detect_openocd_at(path: &Path) -> Option<OpenOcd> {
+    if !path.exists() {
+        return None;
+    }
+    let output = run_limited(Command::new(path).arg("--version")).ok()?;
+    // OpenOCD logs to stderr, including the version banner
+    let text = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    Some(OpenOcd {
+        path: path.to_path_buf(),
+        version: parse_openocd_version(&text)?,
+        scripts: find_scripts_dir(path),
+    })
+}
+
+/// Parse the version from `openocd --version` output.
+///
+/// "Open On-Chip Debugger 0.12.0" or
+/// "xPack Open On-Chip Debugger 0.12.0+dev-01312-g18281b0c4-dirty (2023-09-04-22:32)".
+pub fn parse_openocd_version(output: &str) -> Option<String> {
+    let line = output
+        .lines()
+        .find(|l| l.contains("Open On-Chip Debugger"))?;
+    let rest = &line[line.find("Debugger")? + "Debugger".len()..];
+    let version = rest.split_whitespace().next()?;
+    Some(version.to_string())
+}
+
+/// Find the script directory for an OpenOCD binary.
+pub fn find_scripts_dir(binary: &Path) -> Option<PathBuf> {
+    let bin_dir = binary.parent()?;
+    RELATIVE_SCRIPT_DIRS
+        .iter()
+        .map(|rel| bin_dir.join(rel))
+        .chain(SYSTEM_SCRIPT_DIRS.iter().map(PathBuf::from))
+        .find(|dir| dir.join("interface").is_dir())
+}
+
+/// OpenOCD target script for a part number (longest prefix,
+/// case-insensitive).
+pub fn target_script_for(part: &str) -> Option<&'static str> {
+    let part = part.to_ascii_uppercase();
+    TARGET_SCRIPTS
+        .iter()
+        .filter(|(prefix, _)| part.starts_with(prefix))
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map(|(_, script)| *script)
+}
+
+/// Probe and target configuration for an OpenOCD run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OpenOcdConfig {
+    /// Probe family.
+    pub probe: ProbeKind,
+    /// Target script (e.g. "target/stm32f4x.cfg").
+    pub target_script: String,
+    /// Probe serial number, to pick one of several connected probes.
+    #[serde(default)]
+    pub serial: Option<String>,
+    /// Adapter clock in kHz.
+    #[serde(default)]
+    pub speed_khz: Option<u32>,
+    /// GDB server port.
+    #[serde(default = "default_gdb_port")]
+    pub gdb_port: u16,
+    /// Tcl RPC port.
+    #[serde(default = "default_tcl_port")]
+    pub tcl_port: u16,
+    /// Telnet port.
+    #[serde(default = "default_telnet_port")]
+    pub telnet_port: u16,
+}
+
+fn default_gdb_port() -> u16 {
+    3333
+}
+
+fn default_tcl_port() -> u16 {
+    6666
+}
+
+fn default_telnet_port() -> u16 {
+    4444
+}
+
+impl OpenOcdConfig {
+    /// Create a configuration with the default ports.
+    pub fn new(probe: ProbeKind, target_script: impl Into<String>) -> Self {
+        Self {
+            probe,
+            target_script: target_script.into(),
+            serial: None,
+            speed_khz: None,
+            gdb_port: default_gdb_port(),
+            tcl_port: default_tcl_port(),
+            telnet_port: default_telnet_port(),
+        }
+    }
+
+    /// Create a configuration for a part number.
+    pub fn for_part(probe: ProbeKind, part: &str) -> Result<Self, OpenOcdError> {
+        let script =
+            target_script_for(part).ok_or_else(|| OpenOcdError::UnknownTarget(part.to_string()))?;
+        Ok(Self::new(probe, script))
+    }
+
+    /// Select a probe by serial number.
+    pub fn with_serial(mut self, serial: impl Into<String>) -> Self {
+        self.serial = Some(serial.into());
+        self
+    }
+
+    /// Set the adapter clock.
+    pub fn with_speed(mut self, khz: u32) -> Self {
+        self.speed_khz = Some(khz);
+        self
+    }
+
+    /// Configuration commands, in the order OpenOCD needs them.
+    fn commands(&self) -> Vec<String> {
+        let mut commands = vec![format!("source [find {}]", self.probe.interface_script())];
+        if let Some(transport) = self.probe.transport() {
+            commands.push(format!("transport select {}", transport));
+        }
+        if let Some(serial) = &self.serial {
+            commands.push(format!("adapter serial {}", serial));
+        }
+        if let Some(khz) = self.speed_khz {
+            commands.push(format!("adapter speed {}", khz));
+        }
+        commands.push(format!("source [find {}]", self.target_script));
+        commands.push(format!("gdb_port {}", self.gdb_port));
+        commands.push(format!("tcl_port {}", self.tcl_port));
+        commands.push(format!("telnet_port {}", self.telnet_port));
+        commands
+    }
+
+    /// Render as an OpenOCD configuration file.
+    pub fn to_script(&self) -> String {
+        let mut script = format!(
+            "# OpenOCD configuration generated by Axiom\n# Probe: {}\n\n",
+            self.probe
+        );
+        for command in self.commands() {
+            script.push_str(&command);
+            script.push('\n');
+        }
+        script
+    }
+
+    /// Command-line arguments running the configuration, then `commands`.
+    pub fn args(&self, scripts: Option<&Path>, commands: &[String]) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(scripts) = scripts {
+            args.push("-s".to_string());
+            args.push(scripts.display().to_string());
+        }
+        for command in self.commands().iter().chain(commands) {
+            args.push("-c".to_string());
+            args.push(command.clone());
+        }
+        args
+    }
+}
+
+/// Severity of an OpenOCD log line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OpenOcdLevel {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+/// A log line from OpenOCD.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OpenOcdMessage {
+    /// Severity; unprefixed lines are info.
+    pub level: OpenOcdLevel,
+    /// Text without the level prefix.
+    pub text: String,
+}
+
+/// Notable events recognized in OpenOCD output.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum OpenOcdEvent {
+    /// A probe was found.
+    Probe { description: String },
+    /// Target voltage reported by the probe.
+    TargetVoltage { volts: f64 },
+    /// The GDB server is accepting connections.
+    GdbListening { port: u16 },
+    /// The target halted.
+    Halted { reason: String, pc: Option<u64> },
+    /// Flash programming started.
+    ProgrammingStarted,
+    /// Flash programming finished.
+    ProgrammingFinished,
+    /// Flash contents verified.
+    Verified,
+    /// Bytes written to flash.
+    Wrote { bytes: u64 },
+}
+
+/// Parsed OpenOCD output.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OpenOcdOutput {
+    /// Whether the run succeeded.
+    pub success: bool,
+    /// Log lines.
+    pub messages: Vec<OpenOcdMessage>,
+    /// Recognized events.
+    pub events: Vec<OpenOcdEvent>,
+}
+
+impl OpenOcdOutput {
+    /// Error messages.
+    pub fn errors(&self) -> impl Iterator<Item = &str> {
+        self.messages
+            .iter()
+            .filter(|m| m.level == OpenOcdLevel::Error)
+            .map(|m| m.text.as_str())
+    }
+}
+
+/// Parse OpenOCD log output.
+///
+/// `success` is left false; callers set it from the exit status.
+pub fn parse_openocd_output(output: &str) -> OpenOcdOutput {
+    let mut parsed = OpenOcdOutput::default();
+    for line in output.lines().map(str::trim_end).filter(|l| !l.is_empty()) {
+        let message = parse_message(line);
+        if let Some(event) = parse_event(&message.text) {
+            parsed.events.push(event);
+        } else if let Some(pc) = parse_halt_pc(&message.text) {
+            // "xPSR: 0x01000000 pc: 0x080001c4 msp: 0x20020000" follows the halt line
+            if let Some(OpenOcdEvent::Halted { pc: halted_pc, .. }) = parsed.events.last_mut() {
+                *halted_pc = Some(pc);
+            }
+        }
+        parsed.messages.push(message);
+    }
+    parsed
+}
+
+fn parse_message(line: &str) -> OpenOcdMessage {
+    let prefixes = [
+        ("Debug:", OpenOcdLevel::Debug),
+        ("Info :", OpenOcdLevel::Info),
+        ("Warn :", OpenOcdLevel::Warn),
+        ("Error:", OpenOcdLevel::Error),
+    ];
+    for (prefix, level) in prefixes {
+        if let Some(rest) = line.strip_prefix(prefix) {
+            // Debug lines carry "<n> <ms> file:line func(): " before the text
+            let text = match level {
+                OpenOcdLevel::Debug => rest.split_once("): ").map_or(rest, |(_, t)| t),
+                _ => rest,
+            };
+            return OpenOcdMessage {
+                level,
+                text: text.trim().to_string(),
+            };
+        }
+    }
+    OpenOcdMessage {
+        level: OpenOcdLevel::Info,
+        text: line.trim().to_string(),
+    }
+}
+
+fn parse_event(text: &str) -> Option<OpenOcdEvent> {
+    if let Some(rest) = text.strip_prefix("Listening on port ") {
+        let (port, kind) = rest.split_once(' ')?;
+        if kind.contains("gdb") {
+            return Some(OpenOcdEvent::GdbListening {
+                port: port.parse().ok()?,
+            });
+        }
+        return None;
+    }
+    if let Some(volts) = text.strip_prefix("Target voltage: ") {
+        return Some(OpenOcdEvent::TargetVoltage {
+            volts: volts.trim().parse().ok()?,
+        });
+    }
+    if text.starts_with("STLINK ")
+        || text.starts_with("J-Link ")
+        || text.starts_with("CMSIS-DAP: FW Version")
+    {
+        return Some(OpenOcdEvent::Probe {
+            description: text.to_string(),
+        });
+    }
+    if let Some(rest) = text.strip_prefix("target halted due to ") {
+        let reason = rest.split(',').next().unwrap_or(rest).trim();
+        return Some(OpenOcdEvent::Halted {
+            reason: reason.to_string(),
+            pc: None,
+        });
+    }
+    match text {
+        "** Programming Started **" => return Some(OpenOcdEvent::ProgrammingStarted),
+        "** Programming Finished **" => return Some(OpenOcdEvent::ProgrammingFinished),
+        "** Verified OK **" => return Some(OpenOcdEvent::Verified),
+        _ => {}
+    }
+    if let Some(rest) = text.strip_prefix("wrote ") {
+        let bytes = rest.split_whitespace().next()?.parse().ok()?;
+        return Some(OpenOcdEvent::Wrote { bytes });
+    }
+    None
+}
+
+fn parse_halt_pc(text: &str) -> Option<u64> {
+    let rest = &text[text.find("pc: 0x")? + "pc: 0x".len()..];
+    let hex: String = rest.chars().take_while(char::is_ascii_hexdigit).collect();
+    u64::from_str_radix(&hex, 16).ok()
+}
+
+/// Quote a path for an OpenOCD Tcl command.
+fn tcl_path(path: &Path) -> String {
+    // Tcl treats backslashes as escapes; OpenOCD accepts forward slashes
+    format!("{{{}}}", path.display().to_string().replace('\\', "/"))
+}
+
+/// The `program` command for an image. Raw binaries need a load address.
+pub fn program_command(image: &Path, address: Option<u64>, verify: bool, reset: bool) -> String {
+    let mut command = format!("program {}", tcl_path(image));
+    if verify {
+        command.push_str(" verify");
+    }
+    if reset {
+        command.push_str(" reset");
+    }
+    if let Some(address) = address {
+        command.push_str(&format!(" 0x{:08x}", address));
+    }
+    command
+}
+
+/// Run OpenOCD one-shot: initialize, run `commands`, then shut down.
+pub fn run_openocd(
+    openocd: &OpenOcd,
+    config: &OpenOcdConfig,
+    commands: &[String],
+) -> Result<OpenOcdOutput, OpenOcdError> {
+    let mut all = vec!["init".to_string()];
+    all.extend(commands.iter().cloned());
+    all.push("shutdown".to_string());
+
+    let output = run_limited(
+        Command::new(&openocd.path).args(config.args(openocd.scripts.as_deref(), &all)),
+    )
+    .map_err(|e| {
+        if is_timeout(&e) {
+            OpenOcdError::Timeout
+        } else {
+            OpenOcdError::Io(e)
+        }
+    })?;
+    let text = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let mut parsed = parse_openocd_output(&text);
+    parsed.success = output.status.success() && parsed.errors().next().is_none();
+    Ok(parsed)
+}
+
+/// Flash an image one-shot, verify it, and reset the target.
+///
+/// ELF and Intel HEX images carry their addresses; raw binaries need
+/// `address`.
+pub fn openocd_flash(
+    openocd: &OpenOcd,
+    config: &OpenOcdConfig,
+    image: &Path,
+    address: Option<u64>,
+) -> Result<OpenOcdOutput, OpenOcdError> {
+    run_openocd(
+        openocd,
+        config,
+        &[program_command(image, address, true, true)],
+    )
+}
+
+/// Reset the target one-shot, optionally leaving it halted.
+pub fn openocd_reset(
+    openocd: &OpenOcd,
+    config: &OpenOcdConfig,
+    halt: bool,
+) -> Result<OpenOcdOutput, OpenOcdError> {
+    let command = if halt { "reset halt" } else { "reset run" };
+    run_openocd(openocd, config, &[command.to_string()])
+}
+
+/// Halt the target one-shot.
+pub fn openocd_halt(
+    openocd: &OpenOcd,
+    config: &OpenOcdConfig,
+) -> Result<OpenOcdOutput, OpenOcdError> {
+    run_openocd(openocd, config, &["halt".to_string()])
+}
+
+/// Status of a running OpenOCD server.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OpenOcdServerStatus {
+    /// Whether the process is still running.
+    pub running: bool,
+    /// GDB server port.
+    pub gdb_port: u16,
+    /// Output so far.
+    pub output: OpenOcdOutput,
+}
+
+/// A long-running OpenOCD debug server.
+pub struct OpenOcdServer {
+    child: Child,
+    config: OpenOcdConfig,
+    log: Arc<Mutex<Vec<String>>>,
+}
+
+impl OpenOcdServer {
+    /// Launch OpenOCD and wait until its GDB server is listening.
+    pub fn start(
+        openocd: &OpenOcd,
+        config: &OpenOcdConfig,
+        timeout: Duration,
+    ) -> Result<Self, OpenOcdError> {
+        let mut child = Command::new(&openocd.path)
+            .args(config.args(openocd.scripts.as_deref(), &[]))
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        let log = Arc::new(Mutex::new(Vec::new()));
+        if let Some(stdout) = child.stdout.take() {
+            capture_lines(stdout, Arc::clone(&log));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            capture_lines(stderr, Arc::clone(&log));
+        }
+
+        let mut server = Self {
+            child,
+            config: config.clone(),
+            log,
+        };
+        server.wait_ready(timeout)?;
+        Ok(server)
+    }
+
+    fn wait_ready(&mut self, timeout: Duration) -> Result<(), OpenOcdError> {
+        let start = Instant::now();
+        loop {
+            let output = self.output();
+            let listening = output.events.iter().any(|e| {
+                matches!(e, OpenOcdEvent::GdbListening { port } if *port == self.config.gdb_port)
+            });
+            if listening {
+                return Ok(());
+            }
+            if self.child.try_wait()?.is_some() {
+                // Give the reader threads a moment to drain the pipes
+                std::thread::sleep(Duration::from_millis(50));
+                let output = self.output();
+                let reason = output
+                    .errors()
+                    .last()
+                    .unwrap_or("no error reported")
+                    .to_string();
+                return Err(OpenOcdError::Exited(reason));
+            }
+            if start.elapsed() > timeout {
+                let _ = self.child.kill();
+                return Err(OpenOcdError::Timeout);
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+    }
+
+    /// Configuration the server was started with.
+    pub fn config(&self) -> &OpenOcdConfig {
+        &self.config
+    }
+
+    /// Output so far.
+    pub fn output(&self) -> OpenOcdOutput {
+        let log = self.log.lock().map(|l| l.join("\n")).unwrap_or_default();
+        parse_openocd_output(&log)
+    }
+
+    /// Current status.
+    pub fn status(&mut self) -> OpenOcdServerStatus {
+        let running = matches!(self.child.try_wait(), Ok(None));
+        let mut output = self.output();
+        output.success = running;
+        OpenOcdServerStatus {
+            running,
+            gdb_port: self.config.gdb_port,
+            output,
+        }
+    }
+
+    /// Run a command over the Tcl RPC port and return its result.
+    pub fn command(&self, command: &str) -> Result<String, OpenOcdError> {
+        tcl_command(self.config.tcl_port, command)
+    }
+
+    /// Halt the target.
+    pub fn halt(&self) -> Result<String, OpenOcdError> {
+        self.command("halt")
+    }
+
+    /// Resume the target.
+    pub fn resume(&self) -> Result<String, OpenOcdError> {
+        self.command("resume")
+    }
+
+    /// Reset the target, optionally leaving it halted.
+    pub fn reset(&self, halt: bool) -> Result<String, OpenOcdError> {
+        self.command(if halt { "reset halt" } else { "reset run" })
+    }
+
+    /// Flash an image, verify it, and reset the target.
+    pub fn flash(&self, image: &Path, address: Option<u64>) -> Result<String, OpenOcdError> {
+        self.command(&program_command(image, address, true, true))
+    }
+
+    /// Shut the server down.
+    pub fn stop(mut self) -> Result<(), OpenOcdError> {
+        let _ = self.command("shutdown");
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while Instant::now() < deadline {
+            if self.child.try_wait()?.is_some() {
+                return Ok(());
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        self.child.kill()?;
+        self.child.wait()?;
+        Ok(())
+    }
+}
+
+impl Drop for OpenOcdServer {
+    fn drop(&mut self) {
+        if matches!(self.child.try_wait(), Ok(None)) {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
+
+fn capture_lines(stream: impl Read + Send + 'static, log: Arc<Mutex<Vec<String>>>) {
+    std::thread::spawn(move || {
+        for line in BufReader::new(stream).lines().map_while(Result::ok) {
+            if let Ok(mut log) = log.lock() {
+                log.push(line);
+            }
+        }
+    });
+}
+
+/// Send a command over OpenOCD's Tcl RPC protocol: the command and its
+/// result are each terminated by a 0x1a byte.
+pub fn tcl_command(port: u16, command: &str) -> Result<String, OpenOcdError> {
+    // `program` can take tens of seconds on large images
+    let timeout = Duration::from_secs(120);
+    let mut stream = TcpStream::connect(("127.0.0.1", port))?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.write_all(command.as_bytes())?;
+    stream.write_all(&[0x1a])?;
+
+    let mut response = Vec::new();
+    let mut buf = [0u8; 1024];
+    loop {
+        let n = stream.read(&mut buf)?;
+        if n == 0 {
+            return Err(OpenOcdError::Command("connection closed".to_string()));
+        }
+        response.extend_from_slice(&buf[..n]);
+        if let Some(end) = response.iter().position(|&b| b == 0x1a) {
+            response.truncate(end);
+            break;
+        }
+    }
+    let response = String::from_utf8_lossy(&response).trim().to_string();
+    if response.contains("Error:") || response.starts_with("invalid command name") {
+        return Err(OpenOcdError::Command(response));
+    }
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(
+            parse_openocd_version("Open On-Chip Debugger 0.12.0\nLicensed under GNU GPL v2\n"),
+            Some("0.12.0".to_string())
+        );
+        assert_eq!(
+            parse_openocd_version(
+                "xPack Open On-Chip Debugger 0.12.0+dev-01312-g18281b0c4-dirty (2023-09-04-22:32)"
+            ),
+            Some("0.12.0+dev-01312-g18281b0c4-dirty".to_string())
+        );
+    }
+
+    #[test]
+    fn test_config_for_part() {
+        let config = OpenOcdConfig::for_part(ProbeKind::CmsisDap, "stm32f407vgt6")
+            .unwrap()
+            .with_serial("0669FF49")
+            .with_speed(4000);
+        assert_eq!(config.target_script, "target/stm32f4x.cfg");
+
+        let args = config.args(Some(Path::new("/scripts")), &["init".to_string()]);
+        assert_eq!(&args[..2], &["-s", "/scripts"]);
+        let commands: Vec<_> = args.iter().skip(2).skip(1).step_by(2).cloned().collect();
+        assert_eq!(
+            commands,
+            vec![
+                "source [find interface/cmsis-dap.cfg]",
+                "transport select swd",
+                "adapter serial 0669FF49",
+                "adapter speed 4000",
+                "source [find target/stm32f4x.cfg]",
+                "gdb_port 3333",
+                "tcl_port 6666",
+                "telnet_port 4444",
+                "init",
+            ]
+        );
+        assert!(config.to_script().contains("# Probe: CMSIS-DAP\n"));
+
+        assert!(matches!(
+            OpenOcdConfig::for_part(ProbeKind::StLink, "MSP430"),
+            Err(OpenOcdError::UnknownTarget(_))
+        ));
+    }
+
+    #[test]
+    fn test_program_command() {
+        assert_eq!(
+            program_command(Path::new("build/app.bin"), Some(0x0800_0000), true, true),
+            "program {build/app.bin} verify reset 0x08000000"
+        );
+        assert_eq!(
+            program_command(Path::new(r"C:\fw\app.elf"), None, false, false),
+            "program {C:/fw/app.elf}"
+        );
+    }
+
+    #[test]
+    fn test_parse_flash_output() {
+        let log = "\
+Open On-Chip Debugger 0.12.0
+Info : STLINK V2J37S7 (API v2) VID:PID 0483:3748
+Info : Target voltage: 3.237255
+Info : Listening on port 3333 for gdb connections
+[stm32f4x.cpu] halted due to debug-request, current mode: Thread
+Info : Listening on port 6666 for tcl connections
+target halted due to debug-request, current mode: Thread
+xPSR: 0x01000000 pc: 0x080001c4 msp: 0x20020000
+** Programming Started **
+Info : device id = 0x10076413
+Warn : Adding extra erase range, 0x08003c28 .. 0x08003fff
+** Programming Finished **
+** Verify Started **
+** Verified OK **
+Error: timed out while waiting for target halted
+";
+        let output = parse_openocd_output(log);
+        assert_eq!(
+            output.events,
+            vec![
+                OpenOcdEvent::Probe {
+                    description: "STLINK V2J37S7 (API v2) VID:PID 0483:3748".to_string()
+                },
+                OpenOcdEvent::TargetVoltage { volts: 3.237255 },
+                OpenOcdEvent::GdbListening { port: 3333 },
+                OpenOcdEvent::Halted {
+                    reason: "debug-request".to_string(),
+                    pc: Some(0x0800_01c4)
+                },
+                OpenOcdEvent::ProgrammingStarted,
+                OpenOcdEvent::ProgrammingFinished,
+                OpenOcdEvent::Verified,
+            ]
+        );
+        assert_eq!(output.messages[7].level, OpenOcdLevel::Info);
+        assert_eq!(output.messages[10].level, OpenOcdLevel::Warn);
+        assert_eq!(
+            output.errors().collect::<Vec<_>>(),
+            vec!["timed out while waiting for target halted"]
+        );
+    }
+
+    #[test]
+    fn test_tcl_command() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            for reply in ["target halted", "invalid command name \"bogus\""] {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut received = Vec::new();
+                let mut byte = [0u8];
+                while stream.read(&mut byte).unwrap() == 1 && byte[0] != 0x1a {
+                    received.push(byte[0]);
+                }
+                stream.write_all(reply.as_bytes()).unwrap();
+                stream.write_all(&[0x1a]).unwrap();
+                String::from_utf8(received).unwrap();
+            }
+        });
+
+        assert_eq!(tcl_command(port, "halt").unwrap(), "target halted");
+        assert!(matches!(
+            tcl_command(port, "bogus"),
+            Err(OpenOcdError::Command(_))
+        ));
+        server.join().unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_server_reports_startup_failure() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let fake = dir.path().join("openocd");
+        std::fs::write(
+            &fake,
+            "#!/bin/sh\necho 'Open On-Chip Debugger 0.12.0' >&2\n\
+             echo 'Error: open failed' >&2\nexit 1\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&fake, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let openocd = detect_openocd_at(&fake).unwrap();
+        assert_eq!(openocd.version, "0.12.0");
+        let config = OpenOcdConfig::new(ProbeKind::StLink, "target/stm32f4x.cfg");
+        match OpenOcdServer::start(&openocd, &config, Duration::from_secs(5)) {
+            Err(OpenOcdError::Exited(reason)) => assert_eq!(reason, "open failed"),
+            other => panic!("unexpected: {:?}", other.map(|_| ())),
+        }
+    }
+}
@@ -0,0 +1,1033 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! CMSIS-Pack indexing.
+//!
+//! Installed packs live under a pack root as `<vendor>/<name>/<version>/`
+//! with a `.pdsc` description at the top of each version directory.
+//! Downloaded but unextracted `.pack` archives are indexed from the `.pdsc`
+//! inside them; their file paths stay relative to the archive.
+//!
+//! Device properties in a `.pdsc` are inherited down the
+//! family → subFamily → device → variant hierarchy. Startup files come from
+//! `Device:Startup` components whose conditions hold for the device when
+//! building with GCC.
+
+use crate::zip::{zip_entries, zip_read};
+use crate::{ArmMcuConfig, FloatAbi, McuMemory, MemoryBlock};
+use roxmltree::Node;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Compiler name used when evaluating `Tcompiler` conditions.
+const PACK_COMPILER: &str = "GCC";
+
+/// Maximum nesting of condition references.
+const MAX_CONDITION_DEPTH: usize = 16;
+
+/// Error type for pack operations.
+#[derive(Debug, thiserror::Error)]
+pub enum PackError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("{path}: {source}")]
+    Xml {
+        path: PathBuf,
+        source: roxmltree::Error,
+    },
+
+    #[error("{path}: {reason}")]
+    Invalid { path: PathBuf, reason: String },
+}
+
+/// Pack identity.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PackId {
+    /// Pack vendor (e.g. "Keil").
+    pub vendor: String,
+    /// Pack name (e.g. "STM32F4xx_DFP").
+    pub name: String,
+    /// Latest release version.
+    pub version: String,
+}
+
+impl fmt::Display for PackId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.vendor, self.name, self.version)
+    }
+}
+
+/// Where a pack was found.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "path", rename_all = "lowercase")]
+pub enum PackSource {
+    /// Extracted pack directory; device file paths are absolute.
+    Installed(PathBuf),
+    /// `.pack` archive; device file paths are relative to the archive.
+    Archive(PathBuf),
+}
+
+/// A memory region of a device.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackMemory {
+    /// Region name (e.g. "IROM1", "Flash").
+    pub name: String,
+    /// Start address.
+    pub start: u64,
+    /// Size in bytes.
+    pub size: u64,
+    /// Access attributes (e.g. "rx", "rwx").
+    pub access: String,
+    /// Region is used by default for code or data.
+    pub default: bool,
+    /// Region holds the reset vector.
+    pub startup: bool,
+}
+
+impl PackMemory {
+    /// Whether the region is writable.
+    pub fn is_ram(&self) -> bool {
+        self.access.contains('w')
+    }
+}
+
+/// A flash programming algorithm.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FlashAlgorithm {
+    /// Path to the `.FLM` file.
+    pub path: PathBuf,
+    /// Start of the flash range programmed.
+    pub start: u64,
+    /// Size of the flash range programmed.
+    pub size: u64,
+    /// RAM the algorithm runs from, if given.
+    pub ram_start: Option<u64>,
+    /// Size of that RAM, if given.
+    pub ram_size: Option<u64>,
+    /// Used by default.
+    pub default: bool,
+}
+
+/// A device described by a pack.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackDevice {
+    /// Device or variant name (e.g. "STM32F407VGTx").
+    pub name: String,
+    /// Device vendor, without the vendor ID (e.g. "STMicroelectronics").
+    pub vendor: String,
+    /// Family (e.g. "STM32F4 Series").
+    pub family: String,
+    /// Sub-family, if any.
+    pub sub_family: Option<String>,
+    /// Processor core (e.g. "Cortex-M4").
+    pub core: Option<String>,
+    /// FPU ("NO_FPU", "SP_FPU", "DP_FPU"), if given.
+    pub fpu: Option<String>,
+    /// Preprocessor defines.
+    pub defines: Vec<String>,
+    /// Device headers.
+    pub headers: Vec<PathBuf>,
+    /// Startup and system source files for GCC.
+    pub startup: Vec<PathBuf>,
+    /// Memory regions.
+    pub memory: Vec<PackMemory>,
+    /// Flash programming algorithms.
+    pub algorithms: Vec<FlashAlgorithm>,
+    /// Pack the device came from.
+    pub pack: PackId,
+}
+
+impl PackDevice {
+    /// Compiler settings for the device's core and FPU.
+    ///
+    /// Returns `None` for cores that are not Cortex-M.
+    pub fn mcu_config(&self) -> Option<ArmMcuConfig> {
+        let core = self.core.as_deref()?;
+        let cpu = match core {
+            "Cortex-M0" => "cortex-m0",
+            "Cortex-M0+" => "cortex-m0plus",
+            "Cortex-M1" => "cortex-m1",
+            "Cortex-M3" => "cortex-m3",
+            "Cortex-M4" => "cortex-m4",
+            "Cortex-M7" => "cortex-m7",
+            "Cortex-M23" => "cortex-m23",
+            "Cortex-M33" => "cortex-m33",
+            "Cortex-M35P" => "cortex-m35p",
+            "Cortex-M55" => "cortex-m55",
+            "Cortex-M85" => "cortex-m85",
+            _ => return None,
+        };
+        let fpu = match (self.fpu.as_deref(), cpu) {
+            (Some("SP_FPU") | Some("FPU") | Some("1"), "cortex-m4") => Some("fpv4-sp-d16"),
+            (Some("SP_FPU") | Some("FPU") | Some("1"), _) => Some("fpv5-sp-d16"),
+            (Some("DP_FPU"), _) => Some("fpv5-d16"),
+            _ => None,
+        };
+
+        let mut config = ArmMcuConfig::new(cpu);
+        if let Some(fpu) = fpu {
+            config = config.with_fpu(fpu, FloatAbi::Hard);
+        }
+        for define in &self.defines {
+            config = config.with_define(define);
+        }
+        Some(config)
+    }
+
+    /// Memory layout for linker script generation.
+    ///
+    /// The startup (or first default read-only) region becomes FLASH and the
+    /// first default writable region becomes RAM; other regions are kept
+    /// under their pack names.
+    pub fn memory_layout(&self) -> Option<McuMemory> {
+        let flash = self
+            .memory
+            .iter()
+            .find(|m| m.startup)
+            .or_else(|| self.memory.iter().find(|m| m.default && !m.is_ram()))?;
+        let ram = self
+            .memory
+            .iter()
+            .find(|m| m.default && m.is_ram())
+            .or_else(|| self.memory.iter().find(|m| m.is_ram()))?;
+
+        let mut layout = McuMemory::new(flash.start, flash.size, ram.start, ram.size);
+        for region in &self.memory {
+            if region != flash && region != ram && region.size > 0 {
+                layout = layout.with_region(MemoryBlock::new(
+                    region.name.clone(),
+                    region.start,
+                    region.size,
+                    region.access.clone(),
+                ));
+            }
+        }
+        Some(layout)
+    }
+}
+
+/// A parsed pack description.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Pack {
+    /// Pack identity.
+    pub id: PackId,
+    /// Where it was found.
+    pub source: PackSource,
+    /// Pack description.
+    pub description: String,
+    /// Devices, with variants listed individually.
+    pub devices: Vec<PackDevice>,
+}
+
+/// Packs found under one or more pack roots.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackIndex {
+    /// Indexed packs.
+    pub packs: Vec<Pack>,
+    /// Pack descriptions that could not be read, with the reason.
+    pub errors: Vec<(PathBuf, String)>,
+}
+
+impl PackIndex {
+    /// Index all packs under the given roots.
+    ///
+    /// Missing roots are skipped; unreadable packs are recorded in
+    /// [`PackIndex::errors`] rather than failing the scan.
+    pub fn scan(roots: &[PathBuf]) -> Self {
+        let mut index = Self::default();
+        for root in roots {
+            for path in pdsc_files(root) {
+                match read_pdsc(&path) {
+                    Ok(pack) => index.packs.push(pack),
+                    Err(e) => index.errors.push((path, e.to_string())),
+                }
+            }
+            for path in pack_archives(root) {
+                match read_pack_archive(&path) {
+                    Ok(pack) => index.packs.push(pack),
+                    Err(e) => index.errors.push((path, e.to_string())),
+                }
+            }
+        }
+
+        // Newest version first, installed before archived
+        index.packs.sort_by(|a, b| {
+            (&a.id.vendor, &a.id.name)
+                .cmp(&(&b.id.vendor, &b.id.name))
+                .then_with(|| version_key(&b.id.version).cmp(&version_key(&a.id.version)))
+                .then_with(|| {
+                    matches!(a.source, PackSource::Archive(_))
+                        .cmp(&matches!(b.source, PackSource::Archive(_)))
+                })
+        });
+        index
+    }
+
+    /// Index the default pack roots.
+    pub fn scan_default() -> Self {
+        Self::scan(&default_pack_roots())
+    }
+
+    /// All devices, newest pack version first.
+    pub fn devices(&self) -> impl Iterator<Item = &PackDevice> {
+        self.packs.iter().flat_map(|p| &p.devices)
+    }
+
+    /// Find a device by exact name (case-insensitive) in the newest pack
+    /// that describes it.
+    pub fn device(&self, name: &str) -> Option<&PackDevice> {
+        self.devices().find(|d| d.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Devices whose name, family, or vendor contains `query`
+    /// (case-insensitive), one entry per device name.
+    pub fn search(&self, query: &str) -> Vec<&PackDevice> {
+        let query = query.to_ascii_lowercase();
+        let mut seen = std::collections::HashSet::new();
+        self.devices()
+            .filter(|d| {
+                d.name.to_ascii_lowercase().contains(&query)
+                    || d.family.to_ascii_lowercase().contains(&query)
+                    || d.vendor.to_ascii_lowercase().contains(&query)
+            })
+            .filter(|d| seen.insert(d.name.to_ascii_lowercase()))
+            .collect()
+    }
+}
+
+/// Pack roots searched by default.
+///
+/// `CMSIS_PACK_ROOT` comes first, followed by the per-user root used by the
+/// CMSIS toolbox and, on Windows, the Keil MDK pack directory.
+pub fn default_pack_roots() -> Vec<PathBuf> {
+    let mut roots = Vec::new();
+    if let Some(root) = std::env::var_os("CMSIS_PACK_ROOT") {
+        roots.push(PathBuf::from(root));
+    }
+    if cfg!(windows) {
+        if let Some(local) = std::env::var_os("LOCALAPPDATA") {
+            roots.push(PathBuf::from(local).join("Arm").join("Packs"));
+        }
+        roots.push(PathBuf::from(r"C:\Keil_v5\ARM\PACK"));
+    } else if let Some(home) = std::env::var_os("HOME") {
+        roots.push(PathBuf::from(home).join(".cache").join("arm").join("packs"));
+    }
+    roots
+}
+
+/// Read an installed pack's `.pdsc`; file paths are resolved against its
+/// directory.
+pub fn read_pdsc(path: &Path) -> Result<Pack, PackError> {
+    let content = std::fs::read_to_string(path)?;
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let mut pack = parse_pdsc(&content, path, dir)?;
+    pack.source = PackSource::Installed(dir.to_path_buf());
+    Ok(pack)
+}
+
+/// Read the `.pdsc` inside a `.pack` archive.
+pub fn read_pack_archive(path: &Path) -> Result<Pack, PackError> {
+    let data = std::fs::read(path)?;
+    let content = zip_read_pdsc(&data).map_err(|reason| PackError::Invalid {
+        path: path.to_path_buf(),
+        reason,
+    })?;
+    let mut pack = parse_pdsc(&content, path, Path::new(""))?;
+    pack.source = PackSource::Archive(path.to_path_buf());
+    Ok(pack)
+}
+
+/// Parse a `.pdsc` document.
+///
+/// `path` is used in errors; file references are joined onto `base`.
+pub fn parse_pdsc(content: &str, path: &Path, base: &Path) -> Result<Pack, PackError> {
+    let doc = roxmltree::Document::parse(content).map_err(|source| PackError::Xml {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let package = doc.root_element();
+    let invalid = |reason: &str| PackError::Invalid {
+        path: path.to_path_buf(),
+        reason: reason.to_string(),
+    };
+    if !package.has_tag_name("package") {
+        return Err(invalid("root element is not <package>"));
+    }
+
+    let vendor = child_text(package, "vendor").ok_or_else(|| invalid("missing <vendor>"))?;
+    let name = child_text(package, "name").ok_or_else(|| invalid("missing <name>"))?;
+    let version = child(package, "releases")
+        .and_then(|r| child(r, "release"))
+        .and_then(|r| r.attribute("version"))
+        .map(str::to_string)
+        .or_else(|| {
+            // Fall back to the version directory name
+            base.file_name().map(|n| n.to_string_lossy().into_owned())
+        })
+        .unwrap_or_default();
+    let id = PackId {
+        vendor,
+        name,
+        version,
+    };
+
+    let conditions: HashMap<&str, Node> = child(package, "conditions")
+        .into_iter()
+        .flat_map(|c| c.children().filter(|n| n.has_tag_name("condition")))
+        .filter_map(|c| Some((c.attribute("id")?, c)))
+        .collect();
+    let startup_components: Vec<Node> = child(package, "components")
+        .into_iter()
+        .flat_map(|c| c.descendants())
+        .filter(|n| {
+            n.has_tag_name("component")
+                && n.attribute("Cclass") == Some("Device")
+                && n.attribute("Cgroup") == Some("Startup")
+        })
+        .collect();
+
+    let mut devices = Vec::new();
+    if let Some(families) = child(package, "devices") {
+        for family in families.children().filter(|n| n.has_tag_name("family")) {
+            collect_devices(family, Scope::default(), base, &id, &mut devices);
+        }
+    }
+
+    for device in &mut devices {
+        let context = DeviceContext::from(&*device);
+        let mut startup = Vec::new();
+        let mut headers = device.headers.clone();
+        for component in &startup_components {
+            if !condition_attr_holds(*component, &conditions, &context, 0) {
+                continue;
+            }
+            for file in component.descendants().filter(|n| n.has_tag_name("file")) {
+                let Some(name) = file.attribute("name") else {
+                    continue;
+                };
+                if !condition_attr_holds(file, &conditions, &context, 0) {
+                    continue;
+                }
+                let file_path = base.join(name);
+                match file.attribute("category") {
+                    Some("source" | "sourceAsm" | "sourceC") => {
+                        push_unique(&mut startup, file_path)
+                    }
+                    Some("header") => push_unique(&mut headers, file_path),
+                    _ => {}
+                }
+            }
+        }
+        device.startup = startup;
+        device.headers = headers;
+    }
+
+    Ok(Pack {
+        id,
+        source: PackSource::Installed(base.to_path_buf()),
+        description: child_text(package, "description").unwrap_or_default(),
+        devices,
+    })
+}
+
+/// Properties inherited down the device hierarchy.
+#[derive(Debug, Clone, Default)]
+struct Scope {
+    vendor: String,
+    family: String,
+    sub_family: Option<String>,
+    core: Option<String>,
+    fpu: Option<String>,
+    defines: Vec<String>,
+    headers: Vec<PathBuf>,
+    memory: Vec<PackMemory>,
+    algorithms: Vec<FlashAlgorithm>,
+}
+
+fn collect_devices(
+    node: Node,
+    mut scope: Scope,
+    base: &Path,
+    pack: &PackId,
+    devices: &mut Vec<PackDevice>,
+) {
+    if let Some(vendor) = node.attribute("Dvendor") {
+        scope.vendor = vendor.split(':').next().unwrap_or(vendor).to_string();
+    }
+    if let Some(family) = node.attribute("Dfamily") {
+        scope.family = family.to_string();
+    }
+    if let Some(sub_family) = node.attribute("DsubFamily") {
+        scope.sub_family = Some(sub_family.to_string());
+    }
+
+    for element in node.children().filter(Node::is_element) {
+        match element.tag_name().name() {
+            "processor" => {
+                if let Some(core) = element.attribute("Dcore") {
+                    scope.core = Some(core.to_string());
+                }
+                if let Some(fpu) = element.attribute("Dfpu") {
+                    scope.fpu = Some(fpu.to_string());
+                }
+            }
+            "compile" => {
+                if let Some(header) = element.attribute("header") {
+                    push_unique(&mut scope.headers, base.join(header));
+                }
+                if let Some(define) = element.attribute("define") {
+                    if !scope.defines.iter().any(|d| d == define) {
+                        scope.defines.push(define.to_string());
+                    }
+                }
+            }
+            "memory" => {
+                if let Some(memory) = parse_memory(element) {
+                    scope.memory.retain(|m| m.name != memory.name);
+                    scope.memory.push(memory);
+                }
+            }
+            "algorithm" => {
+                if let Some(algorithm) = parse_algorithm(element, base) {
+                    scope.algorithms.retain(|a| a.path != algorithm.path);
+                    scope.algorithms.push(algorithm);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let name_attr = match node.tag_name().name() {
+        "device" => "Dname",
+        "variant" => "Dvariant",
+        _ => "",
+    };
+    let nested: Vec<Node> = node
+        .children()
+        .filter(|n| n.has_tag_name("subFamily") || n.has_tag_name("device"))
+        .collect();
+    let variants: Vec<Node> = node
+        .children()
+        .filter(|n| n.has_tag_name("variant"))
+        .collect();
+
+    for child in nested {
+        collect_devices(child, scope.clone(), base, pack, devices);
+    }
+    if let Some(name) = node.attribute(name_attr) {
+        if variants.is_empty() {
+            devices.push(PackDevice {
+                name: name.to_string(),
+                vendor: scope.vendor,
+                family: scope.family,
+                sub_family: scope.sub_family,
+                core: scope.core,
+                fpu: scope.fpu,
+                defines: scope.defines,
+                headers: scope.headers,
+                startup: Vec::new(),
+                memory: scope.memory,
+                algorithms: scope.algorithms,
+                pack: pack.clone(),
+            });
+        } else {
+            // Variants are the orderable parts; the device only groups them
+            for variant in variants {
+                collect_devices(variant, scope.clone(), base, pack, devices);
+            }
+        }
+    }
+}
+
+fn parse_memory(node: Node) -> Option<PackMemory> {
+    let name = node.attribute("name").or_else(|| node.attribute("id"))?;
+    let access = node
+        .attribute("access")
+        .map(str::to_string)
+        .unwrap_or_else(|| {
+            if name.starts_with("IRAM") || name.starts_with("RAM") {
+                "rwx".to_string()
+            } else {
+                "rx".to_string()
+            }
+        });
+    Some(PackMemory {
+        name: name.to_string(),
+        start: parse_number(node.attribute("start")?)?,
+        size: parse_number(node.attribute("size")?)?,
+        access,
+        default: node.attribute("default") == Some("1"),
+        startup: node.attribute("startup") == Some("1"),
+    })
+}
+
+fn parse_algorithm(node: Node, base: &Path) -> Option<FlashAlgorithm> {
+    Some(FlashAlgorithm {
+        path: base.join(node.attribute("name")?),
+        start: parse_number(node.attribute("start")?)?,
+        size: parse_number(node.attribute("size")?)?,
+        ram_start: node.attribute("RAMstart").and_then(parse_number),
+        ram_size: node.attribute("RAMsize").and_then(parse_number),
+        default: node.attribute("default") == Some("1"),
+    })
+}
+
+/// Device attributes that conditions are evaluated against.
+struct DeviceContext<'a> {
+    name: &'a str,
+    vendor: &'a str,
+    core: Option<&'a str>,
+    fpu: Option<&'a str>,
+}
+
+impl<'a> From<&'a PackDevice> for DeviceContext<'a> {
+    fn from(device: &'a PackDevice) -> Self {
+        Self {
+            name: &device.name,
+            vendor: &device.vendor,
+            core: device.core.as_deref(),
+            fpu: device.fpu.as_deref(),
+        }
+    }
+}
+
+/// Whether the condition referenced by a node's `condition` attribute holds;
+/// nodes without one are unconditional.
+fn condition_attr_holds(
+    node: Node,
+    conditions: &HashMap<&str, Node>,
+    context: &DeviceContext,
+    depth: usize,
+) -> bool {
+    match node.attribute("condition") {
+        Some(id) => condition_holds(id, conditions, context, depth),
+        None => true,
+    }
+}
+
+fn condition_holds(
+    id: &str,
+    conditions: &HashMap<&str, Node>,
+    context: &DeviceContext,
+    depth: usize,
+) -> bool {
+    if depth > MAX_CONDITION_DEPTH {
+        return false;
+    }
+    let Some(condition) = conditions.get(id) else {
+        // Dangling references are a pack bug; don't exclude files over it
+        return true;
+    };
+
+    let mut accepts = false;
+    let mut accepted = false;
+    for expression in condition.children().filter(Node::is_element) {
+        let holds = expression_holds(expression, conditions, context, depth + 1);
+        match expression.tag_name().name() {
+            "require" if !holds => return false,
+            "deny" if holds => return false,
+            "accept" => {
+                accepts = true;
+                accepted |= holds;
+            }
+            _ => {}
+        }
+    }
+    !accepts || accepted
+}
+
+/// Whether every attribute of a require/accept/deny expression matches.
+fn expression_holds(
+    expression: Node,
+    conditions: &HashMap<&str, Node>,
+    context: &DeviceContext,
+    depth: usize,
+) -> bool {
+    expression.attributes().all(|attr| {
+        let pattern = attr.value();
+        match attr.name() {
+            "Dname" => wildcard_match(pattern, context.name),
+            "Dvendor" => {
+                let vendor = pattern.split(':').next().unwrap_or(pattern);
+                wildcard_match(vendor, context.vendor)
+            }
+            "Dcore" => context.core.is_some_and(|c| wildcard_match(pattern, c)),
+            "Dfpu" => match (pattern, context.fpu) {
+                ("FPU" | "1", Some(fpu)) => fpu != "NO_FPU" && fpu != "0",
+                ("NO_FPU" | "0", fpu) => matches!(fpu, None | Some("NO_FPU" | "0")),
+                (pattern, Some(fpu)) => pattern == fpu,
+                (_, None) => false,
+            },
+            "Tcompiler" => pattern == PACK_COMPILER,
+            "condition" => condition_holds(pattern, conditions, context, depth),
+            // Component and option requirements depend on the project's
+            // selection, which the index does not track
+            _ => true,
+        }
+    })
+}
+
+/// Match `*` and `?` wildcards, case-insensitively.
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_ascii_lowercase().chars().collect();
+    let text: Vec<char> = text.to_ascii_lowercase().chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((sp, st)) = star {
+            p = sp + 1;
+            t = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// `.pdsc` files of installed packs: `<vendor>/<name>/<version>/*.pdsc`.
+fn pdsc_files(root: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for vendor in sorted_dirs(root) {
+        for name in sorted_dirs(&vendor) {
+            for version in sorted_dirs(&name) {
+                let Ok(entries) = std::fs::read_dir(&version) else {
+                    continue;
+                };
+                let mut found: Vec<PathBuf> = entries
+                    .filter_map(|e| e.ok().map(|e| e.path()))
+                    .filter(|p| p.is_file() && axiom_core::walk::has_extension(p, &["pdsc"]))
+                    .collect();
+                found.sort();
+                files.extend(found);
+            }
+        }
+    }
+    files
+}
+
+/// Archives in the pack root's `.Download` cache.
+fn pack_archives(root: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(root.join(".Download")) else {
+        return Vec::new();
+    };
+    let mut archives: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.is_file() && axiom_core::walk::has_extension(p, &["pack"]))
+        .collect();
+    archives.sort();
+    archives
+}
+
+/// Visible subdirectories, sorted.
+fn sorted_dirs(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut dirs: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| {
+            p.is_dir()
+                && !p
+                    .file_name()
+                    .is_some_and(|n| n.to_string_lossy().starts_with('.'))
+        })
+        .collect();
+    dirs.sort();
+    dirs
+}
+
+/// Extract the top-level `.pdsc` from a zip archive.
+fn zip_read_pdsc(data: &[u8]) -> Result<String, String> {
+    let entry = zip_entries(data)?
+        .into_iter()
+        .find(|e| !e.name.contains('/') && e.name.to_ascii_lowercase().ends_with(".pdsc"))
+        .ok_or("no .pdsc in archive")?;
+    let content = zip_read(data, &entry)?;
+    Ok(String::from_utf8_lossy(&content).into_owned())
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|n| n.has_tag_name(name))
+}
+
+fn child_text(node: Node, name: &str) -> Option<String> {
+    child(node, name)
+        .and_then(|n| n.text())
+        .map(|t| t.trim().to_string())
+}
+
+fn push_unique(paths: &mut Vec<PathBuf>, path: PathBuf) {
+    if !paths.contains(&path) {
+        paths.push(path);
+    }
+}
+
+fn parse_number(text: &str) -> Option<u64> {
+    let text = text.trim();
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+/// Numeric components of a version, for ordering.
+fn version_key(version: &str) -> Vec<u64> {
+    version
+        .split(['.', '-', '+'])
+        .map_while(|part| part.parse().ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::DeflateEncoder;
+    use flate2::Compression;
+    use std::fs;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    const PDSC: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<package schemaVersion="1.7.7">
+  <vendor>Keil</vendor>
+  <name>STM32F4xx_DFP</name>
+  <description>STMicroelectronics STM32F4 Series Device Support</description>
+  <releases>
+    <release version="2.17.1">Fixes</release>
+    <release version="2.17.0">Initial</release>
+  </releases>
+  <conditions>
+    <condition id="STM32F4 CMSIS">
+      <require Dvendor="STMicroelectronics:13" Dname="STM32F4*"/>
+    </condition>
+    <condition id="STM32F407 GCC">
+      <require condition="STM32F4 CMSIS"/>
+      <require Dname="STM32F407*"/>
+      <require Tcompiler="GCC"/>
+    </condition>
+    <condition id="STM32F407 ARMCC">
+      <require Dname="STM32F407*"/>
+      <require Tcompiler="ARMCC"/>
+    </condition>
+  </conditions>
+  <devices>
+    <family Dfamily="STM32F4 Series" Dvendor="STMicroelectronics:13">
+      <processor Dcore="Cortex-M4" Dfpu="SP_FPU" Dmpu="MPU" Dendian="Little-endian"/>
+      <compile header="Include/stm32f4xx.h"/>
+      <subFamily DsubFamily="STM32F407">
+        <compile define="STM32F407xx"/>
+        <device Dname="STM32F407VG">
+          <memory id="IROM1" start="0x08000000" size="0x00100000" startup="1" default="1"/>
+          <memory id="IRAM1" start="0x20000000" size="0x00020000" init="0" default="1"/>
+          <memory id="IRAM2" start="0x10000000" size="0x00010000" init="0" default="0"/>
+          <algorithm name="Flash/STM32F4xx_1024.FLM" start="0x08000000" size="0x00100000" RAMstart="0x20000000" RAMsize="0x1000" default="1"/>
+          <variant Dvariant="STM32F407VGTx"/>
+          <variant Dvariant="STM32F407VGHx"/>
+        </device>
+      </subFamily>
+      <device Dname="STM32F401CC">
+        <processor Dfpu="NO_FPU"/>
+        <memory name="Flash" access="rx" start="0x08000000" size="0x40000" startup="1" default="1"/>
+        <memory name="SRAM" access="rwx" start="0x20000000" size="0x10000" default="1"/>
+      </device>
+    </family>
+  </devices>
+  <components>
+    <component Cclass="Device" Cgroup="Startup" Cversion="2.6.0" condition="STM32F4 CMSIS">
+      <files>
+        <file category="header" name="Include/stm32f4xx.h"/>
+        <file category="sourceAsm" name="Source/gcc/startup_stm32f407xx.s" condition="STM32F407 GCC"/>
+        <file category="sourceAsm" name="Source/arm/startup_stm32f407xx.s" condition="STM32F407 ARMCC"/>
+        <file category="sourceC" name="Source/system_stm32f4xx.c"/>
+      </files>
+    </component>
+  </components>
+</package>
+"#;
+
+    fn parse() -> Pack {
+        parse_pdsc(
+            PDSC,
+            Path::new("Keil.STM32F4xx_DFP.pdsc"),
+            Path::new("/packs/dfp"),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse_devices_and_variants() {
+        let pack = parse();
+        assert_eq!(pack.id.to_string(), "Keil.STM32F4xx_DFP.2.17.1");
+        let names: Vec<_> = pack.devices.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, vec!["STM32F407VGTx", "STM32F407VGHx", "STM32F401CC"]);
+
+        let device = &pack.devices[0];
+        assert_eq!(device.vendor, "STMicroelectronics");
+        assert_eq!(device.sub_family.as_deref(), Some("STM32F407"));
+        assert_eq!(device.core.as_deref(), Some("Cortex-M4"));
+        assert_eq!(device.defines, vec!["STM32F407xx"]);
+        assert_eq!(device.memory.len(), 3);
+        assert_eq!(
+            device.algorithms[0].path,
+            Path::new("/packs/dfp/Flash/STM32F4xx_1024.FLM")
+        );
+        assert_eq!(device.algorithms[0].ram_size, Some(0x1000));
+    }
+
+    #[test]
+    fn test_startup_files_follow_conditions() {
+        let pack = parse();
+        let f407 = &pack.devices[0];
+        assert_eq!(
+            f407.startup,
+            vec![
+                PathBuf::from("/packs/dfp/Source/gcc/startup_stm32f407xx.s"),
+                PathBuf::from("/packs/dfp/Source/system_stm32f4xx.c"),
+            ]
+        );
+        assert_eq!(
+            f407.headers,
+            vec![PathBuf::from("/packs/dfp/Include/stm32f4xx.h")]
+        );
+
+        let f401 = &pack.devices[2];
+        assert_eq!(
+            f401.startup,
+            vec![PathBuf::from("/packs/dfp/Source/system_stm32f4xx.c")]
+        );
+    }
+
+    #[test]
+    fn test_device_configuration() {
+        let pack = parse();
+        let config = pack.devices[0].mcu_config().unwrap();
+        assert_eq!(config.cpu, "cortex-m4");
+        assert_eq!(config.fpu.as_deref(), Some("fpv4-sp-d16"));
+        assert_eq!(config.defines, vec!["STM32F407xx"]);
+        assert_eq!(pack.devices[2].mcu_config().unwrap().fpu, None);
+
+        let layout = pack.devices[0].memory_layout().unwrap();
+        let regions: Vec<_> = layout
+            .regions()
+            .map(|r| (r.name.as_str(), r.origin, r.size))
+            .collect();
+        assert_eq!(
+            regions,
+            vec![
+                ("FLASH", 0x0800_0000, 0x10_0000),
+                ("RAM", 0x2000_0000, 0x2_0000),
+                ("IRAM2", 0x1000_0000, 0x1_0000),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("STM32F4*", "stm32f407vg"));
+        assert!(wildcard_match("STM32F40?VG", "STM32F407VG"));
+        assert!(!wildcard_match("STM32F41*", "STM32F407VG"));
+        assert!(wildcard_match("*", ""));
+    }
+
+    /// Build a zip with one entry per (name, content, deflate).
+    fn zip(entries: &[(&str, &str, bool)]) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut central = Vec::new();
+        for (name, content, deflate) in entries {
+            let data = if *deflate {
+                let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(content.as_bytes()).unwrap();
+                encoder.finish().unwrap()
+            } else {
+                content.as_bytes().to_vec()
+            };
+            let method: u16 = if *deflate { 8 } else { 0 };
+            let offset = out.len() as u32;
+
+            out.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+            out.extend_from_slice(&[20, 0, 0, 0]);
+            out.extend_from_slice(&method.to_le_bytes());
+            out.extend_from_slice(&[0; 8]);
+            out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            out.extend_from_slice(&(content.len() as u32).to_le_bytes());
+            out.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            out.extend_from_slice(&[0, 0]);
+            out.extend_from_slice(name.as_bytes());
+            out.extend_from_slice(&data);
+
+            central.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+            central.extend_from_slice(&[20, 0, 20, 0, 0, 0]);
+            central.extend_from_slice(&method.to_le_bytes());
+            central.extend_from_slice(&[0; 8]);
+            central.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            central.extend_from_slice(&(content.len() as u32).to_le_bytes());
+            central.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            central.extend_from_slice(&[0; 12]);
+            central.extend_from_slice(&offset.to_le_bytes());
+            central.extend_from_slice(name.as_bytes());
+        }
+        let central_offset = out.len() as u32;
+        out.extend_from_slice(&central);
+        out.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+        out.extend_from_slice(&[0; 4]);
+        out.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        out.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        out.extend_from_slice(&(central.len() as u32).to_le_bytes());
+        out.extend_from_slice(&central_offset.to_le_bytes());
+        out.extend_from_slice(&[0, 0]);
+        out
+    }
+
+    #[test]
+    fn test_scan_installed_and_archived_packs() {
+        let root = TempDir::new().unwrap();
+        let installed = root
+            .path()
+            .join("Keil")
+            .join("STM32F4xx_DFP")
+            .join("2.17.0");
+        fs::create_dir_all(&installed).unwrap();
+        fs::write(
+            installed.join("Keil.STM32F4xx_DFP.pdsc"),
+            PDSC.replace("2.17.1", "2.16.0"),
+        )
+        .unwrap();
+
+        let download = root.path().join(".Download");
+        fs::create_dir_all(&download).unwrap();
+        let archive = zip(&[
+            ("Include/stm32f4xx.h", "/* header */", false),
+            ("Keil.STM32F4xx_DFP.pdsc", PDSC, true),
+        ]);
+        fs::write(download.join("Keil.STM32F4xx_DFP.2.17.1.pack"), archive).unwrap();
+        fs::write(download.join("broken.pack"), b"not a zip").unwrap();
+
+        let index = PackIndex::scan(&[root.path().to_path_buf()]);
+        assert_eq!(index.packs.len(), 2);
+        assert_eq!(index.errors.len(), 1);
+
+        // The newer archived release wins, with archive-relative paths
+        let device = index.device("stm32f407vgtx").unwrap();
+        assert_eq!(device.pack.version, "2.17.1");
+        assert_eq!(device.headers, vec![PathBuf::from("Include/stm32f4xx.h")]);
+        assert!(matches!(index.packs[1].source, PackSource::Installed(_)));
+        assert!(index.packs[1].devices[0].headers[0].starts_with(&installed));
+
+        let found: Vec<_> = index
+            .search("f407")
+            .iter()
+            .map(|d| d.name.as_str())
+            .collect();
+        assert_eq!(found, vec!["STM32F407VGTx", "STM32F407VGHx"]);
+    }
+}
@@ -0,0 +1,586 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Pre-link object file consistency checks.
+//!
+//! Compares the ARM build attributes and compiler identification of every
+//! object before linking, so a hard-float object mixed into a soft-float
+//! image is reported up front rather than discovered on hardware.
+
+use crate::{ElfError, ElfFile};
+use axiom_core::{Diagnostic, Location, Position, Range};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Pre-link check errors.
+#[derive(Debug, Error)]
+pub enum PrelinkError {
+    #[error("{path}: {source}")]
+    Elf {
+        path: PathBuf,
+        #[source]
+        source: ElfError,
+    },
+
+    #[error("{path}: {source}")]
+    Attributes {
+        path: PathBuf,
+        #[source]
+        source: MalformedAttributes,
+    },
+}
+
+/// A `.ARM.attributes` subsection whose size doesn't cover its header or
+/// runs past the section.
+#[derive(Debug, Error)]
+#[error("Malformed .ARM.attributes subsection at offset {offset}")]
+pub struct MalformedAttributes {
+    /// Offset of the subsection within the section.
+    pub offset: usize,
+}
+
+/// Build attributes and compiler of a single object file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ObjectInfo {
+    /// Object file path.
+    pub path: PathBuf,
+    /// Checked build attributes, keyed by tag name.
+    pub attributes: BTreeMap<String, String>,
+    /// Compiler identification from the .comment section.
+    pub compiler: Option<String>,
+}
+
+/// An attribute whose value differs between objects.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttributeMismatch {
+    /// Attribute name.
+    pub attribute: String,
+    /// Objects grouped by the value they declare.
+    pub values: BTreeMap<String, Vec<PathBuf>>,
+}
+
+/// Result of checking a set of objects.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ObjectConsistencyReport {
+    /// Inspected objects.
+    pub objects: Vec<ObjectInfo>,
+    /// Attributes that differ between objects.
+    pub mismatches: Vec<AttributeMismatch>,
+}
+
+/// Name used for the compiler in mismatch reports.
+const COMPILER_ATTRIBUTE: &str = "Compiler";
+
+/// ARM attribute tags that must agree across an image.
+const CHECKED_TAGS: &[(u64, &str)] = &[
+    (5, "Tag_CPU_name"),
+    (6, "Tag_CPU_arch"),
+    (7, "Tag_CPU_arch_profile"),
+    (10, "Tag_FP_arch"),
+    (18, "Tag_ABI_PCS_wchar_t"),
+    (26, "Tag_ABI_enum_size"),
+    (28, "Tag_ABI_VFP_args"),
+];
+
+impl ObjectConsistencyReport {
+    /// Whether all objects agree.
+    pub fn is_consistent(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+
+    /// Convert mismatches into error diagnostics.
+    pub fn to_diagnostics(&self) -> Vec<Diagnostic> {
+        self.mismatches
+            .iter()
+            .map(|m| {
+                let detail = m
+                    .values
+                    .iter()
+                    .map(|(value, paths)| format!("{} in {}", value, join_paths(paths)))
+                    .collect::<Vec<_>>()
+                    .join("; ");
+                let mut diag =
+                    Diagnostic::error(format!("Incompatible {}: {}", m.attribute, detail))
+                        .with_code("prelink-mismatch");
+                // Point at the first object with the minority value
+                if let Some(path) = m
+                    .values
+                    .values()
+                    .min_by_key(|p| p.len())
+                    .and_then(|p| p.first())
+                {
+                    let origin = Position::new(0, 0);
+                    diag =
+                        diag.with_location(Location::new(path.clone(), Range::new(origin, origin)));
+                }
+                diag
+            })
+            .collect()
+    }
+}
+
+impl std::fmt::Display for ObjectConsistencyReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_consistent() {
+            return write!(f, "{} object files are consistent", self.objects.len());
+        }
+        writeln!(f, "Object files were built with incompatible settings:")?;
+        for m in &self.mismatches {
+            writeln!(f, "  {}:", m.attribute)?;
+            for (value, paths) in &m.values {
+                writeln!(f, "    {}: {}", value, join_paths(paths))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn join_paths(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|p| p.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Inspect objects and report attributes that differ between them.
+pub fn check_object_consistency(
+    objects: &[PathBuf],
+) -> Result<ObjectConsistencyReport, PrelinkError> {
+    let infos = objects
+        .iter()
+        .map(|path| inspect_object(path))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut names: Vec<&str> = CHECKED_TAGS.iter().map(|(_, name)| *name).collect();
+    names.push(COMPILER_ATTRIBUTE);
+
+    // Objects that do not declare an attribute (e.g. hand-written assembly)
+    // are not counted against it.
+    let mut mismatches = Vec::new();
+    for name in names {
+        let mut values: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+        for info in &infos {
+            let value = match name {
+                COMPILER_ATTRIBUTE => info.compiler.as_ref(),
+                _ => info.attributes.get(name),
+            };
+            if let Some(value) = value {
+                values
+                    .entry(value.clone())
+                    .or_default()
+                    .push(info.path.clone());
+            }
+        }
+        if values.len() > 1 {
+            mismatches.push(AttributeMismatch {
+                attribute: name.to_string(),
+                values,
+            });
+        }
+    }
+
+    Ok(ObjectConsistencyReport {
+        objects: infos,
+        mismatches,
+    })
+}
+
+/// Read the build attributes and compiler of one object.
+pub fn inspect_object(path: &Path) -> Result<ObjectInfo, PrelinkError> {
+    let elf = ElfFile::read(path).map_err(|source| PrelinkError::Elf {
+        path: path.to_path_buf(),
+        source,
+    })?;
+
+    let attributes = match elf
+        .section(".ARM.attributes")
+        .and_then(|s| elf.section_data(s))
+    {
+        Some(data) => parse_arm_attributes(data, elf.big_endian).map_err(|source| {
+            PrelinkError::Attributes {
+                path: path.to_path_buf(),
+                source,
+            }
+        })?,
+        None => BTreeMap::new(),
+    };
+
+    let compiler = elf
+        .section(".comment")
+        .and_then(|s| elf.section_data(s))
+        .and_then(parse_compiler_comment);
+
+    Ok(ObjectInfo {
+        path: path.to_path_buf(),
+        attributes,
+        compiler,
+    })
+}
+
+/// Extract the compiler identification from a .comment section.
+fn parse_compiler_comment(data: &[u8]) -> Option<String> {
+    let mut ids: Vec<&str> = data
+        .split(|&b| b == 0)
+        .filter_map(|s| std::str::from_utf8(s).ok())
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .collect();
+    ids.dedup();
+    if ids.is_empty() {
+        None
+    } else {
+        Some(ids.join("; "))
+    }
+}
+
+/// Parse the checked tags of an `aeabi` .ARM.attributes section.
+pub fn parse_arm_attributes(
+    data: &[u8],
+    big_endian: bool,
+) -> Result<BTreeMap<String, String>, MalformedAttributes> {
+    let mut attributes = BTreeMap::new();
+    if data.first() != Some(&b'A') {
+        return Ok(attributes);
+    }
+
+    let read_u32 = |pos: usize| -> Option<usize> {
+        let b: [u8; 4] = data.get(pos..pos + 4)?.try_into().ok()?;
+        let v = if big_endian {
+            u32::from_be_bytes(b)
+        } else {
+            u32::from_le_bytes(b)
+        };
+        Some(v as usize)
+    };
+
+    let mut pos = 1;
+    while let Some(len) = read_u32(pos) {
+        let end = (pos + len).min(data.len());
+        if len < 4 {
+            break;
+        }
+        let vendor = crate::elf::read_cstr(&data[..end], pos + 4);
+        if vendor == "aeabi" {
+            let mut sub = pos + 4 + vendor.len() + 1;
+            while sub < end {
+                let (tag, after_tag) = read_uleb(data, sub);
+                let Some(size) = read_u32(after_tag) else {
+                    break;
+                };
+                // The size covers the tag and itself
+                let body = after_tag + 4;
+                if sub + size < body || body > end {
+                    return Err(MalformedAttributes { offset: sub });
+                }
+                let sub_end = (sub + size).min(end);
+                // Tag_File: attributes apply to the whole object
+                if tag == 1 {
+                    parse_attribute_list(&data[body..sub_end], &mut attributes);
+                }
+                sub = sub_end;
+            }
+        }
+        pos = end;
+    }
+
+    Ok(attributes)
+}
+
+fn parse_attribute_list(data: &[u8], out: &mut BTreeMap<String, String>) {
+    let mut pos = 0;
+    while pos < data.len() {
+        let (tag, next) = read_uleb(data, pos);
+        pos = next;
+
+        // String-valued tags; beyond 32, odd tags are strings by convention
+        let value = if tag == 4 || tag == 5 || tag == 67 || (tag > 32 && tag % 2 == 1) {
+            let s = crate::elf::read_cstr(data, pos);
+            pos += s.len() + 1;
+            AttributeValue::Str(s.to_string())
+        } else if tag == 32 {
+            // Tag_compatibility: flag followed by vendor name
+            let (flag, next) = read_uleb(data, pos);
+            pos = next;
+            if flag != 0 {
+                pos += crate::elf::read_cstr(data, pos).len() + 1;
+            }
+            continue;
+        } else {
+            let (v, next) = read_uleb(data, pos);
+            pos = next;
+            AttributeValue::Int(v)
+        };
+
+        if let Some(&(_, name)) = CHECKED_TAGS.iter().find(|(t, _)| *t == tag) {
+            if let Some(described) = describe_attribute(tag, &value) {
+                out.insert(name.to_string(), described);
+            }
+        }
+    }
+}
+
+enum AttributeValue {
+    Int(u64),
+    Str(String),
+}
+
+/// Render an attribute value for reports.
+fn describe_attribute(tag: u64, value: &AttributeValue) -> Option<String> {
+    let v = match value {
+        AttributeValue::Str(s) => return Some(s.clone()),
+        AttributeValue::Int(v) => *v,
+    };
+    let described = match (tag, v) {
+        (6, _) => match v {
+            0 => "Pre-v4",
+            1 => "v4",
+            2 => "v4T",
+            3 => "v5T",
+            4 => "v5TE",
+            5 => "v5TEJ",
+            6 => "v6",
+            7 => "v6KZ",
+            8 => "v6T2",
+            9 => "v6K",
+            10 => "v7",
+            11 => "v6-M",
+            12 => "v6S-M",
+            13 => "v7E-M",
+            14 => "v8-A",
+            15 => "v8-R",
+            16 => "v8-M.baseline",
+            17 => "v8-M.mainline",
+            21 => "v8.1-M.mainline",
+            _ => return Some(v.to_string()),
+        }
+        .to_string(),
+        (7, 0) => "none".to_string(),
+        (7, _) => char::from_u32(v as u32)
+            .map(|c| c.to_string())
+            .unwrap_or_else(|| v.to_string()),
+        (10, _) => match v {
+            0 => "none",
+            1 => "VFPv1",
+            2 => "VFPv2",
+            3 => "VFPv3",
+            4 => "VFPv3-D16",
+            5 => "VFPv4",
+            6 => "VFPv4-D16",
+            7 => "FP-ARMv8",
+            8 => "FPv5-D16",
+            _ => return Some(v.to_string()),
+        }
+        .to_string(),
+        (18, 0) => "unused".to_string(),
+        (18, _) => format!("{} bytes", v),
+        (26, _) => match v {
+            0 => "unused",
+            1 => "small",
+            2 => "int",
+            3 => "forced 32-bit",
+            _ => return Some(v.to_string()),
+        }
+        .to_string(),
+        (28, _) => match v {
+            0 => "soft-float",
+            1 => "hard-float",
+            2 => "toolchain-specific",
+            // Compatible with both calling conventions
+            3 => return None,
+            _ => return Some(v.to_string()),
+        }
+        .to_string(),
+        _ => v.to_string(),
+    };
+    Some(described)
+}
+
+fn read_uleb(data: &[u8], mut pos: usize) -> (u64, usize) {
+    let mut value = 0u64;
+    let mut shift = 0;
+    while let Some(&byte) = data.get(pos) {
+        pos += 1;
+        if shift < 64 {
+            value |= u64::from(byte & 0x7f) << shift;
+        }
+        shift += 7;
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+    (value, pos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf::{build_test_elf, SHT_ARM_ATTRIBUTES};
+
+    /// Build an .ARM.attributes section with the given File-scope attributes.
+    fn attributes_section(attrs: &[u8]) -> Vec<u8> {
+        let mut file = vec![1u8];
+        file.extend_from_slice(&((attrs.len() + 5) as u32).to_le_bytes());
+        file.extend_from_slice(attrs);
+
+        let mut section = vec![b'A'];
+        section.extend_from_slice(&((file.len() + 10) as u32).to_le_bytes());
+        section.extend_from_slice(b"aeabi\0");
+        section.extend_from_slice(&file);
+        section
+    }
+
+    fn cortex_m4_attrs(vfp_args: u8) -> Vec<u8> {
+        let mut attrs = vec![5];
+        attrs.extend_from_slice(b"cortex-m4\0");
+        attrs.extend_from_slice(&[6, 13, 7, b'M', 10, 6, 18, 4, 26, 1, 28, vfp_args]);
+        attrs
+    }
+
+    fn write_object(dir: &Path, name: &str, attrs: &[u8], comment: &[u8]) -> PathBuf {
+        let section = attributes_section(attrs);
+        let data = build_test_elf(&[
+            (".ARM.attributes", SHT_ARM_ATTRIBUTES, &section),
+            (".comment", 1, comment),
+        ]);
+        let path = dir.join(name);
+        std::fs::write(&path, data).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_parse_arm_attributes() {
+        let section = attributes_section(&cortex_m4_attrs(1));
+        let attrs = parse_arm_attributes(&section, false).unwrap();
+
+        assert_eq!(attrs["Tag_CPU_name"], "cortex-m4");
+        assert_eq!(attrs["Tag_CPU_arch"], "v7E-M");
+        assert_eq!(attrs["Tag_CPU_arch_profile"], "M");
+        assert_eq!(attrs["Tag_FP_arch"], "VFPv4-D16");
+        assert_eq!(attrs["Tag_ABI_PCS_wchar_t"], "4 bytes");
+        assert_eq!(attrs["Tag_ABI_enum_size"], "small");
+        assert_eq!(attrs["Tag_ABI_VFP_args"], "hard-float");
+    }
+
+    #[test]
+    fn test_parse_skips_unchecked_string_tags() {
+        // Tag_compatibility and Tag_conformance before a checked tag
+        let mut attrs = vec![32, 1];
+        attrs.extend_from_slice(b"gnu\0");
+        attrs.push(67);
+        attrs.extend_from_slice(b"2.09\0");
+        attrs.extend_from_slice(&[28, 0]);
+
+        let parsed = parse_arm_attributes(&attributes_section(&attrs), false).unwrap();
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed["Tag_ABI_VFP_args"], "soft-float");
+    }
+
+    #[test]
+    fn test_parse_truncated_subsection() {
+        // Tag_File subsections sized 1 to 4 bytes can't hold their header
+        for size in 0u32..5 {
+            let mut section = vec![b'A'];
+            section.extend_from_slice(&15u32.to_le_bytes());
+            section.extend_from_slice(b"aeabi\0");
+            section.push(1);
+            section.extend_from_slice(&size.to_le_bytes());
+
+            let err = parse_arm_attributes(&section, false).unwrap_err();
+            assert_eq!(err.offset, 11);
+        }
+
+        // A header cut off by the end of the section
+        let mut section = vec![b'A'];
+        section.extend_from_slice(&13u32.to_le_bytes());
+        section.extend_from_slice(b"aeabi\0");
+        section.extend_from_slice(&[1, 9, 0, 0, 0]);
+        assert!(parse_arm_attributes(&section, false).is_err());
+    }
+
+    #[test]
+    fn test_consistent_objects() {
+        let dir = tempfile::tempdir().unwrap();
+        let gcc = b"\0GCC: (Arm GNU Toolchain 13.2.rel1) 13.2.1 20231009\0";
+        let a = write_object(dir.path(), "a.o", &cortex_m4_attrs(1), gcc);
+        let b = write_object(dir.path(), "b.o", &cortex_m4_attrs(1), gcc);
+
+        let report = check_object_consistency(&[a, b]).unwrap();
+        assert!(report.is_consistent());
+        assert_eq!(
+            report.objects[0].compiler.as_deref(),
+            Some("GCC: (Arm GNU Toolchain 13.2.rel1) 13.2.1 20231009")
+        );
+        assert!(report.to_diagnostics().is_empty());
+    }
+
+    #[test]
+    fn test_float_abi_mismatch() {
+        let dir = tempfile::tempdir().unwrap();
+        let gcc = b"GCC: 13.2.1\0";
+        let a = write_object(dir.path(), "a.o", &cortex_m4_attrs(1), gcc);
+        let b = write_object(dir.path(), "b.o", &cortex_m4_attrs(1), gcc);
+        let c = write_object(dir.path(), "c.o", &cortex_m4_attrs(0), b"GCC: 12.3.1\0");
+
+        let report = check_object_consistency(&[a, b, c.clone()]).unwrap();
+        assert!(!report.is_consistent());
+
+        let names: Vec<_> = report
+            .mismatches
+            .iter()
+            .map(|m| m.attribute.as_str())
+            .collect();
+        assert_eq!(names, vec!["Tag_ABI_VFP_args", "Compiler"]);
+        assert_eq!(report.mismatches[0].values["soft-float"], vec![c.clone()]);
+        assert_eq!(report.mismatches[0].values["hard-float"].len(), 2);
+
+        let text = report.to_string();
+        assert!(text.contains("Tag_ABI_VFP_args:"));
+        assert!(text.contains("soft-float"));
+
+        let diags = report.to_diagnostics();
+        assert_eq!(diags.len(), 2);
+        assert_eq!(diags[0].code.as_deref(), Some("prelink-mismatch"));
+        assert_eq!(diags[0].location.as_ref().unwrap().path, c);
+    }
+
+    #[test]
+    fn test_objects_without_attributes_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = write_object(dir.path(), "a.o", &cortex_m4_attrs(1), b"GCC: 13\0");
+        let asm = dir.path().join("startup.o");
+        std::fs::write(&asm, build_test_elf(&[(".text", 1, b"\x00\xbf")])).unwrap();
+
+        let report = check_object_consistency(&[a, asm]).unwrap();
+        assert!(report.is_consistent());
+    }
+
+    #[test]
+    fn test_not_elf() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bad.o");
+        std::fs::write(&path, b"junk").unwrap();
+        assert!(matches!(
+            check_object_consistency(&[path]),
+            Err(PrelinkError::Elf { .. })
+        ));
+    }
+
+    #[test]
+    fn test_malformed_attributes() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut section = attributes_section(&cortex_m4_attrs(1));
+        // Shrink the Tag_File subsection below its header size
+        section[11 + 1..11 + 5].copy_from_slice(&2u32.to_le_bytes());
+        let path = dir.path().join("bad.o");
+        let data = build_test_elf(&[(".ARM.attributes", SHT_ARM_ATTRIBUTES, &section)]);
+        std::fs::write(&path, data).unwrap();
+
+        assert!(matches!(
+            check_object_consistency(&[path]),
+            Err(PrelinkError::Attributes { .. })
+        ));
+    }
+}
@@ -0,0 +1,376 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Per-file preprocessor configuration.
+//!
+//! The compiler is asked directly rather than guessed at: `-E -v` on an
+//! empty input lists the include search order for the file's flags, and
+//! `-E -dM` lists the built-in macros the target and language predefine.
+//! Both runs use the file's flags so `-mcpu`, `-std` and `--sysroot` are
+//! reflected, while `-D`/`-U` are reported separately.
+
+use crate::limits::run_limited;
+use crate::{CachedFlags, SourceKind};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Error type for preprocessor probing.
+#[derive(Debug, thiserror::Error)]
+pub enum PreprocessorError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Compiler failed: {0}")]
+    Failed(String),
+}
+
+/// Flags that write dependency files and take a value.
+const DEPENDENCY_VALUE_FLAGS: &[&str] = &["-MF", "-MT", "-MQ"];
+
+/// A macro definition.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MacroDefinition {
+    /// Name, with the parameter list for function-like macros
+    /// (`MAX(a,b)`).
+    pub name: String,
+    /// Replacement text.
+    pub value: String,
+}
+
+impl MacroDefinition {
+    /// Parse a `-D` value: `NAME` defines `NAME` as `1`.
+    pub fn from_define(define: &str) -> Self {
+        match define.split_once('=') {
+            Some((name, value)) => Self {
+                name: name.to_string(),
+                value: value.to_string(),
+            },
+            None => Self {
+                name: define.to_string(),
+                value: "1".to_string(),
+            },
+        }
+    }
+
+    /// Name without any parameter list.
+    pub fn identifier(&self) -> &str {
+        self.name.split('(').next().unwrap_or(&self.name)
+    }
+}
+
+/// What the preprocessor sees for one source file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PreprocessorConfig {
+    /// Source file.
+    pub source: PathBuf,
+    /// Defines from the command line, after `-U` and redefinitions.
+    pub defines: Vec<MacroDefinition>,
+    /// Directories searched only by `#include "..."`, in order, before
+    /// `include_paths`.
+    pub quote_include_paths: Vec<PathBuf>,
+    /// Directories searched by `#include <...>`, in order, including the
+    /// compiler's system directories.
+    pub include_paths: Vec<PathBuf>,
+    /// Macros the compiler predefines for this target and language.
+    pub builtin_macros: Vec<MacroDefinition>,
+}
+
+impl PreprocessorConfig {
+    /// Effective value of a macro: command-line defines override built-ins.
+    pub fn macro_value(&self, name: &str) -> Option<&str> {
+        self.defines
+            .iter()
+            .chain(&self.builtin_macros)
+            .find(|m| m.identifier() == name)
+            .map(|m| m.value.as_str())
+    }
+
+    /// Full `#include "..."` search order.
+    pub fn quote_search_order(&self) -> Vec<&Path> {
+        self.quote_include_paths
+            .iter()
+            .chain(&self.include_paths)
+            .map(PathBuf::as_path)
+            .collect()
+    }
+}
+
+/// Probe the preprocessor configuration for a source compiled with `flags`.
+pub fn probe_preprocessor(
+    source: &Path,
+    flags: &CachedFlags,
+) -> Result<PreprocessorConfig, PreprocessorError> {
+    let (defines, base) = split_flags(&flags.flags);
+    let language = match SourceKind::from_path(source) {
+        SourceKind::C => "c",
+        SourceKind::Cpp => "c++",
+        SourceKind::Assembly | SourceKind::PreprocessedAssembly => "assembler-with-cpp",
+    };
+
+    let search = run_preprocessor(&flags.compiler, &base, language, "-v")?;
+    let (quote_include_paths, include_paths) = parse_include_search(&search.stderr);
+    let builtins = run_preprocessor(&flags.compiler, &base, language, "-dM")?;
+
+    Ok(PreprocessorConfig {
+        source: source.to_path_buf(),
+        defines,
+        quote_include_paths,
+        include_paths,
+        builtin_macros: parse_macro_definitions(&builtins.stdout),
+    })
+}
+
+struct ProbeOutput {
+    stdout: String,
+    stderr: String,
+}
+
+fn run_preprocessor(
+    compiler: &Path,
+    flags: &[String],
+    language: &str,
+    mode: &str,
+) -> Result<ProbeOutput, PreprocessorError> {
+    let output = run_limited(
+        Command::new(compiler)
+            .args(flags)
+            .args(["-E", mode, "-x", language, "-"]),
+    )?;
+    let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+    if !output.status.success() {
+        return Err(PreprocessorError::Failed(stderr.trim().to_string()));
+    }
+    Ok(ProbeOutput {
+        stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+        stderr,
+    })
+}
+
+/// Split compile flags into resolved command-line defines and the flags to
+/// probe with: no `-D`/`-U`, no `-x`, and no dependency-file output.
+fn split_flags(flags: &[String]) -> (Vec<MacroDefinition>, Vec<String>) {
+    let mut defines: Vec<MacroDefinition> = Vec::new();
+    let mut base = Vec::new();
+    let mut iter = flags.iter();
+
+    while let Some(flag) = iter.next() {
+        if let Some(value) = flag.strip_prefix("-D") {
+            let value = if value.is_empty() {
+                iter.next().map(String::as_str)
+            } else {
+                Some(value)
+            };
+            if let Some(define) = value.map(MacroDefinition::from_define) {
+                defines.retain(|d| d.identifier() != define.identifier());
+                defines.push(define);
+            }
+        } else if let Some(name) = flag.strip_prefix("-U") {
+            let name = if name.is_empty() {
+                iter.next().map(String::as_str)
+            } else {
+                Some(name)
+            };
+            if let Some(name) = name {
+                defines.retain(|d| d.identifier() != name);
+            }
+        } else if flag == "-x" || DEPENDENCY_VALUE_FLAGS.contains(&flag.as_str()) {
+            iter.next();
+        } else if !matches!(flag.as_str(), "-MD" | "-MMD" | "-MP" | "-M" | "-MM")
+            && !flag.starts_with("-x")
+            && !DEPENDENCY_VALUE_FLAGS.iter().any(|f| flag.starts_with(f))
+        {
+            base.push(flag.clone());
+        }
+    }
+    (defines, base)
+}
+
+/// Parse `#define NAME VALUE` lines from `-dM` output.
+pub fn parse_macro_definitions(output: &str) -> Vec<MacroDefinition> {
+    output
+        .lines()
+        .filter_map(|line| line.strip_prefix("#define "))
+        .map(|definition| {
+            // A function-like macro's parameter list is part of its name
+            let end = match definition.find(['(', ' ']) {
+                Some(i) if definition[i..].starts_with('(') => definition[i..]
+                    .find(')')
+                    .map_or(definition.len(), |j| i + j + 1),
+                Some(i) => i,
+                None => definition.len(),
+            };
+            MacroDefinition {
+                name: definition[..end].to_string(),
+                value: definition[end..].trim_start().to_string(),
+            }
+        })
+        .collect()
+}
+
+/// Parse the quote and angle-bracket include search lists from `-E -v`
+/// output.
+pub fn parse_include_search(output: &str) -> (Vec<PathBuf>, Vec<PathBuf>) {
+    let mut quote = Vec::new();
+    let mut angle = Vec::new();
+    let mut current: Option<&mut Vec<PathBuf>> = None;
+
+    for line in output.lines() {
+        if line.starts_with("#include \"...\" search starts here") {
+            current = Some(&mut quote);
+        } else if line.starts_with("#include <...> search starts here") {
+            current = Some(&mut angle);
+        } else if line.starts_with("End of search list") {
+            break;
+        } else if let (Some(list), Some(dir)) = (current.as_mut(), line.strip_prefix(' ')) {
+            // Clang marks macOS framework directories
+            let dir = dir.trim().trim_end_matches(" (framework directory)");
+            list.push(PathBuf::from(dir));
+        } else {
+            current = None;
+        }
+    }
+    (quote, angle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GCC_VERBOSE: &str = "\
+Using built-in specs.
+COLLECT_GCC=arm-none-eabi-gcc
+ignoring nonexistent directory \"/opt/arm/arm-none-eabi/sys-include\"
+#include \"...\" search starts here:
+ quoted
+#include <...> search starts here:
+ inc
+ /opt/arm/lib/gcc/arm-none-eabi/13.2.1/include
+ /opt/arm/arm-none-eabi/include
+ /Library/Frameworks (framework directory)
+End of search list.
+";
+
+    #[test]
+    fn test_parse_include_search() {
+        let (quote, angle) = parse_include_search(GCC_VERBOSE);
+        assert_eq!(quote, vec![PathBuf::from("quoted")]);
+        assert_eq!(
+            angle,
+            vec![
+                PathBuf::from("inc"),
+                PathBuf::from("/opt/arm/lib/gcc/arm-none-eabi/13.2.1/include"),
+                PathBuf::from("/opt/arm/arm-none-eabi/include"),
+                PathBuf::from("/Library/Frameworks"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_macro_definitions() {
+        let macros = parse_macro_definitions(
+            "#define __ARM_ARCH 7\n#define __thumb__ 1\n#define __STDC__\n\
+             #define __INT64_C(c) c ## LL\n#define __VERSION__ \"13.2.1 20231009\"\n",
+        );
+        assert_eq!(macros.len(), 5);
+        assert_eq!(
+            macros[0],
+            MacroDefinition {
+                name: "__ARM_ARCH".to_string(),
+                value: "7".to_string()
+            }
+        );
+        assert_eq!(macros[2].value, "");
+        assert_eq!(macros[3].name, "__INT64_C(c)");
+        assert_eq!(macros[3].identifier(), "__INT64_C");
+        assert_eq!(macros[3].value, "c ## LL");
+        assert_eq!(macros[4].value, "\"13.2.1 20231009\"");
+    }
+
+    #[test]
+    fn test_split_flags() {
+        let flags: Vec<String> = [
+            "-mcpu=cortex-m4",
+            "-DDEBUG",
+            "-D",
+            "LEVEL=2",
+            "-DLEVEL=3",
+            "-DTRACE",
+            "-UTRACE",
+            "-Iinc",
+            "-MMD",
+            "-MF",
+            "main.d",
+            "-x",
+            "c",
+        ]
+        .iter()
+        .map(|f| f.to_string())
+        .collect();
+        let (defines, base) = split_flags(&flags);
+        assert_eq!(
+            defines,
+            vec![
+                MacroDefinition::from_define("DEBUG"),
+                MacroDefinition::from_define("LEVEL=3"),
+            ]
+        );
+        assert_eq!(defines[0].value, "1");
+        assert_eq!(base, vec!["-mcpu=cortex-m4", "-Iinc"]);
+    }
+
+    #[test]
+    fn test_macro_value_prefers_command_line() {
+        let config = PreprocessorConfig {
+            source: PathBuf::from("main.c"),
+            defines: vec![MacroDefinition::from_define("__OPTIMIZE__=0")],
+            quote_include_paths: vec![PathBuf::from("q")],
+            include_paths: vec![PathBuf::from("inc")],
+            builtin_macros: parse_macro_definitions(
+                "#define __OPTIMIZE__ 1\n#define __GNUC__ 13\n",
+            ),
+        };
+        assert_eq!(config.macro_value("__OPTIMIZE__"), Some("0"));
+        assert_eq!(config.macro_value("__GNUC__"), Some("13"));
+        assert_eq!(config.macro_value("MISSING"), None);
+        assert_eq!(
+            config.quote_search_order(),
+            vec![Path::new("q"), Path::new("inc")]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_probe_preprocessor() {
+        use crate::ToolchainKind;
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = tempfile::TempDir::new().unwrap();
+        // Stand-in compiler answering the two probes
+        let compiler = temp.path().join("gcc");
+        std::fs::write(
+            &compiler,
+            "#!/bin/sh\ncase \"$*\" in\n\
+             *-v*) printf '#include <...> search starts here:\\n /usr/include\\nEnd of search list.\\n' >&2 ;;\n\
+             *-dM*) echo \"#define __GNUC__ 13\"; echo \"#define FLAGS $*\" ;;\n\
+             esac\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&compiler, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let flags = CachedFlags {
+            compiler,
+            kind: ToolchainKind::Gcc,
+            version: "13".to_string(),
+            flags: vec!["-std=c11".to_string(), "-DNDEBUG".to_string()],
+        };
+
+        let config = probe_preprocessor(Path::new("src/main.cpp"), &flags).unwrap();
+        assert_eq!(config.include_paths, vec![PathBuf::from("/usr/include")]);
+        assert_eq!(config.defines, vec![MacroDefinition::from_define("NDEBUG")]);
+        assert_eq!(config.macro_value("__GNUC__"), Some("13"));
+        assert_eq!(
+            config.macro_value("FLAGS"),
+            Some("-std=c11 -E -dM -x c++ -")
+        );
+    }
+}
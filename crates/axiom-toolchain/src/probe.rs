@@ -0,0 +1,354 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Debug probe discovery.
+//!
+//! Probes are listed with `probe-rs list` when probe-rs is installed.
+//! Without it, Linux hosts fall back to the USB devices in sysfs, matched
+//! against known probe vendor and product IDs. Target voltage is measured
+//! separately through OpenOCD, since it needs the probe opened.
+
+use crate::limits::run_limited;
+use crate::{parse_openocd_output, OpenOcd, OpenOcdConfig, OpenOcdEvent, ProbeKind};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Known paths for probe-rs.
+const PROBE_RS_PATHS: &[&str] = &[
+    "/usr/local/bin/probe-rs",
+    "/opt/homebrew/bin/probe-rs",
+    "/usr/bin/probe-rs",
+];
+
+/// USB devices directory on Linux.
+const SYSFS_USB_DEVICES: &str = "/sys/bus/usb/devices";
+
+/// Known probes by USB vendor and product ID.
+const KNOWN_PROBES: &[(u16, u16, ProbeKind, &str)] = &[
+    (0x0483, 0x3744, ProbeKind::StLink, "ST-Link V1"),
+    (0x0483, 0x3748, ProbeKind::StLink, "ST-Link V2"),
+    (0x0483, 0x374b, ProbeKind::StLink, "ST-Link V2-1"),
+    (0x0483, 0x3752, ProbeKind::StLink, "ST-Link V2-1"),
+    (0x0483, 0x374d, ProbeKind::StLink, "ST-Link V3 Loader"),
+    (0x0483, 0x374e, ProbeKind::StLink, "ST-Link V3"),
+    (0x0483, 0x374f, ProbeKind::StLink, "ST-Link V3"),
+    (0x0483, 0x3753, ProbeKind::StLink, "ST-Link V3"),
+    (0x0483, 0x3754, ProbeKind::StLink, "ST-Link V3"),
+    (0x0d28, 0x0204, ProbeKind::CmsisDap, "DAPLink"),
+    (
+        0x2e8a,
+        0x000c,
+        ProbeKind::CmsisDap,
+        "Raspberry Pi Debug Probe",
+    ),
+    (0x1fc9, 0x0143, ProbeKind::CmsisDap, "MCU-Link"),
+    (0x03eb, 0x2141, ProbeKind::CmsisDap, "Atmel-ICE"),
+    (0x03eb, 0x2175, ProbeKind::CmsisDap, "EDBG"),
+];
+
+/// SEGGER's USB vendor ID; every product is a J-Link.
+const SEGGER_VID: u16 = 0x1366;
+
+/// How a probe was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ProbeSource {
+    /// `probe-rs list`.
+    ProbeRs,
+    /// USB device enumeration.
+    Usb,
+}
+
+/// A connected debug probe.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DebugProbe {
+    /// Probe family.
+    pub kind: ProbeKind,
+    /// Product name (e.g. "ST-Link V2-1").
+    pub name: String,
+    /// USB vendor ID.
+    pub vendor_id: u16,
+    /// USB product ID.
+    pub product_id: u16,
+    /// Serial number, if the probe reports one.
+    pub serial: Option<String>,
+    /// Target voltage in volts, once measured.
+    pub target_voltage: Option<f64>,
+    /// How the probe was found.
+    pub source: ProbeSource,
+}
+
+impl DebugProbe {
+    /// OpenOCD configuration selecting this probe.
+    pub fn openocd_config(&self, target_script: impl Into<String>) -> OpenOcdConfig {
+        let config = OpenOcdConfig::new(self.kind, target_script);
+        match &self.serial {
+            Some(serial) => config.with_serial(serial.clone()),
+            None => config,
+        }
+    }
+}
+
+/// Classify a USB device as a debug probe.
+pub fn classify_usb_device(
+    vendor_id: u16,
+    product_id: u16,
+    product: Option<&str>,
+) -> Option<(ProbeKind, String)> {
+    if let Some((_, _, kind, name)) = KNOWN_PROBES
+        .iter()
+        .find(|(vid, pid, _, _)| *vid == vendor_id && *pid == product_id)
+    {
+        return Some((*kind, name.to_string()));
+    }
+    if vendor_id == SEGGER_VID {
+        return Some((ProbeKind::JLink, product.unwrap_or("J-Link").to_string()));
+    }
+    // CMSIS-DAP firmware must name itself in the product string
+    product
+        .filter(|p| p.contains("CMSIS-DAP"))
+        .map(|p| (ProbeKind::CmsisDap, p.to_string()))
+}
+
+/// Find probe-rs at the known install paths or in `~/.cargo/bin`.
+pub fn find_probe_rs() -> Option<PathBuf> {
+    let cargo_bin = std::env::var_os("HOME").map(|home| {
+        PathBuf::from(home)
+            .join(".cargo")
+            .join("bin")
+            .join("probe-rs")
+    });
+    PROBE_RS_PATHS
+        .iter()
+        .map(PathBuf::from)
+        .chain(cargo_bin)
+        .find(|p| p.is_file())
+}
+
+/// Parse `probe-rs list` output.
+///
+/// ```text
+/// The following debug probes were found:
+/// [0]: STLink V2-1 -- 0483:374b:066DFF485550755187121723 (ST-LINK)
+/// [1]: J-Link (J-Link) -- 1366:0101:000683000000 (J-Link)
+/// ```
+///
+/// Probe types Axiom can't drive through OpenOCD are skipped.
+pub fn parse_probe_rs_list(output: &str) -> Vec<DebugProbe> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            let rest = line.strip_prefix('[')?;
+            let (_, rest) = rest.split_once("]: ")?;
+            let (name, rest) = rest.rsplit_once(" -- ")?;
+            let (ids, probe_type) = rest.split_once(' ')?;
+
+            let mut parts = ids.splitn(3, ':');
+            let vendor_id = u16::from_str_radix(parts.next()?, 16).ok()?;
+            let product_id = u16::from_str_radix(parts.next()?, 16).ok()?;
+            let serial = parts.next().filter(|s| !s.is_empty()).map(str::to_string);
+
+            let kind = match probe_type.trim_matches(|c| c == '(' || c == ')') {
+                "ST-LINK" => ProbeKind::StLink,
+                "J-Link" => ProbeKind::JLink,
+                "CMSIS-DAP" => ProbeKind::CmsisDap,
+                _ => return None,
+            };
+            Some(DebugProbe {
+                kind,
+                name: name.trim().to_string(),
+                vendor_id,
+                product_id,
+                serial,
+                target_voltage: None,
+                source: ProbeSource::ProbeRs,
+            })
+        })
+        .collect()
+}
+
+/// List probes with probe-rs.
+pub fn probe_rs_probes(probe_rs: &Path) -> Option<Vec<DebugProbe>> {
+    let output = run_limited(Command::new(probe_rs).arg("list")).ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(parse_probe_rs_list(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+/// List probes among USB devices in a sysfs devices directory.
+pub fn usb_probes(devices_dir: &Path) -> Vec<DebugProbe> {
+    let Ok(entries) = std::fs::read_dir(devices_dir) else {
+        return Vec::new();
+    };
+    let mut dirs: Vec<PathBuf> = entries.filter_map(|e| e.ok().map(|e| e.path())).collect();
+    dirs.sort();
+
+    let read = |dir: &Path, name: &str| {
+        std::fs::read_to_string(dir.join(name))
+            .ok()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+    };
+    dirs.iter()
+        .filter_map(|dir| {
+            let vendor_id = u16::from_str_radix(&read(dir, "idVendor")?, 16).ok()?;
+            let product_id = u16::from_str_radix(&read(dir, "idProduct")?, 16).ok()?;
+            let product = read(dir, "product");
+            let (kind, name) = classify_usb_device(vendor_id, product_id, product.as_deref())?;
+            Some(DebugProbe {
+                kind,
+                name,
+                vendor_id,
+                product_id,
+                serial: read(dir, "serial"),
+                target_voltage: None,
+                source: ProbeSource::Usb,
+            })
+        })
+        .collect()
+}
+
+/// Discover connected probes.
+pub fn discover_probes() -> Vec<DebugProbe> {
+    if let Some(probes) = find_probe_rs().and_then(|p| probe_rs_probes(&p)) {
+        return probes;
+    }
+    if cfg!(target_os = "linux") {
+        return usb_probes(Path::new(SYSFS_USB_DEVICES));
+    }
+    Vec::new()
+}
+
+/// Measure the target voltage seen by a probe.
+///
+/// Opens the probe with OpenOCD without a target script; the voltage is
+/// reported while the adapter initializes, even if init then fails.
+pub fn probe_target_voltage(openocd: &OpenOcd, probe: &DebugProbe) -> Option<f64> {
+    let mut args = Vec::new();
+    if let Some(scripts) = &openocd.scripts {
+        args.push("-s".to_string());
+        args.push(scripts.display().to_string());
+    }
+    args.push("-f".to_string());
+    args.push(probe.kind.interface_script().to_string());
+    if let Some(serial) = &probe.serial {
+        args.push("-c".to_string());
+        args.push(format!("adapter serial {}", serial));
+    }
+    args.push("-c".to_string());
+    args.push("init; shutdown".to_string());
+
+    let output = run_limited(Command::new(&openocd.path).args(&args)).ok()?;
+    let text = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    parse_openocd_output(&text)
+        .events
+        .into_iter()
+        .find_map(|e| match e {
+            OpenOcdEvent::TargetVoltage { volts } => Some(volts),
+            _ => None,
+        })
+}
+
+/// Discover probes and measure each one's target voltage with OpenOCD.
+pub fn discover_probes_with_voltage(openocd: &OpenOcd) -> Vec<DebugProbe> {
+    discover_probes()
+        .into_iter()
+        .map(|mut probe| {
+            probe.target_voltage = probe_target_voltage(openocd, &probe);
+            probe
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_probe_rs_list() {
+        let output = "The following debug probes were found:\n\
+            [0]: STLink V2-1 -- 0483:374b:066DFF485550755187121723 (ST-LINK)\n\
+            [1]: J-Link (J-Link) -- 1366:0101:000683000000 (J-Link)\n\
+            [2]: ESP JTAG -- 303a:1001:F4:12:FA:00:11:22 (EspJtag)\n\
+            [3]: CMSIS-DAP -- 0d28:0204 (CMSIS-DAP)\n";
+        let probes = parse_probe_rs_list(output);
+        assert_eq!(probes.len(), 3);
+
+        assert_eq!(probes[0].kind, ProbeKind::StLink);
+        assert_eq!(probes[0].name, "STLink V2-1");
+        assert_eq!(probes[0].vendor_id, 0x0483);
+        assert_eq!(probes[0].product_id, 0x374b);
+        assert_eq!(
+            probes[0].serial.as_deref(),
+            Some("066DFF485550755187121723")
+        );
+        assert_eq!(probes[1].kind, ProbeKind::JLink);
+        assert_eq!(probes[1].name, "J-Link (J-Link)");
+        assert_eq!(probes[2].kind, ProbeKind::CmsisDap);
+        assert_eq!(probes[2].serial, None);
+    }
+
+    #[test]
+    fn test_classify_usb_device() {
+        assert_eq!(
+            classify_usb_device(0x0483, 0x374e, None),
+            Some((ProbeKind::StLink, "ST-Link V3".to_string()))
+        );
+        assert_eq!(
+            classify_usb_device(0x1366, 0x1051, Some("J-Link OB")),
+            Some((ProbeKind::JLink, "J-Link OB".to_string()))
+        );
+        assert_eq!(
+            classify_usb_device(0xc251, 0xf002, Some("LPC-Link CMSIS-DAP")),
+            Some((ProbeKind::CmsisDap, "LPC-Link CMSIS-DAP".to_string()))
+        );
+        assert_eq!(classify_usb_device(0x046d, 0xc077, Some("USB Mouse")), None);
+    }
+
+    #[test]
+    fn test_usb_probes_from_sysfs() {
+        let dir = TempDir::new().unwrap();
+        let device = |name: &str, files: &[(&str, &str)]| {
+            let path = dir.path().join(name);
+            fs::create_dir(&path).unwrap();
+            for (file, content) in files {
+                fs::write(path.join(file), format!("{}\n", content)).unwrap();
+            }
+        };
+        device(
+            "1-1",
+            &[
+                ("idVendor", "0483"),
+                ("idProduct", "374b"),
+                ("product", "STM32 STLink"),
+                ("serial", "0671FF3833554B3043164817"),
+            ],
+        );
+        device("1-2", &[("idVendor", "046d"), ("idProduct", "c077")]);
+        device("1-1:1.0", &[("bInterfaceClass", "ff")]);
+
+        let probes = usb_probes(dir.path());
+        assert_eq!(probes.len(), 1);
+        assert_eq!(probes[0].kind, ProbeKind::StLink);
+        assert_eq!(probes[0].name, "ST-Link V2-1");
+        assert_eq!(probes[0].source, ProbeSource::Usb);
+        assert_eq!(
+            probes[0].serial.as_deref(),
+            Some("0671FF3833554B3043164817")
+        );
+
+        let config = probes[0].openocd_config("target/stm32f4x.cfg");
+        assert_eq!(config.serial.as_deref(), Some("0671FF3833554B3043164817"));
+    }
+}
@@ -0,0 +1,367 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Build profiles.
+//!
+//! Every project has the built-in `debug`, `release` and `coverage`
+//! profiles. Projects add their own, or replace a built-in one, in
+//! `.axiom/profiles.toml`:
+//!
+//! ```toml
+//! [[profile]]
+//! name = "bench"
+//! optimization = "O2"
+//! defines = ["BENCHMARK=1"]
+//! ```
+
+use crate::OptimizationLevel;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Build profile errors.
+#[derive(Debug, Error)]
+pub enum ProfileError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("TOML parse error in {path}: {source}")]
+    Toml {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+
+    #[error("No build profile named {0}")]
+    NotFound(String),
+}
+
+/// What a profile is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ProfileKind {
+    /// Development builds: light optimization, full debug info.
+    Debug,
+    /// Shipping builds: optimized for size, assertions off.
+    Release,
+    /// Instrumented builds for gcov.
+    Coverage,
+    /// Anything else.
+    #[default]
+    Custom,
+}
+
+/// A named set of compile and link flags.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildProfile {
+    /// Profile name (e.g. "debug").
+    pub name: String,
+    /// What the profile is for.
+    #[serde(default)]
+    pub kind: ProfileKind,
+    /// Optimization level.
+    #[serde(default)]
+    pub optimization: OptimizationLevel,
+    /// Include debug symbols.
+    #[serde(default)]
+    pub debug: bool,
+    /// Preprocessor defines (`NAME` or `NAME=VALUE`).
+    #[serde(default)]
+    pub defines: Vec<String>,
+    /// Include directories.
+    #[serde(default)]
+    pub include_paths: Vec<PathBuf>,
+    /// Other compiler flags.
+    #[serde(default)]
+    pub cflags: Vec<String>,
+    /// Linker flags.
+    #[serde(default)]
+    pub ldflags: Vec<String>,
+}
+
+impl BuildProfile {
+    /// Create an empty profile.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            kind: ProfileKind::Custom,
+            optimization: OptimizationLevel::O0,
+            debug: false,
+            defines: Vec::new(),
+            include_paths: Vec::new(),
+            cflags: Vec::new(),
+            ldflags: Vec::new(),
+        }
+    }
+
+    /// The built-in `debug` profile: `-Og -g -DDEBUG`.
+    pub fn debug() -> Self {
+        Self {
+            kind: ProfileKind::Debug,
+            optimization: OptimizationLevel::Og,
+            debug: true,
+            defines: vec!["DEBUG".to_string()],
+            ..Self::new("debug")
+        }
+    }
+
+    /// The built-in `release` profile: `-Os -g -DNDEBUG`.
+    ///
+    /// Debug info stays on; it doesn't reach the flashed image.
+    pub fn release() -> Self {
+        Self {
+            kind: ProfileKind::Release,
+            optimization: OptimizationLevel::Os,
+            debug: true,
+            defines: vec!["NDEBUG".to_string()],
+            ..Self::new("release")
+        }
+    }
+
+    /// The built-in `coverage` profile: unoptimized, instrumented with
+    /// `--coverage` at compile and link time.
+    pub fn coverage() -> Self {
+        Self {
+            kind: ProfileKind::Coverage,
+            optimization: OptimizationLevel::O0,
+            debug: true,
+            cflags: vec!["--coverage".to_string()],
+            ldflags: vec!["--coverage".to_string()],
+            ..Self::new("coverage")
+        }
+    }
+
+    /// The built-in profiles.
+    pub fn builtin() -> Vec<Self> {
+        vec![Self::debug(), Self::release(), Self::coverage()]
+    }
+
+    /// Set what the profile is for.
+    pub fn with_kind(mut self, kind: ProfileKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Set the optimization level.
+    pub fn with_optimization(mut self, level: OptimizationLevel) -> Self {
+        self.optimization = level;
+        self
+    }
+
+    /// Enable or disable debug symbols.
+    pub fn with_debug(mut self, debug: bool) -> Self {
+        self.debug = debug;
+        self
+    }
+
+    /// Add a preprocessor define.
+    pub fn with_define(mut self, define: impl Into<String>) -> Self {
+        self.defines.push(define.into());
+        self
+    }
+
+    /// Add a compiler flag.
+    pub fn with_cflag(mut self, flag: impl Into<String>) -> Self {
+        self.cflags.push(flag.into());
+        self
+    }
+
+    /// Add a linker flag.
+    pub fn with_ldflag(mut self, flag: impl Into<String>) -> Self {
+        self.ldflags.push(flag.into());
+        self
+    }
+
+    /// Classify compiler flags into optimization, debug, defines, include
+    /// paths, and everything else.
+    pub fn add_compile_flags<S: AsRef<str>>(&mut self, flags: &[S]) {
+        let mut iter = flags.iter().map(AsRef::as_ref);
+        while let Some(flag) = iter.next() {
+            if let Some(level) = OptimizationLevel::from_flag(flag) {
+                self.optimization = level;
+            } else if flag.starts_with("-g") && flag[2..].chars().all(|c| c.is_ascii_alphanumeric())
+            {
+                self.debug = true;
+            } else if let Some(define) = flag.strip_prefix("-D") {
+                match define {
+                    "" => self.defines.extend(iter.next().map(String::from)),
+                    _ => self.defines.push(define.to_string()),
+                }
+            } else if let Some(path) = flag.strip_prefix("-I") {
+                match path {
+                    "" => self.include_paths.extend(iter.next().map(PathBuf::from)),
+                    _ => self.include_paths.push(PathBuf::from(path)),
+                }
+            } else {
+                self.cflags.push(flag.to_string());
+            }
+        }
+    }
+
+    /// All compiler flags for this profile.
+    pub fn compile_flags(&self) -> Vec<String> {
+        let mut flags = vec![self.optimization.flag().to_string()];
+        if self.debug {
+            flags.push("-g".to_string());
+        }
+        flags.extend(self.defines.iter().map(|d| format!("-D{}", d)));
+        flags.extend(
+            self.include_paths
+                .iter()
+                .map(|p| format!("-I{}", p.display())),
+        );
+        flags.extend(self.cflags.iter().cloned());
+        flags
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ProfilesFile {
+    #[serde(default)]
+    profile: Vec<BuildProfile>,
+}
+
+/// Path of a project's build profile declarations.
+pub fn build_profiles_path(project_root: &Path) -> PathBuf {
+    project_root.join(".axiom").join("profiles.toml")
+}
+
+/// A project's build profiles: the built-in ones, with any of the same name
+/// replaced by the project's, followed by the project's own.
+pub fn load_build_profiles(project_root: &Path) -> Result<Vec<BuildProfile>, ProfileError> {
+    let mut profiles = BuildProfile::builtin();
+    let path = build_profiles_path(project_root);
+    if !path.is_file() {
+        return Ok(profiles);
+    }
+    let content = std::fs::read_to_string(&path)?;
+    let file: ProfilesFile =
+        toml::from_str(&content).map_err(|source| ProfileError::Toml { path, source })?;
+
+    for profile in file.profile {
+        match profiles.iter_mut().find(|p| p.name == profile.name) {
+            Some(existing) => *existing = profile,
+            None => profiles.push(profile),
+        }
+    }
+    Ok(profiles)
+}
+
+/// Look up a build profile by name. Without a project only the built-in
+/// profiles are available.
+pub fn find_build_profile(
+    project_root: Option<&Path>,
+    name: &str,
+) -> Result<BuildProfile, ProfileError> {
+    let profiles = match project_root {
+        Some(root) => load_build_profiles(root)?,
+        None => BuildProfile::builtin(),
+    };
+    profiles
+        .into_iter()
+        .find(|p| p.name == name)
+        .ok_or_else(|| ProfileError::NotFound(name.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_compile_flags() {
+        let mut profile = BuildProfile::new("debug");
+        profile.add_compile_flags(&[
+            "-mcpu=cortex-m4",
+            "-Og",
+            "-g3",
+            "-DSTM32F407xx",
+            "-D",
+            "DEBUG=1",
+            "-Iinc",
+            "-I",
+            "drivers/inc",
+            "-Wall",
+        ]);
+
+        assert_eq!(profile.optimization, OptimizationLevel::Og);
+        assert!(profile.debug);
+        assert_eq!(profile.defines, vec!["STM32F407xx", "DEBUG=1"]);
+        assert_eq!(
+            profile.include_paths,
+            vec![PathBuf::from("inc"), PathBuf::from("drivers/inc")]
+        );
+        assert_eq!(profile.cflags, vec!["-mcpu=cortex-m4", "-Wall"]);
+    }
+
+    #[test]
+    fn test_builtin_profiles() {
+        let names: Vec<_> = BuildProfile::builtin()
+            .into_iter()
+            .map(|p| (p.name, p.kind))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("debug".to_string(), ProfileKind::Debug),
+                ("release".to_string(), ProfileKind::Release),
+                ("coverage".to_string(), ProfileKind::Coverage),
+            ]
+        );
+        assert_eq!(
+            BuildProfile::release().compile_flags(),
+            vec!["-Os", "-g", "-DNDEBUG"]
+        );
+        assert_eq!(BuildProfile::coverage().ldflags, vec!["--coverage"]);
+    }
+
+    #[test]
+    fn test_load_project_profiles() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(
+            load_build_profiles(dir.path()).unwrap(),
+            BuildProfile::builtin()
+        );
+
+        std::fs::create_dir(dir.path().join(".axiom")).unwrap();
+        std::fs::write(
+            build_profiles_path(dir.path()),
+            r#"
+[[profile]]
+name = "release"
+kind = "release"
+optimization = "O2"
+defines = ["NDEBUG"]
+
+[[profile]]
+name = "bench"
+optimization = "O3"
+ldflags = ["-Wl,--print-memory-usage"]
+"#,
+        )
+        .unwrap();
+
+        let profiles = load_build_profiles(dir.path()).unwrap();
+        assert_eq!(profiles.len(), 4);
+        assert_eq!(profiles[1].optimization, OptimizationLevel::O2);
+        assert!(!profiles[1].debug);
+
+        let bench = find_build_profile(Some(dir.path()), "bench").unwrap();
+        assert_eq!(bench.kind, ProfileKind::Custom);
+        assert_eq!(bench.ldflags, vec!["-Wl,--print-memory-usage"]);
+
+        assert!(find_build_profile(None, "bench").is_err());
+        assert_eq!(
+            find_build_profile(None, "coverage").unwrap(),
+            BuildProfile::coverage()
+        );
+    }
+
+    #[test]
+    fn test_compile_flags_round_trip() {
+        let mut profile = BuildProfile::new("release");
+        profile.add_compile_flags(&["-Os", "-DNDEBUG", "-Iinc", "-ffunction-sections"]);
+
+        let mut again = BuildProfile::new("release");
+        again.add_compile_flags(&profile.compile_flags());
+        assert_eq!(profile, again);
+    }
+}
@@ -0,0 +1,660 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Tool operational verification.
+//!
+//! A fixed suite of known-input compile and link cases is run against a
+//! toolchain and the resulting code is checksummed. Checksums from a
+//! reference run are stored as the project's baseline in
+//! `.axiom/qualification.toml`; later runs pass only when every case
+//! reproduces its baseline, giving DO-330 tool operational requirements
+//! evidence that the installed toolchain behaves as qualified.
+//!
+//! Only allocated sections and relocations are hashed, so file names, debug
+//! info and the scratch directory don't affect the result.
+
+use crate::limits::run_limited;
+use crate::{sha256_file, DetectedToolchain, ElfFile, ToolchainKind, ToolchainSource};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+/// Baseline file name, in the project's `.axiom` directory.
+pub const QUALIFICATION_BASELINE: &str = "qualification.toml";
+
+const SHF_ALLOC: u64 = 0x2;
+const SHT_RELA: u32 = 4;
+const SHT_NOBITS: u32 = 8;
+const SHT_REL: u32 = 9;
+
+/// Error type for qualification baselines.
+#[derive(Debug, thiserror::Error)]
+pub enum QualificationError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid qualification baseline {path}: {source}")]
+    Parse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+
+    #[error("Cannot serialize qualification baseline: {0}")]
+    Serialize(#[from] toml::ser::Error),
+}
+
+/// A source file of a case.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CaseSource {
+    /// File name.
+    pub name: String,
+    /// Contents.
+    pub content: String,
+}
+
+/// A known-input verification case.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QualificationCase {
+    /// Stable identifier, referenced by baselines and reports.
+    pub id: String,
+    /// What the case exercises.
+    pub description: String,
+    /// Sources, each compiled to an object.
+    pub sources: Vec<CaseSource>,
+    /// Compiler flags, besides the target flags.
+    pub flags: Vec<String>,
+    /// Link the objects into an executable and checksum that instead.
+    pub link: bool,
+}
+
+impl QualificationCase {
+    fn new(id: &str, description: &str, flags: &[&str], sources: &[(&str, &str)]) -> Self {
+        Self {
+            id: id.to_string(),
+            description: description.to_string(),
+            sources: sources
+                .iter()
+                .map(|(name, content)| CaseSource {
+                    name: name.to_string(),
+                    content: content.to_string(),
+                })
+                .collect(),
+            flags: flags.iter().map(|f| f.to_string()).collect(),
+            link: false,
+        }
+    }
+
+    fn linked(mut self) -> Self {
+        self.link = true;
+        self
+    }
+}
+
+/// The standard verification suite.
+pub fn standard_cases() -> Vec<QualificationCase> {
+    vec![
+        QualificationCase::new(
+            "OV-001",
+            "Integer arithmetic, shifts and constant folding",
+            &["-O0"],
+            &[("arith.c", ARITH_C)],
+        ),
+        QualificationCase::new(
+            "OV-002",
+            "Integer arithmetic under optimization",
+            &["-O2"],
+            &[("arith.c", ARITH_C)],
+        ),
+        QualificationCase::new(
+            "OV-003",
+            "Structure layout, alignment and bit-fields",
+            &["-Os"],
+            &[("layout.c", LAYOUT_C)],
+        ),
+        QualificationCase::new(
+            "OV-004",
+            "Switch lowering to jump tables",
+            &["-O2"],
+            &[("switch.c", SWITCH_C)],
+        ),
+        QualificationCase::new(
+            "OV-005",
+            "Volatile access ordering",
+            &["-Os"],
+            &[("volatile.c", VOLATILE_C)],
+        ),
+        QualificationCase::new(
+            "OV-006",
+            "Floating-point arithmetic and conversions",
+            &["-O2"],
+            &[("float.c", FLOAT_C)],
+        ),
+        QualificationCase::new(
+            "OV-007",
+            "Linking with a script and section garbage collection",
+            &["-Os", "-ffunction-sections", "-fdata-sections"],
+            &[("start.c", START_C), ("arith.c", ARITH_C)],
+        )
+        .linked(),
+    ]
+}
+
+const ARITH_C: &str = "\
+unsigned int mix(unsigned int a, unsigned int b) {
+    return (a << 3) ^ (b >> 2) ^ (a * 2654435761u) ^ (b / 7u) ^ (a % 13u);
+}
+
+int saturate(int value, int low, int high) {
+    return value < low ? low : value > high ? high : value;
+}
+
+long long widen(int a, int b) {
+    return (long long)a * b + (1 << 20) - 42;
+}
+";
+
+const LAYOUT_C: &str = "\
+#include <stddef.h>
+
+struct packet {
+    unsigned char kind;
+    unsigned int length;
+    unsigned short flags : 3, channel : 5, priority : 8;
+    double timestamp;
+};
+
+_Static_assert(offsetof(struct packet, length) == 4, \"length offset\");
+
+const struct packet template = { 1, 64, 5, 17, 200, 0.5 };
+
+unsigned int channel_of(const struct packet *p) {
+    return p->channel + p->priority;
+}
+";
+
+const SWITCH_C: &str = "\
+int classify(int code) {
+    switch (code) {
+    case 0: return 10;
+    case 1: return 27;
+    case 2: return -3;
+    case 3: return 99;
+    case 4: return 5;
+    case 5: return 64;
+    case 6: return 8;
+    case 7: return -1;
+    default: return 0;
+    }
+}
+";
+
+const VOLATILE_C: &str = "\
+#define REG(addr) (*(volatile unsigned int *)(addr))
+
+void configure(unsigned int mode) {
+    REG(0x40021000) |= 1u << 4;
+    REG(0x40010800) = mode;
+    while ((REG(0x40010808) & 0x1u) == 0) {
+    }
+    REG(0x40010800) = 0;
+}
+";
+
+const FLOAT_C: &str = "\
+float lerp(float a, float b, float t) {
+    return a + (b - a) * t;
+}
+
+int quantize(double value, double scale) {
+    return (int)(value * scale + 0.5);
+}
+";
+
+const START_C: &str = "\
+unsigned int mix(unsigned int a, unsigned int b);
+
+volatile unsigned int sink;
+
+void _start(void) {
+    sink = mix(sink, 17u);
+    for (;;) {
+    }
+}
+";
+
+const LINKER_SCRIPT: &str = "\
+ENTRY(_start)
+SECTIONS
+{
+    . = 0x08000000;
+    .text : { *(.text*) *(.rodata*) }
+    .data : { *(.data*) }
+    .bss : { *(.bss*) *(COMMON) }
+}
+";
+
+/// Outcome of one case.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CaseStatus {
+    /// Matched the baseline checksum.
+    Pass,
+    /// Built, but the checksum differs from the baseline.
+    Fail,
+    /// The toolchain failed to build the case.
+    Error,
+    /// Built, with no baseline checksum to compare against.
+    NoBaseline,
+}
+
+/// Result of one case.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CaseResult {
+    /// Case identifier.
+    pub id: String,
+    /// What the case exercises.
+    pub description: String,
+    /// Outcome.
+    pub status: CaseStatus,
+    /// Checksum of the output, if it was built.
+    pub checksum: Option<String>,
+    /// Baseline checksum, if any.
+    pub expected: Option<String>,
+    /// Toolchain error output, for [`CaseStatus::Error`].
+    pub message: Option<String>,
+}
+
+/// Result of running the suite against a toolchain.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QualificationReport {
+    /// Run time (seconds since the Unix epoch).
+    pub timestamp: u64,
+    /// Toolchain kind.
+    pub kind: ToolchainKind,
+    /// Compiler driver.
+    pub toolchain: PathBuf,
+    /// Version the driver reports.
+    pub version: String,
+    /// SHA-256 of the driver, for local toolchains.
+    pub toolchain_sha256: Option<String>,
+    /// Version recorded in the baseline, if one was used.
+    pub baseline_version: Option<String>,
+    /// One result per case, in suite order.
+    pub results: Vec<CaseResult>,
+}
+
+impl QualificationReport {
+    /// Whether every case matched its baseline.
+    pub fn passed(&self) -> bool {
+        !self.results.is_empty()
+            && self
+                .results
+                .iter()
+                .all(|result| result.status == CaseStatus::Pass)
+    }
+
+    /// Number of cases with a status.
+    pub fn count(&self, status: CaseStatus) -> usize {
+        self.results.iter().filter(|r| r.status == status).count()
+    }
+}
+
+/// Expected checksum of one case.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExpectedChecksum {
+    /// Case identifier.
+    pub id: String,
+    /// SHA-256 of the case output.
+    pub checksum: String,
+}
+
+/// Checksums recorded from a reference run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QualificationBaseline {
+    /// Toolchain version the baseline was recorded with.
+    pub version: String,
+    /// SHA-256 of the driver the baseline was recorded with.
+    #[serde(default)]
+    pub toolchain_sha256: Option<String>,
+    /// Expected checksums.
+    #[serde(default, rename = "case")]
+    pub cases: Vec<ExpectedChecksum>,
+}
+
+impl QualificationBaseline {
+    /// Record every built case of a report as expected.
+    pub fn from_report(report: &QualificationReport) -> Self {
+        Self {
+            version: report.version.clone(),
+            toolchain_sha256: report.toolchain_sha256.clone(),
+            cases: report
+                .results
+                .iter()
+                .filter_map(|result| {
+                    Some(ExpectedChecksum {
+                        id: result.id.clone(),
+                        checksum: result.checksum.clone()?,
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    /// Baseline path for a project.
+    pub fn path_for(project_root: &Path) -> PathBuf {
+        project_root.join(".axiom").join(QUALIFICATION_BASELINE)
+    }
+
+    /// Load a project's baseline, or `None` if it has none.
+    pub fn load(project_root: &Path) -> Result<Option<Self>, QualificationError> {
+        let path = Self::path_for(project_root);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(&path)?;
+        toml::from_str(&content)
+            .map(Some)
+            .map_err(|source| QualificationError::Parse { path, source })
+    }
+
+    /// Write the baseline into a project.
+    pub fn save(&self, project_root: &Path) -> Result<(), QualificationError> {
+        let path = Self::path_for(project_root);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Expected checksum of a case.
+    pub fn checksum(&self, id: &str) -> Option<&str> {
+        self.cases
+            .iter()
+            .find(|case| case.id == id)
+            .map(|case| case.checksum.as_str())
+    }
+}
+
+/// Run cases against a toolchain, comparing with a baseline if given.
+pub fn run_qualification(
+    toolchain: &DetectedToolchain,
+    cases: &[QualificationCase],
+    baseline: Option<&QualificationBaseline>,
+    timestamp: u64,
+) -> QualificationReport {
+    let results = cases
+        .iter()
+        .map(|case| {
+            let expected = baseline
+                .and_then(|b| b.checksum(&case.id))
+                .map(str::to_string);
+            let (status, checksum, message) = match run_case(toolchain, case) {
+                Ok(checksum) => {
+                    let status = match &expected {
+                        Some(expected) if *expected == checksum => CaseStatus::Pass,
+                        Some(_) => CaseStatus::Fail,
+                        None => CaseStatus::NoBaseline,
+                    };
+                    (status, Some(checksum), None)
+                }
+                Err(message) => (CaseStatus::Error, None, Some(message)),
+            };
+            CaseResult {
+                id: case.id.clone(),
+                description: case.description.clone(),
+                status,
+                checksum,
+                expected,
+                message,
+            }
+        })
+        .collect();
+
+    QualificationReport {
+        timestamp,
+        kind: toolchain.kind,
+        toolchain: toolchain.path.clone(),
+        version: toolchain.version.clone(),
+        toolchain_sha256: (toolchain.source == ToolchainSource::Local)
+            .then(|| sha256_file(&toolchain.path).ok())
+            .flatten(),
+        baseline_version: baseline.map(|b| b.version.clone()),
+        results,
+    }
+}
+
+/// Build one case in a scratch directory and checksum its output.
+pub fn run_case(toolchain: &DetectedToolchain, case: &QualificationCase) -> Result<String, String> {
+    let dir = std::env::temp_dir().join(format!(
+        "axiom-qualification-{}-{}",
+        std::process::id(),
+        case.id
+    ));
+    let result = build_case(toolchain, case, &dir);
+    let _ = std::fs::remove_dir_all(&dir);
+    result
+}
+
+fn build_case(
+    toolchain: &DetectedToolchain,
+    case: &QualificationCase,
+    dir: &Path,
+) -> Result<String, String> {
+    std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    let target = target_flags(toolchain.kind);
+
+    let mut objects = Vec::new();
+    for source in &case.sources {
+        let path = dir.join(&source.name);
+        std::fs::write(&path, &source.content).map_err(|e| e.to_string())?;
+        let object = path.with_extension("o");
+        let mut args = target.clone();
+        args.extend(["-std=c11", "-ffreestanding", "-fno-common"].map(String::from));
+        args.extend(case.flags.iter().cloned());
+        args.extend([
+            "-c".to_string(),
+            path.display().to_string(),
+            "-o".to_string(),
+            object.display().to_string(),
+        ]);
+        run(toolchain, &args)?;
+        objects.push(object);
+    }
+
+    let output = if case.link {
+        let script = dir.join("case.ld");
+        std::fs::write(&script, LINKER_SCRIPT).map_err(|e| e.to_string())?;
+        let elf = dir.join("case.elf");
+        let mut args = target;
+        if toolchain.kind != ToolchainKind::ArmGcc {
+            args.push("-no-pie".to_string());
+        }
+        args.extend(["-nostdlib", "-nostartfiles", "-Wl,--gc-sections", "-T"].map(String::from));
+        args.push(script.display().to_string());
+        args.extend(objects.iter().map(|o| o.display().to_string()));
+        args.extend(["-o".to_string(), elf.display().to_string()]);
+        run(toolchain, &args)?;
+        vec![elf]
+    } else {
+        objects
+    };
+
+    let mut hasher = Sha256::new();
+    for path in &output {
+        let elf = ElfFile::read(path).map_err(|e| e.to_string())?;
+        hasher.update(code_checksum(&elf).as_bytes());
+    }
+    Ok(hex(&hasher.finalize()))
+}
+
+fn target_flags(kind: ToolchainKind) -> Vec<String> {
+    match kind {
+        ToolchainKind::ArmGcc => vec!["-mcpu=cortex-m4".to_string(), "-mthumb".to_string()],
+        _ => Vec::new(),
+    }
+}
+
+fn run(toolchain: &DetectedToolchain, args: &[String]) -> Result<(), String> {
+    let output = run_limited(&mut toolchain.command(args)).map_err(|e| e.to_string())?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+/// SHA-256 over the name, type and contents of every allocated and
+/// relocation section, in file order. `NOBITS` sections contribute their
+/// size.
+pub fn code_checksum(elf: &ElfFile) -> String {
+    let mut hasher = Sha256::new();
+    for section in &elf.sections {
+        let relocation = matches!(section.section_type, SHT_REL | SHT_RELA);
+        if section.flags & SHF_ALLOC == 0 && !relocation {
+            continue;
+        }
+        hasher.update(section.name.as_bytes());
+        hasher.update([0]);
+        hasher.update(section.section_type.to_le_bytes());
+        hasher.update(section.size.to_le_bytes());
+        if section.section_type != SHT_NOBITS {
+            hasher.update(elf.section_data(section).unwrap_or_default());
+        }
+    }
+    hex(&hasher.finalize())
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf::build_test_elf;
+    use tempfile::TempDir;
+
+    fn report(checksums: &[(&str, Option<&str>)]) -> QualificationReport {
+        QualificationReport {
+            timestamp: 1,
+            kind: ToolchainKind::ArmGcc,
+            toolchain: PathBuf::from("/opt/arm/bin/arm-none-eabi-gcc"),
+            version: "13.2.1".to_string(),
+            toolchain_sha256: Some("abc".to_string()),
+            baseline_version: None,
+            results: checksums
+                .iter()
+                .map(|(id, checksum)| CaseResult {
+                    id: id.to_string(),
+                    description: String::new(),
+                    status: CaseStatus::NoBaseline,
+                    checksum: checksum.map(str::to_string),
+                    expected: None,
+                    message: None,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_standard_cases_have_unique_ids() {
+        let cases = standard_cases();
+        let mut ids: Vec<&str> = cases.iter().map(|c| c.id.as_str()).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        assert_eq!(ids.len(), cases.len());
+        assert!(cases.iter().any(|c| c.link));
+    }
+
+    #[test]
+    fn test_code_checksum_ignores_unallocated_sections() {
+        // Test ELFs carry no section flags, so relocations stand in for code
+        let code = build_test_elf(&[(".rel.text", SHT_REL, &[0x70, 0x47, 0, 0])]);
+        let with_comment = build_test_elf(&[
+            (".rel.text", SHT_REL, &[0x70, 0x47, 0, 0]),
+            (".comment", 1, b"GCC: 13.2.1"),
+        ]);
+        let patched = build_test_elf(&[(".rel.text", SHT_REL, &[0x00, 0xbf, 0, 0])]);
+
+        let checksum = |data: Vec<u8>| code_checksum(&ElfFile::parse(data).unwrap());
+        assert_eq!(checksum(code.clone()), checksum(with_comment));
+        assert_ne!(checksum(code), checksum(patched));
+    }
+
+    #[test]
+    fn test_baseline_round_trip() {
+        let temp = TempDir::new().unwrap();
+        let baseline = QualificationBaseline::from_report(&report(&[
+            ("OV-001", Some("aa")),
+            ("OV-002", None),
+        ]));
+        assert_eq!(baseline.cases.len(), 1);
+        assert_eq!(baseline.checksum("OV-001"), Some("aa"));
+
+        assert!(QualificationBaseline::load(temp.path()).unwrap().is_none());
+        baseline.save(temp.path()).unwrap();
+        assert_eq!(
+            QualificationBaseline::load(temp.path()).unwrap(),
+            Some(baseline)
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_qualification() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = TempDir::new().unwrap();
+        // Stand-in compiler copying a fixed ELF to its output
+        let elf = temp.path().join("template.elf");
+        std::fs::write(
+            &elf,
+            build_test_elf(&[(".rel.text", SHT_REL, &[1, 2, 3, 4])]),
+        )
+        .unwrap();
+        let compiler = temp.path().join("arm-none-eabi-gcc");
+        std::fs::write(
+            &compiler,
+            format!(
+                "#!/bin/sh\nprev=\"\"\nfor arg in \"$@\"; do\n\
+                 [ \"$prev\" = \"-o\" ] && out=\"$arg\"\n\
+                 case \"$arg\" in *fail.c) echo 'fail.c:1: error: boom' >&2; exit 1 ;; esac\n\
+                 prev=\"$arg\"\ndone\ncp {} \"$out\"\n",
+                elf.display()
+            ),
+        )
+        .unwrap();
+        std::fs::set_permissions(&compiler, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let tc = DetectedToolchain::new(ToolchainKind::ArmGcc, compiler, "13.2.1".to_string());
+
+        let mut cases = standard_cases();
+        cases.truncate(2);
+        cases.push(QualificationCase::new(
+            "OV-900",
+            "Always fails",
+            &[],
+            &[("fail.c", "")],
+        ));
+
+        let first = run_qualification(&tc, &cases, None, 1);
+        assert!(!first.passed());
+        assert_eq!(first.count(CaseStatus::NoBaseline), 2);
+        assert_eq!(first.results[2].status, CaseStatus::Error);
+        assert!(first.results[2]
+            .message
+            .as_deref()
+            .unwrap()
+            .contains("boom"));
+
+        let mut baseline = QualificationBaseline::from_report(&first);
+        baseline.cases[1].checksum = "0".repeat(64);
+        let second = run_qualification(&tc, &cases[..2], Some(&baseline), 2);
+        assert_eq!(second.results[0].status, CaseStatus::Pass);
+        assert_eq!(second.results[1].status, CaseStatus::Fail);
+        assert_eq!(second.baseline_version.as_deref(), Some("13.2.1"));
+
+        let third = run_qualification(&tc, &cases[..1], Some(&baseline), 3);
+        assert!(third.passed());
+    }
+}
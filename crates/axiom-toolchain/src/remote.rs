@@ -0,0 +1,683 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Remote toolchain execution over SSH.
+//!
+//! A qualified toolchain can live on a build server while the IDE runs
+//! locally. The project tree is mirrored to the server with `tar` over
+//! `ssh`, the compiler or linker runs there with project paths rewritten,
+//! and the outputs are copied back, so callers get the same
+//! [`CompileResult`] and [`LinkResult`] as for a local run. Remote
+//! toolchains are declared in `.axiom/remote-toolchains.toml`:
+//!
+//! ```toml
+//! [[toolchain]]
+//! name = "qualified-gcc"
+//! host = "build01.example.com"
+//! user = "ci"
+//! path = "/opt/qualified/arm-gnu-13.2/bin/arm-none-eabi-gcc"
+//! ```
+
+use crate::detection::parse_version;
+use crate::environment::shell_quote;
+use crate::limits::{is_timeout, run_limited, run_limited_with_input};
+use crate::link::{check_budget, check_prelink, parse_link_diagnostics};
+use crate::{
+    build_arm_compile_command, build_arm_link_command, build_command, normalize_diagnostics,
+    parse_diagnostics, read_map_file, ArmCompileRequest, ArmLinkRequest, CompileRequest,
+    CompileResult, DetectedToolchain, LinkResult, ToolchainKind,
+};
+use axiom_core::Diagnostic;
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Instant;
+
+/// Directories never mirrored to the server.
+const SYNC_EXCLUDES: &[&str] = &[".git", ".axiom"];
+
+/// Exit status `ssh` uses for its own failures.
+const SSH_FAILURE: i32 = 255;
+
+/// Error type for remote execution.
+#[derive(Debug, thiserror::Error)]
+pub enum RemoteError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("TOML parse error in {path}: {source}")]
+    Toml {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+
+    #[error("No remote toolchain named {0}")]
+    NotFound(String),
+
+    #[error("Invalid SSH {field} {value:?}: must not start with '-'")]
+    InvalidDestination { field: &'static str, value: String },
+
+    #[error("{0} is outside the project")]
+    OutsideProject(PathBuf),
+
+    #[error("SSH to {host} failed: {message}")]
+    Ssh { host: String, message: String },
+
+    #[error("Cannot run the remote toolchain: {0}")]
+    Toolchain(String),
+}
+
+/// A toolchain installed on a build server.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RemoteToolchain {
+    /// Name the toolchain is selected by.
+    pub name: String,
+    /// Kind of toolchain.
+    #[serde(default = "default_kind")]
+    pub kind: ToolchainKind,
+    /// Server host name.
+    pub host: String,
+    /// Login user; the SSH configuration decides when absent.
+    #[serde(default)]
+    pub user: Option<String>,
+    /// SSH port.
+    #[serde(default)]
+    pub port: Option<u16>,
+    /// Private key to authenticate with.
+    #[serde(default)]
+    pub identity_file: Option<PathBuf>,
+    /// Compiler driver on the server.
+    pub path: String,
+    /// Directory on the server that projects are mirrored under; relative
+    /// paths are relative to the login directory.
+    #[serde(default = "default_workspace")]
+    pub workspace: String,
+}
+
+fn default_kind() -> ToolchainKind {
+    ToolchainKind::ArmGcc
+}
+
+fn default_workspace() -> String {
+    ".axiom-remote".to_string()
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct RemoteToolchainsFile {
+    #[serde(default)]
+    toolchain: Vec<RemoteToolchain>,
+}
+
+/// Path of a project's remote toolchain declarations.
+pub fn remote_toolchains_path(project_root: &Path) -> PathBuf {
+    project_root.join(".axiom").join("remote-toolchains.toml")
+}
+
+/// Load a project's remote toolchains; a missing file gives none.
+pub fn load_remote_toolchains(project_root: &Path) -> Result<Vec<RemoteToolchain>, RemoteError> {
+    let path = remote_toolchains_path(project_root);
+    if !path.is_file() {
+        return Ok(Vec::new());
+    }
+    let content = std::fs::read_to_string(&path)?;
+    let file: RemoteToolchainsFile =
+        toml::from_str(&content).map_err(|source| RemoteError::Toml { path, source })?;
+    Ok(file.toolchain)
+}
+
+/// Look up a project's remote toolchain by name.
+pub fn find_remote_toolchain(
+    project_root: &Path,
+    name: &str,
+) -> Result<RemoteToolchain, RemoteError> {
+    load_remote_toolchains(project_root)?
+        .into_iter()
+        .find(|t| t.name == name)
+        .ok_or_else(|| RemoteError::NotFound(name.to_string()))
+}
+
+impl RemoteToolchain {
+    /// `ssh` destination, `user@host` or `host`.
+    ///
+    /// A host or user starting with `-` is rejected, since `ssh` would
+    /// read it as an option.
+    pub fn destination(&self) -> Result<String, RemoteError> {
+        let check = |field, value: &str| {
+            if value.starts_with('-') {
+                return Err(RemoteError::InvalidDestination {
+                    field,
+                    value: value.to_string(),
+                });
+            }
+            Ok(())
+        };
+        check("host", &self.host)?;
+        match &self.user {
+            Some(user) => {
+                check("user", user)?;
+                Ok(format!("{}@{}", user, self.host))
+            }
+            None => Ok(self.host.clone()),
+        }
+    }
+
+    /// Arguments to `ssh` before the remote command. Batch mode keeps a
+    /// missing key from hanging on a password prompt, and `--` ends the
+    /// options before the destination.
+    pub fn ssh_args(&self) -> Result<Vec<String>, RemoteError> {
+        let mut args = vec!["-o".to_string(), "BatchMode=yes".to_string()];
+        if let Some(port) = self.port {
+            args.push("-p".to_string());
+            args.push(port.to_string());
+        }
+        if let Some(identity) = &self.identity_file {
+            args.push("-i".to_string());
+            args.push(identity.display().to_string());
+        }
+        args.push("--".to_string());
+        args.push(self.destination()?);
+        Ok(args)
+    }
+
+    /// Mirror directory for a project, relative to the workspace.
+    pub fn project_dir(&self, project_root: &Path) -> String {
+        let name = project_root
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "project".to_string());
+        format!("{}/{}", self.workspace.trim_end_matches('/'), name)
+    }
+
+    fn ssh(&self, remote_command: &str) -> Result<Command, RemoteError> {
+        let mut command = Command::new("ssh");
+        command.args(self.ssh_args()?).arg(remote_command);
+        Ok(command)
+    }
+
+    fn ssh_error(&self, stderr: &[u8]) -> RemoteError {
+        RemoteError::Ssh {
+            host: self.host.clone(),
+            message: String::from_utf8_lossy(stderr).trim().to_string(),
+        }
+    }
+}
+
+/// A project mirrored on a build server with a resolved remote toolchain.
+#[derive(Debug, Clone)]
+pub struct RemoteSession {
+    remote: RemoteToolchain,
+    local_root: PathBuf,
+    remote_root: String,
+    toolchain: DetectedToolchain,
+}
+
+impl RemoteSession {
+    /// Create the project mirror on the server and query the toolchain
+    /// version.
+    pub fn connect(remote: RemoteToolchain, project_root: &Path) -> Result<Self, RemoteError> {
+        let dir = remote.project_dir(project_root);
+        let script = format!(
+            "mkdir -p {dir} && cd {dir} && pwd && {} --version",
+            shell_quote(&remote.path),
+            dir = shell_quote(&dir)
+        );
+        let output = run_limited(&mut remote.ssh(&script)?)?;
+        if output.status.code() == Some(SSH_FAILURE) {
+            return Err(remote.ssh_error(&output.stderr));
+        }
+        if !output.status.success() {
+            return Err(RemoteError::Toolchain(
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ));
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let (remote_root, version_output) = stdout.split_once('\n').unwrap_or((&stdout, ""));
+        let version = parse_version(version_output, remote.kind).unwrap_or_default();
+        let toolchain = DetectedToolchain::new(remote.kind, PathBuf::from(&remote.path), version);
+
+        Ok(Self {
+            local_root: project_root.to_path_buf(),
+            remote_root: remote_root.trim().to_string(),
+            toolchain,
+            remote,
+        })
+    }
+
+    /// The remote toolchain as seen by the request builders.
+    pub fn toolchain(&self) -> &DetectedToolchain {
+        &self.toolchain
+    }
+
+    /// Absolute path of the project mirror on the server.
+    pub fn remote_root(&self) -> &str {
+        &self.remote_root
+    }
+
+    /// Copy the project tree to the server.
+    pub fn sync(&self) -> Result<(), RemoteError> {
+        let script = format!("tar -xf - -C {}", shell_quote(&self.remote_root));
+        let mut ssh = self.remote.ssh(&script)?;
+
+        let mut tar = Command::new("tar");
+        tar.arg("-cf").arg("-");
+        for exclude in SYNC_EXCLUDES {
+            tar.arg(format!("--exclude=./{}", exclude));
+        }
+        let mut tar = tar
+            .arg("-C")
+            .arg(&self.local_root)
+            .arg(".")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let archive = tar.stdout.take().expect("piped stdout");
+
+        let output = match run_limited_with_input(&mut ssh, archive) {
+            Ok(output) => output,
+            Err(e) => {
+                let _ = tar.kill();
+                let _ = tar.wait();
+                return Err(e.into());
+            }
+        };
+        let status = tar.wait()?;
+        if !output.status.success() {
+            return Err(self.remote.ssh_error(&output.stderr));
+        }
+        if !status.success() {
+            let mut stderr = String::new();
+            if let Some(mut pipe) = tar.stderr.take() {
+                let _ = pipe.read_to_string(&mut stderr);
+            }
+            return Err(RemoteError::Toolchain(format!(
+                "cannot archive project: {}",
+                stderr.trim()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Copy files that exist on the server back into the project.
+    pub fn fetch(&self, paths: &[PathBuf]) -> Result<(), RemoteError> {
+        let relative: Vec<String> = paths
+            .iter()
+            .map(|p| self.relative(p))
+            .collect::<Result<_, _>>()?;
+        if relative.is_empty() {
+            return Ok(());
+        }
+        let script = format!(
+            "cd {} && for f in {}; do [ -e \"$f\" ] && printf '%s\\n' \"$f\"; done | tar -cf - -T -",
+            shell_quote(&self.remote_root),
+            relative
+                .iter()
+                .map(|p| shell_quote(p))
+                .collect::<Vec<_>>()
+                .join(" ")
+        );
+        let mut ssh = self
+            .remote
+            .ssh(&script)?
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let archive = ssh.stdout.take().expect("piped stdout");
+        let mut tar = Command::new("tar");
+        tar.arg("-xf").arg("-").arg("-C").arg(&self.local_root);
+        let output = match run_limited_with_input(&mut tar, archive) {
+            Ok(output) => output,
+            Err(e) => {
+                let _ = ssh.kill();
+                let _ = ssh.wait();
+                return Err(e.into());
+            }
+        };
+        let ssh = ssh.wait_with_output()?;
+        if !ssh.status.success() {
+            return Err(self.remote.ssh_error(&ssh.stderr));
+        }
+        if !output.status.success() {
+            return Err(RemoteError::Toolchain(format!(
+                "cannot unpack outputs: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Compile on the server; the output is copied back on success.
+    pub fn compile(&self, request: &CompileRequest) -> Result<CompileResult, RemoteError> {
+        let args = build_command(&self.toolchain, request);
+        let run = self.execute(&args, std::slice::from_ref(&request.output))?;
+        Ok(self.compile_result(run, request.warning_profile))
+    }
+
+    /// Compile with the remote ARM GCC; the object and any stack usage or
+    /// call graph files are copied back on success.
+    pub fn compile_arm(&self, request: &ArmCompileRequest) -> Result<CompileResult, RemoteError> {
+        let args = build_arm_compile_command(request);
+        let outputs = [
+            request.output.clone(),
+            request.output.with_extension("su"),
+            request.output.with_extension("ci"),
+        ];
+        let run = self.execute(&args, &outputs)?;
+        Ok(self.compile_result(run, request.warning_profile))
+    }
+
+    /// Link on the server; the ELF and map file are copied back on success
+    /// and the map is parsed locally. Objects built with incompatible
+    /// settings fail before anything is synced.
+    pub fn link_arm(&self, request: &ArmLinkRequest) -> Result<LinkResult, RemoteError> {
+        if let Some(result) = check_prelink(request, &self.local_root) {
+            return Ok(result);
+        }
+        let args = build_arm_link_command(request);
+        let mut outputs = vec![request.output.clone()];
+        outputs.extend(request.linker.map_file.clone());
+        let run = self.execute(&args, &outputs)?;
+
+        let memory_map = request
+            .linker
+            .map_file
+            .as_ref()
+            .filter(|_| run.exit_code == 0)
+            .and_then(|path| read_map_file(&self.local_root.join(path)).ok());
+        let mut result = LinkResult {
+            exit_code: run.exit_code,
+            diagnostics: run.diagnostics(parse_link_diagnostics),
+            stdout: run.stdout,
+            stderr: run.stderr,
+            duration_ms: run.duration_ms,
+            timed_out: run.timed_out,
+            output: request.output.clone(),
+            memory_map,
+            invocation: None,
+            budget_violations: Vec::new(),
+        };
+        check_budget(request, &mut result, &self.local_root);
+        Ok(result)
+    }
+
+    /// Sync, run the driver with project paths rewritten, and fetch the
+    /// outputs if it succeeded. Paths in the tool's output are mapped back.
+    fn execute(&self, args: &[String], outputs: &[PathBuf]) -> Result<RemoteRun, RemoteError> {
+        for output in outputs {
+            self.relative(output)?;
+        }
+        let start = Instant::now();
+        self.sync()?;
+
+        let script = remote_command(&self.remote_root, &self.remote.path, &self.map_args(args));
+        let output = match run_limited(&mut self.remote.ssh(&script)?) {
+            Ok(output) => output,
+            Err(e) if is_timeout(&e) => {
+                return Ok(RemoteRun {
+                    exit_code: -1,
+                    stdout: String::new(),
+                    stderr: e.to_string(),
+                    duration_ms: start.elapsed().as_millis() as u64,
+                    timed_out: true,
+                })
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let exit_code = output.status.code().unwrap_or(-1);
+        if exit_code == SSH_FAILURE {
+            return Err(self.remote.ssh_error(&output.stderr));
+        }
+        if exit_code == 0 {
+            self.fetch(outputs)?;
+        }
+
+        Ok(RemoteRun {
+            exit_code,
+            stdout: self.unmap(&String::from_utf8_lossy(&output.stdout)),
+            stderr: self.unmap(&String::from_utf8_lossy(&output.stderr)),
+            duration_ms: start.elapsed().as_millis() as u64,
+            timed_out: false,
+        })
+    }
+
+    fn compile_result(
+        &self,
+        run: RemoteRun,
+        warning_profile: Option<crate::WarningProfile>,
+    ) -> CompileResult {
+        let mut diagnostics =
+            run.diagnostics(|stderr| parse_diagnostics(stderr, self.toolchain.kind));
+        normalize_diagnostics(
+            &mut diagnostics,
+            self.toolchain.kind,
+            &self.toolchain.version,
+        );
+        CompileResult {
+            exit_code: run.exit_code,
+            stdout: run.stdout,
+            stderr: run.stderr,
+            duration_ms: run.duration_ms,
+            timed_out: run.timed_out,
+            diagnostics,
+            warning_profile,
+            invocation: None,
+        }
+    }
+
+    /// Project-relative, `/`-separated form of a path.
+    fn relative(&self, path: &Path) -> Result<String, RemoteError> {
+        let relative = if path.is_absolute() {
+            path.strip_prefix(&self.local_root)
+                .map_err(|_| RemoteError::OutsideProject(path.to_path_buf()))?
+        } else {
+            path
+        };
+        Ok(relative.to_string_lossy().replace('\\', "/"))
+    }
+
+    fn map_args(&self, args: &[String]) -> Vec<String> {
+        map_paths(args, &self.local_root, &self.remote_root)
+    }
+
+    fn unmap(&self, text: &str) -> String {
+        text.replace(&self.remote_root, &self.local_root.display().to_string())
+    }
+}
+
+/// Output of a tool run on the server.
+struct RemoteRun {
+    exit_code: i32,
+    stdout: String,
+    stderr: String,
+    duration_ms: u64,
+    timed_out: bool,
+}
+
+impl RemoteRun {
+    /// Diagnostics for the run: those parsed from its output, or the
+    /// timeout.
+    fn diagnostics(&self, parse: impl FnOnce(&str) -> Vec<Diagnostic>) -> Vec<Diagnostic> {
+        if self.timed_out {
+            vec![Diagnostic::error(self.stderr.clone())]
+        } else {
+            parse(&self.stderr)
+        }
+    }
+}
+
+/// Rewrite occurrences of the local project root in arguments, including
+/// inside flags such as `-I<dir>` and `-Wl,-Map=<file>`.
+pub fn map_paths(args: &[String], local_root: &Path, remote_root: &str) -> Vec<String> {
+    let local = local_root.display().to_string();
+    args.iter()
+        .map(|arg| match arg.find(&local) {
+            Some(at) => {
+                let rest = arg[at + local.len()..].replace('\\', "/");
+                format!("{}{}{}", &arg[..at], remote_root, rest)
+            }
+            None => arg.clone(),
+        })
+        .collect()
+}
+
+/// Shell command running a program in a directory on the server.
+pub fn remote_command(dir: &str, program: &str, args: &[String]) -> String {
+    let mut command = format!("cd {} && {}", shell_quote(dir), shell_quote(program));
+    for arg in args {
+        command.push(' ');
+        command.push_str(&shell_quote(arg));
+    }
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn remote() -> RemoteToolchain {
+        RemoteToolchain {
+            name: "qualified".to_string(),
+            kind: ToolchainKind::ArmGcc,
+            host: "build01".to_string(),
+            user: Some("ci".to_string()),
+            port: Some(2222),
+            identity_file: Some(PathBuf::from("/keys/ci")),
+            path: "/opt/gcc/bin/arm-none-eabi-gcc".to_string(),
+            workspace: default_workspace(),
+        }
+    }
+
+    #[test]
+    fn test_load_remote_toolchains() {
+        let temp = TempDir::new().unwrap();
+        assert!(load_remote_toolchains(temp.path()).unwrap().is_empty());
+
+        std::fs::create_dir_all(temp.path().join(".axiom")).unwrap();
+        std::fs::write(
+            remote_toolchains_path(temp.path()),
+            r#"
+[[toolchain]]
+name = "qualified"
+host = "build01"
+path = "/opt/gcc/bin/arm-none-eabi-gcc"
+port = 2222
+"#,
+        )
+        .unwrap();
+
+        let toolchain = find_remote_toolchain(temp.path(), "qualified").unwrap();
+        assert_eq!(toolchain.kind, ToolchainKind::ArmGcc);
+        assert_eq!(toolchain.port, Some(2222));
+        assert_eq!(toolchain.workspace, ".axiom-remote");
+        assert!(matches!(
+            find_remote_toolchain(temp.path(), "other"),
+            Err(RemoteError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_ssh_args() {
+        assert_eq!(
+            remote().ssh_args().unwrap(),
+            vec![
+                "-o",
+                "BatchMode=yes",
+                "-p",
+                "2222",
+                "-i",
+                "/keys/ci",
+                "--",
+                "ci@build01"
+            ]
+        );
+        assert_eq!(
+            remote().project_dir(Path::new("/home/dev/blinky")),
+            ".axiom-remote/blinky"
+        );
+    }
+
+    #[test]
+    fn test_option_like_destination() {
+        let mut host = remote();
+        host.host = "-oProxyCommand=touch /tmp/pwned".to_string();
+        assert!(matches!(
+            host.ssh_args(),
+            Err(RemoteError::InvalidDestination { field: "host", .. })
+        ));
+
+        let mut user = remote();
+        user.user = Some("-oProxyCommand=sh".to_string());
+        assert!(matches!(
+            user.destination(),
+            Err(RemoteError::InvalidDestination { field: "user", .. })
+        ));
+
+        let mut anonymous = remote();
+        anonymous.user = None;
+        assert_eq!(anonymous.destination().unwrap(), "build01");
+    }
+
+    #[test]
+    fn test_map_paths() {
+        let args = vec![
+            "-c".to_string(),
+            "/home/dev/blinky/src/main.c".to_string(),
+            "-I/home/dev/blinky/inc".to_string(),
+            "-Wl,-Map=/home/dev/blinky/build/fw.map".to_string(),
+            "-I/usr/include".to_string(),
+        ];
+        assert_eq!(
+            map_paths(&args, Path::new("/home/dev/blinky"), "/srv/ci/blinky"),
+            vec![
+                "-c",
+                "/srv/ci/blinky/src/main.c",
+                "-I/srv/ci/blinky/inc",
+                "-Wl,-Map=/srv/ci/blinky/build/fw.map",
+                "-I/usr/include",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_remote_command_quotes() {
+        let command = remote_command(
+            "/srv/ci/my project",
+            "/opt/gcc/bin/arm-none-eabi-gcc",
+            &["-DNAME=\"x y\"".to_string(), "-c".to_string()],
+        );
+        assert_eq!(
+            command,
+            "cd '/srv/ci/my project' && /opt/gcc/bin/arm-none-eabi-gcc '-DNAME=\"x y\"' -c"
+        );
+    }
+
+    #[test]
+    fn test_outputs_must_be_in_project() {
+        let session = RemoteSession {
+            remote: remote(),
+            local_root: PathBuf::from("/home/dev/blinky"),
+            remote_root: "/srv/ci/blinky".to_string(),
+            toolchain: DetectedToolchain::new(
+                ToolchainKind::ArmGcc,
+                PathBuf::from("/opt/gcc/bin/arm-none-eabi-gcc"),
+                "13.2.1".to_string(),
+            ),
+        };
+        assert_eq!(
+            session
+                .relative(Path::new("/home/dev/blinky/build/main.o"))
+                .unwrap(),
+            "build/main.o"
+        );
+        assert!(matches!(
+            session.relative(Path::new("/tmp/main.o")),
+            Err(RemoteError::OutsideProject(_))
+        ));
+        assert_eq!(
+            session.unmap("/srv/ci/blinky/src/main.c:3:1: error: expected ';'"),
+            "/home/dev/blinky/src/main.c:3:1: error: expected ';'"
+        );
+    }
+}
@@ -0,0 +1,193 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Persisted build reports.
+//!
+//! Each completed build appends one JSON line to
+//! `.axiom/build-reports.jsonl` in the project root.
+
+use crate::{CompileResult, LinkResult};
+use axiom_core::Diagnostic;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Error type for build report persistence.
+#[derive(Debug, thiserror::Error)]
+pub enum ReportError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Malformed build report on line {line}: {source}")]
+    Parse {
+        line: usize,
+        source: serde_json::Error,
+    },
+
+    #[error("JSON serialize error: {0}")]
+    Serialize(#[from] serde_json::Error),
+}
+
+/// Summary of a single build run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BuildReport {
+    /// Build start time (seconds since the Unix epoch).
+    pub timestamp: u64,
+    /// Whether every step succeeded.
+    pub success: bool,
+    /// Wall-clock duration in milliseconds.
+    pub duration_ms: u64,
+    /// Translation units that were compiled.
+    pub units_compiled: u32,
+    /// Translation units reused from the build cache.
+    #[serde(default)]
+    pub cache_hits: u32,
+    /// Diagnostics produced by the build.
+    #[serde(default)]
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl BuildReport {
+    /// Summarize a set of compile results into a report.
+    pub fn from_results(timestamp: u64, results: &[CompileResult], cache_hits: u32) -> Self {
+        Self {
+            timestamp,
+            success: results.iter().all(CompileResult::success),
+            duration_ms: results.iter().map(|r| r.duration_ms).sum(),
+            units_compiled: results.len() as u32,
+            cache_hits,
+            diagnostics: results
+                .iter()
+                .flat_map(|r| r.diagnostics.iter().cloned())
+                .collect(),
+        }
+    }
+
+    /// Summarize a link into a report.
+    pub fn from_link(timestamp: u64, result: &LinkResult) -> Self {
+        Self {
+            timestamp,
+            success: result.success(),
+            duration_ms: result.duration_ms,
+            units_compiled: 0,
+            cache_hits: 0,
+            diagnostics: result.diagnostics.clone(),
+        }
+    }
+}
+
+/// Append-only store of build reports.
+#[derive(Debug, Clone)]
+pub struct BuildReportStore {
+    path: PathBuf,
+}
+
+impl BuildReportStore {
+    /// Create a store backed by the given file.
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Create the store for a project root.
+    pub fn for_project(project_root: &Path) -> Self {
+        Self::new(project_root.join(".axiom").join("build-reports.jsonl"))
+    }
+
+    /// Path of the backing file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append a report.
+    pub fn append(&self, report: &BuildReport) -> Result<(), ReportError> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let line = serde_json::to_string(report)?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", line)?;
+        Ok(())
+    }
+
+    /// Load all reports in the order they were recorded.
+    ///
+    /// A missing file yields an empty list.
+    pub fn load(&self) -> Result<Vec<BuildReport>, ReportError> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let content = fs::read_to_string(&self.path)?;
+        content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                serde_json::from_str(line).map_err(|source| ReportError::Parse {
+                    line: i + 1,
+                    source,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn result(exit_code: i32, duration_ms: u64) -> CompileResult {
+        CompileResult {
+            exit_code,
+            stdout: String::new(),
+            stderr: String::new(),
+            duration_ms,
+            timed_out: false,
+            diagnostics: vec![Diagnostic::warning("main.c:1:1: warning: unused")],
+            warning_profile: None,
+            invocation: None,
+        }
+    }
+
+    #[test]
+    fn test_from_results() {
+        let report = BuildReport::from_results(100, &[result(0, 10), result(1, 5)], 3);
+        assert!(!report.success);
+        assert_eq!(report.duration_ms, 15);
+        assert_eq!(report.units_compiled, 2);
+        assert_eq!(report.cache_hits, 3);
+        assert_eq!(report.diagnostics.len(), 2);
+    }
+
+    #[test]
+    fn test_append_and_load() {
+        let dir = TempDir::new().unwrap();
+        let store = BuildReportStore::for_project(dir.path());
+        assert!(store.load().unwrap().is_empty());
+
+        let first = BuildReport::from_results(100, &[result(0, 10)], 0);
+        let second = BuildReport::from_results(200, &[result(1, 20)], 1);
+        store.append(&first).unwrap();
+        store.append(&second).unwrap();
+
+        assert_eq!(store.load().unwrap(), vec![first, second]);
+    }
+
+    #[test]
+    fn test_load_reports_bad_line() {
+        let dir = TempDir::new().unwrap();
+        let store = BuildReportStore::new(dir.path().join("reports.jsonl"));
+        fs::write(store.path(), "not json\n").unwrap();
+
+        assert!(matches!(
+            store.load(),
+            Err(ReportError::Parse { line: 1, .. })
+        ));
+    }
+}
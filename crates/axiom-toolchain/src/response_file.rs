@@ -0,0 +1,166 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Response files for long command lines.
+//!
+//! Links with hundreds of objects overflow the Windows command line limit.
+//! When an invocation would be too long, its arguments are written to
+//! `<output>.rsp` and the tool is passed `@<output>.rsp` instead; GCC
+//! forwards the file to `ld`. The file sits next to the output so container
+//! toolchains can read it through the workspace mount, and is removed after
+//! the run; its contents are kept in the invocation record.
+
+use crate::invocation::command_argv;
+use crate::limits::run_limited;
+use crate::DetectedToolchain;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Output;
+
+/// Longest command line, in characters, passed directly.
+///
+/// `CreateProcess` accepts 32767 characters; the margin covers the
+/// environment block and tools that re-spawn with extra arguments.
+#[cfg(windows)]
+pub const COMMAND_LINE_LIMIT: usize = 30_000;
+
+/// Longest command line, in bytes, passed directly.
+///
+/// Linux rejects single arguments over 128 KiB and `ARG_MAX` is often
+/// 256 KiB on macOS, shared with the environment.
+#[cfg(not(windows))]
+pub const COMMAND_LINE_LIMIT: usize = 128 * 1024;
+
+/// Whether an argv, program first, is too long to pass directly.
+pub fn exceeds_command_line_limit(argv: &[String]) -> bool {
+    // One separator (or terminator) per argument, plus quotes on Windows
+    argv.iter().map(|arg| arg.len() + 3).sum::<usize>() > COMMAND_LINE_LIMIT
+}
+
+/// Quote an argument for a GCC response file: whitespace, quotes and
+/// backslashes are backslash-escaped.
+pub fn quote_response_arg(arg: &str) -> String {
+    let mut quoted = String::with_capacity(arg.len());
+    for c in arg.chars() {
+        if c.is_whitespace() || matches!(c, '\\' | '"' | '\'') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted
+}
+
+/// Response file contents, one argument per line.
+pub fn response_file_contents(args: &[String]) -> String {
+    args.iter()
+        .map(|arg| quote_response_arg(arg) + "\n")
+        .collect()
+}
+
+/// Response file path for an output.
+pub fn response_file_path(output: &Path) -> PathBuf {
+    let mut path = output.as_os_str().to_owned();
+    path.push(".rsp");
+    PathBuf::from(path)
+}
+
+/// Run a toolchain binary, moving its arguments into a response file when
+/// the command line would be too long.
+///
+/// The tool runs under the current [`ToolLimits`](crate::ToolLimits).
+/// Returns the response file contents when one was used.
+pub(crate) fn run_tool(
+    toolchain: &DetectedToolchain,
+    program: &Path,
+    args: &[String],
+    output: &Path,
+) -> (io::Result<Output>, Option<String>) {
+    let mut command = toolchain.tool_command(program, args);
+    if !exceeds_command_line_limit(&command_argv(&command)) {
+        return (run_limited(&mut command), None);
+    }
+
+    let path = response_file_path(output);
+    let contents = response_file_contents(args);
+    let written = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|()| std::fs::write(&path, &contents));
+    if let Err(e) = written {
+        return (Err(e), Some(contents));
+    }
+
+    let result =
+        run_limited(&mut toolchain.tool_command(program, &[format!("@{}", path.display())]));
+    let _ = std::fs::remove_file(&path);
+    (result, Some(contents))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ToolchainKind;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_quote_response_arg() {
+        assert_eq!(quote_response_arg("-Os"), "-Os");
+        assert_eq!(
+            quote_response_arg(r"C:\My Project\main.o"),
+            r"C:\\My\ Project\\main.o"
+        );
+        assert_eq!(quote_response_arg(r#"-DNAME="x""#), r#"-DNAME=\"x\""#);
+    }
+
+    #[test]
+    fn test_limit() {
+        assert!(!exceeds_command_line_limit(&["gcc".to_string()]));
+        let objects: Vec<String> = (0..COMMAND_LINE_LIMIT / 10)
+            .map(|i| format!("build/obj{}.o", i))
+            .collect();
+        assert!(exceeds_command_line_limit(&objects));
+    }
+
+    #[test]
+    fn test_response_file_path() {
+        assert_eq!(
+            response_file_path(Path::new("build/firmware.elf")),
+            PathBuf::from("build/firmware.elf.rsp")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_tool_uses_response_file() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = TempDir::new().unwrap();
+        // Stand-in driver that prints its arguments and the response file
+        let tool = temp.path().join("arm-none-eabi-gcc");
+        std::fs::write(
+            &tool,
+            "#!/bin/sh\necho \"$@\"\ncase \"$1\" in @*) cat \"${1#@}\" ;; esac\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&tool, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let tc = DetectedToolchain::new(ToolchainKind::ArmGcc, tool.clone(), "13".to_string());
+        let output = temp.path().join("build").join("firmware.elf");
+
+        let (result, contents) = run_tool(&tc, &tool, &["-v".to_string()], &output);
+        assert_eq!(String::from_utf8_lossy(&result.unwrap().stdout), "-v\n");
+        assert!(contents.is_none());
+
+        let mut args: Vec<String> = (0..COMMAND_LINE_LIMIT / 10)
+            .map(|i| format!("build/obj{}.o", i))
+            .collect();
+        args.push("my file.o".to_string());
+        let (result, contents) = run_tool(&tc, &tool, &args, &output);
+        let stdout = String::from_utf8_lossy(&result.unwrap().stdout).into_owned();
+        let contents = contents.unwrap();
+        assert!(stdout.starts_with(&format!("@{}", response_file_path(&output).display())));
+        assert!(stdout.ends_with(&contents));
+        assert!(contents.ends_with("my\\ file.o\n"));
+        assert!(!response_file_path(&output).exists());
+    }
+}
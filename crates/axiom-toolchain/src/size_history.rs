@@ -0,0 +1,490 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Build size history.
+//!
+//! Each linked image appends its text/data/bss sizes, keyed by git commit
+//! and build configuration, to `.axiom/size-history.jsonl` in the project
+//! root. Queries report per-configuration trends and builds whose flash or
+//! RAM use grew past a threshold.
+
+use crate::{ElfError, ElfFile, ElfSection, SHF_ALLOC, SHF_WRITE, SHT_NOBITS};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Error type for size history persistence.
+#[derive(Debug, thiserror::Error)]
+pub enum SizeHistoryError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("ELF error: {0}")]
+    Elf(#[from] ElfError),
+
+    #[error("Malformed size record on line {line}: {source}")]
+    Parse {
+        line: usize,
+        source: serde_json::Error,
+    },
+
+    #[error("JSON serialize error: {0}")]
+    Serialize(#[from] serde_json::Error),
+}
+
+/// Image sizes in the Berkeley `size` layout.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SectionSizes {
+    /// Read-only allocated sections (code and constants).
+    pub text: u64,
+    /// Initialized writable sections.
+    pub data: u64,
+    /// Zero-initialized sections.
+    pub bss: u64,
+}
+
+/// Which `size` column a section counts towards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SizeCategory {
+    /// Read-only: code and constants.
+    Text,
+    /// Initialized writable data.
+    Data,
+    /// Zero-initialized data.
+    Bss,
+}
+
+impl SizeCategory {
+    /// Category of an allocated section; `None` for sections that occupy
+    /// no target memory.
+    pub fn of(section: &ElfSection) -> Option<Self> {
+        if section.flags & SHF_ALLOC == 0 {
+            None
+        } else if section.section_type == SHT_NOBITS {
+            Some(Self::Bss)
+        } else if section.flags & SHF_WRITE != 0 {
+            Some(Self::Data)
+        } else {
+            Some(Self::Text)
+        }
+    }
+}
+
+impl SectionSizes {
+    /// Sum the allocated sections of an ELF.
+    pub fn from_elf(elf: &ElfFile) -> Self {
+        let mut sizes = Self::default();
+        for section in &elf.sections {
+            if let Some(category) = SizeCategory::of(section) {
+                sizes.add(category, section.size);
+            }
+        }
+        sizes
+    }
+
+    /// Count bytes towards a category.
+    pub fn add(&mut self, category: SizeCategory, bytes: u64) {
+        match category {
+            SizeCategory::Text => self.text += bytes,
+            SizeCategory::Data => self.data += bytes,
+            SizeCategory::Bss => self.bss += bytes,
+        }
+    }
+
+    /// Total of all three categories.
+    pub fn total(&self) -> u64 {
+        self.text + self.data + self.bss
+    }
+
+    /// Bytes stored in flash (text plus initial values of data).
+    pub fn flash(&self) -> u64 {
+        self.text + self.data
+    }
+
+    /// Bytes of RAM in use at startup (data plus bss).
+    pub fn ram(&self) -> u64 {
+        self.data + self.bss
+    }
+
+    /// Change from an earlier build.
+    pub fn delta_from(&self, previous: &SectionSizes) -> SizeDelta {
+        SizeDelta {
+            text: self.text as i64 - previous.text as i64,
+            data: self.data as i64 - previous.data as i64,
+            bss: self.bss as i64 - previous.bss as i64,
+        }
+    }
+}
+
+/// Signed change in image sizes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SizeDelta {
+    /// Change in text bytes.
+    pub text: i64,
+    /// Change in data bytes.
+    pub data: i64,
+    /// Change in bss bytes.
+    pub bss: i64,
+}
+
+impl SizeDelta {
+    /// Change in flash bytes.
+    pub fn flash(&self) -> i64 {
+        self.text + self.data
+    }
+
+    /// Change in RAM bytes.
+    pub fn ram(&self) -> i64 {
+        self.data + self.bss
+    }
+}
+
+/// Sizes of one linked image.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SizeRecord {
+    /// Build time (seconds since the Unix epoch).
+    pub timestamp: u64,
+    /// Commit the build was made from, if known.
+    pub commit: Option<String>,
+    /// Build configuration (e.g. profile name).
+    pub config: String,
+    /// Image sizes.
+    pub sizes: SectionSizes,
+}
+
+impl SizeRecord {
+    /// Create a record.
+    pub fn new(
+        timestamp: u64,
+        commit: Option<String>,
+        config: impl Into<String>,
+        sizes: SectionSizes,
+    ) -> Self {
+        Self {
+            timestamp,
+            commit,
+            config: config.into(),
+            sizes,
+        }
+    }
+}
+
+/// Query parameters for size history.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SizeQuery {
+    /// Only include this configuration.
+    #[serde(default)]
+    pub config: Option<String>,
+    /// Only include builds at or after this timestamp.
+    #[serde(default)]
+    pub since: Option<u64>,
+    /// Only include builds before this timestamp.
+    #[serde(default)]
+    pub until: Option<u64>,
+}
+
+impl SizeQuery {
+    /// Whether a record matches the query.
+    fn matches(&self, record: &SizeRecord) -> bool {
+        if let Some(ref config) = self.config {
+            if *config != record.config {
+                return false;
+            }
+        }
+        if let Some(since) = self.since {
+            if record.timestamp < since {
+                return false;
+            }
+        }
+        if let Some(until) = self.until {
+            if record.timestamp >= until {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Size history of one configuration.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SizeTrend {
+    /// Build configuration.
+    pub config: String,
+    /// Records in build order; rebuilds of the same commit keep the latest.
+    pub records: Vec<SizeRecord>,
+    /// Change from the first to the last record.
+    pub net: SizeDelta,
+}
+
+/// A build whose flash or RAM use grew past the threshold.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SizeRegression {
+    /// Build configuration.
+    pub config: String,
+    /// The build that grew.
+    pub record: SizeRecord,
+    /// Commit of the preceding build.
+    pub previous_commit: Option<String>,
+    /// Change from the preceding build.
+    pub delta: SizeDelta,
+}
+
+/// Append-only store of size records.
+#[derive(Debug, Clone)]
+pub struct SizeHistoryStore {
+    path: PathBuf,
+}
+
+impl SizeHistoryStore {
+    /// Create a store backed by the given file.
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Create the store for a project root.
+    pub fn for_project(project_root: &Path) -> Self {
+        Self::new(project_root.join(".axiom").join("size-history.jsonl"))
+    }
+
+    /// Path of the backing file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append a record.
+    pub fn append(&self, record: &SizeRecord) -> Result<(), SizeHistoryError> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let line = serde_json::to_string(record)?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", line)?;
+        Ok(())
+    }
+
+    /// Measure an ELF image and append its record.
+    pub fn record_elf(
+        &self,
+        elf: &Path,
+        timestamp: u64,
+        commit: Option<String>,
+        config: &str,
+    ) -> Result<SizeRecord, SizeHistoryError> {
+        let sizes = SectionSizes::from_elf(&ElfFile::read(elf)?);
+        let record = SizeRecord::new(timestamp, commit, config, sizes);
+        self.append(&record)?;
+        Ok(record)
+    }
+
+    /// Load all records in the order they were recorded.
+    ///
+    /// A missing file yields an empty list.
+    pub fn load(&self) -> Result<Vec<SizeRecord>, SizeHistoryError> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let content = fs::read_to_string(&self.path)?;
+        content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                serde_json::from_str(line).map_err(|source| SizeHistoryError::Parse {
+                    line: i + 1,
+                    source,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Per-configuration size trends, ordered by configuration name.
+pub fn size_trends(records: &[SizeRecord], query: &SizeQuery) -> Vec<SizeTrend> {
+    let mut by_config: BTreeMap<&str, Vec<SizeRecord>> = BTreeMap::new();
+    for record in records.iter().filter(|r| query.matches(r)) {
+        let history = by_config.entry(&record.config).or_default();
+        match history.last_mut() {
+            Some(last) if last.commit.is_some() && last.commit == record.commit => {
+                *last = record.clone();
+            }
+            _ => history.push(record.clone()),
+        }
+    }
+
+    by_config
+        .into_iter()
+        .map(|(config, records)| {
+            let net = match (records.first(), records.last()) {
+                (Some(first), Some(last)) => last.sizes.delta_from(&first.sizes),
+                _ => SizeDelta::default(),
+            };
+            SizeTrend {
+                config: config.to_string(),
+                records,
+                net,
+            }
+        })
+        .collect()
+}
+
+/// Builds whose flash or RAM use grew by more than `threshold` bytes over
+/// the preceding build of the same configuration.
+pub fn size_regressions(
+    records: &[SizeRecord],
+    query: &SizeQuery,
+    threshold: u64,
+) -> Vec<SizeRegression> {
+    let threshold = threshold as i64;
+    let mut regressions = Vec::new();
+    for trend in size_trends(records, query) {
+        for pair in trend.records.windows(2) {
+            let delta = pair[1].sizes.delta_from(&pair[0].sizes);
+            if delta.flash() > threshold || delta.ram() > threshold {
+                regressions.push(SizeRegression {
+                    config: trend.config.clone(),
+                    record: pair[1].clone(),
+                    previous_commit: pair[0].commit.clone(),
+                    delta,
+                });
+            }
+        }
+    }
+    regressions.sort_by_key(|r| r.record.timestamp);
+    regressions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn record(timestamp: u64, commit: &str, config: &str, text: u64, bss: u64) -> SizeRecord {
+        SizeRecord::new(
+            timestamp,
+            Some(commit.to_string()),
+            config,
+            SectionSizes {
+                text,
+                data: 64,
+                bss,
+            },
+        )
+    }
+
+    #[test]
+    fn test_sizes_from_elf() {
+        let mut data = crate::elf::build_test_elf(&[
+            (".text", 1, &[0; 12]),
+            (".rodata", 1, &[0; 4]),
+            (".data", 1, &[0; 8]),
+            (".bss", SHT_NOBITS, &[]),
+            (".comment", 1, b"GCC\0"),
+        ]);
+        // Patch flags (and the .bss size) into the section headers
+        let shoff = u32::from_le_bytes(data[32..36].try_into().unwrap()) as usize;
+        for (index, flags, size) in [
+            (1, 0x6u32, None),
+            (2, 0x2, None),
+            (3, 0x3, None),
+            (4, 0x3, Some(32u32)),
+        ] {
+            let header = shoff + index * 40;
+            data[header + 8..header + 12].copy_from_slice(&flags.to_le_bytes());
+            if let Some(size) = size {
+                data[header + 20..header + 24].copy_from_slice(&size.to_le_bytes());
+            }
+        }
+        let sizes = SectionSizes::from_elf(&ElfFile::parse(data).unwrap());
+        assert_eq!(
+            sizes,
+            SectionSizes {
+                text: 16,
+                data: 8,
+                bss: 32
+            }
+        );
+        assert_eq!(sizes.flash(), 24);
+        assert_eq!(sizes.ram(), 40);
+    }
+
+    #[test]
+    fn test_append_and_load() {
+        let dir = TempDir::new().unwrap();
+        let store = SizeHistoryStore::for_project(dir.path());
+        assert!(store.load().unwrap().is_empty());
+
+        let first = record(100, "a1", "release", 1000, 200);
+        let second = record(200, "b2", "release", 1100, 200);
+        store.append(&first).unwrap();
+        store.append(&second).unwrap();
+        assert_eq!(store.load().unwrap(), vec![first, second]);
+
+        fs::write(store.path(), "{\n").unwrap();
+        assert!(matches!(
+            store.load(),
+            Err(SizeHistoryError::Parse { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_trends_per_config() {
+        let records = vec![
+            record(100, "a1", "release", 1000, 200),
+            record(110, "a1", "debug", 3000, 200),
+            record(200, "b2", "release", 1100, 200),
+            // Rebuild of the same commit replaces the earlier record
+            record(210, "b2", "release", 1050, 200),
+            record(300, "c3", "release", 1060, 220),
+        ];
+        let trends = size_trends(&records, &SizeQuery::default());
+        assert_eq!(trends.len(), 2);
+        assert_eq!(trends[0].config, "debug");
+
+        let release = &trends[1];
+        assert_eq!(release.records.len(), 3);
+        assert_eq!(release.records[1].sizes.text, 1050);
+        assert_eq!(
+            release.net,
+            SizeDelta {
+                text: 60,
+                data: 0,
+                bss: 20
+            }
+        );
+
+        let query = SizeQuery {
+            config: Some("release".to_string()),
+            since: Some(150),
+            until: None,
+        };
+        let trends = size_trends(&records, &query);
+        assert_eq!(trends.len(), 1);
+        assert_eq!(trends[0].records.len(), 2);
+    }
+
+    #[test]
+    fn test_regressions() {
+        let records = vec![
+            record(100, "a1", "release", 1000, 200),
+            record(200, "b2", "release", 1010, 200),
+            record(300, "c3", "release", 1500, 200),
+            record(400, "d4", "release", 1500, 400),
+            record(500, "e5", "release", 1200, 400),
+        ];
+        let regressions = size_regressions(&records, &SizeQuery::default(), 64);
+        assert_eq!(regressions.len(), 2);
+        assert_eq!(regressions[0].record.commit.as_deref(), Some("c3"));
+        assert_eq!(regressions[0].previous_commit.as_deref(), Some("b2"));
+        assert_eq!(regressions[0].delta.flash(), 490);
+        assert_eq!(regressions[1].delta.ram(), 200);
+        assert_eq!(regressions[1].delta.flash(), 0);
+    }
+}
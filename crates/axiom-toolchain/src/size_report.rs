@@ -0,0 +1,375 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Detailed image size reports.
+//!
+//! The aggregate report is the Berkeley `size` triple plus one row per
+//! allocated section, as `size -A` prints. The detailed report adds every
+//! sized symbol from the symbol table, as `nm --size-sort` lists them, and,
+//! when the link's map file is available, the bytes each object file
+//! contributes per section. Symbols are attributed to their object through
+//! the map's input sections.
+
+use crate::{ElfError, ElfFile, MemoryMap, SectionSizes, SizeCategory, SymbolKind, EM_ARM};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// One allocated section of the image.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SectionUsage {
+    /// Section name.
+    pub name: String,
+    /// Run-time address.
+    pub address: u64,
+    /// Size in bytes.
+    pub size: u64,
+    /// Size column the section counts towards.
+    pub category: SizeCategory,
+}
+
+/// Bytes one object file contributes to the image.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ObjectUsage {
+    /// Object, e.g. "build/main.o" or "libc.a(memcpy.o)".
+    pub object: String,
+    /// Contribution per size column.
+    pub sizes: SectionSizes,
+    /// Contribution per output section.
+    pub sections: BTreeMap<String, u64>,
+}
+
+/// One sized symbol of the image.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SymbolUsage {
+    /// Symbol name.
+    pub name: String,
+    /// Address (Thumb bit cleared).
+    pub address: u64,
+    /// Size in bytes.
+    pub size: u64,
+    /// Section the symbol is defined in.
+    pub section: String,
+    /// Size column of that section.
+    pub category: SizeCategory,
+    /// Defining object, when a map file was given.
+    pub object: Option<String>,
+}
+
+/// Size report for a linked image.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SizeReport {
+    /// Berkeley totals.
+    pub totals: SectionSizes,
+    /// Allocated sections, largest first.
+    pub sections: Vec<SectionUsage>,
+    /// Per-object contributions, largest first; empty unless detailed and
+    /// a map file was given.
+    pub objects: Vec<ObjectUsage>,
+    /// Sized symbols, largest first; empty unless detailed.
+    pub symbols: Vec<SymbolUsage>,
+}
+
+impl SizeReport {
+    /// Aggregate report: totals and per-section sizes.
+    pub fn from_elf(elf: &ElfFile) -> Self {
+        let mut sections: Vec<SectionUsage> = elf
+            .sections
+            .iter()
+            .filter(|s| s.size > 0)
+            .filter_map(|s| {
+                Some(SectionUsage {
+                    name: s.name.clone(),
+                    address: s.addr,
+                    size: s.size,
+                    category: SizeCategory::of(s)?,
+                })
+            })
+            .collect();
+        sections.sort_by(|a, b| b.size.cmp(&a.size).then(a.name.cmp(&b.name)));
+
+        Self {
+            totals: SectionSizes::from_elf(elf),
+            sections,
+            objects: Vec::new(),
+            symbols: Vec::new(),
+        }
+    }
+
+    /// Detailed report: the aggregate report plus per-symbol sizes and,
+    /// with a map file, per-object sizes.
+    pub fn detailed(elf: &ElfFile, map: Option<&MemoryMap>) -> Self {
+        let mut report = Self::from_elf(elf);
+        let inputs = map.map(InputIndex::new).unwrap_or_default();
+        report.symbols = symbol_usage(elf, &inputs);
+        if let Some(map) = map {
+            report.objects = object_usage(elf, map);
+        }
+        report
+    }
+
+    /// The `count` largest symbols in a category.
+    pub fn largest_symbols(&self, category: SizeCategory, count: usize) -> Vec<&SymbolUsage> {
+        self.symbols
+            .iter()
+            .filter(|s| s.category == category)
+            .take(count)
+            .collect()
+    }
+}
+
+/// Read an image, and its map file if given, into a size report.
+pub fn size_report(elf: &Path, map: Option<&Path>, detailed: bool) -> Result<SizeReport, ElfError> {
+    let elf = ElfFile::read(elf)?;
+    if !detailed {
+        return Ok(SizeReport::from_elf(&elf));
+    }
+    let map = map.map(crate::read_map_file).transpose()?;
+    Ok(SizeReport::detailed(&elf, map.as_ref()))
+}
+
+/// Input sections of a map by address, for attributing symbols to objects.
+#[derive(Default)]
+struct InputIndex {
+    /// Start, end and object, sorted by start.
+    ranges: Vec<(u64, u64, String)>,
+}
+
+impl InputIndex {
+    fn new(map: &MemoryMap) -> Self {
+        let mut ranges: Vec<(u64, u64, String)> = map
+            .sections
+            .iter()
+            .filter(|s| s.is_allocated())
+            .flat_map(|s| &s.inputs)
+            .filter(|input| input.size > 0)
+            .map(|input| {
+                (
+                    input.address,
+                    input.address + input.size,
+                    input.object.clone(),
+                )
+            })
+            .collect();
+        ranges.sort();
+        Self { ranges }
+    }
+
+    fn object_at(&self, address: u64) -> Option<&str> {
+        let idx = self.ranges.partition_point(|r| r.0 <= address);
+        self.ranges[..idx]
+            .last()
+            .filter(|r| address < r.1)
+            .map(|r| r.2.as_str())
+    }
+}
+
+/// Sized function and object symbols, largest first.
+fn symbol_usage(elf: &ElfFile, inputs: &InputIndex) -> Vec<SymbolUsage> {
+    let thumb_mask = if elf.machine == EM_ARM { !1 } else { !0 };
+    let mut symbols: Vec<SymbolUsage> = elf
+        .symbols
+        .iter()
+        .filter(|s| s.size > 0 && matches!(s.kind, SymbolKind::Func | SymbolKind::Object))
+        .filter_map(|s| {
+            let section = elf.symbol_section(s)?;
+            let category = SizeCategory::of(section)?;
+            let address = if s.kind == SymbolKind::Func {
+                s.value & thumb_mask
+            } else {
+                s.value
+            };
+            Some(SymbolUsage {
+                name: s.name.clone(),
+                address,
+                size: s.size,
+                section: section.name.clone(),
+                category,
+                object: inputs.object_at(address).map(str::to_string),
+            })
+        })
+        .collect();
+    symbols.sort_by(|a, b| {
+        b.size
+            .cmp(&a.size)
+            .then(a.name.cmp(&b.name))
+            .then(a.address.cmp(&b.address))
+    });
+    symbols.dedup_by(|a, b| a.name == b.name && a.address == b.address);
+    symbols
+}
+
+/// Per-object contributions from the map's input sections, largest first.
+///
+/// Output sections missing from the image, such as those discarded after
+/// the map was written, are skipped.
+fn object_usage(elf: &ElfFile, map: &MemoryMap) -> Vec<ObjectUsage> {
+    let mut by_object: BTreeMap<&str, ObjectUsage> = BTreeMap::new();
+    for section in map.sections.iter().filter(|s| s.is_allocated()) {
+        let Some(category) = elf.section(&section.name).and_then(SizeCategory::of) else {
+            continue;
+        };
+        for input in section.inputs.iter().filter(|i| i.size > 0) {
+            let usage = by_object
+                .entry(&input.object)
+                .or_insert_with(|| ObjectUsage {
+                    object: input.object.clone(),
+                    sizes: SectionSizes::default(),
+                    sections: BTreeMap::new(),
+                });
+            usage.sizes.add(category, input.size);
+            *usage.sections.entry(section.name.clone()).or_default() += input.size;
+        }
+    }
+
+    let mut objects: Vec<ObjectUsage> = by_object.into_values().collect();
+    objects.sort_by(|a, b| {
+        b.sizes
+            .total()
+            .cmp(&a.sizes.total())
+            .then(a.object.cmp(&b.object))
+    });
+    objects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf::{build_test_elf, build_test_symtab};
+    use crate::{InputSection, OutputSection, SHF_ALLOC, SHF_WRITE, SHT_NOBITS, SHT_SYMTAB};
+
+    fn image() -> ElfFile {
+        let (symtab, strtab) = build_test_symtab(&[
+            ("main", 0x0800_0001, 0x20, 0x12, 1),
+            ("uart_init", 0x0800_0021, 0x40, 0x12, 1),
+            ("table", 0x0800_0060, 0x10, 0x11, 1),
+            ("buffer", 0x2000_0000, 0x100, 0x11, 2),
+            ("$t", 0x0800_0000, 0, 0x00, 1),
+            ("unsized", 0x0800_0070, 0, 0x12, 1),
+        ]);
+        let data = build_test_elf(&[
+            (".text", 1, &[0; 0x70]),
+            (".bss", SHT_NOBITS, &[]),
+            (".symtab", SHT_SYMTAB, &symtab),
+            (".strtab", 3, &strtab),
+        ]);
+        let mut elf = ElfFile::parse(data).unwrap();
+        elf.sections[1].flags = SHF_ALLOC | crate::SHF_EXECINSTR;
+        elf.sections[1].addr = 0x0800_0000;
+        elf.sections[2].flags = SHF_ALLOC | SHF_WRITE;
+        elf.sections[2].addr = 0x2000_0000;
+        elf.sections[2].size = 0x100;
+        elf
+    }
+
+    fn map() -> MemoryMap {
+        let input = |object: &str, address: u64, size: u64| InputSection {
+            name: String::new(),
+            address,
+            size,
+            object: object.to_string(),
+            symbols: Vec::new(),
+        };
+        let output = |name: &str, address: u64, inputs: Vec<InputSection>| OutputSection {
+            name: name.to_string(),
+            address,
+            size: inputs.iter().map(|i| i.size).sum(),
+            load_address: None,
+            inputs,
+        };
+        MemoryMap {
+            regions: Vec::new(),
+            sections: vec![
+                output(
+                    ".text",
+                    0x0800_0000,
+                    vec![
+                        input("build/main.o", 0x0800_0000, 0x20),
+                        input("build/uart.o", 0x0800_0020, 0x50),
+                    ],
+                ),
+                output(
+                    ".bss",
+                    0x2000_0000,
+                    vec![input("build/uart.o", 0x2000_0000, 0x100)],
+                ),
+                output(".debug_info", 0, vec![input("build/main.o", 0, 0x400)]),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_aggregate() {
+        let report = SizeReport::from_elf(&image());
+        assert_eq!(report.totals.text, 0x70);
+        assert_eq!(report.totals.bss, 0x100);
+        assert_eq!(
+            report
+                .sections
+                .iter()
+                .map(|s| (s.name.as_str(), s.category))
+                .collect::<Vec<_>>(),
+            [(".bss", SizeCategory::Bss), (".text", SizeCategory::Text)]
+        );
+        assert!(report.symbols.is_empty());
+        assert!(report.objects.is_empty());
+    }
+
+    #[test]
+    fn test_detailed() {
+        let report = SizeReport::detailed(&image(), Some(&map()));
+
+        let symbols: Vec<(&str, u64, &str, Option<&str>)> = report
+            .symbols
+            .iter()
+            .map(|s| {
+                (
+                    s.name.as_str(),
+                    s.size,
+                    s.section.as_str(),
+                    s.object.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            symbols,
+            [
+                ("buffer", 0x100, ".bss", Some("build/uart.o")),
+                ("uart_init", 0x40, ".text", Some("build/uart.o")),
+                ("main", 0x20, ".text", Some("build/main.o")),
+                ("table", 0x10, ".text", Some("build/uart.o")),
+            ]
+        );
+        assert_eq!(report.symbols[2].address, 0x0800_0000);
+        assert_eq!(
+            report
+                .largest_symbols(SizeCategory::Text, 1)
+                .iter()
+                .map(|s| s.name.as_str())
+                .collect::<Vec<_>>(),
+            ["uart_init"]
+        );
+
+        assert_eq!(report.objects.len(), 2);
+        let uart = &report.objects[0];
+        assert_eq!(uart.object, "build/uart.o");
+        assert_eq!(
+            uart.sizes,
+            SectionSizes {
+                text: 0x50,
+                data: 0,
+                bss: 0x100
+            }
+        );
+        assert_eq!(uart.sections[".bss"], 0x100);
+        assert_eq!(report.objects[1].sizes.total(), 0x20);
+    }
+
+    #[test]
+    fn test_detailed_without_map() {
+        let report = SizeReport::detailed(&image(), None);
+        assert_eq!(report.symbols.len(), 4);
+        assert!(report.symbols.iter().all(|s| s.object.is_none()));
+        assert!(report.objects.is_empty());
+    }
+}
@@ -0,0 +1,388 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Build statistics aggregated from persisted build reports.
+
+use crate::BuildReport;
+use axiom_core::time::{format_date, SECONDS_PER_DAY};
+use axiom_core::Severity;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Time bucket used to partition statistics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StatsPartition {
+    /// One bucket per UTC day.
+    #[default]
+    Day,
+    /// One bucket per ISO week (starting Monday, UTC).
+    Week,
+}
+
+impl StatsPartition {
+    /// Start of the bucket containing a timestamp.
+    fn bucket_start(self, timestamp: u64) -> u64 {
+        let day = timestamp / SECONDS_PER_DAY;
+        let start_day = match self {
+            StatsPartition::Day => day,
+            // 1970-01-01 was a Thursday.
+            StatsPartition::Week => day - (day + 3) % 7,
+        };
+        start_day * SECONDS_PER_DAY
+    }
+}
+
+/// Query parameters for build statistics.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatsQuery {
+    /// Only include builds at or after this timestamp.
+    pub since: Option<u64>,
+    /// Only include builds before this timestamp.
+    pub until: Option<u64>,
+    /// Time bucket size.
+    pub partition: StatsPartition,
+    /// Number of recurring diagnostics to report.
+    pub top_diagnostics: usize,
+}
+
+impl StatsQuery {
+    /// Whether a timestamp falls inside the query window.
+    fn contains(&self, timestamp: u64) -> bool {
+        if let Some(since) = self.since {
+            if timestamp < since {
+                return false;
+            }
+        }
+        if let Some(until) = self.until {
+            if timestamp >= until {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+impl Default for StatsQuery {
+    fn default() -> Self {
+        Self {
+            since: None,
+            until: None,
+            partition: StatsPartition::Day,
+            top_diagnostics: 10,
+        }
+    }
+}
+
+/// Statistics for one time bucket.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PartitionStats {
+    /// Bucket start (seconds since the Unix epoch).
+    pub start: u64,
+    /// Bucket start as a UTC date.
+    pub label: String,
+    /// Number of builds.
+    pub builds: u32,
+    /// Number of failed builds.
+    pub failures: u32,
+    /// Mean build duration in milliseconds.
+    pub average_duration_ms: f64,
+}
+
+/// A diagnostic that recurs across builds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecurringDiagnostic {
+    /// Severity.
+    pub severity: Severity,
+    /// Normalized diagnostic code, when known.
+    #[serde(default)]
+    pub code: Option<String>,
+    /// Message with the source location stripped (first occurrence when
+    /// grouped by code).
+    pub message: String,
+    /// Total number of occurrences.
+    pub occurrences: u32,
+    /// Number of builds in which it appeared.
+    pub builds: u32,
+}
+
+/// Aggregated build statistics.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BuildStatistics {
+    /// Number of builds considered.
+    pub total_builds: u32,
+    /// Number of failed builds.
+    pub failed_builds: u32,
+    /// Fraction of builds that failed (0.0-1.0).
+    pub failure_rate: f64,
+    /// Mean build duration in milliseconds.
+    pub average_duration_ms: f64,
+    /// Fraction of translation units served from cache (0.0-1.0).
+    pub cache_hit_rate: f64,
+    /// Per-bucket statistics in chronological order.
+    pub partitions: Vec<PartitionStats>,
+    /// Most frequent diagnostics, most frequent first.
+    pub top_diagnostics: Vec<RecurringDiagnostic>,
+}
+
+/// Compute statistics over a set of build reports.
+pub fn compute_build_statistics(reports: &[BuildReport], query: &StatsQuery) -> BuildStatistics {
+    let selected: Vec<&BuildReport> = reports
+        .iter()
+        .filter(|r| query.contains(r.timestamp))
+        .collect();
+
+    let total_builds = selected.len() as u32;
+    let failed_builds = selected.iter().filter(|r| !r.success).count() as u32;
+    let total_duration: u64 = selected.iter().map(|r| r.duration_ms).sum();
+    let units: u64 = selected
+        .iter()
+        .map(|r| u64::from(r.units_compiled) + u64::from(r.cache_hits))
+        .sum();
+    let hits: u64 = selected.iter().map(|r| u64::from(r.cache_hits)).sum();
+
+    BuildStatistics {
+        total_builds,
+        failed_builds,
+        failure_rate: ratio(u64::from(failed_builds), u64::from(total_builds)),
+        average_duration_ms: ratio(total_duration, u64::from(total_builds)),
+        cache_hit_rate: ratio(hits, units),
+        partitions: partition(&selected, query.partition),
+        top_diagnostics: recurring_diagnostics(&selected, query.top_diagnostics),
+    }
+}
+
+fn ratio(numerator: u64, denominator: u64) -> f64 {
+    if denominator == 0 {
+        0.0
+    } else {
+        numerator as f64 / denominator as f64
+    }
+}
+
+fn partition(reports: &[&BuildReport], partition: StatsPartition) -> Vec<PartitionStats> {
+    // (builds, failures, total duration) per bucket
+    let mut buckets: BTreeMap<u64, (u32, u32, u64)> = BTreeMap::new();
+
+    for report in reports {
+        let bucket = buckets
+            .entry(partition.bucket_start(report.timestamp))
+            .or_default();
+        bucket.0 += 1;
+        if !report.success {
+            bucket.1 += 1;
+        }
+        bucket.2 += report.duration_ms;
+    }
+
+    buckets
+        .into_iter()
+        .map(|(start, (builds, failures, duration))| PartitionStats {
+            start,
+            label: format_date(start),
+            builds,
+            failures,
+            average_duration_ms: ratio(duration, u64::from(builds)),
+        })
+        .collect()
+}
+
+fn recurring_diagnostics(reports: &[&BuildReport], limit: usize) -> Vec<RecurringDiagnostic> {
+    // Diagnostics with a normalized code are grouped by code so rewording
+    // between compiler versions does not split them.
+    let mut counts: HashMap<(Severity, String), RecurringDiagnostic> = HashMap::new();
+
+    for report in reports {
+        let mut seen_in_build = HashSet::new();
+        for diag in &report.diagnostics {
+            let message = diagnostic_key(&diag.message);
+            let key = (
+                diag.severity,
+                diag.code.clone().unwrap_or_else(|| message.to_string()),
+            );
+            let entry = counts
+                .entry(key.clone())
+                .or_insert_with(|| RecurringDiagnostic {
+                    severity: diag.severity,
+                    code: diag.code.clone(),
+                    message: message.to_string(),
+                    occurrences: 0,
+                    builds: 0,
+                });
+            entry.occurrences += 1;
+            if seen_in_build.insert(key) {
+                entry.builds += 1;
+            }
+        }
+    }
+
+    let mut recurring: Vec<RecurringDiagnostic> = counts.into_values().collect();
+
+    // Deterministic: most frequent first, then alphabetical.
+    recurring.sort_by(|a, b| {
+        b.occurrences
+            .cmp(&a.occurrences)
+            .then_with(|| a.message.cmp(&b.message))
+    });
+    recurring.truncate(limit);
+    recurring
+}
+
+/// Strip a leading `file:line:col:` prefix so the same diagnostic at
+/// different locations is counted together.
+fn diagnostic_key(message: &str) -> &str {
+    for marker in ["error:", "warning:", "note:"] {
+        if let Some(idx) = message.find(marker) {
+            return message[idx..].trim();
+        }
+    }
+    message.trim()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BuildReportStore;
+    use axiom_core::Diagnostic;
+    use tempfile::TempDir;
+
+    const DAY: u64 = SECONDS_PER_DAY;
+
+    fn report(timestamp: u64, success: bool, duration_ms: u64, diags: &[&str]) -> BuildReport {
+        BuildReport {
+            timestamp,
+            success,
+            duration_ms,
+            units_compiled: 3,
+            cache_hits: 1,
+            diagnostics: diags.iter().map(|m| Diagnostic::warning(*m)).collect(),
+        }
+    }
+
+    #[test]
+    fn test_stored_reports() {
+        let dir = TempDir::new().unwrap();
+        let store = BuildReportStore::for_project(dir.path());
+        for (timestamp, success, duration_ms) in [(DAY, true, 100), (DAY + 60, false, 300)] {
+            let report = report(timestamp, success, duration_ms, &["unused"]);
+            store.append(&report).unwrap();
+        }
+
+        let stats = compute_build_statistics(&store.load().unwrap(), &StatsQuery::default());
+        assert_eq!(stats.total_builds, 2);
+        assert_eq!(stats.failed_builds, 1);
+        assert_eq!(stats.average_duration_ms, 200.0);
+        assert_eq!(stats.top_diagnostics[0].builds, 2);
+    }
+
+    #[test]
+    fn test_empty_reports() {
+        let stats = compute_build_statistics(&[], &StatsQuery::default());
+        assert_eq!(stats.total_builds, 0);
+        assert_eq!(stats.failure_rate, 0.0);
+        assert!(stats.partitions.is_empty());
+    }
+
+    #[test]
+    fn test_totals_and_partitions() {
+        let reports = vec![
+            report(10 * DAY + 5, true, 100, &[]),
+            report(10 * DAY + 50, false, 300, &[]),
+            report(11 * DAY, true, 200, &[]),
+        ];
+
+        let stats = compute_build_statistics(&reports, &StatsQuery::default());
+        assert_eq!(stats.total_builds, 3);
+        assert_eq!(stats.failed_builds, 1);
+        assert!((stats.failure_rate - 1.0 / 3.0).abs() < 1e-9);
+        assert_eq!(stats.average_duration_ms, 200.0);
+        assert_eq!(stats.cache_hit_rate, 0.25);
+
+        assert_eq!(stats.partitions.len(), 2);
+        assert_eq!(stats.partitions[0].label, "1970-01-11");
+        assert_eq!(stats.partitions[0].builds, 2);
+        assert_eq!(stats.partitions[0].failures, 1);
+        assert_eq!(stats.partitions[0].average_duration_ms, 200.0);
+    }
+
+    #[test]
+    fn test_week_partition_starts_monday() {
+        // 1970-01-05 was a Monday.
+        assert_eq!(StatsPartition::Week.bucket_start(4 * DAY), 4 * DAY);
+        assert_eq!(StatsPartition::Week.bucket_start(10 * DAY + 7), 4 * DAY);
+        assert_eq!(StatsPartition::Week.bucket_start(11 * DAY), 11 * DAY);
+    }
+
+    #[test]
+    fn test_time_window() {
+        let reports = vec![
+            report(DAY, true, 10, &[]),
+            report(2 * DAY, true, 10, &[]),
+            report(3 * DAY, true, 10, &[]),
+        ];
+        let query = StatsQuery {
+            since: Some(2 * DAY),
+            until: Some(3 * DAY),
+            ..StatsQuery::default()
+        };
+
+        assert_eq!(compute_build_statistics(&reports, &query).total_builds, 1);
+    }
+
+    #[test]
+    fn test_top_diagnostics_ignore_location() {
+        let reports = vec![
+            report(0, true, 1, &["a.c:1:1: warning: unused variable 'x'"]),
+            report(
+                1,
+                true,
+                1,
+                &[
+                    "b.c:9:2: warning: unused variable 'x'",
+                    "b.c:12:2: warning: unused variable 'x'",
+                    "b.c:20:1: warning: implicit conversion",
+                ],
+            ),
+        ];
+        let query = StatsQuery {
+            top_diagnostics: 1,
+            ..StatsQuery::default()
+        };
+
+        let stats = compute_build_statistics(&reports, &query);
+        assert_eq!(stats.top_diagnostics.len(), 1);
+        let top = &stats.top_diagnostics[0];
+        assert_eq!(top.message, "warning: unused variable 'x'");
+        assert_eq!(top.occurrences, 3);
+        assert_eq!(top.builds, 2);
+    }
+
+    #[test]
+    fn test_top_diagnostics_group_by_code() {
+        let mut gcc10 = report(
+            0,
+            true,
+            1,
+            &["a.c:7:5: warning: 'x' may be used uninitialized in this function"],
+        );
+        let mut gcc13 = report(
+            1,
+            true,
+            1,
+            &["a.c:7:5: warning: 'x' may be used uninitialized"],
+        );
+        for r in [&mut gcc10, &mut gcc13] {
+            r.diagnostics[0].code = Some("maybe-uninitialized".to_string());
+        }
+
+        let stats = compute_build_statistics(&[gcc10, gcc13], &StatsQuery::default());
+        assert_eq!(stats.top_diagnostics.len(), 1);
+        let top = &stats.top_diagnostics[0];
+        assert_eq!(top.code.as_deref(), Some("maybe-uninitialized"));
+        assert_eq!(
+            top.message,
+            "warning: 'x' may be used uninitialized in this function"
+        );
+        assert_eq!(top.builds, 2);
+    }
+}
@@ -0,0 +1,799 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! CMSIS-SVD peripheral register descriptions.
+//!
+//! Parses an SVD file into peripherals, registers, and bitfields with their
+//! enumerated values. Derived peripherals and registers are resolved,
+//! `dim` arrays are expanded, and clusters are flattened so every register
+//! carries its offset from the peripheral base.
+
+use roxmltree::Node;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Error type for SVD parsing.
+#[derive(Debug, thiserror::Error)]
+pub enum SvdError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("{path}: {source}")]
+    Xml {
+        path: PathBuf,
+        source: roxmltree::Error,
+    },
+
+    #[error("Invalid SVD: {0}")]
+    Invalid(String),
+}
+
+/// Register or field access.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SvdAccess {
+    ReadOnly,
+    WriteOnly,
+    ReadWrite,
+    WriteOnce,
+    ReadWriteOnce,
+}
+
+impl SvdAccess {
+    fn parse(text: &str) -> Option<Self> {
+        match text.trim() {
+            "read-only" => Some(SvdAccess::ReadOnly),
+            "write-only" => Some(SvdAccess::WriteOnly),
+            "read-write" => Some(SvdAccess::ReadWrite),
+            "writeOnce" => Some(SvdAccess::WriteOnce),
+            "read-writeOnce" => Some(SvdAccess::ReadWriteOnce),
+            _ => None,
+        }
+    }
+
+    /// Whether the value can be read back.
+    pub fn is_readable(&self) -> bool {
+        !matches!(self, SvdAccess::WriteOnly | SvdAccess::WriteOnce)
+    }
+}
+
+/// A named value of a bitfield.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SvdEnumValue {
+    /// Value name (e.g. "Output").
+    pub name: String,
+    /// Description.
+    pub description: Option<String>,
+    /// Value; `None` for patterns with don't-care bits.
+    pub value: Option<u64>,
+    /// Applies to every value not listed otherwise.
+    pub is_default: bool,
+}
+
+/// A bitfield of a register.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SvdField {
+    /// Field name (e.g. "MODER5").
+    pub name: String,
+    /// Description.
+    pub description: Option<String>,
+    /// Lowest bit.
+    pub bit_offset: u32,
+    /// Width in bits.
+    pub bit_width: u32,
+    /// Access, if different from the register's.
+    pub access: Option<SvdAccess>,
+    /// Enumerated values.
+    pub values: Vec<SvdEnumValue>,
+}
+
+impl SvdField {
+    /// Mask of the field's bits within the register.
+    pub fn mask(&self) -> u64 {
+        let bits = if self.bit_width >= 64 {
+            u64::MAX
+        } else {
+            (1u64 << self.bit_width) - 1
+        };
+        bits << self.bit_offset
+    }
+
+    /// Extract the field from a register value.
+    pub fn extract(&self, register_value: u64) -> u64 {
+        (register_value & self.mask()) >> self.bit_offset
+    }
+
+    /// Enumerated value matching a field value.
+    pub fn lookup(&self, value: u64) -> Option<&SvdEnumValue> {
+        self.values
+            .iter()
+            .find(|v| v.value == Some(value))
+            .or_else(|| self.values.iter().find(|v| v.is_default))
+    }
+}
+
+/// A register of a peripheral.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SvdRegister {
+    /// Register name; registers in clusters are prefixed with the cluster
+    /// name (e.g. "CH0.CTRL").
+    pub name: String,
+    /// Description.
+    pub description: Option<String>,
+    /// Offset from the peripheral base address.
+    pub offset: u64,
+    /// Size in bits.
+    pub size: u32,
+    /// Access.
+    pub access: Option<SvdAccess>,
+    /// Value after reset.
+    pub reset_value: Option<u64>,
+    /// Bitfields, lowest bit first.
+    pub fields: Vec<SvdField>,
+}
+
+/// An interrupt raised by a peripheral.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SvdInterrupt {
+    /// Interrupt name (e.g. "USART1").
+    pub name: String,
+    /// IRQ number.
+    pub value: u32,
+}
+
+/// A memory-mapped peripheral.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SvdPeripheral {
+    /// Peripheral name (e.g. "GPIOA").
+    pub name: String,
+    /// Group name (e.g. "GPIO").
+    pub group: Option<String>,
+    /// Description.
+    pub description: Option<String>,
+    /// Base address.
+    pub base_address: u64,
+    /// Registers, in offset order.
+    pub registers: Vec<SvdRegister>,
+    /// Interrupts.
+    pub interrupts: Vec<SvdInterrupt>,
+}
+
+impl SvdPeripheral {
+    /// Find a register by name (case-insensitive).
+    pub fn register(&self, name: &str) -> Option<&SvdRegister> {
+        self.registers
+            .iter()
+            .find(|r| r.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// A device described by an SVD file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SvdDevice {
+    /// Device name.
+    pub name: String,
+    /// Description.
+    pub description: Option<String>,
+    /// CPU name (e.g. "CM4"), if given.
+    pub cpu: Option<String>,
+    /// Peripherals, in base address order.
+    pub peripherals: Vec<SvdPeripheral>,
+}
+
+/// One field of a decoded register value.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DecodedField {
+    /// Field name.
+    pub name: String,
+    /// Field value.
+    pub value: u64,
+    /// Name of the matching enumerated value.
+    pub meaning: Option<String>,
+}
+
+/// A register value decoded into its fields.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DecodedRegister {
+    /// Peripheral name.
+    pub peripheral: String,
+    /// Register name.
+    pub register: String,
+    /// Register address.
+    pub address: u64,
+    /// Raw value.
+    pub value: u64,
+    /// Fields, lowest bit first.
+    pub fields: Vec<DecodedField>,
+}
+
+impl SvdDevice {
+    /// Find a peripheral by name (case-insensitive).
+    pub fn peripheral(&self, name: &str) -> Option<&SvdPeripheral> {
+        self.peripherals
+            .iter()
+            .find(|p| p.name.eq_ignore_ascii_case(name))
+    }
+
+    /// The register at an absolute address.
+    pub fn register_at(&self, address: u64) -> Option<(&SvdPeripheral, &SvdRegister)> {
+        self.peripherals.iter().find_map(|p| {
+            let offset = address.checked_sub(p.base_address)?;
+            p.registers
+                .iter()
+                .find(|r| r.offset == offset)
+                .map(|r| (p, r))
+        })
+    }
+
+    /// All registers with their absolute addresses.
+    pub fn registers(&self) -> impl Iterator<Item = (u64, &SvdPeripheral, &SvdRegister)> {
+        self.peripherals.iter().flat_map(|p| {
+            p.registers
+                .iter()
+                .map(move |r| (p.base_address + r.offset, p, r))
+        })
+    }
+
+    /// Decode a value read from a register address.
+    pub fn decode(&self, address: u64, value: u64) -> Option<DecodedRegister> {
+        let (peripheral, register) = self.register_at(address)?;
+        let fields = register
+            .fields
+            .iter()
+            .map(|field| {
+                let field_value = field.extract(value);
+                DecodedField {
+                    name: field.name.clone(),
+                    value: field_value,
+                    meaning: field.lookup(field_value).map(|v| v.name.clone()),
+                }
+            })
+            .collect();
+        Some(DecodedRegister {
+            peripheral: peripheral.name.clone(),
+            register: register.name.clone(),
+            address,
+            value,
+            fields,
+        })
+    }
+}
+
+/// Read and parse an SVD file.
+pub fn read_svd(path: &Path) -> Result<SvdDevice, SvdError> {
+    let content = std::fs::read_to_string(path)?;
+    parse_svd(&content).map_err(|e| match e {
+        SvdError::Xml { source, .. } => SvdError::Xml {
+            path: path.to_path_buf(),
+            source,
+        },
+        e => e,
+    })
+}
+
+/// Parse SVD content.
+pub fn parse_svd(content: &str) -> Result<SvdDevice, SvdError> {
+    let doc = roxmltree::Document::parse(content).map_err(|source| SvdError::Xml {
+        path: PathBuf::new(),
+        source,
+    })?;
+    let device = doc.root_element();
+    if !device.has_tag_name("device") {
+        return Err(SvdError::Invalid("root element is not <device>".into()));
+    }
+
+    let name = text(device, "name").ok_or_else(|| SvdError::Invalid("missing <name>".into()))?;
+    let defaults = Defaults::default().inherit(device);
+    let peripheral_nodes: Vec<Node> = child(device, "peripherals")
+        .into_iter()
+        .flat_map(|p| p.children().filter(|n| n.has_tag_name("peripheral")))
+        .collect();
+
+    let mut peripherals = Vec::new();
+    for node in &peripheral_nodes {
+        let base = node
+            .attribute("derivedFrom")
+            .and_then(|from| {
+                peripheral_nodes
+                    .iter()
+                    .find(|n| text(**n, "name").as_deref() == Some(from))
+            })
+            .copied();
+        peripherals.extend(parse_peripheral(*node, base, &defaults)?);
+    }
+    peripherals.sort_by_key(|p| p.base_address);
+
+    Ok(SvdDevice {
+        name,
+        description: text(device, "description"),
+        cpu: child(device, "cpu").and_then(|c| text(c, "name")),
+        peripherals,
+    })
+}
+
+/// Register properties inherited from enclosing elements.
+#[derive(Debug, Clone, Copy)]
+struct Defaults {
+    size: u32,
+    access: Option<SvdAccess>,
+    reset_value: Option<u64>,
+}
+
+impl Default for Defaults {
+    fn default() -> Self {
+        Self {
+            size: 32,
+            access: None,
+            reset_value: None,
+        }
+    }
+}
+
+impl Defaults {
+    fn inherit(mut self, node: Node) -> Self {
+        if let Some(size) = number(node, "size") {
+            self.size = size as u32;
+        }
+        if let Some(access) = text(node, "access").and_then(|a| SvdAccess::parse(&a)) {
+            self.access = Some(access);
+        }
+        if let Some(reset) = number(node, "resetValue") {
+            self.reset_value = Some(reset);
+        }
+        self
+    }
+}
+
+/// Parse a peripheral, expanding `dim` arrays of peripherals.
+fn parse_peripheral(
+    node: Node,
+    base: Option<Node>,
+    defaults: &Defaults,
+) -> Result<Vec<SvdPeripheral>, SvdError> {
+    // Derived peripherals take anything they don't override from the base
+    let lookup = |tag: &str| text(node, tag).or_else(|| base.and_then(|b| text(b, tag)));
+    let name =
+        text(node, "name").ok_or_else(|| SvdError::Invalid("peripheral without <name>".into()))?;
+    let base_address = number(node, "baseAddress")
+        .ok_or_else(|| SvdError::Invalid(format!("peripheral {} has no <baseAddress>", name)))?;
+
+    let mut defaults = *defaults;
+    if let Some(base) = base {
+        defaults = defaults.inherit(base);
+    }
+    let defaults = defaults.inherit(node);
+
+    let registers_node =
+        child(node, "registers").or_else(|| base.and_then(|b| child(b, "registers")));
+    let mut registers = Vec::new();
+    if let Some(registers_node) = registers_node {
+        collect_registers(registers_node, "", 0, &defaults, &mut registers)?;
+    }
+    registers.sort_by_key(|r| r.offset);
+
+    let interrupts = node
+        .children()
+        .filter(|n| n.has_tag_name("interrupt"))
+        .filter_map(|n| {
+            Some(SvdInterrupt {
+                name: text(n, "name")?,
+                value: number(n, "value")? as u32,
+            })
+        })
+        .collect();
+
+    let peripheral = SvdPeripheral {
+        name: name.clone(),
+        group: lookup("groupName"),
+        description: lookup("description"),
+        base_address,
+        registers,
+        interrupts,
+    };
+    Ok(dim_instances(node, &name)
+        .into_iter()
+        .map(|(name, offset)| SvdPeripheral {
+            name,
+            base_address: base_address + offset,
+            ..peripheral.clone()
+        })
+        .collect())
+}
+
+/// Flatten registers and clusters under `node` into `out`.
+fn collect_registers(
+    node: Node,
+    prefix: &str,
+    base_offset: u64,
+    defaults: &Defaults,
+    out: &mut Vec<SvdRegister>,
+) -> Result<(), SvdError> {
+    let siblings: Vec<Node> = node.children().filter(Node::is_element).collect();
+    for element in &siblings {
+        match element.tag_name().name() {
+            "register" => {
+                let base = element.attribute("derivedFrom").and_then(|from| {
+                    siblings.iter().copied().find(|n| {
+                        n.has_tag_name("register") && text(*n, "name").as_deref() == Some(from)
+                    })
+                });
+                let name = text(*element, "name")
+                    .ok_or_else(|| SvdError::Invalid("register without <name>".into()))?;
+                let register = parse_register(*element, base, defaults)?;
+                for (instance, offset) in dim_instances(*element, &name) {
+                    out.push(SvdRegister {
+                        name: format!("{}{}", prefix, instance),
+                        offset: base_offset + register.offset + offset,
+                        ..register.clone()
+                    });
+                }
+            }
+            "cluster" => {
+                let name = text(*element, "name")
+                    .ok_or_else(|| SvdError::Invalid("cluster without <name>".into()))?;
+                let offset = number(*element, "addressOffset").unwrap_or(0);
+                let defaults = defaults.inherit(*element);
+                for (instance, dim_offset) in dim_instances(*element, &name) {
+                    let prefix = format!("{}{}.", prefix, instance);
+                    collect_registers(
+                        *element,
+                        &prefix,
+                        base_offset + offset + dim_offset,
+                        &defaults,
+                        out,
+                    )?;
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn parse_register(
+    node: Node,
+    base: Option<Node>,
+    defaults: &Defaults,
+) -> Result<SvdRegister, SvdError> {
+    let lookup = |tag: &str| text(node, tag).or_else(|| base.and_then(|b| text(b, tag)));
+    let mut defaults = *defaults;
+    if let Some(base) = base {
+        defaults = defaults.inherit(base);
+    }
+    let defaults = defaults.inherit(node);
+
+    let offset = number(node, "addressOffset")
+        .or_else(|| base.and_then(|b| number(b, "addressOffset")))
+        .ok_or_else(|| {
+            SvdError::Invalid(format!(
+                "register {} has no <addressOffset>",
+                lookup("name").unwrap_or_default()
+            ))
+        })?;
+
+    let fields_node = child(node, "fields").or_else(|| base.and_then(|b| child(b, "fields")));
+    let mut fields: Vec<SvdField> = Vec::new();
+    if let Some(fields_node) = fields_node {
+        let field_nodes: Vec<Node> = fields_node
+            .children()
+            .filter(|n| n.has_tag_name("field"))
+            .collect();
+        for field in &field_nodes {
+            let base = field.attribute("derivedFrom").and_then(|from| {
+                field_nodes
+                    .iter()
+                    .copied()
+                    .find(|n| text(*n, "name").as_deref() == Some(from))
+            });
+            let parsed = parse_field(*field, base)?;
+            for (name, offset) in dim_instances(*field, &parsed.name) {
+                fields.push(SvdField {
+                    name,
+                    bit_offset: parsed.bit_offset + offset as u32,
+                    ..parsed.clone()
+                });
+            }
+        }
+    }
+    fields.sort_by_key(|f| f.bit_offset);
+
+    Ok(SvdRegister {
+        name: lookup("name").unwrap_or_default(),
+        description: lookup("description"),
+        offset,
+        size: defaults.size,
+        access: defaults.access,
+        reset_value: defaults.reset_value,
+        fields,
+    })
+}
+
+fn parse_field(node: Node, base: Option<Node>) -> Result<SvdField, SvdError> {
+    let source = |tag: &str| child(node, tag).or_else(|| base.and_then(|b| child(b, tag)));
+    let name =
+        text(node, "name").ok_or_else(|| SvdError::Invalid("field without <name>".into()))?;
+
+    let (bit_offset, bit_width) = if let Some(range) = source("bitRange").and_then(|n| n.text()) {
+        // "[msb:lsb]"
+        let inner = range.trim().trim_start_matches('[').trim_end_matches(']');
+        let (msb, lsb) = inner
+            .split_once(':')
+            .and_then(|(m, l)| Some((m.trim().parse::<u32>().ok()?, l.trim().parse::<u32>().ok()?)))
+            .ok_or_else(|| {
+                SvdError::Invalid(format!("field {} has bad bitRange {}", name, range))
+            })?;
+        (lsb, msb.saturating_sub(lsb) + 1)
+    } else if let (Some(lsb), Some(msb)) = (
+        source("lsb").and_then(node_number),
+        source("msb").and_then(node_number),
+    ) {
+        (lsb as u32, (msb.saturating_sub(lsb) + 1) as u32)
+    } else {
+        let offset = source("bitOffset").and_then(node_number);
+        let width = source("bitWidth").and_then(node_number).unwrap_or(1);
+        let offset = offset
+            .ok_or_else(|| SvdError::Invalid(format!("field {} has no bit position", name)))?;
+        (offset as u32, width as u32)
+    };
+
+    let values = source("enumeratedValues")
+        .map(|e| {
+            e.children()
+                .filter(|n| n.has_tag_name("enumeratedValue"))
+                .filter_map(|v| {
+                    Some(SvdEnumValue {
+                        name: text(v, "name")?,
+                        description: text(v, "description"),
+                        value: text(v, "value").and_then(|t| parse_enum_value(&t)),
+                        is_default: text(v, "isDefault").is_some_and(|d| d == "true" || d == "1"),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(SvdField {
+        name,
+        description: source("description").and_then(|n| n.text()).map(clean),
+        bit_offset,
+        bit_width,
+        access: source("access")
+            .and_then(|n| n.text())
+            .and_then(SvdAccess::parse),
+        values,
+    })
+}
+
+/// Instance names and offsets of a possibly `dim`-repeated element.
+fn dim_instances(node: Node, name: &str) -> Vec<(String, u64)> {
+    let Some(dim) = number(node, "dim") else {
+        return vec![(name.to_string(), 0)];
+    };
+    let increment = number(node, "dimIncrement").unwrap_or(0);
+    let indices: Vec<String> = match text(node, "dimIndex") {
+        Some(index) => match index.split_once('-') {
+            Some((start, end)) => match (start.trim().parse::<u64>(), end.trim().parse::<u64>()) {
+                (Ok(start), Ok(end)) => (start..=end).map(|i| i.to_string()).collect(),
+                _ => {
+                    let (start, end) = (start.trim().chars().next(), end.trim().chars().next());
+                    match (start, end) {
+                        (Some(s), Some(e)) => (s..=e).map(|c| c.to_string()).collect(),
+                        _ => Vec::new(),
+                    }
+                }
+            },
+            None => index.split(',').map(|s| s.trim().to_string()).collect(),
+        },
+        None => (0..dim).map(|i| i.to_string()).collect(),
+    };
+
+    indices
+        .into_iter()
+        .take(dim as usize)
+        .enumerate()
+        .map(|(i, index)| (name.replace("%s", &index), i as u64 * increment))
+        .collect()
+}
+
+/// Parse an enumerated value: decimal, `0x` hex, `0b` or `#` binary.
+/// Binary patterns with `x` don't-care bits give `None`.
+fn parse_enum_value(text: &str) -> Option<u64> {
+    let text = text.trim();
+    let binary = text
+        .strip_prefix("0b")
+        .or_else(|| text.strip_prefix("0B"))
+        .or_else(|| text.strip_prefix('#'));
+    match binary {
+        Some(bits) => u64::from_str_radix(bits, 2).ok(),
+        None => parse_number(text),
+    }
+}
+
+fn parse_number(text: &str) -> Option<u64> {
+    let text = text.trim();
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|n| n.has_tag_name(name))
+}
+
+fn text(node: Node, name: &str) -> Option<String> {
+    child(node, name).and_then(|n| n.text()).map(clean)
+}
+
+fn number(node: Node, name: &str) -> Option<u64> {
+    child(node, name).and_then(node_number)
+}
+
+fn node_number(node: Node) -> Option<u64> {
+    node.text().and_then(parse_number)
+}
+
+/// Collapse the whitespace SVD generators put inside descriptions.
+fn clean(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SVD: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<device schemaVersion="1.1">
+  <name>STM32F407</name>
+  <description>STM32F407
+    device</description>
+  <cpu><name>CM4</name></cpu>
+  <size>0x20</size>
+  <resetValue>0x0</resetValue>
+  <peripherals>
+    <peripheral>
+      <name>GPIOA</name>
+      <groupName>GPIO</groupName>
+      <baseAddress>0x40020000</baseAddress>
+      <registers>
+        <register>
+          <name>MODER</name>
+          <addressOffset>0x0</addressOffset>
+          <access>read-write</access>
+          <resetValue>0xA8000000</resetValue>
+          <fields>
+            <field>
+              <name>MODER%s</name>
+              <bitOffset>0</bitOffset>
+              <bitWidth>2</bitWidth>
+              <dim>2</dim>
+              <dimIncrement>2</dimIncrement>
+              <enumeratedValues>
+                <enumeratedValue><name>Input</name><value>0</value></enumeratedValue>
+                <enumeratedValue><name>Output</name><value>0b01</value></enumeratedValue>
+                <enumeratedValue><name>Other</name><isDefault>true</isDefault></enumeratedValue>
+              </enumeratedValues>
+            </field>
+          </fields>
+        </register>
+        <register>
+          <name>IDR</name>
+          <addressOffset>0x10</addressOffset>
+          <access>read-only</access>
+          <fields>
+            <field><name>IDR0</name><bitRange>[0:0]</bitRange></field>
+            <field><name>IDR1</name><lsb>1</lsb><msb>1</msb></field>
+          </fields>
+        </register>
+      </registers>
+    </peripheral>
+    <peripheral derivedFrom="GPIOA">
+      <name>GPIOB</name>
+      <baseAddress>0x40020400</baseAddress>
+    </peripheral>
+    <peripheral>
+      <name>DMA1</name>
+      <baseAddress>0x40026000</baseAddress>
+      <interrupt><name>DMA1_Stream0</name><value>11</value></interrupt>
+      <registers>
+        <cluster>
+          <name>S%s</name>
+          <dim>2</dim>
+          <dimIncrement>0x18</dimIncrement>
+          <addressOffset>0x10</addressOffset>
+          <register>
+            <name>CR</name>
+            <addressOffset>0x0</addressOffset>
+          </register>
+          <register derivedFrom="CR">
+            <name>NDTR</name>
+            <addressOffset>0x4</addressOffset>
+          </register>
+        </cluster>
+      </registers>
+    </peripheral>
+  </peripherals>
+</device>
+"#;
+
+    #[test]
+    fn test_parse_peripherals() {
+        let device = parse_svd(SVD).unwrap();
+        assert_eq!(device.name, "STM32F407");
+        assert_eq!(device.description.as_deref(), Some("STM32F407 device"));
+        assert_eq!(device.cpu.as_deref(), Some("CM4"));
+        let names: Vec<_> = device.peripherals.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["GPIOA", "GPIOB", "DMA1"]);
+
+        let gpioa = device.peripheral("gpioa").unwrap();
+        let moder = gpioa.register("MODER").unwrap();
+        assert_eq!(moder.size, 32);
+        assert_eq!(moder.access, Some(SvdAccess::ReadWrite));
+        assert_eq!(moder.reset_value, Some(0xA800_0000));
+        let fields: Vec<_> = moder
+            .fields
+            .iter()
+            .map(|f| (f.name.as_str(), f.bit_offset, f.bit_width))
+            .collect();
+        assert_eq!(fields, vec![("MODER0", 0, 2), ("MODER1", 2, 2)]);
+
+        let idr = gpioa.register("IDR").unwrap();
+        assert_eq!(idr.reset_value, Some(0));
+        assert_eq!(idr.fields[1].bit_offset, 1);
+        assert_eq!(idr.fields[1].bit_width, 1);
+    }
+
+    #[test]
+    fn test_derived_peripherals_and_clusters() {
+        let device = parse_svd(SVD).unwrap();
+        let gpiob = device.peripheral("GPIOB").unwrap();
+        assert_eq!(gpiob.group.as_deref(), Some("GPIO"));
+        assert_eq!(gpiob.registers.len(), 2);
+
+        let dma = device.peripheral("DMA1").unwrap();
+        let registers: Vec<_> = dma
+            .registers
+            .iter()
+            .map(|r| (r.name.as_str(), r.offset))
+            .collect();
+        assert_eq!(
+            registers,
+            vec![
+                ("S0.CR", 0x10),
+                ("S0.NDTR", 0x14),
+                ("S1.CR", 0x28),
+                ("S1.NDTR", 0x2C)
+            ]
+        );
+        assert_eq!(dma.interrupts[0].value, 11);
+    }
+
+    #[test]
+    fn test_decode_register_value() {
+        let device = parse_svd(SVD).unwrap();
+        let decoded = device.decode(0x4002_0400, 0b1101).unwrap();
+        assert_eq!(decoded.peripheral, "GPIOB");
+        assert_eq!(decoded.register, "MODER");
+        assert_eq!(decoded.fields[0].value, 1);
+        assert_eq!(decoded.fields[0].meaning.as_deref(), Some("Output"));
+        assert_eq!(decoded.fields[1].value, 3);
+        assert_eq!(decoded.fields[1].meaning.as_deref(), Some("Other"));
+
+        assert!(device.decode(0x4002_0004, 0).is_none());
+        let addresses: Vec<_> = device.registers().map(|(a, _, _)| a).take(2).collect();
+        assert_eq!(addresses, vec![0x4002_0000, 0x4002_0010]);
+    }
+
+    #[test]
+    fn test_enum_value_formats() {
+        assert_eq!(parse_enum_value("#101"), Some(5));
+        assert_eq!(parse_enum_value("0x1F"), Some(31));
+        assert_eq!(parse_enum_value("0b1x"), None);
+    }
+
+    #[test]
+    fn test_invalid_svd() {
+        assert!(matches!(parse_svd("<device/>"), Err(SvdError::Invalid(_))));
+        assert!(matches!(parse_svd("<device"), Err(SvdError::Xml { .. })));
+    }
+}
@@ -0,0 +1,1508 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Tool usage log.
+//!
+//! Every qualified tool invocation is appended to
+//! `.axiom/tool-usage.jsonl` in the project root: which binary ran, its
+//! version, arguments, exit code and duration. The log is the usage
+//! evidence for DO-330 qualification data, summarized per tool by
+//! [`tool_usage_statistics`].
+//!
+//! Compile, link and objcopy requests given a logger with `with_usage_log`,
+//! and make dry runs given one, log themselves with SHA-256 checksums of
+//! their input and output files.
+//!
+//! The log is hash-chained: each record carries the SHA-256 of the
+//! previous line as written, starting from [`GENESIS_HASH`], and a head
+//! file beside the log (`tool-usage.head`) holds the record count and the
+//! hash of the last line. [`ToolQualificationLogger::verify_integrity`]
+//! recomputes the chain, so editing, inserting or removing a record breaks
+//! a link, and truncating the tail disagrees with the head. Rewriting the
+//! whole log and head together is not detectable this way; signing covers
+//! that.
+//!
+//! A third file, `tool-usage.idx`, indexes each record's position, tool,
+//! time, exit code and files, so [`ToolQualificationLogger::query`] scans
+//! the small index and reads only the matching records. The index is
+//! appended to with the log and rebuilt from the log when it falls out of
+//! step.
+//!
+//! [`ToolQualificationLogger::verify_outputs`] rehashes the files the log
+//! says tools wrote, catching outputs edited after the build or rebuilt
+//! without logging.
+//!
+//! With a [`RotationPolicy`], a log that grows past a size or age is
+//! rotated into a gzip-compressed segment under `.axiom/tool-usage/`, listed
+//! in `tool-usage.manifest.json` with its record count, first and last
+//! chain hashes and file checksum. The chain runs on across segments into
+//! the active log, so verification still covers every record ever logged.
+
+use crate::sha256_file;
+use axiom_core::time::unix_now;
+use axiom_core::Diagnostic;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Error type for the tool usage log.
+#[derive(Debug, thiserror::Error)]
+pub enum ToolLogError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Malformed tool usage record on line {line}: {source}")]
+    Parse {
+        line: usize,
+        source: serde_json::Error,
+    },
+
+    #[error("JSON serialize error: {0}")]
+    Serialize(#[from] serde_json::Error),
+
+    #[error("Tool usage manifest version {0} is newer than supported")]
+    UnsupportedVersion(u32),
+}
+
+/// Previous-record hash of the first record.
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Current segment manifest format version.
+pub const LOG_MANIFEST_VERSION: u32 = 1;
+
+fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// A file a tool read or wrote, with its checksum at the time.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileChecksum {
+    /// File path, as passed to the tool.
+    pub path: PathBuf,
+    /// SHA-256 in lowercase hex; `None` if the file could not be read
+    /// (e.g. an output the failed tool never wrote).
+    pub sha256: Option<String>,
+}
+
+impl FileChecksum {
+    /// Checksum a file as it is now.
+    pub fn of(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            sha256: sha256_file(path).ok(),
+        }
+    }
+}
+
+/// One tool invocation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolUsageRecord {
+    /// Run time (seconds since the Unix epoch).
+    pub timestamp: u64,
+    /// Tool name (binary file name).
+    pub tool: String,
+    /// Version the tool reports.
+    pub version: String,
+    /// Path of the binary.
+    pub path: PathBuf,
+    /// Command-line arguments.
+    pub arguments: Vec<String>,
+    /// Exit code; -1 if the tool did not run to completion.
+    pub exit_code: i32,
+    /// Run time in milliseconds.
+    pub duration_ms: u64,
+    /// Files the tool read.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inputs: Vec<FileChecksum>,
+    /// Files the tool wrote.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub outputs: Vec<FileChecksum>,
+    /// SHA-256 of the previous log line; set when the record is logged.
+    #[serde(default)]
+    pub previous_hash: String,
+}
+
+impl ToolUsageRecord {
+    /// Record an invocation of the binary at `path`.
+    pub fn new(timestamp: u64, path: &Path, version: impl Into<String>) -> Self {
+        Self {
+            timestamp,
+            tool: path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            version: version.into(),
+            path: path.to_path_buf(),
+            arguments: Vec::new(),
+            exit_code: 0,
+            duration_ms: 0,
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            previous_hash: String::new(),
+        }
+    }
+
+    /// Set the arguments.
+    pub fn with_arguments(mut self, arguments: Vec<String>) -> Self {
+        self.arguments = arguments;
+        self
+    }
+
+    /// Set the exit code and duration.
+    pub fn with_result(mut self, exit_code: i32, duration_ms: u64) -> Self {
+        self.exit_code = exit_code;
+        self.duration_ms = duration_ms;
+        self
+    }
+
+    /// Checksum the files the tool read.
+    pub fn with_inputs<P: AsRef<Path>>(mut self, paths: impl IntoIterator<Item = P>) -> Self {
+        self.inputs = paths
+            .into_iter()
+            .map(|p| FileChecksum::of(p.as_ref()))
+            .collect();
+        self
+    }
+
+    /// Checksum the files the tool wrote.
+    pub fn with_outputs<P: AsRef<Path>>(mut self, paths: impl IntoIterator<Item = P>) -> Self {
+        self.outputs = paths
+            .into_iter()
+            .map(|p| FileChecksum::of(p.as_ref()))
+            .collect();
+        self
+    }
+}
+
+/// Log an invocation of `program` that just finished, if there is a log.
+pub(crate) fn log_invocation(
+    log: Option<&ToolQualificationLogger>,
+    program: &Path,
+    version: &str,
+    record: impl FnOnce(ToolUsageRecord) -> ToolUsageRecord,
+) -> Result<(), ToolLogError> {
+    match log {
+        Some(log) => log.log(&record(ToolUsageRecord::new(unix_now(), program, version))),
+        None => Ok(()),
+    }
+}
+
+/// Warning for a build whose tool usage could not be logged: the tool's own
+/// result stands, but its usage evidence is missing.
+pub(crate) fn unlogged_usage(error: ToolLogError) -> Diagnostic {
+    Diagnostic::warning(format!("Tool usage not logged: {}", error))
+}
+
+/// Filter for [`ToolQualificationLogger::query`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolUsageQuery {
+    /// Only this tool (binary file name).
+    #[serde(default)]
+    pub tool: Option<String>,
+    /// Only invocations at or after this timestamp.
+    #[serde(default)]
+    pub since: Option<u64>,
+    /// Only invocations before this timestamp.
+    #[serde(default)]
+    pub until: Option<u64>,
+    /// Only invocations with this exit code.
+    #[serde(default)]
+    pub exit_code: Option<i32>,
+    /// Only invocations that read this file.
+    #[serde(default)]
+    pub input: Option<PathBuf>,
+    /// Only invocations that wrote this file.
+    #[serde(default)]
+    pub output: Option<PathBuf>,
+}
+
+impl ToolUsageQuery {
+    /// Only this tool.
+    pub fn with_tool(mut self, tool: impl Into<String>) -> Self {
+        self.tool = Some(tool.into());
+        self
+    }
+
+    /// Only invocations in `[since, until)`.
+    pub fn with_range(mut self, since: Option<u64>, until: Option<u64>) -> Self {
+        self.since = since;
+        self.until = until;
+        self
+    }
+
+    /// Only invocations with this exit code.
+    pub fn with_exit_code(mut self, exit_code: i32) -> Self {
+        self.exit_code = Some(exit_code);
+        self
+    }
+
+    /// Only invocations that read this file.
+    pub fn with_input(mut self, path: impl Into<PathBuf>) -> Self {
+        self.input = Some(path.into());
+        self
+    }
+
+    /// Only invocations that wrote this file.
+    pub fn with_output(mut self, path: impl Into<PathBuf>) -> Self {
+        self.output = Some(path.into());
+        self
+    }
+
+    /// Whether an indexed record matches the query.
+    fn matches(&self, entry: &IndexEntry) -> bool {
+        // Recorded paths are as passed to the tool, so either side may be
+        // the longer one
+        let names = |query: &Option<PathBuf>, paths: &[PathBuf]| {
+            query.as_ref().is_none_or(|query| {
+                paths
+                    .iter()
+                    .any(|path| path.ends_with(query) || query.ends_with(path))
+            })
+        };
+        self.tool.as_ref().is_none_or(|tool| *tool == entry.tool)
+            && self.since.is_none_or(|since| entry.timestamp >= since)
+            && self.until.is_none_or(|until| entry.timestamp < until)
+            && self.exit_code.is_none_or(|code| code == entry.exit_code)
+            && names(&self.input, &entry.inputs)
+            && names(&self.output, &entry.outputs)
+    }
+}
+
+/// Where a record is in the log and what it can be queried by.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct IndexEntry {
+    /// Line number in the log (1-based).
+    line: usize,
+    /// Byte offset of the line.
+    offset: u64,
+    /// Length of the line, without the newline.
+    length: usize,
+    timestamp: u64,
+    tool: String,
+    exit_code: i32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    inputs: Vec<PathBuf>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    outputs: Vec<PathBuf>,
+}
+
+impl IndexEntry {
+    fn new(line: usize, offset: u64, length: usize, record: &ToolUsageRecord) -> Self {
+        let paths = |files: &[FileChecksum]| files.iter().map(|f| f.path.clone()).collect();
+        Self {
+            line,
+            offset,
+            length,
+            timestamp: record.timestamp,
+            tool: record.tool.clone(),
+            exit_code: record.exit_code,
+            inputs: paths(&record.inputs),
+            outputs: paths(&record.outputs),
+        }
+    }
+}
+
+/// State of a logged output file now.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputStatus {
+    /// Matches the checksum logged when it was written.
+    Unchanged,
+    /// Differs from the logged checksum.
+    Modified,
+    /// No longer exists.
+    Missing,
+}
+
+impl OutputStatus {
+    /// Serialized name.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OutputStatus::Unchanged => "unchanged",
+            OutputStatus::Modified => "modified",
+            OutputStatus::Missing => "missing",
+        }
+    }
+}
+
+/// A logged output checked against the file on disk.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutputVerification {
+    /// Output path, as logged.
+    pub path: PathBuf,
+    /// Tool of the last logged invocation that wrote it.
+    pub tool: String,
+    /// When that invocation ran.
+    pub timestamp: u64,
+    /// Segment holding that invocation, if rotated out of the active log.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub segment: Option<String>,
+    /// Line of that invocation in its segment or the active log.
+    pub line: usize,
+    /// Checksum logged then.
+    pub expected: String,
+    /// Checksum now; `None` if the file is missing.
+    pub found: Option<String>,
+    /// Comparison result.
+    pub status: OutputStatus,
+}
+
+/// Result of [`ToolQualificationLogger::verify_outputs`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutputReport {
+    /// Every logged output, by path.
+    pub outputs: Vec<OutputVerification>,
+}
+
+impl OutputReport {
+    /// Outputs that changed or disappeared since they were logged.
+    pub fn mismatches(&self) -> Vec<&OutputVerification> {
+        self.outputs
+            .iter()
+            .filter(|o| o.status != OutputStatus::Unchanged)
+            .collect()
+    }
+}
+
+/// When to rotate the active log into a compressed segment.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RotationPolicy {
+    /// Rotate once the active log reaches this many bytes.
+    #[serde(default)]
+    pub max_bytes: Option<u64>,
+    /// Rotate once the oldest active record is this many seconds old.
+    #[serde(default)]
+    pub max_age_secs: Option<u64>,
+}
+
+impl RotationPolicy {
+    /// Rotate at a size.
+    pub fn with_max_bytes(mut self, bytes: u64) -> Self {
+        self.max_bytes = Some(bytes);
+        self
+    }
+
+    /// Rotate at an age.
+    pub fn with_max_age_secs(mut self, secs: u64) -> Self {
+        self.max_age_secs = Some(secs);
+        self
+    }
+}
+
+/// A rotated part of the log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogSegment {
+    /// File name in the segment directory.
+    pub file: String,
+    /// Number of records.
+    pub records: usize,
+    /// Timestamp of the first record.
+    pub first_timestamp: u64,
+    /// Timestamp of the last record.
+    pub last_timestamp: u64,
+    /// Previous hash of the first record: the end hash of the segment
+    /// before, or [`GENESIS_HASH`].
+    pub start_hash: String,
+    /// SHA-256 of the last line.
+    pub end_hash: String,
+    /// SHA-256 of the compressed file.
+    pub sha256: String,
+}
+
+/// Rotated segments, oldest first.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogManifest {
+    /// Format version.
+    pub version: u32,
+    /// Segments in log order.
+    pub segments: Vec<LogSegment>,
+}
+
+impl Default for LogManifest {
+    fn default() -> Self {
+        Self {
+            version: LOG_MANIFEST_VERSION,
+            segments: Vec::new(),
+        }
+    }
+}
+
+impl LogManifest {
+    /// Hash the active log's chain starts from.
+    fn end_hash(&self) -> String {
+        self.segments
+            .last()
+            .map_or(GENESIS_HASH.to_string(), |s| s.end_hash.clone())
+    }
+}
+
+fn parse_record(line: usize, content: &str) -> Result<ToolUsageRecord, ToolLogError> {
+    serde_json::from_str(content).map_err(|source| ToolLogError::Parse { line, source })
+}
+
+/// Record count and last line hash of a log, kept beside it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogHead {
+    /// Number of records.
+    pub records: usize,
+    /// SHA-256 of the last line, or [`GENESIS_HASH`] when empty.
+    pub hash: String,
+}
+
+/// A defect found by [`ToolQualificationLogger::verify_integrity`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum IntegrityIssue {
+    /// A line that is not a valid record.
+    Malformed { line: usize },
+    /// A record whose previous hash doesn't match the line before it: that
+    /// line was modified, or records were inserted or removed here.
+    BrokenChain {
+        line: usize,
+        expected: String,
+        found: String,
+    },
+    /// A segment in the manifest whose file is gone.
+    SegmentMissing { segment: String },
+    /// A segment file that doesn't match its manifest checksum or can't be
+    /// decompressed.
+    SegmentModified { segment: String },
+    /// A segment whose records don't match its manifest entry: a broken
+    /// chain inside it or at its start, or the wrong count or end hash.
+    SegmentMismatch { segment: String },
+    /// Fewer records than the head recorded: the log was truncated.
+    Truncated { expected: usize, found: usize },
+    /// The head disagrees with the log: the last record was modified or
+    /// records were appended without the logger.
+    HeadMismatch { expected: String, found: String },
+    /// A non-empty log with no head file.
+    MissingHead,
+}
+
+/// Result of verifying a log's hash chain.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IntegrityReport {
+    /// Records checked, in segments and the active log.
+    pub records: usize,
+    /// Hash of the last line.
+    pub head: String,
+    /// Defects, in log order.
+    pub issues: Vec<IntegrityIssue>,
+}
+
+impl IntegrityReport {
+    /// Whether the log is intact.
+    pub fn is_intact(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// Append-only tool usage log backed by a JSON Lines file.
+#[derive(Debug, Clone)]
+pub struct ToolQualificationLogger {
+    path: PathBuf,
+    rotation: Option<RotationPolicy>,
+}
+
+impl ToolQualificationLogger {
+    /// Create a logger backed by the given file.
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            rotation: None,
+        }
+    }
+
+    /// Rotate the active log into a segment when the policy says so, before
+    /// logging a record.
+    pub fn with_rotation(mut self, policy: RotationPolicy) -> Self {
+        self.rotation = Some(policy);
+        self
+    }
+
+    /// Create the logger for a project root.
+    pub fn for_project(project_root: &Path) -> Self {
+        Self::new(project_root.join(".axiom").join("tool-usage.jsonl"))
+    }
+
+    /// Path of the backing file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Path of the head file.
+    pub fn head_path(&self) -> PathBuf {
+        self.path.with_extension("head")
+    }
+
+    /// Path of the index file.
+    pub fn index_path(&self) -> PathBuf {
+        self.path.with_extension("idx")
+    }
+
+    /// Directory of rotated segments.
+    pub fn segment_dir(&self) -> PathBuf {
+        self.path.with_extension("")
+    }
+
+    /// Path of the segment manifest.
+    pub fn manifest_path(&self) -> PathBuf {
+        self.path.with_extension("manifest.json")
+    }
+
+    /// Rotated segments; empty if the log was never rotated.
+    pub fn manifest(&self) -> Result<LogManifest, ToolLogError> {
+        let path = self.manifest_path();
+        if !path.is_file() {
+            return Ok(LogManifest::default());
+        }
+        let manifest: LogManifest = serde_json::from_str(&fs::read_to_string(&path)?)
+            .map_err(|source| ToolLogError::Parse { line: 1, source })?;
+        if manifest.version > LOG_MANIFEST_VERSION {
+            return Err(ToolLogError::UnsupportedVersion(manifest.version));
+        }
+        Ok(manifest)
+    }
+
+    /// Lines of a segment, decompressed as they are read.
+    fn segment_lines(
+        &self,
+        segment: &LogSegment,
+    ) -> Result<impl Iterator<Item = std::io::Result<String>>, ToolLogError> {
+        let file = File::open(self.segment_dir().join(&segment.file))?;
+        Ok(BufReader::new(GzDecoder::new(file)).lines())
+    }
+
+    /// Whether the policy calls for rotating an active log with `head`.
+    fn rotation_due(&self, head: &LogHead) -> Result<bool, ToolLogError> {
+        let Some(policy) = self.rotation else {
+            return Ok(false);
+        };
+        if head.records == 0 {
+            return Ok(false);
+        }
+        if let Some(max_bytes) = policy.max_bytes {
+            if fs::metadata(&self.path)?.len() >= max_bytes {
+                return Ok(true);
+            }
+        }
+        if let Some(max_age) = policy.max_age_secs {
+            let mut first = String::new();
+            BufReader::new(File::open(&self.path)?).read_line(&mut first)?;
+            let oldest = parse_record(1, first.trim_end())?.timestamp;
+            if unix_now().saturating_sub(oldest) >= max_age {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Move the active log into a new compressed segment, continuing the
+    /// chain from its last record. Returns the segment, or `None` if the
+    /// active log is empty.
+    pub fn rotate(&self) -> Result<Option<LogSegment>, ToolLogError> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+        let mut manifest = self.manifest()?;
+        let dir = self.segment_dir();
+        fs::create_dir_all(&dir)?;
+        let file = format!("{:06}.jsonl.gz", manifest.segments.len() + 1);
+        let temporary = dir.join(format!("{}.tmp", file));
+
+        let mut encoder = GzEncoder::new(File::create(&temporary)?, Compression::default());
+        let mut segment: Option<LogSegment> = None;
+        let reader = BufReader::new(File::open(&self.path)?);
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record = parse_record(index + 1, &line)?;
+            writeln!(encoder, "{}", line)?;
+            let segment = segment.get_or_insert_with(|| LogSegment {
+                file: file.clone(),
+                records: 0,
+                first_timestamp: record.timestamp,
+                last_timestamp: record.timestamp,
+                start_hash: record.previous_hash.clone(),
+                end_hash: String::new(),
+                sha256: String::new(),
+            });
+            segment.records += 1;
+            segment.last_timestamp = record.timestamp;
+            segment.end_hash = sha256_hex(line.as_bytes());
+        }
+        encoder.finish()?;
+        let Some(mut segment) = segment else {
+            fs::remove_file(&temporary)?;
+            return Ok(None);
+        };
+        fs::rename(&temporary, dir.join(&file))?;
+        segment.sha256 = sha256_file(&dir.join(&file))?;
+
+        manifest.segments.push(segment.clone());
+        let manifest_path = self.manifest_path();
+        let temporary = manifest_path.with_extension("json.tmp");
+        fs::write(&temporary, serde_json::to_string_pretty(&manifest)? + "\n")?;
+        fs::rename(&temporary, &manifest_path)?;
+
+        let head = LogHead {
+            records: 0,
+            hash: segment.end_hash.clone(),
+        };
+        fs::write(self.head_path(), serde_json::to_string(&head)?)?;
+        fs::remove_file(&self.path)?;
+        if self.index_path().exists() {
+            fs::remove_file(self.index_path())?;
+        }
+        Ok(Some(segment))
+    }
+
+    /// Current head: from the head file, or recomputed from the log when
+    /// there is none.
+    fn head(&self) -> Result<LogHead, ToolLogError> {
+        let head_path = self.head_path();
+        if head_path.is_file() {
+            let content = fs::read_to_string(&head_path)?;
+            return serde_json::from_str(&content)
+                .map_err(|source| ToolLogError::Parse { line: 1, source });
+        }
+        let lines = self.lines()?;
+        Ok(LogHead {
+            records: lines.len(),
+            hash: match lines.last() {
+                Some((_, line)) => sha256_hex(line.as_bytes()),
+                None => self.manifest()?.end_hash(),
+            },
+        })
+    }
+
+    /// Non-blank lines of the log with their line numbers.
+    fn lines(&self) -> Result<Vec<(usize, String)>, ToolLogError> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        Ok(fs::read_to_string(&self.path)?
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| (i + 1, line.to_string()))
+            .collect())
+    }
+
+    /// Append a record, chaining it to the previous one.
+    pub fn log(&self, record: &ToolUsageRecord) -> Result<(), ToolLogError> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut head = self.head()?;
+        if self.rotation_due(&head)? {
+            self.rotate()?;
+            head = self.head()?;
+        }
+        let record = ToolUsageRecord {
+            previous_hash: head.hash,
+            ..record.clone()
+        };
+        let line = serde_json::to_string(&record)?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        let offset = file.metadata()?.len();
+        writeln!(file, "{}", line)?;
+
+        // The logger writes no blank lines, so the record number is the
+        // line number. An index missing earlier records is left for the next
+        // query to rebuild.
+        let index_path = self.index_path();
+        if head.records == 0 || index_path.is_file() {
+            let entry = IndexEntry::new(head.records + 1, offset, line.len(), &record);
+            let mut index = OpenOptions::new()
+                .create(true)
+                .write(true)
+                .append(head.records > 0)
+                .truncate(head.records == 0)
+                .open(&index_path)?;
+            writeln!(index, "{}", serde_json::to_string(&entry)?)?;
+        }
+
+        let head = LogHead {
+            records: head.records + 1,
+            hash: sha256_hex(line.as_bytes()),
+        };
+        fs::write(self.head_path(), serde_json::to_string(&head)?)?;
+        Ok(())
+    }
+
+    /// Recompute the hash chain through every segment and the active log,
+    /// and compare it with the manifest and head file.
+    pub fn verify_integrity(&self) -> Result<IntegrityReport, ToolLogError> {
+        let mut issues = Vec::new();
+        let mut previous = GENESIS_HASH.to_string();
+        let mut records = 0;
+        for segment in self.manifest()?.segments {
+            let name = segment.file.clone();
+            match sha256_file(&self.segment_dir().join(&segment.file)) {
+                Err(_) => issues.push(IntegrityIssue::SegmentMissing { segment: name }),
+                Ok(sha256) if sha256 != segment.sha256 => {
+                    issues.push(IntegrityIssue::SegmentModified { segment: name })
+                }
+                Ok(_) => match self.segment_chain(&segment, &previous) {
+                    Ok(true) => {}
+                    Ok(false) => issues.push(IntegrityIssue::SegmentMismatch { segment: name }),
+                    Err(_) => issues.push(IntegrityIssue::SegmentModified { segment: name }),
+                },
+            }
+            records += segment.records;
+            previous = segment.end_hash;
+        }
+
+        let lines = self.lines()?;
+        for (number, line) in &lines {
+            match serde_json::from_str::<ToolUsageRecord>(line) {
+                Ok(record) if record.previous_hash != previous => {
+                    issues.push(IntegrityIssue::BrokenChain {
+                        line: *number,
+                        expected: previous.clone(),
+                        found: record.previous_hash,
+                    });
+                }
+                Ok(_) => {}
+                Err(_) => issues.push(IntegrityIssue::Malformed { line: *number }),
+            }
+            previous = sha256_hex(line.as_bytes());
+        }
+
+        let head_path = self.head_path();
+        if head_path.is_file() {
+            let head: LogHead = serde_json::from_str(&fs::read_to_string(&head_path)?)
+                .map_err(|source| ToolLogError::Parse { line: 1, source })?;
+            if lines.len() < head.records {
+                issues.push(IntegrityIssue::Truncated {
+                    expected: head.records,
+                    found: lines.len(),
+                });
+            } else if head.hash != previous {
+                issues.push(IntegrityIssue::HeadMismatch {
+                    expected: head.hash,
+                    found: previous.clone(),
+                });
+            }
+        } else if !lines.is_empty() {
+            issues.push(IntegrityIssue::MissingHead);
+        }
+
+        Ok(IntegrityReport {
+            records: records + lines.len(),
+            head: previous,
+            issues,
+        })
+    }
+
+    /// Whether a segment's records chain from `start` and agree with its
+    /// manifest entry.
+    fn segment_chain(&self, segment: &LogSegment, start: &str) -> Result<bool, ToolLogError> {
+        if segment.start_hash != start {
+            return Ok(false);
+        }
+        let mut previous = start.to_string();
+        let mut records = 0;
+        for (index, line) in self.segment_lines(segment)?.enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            if parse_record(index + 1, &line)?.previous_hash != previous {
+                return Ok(false);
+            }
+            previous = sha256_hex(line.as_bytes());
+            records += 1;
+        }
+        Ok(records == segment.records && previous == segment.end_hash)
+    }
+
+    /// Recompute the SHA-256 of every output file in the log and compare it
+    /// with the checksum logged by the last invocation that wrote it.
+    /// Relative paths are resolved against `root`.
+    ///
+    /// The log is read a line at a time; outputs a failed invocation didn't
+    /// write (no logged checksum) don't count as writes.
+    pub fn verify_outputs(&self, root: &Path) -> Result<OutputReport, ToolLogError> {
+        let mut latest: BTreeMap<PathBuf, OutputVerification> = BTreeMap::new();
+        self.for_each_record(|segment, line, record| {
+            for output in record.outputs {
+                let Some(expected) = output.sha256 else {
+                    continue;
+                };
+                latest.insert(
+                    output.path.clone(),
+                    OutputVerification {
+                        path: output.path,
+                        tool: record.tool.clone(),
+                        timestamp: record.timestamp,
+                        segment: segment.map(str::to_string),
+                        line,
+                        expected,
+                        found: None,
+                        status: OutputStatus::Missing,
+                    },
+                );
+            }
+        })?;
+
+        let outputs = latest
+            .into_values()
+            .map(|mut output| {
+                output.found = sha256_file(&root.join(&output.path)).ok();
+                output.status = match &output.found {
+                    None => OutputStatus::Missing,
+                    Some(found) if *found == output.expected => OutputStatus::Unchanged,
+                    Some(_) => OutputStatus::Modified,
+                };
+                output
+            })
+            .collect();
+        Ok(OutputReport { outputs })
+    }
+
+    /// Call `f` with every record, from the oldest segment through the
+    /// active log, with its segment and line number.
+    fn for_each_record(
+        &self,
+        mut f: impl FnMut(Option<&str>, usize, ToolUsageRecord),
+    ) -> Result<(), ToolLogError> {
+        for segment in self.manifest()?.segments {
+            for (index, line) in self.segment_lines(&segment)?.enumerate() {
+                let line = line?;
+                if !line.trim().is_empty() {
+                    f(
+                        Some(&segment.file),
+                        index + 1,
+                        parse_record(index + 1, &line)?,
+                    );
+                }
+            }
+        }
+        if self.path.exists() {
+            for (index, line) in BufReader::new(File::open(&self.path)?).lines().enumerate() {
+                let line = line?;
+                if !line.trim().is_empty() {
+                    f(None, index + 1, parse_record(index + 1, &line)?);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Load all records, rotated ones included, in the order they were
+    /// logged.
+    ///
+    /// A missing file yields an empty list.
+    pub fn load(&self) -> Result<Vec<ToolUsageRecord>, ToolLogError> {
+        let mut records = Vec::new();
+        self.for_each_record(|_, _, record| records.push(record))?;
+        Ok(records)
+    }
+
+    /// Records matching a query, in log order.
+    ///
+    /// In the active log only the index and the matching records are read;
+    /// the index is rebuilt first if it doesn't cover every record. Rotated
+    /// segments are skipped when their time span is outside the query's and
+    /// scanned otherwise.
+    pub fn query(&self, query: &ToolUsageQuery) -> Result<Vec<ToolUsageRecord>, ToolLogError> {
+        let mut matches = Vec::new();
+        for segment in self.manifest()?.segments {
+            if query
+                .since
+                .is_some_and(|since| segment.last_timestamp < since)
+                || query
+                    .until
+                    .is_some_and(|until| segment.first_timestamp >= until)
+            {
+                continue;
+            }
+            for (index, line) in self.segment_lines(&segment)?.enumerate() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let record = parse_record(index + 1, &line)?;
+                if query.matches(&IndexEntry::new(index + 1, 0, line.len(), &record)) {
+                    matches.push(record);
+                }
+            }
+        }
+
+        if !self.path.exists() {
+            return Ok(matches);
+        }
+        let records = self.head()?.records;
+        let entries = match self.scan_index(query)? {
+            Some((indexed, entries)) if indexed == records => entries,
+            _ => {
+                self.rebuild_index()?;
+                self.scan_index(query)?.unwrap_or_default().1
+            }
+        };
+
+        let mut file = File::open(&self.path)?;
+        for entry in entries {
+            file.seek(SeekFrom::Start(entry.offset))?;
+            let mut line = vec![0; entry.length];
+            file.read_exact(&mut line)?;
+            matches.push(
+                serde_json::from_slice(&line).map_err(|source| ToolLogError::Parse {
+                    line: entry.line,
+                    source,
+                })?,
+            );
+        }
+        Ok(matches)
+    }
+
+    /// Number of indexed records and the entries matching a query, or
+    /// `None` without a readable index.
+    fn scan_index(
+        &self,
+        query: &ToolUsageQuery,
+    ) -> Result<Option<(usize, Vec<IndexEntry>)>, ToolLogError> {
+        let file = match File::open(self.index_path()) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let mut indexed = 0;
+        let mut entries = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let Ok(entry) = serde_json::from_str::<IndexEntry>(&line?) else {
+                return Ok(None);
+            };
+            indexed += 1;
+            if query.matches(&entry) {
+                entries.push(entry);
+            }
+        }
+        Ok(Some((indexed, entries)))
+    }
+
+    /// Rewrite the index from the log, a line at a time.
+    pub fn rebuild_index(&self) -> Result<(), ToolLogError> {
+        let mut reader = BufReader::new(File::open(&self.path)?);
+        let index_path = self.index_path();
+        let temporary = index_path.with_extension("idx.tmp");
+        let mut index = BufWriter::new(File::create(&temporary)?);
+
+        let mut offset = 0;
+        let mut line = String::new();
+        for number in 1.. {
+            line.clear();
+            let read = reader.read_line(&mut line)?;
+            if read == 0 {
+                break;
+            }
+            let content = line.trim_end_matches(['\n', '\r']);
+            if !content.trim().is_empty() {
+                let record: ToolUsageRecord =
+                    serde_json::from_str(content).map_err(|source| ToolLogError::Parse {
+                        line: number,
+                        source,
+                    })?;
+                let entry = IndexEntry::new(number, offset, content.len(), &record);
+                writeln!(index, "{}", serde_json::to_string(&entry)?)?;
+            }
+            offset += read as u64;
+        }
+        index.flush()?;
+        drop(index);
+        fs::rename(&temporary, &index_path)?;
+        Ok(())
+    }
+}
+
+/// Usage totals for one tool.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolUsageStats {
+    /// Tool name.
+    pub tool: String,
+    /// Versions seen.
+    pub versions: BTreeSet<String>,
+    /// Number of invocations.
+    pub invocations: u32,
+    /// Invocations with a non-zero exit code.
+    pub failures: u32,
+    /// First invocation.
+    pub first_used: u64,
+    /// Latest invocation.
+    pub last_used: u64,
+}
+
+/// Per-tool usage totals, ordered by tool name.
+pub fn tool_usage_statistics(records: &[ToolUsageRecord]) -> Vec<ToolUsageStats> {
+    let mut stats: BTreeMap<&str, ToolUsageStats> = BTreeMap::new();
+    for record in records {
+        let entry = stats
+            .entry(record.tool.as_str())
+            .or_insert_with(|| ToolUsageStats {
+                tool: record.tool.clone(),
+                versions: BTreeSet::new(),
+                invocations: 0,
+                failures: 0,
+                first_used: record.timestamp,
+                last_used: record.timestamp,
+            });
+        entry.versions.insert(record.version.clone());
+        entry.invocations += 1;
+        if record.exit_code != 0 {
+            entry.failures += 1;
+        }
+        entry.first_used = entry.first_used.min(record.timestamp);
+        entry.last_used = entry.last_used.max(record.timestamp);
+    }
+    stats.into_values().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn record(timestamp: u64, tool: &str, version: &str, exit_code: i32) -> ToolUsageRecord {
+        ToolUsageRecord::new(timestamp, &Path::new("/opt/arm/bin").join(tool), version)
+            .with_arguments(vec!["-c".to_string(), "main.c".to_string()])
+            .with_result(exit_code, 12)
+    }
+
+    #[test]
+    fn test_log_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let logger = ToolQualificationLogger::for_project(dir.path());
+        assert!(logger.load().unwrap().is_empty());
+
+        let first = record(100, "arm-none-eabi-gcc", "13.2", 0);
+        logger.log(&first).unwrap();
+        logger
+            .log(&record(200, "arm-none-eabi-ld", "2.41", 1))
+            .unwrap();
+        let records = logger.load().unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].previous_hash, GENESIS_HASH);
+        assert_eq!(records[0].tool, first.tool);
+        assert_eq!(records[0].arguments, first.arguments);
+
+        std::fs::write(logger.path(), "{}\n").unwrap();
+        assert!(matches!(
+            logger.load(),
+            Err(ToolLogError::Parse { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_file_checksums() {
+        let dir = TempDir::new().unwrap();
+        let source = dir.path().join("main.c");
+        std::fs::write(&source, "int main(void) { return 0; }\n").unwrap();
+        let record = record(100, "arm-none-eabi-gcc", "13.2", 1)
+            .with_inputs([&source])
+            .with_outputs([dir.path().join("main.o")]);
+        assert_eq!(record.inputs[0].sha256, sha256_file(&source).ok());
+        assert_eq!(record.outputs[0].sha256, None);
+
+        let logger = ToolQualificationLogger::for_project(dir.path());
+        logger.log(&record).unwrap();
+        assert_eq!(logger.load().unwrap()[0].inputs, record.inputs);
+
+        // Records without files keep their old form
+        let line = serde_json::to_string(&record.with_inputs(Vec::<PathBuf>::new())).unwrap();
+        assert!(line.contains("outputs") && !line.contains("inputs"));
+    }
+
+    #[test]
+    fn test_query() {
+        let dir = TempDir::new().unwrap();
+        let logger = ToolQualificationLogger::for_project(dir.path());
+        let build = |timestamp, tool, exit_code, input: &str, output: &str| {
+            record(timestamp, tool, "13.2", exit_code)
+                .with_inputs([input])
+                .with_outputs([output])
+        };
+        for record in [
+            build(100, "arm-none-eabi-gcc", 0, "src/main.c", "build/main.o"),
+            build(200, "arm-none-eabi-gcc", 1, "src/uart.c", "build/uart.o"),
+            build(300, "arm-none-eabi-gcc", 0, "build/main.o", "build/app.elf"),
+            build(
+                400,
+                "arm-none-eabi-objcopy",
+                0,
+                "build/app.elf",
+                "build/app.hex",
+            ),
+        ] {
+            logger.log(&record).unwrap();
+        }
+        let timestamps = |query: ToolUsageQuery| -> Vec<u64> {
+            logger
+                .query(&query)
+                .unwrap()
+                .iter()
+                .map(|r| r.timestamp)
+                .collect()
+        };
+
+        assert_eq!(timestamps(ToolUsageQuery::default()), [100, 200, 300, 400]);
+        assert_eq!(
+            timestamps(ToolUsageQuery::default().with_tool("arm-none-eabi-objcopy")),
+            [400]
+        );
+        assert_eq!(
+            timestamps(ToolUsageQuery::default().with_range(Some(200), Some(400))),
+            [200, 300]
+        );
+        assert_eq!(
+            timestamps(ToolUsageQuery::default().with_exit_code(1)),
+            [200]
+        );
+        assert_eq!(
+            timestamps(ToolUsageQuery::default().with_input("main.o")),
+            [300]
+        );
+        assert_eq!(
+            timestamps(ToolUsageQuery::default().with_output("/work/build/app.elf")),
+            [300]
+        );
+
+        // A log without an index, or with a stale one, is reindexed
+        std::fs::remove_file(logger.index_path()).unwrap();
+        logger
+            .log(&build(
+                500,
+                "arm-none-eabi-gcc",
+                0,
+                "src/adc.c",
+                "build/adc.o",
+            ))
+            .unwrap();
+        assert!(!logger.index_path().exists());
+        assert_eq!(
+            timestamps(ToolUsageQuery::default().with_range(Some(300), None)),
+            [300, 400, 500]
+        );
+        assert!(logger.index_path().exists());
+        let full = std::fs::read_to_string(logger.index_path()).unwrap();
+        std::fs::write(logger.index_path(), full.lines().next().unwrap()).unwrap();
+        assert_eq!(timestamps(ToolUsageQuery::default()).len(), 5);
+
+        let empty = TempDir::new().unwrap();
+        let logger = ToolQualificationLogger::for_project(empty.path());
+        assert!(logger.query(&ToolUsageQuery::default()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_verify_outputs() {
+        let dir = TempDir::new().unwrap();
+        let logger = ToolQualificationLogger::for_project(dir.path());
+        let build = dir.path().join("build");
+        std::fs::create_dir_all(&build).unwrap();
+        let write = |name: &str, content: &str| {
+            std::fs::write(build.join(name), content).unwrap();
+            record(100, "arm-none-eabi-gcc", "13.2", 0).with_outputs([build.join(name)])
+        };
+
+        logger.log(&write("main.o", "v1")).unwrap();
+        logger.log(&write("main.o", "v2")).unwrap();
+        logger.log(&write("uart.o", "uart")).unwrap();
+        logger.log(&write("app.elf", "elf")).unwrap();
+        // A failed rebuild that didn't write its output
+        logger
+            .log(&record(200, "arm-none-eabi-gcc", "13.2", 1).with_outputs([build.join("none.o")]))
+            .unwrap();
+        std::fs::write(build.join("uart.o"), "tampered").unwrap();
+        std::fs::remove_file(build.join("app.elf")).unwrap();
+
+        let report = logger.verify_outputs(dir.path()).unwrap();
+        let statuses: Vec<(&str, OutputStatus)> = report
+            .outputs
+            .iter()
+            .map(|o| (o.path.file_name().unwrap().to_str().unwrap(), o.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            [
+                ("app.elf", OutputStatus::Missing),
+                ("main.o", OutputStatus::Unchanged),
+                ("uart.o", OutputStatus::Modified),
+            ]
+        );
+        assert_eq!(report.outputs[1].line, 2);
+        assert_eq!(report.mismatches().len(), 2);
+    }
+
+    fn logged(dir: &TempDir, count: u64) -> ToolQualificationLogger {
+        let logger = ToolQualificationLogger::for_project(dir.path());
+        for i in 0..count {
+            logger
+                .log(&record(i, "arm-none-eabi-gcc", "13.2", 0))
+                .unwrap();
+        }
+        logger
+    }
+
+    fn lines(logger: &ToolQualificationLogger) -> Vec<String> {
+        std::fs::read_to_string(logger.path())
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    fn write_lines(logger: &ToolQualificationLogger, lines: &[String]) {
+        std::fs::write(logger.path(), lines.join("\n") + "\n").unwrap();
+    }
+
+    #[test]
+    fn test_verify_intact_chain() {
+        let dir = TempDir::new().unwrap();
+        let logger = logged(&dir, 3);
+        let report = logger.verify_integrity().unwrap();
+        assert!(report.is_intact(), "{:?}", report.issues);
+        assert_eq!(report.records, 3);
+
+        let empty = TempDir::new().unwrap();
+        let report = ToolQualificationLogger::for_project(empty.path())
+            .verify_integrity()
+            .unwrap();
+        assert!(report.is_intact());
+        assert_eq!(report.head, GENESIS_HASH);
+    }
+
+    #[test]
+    fn test_verify_detects_tampering() {
+        // Modified record
+        let dir = TempDir::new().unwrap();
+        let logger = logged(&dir, 3);
+        let mut tampered = lines(&logger);
+        tampered[1] = tampered[1].replace("\"exit_code\":0", "\"exit_code\":1");
+        write_lines(&logger, &tampered);
+        let issues = logger.verify_integrity().unwrap().issues;
+        assert!(matches!(
+            issues[..],
+            [IntegrityIssue::BrokenChain { line: 3, .. }]
+        ));
+
+        // Modified last record
+        let dir = TempDir::new().unwrap();
+        let logger = logged(&dir, 3);
+        let mut tampered = lines(&logger);
+        tampered[2] = tampered[2].replace("\"duration_ms\":12", "\"duration_ms\":13");
+        write_lines(&logger, &tampered);
+        let issues = logger.verify_integrity().unwrap().issues;
+        assert!(matches!(issues[..], [IntegrityIssue::HeadMismatch { .. }]));
+
+        // Inserted record
+        let dir = TempDir::new().unwrap();
+        let logger = logged(&dir, 3);
+        let mut tampered = lines(&logger);
+        tampered.insert(1, tampered[1].clone());
+        write_lines(&logger, &tampered);
+        let issues = logger.verify_integrity().unwrap().issues;
+        assert!(matches!(
+            issues[0],
+            IntegrityIssue::BrokenChain { line: 3, .. }
+        ));
+
+        // Truncated tail
+        let dir = TempDir::new().unwrap();
+        let logger = logged(&dir, 3);
+        let tampered = lines(&logger);
+        write_lines(&logger, &tampered[..2]);
+        let issues = logger.verify_integrity().unwrap().issues;
+        assert_eq!(
+            issues,
+            [IntegrityIssue::Truncated {
+                expected: 3,
+                found: 2
+            }]
+        );
+
+        // Removed head
+        std::fs::remove_file(logger.head_path()).unwrap();
+        let issues = logger.verify_integrity().unwrap().issues;
+        assert_eq!(issues, [IntegrityIssue::MissingHead]);
+    }
+
+    #[test]
+    fn test_rotation() {
+        let dir = TempDir::new().unwrap();
+        let logger = ToolQualificationLogger::for_project(dir.path());
+        assert_eq!(logger.rotate().unwrap(), None);
+
+        // Rotate once the active log holds more than one record
+        let output = dir.path().join("main.o");
+        std::fs::write(&output, "").unwrap();
+        let log = |logger: &ToolQualificationLogger, timestamp| {
+            logger
+                .log(&record(timestamp, "arm-none-eabi-gcc", "13.2", 0).with_outputs([&output]))
+                .unwrap();
+        };
+        log(&logger, 0);
+        let size = std::fs::metadata(logger.path()).unwrap().len();
+        let logger = logger.with_rotation(RotationPolicy::default().with_max_bytes(size * 3 / 2));
+        for timestamp in [10, 20, 30, 40] {
+            log(&logger, timestamp);
+        }
+        let manifest = logger.manifest().unwrap();
+        let files: Vec<&str> = manifest.segments.iter().map(|s| s.file.as_str()).collect();
+        assert_eq!(files, ["000001.jsonl.gz", "000002.jsonl.gz"]);
+        assert_eq!(manifest.segments[0].records, 2);
+        assert_eq!(manifest.segments[0].start_hash, GENESIS_HASH);
+        assert_eq!(
+            manifest.segments[1].start_hash,
+            manifest.segments[0].end_hash
+        );
+        assert_eq!(manifest.segments[1].first_timestamp, 20);
+        assert!(logger.segment_dir().join("000002.jsonl.gz").is_file());
+        assert_eq!(lines(&logger).len(), 1);
+
+        let report = logger.verify_integrity().unwrap();
+        assert!(report.is_intact(), "{:?}", report.issues);
+        assert_eq!(report.records, 5);
+
+        let timestamps: Vec<u64> = logger.load().unwrap().iter().map(|r| r.timestamp).collect();
+        assert_eq!(timestamps, [0, 10, 20, 30, 40]);
+        let timestamps: Vec<u64> = logger
+            .query(&ToolUsageQuery::default().with_range(Some(10), Some(40)))
+            .unwrap()
+            .iter()
+            .map(|r| r.timestamp)
+            .collect();
+        assert_eq!(timestamps, [10, 20, 30]);
+
+        // An empty active log still chains from the last segment
+        let segment = logger.rotate().unwrap().unwrap();
+        assert_eq!(segment.file, "000003.jsonl.gz");
+        assert!(!logger.path().exists());
+        logger
+            .log(&record(50, "arm-none-eabi-gcc", "13.2", 0))
+            .unwrap();
+        assert!(logger.verify_integrity().unwrap().is_intact());
+
+        let output = &logger.verify_outputs(dir.path()).unwrap().outputs[0];
+        assert_eq!(output.segment.as_deref(), Some("000003.jsonl.gz"));
+        assert_eq!(output.timestamp, 40);
+    }
+
+    #[test]
+    fn test_verify_detects_segment_tampering() {
+        let rotated = |dir: &TempDir| {
+            let logger = logged(dir, 2);
+            logger.rotate().unwrap();
+            logger
+                .log(&record(2, "arm-none-eabi-gcc", "13.2", 0))
+                .unwrap();
+            logger.rotate().unwrap();
+            logger
+                .log(&record(3, "arm-none-eabi-gcc", "13.2", 0))
+                .unwrap();
+            logger
+        };
+        let segment = |name: &str| IntegrityIssue::SegmentModified {
+            segment: name.to_string(),
+        };
+
+        // Modified segment
+        let dir = TempDir::new().unwrap();
+        let logger = rotated(&dir);
+        let path = logger.segment_dir().join("000001.jsonl.gz");
+        let mut content = std::fs::read(&path).unwrap();
+        let last = content.len() - 1;
+        content[last] ^= 1;
+        std::fs::write(&path, content).unwrap();
+        assert_eq!(
+            logger.verify_integrity().unwrap().issues,
+            [segment("000001.jsonl.gz")]
+        );
+
+        // Missing segment
+        let dir = TempDir::new().unwrap();
+        let logger = rotated(&dir);
+        std::fs::remove_file(logger.segment_dir().join("000002.jsonl.gz")).unwrap();
+        assert_eq!(
+            logger.verify_integrity().unwrap().issues,
+            [IntegrityIssue::SegmentMissing {
+                segment: "000002.jsonl.gz".to_string()
+            }]
+        );
+
+        // Segment rewritten along with its manifest checksum
+        let dir = TempDir::new().unwrap();
+        let logger = rotated(&dir);
+        let path = logger.segment_dir().join("000001.jsonl.gz");
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        writeln!(
+            encoder,
+            "{}",
+            serde_json::to_string(&record(0, "gcc", "1", 0)).unwrap()
+        )
+        .unwrap();
+        std::fs::write(&path, encoder.finish().unwrap()).unwrap();
+        let mut manifest = logger.manifest().unwrap();
+        manifest.segments[0].sha256 = sha256_file(&path).unwrap();
+        std::fs::write(
+            logger.manifest_path(),
+            serde_json::to_string(&manifest).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            logger.verify_integrity().unwrap().issues,
+            [IntegrityIssue::SegmentMismatch {
+                segment: "000001.jsonl.gz".to_string()
+            }]
+        );
+
+        // Segment dropped from the manifest
+        let dir = TempDir::new().unwrap();
+        let logger = rotated(&dir);
+        let mut manifest = logger.manifest().unwrap();
+        manifest.segments.remove(0);
+        std::fs::write(
+            logger.manifest_path(),
+            serde_json::to_string(&manifest).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            logger.verify_integrity().unwrap().issues,
+            [IntegrityIssue::SegmentMismatch {
+                segment: "000002.jsonl.gz".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_usage_statistics() {
+        let records = [
+            record(300, "arm-none-eabi-gcc", "13.2", 0),
+            record(100, "arm-none-eabi-gcc", "13.1", 1),
+            record(200, "arm-none-eabi-ld", "2.41", 0),
+        ];
+        let stats = tool_usage_statistics(&records);
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].tool, "arm-none-eabi-gcc");
+        assert_eq!(stats[0].invocations, 2);
+        assert_eq!(stats[0].failures, 1);
+        assert_eq!(stats[0].first_used, 100);
+        assert_eq!(stats[0].last_used, 300);
+        assert_eq!(stats[0].versions.len(), 2);
+    }
+}
@@ -0,0 +1,519 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Semihosting and SWO/ITM console capture.
+//!
+//! OpenOCD redirects semihosting output and the raw SWO stream to TCP
+//! ports. A capture connects to both, decodes ITM packets into per-port
+//! channels, and splits every channel into timestamped lines.
+
+use crate::OpenOcdServer;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{ErrorKind, Read};
+use std::net::TcpStream;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// How often reader threads check for shutdown.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Lines kept per capture before the oldest are dropped.
+const MAX_LINES: usize = 10_000;
+
+/// Error type for trace capture.
+#[derive(Debug, thiserror::Error)]
+pub enum TraceError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("OpenOCD error: {0}")]
+    OpenOcd(#[from] crate::OpenOcdError),
+}
+
+/// An ITM packet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ItmPacket {
+    /// Synchronization.
+    Sync,
+    /// The ITM FIFO overflowed and packets were lost.
+    Overflow,
+    /// Cycles since the previous local timestamp.
+    LocalTimestamp { delta: u32 },
+    /// Software (stimulus port) data, from `ITM_SendChar` and friends.
+    Instrumentation { port: u8, data: Vec<u8> },
+    /// DWT hardware source data (PC samples, data trace, event counters).
+    Hardware { discriminator: u8, data: Vec<u8> },
+}
+
+/// Incremental ITM packet decoder.
+///
+/// Packets may straddle reads; incomplete bytes are kept until the next
+/// [`push`](Self::push).
+#[derive(Debug, Default)]
+pub struct ItmDecoder {
+    pending: Vec<u8>,
+}
+
+impl ItmDecoder {
+    /// Create a decoder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decode as many packets as the buffered bytes allow.
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<ItmPacket> {
+        self.pending.extend_from_slice(bytes);
+        let mut packets = Vec::new();
+        let mut pos = 0;
+        while pos < self.pending.len() {
+            match decode_packet(&self.pending[pos..]) {
+                Decoded::Packet(packet, len) => {
+                    packets.extend(packet);
+                    pos += len;
+                }
+                Decoded::Incomplete => break,
+            }
+        }
+        self.pending.drain(..pos);
+        packets
+    }
+}
+
+enum Decoded {
+    /// A packet (or skipped bytes) and its length.
+    Packet(Option<ItmPacket>, usize),
+    Incomplete,
+}
+
+/// Length of a header's continuation payload: bytes up to and including
+/// the first without bit 7 set.
+fn continuation_len(payload: &[u8], max: usize) -> Option<usize> {
+    payload
+        .iter()
+        .take(max)
+        .position(|b| b & 0x80 == 0)
+        .map(|i| i + 1)
+        .or((payload.len() >= max).then_some(max))
+}
+
+fn decode_packet(bytes: &[u8]) -> Decoded {
+    let header = bytes[0];
+
+    // Sync: at least five zero bytes, then 0x80
+    if header == 0 {
+        let zeros = bytes.iter().take_while(|&&b| b == 0).count();
+        return match bytes.get(zeros) {
+            None => Decoded::Incomplete,
+            Some(0x80) if zeros >= 5 => Decoded::Packet(Some(ItmPacket::Sync), zeros + 1),
+            Some(_) => Decoded::Packet(None, zeros),
+        };
+    }
+    if header == 0x70 {
+        return Decoded::Packet(Some(ItmPacket::Overflow), 1);
+    }
+
+    // Source packets: size in bits 1:0, hardware flag in bit 2, port above
+    let size = match header & 0x03 {
+        0 => 0,
+        1 => 1,
+        2 => 2,
+        _ => 4,
+    };
+    if size > 0 {
+        let Some(data) = bytes.get(1..1 + size) else {
+            return Decoded::Incomplete;
+        };
+        let id = header >> 3;
+        let packet = if header & 0x04 == 0 {
+            ItmPacket::Instrumentation {
+                port: id,
+                data: data.to_vec(),
+            }
+        } else {
+            ItmPacket::Hardware {
+                discriminator: id,
+                data: data.to_vec(),
+            }
+        };
+        return Decoded::Packet(Some(packet), 1 + size);
+    }
+
+    // Local timestamps: low nibble zero
+    if header & 0x0f == 0 {
+        if header & 0x80 == 0 {
+            let delta = u32::from((header >> 4) & 0x07);
+            return Decoded::Packet(Some(ItmPacket::LocalTimestamp { delta }), 1);
+        }
+        let Some(len) = continuation_len(&bytes[1..], 4) else {
+            return Decoded::Incomplete;
+        };
+        let delta = bytes[1..1 + len]
+            .iter()
+            .enumerate()
+            .fold(0u32, |acc, (i, b)| acc | (u32::from(b & 0x7f) << (7 * i)));
+        return Decoded::Packet(Some(ItmPacket::LocalTimestamp { delta }), 1 + len);
+    }
+
+    // Global timestamps and extension packets carry continuation payloads
+    // the console doesn't use
+    let has_payload =
+        matches!(header, 0x94 | 0xb4) || (header & 0x0b == 0x08 && header & 0x80 != 0);
+    if has_payload {
+        let Some(len) = continuation_len(&bytes[1..], 6) else {
+            return Decoded::Incomplete;
+        };
+        return Decoded::Packet(None, 1 + len);
+    }
+    Decoded::Packet(None, 1)
+}
+
+/// Where a line of trace output came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum TraceChannel {
+    /// Semihosting console output.
+    Semihosting,
+    /// An ITM stimulus port.
+    Itm { port: u8 },
+}
+
+/// A line of trace output.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TraceLine {
+    /// Source channel.
+    pub channel: TraceChannel,
+    /// Milliseconds since capture started, when the line completed.
+    pub timestamp_ms: u64,
+    /// Target cycle count from ITM local timestamps, if enabled.
+    pub target_cycles: Option<u64>,
+    /// Text without the line ending.
+    pub text: String,
+}
+
+/// Splits channel output into lines.
+#[derive(Debug)]
+pub struct TraceConsole {
+    started: Instant,
+    partial: BTreeMap<TraceChannel, Vec<u8>>,
+    lines: Vec<TraceLine>,
+    cycles: Option<u64>,
+    /// Lines dropped because the buffer was full.
+    pub dropped: usize,
+}
+
+impl Default for TraceConsole {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TraceConsole {
+    /// Create a console; timestamps count from now.
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            partial: BTreeMap::new(),
+            lines: Vec::new(),
+            cycles: None,
+            dropped: 0,
+        }
+    }
+
+    /// Append raw output to a channel.
+    pub fn write(&mut self, channel: TraceChannel, bytes: &[u8]) {
+        let timestamp_ms = self.started.elapsed().as_millis() as u64;
+        let partial = self.partial.entry(channel).or_default();
+        for &byte in bytes {
+            if byte != b'\n' {
+                partial.push(byte);
+                continue;
+            }
+            let mut text = String::from_utf8_lossy(partial).into_owned();
+            if text.ends_with('\r') {
+                text.pop();
+            }
+            partial.clear();
+            self.lines.push(TraceLine {
+                channel,
+                timestamp_ms,
+                target_cycles: self.cycles,
+                text,
+            });
+        }
+        if self.lines.len() > MAX_LINES {
+            let excess = self.lines.len() - MAX_LINES;
+            self.lines.drain(..excess);
+            self.dropped += excess;
+        }
+    }
+
+    /// Route decoded ITM packets to their port channels.
+    pub fn write_itm(&mut self, packets: &[ItmPacket]) {
+        for packet in packets {
+            match packet {
+                ItmPacket::Instrumentation { port, data } => {
+                    self.write(TraceChannel::Itm { port: *port }, data)
+                }
+                ItmPacket::LocalTimestamp { delta } => {
+                    self.cycles = Some(self.cycles.unwrap_or(0) + u64::from(*delta));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Take the completed lines.
+    pub fn drain(&mut self) -> Vec<TraceLine> {
+        std::mem::take(&mut self.lines)
+    }
+}
+
+/// SWO configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SwoConfig {
+    /// TCP port OpenOCD streams SWO data on.
+    pub port: u16,
+    /// Trace clock (usually the core clock) in Hz.
+    pub trace_clock_hz: u32,
+    /// SWO pin frequency in Hz.
+    pub swo_frequency_hz: u32,
+}
+
+/// What to capture.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TraceConfig {
+    /// TCP port to redirect semihosting output to.
+    #[serde(default)]
+    pub semihosting_port: Option<u16>,
+    /// SWO capture settings.
+    #[serde(default)]
+    pub swo: Option<SwoConfig>,
+}
+
+/// OpenOCD commands enabling semihosting with output on a TCP port.
+pub fn semihosting_commands(port: u16) -> Vec<String> {
+    vec![
+        "arm semihosting enable".to_string(),
+        format!("arm semihosting_redirect tcp {} stdio", port),
+    ]
+}
+
+/// OpenOCD commands streaming SWO over a TCP port, with every stimulus
+/// port enabled.
+pub fn swo_commands(swo: &SwoConfig) -> Vec<String> {
+    vec![
+        format!(
+            "[lindex [tpiu names] 0] configure -protocol uart -output :{} \
+             -traceclk {} -pin-freq {} -formatter 0",
+            swo.port, swo.trace_clock_hz, swo.swo_frequency_hz
+        ),
+        "[lindex [tpiu names] 0] enable".to_string(),
+        "itm ports on".to_string(),
+    ]
+}
+
+/// A running semihosting and SWO capture.
+pub struct TraceCapture {
+    console: Arc<Mutex<TraceConsole>>,
+    stop: Arc<AtomicBool>,
+    readers: Vec<JoinHandle<()>>,
+}
+
+impl TraceCapture {
+    /// Configure OpenOCD and start reading the trace streams.
+    pub fn start(server: &OpenOcdServer, config: &TraceConfig) -> Result<Self, TraceError> {
+        let mut capture = Self {
+            console: Arc::new(Mutex::new(TraceConsole::new())),
+            stop: Arc::new(AtomicBool::new(false)),
+            readers: Vec::new(),
+        };
+
+        if let Some(port) = config.semihosting_port {
+            for command in semihosting_commands(port) {
+                server.command(&command)?;
+            }
+            let stream = connect(port)?;
+            capture.spawn_reader(stream, |console, bytes, _| {
+                console.write(TraceChannel::Semihosting, bytes)
+            });
+        }
+        if let Some(swo) = &config.swo {
+            for command in swo_commands(swo) {
+                server.command(&command)?;
+            }
+            let stream = connect(swo.port)?;
+            capture.spawn_reader(stream, |console, bytes, decoder| {
+                let packets = decoder.push(bytes);
+                console.write_itm(&packets);
+            });
+        }
+        Ok(capture)
+    }
+
+    fn spawn_reader(
+        &mut self,
+        mut stream: TcpStream,
+        handle: impl Fn(&mut TraceConsole, &[u8], &mut ItmDecoder) + Send + 'static,
+    ) {
+        let console = Arc::clone(&self.console);
+        let stop = Arc::clone(&self.stop);
+        self.readers.push(std::thread::spawn(move || {
+            let mut decoder = ItmDecoder::new();
+            let mut buf = [0u8; 4096];
+            while !stop.load(Ordering::Relaxed) {
+                match stream.read(&mut buf) {
+                    Ok(0) => break,
+                    Ok(n) => {
+                        if let Ok(mut console) = console.lock() {
+                            handle(&mut console, &buf[..n], &mut decoder);
+                        }
+                    }
+                    Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+                    Err(_) => break,
+                }
+            }
+        }));
+    }
+
+    /// Take the lines captured since the last call.
+    pub fn drain(&self) -> Vec<TraceLine> {
+        self.console
+            .lock()
+            .map(|mut console| console.drain())
+            .unwrap_or_default()
+    }
+
+    /// Stop reading and wait for the reader threads.
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        for reader in self.readers.drain(..) {
+            let _ = reader.join();
+        }
+    }
+}
+
+impl Drop for TraceCapture {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+fn connect(port: u16) -> Result<TcpStream, TraceError> {
+    let stream = TcpStream::connect(("127.0.0.1", port))?;
+    stream.set_read_timeout(Some(POLL_INTERVAL))?;
+    Ok(stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_instrumentation_packets() {
+        let mut decoder = ItmDecoder::new();
+        // Port 0 byte 'H', port 1 half-word "ok", split mid-packet
+        let packets = decoder.push(&[0x01, b'H', 0x0a]);
+        assert_eq!(
+            packets,
+            vec![ItmPacket::Instrumentation {
+                port: 0,
+                data: vec![b'H']
+            }]
+        );
+        let packets = decoder.push(b"ok");
+        assert_eq!(
+            packets,
+            vec![ItmPacket::Instrumentation {
+                port: 1,
+                data: vec![b'o', b'k']
+            }]
+        );
+    }
+
+    #[test]
+    fn test_decode_sync_overflow_and_timestamps() {
+        let mut decoder = ItmDecoder::new();
+        let packets = decoder.push(&[
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x80, // sync
+            0x70, // overflow
+            0x30, // local timestamp, delta 3
+            0xc0, 0x81, 0x01, // local timestamp, delta 129
+            0x94, 0x81, 0x00, // global timestamp, skipped
+            0x05, 0x12, // hardware source, discriminator 0
+        ]);
+        assert_eq!(
+            packets,
+            vec![
+                ItmPacket::Sync,
+                ItmPacket::Overflow,
+                ItmPacket::LocalTimestamp { delta: 3 },
+                ItmPacket::LocalTimestamp { delta: 129 },
+                ItmPacket::Hardware {
+                    discriminator: 0,
+                    data: vec![0x12]
+                },
+            ]
+        );
+
+        // A timestamp whose payload hasn't arrived yet
+        assert!(decoder.push(&[0xc0, 0x81]).is_empty());
+        assert_eq!(
+            decoder.push(&[0x00]),
+            vec![ItmPacket::LocalTimestamp { delta: 1 }]
+        );
+    }
+
+    #[test]
+    fn test_console_lines() {
+        let mut console = TraceConsole::new();
+        console.write(TraceChannel::Semihosting, b"boot\r\nrea");
+        console.write_itm(&[
+            ItmPacket::LocalTimestamp { delta: 100 },
+            ItmPacket::Instrumentation {
+                port: 2,
+                data: b"adc=12\n".to_vec(),
+            },
+        ]);
+        console.write(TraceChannel::Semihosting, b"dy\n");
+
+        let lines = console.drain();
+        let text: Vec<_> = lines.iter().map(|l| (l.channel, l.text.as_str())).collect();
+        assert_eq!(
+            text,
+            vec![
+                (TraceChannel::Semihosting, "boot"),
+                (TraceChannel::Itm { port: 2 }, "adc=12"),
+                (TraceChannel::Semihosting, "ready"),
+            ]
+        );
+        assert_eq!(lines[0].target_cycles, None);
+        assert_eq!(lines[1].target_cycles, Some(100));
+        assert!(console.drain().is_empty());
+    }
+
+    #[test]
+    fn test_openocd_commands() {
+        assert_eq!(
+            semihosting_commands(4445),
+            vec![
+                "arm semihosting enable",
+                "arm semihosting_redirect tcp 4445 stdio"
+            ]
+        );
+        let commands = swo_commands(&SwoConfig {
+            port: 4446,
+            trace_clock_hz: 84_000_000,
+            swo_frequency_hz: 2_000_000,
+        });
+        assert!(commands[0].contains("-output :4446"));
+        assert!(commands[0].contains("-traceclk 84000000 -pin-freq 2000000"));
+        assert_eq!(commands[2], "itm ports on");
+    }
+}
@@ -3,8 +3,13 @@
 
 //! Toolchain types.
 
+use crate::{
+    BuildProfile, ContainerConfig, EnvironmentCapture, InvocationRecord, OptimizationLevel,
+    ToolQualificationLogger, WarningProfile,
+};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::Command;
 
 /// Kind of toolchain.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -30,6 +35,17 @@ impl std::fmt::Display for ToolchainKind {
     }
 }
 
+/// Where a toolchain's binaries run.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum ToolchainSource {
+    /// Installed on this machine.
+    #[default]
+    Local,
+    /// Inside a container image; paths refer to the image.
+    Container(ContainerConfig),
+}
+
 /// Detected toolchain information.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct DetectedToolchain {
@@ -41,6 +57,9 @@ pub struct DetectedToolchain {
     pub version: String,
     /// Whether this is the bundled version.
     pub bundled: bool,
+    /// Where the binaries run.
+    #[serde(default)]
+    pub source: ToolchainSource,
 }
 
 impl DetectedToolchain {
@@ -51,6 +70,7 @@ impl DetectedToolchain {
             path,
             version,
             bundled: false,
+            source: ToolchainSource::Local,
         }
     }
 
@@ -59,6 +79,105 @@ impl DetectedToolchain {
         self.bundled = true;
         self
     }
+
+    /// Set where the binaries run.
+    pub fn with_source(mut self, source: ToolchainSource) -> Self {
+        self.source = source;
+        self
+    }
+
+    /// Command running the compiler driver with `args`.
+    pub fn command(&self, args: &[String]) -> Command {
+        self.tool_command(&self.path, args)
+    }
+
+    /// Command running one of this toolchain's binaries (the driver or a
+    /// [`sibling_tool`](Self::sibling_tool)) with `args`, inside the
+    /// container for container toolchains.
+    pub fn tool_command(&self, program: &Path, args: &[String]) -> Command {
+        match &self.source {
+            ToolchainSource::Local => {
+                let mut command = Command::new(program);
+                command.args(args);
+                command
+            }
+            ToolchainSource::Container(config) => config.command(program, args),
+        }
+    }
+
+    /// Path of a companion tool installed alongside the compiler.
+    ///
+    /// `arm-none-eabi-gcc` pairs with `arm-none-eabi-objdump`, `gcc-12` with
+    /// `objdump`, and `clang` with `llvm-objdump`.
+    pub fn sibling_tool(&self, tool: &str) -> PathBuf {
+        let name = self
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let sibling = match self.kind {
+            ToolchainKind::Clang => format!("llvm-{}", tool),
+            _ => match name.rfind("gcc") {
+                Some(idx) => format!("{}{}", &name[..idx], tool),
+                None => tool.to_string(),
+            },
+        };
+        self.path.with_file_name(sibling)
+    }
+}
+
+/// Kind of source file, by extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SourceKind {
+    /// C source.
+    C,
+    /// C++ source.
+    Cpp,
+    /// Assembly passed straight to the assembler (`.s`, `.asm`).
+    Assembly,
+    /// Assembly run through the C preprocessor first (`.S`, `.sx`).
+    PreprocessedAssembly,
+}
+
+impl SourceKind {
+    /// Classify a source path. The `.s`/`.S` distinction is case-sensitive,
+    /// as it is for GCC.
+    pub fn from_path(path: &Path) -> Self {
+        let extension = path
+            .extension()
+            .map(|e| e.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        match extension.as_str() {
+            "s" | "asm" => SourceKind::Assembly,
+            "S" | "sx" => SourceKind::PreprocessedAssembly,
+            "cpp" | "cxx" | "cc" | "C" | "c++" => SourceKind::Cpp,
+            _ => SourceKind::C,
+        }
+    }
+
+    /// Whether this is an assembly source.
+    pub fn is_assembly(&self) -> bool {
+        matches!(
+            self,
+            SourceKind::Assembly | SourceKind::PreprocessedAssembly
+        )
+    }
+
+    /// Whether the source goes through the C preprocessor.
+    pub fn is_preprocessed(&self) -> bool {
+        *self != SourceKind::Assembly
+    }
+
+    /// Language for `-x`, given explicitly for assembly so `.asm` files and
+    /// case-insensitive file systems don't depend on the driver's guess.
+    pub fn language(&self) -> Option<&'static str> {
+        match self {
+            SourceKind::Assembly => Some("assembler"),
+            SourceKind::PreprocessedAssembly => Some("assembler-with-cpp"),
+            SourceKind::C | SourceKind::Cpp => None,
+        }
+    }
 }
 
 /// A request to compile source code.
@@ -76,6 +195,12 @@ pub struct CompileRequest {
     pub optimization: u8,
     /// Include debug symbols.
     pub debug: bool,
+    /// Warning profile (optional).
+    pub warning_profile: Option<WarningProfile>,
+    /// Record the invocation environment in the result.
+    pub capture: Option<EnvironmentCapture>,
+    /// Log the invocation for tool qualification.
+    pub usage_log: Option<ToolQualificationLogger>,
 }
 
 impl CompileRequest {
@@ -88,6 +213,9 @@ impl CompileRequest {
             flags: Vec::new(),
             optimization: 0,
             debug: true,
+            warning_profile: None,
+            capture: None,
+            usage_log: None,
         }
     }
 
@@ -114,6 +242,53 @@ impl CompileRequest {
         self.debug = debug;
         self
     }
+
+    /// Set the warning profile.
+    pub fn with_warning_profile(mut self, profile: WarningProfile) -> Self {
+        self.warning_profile = Some(profile);
+        self
+    }
+
+    /// Apply a build profile: its optimization level and debug setting
+    /// replace the request's, and its defines, include paths and flags are
+    /// added.
+    ///
+    /// `-Os` and `-Og` have no numeric level and are passed as flags.
+    pub fn with_profile(mut self, profile: &BuildProfile) -> Self {
+        match profile.optimization {
+            OptimizationLevel::O0 => self.optimization = 0,
+            OptimizationLevel::O1 => self.optimization = 1,
+            OptimizationLevel::O2 => self.optimization = 2,
+            OptimizationLevel::O3 => self.optimization = 3,
+            OptimizationLevel::Os | OptimizationLevel::Og => {
+                self.flags.push(profile.optimization.flag().to_string())
+            }
+        }
+        self.debug = profile.debug;
+        self.flags
+            .extend(profile.defines.iter().map(|d| format!("-D{}", d)));
+        self.flags.extend(
+            profile
+                .include_paths
+                .iter()
+                .map(|p| format!("-I{}", p.display())),
+        );
+        self.flags.extend(profile.cflags.iter().cloned());
+        self
+    }
+
+    /// Record the command line, environment and toolchain hash in the result.
+    pub fn with_environment_capture(mut self, capture: EnvironmentCapture) -> Self {
+        self.capture = Some(capture);
+        self
+    }
+
+    /// Log the invocation, with source and output checksums, to a tool
+    /// usage log.
+    pub fn with_usage_log(mut self, log: ToolQualificationLogger) -> Self {
+        self.usage_log = Some(log);
+        self
+    }
 }
 
 /// Result of a compilation.
@@ -127,8 +302,17 @@ pub struct CompileResult {
     pub stderr: String,
     /// Duration in milliseconds.
     pub duration_ms: u64,
+    /// Whether the compiler was killed for exceeding the tool timeout.
+    #[serde(default)]
+    pub timed_out: bool,
     /// Parsed diagnostics.
     pub diagnostics: Vec<axiom_core::Diagnostic>,
+    /// Warning profile the compile was run with.
+    #[serde(default)]
+    pub warning_profile: Option<WarningProfile>,
+    /// Exact invocation, when environment capture was enabled.
+    #[serde(default)]
+    pub invocation: Option<InvocationRecord>,
 }
 
 impl CompileResult {
@@ -137,3 +321,51 @@ impl CompileResult {
         self.exit_code == 0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sibling_tool() {
+        let arm = DetectedToolchain::new(
+            ToolchainKind::ArmGcc,
+            PathBuf::from("/opt/arm/bin/arm-none-eabi-gcc"),
+            "13.2.1".to_string(),
+        );
+        assert_eq!(
+            arm.sibling_tool("objdump"),
+            PathBuf::from("/opt/arm/bin/arm-none-eabi-objdump")
+        );
+
+        let gcc = DetectedToolchain::new(
+            ToolchainKind::Gcc,
+            PathBuf::from("/usr/bin/gcc-12"),
+            "12.2.0".to_string(),
+        );
+        assert_eq!(gcc.sibling_tool("nm"), PathBuf::from("/usr/bin/nm"));
+
+        let clang = DetectedToolchain::new(
+            ToolchainKind::Clang,
+            PathBuf::from("/usr/bin/clang"),
+            "17.0.6".to_string(),
+        );
+        assert_eq!(
+            clang.sibling_tool("objdump"),
+            PathBuf::from("/usr/bin/llvm-objdump")
+        );
+    }
+
+    #[test]
+    fn test_source_kind() {
+        let kind = |p: &str| SourceKind::from_path(Path::new(p));
+        assert_eq!(kind("src/main.c"), SourceKind::C);
+        assert_eq!(kind("src/driver.cpp"), SourceKind::Cpp);
+        assert_eq!(kind("startup.s"), SourceKind::Assembly);
+        assert_eq!(kind("startup.S"), SourceKind::PreprocessedAssembly);
+        assert_eq!(kind("vectors.sx"), SourceKind::PreprocessedAssembly);
+        assert!(kind("boot.asm").is_assembly());
+        assert!(!kind("boot.asm").is_preprocessed());
+        assert!(kind("main.c").is_preprocessed());
+    }
+}
@@ -0,0 +1,145 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Named warning-flag profiles.
+
+use crate::ToolchainKind;
+use serde::{Deserialize, Serialize};
+
+/// Warnings shared by the strict profiles.
+const STRICT_COMMON: &[&str] = &[
+    "-Wall",
+    "-Wextra",
+    "-Wpedantic",
+    "-Wconversion",
+    "-Wsign-conversion",
+    "-Wundef",
+    "-Wshadow",
+    "-Wcast-qual",
+    "-Wswitch-default",
+    "-Wswitch-enum",
+    "-Wimplicit-fallthrough",
+    "-Wvla",
+    "-Wstrict-prototypes",
+    "-Wmissing-prototypes",
+];
+
+/// Additional warnings for the avionics-strict profile.
+const AVIONICS_EXTRA: &[&str] = &[
+    "-Wcast-align",
+    "-Wdouble-promotion",
+    "-Wformat=2",
+    "-Wnull-dereference",
+    "-Wwrite-strings",
+    "-Werror",
+];
+
+/// Additional warnings for the MISRA-oriented profile.
+const MISRA_EXTRA: &[&str] = &[
+    "-Wold-style-definition",
+    "-Wpointer-arith",
+    "-Wfloat-equal",
+    "-Wmissing-declarations",
+    "-Wredundant-decls",
+];
+
+/// GCC-only warnings appended for strict profiles.
+const GCC_STRICT_EXTRA: &[&str] = &["-Wlogical-op", "-Wduplicated-cond", "-Wduplicated-branches"];
+
+/// A named set of warning flags selectable per project.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WarningProfile {
+    /// Maximum warnings, all treated as errors.
+    AvionicsStrict,
+    /// Warnings that approximate MISRA C:2012 rules the compiler can check.
+    MisraOriented,
+    /// Common warnings only (`-Wall`).
+    Permissive,
+}
+
+impl WarningProfile {
+    /// All profiles, in display order.
+    pub const ALL: [WarningProfile; 3] = [
+        WarningProfile::AvionicsStrict,
+        WarningProfile::MisraOriented,
+        WarningProfile::Permissive,
+    ];
+
+    /// Stable profile name used in settings.
+    pub fn name(&self) -> &'static str {
+        match self {
+            WarningProfile::AvionicsStrict => "avionics-strict",
+            WarningProfile::MisraOriented => "misra-oriented",
+            WarningProfile::Permissive => "permissive",
+        }
+    }
+
+    /// Look up a profile by its settings name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|p| p.name() == name)
+    }
+
+    /// Compiler flags for this profile.
+    pub fn flags(&self, kind: ToolchainKind) -> Vec<String> {
+        let mut flags: Vec<&str> = match self {
+            WarningProfile::AvionicsStrict => [STRICT_COMMON, AVIONICS_EXTRA].concat(),
+            WarningProfile::MisraOriented => [STRICT_COMMON, MISRA_EXTRA].concat(),
+            WarningProfile::Permissive => vec!["-Wall"],
+        };
+
+        let is_gcc = matches!(kind, ToolchainKind::Gcc | ToolchainKind::ArmGcc);
+        if is_gcc && *self != WarningProfile::Permissive {
+            flags.extend_from_slice(GCC_STRICT_EXTRA);
+        }
+
+        flags.into_iter().map(String::from).collect()
+    }
+}
+
+impl std::fmt::Display for WarningProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_name_round_trip() {
+        for profile in WarningProfile::ALL {
+            assert_eq!(WarningProfile::from_name(profile.name()), Some(profile));
+        }
+        assert_eq!(WarningProfile::from_name("lenient"), None);
+    }
+
+    #[test]
+    fn test_avionics_strict_flags() {
+        let flags = WarningProfile::AvionicsStrict.flags(ToolchainKind::Clang);
+        assert!(flags.contains(&"-Wconversion".to_string()));
+        assert!(flags.contains(&"-Wundef".to_string()));
+        assert!(flags.contains(&"-Werror".to_string()));
+        assert!(!flags.contains(&"-Wlogical-op".to_string()));
+    }
+
+    #[test]
+    fn test_gcc_only_flags() {
+        let flags = WarningProfile::MisraOriented.flags(ToolchainKind::ArmGcc);
+        assert!(flags.contains(&"-Wlogical-op".to_string()));
+        assert!(!flags.contains(&"-Werror".to_string()));
+    }
+
+    #[test]
+    fn test_permissive_flags() {
+        let flags = WarningProfile::Permissive.flags(ToolchainKind::Gcc);
+        assert_eq!(flags, vec!["-Wall".to_string()]);
+    }
+
+    #[test]
+    fn test_serde_name() {
+        let json = serde_json::to_string(&WarningProfile::MisraOriented).unwrap();
+        assert_eq!(json, "\"misra-oriented\"");
+    }
+}
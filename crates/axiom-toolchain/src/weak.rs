@@ -0,0 +1,290 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Weak symbol resolution analysis.
+//!
+//! Shows, per weak symbol, which definition the linker kept. The common
+//! case is a startup file that defines every interrupt handler as a weak
+//! alias of `Default_Handler`: a misspelled user handler links cleanly and
+//! the interrupt silently lands in the default loop.
+
+use crate::{ElfError, ElfFile, ElfSymbol, SymbolBinding};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Weak symbol analysis errors.
+#[derive(Debug, Error)]
+pub enum WeakSymbolError {
+    #[error("{path}: {source}")]
+    Elf {
+        path: PathBuf,
+        #[source]
+        source: ElfError,
+    },
+}
+
+/// How a weak symbol was resolved in the final image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WeakResolution {
+    /// A strong definition replaced the weak default.
+    Overridden,
+    /// The weak default is what got linked.
+    Default,
+    /// Only weak references exist; the symbol resolves to zero.
+    Unresolved,
+    /// The symbol is not in the final image (e.g. removed by --gc-sections).
+    Discarded,
+}
+
+/// One definition of a symbol in an input object.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SymbolDefinition {
+    /// Defining object.
+    pub object: PathBuf,
+    /// Binding in that object.
+    pub binding: SymbolBinding,
+}
+
+/// Resolution of one weak symbol.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WeakSymbol {
+    /// Symbol name.
+    pub name: String,
+    /// Outcome.
+    pub resolution: WeakResolution,
+    /// Definitions in link order.
+    pub definitions: Vec<SymbolDefinition>,
+    /// Object whose definition was linked.
+    pub winner: Option<PathBuf>,
+    /// Address in the final image.
+    pub address: Option<u64>,
+    /// Another symbol at the same address (e.g. "Default_Handler").
+    pub alias_of: Option<String>,
+}
+
+/// Weak symbol resolution for a linked image.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WeakSymbolReport {
+    /// Every symbol that is weak in at least one input, sorted by name.
+    pub symbols: Vec<WeakSymbol>,
+}
+
+impl WeakSymbolReport {
+    /// Symbols whose weak default was replaced.
+    pub fn overridden(&self) -> Vec<&WeakSymbol> {
+        self.with_resolution(WeakResolution::Overridden)
+    }
+
+    /// Symbols still using their weak default.
+    pub fn defaults(&self) -> Vec<&WeakSymbol> {
+        self.with_resolution(WeakResolution::Default)
+    }
+
+    /// Handlers (names ending in "Handler") still using their weak default.
+    pub fn default_handlers(&self) -> Vec<&WeakSymbol> {
+        self.defaults()
+            .into_iter()
+            .filter(|s| s.name.ends_with("Handler"))
+            .collect()
+    }
+
+    fn with_resolution(&self, resolution: WeakResolution) -> Vec<&WeakSymbol> {
+        self.symbols
+            .iter()
+            .filter(|s| s.resolution == resolution)
+            .collect()
+    }
+}
+
+/// Analyze weak symbol resolution from the linked objects and final ELF.
+///
+/// `objects` must be in link order.
+pub fn analyze_weak_symbols(
+    objects: &[PathBuf],
+    elf: &Path,
+) -> Result<WeakSymbolReport, WeakSymbolError> {
+    let read = |path: &Path| {
+        ElfFile::read(path).map_err(|source| WeakSymbolError::Elf {
+            path: path.to_path_buf(),
+            source,
+        })
+    };
+    let inputs = objects
+        .iter()
+        .map(|o| Ok((o.clone(), read(o)?)))
+        .collect::<Result<Vec<_>, WeakSymbolError>>()?;
+    Ok(resolve_weak_symbols(&inputs, &read(elf)?))
+}
+
+/// Resolve weak symbols from parsed inputs (in link order) and the final image.
+pub fn resolve_weak_symbols(inputs: &[(PathBuf, ElfFile)], image: &ElfFile) -> WeakSymbolReport {
+    let is_global = |s: &&ElfSymbol| s.binding != SymbolBinding::Local && !s.name.is_empty();
+
+    let mut weak_names: Vec<&str> = inputs
+        .iter()
+        .flat_map(|(_, elf)| elf.symbols.iter().filter(is_global))
+        .filter(|s| s.binding == SymbolBinding::Weak)
+        .map(|s| s.name.as_str())
+        .collect();
+    weak_names.sort_unstable();
+    weak_names.dedup();
+
+    let final_symbols: BTreeMap<&str, &ElfSymbol> = image
+        .symbols
+        .iter()
+        .filter(is_global)
+        .map(|s| (s.name.as_str(), s))
+        .collect();
+
+    let symbols = weak_names
+        .into_iter()
+        .map(|name| {
+            let definitions: Vec<SymbolDefinition> = inputs
+                .iter()
+                .filter_map(|(path, elf)| {
+                    elf.symbols
+                        .iter()
+                        .find(|s| s.name == name && !s.is_undefined() && is_global(s))
+                        .map(|s| SymbolDefinition {
+                            object: path.clone(),
+                            binding: s.binding,
+                        })
+                })
+                .collect();
+
+            let linked = final_symbols.get(name).filter(|s| !s.is_undefined());
+            let resolution = match linked {
+                None if final_symbols.contains_key(name) => WeakResolution::Unresolved,
+                None => WeakResolution::Discarded,
+                Some(s) if s.binding == SymbolBinding::Weak => WeakResolution::Default,
+                Some(_) => WeakResolution::Overridden,
+            };
+
+            let winner = match resolution {
+                WeakResolution::Overridden => definitions
+                    .iter()
+                    .find(|d| d.binding != SymbolBinding::Weak),
+                WeakResolution::Default => definitions.first(),
+                _ => None,
+            }
+            .map(|d| d.object.clone());
+
+            let address = linked.map(|s| s.value);
+            let alias_of = address.and_then(|addr| {
+                final_symbols
+                    .values()
+                    .filter(|s| s.value == addr && s.name != name && !s.is_undefined())
+                    .min_by_key(|s| (s.binding == SymbolBinding::Weak, s.name.as_str()))
+                    .map(|s| s.name.clone())
+            });
+
+            WeakSymbol {
+                name: name.to_string(),
+                resolution,
+                definitions,
+                winner,
+                address,
+                alias_of,
+            }
+        })
+        .collect();
+
+    WeakSymbolReport { symbols }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf::{build_test_elf, build_test_symtab};
+    use crate::SHT_SYMTAB;
+
+    const GLOBAL_FUNC: u8 = 0x12;
+    const WEAK_FUNC: u8 = 0x22;
+
+    fn elf(symbols: &[(&str, u32, u32, u8, u16)]) -> ElfFile {
+        let (symtab, strtab) = build_test_symtab(symbols);
+        ElfFile::parse(build_test_elf(&[
+            (".text", 1, &[0u8; 16]),
+            (".symtab", SHT_SYMTAB, &symtab),
+            (".strtab", 3, &strtab),
+        ]))
+        .unwrap()
+    }
+
+    fn report() -> WeakSymbolReport {
+        let startup = elf(&[
+            ("Default_Handler", 0x0, 2, GLOBAL_FUNC, 1),
+            ("SysTick_Handler", 0x0, 2, WEAK_FUNC, 1),
+            ("USART1_IRQHandler", 0x0, 2, WEAK_FUNC, 1),
+            ("SPI1_IRQHandler", 0x0, 2, WEAK_FUNC, 1),
+            ("board_hook", 0x0, 0, 0x20, 0),
+        ]);
+        let main = elf(&[
+            ("main", 0x0, 8, GLOBAL_FUNC, 1),
+            ("SysTick_Handler", 0x8, 4, GLOBAL_FUNC, 1),
+            ("USART1_IRQHandler", 0x0, 0, 0x10, 0),
+        ]);
+        let image = elf(&[
+            ("Default_Handler", 0x0800_0201, 2, GLOBAL_FUNC, 1),
+            ("USART1_IRQHandler", 0x0800_0201, 2, WEAK_FUNC, 1),
+            ("SysTick_Handler", 0x0800_0109, 4, GLOBAL_FUNC, 1),
+            ("main", 0x0800_0101, 8, GLOBAL_FUNC, 1),
+            ("board_hook", 0, 0, 0x20, 0),
+        ]);
+
+        resolve_weak_symbols(
+            &[
+                (PathBuf::from("startup.o"), startup),
+                (PathBuf::from("main.o"), main),
+            ],
+            &image,
+        )
+    }
+
+    fn find<'a>(report: &'a WeakSymbolReport, name: &str) -> &'a WeakSymbol {
+        report.symbols.iter().find(|s| s.name == name).unwrap()
+    }
+
+    #[test]
+    fn test_overridden_handler() {
+        let report = report();
+        let systick = find(&report, "SysTick_Handler");
+        assert_eq!(systick.resolution, WeakResolution::Overridden);
+        assert_eq!(systick.winner, Some(PathBuf::from("main.o")));
+        assert_eq!(systick.definitions.len(), 2);
+        assert_eq!(systick.alias_of, None);
+    }
+
+    #[test]
+    fn test_default_handler_alias() {
+        let report = report();
+        let usart = find(&report, "USART1_IRQHandler");
+        assert_eq!(usart.resolution, WeakResolution::Default);
+        assert_eq!(usart.winner, Some(PathBuf::from("startup.o")));
+        assert_eq!(usart.alias_of.as_deref(), Some("Default_Handler"));
+
+        let names: Vec<_> = report
+            .default_handlers()
+            .iter()
+            .map(|s| s.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["USART1_IRQHandler"]);
+    }
+
+    #[test]
+    fn test_unresolved_and_discarded() {
+        let report = report();
+        assert_eq!(
+            find(&report, "board_hook").resolution,
+            WeakResolution::Unresolved
+        );
+        assert_eq!(
+            find(&report, "SPI1_IRQHandler").resolution,
+            WeakResolution::Discarded
+        );
+        assert!(report.symbols.iter().all(|s| s.name != "main"));
+    }
+}
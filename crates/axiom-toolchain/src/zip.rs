@@ -0,0 +1,100 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Minimal zip reader for stored and deflated entries.
+
+use flate2::read::DeflateDecoder;
+use std::io::Read;
+
+/// An entry in a zip central directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ZipEntry {
+    /// Path inside the archive, `/`-separated.
+    pub name: String,
+    /// Compression method (0 stored, 8 deflate).
+    method: u16,
+    /// Compressed size.
+    compressed: usize,
+    /// Offset of the local header.
+    offset: usize,
+    /// Unix permission bits, when the archive was made on Unix.
+    pub unix_mode: Option<u32>,
+}
+
+impl ZipEntry {
+    /// Whether the entry is a directory.
+    pub fn is_dir(&self) -> bool {
+        self.name.ends_with('/')
+    }
+}
+
+fn u16_at(data: &[u8], at: usize) -> Result<usize, String> {
+    data.get(at..at + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]) as usize)
+        .ok_or_else(|| "truncated archive".to_string())
+}
+
+fn u32_at(data: &[u8], at: usize) -> Result<usize, String> {
+    data.get(at..at + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize)
+        .ok_or_else(|| "truncated archive".to_string())
+}
+
+/// List the entries of a zip archive.
+pub(crate) fn zip_entries(data: &[u8]) -> Result<Vec<ZipEntry>, String> {
+    // End of central directory record, searched backwards past any comment
+    let eocd = (0..data.len().saturating_sub(21))
+        .rev()
+        .find(|&i| data[i..].starts_with(&[0x50, 0x4b, 0x05, 0x06]))
+        .ok_or("not a zip archive")?;
+    let count = u16_at(data, eocd + 10)?;
+    let mut at = u32_at(data, eocd + 16)?;
+
+    let mut entries = Vec::with_capacity(count);
+    for _ in 0..count {
+        if u32_at(data, at)? != 0x0201_4b50 {
+            return Err("corrupt central directory".to_string());
+        }
+        let made_by = u16_at(data, at + 4)? >> 8;
+        let name_len = u16_at(data, at + 28)?;
+        let extra_len = u16_at(data, at + 30)?;
+        let comment_len = u16_at(data, at + 32)?;
+        let external = u32_at(data, at + 38)?;
+        let name = data
+            .get(at + 46..at + 46 + name_len)
+            .ok_or("truncated archive")?;
+        entries.push(ZipEntry {
+            name: String::from_utf8_lossy(name).into_owned(),
+            method: u16_at(data, at + 10)? as u16,
+            compressed: u32_at(data, at + 20)?,
+            offset: u32_at(data, at + 42)?,
+            // Host system 3 is Unix; the mode sits in the high half
+            unix_mode: (made_by == 3 && external >> 16 != 0).then_some((external >> 16) as u32),
+        });
+        at += 46 + name_len + extra_len + comment_len;
+    }
+    Ok(entries)
+}
+
+/// Read an entry's contents.
+pub(crate) fn zip_read(data: &[u8], entry: &ZipEntry) -> Result<Vec<u8>, String> {
+    if u32_at(data, entry.offset)? != 0x0403_4b50 {
+        return Err("corrupt local header".to_string());
+    }
+    let start =
+        entry.offset + 30 + u16_at(data, entry.offset + 26)? + u16_at(data, entry.offset + 28)?;
+    let raw = data
+        .get(start..start + entry.compressed)
+        .ok_or("truncated archive")?;
+    match entry.method {
+        0 => Ok(raw.to_vec()),
+        8 => {
+            let mut content = Vec::new();
+            DeflateDecoder::new(raw)
+                .read_to_end(&mut content)
+                .map_err(|e| format!("cannot inflate {}: {}", entry.name, e))?;
+            Ok(content)
+        }
+        method => Err(format!("unsupported compression method {}", method)),
+    }
+}
@@ -0,0 +1,246 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Analysis command handlers.
+
+use crate::state::AppState;
+use axiom_analysis::{
+    detect_clang_tidy, detect_cppcheck, run_clang_analyzer, run_clang_tidy, run_cppcheck,
+    AnalyzerRequest, AnalyzerResult, CallGraph, ClangTidyRequest, ClangTidyResult,
+    CouplingAnalysis, CouplingCoverageReport, CppcheckRequest, CppcheckResult, ErrorCatalog,
+    GuardFix, GuardReport, RuleSet, StackDepth,
+};
+use axiom_core::Diagnostic;
+use axiom_parser::Language;
+use axiom_toolchain::ToolchainKind;
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+/// Evaluate the project's custom diagnostic rules against a file.
+#[tauri::command]
+pub fn run_custom_rules(
+    state: State<AppState>,
+    project_path: String,
+    file_path: String,
+) -> Result<Vec<Diagnostic>, String> {
+    let path = Path::new(&file_path);
+    let language =
+        Language::from_path(path).ok_or_else(|| format!("Unsupported language: {}", file_path))?;
+    let source = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+
+    let rules = RuleSet::load_project(Path::new(&project_path)).map_err(|e| e.to_string())?;
+
+    let mut parser = state.parser.lock().map_err(|e| e.to_string())?;
+    rules
+        .evaluate(&mut parser, path, &source, language)
+        .map_err(|e| e.to_string())
+}
+
+/// Run clang-tidy over files, using the project's compilation database if present.
+///
+/// Without one, the defines and include paths of the build profile selected
+/// in settings (or the project's `debug` profile) are passed instead.
+#[tauri::command]
+pub fn run_clang_tidy_analysis(
+    state: State<AppState>,
+    project_path: String,
+    file_paths: Vec<String>,
+    checks: Option<String>,
+) -> Result<ClangTidyResult, String> {
+    let clang_tidy = detect_clang_tidy().ok_or("clang-tidy not found")?;
+    let sources = file_paths.into_iter().map(PathBuf::from).collect();
+    let mut request = ClangTidyRequest::new(clang_tidy, sources);
+
+    let root = Path::new(&project_path);
+    if let Some(dir) = [root.to_path_buf(), root.join("build")]
+        .into_iter()
+        .find(|dir| dir.join("compile_commands.json").exists())
+    {
+        request = request.with_compile_commands(dir);
+    } else {
+        let name = {
+            let settings = state.settings.lock().map_err(|e| e.to_string())?;
+            settings.build.profile.clone()
+        };
+        let profile =
+            axiom_toolchain::find_build_profile(Some(root), name.as_deref().unwrap_or("debug"))
+                .map_err(|e| e.to_string())?;
+        for define in &profile.defines {
+            request = request.with_flag(format!("-D{}", define));
+        }
+        for include in &profile.include_paths {
+            request = request.with_flag(format!("-I{}", root.join(include).display()));
+        }
+    }
+    if let Some(checks) = checks {
+        request = request.with_checks(checks);
+    }
+
+    Ok(run_clang_tidy(&request))
+}
+
+/// Run cppcheck over a project with the given defines and include paths.
+#[tauri::command]
+pub fn run_cppcheck_analysis(
+    project_path: String,
+    defines: Vec<String>,
+    include_paths: Vec<String>,
+) -> Result<CppcheckResult, String> {
+    let cppcheck = detect_cppcheck().ok_or("cppcheck not found")?;
+    let mut request = CppcheckRequest::new(cppcheck, vec![PathBuf::from(&project_path)]);
+    request.defines = defines;
+    request.include_paths = include_paths.into_iter().map(PathBuf::from).collect();
+
+    Ok(run_cppcheck(&request))
+}
+
+/// Run the Clang Static Analyzer on each file.
+#[tauri::command]
+pub fn run_static_analyzer(
+    state: State<AppState>,
+    file_paths: Vec<String>,
+    flags: Vec<String>,
+) -> Result<Vec<AnalyzerResult>, String> {
+    let clang = {
+        let toolchains = state.toolchains.lock().map_err(|e| e.to_string())?;
+        toolchains
+            .iter()
+            .find(|t| t.kind == ToolchainKind::Clang)
+            .map(|t| t.path.clone())
+            .ok_or("Clang toolchain not found")?
+    };
+
+    // Private to this run so concurrent runs don't overwrite each other
+    let out_dir = tempfile::TempDir::new().map_err(|e| e.to_string())?;
+
+    Ok(file_paths
+        .iter()
+        .enumerate()
+        .map(|(i, file)| {
+            let plist = out_dir.path().join(format!("tu-{}.plist", i));
+            let mut request = AnalyzerRequest::new(clang.clone(), PathBuf::from(file), plist);
+            request.flags = flags.clone();
+            run_clang_analyzer(&request)
+        })
+        .collect())
+}
+
+/// Check include guards across a project's headers.
+#[tauri::command]
+pub fn check_include_guards(project_path: String) -> Result<GuardReport, String> {
+    axiom_analysis::check_include_guards(Path::new(&project_path)).map_err(|e| e.to_string())
+}
+
+/// Preview a path-derived include guard fix for a header.
+#[tauri::command]
+pub fn plan_guard_fix(project_path: String, file_path: String) -> Result<Option<GuardFix>, String> {
+    let path = Path::new(&file_path);
+    let source = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    Ok(axiom_analysis::plan_guard_fix(
+        Path::new(&project_path),
+        path,
+        &source,
+    ))
+}
+
+/// Apply a previewed include guard fix.
+#[tauri::command]
+pub fn apply_guard_fix(fix: GuardFix) -> Result<(), String> {
+    axiom_analysis::apply_guard_fix(&fix).map_err(|e| e.to_string())
+}
+
+/// Compute worst-case stack depth per entry point from `.su` files in a build directory.
+#[tauri::command]
+pub fn analyze_stack_usage(
+    build_dir: String,
+    call_graph: CallGraph,
+    entries: Vec<String>,
+) -> Result<Vec<StackDepth>, String> {
+    let frames =
+        axiom_analysis::load_stack_usage(Path::new(&build_dir)).map_err(|e| e.to_string())?;
+    Ok(axiom_analysis::worst_case_stack(
+        &frames,
+        &call_graph,
+        &entries,
+    ))
+}
+
+/// Extract the project's assert/error-code catalog.
+#[tauri::command]
+pub fn extract_error_catalog(project_path: String) -> Result<ErrorCatalog, String> {
+    let root = Path::new(&project_path);
+    let patterns = axiom_analysis::load_catalog_patterns(root).map_err(|e| e.to_string())?;
+    axiom_analysis::extract_catalog(root, &patterns).map_err(|e| e.to_string())
+}
+
+/// Export the error-code catalog as "csv" or "markdown".
+#[tauri::command]
+pub fn export_error_catalog(
+    project_path: String,
+    format: String,
+    output_path: String,
+) -> Result<(), String> {
+    let catalog = extract_error_catalog(project_path)?;
+    let content = match format.to_lowercase().as_str() {
+        "csv" => catalog.to_csv(),
+        "markdown" | "md" => catalog.to_markdown(),
+        other => return Err(format!("Unsupported catalog format: {}", other)),
+    };
+    std::fs::write(output_path, content).map_err(|e| e.to_string())
+}
+
+/// Build a call graph from `.ci` files in a build directory, or by
+/// disassembling a linked ELF with the ARM toolchain's objdump.
+#[tauri::command]
+pub fn build_call_graph(
+    state: State<AppState>,
+    build_dir: Option<String>,
+    elf_path: Option<String>,
+) -> Result<CallGraph, String> {
+    let mut graph = CallGraph::new();
+
+    if let Some(dir) = build_dir {
+        graph.merge(
+            axiom_analysis::load_callgraph_info(Path::new(&dir)).map_err(|e| e.to_string())?,
+        );
+    }
+
+    if let Some(elf) = elf_path {
+        let objdump = {
+            let toolchains = state.toolchains.lock().map_err(|e| e.to_string())?;
+            toolchains
+                .iter()
+                .find(|t| t.kind == ToolchainKind::ArmGcc)
+                .map(|t| t.sibling_tool("objdump"))
+                .ok_or("ARM GCC toolchain not found")?
+        };
+        graph.merge(
+            axiom_analysis::callgraph_from_elf(&objdump, Path::new(&elf))
+                .map_err(|e| e.to_string())?,
+        );
+    }
+
+    Ok(graph)
+}
+
+/// Find the data and control couplings between a project's modules.
+#[tauri::command]
+pub fn analyze_coupling(project_path: String) -> Result<CouplingAnalysis, String> {
+    axiom_analysis::analyze_project_coupling(Path::new(&project_path)).map_err(|e| e.to_string())
+}
+
+/// Check which of a project's couplings the given coverage files exercise.
+#[tauri::command]
+pub fn verify_coupling_coverage(
+    project_path: String,
+    coverage_paths: Vec<String>,
+) -> Result<CouplingCoverageReport, String> {
+    let root = Path::new(&project_path);
+    let analysis = axiom_analysis::analyze_project_coupling(root).map_err(|e| e.to_string())?;
+    let paths: Vec<PathBuf> = coverage_paths.into_iter().map(PathBuf::from).collect();
+    let coverage =
+        axiom_toolchain::generate_coverage_report(&paths, None, &[]).map_err(|e| e.to_string())?;
+    Ok(axiom_analysis::verify_coupling_coverage(
+        &analysis, &coverage,
+    ))
+}
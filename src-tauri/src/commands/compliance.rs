@@ -0,0 +1,779 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Compliance command handlers.
+
+use crate::state::AppState;
+use axiom_compliance::{
+    correlate_deviations, coverage_pdf, deviations_pdf, find_project_misra_suppressions,
+    generate_configuration_index, load_compliance_config, load_csv_mapping,
+    load_or_create_signing_key, read_junit, read_misra_findings, render_configuration_index,
+    render_qualification_document, signing_key_path, traceability_pdf, verify_artifact,
+    write_test_skeletons, AllocationReport, AnnotationSyntax, ArtifactSignature, Baseline,
+    BaselineDiff, BaselineStore, ChangeImpact, ComplianceConfig, ComplianceSnapshot,
+    ComplianceStatus, ConfigurationIndex, CsvMapping, DalPolicy, DeviationCorrelation,
+    DeviationRecord, DeviationStore, DocumentFormat, GapReport, GapSources, HtmlReport,
+    LinkVerification, MatrixStore, MergeSummary, ObjectTrace, PdfMetadata, ProblemReport,
+    ProblemReportStore, PublicKey, QualificationData, QualificationDocKind, Requirement,
+    RequirementCoverageReport, RequirementQuery, RequirementStore, SarifExport, SavedMatrix,
+    SignatureStatus, SigningKey, SnapshotChanges, SystemRequirement, SystemRequirementStore,
+    TraceabilityMatrix,
+};
+use axiom_core::time::unix_now;
+use axiom_core::Diagnostic;
+use axiom_settings::AnnotationSettings;
+use axiom_toolchain::{load_coverage_justifications, CoverageReport};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+/// The project's declared assurance level and compliance modes.
+#[tauri::command]
+pub fn get_compliance_config(project_path: String) -> Result<ComplianceConfig, String> {
+    load_compliance_config(Path::new(&project_path)).map_err(|e| e.to_string())
+}
+
+/// Objectives that follow from the project's assurance level and modes.
+#[tauri::command]
+pub fn get_dal_policy(project_path: String) -> Result<DalPolicy, String> {
+    Ok(get_compliance_config(project_path)?.policy())
+}
+
+/// Requirements in the project database matching a query, ordered by ID.
+#[tauri::command]
+pub fn list_requirements(
+    project_path: String,
+    query: Option<RequirementQuery>,
+) -> Result<Vec<Requirement>, String> {
+    let set = RequirementStore::for_project(Path::new(&project_path))
+        .load()
+        .map_err(|e| e.to_string())?;
+    Ok(set
+        .query(&query.unwrap_or_default())
+        .into_iter()
+        .cloned()
+        .collect())
+}
+
+/// A single requirement from the project database.
+#[tauri::command]
+pub fn get_requirement(project_path: String, id: String) -> Result<Requirement, String> {
+    let set = RequirementStore::for_project(Path::new(&project_path))
+        .load()
+        .map_err(|e| e.to_string())?;
+    set.get(&id)
+        .cloned()
+        .ok_or_else(|| format!("Requirement not found: {}", id))
+}
+
+/// Add a requirement to the project database.
+#[tauri::command]
+pub fn create_requirement(project_path: String, requirement: Requirement) -> Result<(), String> {
+    RequirementStore::for_project(Path::new(&project_path))
+        .create(requirement)
+        .map_err(|e| e.to_string())
+}
+
+/// Replace a requirement, returning the previous version.
+#[tauri::command]
+pub fn update_requirement(
+    project_path: String,
+    requirement: Requirement,
+) -> Result<Requirement, String> {
+    RequirementStore::for_project(Path::new(&project_path))
+        .update(requirement)
+        .map_err(|e| e.to_string())
+}
+
+/// Delete a requirement that no other requirement refines.
+#[tauri::command]
+pub fn delete_requirement(project_path: String, id: String) -> Result<Requirement, String> {
+    RequirementStore::for_project(Path::new(&project_path))
+        .delete(&id)
+        .map_err(|e| e.to_string())
+}
+
+/// List the project's ARP4754A system requirements.
+#[tauri::command]
+pub fn list_system_requirements(project_path: String) -> Result<Vec<SystemRequirement>, String> {
+    SystemRequirementStore::for_project(Path::new(&project_path))
+        .load()
+        .map_err(|e| e.to_string())
+}
+
+/// Add a system requirement.
+#[tauri::command]
+pub fn create_system_requirement(
+    project_path: String,
+    requirement: SystemRequirement,
+) -> Result<(), String> {
+    SystemRequirementStore::for_project(Path::new(&project_path))
+        .create(requirement)
+        .map_err(|e| e.to_string())
+}
+
+/// Replace a system requirement, returning the previous version.
+#[tauri::command]
+pub fn update_system_requirement(
+    project_path: String,
+    requirement: SystemRequirement,
+) -> Result<SystemRequirement, String> {
+    SystemRequirementStore::for_project(Path::new(&project_path))
+        .update(requirement)
+        .map_err(|e| e.to_string())
+}
+
+/// Delete a system requirement.
+#[tauri::command]
+pub fn delete_system_requirement(
+    project_path: String,
+    id: String,
+) -> Result<SystemRequirement, String> {
+    SystemRequirementStore::for_project(Path::new(&project_path))
+        .delete(&id)
+        .map_err(|e| e.to_string())
+}
+
+/// Check system requirement allocations against the software requirements.
+#[tauri::command]
+pub fn system_allocation_report(project_path: String) -> Result<AllocationReport, String> {
+    let root = Path::new(&project_path);
+    let system = SystemRequirementStore::for_project(root)
+        .load()
+        .map_err(|e| e.to_string())?;
+    let software: Vec<Requirement> = RequirementStore::for_project(root)
+        .load()
+        .map_err(|e| e.to_string())?
+        .iter()
+        .cloned()
+        .collect();
+    Ok(axiom_compliance::allocation_coverage(&system, &software))
+}
+
+/// Import a ReqIF file into the project's requirements database.
+#[tauri::command]
+pub fn import_reqif(project_path: String, path: String) -> Result<MergeSummary, String> {
+    let store = RequirementStore::for_project(Path::new(&project_path));
+    axiom_compliance::import_reqif(&store, Path::new(&path)).map_err(|e| e.to_string())
+}
+
+/// Export the project's requirements database as a ReqIF file.
+#[tauri::command]
+pub fn export_reqif(project_path: String, path: String, title: String) -> Result<(), String> {
+    let store = RequirementStore::for_project(Path::new(&project_path));
+    axiom_compliance::export_reqif_file(&store, Path::new(&path), &title, unix_now())
+        .map_err(|e| e.to_string())
+}
+
+/// Import a CSV file into the project's requirements database, using the
+/// given column mapping or else the project's.
+#[tauri::command]
+pub fn import_requirements_csv(
+    project_path: String,
+    path: String,
+    mapping: Option<CsvMapping>,
+) -> Result<MergeSummary, String> {
+    let root = Path::new(&project_path);
+    let mapping = match mapping {
+        Some(mapping) => mapping,
+        None => load_csv_mapping(root).map_err(|e| e.to_string())?,
+    };
+    axiom_compliance::import_requirements_csv(
+        &RequirementStore::for_project(root),
+        Path::new(&path),
+        &mapping,
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Export the project's requirements database as CSV, using the given
+/// column mapping or else the project's.
+#[tauri::command]
+pub fn export_requirements_csv(
+    project_path: String,
+    path: String,
+    mapping: Option<CsvMapping>,
+) -> Result<(), String> {
+    let root = Path::new(&project_path);
+    let mapping = match mapping {
+        Some(mapping) => mapping,
+        None => load_csv_mapping(root).map_err(|e| e.to_string())?,
+    };
+    axiom_compliance::export_requirements_csv_file(
+        &RequirementStore::for_project(root),
+        Path::new(&path),
+        &mapping,
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Scan the project's sources for requirement annotations and join them
+/// with the requirements database, recording verification status from
+/// JUnit result files when given.
+#[tauri::command]
+pub fn generate_traceability_matrix(
+    state: State<AppState>,
+    project_path: String,
+    junit_paths: Option<Vec<String>>,
+) -> Result<TraceabilityMatrix, String> {
+    let matrix = axiom_compliance::generate_traceability_matrix(
+        Path::new(&project_path),
+        &annotation_syntax(&state)?,
+    )
+    .map_err(|e| e.to_string())?;
+    let paths: Vec<PathBuf> = junit_paths
+        .unwrap_or_default()
+        .into_iter()
+        .map(PathBuf::from)
+        .collect();
+    let results = read_junit(&paths).map_err(|e| e.to_string())?;
+    Ok(matrix.with_test_results(results))
+}
+
+/// Generate the traceability matrix and save it in the project, so it can be
+/// committed and reloaded without a rescan.
+#[tauri::command]
+pub fn save_traceability_matrix(
+    state: State<AppState>,
+    project_path: String,
+) -> Result<TraceabilityMatrix, String> {
+    let root = Path::new(&project_path);
+    let matrix = axiom_compliance::generate_traceability_matrix(root, &annotation_syntax(&state)?)
+        .map_err(|e| e.to_string())?;
+    MatrixStore::for_project(root)
+        .save(&matrix, unix_now())
+        .map_err(|e| e.to_string())?;
+    Ok(matrix)
+}
+
+/// The project's saved traceability matrix, if one has been saved.
+#[tauri::command]
+pub fn load_traceability_matrix(project_path: String) -> Result<Option<SavedMatrix>, String> {
+    MatrixStore::for_project(Path::new(&project_path))
+        .load()
+        .map_err(|e| e.to_string())
+}
+
+/// Re-check each link of the saved traceability matrix against the current
+/// sources, flagging moved, changed and deleted annotations.
+#[tauri::command]
+pub fn verify_traceability_links(
+    state: State<AppState>,
+    project_path: String,
+) -> Result<Vec<LinkVerification>, String> {
+    let root = Path::new(&project_path);
+    let saved = MatrixStore::for_project(root)
+        .load()
+        .map_err(|e| e.to_string())?
+        .ok_or("No saved traceability matrix")?;
+    axiom_compliance::verify_links(&saved.matrix, root, &annotation_syntax(&state)?)
+        .map_err(|e| e.to_string())
+}
+
+/// Structural coverage of each requirement's implementing functions,
+/// against the project's DAL threshold.
+#[tauri::command]
+pub fn requirement_coverage(
+    state: State<AppState>,
+    project_path: String,
+    coverage_paths: Vec<String>,
+) -> Result<RequirementCoverageReport, String> {
+    let root = Path::new(&project_path);
+    let matrix = axiom_compliance::generate_traceability_matrix(root, &annotation_syntax(&state)?)
+        .map_err(|e| e.to_string())?;
+    let policy = load_compliance_config(root)
+        .map_err(|e| e.to_string())?
+        .policy();
+    let coverage = project_coverage(root, coverage_paths)?;
+    Ok(axiom_compliance::requirement_coverage(
+        &matrix, &coverage, &policy,
+    ))
+}
+
+/// Write a self-contained HTML traceability and compliance report,
+/// including a coverage summary when coverage files are given.
+#[tauri::command]
+pub fn export_html_report(
+    state: State<AppState>,
+    project_path: String,
+    path: String,
+    title: String,
+    coverage_paths: Option<Vec<String>>,
+) -> Result<(), String> {
+    let root = Path::new(&project_path);
+    let matrix = axiom_compliance::generate_traceability_matrix(root, &annotation_syntax(&state)?)
+        .map_err(|e| e.to_string())?;
+    let policy = load_compliance_config(root)
+        .map_err(|e| e.to_string())?
+        .policy();
+
+    let mut report = HtmlReport::new(title, unix_now()).with_policy(policy);
+    if let Some(paths) = coverage_paths.filter(|p| !p.is_empty()) {
+        report = report.with_coverage(project_coverage(root, paths)?);
+    }
+    std::fs::write(&path, report.render(&matrix)).map_err(|e| e.to_string())
+}
+
+/// Requirement annotation syntax from the compliance settings.
+fn annotation_syntax(state: &State<AppState>) -> Result<AnnotationSyntax, String> {
+    let settings = state.settings.lock().map_err(|e| e.to_string())?;
+    build_annotation_syntax(&settings.compliance.annotations)
+}
+
+fn build_annotation_syntax(annotations: &AnnotationSettings) -> Result<AnnotationSyntax, String> {
+    AnnotationSyntax::new(
+        annotations.prefixes.clone(),
+        annotations.id_patterns.clone(),
+        annotations.test_markers.clone(),
+        annotations.derived_markers.clone(),
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Coverage report from the given files, with the project's coverage
+/// justifications applied.
+fn project_coverage(root: &Path, paths: Vec<String>) -> Result<CoverageReport, String> {
+    let paths: Vec<PathBuf> = paths.into_iter().map(PathBuf::from).collect();
+    let justifications = load_coverage_justifications(root).map_err(|e| e.to_string())?;
+    axiom_toolchain::generate_coverage_report(&paths, None, &justifications)
+        .map_err(|e| e.to_string())
+}
+
+/// PDF metadata for a project: its directory name and an optional baseline.
+fn pdf_metadata(root: &Path, baseline: Option<String>) -> PdfMetadata {
+    let project = root
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| root.display().to_string());
+    let metadata = PdfMetadata::new(project, unix_now());
+    match baseline {
+        Some(baseline) => metadata.with_baseline(baseline),
+        None => metadata,
+    }
+}
+
+/// Write the traceability matrix as a PDF.
+#[tauri::command]
+pub fn export_traceability_pdf(
+    state: State<AppState>,
+    project_path: String,
+    path: String,
+    baseline: Option<String>,
+) -> Result<(), String> {
+    let root = Path::new(&project_path);
+    let matrix = axiom_compliance::generate_traceability_matrix(root, &annotation_syntax(&state)?)
+        .map_err(|e| e.to_string())?;
+    let doc = traceability_pdf(&matrix, pdf_metadata(root, baseline));
+    std::fs::write(&path, doc.render()).map_err(|e| e.to_string())
+}
+
+/// Write a structural coverage report as a PDF, checked against the
+/// project's assurance level.
+#[tauri::command]
+pub fn export_coverage_pdf(
+    project_path: String,
+    path: String,
+    coverage_paths: Vec<String>,
+    baseline: Option<String>,
+) -> Result<(), String> {
+    let root = Path::new(&project_path);
+    let policy = load_compliance_config(root)
+        .map_err(|e| e.to_string())?
+        .policy();
+    let coverage = project_coverage(root, coverage_paths)?;
+    let doc = coverage_pdf(&coverage, Some(&policy), pdf_metadata(root, baseline));
+    std::fs::write(&path, doc.render()).map_err(|e| e.to_string())
+}
+
+/// Write the project's deviation records as a PDF.
+#[tauri::command]
+pub fn export_deviations_pdf(
+    project_path: String,
+    path: String,
+    baseline: Option<String>,
+) -> Result<(), String> {
+    let root = Path::new(&project_path);
+    let deviations = DeviationStore::for_project(root)
+        .load()
+        .map_err(|e| e.to_string())?;
+    let doc = deviations_pdf(&deviations, pdf_metadata(root, baseline));
+    std::fs::write(&path, doc.render()).map_err(|e| e.to_string())
+}
+
+/// Correlate requirement-linked functions with the function symbols of a
+/// linked ELF image or linker map.
+#[tauri::command]
+pub fn correlate_object_code(
+    state: State<AppState>,
+    project_path: String,
+    binary_path: String,
+) -> Result<ObjectTrace, String> {
+    let matrix = axiom_compliance::generate_traceability_matrix(
+        Path::new(&project_path),
+        &annotation_syntax(&state)?,
+    )
+    .map_err(|e| e.to_string())?;
+    let symbols = axiom_compliance::read_object_symbols(Path::new(&binary_path))
+        .map_err(|e| e.to_string())?;
+    Ok(axiom_compliance::correlate_object_code(&matrix, &symbols))
+}
+
+/// Write annotated test skeletons for untested requirements into the
+/// configured test directory, returning the files created.
+#[tauri::command]
+pub fn generate_test_skeletons(
+    state: State<AppState>,
+    project_path: String,
+) -> Result<Vec<PathBuf>, String> {
+    let root = Path::new(&project_path);
+    let config = load_compliance_config(root).map_err(|e| e.to_string())?;
+    let matrix = axiom_compliance::generate_traceability_matrix(root, &annotation_syntax(&state)?)
+        .map_err(|e| e.to_string())?;
+    let skeletons = axiom_compliance::generate_test_skeletons(&matrix, &config);
+    write_test_skeletons(root, &skeletons).map_err(|e| e.to_string())
+}
+
+/// Write a DO-330 TQP, TOR or TAS skeleton populated from the project's
+/// toolchain lock, tool usage log and qualification baseline.
+#[tauri::command]
+pub fn export_qualification_document(
+    project_path: String,
+    kind: QualificationDocKind,
+    format: DocumentFormat,
+    path: String,
+) -> Result<(), String> {
+    let data =
+        QualificationData::load(Path::new(&project_path), unix_now()).map_err(|e| e.to_string())?;
+    let document = render_qualification_document(kind, format, &data);
+    std::fs::write(&path, document).map_err(|e| e.to_string())
+}
+
+/// The project's signing key, kept in the `keys` directory beside the user
+/// settings and created on first use.
+fn project_signing_key(project_path: &str) -> Result<SigningKey, String> {
+    let keys_dir = axiom_settings::default_settings_path().with_file_name("keys");
+    load_or_create_signing_key(&signing_key_path(&keys_dir, Path::new(project_path)))
+        .map_err(|e| e.to_string())
+}
+
+/// The project's public signing key, hex-encoded, creating the key pair on
+/// first use.
+#[tauri::command]
+pub fn get_signing_public_key(project_path: String) -> Result<String, String> {
+    Ok(project_signing_key(&project_path)?.public_key().to_hex())
+}
+
+/// Sign an exported artifact with the project's key, writing a detached
+/// `.sig` file beside it.
+#[tauri::command]
+pub fn sign_artifact(
+    project_path: String,
+    artifact_path: String,
+) -> Result<ArtifactSignature, String> {
+    let key = project_signing_key(&project_path)?;
+    axiom_compliance::sign_artifact(&key, Path::new(&artifact_path), unix_now())
+        .map_err(|e| e.to_string())
+}
+
+/// Check an artifact against its signature, optionally requiring a
+/// specific hex-encoded public key.
+#[tauri::command]
+pub fn verify_artifact_signature(
+    artifact_path: String,
+    public_key: Option<String>,
+) -> Result<SignatureStatus, String> {
+    let trusted = public_key
+        .map(|k| PublicKey::from_hex(&k))
+        .transpose()
+        .map_err(|e| e.to_string())?;
+    verify_artifact(Path::new(&artifact_path), trusted.as_ref()).map_err(|e| e.to_string())
+}
+
+/// Capture the project's compliance state as a named baseline, with
+/// coverage from the given files if any.
+#[tauri::command]
+pub fn create_baseline(
+    state: State<AppState>,
+    project_path: String,
+    label: String,
+    coverage_paths: Option<Vec<String>>,
+) -> Result<Baseline, String> {
+    let root = Path::new(&project_path);
+    let coverage = match coverage_paths.filter(|p| !p.is_empty()) {
+        Some(paths) => Some(project_coverage(root, paths)?),
+        None => None,
+    };
+    let baseline = Baseline::capture(
+        root,
+        &annotation_syntax(&state)?,
+        label,
+        unix_now(),
+        coverage.as_ref(),
+    )
+    .map_err(|e| e.to_string())?;
+    BaselineStore::for_project(root)
+        .save(&baseline)
+        .map_err(|e| e.to_string())?;
+    Ok(baseline)
+}
+
+/// Labels of the project's baselines.
+#[tauri::command]
+pub fn list_baselines(project_path: String) -> Result<Vec<String>, String> {
+    BaselineStore::for_project(Path::new(&project_path))
+        .labels()
+        .map_err(|e| e.to_string())
+}
+
+/// Compare two of the project's baselines.
+#[tauri::command]
+pub fn diff_baselines(
+    project_path: String,
+    from: String,
+    to: String,
+) -> Result<BaselineDiff, String> {
+    let store = BaselineStore::for_project(Path::new(&project_path));
+    let from = store.load(&from).map_err(|e| e.to_string())?;
+    let to = store.load(&to).map_err(|e| e.to_string())?;
+    Ok(axiom_compliance::diff_baselines(&from, &to))
+}
+
+/// Checksums and traced requirements of the project's sources now.
+#[tauri::command]
+pub fn capture_compliance_snapshot(
+    state: State<AppState>,
+    project_path: String,
+) -> Result<ComplianceSnapshot, String> {
+    ComplianceSnapshot::capture(Path::new(&project_path), &annotation_syntax(&state)?)
+        .map_err(|e| e.to_string())
+}
+
+/// Files and requirement tracing changed since an earlier snapshot.
+#[tauri::command]
+pub fn compliance_snapshot_changes(
+    state: State<AppState>,
+    project_path: String,
+    previous: ComplianceSnapshot,
+) -> Result<SnapshotChanges, String> {
+    let current =
+        ComplianceSnapshot::capture(Path::new(&project_path), &annotation_syntax(&state)?)
+            .map_err(|e| e.to_string())?;
+    Ok(current.changes_since(&previous))
+}
+
+/// Requirements, tests and coverage affected by the commit range
+/// `from..to`, to scope re-verification.
+#[tauri::command]
+pub fn analyze_change_impact(
+    state: State<AppState>,
+    project_path: String,
+    from: String,
+    to: String,
+) -> Result<ChangeImpact, String> {
+    axiom_compliance::analyze_change_impact(
+        Path::new(&project_path),
+        &annotation_syntax(&state)?,
+        &from,
+        &to,
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Every open compliance gap in the project, prioritized by its DAL
+/// policy, with coverage gaps from the given files if any.
+#[tauri::command]
+pub fn generate_gap_analysis(
+    state: State<AppState>,
+    project_path: String,
+    coverage_paths: Option<Vec<String>>,
+) -> Result<GapReport, String> {
+    let root = Path::new(&project_path);
+    let policy = load_compliance_config(root)
+        .map_err(|e| e.to_string())?
+        .policy();
+    let coverage = match coverage_paths.filter(|p| !p.is_empty()) {
+        Some(paths) => Some(project_coverage(root, paths)?),
+        None => None,
+    };
+    let sources =
+        GapSources::load(root, &annotation_syntax(&state)?, coverage).map_err(|e| e.to_string())?;
+    Ok(axiom_compliance::generate_gap_analysis(&sources, &policy))
+}
+
+/// Requirement counts, trace and structural coverage, open deviations and
+/// tool qualification for the compliance dashboard.
+#[tauri::command]
+pub fn compliance_status(
+    state: State<AppState>,
+    project_path: String,
+    coverage_paths: Option<Vec<String>>,
+) -> Result<ComplianceStatus, String> {
+    let root = Path::new(&project_path);
+    let coverage = match coverage_paths.filter(|p| !p.is_empty()) {
+        Some(paths) => Some(project_coverage(root, paths)?),
+        None => None,
+    };
+    ComplianceStatus::compute(root, &annotation_syntax(&state)?, coverage)
+        .map_err(|e| e.to_string())
+}
+
+/// Write the project's compliance gaps, plus static-analysis diagnostics by
+/// tool and MISRA checker findings when given, as a SARIF 2.1.0 log.
+#[tauri::command]
+pub fn export_sarif(
+    state: State<AppState>,
+    project_path: String,
+    path: String,
+    coverage_paths: Option<Vec<String>>,
+    diagnostics: Option<BTreeMap<String, Vec<Diagnostic>>>,
+    misra_reports: Option<Vec<String>>,
+) -> Result<(), String> {
+    let report = generate_gap_analysis(state, project_path.clone(), coverage_paths)?;
+    let mut export = SarifExport::new(&project_path).with_gaps(&report);
+    for (tool, diagnostics) in diagnostics.unwrap_or_default() {
+        export = export.with_diagnostics(&tool, &diagnostics);
+    }
+    let mut findings = Vec::new();
+    for report in misra_reports.unwrap_or_default() {
+        findings.extend(read_misra_findings(Path::new(&report)).map_err(|e| e.to_string())?);
+    }
+    export = export.with_misra_findings(&findings);
+    std::fs::write(&path, export.render()).map_err(|e| e.to_string())
+}
+
+/// Write the project's Software Configuration Index for a build profile
+/// (`release` by default) and return it.
+#[tauri::command]
+pub fn export_configuration_index(
+    project_path: String,
+    profile: Option<String>,
+    format: DocumentFormat,
+    path: String,
+) -> Result<ConfigurationIndex, String> {
+    let index = generate_configuration_index(
+        Path::new(&project_path),
+        profile.as_deref().unwrap_or("release"),
+        unix_now(),
+    )
+    .map_err(|e| e.to_string())?;
+    std::fs::write(&path, render_configuration_index(&index, format)).map_err(|e| e.to_string())?;
+    Ok(index)
+}
+
+/// The project's problem reports, ordered by ID.
+#[tauri::command]
+pub fn list_problem_reports(project_path: String) -> Result<Vec<ProblemReport>, String> {
+    ProblemReportStore::for_project(Path::new(&project_path))
+        .load()
+        .map_err(|e| e.to_string())
+}
+
+/// Raise a problem report under the next free ID and return it.
+#[tauri::command]
+pub fn create_problem_report(
+    project_path: String,
+    title: String,
+    description: String,
+    requirements: Vec<String>,
+    files: Vec<String>,
+) -> Result<ProblemReport, String> {
+    let store = ProblemReportStore::for_project(Path::new(&project_path));
+    let id = store.next_id().map_err(|e| e.to_string())?;
+    let mut report = ProblemReport::new(id, title, unix_now()).with_description(description);
+    report.requirements = requirements;
+    report.files = files.into_iter().map(PathBuf::from).collect();
+    store.create(report.clone()).map_err(|e| e.to_string())?;
+    Ok(report)
+}
+
+/// Replace a problem report, returning the previous version.
+#[tauri::command]
+pub fn update_problem_report(
+    project_path: String,
+    mut report: ProblemReport,
+) -> Result<ProblemReport, String> {
+    report.updated = unix_now();
+    ProblemReportStore::for_project(Path::new(&project_path))
+        .update(report)
+        .map_err(|e| e.to_string())
+}
+
+/// The project's coding-standard deviation records, ordered by ID.
+#[tauri::command]
+pub fn list_deviations(project_path: String) -> Result<Vec<DeviationRecord>, String> {
+    DeviationStore::for_project(Path::new(&project_path))
+        .load()
+        .map_err(|e| e.to_string())
+}
+
+/// Propose a deviation under the next free ID and return it.
+#[tauri::command]
+pub fn create_deviation(
+    project_path: String,
+    rule: String,
+    justification: String,
+    file: Option<String>,
+    line: Option<u32>,
+) -> Result<DeviationRecord, String> {
+    let store = DeviationStore::for_project(Path::new(&project_path));
+    let id = store.next_id().map_err(|e| e.to_string())?;
+    let mut deviation = DeviationRecord::new(id, rule, justification, unix_now());
+    if let Some(file) = file {
+        deviation = deviation.with_location(file, line);
+    }
+    store.create(deviation.clone()).map_err(|e| e.to_string())?;
+    Ok(deviation)
+}
+
+/// Replace a deviation record, returning the previous version.
+#[tauri::command]
+pub fn update_deviation(
+    project_path: String,
+    deviation: DeviationRecord,
+) -> Result<DeviationRecord, String> {
+    DeviationStore::for_project(Path::new(&project_path))
+        .update(deviation)
+        .map_err(|e| e.to_string())
+}
+
+/// Approve a deviation.
+#[tauri::command]
+pub fn approve_deviation(
+    project_path: String,
+    id: String,
+    approver: String,
+) -> Result<DeviationRecord, String> {
+    let store = DeviationStore::for_project(Path::new(&project_path));
+    let deviation = store
+        .get(&id)
+        .map_err(|e| e.to_string())?
+        .approve(approver, unix_now());
+    store.update(deviation.clone()).map_err(|e| e.to_string())?;
+    Ok(deviation)
+}
+
+/// Correlate MISRA checker reports (cppcheck XML or PC-lint text) and the
+/// project's suppression comments with its approved deviations.
+#[tauri::command]
+pub fn correlate_misra_findings(
+    project_path: String,
+    report_paths: Vec<String>,
+) -> Result<DeviationCorrelation, String> {
+    let root = Path::new(&project_path);
+    let mut findings = Vec::new();
+    for path in report_paths {
+        findings.extend(read_misra_findings(Path::new(&path)).map_err(|e| e.to_string())?);
+    }
+    let deviations = DeviationStore::for_project(root)
+        .load()
+        .map_err(|e| e.to_string())?;
+    let suppressions = find_project_misra_suppressions(root).map_err(|e| e.to_string())?;
+    Ok(correlate_deviations(&deviations, &findings, &suppressions))
+}
+
+/// Check a requirement annotation syntax before it is saved to the
+/// compliance settings.
+#[tauri::command]
+pub fn validate_annotation_syntax(annotations: AnnotationSettings) -> Result<(), String> {
+    build_annotation_syntax(&annotations).map(|_| ())
+}
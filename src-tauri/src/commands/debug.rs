@@ -0,0 +1,382 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Debug server and probe command handlers.
+
+use crate::state::AppState;
+use axiom_toolchain::{
+    DebugProbe, FlashOptions, FlashResult, GdbBreakpoint, GdbError, GdbEvent, GdbFrame,
+    GdbRegister, GdbSession, GdbVariable, OpenOcd, OpenOcdConfig, OpenOcdError, OpenOcdOutput,
+    OpenOcdServer, OpenOcdServerStatus, ProbeKind, TraceCapture, TraceConfig, TraceLine,
+};
+use std::path::Path;
+use std::time::Duration;
+use tauri::State;
+
+/// Time allowed for OpenOCD to connect to the probe and target.
+const OPENOCD_START_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Detect the OpenOCD installation.
+#[tauri::command]
+pub fn detect_openocd() -> Option<OpenOcd> {
+    axiom_toolchain::detect_openocd()
+}
+
+/// List connected debug probes.
+///
+/// With `measure_voltage`, each probe is opened through OpenOCD to read the
+/// target voltage. Probes can't be opened while the debug server holds one.
+#[tauri::command]
+pub fn list_debug_probes(
+    state: State<AppState>,
+    measure_voltage: bool,
+) -> Result<Vec<DebugProbe>, String> {
+    if !measure_voltage {
+        return Ok(axiom_toolchain::discover_probes());
+    }
+    if state.openocd.lock().map_err(|e| e.to_string())?.is_some() {
+        return Err("Stop the debug server before measuring target voltage".to_string());
+    }
+    let openocd = axiom_toolchain::detect_openocd().ok_or("OpenOCD not found")?;
+    Ok(axiom_toolchain::discover_probes_with_voltage(&openocd))
+}
+
+/// Build an OpenOCD configuration selecting a discovered probe.
+#[tauri::command]
+pub fn debug_probe_config(probe: DebugProbe, part: String) -> Result<OpenOcdConfig, String> {
+    let target = axiom_toolchain::target_script_for(&part)
+        .ok_or_else(|| OpenOcdError::UnknownTarget(part.clone()).to_string())?;
+    Ok(probe.openocd_config(target))
+}
+
+/// Build an OpenOCD configuration for a probe and part number.
+#[tauri::command]
+pub fn openocd_config(probe: ProbeKind, part: String) -> Result<OpenOcdConfig, String> {
+    OpenOcdConfig::for_part(probe, &part).map_err(|e| e.to_string())
+}
+
+/// Render an OpenOCD configuration as a `.cfg` file.
+#[tauri::command]
+pub fn openocd_config_script(config: OpenOcdConfig) -> String {
+    config.to_script()
+}
+
+/// Start the OpenOCD debug server, replacing any running one.
+#[tauri::command]
+pub fn openocd_start(
+    state: State<AppState>,
+    config: OpenOcdConfig,
+) -> Result<OpenOcdServerStatus, String> {
+    let openocd = axiom_toolchain::detect_openocd().ok_or("OpenOCD not found")?;
+    let mut server = state.openocd.lock().map_err(|e| e.to_string())?;
+    if let Some(previous) = server.take() {
+        previous.stop().map_err(|e| e.to_string())?;
+    }
+
+    let mut started = OpenOcdServer::start(&openocd, &config, OPENOCD_START_TIMEOUT)
+        .map_err(|e| e.to_string())?;
+    let status = started.status();
+    *server = Some(started);
+    Ok(status)
+}
+
+/// Stop the OpenOCD debug server.
+#[tauri::command]
+pub fn openocd_stop(state: State<AppState>) -> Result<(), String> {
+    let mut server = state.openocd.lock().map_err(|e| e.to_string())?;
+    match server.take() {
+        Some(server) => server.stop().map_err(|e| e.to_string()),
+        None => Ok(()),
+    }
+}
+
+/// Status of the OpenOCD debug server, if one was started.
+#[tauri::command]
+pub fn openocd_status(state: State<AppState>) -> Result<Option<OpenOcdServerStatus>, String> {
+    let mut server = state.openocd.lock().map_err(|e| e.to_string())?;
+    Ok(server.as_mut().map(OpenOcdServer::status))
+}
+
+/// Run an operation through the running server, or one-shot with `config`
+/// when no server is running.
+fn run_openocd_operation(
+    state: &State<AppState>,
+    config: Option<OpenOcdConfig>,
+    on_server: impl FnOnce(&OpenOcdServer) -> Result<String, OpenOcdError>,
+    one_shot: impl FnOnce(&OpenOcd, &OpenOcdConfig) -> Result<OpenOcdOutput, OpenOcdError>,
+) -> Result<OpenOcdOutput, String> {
+    let mut server = state.openocd.lock().map_err(|e| e.to_string())?;
+    if let Some(running) = server.as_mut() {
+        if running.status().running {
+            let response = on_server(running).map_err(|e| e.to_string())?;
+            let mut output = axiom_toolchain::parse_openocd_output(&response);
+            output.success = true;
+            return Ok(output);
+        }
+    }
+
+    let config = config.ok_or("OpenOCD server is not running and no configuration was given")?;
+    let openocd = axiom_toolchain::detect_openocd().ok_or("OpenOCD not found")?;
+    one_shot(&openocd, &config).map_err(|e| e.to_string())
+}
+
+/// Flash an image with OpenOCD, verify it, and reset the target.
+#[tauri::command]
+pub fn openocd_flash(
+    state: State<AppState>,
+    config: Option<OpenOcdConfig>,
+    image: String,
+    address: Option<u64>,
+) -> Result<OpenOcdOutput, String> {
+    let image = Path::new(&image);
+    run_openocd_operation(
+        &state,
+        config,
+        |server| server.flash(image, address),
+        |openocd, config| axiom_toolchain::openocd_flash(openocd, config, image, address),
+    )
+}
+
+/// Reset the target, optionally leaving it halted.
+#[tauri::command]
+pub fn openocd_reset(
+    state: State<AppState>,
+    config: Option<OpenOcdConfig>,
+    halt: bool,
+) -> Result<OpenOcdOutput, String> {
+    run_openocd_operation(
+        &state,
+        config,
+        |server| server.reset(halt),
+        |openocd, config| axiom_toolchain::openocd_reset(openocd, config, halt),
+    )
+}
+
+/// Halt the target.
+#[tauri::command]
+pub fn openocd_halt(
+    state: State<AppState>,
+    config: Option<OpenOcdConfig>,
+) -> Result<OpenOcdOutput, String> {
+    run_openocd_operation(
+        &state,
+        config,
+        OpenOcdServer::halt,
+        axiom_toolchain::openocd_halt,
+    )
+}
+
+/// Program an image into a target, verify it, and reset the target.
+///
+/// The probe can't be shared with the debug server, so that must be stopped
+/// first.
+#[tauri::command]
+pub fn flash_target(
+    state: State<AppState>,
+    image: String,
+    probe: DebugProbe,
+    part: String,
+    options: Option<FlashOptions>,
+) -> Result<FlashResult, String> {
+    if state.openocd.lock().map_err(|e| e.to_string())?.is_some() {
+        return Err("Stop the debug server before flashing".to_string());
+    }
+    let options = options.unwrap_or_default();
+    axiom_toolchain::flash_target(Path::new(&image), &probe, &part, &options)
+        .map_err(|e| e.to_string())
+}
+
+/// Start a GDB session for an ELF file, replacing any running one.
+///
+/// Connects to `remote` (e.g. `localhost:3333`) when given, otherwise to the
+/// running OpenOCD server's GDB port, if any.
+#[tauri::command]
+pub fn gdb_start(
+    state: State<AppState>,
+    elf: String,
+    remote: Option<String>,
+) -> Result<(), String> {
+    let gdb = {
+        let toolchains = state.toolchains.lock().map_err(|e| e.to_string())?;
+        axiom_toolchain::find_gdb(&toolchains).ok_or("GDB not found")?
+    };
+    let remote = match remote {
+        Some(remote) => Some(remote),
+        None => {
+            let mut server = state.openocd.lock().map_err(|e| e.to_string())?;
+            server
+                .as_mut()
+                .map(OpenOcdServer::status)
+                .filter(|status| status.running)
+                .map(|status| format!("localhost:{}", status.gdb_port))
+        }
+    };
+
+    let mut session = state.gdb.lock().map_err(|e| e.to_string())?;
+    if let Some(previous) = session.take() {
+        previous.stop().map_err(|e| e.to_string())?;
+    }
+    let mut started = GdbSession::start(&gdb, Some(Path::new(&elf))).map_err(|e| e.to_string())?;
+    if let Some(remote) = remote {
+        started.connect(&remote).map_err(|e| e.to_string())?;
+    }
+    *session = Some(started);
+    Ok(())
+}
+
+/// Exit the GDB session.
+#[tauri::command]
+pub fn gdb_stop(state: State<AppState>) -> Result<(), String> {
+    let mut session = state.gdb.lock().map_err(|e| e.to_string())?;
+    match session.take() {
+        Some(session) => session.stop().map_err(|e| e.to_string()),
+        None => Ok(()),
+    }
+}
+
+/// Run an operation on the GDB session.
+fn with_gdb<T>(
+    state: &State<AppState>,
+    operation: impl FnOnce(&mut GdbSession) -> Result<T, GdbError>,
+) -> Result<T, String> {
+    let mut session = state.gdb.lock().map_err(|e| e.to_string())?;
+    let session = session.as_mut().ok_or("No GDB session")?;
+    operation(session).map_err(|e| e.to_string())
+}
+
+/// Drain events from the GDB session.
+#[tauri::command]
+pub fn gdb_events(state: State<AppState>) -> Result<Vec<GdbEvent>, String> {
+    with_gdb(&state, |session| Ok(session.events()))
+}
+
+/// Download the ELF to the target.
+#[tauri::command]
+pub fn gdb_load(state: State<AppState>) -> Result<(), String> {
+    with_gdb(&state, GdbSession::load)
+}
+
+/// Insert a breakpoint.
+#[tauri::command]
+pub fn gdb_insert_breakpoint(
+    state: State<AppState>,
+    location: String,
+) -> Result<GdbBreakpoint, String> {
+    with_gdb(&state, |session| session.insert_breakpoint(&location))
+}
+
+/// Delete a breakpoint.
+#[tauri::command]
+pub fn gdb_delete_breakpoint(state: State<AppState>, number: u32) -> Result<(), String> {
+    with_gdb(&state, |session| session.delete_breakpoint(number))
+}
+
+/// List breakpoints.
+#[tauri::command]
+pub fn gdb_breakpoints(state: State<AppState>) -> Result<Vec<GdbBreakpoint>, String> {
+    with_gdb(&state, GdbSession::breakpoints)
+}
+
+/// Resume the target.
+#[tauri::command]
+pub fn gdb_continue(state: State<AppState>) -> Result<(), String> {
+    with_gdb(&state, GdbSession::continue_execution)
+}
+
+/// Interrupt the running target.
+#[tauri::command]
+pub fn gdb_interrupt(state: State<AppState>) -> Result<(), String> {
+    with_gdb(&state, GdbSession::interrupt)
+}
+
+/// Step one source line, into calls.
+#[tauri::command]
+pub fn gdb_step_into(state: State<AppState>) -> Result<(), String> {
+    with_gdb(&state, GdbSession::step_into)
+}
+
+/// Step one source line, over calls.
+#[tauri::command]
+pub fn gdb_step_over(state: State<AppState>) -> Result<(), String> {
+    with_gdb(&state, GdbSession::step_over)
+}
+
+/// Step one instruction.
+#[tauri::command]
+pub fn gdb_step_instruction(state: State<AppState>) -> Result<(), String> {
+    with_gdb(&state, GdbSession::step_instruction)
+}
+
+/// Run until the current function returns.
+#[tauri::command]
+pub fn gdb_finish(state: State<AppState>) -> Result<(), String> {
+    with_gdb(&state, GdbSession::finish)
+}
+
+/// Stack frames of the current thread.
+#[tauri::command]
+pub fn gdb_stack_frames(state: State<AppState>) -> Result<Vec<GdbFrame>, String> {
+    with_gdb(&state, GdbSession::stack_frames)
+}
+
+/// Arguments and locals of the selected frame.
+#[tauri::command]
+pub fn gdb_variables(state: State<AppState>) -> Result<Vec<GdbVariable>, String> {
+    with_gdb(&state, GdbSession::variables)
+}
+
+/// Evaluate an expression in the selected frame.
+#[tauri::command]
+pub fn gdb_evaluate(state: State<AppState>, expression: String) -> Result<String, String> {
+    with_gdb(&state, |session| session.evaluate(&expression))
+}
+
+/// Read all registers.
+#[tauri::command]
+pub fn gdb_registers(state: State<AppState>) -> Result<Vec<GdbRegister>, String> {
+    with_gdb(&state, GdbSession::registers)
+}
+
+/// Read target memory.
+#[tauri::command]
+pub fn gdb_read_memory(
+    state: State<AppState>,
+    address: u64,
+    length: usize,
+) -> Result<Vec<u8>, String> {
+    with_gdb(&state, |session| session.read_memory(address, length))
+}
+
+/// Start capturing semihosting and SWO output from the running debug
+/// server, replacing any running capture.
+#[tauri::command]
+pub fn trace_start(state: State<AppState>, config: TraceConfig) -> Result<(), String> {
+    let server = state.openocd.lock().map_err(|e| e.to_string())?;
+    let server = server.as_ref().ok_or("OpenOCD server is not running")?;
+    let mut capture = state.trace.lock().map_err(|e| e.to_string())?;
+    if let Some(previous) = capture.take() {
+        previous.stop();
+    }
+    *capture = Some(TraceCapture::start(server, &config).map_err(|e| e.to_string())?);
+    Ok(())
+}
+
+/// Take trace lines captured since the last poll.
+#[tauri::command]
+pub fn trace_poll(state: State<AppState>) -> Result<Vec<TraceLine>, String> {
+    let capture = state.trace.lock().map_err(|e| e.to_string())?;
+    Ok(capture
+        .as_ref()
+        .map(TraceCapture::drain)
+        .unwrap_or_default())
+}
+
+/// Stop trace capture.
+#[tauri::command]
+pub fn trace_stop(state: State<AppState>) -> Result<(), String> {
+    let mut capture = state.trace.lock().map_err(|e| e.to_string())?;
+    if let Some(capture) = capture.take() {
+        capture.stop();
+    }
+    Ok(())
+}
@@ -0,0 +1,69 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024 HawkLogic Systems
+
+//! Project health command handlers.
+
+use crate::state::AppState;
+use axiom_core::{HealthFinding, HealthReport};
+use std::path::Path;
+use tauri::State;
+
+/// Run every health check against a project and return the findings,
+/// highest priority first.
+#[tauri::command]
+pub fn diagnose_project(
+    state: State<AppState>,
+    project_path: String,
+) -> Result<HealthReport, String> {
+    let root = Path::new(&project_path);
+    if !root.is_dir() {
+        return Err(format!("Not a directory: {}", project_path));
+    }
+
+    let toolchains = state.toolchains.lock().map_err(|e| e.to_string())?.clone();
+    let output_dir = state
+        .settings
+        .lock()
+        .map_err(|e| e.to_string())?
+        .build
+        .output_dir
+        .clone();
+
+    let mut report = HealthReport::new();
+    report.add(
+        "toolchain",
+        axiom_toolchain::diagnose_toolchains(&toolchains, root),
+    );
+    report.add("linker", axiom_toolchain::diagnose_linker_scripts(root));
+    report.add("includes", axiom_toolchain::diagnose_include_paths(root));
+    report.add(
+        "caches",
+        axiom_toolchain::diagnose_caches(root, &output_dir),
+    );
+    report.add("git", axiom_git::diagnose_repository(root));
+    report.add(
+        "settings",
+        axiom_settings::diagnose_settings(&axiom_settings::default_settings_path()),
+    );
+
+    // Subsystems left disabled by safe mode
+    let status = state.startup.lock().map_err(|e| e.to_string())?.status();
+    let startup = status
+        .disabled
+        .iter()
+        .map(|failure| {
+            HealthFinding::warning(
+                "startup.safe-mode",
+                format!(
+                    "{} is disabled: {}",
+                    failure.subsystem.name(),
+                    failure.reason
+                ),
+            )
+            .with_action("Exit safe mode once the cause is fixed")
+        })
+        .collect();
+    report.add("startup", startup);
+
+    Ok(report)
+}
@@ -3,7 +3,10 @@
 
 //! Git command handlers.
 
-use axiom_git::{CommitInfo, FileDiff, RemoteStatus, RepoStatus, Repository};
+use axiom_git::{
+    CommitInfo, CommitRequirementChanges, FileDiff, RemoteStatus, RepoStatus, Repository,
+    VolatilityReport,
+};
 use std::path::Path;
 
 /// Get git status for a repository.
@@ -103,3 +106,17 @@ pub fn git_commit_files(path: String, commit_id: String) -> Result<Vec<String>,
     let repo = Repository::discover(Path::new(&path)).map_err(|e| e.to_string())?;
     repo.commit_files(&commit_id).map_err(|e| e.to_string())
 }
+
+/// Get requirement tag changes over recent history.
+#[tauri::command]
+pub fn git_requirement_history(path: String, limit: usize) -> Result<Vec<CommitRequirementChanges>, String> {
+    let repo = Repository::discover(Path::new(&path)).map_err(|e| e.to_string())?;
+    axiom_git::requirement_history(&repo, limit).map_err(|e| e.to_string())
+}
+
+/// Get the requirement volatility report over recent history.
+#[tauri::command]
+pub fn git_requirement_volatility(path: String, limit: usize) -> Result<VolatilityReport, String> {
+    let repo = Repository::discover(Path::new(&path)).map_err(|e| e.to_string())?;
+    axiom_git::requirement_volatility(&repo, limit).map_err(|e| e.to_string())
+}
@@ -3,6 +3,10 @@
 
 //! Tauri command handlers.
 
+pub mod analysis;
+pub mod compliance;
+pub mod debug;
+pub mod doctor;
 pub mod fs;
 pub mod git;
 pub mod parser;
@@ -4,8 +4,9 @@
 //! Parser command handlers.
 
 use crate::state::AppState;
-use axiom_parser::{AstNode, Language};
-use std::path::PathBuf;
+use axiom_parser::{AstNode, InlayHint, InlayHintOptions, Language, RegisterMap};
+use axiom_settings::Subsystem;
+use std::path::{Path, PathBuf};
 use tauri::State;
 
 /// Parse a file and return the AST.
@@ -17,8 +18,10 @@ pub fn parse_file(state: State<AppState>, path: String) -> Result<AstNode, Strin
     let ast = parser.parse_file(&path).map_err(|e| e.to_string())?;
 
     // Also index the file for symbols
-    let mut index = state.symbol_index.lock().map_err(|e| e.to_string())?;
-    index.index_file(path, &ast);
+    if state.subsystem_enabled(Subsystem::SymbolIndex) {
+        let mut index = state.symbol_index.lock().map_err(|e| e.to_string())?;
+        index.index_file(path, &ast);
+    }
 
     Ok(ast)
 }
@@ -35,3 +38,37 @@ pub fn get_ast(state: State<AppState>, source: String, language: String) -> Resu
     let mut parser = state.parser.lock().map_err(|e| e.to_string())?;
     parser.parse(&source, lang).map_err(|e| e.to_string())
 }
+
+/// Get inlay hints for numeric literals in source code.
+#[tauri::command]
+pub fn get_inlay_hints(
+    state: State<AppState>,
+    source: String,
+    language: String,
+    registers: Option<RegisterMap>,
+    options: Option<InlayHintOptions>,
+) -> Result<Vec<InlayHint>, String> {
+    let lang = match language.to_lowercase().as_str() {
+        "c" => Language::C,
+        "cpp" | "c++" => Language::Cpp,
+        _ => return Err(format!("Unsupported language: {}", language)),
+    };
+
+    let registers = registers.unwrap_or_default();
+    let options = options.unwrap_or_default();
+    let mut parser = state.parser.lock().map_err(|e| e.to_string())?;
+    parser
+        .inlay_hints(&source, lang, &registers, &options)
+        .map_err(|e| e.to_string())
+}
+
+/// Build a register address map from an SVD file for inlay hints.
+#[tauri::command]
+pub fn get_svd_register_map(svd_path: String) -> Result<RegisterMap, String> {
+    let device = axiom_toolchain::read_svd(Path::new(&svd_path)).map_err(|e| e.to_string())?;
+    let mut registers = RegisterMap::new();
+    for (address, peripheral, register) in device.registers() {
+        registers.insert(address, &peripheral.name, &register.name);
+    }
+    Ok(registers)
+}
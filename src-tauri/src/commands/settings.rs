@@ -4,7 +4,7 @@
 //! Settings command handlers.
 
 use crate::state::AppState;
-use axiom_settings::Settings;
+use axiom_settings::{SafeModeStatus, Settings};
 use tauri::State;
 
 /// Get current settings.
@@ -21,6 +21,7 @@ pub fn set_settings(state: State<AppState>, settings: Settings) -> Result<(), St
     axiom_settings::save_default(&settings).map_err(|e| e.to_string())?;
 
     // Update state
+    apply_tool_limits(&settings);
     let mut current = state.settings.lock().map_err(|e| e.to_string())?;
     *current = settings;
 
@@ -32,9 +33,35 @@ pub fn set_settings(state: State<AppState>, settings: Settings) -> Result<(), St
 pub fn reset_settings(state: State<AppState>) -> Result<Settings, String> {
     let default_settings = Settings::default();
     axiom_settings::save_default(&default_settings).map_err(|e| e.to_string())?;
+    apply_tool_limits(&default_settings);
 
     let mut current = state.settings.lock().map_err(|e| e.to_string())?;
     *current = default_settings.clone();
 
     Ok(default_settings)
 }
+
+/// Apply the configured timeout and output limit to tool invocations.
+pub fn apply_tool_limits(settings: &Settings) {
+    let build = &settings.build;
+    axiom_toolchain::set_tool_limits(axiom_toolchain::ToolLimits {
+        timeout_secs: Some(build.tool_timeout_secs).filter(|&secs| secs > 0),
+        max_output_bytes: Some(build.max_tool_output_mib as usize * 1024 * 1024)
+            .filter(|&bytes| bytes > 0),
+    });
+}
+
+/// Get safe mode status: which subsystems are disabled and why.
+#[tauri::command]
+pub fn get_safe_mode_status(state: State<AppState>) -> Result<SafeModeStatus, String> {
+    let startup = state.startup.lock().map_err(|e| e.to_string())?;
+    Ok(startup.status())
+}
+
+/// Re-enable all subsystems disabled by safe mode.
+#[tauri::command]
+pub fn exit_safe_mode(state: State<AppState>) -> Result<(), String> {
+    let mut startup = state.startup.lock().map_err(|e| e.to_string())?;
+    startup.reset();
+    Ok(())
+}
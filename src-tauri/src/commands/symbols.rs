@@ -4,6 +4,7 @@
 //! Symbol command handlers.
 
 use crate::state::AppState;
+use axiom_settings::Subsystem;
 use axiom_symbols::Symbol;
 use std::path::PathBuf;
 use tauri::State;
@@ -22,6 +23,10 @@ pub fn get_completions(state: State<AppState>, prefix: String) -> Result<Vec<Sym
 /// Index a file for symbols.
 #[tauri::command]
 pub fn index_file(state: State<AppState>, path: String) -> Result<usize, String> {
+    if !state.subsystem_enabled(Subsystem::SymbolIndex) {
+        return Err("Symbol indexing is disabled in safe mode".to_string());
+    }
+
     let path_buf = PathBuf::from(&path);
 
     // Parse the file first
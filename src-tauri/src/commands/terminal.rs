@@ -5,6 +5,7 @@
 
 use crate::logging;
 use crate::state::AppState;
+use axiom_settings::Subsystem;
 use axiom_terminal::{SessionId, TerminalSize};
 use tauri::{AppHandle, Emitter, State};
 use serde::Serialize;
@@ -33,7 +34,11 @@ fn get_readers() -> &'static Mutex<HashMap<SessionId, Arc<AtomicBool>>> {
 pub fn terminal_create(state: State<AppState>, app: AppHandle) -> Result<SessionId, String> {
     logging::info("terminal", "Creating new PTY session");
     let mut manager = state.terminal_manager.lock().map_err(|e| e.to_string())?;
-    let id = manager.create_session().map_err(|e| {
+    let created = {
+        let mut startup = state.startup.lock().map_err(|e| e.to_string())?;
+        startup.run(Subsystem::TerminalAutostart, || manager.create_session())
+    };
+    let id = created.map_err(|e| {
         logging::error("terminal", &format!("Failed to create session: {}", e));
         e.to_string()
     })?;
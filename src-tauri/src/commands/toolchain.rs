@@ -4,9 +4,31 @@
 //! Toolchain command handlers.
 
 use crate::state::AppState;
-use axiom_toolchain::{CompileRequest, CompileResult, DetectedToolchain, ToolchainKind};
-use std::path::PathBuf;
-use tauri::State;
+use axiom_compliance::{load_compliance_config, ComplianceMode};
+use axiom_core::time::unix_now;
+use axiom_core::Diagnostic;
+use axiom_settings::Subsystem;
+use axiom_toolchain::{
+    load_coverage_justifications, ArchiveRequest, ArchiveResult, ArmCompileRequest, ArmLinkRequest,
+    ArmMcuConfig, AssemblyOptions, AssemblyOutput, BinaryFormat, BuildProfile, BuildReport,
+    BuildReportStore, BuildStatistics, CachedFlags, CaseStatus, CompileRequest, CompileResult,
+    ContainerConfig, CoverageFormat, CoverageHistoryStore, CoverageQuery, CoverageRecord,
+    CoverageRegression, CoverageReport, CoverageTrend, DebugInfo, DecodedRegister,
+    DetectedToolchain, DeterminismReport, DeterminismRequest, DisassemblyLine, ElfFile,
+    EnvironmentCapture, FirmwareDiff, FirmwareImage, GenerationMethod, HostTestBuild,
+    HostTestConfig, HostTestRun, InstalledToolchain, LinkResult, LinkerConfig, LinkerScript,
+    LinkerScriptOptions, LockMismatch, LogSegment, MakeCompileCommand, MakefileInfo, MakefileModel,
+    MakefileUpdate, McuInfo, McuMemory, MemoryBudget, MemoryMap, MemoryRegion, NewlyUncovered,
+    ObjectConsistencyReport, OutputReport, PackDevice, PackIndex, PreprocessorConfig,
+    QualificationBaseline, QualificationReport, RemoteSession, RemoteToolchain, RotationPolicy,
+    SizeHistoryStore, SizeQuery, SizeRecord, SizeRegression, SizeReport, SizeTrend, SourceLine,
+    StatsQuery, SvdDevice, ToolQualificationLogger, ToolUsageQuery, ToolUsageRecord, ToolchainKind,
+    ToolchainLock, WarningProfile, WeakSymbolReport,
+};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, Manager, State};
 
 /// Detect all available toolchains.
 #[tauri::command]
@@ -27,6 +49,192 @@ pub fn get_toolchains(state: State<AppState>) -> Result<Vec<DetectedToolchain>,
     Ok(toolchains.clone())
 }
 
+/// Install an ARM toolchain from an offline bundle after checking it
+/// against a SHA-256 manifest, and make it the active ARM GCC.
+#[tauri::command]
+pub fn install_toolchain_bundle(
+    state: State<AppState>,
+    archive: String,
+    manifest: String,
+) -> Result<InstalledToolchain, String> {
+    let root = axiom_toolchain::default_toolchain_dir()
+        .ok_or("Cannot determine the toolchain directory")?;
+    let installed =
+        axiom_toolchain::install_toolchain_bundle(Path::new(&archive), Path::new(&manifest), &root)
+            .map_err(|e| e.to_string())?;
+    let detected = installed
+        .detect()
+        .ok_or_else(|| format!("{} does not run on this host", installed.compiler.display()))?;
+
+    let mut toolchains = state.toolchains.lock().map_err(|e| e.to_string())?;
+    toolchains.retain(|t| t.kind != ToolchainKind::ArmGcc);
+    toolchains.push(detected);
+    Ok(installed)
+}
+
+/// Use a toolchain inside a container image in place of the detected one
+/// of the same kind.
+#[tauri::command]
+pub fn use_container_toolchain(
+    state: State<AppState>,
+    config: ContainerConfig,
+    path: String,
+    kind: ToolchainKind,
+) -> Result<DetectedToolchain, String> {
+    let detected = axiom_toolchain::detect_in_container(&config, Path::new(&path), kind)
+        .ok_or_else(|| {
+            format!(
+                "Cannot run {} in {} image {}",
+                path, config.engine, config.image
+            )
+        })?;
+
+    let mut toolchains = state.toolchains.lock().map_err(|e| e.to_string())?;
+    toolchains.retain(|t| t.kind != kind);
+    toolchains.push(detected.clone());
+    Ok(detected)
+}
+
+/// List toolchains installed from offline bundles.
+#[tauri::command]
+pub fn get_installed_toolchains() -> Result<Vec<InstalledToolchain>, String> {
+    Ok(axiom_toolchain::default_toolchain_dir()
+        .map(|root| axiom_toolchain::installed_toolchains(&root))
+        .unwrap_or_default())
+}
+
+/// Pin the detected compilers in the project's `toolchain.lock`.
+#[tauri::command]
+pub fn write_toolchain_lock(
+    state: State<AppState>,
+    project_path: String,
+) -> Result<ToolchainLock, String> {
+    let toolchains = state.toolchains.lock().map_err(|e| e.to_string())?;
+    let compilers: Vec<DetectedToolchain> = toolchains
+        .iter()
+        .filter(|t| t.kind != ToolchainKind::Python)
+        .cloned()
+        .collect();
+    let lock = ToolchainLock::capture(&compilers).map_err(|e| e.to_string())?;
+    lock.save(Path::new(&project_path))
+        .map_err(|e| e.to_string())?;
+    Ok(lock)
+}
+
+/// Compare the detected toolchains against the project's `toolchain.lock`.
+///
+/// Fails instead of returning the differences when the compliance settings
+/// enforce the lock.
+#[tauri::command]
+pub fn check_toolchain_lock(
+    state: State<AppState>,
+    project_path: String,
+) -> Result<Vec<LockMismatch>, String> {
+    let Some(lock) = ToolchainLock::load(Path::new(&project_path)).map_err(|e| e.to_string())?
+    else {
+        return Ok(Vec::new());
+    };
+    let toolchains = state.toolchains.lock().map_err(|e| e.to_string())?;
+    if lock_enforced(&state)? {
+        lock.enforce(&toolchains).map_err(|e| e.to_string())?;
+    }
+    Ok(lock.verify(&toolchains))
+}
+
+/// Whether the compliance settings refuse builds that differ from the lock.
+fn lock_enforced(state: &State<AppState>) -> Result<bool, String> {
+    let settings = state.settings.lock().map_err(|e| e.to_string())?;
+    Ok(settings.compliance.enforce_toolchain_lock)
+}
+
+/// Run the operational verification suite against the ARM toolchain,
+/// comparing with the project's baseline if it has one.
+#[tauri::command]
+pub fn run_toolchain_qualification(
+    state: State<AppState>,
+    project_path: String,
+) -> Result<QualificationReport, String> {
+    let baseline =
+        QualificationBaseline::load(Path::new(&project_path)).map_err(|e| e.to_string())?;
+    let toolchains = state.toolchains.lock().map_err(|e| e.to_string())?;
+    let toolchain = toolchains
+        .iter()
+        .find(|t| t.kind == ToolchainKind::ArmGcc)
+        .ok_or_else(|| "ARM GCC toolchain not found".to_string())?;
+
+    Ok(axiom_toolchain::run_qualification(
+        toolchain,
+        &axiom_toolchain::standard_cases(),
+        baseline.as_ref(),
+        unix_now(),
+    ))
+}
+
+/// Run the operational verification suite and record its checksums as the
+/// project's baseline.
+#[tauri::command]
+pub fn record_qualification_baseline(
+    state: State<AppState>,
+    project_path: String,
+) -> Result<QualificationReport, String> {
+    let toolchains = state.toolchains.lock().map_err(|e| e.to_string())?;
+    let toolchain = toolchains
+        .iter()
+        .find(|t| t.kind == ToolchainKind::ArmGcc)
+        .ok_or_else(|| "ARM GCC toolchain not found".to_string())?;
+
+    let report = axiom_toolchain::run_qualification(
+        toolchain,
+        &axiom_toolchain::standard_cases(),
+        None,
+        unix_now(),
+    );
+    if let Some(error) = report
+        .results
+        .iter()
+        .find(|r| r.status == CaseStatus::Error)
+    {
+        return Err(format!(
+            "{} failed to build: {}",
+            error.id,
+            error.message.as_deref().unwrap_or_default()
+        ));
+    }
+    QualificationBaseline::from_report(&report)
+        .save(Path::new(&project_path))
+        .map_err(|e| e.to_string())?;
+    Ok(report)
+}
+
+/// Tool usage log records matching a query, in log order.
+#[tauri::command]
+pub fn query_tool_usage(
+    project_path: String,
+    query: ToolUsageQuery,
+) -> Result<Vec<ToolUsageRecord>, String> {
+    ToolQualificationLogger::for_project(Path::new(&project_path))
+        .query(&query)
+        .map_err(|e| e.to_string())
+}
+
+/// Rehash every output file in the tool usage log and compare it with the
+/// logged checksum.
+#[tauri::command]
+pub fn verify_tool_outputs(project_path: String) -> Result<OutputReport, String> {
+    let root = Path::new(&project_path);
+    ToolQualificationLogger::for_project(root)
+        .verify_outputs(root)
+        .map_err(|e| e.to_string())
+}
+
+/// Rotate the tool usage log into a new compressed segment now.
+#[tauri::command]
+pub fn rotate_tool_log(project_path: String) -> Result<Option<LogSegment>, String> {
+    ToolQualificationLogger::for_project(Path::new(&project_path))
+        .rotate()
+        .map_err(|e| e.to_string())
+}
+
 /// Compile a file.
 #[tauri::command]
 pub fn compile_file(
@@ -34,6 +242,7 @@ pub fn compile_file(
     source: String,
     output: String,
     toolchain_kind: Option<String>,
+    profile: Option<String>,
 ) -> Result<CompileResult, String> {
     let toolchains = state.toolchains.lock().map_err(|e| e.to_string())?;
 
@@ -53,8 +262,31 @@ pub fn compile_file(
         .find(|t| t.kind == kind)
         .ok_or_else(|| format!("Toolchain {:?} not found", kind))?;
 
-    let request = CompileRequest::new(PathBuf::from(source), PathBuf::from(output));
-    let result = axiom_toolchain::compile(toolchain, &request);
+    let mut request = apply_warning_profile(
+        &state,
+        CompileRequest::new(PathBuf::from(source), PathBuf::from(output)),
+    )?;
+    if let Some(profile) = build_profile(&state, profile)? {
+        request = request.with_profile(&profile);
+    }
+    if let Some(capture) = environment_capture(&state)? {
+        request = request.with_environment_capture(capture);
+    }
+    if let Some(log) = usage_log(&state)? {
+        request = request.with_usage_log(log);
+    }
+    let mut result = axiom_toolchain::compile(toolchain, &request);
+    if let Some(root) = open_project(&state)? {
+        let report = BuildReport::from_results(unix_now(), std::slice::from_ref(&result), 0);
+        record_build_report(&root, &report, &mut result.diagnostics);
+    }
+
+    if let Ok(mut checker) = state.save_checker.lock() {
+        checker.cache_flags(
+            request.source.clone(),
+            CachedFlags::from_compile(toolchain, &request),
+        );
+    }
 
     Ok(result)
 }
@@ -66,6 +298,7 @@ pub fn compile_dry_run(
     source: String,
     output: String,
     toolchain_kind: Option<String>,
+    profile: Option<String>,
 ) -> Result<String, String> {
     let toolchains = state.toolchains.lock().map_err(|e| e.to_string())?;
 
@@ -84,8 +317,960 @@ pub fn compile_dry_run(
         .find(|t| t.kind == kind)
         .ok_or_else(|| format!("Toolchain {:?} not found", kind))?;
 
-    let request = CompileRequest::new(PathBuf::from(source), PathBuf::from(output));
+    let mut request = apply_warning_profile(
+        &state,
+        CompileRequest::new(PathBuf::from(source), PathBuf::from(output)),
+    )?;
+    if let Some(profile) = build_profile(&state, profile)? {
+        request = request.with_profile(&profile);
+    }
     let command = axiom_toolchain::dry_run(toolchain, &request);
 
     Ok(command)
 }
+
+/// Full argv an ARM compile would run, without executing it.
+#[tauri::command]
+pub fn arm_compile_dry_run(
+    state: State<AppState>,
+    source: String,
+    output: String,
+    mcu: ArmMcuConfig,
+    profile: Option<String>,
+) -> Result<Vec<String>, String> {
+    let toolchains = state.toolchains.lock().map_err(|e| e.to_string())?;
+    let toolchain = toolchains
+        .iter()
+        .find(|t| t.kind == ToolchainKind::ArmGcc)
+        .ok_or_else(|| "ARM GCC toolchain not found".to_string())?;
+
+    let mut request = ArmCompileRequest::new(PathBuf::from(source), PathBuf::from(output), mcu);
+    if let Some(profile) = build_profile(&state, profile)? {
+        request = request.with_profile(&profile);
+    }
+    let settings = state.settings.lock().map_err(|e| e.to_string())?;
+    if let Some(name) = settings.build.warning_profile.as_deref() {
+        let profile = WarningProfile::from_name(name)
+            .ok_or_else(|| format!("Unknown warning profile: {}", name))?;
+        request = request.with_warning_profile(profile);
+    }
+    Ok(axiom_toolchain::dry_run_arm(toolchain, &request))
+}
+
+/// Full argv a firmware link would run, without executing it.
+#[tauri::command]
+pub fn link_dry_run(
+    state: State<AppState>,
+    objects: Vec<String>,
+    output: String,
+    mcu: ArmMcuConfig,
+    linker: LinkerConfig,
+    profile: Option<String>,
+) -> Result<Vec<String>, String> {
+    let toolchains = state.toolchains.lock().map_err(|e| e.to_string())?;
+    let toolchain = toolchains
+        .iter()
+        .find(|t| t.kind == ToolchainKind::ArmGcc)
+        .ok_or_else(|| "ARM GCC toolchain not found".to_string())?;
+
+    let mut request = ArmLinkRequest::new(
+        objects.into_iter().map(PathBuf::from).collect(),
+        PathBuf::from(output),
+        mcu,
+        linker,
+    );
+    if let Some(profile) = build_profile(&state, profile)? {
+        request = request.with_profile(&profile);
+    }
+    Ok(axiom_toolchain::dry_run_arm_link(toolchain, &request))
+}
+
+/// Apply the warning profile selected in settings to a compile request.
+fn apply_warning_profile(
+    state: &State<AppState>,
+    request: CompileRequest,
+) -> Result<CompileRequest, String> {
+    let settings = state.settings.lock().map_err(|e| e.to_string())?;
+
+    match settings.build.warning_profile.as_deref() {
+        None => Ok(request),
+        Some(name) => WarningProfile::from_name(name)
+            .map(|profile| request.with_warning_profile(profile))
+            .ok_or_else(|| format!("Unknown warning profile: {}", name)),
+    }
+}
+
+/// The named build profile, or the one selected in settings, looked up in
+/// the open project's profiles.
+fn build_profile(
+    state: &State<AppState>,
+    name: Option<String>,
+) -> Result<Option<BuildProfile>, String> {
+    let name = match name {
+        Some(name) => name,
+        None => {
+            let settings = state.settings.lock().map_err(|e| e.to_string())?;
+            match settings.build.profile.clone() {
+                Some(name) => name,
+                None => return Ok(None),
+            }
+        }
+    };
+    let project = state.project_path.lock().map_err(|e| e.to_string())?;
+    axiom_toolchain::find_build_profile(project.as_deref(), &name)
+        .map(Some)
+        .map_err(|e| e.to_string())
+}
+
+/// Environment capture selected by the compliance settings, if enabled.
+fn environment_capture(state: &State<AppState>) -> Result<Option<EnvironmentCapture>, String> {
+    if !state.subsystem_enabled(Subsystem::Compliance) {
+        return Ok(None);
+    }
+
+    let settings = state.settings.lock().map_err(|e| e.to_string())?;
+    let compliance = &settings.compliance;
+    if !compliance.capture_environment {
+        return Ok(None);
+    }
+
+    let mut capture = EnvironmentCapture::default().with_hash_toolchain(compliance.hash_toolchain);
+    if !compliance.captured_variables.is_empty() {
+        capture = capture.with_variables(compliance.captured_variables.iter().cloned());
+    }
+    Ok(Some(capture))
+}
+
+/// Tool usage log of the open project, if its compliance configuration
+/// enables DO-330, rotating as the compliance settings say.
+fn usage_log(state: &State<AppState>) -> Result<Option<ToolQualificationLogger>, String> {
+    if !state.subsystem_enabled(Subsystem::Compliance) {
+        return Ok(None);
+    }
+
+    let project = state.project_path.lock().map_err(|e| e.to_string())?;
+    let Some(root) = project.as_deref() else {
+        return Ok(None);
+    };
+    let config = load_compliance_config(root).map_err(|e| e.to_string())?;
+    if !config.is_enabled(ComplianceMode::Do330) {
+        return Ok(None);
+    }
+
+    let settings = state.settings.lock().map_err(|e| e.to_string())?;
+    let mut policy = RotationPolicy::default();
+    if let Some(mb) = settings.compliance.tool_log_max_mb {
+        policy = policy.with_max_bytes(mb.saturating_mul(1024 * 1024));
+    }
+    if let Some(days) = settings.compliance.tool_log_max_age_days {
+        policy = policy.with_max_age_secs(days.saturating_mul(24 * 60 * 60));
+    }
+    Ok(Some(
+        ToolQualificationLogger::for_project(root).with_rotation(policy),
+    ))
+}
+
+/// Root of the open project, if any.
+fn open_project(state: &State<AppState>) -> Result<Option<PathBuf>, String> {
+    let project = state.project_path.lock().map_err(|e| e.to_string())?;
+    Ok(project.clone())
+}
+
+/// Append a build report to a project's history for the build statistics,
+/// warning in `diagnostics` if it can't be saved.
+fn record_build_report(root: &Path, report: &BuildReport, diagnostics: &mut Vec<Diagnostic>) {
+    if let Err(e) = BuildReportStore::for_project(root).append(report) {
+        diagnostics.push(Diagnostic::warning(format!(
+            "Build report not saved: {}",
+            e
+        )));
+    }
+}
+
+/// Get aggregated build statistics for a project.
+#[tauri::command]
+pub fn get_build_statistics(
+    project_path: String,
+    query: Option<StatsQuery>,
+) -> Result<BuildStatistics, String> {
+    let store = BuildReportStore::for_project(Path::new(&project_path));
+    let reports = store.load().map_err(|e| e.to_string())?;
+    let query = query.unwrap_or_default();
+
+    Ok(axiom_toolchain::compute_build_statistics(&reports, &query))
+}
+
+/// Verify that object files were built with compatible settings before linking.
+#[tauri::command]
+pub fn check_object_consistency(objects: Vec<String>) -> Result<ObjectConsistencyReport, String> {
+    let objects: Vec<PathBuf> = objects.into_iter().map(PathBuf::from).collect();
+    axiom_toolchain::check_object_consistency(&objects).map_err(|e| e.to_string())
+}
+
+/// Link objects into an ARM executable, parsing the map file if requested
+/// and checking the image against the budget if given.
+#[tauri::command]
+pub fn link_firmware(
+    state: State<AppState>,
+    objects: Vec<String>,
+    output: String,
+    mcu: ArmMcuConfig,
+    linker: LinkerConfig,
+    budget: Option<MemoryBudget>,
+    profile: Option<String>,
+) -> Result<LinkResult, String> {
+    let toolchains = state.toolchains.lock().map_err(|e| e.to_string())?;
+    let toolchain = toolchains
+        .iter()
+        .find(|t| t.kind == ToolchainKind::ArmGcc)
+        .ok_or_else(|| "ARM GCC toolchain not found".to_string())?;
+
+    let mut request = ArmLinkRequest::new(
+        objects.into_iter().map(PathBuf::from).collect(),
+        PathBuf::from(output),
+        mcu,
+        linker,
+    );
+    if let Some(capture) = environment_capture(&state)? {
+        request = request.with_environment_capture(capture);
+    }
+    if let Some(budget) = budget {
+        request = request.with_budget(budget);
+    }
+    if let Some(profile) = build_profile(&state, profile)? {
+        request = request.with_profile(&profile);
+    }
+    if let Some(log) = usage_log(&state)? {
+        request = request.with_usage_log(log);
+    }
+    let mut result = axiom_toolchain::link_arm(toolchain, &request);
+    if let Some(root) = open_project(&state)? {
+        let report = BuildReport::from_link(unix_now(), &result);
+        record_build_report(&root, &report, &mut result.diagnostics);
+    }
+    Ok(result)
+}
+
+/// Build firmware twice with reproducible flags and report any artifact
+/// whose hash differs between the builds. Objects are written next to the
+/// ELF.
+#[tauri::command]
+pub fn verify_determinism(
+    state: State<AppState>,
+    project_path: String,
+    sources: Vec<String>,
+    output: String,
+    mcu: ArmMcuConfig,
+    linker: LinkerConfig,
+    source_date_epoch: Option<u64>,
+) -> Result<DeterminismReport, String> {
+    let toolchains = state.toolchains.lock().map_err(|e| e.to_string())?;
+    let toolchain = toolchains
+        .iter()
+        .find(|t| t.kind == ToolchainKind::ArmGcc)
+        .ok_or_else(|| "ARM GCC toolchain not found".to_string())?;
+
+    let output = PathBuf::from(output);
+    let compiles: Vec<ArmCompileRequest> = sources
+        .into_iter()
+        .map(PathBuf::from)
+        .map(|source| {
+            let stem = source.file_stem().unwrap_or_default().to_os_string();
+            let object = output.with_file_name(stem).with_extension("o");
+            ArmCompileRequest::new(source, object, mcu.clone())
+        })
+        .collect();
+    let link = ArmLinkRequest::new(
+        compiles.iter().map(|c| c.output.clone()).collect(),
+        output,
+        mcu,
+        linker,
+    );
+    let mut request = DeterminismRequest::new(PathBuf::from(project_path), compiles, link);
+    if let Some(epoch) = source_date_epoch {
+        request = request.with_source_date_epoch(epoch);
+    }
+    axiom_toolchain::verify_determinism(toolchain, &request).map_err(|e| e.to_string())
+}
+
+/// List the remote toolchains declared for a project.
+#[tauri::command]
+pub fn get_remote_toolchains(project_path: String) -> Result<Vec<RemoteToolchain>, String> {
+    axiom_toolchain::load_remote_toolchains(Path::new(&project_path)).map_err(|e| e.to_string())
+}
+
+/// Compile a file with a project's remote toolchain over SSH.
+#[tauri::command]
+pub fn compile_file_remote(
+    state: State<AppState>,
+    project_path: String,
+    toolchain: String,
+    source: String,
+    output: String,
+) -> Result<CompileResult, String> {
+    let session = remote_session(&project_path, &toolchain)?;
+    let request = apply_warning_profile(
+        &state,
+        CompileRequest::new(PathBuf::from(source), PathBuf::from(output)),
+    )?;
+    let mut result = session.compile(&request).map_err(|e| e.to_string())?;
+    let report = BuildReport::from_results(unix_now(), std::slice::from_ref(&result), 0);
+    record_build_report(Path::new(&project_path), &report, &mut result.diagnostics);
+    Ok(result)
+}
+
+/// Link objects into an ARM executable with a project's remote toolchain,
+/// checking the image against the project's memory budget.
+#[tauri::command]
+pub fn link_firmware_remote(
+    project_path: String,
+    toolchain: String,
+    objects: Vec<String>,
+    output: String,
+    mcu: ArmMcuConfig,
+    linker: LinkerConfig,
+) -> Result<LinkResult, String> {
+    let session = remote_session(&project_path, &toolchain)?;
+    let budget =
+        axiom_toolchain::load_memory_budget(Path::new(&project_path)).map_err(|e| e.to_string())?;
+    let request = ArmLinkRequest::new(
+        objects.into_iter().map(PathBuf::from).collect(),
+        PathBuf::from(output),
+        mcu,
+        linker,
+    )
+    .with_budget(budget);
+    let mut result = session.link_arm(&request).map_err(|e| e.to_string())?;
+    let report = BuildReport::from_link(unix_now(), &result);
+    record_build_report(Path::new(&project_path), &report, &mut result.diagnostics);
+    Ok(result)
+}
+
+/// Load a project's flash and RAM budgets from `.axiom/memory-budget.toml`.
+#[tauri::command]
+pub fn get_memory_budget(project_path: String) -> Result<MemoryBudget, String> {
+    axiom_toolchain::load_memory_budget(Path::new(&project_path)).map_err(|e| e.to_string())
+}
+
+fn remote_session(project_path: &str, name: &str) -> Result<RemoteSession, String> {
+    let root = Path::new(project_path);
+    let remote = axiom_toolchain::find_remote_toolchain(root, name).map_err(|e| e.to_string())?;
+    RemoteSession::connect(remote, root).map_err(|e| e.to_string())
+}
+
+/// Build a static library from compiled objects with the ARM toolchain's ar.
+#[tauri::command]
+pub fn build_static_library(
+    state: State<AppState>,
+    request: ArchiveRequest,
+) -> Result<ArchiveResult, String> {
+    let toolchains = state.toolchains.lock().map_err(|e| e.to_string())?;
+    let toolchain = toolchains
+        .iter()
+        .find(|t| t.kind == ToolchainKind::ArmGcc)
+        .ok_or_else(|| "ARM GCC toolchain not found".to_string())?;
+    Ok(axiom_toolchain::create_archive(toolchain, &request))
+}
+
+/// Read sections, segments and symbols from an ELF file.
+#[tauri::command]
+pub fn read_elf(path: String) -> Result<ElfFile, String> {
+    ElfFile::read(Path::new(&path)).map_err(|e| e.to_string())
+}
+
+/// Map an address in a built ELF to its source line.
+#[tauri::command]
+pub fn source_for_address(elf_path: String, address: u64) -> Result<Option<SourceLine>, String> {
+    let info = DebugInfo::read(Path::new(&elf_path)).map_err(|e| e.to_string())?;
+    Ok(info.source_for(address))
+}
+
+/// Breakpoint addresses for a source line in a built ELF.
+#[tauri::command]
+pub fn addresses_for_line(elf_path: String, file: String, line: u32) -> Result<Vec<u64>, String> {
+    let info = DebugInfo::read(Path::new(&elf_path)).map_err(|e| e.to_string())?;
+    Ok(info.addresses_for(Path::new(&file), line))
+}
+
+/// Disassemble a built ARM image, or one function in it, without objdump.
+///
+/// Source lines are included when the image has DWARF line tables.
+#[tauri::command]
+pub fn disassemble_elf(
+    elf_path: String,
+    function: Option<String>,
+) -> Result<Vec<DisassemblyLine>, String> {
+    let elf = ElfFile::read(Path::new(&elf_path)).map_err(|e| e.to_string())?;
+    let debug = DebugInfo::from_elf(&elf).ok();
+    axiom_toolchain::disassemble(&elf, debug.as_ref(), function.as_deref())
+        .map_err(|e| e.to_string())
+}
+
+/// Show which weak symbol definitions were overridden in a linked image.
+#[tauri::command]
+pub fn analyze_weak_symbols(
+    objects: Vec<String>,
+    elf_path: String,
+) -> Result<WeakSymbolReport, String> {
+    let objects: Vec<PathBuf> = objects.into_iter().map(PathBuf::from).collect();
+    axiom_toolchain::analyze_weak_symbols(&objects, Path::new(&elf_path)).map_err(|e| e.to_string())
+}
+
+/// Parse a GNU ld map file.
+#[tauri::command]
+pub fn read_map_file(path: String) -> Result<MemoryMap, String> {
+    axiom_toolchain::read_map_file(Path::new(&path)).map_err(|e| e.to_string())
+}
+
+/// Write a firmware image from an ELF, falling back to objcopy if needed.
+#[tauri::command]
+pub fn generate_firmware_image(
+    state: State<AppState>,
+    elf_path: String,
+    output: String,
+    format: BinaryFormat,
+) -> Result<GenerationMethod, String> {
+    let toolchains = state.toolchains.lock().map_err(|e| e.to_string())?;
+    let toolchain = toolchains.iter().find(|t| t.kind == ToolchainKind::ArmGcc);
+    axiom_toolchain::generate_image(
+        toolchain,
+        Path::new(&elf_path),
+        Path::new(&output),
+        format,
+        usage_log(&state)?.as_ref(),
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Read an Intel HEX or S-record firmware image.
+#[tauri::command]
+pub fn read_firmware_image(path: String) -> Result<FirmwareImage, String> {
+    axiom_toolchain::read_image(Path::new(&path)).map_err(|e| e.to_string())
+}
+
+/// Structurally diff two ELF, Intel HEX or S-record builds.
+#[tauri::command]
+pub fn diff_firmware(old_path: String, new_path: String) -> Result<FirmwareDiff, String> {
+    axiom_toolchain::diff_firmware_files(Path::new(&old_path), Path::new(&new_path))
+        .map_err(|e| e.to_string())
+}
+
+/// Load the project's host test configuration.
+#[tauri::command]
+pub fn get_host_test_config(project_path: String) -> Result<HostTestConfig, String> {
+    axiom_toolchain::load_host_test_config(Path::new(&project_path)).map_err(|e| e.to_string())
+}
+
+/// Compile sources for the host with the configured mocks and link a test
+/// executable.
+#[tauri::command]
+pub fn build_host_tests(
+    state: State<AppState>,
+    project_path: String,
+    sources: Vec<String>,
+    name: String,
+) -> Result<HostTestBuild, String> {
+    let root = Path::new(&project_path);
+    let config = axiom_toolchain::load_host_test_config(root).map_err(|e| e.to_string())?;
+    let toolchains = state.toolchains.lock().map_err(|e| e.to_string())?;
+    if lock_enforced(&state)? {
+        if let Some(lock) = ToolchainLock::load(root).map_err(|e| e.to_string())? {
+            lock.enforce(&toolchains).map_err(|e| e.to_string())?;
+        }
+    }
+    let toolchain = toolchains
+        .iter()
+        .find(|t| t.kind == config.compiler)
+        .ok_or_else(|| format!("Toolchain {:?} not found", config.compiler))?;
+
+    let sources: Vec<PathBuf> = sources.into_iter().map(PathBuf::from).collect();
+    axiom_toolchain::build_host_tests(toolchain, &config, root, &sources, &name)
+        .map_err(|e| e.to_string())
+}
+
+/// Build and run host tests, collecting per-case results as JUnit XML and,
+/// when instrumented, gcov coverage of the sources under test.
+#[tauri::command]
+pub fn run_host_tests(
+    state: State<AppState>,
+    project_path: String,
+    sources: Vec<String>,
+    name: String,
+    coverage: bool,
+) -> Result<HostTestRun, String> {
+    let root = Path::new(&project_path);
+    let config = axiom_toolchain::load_host_test_config(root).map_err(|e| e.to_string())?;
+    let toolchains = state.toolchains.lock().map_err(|e| e.to_string())?;
+    if lock_enforced(&state)? {
+        if let Some(lock) = ToolchainLock::load(root).map_err(|e| e.to_string())? {
+            lock.enforce(&toolchains).map_err(|e| e.to_string())?;
+        }
+    }
+    let toolchain = toolchains
+        .iter()
+        .find(|t| t.kind == config.compiler)
+        .ok_or_else(|| format!("Toolchain {:?} not found", config.compiler))?;
+
+    let sources: Vec<PathBuf> = sources.into_iter().map(PathBuf::from).collect();
+    axiom_toolchain::run_host_tests(toolchain, &config, root, &sources, &name, coverage)
+        .map_err(|e| e.to_string())
+}
+
+/// Build a coverage report from gcov text, gcov JSON, gcovr JSON or lcov
+/// files, applying the project's coverage justifications if a project is
+/// given.
+#[tauri::command]
+pub fn generate_coverage_report(
+    paths: Vec<String>,
+    format: Option<CoverageFormat>,
+    project_path: Option<String>,
+) -> Result<CoverageReport, String> {
+    let paths: Vec<PathBuf> = paths.into_iter().map(PathBuf::from).collect();
+    let justifications = match project_path {
+        Some(root) => load_coverage_justifications(Path::new(&root)).map_err(|e| e.to_string())?,
+        None => Vec::new(),
+    };
+    axiom_toolchain::generate_coverage_report(&paths, format, &justifications)
+        .map_err(|e| e.to_string())
+}
+
+/// Generate a coverage report and record it against the current commit.
+#[tauri::command]
+pub fn record_coverage(
+    project_path: String,
+    paths: Vec<String>,
+    format: Option<CoverageFormat>,
+    config: String,
+) -> Result<CoverageRecord, String> {
+    let root = Path::new(&project_path);
+    let paths: Vec<PathBuf> = paths.into_iter().map(PathBuf::from).collect();
+    let justifications = load_coverage_justifications(root).map_err(|e| e.to_string())?;
+    let report = axiom_toolchain::generate_coverage_report(&paths, format, &justifications)
+        .map_err(|e| e.to_string())?;
+    let commit = axiom_git::Repository::discover(root)
+        .ok()
+        .and_then(|repo| repo.last_commit().ok().flatten())
+        .map(|c| c.id);
+    CoverageHistoryStore::for_project(root)
+        .record_report(&report, unix_now(), commit, &config)
+        .map_err(|e| e.to_string())
+}
+
+/// Per-configuration coverage trends from the project's coverage history.
+#[tauri::command]
+pub fn get_coverage_trends(
+    project_path: String,
+    query: Option<CoverageQuery>,
+) -> Result<Vec<CoverageTrend>, String> {
+    let records = CoverageHistoryStore::for_project(Path::new(&project_path))
+        .load()
+        .map_err(|e| e.to_string())?;
+    Ok(axiom_toolchain::coverage_trends(
+        &records,
+        &query.unwrap_or_default(),
+    ))
+}
+
+/// Runs whose line or branch coverage fell by more than `threshold`
+/// percentage points.
+#[tauri::command]
+pub fn get_coverage_regressions(
+    project_path: String,
+    query: Option<CoverageQuery>,
+    threshold: f64,
+) -> Result<Vec<CoverageRegression>, String> {
+    let records = CoverageHistoryStore::for_project(Path::new(&project_path))
+        .load()
+        .map_err(|e| e.to_string())?;
+    Ok(axiom_toolchain::coverage_regressions(
+        &records,
+        &query.unwrap_or_default(),
+        threshold,
+    ))
+}
+
+/// Lines uncovered in the latest run of `config` that were not uncovered
+/// at the baseline commit.
+#[tauri::command]
+pub fn get_newly_uncovered(
+    project_path: String,
+    baseline_commit: String,
+    config: String,
+) -> Result<Vec<NewlyUncovered>, String> {
+    let records = CoverageHistoryStore::for_project(Path::new(&project_path))
+        .load()
+        .map_err(|e| e.to_string())?;
+    axiom_toolchain::uncovered_since(&records, &baseline_commit, &config).ok_or_else(|| {
+        format!(
+            "No coverage recorded for {} at commit {}",
+            config, baseline_commit
+        )
+    })
+}
+
+/// Record the size of a linked image against the current commit.
+#[tauri::command]
+pub fn record_build_size(
+    project_path: String,
+    elf_path: String,
+    config: String,
+) -> Result<SizeRecord, String> {
+    let root = Path::new(&project_path);
+    let commit = axiom_git::Repository::discover(root)
+        .ok()
+        .and_then(|repo| repo.last_commit().ok().flatten())
+        .map(|c| c.id);
+    SizeHistoryStore::for_project(root)
+        .record_elf(Path::new(&elf_path), unix_now(), commit, &config)
+        .map_err(|e| e.to_string())
+}
+
+/// Per-configuration size trends from the project's size history.
+#[tauri::command]
+pub fn get_size_trends(
+    project_path: String,
+    query: Option<SizeQuery>,
+) -> Result<Vec<SizeTrend>, String> {
+    let records = SizeHistoryStore::for_project(Path::new(&project_path))
+        .load()
+        .map_err(|e| e.to_string())?;
+    Ok(axiom_toolchain::size_trends(
+        &records,
+        &query.unwrap_or_default(),
+    ))
+}
+
+/// Builds whose flash or RAM use grew by more than `threshold` bytes.
+#[tauri::command]
+pub fn get_size_regressions(
+    project_path: String,
+    query: Option<SizeQuery>,
+    threshold: u64,
+) -> Result<Vec<SizeRegression>, String> {
+    let records = SizeHistoryStore::for_project(Path::new(&project_path))
+        .load()
+        .map_err(|e| e.to_string())?;
+    Ok(axiom_toolchain::size_regressions(
+        &records,
+        &query.unwrap_or_default(),
+        threshold,
+    ))
+}
+
+/// Section sizes of a linked image; with `detailed`, also per-symbol sizes
+/// and, given the map file, per-object sizes, each sorted largest first.
+#[tauri::command]
+pub fn get_size_stats(
+    elf_path: String,
+    map_path: Option<String>,
+    detailed: Option<bool>,
+) -> Result<SizeReport, String> {
+    axiom_toolchain::size_report(
+        Path::new(&elf_path),
+        map_path.as_deref().map(Path::new),
+        detailed.unwrap_or(false),
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Parse a linker script's MEMORY and SECTIONS commands.
+#[tauri::command]
+pub fn read_linker_script(path: String) -> Result<LinkerScript, String> {
+    axiom_toolchain::read_linker_script(Path::new(&path)).map_err(|e| e.to_string())
+}
+
+/// Check a linker script for undefined and overlapping regions.
+#[tauri::command]
+pub fn validate_linker_script(path: String) -> Result<Vec<Diagnostic>, String> {
+    let path = Path::new(&path);
+    let script = axiom_toolchain::read_linker_script(path).map_err(|e| e.to_string())?;
+    Ok(script.validate(path))
+}
+
+/// List the parts in the built-in MCU database.
+#[tauri::command]
+pub fn list_mcus(query: Option<String>) -> Vec<McuInfo> {
+    match query {
+        Some(query) => axiom_toolchain::search_mcus(&query),
+        None => axiom_toolchain::list_mcus(),
+    }
+}
+
+/// Look up a part number in the built-in MCU database.
+#[tauri::command]
+pub fn get_mcu(part: String) -> Option<McuInfo> {
+    axiom_toolchain::lookup_mcu(&part)
+}
+
+/// Index installed CMSIS packs under the default pack roots and any extra
+/// roots, replacing the cached index.
+#[tauri::command]
+pub fn scan_packs(state: State<AppState>, roots: Option<Vec<String>>) -> Result<PackIndex, String> {
+    let mut all_roots = axiom_toolchain::default_pack_roots();
+    all_roots.extend(roots.unwrap_or_default().into_iter().map(PathBuf::from));
+    let index = PackIndex::scan(&all_roots);
+
+    let mut cached = state.pack_index.lock().map_err(|e| e.to_string())?;
+    *cached = Some(index.clone());
+    Ok(index)
+}
+
+/// Look up a device in the pack index, scanning the default roots if no
+/// index has been built yet.
+#[tauri::command]
+pub fn get_pack_device(state: State<AppState>, name: String) -> Result<Option<PackDevice>, String> {
+    let mut cached = state.pack_index.lock().map_err(|e| e.to_string())?;
+    let index = cached.get_or_insert_with(PackIndex::scan_default);
+    Ok(index.device(&name).cloned())
+}
+
+/// Search pack devices by name, family, or vendor.
+#[tauri::command]
+pub fn search_pack_devices(
+    state: State<AppState>,
+    query: String,
+) -> Result<Vec<PackDevice>, String> {
+    let mut cached = state.pack_index.lock().map_err(|e| e.to_string())?;
+    let index = cached.get_or_insert_with(PackIndex::scan_default);
+    Ok(index.search(&query).into_iter().cloned().collect())
+}
+
+/// Parse an SVD file into peripheral and register definitions.
+#[tauri::command]
+pub fn load_svd(path: String) -> Result<SvdDevice, String> {
+    axiom_toolchain::read_svd(Path::new(&path)).map_err(|e| e.to_string())
+}
+
+/// Decode a register value using an SVD file.
+#[tauri::command]
+pub fn decode_svd_register(
+    svd_path: String,
+    address: u64,
+    value: u64,
+) -> Result<Option<DecodedRegister>, String> {
+    let device = axiom_toolchain::read_svd(Path::new(&svd_path)).map_err(|e| e.to_string())?;
+    Ok(device.decode(address, value))
+}
+
+/// Generate a baseline linker script for an MCU memory layout.
+#[tauri::command]
+pub fn generate_linker_script(
+    memory: McuMemory,
+    options: Option<LinkerScriptOptions>,
+) -> Result<String, String> {
+    axiom_toolchain::generate_linker_script(&memory, &options.unwrap_or_default())
+        .map_err(|e| e.to_string())
+}
+
+/// Memory regions of a linker script with usage from a linked ELF.
+#[tauri::command]
+pub fn get_memory_layout(
+    script_path: String,
+    elf_path: String,
+) -> Result<Vec<MemoryRegion>, String> {
+    let script =
+        axiom_toolchain::read_linker_script(Path::new(&script_path)).map_err(|e| e.to_string())?;
+    let elf = ElfFile::read(Path::new(&elf_path)).map_err(|e| e.to_string())?;
+    Ok(script.usage_from_elf(&elf))
+}
+
+/// Check-on-save event payload.
+#[derive(Clone, Serialize)]
+struct SaveCheckResult {
+    path: String,
+    result: CompileResult,
+}
+
+/// Recompile a saved file after the debounce window and emit its diagnostics.
+///
+/// Uses the flags from the file's last compile; files that have not been
+/// compiled yet are checked with the default Clang flags.
+#[tauri::command]
+pub fn check_on_save(app: AppHandle, state: State<AppState>, path: String) -> Result<(), String> {
+    let source = PathBuf::from(&path);
+    let (ticket, window) = {
+        let mut checker = state.save_checker.lock().map_err(|e| e.to_string())?;
+        (checker.notify_saved(&source), checker.window())
+    };
+
+    std::thread::spawn(move || {
+        std::thread::sleep(window);
+
+        let state = app.state::<AppState>();
+        let flags = {
+            let Ok(checker) = state.save_checker.lock() else {
+                return;
+            };
+            // A newer save supersedes this one
+            if !checker.is_current(&source, ticket) {
+                return;
+            }
+            checker.cached_flags(&source).cloned()
+        };
+
+        let flags = match flags {
+            Some(flags) => flags,
+            None => {
+                let Ok(toolchains) = state.toolchains.lock() else {
+                    return;
+                };
+                let Some(toolchain) = toolchains.iter().find(|t| t.kind == ToolchainKind::Clang)
+                else {
+                    return;
+                };
+                let request = CompileRequest::new(source.clone(), PathBuf::from("check.o"));
+                CachedFlags::from_compile(toolchain, &request)
+            }
+        };
+
+        let result = axiom_toolchain::check_source(&source, &flags);
+        let _ = app.emit("save-check-result", SaveCheckResult { path, result });
+    });
+
+    Ok(())
+}
+
+/// Resolved defines, include search order and built-in macros for a file.
+///
+/// Uses the flags from the file's last compile, or the default Clang flags
+/// for files that have not been compiled yet.
+#[tauri::command]
+pub fn probe_preprocessor(
+    state: State<AppState>,
+    path: String,
+) -> Result<PreprocessorConfig, String> {
+    let source = PathBuf::from(&path);
+    let flags = source_flags(&state, &source)?;
+    axiom_toolchain::probe_preprocessor(&source, &flags).map_err(|e| e.to_string())
+}
+
+/// Assembly for a file, optionally interleaved with its source lines and
+/// with C++ symbols demangled.
+///
+/// Uses the flags from the file's last compile, or the default Clang flags
+/// for files that have not been compiled yet.
+#[tauri::command]
+pub fn get_assembly_output(
+    state: State<AppState>,
+    path: String,
+    interleave_source: Option<bool>,
+    demangle: Option<bool>,
+) -> Result<AssemblyOutput, String> {
+    let source = PathBuf::from(&path);
+    let flags = source_flags(&state, &source)?;
+    let defaults = AssemblyOptions::default();
+    let options = defaults
+        .with_interleave_source(interleave_source.unwrap_or(defaults.interleave_source))
+        .with_demangle(demangle.unwrap_or(defaults.demangle));
+    axiom_toolchain::generate_assembly(&source, &flags, options).map_err(|e| e.to_string())
+}
+
+/// Flags from a file's last compile, or the default Clang flags.
+fn source_flags(state: &AppState, source: &Path) -> Result<CachedFlags, String> {
+    let cached = {
+        let checker = state.save_checker.lock().map_err(|e| e.to_string())?;
+        checker.cached_flags(source).cloned()
+    };
+    if let Some(flags) = cached {
+        return Ok(flags);
+    }
+    let toolchains = state.toolchains.lock().map_err(|e| e.to_string())?;
+    let toolchain = toolchains
+        .iter()
+        .find(|t| t.kind == ToolchainKind::Clang)
+        .ok_or_else(|| "Clang toolchain not found".to_string())?;
+    let request = CompileRequest::new(source.to_path_buf(), PathBuf::from("probe.o"));
+    Ok(CachedFlags::from_compile(toolchain, &request))
+}
+
+/// Detect the project's Makefile and list its targets.
+#[tauri::command]
+pub fn detect_makefile(project_path: String) -> Option<MakefileInfo> {
+    axiom_toolchain::detect_makefile(Path::new(&project_path))
+}
+
+/// Parse the project's Makefile and everything it includes.
+#[tauri::command]
+pub fn parse_makefile(project_path: String) -> Result<MakefileModel, String> {
+    let root = Path::new(&project_path);
+    let info = axiom_toolchain::detect_makefile(root).ok_or("No Makefile found")?;
+    axiom_toolchain::parse_makefile(&info.path).map_err(|e| e.to_string())
+}
+
+/// Effective values of the project's Makefile variables.
+#[tauri::command]
+pub fn get_makefile_variables(project_path: String) -> Result<BTreeMap<String, String>, String> {
+    Ok(parse_makefile(project_path)?.effective_variables())
+}
+
+/// Files a Makefile target is built from.
+#[tauri::command]
+pub fn get_makefile_target_files(
+    project_path: String,
+    target: String,
+) -> Result<Vec<String>, String> {
+    Ok(parse_makefile(project_path)?.files(&target))
+}
+
+/// Extract per-file compile commands from a `make -n` dry run, and use
+/// them as the check-on-save flags for each file.
+#[tauri::command]
+pub fn extract_makefile_compile_commands(
+    state: State<AppState>,
+    project_path: String,
+    target: Option<String>,
+) -> Result<Vec<MakeCompileCommand>, String> {
+    let commands = axiom_toolchain::extract_make_compile_commands(
+        Path::new(&project_path),
+        target.as_deref(),
+        usage_log(&state)?.as_ref(),
+    )
+    .map_err(|e| e.to_string())?;
+
+    let toolchains = state.toolchains.lock().map_err(|e| e.to_string())?;
+    let mut checker = state.save_checker.lock().map_err(|e| e.to_string())?;
+    for command in &commands {
+        let version = toolchains
+            .iter()
+            .find(|t| t.kind == command.kind())
+            .map(|t| t.version.clone())
+            .unwrap_or_default();
+        checker.cache_flags(command.source.clone(), command.cached_flags(version));
+    }
+
+    Ok(commands)
+}
+
+/// List the build profiles of a project: the built-in ones and those in
+/// `.axiom/profiles.toml`.
+#[tauri::command]
+pub fn get_build_profiles(project_path: String) -> Result<Vec<BuildProfile>, String> {
+    axiom_toolchain::load_build_profiles(Path::new(&project_path)).map_err(|e| e.to_string())
+}
+
+/// Import a build profile from the project's Makefile CFLAGS/LDFLAGS.
+#[tauri::command]
+pub fn import_makefile_profile(project_path: String, name: String) -> Result<BuildProfile, String> {
+    let info =
+        axiom_toolchain::detect_makefile(Path::new(&project_path)).ok_or("No Makefile found")?;
+    let content = std::fs::read_to_string(&info.path).map_err(|e| e.to_string())?;
+    Ok(axiom_toolchain::profile_from_makefile(&content, name))
+}
+
+/// Preview writing a build profile's flags back into the project's Makefile.
+#[tauri::command]
+pub fn preview_makefile_profile(
+    project_path: String,
+    profile: BuildProfile,
+) -> Result<MakefileUpdate, String> {
+    let info =
+        axiom_toolchain::detect_makefile(Path::new(&project_path)).ok_or("No Makefile found")?;
+    axiom_toolchain::plan_makefile_update(&info.path, &profile).map_err(|e| e.to_string())
+}
+
+/// Apply a previewed Makefile update, returning the backup of the previous
+/// contents.
+#[tauri::command]
+pub fn export_makefile_profile(update: MakefileUpdate) -> Result<PathBuf, String> {
+    axiom_toolchain::apply_makefile_update(&update).map_err(|e| e.to_string())
+}
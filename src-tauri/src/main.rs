@@ -12,9 +12,13 @@ pub mod logging;
 mod state;
 
 use state::AppState;
+use std::time::Duration;
 use tauri::{Emitter, Manager};
 use tauri::menu::{AboutMetadataBuilder, MenuBuilder, SubmenuBuilder};
 
+/// How long the app must stay up before a launch counts as successful.
+const STARTUP_GRACE: Duration = Duration::from_secs(15);
+
 fn main() {
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
@@ -34,6 +38,22 @@ fn main() {
                 logging::debug("toolchain", &format!("  {:?} at {}", tc.kind, tc.path.display()));
             }
             drop(toolchains);
+
+            // Report safe mode and mark the boot successful once it has stayed up
+            let status = state.startup.lock().unwrap().status();
+            if status.active {
+                logging::warn("core", format!("Safe mode: {} failed startup(s)", status.failed_boots));
+                for failure in &status.disabled {
+                    logging::warn("core", format!("  {} disabled: {}", failure.subsystem.name(), failure.reason));
+                }
+            }
+            let handle = app.handle().clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(STARTUP_GRACE);
+                if let Ok(mut startup) = handle.state::<AppState>().startup.lock() {
+                    startup.mark_ready();
+                }
+            });
             
             // Build the application menu
             let about_metadata = AboutMetadataBuilder::new()
@@ -133,14 +153,184 @@ fn main() {
             commands::settings::get_settings,
             commands::settings::set_settings,
             commands::settings::reset_settings,
+            commands::settings::get_safe_mode_status,
+            commands::settings::exit_safe_mode,
+            // Doctor commands
+            commands::doctor::diagnose_project,
             // Toolchain commands
             commands::toolchain::detect_toolchains,
             commands::toolchain::get_toolchains,
+            commands::toolchain::install_toolchain_bundle,
+            commands::toolchain::get_installed_toolchains,
+            commands::toolchain::use_container_toolchain,
+            commands::toolchain::write_toolchain_lock,
+            commands::toolchain::check_toolchain_lock,
+            commands::toolchain::run_toolchain_qualification,
+            commands::toolchain::record_qualification_baseline,
+            commands::toolchain::query_tool_usage,
+            commands::toolchain::verify_tool_outputs,
+            commands::toolchain::rotate_tool_log,
             commands::toolchain::compile_file,
             commands::toolchain::compile_dry_run,
+            commands::toolchain::arm_compile_dry_run,
+            commands::toolchain::link_dry_run,
+            commands::toolchain::get_build_statistics,
+            commands::toolchain::check_object_consistency,
+            commands::toolchain::link_firmware,
+            commands::toolchain::verify_determinism,
+            commands::toolchain::get_remote_toolchains,
+            commands::toolchain::compile_file_remote,
+            commands::toolchain::link_firmware_remote,
+            commands::toolchain::get_memory_budget,
+            commands::toolchain::build_static_library,
+            commands::toolchain::read_map_file,
+            commands::toolchain::generate_firmware_image,
+            commands::toolchain::read_firmware_image,
+            commands::toolchain::diff_firmware,
+            commands::toolchain::get_host_test_config,
+            commands::toolchain::build_host_tests,
+            commands::toolchain::run_host_tests,
+            commands::toolchain::generate_coverage_report,
+            commands::toolchain::record_coverage,
+            commands::toolchain::get_coverage_trends,
+            commands::toolchain::get_coverage_regressions,
+            commands::toolchain::get_newly_uncovered,
+            commands::toolchain::record_build_size,
+            commands::toolchain::get_size_trends,
+            commands::toolchain::get_size_regressions,
+            commands::toolchain::get_size_stats,
+            commands::toolchain::read_linker_script,
+            commands::toolchain::validate_linker_script,
+            commands::toolchain::generate_linker_script,
+            commands::toolchain::list_mcus,
+            commands::toolchain::get_mcu,
+            commands::toolchain::scan_packs,
+            commands::toolchain::get_pack_device,
+            commands::toolchain::search_pack_devices,
+            commands::toolchain::load_svd,
+            commands::toolchain::decode_svd_register,
+            commands::toolchain::get_memory_layout,
+            commands::toolchain::read_elf,
+            commands::toolchain::source_for_address,
+            commands::toolchain::addresses_for_line,
+            commands::toolchain::disassemble_elf,
+            commands::toolchain::analyze_weak_symbols,
+            commands::toolchain::check_on_save,
+            commands::toolchain::probe_preprocessor,
+            commands::toolchain::get_assembly_output,
+            commands::toolchain::detect_makefile,
+            commands::toolchain::parse_makefile,
+            commands::toolchain::get_makefile_variables,
+            commands::toolchain::get_makefile_target_files,
+            commands::toolchain::extract_makefile_compile_commands,
+            commands::toolchain::get_build_profiles,
+            commands::toolchain::import_makefile_profile,
+            commands::toolchain::preview_makefile_profile,
+            commands::toolchain::export_makefile_profile,
+            // Debug commands
+            commands::debug::detect_openocd,
+            commands::debug::list_debug_probes,
+            commands::debug::debug_probe_config,
+            commands::debug::openocd_config,
+            commands::debug::openocd_config_script,
+            commands::debug::openocd_start,
+            commands::debug::openocd_stop,
+            commands::debug::openocd_status,
+            commands::debug::openocd_flash,
+            commands::debug::openocd_reset,
+            commands::debug::openocd_halt,
+            commands::debug::flash_target,
+            commands::debug::gdb_start,
+            commands::debug::gdb_stop,
+            commands::debug::gdb_events,
+            commands::debug::gdb_load,
+            commands::debug::gdb_insert_breakpoint,
+            commands::debug::gdb_delete_breakpoint,
+            commands::debug::gdb_breakpoints,
+            commands::debug::gdb_continue,
+            commands::debug::gdb_interrupt,
+            commands::debug::gdb_step_into,
+            commands::debug::gdb_step_over,
+            commands::debug::gdb_step_instruction,
+            commands::debug::gdb_finish,
+            commands::debug::gdb_stack_frames,
+            commands::debug::gdb_variables,
+            commands::debug::gdb_evaluate,
+            commands::debug::gdb_registers,
+            commands::debug::gdb_read_memory,
+            commands::debug::trace_start,
+            commands::debug::trace_poll,
+            commands::debug::trace_stop,
+            // Analysis commands
+            commands::analysis::run_custom_rules,
+            commands::analysis::run_clang_tidy_analysis,
+            commands::analysis::run_cppcheck_analysis,
+            commands::analysis::run_static_analyzer,
+            commands::analysis::check_include_guards,
+            commands::analysis::plan_guard_fix,
+            commands::analysis::apply_guard_fix,
+            commands::analysis::analyze_stack_usage,
+            commands::analysis::build_call_graph,
+            commands::analysis::extract_error_catalog,
+            commands::analysis::export_error_catalog,
+            commands::analysis::analyze_coupling,
+            commands::analysis::verify_coupling_coverage,
+            // Compliance commands
+            commands::compliance::get_compliance_config,
+            commands::compliance::get_dal_policy,
+            commands::compliance::list_requirements,
+            commands::compliance::get_requirement,
+            commands::compliance::create_requirement,
+            commands::compliance::update_requirement,
+            commands::compliance::delete_requirement,
+            commands::compliance::list_system_requirements,
+            commands::compliance::create_system_requirement,
+            commands::compliance::update_system_requirement,
+            commands::compliance::delete_system_requirement,
+            commands::compliance::system_allocation_report,
+            commands::compliance::import_reqif,
+            commands::compliance::export_reqif,
+            commands::compliance::import_requirements_csv,
+            commands::compliance::export_requirements_csv,
+            commands::compliance::generate_traceability_matrix,
+            commands::compliance::save_traceability_matrix,
+            commands::compliance::load_traceability_matrix,
+            commands::compliance::verify_traceability_links,
+            commands::compliance::requirement_coverage,
+            commands::compliance::export_html_report,
+            commands::compliance::export_traceability_pdf,
+            commands::compliance::export_coverage_pdf,
+            commands::compliance::export_deviations_pdf,
+            commands::compliance::correlate_object_code,
+            commands::compliance::generate_test_skeletons,
+            commands::compliance::export_qualification_document,
+            commands::compliance::get_signing_public_key,
+            commands::compliance::sign_artifact,
+            commands::compliance::verify_artifact_signature,
+            commands::compliance::create_baseline,
+            commands::compliance::list_baselines,
+            commands::compliance::diff_baselines,
+            commands::compliance::capture_compliance_snapshot,
+            commands::compliance::compliance_snapshot_changes,
+            commands::compliance::analyze_change_impact,
+            commands::compliance::generate_gap_analysis,
+            commands::compliance::compliance_status,
+            commands::compliance::export_sarif,
+            commands::compliance::export_configuration_index,
+            commands::compliance::list_problem_reports,
+            commands::compliance::create_problem_report,
+            commands::compliance::update_problem_report,
+            commands::compliance::list_deviations,
+            commands::compliance::create_deviation,
+            commands::compliance::update_deviation,
+            commands::compliance::approve_deviation,
+            commands::compliance::correlate_misra_findings,
+            commands::compliance::validate_annotation_syntax,
             // Parser commands
             commands::parser::parse_file,
             commands::parser::get_ast,
+            commands::parser::get_inlay_hints,
+            commands::parser::get_svd_register_map,
             // Symbol commands
             commands::symbols::get_completions,
             commands::symbols::index_file,
@@ -158,6 +348,8 @@ fn main() {
             commands::git::git_remote_status,
             commands::git::git_log,
             commands::git::git_commit_files,
+            commands::git::git_requirement_history,
+            commands::git::git_requirement_volatility,
             // Terminal commands
             commands::terminal::terminal_create,
             commands::terminal::terminal_write,
@@ -4,10 +4,12 @@
 //! Application state management.
 
 use axiom_parser::Parser;
-use axiom_settings::Settings;
+use axiom_settings::{Settings, StartupGuard, Subsystem};
 use axiom_symbols::SymbolIndex;
 use axiom_terminal::SessionManager;
-use axiom_toolchain::DetectedToolchain;
+use axiom_toolchain::{
+    DetectedToolchain, GdbSession, OpenOcdServer, PackIndex, SaveChecker, TraceCapture,
+};
 use std::path::PathBuf;
 use std::sync::Mutex;
 
@@ -23,6 +25,18 @@ pub struct AppState {
     pub symbol_index: Mutex<SymbolIndex>,
     /// Terminal session manager.
     pub terminal_manager: Mutex<SessionManager>,
+    /// Check-on-save debounce state and flag cache.
+    pub save_checker: Mutex<SaveChecker>,
+    /// Startup failure tracking and safe mode.
+    pub startup: Mutex<StartupGuard>,
+    /// CMSIS pack index, scanned on first use.
+    pub pack_index: Mutex<Option<PackIndex>>,
+    /// Running OpenOCD debug server.
+    pub openocd: Mutex<Option<OpenOcdServer>>,
+    /// Running GDB session.
+    pub gdb: Mutex<Option<GdbSession>>,
+    /// Running semihosting and SWO capture.
+    pub trace: Mutex<Option<TraceCapture>>,
     /// Current project path.
     #[allow(dead_code)]
     pub project_path: Mutex<Option<PathBuf>>,
@@ -31,8 +45,21 @@ pub struct AppState {
 impl AppState {
     /// Create new application state.
     pub fn new() -> Self {
-        // Load settings from default path
-        let settings = axiom_settings::load_default().unwrap_or_default();
+        let mut startup = StartupGuard::begin_default();
+
+        // Load settings from default path, unless safe mode disabled them
+        let settings = if startup.is_enabled(Subsystem::Settings) {
+            match startup.run(Subsystem::Settings, axiom_settings::load_default) {
+                Ok(settings) => settings,
+                Err(e) => {
+                    startup.record_failure(Subsystem::Settings, e.to_string());
+                    Settings::default()
+                }
+            }
+        } else {
+            Settings::default()
+        };
+        crate::commands::settings::apply_tool_limits(&settings);
 
         // Detect toolchains
         let toolchains = axiom_toolchain::detect_all();
@@ -40,15 +67,31 @@ impl AppState {
         // Create parser
         let parser = Parser::default();
 
+        let symbol_index = startup.run(Subsystem::SymbolIndex, SymbolIndex::new);
+
         Self {
             settings: Mutex::new(settings),
             toolchains: Mutex::new(toolchains),
             parser: Mutex::new(parser),
-            symbol_index: Mutex::new(SymbolIndex::new()),
+            symbol_index: Mutex::new(symbol_index),
             terminal_manager: Mutex::new(SessionManager::new()),
+            save_checker: Mutex::new(SaveChecker::default()),
+            startup: Mutex::new(startup),
+            pack_index: Mutex::new(None),
+            openocd: Mutex::new(None),
+            gdb: Mutex::new(None),
+            trace: Mutex::new(None),
             project_path: Mutex::new(None),
         }
     }
+
+    /// Whether safe mode left a subsystem enabled.
+    pub fn subsystem_enabled(&self, subsystem: Subsystem) -> bool {
+        self.startup
+            .lock()
+            .map(|startup| startup.is_enabled(subsystem))
+            .unwrap_or(true)
+    }
 }
 
 impl Default for AppState {
@@ -20,6 +20,10 @@ export interface Settings {
     output_dir: string;
     optimization_level: number;
     debug_symbols: boolean;
+    warning_profile: string | null;
+    profile: string | null;
+    tool_timeout_secs: number;
+    max_tool_output_mib: number;
   };
   editor: {
     font_size: number;
@@ -41,6 +45,11 @@ export interface Settings {
     theme: 'dark' | 'light';
     font_size: number;
   };
+  compliance: {
+    capture_environment: boolean;
+    captured_variables: string[];
+    hash_toolchain: boolean;
+  };
 }
 
 function createSettingsStore() {